@@ -12,12 +12,18 @@ export default class Crate extends Model {
   @attr('date') updated_at;
   @attr max_version;
   @attr max_stable_version;
+  @attr default_version;
   @attr newest_version;
 
   @attr description;
   @attr homepage;
   @attr documentation;
   @attr repository;
+  /**
+   * Whether an owner proved control of the repository.
+   * @type {boolean}
+   */
+  @attr verified_repository;
 
   @hasMany('version', { async: true, inverse: 'crate' }) versions;
   @hasMany('team', { async: true, inverse: null }) owner_team;
@@ -34,6 +40,9 @@ export default class Crate extends Model {
    * @return {string}
    */
   get defaultVersion() {
+    if (this.default_version) {
+      return this.default_version;
+    }
     if (this.max_stable_version) {
       return this.max_stable_version;
     }
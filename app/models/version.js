@@ -31,6 +31,15 @@ export default class Version extends Model {
   @attr has_lib;
   /** @type {string[] | null} */
   @attr bin_names;
+  /** @type {boolean | null} */
+  @attr has_build_script;
+  /** @type {boolean | null} */
+  @attr is_proc_macro;
+  /**
+   * Status of the documentation build on docs.rs.
+   * @type {'in_progress' | 'success' | 'failure' | null}
+   */
+  @attr docs_build_status;
 
   @belongsTo('crate', { async: false, inverse: 'versions' }) crate;
 
@@ -60,6 +60,9 @@ Router.map(function () {
   this.route('data-access');
   this.route('confirm', { path: '/confirm/:email_token' });
   this.route('accept-invite', { path: '/accept-invite/:token' });
+  this.route('confirm-publish', { path: '/confirm-publish/:token' });
+  this.route('lock-account', { path: '/lock-account/:token' });
+  this.route('confirm-account-recovery', { path: '/confirm-account-recovery/:token' });
 
   this.route('catch-all', { path: '*path' });
 });
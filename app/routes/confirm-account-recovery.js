@@ -0,0 +1,28 @@
+import Route from '@ember/routing/route';
+import { inject as service } from '@ember/service';
+
+import ajax from '../utils/ajax';
+
+export default class ConfirmAccountRecoveryRoute extends Route {
+  @service notifications;
+  @service router;
+
+  async model(params) {
+    try {
+      await ajax(`/api/v1/confirm_account_recovery/${params.token}`, { method: 'PUT', body: '{}' });
+
+      this.notifications.success(
+        'Thank you for confirming the recovery of your account. The crates.io team will review the request shortly.',
+      );
+    } catch (error) {
+      let detail = error.errors?.[0]?.detail;
+      if (detail && !detail.startsWith('{')) {
+        this.notifications.error(`Error while confirming the account recovery: ${detail}`);
+      } else {
+        this.notifications.error(`Unknown error while confirming the account recovery`);
+      }
+    }
+
+    this.router.replaceWith('index');
+  }
+}
@@ -0,0 +1,27 @@
+import Route from '@ember/routing/route';
+import { inject as service } from '@ember/service';
+
+import ajax from '../utils/ajax';
+
+export default class ConfirmPublishRoute extends Route {
+  @service notifications;
+  @service router;
+
+  async model(params) {
+    try {
+      let { crate } = await ajax(`/api/v1/confirm_publish/${params.token}`, { method: 'PUT', body: '{}' });
+
+      this.notifications.success(`Thank you for confirming the publish of ${crate.name}!`);
+      this.router.replaceWith('crate', crate.name);
+    } catch (error) {
+      let detail = error.errors?.[0]?.detail;
+      if (detail && !detail.startsWith('{')) {
+        this.notifications.error(`Error in publish confirmation: ${detail}`);
+      } else {
+        this.notifications.error(`Unknown error in publish confirmation`);
+      }
+
+      this.router.replaceWith('index');
+    }
+  }
+}
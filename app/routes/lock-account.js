@@ -0,0 +1,28 @@
+import Route from '@ember/routing/route';
+import { inject as service } from '@ember/service';
+
+import ajax from '../utils/ajax';
+
+export default class LockAccountRoute extends Route {
+  @service notifications;
+  @service router;
+
+  async model(params) {
+    try {
+      await ajax(`/api/v1/lock_account/${params.token}`, { method: 'PUT', body: '{}' });
+
+      this.notifications.success(
+        'Your account has been locked. Please contact help@crates.io to verify your identity and unlock it.',
+      );
+    } catch (error) {
+      let detail = error.errors?.[0]?.detail;
+      if (detail && !detail.startsWith('{')) {
+        this.notifications.error(`Error while locking your account: ${detail}`);
+      } else {
+        this.notifications.error(`Unknown error while locking your account`);
+      }
+    }
+
+    this.router.replaceWith('index');
+  }
+}
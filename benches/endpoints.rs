@@ -0,0 +1,200 @@
+//! Benchmarks for the hottest API endpoints.
+//!
+//! The benchmarks run the full application router against a fresh test
+//! database, which is seeded with a representative number of crates and
+//! versions. Each endpoint is measured for every dataset size in
+//! [`DATASET_SIZES`], and the number of heap allocations per request is
+//! printed before the timing loop starts.
+//!
+//! Similar to the integration tests, the `TEST_DATABASE_URL` environment
+//! variable has to point to a PostgreSQL database. The remaining server
+//! configuration is read from the environment (or the `.env` file) as usual:
+//!
+//! ```sh
+//! cargo bench --bench endpoints
+//! ```
+
+use axum::body::Body;
+use axum::extract::connect_info::MockConnectInfo;
+use axum::Router;
+use crates_io::config;
+use crates_io::storage::StorageConfig;
+use crates_io::{App, Emails};
+use crates_io_github::RealGitHubClient;
+use crates_io_test_db::TestDatabase;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diesel::prelude::*;
+use diesel::sql_query;
+use http::{header, Request, StatusCode};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// The number of crates in the seeded databases. Every crate has
+/// [`VERSIONS_PER_CRATE`] versions.
+const DATASET_SIZES: &[usize] = &[100, 1_000, 10_000];
+
+const VERSIONS_PER_CRATE: usize = 10;
+
+/// A global allocator that counts the allocations of all threads, since the
+/// request handlers perform most of their work on the blocking thread pool.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Seeds the database with `num_crates` crates, their versions and download
+/// counts, using a handful of bulk inserts instead of the publish endpoint.
+fn seed(conn: &mut PgConnection, num_crates: usize) -> QueryResult<()> {
+    sql_query(
+        "INSERT INTO crates (name, description, readme)
+         SELECT 'crate_' || i, 'Benchmark crate number ' || i, '# crate_' || i
+         FROM generate_series(1, $1) AS i",
+    )
+    .bind::<diesel::sql_types::Integer, _>(num_crates as i32)
+    .execute(conn)?;
+
+    sql_query(
+        "INSERT INTO versions (crate_id, num, checksum, features, license, crate_size)
+         SELECT crates.id, '1.0.' || v, repeat('0', 64), '{}', 'MIT OR Apache-2.0', 1024
+         FROM crates, generate_series(0, $1 - 1) AS v",
+    )
+    .bind::<diesel::sql_types::Integer, _>(VERSIONS_PER_CRATE as i32)
+    .execute(conn)?;
+
+    sql_query(
+        "INSERT INTO default_versions (crate_id, version_id)
+         SELECT crate_id, max(id) FROM versions GROUP BY crate_id",
+    )
+    .execute(conn)?;
+
+    sql_query(
+        "INSERT INTO version_downloads (version_id, downloads, date)
+         SELECT id, (id * 7919) % 1000, current_date FROM versions",
+    )
+    .execute(conn)?;
+
+    sql_query("UPDATE versions SET downloads = (id * 7919) % 1000").execute(conn)?;
+
+    sql_query(
+        "UPDATE crate_downloads SET downloads = totals.downloads
+         FROM (SELECT crate_id, sum(downloads) AS downloads FROM versions GROUP BY crate_id) AS totals
+         WHERE crate_downloads.crate_id = totals.crate_id",
+    )
+    .execute(conn)?;
+
+    sql_query("REFRESH MATERIALIZED VIEW recent_crate_downloads").execute(conn)?;
+    sql_query("ANALYZE").execute(conn)?;
+
+    Ok(())
+}
+
+/// Builds the application router for the given test database.
+fn build_router(test_database: &TestDatabase) -> Router {
+    let mut config = config::Server::from_environment().unwrap();
+    config.db.primary.url = test_database.url().to_string().into();
+    config.db.replica = None;
+    config.db.enforce_tls = false;
+    config.storage = StorageConfig::in_memory();
+    config.storage.cdn_prefix = Some("static.crates.io".to_string());
+
+    let github = Box::new(RealGitHubClient::new(reqwest::Client::new()));
+    let app = Arc::new(App::new(config, Emails::new_in_memory(), github));
+
+    // Add a mock `SocketAddr` to the requests so that the `ConnectInfo`
+    // extractor has something to extract.
+    let socket_addr = SocketAddr::from(([127, 0, 0, 1], 52381));
+    crates_io::build_handler(app).layer(MockConnectInfo(socket_addr))
+}
+
+async fn request(router: &Router, path: &str, expected: StatusCode) {
+    let request = Request::get(path)
+        .header(header::USER_AGENT, "crates.io benchmarks")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), expected, "unexpected status for {path}");
+
+    axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    for &num_crates in DATASET_SIZES {
+        let test_database = TestDatabase::new();
+        seed(&mut test_database.connect(), num_crates).unwrap();
+
+        let router = {
+            let _guard = rt.enter();
+            build_router(&test_database)
+        };
+
+        // Pick a crate from the middle of the dataset, so that lookups don't
+        // benefit from the rows being at the start of the table.
+        let name = format!("crate_{}", num_crates / 2);
+        let endpoints = [
+            (
+                "search",
+                "/api/v1/crates?q=benchmark&sort=downloads&per_page=20".to_string(),
+                StatusCode::OK,
+            ),
+            (
+                "crate_detail",
+                format!("/api/v1/crates/{name}"),
+                StatusCode::OK,
+            ),
+            (
+                "download",
+                format!("/api/v1/crates/{name}/1.0.0/download"),
+                StatusCode::FOUND,
+            ),
+        ];
+
+        for (group_name, path, expected) in &endpoints {
+            // Warm up the connection pool and any caches before counting the
+            // allocations of a single request.
+            rt.block_on(request(&router, path, *expected));
+
+            ALLOCATIONS.store(0, Ordering::Relaxed);
+            rt.block_on(request(&router, path, *expected));
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+            println!("{group_name}/{num_crates}: {allocations} allocations per request");
+
+            let mut group = c.benchmark_group(*group_name);
+            group.bench_with_input(BenchmarkId::from_parameter(num_crates), path, |b, path| {
+                b.to_async(&rt).iter(|| request(&router, path, *expected));
+            });
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -4,6 +4,7 @@
 //! and <https://www.w3.org/TR/WD-logfile.html>.
 
 use crate::paths::parse_path;
+use crate::regions::country_code;
 use crate::DownloadsMap;
 use chrono::NaiveDate;
 use std::borrow::Cow;
@@ -18,6 +19,7 @@ const FIELD_DATE: &str = "date";
 const FIELD_METHOD: &str = "cs-method";
 const FIELD_PATH: &str = "cs-uri-stem";
 const FIELD_STATUS: &str = "sc-status";
+const FIELD_COUNTRY: &str = "c-country";
 
 #[instrument(level = "debug", skip(reader))]
 pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Result<DownloadsMap> {
@@ -26,6 +28,7 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
     let mut method_index = None;
     let mut path_index = None;
     let mut status_index = None;
+    let mut country_index = None;
 
     let mut downloads = DownloadsMap::new();
 
@@ -47,6 +50,7 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
             method_index = fields.iter().position(|f| f == &FIELD_METHOD);
             path_index = fields.iter().position(|f| f == &FIELD_PATH);
             status_index = fields.iter().position(|f| f == &FIELD_STATUS);
+            country_index = fields.iter().position(|f| f == &FIELD_COUNTRY);
 
             continue;
         }
@@ -99,6 +103,13 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
             }
         };
 
+        // The `c-country` field is optional, so a missing field is not
+        // logged as a warning.
+        let country = country_index.and_then(|i| values.get(i));
+        if let Some(country) = country.and_then(|country| country_code(country)) {
+            downloads.add_country(&name, date, country);
+        }
+
         downloads.add(name, version, date);
     }
 
@@ -208,6 +219,24 @@ mod tests {
         "###);
     }
 
+    #[tokio::test]
+    async fn test_countries() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/cloudfront/countries.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r###"
+        DownloadsMap {
+            2024-01-16  bindgen@0.65.1 .. 3
+            2024-01-17  rand@0.8.5 .. 1
+            2024-01-16  bindgen [FR] .. 1
+            2024-01-16  bindgen [US] .. 1
+            2024-01-17  rand [JP] .. 1
+        }
+        "###);
+    }
+
     #[tokio::test]
     async fn test_unknown_version() {
         let _guard = enable_tracing_output();
@@ -1,41 +1,75 @@
 use chrono::NaiveDate;
-use derive_deref::Deref;
 use semver::Version;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
-
-#[derive(Clone, Default, Deref)]
-pub struct DownloadsMap(HashMap<(String, Version, NaiveDate), u64>);
+use std::ops::Deref;
+
+#[derive(Clone, Default)]
+pub struct DownloadsMap {
+    versions: HashMap<(String, Version, NaiveDate), u64>,
+    /// Download counts per crate, date and country, for the downloads whose
+    /// country is known.
+    countries: HashMap<(String, NaiveDate, &'static str), u64>,
+}
 
 impl DownloadsMap {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
 
     /// Increments the download count for the given crate version on the given date.
     pub fn add(&mut self, name: String, version: Version, date: NaiveDate) {
-        *self.0.entry((name, version, date)).or_default() += 1;
+        *self.versions.entry((name, version, date)).or_default() += 1;
+    }
+
+    /// Increments the download count for the given crate on the given date
+    /// in the given country.
+    ///
+    /// This is counted in addition to [`DownloadsMap::add()`], and only for
+    /// the downloads whose country is known.
+    pub fn add_country(&mut self, name: &str, date: NaiveDate, country: &'static str) {
+        let key = (name.to_string(), date, country);
+        *self.countries.entry(key).or_default() += 1;
     }
 
     /// Returns a [HashSet] of all crate names in the map.
     pub fn unique_crates(&self) -> HashSet<&str> {
-        self.0.keys().map(|(krate, _, _)| krate.as_str()).collect()
+        self.versions
+            .keys()
+            .map(|(krate, _, _)| krate.as_str())
+            .collect()
     }
 
     /// Returns the total number of downloads across all crates and versions.
     pub fn sum_downloads(&self) -> u64 {
-        self.0.values().sum()
+        self.versions.values().sum()
+    }
+
+    /// Returns a vector of `(crate, date, country, downloads)` tuples.
+    pub fn country_vec(&self) -> Vec<(&str, NaiveDate, &'static str, u64)> {
+        self.countries
+            .iter()
+            .map(|((name, date, country), downloads)| (name.as_str(), *date, *country, *downloads))
+            .collect()
     }
 
     /// Converts the map into a vector of `(crate, version, date, downloads)` tuples.
     pub fn into_vec(self) -> Vec<(String, Version, NaiveDate, u64)> {
-        self.0
+        self.versions
             .into_iter()
             .map(|((name, version, date), downloads)| (name, version, date, downloads))
             .collect()
     }
 }
 
+impl Deref for DownloadsMap {
+    type Target = HashMap<(String, Version, NaiveDate), u64>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.versions
+    }
+}
+
 impl Debug for DownloadsMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut downloads = self
@@ -52,6 +86,20 @@ impl Debug for DownloadsMap {
             f.write_fmt(format_args!("{date}  {krate}@{version} .. {downloads}"))?;
             f.write_str("\n")?;
         }
+
+        let mut countries = self
+            .countries
+            .iter()
+            .map(|((krate, date, country), downloads)| (date, krate, country, downloads))
+            .collect::<Vec<_>>();
+
+        countries.sort();
+
+        for (date, krate, country, downloads) in countries {
+            f.write_str("    ")?;
+            f.write_fmt(format_args!("{date}  {krate} [{country}] .. {downloads}"))?;
+            f.write_str("\n")?;
+        }
         f.write_str("}")?;
 
         Ok(())
@@ -105,5 +153,22 @@ mod tests {
             2023-12-26  xmas@2.0.0 .. 1
         }
         "###);
+
+        // Add entries with a known country
+        let date = "2023-12-25".parse::<NaiveDate>().unwrap();
+        downloads.add_country("xmas", date, "DE");
+        downloads.add_country("xmas", date, "DE");
+        downloads.add_country("xmas", date, "US");
+        assert_debug_snapshot!(downloads, @r###"
+        DownloadsMap {
+            2023-12-25  foo@2.0.0 .. 1
+            2023-12-25  xmas@1.0.0 .. 1
+            2023-12-25  xmas@2.0.0 .. 2
+            2023-12-26  xmas@2.0.0 .. 1
+            2023-12-25  xmas [DE] .. 2
+            2023-12-25  xmas [US] .. 1
+        }
+        "###);
+        assert_eq!(downloads.len(), 4);
     }
 }
@@ -37,6 +37,12 @@ impl LogLine<'_> {
             LogLine::V1(line) => line.status,
         }
     }
+
+    pub fn country(&self) -> Option<&str> {
+        match self {
+            LogLine::V1(line) => line.country.as_deref(),
+        }
+    }
 }
 
 /// This struct corresponds to the `"version": "1"` variant of the [LogLine] enum.
@@ -52,6 +58,8 @@ impl LogLine<'_> {
 ///   crates.io codebase.
 /// - The `method` and `url` fields are using `Cow` to avoid
 ///   unnecessary allocations.
+/// - The optional `country` field contains the ISO 3166-1 alpha-2 code of
+///   the country that the request originated from (`client.geo.country_code`).
 #[derive(Debug, Deserialize)]
 pub struct LogLineV1<'a> {
     pub date_time: DateTime<Utc>,
@@ -60,6 +68,8 @@ pub struct LogLineV1<'a> {
     #[serde(borrow)]
     pub url: Cow<'a, str>,
     pub status: u16,
+    #[serde(borrow, default)]
+    pub country: Option<Cow<'a, str>>,
 }
 
 #[cfg(test)]
@@ -79,6 +89,7 @@ mod tests {
                 method: "GET",
                 url: "https://static.staging.crates.io/?1705420437",
                 status: 403,
+                country: None,
             },
         )
         "###);
@@ -90,6 +101,7 @@ mod tests {
         assert_eq!(output.method(), "GET");
         assert_eq!(output.url(), "https://static.staging.crates.io/?1705420437");
         assert_eq!(output.status(), 403);
+        assert_eq!(output.country(), None);
 
         match output {
             LogLine::V1(l) => {
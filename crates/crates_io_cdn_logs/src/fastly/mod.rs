@@ -5,6 +5,7 @@
 mod json;
 
 use crate::paths::parse_path;
+use crate::regions::country_code;
 use crate::DownloadsMap;
 use std::borrow::Cow;
 use tokio::io::{AsyncBufRead, AsyncBufReadExt};
@@ -54,6 +55,10 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
 
         let date = json.date_time().date_naive();
 
+        if let Some(country) = json.country().and_then(country_code) {
+            downloads.add_country(&name, date, country);
+        }
+
         downloads.add(name, version, date);
     }
 
@@ -171,4 +176,21 @@ mod tests {
         }
         "###);
     }
+
+    #[tokio::test]
+    async fn test_countries() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../../test_data/fastly/countries.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads, @r###"
+        DownloadsMap {
+            2024-01-16  strsim@0.10.0 .. 2
+            2024-01-16  strsim@0.11.0 .. 3
+            2024-01-16  strsim [DE] .. 2
+            2024-01-16  strsim [US] .. 1
+        }
+        "###);
+    }
 }
@@ -3,6 +3,7 @@ mod compression;
 mod download_map;
 pub mod fastly;
 mod paths;
+pub mod regions;
 #[cfg(test)]
 mod test_utils;
 
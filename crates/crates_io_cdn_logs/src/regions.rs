@@ -0,0 +1,109 @@
+//! Coarse geographic regions of downloads.
+//!
+//! The CDN logs can contain the ISO 3166-1 alpha-2 code of the country that
+//! a request originated from, as determined by the CDN. The country is the
+//! most detailed location that is extracted from the logs, and every country
+//! is mapped to one of the seven continents.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The country codes of each continent.
+const CONTINENTS: &[(&str, &str)] = &[
+    (
+        "AF",
+        "AO BF BI BJ BW CD CF CG CI CM CV DJ DZ EG EH ER ET GA GH GM GN GQ GW KE KM LR LS LY \
+         MA MG ML MR MU MW MZ NA NE NG RE RW SC SD SH SL SN SO SS ST SZ TD TG TN TZ UG YT ZA \
+         ZM ZW",
+    ),
+    ("AN", "AQ BV GS HM TF"),
+    (
+        "AS",
+        "AE AF AM AZ BD BH BN BT CC CN CX CY GE HK ID IL IN IO IQ IR JO JP KG KH KP KR KW KZ \
+         LA LB LK MM MN MO MV MY NP OM PH PK PS QA SA SG SY TH TJ TL TM TR TW UZ VN YE",
+    ),
+    (
+        "EU",
+        "AD AL AT AX BA BE BG BY CH CZ DE DK EE ES FI FO FR GB GG GI GR HR HU IE IM IS IT JE \
+         LI LT LU LV MC MD ME MK MT NL NO PL PT RO RS RU SE SI SJ SK SM UA VA XK",
+    ),
+    (
+        "NA",
+        "AG AI AW BB BL BM BQ BS BZ CA CR CU CW DM DO GD GL GP GT HN HT JM KN KY LC MF MQ MS \
+         MX NI PA PM PR SV SX TC TT US VC VG VI",
+    ),
+    (
+        "OC",
+        "AS AU CK FJ FM GU KI MH MP NC NF NR NU NZ PF PG PN PW SB TK TO TV UM VU WF WS",
+    ),
+    ("SA", "AR BO BR CL CO EC FK GF GY PE PY SR UY VE"),
+];
+
+/// Maps every known country code to the code of its continent.
+fn countries() -> &'static HashMap<&'static str, &'static str> {
+    static COUNTRIES: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+    COUNTRIES.get_or_init(|| {
+        CONTINENTS
+            .iter()
+            .flat_map(|(continent, countries)| {
+                countries
+                    .split_whitespace()
+                    .map(move |country| (country, *continent))
+            })
+            .collect()
+    })
+}
+
+/// Normalizes a country code from the CDN logs.
+///
+/// Returns `None` for unknown codes, and for the placeholders that the CDNs
+/// use if the location of a request could not be determined.
+pub fn country_code(value: &str) -> Option<&'static str> {
+    let value = value.trim();
+    if value.len() != 2 {
+        return None;
+    }
+
+    let value = value.to_ascii_uppercase();
+    countries().get_key_value(value.as_str()).map(|(k, _)| *k)
+}
+
+/// Returns the code of the continent of the given country, e.g. `EU` for
+/// `DE`.
+pub fn continent(country: &str) -> Option<&'static str> {
+    countries().get(country).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_country_code() {
+        assert_eq!(country_code("DE"), Some("DE"));
+        assert_eq!(country_code(" us"), Some("US"));
+        assert_eq!(country_code(""), None);
+        assert_eq!(country_code("-"), None);
+        assert_eq!(country_code("**"), None);
+        assert_eq!(country_code("USA"), None);
+    }
+
+    #[test]
+    fn test_continent() {
+        assert_eq!(continent("DE"), Some("EU"));
+        assert_eq!(continent("JP"), Some("AS"));
+        assert_eq!(continent("BR"), Some("SA"));
+        assert_eq!(continent("NZ"), Some("OC"));
+        assert_eq!(continent("XX"), None);
+    }
+
+    #[test]
+    fn test_unique_countries() {
+        let num_countries = CONTINENTS
+            .iter()
+            .flat_map(|(_, countries)| countries.split_whitespace())
+            .count();
+
+        assert_eq!(countries().len(), num_countries);
+    }
+}
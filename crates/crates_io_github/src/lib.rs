@@ -40,6 +40,13 @@ pub trait GitHubClient: Send + Sync {
         auth: &AccessToken,
     ) -> Result<GitHubOrgMembership>;
     async fn public_keys(&self, username: &str, password: &str) -> Result<Vec<GitHubPublicKey>>;
+    async fn repository_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        auth: &AccessToken,
+    ) -> Result<String>;
 }
 
 #[derive(Debug)]
@@ -148,6 +155,30 @@ impl GitHubClient for RealGitHubClient {
             Err(e) => Err(e),
         }
     }
+
+    /// Returns the raw contents of a file in the default branch of a repository
+    async fn repository_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        auth: &AccessToken,
+    ) -> Result<String> {
+        let url = format!("https://api.github.com/repos/{owner}/{repo}/contents/{path}");
+        info!("GITHUB HTTP: {url}");
+
+        self.client
+            .get(&url)
+            .header(header::ACCEPT, "application/vnd.github.raw+json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", auth.secret()))
+            .header(header::USER_AGENT, "crates.io (https://crates.io)")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+            .map_err(Into::into)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -179,6 +210,9 @@ pub struct GithubUser {
     pub id: i32,
     pub login: String,
     pub name: Option<String>,
+    /// Only included in the response if the user is the authenticated user.
+    #[serde(default)]
+    pub two_factor_authentication: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -197,6 +231,8 @@ pub struct GitHubTeam {
 #[derive(Debug, Deserialize)]
 pub struct GitHubTeamMembership {
     pub state: String,
+    /// Either `member` or `maintainer`.
+    pub role: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Crate {
     pub name: String,
     pub vers: String,
@@ -24,6 +24,12 @@ pub struct Crate {
     pub links: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rust_version: Option<String>,
+    /// The release channel (e.g. `beta`) that the version was published to.
+    ///
+    /// This field is ignored by cargo, but allows other tools to follow a
+    /// release train of a crate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
     /// The schema version for this entry.
     ///
     /// If this is None, it defaults to version 1. Entries with unknown
@@ -47,7 +53,81 @@ pub struct Crate {
     pub v: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+/// How the features of the index entries are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureEncoding {
+    /// Whether duplicate feature values are removed from the entries.
+    ///
+    /// Cargo treats these lists as sets, so the entries are still
+    /// interpreted the same way by all cargo versions.
+    pub deduplicate: bool,
+    /// Whether features that don't use the extended syntax are kept in the
+    /// legacy `features` field.
+    ///
+    /// If this is disabled, all features are written to the `features2`
+    /// field, which only cargo 1.60 and newer understand, since that field
+    /// requires the version `2` schema.
+    pub legacy_features: bool,
+}
+
+impl Default for FeatureEncoding {
+    fn default() -> Self {
+        Self {
+            deduplicate: false,
+            legacy_features: true,
+        }
+    }
+}
+
+impl Crate {
+    /// Encodes the features of this entry with the given [`FeatureEncoding`].
+    ///
+    /// Features with the extended syntax (`dep:` and `pkg?/feat`) are always
+    /// written to the `features2` field, and the schema version is set to
+    /// `2` if that field is not empty. Keys that are present in both fields
+    /// are only kept in `features2`, since cargo merges that field on top of
+    /// `features` anyway.
+    pub fn encode_features(&mut self, encoding: FeatureEncoding) {
+        let mut features = std::mem::take(&mut self.features);
+        features.extend(self.features2.take().unwrap_or_default());
+
+        if encoding.deduplicate {
+            features.values_mut().for_each(deduplicate);
+            for dep in &mut self.deps {
+                deduplicate(&mut dep.features);
+            }
+        }
+
+        let (features, features2) = if encoding.legacy_features {
+            features
+                .into_iter()
+                .partition(|(_, values)| !values.iter().any(|v| is_extended_syntax(v)))
+        } else {
+            (BTreeMap::new(), features)
+        };
+
+        self.features = features;
+        (self.features2, self.v) = if features2.is_empty() {
+            (None, None)
+        } else {
+            (Some(features2), Some(2))
+        };
+    }
+}
+
+/// Whether the feature value uses syntax that cargo versions older than 1.19
+/// can't parse, like namespaced features (`dep:`) and weak dependencies
+/// (`pkg?/feat`).
+fn is_extended_syntax(value: &str) -> bool {
+    value.starts_with("dep:") || value.contains("?/")
+}
+
+fn deduplicate(values: &mut Vec<String>) {
+    let mut seen = HashSet::with_capacity(values.len());
+    values.retain(|value| seen.insert(value.clone()));
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Dependency {
     pub name: String,
     pub req: String,
@@ -104,3 +184,143 @@ pub enum DependencyKind {
     Build,
     Dev,
 }
+
+/// The contents of the `config.json` file at the root of the index.
+///
+/// See <https://doc.rust-lang.org/cargo/reference/registry-index.html#index-configuration>.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexConfig {
+    /// The URL that crate files are downloaded from.
+    pub dl: String,
+    /// The base URL of the web API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+    /// Whether cargo has to send the registry token with all requests,
+    /// including index fetches and downloads.
+    #[serde(
+        rename = "auth-required",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub auth_required: bool,
+    /// The URL of the sparse index, including the `sparse+` prefix.
+    ///
+    /// This is not part of the format that cargo reads, and allows clients
+    /// that only know the API URL to find the index.
+    #[serde(rename = "sparse-index", skip_serializing_if = "Option::is_none")]
+    pub sparse_index: Option<String>,
+    /// The optional capabilities of the registry, like `batch-publish`, so
+    /// that clients can detect them before using them.
+    ///
+    /// This is not part of the format that cargo reads either, so cargo
+    /// ignores it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(key, values)| {
+                let values = values.iter().map(|value| value.to_string()).collect();
+                (key.to_string(), values)
+            })
+            .collect()
+    }
+
+    fn krate(features: BTreeMap<String, Vec<String>>) -> Crate {
+        Crate {
+            name: "foo".to_string(),
+            vers: "1.2.3".to_string(),
+            deps: vec![Dependency {
+                name: "bar".to_string(),
+                req: "^1.0".to_string(),
+                features: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+                optional: true,
+                default_features: true,
+                target: None,
+                kind: Some(DependencyKind::Normal),
+                package: None,
+            }],
+            cksum: "0123456789asbcdef".to_string(),
+            features,
+            features2: None,
+            yanked: None,
+            links: None,
+            rust_version: None,
+            channel: None,
+            v: None,
+        }
+    }
+
+    #[test]
+    fn test_encode_legacy_features() {
+        let mut krate = krate(features(&[
+            ("default", &["std", "bar?/std", "std"]),
+            ("serde", &["dep:serde"]),
+            ("std", &[]),
+        ]));
+
+        krate.encode_features(FeatureEncoding::default());
+        let json = serde_json::to_string(&krate).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"foo","vers":"1.2.3","deps":[{"name":"bar","req":"^1.0","features":["a","b","a"],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"cksum":"0123456789asbcdef","features":{"std":[]},"features2":{"default":["std","bar?/std","std"],"serde":["dep:serde"]},"yanked":null,"v":2}"#
+        );
+    }
+
+    #[test]
+    fn test_encode_deduplicated_features() {
+        let mut krate = krate(features(&[
+            ("default", &["std", "serde", "std"]),
+            ("serde", &["dep:serde"]),
+            ("std", &[]),
+        ]));
+        // Keys that are also in `features2` are only kept there
+        krate.features2 = Some(features(&[("serde", &["dep:serde", "dep:serde"])]));
+
+        let encoding = FeatureEncoding {
+            deduplicate: true,
+            ..Default::default()
+        };
+        krate.encode_features(encoding);
+        let json = serde_json::to_string(&krate).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"foo","vers":"1.2.3","deps":[{"name":"bar","req":"^1.0","features":["a","b"],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"cksum":"0123456789asbcdef","features":{"default":["std","serde"],"std":[]},"features2":{"serde":["dep:serde"]},"yanked":null,"v":2}"#
+        );
+    }
+
+    #[test]
+    fn test_encode_features_without_legacy_features() {
+        let mut krate = krate(features(&[("default", &["std"]), ("std", &[])]));
+
+        let encoding = FeatureEncoding {
+            deduplicate: true,
+            legacy_features: false,
+        };
+        krate.encode_features(encoding);
+        let json = serde_json::to_string(&krate).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"foo","vers":"1.2.3","deps":[{"name":"bar","req":"^1.0","features":["a","b"],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"cksum":"0123456789asbcdef","features":{},"features2":{"default":["std"],"std":[]},"yanked":null,"v":2}"#
+        );
+    }
+
+    #[test]
+    fn test_encode_features_without_extended_syntax() {
+        let mut krate = krate(features(&[("default", &["std"]), ("std", &[])]));
+        krate.v = Some(2);
+
+        krate.encode_features(FeatureEncoding::default());
+        let json = serde_json::to_string(&krate).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"foo","vers":"1.2.3","deps":[{"name":"bar","req":"^1.0","features":["a","b","a"],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"cksum":"0123456789asbcdef","features":{"default":["std"],"std":[]},"yanked":null}"#
+        );
+    }
+}
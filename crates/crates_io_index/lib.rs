@@ -11,6 +11,6 @@ mod ser;
 pub mod testing;
 
 pub use crate::credentials::Credentials;
-pub use crate::data::{Crate, Dependency, DependencyKind};
+pub use crate::data::{Crate, Dependency, DependencyKind, FeatureEncoding, IndexConfig};
 pub use crate::repo::{Repository, RepositoryConfig};
 pub use crate::ser::write_crates;
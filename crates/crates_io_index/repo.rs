@@ -176,20 +176,22 @@ impl Repository {
         Ok(head.target().unwrap())
     }
 
-    /// Commits the specified file with the specified commit message and pushes
+    /// Commits the specified files with the specified commit message and pushes
     /// the commit to the `master` branch on the `origin` remote.
     ///
-    /// Note that `modified_file` expects a file path **relative** to the
+    /// Note that `modified_files` expects file paths **relative** to the
     /// repository working folder!
     #[instrument(skip_all, fields(message = %msg))]
-    fn perform_commit_and_push(&self, msg: &str, modified_file: &Path) -> anyhow::Result<()> {
-        // git add $file
+    fn perform_commit_and_push(&self, msg: &str, modified_files: &[&Path]) -> anyhow::Result<()> {
+        // git add $files
         let mut index = self.repository.index()?;
 
-        if self.checkout_path.path().join(modified_file).exists() {
-            index.add_path(modified_file)?;
-        } else {
-            index.remove_path(modified_file)?;
+        for modified_file in modified_files {
+            if self.checkout_path.path().join(modified_file).exists() {
+                index.add_path(modified_file)?;
+            } else {
+                index.remove_path(modified_file)?;
+            }
         }
 
         index.write()?;
@@ -265,10 +267,30 @@ impl Repository {
     /// This function also prints the commit message and a success or failure
     /// message to the console.
     pub fn commit_and_push(&self, message: &str, modified_file: &Path) -> anyhow::Result<()> {
+        self.commit_and_push_files(message, &[modified_file])
+    }
+
+    /// Commits all of the specified files in a single commit with the
+    /// specified commit message and pushes the commit to the `master` branch
+    /// on the `origin` remote.
+    ///
+    /// Note that `modified_files` expects **absolute** file paths!
+    ///
+    /// This function also prints the commit message and a success or failure
+    /// message to the console.
+    pub fn commit_and_push_files(
+        &self,
+        message: &str,
+        modified_files: &[&Path],
+    ) -> anyhow::Result<()> {
         info!("Committing and pushing \"{message}\"");
 
-        let relative_path = modified_file.strip_prefix(self.checkout_path.path())?;
-        self.perform_commit_and_push(message, relative_path)
+        let relative_paths = modified_files
+            .iter()
+            .map(|path| path.strip_prefix(self.checkout_path.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.perform_commit_and_push(message, &relative_paths)
             .map(|_| info!("Commit and push finished for \"{message}\""))
             .map_err(|err| {
                 error!(?err, "Commit and push for \"{message}\" errored");
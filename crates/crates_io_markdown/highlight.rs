@@ -0,0 +1,309 @@
+//! Server-side syntax highlighting of code blocks.
+//!
+//! Highlighters split the code into tokens of a small, fixed set of
+//! [`TokenKind`]s, which are rendered as `<span>` tags with one of the
+//! [`HIGHLIGHT_CLASSES`]. Keeping the set of classes fixed allows the HTML
+//! sanitizer to only keep these classes, and the frontend to style them
+//! independently of the highlighter that produced them.
+
+use htmlescape::{encode_attribute, encode_minimal};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// The maximum total size of the code blocks of a single document that are
+/// highlighted, in bytes. Code blocks beyond this size are rendered without
+/// highlighting, to bound the time it takes to render huge READMEs.
+pub const MAX_HIGHLIGHTED_SIZE: usize = 128 * 1024;
+
+/// The maximum size of a single code block that is highlighted by the
+/// [`SyntectHighlighter`], in bytes.
+const MAX_BLOCK_SIZE: usize = 32 * 1024;
+
+/// The maximum number of lines of a single code block that is highlighted by
+/// the [`SyntectHighlighter`].
+const MAX_BLOCK_LINES: usize = 1000;
+
+/// The maximum length of a single line of a code block that is highlighted
+/// by the [`SyntectHighlighter`], in bytes. The regular expressions of the
+/// syntax definitions are matched per line, and can take a very long time
+/// for long lines, e.g. of minified or generated code.
+const MAX_LINE_LENGTH: usize = 2000;
+
+/// The classes of the `<span>` tags of highlighted tokens, which are kept by
+/// the HTML sanitizer.
+pub const HIGHLIGHT_CLASSES: &[&str] = &[
+    "hl-attribute",
+    "hl-comment",
+    "hl-constant",
+    "hl-function",
+    "hl-keyword",
+    "hl-macro",
+    "hl-number",
+    "hl-operator",
+    "hl-punctuation",
+    "hl-string",
+    "hl-type",
+    "hl-variable",
+];
+
+/// The kinds of tokens that highlighters can distinguish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Attribute,
+    Comment,
+    Constant,
+    Function,
+    Keyword,
+    Macro,
+    Number,
+    Operator,
+    Punctuation,
+    String,
+    Type,
+    Variable,
+}
+
+impl TokenKind {
+    /// The class of the `<span>` tag of tokens of this kind.
+    pub fn class(self) -> &'static str {
+        match self {
+            Self::Attribute => "hl-attribute",
+            Self::Comment => "hl-comment",
+            Self::Constant => "hl-constant",
+            Self::Function => "hl-function",
+            Self::Keyword => "hl-keyword",
+            Self::Macro => "hl-macro",
+            Self::Number => "hl-number",
+            Self::Operator => "hl-operator",
+            Self::Punctuation => "hl-punctuation",
+            Self::String => "hl-string",
+            Self::Type => "hl-type",
+            Self::Variable => "hl-variable",
+        }
+    }
+}
+
+/// Splits code blocks into highlighted tokens.
+pub trait SyntaxHighlighter: Send + Sync {
+    /// Returns the tokens of the `code`, or `None` if the `language` from the
+    /// info string of the code block is not supported. Tokens without a kind
+    /// are rendered without highlighting.
+    ///
+    /// Concatenating the text of all tokens has to result in the original
+    /// `code`.
+    fn highlight(&self, language: &str, code: &str) -> Option<Vec<(Option<TokenKind>, String)>>;
+}
+
+/// Renders a highlighted code block as HTML, like `comrak` renders code
+/// blocks without highlighting.
+pub(crate) fn highlighted_code_block(
+    language: &str,
+    tokens: Vec<(Option<TokenKind>, String)>,
+) -> String {
+    let mut html = format!(
+        "<pre><code class=\"language-{}\">",
+        encode_attribute(language)
+    );
+    for (kind, text) in tokens {
+        match kind {
+            Some(kind) => {
+                html.push_str("<span class=\"");
+                html.push_str(kind.class());
+                html.push_str("\">");
+                html.push_str(&encode_minimal(&text));
+                html.push_str("</span>");
+            }
+            None => html.push_str(&encode_minimal(&text)),
+        }
+    }
+    html.push_str("</code></pre>\n");
+    html
+}
+
+/// Maps the prefixes of TextMate scopes to token kinds. The innermost scope
+/// that matches one of the prefixes determines the kind of a token, and
+/// earlier prefixes take precedence over later ones for the same scope.
+const SCOPE_KINDS: &[(&str, TokenKind)] = &[
+    ("comment", TokenKind::Comment),
+    ("string", TokenKind::String),
+    ("constant.character", TokenKind::String),
+    ("constant.numeric", TokenKind::Number),
+    ("constant", TokenKind::Constant),
+    ("keyword.operator", TokenKind::Operator),
+    ("keyword", TokenKind::Keyword),
+    ("storage", TokenKind::Keyword),
+    ("entity.name.function", TokenKind::Function),
+    ("support.function", TokenKind::Function),
+    ("variable.function", TokenKind::Function),
+    ("support.macro", TokenKind::Macro),
+    ("entity.name.macro", TokenKind::Macro),
+    ("entity.name", TokenKind::Type),
+    ("support.type", TokenKind::Type),
+    ("support.class", TokenKind::Type),
+    ("meta.annotation", TokenKind::Attribute),
+    ("entity.other.attribute-name", TokenKind::Attribute),
+    ("variable", TokenKind::Variable),
+    ("punctuation", TokenKind::Punctuation),
+];
+
+/// A [`SyntaxHighlighter`] based on the default syntax definitions of
+/// `syntect`.
+///
+/// Code blocks that exceed [`MAX_BLOCK_SIZE`], [`MAX_BLOCK_LINES`] or
+/// [`MAX_LINE_LENGTH`] are not highlighted.
+///
+/// Loading the syntax definitions takes a while, so a single instance should
+/// be shared by all renderings.
+pub struct SyntectHighlighter {
+    syntaxes: SyntaxSet,
+}
+
+impl SyntectHighlighter {
+    pub fn new() -> Self {
+        Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+        }
+    }
+}
+
+impl Default for SyntectHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyntaxHighlighter for SyntectHighlighter {
+    fn highlight(&self, language: &str, code: &str) -> Option<Vec<(Option<TokenKind>, String)>> {
+        if !within_limits(code) {
+            return None;
+        }
+
+        let syntax = self.syntaxes.find_syntax_by_token(language)?;
+
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut tokens: Vec<(Option<TokenKind>, String)> = Vec::new();
+        let mut push = |kind: Option<TokenKind>, text: &str| match tokens.last_mut() {
+            Some((last_kind, last_text)) if *last_kind == kind => last_text.push_str(text),
+            _ => tokens.push((kind, text.to_string())),
+        };
+
+        for line in LinesWithEndings::from(code) {
+            let ops = state.parse_line(line, &self.syntaxes).ok()?;
+
+            let mut start = 0;
+            for (position, op) in ops {
+                if position > start {
+                    push(token_kind(&stack), &line[start..position]);
+                    start = position;
+                }
+                stack.apply(&op).ok()?;
+            }
+            if start < line.len() {
+                push(token_kind(&stack), &line[start..]);
+            }
+        }
+
+        Some(tokens)
+    }
+}
+
+/// Returns whether the `code` is small enough to be highlighted by the
+/// [`SyntectHighlighter`].
+fn within_limits(code: &str) -> bool {
+    code.len() <= MAX_BLOCK_SIZE
+        && code.lines().count() <= MAX_BLOCK_LINES
+        && code.lines().all(|line| line.len() <= MAX_LINE_LENGTH)
+}
+
+/// Returns the kind of the tokens with the given scopes, see [`SCOPE_KINDS`].
+///
+/// Comments and strings are highlighted as a whole, including their
+/// punctuation and escape sequences.
+fn token_kind(stack: &ScopeStack) -> Option<TokenKind> {
+    let kinds = stack
+        .as_slice()
+        .iter()
+        .filter_map(|scope| scope_kind(&scope.build_string()))
+        .collect::<Vec<_>>();
+
+    kinds
+        .iter()
+        .find(|kind| matches!(kind, TokenKind::Comment | TokenKind::String))
+        .or_else(|| kinds.last())
+        .copied()
+}
+
+fn scope_kind(scope: &str) -> Option<TokenKind> {
+    SCOPE_KINDS
+        .iter()
+        .find(|(prefix, _)| {
+            scope
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+        .map(|(_, kind)| *kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn highlights_rust() {
+        let highlighter = SyntectHighlighter::new();
+        let tokens = highlighter
+            .highlight("rust", "// Hi\nfn main() { let x = 42; }\n")
+            .unwrap();
+
+        let code: String = tokens.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(code, "// Hi\nfn main() { let x = 42; }\n");
+
+        let kind_of = |token: &str| {
+            tokens
+                .iter()
+                .find(|(_, text)| text.trim() == token)
+                .map(|(kind, _)| *kind)
+        };
+        assert_eq!(kind_of("// Hi"), Some(Some(TokenKind::Comment)));
+        assert_eq!(kind_of("fn"), Some(Some(TokenKind::Keyword)));
+        assert_eq!(kind_of("main"), Some(Some(TokenKind::Function)));
+        assert_eq!(kind_of("42"), Some(Some(TokenKind::Number)));
+    }
+
+    #[test]
+    fn unknown_languages_are_not_highlighted() {
+        let highlighter = SyntectHighlighter::new();
+        assert_eq!(highlighter.highlight("mermaid", "graph TD;"), None);
+    }
+
+    #[test]
+    fn large_code_blocks_are_not_highlighted() {
+        let highlighter = SyntectHighlighter::new();
+
+        let long_line = format!("let x = \"{}\";", "a".repeat(MAX_LINE_LENGTH));
+        assert_eq!(highlighter.highlight("rust", &long_line), None);
+
+        let many_lines = "let x = 1;\n".repeat(MAX_BLOCK_LINES + 1);
+        assert_eq!(highlighter.highlight("rust", &many_lines), None);
+
+        let large_block = format!("{}\n", "a".repeat(MAX_LINE_LENGTH)).repeat(20);
+        assert!(large_block.len() > MAX_BLOCK_SIZE);
+        assert_eq!(highlighter.highlight("rust", &large_block), None);
+
+        let small_block = "let x = 1;\n".repeat(MAX_BLOCK_LINES);
+        assert!(highlighter.highlight("rust", &small_block).is_some());
+    }
+
+    #[test]
+    fn highlighted_code_block_is_escaped() {
+        let tokens = vec![
+            (Some(TokenKind::String), "\"<b>\"".to_string()),
+            (None, " & ".to_string()),
+        ];
+        assert_snapshot!(highlighted_code_block("x\"y", tokens), @r###"
+        <pre><code class="language-x&quot;y"><span class="hl-string">&quot;&lt;b&gt;&quot;</span> &amp; </code></pre>
+        "###);
+    }
+}
@@ -1,15 +1,134 @@
 //! Render Markdown files to HTML.
 
-use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
-use comrak::nodes::{AstNode, NodeValue};
+use ammonia::{AttributeFilter, Builder, UrlRelative, UrlRelativeEvaluate};
+use comrak::nodes::{AstNode, NodeCode, NodeHeading, NodeHtmlBlock, NodeValue};
+use highlight::highlighted_code_block;
 use htmlescape::encode_minimal;
 use std::borrow::Cow;
 use std::path::Path;
+use std::sync::Arc;
 use url::Url;
 
+mod highlight;
+
+pub use highlight::{
+    SyntaxHighlighter, SyntectHighlighter, TokenKind, HIGHLIGHT_CLASSES, MAX_HIGHLIGHTED_SIZE,
+};
+
+/// The `language-*` classes of code blocks that are kept by the default
+/// [`SanitizerPolicy`], so that the frontend can highlight their syntax.
+pub const DEFAULT_CODE_CLASSES: &[&str] = &[
+    // Languages
+    "language-bash",
+    "language-c",
+    "language-glsl",
+    "language-go",
+    "language-ini",
+    "language-javascript",
+    "language-json",
+    "language-xml",
+    "language-mermaid",
+    "language-protobuf",
+    "language-ruby",
+    "language-rust",
+    "language-scss",
+    "language-sql",
+    "language-toml",
+    "language-yaml",
+    // Aliases
+    "language-rs",
+    "language-clike",
+    "language-markup",
+];
+
+/// The parts of the HTML allow-list of the sanitizer that can be configured.
+///
+/// Everything else, like the allowed URL schemes, is fixed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizerPolicy {
+    /// The version of the policy. It has to be increased whenever the policy
+    /// changes, so that already rendered READMEs can be rendered again.
+    ///
+    /// Version 1 is the policy before it became configurable, which did not
+    /// allow the `open` attribute of `<details>` blocks. Version 2 did not
+    /// allow the classes of highlighted tokens in code blocks.
+    pub version: i32,
+    /// Whether the `id` attribute of `<a>` tags is kept, so that READMEs can
+    /// link to anchors within the document. The ids are always prefixed with
+    /// `user-content-`.
+    pub anchor_ids: bool,
+    /// Whether `<details>` and `<summary>` blocks are kept. If disabled, only
+    /// their content is rendered.
+    pub details: bool,
+    /// The classes of code blocks that are kept, e.g. `language-rust`.
+    pub code_classes: Vec<String>,
+    /// Whether code blocks are highlighted by the
+    /// [`RenderOptions::highlighter`], and the classes of the highlighted
+    /// tokens are kept.
+    pub syntax_highlighting: bool,
+}
+
+impl SanitizerPolicy {
+    /// The version of the default policy.
+    pub const DEFAULT_VERSION: i32 = 3;
+}
+
+impl Default for SanitizerPolicy {
+    fn default() -> Self {
+        Self {
+            version: Self::DEFAULT_VERSION,
+            anchor_ids: true,
+            details: true,
+            code_classes: DEFAULT_CODE_CLASSES
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            syntax_highlighting: true,
+        }
+    }
+}
+
+/// Options for rendering text files to HTML.
+#[derive(Default)]
+pub struct RenderOptions {
+    /// The allow-list of the HTML sanitizer.
+    pub policy: SanitizerPolicy,
+    /// If set, all images in Markdown files are loaded via this proxy.
+    pub image_proxy: Option<Arc<dyn ImageProxy>>,
+    /// Whether the headings of Markdown files are extracted into a table of
+    /// contents, see [`RenderedDocument::table_of_contents`].
+    pub table_of_contents: bool,
+    /// If set, and enabled by the [`SanitizerPolicy::syntax_highlighting`],
+    /// fenced code blocks of Markdown files are highlighted on the server.
+    pub highlighter: Option<Arc<dyn SyntaxHighlighter>>,
+}
+
+/// A heading of a rendered Markdown document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// The level of the heading, from 1 for `<h1>` to 6 for `<h6>`.
+    pub level: u8,
+    /// The plain text of the heading, without any formatting.
+    pub text: String,
+    /// The anchor that links to the heading, e.g. `#usage`. The `id` of the
+    /// anchor element in the HTML is prefixed with `user-content-`.
+    pub anchor: String,
+}
+
+/// A text file rendered to sanitized HTML.
+#[derive(Debug, Default)]
+pub struct RenderedDocument {
+    pub html: String,
+    /// The headings of the document in the order in which they appear, if
+    /// [`RenderOptions::table_of_contents`] was enabled and the document was
+    /// rendered as Markdown.
+    pub table_of_contents: Option<Vec<Heading>>,
+}
+
 /// Context for markdown to HTML rendering.
 struct MarkdownRenderer<'a> {
     html_sanitizer: Builder<'a>,
+    highlighter: Option<&'a dyn SyntaxHighlighter>,
 }
 
 impl<'a> MarkdownRenderer<'a> {
@@ -17,36 +136,20 @@ impl<'a> MarkdownRenderer<'a> {
     ///
     /// Per `text_to_html`, `base_url` is the base URL prepended to any
     /// relative links in the input document.  See that function for more detail.
-    fn new(base_url: Option<&'a str>, base_dir: &'a str) -> MarkdownRenderer<'a> {
-        let allowed_classes = hashmap(&[
-            (
-                "code",
-                hashset(&[
-                    // Languages
-                    "language-bash",
-                    "language-c",
-                    "language-glsl",
-                    "language-go",
-                    "language-ini",
-                    "language-javascript",
-                    "language-json",
-                    "language-xml",
-                    "language-mermaid",
-                    "language-protobuf",
-                    "language-ruby",
-                    "language-rust",
-                    "language-scss",
-                    "language-sql",
-                    "language-toml",
-                    "language-yaml",
-                    // Aliases
-                    "language-rs",
-                    "language-clike",
-                    "language-markup",
-                ]),
-            ),
-            ("section", hashset(&["footnotes"])),
-        ]);
+    fn new(
+        base_url: Option<&'a str>,
+        base_dir: &'a str,
+        options: &'a RenderOptions,
+    ) -> MarkdownRenderer<'a> {
+        let policy = &options.policy;
+
+        let code_classes: std::collections::HashSet<&str> =
+            policy.code_classes.iter().map(String::as_str).collect();
+        let mut allowed_classes =
+            hashmap(&[("code", code_classes), ("section", hashset(&["footnotes"]))]);
+        if policy.syntax_highlighting {
+            allowed_classes.insert("span", hashset(HIGHLIGHT_CLASSES));
+        }
         let sanitize_url = UrlRelative::Custom(Box::new(SanitizeUrl::new(base_url, base_dir)));
 
         let mut html_sanitizer = Builder::default();
@@ -54,18 +157,45 @@ impl<'a> MarkdownRenderer<'a> {
             .add_tags(&["input", "ol", "picture", "section", "source"])
             .link_rel(Some("nofollow noopener noreferrer"))
             .add_generic_attributes(&["align"])
-            .add_tag_attributes("a", &["id", "target"])
+            .add_tag_attributes("a", &["target"])
             .add_tag_attributes("input", &["checked", "disabled", "type"])
             .add_tag_attributes("li", &["id"])
             .add_tag_attributes("source", &["media", "srcset"])
             .allowed_classes(allowed_classes)
             .url_relative(sanitize_url)
             .id_prefix(Some("user-content-"));
-        MarkdownRenderer { html_sanitizer }
+
+        if policy.anchor_ids {
+            html_sanitizer.add_tag_attributes("a", &["id"]);
+        }
+
+        if policy.details {
+            html_sanitizer.add_tag_attributes("details", &["open"]);
+        } else {
+            html_sanitizer.rm_tags(&["details", "summary"]);
+        }
+
+        if let Some(image_proxy) = &options.image_proxy {
+            html_sanitizer.attribute_filter(ProxyImages {
+                sanitize_url: SanitizeUrl::new(base_url, base_dir),
+                image_proxy: image_proxy.clone(),
+            });
+        }
+
+        let highlighter = options
+            .highlighter
+            .as_deref()
+            .filter(|_| policy.syntax_highlighting);
+
+        MarkdownRenderer {
+            html_sanitizer,
+            highlighter,
+        }
     }
 
-    /// Renders the given markdown to HTML using the current settings.
-    fn to_html(&self, text: &str) -> String {
+    /// Renders the given markdown to HTML using the current settings, and
+    /// optionally extracts the headings of the document.
+    fn render(&self, text: &str, table_of_contents: bool) -> RenderedDocument {
         use comrak::{
             format_html, parse_document, Arena, ComrakExtensionOptions, ComrakOptions,
             ComrakRenderOptions,
@@ -105,10 +235,87 @@ impl<'a> MarkdownRenderer<'a> {
             }
         });
 
+        if let Some(highlighter) = self.highlighter {
+            highlight_code_blocks(root, highlighter);
+        }
+
+        let table_of_contents = table_of_contents.then(|| collect_headings(root));
+
         let mut html = Vec::new();
         format_html(root, &options, &mut html).unwrap();
         let rendered = String::from_utf8(html).unwrap();
-        self.html_sanitizer.clean(&rendered).to_string()
+        let html = self.html_sanitizer.clean(&rendered).to_string();
+
+        RenderedDocument {
+            html,
+            table_of_contents,
+        }
+    }
+}
+
+/// Replaces the fenced code blocks of the document with highlighted HTML
+/// blocks, until [`MAX_HIGHLIGHTED_SIZE`] bytes of code were highlighted.
+///
+/// The language is the first word of the info string of the code block.
+/// Code blocks in unsupported languages are rendered as usual.
+fn highlight_code_blocks<'a>(root: &'a AstNode<'a>, highlighter: &dyn SyntaxHighlighter) {
+    let mut budget = MAX_HIGHLIGHTED_SIZE;
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        let NodeValue::CodeBlock(ref ncb) = data.value else {
+            continue;
+        };
+
+        let language = ncb.info.split_whitespace().next().unwrap_or("");
+        if !ncb.fenced || language.is_empty() || ncb.literal.len() > budget {
+            continue;
+        }
+
+        if let Some(tokens) = highlighter.highlight(language, &ncb.literal) {
+            budget -= ncb.literal.len();
+            let literal = highlighted_code_block(language, tokens);
+            data.value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 0,
+                literal,
+            });
+        }
+    }
+}
+
+/// Collects the headings of the document, with the same anchors that
+/// `comrak` generates for the `id` attributes of the headings.
+fn collect_headings<'a>(root: &'a AstNode<'a>) -> Vec<Heading> {
+    let mut anchorizer = comrak::Anchorizer::new();
+    root.descendants()
+        .filter_map(|node| match node.data.borrow().value {
+            NodeValue::Heading(NodeHeading { level, .. }) => Some((node, level)),
+            _ => None,
+        })
+        .map(|(node, level)| {
+            let mut text = String::new();
+            collect_text(node, &mut text);
+            let anchor = format!("#{}", anchorizer.anchorize(text.clone()));
+            Heading {
+                level,
+                text: text.trim().to_string(),
+                anchor,
+            }
+        })
+        .collect()
+}
+
+/// Collects the plain text of a node like `comrak` does for heading anchors.
+fn collect_text<'a>(node: &'a AstNode<'a>, output: &mut String) {
+    match node.data.borrow().value {
+        NodeValue::Text(ref literal) | NodeValue::Code(NodeCode { ref literal, .. }) => {
+            output.push_str(literal)
+        }
+        NodeValue::LineBreak | NodeValue::SoftBreak => output.push(' '),
+        _ => {
+            for child in node.children() {
+                collect_text(child, output);
+            }
+        }
     }
 }
 
@@ -238,11 +445,90 @@ impl UrlRelativeEvaluate<'_> for SanitizeUrl {
     }
 }
 
+/// Rewrites the URLs of images in rendered documents, e.g. to load them via
+/// a proxy instead of directly from third-party hosts.
+pub trait ImageProxy: Send + Sync {
+    /// Returns the URL that the image at the given absolute `http` or `https`
+    /// URL should be loaded from, or `None` to keep the original URL.
+    ///
+    /// The returned URL has to be absolute, since relative URLs are resolved
+    /// against the repository of the crate afterwards.
+    fn proxy_url(&self, url: &str) -> Option<String>;
+}
+
+/// Passes the URLs of images through an [`ImageProxy`].
+///
+/// Relative URLs are resolved the same way as by [`SanitizeUrl`] before
+/// they are passed to the proxy.
+struct ProxyImages {
+    sanitize_url: SanitizeUrl,
+    image_proxy: Arc<dyn ImageProxy>,
+}
+
+impl ProxyImages {
+    /// Returns the proxied URL of a single image, or `None` if the URL is
+    /// relative and can not be resolved.
+    fn proxy(&self, url: &str) -> Option<String> {
+        let absolute = match Url::parse(url) {
+            Ok(_) => Cow::Borrowed(url),
+            Err(url::ParseError::RelativeUrlWithoutBase) => self.sanitize_url.evaluate(url)?,
+            Err(_) => return Some(url.to_string()),
+        };
+
+        let is_http = absolute.starts_with("https://") || absolute.starts_with("http://");
+        let proxied = is_http
+            .then(|| self.image_proxy.proxy_url(&absolute))
+            .flatten();
+
+        Some(proxied.unwrap_or_else(|| absolute.into_owned()))
+    }
+
+    /// Proxies all image candidates of a `srcset` attribute, e.g.
+    /// `logo.png 1x, logo@2x.png 2x`. Candidates that can not be resolved
+    /// are kept unchanged.
+    fn proxy_srcset(&self, srcset: &str) -> String {
+        srcset
+            .split(',')
+            .map(|candidate| {
+                let candidate = candidate.trim();
+                let (url, descriptor) = candidate
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((candidate, ""));
+
+                let url = self.proxy(url).unwrap_or_else(|| url.to_string());
+                format!("{url} {}", descriptor.trim())
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl AttributeFilter for ProxyImages {
+    fn filter<'u>(&self, element: &str, attribute: &str, value: &'u str) -> Option<Cow<'u, str>> {
+        match (element, attribute) {
+            ("img", "src") => self.proxy(value).map(Cow::Owned),
+            ("source", "srcset") => Some(Cow::Owned(self.proxy_srcset(value))),
+            _ => Some(Cow::Borrowed(value)),
+        }
+    }
+}
+
 /// Renders Markdown text to sanitized HTML with a given `base_url`.
 /// See `text_to_html` for the interpretation of `base_url`.
 fn markdown_to_html(text: &str, base_url: Option<&str>, base_dir: &str) -> String {
-    let renderer = MarkdownRenderer::new(base_url, base_dir);
-    renderer.to_html(text)
+    render_markdown(text, base_url, base_dir, &RenderOptions::default()).html
+}
+
+fn render_markdown(
+    text: &str,
+    base_url: Option<&str>,
+    base_dir: &str,
+    options: &RenderOptions,
+) -> RenderedDocument {
+    let renderer = MarkdownRenderer::new(base_url, base_dir, options);
+    renderer.render(text, options.table_of_contents)
 }
 
 /// Any file with a filename ending in one of these extensions will be rendered as Markdown.
@@ -276,6 +562,37 @@ pub fn text_to_html<P: AsRef<Path>>(
     base_url: Option<&str>,
     pkg_path_in_vcs: Option<P>,
 ) -> String {
+    let options = RenderOptions::default();
+    text_to_html_with_options(
+        text,
+        readme_path_in_pkg,
+        base_url,
+        pkg_path_in_vcs,
+        &options,
+    )
+}
+
+/// Renders a text file to sanitized HTML like [`text_to_html`], but with
+/// the given sanitizer policy and image proxy.
+pub fn text_to_html_with_options<P: AsRef<Path>>(
+    text: &str,
+    readme_path_in_pkg: P,
+    base_url: Option<&str>,
+    pkg_path_in_vcs: Option<P>,
+    options: &RenderOptions,
+) -> String {
+    render_document(text, readme_path_in_pkg, base_url, pkg_path_in_vcs, options).html
+}
+
+/// Renders a text file like [`text_to_html_with_options`], and extracts its
+/// table of contents if enabled in the `options`.
+pub fn render_document<P: AsRef<Path>>(
+    text: &str,
+    readme_path_in_pkg: P,
+    base_url: Option<&str>,
+    pkg_path_in_vcs: Option<P>,
+    options: &RenderOptions,
+) -> RenderedDocument {
     let path_in_vcs = match pkg_path_in_vcs {
         None => readme_path_in_pkg.as_ref().to_path_buf(),
         Some(pkg_path_in_vcs) => pkg_path_in_vcs.as_ref().join(readme_path_in_pkg),
@@ -284,16 +601,19 @@ pub fn text_to_html<P: AsRef<Path>>(
     let base_dir = path_in_vcs.parent().and_then(|p| p.to_str()).unwrap_or("");
 
     if path_in_vcs.extension().is_none() {
-        return markdown_to_html(text, base_url, base_dir);
+        return render_markdown(text, base_url, base_dir, options);
     }
 
     if let Some(ext) = path_in_vcs.extension().and_then(|ext| ext.to_str()) {
         if MARKDOWN_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
-            return markdown_to_html(text, base_url, base_dir);
+            return render_markdown(text, base_url, base_dir, options);
         }
     }
 
-    encode_minimal(text).replace('\n', "<br>\n")
+    RenderedDocument {
+        html: encode_minimal(text).replace('\n', "<br>\n"),
+        table_of_contents: None,
+    }
 }
 
 /// Helper function to build a new `HashSet` from the items slice.
@@ -643,6 +963,53 @@ There can also be some text in between!
         "###);
     }
 
+    #[test]
+    fn table_of_contents() {
+        let text = "# My crate\n\n## Usage\n\n### The `foo` *function*\n\nFoo\n---\n\n## Usage\n";
+        let options = RenderOptions {
+            table_of_contents: true,
+            ..Default::default()
+        };
+        let rendered = render_document(text, "README.md", None, None, &options);
+
+        let headings = rendered.table_of_contents.unwrap();
+        let headings = headings
+            .iter()
+            .map(|h| format!("{} {} {}", h.level, h.text, h.anchor))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            vec![
+                "1 My crate #my-crate",
+                "2 Usage #usage",
+                "3 The foo function #the-foo-function",
+                "2 Foo #foo",
+                "2 Usage #usage-1",
+            ]
+        );
+
+        // The anchors match the ids of the headings in the HTML
+        assert!(rendered
+            .html
+            .contains(r#"id="user-content-the-foo-function""#));
+        assert!(rendered.html.contains(r#"id="user-content-usage-1""#));
+    }
+
+    #[test]
+    fn table_of_contents_is_optional() {
+        let text = "# My crate\n";
+        let options = RenderOptions::default();
+        let rendered = render_document(text, "README.md", None, None, &options);
+        assert_eq!(rendered.table_of_contents, None);
+
+        let options = RenderOptions {
+            table_of_contents: true,
+            ..Default::default()
+        };
+        let rendered = render_document(text, "README.txt", None, None, &options);
+        assert_eq!(rendered.table_of_contents, None);
+    }
+
     #[test]
     fn manual_anchor_is_sanitized() {
         let text =
@@ -694,4 +1061,182 @@ There can also be some text in between!
         </picture>
         "###);
     }
+
+    struct TestProxy;
+
+    impl ImageProxy for TestProxy {
+        fn proxy_url(&self, url: &str) -> Option<String> {
+            (!url.starts_with("https://internal.example/"))
+                .then(|| format!("https://proxy.example/?url={url}"))
+        }
+    }
+
+    #[test]
+    fn proxied_images() {
+        let text = r#"
+![logo](https://img.shields.io/crates/v/clap.svg) ![relative](img.png) ![internal](https://internal.example/logo.png)
+
+[link](https://crates.io/)
+
+<picture>
+    <source media="(prefers-color-scheme: dark)" srcset="https://test.crates.io/logo_dark.svg 1x, logo@2x.svg 2x">
+    <img src="https://test.crates.io/logo.svg" alt="logo" width="200">
+</picture>
+        "#;
+        let repository = Some("https://github.com/foo/bar/");
+        let options = RenderOptions {
+            image_proxy: Some(Arc::new(TestProxy)),
+            ..Default::default()
+        };
+        let html = text_to_html_with_options(text, "README.md", repository, None, &options);
+        assert_snapshot!(html, @r###"
+        <p><img src="https://proxy.example/?url=https://img.shields.io/crates/v/clap.svg" alt="logo"> <img src="https://proxy.example/?url=https://github.com/foo/bar/raw/HEAD/img.png" alt="relative"> <img src="https://internal.example/logo.png" alt="internal"></p>
+        <p><a href="https://crates.io/" rel="nofollow noopener noreferrer">link</a></p>
+        <picture>
+            <source media="(prefers-color-scheme: dark)" srcset="https://proxy.example/?url=https://test.crates.io/logo_dark.svg 1x, https://proxy.example/?url=https://github.com/foo/bar/raw/HEAD/logo@2x.svg?sanitize=true 2x">
+            <img src="https://proxy.example/?url=https://test.crates.io/logo.svg" alt="logo" width="200">
+        </picture>
+        "###);
+    }
+
+    #[test]
+    fn default_sanitizer_policy() {
+        let text = r#"
+<details open><summary>Example</summary>
+
+<a id="example" href="https://example.com">link</a>
+
+```python
+print("hello")
+```
+</details>
+        "#;
+        assert_snapshot!(markdown_to_html(text, None, ""), @r###"
+        <details open=""><summary>Example</summary>
+        <p><a id="user-content-example" href="https://example.com" rel="nofollow noopener noreferrer">link</a></p>
+        <pre><code>print("hello")
+        </code></pre>
+        </details>
+        "###);
+    }
+
+    #[test]
+    fn custom_sanitizer_policy() {
+        let text = r#"
+<details open><summary>Example</summary>
+
+<a id="example" href="https://example.com">link</a>
+
+```python
+print("hello")
+```
+</details>
+        "#;
+        let options = RenderOptions {
+            policy: SanitizerPolicy {
+                version: 3,
+                anchor_ids: false,
+                details: false,
+                code_classes: vec!["language-python".to_string()],
+                syntax_highlighting: false,
+            },
+            ..Default::default()
+        };
+        let html = render_markdown(text, None, "", &options).html;
+        assert_snapshot!(html, @r###"
+        Example
+        <p><a href="https://example.com" rel="nofollow noopener noreferrer">link</a></p>
+        <pre><code class="language-python">print("hello")
+        </code></pre>
+        "###);
+    }
+
+    /// Highlights every word of `rust` code blocks as a keyword.
+    struct TestHighlighter;
+
+    impl SyntaxHighlighter for TestHighlighter {
+        fn highlight(
+            &self,
+            language: &str,
+            code: &str,
+        ) -> Option<Vec<(Option<TokenKind>, String)>> {
+            (language == "rust").then(|| {
+                code.split_inclusive(' ')
+                    .map(|word| match word.trim_end() {
+                        "" => (None, word.to_string()),
+                        keyword => (
+                            Some(TokenKind::Keyword),
+                            keyword.to_string() + &word[keyword.len()..],
+                        ),
+                    })
+                    .collect()
+            })
+        }
+    }
+
+    #[test]
+    fn highlighted_code_blocks() {
+        let text = r#"
+```rust,ignore
+fn <main>
+```
+
+```python
+print("hello")
+```
+
+    indented code
+
+<pre><code><span class="hl-keyword" style="color: red">fn</span> <span class="evil">main</span></code></pre>
+        "#;
+        let options = RenderOptions {
+            highlighter: Some(Arc::new(TestHighlighter)),
+            ..Default::default()
+        };
+        let html = render_markdown(text, None, "", &options).html;
+        assert_snapshot!(html, @r###"
+        <pre><code class="language-rust"><span class="hl-keyword">fn </span><span class="hl-keyword">&lt;main&gt;
+        </span></code></pre>
+        <pre><code>print("hello")
+        </code></pre>
+        <pre><code>indented code
+        </code></pre>
+        <pre><code><span class="hl-keyword">fn</span> <span>main</span></code></pre>
+        "###);
+    }
+
+    #[test]
+    fn highlighting_is_limited() {
+        let code = "fn main() {}\n".repeat(MAX_HIGHLIGHTED_SIZE / 13 - 1);
+        let text = format!("```rust\n{code}```\n\n```rust\n{code}```\n");
+        let options = RenderOptions {
+            highlighter: Some(Arc::new(TestHighlighter)),
+            ..Default::default()
+        };
+        let html = render_markdown(&text, None, "", &options).html;
+        assert_eq!(
+            html.matches("<pre><code class=\"language-rust\"><span")
+                .count(),
+            1
+        );
+        assert_eq!(
+            html.matches("<pre><code class=\"language-rust\">fn")
+                .count(),
+            1
+        );
+
+        let options = RenderOptions {
+            policy: SanitizerPolicy {
+                syntax_highlighting: false,
+                ..Default::default()
+            },
+            highlighter: Some(Arc::new(TestHighlighter)),
+            ..Default::default()
+        };
+        let html = render_markdown("```rust\nfn main() {}\n```\n", None, "", &options).html;
+        assert_snapshot!(html, @r###"
+        <pre><code class="language-rust">fn main() {}
+        </code></pre>
+        "###);
+    }
 }
@@ -1,6 +1,6 @@
 use anyhow::anyhow;
 use clap::Parser;
-use crates_io_tarball::process_tarball;
+use crates_io_tarball::{process_tarball, TarballLimits};
 use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 use std::fs::File;
@@ -71,7 +71,7 @@ fn process_path(path: &Path, pb: &ProgressBar) {
     let pkg_name = path_no_ext.file_name().unwrap().to_string_lossy();
     pb.set_message(format!("{pkg_name}"));
 
-    let result = process_tarball(&pkg_name, &file, u64::MAX);
+    let result = process_tarball(&pkg_name, &file, &TarballLimits::default());
     pb.suspend(|| match result {
         Ok(result) => debug!(%pkg_name, path = %path.display(), ?result),
         Err(error) => warn!(%pkg_name, path = %path.display(), %error, "Failed to process tarball"),
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context};
 use clap::Parser;
-use crates_io_tarball::process_tarball;
+use crates_io_tarball::{process_tarball, TarballLimits};
 use std::fs::File;
 use std::path::PathBuf;
 use tracing_subscriber::filter::LevelFilter;
@@ -30,8 +30,8 @@ fn main() -> anyhow::Result<()> {
     let path_no_ext = path.with_extension("");
     let pkg_name = path_no_ext.file_name().unwrap().to_string_lossy();
 
-    let result =
-        process_tarball(&pkg_name, &file, u64::MAX).context("Failed to process tarball")?;
+    let result = process_tarball(&pkg_name, &file, &TarballLimits::default())
+        .context("Failed to process tarball")?;
 
     println!("{result:#?}");
 
@@ -10,8 +10,9 @@ pub use crate::vcs_info::CargoVcsInfo;
 use cargo_manifest::AbstractFilesystem;
 pub use cargo_manifest::{Manifest, StringOrBool};
 use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
 use std::collections::{BTreeMap, BTreeSet};
-use std::io::Read;
+use std::io::{self, Read};
 use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 use tracing::instrument;
@@ -22,10 +23,44 @@ mod limit_reader;
 mod manifest;
 mod vcs_info;
 
+/// The limits that are enforced while processing a tarball, to reject
+/// decompression bombs and other abusive uploads early.
+#[derive(Debug, Clone, Copy)]
+pub struct TarballLimits {
+    /// The maximum total size of all files in the tarball, after
+    /// decompression.
+    pub max_unpack_size: u64,
+    /// The maximum number of entries in the tarball.
+    pub max_files: usize,
+    /// The maximum number of components of a path, relative to the package
+    /// root.
+    pub max_path_depth: usize,
+}
+
+impl Default for TarballLimits {
+    /// No limits at all, which is only suitable for trusted tarballs.
+    fn default() -> Self {
+        Self {
+            max_unpack_size: u64::MAX,
+            max_files: usize::MAX,
+            max_path_depth: usize::MAX,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TarballInfo {
     pub manifest: Manifest,
     pub vcs_info: Option<CargoVcsInfo>,
+    /// The paths of all files in the tarball, relative to the package root.
+    pub paths: Vec<PathBuf>,
+    /// The SHA256 checksums of the contents of all files in the tarball,
+    /// keyed by their paths relative to the package root. Unlike the
+    /// checksum of the tarball itself, these don't depend on the
+    /// compression or the metadata of the archive.
+    pub file_checksums: BTreeMap<PathBuf, [u8; 32]>,
+    /// The total size of all files in the tarball, after decompression.
+    pub uncompressed_size: u64,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -34,6 +69,14 @@ pub enum TarballError {
     Malformed(#[source] std::io::Error),
     #[error("invalid path found: {0}")]
     InvalidPath(String),
+    #[error("path traversal attempt found: {0}")]
+    PathTraversal(String),
+    #[error("path is nested more than {max} levels deep: {path}")]
+    PathTooDeep { path: String, max: usize },
+    #[error("tarball contains more than {max} files")]
+    TooManyFiles { max: usize },
+    #[error("tarball is larger than {max} bytes when decompressed")]
+    TooLarge { max: u64 },
     #[error("unexpected symlink or hard link found: {0}")]
     UnexpectedSymlink(String),
     #[error("Cargo.toml manifest is missing")]
@@ -52,14 +95,14 @@ pub enum TarballError {
 pub fn process_tarball<R: Read>(
     pkg_name: &str,
     tarball: R,
-    max_unpack: u64,
+    limits: &TarballLimits,
 ) -> Result<TarballInfo, TarballError> {
     // All our data is currently encoded with gzip
     let decoder = GzDecoder::new(tarball);
 
     // Don't let gzip decompression go into the weeeds, apply a fixed cap after
     // which point we say the decompressed source is "too large".
-    let decoder = LimitErrorReader::new(decoder, max_unpack);
+    let decoder = LimitErrorReader::new(decoder, limits.max_unpack_size);
 
     // Use this I/O object now to take a peek inside
     let mut archive = tar::Archive::new(decoder);
@@ -68,6 +111,8 @@ pub fn process_tarball<R: Read>(
 
     let mut vcs_info = None;
     let mut paths = Vec::new();
+    let mut file_checksums = BTreeMap::new();
+    let mut uncompressed_size = 0;
     let mut manifests = BTreeMap::new();
 
     for entry in archive.entries()? {
@@ -83,6 +128,25 @@ pub fn process_tarball<R: Read>(
             return Err(TarballError::InvalidPath(entry_path.display().to_string()));
         };
 
+        // The `tar` crate only guards against `..` components when unpacking
+        // the archive to disk, so we need to check for them ourselves before
+        // the paths are used anywhere else.
+        let is_traversal = in_pkg_path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if is_traversal {
+            return Err(TarballError::PathTraversal(
+                entry_path.display().to_string(),
+            ));
+        }
+
+        if in_pkg_path.components().count() > limits.max_path_depth {
+            return Err(TarballError::PathTooDeep {
+                path: entry_path.display().to_string(),
+                max: limits.max_path_depth,
+            });
+        }
+
         // Historical versions of the `tar` crate which Cargo uses internally
         // don't properly prevent hard links and symlinks from overwriting
         // arbitrary files on the filesystem. As a bit of a hammer we reject any
@@ -95,15 +159,33 @@ pub fn process_tarball<R: Read>(
             ));
         }
 
-        paths.push(in_pkg_path.to_path_buf());
+        if paths.len() >= limits.max_files {
+            return Err(TarballError::TooManyFiles {
+                max: limits.max_files,
+            });
+        }
+
+        // The sizes in the entry headers can be checked before the contents
+        // are decompressed, which rejects decompression bombs early.
+        uncompressed_size += entry.size();
+        if uncompressed_size > limits.max_unpack_size {
+            return Err(TarballError::TooLarge {
+                max: limits.max_unpack_size,
+            });
+        }
+
+        let in_pkg_path = in_pkg_path.to_path_buf();
+        paths.push(in_pkg_path.clone());
 
         // Let's go hunting for the VCS info and crate manifest. The only valid place for these is
         // in the package root in the tarball.
+        let mut hasher = Sha256::new();
         if entry_path.parent() == Some(pkg_root) {
             let entry_file = entry_path.file_name().unwrap_or_default();
             if entry_file == ".cargo_vcs_info.json" {
                 let mut contents = String::new();
                 entry.read_to_string(&mut contents)?;
+                hasher.update(&contents);
                 vcs_info = CargoVcsInfo::from_contents(&contents).ok();
             } else if entry_file.to_ascii_lowercase() == "cargo.toml" {
                 // Try to extract and read the Cargo.toml from the tarball, silently erroring if it
@@ -111,6 +193,7 @@ pub fn process_tarball<R: Read>(
                 let owned_entry_path = entry_path.into_owned();
                 let mut contents = String::new();
                 entry.read_to_string(&mut contents)?;
+                hasher.update(&contents);
 
                 let manifest = Manifest::from_str(&contents)?;
                 validate_manifest(&manifest)?;
@@ -118,6 +201,11 @@ pub fn process_tarball<R: Read>(
                 manifests.insert(owned_entry_path, manifest);
             }
         }
+
+        // Reading the remaining contents is a no-op for the files that were
+        // already read above.
+        io::copy(&mut entry, &mut hasher).map_err(TarballError::Malformed)?;
+        file_checksums.insert(in_pkg_path, hasher.finalize().into());
     }
 
     if manifests.len() > 1 {
@@ -140,14 +228,28 @@ pub fn process_tarball<R: Read>(
         return Err(TarballError::IncorrectlyCasedManifest(file.into()));
     }
 
-    manifest.complete_from_abstract_filesystem(&PathsFileSystem(paths))?;
+    // Cargo automatically uses a `build.rs` file in the package root as the
+    // build script, unless `package.build` is set explicitly.
+    if let Some(package) = manifest.package.as_mut() {
+        if package.build.is_none() && paths.iter().any(|path| path == Path::new("build.rs")) {
+            package.build = Some(StringOrBool::String("build.rs".to_string()));
+        }
+    }
+
+    manifest.complete_from_abstract_filesystem(&PathsFileSystem(&paths))?;
 
-    Ok(TarballInfo { manifest, vcs_info })
+    Ok(TarballInfo {
+        manifest,
+        vcs_info,
+        paths,
+        file_checksums,
+        uncompressed_size,
+    })
 }
 
-struct PathsFileSystem(Vec<PathBuf>);
+struct PathsFileSystem<'a>(&'a [PathBuf]);
 
-impl AbstractFilesystem for PathsFileSystem {
+impl AbstractFilesystem for PathsFileSystem<'_> {
     fn file_names_in<T: AsRef<Path>>(&self, rel_path: T) -> std::io::Result<BTreeSet<Box<str>>> {
         let mut rel_path = rel_path.as_ref();
 
@@ -175,13 +277,19 @@ impl AbstractFilesystem for PathsFileSystem {
 
 #[cfg(test)]
 mod tests {
-    use super::process_tarball;
-    use crate::TarballBuilder;
+    use super::{process_tarball, TarballLimits};
+    use crate::{TarballBuilder, TarballError};
     use cargo_manifest::{MaybeInherited, StringOrBool};
     use insta::{assert_debug_snapshot, assert_snapshot};
+    use sha2::{Digest, Sha256};
+    use std::path::{Path, PathBuf};
 
     const MANIFEST: &[u8] = b"[package]\nname = \"foo\"\nversion = \"0.0.1\"\n";
-    const MAX_SIZE: u64 = 512 * 1024 * 1024;
+    const LIMITS: TarballLimits = TarballLimits {
+        max_unpack_size: 512 * 1024 * 1024,
+        max_files: 1000,
+        max_path_depth: 10,
+    };
 
     #[test]
     fn process_tarball_test() {
@@ -189,13 +297,17 @@ mod tests {
             .add_file("foo-0.0.1/Cargo.toml", MANIFEST)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         assert_none!(tarball_info.vcs_info);
+        assert_eq!(tarball_info.paths, vec![PathBuf::from("Cargo.toml")]);
+        assert_eq!(tarball_info.uncompressed_size, MANIFEST.len() as u64);
+        let checksum = tarball_info.file_checksums[Path::new("Cargo.toml")];
+        assert_eq!(checksum, <[u8; 32]>::from(Sha256::digest(MANIFEST)));
         assert_none!(tarball_info.manifest.lib);
         assert_eq!(tarball_info.manifest.bin, vec![]);
         assert_eq!(tarball_info.manifest.example, vec![]);
 
-        let err = assert_err!(process_tarball("bar-0.0.1", &*tarball, MAX_SIZE));
+        let err = assert_err!(process_tarball("bar-0.0.1", &*tarball, &LIMITS));
         assert_snapshot!(err, @"invalid path found: foo-0.0.1/Cargo.toml");
     }
 
@@ -206,7 +318,7 @@ mod tests {
             .add_file("foo-0.0.1/.cargo_vcs_info.json", br#"{"unknown": "field"}"#)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let vcs_info = assert_some!(tarball_info.vcs_info);
         assert_eq!(vcs_info.path_in_vcs, "");
     }
@@ -219,7 +331,7 @@ mod tests {
             .add_file("foo-0.0.1/.cargo_vcs_info.json", vcs_info)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let vcs_info = assert_some!(tarball_info.vcs_info);
         assert_eq!(vcs_info.path_in_vcs, "path/in/vcs");
     }
@@ -238,7 +350,7 @@ mod tests {
             .add_file("foo-0.0.1/Cargo.toml", manifest)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let package = assert_some!(tarball_info.manifest.package);
         assert_matches!(package.readme, Some(MaybeInherited::Local(StringOrBool::String(s))) if s == "README.md");
         assert_matches!(package.repository, Some(MaybeInherited::Local(s)) if s ==  "https://github.com/foo/bar");
@@ -257,7 +369,7 @@ mod tests {
             .add_file("foo-0.0.1/Cargo.toml", manifest)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let package = assert_some!(tarball_info.manifest.package);
         assert_matches!(package.rust_version, Some(MaybeInherited::Local(s)) if s == "1.23");
     }
@@ -268,7 +380,7 @@ mod tests {
             .add_file("foo-0.0.1/Cargo.toml", MANIFEST)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let package = assert_some!(tarball_info.manifest.package);
         assert_none!(package.readme);
     }
@@ -285,7 +397,7 @@ mod tests {
             .add_file("foo-0.0.1/Cargo.toml", manifest)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let package = assert_some!(tarball_info.manifest.package);
         assert_matches!(package.readme, Some(MaybeInherited::Local(StringOrBool::Bool(b))) if !b);
     }
@@ -302,11 +414,41 @@ mod tests {
             .add_file("foo-0.0.1/cargo.toml", manifest)
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let package = assert_some!(tarball_info.manifest.package);
         assert_matches!(package.repository, Some(MaybeInherited::Local(s)) if s ==  "https://github.com/foo/bar");
     }
 
+    #[test]
+    fn process_tarball_test_build_script() {
+        let process = |manifest: &[u8], files: &[&str]| {
+            let tarball = files
+                .iter()
+                .fold(TarballBuilder::new(), |builder, file| {
+                    builder.add_file(&format!("foo-0.0.1/{file}"), b"")
+                })
+                .add_file("foo-0.0.1/Cargo.toml", manifest)
+                .build();
+
+            let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
+            assert_some!(tarball_info.manifest.package).build
+        };
+
+        assert_none!(process(MANIFEST, &[]));
+        assert_none!(process(MANIFEST, &["src/build.rs"]));
+
+        let build = process(MANIFEST, &["build.rs"]);
+        assert_matches!(build, Some(StringOrBool::String(s)) if s == "build.rs");
+
+        let manifest = b"[package]\nname = \"foo\"\nversion = \"0.0.1\"\nbuild = false\n";
+        let build = process(manifest, &["build.rs"]);
+        assert_matches!(build, Some(StringOrBool::Bool(false)));
+
+        let manifest = b"[package]\nname = \"foo\"\nversion = \"0.0.1\"\nbuild = \"gen.rs\"\n";
+        let build = process(manifest, &["gen.rs"]);
+        assert_matches!(build, Some(StringOrBool::String(s)) if s == "gen.rs");
+    }
+
     #[test]
     fn process_tarball_test_incorrect_manifest_casing() {
         let process = |file: &str| {
@@ -314,7 +456,7 @@ mod tests {
                 .add_file(&format!("foo-0.0.1/{file}"), MANIFEST)
                 .build();
 
-            process_tarball("foo-0.0.1", &*tarball, MAX_SIZE)
+            process_tarball("foo-0.0.1", &*tarball, &LIMITS)
         };
 
         let err = assert_err!(process("CARGO.TOML"));
@@ -324,6 +466,62 @@ mod tests {
         assert_snapshot!(err, @r###"Cargo.toml manifest is incorrectly cased: "Cargo.Toml""###);
     }
 
+    #[test]
+    fn process_tarball_test_path_traversal() {
+        let mut builder = TarballBuilder::new().add_file("foo-0.0.1/Cargo.toml", MANIFEST);
+
+        // `tar::Header::set_path()` refuses `..` components, so the name has
+        // to be written into the header directly.
+        let mut header = tar::Header::new_gnu();
+        let name = b"foo-0.0.1/../bar-0.0.1/src/lib.rs";
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.set_size(0);
+        header.set_cksum();
+        assert_ok!(builder.as_mut().append(&header, &[][..]));
+
+        let tarball = builder.build();
+        let err = assert_err!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
+        assert_snapshot!(err, @"path traversal attempt found: foo-0.0.1/../bar-0.0.1/src/lib.rs");
+    }
+
+    #[test]
+    fn process_tarball_test_limits() {
+        let tarball = TarballBuilder::new()
+            .add_file("foo-0.0.1/Cargo.toml", MANIFEST)
+            .add_file("foo-0.0.1/src/lib.rs", b"pub fn foo() {}")
+            .add_file("foo-0.0.1/src/a/b/c.rs", b"")
+            .build();
+
+        let limits = TarballLimits {
+            max_files: 2,
+            ..LIMITS
+        };
+        let err = assert_err!(process_tarball("foo-0.0.1", &*tarball, &limits));
+        assert_matches!(err, TarballError::TooManyFiles { max: 2 });
+        assert_snapshot!(err, @"tarball contains more than 2 files");
+
+        let limits = TarballLimits {
+            max_path_depth: 3,
+            ..LIMITS
+        };
+        let err = assert_err!(process_tarball("foo-0.0.1", &*tarball, &limits));
+        assert_snapshot!(err, @"path is nested more than 3 levels deep: foo-0.0.1/src/a/b/c.rs");
+
+        let limits = TarballLimits {
+            max_unpack_size: MANIFEST.len() as u64 + 10,
+            ..LIMITS
+        };
+        let err = assert_err!(process_tarball("foo-0.0.1", &*tarball, &limits));
+        assert_snapshot!(err, @"tarball is larger than 51 bytes when decompressed");
+
+        let limits = TarballLimits {
+            max_files: 3,
+            max_path_depth: 4,
+            ..LIMITS
+        };
+        assert_ok!(process_tarball("foo-0.0.1", &*tarball, &limits));
+    }
+
     #[test]
     fn process_tarball_test_multiple_manifests() {
         let process = |files: Vec<&str>| {
@@ -334,7 +532,7 @@ mod tests {
                 })
                 .build();
 
-            process_tarball("foo-0.0.1", &*tarball, MAX_SIZE)
+            process_tarball("foo-0.0.1", &*tarball, &LIMITS)
         };
 
         let err = assert_err!(process(vec!["cargo.toml", "Cargo.toml"]));
@@ -354,7 +552,7 @@ mod tests {
             .add_file("foo-0.0.1/src/lib.rs", b"pub fn foo() {}")
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let lib = assert_some!(tarball_info.manifest.lib);
         assert_debug_snapshot!(lib);
         assert_eq!(tarball_info.manifest.bin, vec![]);
@@ -371,7 +569,7 @@ mod tests {
             .add_file("foo-0.0.1/src/bin/bar.rs", b"fn main() {}")
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         let lib = assert_some!(tarball_info.manifest.lib);
         assert_debug_snapshot!(lib);
         assert_debug_snapshot!(tarball_info.manifest.bin);
@@ -385,7 +583,7 @@ mod tests {
             .add_file("foo-0.0.1/src/main.rs", b"fn main() {}")
             .build();
 
-        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, MAX_SIZE));
+        let tarball_info = assert_ok!(process_tarball("foo-0.0.1", &*tarball, &LIMITS));
         assert_none!(tarball_info.manifest.lib);
         assert_debug_snapshot!(tarball_info.manifest.bin);
         assert_eq!(tarball_info.manifest.example, vec![]);
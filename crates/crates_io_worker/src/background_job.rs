@@ -24,6 +24,14 @@ pub trait BackgroundJob: Serialize + DeserializeOwned + Send + Sync + 'static {
     /// Job queue where this job will be executed.
     const QUEUE: &'static str = DEFAULT_QUEUE;
 
+    /// Whether at most one job of this type may run at the same time, across
+    /// all worker instances.
+    ///
+    /// Exclusive jobs hold a Postgres advisory lock for their job type while
+    /// they are running. Other workers skip the pending jobs of the same type
+    /// until the lock is released.
+    const EXCLUSIVE: bool = false;
+
     /// The application data provided to this job at runtime.
     type Context: Clone + Send + 'static;
 
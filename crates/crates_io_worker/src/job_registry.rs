@@ -1,5 +1,5 @@
 use crate::BackgroundJob;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -10,12 +10,14 @@ type RunTaskFn<Context> = dyn Fn(Context, serde_json::Value) -> RunTaskFnReturn
 #[derive(Clone)]
 pub struct JobRegistry<Context> {
     entries: HashMap<String, Arc<RunTaskFn<Context>>>,
+    exclusive: HashSet<String>,
 }
 
 impl<Context> Default for JobRegistry<Context> {
     fn default() -> Self {
         Self {
             entries: HashMap::new(),
+            exclusive: HashSet::new(),
         }
     }
 }
@@ -24,6 +26,15 @@ impl<Context: Clone + Send + Sync + 'static> JobRegistry<Context> {
     pub fn register<J: BackgroundJob<Context = Context>>(&mut self) {
         self.entries
             .insert(J::JOB_NAME.to_string(), Arc::new(runnable::<J>));
+
+        if J::EXCLUSIVE {
+            self.exclusive.insert(J::JOB_NAME.to_string());
+        }
+    }
+
+    /// Returns whether the job type was declared as [`BackgroundJob::EXCLUSIVE`].
+    pub fn is_exclusive(&self, key: &str) -> bool {
+        self.exclusive.contains(key)
     }
 
     pub fn get(&self, key: &str) -> Option<&Arc<RunTaskFn<Context>>> {
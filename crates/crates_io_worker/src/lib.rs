@@ -1,6 +1,7 @@
 mod background_job;
 mod errors;
 mod job_registry;
+mod metrics;
 mod runner;
 pub mod schema;
 mod storage;
@@ -9,4 +10,5 @@ mod worker;
 
 pub use self::background_job::BackgroundJob;
 pub use self::errors::EnqueueError;
+pub use self::metrics::WorkerMetrics;
 pub use self::runner::Runner;
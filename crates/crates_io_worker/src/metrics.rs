@@ -0,0 +1,58 @@
+use prometheus::proto::MetricFamily;
+use prometheus::{IntCounterVec, Opts, Registry};
+
+/// Metrics of the background workers of a [`Runner`](crate::Runner).
+#[derive(Clone)]
+pub struct WorkerMetrics {
+    registry: Registry,
+    /// Number of times that an exclusive job was run, by job type
+    pub(crate) exclusive_job_locks_acquired: IntCounterVec,
+    /// Number of times that an exclusive job was skipped because a job of the
+    /// same type was already running, by job type
+    pub(crate) exclusive_job_lock_contention: IntCounterVec,
+}
+
+impl WorkerMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+
+        let exclusive_job_locks_acquired = IntCounterVec::new(
+            Opts::new(
+                "exclusive_job_locks_acquired_total",
+                "Number of times that an exclusive job was run",
+            )
+            .namespace("cratesio_worker"),
+            &["job"],
+        )?;
+        registry.register(Box::new(exclusive_job_locks_acquired.clone()))?;
+
+        let exclusive_job_lock_contention = IntCounterVec::new(
+            Opts::new(
+                "exclusive_job_lock_contention_total",
+                "Number of times that an exclusive job was skipped because a job of the same \
+                 type was already running",
+            )
+            .namespace("cratesio_worker"),
+            &["job"],
+        )?;
+        registry.register(Box::new(exclusive_job_lock_contention.clone()))?;
+
+        Ok(Self {
+            registry,
+            exclusive_job_locks_acquired,
+            exclusive_job_lock_contention,
+        })
+    }
+
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+
+    /// Returns the number of times that an exclusive job of the given type
+    /// was skipped because a job of the same type was already running.
+    pub fn lock_contention(&self, job_type: &str) -> u64 {
+        self.exclusive_job_lock_contention
+            .with_label_values(&[job_type])
+            .get()
+    }
+}
@@ -1,5 +1,6 @@
 use crate::background_job::DEFAULT_QUEUE;
 use crate::job_registry::JobRegistry;
+use crate::metrics::WorkerMetrics;
 use crate::worker::Worker;
 use crate::{storage, BackgroundJob};
 use anyhow::anyhow;
@@ -8,8 +9,10 @@ use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::AsyncPgConnection;
 use futures_util::future::join_all;
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::watch;
 use tokio::task::{spawn_blocking, JoinHandle};
 use tracing::{info, info_span, warn, Instrument};
 
@@ -21,6 +24,7 @@ pub struct Runner<Context> {
     queues: HashMap<String, Queue<Context>>,
     context: Context,
     shutdown_when_queue_empty: bool,
+    metrics: WorkerMetrics,
 }
 
 impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
@@ -30,6 +34,7 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
             queues: HashMap::new(),
             context,
             shutdown_when_queue_empty: false,
+            metrics: WorkerMetrics::new().expect("could not initialize worker metrics"),
         }
     }
 
@@ -64,10 +69,17 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
         self
     }
 
+    /// Returns the metrics of the background workers.
+    pub fn metrics(&self) -> &WorkerMetrics {
+        &self.metrics
+    }
+
     /// Start the background workers.
     ///
     /// This returns a `RunningRunner` which can be used to wait for the workers to shutdown.
     pub fn start(&self) -> RunHandle {
+        let (shutdown, shutdown_requested) = watch::channel(false);
+
         let mut handles = Vec::new();
         for (queue_name, queue) in &self.queues {
             for i in 1..=queue.num_workers {
@@ -80,6 +92,8 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
                     job_registry: Arc::new(queue.job_registry.clone()),
                     shutdown_when_queue_empty: self.shutdown_when_queue_empty,
                     poll_interval: queue.poll_interval,
+                    shutdown_requested: shutdown_requested.clone(),
+                    metrics: self.metrics.clone(),
                 };
 
                 let span = info_span!("worker", worker.name = %name);
@@ -89,7 +103,7 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
             }
         }
 
-        RunHandle { handles }
+        RunHandle { handles, shutdown }
     }
 
     /// Check if any jobs in the queue have failed.
@@ -115,6 +129,7 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
 
 pub struct RunHandle {
     handles: Vec<JoinHandle<()>>,
+    shutdown: watch::Sender<bool>,
 }
 
 impl RunHandle {
@@ -126,6 +141,16 @@ impl RunHandle {
             }
         });
     }
+
+    /// Ask all background workers to shut down, and wait for them to do so.
+    ///
+    /// The workers finish the jobs that they are currently running, so that
+    /// these jobs are either deleted or rescheduled, but don't start any
+    /// new jobs.
+    pub fn shutdown(self) -> impl Future<Output = ()> {
+        self.shutdown.send_replace(true);
+        self.wait_for_shutdown()
+    }
 }
 
 pub struct Queue<Context> {
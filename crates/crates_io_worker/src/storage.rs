@@ -3,7 +3,7 @@ use diesel::connection::LoadConnection;
 use diesel::dsl::now;
 use diesel::pg::Pg;
 use diesel::prelude::*;
-use diesel::sql_types::{Bool, Integer, Interval};
+use diesel::sql_types::{Bool, Integer, Interval, Text};
 use diesel::{delete, update};
 
 #[derive(Queryable, Selectable, Identifiable, Debug, Clone)]
@@ -40,6 +40,28 @@ pub(super) fn find_next_unlocked_job(
         .first::<BackgroundJob>(conn)
 }
 
+/// The first key of the advisory locks of exclusive jobs, which avoids
+/// conflicts with advisory locks that are taken for other purposes.
+const EXCLUSIVE_JOB_LOCK_CLASS: i32 = 0x6a6f6273; // "jobs"
+
+define_sql_function!(fn hashtext(x: Text) -> Integer);
+define_sql_function!(fn pg_try_advisory_xact_lock(key1: Integer, key2: Integer) -> Bool);
+
+/// Tries to take the advisory lock of the given job type, which is held until
+/// the end of the current transaction.
+///
+/// Returns `false` if the lock is already held by another transaction.
+pub(super) fn try_lock_job_type(
+    conn: &mut impl LoadConnection<Backend = Pg>,
+    job_type: &str,
+) -> QueryResult<bool> {
+    diesel::select(pg_try_advisory_xact_lock(
+        EXCLUSIVE_JOB_LOCK_CLASS,
+        hashtext(job_type),
+    ))
+    .get_result(conn)
+}
+
 /// The number of jobs that have failed at least once
 pub(super) fn failed_job_count(conn: &mut impl LoadConnection<Backend = Pg>) -> QueryResult<i64> {
     background_jobs::table
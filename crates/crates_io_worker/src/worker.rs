@@ -1,4 +1,5 @@
 use crate::job_registry::JobRegistry;
+use crate::metrics::WorkerMetrics;
 use crate::storage;
 use crate::util::{try_to_extract_panic_info, with_sentry_transaction};
 use anyhow::anyhow;
@@ -12,6 +13,7 @@ use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime::Handle;
+use tokio::sync::watch;
 use tokio::task::spawn_blocking;
 use tokio::time::sleep;
 use tracing::{debug, error, info_span, warn};
@@ -22,12 +24,22 @@ pub struct Worker<Context> {
     pub(crate) job_registry: Arc<JobRegistry<Context>>,
     pub(crate) shutdown_when_queue_empty: bool,
     pub(crate) poll_interval: Duration,
+    pub(crate) shutdown_requested: watch::Receiver<bool>,
+    pub(crate) metrics: WorkerMetrics,
 }
 
 impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
     /// Run background jobs forever, or until the queue is empty if `shutdown_when_queue_empty` is set.
+    ///
+    /// The worker also stops once a shutdown is requested, but only after
+    /// the job that it is currently running has finished.
     pub async fn run(&self) {
         loop {
+            if *self.shutdown_requested.borrow() {
+                debug!("Shutdown requested. Shutting down the worker…");
+                break;
+            }
+
             match self.run_next_job().await {
                 Ok(Some(_)) => {}
                 Ok(None) if self.shutdown_when_queue_empty => {
@@ -39,17 +51,27 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                         "No pending background worker jobs found. Polling again in {:?}…",
                         self.poll_interval
                     );
-                    sleep(self.poll_interval).await;
+                    self.wait_for_next_poll().await;
                 }
                 Err(error) => {
                     let error = format!("{error:#}");
                     error!(error, "Failed to run job");
-                    sleep(self.poll_interval).await;
+                    self.wait_for_next_poll().await;
                 }
             }
         }
     }
 
+    /// Sleep until the next poll, or until a shutdown is requested.
+    async fn wait_for_next_poll(&self) {
+        let mut shutdown_requested = self.shutdown_requested.clone();
+
+        tokio::select! {
+            _ = sleep(self.poll_interval) => {}
+            Ok(_) = shutdown_requested.wait_for(|requested| *requested) => {}
+        }
+    }
+
     /// Run the next job in the queue, if there is one.
     ///
     /// Returns:
@@ -59,17 +81,43 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
     async fn run_next_job(&self) -> anyhow::Result<Option<i64>> {
         let context = self.context.clone();
         let job_registry = self.job_registry.clone();
+        let metrics = self.metrics.clone();
         let conn = self.connection_pool.get().await?;
 
         spawn_blocking(move || {
             let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-            let job_types = job_registry.job_types();
+            let mut job_types = job_registry.job_types();
             conn.transaction(|conn| {
                 debug!("Looking for next background worker job…");
-                let Some(job) = storage::find_next_unlocked_job(conn, &job_types).optional()?
-                else {
-                    return Ok(None);
+                let job = loop {
+                    let Some(job) = storage::find_next_unlocked_job(conn, &job_types).optional()?
+                    else {
+                        return Ok(None);
+                    };
+
+                    if !job_registry.is_exclusive(&job.job_type) {
+                        break job;
+                    }
+
+                    // The advisory lock is held until the transaction ends,
+                    // i.e. until the job has finished running.
+                    let labels = [job.job_type.as_str()];
+                    if storage::try_lock_job_type(conn, &job.job_type)? {
+                        metrics
+                            .exclusive_job_locks_acquired
+                            .with_label_values(&labels)
+                            .inc();
+                        break job;
+                    }
+
+                    debug!(job.typ = %job.job_type, "Skipping exclusive job that is already running…");
+                    metrics
+                        .exclusive_job_lock_contention
+                        .with_label_values(&labels)
+                        .inc();
+
+                    job_types.retain(|job_type| *job_type != job.job_type);
                 };
 
                 let span = info_span!("job", job.id = %job.id, job.typ = %job.job_type);
@@ -7,6 +7,7 @@ use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::AsyncPgConnection;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Barrier;
 
 fn job_exists(id: i64, conn: &mut PgConnection) -> bool {
@@ -116,6 +117,134 @@ async fn jobs_are_deleted_when_successfully_run() {
     assert_eq!(remaining_jobs(&mut conn), 0);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn shutdown_finishes_running_jobs() {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started_barrier: Arc<Barrier>,
+        assertions_finished_barrier: Arc<Barrier>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.job_started_barrier.wait().await;
+            ctx.assertions_finished_barrier.wait().await;
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let test_context = TestContext {
+        job_started_barrier: Arc::new(Barrier::new(2)),
+        assertions_finished_barrier: Arc::new(Barrier::new(2)),
+    };
+
+    // Unlike the other tests, the runner keeps polling for new jobs until
+    // it is shut down.
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(test_database.url());
+    let deadpool = Pool::builder(manager).max_size(4).build().unwrap();
+    let runner = Runner::new(deadpool, test_context.clone())
+        .configure_default_queue(|queue| queue.num_workers(1))
+        .register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let running_job_id = TestJob.enqueue(&mut conn).unwrap();
+
+    let runner = runner.start();
+    test_context.job_started_barrier.wait().await;
+
+    let pending_job_id = TestJob.enqueue(&mut conn).unwrap();
+    let shutdown = runner.shutdown();
+
+    test_context.assertions_finished_barrier.wait().await;
+    shutdown.await;
+
+    // The running job was finished, but the pending job was not started.
+    assert!(!job_exists(running_job_id, &mut conn));
+    assert!(job_exists(pending_job_id, &mut conn));
+    assert!(!job_is_locked(pending_job_id, &mut conn));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exclusive_jobs_do_not_run_concurrently() {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started_barrier: Arc<Barrier>,
+        assertions_finished_barrier: Arc<Barrier>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        const EXCLUSIVE: bool = true;
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.job_started_barrier.wait().await;
+            ctx.assertions_finished_barrier.wait().await;
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let test_context = TestContext {
+        job_started_barrier: Arc::new(Barrier::new(2)),
+        assertions_finished_barrier: Arc::new(Barrier::new(2)),
+    };
+
+    let runner = runner(test_database.url(), test_context.clone()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let first_job_id = TestJob.enqueue(&mut conn).unwrap();
+    let second_job_id = TestJob.enqueue(&mut conn).unwrap();
+
+    let handle = runner.start();
+    test_context.job_started_barrier.wait().await;
+
+    // Wait for the other worker to skip the other job
+    for _ in 0..100 {
+        if runner.metrics().lock_contention(TestJob::JOB_NAME) > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(runner.metrics().lock_contention(TestJob::JOB_NAME), 1);
+
+    // Depending on which worker takes the advisory lock first, either of the
+    // jobs may be running, but never both of them.
+    let job_ids = [first_job_id, second_job_id];
+    let locked_jobs = |conn: &mut PgConnection| {
+        job_ids
+            .iter()
+            .filter(|id| job_is_locked(**id, conn))
+            .count()
+    };
+    let existing_jobs =
+        |conn: &mut PgConnection| job_ids.iter().filter(|id| job_exists(**id, conn)).count();
+
+    assert_eq!(locked_jobs(&mut conn), 1);
+    test_context.assertions_finished_barrier.wait().await;
+
+    // The other job runs once the first one has finished
+    test_context.job_started_barrier.wait().await;
+    assert_eq!(existing_jobs(&mut conn), 1);
+    assert_eq!(locked_jobs(&mut conn), 1);
+    test_context.assertions_finished_barrier.wait().await;
+
+    handle.wait_for_shutdown().await;
+    assert_eq!(existing_jobs(&mut conn), 0);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn failed_jobs_do_not_release_lock_before_updating_retry_time() {
     #[derive(Clone)]
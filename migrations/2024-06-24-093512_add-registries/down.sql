@@ -0,0 +1,5 @@
+alter table api_tokens
+    drop column registry;
+
+alter table crates
+    drop column registry;
@@ -0,0 +1,9 @@
+alter table crates
+    add column registry varchar not null default 'default';
+
+comment on column crates.registry is 'Name of the registry that the crate belongs to, see `src/registries.rs`.';
+
+alter table api_tokens
+    add column registry varchar not null default 'default';
+
+comment on column api_tokens.registry is 'Name of the registry that the token can be used with.';
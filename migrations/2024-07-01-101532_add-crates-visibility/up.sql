@@ -0,0 +1,4 @@
+alter table crates
+    add column visibility integer not null default 0;
+
+comment on column crates.visibility is 'Visibility of the crate. 0 = public, 1 = private. Private crates are hidden from search, and their metadata and downloads are only available to their owners.';
@@ -0,0 +1,4 @@
+drop table pending_publishes;
+
+alter table users
+    drop column publish_confirmation_required;
@@ -0,0 +1,26 @@
+alter table users
+    add column publish_confirmation_required boolean not null default false;
+
+comment on column users.publish_confirmation_required is 'If true, publishes using an API token are held pending until the user confirms them via the link in the confirmation email.';
+
+create table pending_publishes
+(
+    id           serial primary key,
+    user_id      integer   not null references users (id) on delete cascade,
+    api_token_id integer references api_tokens (id) on delete set null,
+    crate_name   varchar   not null,
+    version      varchar   not null,
+    body         bytea     not null,
+    token        text      not null default random_string(26) unique,
+    created_at   timestamp not null default now()
+);
+
+comment on table pending_publishes is 'Publishes that are held back until the user confirms them via email.';
+comment on column pending_publishes.id is 'Unique identifier of the pending publish.';
+comment on column pending_publishes.user_id is 'The user that published the crate, and that has to confirm the publish.';
+comment on column pending_publishes.api_token_id is 'The API token that was used to publish the crate.';
+comment on column pending_publishes.crate_name is 'Name of the crate that is being published.';
+comment on column pending_publishes.version is 'Version of the crate that is being published.';
+comment on column pending_publishes.body is 'Raw body of the original publish request, including the metadata and the tarball.';
+comment on column pending_publishes.token is 'Secret token that is sent to the user to confirm the publish.';
+comment on column pending_publishes.created_at is 'Date and time when the publish was requested. Pending publishes expire after a day.';
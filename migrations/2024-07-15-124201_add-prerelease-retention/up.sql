@@ -0,0 +1,4 @@
+alter table crates
+    add column prerelease_retention_days integer;
+
+comment on column crates.prerelease_retention_days is 'If set, pre-release versions older than this number of days are automatically yanked once a newer stable version has been published.';
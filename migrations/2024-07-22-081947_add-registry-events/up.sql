@@ -0,0 +1,15 @@
+create table registry_events
+(
+    id         bigserial primary key,
+    kind       integer   not null,
+    crate_name varchar   not null,
+    version    varchar,
+    created_at timestamp not null default now()
+);
+
+comment on table registry_events is 'Append-only log of changes to the registry, which is exposed via the changes feed.';
+comment on column registry_events.id is 'Monotonically increasing identifier of the event, which is used as the cursor of the changes feed.';
+comment on column registry_events.kind is 'The kind of change: 0=publish, 1=yank, 2=unyank, 3=owner change, 4=delete.';
+comment on column registry_events.crate_name is 'Name of the crate that was changed. Deliberately not a foreign key, since events outlive deleted crates.';
+comment on column registry_events.version is 'Version of the crate that was changed, or NULL if the event applies to the whole crate.';
+comment on column registry_events.created_at is 'Date and time when the change happened.';
@@ -0,0 +1,7 @@
+drop trigger trigger_set_semver_ord on versions;
+drop function set_semver_ord();
+
+alter table versions
+    drop column semver_ord;
+
+drop function semver_ord(varchar);
@@ -0,0 +1,97 @@
+-- Returns a `jsonb` value that sorts versions in semver order, or NULL if the
+-- version number can not be parsed.
+--
+-- The value is an array of the form `[major, minor, patch, prerelease]`.
+-- Build metadata is ignored, as specified by semver. `jsonb` arrays are
+-- compared element by element, but only if they have the same length.
+-- Numeric prerelease identifiers are stored as numbers, and alphanumeric
+-- identifiers as single-element arrays, since `jsonb` sorts arrays after
+-- numbers, which matches the semver rules for prerelease identifiers. Note
+-- that strings are compared using the collation of the database, which may
+-- differ from the ASCII ordering that semver specifies for alphanumeric
+-- identifiers.
+create or replace function semver_ord(num varchar) returns jsonb
+    immutable
+    strict
+    parallel safe
+    language plpgsql
+as
+$$
+declare
+    -- Build metadata is ignored when comparing versions
+    version     varchar := split_part(num, '+', 1);
+    prerelease  varchar;
+    identifiers varchar[];
+    identifier  varchar;
+    pre         jsonb   := '[]'::jsonb;
+begin
+    if strpos(version, '-') > 0 then
+        prerelease := substr(version, strpos(version, '-') + 1);
+        version := substr(version, 1, strpos(version, '-') - 1);
+    end if;
+
+    if version !~ '^\d+\.\d+\.\d+$' then
+        return null;
+    end if;
+
+    if prerelease is null then
+        -- Objects are sorted after arrays in `jsonb`, so versions without a
+        -- prerelease part are sorted after all of their prereleases.
+        pre := '{}'::jsonb;
+    else
+        identifiers := string_to_array(prerelease, '.');
+
+        -- `jsonb` arrays are only compared element by element if they have
+        -- the same length, so all arrays are padded to exactly 10 elements.
+        -- Prereleases with more identifiers keep the identifiers after the
+        -- ninth as a single alphanumeric identifier, which only approximates
+        -- the semver order in this very rare case.
+        if array_length(identifiers, 1) > 10 then
+            identifiers := identifiers[1:9] || array_to_string(identifiers[10:], '.');
+        end if;
+
+        foreach identifier in array identifiers
+            loop
+                if identifier ~ '^\d+$' then
+                    pre := pre || to_jsonb(identifier::numeric);
+                else
+                    pre := pre || jsonb_build_array(jsonb_build_array(identifier));
+                end if;
+            end loop;
+
+        -- The identifiers are padded with `null` values, which are sorted
+        -- before any number or string, so that shorter prereleases are sorted
+        -- before longer ones with the same leading identifiers.
+        while jsonb_array_length(pre) < 10
+            loop
+                pre := pre || 'null'::jsonb;
+            end loop;
+    end if;
+
+    return jsonb_build_array(
+        split_part(version, '.', 1)::numeric,
+        split_part(version, '.', 2)::numeric,
+        split_part(version, '.', 3)::numeric,
+        pre
+    );
+end;
+$$;
+
+alter table versions
+    add column semver_ord jsonb;
+
+comment on column versions.semver_ord is 'JSONB representation of the version number for sorting purposes.';
+
+create or replace function set_semver_ord() returns trigger as
+$$
+begin
+    new.semver_ord := semver_ord(new.num);
+    return new;
+end
+$$ language plpgsql;
+
+create trigger trigger_set_semver_ord
+    before insert or update of num
+    on versions
+    for each row
+execute procedure set_semver_ord();
@@ -0,0 +1,5 @@
+-- The `semver_ord` column of existing versions is calculated by the
+-- `backfill_semver_ord` background job, so that the `versions` table is not
+-- locked by a single large `update` statement.
+create index concurrently if not exists versions_crate_id_semver_ord_idx
+    on versions (crate_id, semver_ord desc nulls last);
@@ -0,0 +1,3 @@
+drop table crate_name_skeleton_overrides;
+
+drop function crate_name_skeleton(text);
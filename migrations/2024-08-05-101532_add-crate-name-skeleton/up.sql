@@ -0,0 +1,36 @@
+-- Returns a normalized "skeleton" of a crate name, which is used to detect
+-- new crate names that can easily be confused with the name of an existing
+-- crate.
+--
+-- In addition to the normalization of `canon_crate_name()` (case and
+-- `-`/`_` differences), this maps ASCII characters and character sequences
+-- that look alike in common fonts to the same representation. Crate names
+-- are restricted to ASCII, so Unicode confusables can not be registered in
+-- the first place.
+create or replace function crate_name_skeleton(name text) returns text
+    immutable
+    strict
+    parallel safe
+    language sql
+as
+$$
+select replace(
+    replace(
+        -- An uppercase `I` looks like a lowercase `l`, so it has to be
+        -- mapped before the name is converted to lowercase.
+        translate(lower(replace(name, 'I', 'l')), '-01', '_ol'),
+        'rn', 'm'
+    ),
+    'vv', 'w'
+);
+$$;
+
+create table crate_name_skeleton_overrides
+(
+    name       varchar   not null primary key,
+    created_at timestamp not null default now()
+);
+
+comment on table crate_name_skeleton_overrides is 'Crate names that may be published even though they can be confused with the name of an existing crate.';
+comment on column crate_name_skeleton_overrides.name is 'Name of the crate that is allowed to be published.';
+comment on column crate_name_skeleton_overrides.created_at is 'Date and time when the override was added.';
@@ -0,0 +1 @@
+drop index concurrently if exists crates_crate_name_skeleton_idx;
@@ -0,0 +1,2 @@
+create index concurrently if not exists crates_crate_name_skeleton_idx
+    on crates (crate_name_skeleton(name));
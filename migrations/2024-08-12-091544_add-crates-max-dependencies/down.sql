@@ -0,0 +1,2 @@
+alter table crates
+    drop column max_dependencies;
@@ -0,0 +1,4 @@
+alter table crates
+    add column max_dependencies smallint;
+
+comment on column crates.max_dependencies is 'If set, overrides the maximum number of dependencies that a version of this crate can declare.';
@@ -0,0 +1,17 @@
+create table metadata_findings
+(
+    id         serial primary key,
+    version_id integer   not null references versions (id) on delete cascade,
+    rule       integer   not null,
+    message    text      not null,
+    created_at timestamp not null default now()
+);
+
+comment on table metadata_findings is 'Violations of the current publish validation rules that were found in the metadata of already published versions.';
+comment on column metadata_findings.id is 'Unique identifier of the finding.';
+comment on column metadata_findings.version_id is 'The version whose metadata violates the rule.';
+comment on column metadata_findings.rule is 'The validation rule that is violated. See the `MetadataRule` enum for the possible values.';
+comment on column metadata_findings.message is 'Human-readable description of the violation.';
+comment on column metadata_findings.created_at is 'Date and time when the finding was recorded.';
+
+create index metadata_findings_version_id_idx on metadata_findings (version_id);
@@ -0,0 +1,23 @@
+create table user_api_usage
+(
+    user_id      integer   not null primary key references users (id) on delete cascade,
+    window_start timestamp not null,
+    requests     integer   not null default 0
+);
+
+comment on table user_api_usage is 'Number of quota-limited API requests that a user has made in the current quota window.';
+comment on column user_api_usage.user_id is 'Reference to the user in the `users` table.';
+comment on column user_api_usage.window_start is 'Start of the quota window that the `requests` counter applies to.';
+comment on column user_api_usage.requests is 'Number of requests within the quota window.';
+
+create table api_token_usage
+(
+    api_token_id integer   not null primary key references api_tokens (id) on delete cascade,
+    window_start timestamp not null,
+    requests     integer   not null default 0
+);
+
+comment on table api_token_usage is 'Number of quota-limited API requests that have been made with an API token in the current quota window.';
+comment on column api_token_usage.api_token_id is 'Reference to the API token in the `api_tokens` table.';
+comment on column api_token_usage.window_start is 'Start of the quota window that the `requests` counter applies to.';
+comment on column api_token_usage.requests is 'Number of requests within the quota window.';
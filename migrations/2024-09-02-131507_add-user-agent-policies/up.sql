@@ -0,0 +1,13 @@
+create table user_agent_policies
+(
+    id             serial primary key,
+    pattern        varchar   not null unique,
+    throttle_class integer   not null,
+    created_at     timestamp not null default now()
+);
+
+comment on table user_agent_policies is 'Throttling policies for anonymous requests, based on their `User-Agent` header.';
+comment on column user_agent_policies.id is 'Unique identifier of the policy.';
+comment on column user_agent_policies.pattern is 'Case-insensitive substring that is matched against the `User-Agent` header.';
+comment on column user_agent_policies.throttle_class is 'How matching requests are treated. See the `ThrottleClass` enum for the possible values.';
+comment on column user_agent_policies.created_at is 'Date and time when the policy was created or last changed.';
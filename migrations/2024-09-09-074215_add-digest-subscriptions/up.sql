@@ -0,0 +1,13 @@
+create table digest_subscriptions
+(
+    user_id      integer   not null primary key references users (id) on delete cascade,
+    timezone     varchar   not null default 'UTC',
+    last_sent_at timestamp,
+    created_at   timestamp not null default now()
+);
+
+comment on table digest_subscriptions is 'Users that opted in to the weekly maintainer digest email.';
+comment on column digest_subscriptions.user_id is 'Reference to the user in the `users` table.';
+comment on column digest_subscriptions.timezone is 'IANA timezone name of the user, used to send the digest on Monday morning local time.';
+comment on column digest_subscriptions.last_sent_at is 'Date and time when the last digest was sent to the user.';
+comment on column digest_subscriptions.created_at is 'Date and time when the user opted in to the digest.';
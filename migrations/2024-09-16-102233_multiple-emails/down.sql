@@ -0,0 +1,6 @@
+drop table email_notification_preferences;
+
+delete from emails where not is_primary;
+
+alter table emails drop column is_primary;
+alter table emails add constraint emails_user_id_key unique (user_id);
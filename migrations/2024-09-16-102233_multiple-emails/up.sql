@@ -0,0 +1,22 @@
+alter table emails drop constraint emails_user_id_key;
+
+-- Every existing address is the primary address of its user. The column is
+-- added with a `true` default, which does not rewrite or update the table,
+-- and new addresses are not primary by default afterwards.
+alter table emails add column is_primary boolean not null default true;
+alter table emails alter column is_primary set default false;
+
+comment on column emails.is_primary is 'Whether this is the primary address of the user. Every user has at most one primary address.';
+
+create table email_notification_preferences
+(
+    user_id            integer not null references users (id) on delete cascade,
+    notification_class integer not null,
+    email_id           integer not null references emails (id) on delete cascade,
+    primary key (user_id, notification_class)
+);
+
+comment on table email_notification_preferences is 'Selects which of the email addresses of a user receives which class of notifications.';
+comment on column email_notification_preferences.user_id is 'Reference to the user in the `users` table.';
+comment on column email_notification_preferences.notification_class is 'Class of notifications: 0=publishing, 1=ownership, 2=digest.';
+comment on column email_notification_preferences.email_id is 'Reference to the address in the `emails` table that receives the notifications.';
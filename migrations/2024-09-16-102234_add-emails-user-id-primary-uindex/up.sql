@@ -0,0 +1,2 @@
+create unique index concurrently if not exists emails_user_id_primary_uindex
+    on emails (user_id) where is_primary;
@@ -0,0 +1 @@
+drop index concurrently if exists emails_user_id_email_uindex;
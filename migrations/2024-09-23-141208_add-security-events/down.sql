@@ -0,0 +1 @@
+drop table security_events;
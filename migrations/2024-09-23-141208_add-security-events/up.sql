@@ -0,0 +1,23 @@
+create table security_events
+(
+    id           serial primary key,
+    user_id      integer   not null references users (id) on delete cascade,
+    kind         integer   not null,
+    ip_address   varchar,
+    user_agent   varchar,
+    api_token_id integer references api_tokens (id) on delete set null,
+    lock_token   text      not null default random_string(26) unique,
+    created_at   timestamp not null default now()
+);
+
+create index security_events_user_id_kind_index on security_events (user_id, kind);
+
+comment on table security_events is 'Security relevant events of user accounts, like sign-ins and the creation of API tokens.';
+comment on column security_events.id is 'Unique identifier of the security event.';
+comment on column security_events.user_id is 'Reference to the user in the `users` table.';
+comment on column security_events.kind is 'Kind of the event: 0=login, 1=api_token_created, 2=account_locked.';
+comment on column security_events.ip_address is 'IP address of the client that caused the event.';
+comment on column security_events.user_agent is 'User agent of the client that caused the event.';
+comment on column security_events.api_token_id is 'Reference to the API token in the `api_tokens` table, if the event is related to an API token.';
+comment on column security_events.lock_token is 'Secret token that is sent to the user in security alerts, and that can be used to lock the account if the event was not caused by the user.';
+comment on column security_events.created_at is 'Date and time when the event happened.';
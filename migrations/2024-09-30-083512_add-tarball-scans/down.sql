@@ -0,0 +1,2 @@
+drop table tarball_scans;
+drop table version_quarantines;
@@ -0,0 +1,33 @@
+create table version_quarantines
+(
+    version_id integer   not null primary key references versions (id) on delete cascade,
+    reason     varchar   not null,
+    was_yanked boolean   not null,
+    created_at timestamp not null default now()
+);
+
+comment on table version_quarantines is 'Versions that are quarantined because they are suspected to be malicious. Quarantined versions are yanked, their tarballs are moved out of the public storage area, and they can not be unyanked by their owners.';
+comment on column version_quarantines.version_id is 'Reference to the version in the `versions` table.';
+comment on column version_quarantines.reason is 'Reason why the version was quarantined.';
+comment on column version_quarantines.was_yanked is 'Whether the version was already yanked before it was quarantined. Used to restore the previous state if the quarantine is lifted.';
+comment on column version_quarantines.created_at is 'Date and time when the version was quarantined.';
+
+create table tarball_scans
+(
+    version_id  integer   not null primary key references versions (id) on delete cascade,
+    verdict     integer   not null,
+    signature   varchar,
+    scanned_at  timestamp not null default now(),
+    reviewed_by integer references users (id) on delete set null,
+    reviewed_at timestamp
+);
+
+create index tarball_scans_pending_review_index on tarball_scans (scanned_at) where verdict = 1 and reviewed_at is null;
+
+comment on table tarball_scans is 'Results of the antivirus scans of uploaded crate tarballs.';
+comment on column tarball_scans.version_id is 'Reference to the version in the `versions` table.';
+comment on column tarball_scans.verdict is 'Verdict of the scan: 0=clean, 1=infected.';
+comment on column tarball_scans.signature is 'Name of the signature that matched, if the tarball is infected.';
+comment on column tarball_scans.scanned_at is 'Date and time when the tarball was scanned.';
+comment on column tarball_scans.reviewed_by is 'Reference to the admin in the `users` table that reviewed the verdict.';
+comment on column tarball_scans.reviewed_at is 'Date and time when the verdict was reviewed by an admin.';
@@ -0,0 +1,3 @@
+alter table versions
+    drop column has_build_script,
+    drop column is_proc_macro;
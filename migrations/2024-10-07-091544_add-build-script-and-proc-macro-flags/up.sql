@@ -0,0 +1,6 @@
+alter table versions
+    add has_build_script boolean,
+    add is_proc_macro boolean;
+
+comment on column versions.has_build_script is 'TRUE if the version has a build script (e.g. `build.rs`), FALSE if no build script was detected, or NULL if the version has not been analyzed yet.';
+comment on column versions.is_proc_macro is 'TRUE if the library of the version is a procedural macro (`proc-macro = true`), FALSE if it is not, or NULL if the version has not been analyzed yet.';
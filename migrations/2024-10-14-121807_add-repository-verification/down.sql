@@ -0,0 +1,2 @@
+alter table crates
+    drop column repository_verified_at;
@@ -0,0 +1,4 @@
+alter table crates
+    add repository_verified_at timestamp;
+
+comment on column crates.repository_verified_at is 'Time at which an owner proved control of the `repository` of the crate, or NULL if the repository has not been verified. Reset whenever the repository changes.';
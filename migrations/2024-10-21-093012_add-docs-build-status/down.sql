@@ -0,0 +1,2 @@
+alter table versions
+    drop column docs_build_status;
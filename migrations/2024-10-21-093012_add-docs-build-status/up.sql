@@ -0,0 +1,4 @@
+alter table versions
+    add docs_build_status integer;
+
+comment on column versions.docs_build_status is 'Status of the documentation build on docs.rs, as reported by its webhook. 0 = in progress, 1 = success, 2 = failure, NULL = unknown.';
@@ -0,0 +1 @@
+drop table database_dumps;
@@ -0,0 +1,21 @@
+create table database_dumps
+(
+    id          serial primary key,
+    created_at  timestamp not null default now(),
+    directory   varchar   not null,
+    tar_size    bigint    not null,
+    tar_sha256  varchar   not null,
+    zip_size    bigint    not null,
+    zip_sha256  varchar   not null
+);
+
+comment on table database_dumps is 'Public database dumps that were uploaded to the storage bucket.';
+comment on column database_dumps.id is 'Unique identifier of the database dump.';
+comment on column database_dumps.created_at is 'Date and time when the database dump was started.';
+comment on column database_dumps.directory is 'Path of the directory in the storage bucket that contains the archives and the manifest of the dump.';
+comment on column database_dumps.tar_size is 'Size of the `db-dump.tar.gz` archive in bytes.';
+comment on column database_dumps.tar_sha256 is 'Hex-encoded SHA-256 checksum of the `db-dump.tar.gz` archive.';
+comment on column database_dumps.zip_size is 'Size of the `db-dump.zip` archive in bytes.';
+comment on column database_dumps.zip_sha256 is 'Hex-encoded SHA-256 checksum of the `db-dump.zip` archive.';
+
+create index database_dumps_created_at_index on database_dumps (created_at);
@@ -0,0 +1,4 @@
+drop table database_dump_deltas;
+
+alter table database_dumps
+    drop column last_event_id;
@@ -0,0 +1,28 @@
+alter table database_dumps
+    add last_event_id bigint not null default 0;
+
+comment on column database_dumps.last_event_id is 'ID of the last registry event at the time the dump was started. The deltas of this dump start after this event.';
+
+create table database_dump_deltas
+(
+    id            serial primary key,
+    dump_id       integer   not null references database_dumps on delete cascade,
+    created_at    timestamp not null default now(),
+    from_event_id bigint    not null,
+    to_event_id   bigint    not null,
+    path          varchar   not null,
+    size          bigint    not null,
+    sha256        varchar   not null
+);
+
+comment on table database_dump_deltas is 'Daily changes since a public database dump, derived from the registry events.';
+comment on column database_dump_deltas.id is 'Unique identifier of the delta.';
+comment on column database_dump_deltas.dump_id is 'Reference to the full database dump in the `database_dumps` table that the delta is based on.';
+comment on column database_dump_deltas.created_at is 'Date and time when the delta was created.';
+comment on column database_dump_deltas.from_event_id is 'ID of the registry event after which the delta starts (exclusive).';
+comment on column database_dump_deltas.to_event_id is 'ID of the last registry event included in the delta (inclusive).';
+comment on column database_dump_deltas.path is 'Path of the delta file in the storage bucket.';
+comment on column database_dump_deltas.size is 'Size of the delta file in bytes.';
+comment on column database_dump_deltas.sha256 is 'Hex-encoded SHA-256 checksum of the delta file.';
+
+create index database_dump_deltas_dump_id_index on database_dump_deltas (dump_id);
@@ -0,0 +1,4 @@
+alter table api_tokens
+    add service_contact varchar;
+
+comment on column api_tokens.service_contact is 'Contact information of the operator of a service token (e.g. an email address or a URL). Service tokens can not be used to perform any actions, but have elevated API quotas for read requests. NULL for regular API tokens.';
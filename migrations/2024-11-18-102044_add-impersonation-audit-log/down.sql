@@ -0,0 +1 @@
+drop table impersonation_audit_log;
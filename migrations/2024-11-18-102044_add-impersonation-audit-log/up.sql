@@ -0,0 +1,20 @@
+create table impersonation_audit_log
+(
+    id              serial primary key,
+    impersonator_id integer   not null references users (id) on delete cascade,
+    user_id         integer   not null references users (id) on delete cascade,
+    method          varchar   not null,
+    path            varchar   not null,
+    created_at      timestamp not null default now()
+);
+
+comment on table impersonation_audit_log is 'Requests that were made by admins while impersonating another user.';
+comment on column impersonation_audit_log.id is 'Unique identifier of the log entry.';
+comment on column impersonation_audit_log.impersonator_id is 'Reference to the admin in the `users` table that impersonated the user.';
+comment on column impersonation_audit_log.user_id is 'Reference to the impersonated user in the `users` table.';
+comment on column impersonation_audit_log.method is 'HTTP method of the request.';
+comment on column impersonation_audit_log.path is 'Path of the request, without the query string.';
+comment on column impersonation_audit_log.created_at is 'Date and time when the request was made.';
+
+create index impersonation_audit_log_impersonator_id_index on impersonation_audit_log (impersonator_id);
+create index impersonation_audit_log_user_id_index on impersonation_audit_log (user_id);
@@ -0,0 +1,11 @@
+alter table crates
+    add column prerelease_retention_days integer;
+
+comment on column crates.prerelease_retention_days is 'If set, pre-release versions older than this number of days are automatically yanked once a newer stable version has been published.';
+
+update crates
+set prerelease_retention_days = crate_settings.prerelease_retention_days
+from crate_settings
+where crates.id = crate_settings.crate_id;
+
+drop table crate_settings;
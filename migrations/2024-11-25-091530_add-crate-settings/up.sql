@@ -0,0 +1,19 @@
+create table crate_settings
+(
+    crate_id                  integer   not null primary key references crates (id) on delete cascade,
+    prerelease_retention_days integer,
+    updated_at                timestamp not null default now()
+);
+
+comment on table crate_settings is 'Settings of a crate that can be changed by its owners. Crates without a row use the default settings.';
+comment on column crate_settings.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_settings.prerelease_retention_days is 'If set, pre-release versions older than this number of days are automatically yanked once a newer stable version has been published.';
+comment on column crate_settings.updated_at is 'Date and time when the settings were last changed.';
+
+insert into crate_settings (crate_id, prerelease_retention_days)
+select id, prerelease_retention_days
+from crates
+where prerelease_retention_days is not null;
+
+alter table crates
+    drop column prerelease_retention_days;
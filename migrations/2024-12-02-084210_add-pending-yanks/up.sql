@@ -0,0 +1,17 @@
+create table pending_yanks
+(
+    id           serial primary key,
+    version_id   integer   not null references versions (id) on delete cascade,
+    user_id      integer   not null references users (id) on delete cascade,
+    api_token_id integer references api_tokens (id) on delete set null,
+    token        text      not null default random_string(26) unique,
+    created_at   timestamp not null default now()
+);
+
+comment on table pending_yanks is 'Yanks of heavily downloaded versions that are held back until one of the crate owners confirms them via email.';
+comment on column pending_yanks.id is 'Unique identifier of the pending yank.';
+comment on column pending_yanks.version_id is 'The version that is being yanked.';
+comment on column pending_yanks.user_id is 'The user that requested the yank.';
+comment on column pending_yanks.api_token_id is 'The API token that was used to request the yank.';
+comment on column pending_yanks.token is 'Secret token that is sent to the owners of the crate to confirm the yank.';
+comment on column pending_yanks.created_at is 'Date and time when the yank was requested. Pending yanks expire after a day.';
@@ -0,0 +1,10 @@
+alter table pending_yanks
+    drop column reason;
+
+delete from version_owner_actions where user_id is null;
+
+alter table version_owner_actions
+    alter column user_id set not null;
+
+alter table version_owner_actions
+    drop column reason;
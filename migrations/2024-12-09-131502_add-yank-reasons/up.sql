@@ -0,0 +1,14 @@
+alter table version_owner_actions
+    add column reason varchar;
+
+comment on column version_owner_actions.reason is 'Optional reason that the user provided for the action, e.g. why a version was yanked.';
+
+-- Versions that are yanked by crates.io itself (e.g. by background jobs or
+-- admin commands) are recorded without a user.
+alter table version_owner_actions
+    alter column user_id drop not null;
+
+alter table pending_yanks
+    add column reason varchar;
+
+comment on column pending_yanks.reason is 'Optional reason that the user provided for the yank.';
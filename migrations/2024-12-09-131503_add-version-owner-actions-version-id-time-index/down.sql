@@ -0,0 +1 @@
+drop index concurrently if exists version_owner_actions_version_id_time_index;
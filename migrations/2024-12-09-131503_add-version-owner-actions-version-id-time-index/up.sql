@@ -0,0 +1,2 @@
+create index concurrently if not exists version_owner_actions_version_id_time_index
+    on version_owner_actions (version_id, time);
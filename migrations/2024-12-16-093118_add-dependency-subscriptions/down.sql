@@ -0,0 +1,5 @@
+delete from email_notification_preferences where notification_class = 3;
+
+comment on column email_notification_preferences.notification_class is 'Class of notifications: 0=publishing, 1=ownership, 2=digest.';
+
+drop table dependency_subscriptions;
@@ -0,0 +1,21 @@
+create table dependency_subscriptions
+(
+    id          serial primary key,
+    user_id     integer   not null references users (id) on delete cascade,
+    crate_id    integer   not null references crates (id) on delete cascade,
+    version_req varchar   not null,
+    created_at  timestamp not null default now(),
+    unique (user_id, crate_id, version_req)
+);
+
+comment on table dependency_subscriptions is 'Subscriptions of users that want to be notified when a crate publishes a version matching a version requirement.';
+comment on column dependency_subscriptions.id is 'Unique identifier of the subscription.';
+comment on column dependency_subscriptions.user_id is 'The user that is notified about new versions.';
+comment on column dependency_subscriptions.crate_id is 'The crate that the user subscribed to.';
+comment on column dependency_subscriptions.version_req is 'Semver version requirement that new versions have to match, e.g. `^2`.';
+comment on column dependency_subscriptions.created_at is 'Date and time when the subscription was created.';
+
+create index dependency_subscriptions_crate_id_index
+    on dependency_subscriptions (crate_id);
+
+comment on column email_notification_preferences.notification_class is 'Class of notifications: 0=publishing, 1=ownership, 2=digest, 3=subscriptions.';
@@ -0,0 +1,28 @@
+create table crate_health
+(
+    crate_id             integer   not null primary key references crates (id) on delete cascade,
+    score                smallint  not null,
+    last_release_at      timestamp not null,
+    maintainers          integer   not null,
+    active_maintainers   integer   not null,
+    quarantined_versions integer   not null,
+    docs_build_status    integer,
+    versions             integer   not null,
+    yanked_versions      integer   not null,
+    computed_at          timestamp not null default now()
+);
+
+comment on table crate_health is 'Health scores of crates and the metrics that they are computed from. Updated periodically by the `update_crate_health` background job.';
+comment on column crate_health.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_health.score is 'Overall health score of the crate, between 0 and 100.';
+comment on column crate_health.last_release_at is 'Date and time when the most recent version of the crate was published.';
+comment on column crate_health.maintainers is 'Number of users that own the crate.';
+comment on column crate_health.active_maintainers is 'Number of owners that published a version of the crate within the last year.';
+comment on column crate_health.quarantined_versions is 'Number of versions of the crate that are quarantined because they are suspected to be malicious.';
+comment on column crate_health.docs_build_status is 'Status of the docs.rs build of the default version: 0=in progress, 1=success, 2=failure, NULL=unknown.';
+comment on column crate_health.versions is 'Total number of versions of the crate.';
+comment on column crate_health.yanked_versions is 'Number of yanked versions of the crate.';
+comment on column crate_health.computed_at is 'Date and time when the health score was computed.';
+
+create index crate_health_score_index
+    on crate_health (score);
@@ -0,0 +1,4 @@
+alter table crate_settings
+    drop column security_contact,
+    drop column disclosure_policy,
+    drop column msrv_policy;
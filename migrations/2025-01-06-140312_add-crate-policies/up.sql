@@ -0,0 +1,8 @@
+alter table crate_settings
+    add column security_contact  varchar,
+    add column disclosure_policy text,
+    add column msrv_policy       text;
+
+comment on column crate_settings.security_contact is 'Email address or URL that security issues of the crate should be reported to.';
+comment on column crate_settings.disclosure_policy is 'Description of how security issues of the crate are handled and disclosed.';
+comment on column crate_settings.msrv_policy is 'Description of the minimum supported Rust version policy of the crate.';
@@ -0,0 +1,2 @@
+alter table api_tokens
+    drop column team_id;
@@ -0,0 +1,6 @@
+alter table api_tokens
+    add team_id integer references teams (id) on delete cascade;
+
+comment on column api_tokens.team_id is 'Reference to the team in the `teams` table, if this is a team token. Team tokens can only be used for crates owned by the team, and `user_id` refers to the team maintainer that created the token, who is recorded as the acting user of all actions performed with it. NULL for regular API tokens.';
+
+create index api_tokens_team_id_index on api_tokens (team_id) where team_id is not null;
@@ -0,0 +1,5 @@
+alter table pending_publishes
+    drop column ci_system,
+    drop column ci_run_url;
+
+drop table version_ci_annotations;
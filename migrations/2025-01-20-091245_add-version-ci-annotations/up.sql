@@ -0,0 +1,20 @@
+create table version_ci_annotations
+(
+    version_id integer   not null primary key references versions (id) on delete cascade,
+    ci_system  varchar   not null,
+    run_url    varchar,
+    created_at timestamp not null default now()
+);
+
+comment on table version_ci_annotations is 'Information about the CI run that published a version, as reported by the publisher via the `X-Cargo-CI-System` and `X-Cargo-CI-Run-URL` headers.';
+comment on column version_ci_annotations.version_id is 'Reference to the version in the `versions` table.';
+comment on column version_ci_annotations.ci_system is 'Identifier of the CI system that published the version (e.g. `github-actions`).';
+comment on column version_ci_annotations.run_url is 'URL of the CI run that published the version.';
+comment on column version_ci_annotations.created_at is 'Date and time when the version was published.';
+
+alter table pending_publishes
+    add ci_system varchar,
+    add ci_run_url varchar;
+
+comment on column pending_publishes.ci_system is 'Identifier of the CI system that requested the publish, if it was reported via the `X-Cargo-CI-System` header.';
+comment on column pending_publishes.ci_run_url is 'URL of the CI run that requested the publish, if it was reported via the `X-Cargo-CI-Run-URL` header.';
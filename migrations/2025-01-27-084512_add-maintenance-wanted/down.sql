@@ -0,0 +1,2 @@
+alter table crates
+    drop column maintenance_wanted_at;
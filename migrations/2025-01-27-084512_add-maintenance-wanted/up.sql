@@ -0,0 +1,4 @@
+alter table crates
+    add maintenance_wanted_at timestamp;
+
+comment on column crates.maintenance_wanted_at is 'Time at which the owners of the crate flagged it as looking for new maintainers, or NULL if the crate is not looking for maintainers.';
@@ -0,0 +1 @@
+drop index concurrently if exists crates_maintenance_wanted_at_index;
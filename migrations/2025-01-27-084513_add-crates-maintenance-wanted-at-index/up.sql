@@ -0,0 +1,3 @@
+create index concurrently if not exists crates_maintenance_wanted_at_index
+    on crates (maintenance_wanted_at)
+    where maintenance_wanted_at is not null;
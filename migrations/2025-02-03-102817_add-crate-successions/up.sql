@@ -0,0 +1,21 @@
+create table crate_successions
+(
+    predecessor_id           integer   not null primary key references crates (id) on delete cascade,
+    successor_id             integer   not null references crates (id) on delete cascade,
+    predecessor_confirmed_at timestamp,
+    successor_confirmed_at   timestamp,
+    created_by               integer   not null references users (id),
+    created_at               timestamp not null default now(),
+    constraint crate_successions_not_self check (predecessor_id <> successor_id)
+);
+
+comment on table crate_successions is 'Relations between crates that have been superseded and the crates that supersede them. A relation is only in effect once the owners of both crates confirmed it.';
+comment on column crate_successions.predecessor_id is 'Reference to the superseded crate in the `crates` table. Every crate can only be superseded by one other crate.';
+comment on column crate_successions.successor_id is 'Reference to the superseding crate in the `crates` table.';
+comment on column crate_successions.predecessor_confirmed_at is 'Date and time when an owner of the superseded crate confirmed the relation, or NULL if it is still awaiting their confirmation.';
+comment on column crate_successions.successor_confirmed_at is 'Date and time when an owner of the superseding crate confirmed the relation, or NULL if it is still awaiting their confirmation.';
+comment on column crate_successions.created_by is 'Reference to the user that declared the relation.';
+comment on column crate_successions.created_at is 'Date and time when the relation was declared.';
+
+create index crate_successions_successor_id_index
+    on crate_successions (successor_id);
@@ -0,0 +1,15 @@
+create table rate_limit_rejections
+(
+    id         bigserial primary key,
+    user_id    integer   not null references users (id) on delete cascade,
+    action     integer   not null,
+    created_at timestamp not null default now()
+);
+
+create index rate_limit_rejections_created_at_index on rate_limit_rejections (created_at);
+
+comment on table rate_limit_rejections is 'Requests that were rejected by the rate limiter. Used by the admin report of the most throttled users. Old rows are removed by the daily database maintenance job.';
+comment on column rate_limit_rejections.id is 'Unique identifier of the rejection.';
+comment on column rate_limit_rejections.user_id is 'Reference to the user in the `users` table whose request was rejected.';
+comment on column rate_limit_rejections.action is 'The rate limited action that was rejected: 0=publish_new, 1=publish_update, 2=yank_unyank, 3=bulk_metadata.';
+comment on column rate_limit_rejections.created_at is 'Date and time when the request was rejected.';
@@ -0,0 +1,18 @@
+create table publish_idempotency_keys
+(
+    user_id    integer   not null references users (id) on delete cascade,
+    key        varchar   not null,
+    crate_name varchar   not null,
+    version    varchar   not null,
+    response   jsonb     not null,
+    created_at timestamp not null default now(),
+    primary key (user_id, key)
+);
+
+comment on table publish_idempotency_keys is 'Outcomes of successful publishes that were sent with an `Idempotency-Key` header. Retries of a publish with the same key replay the stored response instead of failing.';
+comment on column publish_idempotency_keys.user_id is 'Reference to the user in the `users` table that sent the publish.';
+comment on column publish_idempotency_keys.key is 'Value of the `Idempotency-Key` header, unique per user.';
+comment on column publish_idempotency_keys.crate_name is 'Name of the crate that was published with the key.';
+comment on column publish_idempotency_keys.version is 'Version of the crate that was published with the key.';
+comment on column publish_idempotency_keys.response is 'JSON body of the original response, which is replayed for retries.';
+comment on column publish_idempotency_keys.created_at is 'Date and time when the publish was completed. Keys expire after a day.';
@@ -0,0 +1,14 @@
+create table crate_region_downloads
+(
+    crate_id  integer not null references crates (id) on delete cascade,
+    date      date    not null,
+    country   varchar not null,
+    downloads integer not null default 0,
+    primary key (crate_id, date, country)
+);
+
+comment on table crate_region_downloads is 'Daily download counts of crates per country, as determined by the CDN. Only the last 90 days are kept, and the counts are only exposed in aggregated form.';
+comment on column crate_region_downloads.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_region_downloads.date is 'Date of the downloads.';
+comment on column crate_region_downloads.country is 'ISO 3166-1 alpha-2 code of the country that the downloads originated from.';
+comment on column crate_region_downloads.downloads is 'Number of downloads of the crate from the country on the date.';
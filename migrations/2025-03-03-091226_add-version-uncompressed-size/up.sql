@@ -0,0 +1,4 @@
+alter table versions
+    add uncompressed_size bigint;
+
+comment on column versions.uncompressed_size is 'Total size of all files in the crate file after decompression, in bytes. NULL if the version was published before the size was recorded.';
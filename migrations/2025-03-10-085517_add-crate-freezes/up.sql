@@ -0,0 +1,13 @@
+create table crate_freezes
+(
+    crate_id   integer   not null primary key references crates (id) on delete cascade,
+    reason     text      not null,
+    frozen_by  integer   not null references users (id),
+    created_at timestamp not null default now()
+);
+
+comment on table crate_freezes is 'Crates that have been frozen by an admin pending an investigation. No new versions can be published for frozen crates and their owners can not be changed, but their existing versions can still be downloaded.';
+comment on column crate_freezes.crate_id is 'Reference to the frozen crate in the `crates` table.';
+comment on column crate_freezes.reason is 'The reason why the crate was frozen, which is shown to the owners of the crate when they try to change it.';
+comment on column crate_freezes.frozen_by is 'Reference to the admin that froze the crate.';
+comment on column crate_freezes.created_at is 'Date and time when the crate was frozen.';
@@ -0,0 +1,30 @@
+create table account_recoveries
+(
+    id                 serial primary key,
+    user_id            integer   not null references users (id) on delete cascade,
+    requested_by       integer   not null references users (id) on delete cascade,
+    email              varchar   not null,
+    email_token        text      not null default random_string(26) unique,
+    email_confirmed_at timestamp,
+    approved_by        integer references users (id) on delete set null,
+    approved_at        timestamp,
+    completed_at       timestamp,
+    cancelled_at       timestamp,
+    created_at         timestamp not null default now()
+);
+
+create unique index account_recoveries_open_user_id_index on account_recoveries (user_id)
+    where completed_at is null and cancelled_at is null;
+
+comment on table account_recoveries is 'Requests to re-link accounts to a new GitHub identity after their owners lost access to the previous one. Recoveries have to be confirmed via the verified email address of the account and approved by an admin, and can only be completed after a waiting period.';
+comment on column account_recoveries.id is 'Unique identifier of the account recovery.';
+comment on column account_recoveries.user_id is 'Reference to the account that is recovered in the `users` table.';
+comment on column account_recoveries.requested_by is 'Reference to the account of the new GitHub identity that requested the recovery, which is merged into the recovered account on completion.';
+comment on column account_recoveries.email is 'The verified email address of the recovered account that the confirmation link was sent to.';
+comment on column account_recoveries.email_token is 'Secret token that is sent to the verified email address of the recovered account to confirm the recovery.';
+comment on column account_recoveries.email_confirmed_at is 'Date and time when the recovery was confirmed via the email address of the recovered account.';
+comment on column account_recoveries.approved_by is 'Reference to the admin that approved the recovery.';
+comment on column account_recoveries.approved_at is 'Date and time when the recovery was approved by an admin.';
+comment on column account_recoveries.completed_at is 'Date and time when the account was re-linked to the new GitHub identity.';
+comment on column account_recoveries.cancelled_at is 'Date and time when the recovery was cancelled.';
+comment on column account_recoveries.created_at is 'Date and time when the recovery was requested.';
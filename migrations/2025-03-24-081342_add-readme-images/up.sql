@@ -0,0 +1,19 @@
+create table readme_images
+(
+    digest       text primary key,
+    url          text      not null,
+    content_type varchar,
+    size         integer,
+    fetched_at   timestamp,
+    failed_at    timestamp,
+    created_at   timestamp not null default now()
+);
+
+comment on table readme_images is 'Images that are referenced by rendered READMEs and served from the storage bucket of crates.io instead of being loaded from third-party hosts.';
+comment on column readme_images.digest is 'SHA256 digest of the original URL of the image, which is used in the URLs of the proxy endpoint and the storage bucket.';
+comment on column readme_images.url is 'The original URL of the image.';
+comment on column readme_images.content_type is 'The content type of the cached image, or NULL if it has not been fetched successfully yet.';
+comment on column readme_images.size is 'The size of the cached image in bytes, or NULL if it has not been fetched successfully yet.';
+comment on column readme_images.fetched_at is 'Date and time when the image was last fetched and uploaded to the storage bucket successfully.';
+comment on column readme_images.failed_at is 'Date and time when fetching the image last failed, e.g. because it was too large or not an image.';
+comment on column readme_images.created_at is 'Date and time when the image was first referenced by a README.';
@@ -0,0 +1 @@
+alter table readme_renderings drop column policy_version;
@@ -0,0 +1,4 @@
+alter table readme_renderings
+    add column policy_version integer not null default 1;
+
+comment on column readme_renderings.policy_version is 'Version of the HTML sanitizer policy that the README was rendered with. READMEs that were rendered with an older policy are rendered again by the `rerender_readmes` background job.';
@@ -0,0 +1 @@
+drop table spam_flags;
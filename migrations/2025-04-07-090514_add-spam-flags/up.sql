@@ -0,0 +1,19 @@
+create table spam_flags
+(
+    version_id  integer   not null primary key references versions (id) on delete cascade,
+    score       integer   not null,
+    reasons     text[]    not null,
+    created_at  timestamp not null default now(),
+    reviewed_by integer references users (id) on delete set null,
+    reviewed_at timestamp
+);
+
+create index spam_flags_pending_review_index on spam_flags (created_at) where reviewed_at is null;
+
+comment on table spam_flags is 'Moderation queue of published versions whose descriptions or keywords were classified as potential spam.';
+comment on column spam_flags.version_id is 'Reference to the version in the `versions` table.';
+comment on column spam_flags.score is 'Spam score of the metadata of the version at the time of the publish.';
+comment on column spam_flags.reasons is 'Human-readable explanations of the spam score.';
+comment on column spam_flags.created_at is 'Date and time when the version was flagged.';
+comment on column spam_flags.reviewed_by is 'Reference to the admin in the `users` table that reviewed the flag.';
+comment on column spam_flags.reviewed_at is 'Date and time when the flag was reviewed by an admin.';
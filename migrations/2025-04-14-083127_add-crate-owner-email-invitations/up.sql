@@ -0,0 +1,16 @@
+create table crate_owner_email_invitations
+(
+    crate_id           integer   not null references crates (id) on delete cascade,
+    email              varchar   not null,
+    invited_by_user_id integer   not null references users (id) on delete cascade,
+    created_at         timestamp not null default now(),
+    primary key (crate_id, email)
+);
+
+create index crate_owner_email_invitations_email_index on crate_owner_email_invitations (email);
+
+comment on table crate_owner_email_invitations is 'Pending crate ownership invitations for email addresses that do not belong to a crates.io account yet. They are turned into regular invitations once a user with a matching verified email address logs in.';
+comment on column crate_owner_email_invitations.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_owner_email_invitations.email is 'Lowercased email address of the invitee.';
+comment on column crate_owner_email_invitations.invited_by_user_id is 'Reference to the owner in the `users` table that sent the invitation.';
+comment on column crate_owner_email_invitations.created_at is 'Date and time when the invitation was created.';
@@ -0,0 +1,2 @@
+alter table crate_settings
+    drop column denied_dependencies;
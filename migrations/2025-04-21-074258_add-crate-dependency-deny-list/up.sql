@@ -0,0 +1,4 @@
+alter table crate_settings
+    add column denied_dependencies jsonb not null default '[]';
+
+comment on column crate_settings.denied_dependencies is 'Dependencies that new versions of the crate must not use, as a JSON array of objects with a `name` and an optional `version_req`.';
@@ -0,0 +1 @@
+drop table dependency_policy_exceptions;
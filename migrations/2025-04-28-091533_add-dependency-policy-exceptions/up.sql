@@ -0,0 +1,13 @@
+create table dependency_policy_exceptions
+(
+    crate_id   integer   not null primary key references crates (id) on delete cascade,
+    reason     text      not null,
+    created_by integer   not null references users (id),
+    created_at timestamp not null default now()
+);
+
+comment on table dependency_policy_exceptions is 'Crates that new versions of other crates may depend on, even though some of their versions are quarantined. Managed by the admins.';
+comment on column dependency_policy_exceptions.crate_id is 'Reference to the exempted dependency in the `crates` table.';
+comment on column dependency_policy_exceptions.reason is 'The reason why dependencies on the crate are allowed.';
+comment on column dependency_policy_exceptions.created_by is 'Reference to the admin that added the exception.';
+comment on column dependency_policy_exceptions.created_at is 'Date and time when the exception was added.';
@@ -0,0 +1 @@
+drop table persistent_sessions;
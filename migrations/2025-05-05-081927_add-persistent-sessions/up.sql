@@ -0,0 +1,19 @@
+create table persistent_sessions
+(
+    id           bigserial primary key,
+    user_id      integer   not null references users (id) on delete cascade,
+    hashed_token bytea     not null unique,
+    created_at   timestamp not null default now(),
+    last_used_at timestamp not null default now(),
+    revoked      boolean   not null default false
+);
+
+create index persistent_sessions_user_id_index on persistent_sessions (user_id);
+
+comment on table persistent_sessions is 'Server-side state of the cookie sessions of the users, which allows enforcing session lifetimes and revoking sessions.';
+comment on column persistent_sessions.id is 'Unique identifier of the session.';
+comment on column persistent_sessions.user_id is 'Reference to the user in the `users` table that signed in. For impersonation sessions this is the admin.';
+comment on column persistent_sessions.hashed_token is 'SHA256 hash of the token that is stored in the session cookie.';
+comment on column persistent_sessions.created_at is 'Date and time when the user signed in.';
+comment on column persistent_sessions.last_used_at is 'Date and time when the session was last used to authenticate a request.';
+comment on column persistent_sessions.revoked is 'Whether the session was revoked, e.g. because the user signed out or the session identifier was rotated.';
@@ -0,0 +1,4 @@
+alter table versions
+    add column channel varchar;
+
+comment on column versions.channel is 'Name of the release channel (e.g. `beta` or `nightly`) that the version was published to, or NULL if it was not published to a channel.';
@@ -0,0 +1 @@
+drop index concurrently if exists versions_crate_id_channel_index;
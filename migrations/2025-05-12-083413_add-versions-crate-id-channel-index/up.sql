@@ -0,0 +1,3 @@
+create index concurrently if not exists versions_crate_id_channel_index
+    on versions (crate_id, channel)
+    where channel is not null;
@@ -0,0 +1,4 @@
+drop table reproducibility_reports;
+
+alter table versions
+    drop column normalized_checksum;
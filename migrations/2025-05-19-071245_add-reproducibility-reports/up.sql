@@ -0,0 +1,23 @@
+alter table versions
+    add column normalized_checksum char(64);
+
+comment on column versions.normalized_checksum is 'SHA256 checksum of the normalized crate tarball (fixed mtimes, sorted entries), as reported by the publisher, or NULL if it was not reported.';
+
+create table reproducibility_reports
+(
+    version_id  integer   not null references versions (id) on delete cascade,
+    reporter_id integer   not null references users (id) on delete cascade,
+    checksum    char(64)  not null,
+    matches     boolean   not null,
+    details     varchar,
+    created_at  timestamp not null default now(),
+    primary key (version_id, reporter_id)
+);
+
+comment on table reproducibility_reports is 'Reports of independent rebuilders that attest whether a version could be reproduced from source.';
+comment on column reproducibility_reports.version_id is 'Reference to the version in the `versions` table.';
+comment on column reproducibility_reports.reporter_id is 'Reference to the user in the `users` table that submitted the report.';
+comment on column reproducibility_reports.checksum is 'SHA256 checksum of the normalized tarball that the rebuilder produced.';
+comment on column reproducibility_reports.matches is 'TRUE if the checksum matches the `normalized_checksum` of the version at the time of the report.';
+comment on column reproducibility_reports.details is 'Optional free-form details about the rebuild, e.g. a link to the build log.';
+comment on column reproducibility_reports.created_at is 'Date and time when the report was last submitted.';
@@ -0,0 +1,2 @@
+drop table version_attestations;
+drop table attestation_providers;
@@ -0,0 +1,37 @@
+create table attestation_providers
+(
+    user_id     integer   not null primary key references users (id) on delete cascade,
+    name        varchar   not null,
+    approved_by integer   references users (id) on delete set null,
+    created_at  timestamp not null default now()
+);
+
+comment on table attestation_providers is 'Accounts that were approved by an admin to attach attestations (e.g. rebuilds or audits) to versions.';
+comment on column attestation_providers.user_id is 'Reference to the account of the provider in the `users` table.';
+comment on column attestation_providers.name is 'Human-readable name of the provider, e.g. the name of the rebuilder or audit organization.';
+comment on column attestation_providers.approved_by is 'Reference to the admin in the `users` table that approved the provider.';
+comment on column attestation_providers.created_at is 'Date and time when the provider was approved.';
+
+create table version_attestations
+(
+    id          serial    primary key,
+    version_id  integer   not null references versions (id) on delete cascade,
+    provider_id integer   not null references attestation_providers (user_id) on delete cascade,
+    kind        integer   not null,
+    statement   text      not null,
+    signature   text      not null,
+    url         varchar,
+    created_at  timestamp not null default now()
+);
+
+create index version_attestations_version_id_index on version_attestations (version_id);
+
+comment on table version_attestations is 'Signed statements of attestation providers about versions, e.g. reproducible rebuilds, audits or fuzzing coverage.';
+comment on column version_attestations.id is 'Unique identifier of the attestation.';
+comment on column version_attestations.version_id is 'Reference to the version in the `versions` table.';
+comment on column version_attestations.provider_id is 'Reference to the provider in the `attestation_providers` table.';
+comment on column version_attestations.kind is 'Kind of the attestation: 0=reproducible build, 1=audit, 2=fuzzing.';
+comment on column version_attestations.statement is 'The statement of the provider, in a format that is defined by the provider.';
+comment on column version_attestations.signature is 'Signature of the statement, which can be verified with the published key of the provider.';
+comment on column version_attestations.url is 'Optional link to more details, e.g. a build log or an audit report.';
+comment on column version_attestations.created_at is 'Date and time when the attestation was submitted.';
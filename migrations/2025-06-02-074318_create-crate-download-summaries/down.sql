@@ -0,0 +1,2 @@
+drop function refresh_crate_download_summaries();
+drop materialized view crate_download_summaries;
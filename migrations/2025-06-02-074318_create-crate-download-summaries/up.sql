@@ -0,0 +1,30 @@
+create materialized view crate_download_summaries
+            (crate_id, last_day, last_week, last_month, last_90_days, previous_week, computed_at) as
+select versions.crate_id,
+       coalesce(sum(version_downloads.downloads) filter (where version_downloads.date > current_date - 1), 0),
+       coalesce(sum(version_downloads.downloads) filter (where version_downloads.date > current_date - 7), 0),
+       coalesce(sum(version_downloads.downloads) filter (where version_downloads.date > current_date - 30), 0),
+       coalesce(sum(version_downloads.downloads), 0),
+       coalesce(sum(version_downloads.downloads) filter (where version_downloads.date <= current_date - 7
+                                                          and version_downloads.date > current_date - 14), 0),
+       now()::timestamp
+from version_downloads
+         inner join versions on version_downloads.version_id = versions.id
+where version_downloads.date > current_date - 90
+group by versions.crate_id;
+
+create unique index crate_download_summaries_crate_id on crate_download_summaries (crate_id);
+
+comment on materialized view crate_download_summaries is 'Precomputed download totals of the crates for the windows that are shown in charts and badges. Refreshed by the `update_downloads` background job.';
+comment on column crate_download_summaries.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_download_summaries.last_day is 'Number of downloads of the current day.';
+comment on column crate_download_summaries.last_week is 'Number of downloads of the last 7 days, including the current day.';
+comment on column crate_download_summaries.last_month is 'Number of downloads of the last 30 days, including the current day.';
+comment on column crate_download_summaries.last_90_days is 'Number of downloads of the last 90 days, including the current day.';
+comment on column crate_download_summaries.previous_week is 'Number of downloads of the 7 days before the last 7 days.';
+comment on column crate_download_summaries.computed_at is 'Date and time when the view was last refreshed.';
+
+create function refresh_crate_download_summaries() returns void as
+$$
+refresh materialized view concurrently crate_download_summaries;
+$$ language sql;
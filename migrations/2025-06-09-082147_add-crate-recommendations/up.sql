@@ -0,0 +1,18 @@
+create table crate_recommendations
+(
+    crate_id         integer   not null references crates (id) on delete cascade,
+    kind             integer   not null,
+    related_crate_id integer   not null references crates (id) on delete cascade,
+    score            bigint    not null,
+    computed_at      timestamp not null default now(),
+    primary key (crate_id, kind, related_crate_id)
+);
+
+create index crate_recommendations_related_crate_id_index on crate_recommendations (related_crate_id);
+
+comment on table crate_recommendations is 'Crates that are related to other crates, as computed by the `update_crate_recommendations` background job.';
+comment on column crate_recommendations.crate_id is 'Reference to the crate in the `crates` table that the recommendation is shown for.';
+comment on column crate_recommendations.kind is '0=depended_on_together, 1=used_together';
+comment on column crate_recommendations.related_crate_id is 'Reference to the recommended crate in the `crates` table.';
+comment on column crate_recommendations.score is 'Number of crates that depend on both crates for `depended_on_together`, or the recent downloads of these crates for `used_together`.';
+comment on column crate_recommendations.computed_at is 'Date and time when the recommendation was computed.';
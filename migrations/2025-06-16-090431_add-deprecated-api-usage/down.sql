@@ -0,0 +1 @@
+drop table deprecated_api_usage;
@@ -0,0 +1,18 @@
+create table deprecated_api_usage
+(
+    surface       varchar   not null,
+    api_token_id  integer   not null references api_tokens (id) on delete cascade,
+    requests      bigint    not null default 1,
+    first_used_at timestamp not null default now(),
+    last_used_at  timestamp not null default now(),
+    primary key (surface, api_token_id)
+);
+
+create index deprecated_api_usage_api_token_id_index on deprecated_api_usage (api_token_id);
+
+comment on table deprecated_api_usage is 'Usage of deprecated API endpoints and query parameters per API token, so that the owners of the tokens can be contacted before the surfaces are removed.';
+comment on column deprecated_api_usage.surface is 'The deprecated route pattern, followed by `?<param>` if only a query parameter of the route is deprecated.';
+comment on column deprecated_api_usage.api_token_id is 'Reference to the API token in the `api_tokens` table that the requests were authenticated with.';
+comment on column deprecated_api_usage.requests is 'Number of requests to the deprecated surface.';
+comment on column deprecated_api_usage.first_used_at is 'Date and time of the first request to the deprecated surface.';
+comment on column deprecated_api_usage.last_used_at is 'Date and time of the most recent request to the deprecated surface.';
@@ -0,0 +1,21 @@
+create table mirrors
+(
+    id          serial primary key,
+    name        varchar   not null unique,
+    base_url    varchar   not null,
+    region      varchar   not null,
+    enabled     boolean   not null default true,
+    created_at  timestamp not null default now(),
+    checked_at  timestamp,
+    verified_at timestamp
+);
+
+comment on table mirrors is 'Registered mirrors of the crate files, which downloads are redirected to based on the region of the client.';
+comment on column mirrors.id is 'Unique identifier of the mirror.';
+comment on column mirrors.name is 'Unique name of the mirror, used to manage it with the `crates-admin mirrors` command.';
+comment on column mirrors.base_url is 'URL that the paths of the crate files are appended to, e.g. `https://mirror.example.com` for `https://mirror.example.com/crates/foo/foo-1.0.0.crate`.';
+comment on column mirrors.region is 'Code of the continent that the mirror serves, e.g. `EU`.';
+comment on column mirrors.enabled is 'Whether downloads are redirected to the mirror. Mirrors are disabled automatically if they could not be verified for a while.';
+comment on column mirrors.created_at is 'Date and time when the mirror was registered.';
+comment on column mirrors.checked_at is 'Date and time of the most recent health check of the mirror.';
+comment on column mirrors.verified_at is 'Date and time of the most recent health check that found a crate file with the expected checksum on the mirror.';
@@ -0,0 +1,33 @@
+create table crate_owner_actions
+(
+    id           serial primary key,
+    crate_id     integer   not null references crates (id) on delete cascade,
+    owner_id     integer   not null,
+    owner_kind   integer   not null,
+    action       integer   not null,
+    performed_by integer references users (id) on delete set null,
+    time         timestamp not null default now()
+);
+
+comment on table crate_owner_actions is 'Append-only log of the users and teams that were added to or removed from the owners of a crate.';
+comment on column crate_owner_actions.id is 'Unique identifier of the action.';
+comment on column crate_owner_actions.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column crate_owner_actions.owner_id is 'This refers either to the `users.id` or `teams.id` column, depending on the value of the `owner_kind` column.';
+comment on column crate_owner_actions.owner_kind is '`owner_kind = 0` refers to `users`, `owner_kind = 1` refers to `teams`.';
+comment on column crate_owner_actions.action is '0=add, 1=remove';
+comment on column crate_owner_actions.performed_by is 'Reference to the user in the `users` table that performed the action, or NULL if it was performed by the crates.io team or is unknown.';
+comment on column crate_owner_actions.time is 'Date and time when the action was performed.';
+
+create index crate_owner_actions_crate_id_index on crate_owner_actions (crate_id);
+
+-- Backfill the history from the current state of the `crate_owners` table.
+-- Previous removals and additions of the same owner are unknown, and so is
+-- the user that removed an owner.
+insert into crate_owner_actions (crate_id, owner_id, owner_kind, action, performed_by, time)
+select crate_id, owner_id, owner_kind, 0, created_by, created_at
+from crate_owners;
+
+insert into crate_owner_actions (crate_id, owner_id, owner_kind, action, performed_by, time)
+select crate_id, owner_id, owner_kind, 1, null, updated_at
+from crate_owners
+where deleted;
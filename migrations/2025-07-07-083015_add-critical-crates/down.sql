@@ -0,0 +1,5 @@
+drop table pending_owner_removals;
+drop table critical_crates;
+
+alter table users
+    drop column gh_two_factor;
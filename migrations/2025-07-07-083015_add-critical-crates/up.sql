@@ -0,0 +1,37 @@
+alter table users
+    add column gh_two_factor boolean not null default false;
+
+comment on column users.gh_two_factor is 'Whether the GitHub account of the user had two-factor authentication enabled when the user last signed in.';
+
+create table critical_crates
+(
+    crate_id      integer   not null primary key references crates (id) on delete cascade,
+    designated_by integer   not null references users (id),
+    created_at    timestamp not null default now()
+);
+
+comment on table critical_crates is 'Crates that have been designated as critical for the ecosystem by an admin. All owners of critical crates must use two-factor authentication, and yanks and owner removals are subject to stricter rules.';
+comment on column critical_crates.crate_id is 'Reference to the critical crate in the `crates` table.';
+comment on column critical_crates.designated_by is 'Reference to the admin that designated the crate as critical.';
+comment on column critical_crates.created_at is 'Date and time when the crate was designated as critical.';
+
+create table pending_owner_removals
+(
+    id           serial    not null primary key,
+    crate_id     integer   not null references crates (id) on delete cascade,
+    owner_id     integer   not null,
+    owner_kind   integer   not null,
+    requested_by integer   not null references users (id),
+    approved_by  integer references users (id),
+    created_at   timestamp not null default now(),
+    unique (crate_id, owner_id, owner_kind)
+);
+
+comment on table pending_owner_removals is 'Removals of owners of critical crates that are held back until two admins approved them, or until the waiting period is over.';
+comment on column pending_owner_removals.id is 'Unique identifier of the pending removal.';
+comment on column pending_owner_removals.crate_id is 'Reference to the crate in the `crates` table.';
+comment on column pending_owner_removals.owner_id is 'Reference to the owner that is being removed, either in the `users` or the `teams` table.';
+comment on column pending_owner_removals.owner_kind is '0 = user, 1 = team';
+comment on column pending_owner_removals.requested_by is 'Reference to the user that requested the removal.';
+comment on column pending_owner_removals.approved_by is 'Reference to the first admin that approved the removal, or NULL if no admin approved it yet.';
+comment on column pending_owner_removals.created_at is 'Date and time when the removal was requested.';
@@ -0,0 +1,2 @@
+alter table readme_renderings
+    drop column table_of_contents;
@@ -0,0 +1,4 @@
+alter table readme_renderings
+    add column table_of_contents jsonb;
+
+comment on column readme_renderings.table_of_contents is 'The headings of the rendered README as a JSON array of objects with `level`, `text` and `anchor` fields, or NULL if the README is not a Markdown file or was rendered before tables of contents were extracted.';
@@ -0,0 +1,2 @@
+drop table admin_audit_log;
+drop function reject_admin_audit_log_changes();
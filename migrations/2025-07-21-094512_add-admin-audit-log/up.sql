@@ -0,0 +1,40 @@
+create table admin_audit_log
+(
+    id         serial primary key,
+    actor_id   integer,
+    action     integer   not null,
+    crate_name varchar,
+    details    jsonb     not null default '{}',
+    created_at timestamp not null default now()
+);
+
+comment on table admin_audit_log is 'Actions that were performed by crates.io admins, either via the API or via the admin CLI. Entries can not be changed or deleted.';
+comment on column admin_audit_log.id is 'Unique identifier of the log entry.';
+comment on column admin_audit_log.actor_id is 'Reference to the admin in the `users` table that performed the action, or NULL if the action was performed via the admin CLI. This is not a foreign key, since the table is append-only and the entries of deleted users have to be kept.';
+comment on column admin_audit_log.action is 'The kind of action that was performed (see `AdminAction` in the source code).';
+comment on column admin_audit_log.crate_name is 'Name of the crate that the action affected, if any. This is not a reference to the `crates` table, so that the entries of deleted crates are kept.';
+comment on column admin_audit_log.details is 'Additional details of the action, e.g. the reason of a freeze or the affected versions.';
+comment on column admin_audit_log.created_at is 'Date and time when the action was performed.';
+
+create index admin_audit_log_actor_id_index on admin_audit_log (actor_id);
+create index admin_audit_log_action_index on admin_audit_log (action);
+create index admin_audit_log_crate_name_index on admin_audit_log (crate_name);
+
+-- The audit log is append-only. Revoking the privileges only affects roles
+-- other than the owner of the table, so the trigger rejects the changes of
+-- the owner as well.
+revoke update, delete, truncate on admin_audit_log from public;
+
+create function reject_admin_audit_log_changes() returns trigger as $$
+begin
+    raise exception 'the admin audit log is append-only';
+end;
+$$ language plpgsql;
+
+create trigger trigger_reject_admin_audit_log_changes
+    before update or delete on admin_audit_log
+    for each row execute procedure reject_admin_audit_log_changes();
+
+create trigger trigger_reject_admin_audit_log_truncate
+    before truncate on admin_audit_log
+    for each statement execute procedure reject_admin_audit_log_changes();
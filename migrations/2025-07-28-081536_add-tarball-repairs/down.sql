@@ -0,0 +1,2 @@
+drop table version_checksum_rotations;
+drop table version_content_manifests;
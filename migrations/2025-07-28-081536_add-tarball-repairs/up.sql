@@ -0,0 +1,29 @@
+create table version_content_manifests
+(
+    version_id integer not null primary key references versions (id) on delete cascade,
+    files      jsonb   not null
+);
+
+comment on table version_content_manifests is 'The files in the crate tarball of a version, recorded at publish time. This is used to verify replacement tarballs when a stored tarball has to be repaired.';
+comment on column version_content_manifests.version_id is 'Reference to the version in the `versions` table.';
+comment on column version_content_manifests.files is 'JSON object with the paths of all files in the tarball, relative to the package root, as keys and the hex-encoded SHA256 checksums of their contents as values.';
+
+create table version_checksum_rotations
+(
+    id           serial primary key,
+    version_id   integer   not null references versions (id) on delete cascade,
+    old_checksum char(64)  not null,
+    new_checksum char(64)  not null,
+    reason       varchar   not null,
+    created_at   timestamp not null default now()
+);
+
+comment on table version_checksum_rotations is 'Changes of the checksum of a version, after its stored tarball was replaced by an admin with a repaired tarball with the same contents.';
+comment on column version_checksum_rotations.id is 'Unique identifier of the rotation.';
+comment on column version_checksum_rotations.version_id is 'Reference to the version in the `versions` table.';
+comment on column version_checksum_rotations.old_checksum is 'SHA256 checksum of the tarball before the repair.';
+comment on column version_checksum_rotations.new_checksum is 'SHA256 checksum of the replacement tarball.';
+comment on column version_checksum_rotations.reason is 'Explanation of why the tarball had to be repaired, as given by the admin.';
+comment on column version_checksum_rotations.created_at is 'Date and time when the tarball was replaced.';
+
+create index version_checksum_rotations_version_id_index on version_checksum_rotations (version_id);
@@ -0,0 +1,6 @@
+alter table rate_limit_rejections drop column scope;
+
+delete from publish_limit_buckets where scope != '';
+alter table publish_limit_buckets drop constraint publish_limit_buckets_pkey;
+alter table publish_limit_buckets add constraint publish_limit_buckets_pkey primary key (user_id, action);
+alter table publish_limit_buckets drop column scope;
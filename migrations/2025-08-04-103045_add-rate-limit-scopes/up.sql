@@ -0,0 +1,12 @@
+alter table publish_limit_buckets
+    add column scope varchar not null default '';
+
+alter table publish_limit_buckets drop constraint publish_limit_buckets_pkey;
+alter table publish_limit_buckets add constraint publish_limit_buckets_pkey primary key (user_id, action, scope);
+
+comment on column publish_limit_buckets.scope is 'Scope of the rate limited action, e.g. the name of a crate, or an empty string if the bucket applies to the action as a whole.';
+
+alter table rate_limit_rejections
+    add column scope varchar;
+
+comment on column rate_limit_rejections.scope is 'Scope of the rejected action, e.g. the name of a crate, or NULL if the action is not scoped.';
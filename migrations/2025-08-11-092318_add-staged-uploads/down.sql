@@ -0,0 +1 @@
+drop table staged_uploads;
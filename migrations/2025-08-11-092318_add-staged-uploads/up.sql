@@ -0,0 +1,19 @@
+create table staged_uploads
+(
+    id           text      not null primary key default random_string(32),
+    user_id      integer   not null references users (id) on delete cascade,
+    checksum     char(64)  not null,
+    size         integer   not null,
+    scan_verdict integer,
+    created_at   timestamp not null default now()
+);
+
+comment on table staged_uploads is 'Crate tarballs that were uploaded ahead of the publish request that references them.';
+comment on column staged_uploads.id is 'Unique identifier of the staged upload, which is referenced by the publish request.';
+comment on column staged_uploads.user_id is 'The user that uploaded the tarball, and that is the only one who can publish it.';
+comment on column staged_uploads.checksum is 'SHA256 checksum of the tarball.';
+comment on column staged_uploads.size is 'Size of the tarball in bytes.';
+comment on column staged_uploads.scan_verdict is 'Verdict of the antivirus scan of the tarball: 0=clean, 1=infected, or NULL if it has not been scanned yet.';
+comment on column staged_uploads.created_at is 'Date and time when the tarball was uploaded. Staged uploads expire after a day.';
+
+create index staged_uploads_user_id_checksum_index on staged_uploads (user_id, checksum);
@@ -0,0 +1 @@
+drop index concurrently if exists versions_links_index;
@@ -0,0 +1,3 @@
+create index concurrently if not exists versions_links_index
+    on versions (links)
+    where links is not null;
@@ -0,0 +1,8 @@
+comment on column registry_events.kind is 'The kind of change: 0=publish, 1=yank, 2=unyank, 3=owner change, 4=delete.';
+
+delete from registry_events where kind in (5, 6);
+delete from version_owner_actions where action in (3, 4);
+
+alter table versions
+    drop column flag_message,
+    drop column flagged_at;
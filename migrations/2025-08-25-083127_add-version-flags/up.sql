@@ -0,0 +1,8 @@
+alter table versions
+    add column flag_message varchar,
+    add column flagged_at timestamp;
+
+comment on column versions.flag_message is 'Warning that the owners attached to the version, e.g. because of a known bug, without yanking it, or NULL if the version is not flagged.';
+comment on column versions.flagged_at is 'Date and time when the version was last flagged, or NULL if the version is not flagged.';
+
+comment on column registry_events.kind is 'The kind of change: 0=publish, 1=yank, 2=unyank, 3=owner change, 4=delete, 5=flag, 6=unflag.';
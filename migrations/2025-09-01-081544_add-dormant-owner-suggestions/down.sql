@@ -0,0 +1 @@
+drop table dormant_owner_suggestions;
@@ -0,0 +1,20 @@
+create table dormant_owner_suggestions
+(
+    id           serial primary key,
+    crate_id     integer   not null references crates (id) on delete cascade,
+    owner_id     integer   not null references users (id) on delete cascade,
+    recipient_id integer   not null references users (id) on delete cascade,
+    token        text      not null default random_string(26) unique,
+    created_at   timestamp not null default now()
+);
+
+create index dormant_owner_suggestions_crate_id_owner_id_index
+    on dormant_owner_suggestions (crate_id, owner_id);
+
+comment on table dormant_owner_suggestions is 'Suggestions to remove dormant owners of a crate that were sent to the active owners of the crate via email.';
+comment on column dormant_owner_suggestions.id is 'Unique identifier of the suggestion.';
+comment on column dormant_owner_suggestions.crate_id is 'The crate that the dormant owner owns.';
+comment on column dormant_owner_suggestions.owner_id is 'The owner that has not been active for a while.';
+comment on column dormant_owner_suggestions.recipient_id is 'The active owner that the suggestion was sent to.';
+comment on column dormant_owner_suggestions.token is 'Secret token that is sent to the active owner to confirm the removal.';
+comment on column dormant_owner_suggestions.created_at is 'Date and time when the suggestion was sent. Suggestions expire after 30 days.';
@@ -0,0 +1,27 @@
+create table advisories
+(
+    id           varchar   not null primary key,
+    crate_name   varchar   not null,
+    title        text      not null,
+    url          text,
+    date         date      not null,
+    withdrawn    date,
+    aliases      text[]    not null default '{}',
+    patched      text[]    not null default '{}',
+    unaffected   text[]    not null default '{}',
+    synced_at    timestamp not null default now()
+);
+
+create index advisories_canon_crate_name_index on advisories (canon_crate_name(crate_name));
+
+comment on table advisories is 'Security advisories of the RustSec advisory database, which are imported by the `sync_advisories` background job.';
+comment on column advisories.id is 'The ID of the advisory in the RustSec advisory database, e.g. `RUSTSEC-2024-0001`.';
+comment on column advisories.crate_name is 'The name of the affected crate. The crate does not necessarily exist on this registry.';
+comment on column advisories.title is 'The title of the advisory.';
+comment on column advisories.url is 'The URL of a web page with more details about the advisory, if there is one.';
+comment on column advisories.date is 'The date when the advisory was published.';
+comment on column advisories.withdrawn is 'The date when the advisory was withdrawn, e.g. because it was published in error, or NULL if it is still in effect.';
+comment on column advisories.aliases is 'Other IDs of the same vulnerability, e.g. CVE or GHSA IDs.';
+comment on column advisories.patched is 'Version requirements that match the versions in which the vulnerability is fixed.';
+comment on column advisories.unaffected is 'Version requirements that match the versions that were never affected by the vulnerability.';
+comment on column advisories.synced_at is 'Date and time when the advisory was last imported from the advisory database.';
@@ -0,0 +1,28 @@
+create table rate_limit_override_requests
+(
+    id            serial primary key,
+    user_id       integer   not null references users (id) on delete cascade,
+    action        integer   not null,
+    burst         integer   not null,
+    justification varchar   not null,
+    created_at    timestamp not null default now(),
+    reviewed_by   integer references users (id) on delete set null,
+    reviewed_at   timestamp,
+    approved      boolean,
+    expires_at    timestamp
+);
+
+create unique index rate_limit_override_requests_pending_index
+    on rate_limit_override_requests (user_id, action) where reviewed_at is null;
+
+comment on table rate_limit_override_requests is 'Requests of users for a higher burst of a rate limited action, which are reviewed by admins.';
+comment on column rate_limit_override_requests.id is 'Unique identifier of the request.';
+comment on column rate_limit_override_requests.user_id is 'Reference to the user in the `users` table that filed the request.';
+comment on column rate_limit_override_requests.action is 'The rate limited action: 0=publish_new, 1=publish_update, 2=yank_unyank, 3=bulk_metadata.';
+comment on column rate_limit_override_requests.burst is 'The requested burst, which the admin may change when approving the request.';
+comment on column rate_limit_override_requests.justification is 'Explanation of the user why they need a higher limit.';
+comment on column rate_limit_override_requests.created_at is 'Date and time when the request was filed.';
+comment on column rate_limit_override_requests.reviewed_by is 'Reference to the admin in the `users` table that reviewed the request.';
+comment on column rate_limit_override_requests.reviewed_at is 'Date and time when the request was reviewed, or NULL if it is still pending.';
+comment on column rate_limit_override_requests.approved is 'Whether the request was approved, or NULL if it is still pending.';
+comment on column rate_limit_override_requests.expires_at is 'Date and time when the granted override expires, if the request was approved.';
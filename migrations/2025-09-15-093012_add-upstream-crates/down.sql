@@ -0,0 +1 @@
+drop table upstream_crates;
@@ -0,0 +1,14 @@
+create table upstream_crates
+(
+    name           varchar   not null primary key,
+    created_at     timestamp not null default now(),
+    revalidated_at timestamp not null default now()
+);
+
+create unique index upstream_crates_canon_name_index on upstream_crates (canon_crate_name(name));
+create index upstream_crates_revalidated_at_index on upstream_crates (revalidated_at);
+
+comment on table upstream_crates is 'Crates that were fetched from the upstream registry of a pull-through cache. Their index files and crate files are cached in the storage bucket, but they are not added to the other tables.';
+comment on column upstream_crates.name is 'The name of the crate in the upstream registry.';
+comment on column upstream_crates.created_at is 'Date and time when the crate was fetched from the upstream registry for the first time.';
+comment on column upstream_crates.revalidated_at is 'Date and time when the cached index file was last revalidated against the upstream registry.';
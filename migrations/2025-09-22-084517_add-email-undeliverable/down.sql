@@ -0,0 +1,3 @@
+alter table emails
+    drop column undeliverable_at,
+    drop column undeliverable_reason;
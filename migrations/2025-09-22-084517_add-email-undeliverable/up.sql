@@ -0,0 +1,6 @@
+alter table emails
+    add column undeliverable_at     timestamp,
+    add column undeliverable_reason varchar;
+
+comment on column emails.undeliverable_at is 'Date and time when the email provider reported that emails to this address bounced permanently or were marked as spam. No notifications are sent to undeliverable addresses until they are verified again.';
+comment on column emails.undeliverable_reason is 'The reason why the address is undeliverable, either `bounce` or `complaint`.';
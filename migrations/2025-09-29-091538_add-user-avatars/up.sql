@@ -0,0 +1,17 @@
+create table user_avatars
+(
+    user_id      integer primary key references users (id) on delete cascade,
+    url          text      not null,
+    digest       text,
+    content_type varchar,
+    fetched_at   timestamp,
+    failed_at    timestamp
+);
+
+comment on table user_avatars is 'Avatars of users that are served from the storage bucket of crates.io instead of being loaded from GitHub.';
+comment on column user_avatars.user_id is 'Reference to the user in the `users` table.';
+comment on column user_avatars.url is 'The original URL of the avatar that was last fetched, or that failed to be fetched.';
+comment on column user_avatars.digest is 'SHA256 digest of the cached avatar, which is used in its path in the storage bucket, or NULL if it has not been fetched successfully yet.';
+comment on column user_avatars.content_type is 'The content type of the cached avatar, or NULL if it has not been fetched successfully yet.';
+comment on column user_avatars.fetched_at is 'Date and time when the avatar was last fetched and uploaded to the storage bucket successfully.';
+comment on column user_avatars.failed_at is 'Date and time when fetching the avatar last failed, e.g. because it was too large or not an image.';
@@ -0,0 +1,5 @@
+alter table users
+    drop column max_dependencies;
+
+alter table crates
+    drop column max_feature_values;
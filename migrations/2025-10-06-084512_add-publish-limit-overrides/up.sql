@@ -0,0 +1,9 @@
+alter table crates
+    add column max_feature_values smallint;
+
+comment on column crates.max_feature_values is 'If set, overrides the maximum number of features or dependencies that a single feature of this crate can enable.';
+
+alter table users
+    add column max_dependencies smallint;
+
+comment on column users.max_dependencies is 'If set, overrides the maximum number of dependencies that a version published by this user can declare, unless the crate has its own override.';
@@ -59,6 +59,7 @@ export default BaseSerializer.extend({
     semverSort(versionNums, { loose: true });
     hash.max_version = versionNums[0] ?? '0.0.0';
     hash.max_stable_version = versionNums.find(it => !prerelease(it, { loose: true })) ?? null;
+    hash.default_version = hash.max_stable_version ?? versionNums[0] ?? null;
 
     let newestVersions = versions.models.sort((a, b) => compareIsoDates(b.updated_at, a.updated_at));
     hash.newest_version = newestVersions[0]?.num ?? '0.0.0';
@@ -0,0 +1,66 @@
+use crate::admin::dialoguer;
+use crate::db;
+use crate::models::{AdminAction, NewAdminAuditEntry};
+use crate::schema::{crate_name_skeleton_overrides, crates};
+use crate::sql::crate_name_skeleton;
+use anyhow::Context;
+use diesel::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "allow-crate-name",
+    about = "Allow a crate name to be published, even though it can be confused with the name of an existing crate."
+)]
+pub struct Opts {
+    /// Name of the crate that should be allowed
+    crate_name: String,
+
+    /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+    #[arg(short, long)]
+    yes: bool,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    let Opts { crate_name, yes } = opts;
+
+    let similar_crates: Vec<String> = crates::table
+        .filter(crate_name_skeleton(crates::name).eq(crate_name_skeleton(&crate_name)))
+        .select(crates::name)
+        .load(conn)
+        .context("Failed to look up similar crate names")?;
+
+    if similar_crates.is_empty() {
+        println!("No existing crate name can be confused with `{crate_name}`");
+    } else {
+        println!("The following crate names can be confused with `{crate_name}`:");
+        for name in &similar_crates {
+            println!("  - {name}");
+        }
+    }
+
+    if !yes {
+        let prompt = format!("Are you sure you want to allow publishing `{crate_name}`?");
+        if !dialoguer::confirm(&prompt) {
+            return Ok(());
+        }
+    }
+
+    conn.transaction(|conn| {
+        diesel::insert_into(crate_name_skeleton_overrides::table)
+            .values(crate_name_skeleton_overrides::name.eq(&crate_name))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        NewAdminAuditEntry::by_cli(AdminAction::AllowCrateName)
+            .krate(&crate_name)
+            .details(json!({ "similar_crates": similar_crates }))
+            .insert(conn)
+    })
+    .context("Failed to insert crate name override")?;
+
+    println!("`{crate_name}` can now be published");
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use crate::admin::dialoguer;
+use crate::db;
+use crate::models::{AdminAction, Crate, NewAdminAuditEntry};
+use crate::schema::crates;
+use anyhow::Context;
+use diesel::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "crate-limits",
+    about = "Show or override the publish limits of a crate.",
+    after_help = "Without any limit arguments, the current limits of the crate are shown."
+)]
+pub struct Opts {
+    /// Name of the crate
+    crate_name: String,
+
+    /// Maximum size of an uploaded crate file in bytes
+    #[arg(long)]
+    max_upload_size: Option<i32>,
+
+    /// Maximum number of features
+    #[arg(long)]
+    max_features: Option<i16>,
+
+    /// Maximum number of features or dependencies that a single feature can enable
+    #[arg(long)]
+    max_feature_values: Option<i16>,
+
+    /// Maximum number of dependencies of a version
+    #[arg(long)]
+    max_dependencies: Option<i16>,
+
+    /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+    #[arg(short, long)]
+    yes: bool,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = crates)]
+struct CrateLimits {
+    max_upload_size: Option<i32>,
+    max_features: Option<i16>,
+    max_feature_values: Option<i16>,
+    max_dependencies: Option<i16>,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    let krate: Crate = Crate::by_name(&opts.crate_name)
+        .first(conn)
+        .with_context(|| format!("Failed to find crate `{}`", opts.crate_name))?;
+
+    println!("Current limits of `{}`:", krate.name);
+    println!("  max upload size:    {:?}", krate.max_upload_size);
+    println!("  max features:       {:?}", krate.max_features);
+    println!("  max feature values: {:?}", krate.max_feature_values);
+    println!("  max dependencies:   {:?}", krate.max_dependencies);
+
+    let limits = CrateLimits {
+        max_upload_size: opts.max_upload_size,
+        max_features: opts.max_features,
+        max_feature_values: opts.max_feature_values,
+        max_dependencies: opts.max_dependencies,
+    };
+
+    if limits.max_upload_size.is_none()
+        && limits.max_features.is_none()
+        && limits.max_feature_values.is_none()
+        && limits.max_dependencies.is_none()
+    {
+        return Ok(());
+    }
+
+    if !opts.yes {
+        let prompt = format!(
+            "Are you sure you want to apply {limits:?} to `{}`?",
+            krate.name
+        );
+        if !dialoguer::confirm(&prompt) {
+            return Ok(());
+        }
+    }
+
+    conn.transaction(|conn| {
+        let krate = diesel::update(&krate)
+            .set(&limits)
+            .returning(Crate::as_returning())
+            .get_result(conn)?;
+
+        NewAdminAuditEntry::by_cli(AdminAction::ChangeCrateLimits)
+            .krate(&krate.name)
+            .details(json!({
+                "max_upload_size": krate.max_upload_size,
+                "max_features": krate.max_features,
+                "max_dependencies": krate.max_dependencies,
+                "max_feature_values": krate.max_feature_values,
+            }))
+            .insert(conn)
+    })
+    .context("Failed to update crate limits")?;
+
+    println!("Limits of `{}` updated", krate.name);
+
+    Ok(())
+}
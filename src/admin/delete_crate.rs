@@ -1,3 +1,4 @@
+use crate::models::{AdminAction, NewAdminAuditEntry, NewRegistryEvent, RegistryEventKind};
 use crate::schema::{crate_owners, teams, users};
 use crate::storage::{FeedId, Storage};
 use crate::worker::jobs;
@@ -81,7 +82,14 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
     for name in &crate_names {
         if let Some((id, _)) = existing_crates.get(name) {
             info!(%name, "Deleting crate from the database");
-            if let Err(error) = diesel::delete(crates::table.find(id)).execute(conn) {
+            let result = conn.transaction(|conn| {
+                diesel::delete(crates::table.find(id)).execute(conn)?;
+                NewRegistryEvent::krate(RegistryEventKind::Delete, name).insert(conn)?;
+                NewAdminAuditEntry::by_cli(AdminAction::DeleteCrate)
+                    .krate(name)
+                    .insert(conn)
+            });
+            if let Err(error) = result {
                 warn!(%name, %id, ?error, "Failed to delete crate from the database");
             }
         } else {
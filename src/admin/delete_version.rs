@@ -1,4 +1,6 @@
-use crate::models::update_default_version;
+use crate::models::{
+    update_default_version, AdminAction, NewAdminAuditEntry, NewRegistryEvent, RegistryEventKind,
+};
 use crate::schema::crates;
 use crate::storage::Storage;
 use crate::worker::jobs;
@@ -56,20 +58,36 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
                 .filter(versions::crate_id.eq(crate_id))
                 .filter(versions::num.eq_any(&opts.versions)),
         )
-        .execute(conn);
+        .returning(versions::num)
+        .get_results::<String>(conn);
 
-        match result {
-            Ok(num_deleted) if num_deleted == opts.versions.len() => {}
-            Ok(num_deleted) => {
+        let deleted = match result {
+            Ok(deleted) if deleted.len() == opts.versions.len() => deleted,
+            Ok(deleted) => {
                 warn!(
                     %crate_name,
                     "Deleted only {num_deleted} of {num_expected} versions from the database",
+                    num_deleted = deleted.len(),
                     num_expected = opts.versions.len()
                 );
+                deleted
             }
             Err(error) => {
-                warn!(%crate_name, ?error, "Failed to delete versions from the database")
+                warn!(%crate_name, ?error, "Failed to delete versions from the database");
+                vec![]
             }
+        };
+
+        for version in &deleted {
+            NewRegistryEvent::version(RegistryEventKind::Delete, crate_name, version)
+                .insert(conn)?;
+        }
+
+        if !deleted.is_empty() {
+            NewAdminAuditEntry::by_cli(AdminAction::DeleteVersion)
+                .krate(crate_name)
+                .details(json!({ "versions": deleted }))
+                .insert(conn)?;
         }
 
         info!(%crate_name, %crate_id, "Updating default version in the database");
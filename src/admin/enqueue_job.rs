@@ -1,5 +1,5 @@
 use crate::db;
-use crate::schema::{background_jobs, crates};
+use crate::schema::{background_jobs, crates, versions};
 use crate::worker::jobs;
 use anyhow::Result;
 use chrono::NaiveDate;
@@ -14,33 +14,65 @@ use diesel::prelude::*;
     rename_all = "snake_case"
 )]
 pub enum Command {
+    ApplyPendingOwnerRemovals,
+    ApplyPrereleaseRetention,
     ArchiveVersionDownloads {
         #[arg(long)]
         /// The date before which to archive version downloads (default: 90 days ago)
         before: Option<NaiveDate>,
     },
+    /// Calculates the `semver_ord` column of versions that were published
+    /// before the column was added
+    BackfillSemverOrd,
+    UpdateCrateHealth,
+    UpdateCrateRecommendations,
     UpdateDownloads,
     CleanProcessedLogFiles,
     DumpDb,
+    DumpDbDelta,
     DailyDbMaintenance,
     SquashIndex,
+    SyncRegistryConfigs,
     NormalizeIndex {
         #[arg(long = "dry-run")]
         dry_run: bool,
     },
+    CheckMirrors,
     CheckTyposquat {
         #[arg()]
         name: String,
     },
     ProcessCdnLogQueue(jobs::ProcessCdnLogQueue),
+    PurgeExpiredRecords,
+    /// Regenerates the sparse index files of all crates, e.g. after the
+    /// format of the index entries was changed
+    RegenerateSparseIndex,
+    /// Renders the READMEs again that were rendered with an outdated
+    /// sanitizer policy
+    RerenderReadmes,
+    /// Refreshes the cached index files of a pull-through cache from the
+    /// upstream registry
+    RevalidateUpstreamCrates,
+    ScanTarball {
+        #[arg()]
+        name: String,
+        #[arg()]
+        version: String,
+    },
     SyncAdmins {
         /// Force a sync even if one is already in progress
         #[arg(long)]
         force: bool,
     },
+    /// Imports the security advisories of the advisory database
+    SyncAdvisories,
     SendTokenExpiryNotifications,
+    SendWeeklyDigests,
+    SuggestDormantOwnerRemovals,
     SyncCratesFeed,
+    SyncIndexConfig,
     SyncUpdatesFeed,
+    ValidateVersionMetadata,
 }
 
 pub fn run(command: Command) -> Result<()> {
@@ -48,12 +80,27 @@ pub fn run(command: Command) -> Result<()> {
     println!("Enqueueing background job: {command:?}");
 
     match command {
+        Command::ApplyPendingOwnerRemovals => {
+            jobs::ApplyPendingOwnerRemovals.enqueue(conn)?;
+        }
+        Command::ApplyPrereleaseRetention => {
+            jobs::ApplyPrereleaseRetention.enqueue(conn)?;
+        }
         Command::ArchiveVersionDownloads { before } => {
             before
                 .map(jobs::ArchiveVersionDownloads::before)
                 .unwrap_or_default()
                 .enqueue(conn)?;
         }
+        Command::BackfillSemverOrd => {
+            jobs::BackfillSemverOrd::default().enqueue(conn)?;
+        }
+        Command::UpdateCrateHealth => {
+            jobs::UpdateCrateHealth.enqueue(conn)?;
+        }
+        Command::UpdateCrateRecommendations => {
+            jobs::UpdateCrateRecommendations.enqueue(conn)?;
+        }
         Command::UpdateDownloads => {
             let count: i64 = background_jobs::table
                 .filter(background_jobs::job_type.eq(jobs::UpdateDownloads::JOB_NAME))
@@ -75,6 +122,9 @@ pub fn run(command: Command) -> Result<()> {
         Command::DumpDb => {
             jobs::DumpDb.enqueue(conn)?;
         }
+        Command::DumpDbDelta => {
+            jobs::DumpDbDelta.enqueue(conn)?;
+        }
         Command::SyncAdmins { force } => {
             if !force {
                 // By default, we don't want to enqueue a sync if one is already
@@ -97,18 +147,42 @@ pub fn run(command: Command) -> Result<()> {
 
             jobs::SyncAdmins.enqueue(conn)?;
         }
+        Command::SyncAdvisories => {
+            jobs::SyncAdvisories.enqueue(conn)?;
+        }
         Command::DailyDbMaintenance => {
             jobs::DailyDbMaintenance.enqueue(conn)?;
         }
         Command::ProcessCdnLogQueue(job) => {
             job.enqueue(conn)?;
         }
+        Command::PurgeExpiredRecords => {
+            jobs::PurgeExpiredRecords.enqueue(conn)?;
+        }
+        Command::RegenerateSparseIndex => {
+            jobs::RegenerateSparseIndex::default().enqueue(conn)?;
+        }
+        Command::RerenderReadmes => {
+            jobs::RerenderReadmes::default().enqueue(conn)?;
+        }
+        Command::RevalidateUpstreamCrates => {
+            jobs::RevalidateUpstreamCrates.enqueue(conn)?;
+        }
+        Command::SyncIndexConfig => {
+            jobs::SyncIndexConfig.enqueue(conn)?;
+        }
         Command::SquashIndex => {
             jobs::SquashIndex.enqueue(conn)?;
         }
+        Command::SyncRegistryConfigs => {
+            jobs::SyncRegistryConfigs.enqueue(conn)?;
+        }
         Command::NormalizeIndex { dry_run } => {
             jobs::NormalizeIndex::new(dry_run).enqueue(conn)?;
         }
+        Command::CheckMirrors => {
+            jobs::CheckMirrors.enqueue(conn)?;
+        }
         Command::CheckTyposquat { name } => {
             // The job will fail if the crate doesn't actually exist, so let's check that up front.
             if crates::table
@@ -124,15 +198,36 @@ pub fn run(command: Command) -> Result<()> {
 
             jobs::CheckTyposquat::new(&name).enqueue(conn)?;
         }
+        Command::ScanTarball { name, version } => {
+            let version_id: i32 = versions::table
+                .inner_join(crates::table)
+                .filter(crates::name.eq(&name))
+                .filter(versions::num.eq(&version))
+                .select(versions::id)
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("version {version} of {name} does not exist"))?;
+
+            jobs::ScanTarball::new(version_id).enqueue(conn)?;
+        }
         Command::SendTokenExpiryNotifications => {
             jobs::SendTokenExpiryNotifications.enqueue(conn)?;
         }
+        Command::SendWeeklyDigests => {
+            jobs::SendWeeklyDigests.enqueue(conn)?;
+        }
+        Command::SuggestDormantOwnerRemovals => {
+            jobs::SuggestDormantOwnerRemovals.enqueue(conn)?;
+        }
         Command::SyncCratesFeed => {
             jobs::rss::SyncCratesFeed.enqueue(conn)?;
         }
         Command::SyncUpdatesFeed => {
             jobs::rss::SyncUpdatesFeed.enqueue(conn)?;
         }
+        Command::ValidateVersionMetadata => {
+            jobs::ValidateVersionMetadata::default().enqueue(conn)?;
+        }
     };
 
     Ok(())
@@ -0,0 +1,550 @@
+use crate::admin::dialoguer;
+use crate::db;
+use crate::models::CrateVisibility;
+use crate::schema::{crates, emails};
+use crate::sql::{canon_crate_name, lower};
+use crate::storage::Storage;
+use crate::worker::jobs::{ImportCrate, ImportedOwner, ImportedVersion};
+use anyhow::Context;
+use crates_io_tarball::{process_tarball, TarballLimits};
+use crates_io_worker::BackgroundJob;
+use diesel::connection::DefaultLoadingMode;
+use diesel::prelude::*;
+use hex::ToHex;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The limits for the crate files of the other registry.
+const LIMITS: TarballLimits = TarballLimits {
+    max_unpack_size: 512 * 1024 * 1024,
+    max_files: usize::MAX,
+    max_path_depth: usize::MAX,
+};
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "import-registry",
+    about = "Import crates from the git index and crate files of another registry \
+        (e.g. Alexandrie or Kellnr)."
+)]
+pub struct Opts {
+    /// Path to a local checkout of the git index of the other registry
+    #[arg(long)]
+    index: PathBuf,
+
+    /// Path to the directory with the `.crate` files of the other registry,
+    /// either as `<name>-<version>.crate` or `<name>/<name>-<version>.crate`
+    #[arg(long)]
+    crates: PathBuf,
+
+    /// Path to a JSON file that maps crate names to the email addresses of
+    /// their owners, e.g. `{"foo": ["alice@example.com"]}`. Owners are mapped
+    /// to existing users via their verified email addresses.
+    #[arg(long)]
+    owners: PathBuf,
+
+    /// Only report conflicts, without importing any crates
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// A problem that was found while preparing the import of a crate.
+#[derive(Debug, PartialEq, Eq)]
+enum Conflict {
+    CrateExists,
+    NoOwners,
+    UnknownOwner(String),
+    MissingCrateFile(String),
+    ChecksumMismatch(String),
+    InvalidCrateFile(String, String),
+    UnknownDependency(String, String),
+}
+
+impl Conflict {
+    /// Crates with blocking conflicts are not imported.
+    fn is_blocking(&self) -> bool {
+        !matches!(self, Conflict::UnknownOwner(_))
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::CrateExists => write!(f, "a crate with this name already exists"),
+            Conflict::NoOwners => write!(f, "none of the owners could be mapped to a user"),
+            Conflict::UnknownOwner(email) => {
+                write!(
+                    f,
+                    "no user with the verified email address `{email}` exists"
+                )
+            }
+            Conflict::MissingCrateFile(version) => {
+                write!(f, "the crate file of version {version} is missing")
+            }
+            Conflict::ChecksumMismatch(version) => {
+                write!(
+                    f,
+                    "the crate file of version {version} has an unexpected checksum"
+                )
+            }
+            Conflict::InvalidCrateFile(version, error) => {
+                write!(f, "the crate file of version {version} is invalid: {error}")
+            }
+            Conflict::UnknownDependency(version, dependency) => write!(
+                f,
+                "version {version} depends on `{dependency}`, which is neither imported nor known"
+            ),
+        }
+    }
+}
+
+/// A crate that can be imported, and the paths of its crate files.
+struct PreparedCrate {
+    job: ImportCrate,
+    crate_files: Vec<(String, PathBuf)>,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection()?;
+
+    let owners = std::fs::read_to_string(&opts.owners)
+        .with_context(|| format!("Failed to read {}", opts.owners.display()))?;
+    let owners: BTreeMap<String, Vec<String>> = serde_json::from_str(&owners)?;
+
+    println!("reading index from {}", opts.index.display());
+    let index = read_index(&opts.index)?;
+    println!("found {} crates in the index", index.len());
+
+    let addresses = owners.values().flatten().map(|email| email.to_lowercase());
+    let users = find_users_by_email(addresses.collect(), conn)?;
+    let known_crates = find_known_crates(&index, conn)?;
+
+    let mut prepared = Vec::new();
+    let mut num_conflicts = 0;
+    for (name, entries) in index.iter() {
+        let crate_owners = owners.get(name).map(Vec::as_slice).unwrap_or_default();
+        let (crate_, conflicts) = prepare_crate(
+            name,
+            entries,
+            &opts.crates,
+            crate_owners,
+            &users,
+            &index,
+            &known_crates,
+        );
+
+        for conflict in &conflicts {
+            println!("{name}: {conflict}");
+        }
+
+        if conflicts.iter().any(Conflict::is_blocking) {
+            num_conflicts += 1;
+        } else if let Some(crate_) = crate_ {
+            prepared.push(crate_);
+        }
+    }
+
+    println!(
+        "{} crates can be imported, {num_conflicts} crates are skipped because of conflicts",
+        prepared.len()
+    );
+
+    if opts.dry_run || prepared.is_empty() || !dialoguer::confirm("continue with import?") {
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to initialize tokio runtime")?;
+
+    let storage = Storage::from_environment();
+
+    for crate_ in prepared {
+        let name = &crate_.job.name;
+        for (version, path) in &crate_.crate_files {
+            let bytes = std::fs::read(path)?;
+            // Crates are only imported if they don't exist yet, so they are
+            // always public.
+            let visibility = CrateVisibility::Public;
+            let future = storage.upload_crate_file(name, version, visibility, bytes.into());
+            rt.block_on(future)?;
+        }
+
+        crate_.job.enqueue(conn)?;
+        println!("enqueued import of `{name}`");
+    }
+
+    Ok(())
+}
+
+/// Reads all entries from a git index checkout, grouped by crate name.
+fn read_index(path: &Path) -> anyhow::Result<BTreeMap<String, Vec<crates_io_index::Crate>>> {
+    let mut index: BTreeMap<String, Vec<crates_io_index::Crate>> = BTreeMap::new();
+
+    let mut directories = vec![path.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_name = entry.file_name();
+
+            if file_name == ".git" || file_name == "config.json" {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+                continue;
+            }
+
+            let contents = std::fs::read_to_string(&path)?;
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                let entry: crates_io_index::Crate = serde_json::from_str(line)
+                    .with_context(|| format!("Failed to parse entry in {}", path.display()))?;
+
+                index.entry(entry.name.clone()).or_default().push(entry);
+            }
+        }
+    }
+
+    Ok(index)
+}
+
+/// Finds the IDs of the users with the given verified email addresses,
+/// keyed by the lowercased email address.
+fn find_users_by_email(
+    addresses: Vec<String>,
+    conn: &mut PgConnection,
+) -> QueryResult<HashMap<String, ImportedOwner>> {
+    let users: Vec<(i32, String)> = emails::table
+        .filter(emails::verified.eq(true))
+        .filter(lower(emails::email).eq_any(addresses))
+        .select((emails::user_id, emails::email))
+        .load(conn)?;
+
+    Ok(users
+        .into_iter()
+        .map(|(user_id, email)| (email.to_lowercase(), ImportedOwner { user_id, email }))
+        .collect())
+}
+
+/// Finds the canonical names of the crates that already exist in the
+/// database, among the imported crates and their dependencies.
+fn find_known_crates(
+    index: &BTreeMap<String, Vec<crates_io_index::Crate>>,
+    conn: &mut PgConnection,
+) -> QueryResult<HashSet<String>> {
+    let dependencies = index
+        .values()
+        .flatten()
+        .flat_map(|entry| &entry.deps)
+        .map(dependency_crate_name);
+
+    let names = index
+        .keys()
+        .map(String::as_str)
+        .chain(dependencies)
+        .map(canonicalize)
+        .collect::<Vec<_>>();
+
+    crates::table
+        .filter(canon_crate_name(crates::name).eq_any(names))
+        .select(canon_crate_name(crates::name))
+        .load_iter::<String, DefaultLoadingMode>(conn)?
+        .collect()
+}
+
+fn prepare_crate(
+    name: &str,
+    entries: &[crates_io_index::Crate],
+    crates_dir: &Path,
+    owner_emails: &[String],
+    users: &HashMap<String, ImportedOwner>,
+    index: &BTreeMap<String, Vec<crates_io_index::Crate>>,
+    known_crates: &HashSet<String>,
+) -> (Option<PreparedCrate>, Vec<Conflict>) {
+    let mut conflicts = Vec::new();
+
+    if known_crates.contains(&canonicalize(name)) {
+        return (None, vec![Conflict::CrateExists]);
+    }
+
+    let mut owners = Vec::new();
+    for email in owner_emails {
+        match users.get(&email.to_lowercase()) {
+            Some(owner) => owners.push(owner.clone()),
+            None => conflicts.push(Conflict::UnknownOwner(email.clone())),
+        }
+    }
+    if owners.is_empty() {
+        conflicts.push(Conflict::NoOwners);
+    }
+
+    let mut versions = Vec::new();
+    let mut crate_files = Vec::new();
+    let mut latest_package = None;
+    for entry in entries {
+        let version = &entry.vers;
+
+        for dep in &entry.deps {
+            let dep_name = dependency_crate_name(dep);
+            if !index.contains_key(dep_name) && !known_crates.contains(&canonicalize(dep_name)) {
+                let conflict = Conflict::UnknownDependency(version.clone(), dep_name.to_string());
+                if !conflicts.contains(&conflict) {
+                    conflicts.push(conflict);
+                }
+            }
+        }
+
+        let Some(path) = find_crate_file(crates_dir, name, version) else {
+            conflicts.push(Conflict::MissingCrateFile(version.clone()));
+            continue;
+        };
+
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                let conflict = Conflict::InvalidCrateFile(version.clone(), error.to_string());
+                conflicts.push(conflict);
+                continue;
+            }
+        };
+
+        let checksum: String = Sha256::digest(&bytes).encode_hex();
+        if checksum != entry.cksum {
+            conflicts.push(Conflict::ChecksumMismatch(version.clone()));
+            continue;
+        }
+
+        let pkg_name = format!("{name}-{version}");
+        let package = match process_tarball(&pkg_name, &*bytes, &LIMITS) {
+            Ok(info) => info.manifest.package,
+            Err(error) => {
+                let conflict = Conflict::InvalidCrateFile(version.clone(), error.to_string());
+                conflicts.push(conflict);
+                continue;
+            }
+        };
+
+        let license = package
+            .as_ref()
+            .and_then(|package| package.license.clone())
+            .and_then(|license| license.as_local());
+
+        versions.push(ImportedVersion {
+            index: entry.clone(),
+            license,
+            crate_size: bytes.len() as i32,
+        });
+        crate_files.push((version.clone(), path));
+        latest_package = package;
+    }
+
+    if conflicts.iter().any(Conflict::is_blocking) {
+        return (None, conflicts);
+    }
+
+    let local = |field: Option<cargo_manifest::MaybeInherited<String>>| {
+        field.and_then(|field| field.as_local())
+    };
+
+    let job = match latest_package {
+        Some(package) => ImportCrate {
+            name: name.to_string(),
+            description: local(package.description),
+            homepage: local(package.homepage),
+            documentation: local(package.documentation),
+            repository: local(package.repository),
+            keywords: package
+                .keywords
+                .and_then(|keywords| keywords.as_local())
+                .unwrap_or_default(),
+            owners,
+            versions,
+        },
+        None => ImportCrate {
+            name: name.to_string(),
+            description: None,
+            homepage: None,
+            documentation: None,
+            repository: None,
+            keywords: vec![],
+            owners,
+            versions,
+        },
+    };
+
+    let prepared = PreparedCrate { job, crate_files };
+    (Some(prepared), conflicts)
+}
+
+/// Finds the crate file of a version, as `<name>-<version>.crate` or
+/// `<name>/<name>-<version>.crate` in `crates_dir`.
+fn find_crate_file(crates_dir: &Path, name: &str, version: &str) -> Option<PathBuf> {
+    let file_name = format!("{name}-{version}.crate");
+    [
+        crates_dir.join(&file_name),
+        crates_dir.join(name).join(&file_name),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Returns the name of the crate that a dependency refers to. In the index,
+/// `package` contains the original name of renamed dependencies.
+fn dependency_crate_name(dep: &crates_io_index::Dependency) -> &str {
+    dep.package.as_deref().unwrap_or(&dep.name)
+}
+
+fn canonicalize(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io_tarball::TarballBuilder;
+
+    fn entry(name: &str, version: &str, cksum: &str) -> crates_io_index::Crate {
+        crates_io_index::Crate {
+            name: name.to_string(),
+            vers: version.to_string(),
+            deps: vec![],
+            cksum: cksum.to_string(),
+            features: Default::default(),
+            features2: None,
+            yanked: None,
+            links: None,
+            rust_version: None,
+            channel: None,
+            v: None,
+        }
+    }
+
+    fn tarball(name: &str, version: &str) -> Vec<u8> {
+        let manifest = format!(
+            "[package]\nname = \"{name}\"\nversion = \"{version}\"\n\
+            description = \"imported\"\nlicense = \"MIT\"\n"
+        );
+
+        TarballBuilder::new()
+            .add_file(&format!("{name}-{version}/Cargo.toml"), manifest.as_bytes())
+            .build()
+    }
+
+    #[test]
+    fn test_read_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("3/f")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("config.json"), "{}").unwrap();
+        std::fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+
+        let lines = [entry("foo", "1.0.0", "a"), entry("foo", "1.1.0", "b")]
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(dir.path().join("3/f/foo"), lines).unwrap();
+
+        let index = read_index(dir.path()).unwrap();
+        assert_eq!(index.len(), 1);
+        let versions = index["foo"].iter().map(|entry| &entry.vers);
+        assert_eq!(versions.collect::<Vec<_>>(), ["1.0.0", "1.1.0"]);
+    }
+
+    #[test]
+    fn test_prepare_crate() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("foo")).unwrap();
+
+        let bytes = tarball("foo", "1.0.0");
+        let checksum: String = Sha256::digest(&bytes).encode_hex();
+        std::fs::write(dir.path().join("foo/foo-1.0.0.crate"), &bytes).unwrap();
+
+        let users = HashMap::from([(
+            "alice@example.com".to_string(),
+            ImportedOwner {
+                user_id: 42,
+                email: "Alice@example.com".to_string(),
+            },
+        )]);
+        let owners = [
+            "Alice@example.com".to_string(),
+            "bob@example.com".to_string(),
+        ];
+
+        let mut entries = vec![entry("foo", "1.0.0", &checksum)];
+        let index = BTreeMap::from([("foo".to_string(), entries.clone())]);
+        let known_crates = HashSet::new();
+
+        let (prepared, conflicts) = prepare_crate(
+            "foo",
+            &entries,
+            dir.path(),
+            &owners,
+            &users,
+            &index,
+            &known_crates,
+        );
+        let prepared = prepared.unwrap();
+        assert_eq!(
+            conflicts,
+            [Conflict::UnknownOwner("bob@example.com".to_string())]
+        );
+        assert_eq!(prepared.job.description.as_deref(), Some("imported"));
+        assert_eq!(prepared.job.owners.len(), 1);
+        assert_eq!(prepared.job.versions.len(), 1);
+        assert_eq!(prepared.job.versions[0].license.as_deref(), Some("MIT"));
+        assert_eq!(prepared.crate_files.len(), 1);
+
+        // Missing crate files and unknown dependencies are blocking conflicts
+        let mut unknown_dep = entry("foo", "1.1.0", "");
+        unknown_dep.deps.push(crates_io_index::Dependency {
+            name: "bar".to_string(),
+            req: "^1".to_string(),
+            features: vec![],
+            optional: false,
+            default_features: true,
+            target: None,
+            kind: None,
+            package: None,
+        });
+        entries.push(unknown_dep);
+
+        let (prepared, conflicts) = prepare_crate(
+            "foo",
+            &entries,
+            dir.path(),
+            &owners[..1],
+            &users,
+            &index,
+            &known_crates,
+        );
+        assert!(prepared.is_none());
+        assert_eq!(
+            conflicts,
+            [
+                Conflict::UnknownDependency("1.1.0".to_string(), "bar".to_string()),
+                Conflict::MissingCrateFile("1.1.0".to_string()),
+            ]
+        );
+
+        // Existing crates are never imported
+        let known_crates = HashSet::from(["foo".to_string()]);
+        let (prepared, conflicts) = prepare_crate(
+            "foo",
+            &entries,
+            dir.path(),
+            &owners,
+            &users,
+            &index,
+            &known_crates,
+        );
+        assert!(prepared.is_none());
+        assert_eq!(conflicts, [Conflict::CrateExists]);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::db;
+use crate::mirrors::REGIONS;
+use crate::models::{AdminAction, Mirror, NewAdminAuditEntry, NewMirror};
+use anyhow::{anyhow, bail, Context};
+use diesel::prelude::*;
+use url::Url;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "mirrors",
+    about = "Manage the mirrors that crate downloads are redirected to.",
+    rename_all = "kebab-case"
+)]
+pub enum Command {
+    /// List the registered mirrors and their health
+    List,
+    /// Register a new mirror. Downloads are only redirected to it once the
+    /// `check_mirrors` background job verified it.
+    Add {
+        /// Unique name of the mirror
+        name: String,
+        /// URL that the paths of the crate files are appended to
+        base_url: String,
+        /// Code of the continent that the mirror serves, e.g. `EU`
+        region: String,
+    },
+    /// Enable a mirror, e.g. after it was disabled by the health checks
+    Enable { name: String },
+    /// Stop redirecting downloads to a mirror
+    Disable { name: String },
+}
+
+pub fn run(command: Command) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    match command {
+        Command::List => {
+            for mirror in Mirror::all(conn)? {
+                println!(
+                    "{} ({}, {}): enabled: {}, checked at: {:?}, verified at: {:?}",
+                    mirror.name,
+                    mirror.region,
+                    mirror.base_url,
+                    mirror.enabled,
+                    mirror.checked_at,
+                    mirror.verified_at,
+                );
+            }
+        }
+        Command::Add {
+            name,
+            base_url,
+            region,
+        } => {
+            let url = Url::parse(&base_url).context("Invalid base URL")?;
+            if url.scheme() != "https" {
+                bail!("The base URL of a mirror must use HTTPS");
+            }
+
+            let region = region.to_uppercase();
+            if !REGIONS.contains(&region.as_str()) {
+                bail!("Unknown region `{region}`, expected one of {REGIONS:?}");
+            }
+
+            let new_mirror = NewMirror {
+                name: &name,
+                base_url: &base_url,
+                region: &region,
+            };
+            conn.transaction(|conn| {
+                new_mirror.insert(conn)?;
+
+                NewAdminAuditEntry::by_cli(AdminAction::AddMirror)
+                    .details(json!({
+                        "mirror": name,
+                        "base_url": base_url,
+                        "region": region,
+                    }))
+                    .insert(conn)
+            })
+            .with_context(|| format!("Failed to register mirror `{name}`"))?;
+
+            println!("Registered mirror `{name}`");
+        }
+        Command::Enable { name } => {
+            set_enabled(conn, &name, true)?;
+            println!("Enabled mirror `{name}`");
+        }
+        Command::Disable { name } => {
+            set_enabled(conn, &name, false)?;
+            println!("Disabled mirror `{name}`");
+        }
+    }
+
+    Ok(())
+}
+
+fn set_enabled(conn: &mut diesel::PgConnection, name: &str, enabled: bool) -> anyhow::Result<()> {
+    let mirror = find_mirror(conn, name)?;
+
+    let action = if enabled {
+        AdminAction::EnableMirror
+    } else {
+        AdminAction::DisableMirror
+    };
+
+    conn.transaction(|conn| {
+        mirror.set_enabled(conn, enabled)?;
+
+        NewAdminAuditEntry::by_cli(action)
+            .details(json!({ "mirror": mirror.name }))
+            .insert(conn)
+    })?;
+
+    Ok(())
+}
+
+fn find_mirror(conn: &mut diesel::PgConnection, name: &str) -> anyhow::Result<Mirror> {
+    Mirror::find_by_name(conn, name)?.ok_or_else(|| anyhow!("Failed to find mirror `{name}`"))
+}
@@ -1,14 +1,20 @@
+pub mod allow_crate_name;
+pub mod crate_limits;
 pub mod default_versions;
 pub mod delete_crate;
 pub mod delete_version;
 pub mod dialoguer;
 pub mod enqueue_job;
+pub mod import_registry;
 pub mod migrate;
+pub mod mirrors;
 pub mod on_call;
 pub mod populate;
 pub mod render_readmes;
 pub mod test_pagerduty;
 pub mod transfer_crates;
 pub mod upload_index;
+pub mod user_limits;
+pub mod verify_db_deltas;
 pub mod verify_token;
 pub mod yank_version;
@@ -9,7 +9,7 @@ use std::{io::Read, path::Path, sync::Arc, thread};
 
 use crate::storage::Storage;
 use chrono::{NaiveDateTime, Utc};
-use crates_io_markdown::text_to_html;
+use crates_io_markdown::{text_to_html, SanitizerPolicy};
 use crates_io_tarball::{Manifest, StringOrBool};
 use diesel::prelude::*;
 use flate2::read::GzDecoder;
@@ -104,7 +104,7 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
 
         let mut tasks = Vec::with_capacity(page_size);
         for (version, krate_name) in versions {
-            Version::record_readme_rendering(version.id, conn)
+            Version::record_readme_rendering(version.id, SanitizerPolicy::DEFAULT_VERSION, conn)
                 .context("Couldn't record rendering time")?;
 
             let client = client.clone();
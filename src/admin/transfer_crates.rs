@@ -1,7 +1,10 @@
 use crate::{
     admin::dialoguer,
     db,
-    models::{Crate, OwnerKind, User},
+    models::{
+        AdminAction, Crate, NewAdminAuditEntry, NewCrateOwnerAction, NewRegistryEvent, OwnerKind,
+        RegistryEventKind, User,
+    },
     schema::{crate_owners, crates, users},
 };
 use std::process::exit;
@@ -60,17 +63,37 @@ fn transfer(opts: Opts, conn: &mut PgConnection) -> anyhow::Result<()> {
         .filter(crates::id.eq_any(crate_owners.select(crate_owners::crate_id)))
         .load(conn)?;
 
-    for krate in crates {
+    for krate in &crates {
         let owners = krate.owners(conn)?;
         if owners.len() != 1 {
             println!("warning: not exactly one owner for {}", krate.name);
         }
     }
 
+    let active_crate_ids: Vec<i32> = crate_owners
+        .filter(crate_owners::deleted.eq(false))
+        .select(crate_owners::crate_id)
+        .load(conn)?;
+
     diesel::update(crate_owners)
         .set(crate_owners::owner_id.eq(to.id))
         .execute(conn)?;
 
+    // The transfer is performed by the crates.io team, not by one of the users
+    for crate_id in active_crate_ids {
+        NewCrateOwnerAction::remove(crate_id, from.id, OwnerKind::User, None).insert(conn)?;
+        NewCrateOwnerAction::add(crate_id, to.id, OwnerKind::User, None).insert(conn)?;
+    }
+
+    for krate in &crates {
+        NewRegistryEvent::krate(RegistryEventKind::OwnerChange, &krate.name).insert(conn)?;
+
+        NewAdminAuditEntry::by_cli(AdminAction::TransferCrates)
+            .krate(&krate.name)
+            .details(json!({ "from": from.gh_login, "to": to.gh_login }))
+            .insert(conn)?;
+    }
+
     get_confirm("commit?");
 
     Ok(())
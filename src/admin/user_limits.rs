@@ -0,0 +1,79 @@
+use crate::admin::dialoguer;
+use crate::db;
+use crate::models::{AdminAction, NewAdminAuditEntry, User};
+use crate::schema::users;
+use anyhow::Context;
+use diesel::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "user-limits",
+    about = "Show or override the publish limits of a user.",
+    after_help = "Without any limit arguments, the current limits of the user are shown. \
+        The limits of a user apply to all crates that the user publishes, unless the crate \
+        has its own override."
+)]
+pub struct Opts {
+    /// GitHub login of the user
+    user: String,
+
+    /// Maximum number of dependencies of a version
+    #[arg(long)]
+    max_dependencies: Option<i16>,
+
+    /// Remove the override of the maximum number of dependencies
+    #[arg(long, conflicts_with = "max_dependencies")]
+    reset_max_dependencies: bool,
+
+    /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+    #[arg(short, long)]
+    yes: bool,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    let user = User::find_by_login(conn, &opts.user)
+        .with_context(|| format!("Failed to find user `{}`", opts.user))?;
+
+    println!("Current limits of `{}`:", user.gh_login);
+    println!("  max dependencies: {:?}", user.max_dependencies);
+
+    let max_dependencies = match (opts.max_dependencies, opts.reset_max_dependencies) {
+        (Some(max_dependencies), _) => Some(max_dependencies),
+        (None, true) => None,
+        (None, false) => return Ok(()),
+    };
+
+    if max_dependencies.is_some_and(|max_dependencies| max_dependencies <= 0) {
+        anyhow::bail!("limits must be positive numbers");
+    }
+
+    if !opts.yes {
+        let prompt = format!(
+            "Are you sure you want to set the max dependencies of `{}` to {max_dependencies:?}?",
+            user.gh_login
+        );
+        if !dialoguer::confirm(&prompt) {
+            return Ok(());
+        }
+    }
+
+    conn.transaction(|conn| {
+        diesel::update(&user)
+            .set(users::max_dependencies.eq(max_dependencies))
+            .execute(conn)?;
+
+        NewAdminAuditEntry::by_cli(AdminAction::ChangeUserLimits)
+            .details(json!({
+                "user": user.gh_login,
+                "max_dependencies": max_dependencies,
+            }))
+            .insert(conn)
+    })
+    .context("Failed to update user limits")?;
+
+    println!("Limits of `{}` updated", user.gh_login);
+
+    Ok(())
+}
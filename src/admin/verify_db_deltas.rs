@@ -0,0 +1,80 @@
+use crate::worker::jobs::dump_db::{read_delta, DeltaChain, RegistryState};
+use anyhow::bail;
+use std::path::PathBuf;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "verify-db-deltas",
+    about = "Verify the deltas of a database dump by replaying them.",
+    long_about = "Verify the deltas of a database dump by replaying them. Checks that the \
+        deltas listed in `deltas.json` form an unbroken chain and match their checksums, \
+        applies them to the crates and versions of the dump, and optionally compares \
+        the result with a later full dump."
+)]
+pub struct Opts {
+    /// Path of the extracted database dump that the deltas are based on.
+    #[arg(long)]
+    base: PathBuf,
+
+    /// Path of the downloaded `db-dumps/<timestamp>` directory of the dump,
+    /// containing the `deltas.json` file and the `deltas` directory.
+    #[arg(long)]
+    deltas: PathBuf,
+
+    /// Path of an extracted later database dump, which has to match the
+    /// result of replaying the deltas.
+    #[arg(long)]
+    compare: Option<PathBuf>,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let chain = DeltaChain::read(&opts.deltas)?;
+    chain.verify(&opts.deltas)?;
+    println!(
+        "Verified the checksums of {} deltas (events {}..={})",
+        chain.deltas.len(),
+        chain.last_event_id + 1,
+        chain
+            .deltas
+            .last()
+            .map(|delta| delta.to_event_id)
+            .unwrap_or(chain.last_event_id)
+    );
+
+    let mut state = RegistryState::from_dump(&opts.base)?;
+    println!("Loaded {} crates from the base dump", state.crates.len());
+
+    for entry in &chain.deltas {
+        let records = read_delta(&opts.deltas.join(&entry.path), entry)?;
+        for record in &records {
+            state.apply(record)?;
+        }
+        println!("Applied {} changes from `{}`", records.len(), entry.path);
+    }
+
+    let Some(compare) = opts.compare else {
+        return Ok(());
+    };
+
+    let expected = RegistryState::from_dump(&compare)?;
+    let mut mismatches = 0;
+    for (name, versions) in &expected.crates {
+        if state.crates.get(name) != Some(versions) {
+            println!("Mismatch: crate `{name}` differs after replaying the deltas");
+            mismatches += 1;
+        }
+    }
+    for name in state.crates.keys() {
+        if !expected.crates.contains_key(name) {
+            println!("Mismatch: crate `{name}` is missing in the later dump");
+            mismatches += 1;
+        }
+    }
+
+    if mismatches > 0 {
+        bail!("Found {mismatches} mismatches between the replayed deltas and the later dump");
+    }
+
+    println!("The replayed deltas match the later dump");
+    Ok(())
+}
@@ -1,10 +1,7 @@
 use crate::admin::dialoguer;
 use crate::db;
-use crate::models::{Crate, Version};
+use crate::models::{set_yanked, AdminAction, Crate, NewAdminAuditEntry, Version, YankActor};
 use crate::schema::versions;
-use crate::worker::jobs;
-use crate::worker::jobs::UpdateDefaultVersion;
-use crates_io_worker::BackgroundJob;
 use diesel::prelude::*;
 
 #[derive(clap::Parser, Debug)]
@@ -17,6 +14,9 @@ pub struct Opts {
     crate_name: String,
     /// Version number that should be deleted
     version: String,
+    /// Reason for the yank, which is shown in the yank history of the version
+    #[arg(long)]
+    reason: Option<String>,
     /// Don't ask for confirmation: yes, we are sure. Best for scripting.
     #[arg(short, long)]
     yes: bool,
@@ -32,6 +32,7 @@ fn yank(opts: Opts, conn: &mut PgConnection) -> anyhow::Result<()> {
     let Opts {
         crate_name,
         version,
+        reason,
         yes,
     } = opts;
     let krate: Crate = Crate::by_name(&crate_name).first(conn)?;
@@ -55,13 +56,21 @@ fn yank(opts: Opts, conn: &mut PgConnection) -> anyhow::Result<()> {
     }
 
     println!("yanking version {} ({})", v.num, v.id);
-    diesel::update(&v)
-        .set(versions::yanked.eq(true))
-        .execute(conn)?;
+    let reason = reason.as_deref();
+    set_yanked(
+        conn,
+        krate.id,
+        &krate.name,
+        &[v.id],
+        true,
+        YankActor::System,
+        reason,
+    )?;
 
-    jobs::enqueue_sync_to_index(&krate.name, conn)?;
-
-    UpdateDefaultVersion::new(krate.id).enqueue(conn)?;
+    NewAdminAuditEntry::by_cli(AdminAction::YankVersion)
+        .krate(&krate.name)
+        .details(json!({ "version": v.num, "reason": reason }))
+        .insert(conn)?;
 
     Ok(())
 }
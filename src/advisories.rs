@@ -0,0 +1,396 @@
+//! Import of the security advisories of the [RustSec advisory database].
+//!
+//! If the `ADVISORY_DB_URL` environment variable is set, the `sync_advisories`
+//! background job downloads the gzipped tarball of the advisory database,
+//! and stores the advisories in the `advisories` table. The
+//! `/api/v1/advisories/:advisory_id` endpoint then lists the versions that
+//! are affected by an advisory, the versions that fix it, and the most
+//! downloaded dependents that can not update to a fixed version without
+//! changing their version requirement.
+//!
+//! The [AdvisorySource] trait is used to abstract away the HTTP client for
+//! testing purposes.
+//!
+//! [RustSec advisory database]: https://github.com/rustsec/advisory-db
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use flate2::read::GzDecoder;
+use mockall::automock;
+use semver::{Version, VersionReq};
+use std::io::Read;
+use std::path::Path;
+
+/// The `User-Agent` header that is sent when downloading the advisory
+/// database.
+const USER_AGENT: &str = "crates.io advisory sync (https://crates.io)";
+
+/// The maximum duration of downloading the advisory database.
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The maximum size of the gzipped tarball of the advisory database.
+const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The maximum size of a single advisory file. Larger files are skipped.
+const MAX_ADVISORY_SIZE: u64 = 1024 * 1024;
+
+/// An advisory of the advisory database, as parsed from its Markdown file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedAdvisory {
+    pub id: String,
+    pub crate_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub withdrawn: Option<NaiveDate>,
+    pub aliases: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+}
+
+/// The TOML front matter of an advisory file.
+#[derive(Deserialize)]
+struct FrontMatter {
+    advisory: AdvisoryMetadata,
+    #[serde(default)]
+    versions: VersionsMetadata,
+}
+
+#[derive(Deserialize)]
+struct AdvisoryMetadata {
+    id: String,
+    package: String,
+    date: NaiveDate,
+    url: Option<String>,
+    withdrawn: Option<NaiveDate>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct VersionsMetadata {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// Parses an advisory file, which starts with a fenced TOML code block with
+/// the metadata, followed by a Markdown heading with the title.
+pub fn parse_advisory(content: &str) -> anyhow::Result<ParsedAdvisory> {
+    let front_matter = content
+        .trim_start()
+        .strip_prefix("```toml")
+        .ok_or_else(|| anyhow!("the advisory does not start with a TOML code block"))?;
+
+    let (front_matter, description) = front_matter
+        .split_once("\n```")
+        .ok_or_else(|| anyhow!("the TOML code block of the advisory is not closed"))?;
+
+    let FrontMatter { advisory, versions } =
+        toml::from_str(front_matter).context("invalid front matter")?;
+
+    let title = description
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .ok_or_else(|| anyhow!("the advisory does not have a title"))?;
+
+    // Advisories with requirements that can not be evaluated are skipped, so
+    // that no versions are reported as affected by mistake.
+    VersionMatcher::new(&versions.patched, &versions.unaffected)?;
+
+    Ok(ParsedAdvisory {
+        id: advisory.id,
+        crate_name: advisory.package,
+        title,
+        url: advisory.url,
+        date: advisory.date,
+        withdrawn: advisory.withdrawn,
+        aliases: advisory.aliases,
+        patched: versions.patched,
+        unaffected: versions.unaffected,
+    })
+}
+
+/// Parses all advisories of crates in the gzipped tarball of the advisory
+/// database. Invalid advisories are skipped.
+pub fn parse_archive(bytes: &[u8]) -> anyhow::Result<Vec<ParsedAdvisory>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+
+    let mut advisories = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !is_crate_advisory(&path) {
+            continue;
+        }
+
+        if entry.size() > MAX_ADVISORY_SIZE {
+            warn!(path = %path.display(), "Skipping advisory, since the file is too large");
+            continue;
+        }
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+
+        match parse_advisory(&content) {
+            Ok(advisory) => advisories.push(advisory),
+            Err(error) => warn!(path = %path.display(), "Skipping invalid advisory: {error:#}"),
+        }
+    }
+
+    Ok(advisories)
+}
+
+/// Returns whether the path of the archive entry is an advisory of a crate,
+/// e.g. `advisory-db-main/crates/foo/RUSTSEC-2024-0001.md`. Advisories of
+/// the Rust toolchain are stored in the `rust` directory instead.
+fn is_crate_advisory(path: &Path) -> bool {
+    let components = path
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>();
+
+    matches!(
+        components.as_deref(),
+        Some([_, "crates", _, file]) if file.starts_with("RUSTSEC-") && file.ends_with(".md")
+    )
+}
+
+/// Determines whether versions are affected by an advisory. Versions are
+/// affected unless they match one of the `patched` or `unaffected`
+/// requirements of the advisory.
+#[derive(Debug)]
+pub struct VersionMatcher {
+    patched: Vec<VersionReq>,
+    unaffected: Vec<VersionReq>,
+}
+
+impl VersionMatcher {
+    pub fn new(patched: &[String], unaffected: &[String]) -> anyhow::Result<Self> {
+        let parse = |reqs: &[String]| {
+            reqs.iter()
+                .map(|req| {
+                    VersionReq::parse(req).with_context(|| format!("invalid requirement `{req}`"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            patched: parse(patched)?,
+            unaffected: parse(unaffected)?,
+        })
+    }
+
+    pub fn is_affected(&self, version: &Version) -> bool {
+        !self
+            .patched
+            .iter()
+            .chain(&self.unaffected)
+            .any(|req| req.matches(version))
+    }
+}
+
+/// A contiguous range of published versions that are affected by an
+/// advisory.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AffectedRange {
+    /// The lowest affected version of the range.
+    pub first: Version,
+    /// The highest affected version of the range.
+    pub last: Version,
+    /// The lowest version above the range that is not affected and not
+    /// yanked, which users of the range can update to, if there is one.
+    pub first_patched: Option<Version>,
+}
+
+/// Groups the published versions that are affected by the advisory into
+/// contiguous ranges, ordered by version.
+///
+/// `versions` contains the published versions of the crate, and whether
+/// they are yanked. Yanked versions can still be affected, e.g. if they are
+/// locked in a lockfile, but they are never suggested as a fix.
+pub fn affected_ranges(
+    matcher: &VersionMatcher,
+    versions: &[(Version, bool)],
+) -> Vec<AffectedRange> {
+    let mut versions = versions.iter().collect::<Vec<_>>();
+    versions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut ranges: Vec<AffectedRange> = Vec::new();
+    let mut in_range = false;
+    for (version, yanked) in versions {
+        if matcher.is_affected(version) {
+            match ranges.last_mut() {
+                Some(range) if in_range => range.last = version.clone(),
+                _ => ranges.push(AffectedRange {
+                    first: version.clone(),
+                    last: version.clone(),
+                    first_patched: None,
+                }),
+            }
+            in_range = true;
+        } else {
+            in_range = false;
+
+            // The following versions of an unpatched range are patched too,
+            // unless they are yanked.
+            for range in ranges.iter_mut().rev() {
+                if range.first_patched.is_some() {
+                    break;
+                }
+                if !yanked {
+                    range.first_patched = Some(version.clone());
+                }
+            }
+        }
+    }
+
+    ranges
+}
+
+#[automock]
+#[async_trait]
+pub trait AdvisorySource {
+    /// Fetches all advisories of crates from the advisory database.
+    async fn fetch(&self) -> anyhow::Result<Vec<ParsedAdvisory>>;
+}
+
+/// Downloads the advisory database as a gzipped tarball via HTTP.
+pub struct HttpAdvisorySource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpAdvisorySource {
+    pub fn new(url: String) -> anyhow::Result<Self> {
+        // The tarball is decompressed while parsing it, so the HTTP client
+        // must not decompress it if it is served with a `Content-Encoding`.
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(FETCH_TIMEOUT)
+            .no_gzip()
+            .build()?;
+
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl AdvisorySource for HttpAdvisorySource {
+    #[instrument(skip(self), fields(url = %self.url))]
+    async fn fetch(&self) -> anyhow::Result<Vec<ParsedAdvisory>> {
+        let response = self.client.get(&self.url).send().await?;
+        let response = response.error_for_status()?;
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_ARCHIVE_SIZE)
+        {
+            bail!("the advisory database is larger than {MAX_ARCHIVE_SIZE} bytes");
+        }
+
+        let bytes = response.bytes().await?;
+        if bytes.len() as u64 > MAX_ARCHIVE_SIZE {
+            bail!("the advisory database is larger than {MAX_ARCHIVE_SIZE} bytes");
+        }
+
+        tokio::task::spawn_blocking(move || parse_archive(&bytes)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2024-0001"
+package = "foo"
+date = "2024-01-15"
+url = "https://example.com/advisory"
+aliases = ["CVE-2024-1234"]
+
+[versions]
+patched = [">= 1.2.3", "^1.1.5"]
+unaffected = ["< 1.0.0"]
+```
+
+# Memory corruption in `Foo::bar`
+
+Calling `Foo::bar` with an empty slice corrupts memory.
+"#;
+
+    fn version(num: &str) -> Version {
+        Version::parse(num).unwrap()
+    }
+
+    #[test]
+    fn parses_advisories() {
+        let advisory = assert_ok!(parse_advisory(ADVISORY));
+        assert_eq!(advisory.id, "RUSTSEC-2024-0001");
+        assert_eq!(advisory.crate_name, "foo");
+        assert_eq!(advisory.title, "Memory corruption in `Foo::bar`");
+        assert_eq!(advisory.date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(advisory.withdrawn, None);
+        assert_eq!(advisory.aliases, vec!["CVE-2024-1234"]);
+        assert_eq!(advisory.patched, vec![">= 1.2.3", "^1.1.5"]);
+        assert_eq!(advisory.unaffected, vec!["< 1.0.0"]);
+
+        assert_err!(parse_advisory("# Missing front matter"));
+        assert_err!(parse_advisory(
+            &ADVISORY.replace(">= 1.2.3", "not a requirement")
+        ));
+        assert_err!(parse_advisory(&ADVISORY.replace("# Memory", "Memory")));
+    }
+
+    #[test]
+    fn only_crate_advisories_are_parsed() {
+        let path = |path: &str| is_crate_advisory(Path::new(path));
+        assert!(path("advisory-db-main/crates/foo/RUSTSEC-2024-0001.md"));
+        assert!(!path("advisory-db-main/rust/std/RUSTSEC-2024-0002.md"));
+        assert!(!path("advisory-db-main/crates/foo/README.md"));
+        assert!(!path("advisory-db-main/README.md"));
+    }
+
+    #[test]
+    fn groups_affected_versions_into_ranges() {
+        let advisory = parse_advisory(ADVISORY).unwrap();
+        let matcher = VersionMatcher::new(&advisory.patched, &advisory.unaffected).unwrap();
+
+        assert!(!matcher.is_affected(&version("0.9.0")));
+        assert!(matcher.is_affected(&version("1.0.0")));
+        assert!(!matcher.is_affected(&version("1.1.5")));
+        assert!(matcher.is_affected(&version("1.2.0")));
+        assert!(!matcher.is_affected(&version("1.2.3")));
+
+        let versions = [
+            (version("1.2.3"), true),
+            (version("0.9.0"), false),
+            (version("1.0.0"), false),
+            (version("1.1.0"), false),
+            (version("1.1.5"), false),
+            (version("1.2.0"), false),
+            (version("1.2.4"), false),
+        ];
+
+        assert_eq!(
+            affected_ranges(&matcher, &versions),
+            vec![
+                AffectedRange {
+                    first: version("1.0.0"),
+                    last: version("1.1.0"),
+                    first_patched: Some(version("1.1.5")),
+                },
+                AffectedRange {
+                    first: version("1.2.0"),
+                    last: version("1.2.0"),
+                    first_patched: Some(version("1.2.4")),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Antivirus scanning of uploaded crate tarballs.
+//!
+//! The [VirusScanner] trait is used to abstract away the actual scanner for
+//! testing purposes. The [ClamAv] struct is the implementation of the trait
+//! that streams the tarballs to a [clamd](https://docs.clamav.net/manual/Usage/Scanning.html#clamd)
+//! daemon.
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use hyper::body::Bytes;
+use mockall::automock;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UnixStream};
+
+/// The maximum size of the chunks that are sent to clamd.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The maximum duration of a single scan, including connecting to clamd.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanResult {
+    Clean,
+    /// The tarball matched the signature with the given name.
+    Infected(String),
+}
+
+#[automock]
+#[async_trait]
+pub trait VirusScanner {
+    async fn scan(&self, bytes: Bytes) -> anyhow::Result<ScanResult>;
+}
+
+/// Scans files via the `INSTREAM` command of a clamd daemon.
+///
+/// The address is either a `host:port` pair for a TCP connection, or the
+/// path of a Unix socket prefixed with `unix:`.
+pub struct ClamAv {
+    address: String,
+}
+
+impl ClamAv {
+    pub fn new(address: impl Into<String>) -> Self {
+        let address = address.into();
+        Self { address }
+    }
+
+    async fn scan_stream<S>(&self, mut stream: S, bytes: &[u8]) -> anyhow::Result<ScanResult>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in bytes.chunks(CHUNK_SIZE) {
+            stream
+                .write_all(&(chunk.len() as u32).to_be_bytes())
+                .await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        stream.flush().await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+
+        parse_response(&String::from_utf8_lossy(&response))
+    }
+}
+
+#[async_trait]
+impl VirusScanner for ClamAv {
+    #[instrument(skip_all, fields(address = %self.address, size = bytes.len()))]
+    async fn scan(&self, bytes: Bytes) -> anyhow::Result<ScanResult> {
+        let scan = async {
+            match self.address.strip_prefix("unix:") {
+                Some(path) => {
+                    let stream = UnixStream::connect(path).await?;
+                    self.scan_stream(stream, &bytes).await
+                }
+                None => {
+                    let stream = TcpStream::connect(&self.address).await?;
+                    self.scan_stream(stream, &bytes).await
+                }
+            }
+        };
+
+        tokio::time::timeout(SCAN_TIMEOUT, scan)
+            .await
+            .context("Timed out while waiting for clamd")?
+    }
+}
+
+/// Parses the response of clamd to a single `INSTREAM` command, e.g.
+/// `stream: OK` or `stream: Eicar-Signature FOUND`.
+fn parse_response(response: &str) -> anyhow::Result<ScanResult> {
+    let response = response.trim_end_matches(['\0', '\n']);
+    let result = response.strip_prefix("stream: ").unwrap_or(response);
+
+    if result == "OK" {
+        return Ok(ScanResult::Clean);
+    }
+
+    if let Some(signature) = result.strip_suffix(" FOUND") {
+        return Ok(ScanResult::Infected(signature.to_string()));
+    }
+
+    Err(anyhow!("Unexpected response from clamd: {response}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(parse_response("stream: OK\0").unwrap(), ScanResult::Clean);
+
+        let result = parse_response("stream: Eicar-Signature FOUND\0").unwrap();
+        assert_eq!(result, ScanResult::Infected("Eicar-Signature".into()));
+
+        let error = parse_response("INSTREAM size limit exceeded. ERROR\0").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unexpected response from clamd: INSTREAM size limit exceeded. ERROR"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_stream() {
+        let (client, mut server) = tokio::io::duplex(1024);
+
+        let server = tokio::spawn(async move {
+            let mut command = [0; 10];
+            server.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+
+            let mut content = Vec::new();
+            loop {
+                let length = server.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    break;
+                }
+
+                let mut chunk = vec![0; length];
+                server.read_exact(&mut chunk).await.unwrap();
+                content.extend(chunk);
+            }
+            assert_eq!(content, b"hello world");
+
+            server.write_all(b"stream: OK\0").await.unwrap();
+        });
+
+        let scanner = ClamAv::new("localhost:3310");
+        let result = scanner.scan_stream(client, b"hello world").await.unwrap();
+        assert_eq!(result, ScanResult::Clean);
+
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! Quotas for the number of requests that users and API tokens can send to
+//! expensive read-only API endpoints.
+//!
+//! In contrast to the [`crate::rate_limiter`], which limits how often certain
+//! actions can be performed, these quotas count all requests to the affected
+//! endpoints within a fixed time window. Anonymous requests are not counted.
+//!
+//! The quotas currently apply to the crate search (`GET /api/v1/crates`) and
+//! to the reverse dependencies endpoint. Users can see their consumption via
+//! `GET /api/v1/me/usage`.
+//!
+//! Requests with service tokens are only counted against the elevated quota
+//! of the service token, and not against the quota of its user.
+
+use crate::app::AppState;
+use crate::auth::{authenticate_optional, Authentication};
+use crate::middleware::session::RequestSession;
+use crate::models::ApiToken;
+use crate::schema::{api_token_usage, user_api_usage};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::util::errors::{ApiQuotaExceeded, AppResult};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::dsl::case_when;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use http::header;
+use http::request::Parts;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const DEFAULT_WINDOW_SECONDS: u64 = 60 * 60; // 1 hour
+pub const DEFAULT_USER_REQUESTS: i32 = 10_000;
+pub const DEFAULT_TOKEN_REQUESTS: i32 = 5_000;
+pub const DEFAULT_SERVICE_TOKEN_REQUESTS: i32 = 100_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ApiQuotaConfig {
+    /// Length of the fixed time windows that the quotas apply to.
+    pub window: Duration,
+    /// Maximum number of requests of a user within a window, regardless of
+    /// whether they were authenticated via cookie or via API token.
+    pub user_requests: i32,
+    /// Maximum number of requests of a single API token within a window.
+    pub token_requests: i32,
+    /// Maximum number of requests of a single service token within a window.
+    pub service_token_requests: i32,
+}
+
+impl Default for ApiQuotaConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(DEFAULT_WINDOW_SECONDS),
+            user_requests: DEFAULT_USER_REQUESTS,
+            token_requests: DEFAULT_TOKEN_REQUESTS,
+            service_token_requests: DEFAULT_SERVICE_TOKEN_REQUESTS,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ApiQuota {
+    config: ApiQuotaConfig,
+}
+
+impl ApiQuota {
+    pub fn new(config: ApiQuotaConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ApiQuotaConfig {
+        &self.config
+    }
+
+    /// Returns the start of the quota window that contains `now`.
+    ///
+    /// Windows are aligned to the Unix epoch, so that all users and tokens
+    /// share the same windows.
+    pub fn window_start(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let window = self.config.window.as_secs().max(1) as i64;
+        let timestamp = now.and_utc().timestamp();
+        let start = timestamp - timestamp.rem_euclid(window);
+        DateTime::from_timestamp(start, 0)
+            .expect("window start is out of range")
+            .naive_utc()
+    }
+
+    /// Returns the end of the quota window that starts at `window_start`.
+    pub fn window_end(&self, window_start: NaiveDateTime) -> NaiveDateTime {
+        let window = self.config.window.as_secs().max(1) as i64;
+        window_start + chrono::Duration::seconds(window)
+    }
+
+    /// Returns the maximum number of requests of the given API token within a
+    /// window.
+    pub fn token_limit(&self, token: &ApiToken) -> i32 {
+        match token.is_service_token() {
+            true => self.config.service_token_requests,
+            false => self.config.token_requests,
+        }
+    }
+
+    /// Counts a request against the quota of the authenticated user, and
+    /// against the quota of the API token if one was used.
+    ///
+    /// Requests with service tokens are only counted against the quota of
+    /// the service token.
+    ///
+    /// Returns an error if any of the quotas has been exceeded.
+    pub fn record_request(
+        &self,
+        auth: &Authentication,
+        now: NaiveDateTime,
+        conn: &mut impl Conn,
+    ) -> AppResult<()> {
+        let window_start = self.window_start(now);
+
+        let mut exceeded = false;
+
+        let is_service_token = auth.api_token().is_some_and(ApiToken::is_service_token);
+        if !is_service_token {
+            let user_requests: i32 = diesel::insert_into(user_api_usage::table)
+                .values((
+                    user_api_usage::user_id.eq(auth.user_id()),
+                    user_api_usage::window_start.eq(window_start),
+                    user_api_usage::requests.eq(1),
+                ))
+                .on_conflict(user_api_usage::user_id)
+                .do_update()
+                .set((
+                    user_api_usage::requests.eq(case_when(
+                        user_api_usage::window_start.eq(window_start),
+                        user_api_usage::requests + 1,
+                    )
+                    .otherwise(1)),
+                    user_api_usage::window_start.eq(window_start),
+                ))
+                .returning(user_api_usage::requests)
+                .get_result(conn)?;
+
+            exceeded |= user_requests > self.config.user_requests;
+        }
+
+        if let Some(api_token) = auth.api_token() {
+            let token_requests: i32 = diesel::insert_into(api_token_usage::table)
+                .values((
+                    api_token_usage::api_token_id.eq(api_token.id),
+                    api_token_usage::window_start.eq(window_start),
+                    api_token_usage::requests.eq(1),
+                ))
+                .on_conflict(api_token_usage::api_token_id)
+                .do_update()
+                .set((
+                    api_token_usage::requests.eq(case_when(
+                        api_token_usage::window_start.eq(window_start),
+                        api_token_usage::requests + 1,
+                    )
+                    .otherwise(1)),
+                    api_token_usage::window_start.eq(window_start),
+                ))
+                .returning(api_token_usage::requests)
+                .get_result(conn)?;
+
+            exceeded |= token_requests > self.token_limit(api_token);
+        }
+
+        if exceeded {
+            let retry_after = self.window_end(window_start);
+            return Err(Box::new(ApiQuotaExceeded { retry_after }));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of requests that the user has sent within the
+    /// quota window that contains `now`.
+    pub fn user_usage(
+        &self,
+        user_id: i32,
+        now: NaiveDateTime,
+        conn: &mut impl Conn,
+    ) -> QueryResult<i32> {
+        let requests = user_api_usage::table
+            .find(user_id)
+            .filter(user_api_usage::window_start.eq(self.window_start(now)))
+            .select(user_api_usage::requests)
+            .first(conn)
+            .optional()?;
+
+        Ok(requests.unwrap_or(0))
+    }
+
+    /// Returns the number of requests that have been sent with the given API
+    /// tokens within the quota window that contains `now`.
+    ///
+    /// Tokens without any requests in the window are not included.
+    pub fn token_usage(
+        &self,
+        api_token_ids: &[i32],
+        now: NaiveDateTime,
+        conn: &mut impl Conn,
+    ) -> QueryResult<HashMap<i32, i32>> {
+        let usage = api_token_usage::table
+            .filter(api_token_usage::api_token_id.eq_any(api_token_ids))
+            .filter(api_token_usage::window_start.eq(self.window_start(now)))
+            .select((api_token_usage::api_token_id, api_token_usage::requests))
+            .load::<(i32, i32)>(conn)?;
+
+        Ok(usage.into_iter().collect())
+    }
+}
+
+/// Counts the request against the API quotas of the authenticated user and
+/// API token.
+///
+/// Anonymous requests are not counted, and neither are requests while the
+/// database is in read-only mode.
+pub async fn check_api_quota(app: &AppState, req: &Parts) -> AppResult<()> {
+    let has_credentials =
+        req.headers.contains_key(header::AUTHORIZATION) || req.session().get("user_id").is_some();
+
+    if !has_credentials || app.config.db.are_all_read_only() {
+        return Ok(());
+    }
+
+    let app = app.clone();
+    let req = req.clone();
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let Some(auth) = authenticate_optional(&req, conn) else {
+            return Ok(());
+        };
+
+        app.api_quota
+            .record_request(&auth, Utc::now().naive_utc(), conn)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn datetime(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 8, 26)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn window_boundaries() {
+        let quota = ApiQuota::new(ApiQuotaConfig::default());
+
+        let start = quota.window_start(datetime(10, 42));
+        assert_eq!(start, datetime(10, 0));
+        assert_eq!(quota.window_end(start), datetime(11, 0));
+
+        assert_eq!(quota.window_start(datetime(11, 0)), datetime(11, 0));
+
+        let quota = ApiQuota::new(ApiQuotaConfig {
+            window: Duration::from_secs(15 * 60),
+            ..Default::default()
+        });
+
+        assert_eq!(quota.window_start(datetime(10, 42)), datetime(10, 30));
+    }
+}
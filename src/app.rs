@@ -5,10 +5,21 @@ use crate::db::{connection_url, make_manager_config, ConnectionConfig};
 use std::ops::Deref;
 use std::sync::Arc;
 
+use crate::api_quota::ApiQuota;
+use crate::avatars::AvatarRefreshes;
+use crate::challenge::Challenge;
+use crate::changes_feed::ChangesFeed;
 use crate::email::Emails;
+use crate::load_shedding::LoadShedder;
 use crate::metrics::{InstanceMetrics, ServiceMetrics};
+use crate::private_crates::PrivateCrates;
+use crate::publish_queue::PublishQueue;
 use crate::rate_limiter::RateLimiter;
+use crate::shutdown::Shutdown;
+use crate::spam::{Heuristics, SpamClassifier};
 use crate::storage::Storage;
+use crate::upstream::{FetchLocks, HttpUpstreamRegistry, UpstreamRegistry};
+use crate::user_agent_throttle::UserAgentThrottle;
 use axum::extract::{FromRef, FromRequestParts, State};
 use crates_io_github::GitHubClient;
 use deadpool_diesel::Runtime;
@@ -54,6 +65,42 @@ pub struct App {
 
     /// Rate limit select actions.
     pub rate_limiter: RateLimiter,
+
+    /// Request quotas for expensive read-only endpoints.
+    pub api_quota: ApiQuota,
+
+    /// Throttling of anonymous crawlers based on their `User-Agent` header.
+    pub user_agent_throttle: UserAgentThrottle,
+
+    /// Challenges for abusive routes, see `src/challenge.rs`.
+    pub challenge: Challenge,
+
+    /// Shedding of low priority traffic, see `src/load_shedding.rs`.
+    pub load_shedder: LoadShedder,
+
+    /// Fair scheduling of concurrent publishes, see `src/publish_queue.rs`.
+    pub publish_queue: PublishQueue,
+
+    /// Draining of in-flight requests on shutdown, see `src/shutdown.rs`.
+    pub shutdown: Shutdown,
+
+    /// Spam classification of published crates, see `src/spam.rs`.
+    pub spam_classifier: Box<dyn SpamClassifier>,
+
+    /// The upstream registry of a pull-through cache, see `src/upstream.rs`.
+    pub upstream: Option<Arc<dyn UpstreamRegistry + Send + Sync>>,
+
+    /// Deduplicates concurrent fetches from the upstream registry.
+    pub upstream_fetches: FetchLocks,
+
+    /// Cached names of private crates, see `src/private_crates.rs`.
+    pub private_crates: PrivateCrates,
+
+    /// Shared polling of the changes feed, see `src/changes_feed.rs`.
+    pub changes_feed: ChangesFeed,
+
+    /// Rate limits the refreshes of avatars, see `src/avatars.rs`.
+    pub avatar_refreshes: AvatarRefreshes,
 }
 
 impl App {
@@ -125,6 +172,13 @@ impl App {
             None
         };
 
+        let upstream = config.upstream.clone().map(|config| {
+            let registry = HttpUpstreamRegistry::new(config)
+                .expect("could not initialize upstream registry client");
+            let registry: Arc<dyn UpstreamRegistry + Send + Sync> = Arc::new(registry);
+            registry
+        });
+
         App {
             primary_database,
             replica_database,
@@ -135,6 +189,18 @@ impl App {
             service_metrics: ServiceMetrics::new().expect("could not initialize service metrics"),
             instance_metrics,
             rate_limiter: RateLimiter::new(config.rate_limiter.clone()),
+            api_quota: ApiQuota::new(config.api_quota),
+            user_agent_throttle: UserAgentThrottle::new(config.user_agent_throttle),
+            challenge: Challenge::new(config.challenge.clone()),
+            load_shedder: LoadShedder::new(config.load_shedding.clone()),
+            publish_queue: PublishQueue::new(config.publish_queue),
+            shutdown: Shutdown::default(),
+            spam_classifier: Box::new(Heuristics::new(&config.spam)),
+            upstream,
+            upstream_fetches: FetchLocks::default(),
+            private_crates: PrivateCrates::default(),
+            changes_feed: ChangesFeed::default(),
+            avatar_refreshes: AvatarRefreshes::default(),
             config: Arc::new(config),
         }
     }
@@ -1,22 +1,30 @@
 use crate::controllers;
 use crate::controllers::util::RequestPartsExt;
+use crate::middleware::app::RequestApp;
+use crate::middleware::impersonation::Impersonation;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::registry::RequestRegistry;
 use crate::middleware::session::RequestSession;
 use crate::models::token::{CrateScope, EndpointScope};
-use crate::models::{ApiToken, User};
+use crate::models::{ApiToken, Crate, OwnerKind, PersistentSession, User, SESSION_TOKEN_KEY};
+use crate::schema::{api_tokens, crate_owners, crates};
 use crate::util::diesel::Conn;
 use crate::util::errors::{
     account_locked, forbidden, internal, AppResult, InsecurelyGeneratedTokenRevoked,
 };
 use crate::util::token::HashedToken;
 use chrono::Utc;
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
 use http::header;
 
 #[derive(Debug, Clone)]
 pub struct AuthCheck {
     allow_token: bool,
+    allow_service_token: bool,
     endpoint_scope: Option<EndpointScope>,
     crate_name: Option<String>,
+    skip_scope_checks: bool,
 }
 
 impl AuthCheck {
@@ -26,8 +34,10 @@ impl AuthCheck {
     pub fn default() -> Self {
         Self {
             allow_token: true,
+            allow_service_token: false,
             endpoint_scope: None,
             crate_name: None,
+            skip_scope_checks: false,
         }
     }
 
@@ -35,24 +45,73 @@ impl AuthCheck {
     pub fn only_cookie() -> Self {
         Self {
             allow_token: false,
+            allow_service_token: false,
             endpoint_scope: None,
             crate_name: None,
+            skip_scope_checks: false,
+        }
+    }
+
+    /// Allows service tokens, which are otherwise rejected by all endpoints
+    /// that require authentication.
+    ///
+    /// Only endpoints that do not perform any actions (e.g. the API quota
+    /// usage) should allow service tokens.
+    pub fn allow_service_token(&self) -> Self {
+        Self {
+            allow_service_token: true,
+            ..self.clone()
         }
     }
 
     pub fn with_endpoint_scope(&self, endpoint_scope: EndpointScope) -> Self {
         Self {
             allow_token: self.allow_token,
+            allow_service_token: self.allow_service_token,
             endpoint_scope: Some(endpoint_scope),
             crate_name: self.crate_name.clone(),
+            skip_scope_checks: self.skip_scope_checks,
         }
     }
 
     pub fn for_crate(&self, crate_name: &str) -> Self {
         Self {
             allow_token: self.allow_token,
+            allow_service_token: self.allow_service_token,
             endpoint_scope: self.endpoint_scope,
             crate_name: Some(crate_name.to_string()),
+            skip_scope_checks: self.skip_scope_checks,
+        }
+    }
+
+    /// Accepts tokens regardless of their endpoint scopes, crate scopes and
+    /// owning team.
+    ///
+    /// This is only meant for endpoints that report what the token may do
+    /// (see [`AuthCheck::permits`]) instead of performing an action.
+    pub fn skip_scope_checks(&self) -> Self {
+        Self {
+            skip_scope_checks: true,
+            ..self.clone()
+        }
+    }
+
+    /// Checks whether the token passes the scope checks of this endpoint,
+    /// without authenticating a request.
+    ///
+    /// Service tokens are never permitted, since they can only be used for
+    /// read-only requests.
+    pub fn permits(&self, token: &ApiToken, conn: &mut impl Conn) -> QueryResult<bool> {
+        if token.is_service_token()
+            || !self.endpoint_scope_matches(token.endpoint_scopes.as_ref())
+            || !self.crate_scope_matches(token.crate_scopes.as_ref())
+        {
+            return Ok(false);
+        }
+
+        match token.team_id {
+            Some(team_id) => self.team_owns_crate(team_id, conn),
+            None => Ok(true),
         }
     }
 
@@ -75,6 +134,23 @@ impl AuthCheck {
                 ));
             }
 
+            if token.is_service_token() {
+                if !self.allow_service_token {
+                    let error_message = "Service tokens are not allowed for this API";
+                    request.request_log().add("cause", error_message);
+
+                    return Err(forbidden(
+                        "service tokens can only be used for read-only requests",
+                    ));
+                }
+
+                return Ok(auth);
+            }
+
+            if self.skip_scope_checks {
+                return Ok(auth);
+            }
+
             if !self.endpoint_scope_matches(token.endpoint_scopes.as_ref()) {
                 let error_message = "Endpoint scope mismatch";
                 request.request_log().add("cause", error_message);
@@ -92,11 +168,54 @@ impl AuthCheck {
                     "this token does not have the required permissions to perform this action",
                 ));
             }
+
+            // Tokens can only be used with the registry they were created for
+            let token_registry: String = api_tokens::table
+                .find(token.id)
+                .select(api_tokens::registry)
+                .first(conn)?;
+
+            if token_registry != request.registry() {
+                let error_message = "Registry mismatch";
+                request.request_log().add("cause", error_message);
+
+                return Err(forbidden("this token can not be used with this registry"));
+            }
+
+            if let Some(team_id) = token.team_id {
+                if !self.team_owns_crate(team_id, conn)? {
+                    let error_message = "Team token used for a crate not owned by the team";
+                    request.request_log().add("cause", error_message);
+
+                    return Err(forbidden(
+                        "team tokens can only be used for crates owned by the team",
+                    ));
+                }
+            }
         }
 
         Ok(auth)
     }
 
+    /// Checks whether the crate that the endpoint deals with is owned by the
+    /// given team. Team tokens can not be used for endpoints that do not deal
+    /// with a specific crate.
+    fn team_owns_crate(&self, team_id: i32, conn: &mut impl Conn) -> QueryResult<bool> {
+        let Some(crate_name) = &self.crate_name else {
+            return Ok(false);
+        };
+
+        select(exists(
+            crate_owners::table
+                .inner_join(crates::table)
+                .filter(Crate::with_name(crate_name))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::Team))
+                .filter(crate_owners::owner_id.eq(team_id))
+                .filter(crate_owners::deleted.eq(false)),
+        ))
+        .get_result(conn)
+    }
+
     fn endpoint_scope_matches(&self, token_scopes: Option<&Vec<EndpointScope>>) -> bool {
         match (&token_scopes, &self.endpoint_scope) {
             // The token is a legacy token.
@@ -191,6 +310,25 @@ fn authenticate_via_cookie<T: RequestPartsExt>(
 
     ensure_not_locked(&user)?;
 
+    // The persistent session always belongs to the user that signed in,
+    // which is the admin for impersonation sessions.
+    let session_user_id = Impersonation::from_session(req.session())
+        .map(|impersonation| impersonation.impersonator_id)
+        .unwrap_or(id);
+
+    let persistent_session = if req.session().get(SESSION_TOKEN_KEY).is_none() {
+        let session = PersistentSession::upgrade_legacy(req.session(), conn, session_user_id)?;
+        Some(session)
+    } else {
+        PersistentSession::from_session(req.session(), conn, &req.app().config)?
+    };
+    if !persistent_session.is_some_and(|session| session.user_id == session_user_id) {
+        req.request_log().add("cause", "session expired or revoked");
+        req.session().remove("user_id");
+        Impersonation::clear(req.session());
+        return Ok(None);
+    }
+
     req.request_log().add("uid", id);
 
     Ok(Some(CookieAuthentication { user }))
@@ -256,7 +394,32 @@ fn authenticate<T: RequestPartsExt>(req: &T, conn: &mut impl Conn) -> AppResult<
     return Err(forbidden("this action requires authentication"));
 }
 
-fn ensure_not_locked(user: &User) -> AppResult<()> {
+/// Authenticates the request if it contains a cookie session or an API token,
+/// but returns `None` for anonymous requests and invalid credentials instead
+/// of failing.
+///
+/// The scopes of API tokens are not checked, so the result must only be used
+/// to attribute requests to users (e.g. for API quotas), and never to
+/// authorize any actions.
+#[instrument(skip_all)]
+pub fn authenticate_optional<T: RequestPartsExt>(
+    req: &T,
+    conn: &mut impl Conn,
+) -> Option<Authentication> {
+    controllers::util::verify_origin(req).ok()?;
+
+    if let Ok(Some(auth)) = authenticate_via_cookie(req, conn) {
+        return Some(Authentication::Cookie(auth));
+    }
+
+    if let Ok(Some(auth)) = authenticate_via_token(req, conn) {
+        return Some(Authentication::Token(auth));
+    }
+
+    None
+}
+
+pub fn ensure_not_locked(user: &User) -> AppResult<()> {
     if let Some(reason) = &user.account_lock_reason {
         let still_locked = user
             .account_lock_until
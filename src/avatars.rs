@@ -0,0 +1,138 @@
+//! Proxying and caching of the avatars of users.
+//!
+//! Loading avatars directly from GitHub leaks the IP addresses of crates.io
+//! visitors to GitHub, and breaks avatar URLs that contain the login of a
+//! user once the account is renamed. If the proxy is enabled via the
+//! `AVATAR_PROXY` environment variable, the `/api/v1/users/:id/avatar`
+//! endpoint redirects to a copy of the avatar in the storage bucket, which
+//! the background worker refreshes regularly.
+//!
+//! Avatars that have not been fetched yet, or that failed to be fetched, are
+//! replaced by an [identicon] that is generated from the ID of the user.
+//!
+//! The avatars are fetched with the [crate::readme_images::ImageFetcher] of
+//! the README image proxy, which only fetches images from public hosts.
+//!
+//! The endpoint only reads from the database. Stale avatars are refreshed by
+//! enqueueing a background job, which is rate limited per instance via
+//! [`AvatarRefreshes`], so that anonymous requests can not cause a write for
+//! every request.
+
+use chrono::Duration;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Instant;
+
+/// The maximum size of avatars that are cached. GitHub avatars are usually
+/// much smaller than this.
+pub const MAX_AVATAR_SIZE: u64 = 1024 * 1024;
+
+/// Cached avatars are fetched again after this amount of time, so that
+/// changed avatars show up eventually. The same applies to avatars that
+/// failed to be fetched.
+pub const REFRESH_INTERVAL: Duration = Duration::days(1);
+
+/// The minimum amount of time between two refreshes of the same avatar that
+/// are enqueued by the endpoint on a single instance, so that the background
+/// jobs don't pile up for frequently requested avatars.
+const REQUEST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// The number of remembered refresh requests above which the expired ones
+/// are removed.
+const MAX_REQUESTS: usize = 10_000;
+
+/// The number of cells of an identicon in each direction.
+const IDENTICON_CELLS: usize = 5;
+
+/// Remembers when this instance last enqueued the refresh of the avatar of
+/// each user.
+#[derive(Debug, Default)]
+pub struct AvatarRefreshes {
+    requested: Mutex<HashMap<i32, Instant>>,
+}
+
+impl AvatarRefreshes {
+    /// Returns `true` if the refresh of the avatar of the user should be
+    /// enqueued, because this instance did not enqueue one within the
+    /// [`REQUEST_INTERVAL`], and records the request.
+    pub fn request(&self, user_id: i32) -> bool {
+        let now = Instant::now();
+        let is_recent = |at: &Instant| now.duration_since(*at) < REQUEST_INTERVAL;
+
+        let mut requested = self.requested.lock();
+        if requested.get(&user_id).is_some_and(is_recent) {
+            return false;
+        }
+
+        if requested.len() >= MAX_REQUESTS {
+            requested.retain(|_, at| is_recent(at));
+        }
+
+        requested.insert(user_id, now);
+        true
+    }
+}
+
+/// Generates an SVG image with a symmetric pattern and color that are derived
+/// from the seed, so that users without a cached avatar can still be told
+/// apart.
+pub fn identicon(seed: &str) -> String {
+    let hash = Sha256::digest(seed.as_bytes());
+
+    let hue = u16::from_be_bytes([hash[0], hash[1]]) % 360;
+    let color = format!("hsl({hue}, 55%, 55%)");
+
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {size} {size}" shape-rendering="crispEdges"><rect width="{size}" height="{size}" fill="#f0f0f0"/>"##,
+        size = IDENTICON_CELLS + 1,
+    );
+
+    // Only the left half of the cells is derived from the hash, and mirrored
+    // to the right half.
+    let half = IDENTICON_CELLS.div_ceil(2);
+    for row in 0..IDENTICON_CELLS {
+        for column in 0..half {
+            if hash[2 + row * half + column] % 2 == 0 {
+                continue;
+            }
+
+            for x in [column, IDENTICON_CELLS - 1 - column] {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{x}.5" y="{row}.5" width="1" height="1" fill="{color}"/>"#
+                );
+                if x == IDENTICON_CELLS - 1 - x {
+                    break;
+                }
+            }
+        }
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refreshes_are_requested_once_per_interval() {
+        let refreshes = AvatarRefreshes::default();
+        assert!(refreshes.request(1));
+        assert!(!refreshes.request(1));
+        assert!(refreshes.request(2));
+    }
+
+    #[test]
+    fn identicons_are_deterministic() {
+        assert_eq!(identicon("foo"), identicon("foo"));
+        assert_ne!(identicon("foo"), identicon("bar"));
+
+        let svg = identicon("foo");
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+    }
+}
@@ -7,6 +7,9 @@
 //! the worker thread), we will rebuild the runner and try again up to 5 times.
 //! After the 5th occurrence, we will panic.
 //!
+//! When the process receives a shutdown signal, the workers finish the jobs
+//! that they are currently running, but don't start any new ones.
+//!
 //! Usage:
 //!      cargo run --bin background-worker
 
@@ -14,26 +17,36 @@
 extern crate tracing;
 
 use anyhow::Context;
+use crates_io::advisories::{AdvisorySource, HttpAdvisorySource};
+use crates_io::antivirus::{ClamAv, VirusScanner};
 use crates_io::cloudfront::CloudFront;
 use crates_io::db::make_manager_config;
 use crates_io::fastly::Fastly;
+use crates_io::metrics::LogEncoder;
+use crates_io::mirrors::{HttpMirrorProbe, MirrorProbe};
+use crates_io::readme_images::{HttpImageFetcher, ImageFetcher};
+use crates_io::shutdown::shutdown_signal;
 use crates_io::storage::Storage;
 use crates_io::team_repo::TeamRepoImpl;
+use crates_io::upstream::{HttpUpstreamRegistry, UpstreamRegistry};
 use crates_io::worker::{Environment, RunnerExt};
 use crates_io::{config, Emails};
 use crates_io::{db, ssh};
 use crates_io_env_vars::var;
 use crates_io_index::RepositoryConfig;
-use crates_io_worker::Runner;
+use crates_io_worker::{Runner, WorkerMetrics};
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use object_store::prefix::PrefixStore;
 use object_store::ObjectStore;
+use prometheus::Encoder;
 use reqwest::Client;
 use secrecy::ExposeSecret;
+use std::io::Write;
 use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
+use tokio::time::timeout;
 
 fn main() -> anyhow::Result<()> {
     let _sentry = crates_io::sentry::init();
@@ -46,6 +59,10 @@ fn main() -> anyhow::Result<()> {
     info!("Booting runner");
 
     let config = config::Server::from_environment()?;
+    let shutdown_timeout = config.shutdown_timeout;
+    let metrics_log_interval = config
+        .instance_metrics_log_every_seconds
+        .map(Duration::from_secs);
 
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -85,6 +102,43 @@ fn main() -> anyhow::Result<()> {
     let fastly = Fastly::from_environment(client.clone());
     let team_repo = TeamRepoImpl::default();
 
+    let virus_scanner = config.clamd_address.as_ref().map(|address| {
+        let scanner: Box<dyn VirusScanner + Send + Sync> = Box::new(ClamAv::new(address));
+        scanner
+    });
+
+    let image_fetcher = if config.readme_image_proxy || config.avatar_proxy {
+        let fetcher: Box<dyn ImageFetcher + Send + Sync> = Box::new(HttpImageFetcher::new()?);
+        Some(fetcher)
+    } else {
+        None
+    };
+
+    let mirror_probe = if config.mirror_redirects {
+        let probe: Box<dyn MirrorProbe + Send + Sync> = Box::new(HttpMirrorProbe::new()?);
+        Some(probe)
+    } else {
+        None
+    };
+
+    let advisory_source = match config.advisory_db_url.clone() {
+        Some(url) => {
+            let source: Box<dyn AdvisorySource + Send + Sync> =
+                Box::new(HttpAdvisorySource::new(url)?);
+            Some(source)
+        }
+        None => None,
+    };
+
+    let upstream = match config.upstream.clone() {
+        Some(upstream_config) => {
+            let registry: Arc<dyn UpstreamRegistry + Send + Sync> =
+                Arc::new(HttpUpstreamRegistry::new(upstream_config)?);
+            Some(registry)
+        }
+        None => None,
+    };
+
     let manager_config = make_manager_config(config.db.enforce_tls);
     let manager = AsyncDieselConnectionManager::new_with_config(db_url, manager_config);
     let deadpool = Pool::builder(manager).max_size(10).build().unwrap();
@@ -99,6 +153,11 @@ fn main() -> anyhow::Result<()> {
         .deadpool(deadpool.clone())
         .emails(emails)
         .team_repo(Box::new(team_repo))
+        .virus_scanner(virus_scanner)
+        .image_fetcher(image_fetcher)
+        .mirror_probe(mirror_probe)
+        .advisory_source(advisory_source)
+        .upstream(upstream)
         .build()?;
 
     let environment = Arc::new(environment);
@@ -118,12 +177,54 @@ fn main() -> anyhow::Result<()> {
         .configure_queue("repository", |queue| queue.num_workers(1))
         .register_crates_io_job_types();
 
+    if let Some(interval) = metrics_log_interval {
+        log_worker_metrics_thread(runner.metrics().clone(), environment.clone(), interval);
+    }
+
     runtime.block_on(async {
         let handle = runner.start();
 
         info!("Runner booted, running jobs");
-        handle.wait_for_shutdown().await
+        shutdown_signal().await;
+
+        // Let the workers finish their current jobs, so that they are either
+        // deleted or rescheduled. Jobs that don't finish in time are unlocked
+        // when the process exits and are retried later.
+        info!("Shutdown signal received, waiting for running jobs to finish…");
+        if timeout(shutdown_timeout, handle.shutdown()).await.is_err() {
+            warn!("Timed out waiting for running jobs to finish");
+        }
     });
 
     Ok(())
 }
+
+/// Periodically logs the metrics of the background workers, e.g. how often
+/// exclusive jobs were skipped because they were already running elsewhere,
+/// and the metrics that are recorded by the jobs themselves.
+fn log_worker_metrics_thread(
+    metrics: WorkerMetrics,
+    environment: Arc<Environment>,
+    interval: Duration,
+) {
+    std::thread::spawn(move || loop {
+        if let Err(err) = log_worker_metrics_inner(&metrics, &environment) {
+            error!(?err, "log_worker_metrics error");
+        }
+        sleep(interval);
+    });
+}
+
+fn log_worker_metrics_inner(
+    metrics: &WorkerMetrics,
+    environment: &Environment,
+) -> anyhow::Result<()> {
+    let mut families = metrics.gather();
+    families.extend(environment.metrics.gather());
+
+    let mut stdout = std::io::stdout();
+    LogEncoder::new().encode(&families, &mut stdout)?;
+    stdout.flush()?;
+
+    Ok(())
+}
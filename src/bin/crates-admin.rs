@@ -2,8 +2,9 @@
 extern crate tracing;
 
 use crates_io::admin::{
-    default_versions, delete_crate, delete_version, enqueue_job, migrate, populate, render_readmes,
-    test_pagerduty, transfer_crates, upload_index, verify_token, yank_version,
+    allow_crate_name, crate_limits, default_versions, delete_crate, delete_version, enqueue_job,
+    import_registry, migrate, mirrors, populate, render_readmes, test_pagerduty, transfer_crates,
+    upload_index, user_limits, verify_db_deltas, verify_token, yank_version,
 };
 
 #[derive(clap::Parser, Debug)]
@@ -16,13 +17,20 @@ enum Command {
     TestPagerduty(test_pagerduty::Opts),
     TransferCrates(transfer_crates::Opts),
     VerifyToken(verify_token::Opts),
+    VerifyDbDeltas(verify_db_deltas::Opts),
     Migrate(migrate::Opts),
     UploadIndex(upload_index::Opts),
     YankVersion(yank_version::Opts),
+    AllowCrateName(allow_crate_name::Opts),
+    CrateLimits(crate_limits::Opts),
+    UserLimits(user_limits::Opts),
+    ImportRegistry(import_registry::Opts),
     #[clap(subcommand)]
     EnqueueJob(enqueue_job::Command),
     #[clap(subcommand)]
     DefaultVersions(default_versions::Command),
+    #[clap(subcommand)]
+    Mirrors(mirrors::Command),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -45,11 +53,17 @@ fn main() -> anyhow::Result<()> {
         Command::TestPagerduty(opts) => test_pagerduty::run(opts),
         Command::TransferCrates(opts) => transfer_crates::run(opts),
         Command::VerifyToken(opts) => verify_token::run(opts),
+        Command::VerifyDbDeltas(opts) => verify_db_deltas::run(opts),
         Command::Migrate(opts) => migrate::run(opts),
         Command::UploadIndex(opts) => upload_index::run(opts),
         Command::YankVersion(opts) => yank_version::run(opts),
+        Command::AllowCrateName(opts) => allow_crate_name::run(opts),
+        Command::CrateLimits(opts) => crate_limits::run(opts),
+        Command::UserLimits(opts) => user_limits::run(opts),
+        Command::ImportRegistry(opts) => import_registry::run(opts),
         Command::EnqueueJob(command) => enqueue_job::run(command),
         Command::DefaultVersions(opts) => default_versions::run(opts),
+        Command::Mirrors(command) => mirrors::run(command),
     }
 }
 
@@ -2,6 +2,7 @@
 extern crate tracing;
 
 use crates_io::middleware::normalize_path::normalize_path;
+use crates_io::shutdown::shutdown_signal;
 use crates_io::{metrics::LogEncoder, App, Emails};
 use std::{sync::Arc, time::Duration};
 
@@ -12,7 +13,6 @@ use reqwest::Client;
 use std::io::Write;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
-use tokio::signal::unix::{signal, SignalKind};
 use tower::Layer;
 
 const CORE_THREADS: usize = 4;
@@ -64,13 +64,26 @@ fn main() -> anyhow::Result<()> {
 
         let addr = listener.local_addr()?;
 
+        // Keep the crawler throttling policies in sync with the database.
+        tokio::spawn(crates_io::user_agent_throttle::refresh_periodically(
+            app.clone(),
+        ));
+
+        // Keep the names of private crates in sync with the database.
+        tokio::spawn(crates_io::private_crates::refresh_periodically(app.clone()));
+
+        // Measure the event loop lag that low priority requests are shed at.
+        tokio::spawn(crates_io::load_shedding::monitor_event_loop_lag(
+            app.clone(),
+        ));
+
         // Do not change this line! Removing the line or changing its contents in any way will break
         // the test suite :)
         info!("Listening at http://{addr}");
 
         // Run the server with graceful shutdown
         axum::serve(listener, make_service)
-            .with_graceful_shutdown(shutdown_signal())
+            .with_graceful_shutdown(drain(app.clone()))
             .await
     })?;
 
@@ -78,24 +91,21 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn shutdown_signal() {
-    let interrupt = async {
-        signal(SignalKind::interrupt())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
-    };
-
-    let terminate = async {
-        signal(SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
-    };
-
-    tokio::select! {
-        _ = interrupt => {},
-        _ = terminate => {},
+/// Waits for a shutdown signal, and then for the in-flight mutating requests
+/// (e.g. publishes) to finish, before the server stops accepting connections.
+async fn drain(app: Arc<App>) {
+    shutdown_signal().await;
+
+    info!("Shutdown signal received, draining in-flight requests…");
+    app.shutdown.begin_draining();
+
+    let timeout = app.config.shutdown_timeout;
+    if !app.shutdown.wait_for_mutations(timeout).await {
+        let in_flight = app.shutdown.in_flight_mutations();
+        warn!(
+            in_flight,
+            "Timed out waiting for in-flight requests to finish"
+        );
     }
 }
 
@@ -0,0 +1,239 @@
+//! Challenges that have to be solved before abusive routes can be used.
+//!
+//! During abuse incidents, operators can require a challenge for individual
+//! routes (e.g. account creation or report submission) via the
+//! `CHALLENGED_ROUTES` environment variable. Depending on the configuration,
+//! the challenge is either an [hCaptcha](https://www.hcaptcha.com/), or a
+//! proof-of-work that has to be computed by the client. See
+//! [`crate::middleware`] for where this is enforced.
+//!
+//! The solution is sent in the [`CHALLENGE_RESPONSE_HEADER`] header. For
+//! proof-of-work challenges, it has the format `<timestamp>:<nonce>`, where
+//! `<timestamp>` is the current Unix timestamp in seconds and `<nonce>` is
+//! chosen so that the SHA-256 hash of `<timestamp>:<nonce>:<path>` starts
+//! with at least `difficulty` zero bits.
+//!
+//! Authenticated users that have published at least one version are never
+//! challenged.
+
+use anyhow::Context;
+use parking_lot::Mutex;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_POW_DIFFICULTY: u32 = 20;
+
+/// How long a proof-of-work solution stays valid after its timestamp.
+const POW_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// How far the timestamp of a proof-of-work solution may be in the future,
+/// to account for clock skew.
+const POW_MAX_SKEW: Duration = Duration::from_secs(60);
+
+const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+/// The request header that contains the solution of the challenge.
+pub const CHALLENGE_RESPONSE_HEADER: &str = "x-challenge-response";
+
+/// The response header that describes the challenge that has to be solved.
+pub const CHALLENGE_HEADER: &str = "x-challenge";
+
+#[derive(Debug, Clone)]
+pub enum ChallengeProvider {
+    /// Verifies hCaptcha response tokens with the hCaptcha API.
+    HCaptcha { secret: String },
+    /// Requires a proof-of-work with the given number of leading zero bits.
+    ProofOfWork { difficulty: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct ChallengeConfig {
+    /// The route patterns (e.g. `/api/v1/crates/:crate_id/follow`) that
+    /// require solving a challenge.
+    pub routes: HashSet<String>,
+    pub provider: ChallengeProvider,
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self {
+            routes: HashSet::new(),
+            provider: ChallengeProvider::ProofOfWork {
+                difficulty: DEFAULT_POW_DIFFICULTY,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Challenge {
+    config: ChallengeConfig,
+    client: Client,
+    /// Proof-of-work solutions that were already used, so that they can not
+    /// be replayed while they are still valid.
+    used_solutions: Mutex<HashMap<String, Instant>>,
+}
+
+impl Challenge {
+    pub fn new(config: ChallengeConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            used_solutions: Default::default(),
+        }
+    }
+
+    /// Returns `true` if requests to the given route pattern have to solve a
+    /// challenge.
+    pub fn is_challenged(&self, route: &str) -> bool {
+        self.config.routes.contains(route)
+    }
+
+    /// Describes the challenge that has to be solved, for the
+    /// [`CHALLENGE_HEADER`] response header.
+    pub fn describe(&self) -> String {
+        match &self.config.provider {
+            ChallengeProvider::HCaptcha { .. } => "hcaptcha".to_string(),
+            ChallengeProvider::ProofOfWork { difficulty } => {
+                format!("proof-of-work; difficulty={difficulty}")
+            }
+        }
+    }
+
+    /// Checks if `solution` solves the challenge for a request to `path`.
+    pub async fn verify(&self, path: &str, solution: &str) -> anyhow::Result<bool> {
+        match &self.config.provider {
+            ChallengeProvider::HCaptcha { secret } => self.verify_hcaptcha(secret, solution).await,
+            ChallengeProvider::ProofOfWork { difficulty } => {
+                let now = chrono::Utc::now().timestamp();
+                if !verify_proof_of_work(path, solution, *difficulty, now) {
+                    return Ok(false);
+                }
+
+                Ok(self.mark_as_used(path, solution, Instant::now()))
+            }
+        }
+    }
+
+    async fn verify_hcaptcha(&self, secret: &str, token: &str) -> anyhow::Result<bool> {
+        #[derive(Deserialize)]
+        struct VerifyResponse {
+            success: bool,
+        }
+
+        let params = [("secret", secret), ("response", token)];
+
+        let response: VerifyResponse = self
+            .client
+            .post(HCAPTCHA_VERIFY_URL)
+            .form(&params)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to send hCaptcha verification request")?
+            .json()
+            .await
+            .context("Failed to parse hCaptcha verification response")?;
+
+        Ok(response.success)
+    }
+
+    /// Records the solution as used, and returns `false` if it was already
+    /// used before.
+    fn mark_as_used(&self, path: &str, solution: &str, now: Instant) -> bool {
+        let mut used_solutions = self.used_solutions.lock();
+
+        let max_age = POW_MAX_AGE + POW_MAX_SKEW;
+        used_solutions.retain(|_, used_at| now.duration_since(*used_at) < max_age);
+
+        let key = format!("{solution}:{path}");
+        used_solutions.insert(key, now).is_none()
+    }
+}
+
+/// Checks if `solution` is a valid proof-of-work for a request to `path` at
+/// the Unix timestamp `now`.
+fn verify_proof_of_work(path: &str, solution: &str, difficulty: u32, now: i64) -> bool {
+    let Some((timestamp, _nonce)) = solution.split_once(':') else {
+        return false;
+    };
+
+    let Ok(timestamp) = timestamp.parse::<i64>() else {
+        return false;
+    };
+
+    let age = now - timestamp;
+    if age > POW_MAX_AGE.as_secs() as i64 || -age > POW_MAX_SKEW.as_secs() as i64 {
+        return false;
+    }
+
+    let hash = Sha256::digest(format!("{solution}:{path}"));
+    leading_zero_bits(&hash) >= difficulty
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(path: &str, difficulty: u32, timestamp: i64) -> String {
+        (0..)
+            .map(|nonce| format!("{timestamp}:{nonce}"))
+            .find(|solution| verify_proof_of_work(path, solution, difficulty, timestamp))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x01, 0xff]), 7);
+        assert_eq!(leading_zero_bits(&[0x00, 0x0f]), 12);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn test_verify_proof_of_work() {
+        let now = 1_700_000_000;
+        let solution = solve("/api/v1/foo", 8, now);
+
+        assert!(verify_proof_of_work("/api/v1/foo", &solution, 8, now));
+        assert!(verify_proof_of_work("/api/v1/foo", &solution, 8, now + 60));
+
+        // Solutions expire
+        assert!(!verify_proof_of_work(
+            "/api/v1/foo",
+            &solution,
+            8,
+            now + 301
+        ));
+        assert!(!verify_proof_of_work("/api/v1/foo", &solution, 8, now - 61));
+
+        assert!(!verify_proof_of_work("/api/v1/foo", "", 8, now));
+        assert!(!verify_proof_of_work("/api/v1/foo", "foo:bar", 8, now));
+    }
+
+    #[test]
+    fn test_solutions_can_not_be_replayed() {
+        let challenge = Challenge::new(ChallengeConfig::default());
+        let now = Instant::now();
+
+        assert!(challenge.mark_as_used("/api/v1/foo", "1:2", now));
+        assert!(!challenge.mark_as_used("/api/v1/foo", "1:2", now));
+        assert!(challenge.mark_as_used("/api/v1/bar", "1:2", now));
+
+        let later = now + POW_MAX_AGE + POW_MAX_SKEW;
+        assert!(challenge.mark_as_used("/api/v1/foo", "1:2", later));
+    }
+}
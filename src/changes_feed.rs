@@ -0,0 +1,192 @@
+//! Shared polling of the changes feed for the `GET /changes/stream` route.
+//!
+//! Instead of polling the database for every open stream, a single task per
+//! instance polls for new events and broadcasts them to all streams. The
+//! task is started by the first stream, and stops once the last stream is
+//! closed. Streams only query the database themselves to catch up with the
+//! broadcasted events, e.g. when they are opened with an old cursor, or when
+//! they fell too far behind.
+
+use crate::models::{CrateVisibility, RegistryEvent};
+use crate::schema::{crates, registry_events};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::util::errors::AppResult;
+use crate::App;
+use diesel::dsl::now;
+use diesel::pg::data_types::PgInterval;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+
+/// The maximum number of events that are loaded by a single query.
+pub const MAX_EVENTS: i64 = 1000;
+
+/// How often the database is checked for new events while streams are open.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The maximum number of streams that can be open at the same time on a
+/// single instance.
+const MAX_STREAMS: usize = 1000;
+
+/// The number of batches that are buffered for streams that are slower than
+/// the poller. Streams that fall further behind catch up via the database.
+const BUFFER_SIZE: usize = 64;
+
+/// The events that were loaded by a single poll.
+#[derive(Debug)]
+pub struct Batch {
+    /// The cursor that the events were loaded with. Streams with an older
+    /// cursor have to catch up via the database first.
+    pub since: i64,
+    /// The new events, ordered by their ID.
+    pub events: Vec<RegistryEvent>,
+}
+
+pub struct ChangesFeed {
+    sender: broadcast::Sender<Arc<Batch>>,
+    streams: Arc<Semaphore>,
+    /// Whether the poller task is running. The mutex is also held while the
+    /// poller decides to stop, so that no subscriber is left without one.
+    polling: Mutex<bool>,
+}
+
+impl Default for ChangesFeed {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(BUFFER_SIZE);
+        Self {
+            sender,
+            streams: Arc::new(Semaphore::new(MAX_STREAMS)),
+            polling: Mutex::new(false),
+        }
+    }
+}
+
+impl ChangesFeed {
+    /// Reserves a slot for a new stream, or returns `None` if the maximum
+    /// number of open streams is reached. The slot is released when the
+    /// permit is dropped.
+    pub fn acquire_stream(&self) -> Option<OwnedSemaphorePermit> {
+        self.streams.clone().try_acquire_owned().ok()
+    }
+
+    /// Subscribes to the batches of new events, and starts the poller unless
+    /// it is already running.
+    pub fn subscribe(app: &Arc<App>) -> broadcast::Receiver<Arc<Batch>> {
+        let feed = &app.changes_feed;
+        let receiver = feed.sender.subscribe();
+
+        let mut polling = feed.polling.lock();
+        if !*polling {
+            *polling = true;
+            tokio::spawn(poll(app.clone()));
+        }
+
+        receiver
+    }
+}
+
+/// Polls for new events until there are no subscribers anymore.
+async fn poll(app: Arc<App>) {
+    let feed = &app.changes_feed;
+
+    let mut cursor = None;
+    loop {
+        {
+            let mut polling = feed.polling.lock();
+            if feed.sender.receiver_count() == 0 {
+                *polling = false;
+                return;
+            }
+        }
+
+        match cursor {
+            None => match latest_cursor(&app).await {
+                Ok(latest) => cursor = Some(latest),
+                Err(error) => warn!("Failed to load the latest registry event: {error}"),
+            },
+            Some(since) => match load_events_async(&app, since).await {
+                Ok(events) => {
+                    if let Some(last) = events.last() {
+                        cursor = Some(last.id);
+
+                        // Sending only fails if all streams were closed in
+                        // the meantime.
+                        let _ = feed.sender.send(Arc::new(Batch { since, events }));
+                    }
+                }
+                Err(error) => warn!("Failed to load registry events: {error}"),
+            },
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Returns the ID of the most recent event that is visible in the feed, or
+/// `0` if there are no events yet.
+pub async fn latest_cursor(app: &App) -> AppResult<i64> {
+    let delay = app.config.changes_feed_delay;
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let latest: Option<i64> = registry_events::table
+            .filter(registry_events::created_at.le(now - visibility_delay(delay)))
+            .select(diesel::dsl::max(registry_events::id))
+            .get_result(conn)?;
+
+        Ok(latest.unwrap_or(0))
+    })
+    .await
+}
+
+/// Loads the events that happened after the `since` cursor, see
+/// [`load_events()`].
+pub async fn load_events_async(app: &App, since: i64) -> AppResult<Vec<RegistryEvent>> {
+    let delay = app.config.changes_feed_delay;
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+        Ok(load_events(conn, since, delay)?)
+    })
+    .await
+}
+
+/// Converts the `changes_feed_delay` into an interval, which is subtracted
+/// from the current time to get the creation time of the newest events that
+/// are included in the feed.
+///
+/// The IDs of the events are assigned when they are inserted, and not when
+/// their transaction commits, so a newer event can become visible before an
+/// older one. Without the delay, clients that moved their cursor past the
+/// newer event would never see the older one.
+fn visibility_delay(delay: Duration) -> PgInterval {
+    PgInterval::from_microseconds(delay.as_micros() as i64)
+}
+
+/// Loads the events that happened after the `since` cursor, and that are
+/// older than the `delay`, see [`visibility_delay()`].
+///
+/// Events of private crates are not included in the feed.
+pub fn load_events(
+    conn: &mut impl Conn,
+    since: i64,
+    delay: Duration,
+) -> QueryResult<Vec<RegistryEvent>> {
+    let private_crates = crates::table
+        .filter(crates::visibility.ne(CrateVisibility::Public))
+        .select(crates::name);
+
+    registry_events::table
+        .filter(registry_events::id.gt(since))
+        .filter(registry_events::created_at.le(now - visibility_delay(delay)))
+        .filter(registry_events::crate_name.ne_all(private_crates))
+        .order(registry_events::id.asc())
+        .limit(MAX_EVENTS)
+        .select(RegistryEvent::as_select())
+        .load(conn)
+}
@@ -2,7 +2,18 @@ use anyhow::{anyhow, Context};
 use ipnetwork::IpNetwork;
 use oauth2::{ClientId, ClientSecret};
 
+use crate::api_quota::{self, ApiQuotaConfig};
+use crate::challenge::{self, ChallengeConfig, ChallengeProvider};
+use crate::deprecation::{self, Deprecation};
+use crate::load_shedding::{self, LoadSheddingConfig};
+use crate::mirrors;
+use crate::publish_queue::{self, PublishQueueConfig};
 use crate::rate_limiter::{LimitedAction, RateLimiterConfig};
+use crate::registries::Registries;
+use crate::request_limits::{self, RequestLimits, RequestLimitsConfig, RouteClass};
+use crate::retention::{self, RetentionConfig};
+use crate::spam::{self, SpamConfig};
+use crate::upstream::{self, UpstreamConfig};
 use crate::Env;
 
 use super::base::Base;
@@ -11,8 +22,13 @@ use crate::config::cdn_log_storage::CdnLogStorageConfig;
 use crate::config::CdnLogQueueConfig;
 use crate::middleware::cargo_compat::StatusCodeConfig;
 use crate::storage::StorageConfig;
+use crate::user_agent_throttle::{self, UserAgentThrottleConfig};
 use crates_io_env_vars::{list, list_parsed, required_var, var, var_parsed};
+use crates_io_index::FeatureEncoding;
+use crates_io_markdown::SanitizerPolicy;
 use http::HeaderValue;
+use p256::ecdsa::SigningKey;
+use p256::pkcs8::DecodePrivateKey;
 use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
 use std::str::FromStr;
@@ -21,13 +37,75 @@ use std::time::Duration;
 const DEFAULT_VERSION_ID_CACHE_SIZE: u64 = 10_000;
 const DEFAULT_VERSION_ID_CACHE_TTL: u64 = 5 * 60; // 5 minutes
 
-/// Maximum number of features a crate can have or that a feature itself can
-/// enable. This value can be overridden in the database on a per-crate basis.
+/// Maximum number of features a crate can have. This value can be overridden
+/// in the database on a per-crate basis.
 const DEFAULT_MAX_FEATURES: usize = 300;
 
-/// Maximum number of dependencies a crate can have.
+/// Maximum number of features or dependencies that a single feature can
+/// enable. This value can be overridden in the database on a per-crate basis.
+const DEFAULT_MAX_FEATURE_VALUES: usize = 300;
+
+/// Maximum number of dependencies a crate can have. This value can be
+/// overridden in the database on a per-crate or per-user basis.
 const DEFAULT_MAX_DEPENDENCIES: usize = 500;
 
+/// Maximum total size of the files in a crate file, after decompression.
+const DEFAULT_MAX_UNPACK_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Maximum number of files in a crate file.
+const DEFAULT_MAX_TARBALL_FILES: usize = 50_000;
+
+/// Maximum number of nested directories of the paths in a crate file.
+const DEFAULT_MAX_TARBALL_PATH_DEPTH: usize = 32;
+
+/// Maximum number of pending index updates that are coalesced into a single
+/// commit on the git index.
+const DEFAULT_GIT_INDEX_SYNC_BATCH_SIZE: usize = 20;
+
+/// Number of downloads within the last 90 days above which yanking a version
+/// has to be confirmed by one of the crate owners via email.
+const DEFAULT_YANK_CONFIRMATION_DOWNLOADS: i64 = 1_000_000;
+
+/// Minimum number of seconds between two yank or unyank actions of the same
+/// version.
+const DEFAULT_YANK_COOLDOWN_SECONDS: u64 = 10 * 60;
+
+/// Number of days between the approval and the completion of an account
+/// recovery.
+const DEFAULT_ACCOUNT_RECOVERY_WAITING_DAYS: u64 = 7;
+
+/// Number of downloads within the last 90 days above which yanking a version
+/// of a critical crate has to be confirmed by one of the crate owners via
+/// email.
+const DEFAULT_CRITICAL_YANK_CONFIRMATION_DOWNLOADS: i64 = 10_000;
+
+/// Number of days after which the removal of an owner of a critical crate
+/// takes effect if it was not approved by two admins before.
+const DEFAULT_CRITICAL_OWNER_REMOVAL_WAITING_DAYS: u64 = 7;
+
+/// Number of days without any activity after which the removal of an owner
+/// is suggested to the other owners of the crate.
+const DEFAULT_DORMANT_OWNER_DAYS: u64 = 2 * 365;
+
+/// Number of days after which a cookie session expires, regardless of its
+/// activity.
+const DEFAULT_SESSION_LIFETIME_DAYS: u64 = 90;
+
+/// Number of days without any requests after which a cookie session expires.
+const DEFAULT_SESSION_IDLE_TIMEOUT_DAYS: u64 = 30;
+
+/// Number of seconds that the processes wait for in-flight work to finish
+/// after receiving a shutdown signal. Heroku kills the processes 30 seconds
+/// after the signal.
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 25;
+
+/// Number of seconds after which new events are included in the changes
+/// feed.
+const DEFAULT_CHANGES_FEED_DELAY_SECONDS: u64 = 30;
+
+/// Maximum size of images that are cached by the README image proxy.
+const DEFAULT_MAX_README_IMAGE_SIZE: u64 = 5 * 1024 * 1024;
+
 pub struct Server {
     pub base: Base,
     pub ip: IpAddr,
@@ -42,9 +120,21 @@ pub struct Server {
     pub gh_client_secret: ClientSecret,
     pub max_upload_size: u64,
     pub max_unpack_size: u64,
+    pub max_tarball_files: usize,
+    pub max_tarball_path_depth: usize,
     pub max_dependencies: usize,
     pub max_features: usize,
+    pub max_feature_values: usize,
     pub rate_limiter: HashMap<LimitedAction, RateLimiterConfig>,
+    pub api_quota: ApiQuotaConfig,
+    pub user_agent_throttle: UserAgentThrottleConfig,
+    pub challenge: ChallengeConfig,
+    pub load_shedding: LoadSheddingConfig,
+    pub publish_queue: PublishQueueConfig,
+    pub request_limits: RequestLimitsConfig,
+    /// The deprecated API surfaces, see `src/deprecation.rs`.
+    pub deprecations: Vec<Deprecation>,
+    pub spam: SpamConfig,
     pub new_version_rate_limit: Option<u32>,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
     pub blocked_ips: HashSet<IpAddr>,
@@ -56,6 +146,8 @@ pub struct Server {
     pub allowed_origins: AllowedOrigins,
     pub downloads_persist_interval: Duration,
     pub ownership_invitations_expiration_days: u64,
+    /// How long expired invitations and tokens are kept, see `src/retention.rs`.
+    pub retention: RetentionConfig,
     pub metrics_authorization_token: Option<String>,
     pub instance_metrics_log_every_seconds: Option<u64>,
     pub blocked_routes: HashSet<String>,
@@ -63,6 +155,131 @@ pub struct Server {
     pub version_id_cache_ttl: Duration,
     pub cdn_user_agent: String,
 
+    /// Address of the clamd daemon that newly published tarballs are
+    /// scanned with. Scanning is disabled if this is not set.
+    pub clamd_address: Option<String>,
+
+    /// Token that docs.rs uses to authenticate its build status webhook
+    /// requests. The webhook is disabled if this is not set.
+    pub docs_rs_webhook_token: Option<String>,
+
+    /// Token that the email provider uses to authenticate its bounce and
+    /// complaint webhook requests. The webhooks are disabled if this is not
+    /// set.
+    pub email_webhook_token: Option<String>,
+
+    /// ECDSA P-256 key that the advisories about checksum rotations of
+    /// repaired tarballs are signed with. The advisories are served without
+    /// a signature if this is not set.
+    pub checksum_advisory_key: Option<SigningKey>,
+
+    /// Maximum number of pending `sync_to_git_index` jobs that are processed
+    /// together and pushed as a single commit to the git index.
+    pub git_index_sync_batch_size: usize,
+
+    /// The additional registries that are served by this deployment, see
+    /// `src/registries.rs` for more details.
+    pub registries: Registries,
+
+    /// Number of downloads within the last 90 days above which yanking a
+    /// version has to be confirmed by one of the crate owners via email.
+    /// Confirmations are disabled if this is not set.
+    pub yank_confirmation_downloads: Option<i64>,
+
+    /// Minimum amount of time between two yank or unyank actions of the same
+    /// version, to prevent the yanked state from flapping while resolvers
+    /// cache it. The cooldown is disabled if this is not set.
+    pub yank_cooldown: Option<Duration>,
+
+    /// Amount of time that has to pass between the approval of an account
+    /// recovery by an admin and its completion, so that the previous owner
+    /// of the account has a chance to object.
+    pub account_recovery_waiting_period: Duration,
+
+    /// Number of downloads within the last 90 days above which yanking a
+    /// version of a critical crate has to be confirmed by one of the crate
+    /// owners via email. This is usually lower than
+    /// `yank_confirmation_downloads`.
+    pub critical_yank_confirmation_downloads: i64,
+
+    /// Amount of time after which the removal of an owner of a critical
+    /// crate takes effect, unless two admins approve it earlier.
+    pub critical_owner_removal_waiting_period: Duration,
+
+    /// Amount of time without any activity after which the active owners of
+    /// a crate get an email suggesting the removal of an owner. The
+    /// suggestions are disabled if this is not set.
+    pub dormant_owner_period: Option<Duration>,
+
+    /// Amount of time after which a cookie session expires and the user has
+    /// to sign in again, regardless of the activity of the session.
+    pub session_lifetime: Duration,
+
+    /// Amount of time without any requests after which a cookie session
+    /// expires.
+    pub session_idle_timeout: Duration,
+
+    /// Amount of time that the server and background worker wait for
+    /// in-flight publishes and jobs to finish when shutting down.
+    pub shutdown_timeout: Duration,
+
+    /// Amount of time after which new events are included in the changes
+    /// feed. Events are only ordered by their ID, which is assigned when
+    /// they are inserted, so events of transactions that commit out of
+    /// order would otherwise be skipped by clients that already moved
+    /// their cursor past them.
+    pub changes_feed_delay: Duration,
+
+    /// Whether images in READMEs are served via the image proxy of
+    /// crates.io instead of being loaded from third-party hosts.
+    pub readme_image_proxy: bool,
+
+    /// Whether the avatars of users are served from the storage bucket via
+    /// the avatar proxy instead of being loaded from GitHub. See
+    /// `src/avatars.rs` for more details.
+    pub avatar_proxy: bool,
+
+    /// Whether crate downloads are redirected to healthy mirrors in the
+    /// region of the client. See `src/mirrors.rs` for more details.
+    pub mirror_redirects: bool,
+
+    /// The header that the CDN adds with the country code of the client,
+    /// which determines the mirrors that downloads are redirected to.
+    pub mirror_country_header: String,
+
+    /// The URL of the gzipped tarball of the RustSec advisory database that
+    /// the `sync_advisories` background job imports, or `None` if the
+    /// advisories are not synchronized. See `src/advisories.rs` for more
+    /// details.
+    pub advisory_db_url: Option<String>,
+
+    /// Whether crate downloads require authentication, for deployments with
+    /// private crates. The sparse index config then advertises
+    /// `auth-required`, so that cargo sends the registry token along with
+    /// all requests.
+    pub download_auth_required: bool,
+
+    /// How the features of the index entries are encoded. Duplicate feature
+    /// values are kept and the legacy `features` field is used by default,
+    /// so that existing index files only change once they are regenerated
+    /// on purpose with the `regenerate_sparse_index` job.
+    pub index_feature_encoding: FeatureEncoding,
+
+    /// The upstream registry that crates which don't exist locally are
+    /// fetched from, if this deployment is a pull-through cache. See
+    /// `src/upstream.rs` for more details.
+    pub upstream: Option<UpstreamConfig>,
+
+    /// The URL of the sparse index of this deployment, which is advertised
+    /// in the index config for clients that only know the API URL.
+    pub sparse_index_url: Option<String>,
+
+    /// Maximum size of images that are cached by the README image proxy.
+    pub max_readme_image_size: u64,
+
+    /// The allow-list of the HTML sanitizer that READMEs are rendered with.
+    pub readme_sanitizer_policy: SanitizerPolicy,
+
     /// Instructs the `cargo_compat` middleware whether to adjust response
     /// status codes to `200 OK` for all endpoints that are relevant for cargo.
     pub cargo_compat_status_code_config: StatusCodeConfig,
@@ -103,12 +320,127 @@ impl Server {
     ///   will occur.
     /// - `WEB_PAGE_OFFSET_CIDR_BLOCKLIST`: A comma separated list of CIDR blocks that will be used
     ///   to block IP addresses, e.g. `192.168.1.0/24`. If not set or empty, no blocking will occur.
-    /// - `INSTANCE_METRICS_LOG_EVERY_SECONDS`: How frequently should instance metrics be logged.
-    ///   If the environment variable is not present instance metrics are not logged.
+    /// - `INSTANCE_METRICS_LOG_EVERY_SECONDS`: How frequently should instance metrics (and the
+    ///   metrics of the background worker) be logged. If the environment variable is not present
+    ///   instance metrics are not logged.
     /// - `FORCE_UNCONDITIONAL_REDIRECTS`: Whether to force unconditional redirects in the download
     ///   endpoint even with a healthy database pool.
     /// - `BLOCKED_ROUTES`: A comma separated list of HTTP route patterns that are manually blocked
     ///   by an operator (e.g. `/crates/:crate_id/:version/download`).
+    /// - `GIT_INDEX_SYNC_BATCH_SIZE`: The maximum number of pending index updates that are
+    ///   coalesced into a single git commit. Defaults to 20.
+    /// - `MAX_UNPACK_SIZE`: The maximum total size of the files in a crate file after
+    ///   decompression, in bytes. Defaults to 512 MiB.
+    /// - `MAX_TARBALL_FILES`: The maximum number of files in a crate file. Defaults to 50,000.
+    /// - `MAX_TARBALL_PATH_DEPTH`: The maximum number of nested directories of the paths in a
+    ///   crate file. Defaults to 32.
+    /// - `MAX_DEPENDENCIES`: The maximum number of dependencies that a version can declare,
+    ///   unless overridden for the crate or the publishing user. Defaults to 500.
+    /// - `MAX_FEATURES`: The maximum number of features that a version can declare, unless
+    ///   overridden for the crate. Defaults to 300.
+    /// - `MAX_FEATURE_VALUES`: The maximum number of features or dependencies that a single
+    ///   feature can enable, unless overridden for the crate. Defaults to 300.
+    /// - `CLAMD_ADDRESS`: The `host:port` or `unix:/path/to/socket` address of a clamd daemon
+    ///   that newly published tarballs are scanned with. If missing, tarballs are not scanned.
+    /// - `CHALLENGED_ROUTES`: A comma separated list of HTTP route patterns that require solving a
+    ///   challenge from anonymous and new users. See the `challenge` module for more documentation.
+    /// - `CHALLENGE_HCAPTCHA_SECRET`: If set, challenges are hCaptchas that are verified with this
+    ///   secret. Otherwise, a proof-of-work is required.
+    /// - `CHALLENGE_POW_DIFFICULTY`: The number of leading zero bits required for proof-of-work
+    ///   challenges. Defaults to 20.
+    /// - `LOAD_SHEDDING_MAX_IN_FLIGHT`: The number of in-flight requests at which normal priority
+    ///   requests are rejected. Low priority requests are rejected at half of this number. If
+    ///   missing, requests are not rejected based on the number of in-flight requests. See the
+    ///   `load_shedding` module for more documentation.
+    /// - `LOAD_SHEDDING_MAX_LAG_MS`: The event loop lag at which low priority requests are
+    ///   rejected. Normal priority requests are rejected at twice this lag. If missing, requests
+    ///   are not rejected based on the event loop lag.
+    /// - `LOAD_SHEDDING_ROUTE_PRIORITIES`: A comma separated list of `<route>=<priority>` pairs
+    ///   (e.g. `/api/v1/summary=critical`) that override the default priorities of the routes.
+    ///   The priority is one of `low`, `normal` or `critical`.
+    /// - `LOAD_SHEDDING_RETRY_AFTER_SECONDS`: The value of the `Retry-After` header of rejected
+    ///   requests. Defaults to 30.
+    /// - `REQUEST_LIMITS_ROUTE_CLASSES`: A comma separated list of `<route>=<class>` pairs
+    ///   (e.g. `/api/v1/summary=search`) that override the default classes of the routes. The
+    ///   class is one of `publish`, `search` or `default`. See the `request_limits` module for
+    ///   more documentation.
+    /// - `REQUEST_LIMITS_<CLASS>_TIMEOUT_SECONDS`: The number of seconds after which requests of
+    ///   the route class (e.g. `REQUEST_LIMITS_PUBLISH_TIMEOUT_SECONDS`) are aborted. For `publish`
+    ///   this only applies to receiving the request body. Defaults to 120 for `publish`, 10 for
+    ///   `search` and 30 for `default`.
+    /// - `REQUEST_LIMITS_<CLASS>_MAX_BODY_SIZE`: The maximum size in bytes of the request bodies
+    ///   of the route class. Defaults to 128MiB for `publish`, 1KiB for `search` and 2MiB for
+    ///   `default`.
+    /// - `RETENTION_INVITATIONS_DAYS`: The number of days that ownership invitations are kept
+    ///   after they expired. Defaults to 30.
+    /// - `RETENTION_API_TOKENS_DAYS`: The number of days that API tokens are kept after they
+    ///   expired. Defaults to 90.
+    /// - `RETENTION_EMAIL_TOKENS_DAYS`: The number of days after which the verification tokens of
+    ///   unverified email addresses are invalidated. Defaults to 30.
+    /// - `RETENTION_BATCH_SIZE`: The maximum number of rows that are purged by a single statement
+    ///   of the `purge_expired_records` job. Defaults to 1000. See the `retention` module for
+    ///   more documentation.
+    /// - `SPAM_SCORE_THRESHOLD`: The minimum spam score of publishes that are added to the
+    ///   moderation queue. Defaults to 100. See the `spam` module for more documentation.
+    /// - `SPAM_PHRASES`: A comma separated list of additional phrases in crate descriptions and
+    ///   keywords that are considered spam.
+    /// - `DOCS_RS_WEBHOOK_TOKEN`: The token that docs.rs uses to authenticate its build status
+    ///   webhook requests. If missing, the webhook is disabled.
+    /// - `EMAIL_WEBHOOK_TOKEN`: The token that the email provider uses to authenticate its bounce
+    ///   and complaint webhook requests, as the password of HTTP basic authentication. If
+    ///   missing, the webhooks are disabled.
+    /// - `YANK_CONFIRMATION_DOWNLOADS`: The number of downloads within the last 90 days above
+    ///   which yanking a version has to be confirmed by one of the crate owners via email.
+    ///   Defaults to 1,000,000. Set to 0 to disable the confirmations.
+    /// - `YANK_COOLDOWN_SECONDS`: The minimum number of seconds between two yank or unyank
+    ///   actions of the same version. Defaults to 10 minutes. Set to 0 to disable the cooldown.
+    /// - `ACCOUNT_RECOVERY_WAITING_DAYS`: The number of days between the approval of an account
+    ///   recovery and its completion. Defaults to 7.
+    /// - `CRITICAL_YANK_CONFIRMATION_DOWNLOADS`: The number of downloads within the last 90 days
+    ///   above which yanking a version of a critical crate has to be confirmed by one of the crate
+    ///   owners via email. Defaults to 10,000.
+    /// - `CRITICAL_OWNER_REMOVAL_WAITING_DAYS`: The number of days after which the removal of an
+    ///   owner of a critical crate takes effect, unless two admins approve it earlier.
+    ///   Defaults to 7.
+    /// - `SESSION_LIFETIME_DAYS`: The number of days after which a cookie session expires,
+    ///   regardless of its activity. Defaults to 90.
+    /// - `SESSION_IDLE_TIMEOUT_DAYS`: The number of days without any requests after which a
+    ///   cookie session expires. Defaults to 30.
+    /// - `SHUTDOWN_TIMEOUT_SECONDS`: The number of seconds that the server and background worker
+    ///   wait for in-flight publishes and jobs to finish when shutting down. Defaults to 25.
+    /// - `CHANGES_FEED_DELAY_SECONDS`: The number of seconds after which new events are included
+    ///   in the changes feed. Has to be longer than the transactions that insert the events.
+    ///   Defaults to 30.
+    /// - `README_IMAGE_PROXY`: If set, images in READMEs are rewritten to the image proxy
+    ///   endpoint, and cached in the storage bucket by the background worker.
+    /// - `MAX_README_IMAGE_SIZE`: The maximum size of images that are cached by the README image
+    ///   proxy, in bytes. Defaults to 5 MiB.
+    /// - `AVATAR_PROXY`: If set, the avatars of users are cached in the storage bucket by the
+    ///   background worker, and served from there by the avatar endpoint.
+    /// - `MIRROR_REDIRECTS`: If set, crate downloads are redirected to healthy mirrors in the
+    ///   region of the client. See the `mirrors` module for more documentation.
+    /// - `MIRROR_COUNTRY_HEADER`: The header that the CDN adds with the country code of the
+    ///   client. Defaults to `CloudFront-Viewer-Country`.
+    /// - `ADVISORY_DB_URL`: The URL of the gzipped tarball of the RustSec advisory database. If
+    ///   set, the advisories are imported by the `sync_advisories` background job.
+    /// - `README_POLICY_VERSION`: The version of the README sanitizer policy. Has to be increased
+    ///   whenever one of the other `README_*` policy variables changes, so that existing READMEs
+    ///   are rendered again by the `rerender_readmes` job.
+    /// - `README_ANCHOR_IDS`: Whether `id` attributes of links in READMEs are kept. Defaults to
+    ///   `true`.
+    /// - `README_DETAILS`: Whether `<details>` blocks in READMEs are kept. Defaults to `true`.
+    /// - `README_CODE_LANGUAGES`: A comma separated list of additional languages whose code blocks
+    ///   keep their `language-*` class for syntax highlighting.
+    /// - `README_SYNTAX_HIGHLIGHTING`: Whether code blocks in READMEs are highlighted on the
+    ///   server. Defaults to `true`.
+    /// - `UPSTREAM_INDEX_URL`: The URL of the sparse index of an upstream registry (e.g.
+    ///   `https://index.crates.io`). If set, this deployment acts as a pull-through cache for
+    ///   the crates of the upstream registry. See the `upstream` module for more documentation.
+    /// - `UPSTREAM_DOWNLOAD_URL`: The URL below which the crate files of the upstream registry
+    ///   are served. Defaults to `https://static.crates.io/crates`.
+    /// - `SPARSE_INDEX_URL`: The URL of the sparse index of this deployment (e.g.
+    ///   `https://index.crates.io/`), which is advertised in the index config. Defaults to the
+    ///   `/index/` endpoint of pull-through caches.
     ///
     /// # Panics
     ///
@@ -155,6 +487,130 @@ impl Server {
             );
         }
 
+        // See `src/api_quota.rs` for the endpoints that are subject to these quotas.
+        let api_quota = ApiQuotaConfig {
+            window: Duration::from_secs(
+                var_parsed("API_QUOTA_WINDOW_SECONDS")?
+                    .unwrap_or(api_quota::DEFAULT_WINDOW_SECONDS),
+            ),
+            user_requests: var_parsed("API_QUOTA_USER_REQUESTS")?
+                .unwrap_or(api_quota::DEFAULT_USER_REQUESTS),
+            token_requests: var_parsed("API_QUOTA_TOKEN_REQUESTS")?
+                .unwrap_or(api_quota::DEFAULT_TOKEN_REQUESTS),
+            service_token_requests: var_parsed("API_QUOTA_SERVICE_TOKEN_REQUESTS")?
+                .unwrap_or(api_quota::DEFAULT_SERVICE_TOKEN_REQUESTS),
+        };
+
+        // See `src/user_agent_throttle.rs` for how these are used.
+        let user_agent_throttle = UserAgentThrottleConfig {
+            slow_rate: Duration::from_millis(
+                var_parsed("USER_AGENT_THROTTLE_SLOW_RATE_MS")?
+                    .unwrap_or(user_agent_throttle::DEFAULT_SLOW_RATE_MILLIS),
+            ),
+            slow_burst: var_parsed("USER_AGENT_THROTTLE_SLOW_BURST")?
+                .unwrap_or(user_agent_throttle::DEFAULT_SLOW_BURST),
+            refresh_interval: Duration::from_secs(
+                var_parsed("USER_AGENT_THROTTLE_REFRESH_INTERVAL_SECONDS")?
+                    .unwrap_or(user_agent_throttle::DEFAULT_REFRESH_INTERVAL_SECONDS),
+            ),
+        };
+
+        // See `src/challenge.rs` for how these are used.
+        let challenge = ChallengeConfig {
+            routes: HashSet::from_iter(list("CHALLENGED_ROUTES")?),
+            provider: match var("CHALLENGE_HCAPTCHA_SECRET")? {
+                Some(secret) => ChallengeProvider::HCaptcha { secret },
+                None => ChallengeProvider::ProofOfWork {
+                    difficulty: var_parsed("CHALLENGE_POW_DIFFICULTY")?
+                        .unwrap_or(challenge::DEFAULT_POW_DIFFICULTY),
+                },
+            },
+        };
+
+        // See `src/load_shedding.rs` for how these are used.
+        let mut route_priorities = load_shedding::default_route_priorities();
+        route_priorities.extend(list_parsed(
+            "LOAD_SHEDDING_ROUTE_PRIORITIES",
+            load_shedding::parse_route_priority,
+        )?);
+
+        let load_shedding = LoadSheddingConfig {
+            max_in_flight: var_parsed("LOAD_SHEDDING_MAX_IN_FLIGHT")?,
+            max_lag: var_parsed("LOAD_SHEDDING_MAX_LAG_MS")?.map(Duration::from_millis),
+            route_priorities,
+            retry_after: Duration::from_secs(
+                var_parsed("LOAD_SHEDDING_RETRY_AFTER_SECONDS")?
+                    .unwrap_or(load_shedding::DEFAULT_RETRY_AFTER_SECONDS),
+            ),
+        };
+
+        // See `src/publish_queue.rs` for how these are used.
+        let publish_queue = PublishQueueConfig {
+            max_concurrent: var_parsed("PUBLISH_QUEUE_MAX_CONCURRENT")?,
+            max_queued_per_user: var_parsed("PUBLISH_QUEUE_MAX_QUEUED_PER_USER")?
+                .unwrap_or(publish_queue::DEFAULT_MAX_QUEUED_PER_USER),
+        };
+
+        // See `src/request_limits.rs` for how these are used.
+        let mut route_classes = request_limits::default_route_classes();
+        route_classes.extend(list_parsed(
+            "REQUEST_LIMITS_ROUTE_CLASSES",
+            request_limits::parse_route_class,
+        )?);
+
+        let mut classes = HashMap::new();
+        for class in RouteClass::ALL {
+            let defaults = class.default_limits();
+            let name = class.as_str().to_uppercase();
+
+            let timeout = var_parsed(&format!("REQUEST_LIMITS_{name}_TIMEOUT_SECONDS"))?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.timeout);
+            let max_body_size = var_parsed(&format!("REQUEST_LIMITS_{name}_MAX_BODY_SIZE"))?
+                .unwrap_or(defaults.max_body_size);
+
+            let limits = RequestLimits {
+                timeout,
+                max_body_size,
+            };
+            classes.insert(class, limits);
+        }
+
+        let request_limits = RequestLimitsConfig {
+            classes,
+            route_classes,
+        };
+
+        // See `src/retention.rs` for how these are used.
+        let retention = RetentionConfig {
+            batch_size: var_parsed("RETENTION_BATCH_SIZE")?
+                .unwrap_or(retention::DEFAULT_BATCH_SIZE),
+            ..RetentionConfig::from_days(
+                var_parsed("RETENTION_INVITATIONS_DAYS")?
+                    .unwrap_or(retention::DEFAULT_INVITATIONS_DAYS),
+                var_parsed("RETENTION_API_TOKENS_DAYS")?
+                    .unwrap_or(retention::DEFAULT_API_TOKENS_DAYS),
+                var_parsed("RETENTION_EMAIL_TOKENS_DAYS")?
+                    .unwrap_or(retention::DEFAULT_EMAIL_TOKENS_DAYS),
+            )
+        };
+
+        // See `src/spam.rs` for how these are used.
+        let spam = SpamConfig {
+            threshold: var_parsed("SPAM_SCORE_THRESHOLD")?.unwrap_or(spam::DEFAULT_THRESHOLD),
+            phrases: list("SPAM_PHRASES")?,
+        };
+
+        // See `src/upstream.rs` for how this is used.
+        let upstream = match var("UPSTREAM_INDEX_URL")? {
+            Some(index_url) => Some(UpstreamConfig {
+                index_url,
+                download_url: var("UPSTREAM_DOWNLOAD_URL")?
+                    .unwrap_or_else(|| upstream::DEFAULT_DOWNLOAD_URL.into()),
+            }),
+            None => None,
+        };
+
         let storage = StorageConfig::from_environment();
 
         // `sha256-dbf9FMl76C7BnK1CC3eWb3pvsQAUaTYSHAlBy9tNTG0=` refers to
@@ -183,10 +639,24 @@ impl Server {
             gh_client_id: ClientId::new(required_var("GH_CLIENT_ID")?),
             gh_client_secret: ClientSecret::new(required_var("GH_CLIENT_SECRET")?),
             max_upload_size: 10 * 1024 * 1024, // 10 MB default file upload size limit
-            max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
-            max_dependencies: DEFAULT_MAX_DEPENDENCIES,
-            max_features: DEFAULT_MAX_FEATURES,
+            max_unpack_size: var_parsed("MAX_UNPACK_SIZE")?.unwrap_or(DEFAULT_MAX_UNPACK_SIZE),
+            max_tarball_files: var_parsed("MAX_TARBALL_FILES")?
+                .unwrap_or(DEFAULT_MAX_TARBALL_FILES),
+            max_tarball_path_depth: var_parsed("MAX_TARBALL_PATH_DEPTH")?
+                .unwrap_or(DEFAULT_MAX_TARBALL_PATH_DEPTH),
+            max_dependencies: var_parsed("MAX_DEPENDENCIES")?.unwrap_or(DEFAULT_MAX_DEPENDENCIES),
+            max_features: var_parsed("MAX_FEATURES")?.unwrap_or(DEFAULT_MAX_FEATURES),
+            max_feature_values: var_parsed("MAX_FEATURE_VALUES")?
+                .unwrap_or(DEFAULT_MAX_FEATURE_VALUES),
             rate_limiter,
+            api_quota,
+            user_agent_throttle,
+            challenge,
+            load_shedding,
+            publish_queue,
+            request_limits,
+            deprecations: deprecation::default_deprecations(),
+            spam,
             new_version_rate_limit: var_parsed("MAX_NEW_VERSIONS_DAILY")?,
             blocked_traffic: blocked_traffic(),
             blocked_ips,
@@ -200,6 +670,7 @@ impl Server {
                 .map(Duration::from_millis)
                 .unwrap_or(Duration::from_secs(60)),
             ownership_invitations_expiration_days: 30,
+            retention,
             metrics_authorization_token: var("METRICS_AUTHORIZATION_TOKEN")?,
             instance_metrics_log_every_seconds: var_parsed("INSTANCE_METRICS_LOG_EVERY_SECONDS")?,
             blocked_routes: HashSet::from_iter(list("BLOCKED_ROUTES")?),
@@ -210,6 +681,83 @@ impl Server {
             ),
             cdn_user_agent: var("WEB_CDN_USER_AGENT")?
                 .unwrap_or_else(|| "Amazon CloudFront".into()),
+            clamd_address: var("CLAMD_ADDRESS")?,
+            docs_rs_webhook_token: var("DOCS_RS_WEBHOOK_TOKEN")?,
+            email_webhook_token: var("EMAIL_WEBHOOK_TOKEN")?,
+            checksum_advisory_key: var("CHECKSUM_ADVISORY_KEY")?
+                .map(|pem| SigningKey::from_pkcs8_pem(&pem))
+                .transpose()
+                .context("CHECKSUM_ADVISORY_KEY is not a PKCS#8 PEM encoded P-256 key")?,
+            git_index_sync_batch_size: var_parsed("GIT_INDEX_SYNC_BATCH_SIZE")?
+                .unwrap_or(DEFAULT_GIT_INDEX_SYNC_BATCH_SIZE),
+            registries: Registries::from_environment()?,
+            yank_confirmation_downloads: var_parsed("YANK_CONFIRMATION_DOWNLOADS")?
+                .or(Some(DEFAULT_YANK_CONFIRMATION_DOWNLOADS))
+                .filter(|downloads| *downloads > 0),
+            yank_cooldown: Some(
+                var_parsed("YANK_COOLDOWN_SECONDS")?.unwrap_or(DEFAULT_YANK_COOLDOWN_SECONDS),
+            )
+            .filter(|seconds| *seconds > 0)
+            .map(Duration::from_secs),
+            account_recovery_waiting_period: Duration::from_secs(
+                var_parsed("ACCOUNT_RECOVERY_WAITING_DAYS")?
+                    .unwrap_or(DEFAULT_ACCOUNT_RECOVERY_WAITING_DAYS)
+                    * 24
+                    * 60
+                    * 60,
+            ),
+            critical_yank_confirmation_downloads: var_parsed(
+                "CRITICAL_YANK_CONFIRMATION_DOWNLOADS",
+            )?
+            .unwrap_or(DEFAULT_CRITICAL_YANK_CONFIRMATION_DOWNLOADS),
+            critical_owner_removal_waiting_period: Duration::from_secs(
+                var_parsed("CRITICAL_OWNER_REMOVAL_WAITING_DAYS")?
+                    .unwrap_or(DEFAULT_CRITICAL_OWNER_REMOVAL_WAITING_DAYS)
+                    * 24
+                    * 60
+                    * 60,
+            ),
+            dormant_owner_period: Some(
+                var_parsed("DORMANT_OWNER_DAYS")?.unwrap_or(DEFAULT_DORMANT_OWNER_DAYS),
+            )
+            .filter(|days| *days > 0)
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            session_lifetime: Duration::from_secs(
+                var_parsed("SESSION_LIFETIME_DAYS")?.unwrap_or(DEFAULT_SESSION_LIFETIME_DAYS)
+                    * 24
+                    * 60
+                    * 60,
+            ),
+            session_idle_timeout: Duration::from_secs(
+                var_parsed("SESSION_IDLE_TIMEOUT_DAYS")?
+                    .unwrap_or(DEFAULT_SESSION_IDLE_TIMEOUT_DAYS)
+                    * 24
+                    * 60
+                    * 60,
+            ),
+            shutdown_timeout: Duration::from_secs(
+                var_parsed("SHUTDOWN_TIMEOUT_SECONDS")?.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECONDS),
+            ),
+            changes_feed_delay: Duration::from_secs(
+                var_parsed("CHANGES_FEED_DELAY_SECONDS")?
+                    .unwrap_or(DEFAULT_CHANGES_FEED_DELAY_SECONDS),
+            ),
+            readme_image_proxy: var("README_IMAGE_PROXY")?.is_some(),
+            avatar_proxy: var("AVATAR_PROXY")?.is_some(),
+            mirror_redirects: var("MIRROR_REDIRECTS")?.is_some(),
+            mirror_country_header: var("MIRROR_COUNTRY_HEADER")?
+                .unwrap_or_else(|| mirrors::DEFAULT_COUNTRY_HEADER.into()),
+            advisory_db_url: var("ADVISORY_DB_URL")?,
+            download_auth_required: var("DOWNLOAD_AUTH_REQUIRED")?.is_some(),
+            index_feature_encoding: FeatureEncoding {
+                deduplicate: var("INDEX_DEDUPLICATE_FEATURES")?.is_some(),
+                legacy_features: var_parsed("INDEX_LEGACY_FEATURES")?.unwrap_or(true),
+            },
+            upstream,
+            sparse_index_url: var("SPARSE_INDEX_URL")?,
+            max_readme_image_size: var_parsed("MAX_README_IMAGE_SIZE")?
+                .unwrap_or(DEFAULT_MAX_README_IMAGE_SIZE),
+            readme_sanitizer_policy: readme_sanitizer_policy()?,
             cargo_compat_status_code_config: var_parsed("CARGO_COMPAT_STATUS_CODES")?
                 .unwrap_or(StatusCodeConfig::AdjustAll),
             serve_dist: true,
@@ -251,6 +799,31 @@ fn parse_cidr_block(block: &str) -> anyhow::Result<IpNetwork> {
     Ok(cidr)
 }
 
+/// Builds the README sanitizer policy from the default policy and the
+/// `README_*` environment variables.
+fn readme_sanitizer_policy() -> anyhow::Result<SanitizerPolicy> {
+    let mut policy = SanitizerPolicy::default();
+
+    if let Some(version) = var_parsed("README_POLICY_VERSION")? {
+        policy.version = version;
+    }
+    if let Some(anchor_ids) = var_parsed("README_ANCHOR_IDS")? {
+        policy.anchor_ids = anchor_ids;
+    }
+    if let Some(details) = var_parsed("README_DETAILS")? {
+        policy.details = details;
+    }
+    if let Some(syntax_highlighting) = var_parsed("README_SYNTAX_HIGHLIGHTING")? {
+        policy.syntax_highlighting = syntax_highlighting;
+    }
+
+    let code_languages = list("README_CODE_LANGUAGES")?;
+    let code_classes = code_languages.iter().map(|lang| format!("language-{lang}"));
+    policy.code_classes.extend(code_classes);
+
+    Ok(policy)
+}
+
 fn blocked_traffic() -> Vec<(String, Vec<String>)> {
     let pattern_list = dotenvy::var("BLOCKED_TRAFFIC").unwrap_or_default();
     parse_traffic_patterns(&pattern_list)
@@ -63,16 +63,38 @@ mod prelude {
 pub mod helpers;
 pub mod util;
 
+pub mod account_recovery;
+pub mod admin_audit;
+pub mod advisory;
+pub mod attestation_provider;
+pub mod bulk_yank;
 pub mod category;
+pub mod changes;
 pub mod crate_owner_invitation;
+pub mod crate_size;
+pub mod db_dump;
+pub mod deprecation;
+pub mod docs_rs;
+pub mod email_events;
 pub mod git;
 pub mod github;
+pub mod impersonation;
+pub mod index;
 pub mod keyword;
 pub mod krate;
+pub mod links_key;
 pub mod metrics;
+pub mod rate_limit;
+pub mod readme_image;
+pub mod research;
+pub mod service_token;
 pub mod site_metadata;
+pub mod spam_flag;
+pub mod staging;
 pub mod summary;
+pub mod tarball_scan;
 pub mod team;
 pub mod token;
 pub mod user;
+pub mod user_agent_policy;
 pub mod version;
@@ -0,0 +1,418 @@
+//! Endpoints for recovering accounts whose owners lost access to the linked
+//! GitHub account.
+//!
+//! A recovery is requested while signed in with the new GitHub account, and
+//! re-links the previous account to the new GitHub identity once:
+//!
+//! 1. the request was confirmed via the verified email address of the
+//!    previous account,
+//! 2. an admin approved it, and
+//! 3. the waiting period after the approval has passed.
+//!
+//! The previous owner of the account is notified about every step, and the
+//! recovery can be cancelled by either account or an admin until it is
+//! completed.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::email::Email;
+use crate::models::{
+    AccountRecovery, AdminAction, NewAccountRecovery, NewAdminAuditEntry, OwnerKind, User,
+};
+use crate::schema::crate_owners;
+use crate::util::diesel::Conn;
+use crate::util::errors::{custom, forbidden, not_found};
+use crate::views::EncodableAccountRecovery;
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::{exists, select};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use secrecy::{ExposeSecret, SecretString};
+
+#[derive(Deserialize)]
+pub struct RecoveryRequest {
+    login: String,
+}
+
+/// Handles the `PUT /api/private/account_recoveries` route.
+///
+/// Requests the recovery of the account with the given login on behalf of
+/// the signed in user, and sends a confirmation link to the verified email
+/// address of that account.
+pub async fn request(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<RecoveryRequest>,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let requester = auth.user();
+
+        let user = User::find_by_login(conn, &body.login)
+            .optional()?
+            .ok_or_else(not_found)?;
+        if user.id == requester.id {
+            return Err(bad_request("you can not recover your own account"));
+        }
+
+        // The account of the new GitHub identity is locked once the recovery
+        // is completed, so it must not be needed for anything else.
+        let owns_crates = select(exists(
+            crate_owners::table
+                .filter(crate_owners::owner_id.eq(requester.id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+                .filter(crate_owners::deleted.eq(false)),
+        ))
+        .get_result::<bool>(conn)?;
+        if owns_crates {
+            return Err(bad_request(
+                "accounts that own crates can not be used to recover another account",
+            ));
+        }
+
+        let Some(email) = user.verified_email(conn)? else {
+            let detail = format!(
+                "The account `{}` has no verified email address, so it can not be recovered \
+                automatically. Please contact help@crates.io to verify your identity.",
+                user.gh_login
+            );
+            return Err(custom(StatusCode::UNPROCESSABLE_ENTITY, detail));
+        };
+
+        let new_recovery = NewAccountRecovery {
+            user_id: user.id,
+            requested_by: requester.id,
+            email: &email,
+        };
+
+        let Some((recovery, email_token)) = new_recovery.insert(conn)? else {
+            return Err(bad_request(format!(
+                "there already is an open recovery for the account `{}`",
+                user.gh_login
+            )));
+        };
+
+        let confirmation = AccountRecoveryConfirmEmail {
+            user_name: &user.gh_login,
+            requested_by: &requester.gh_login,
+            domain: &app.emails.domain,
+            email_token,
+        };
+        if let Err(error) = app.emails.send(conn, &email, confirmation) {
+            warn!(
+                ?error,
+                "Failed to send account recovery confirmation to {email}"
+            );
+        }
+
+        info!(
+            "User {} requested the recovery of the account {} ({})",
+            requester.gh_login, user.gh_login, user.id
+        );
+
+        let recovery = encode(&app, recovery, conn)?;
+        Ok(Json(json!({ "account_recovery": recovery })))
+    })
+    .await
+}
+
+/// Handles the `PUT /api/v1/confirm_account_recovery/:token` route.
+///
+/// This is the link of the confirmation email that is sent to the verified
+/// email address of the recovered account.
+pub async fn confirm(app: AppState, Path(token): Path<String>) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        if AccountRecovery::confirm_email(conn, &token)?.is_none() {
+            return Err(bad_request("Invalid or expired account recovery token."));
+        }
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `GET /api/private/account_recoveries` route.
+///
+/// Lists all recoveries that have been neither completed nor cancelled yet.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let recoveries = AccountRecovery::open(conn)?
+            .into_iter()
+            .map(|recovery| encode(&app, recovery, conn))
+            .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(Json(json!({ "account_recoveries": recoveries })))
+    })
+    .await
+}
+
+/// Handles the `PUT /api/private/account_recoveries/:id/approve` route.
+///
+/// Approves a recovery that has been confirmed via email, which starts the
+/// waiting period after which the recovery can be completed.
+pub async fn approve(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let recovery = load_open_recovery(id, conn)?;
+
+        if admin.id == recovery.user_id || admin.id == recovery.requested_by {
+            return Err(forbidden(
+                "admins can not approve their own account recoveries",
+            ));
+        }
+        if recovery.email_confirmed_at.is_none() {
+            return Err(bad_request(
+                "the recovery has not been confirmed via email yet",
+            ));
+        }
+        if recovery.approved_at.is_some() {
+            return Err(bad_request("the recovery has already been approved"));
+        }
+
+        let recovery = recovery.approve(conn, admin.id)?;
+
+        let user = User::find(conn, recovery.user_id)?;
+        let requester = User::find(conn, recovery.requested_by)?;
+        warn!(
+            "Admin {} approved the recovery of the account {} ({}) by {}",
+            admin.gh_login, user.gh_login, user.id, requester.gh_login
+        );
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::ApproveAccountRecovery)
+            .details(json!({
+                "recovery_id": recovery.id,
+                "user_id": user.id,
+                "requested_by": requester.id,
+            }))
+            .insert(conn)?;
+
+        let completable_at = recovery.completable_at(app.config.account_recovery_waiting_period);
+        let notification = AccountRecoveryNotificationEmail {
+            user_name: &user.gh_login,
+            requested_by: &requester.gh_login,
+            completable_at,
+        };
+        notify(&app, &recovery, notification, conn);
+
+        let recovery = encode(&app, recovery, conn)?;
+        Ok(Json(json!({ "account_recovery": recovery })))
+    })
+    .await
+}
+
+/// Handles the `PUT /api/private/account_recoveries/:id/complete` route.
+///
+/// Re-links the recovered account to the GitHub identity of the signed in
+/// user, once the waiting period after the approval has passed.
+pub async fn complete(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let requester = auth.user();
+
+        let recovery = load_open_recovery(id, conn)?;
+        if recovery.requested_by != requester.id {
+            return Err(not_found());
+        }
+
+        let waiting_period = app.config.account_recovery_waiting_period;
+        let Some(completable_at) = recovery.completable_at(waiting_period) else {
+            return Err(bad_request(
+                "the recovery has not been approved by the crates.io team yet",
+            ));
+        };
+        if completable_at > Utc::now().naive_utc() {
+            let completable_at = completable_at.format("%Y-%m-%d at %H:%M:%S UTC");
+            return Err(bad_request(format!(
+                "the recovery can not be completed before {completable_at}"
+            )));
+        }
+
+        // The login of the recovered account changes on completion
+        let user = User::find(conn, recovery.user_id)?;
+        let recovery = recovery.complete(conn)?;
+
+        warn!(
+            "User {} completed the recovery of the account {} ({})",
+            requester.gh_login, user.gh_login, user.id
+        );
+
+        let notification = AccountRecoveryNotificationEmail {
+            user_name: &user.gh_login,
+            requested_by: &requester.gh_login,
+            completable_at: None,
+        };
+        notify(&app, &recovery, notification, conn);
+
+        let recovery = encode(&app, recovery, conn)?;
+        Ok(Json(json!({ "account_recovery": recovery })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/account_recoveries/:id` route.
+///
+/// Recoveries can be cancelled by the user that requested them, by the
+/// owner of the recovered account, and by admins.
+pub async fn cancel(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let recovery = load_open_recovery(id, conn)?;
+        let is_involved = user.id == recovery.user_id || user.id == recovery.requested_by;
+        if !is_involved && !user.is_admin {
+            return Err(not_found());
+        }
+
+        recovery.cancel(conn)?;
+
+        warn!(
+            "User {} cancelled the recovery of the account with ID {}",
+            user.gh_login, recovery.user_id
+        );
+
+        ok_true()
+    })
+    .await
+}
+
+fn load_open_recovery(id: i32, conn: &mut impl Conn) -> AppResult<AccountRecovery> {
+    AccountRecovery::find(conn, id)
+        .optional()?
+        .filter(AccountRecovery::is_open)
+        .ok_or_else(not_found)
+}
+
+fn encode(
+    app: &AppState,
+    recovery: AccountRecovery,
+    conn: &mut impl Conn,
+) -> AppResult<EncodableAccountRecovery> {
+    let user = User::find(conn, recovery.user_id)?;
+    let requester = User::find(conn, recovery.requested_by)?;
+    let waiting_period = app.config.account_recovery_waiting_period;
+    Ok(EncodableAccountRecovery::from(
+        recovery,
+        user,
+        requester,
+        waiting_period,
+        app.config.avatar_proxy,
+    ))
+}
+
+/// Notifies the previous owner of the recovered account about the progress
+/// of the recovery.
+fn notify(
+    app: &AppState,
+    recovery: &AccountRecovery,
+    email: AccountRecoveryNotificationEmail<'_>,
+    conn: &mut impl Conn,
+) {
+    if let Err(error) = app.emails.send(conn, &recovery.email, email) {
+        warn!(
+            ?error,
+            "Failed to send account recovery notification to {}", recovery.email
+        );
+    }
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden("must be an admin to manage account recoveries"));
+    }
+
+    Ok(user.clone())
+}
+
+struct AccountRecoveryConfirmEmail<'a> {
+    user_name: &'a str,
+    requested_by: &'a str,
+    domain: &'a str,
+    email_token: SecretString,
+}
+
+impl Email for AccountRecoveryConfirmEmail<'_> {
+    const SUBJECT: &'static str = "Please confirm the recovery of your crates.io account";
+
+    fn body(&self) -> String {
+        format!(
+            "Hello {user_name}!
+
+The GitHub user {requested_by} asked to link your crates.io account to their \
+GitHub account, because you lost access to the GitHub account that it is \
+currently linked to.
+
+If this was you, please confirm the request by visiting the following link:
+
+https://{domain}/confirm-account-recovery/{email_token}
+
+The request will then be reviewed by the crates.io team.
+
+If this wasn't you, you don't need to do anything, but please let us know \
+by replying to help@crates.io.",
+            user_name = self.user_name,
+            requested_by = self.requested_by,
+            domain = self.domain,
+            email_token = self.email_token.expose_secret(),
+        )
+    }
+}
+
+/// Notification about an approved recovery, or a completed one if
+/// `completable_at` is not set.
+struct AccountRecoveryNotificationEmail<'a> {
+    user_name: &'a str,
+    requested_by: &'a str,
+    completable_at: Option<NaiveDateTime>,
+}
+
+impl Email for AccountRecoveryNotificationEmail<'_> {
+    const SUBJECT: &'static str = "Recovery of your crates.io account";
+
+    fn body(&self) -> String {
+        let status = match self.completable_at {
+            Some(completable_at) => format!(
+                "The request to link your crates.io account to the GitHub account \
+{requested_by} was approved by the crates.io team. The account can be \
+linked to the new GitHub account from {completable_at} on.
+
+If you didn't request this, please contact help@crates.io immediately. You \
+can also cancel the recovery while signed in to crates.io with the GitHub \
+account that your crates.io account is currently linked to.",
+                requested_by = self.requested_by,
+                completable_at = completable_at.format("%Y-%m-%d at %H:%M:%S UTC"),
+            ),
+            None => format!(
+                "Your crates.io account is now linked to the GitHub account \
+{requested_by}. Signing in with the previous GitHub account is no longer \
+possible.
+
+If you didn't request this, please contact help@crates.io immediately.",
+                requested_by = self.requested_by,
+            ),
+        };
+
+        format!("Hello {}!\n\n{status}", self.user_name)
+    }
+}
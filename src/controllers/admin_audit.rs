@@ -0,0 +1,113 @@
+//! Endpoint for querying the audit log of admin actions
+//!
+//! Admin actions like freezes, quarantines and overrides are recorded by
+//! [`NewAdminAuditEntry`](crate::models::NewAdminAuditEntry) in the
+//! append-only `admin_audit_log` table.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{encode_seek, Page, PaginationOptions};
+use crate::models::{AdminAction, AdminAuditEntry, User};
+use crate::schema::{admin_audit_log, users};
+use crate::util::errors::forbidden;
+use crate::views::EncodableAdminAuditEntry;
+use axum::extract::Query;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use indexmap::IndexMap;
+
+#[derive(Deserialize)]
+pub struct AuditParams {
+    /// Login of the admin that performed the actions.
+    actor: Option<String>,
+    action: Option<AdminAction>,
+    /// Name of the crate that the actions affected.
+    #[serde(rename = "crate")]
+    krate: Option<String>,
+}
+
+/// Handles the `GET /api/private/audit` route.
+///
+/// Returns the entries of the audit log that match all of the given filters,
+/// most recent first.
+pub async fn list(
+    state: AppState,
+    Query(params): Query<AuditParams>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        if !auth.user().is_admin {
+            return Err(forbidden("must be an admin to query the audit log"));
+        }
+
+        let pagination = PaginationOptions::builder()
+            .enable_pages(false)
+            .enable_seek(true)
+            .gather(&req)?;
+
+        let mut query = admin_audit_log::table
+            .select(AdminAuditEntry::as_select())
+            .order(admin_audit_log::id.desc())
+            // One more entry is loaded to detect whether there is a next page
+            .limit(pagination.per_page + 1)
+            .into_boxed();
+
+        if let Some(actor) = &params.actor {
+            let Some(actor) = User::find_by_login(conn, actor).optional()? else {
+                return Ok(Json(
+                    json!({ "entries": [], "meta": { "next_page": null } }),
+                ));
+            };
+            query = query.filter(admin_audit_log::actor_id.eq(actor.id));
+        }
+        if let Some(action) = params.action {
+            query = query.filter(admin_audit_log::action.eq(action));
+        }
+        if let Some(krate) = &params.krate {
+            query = query.filter(admin_audit_log::crate_name.eq(krate));
+        }
+        if let Page::Seek(seek) = pagination.page {
+            let id: i32 = seek.decode()?;
+            query = query.filter(admin_audit_log::id.lt(id));
+        }
+
+        let mut entries: Vec<AdminAuditEntry> = query.load(conn)?;
+
+        let mut next_page = None;
+        if entries.len() > pagination.per_page as usize {
+            entries.pop();
+            if let Some(last) = entries.last() {
+                let params = IndexMap::from([("seek".into(), encode_seek(last.id)?)]);
+                next_page = Some(req.query_with_params(params));
+            }
+        }
+
+        let actor_ids = entries
+            .iter()
+            .filter_map(|entry| entry.actor_id)
+            .collect::<Vec<_>>();
+        let actors: Vec<User> = users::table
+            .filter(users::id.eq_any(actor_ids))
+            .load(conn)?;
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                let actor = entry
+                    .actor_id
+                    .and_then(|id| actors.iter().find(|user| user.id == id))
+                    .cloned();
+                EncodableAdminAuditEntry::from(entry, actor, state.config.avatar_proxy)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "entries": entries,
+            "meta": { "next_page": next_page },
+        })))
+    })
+    .await
+}
@@ -0,0 +1,141 @@
+//! Endpoint for the security advisories that are imported from the advisory
+//! database.
+//!
+//! See the `advisories` module for more details.
+
+use crate::advisories::{affected_ranges, VersionMatcher};
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Advisory, Crate, CrateVisibility, DependencyKind};
+use crate::schema::{crate_downloads, crates, default_versions, dependencies, versions};
+use crate::util::diesel::Conn;
+use crate::util::errors::custom;
+use crate::views::{EncodableAdvisory, EncodablePinnedDependent};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use semver::{Version, VersionReq};
+use std::collections::HashSet;
+
+/// The maximum number of dependents that are checked for requirements that
+/// are pinned to affected versions, starting with the most downloaded ones.
+const MAX_DEPENDENTS_CHECKED: i64 = 1000;
+
+/// The maximum number of pinned dependents that are returned.
+const MAX_PINNED_DEPENDENTS: usize = 10;
+
+/// Handles the `GET /api/v1/advisories/:advisory_id` route.
+///
+/// Returns the ranges of published versions that are affected by the
+/// advisory, the first versions that fix them, and the most downloaded
+/// crates that can not update to a fixed version, because their default
+/// version requires an affected version of the crate. Withdrawn advisories
+/// are returned without affected versions.
+pub async fn show(state: AppState, Path(advisory_id): Path<String>) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let advisory = Advisory::find(conn, &advisory_id)?.ok_or_else(|| {
+            let detail = format!("advisory `{advisory_id}` does not exist");
+            custom(StatusCode::NOT_FOUND, detail)
+        })?;
+
+        let krate: Option<Crate> = Crate::by_name(&advisory.crate_name)
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .first(conn)
+            .optional()?;
+
+        let (ranges, pinned_dependents) = match krate {
+            Some(krate) if advisory.withdrawn.is_none() => {
+                let matcher = VersionMatcher::new(&advisory.patched, &advisory.unaffected)
+                    .map_err(|error| server_error(format!("{error:#}")))?;
+
+                let versions: Vec<(String, bool)> = versions::table
+                    .filter(versions::crate_id.eq(krate.id))
+                    .select((versions::num, versions::yanked))
+                    .load(conn)?;
+
+                // Versions that are not valid semver were published before
+                // the validation was introduced, and can't be matched.
+                let versions = versions
+                    .into_iter()
+                    .filter_map(|(num, yanked)| Some((Version::parse(&num).ok()?, yanked)))
+                    .collect::<Vec<_>>();
+
+                let ranges = affected_ranges(&matcher, &versions);
+                let pinned_dependents = if ranges.is_empty() {
+                    Vec::new()
+                } else {
+                    pinned_dependents(conn, krate.id, &matcher, &versions)?
+                };
+
+                (ranges, pinned_dependents)
+            }
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        let advisory = EncodableAdvisory::from(advisory, ranges, pinned_dependents);
+        Ok(Json(json!({ "advisory": advisory })))
+    })
+    .await
+}
+
+/// Returns the most downloaded public crates whose default version depends
+/// on the crate with a requirement that matches an affected version, but no
+/// version that is neither affected nor yanked.
+fn pinned_dependents(
+    conn: &mut impl Conn,
+    crate_id: i32,
+    matcher: &VersionMatcher,
+    versions: &[(Version, bool)],
+) -> QueryResult<Vec<EncodablePinnedDependent>> {
+    let dependents: Vec<(String, String, String, i64)> = dependencies::table
+        .inner_join(versions::table.on(versions::id.eq(dependencies::version_id)))
+        .inner_join(default_versions::table.on(default_versions::version_id.eq(versions::id)))
+        .inner_join(crates::table.on(crates::id.eq(versions::crate_id)))
+        .inner_join(crate_downloads::table.on(crate_downloads::crate_id.eq(crates::id)))
+        .filter(dependencies::crate_id.eq(crate_id))
+        .filter(dependencies::kind.ne(DependencyKind::Dev))
+        .filter(versions::yanked.eq(false))
+        .filter(crates::visibility.eq(CrateVisibility::Public))
+        .order((crate_downloads::downloads.desc(), crates::name.asc()))
+        .select((
+            crates::name,
+            versions::num,
+            dependencies::req,
+            crate_downloads::downloads,
+        ))
+        .limit(MAX_DEPENDENTS_CHECKED)
+        .load(conn)?;
+
+    let is_pinned = |req: &str| {
+        let Ok(req) = VersionReq::parse(req) else {
+            return false;
+        };
+
+        let mut has_affected = false;
+        for (version, yanked) in versions.iter().filter(|(version, _)| req.matches(version)) {
+            if matcher.is_affected(version) {
+                has_affected = true;
+            } else if !yanked {
+                return false;
+            }
+        }
+        has_affected
+    };
+
+    // A crate can depend on the same crate multiple times, e.g. as a normal
+    // and a build dependency, so it is only listed once.
+    let mut seen = HashSet::new();
+    Ok(dependents
+        .into_iter()
+        .filter(|(name, _, req, _)| is_pinned(req) && seen.insert(name.clone()))
+        .take(MAX_PINNED_DEPENDENTS)
+        .map(
+            |(krate, version, req, downloads)| EncodablePinnedDependent {
+                krate,
+                version,
+                req,
+                downloads,
+            },
+        )
+        .collect())
+}
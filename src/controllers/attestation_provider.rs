@@ -0,0 +1,145 @@
+//! Endpoints for managing the accounts that can attach attestations to
+//! versions
+//!
+//! Attestation providers (e.g. independent rebuilders or audit
+//! organizations) have to be approved by an admin before they can submit
+//! attestations via the `POST /crates/:crate_id/:version/attestations` route.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, AttestationProvider, NewAdminAuditEntry, NewAttestationProvider, User,
+};
+use crate::schema::{attestation_providers, users};
+use crate::util::diesel::Conn;
+use crate::util::errors::{forbidden, not_found};
+use crate::views::EncodableAttestationProvider;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum length of the name of a provider.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Handles the `GET /api/private/attestation_providers` route.
+pub async fn list(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let providers: Vec<(AttestationProvider, User)> = attestation_providers::table
+            .inner_join(users::table)
+            .order(attestation_providers::name.asc())
+            .select((AttestationProvider::as_select(), users::all_columns))
+            .load(conn)?;
+
+        let providers = providers
+            .into_iter()
+            .map(|(provider, user)| {
+                EncodableAttestationProvider::from(provider, user, state.config.avatar_proxy)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "providers": providers })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct ProviderApproval {
+    name: String,
+}
+
+/// Handles the `PUT /api/private/attestation_providers/:user_id` route.
+///
+/// Approves the account as an attestation provider, or renames the provider
+/// if the account was approved already.
+pub async fn approve(
+    state: AppState,
+    Path(user_id): Path<i32>,
+    req: Parts,
+    Json(approval): Json<ProviderApproval>,
+) -> AppResult<Json<Value>> {
+    let name = approval.name.trim().to_string();
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        let detail = format!("`name` must be between 1 and {MAX_NAME_LENGTH} characters");
+        return Err(bad_request(detail));
+    }
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+
+        let user: User = users::table
+            .find(user_id)
+            .first(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        let provider = NewAttestationProvider {
+            user_id: user.id,
+            name: &name,
+            approved_by: admin.id,
+        }
+        .upsert(conn)?;
+
+        warn!(
+            "Admin {} approved {} as the attestation provider {:?}",
+            admin.gh_login, user.gh_login, provider.name
+        );
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::ApproveAttestationProvider)
+            .details(json!({ "user_id": user.id, "name": provider.name }))
+            .insert(conn)?;
+
+        let provider =
+            EncodableAttestationProvider::from(provider, user, state.config.avatar_proxy);
+        Ok(Json(json!({ "provider": provider })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/attestation_providers/:user_id` route.
+///
+/// Revokes the approval of the provider. The attestations that the provider
+/// submitted are deleted as well.
+pub async fn revoke(state: AppState, Path(user_id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+
+        let name: String = diesel::delete(attestation_providers::table.find(user_id))
+            .returning(attestation_providers::name)
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        warn!(
+            "Admin {} revoked the attestation provider {name:?}",
+            admin.gh_login
+        );
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::RevokeAttestationProvider)
+            .details(json!({ "user_id": user_id, "name": name }))
+            .insert(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden(
+            "must be an admin to manage attestation providers",
+        ));
+    }
+
+    Ok(user.clone())
+}
@@ -0,0 +1,163 @@
+//! Endpoint for yanking the versions that were published by a compromised
+//! account or API token
+//!
+//! The typical signature of a compromise is a burst of new versions of
+//! several crates, published by a single user or token within a short time
+//! window. Admins can yank all of these versions at once, after checking the
+//! matching versions with a dry run first.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{AdminAction, NewAdminAuditEntry, User, VersionAction};
+use crate::schema::{api_tokens, crates, version_owner_actions, versions};
+use crate::util::errors::{forbidden, not_found};
+use crate::util::rfc3339;
+use crate::views::EncodableBulkYankVersion;
+use crate::worker::jobs::BulkYankVersions;
+use chrono::NaiveDateTime;
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+#[derive(Deserialize)]
+pub struct BulkYankRequest {
+    /// Names of the crates whose versions are yanked.
+    crates: Vec<String>,
+    /// Login of the user that published the versions.
+    user: Option<String>,
+    /// ID of the API token that published the versions.
+    api_token_id: Option<i32>,
+    /// Start of the time window in which the versions were published.
+    #[serde(with = "rfc3339")]
+    from: NaiveDateTime,
+    /// End of the time window in which the versions were published.
+    #[serde(with = "rfc3339")]
+    until: NaiveDateTime,
+    /// Only report the matching versions, without yanking them.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Handles the `PUT /api/private/bulk_yanks` route.
+///
+/// Returns the versions of the given crates that were published within the
+/// time window by the given user or API token, and yanks them in a
+/// background job unless `dry_run` is set.
+pub async fn bulk_yank(
+    state: AppState,
+    req: Parts,
+    Json(request): Json<BulkYankRequest>,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let admin = auth.user();
+        if !admin.is_admin {
+            return Err(forbidden("must be an admin to bulk yank versions"));
+        }
+
+        if request.crates.is_empty() {
+            return Err(bad_request("at least one crate is required"));
+        }
+        if request.user.is_none() && request.api_token_id.is_none() {
+            return Err(bad_request("either a user or an API token is required"));
+        }
+        if request.from >= request.until {
+            return Err(bad_request("the time window must not be empty"));
+        }
+
+        let user_id = match &request.user {
+            Some(login) => {
+                let user = User::find_by_login(conn, login)
+                    .optional()?
+                    .ok_or_else(not_found)?;
+                Some(user.id)
+            }
+            None => None,
+        };
+
+        let token_user_id = match request.api_token_id {
+            Some(api_token_id) => {
+                let user_id = api_tokens::table
+                    .find(api_token_id)
+                    .select(api_tokens::user_id)
+                    .get_result::<i32>(conn)
+                    .optional()?
+                    .ok_or_else(not_found)?;
+                Some(user_id)
+            }
+            None => None,
+        };
+
+        let mut query = versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq_any(&request.crates))
+            .filter(versions::yanked.eq(false))
+            .filter(versions::created_at.ge(request.from))
+            .filter(versions::created_at.lt(request.until))
+            .select((
+                versions::id,
+                crates::name,
+                versions::num,
+                versions::created_at,
+            ))
+            .order((crates::name, versions::id))
+            .into_boxed();
+
+        if let Some(user_id) = user_id {
+            query = query.filter(versions::published_by.eq(user_id));
+        }
+
+        if let Some(api_token_id) = request.api_token_id {
+            let published_with_token = version_owner_actions::table
+                .filter(version_owner_actions::api_token_id.eq(api_token_id))
+                .filter(version_owner_actions::action.eq(VersionAction::Publish))
+                .select(version_owner_actions::version_id);
+
+            query = query.filter(versions::id.eq_any(published_with_token));
+        }
+
+        let versions: Vec<(i32, String, String, NaiveDateTime)> = query.load(conn)?;
+
+        if !request.dry_run && !versions.is_empty() {
+            let version_ids = versions.iter().map(|(id, ..)| *id).collect();
+            let compromised_user_id = user_id.or(token_user_id);
+            BulkYankVersions::new(version_ids, compromised_user_id, admin.id).enqueue(conn)?;
+
+            let yanked = versions
+                .iter()
+                .map(|(_, krate, num, _)| format!("{krate}@{num}"))
+                .collect::<Vec<_>>();
+            NewAdminAuditEntry::by_admin(admin, AdminAction::BulkYank)
+                .details(json!({
+                    "user": request.user,
+                    "api_token_id": request.api_token_id,
+                    "versions": yanked,
+                }))
+                .insert(conn)?;
+
+            warn!(
+                "Admin {} requested the bulk yank of {} versions",
+                admin.gh_login,
+                versions.len()
+            );
+        }
+
+        let versions = versions
+            .into_iter()
+            .map(|(id, krate, num, created_at)| EncodableBulkYankVersion {
+                id,
+                krate,
+                num,
+                created_at,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "dry_run": request.dry_run,
+            "versions": versions,
+        })))
+    })
+    .await
+}
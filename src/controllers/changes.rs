@@ -0,0 +1,191 @@
+//! Endpoints for the changes feed of the registry
+//!
+//! The changes feed allows downstream consumers (e.g. docs.rs, mirrors or
+//! security scanners) to tail the changes to the registry, without having
+//! to diff the git index.
+
+use crate::changes_feed::{self, Batch, ChangesFeed, MAX_EVENTS};
+use crate::controllers::frontend_prelude::*;
+use crate::models::RegistryEvent;
+use crate::util::errors::custom;
+use crate::views::EncodableRegistryEvent;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use futures_util::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::OwnedSemaphorePermit;
+
+/// How long a stream waits before it retries to load events from the
+/// database after a failure.
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often a comment is sent to keep idle streams from being closed by
+/// proxies and load balancers.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Handles the `GET /changes` route.
+///
+/// Returns the events that happened after the `since` cursor, in the order
+/// in which they happened. The `meta.next_cursor` field of the response can
+/// be used as the `since` parameter of the next request.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let since = parse_cursor(req.query().get("since"))?.unwrap_or(0);
+
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let delay = app.config.changes_feed_delay;
+        let events = changes_feed::load_events(conn, since, delay)?;
+
+        let next_cursor = events.last().map(|event| event.id).unwrap_or(since);
+        let more = events.len() as i64 == MAX_EVENTS;
+
+        let events = events
+            .into_iter()
+            .map(EncodableRegistryEvent::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "events": events,
+            "meta": {
+                "next_cursor": next_cursor.to_string(),
+                "more": more,
+            },
+        })))
+    })
+    .await
+}
+
+/// Handles the `GET /changes/stream` route.
+///
+/// Streams the events of the changes feed as Server-Sent Events. Clients
+/// can resume a stream with the standard `Last-Event-ID` header, or with the
+/// `since` query parameter. Without either, only events that happen after
+/// the stream was opened are sent.
+///
+/// New events are loaded by a single poller per instance, see
+/// `src/changes_feed.rs`, and the number of open streams is limited.
+pub async fn stream(
+    app: AppState,
+    req: Parts,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let permit = app.changes_feed.acquire_stream().ok_or_else(|| {
+        custom(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many open streams of the changes feed. Please try again later.",
+        )
+    })?;
+
+    let last_event_id = req
+        .headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+
+    let cursor = match parse_cursor(last_event_id.as_ref())? {
+        Some(cursor) => cursor,
+        None => match parse_cursor(req.query().get("since"))? {
+            Some(cursor) => cursor,
+            None => changes_feed::latest_cursor(&app).await?,
+        },
+    };
+
+    // The stream subscribes before it catches up, so that no batch of the
+    // poller is missed in between.
+    let receiver = ChangesFeed::subscribe(&app.0);
+
+    let state = StreamState {
+        app,
+        cursor,
+        pending: VecDeque::new(),
+        receiver,
+        catching_up: true,
+        _permit: permit,
+    };
+
+    let stream = stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((Ok(to_sse_event(event)), state));
+            }
+
+            if state.catching_up {
+                match changes_feed::load_events_async(&state.app, state.cursor).await {
+                    Ok(events) => {
+                        state.catching_up = events.len() as i64 == MAX_EVENTS;
+                        state.push(events);
+                    }
+                    Err(error) => {
+                        warn!("Failed to load registry events: {error}");
+                        tokio::time::sleep(RETRY_INTERVAL).await;
+                    }
+                }
+                continue;
+            }
+
+            match state.receiver.recv().await {
+                // Events between the cursor of the stream and the cursor of
+                // the batch are loaded from the database first, which also
+                // includes the events of the batch.
+                Ok(batch) if batch.since > state.cursor => state.catching_up = true,
+                Ok(batch) => state.push_batch(&batch),
+                Err(RecvError::Lagged(_)) => state.catching_up = true,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let keep_alive = KeepAlive::new().interval(HEARTBEAT_INTERVAL);
+    Ok(Sse::new(stream).keep_alive(keep_alive))
+}
+
+struct StreamState {
+    app: AppState,
+    cursor: i64,
+    pending: VecDeque<RegistryEvent>,
+    receiver: Receiver<Arc<Batch>>,
+    /// Whether the stream is behind the poller, and has to load the missed
+    /// events from the database.
+    catching_up: bool,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl StreamState {
+    fn push(&mut self, events: Vec<RegistryEvent>) {
+        if let Some(last) = events.last() {
+            self.cursor = last.id;
+        }
+        self.pending.extend(events);
+    }
+
+    fn push_batch(&mut self, batch: &Batch) {
+        let cursor = self.cursor;
+        let events = batch.events.iter().filter(|event| event.id > cursor);
+        self.push(events.cloned().collect());
+    }
+}
+
+fn to_sse_event(event: RegistryEvent) -> Event {
+    let id = event.id.to_string();
+    let event = EncodableRegistryEvent::from(event);
+    let kind: &str = event.kind.into();
+
+    Event::default()
+        .id(id)
+        .event(kind)
+        .json_data(event)
+        .expect("registry events are always serializable")
+}
+
+fn parse_cursor(cursor: Option<&String>) -> AppResult<Option<i64>> {
+    cursor
+        .map(|cursor| cursor.parse::<i64>())
+        .transpose()
+        .map_err(|_| bad_request("invalid `since` cursor"))
+}
@@ -3,6 +3,7 @@ use super::frontend_prelude::*;
 use crate::auth::AuthCheck;
 use crate::auth::Authentication;
 use crate::controllers::helpers::pagination::{Page, PaginationOptions};
+use crate::models::token::EndpointScope;
 use crate::models::{Crate, CrateOwnerInvitation, Rights, User};
 use crate::schema::{crate_owner_invitations, crates, users};
 use crate::util::diesel::Conn;
@@ -12,7 +13,6 @@ use crate::views::{
     InvitationResponse,
 };
 use chrono::{Duration, Utc};
-use diesel::{pg::Pg, sql_types::Bool};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use indexmap::IndexMap;
 use std::collections::{HashMap, HashSet};
@@ -27,9 +27,14 @@ pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
         let auth = AuthCheck::only_cookie().check(&req, conn)?;
         let user_id = auth.user_id();
 
+        let filter = ListFilter {
+            invitee_id: Some(user_id),
+            ..Default::default()
+        };
+
         let PrivateListResponse {
             invitations, users, ..
-        } = prepare_list(&app, &req, auth, ListFilter::InviteeId(user_id), conn)?;
+        } = prepare_list(&app, &req, auth, filter, conn)?;
 
         // The schema for the private endpoints is converted to the schema used by v1 endpoints.
         let crate_owner_invitations = invitations
@@ -61,30 +66,50 @@ pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
 }
 
 /// Handles the `GET /api/private/crate_owner_invitations` route.
+///
+/// Lists the pending invitations of a crate (`crate_name`), of a user
+/// (`invitee_id`), or of a user for a crate (both), with seek pagination.
+/// Unlike the v1 endpoint, this endpoint can also be used with API tokens.
 pub async fn private_list(app: AppState, req: Parts) -> AppResult<Json<PrivateListResponse>> {
     let conn = app.db_read().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let query = req.query();
 
-        let filter = if let Some(crate_name) = req.query().get("crate_name") {
-            ListFilter::CrateName(crate_name.clone())
-        } else if let Some(id) = req.query().get("invitee_id").and_then(|i| i.parse().ok()) {
-            ListFilter::InviteeId(id)
-        } else {
-            return Err(bad_request("missing or invalid filter"));
+        let invitee_id = match query.get("invitee_id") {
+            Some(id) => Some(
+                id.parse()
+                    .map_err(|_| bad_request("missing or invalid filter"))?,
+            ),
+            None => None,
+        };
+
+        let filter = ListFilter {
+            crate_name: query.get("crate_name").cloned(),
+            invitee_id,
         };
 
+        if filter.crate_name.is_none() && filter.invitee_id.is_none() {
+            return Err(bad_request("missing or invalid filter"));
+        }
+
+        let mut auth_check = AuthCheck::default().with_endpoint_scope(EndpointScope::ChangeOwners);
+        if let Some(crate_name) = &filter.crate_name {
+            auth_check = auth_check.for_crate(crate_name);
+        }
+        let auth = auth_check.check(&req, conn)?;
+
         let list = prepare_list(&app, &req, auth, filter, conn)?;
         Ok(Json(list))
     })
     .await
 }
 
-enum ListFilter {
-    CrateName(String),
-    InviteeId(i32),
+#[derive(Default)]
+struct ListFilter {
+    crate_name: Option<String>,
+    invitee_id: Option<i32>,
 }
 
 fn prepare_list(
@@ -107,36 +132,53 @@ fn prepare_list(
     let mut users = IndexMap::new();
     users.insert(user.id, user.clone());
 
-    let sql_filter: Box<dyn BoxableExpression<crate_owner_invitations::table, Pg, SqlType = Bool>> =
-        match filter {
-            ListFilter::CrateName(crate_name) => {
-                // Only allow crate owners to query pending invitations for their crate.
-                let krate: Crate = Crate::by_name(&crate_name).first(conn)?;
-                let owners = krate.owners(conn)?;
-                if Handle::current().block_on(user.rights(state, &owners))? != Rights::Full {
-                    let detail = "only crate owners can query pending invitations for their crate";
-                    return Err(forbidden(detail));
-                }
-
-                // Cache the crate name to avoid querying it from the database again
-                crate_names.insert(krate.id, krate.name.clone());
-
-                Box::new(crate_owner_invitations::crate_id.eq(krate.id))
-            }
-            ListFilter::InviteeId(invitee_id) => {
-                if invitee_id != user.id {
-                    let detail = "only the invitee can query their pending invitations";
-                    return Err(forbidden(detail));
-                }
-                Box::new(crate_owner_invitations::invited_user_id.eq(invitee_id))
+    if filter
+        .invitee_id
+        .is_some_and(|invitee_id| invitee_id != user.id)
+    {
+        let detail = "only the invitee can query their pending invitations";
+        return Err(forbidden(detail));
+    }
+
+    let mut crate_id = None;
+    if let Some(crate_name) = filter.crate_name {
+        let krate: Crate = Crate::by_name(&crate_name).first(conn)?;
+
+        // Only allow crate owners to query all pending invitations for their
+        // crate. Invitees can only query their own invitations for it.
+        if filter.invitee_id.is_none() {
+            let owners = krate.owners(conn)?;
+            if Handle::current().block_on(user.rights(state, &owners))? != Rights::Full {
+                let detail = "only crate owners can query pending invitations for their crate";
+                return Err(forbidden(detail));
             }
-        };
+        }
+
+        // Cache the crate name to avoid querying it from the database again
+        crate_names.insert(krate.id, krate.name.clone());
+
+        crate_id = Some(krate.id);
+    }
 
     // Load all the non-expired invitations matching the filter.
-    let expire_cutoff = Duration::days(config.ownership_invitations_expiration_days as i64);
-    let query = crate_owner_invitations::table
-        .filter(sql_filter)
-        .filter(crate_owner_invitations::created_at.gt((Utc::now() - expire_cutoff).naive_utc()))
+    let expiration_days = config.ownership_invitations_expiration_days;
+    let expire_cutoff = (Utc::now() - Duration::days(expiration_days as i64)).naive_utc();
+    let filtered = || {
+        let mut query = crate_owner_invitations::table
+            .filter(crate_owner_invitations::created_at.gt(expire_cutoff))
+            .into_boxed();
+        if let Some(invitee_id) = filter.invitee_id {
+            query = query.filter(crate_owner_invitations::invited_user_id.eq(invitee_id));
+        }
+        if let Some(crate_id) = crate_id {
+            query = query.filter(crate_owner_invitations::crate_id.eq(crate_id));
+        }
+        query
+    };
+
+    let total: i64 = filtered().count().get_result(conn)?;
+
+    let query = filtered()
         .order_by((
             crate_owner_invitations::crate_id,
             crate_owner_invitations::invited_user_id,
@@ -244,8 +286,15 @@ fn prepare_list(
 
     Ok(PrivateListResponse {
         invitations,
-        users: users.into_iter().map(|(_, user)| user.into()).collect(),
-        meta: ResponseMeta { next_page },
+        users: users
+            .into_iter()
+            .map(|(_, user)| EncodablePublicUser::from(user, state.config.avatar_proxy))
+            .collect(),
+        meta: ResponseMeta {
+            next_page,
+            total,
+            expiration_days,
+        },
     })
 }
 
@@ -259,6 +308,11 @@ pub struct PrivateListResponse {
 #[derive(Serialize)]
 struct ResponseMeta {
     next_page: Option<String>,
+    /// The number of pending invitations that match the filter, across all
+    /// pages.
+    total: i64,
+    /// The number of days after which invitations expire, see `expires_at`.
+    expiration_days: u64,
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,109 @@
+//! Endpoint for the admin report of the largest crates in the registry
+//!
+//! The report is based on the default version of every crate, so that it
+//! reflects what users actually download, and helps admins to notice size
+//! regressions before users start to complain about them.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::schema::{crates, default_versions, versions};
+use crate::sql::coalesce;
+use crate::util::diesel::Conn;
+use crate::util::errors::forbidden;
+use crate::views::EncodableCrateSize;
+use chrono::NaiveDateTime;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The default and maximum number of crates that are included in the report.
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// Handles the `GET /api/private/crates/largest` route.
+///
+/// Returns the crates with the largest default versions, sorted by the size
+/// of the crate file, or by the uncompressed size if `sort=uncompressed` is
+/// passed. Versions without a recorded size are ignored.
+pub async fn largest(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let limit = match req.query().get("limit") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| bad_request("invalid `limit` parameter"))?,
+        None => DEFAULT_LIMIT,
+    };
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(bad_request(format!(
+            "`limit` must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+
+    let sort_by_uncompressed = match req.query().get("sort").map(String::as_str) {
+        None | Some("compressed") => false,
+        Some("uncompressed") => true,
+        Some(_) => {
+            return Err(bad_request(
+                "`sort` must be either `compressed` or `uncompressed`",
+            ))
+        }
+    };
+
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let query = default_versions::table
+            .inner_join(versions::table)
+            .inner_join(crates::table)
+            .select((
+                crates::name,
+                versions::num,
+                versions::crate_size,
+                versions::uncompressed_size,
+                versions::created_at,
+            ))
+            .limit(limit)
+            .into_boxed();
+
+        let query = if sort_by_uncompressed {
+            query
+                .filter(versions::uncompressed_size.is_not_null())
+                .order((
+                    coalesce(versions::uncompressed_size, 0).desc(),
+                    crates::name,
+                ))
+        } else {
+            query
+                .filter(versions::crate_size.is_not_null())
+                .order((coalesce(versions::crate_size, 0).desc(), crates::name))
+        };
+
+        let rows: Vec<(String, String, Option<i32>, Option<i64>, NaiveDateTime)> =
+            query.load(conn)?;
+
+        let crates = rows
+            .into_iter()
+            .map(
+                |(name, version, crate_size, uncompressed_size, published_at)| EncodableCrateSize {
+                    name,
+                    version,
+                    crate_size,
+                    uncompressed_size,
+                    published_at,
+                },
+            )
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "crates": crates })))
+    })
+    .await
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<()> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    if !auth.user().is_admin {
+        return Err(forbidden("must be an admin to view the largest crates"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Endpoint for listing the public database dumps.
+//!
+//! The dumps are created by the `dump_db` background job and contain CSV
+//! files of the public information in the database. See
+//! <https://crates.io/data-access> for more information.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::DatabaseDump;
+use crate::schema::database_dump_deltas;
+use crate::views::EncodableDatabaseDump;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum number of database dumps that are listed.
+const MAX_DUMPS: i64 = 30;
+
+/// Handles the `GET /api/v1/db_dumps` route.
+pub async fn list(state: AppState) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let dumps = DatabaseDump::recent(conn, MAX_DUMPS)?;
+
+        let dump_ids = dumps.iter().map(|dump| dump.id).collect::<Vec<_>>();
+        let dumps_with_deltas: Vec<i32> = database_dump_deltas::table
+            .filter(database_dump_deltas::dump_id.eq_any(&dump_ids))
+            .select(database_dump_deltas::dump_id)
+            .distinct()
+            .load(conn)?;
+
+        let db_dumps = dumps
+            .into_iter()
+            .map(|dump| {
+                let has_deltas = dumps_with_deltas.contains(&dump.id);
+                EncodableDatabaseDump::from(dump, has_deltas, &state.storage)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "db_dumps": db_dumps })))
+    })
+    .await
+}
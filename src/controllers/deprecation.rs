@@ -0,0 +1,93 @@
+//! Endpoint for reviewing who still uses deprecated API surfaces
+//!
+//! The report lists the API tokens that were used for requests to the
+//! deprecated surfaces, so that admins can contact their owners before a
+//! surface is removed. See `src/deprecation.rs` for how surfaces are
+//! deprecated.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::User;
+use crate::schema::{api_tokens, deprecated_api_usage, users};
+use crate::util::errors::forbidden;
+use crate::views::{EncodableDeprecatedApiUsage, EncodableDeprecation, EncodablePublicUser};
+use chrono::NaiveDateTime;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::HashMap;
+
+/// Handles the `GET /api/private/deprecations` route.
+///
+/// Returns all deprecated surfaces, together with the API tokens that were
+/// used for requests to them, most used first.
+pub async fn report(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        if !auth.user().is_admin {
+            return Err(forbidden("must be an admin to view the deprecation report"));
+        }
+
+        let surfaces = state
+            .config
+            .deprecations
+            .iter()
+            .map(|deprecation| deprecation.surface())
+            .collect::<Vec<_>>();
+
+        let rows: Vec<(String, i32, String, i64, NaiveDateTime, NaiveDateTime, User)> =
+            deprecated_api_usage::table
+                .inner_join(api_tokens::table.inner_join(users::table))
+                .filter(deprecated_api_usage::surface.eq_any(&surfaces))
+                .select((
+                    deprecated_api_usage::surface,
+                    api_tokens::id,
+                    api_tokens::name,
+                    deprecated_api_usage::requests,
+                    deprecated_api_usage::first_used_at,
+                    deprecated_api_usage::last_used_at,
+                    users::all_columns,
+                ))
+                .order((
+                    deprecated_api_usage::requests.desc(),
+                    deprecated_api_usage::api_token_id,
+                ))
+                .load(conn)?;
+
+        let mut usage_by_surface: HashMap<String, Vec<EncodableDeprecatedApiUsage>> =
+            HashMap::new();
+        for (surface, token_id, token_name, requests, first_used_at, last_used_at, user) in rows {
+            let usage = EncodableDeprecatedApiUsage {
+                user: EncodablePublicUser::from(user, state.config.avatar_proxy),
+                token_id,
+                token_name,
+                requests,
+                first_used_at,
+                last_used_at,
+            };
+            usage_by_surface.entry(surface).or_default().push(usage);
+        }
+
+        let deprecations = state
+            .config
+            .deprecations
+            .iter()
+            .map(|deprecation| {
+                let surface = deprecation.surface();
+                let usage = usage_by_surface.remove(&surface).unwrap_or_default();
+                EncodableDeprecation {
+                    requests: usage.iter().map(|usage| usage.requests).sum(),
+                    surface,
+                    deprecated_at: deprecation.deprecated_at.naive_utc(),
+                    sunset_at: deprecation.sunset_at.map(|sunset_at| sunset_at.naive_utc()),
+                    link: deprecation.link.clone(),
+                    usage,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "deprecations": deprecations })))
+    })
+    .await
+}
@@ -0,0 +1,64 @@
+//! Webhook receiver for the documentation build status of docs.rs
+//!
+//! docs.rs notifies crates.io whenever the documentation build of a version
+//! starts or finishes, so that the status can be shown on crates.io without
+//! any cross-origin requests from the frontend.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::DocsBuildStatus;
+use crate::schema::{crates, versions};
+use crate::util::errors::{custom, forbidden, version_not_found};
+use crate::util::token::secure_compare;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+#[derive(Deserialize)]
+pub struct BuildStatus {
+    #[serde(rename = "crate")]
+    krate: String,
+    version: String,
+    status: DocsBuildStatus,
+}
+
+/// Handles the `PUT /api/private/docs_rs/build_status` route.
+pub async fn update_build_status(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<BuildStatus>,
+) -> AppResult<Response> {
+    let Some(expected_token) = &app.config.docs_rs_webhook_token else {
+        let detail = "The docs.rs webhook is disabled on this crates.io instance";
+        return Err(custom(StatusCode::NOT_FOUND, detail));
+    };
+
+    let provided_token = req
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if !provided_token.is_some_and(|token| secure_compare(token, expected_token)) {
+        return Err(forbidden("invalid or missing authorization token"));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let crate_id = crates::table
+            .select(crates::id)
+            .filter(crates::name.eq(&body.krate));
+
+        let updated = diesel::update(versions::table)
+            .filter(versions::crate_id.eq_any(crate_id))
+            .filter(versions::num.eq(&body.version))
+            .set(versions::docs_build_status.eq(body.status))
+            .execute(conn)?;
+
+        if updated == 0 {
+            return Err(version_not_found(&body.krate, &body.version));
+        }
+
+        ok_true()
+    })
+    .await
+}
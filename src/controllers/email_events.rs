@@ -0,0 +1,202 @@
+//! Webhook receivers for the bounce and complaint notifications of the email
+//! provider
+//!
+//! Addresses that bounce permanently, or whose recipients mark our emails as
+//! spam, are marked as undeliverable, so that no further notifications are
+//! sent to them. Repeatedly sending emails to such addresses damages the
+//! reputation of our sending domain. The users are asked to verify a new
+//! address via the `email_undeliverable` field of the `/me` API.
+//!
+//! Both Amazon SES (via SNS) and Mailgun are supported. Since neither can
+//! send custom headers, the requests are authenticated via HTTP basic
+//! authentication with the `EMAIL_WEBHOOK_TOKEN` as the password, which is
+//! part of the webhook URL that is configured at the provider.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Email, UndeliverableReason};
+use crate::util::errors::{custom, forbidden};
+use crate::util::token::secure_compare;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// A message that Amazon SNS delivers to the subscribed endpoint.
+#[derive(Deserialize)]
+#[serde(tag = "Type")]
+enum SnsMessage {
+    SubscriptionConfirmation {
+        #[serde(rename = "TopicArn")]
+        topic_arn: String,
+        #[serde(rename = "SubscribeURL")]
+        subscribe_url: String,
+    },
+    Notification {
+        /// The JSON encoded [SesNotification].
+        #[serde(rename = "Message")]
+        message: String,
+    },
+    UnsubscribeConfirmation,
+}
+
+/// A notification of Amazon SES. Notifications of SES event publishing use
+/// `eventType` instead of `notificationType`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesNotification {
+    #[serde(alias = "eventType")]
+    notification_type: String,
+    bounce: Option<SesBounce>,
+    complaint: Option<SesComplaint>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesBounce {
+    bounce_type: String,
+    bounced_recipients: Vec<SesRecipient>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesComplaint {
+    complained_recipients: Vec<SesRecipient>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesRecipient {
+    email_address: String,
+}
+
+/// Handles the `POST /api/private/email_events/ses` route.
+///
+/// SNS sends its messages with a `text/plain` content type, so the body is
+/// parsed manually.
+pub async fn ses(app: AppState, req: BytesRequest) -> AppResult<Response> {
+    let (req, body) = req.0.into_parts();
+    authenticate(&app, &req)?;
+
+    let message: SnsMessage =
+        serde_json::from_slice(&body).map_err(|_| bad_request("invalid SNS message"))?;
+
+    let notification = match message {
+        SnsMessage::SubscriptionConfirmation {
+            topic_arn,
+            subscribe_url,
+        } => {
+            // Subscriptions are confirmed manually, so that this endpoint
+            // never sends requests to URLs from the request body.
+            warn!("Confirm the subscription to {topic_arn} by visiting {subscribe_url}");
+            return ok_true();
+        }
+        SnsMessage::Notification { message } => message,
+        SnsMessage::UnsubscribeConfirmation => return ok_true(),
+    };
+
+    let notification: SesNotification =
+        serde_json::from_str(&notification).map_err(|_| bad_request("invalid SES notification"))?;
+
+    let (reason, recipients) = match notification.notification_type.as_str() {
+        // Transient bounces, e.g. because of a full mailbox, are ignored.
+        "Bounce" => match notification.bounce {
+            Some(bounce) if bounce.bounce_type == "Permanent" => {
+                (UndeliverableReason::Bounce, bounce.bounced_recipients)
+            }
+            _ => return ok_true(),
+        },
+        "Complaint" => match notification.complaint {
+            Some(complaint) => (
+                UndeliverableReason::Complaint,
+                complaint.complained_recipients,
+            ),
+            None => return ok_true(),
+        },
+        _ => return ok_true(),
+    };
+
+    let addresses = recipients.into_iter().map(|r| r.email_address).collect();
+    mark_undeliverable(app, addresses, reason).await
+}
+
+/// A webhook request of Mailgun.
+#[derive(Deserialize)]
+pub struct MailgunWebhook {
+    #[serde(rename = "event-data")]
+    event_data: MailgunEvent,
+}
+
+#[derive(Deserialize)]
+struct MailgunEvent {
+    event: String,
+    severity: Option<String>,
+    recipient: String,
+}
+
+/// Handles the `POST /api/private/email_events/mailgun` route.
+pub async fn mailgun(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<MailgunWebhook>,
+) -> AppResult<Response> {
+    authenticate(&app, &req)?;
+
+    let event = body.event_data;
+    let reason = match (event.event.as_str(), event.severity.as_deref()) {
+        // Temporary failures are retried by Mailgun, so they are ignored.
+        ("failed", Some("permanent")) => UndeliverableReason::Bounce,
+        ("complained", _) => UndeliverableReason::Complaint,
+        _ => return ok_true(),
+    };
+
+    mark_undeliverable(app, vec![event.recipient], reason).await
+}
+
+/// Checks that the request was sent by the email provider.
+fn authenticate(app: &AppState, req: &Parts) -> AppResult<()> {
+    let Some(expected_token) = &app.config.email_webhook_token else {
+        let detail = "The email webhooks are disabled on this crates.io instance";
+        return Err(custom(StatusCode::NOT_FOUND, detail));
+    };
+
+    let provided_token = req
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|value| STANDARD.decode(value).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .and_then(|credentials| {
+            let (_, password) = credentials.split_once(':')?;
+            Some(password.to_string())
+        });
+
+    if !provided_token.is_some_and(|token| secure_compare(&token, expected_token)) {
+        return Err(forbidden("invalid or missing authorization token"));
+    }
+
+    Ok(())
+}
+
+async fn mark_undeliverable(
+    app: AppState,
+    addresses: Vec<String>,
+    reason: UndeliverableReason,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        for address in &addresses {
+            let updated = Email::mark_undeliverable(conn, address, reason)?;
+            if updated > 0 {
+                info!(
+                    "Marked {updated} email addresses as undeliverable: {}",
+                    reason.as_str()
+                );
+            }
+        }
+
+        ok_true()
+    })
+    .await
+}
@@ -191,7 +191,7 @@ fn send_notification_email(
         url: &alert.url,
     };
 
-    state.emails.send(&recipient, email)?;
+    state.emails.send(conn, &recipient, email)?;
 
     Ok(())
 }
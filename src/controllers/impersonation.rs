@@ -0,0 +1,95 @@
+//! Endpoints for admins to impersonate other users, e.g. to reproduce bugs
+//! that only affect a specific account.
+//!
+//! See [`crate::middleware::impersonation`] for the restrictions that apply
+//! to impersonation sessions.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::middleware::impersonation::{Impersonation, IMPERSONATION_DURATION};
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::session::RequestSession;
+use crate::models::{
+    AdminAction, NewAdminAuditEntry, NewImpersonationAuditLogEntry, PersistentSession, User,
+};
+use crate::util::errors::{forbidden, not_found};
+use crate::views::EncodablePublicUser;
+use chrono::Utc;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `POST /api/private/impersonate/:user_id` route.
+///
+/// Signs the session of the admin in as the given user until the
+/// impersonation is ended or expires.
+pub async fn start(app: AppState, Path(user_id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let admin = auth.user();
+        if !admin.is_admin {
+            return Err(forbidden("must be an admin to impersonate users"));
+        }
+
+        let user = User::find(conn, user_id)
+            .optional()?
+            .ok_or_else(not_found)?;
+        if user.id == admin.id {
+            return Err(bad_request("admins can not impersonate themselves"));
+        }
+        if user.is_admin {
+            return Err(forbidden("admins can not be impersonated"));
+        }
+
+        let entry = NewImpersonationAuditLogEntry {
+            impersonator_id: admin.id,
+            user_id: user.id,
+            method: req.method.as_str(),
+            path: req.uri.path(),
+        };
+        entry.insert(conn)?;
+
+        NewAdminAuditEntry::by_admin(admin, AdminAction::Impersonate)
+            .details(json!({ "user_id": user.id, "login": user.gh_login }))
+            .insert(conn)?;
+
+        let impersonation = Impersonation {
+            impersonator_id: admin.id,
+            user_id: user.id,
+            expires_at: Utc::now() + IMPERSONATION_DURATION,
+        };
+        // The session identifier is rotated, so that the identifier of the
+        // admin's session before the impersonation can not be used with the
+        // privileges of the impersonated user.
+        PersistentSession::rotate(req.session(), conn, admin.id)?;
+        impersonation.start(req.session());
+
+        warn!(
+            "Admin {} started impersonating user {} ({})",
+            admin.gh_login, user.gh_login, user.id
+        );
+        req.request_log().add("impersonated", user.id);
+
+        Ok(Json(json!({
+            "impersonation": {
+                "user": EncodablePublicUser::from(user, app.config.avatar_proxy),
+                "expires_at": impersonation.expires_at.to_rfc3339(),
+            },
+        })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/impersonate` route.
+///
+/// Ends the impersonation and signs the session back in as the admin.
+pub async fn end(req: Parts) -> AppResult<Response> {
+    let session = req.session();
+    let impersonation = Impersonation::from_session(session)
+        .ok_or_else(|| bad_request("the session is not impersonating a user"))?;
+
+    impersonation.end(session);
+
+    ok_true()
+}
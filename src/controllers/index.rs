@@ -0,0 +1,182 @@
+//! Endpoints for maintaining and serving the package index
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{AdminAction, Crate, NewAdminAuditEntry};
+use crate::upstream::{parse_index_file, record_cached_crate};
+use crate::util::errors::{crate_not_found, custom, forbidden, internal, not_found};
+use crate::worker::jobs;
+use crates_io_index::{IndexConfig, Repository};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `POST /api/private/index/:crate_id/rebuild` route.
+///
+/// Enqueues background jobs that regenerate the git and sparse index files
+/// of the crate from the database, overwriting any corrupted or manually
+/// edited content. This endpoint is only available to crates.io admins.
+pub async fn rebuild(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+        if !user.is_admin {
+            return Err(forbidden("must be an admin to rebuild the index"));
+        }
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        warn!(
+            "Admin {} is rebuilding the index for {}",
+            user.gh_login, krate.name
+        );
+
+        jobs::enqueue_sync_to_index(&krate.name, conn)?;
+
+        NewAdminAuditEntry::by_admin(user, AdminAction::RebuildIndex)
+            .krate(&krate.name)
+            .insert(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `GET /config.json` route.
+///
+/// Responds with the same config that is synced to the root of the sparse
+/// index, so that clients can detect the features of this deployment via
+/// the API.
+pub async fn config(state: AppState) -> Json<IndexConfig> {
+    let location = state.storage.crate_files_location();
+    Json(jobs::index_config(&state.config, location))
+}
+
+/// Handles the `GET /index/*path` route, which is only available if this
+/// deployment is a pull-through cache, or if downloads require
+/// authentication.
+///
+/// Serves the files of the local sparse index. The index files of crates
+/// that don't exist locally are fetched from the upstream registry and
+/// cached (see [`crate::upstream`]).
+///
+/// If downloads require authentication, the index files require the same
+/// authentication as the downloads. The `config.json` file is served to
+/// anyone, so that cargo can discover that it has to send its token.
+pub async fn sparse_index_file(
+    state: AppState,
+    Path(path): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    if path == "config.json" {
+        return Ok(config(state).await.into_response());
+    }
+
+    let name = path.rsplit('/').next().unwrap_or_default().to_string();
+    if Crate::validate_crate_name("crate", &name).is_err()
+        || Repository::relative_index_file_for_url(&name) != path
+    {
+        return Err(not_found());
+    }
+
+    if state.config.download_auth_required {
+        let conn = state.db_read().await?;
+        let name = name.clone();
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            AuthCheck::default()
+                .allow_service_token()
+                .with_endpoint_scope(EndpointScope::Download)
+                .for_crate(&name)
+                .check(&req, conn)
+        })
+        .await?;
+    }
+
+    if let Some(content) = read_cached_index_file(&state, &name).await? {
+        return Ok(content.into_response());
+    }
+
+    let Some(upstream) = state.upstream.clone() else {
+        return Err(not_found());
+    };
+
+    // Concurrent requests for the same index file wait for the first one to
+    // fetch it, and then find it in the cache.
+    let _guard = state.upstream_fetches.lock(format!("index/{path}")).await;
+    if let Some(content) = read_cached_index_file(&state, &name).await? {
+        return Ok(content.into_response());
+    }
+
+    // The index files of local crates are written by the background worker,
+    // so they are never replaced by upstream crates.
+    let conn = state.db_read().await?;
+    let local_crate = spawn_blocking({
+        let name = name.clone();
+        move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            let local_crate = Crate::by_name(&name).first::<Crate>(conn).optional()?;
+            Ok::<_, BoxedAppError>(local_crate)
+        }
+    })
+    .await?;
+    if local_crate.is_some() {
+        return Err(not_found());
+    }
+
+    let content = upstream
+        .index_file(&name)
+        .await
+        .map_err(|error| {
+            warn!("Failed to fetch the index file of `{name}` from upstream: {error:#}");
+            custom(
+                StatusCode::BAD_GATEWAY,
+                "the crate could not be fetched from the upstream registry",
+            )
+        })?
+        .ok_or_else(not_found)?;
+
+    let entries = parse_index_file(&content)
+        .map_err(|e| internal(format!("invalid upstream index file of `{name}`: {e}")))?;
+    let Some(upstream_name) = entries.first().map(|entry| entry.name.clone()) else {
+        return Err(not_found());
+    };
+
+    let conn = state.db_write().await?;
+    spawn_blocking({
+        let upstream_name = upstream_name.clone();
+        move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            record_cached_crate(conn, &upstream_name)?;
+            Ok::<_, BoxedAppError>(())
+        }
+    })
+    .await?;
+
+    state
+        .storage
+        .sync_index(&upstream_name, Some(content.clone()))
+        .await
+        .map_err(|e| internal(format!("failed to cache index file: {e}")))?;
+
+    info!("Cached the index file of `{upstream_name}` from the upstream registry");
+
+    Ok(content.into_response())
+}
+
+/// Returns the content of the index file of the crate from the local sparse
+/// index, if it exists.
+async fn read_cached_index_file(state: &AppState, name: &str) -> AppResult<Option<String>> {
+    let cached = state.storage.read_index_file(name).await;
+    cached.map_err(|e| internal(format!("failed to read index file: {e}")))
+}
@@ -1,7 +1,70 @@
+pub mod bulk;
+pub mod critical;
+pub mod dependency_policy;
 pub mod downloads;
+pub mod export;
 pub mod follow;
+pub mod freeze;
+pub mod health;
 pub mod metadata;
+pub mod metadata_findings;
 pub mod owners;
+pub mod permissions;
+pub mod policy;
 pub mod publish;
+pub mod related;
+pub mod repository;
 pub mod search;
+pub mod settings;
+pub mod succession;
 pub mod versions;
+pub mod visibility;
+
+use super::prelude::*;
+
+use crate::auth::AuthCheck;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, CrateVisibility, Rights};
+use crate::util::diesel::Conn;
+use crate::util::errors::crate_not_found;
+use tokio::runtime::Handle;
+
+/// Ensures that the sender of the request is allowed to see the crate.
+///
+/// Public crates are visible to everyone, while private crates are only
+/// visible to their owners (including members of owning teams) and to
+/// crates.io admins. Everyone else gets a "crate not found" error, so that
+/// the existence of a private crate is not revealed.
+///
+/// Note that this function blocks on the GitHub API for team owners, so it
+/// must be called from a `spawn_blocking()` context.
+pub fn ensure_crate_visible(
+    state: &AppState,
+    req: &Parts,
+    krate: &Crate,
+    conn: &mut impl Conn,
+) -> AppResult<()> {
+    if krate.visibility == CrateVisibility::Public {
+        return Ok(());
+    }
+
+    // Tokens with the `download` scope need to see the private crates of
+    // their owner to be able to download them.
+    let auth = AuthCheck::default()
+        .with_endpoint_scope(EndpointScope::Download)
+        .for_crate(&krate.name)
+        .check(req, conn)
+        .map_err(|_| crate_not_found(&krate.name))?;
+
+    let user = auth.user();
+    if user.is_admin {
+        return Ok(());
+    }
+
+    let owners = krate.owners(conn)?;
+    if Handle::current().block_on(user.rights(state, &owners))? < Rights::Publish {
+        return Err(crate_not_found(&krate.name));
+    }
+
+    Ok(())
+}
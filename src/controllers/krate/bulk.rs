@@ -0,0 +1,349 @@
+//! Endpoint for fetching the metadata of many crates at once
+//!
+//! This endpoint is meant for mirror operators and package search engines,
+//! which would otherwise have to crawl the per-crate endpoint one request at
+//! a time.
+
+use crate::auth::AuthCheck;
+use chrono::{DateTime, NaiveDateTime};
+use diesel::dsl::sql;
+use diesel::sql_types::{Array, BigInt, Bool, Integer};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use hex::ToHex;
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{decode_seek, encode_seek};
+use crate::models::{
+    load_default_versions, Category, Crate, CrateVisibility, Keyword, TopVersions, User, Version,
+    VersionOwnerAction,
+};
+use crate::rate_limiter::LimitedAction;
+use crate::schema::*;
+use crate::sql::canon_crate_name;
+use crate::views::{EncodableCategory, EncodableCrate, EncodableKeyword, EncodableVersion};
+
+/// The maximum number of crates that are returned by a single request.
+const MAX_CRATES: usize = 100;
+
+/// The maximum number of versions that are returned per crate. Only the
+/// most recently published versions of crates with more versions are
+/// returned, and the remaining versions have to be fetched via the per-crate
+/// endpoint.
+const MAX_VERSIONS_PER_CRATE: i64 = 100;
+
+/// The endpoint requires authentication, so responses must not be stored by
+/// shared caches.
+const CACHE_CONTROL: &str = "private,max-age=300";
+
+/// Handles the `GET /bulk/crates` route.
+///
+/// Returns the full metadata of the requested crates, either selected by
+/// name (`?ids=foo,bar`) or by the time of their last update
+/// (`?updated_since=2024-01-01T00:00:00Z`). Private crates are never
+/// included in the response.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Response> {
+    let mode = BulkMode::from_request(&req)?;
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default().check(&req, conn)?;
+
+        let query = crates::table
+            .inner_join(crate_downloads::table)
+            .left_join(recent_crate_downloads::table)
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .select((
+                Crate::as_select(),
+                crate_downloads::downloads,
+                recent_crate_downloads::downloads.nullable(),
+            ))
+            .into_boxed();
+
+        let query = match &mode {
+            BulkMode::Names(names) => {
+                let names = names.iter().map(|name| canonicalize(name));
+                query
+                    .filter(canon_crate_name(crates::name).eq_any(names.collect::<Vec<_>>()))
+                    .order(crates::name.asc())
+            }
+            &BulkMode::UpdatedSince { updated_at, id } => query
+                .filter(
+                    crates::updated_at
+                        .gt(updated_at)
+                        .or(crates::updated_at.eq(updated_at).and(crates::id.gt(id))),
+                )
+                .order((crates::updated_at.asc(), crates::id.asc())),
+        };
+
+        let data: Vec<(Crate, i64, Option<i64>)> = query.limit(MAX_CRATES as i64).load(conn)?;
+
+        // Only the incremental mode is paginated, since the number of
+        // requested names is already limited.
+        let next_page = match (&mode, data.last()) {
+            (BulkMode::UpdatedSince { .. }, Some((last, _, _))) if data.len() == MAX_CRATES => {
+                let seek = encode_seek((last.updated_at, last.id))?;
+                let params = IndexMap::from([("seek".into(), seek)]);
+                Some(req.query_with_params(params))
+            }
+            _ => None,
+        };
+
+        // Clients that already have an up-to-date copy of the response are
+        // answered before the remaining queries, and don't use up a rate
+        // limit token.
+        let etag = etag(&data, next_page.as_deref());
+        if is_fresh(&req, &etag) {
+            return Ok(not_modified(etag));
+        }
+
+        app.rate_limiter
+            .check_rate_limit(auth.user_id(), LimitedAction::BulkMetadata, conn)?;
+
+        let crate_ids = data
+            .iter()
+            .map(|(krate, _, _)| krate.id)
+            .collect::<Vec<_>>();
+
+        let latest_versions = sql::<Bool>(
+            "versions.id IN (SELECT id FROM (\
+                SELECT id, row_number() OVER (PARTITION BY crate_id ORDER BY id DESC) AS rank \
+                FROM versions WHERE crate_id = ANY(",
+        )
+        .bind::<Array<Integer>, _>(&crate_ids)
+        .sql(")) AS ranked WHERE rank <= ")
+        .bind::<BigInt, _>(MAX_VERSIONS_PER_CRATE)
+        .sql(")");
+
+        let mut versions_and_publishers: Vec<(Version, Option<User>)> = versions::table
+            .left_outer_join(users::table)
+            .filter(latest_versions)
+            .select((versions::all_columns, users::all_columns.nullable()))
+            .order(versions::id.asc())
+            .load(conn)?;
+        versions_and_publishers.sort_by_cached_key(|(version, _)| {
+            (
+                version.crate_id,
+                std::cmp::Reverse(semver::Version::parse(&version.num).ok()),
+            )
+        });
+
+        let versions = versions_and_publishers
+            .iter()
+            .map(|(v, _)| v)
+            .cloned()
+            .collect::<Vec<_>>();
+        let actions = VersionOwnerAction::for_versions(conn, &versions)?;
+
+        let keywords: Vec<(i32, Keyword)> = crates_keywords::table
+            .inner_join(keywords::table)
+            .filter(crates_keywords::crate_id.eq_any(&crate_ids))
+            .select((crates_keywords::crate_id, keywords::all_columns))
+            .load(conn)?;
+
+        let categories: Vec<(i32, Category)> = crates_categories::table
+            .inner_join(categories::table)
+            .filter(crates_categories::crate_id.eq_any(&crate_ids))
+            .select((crates_categories::crate_id, categories::all_columns))
+            .load(conn)?;
+
+        let mut versions_by_crate: HashMap<i32, Vec<&Version>> = HashMap::new();
+        for version in &versions {
+            versions_by_crate
+                .entry(version.crate_id)
+                .or_default()
+                .push(version);
+        }
+
+        let mut keywords_by_crate: HashMap<i32, Vec<Keyword>> = HashMap::new();
+        let mut all_keywords = BTreeMap::new();
+        for (crate_id, keyword) in keywords {
+            all_keywords.insert(keyword.id, keyword.clone());
+            keywords_by_crate.entry(crate_id).or_default().push(keyword);
+        }
+
+        let mut categories_by_crate: HashMap<i32, Vec<Category>> = HashMap::new();
+        let mut all_categories = BTreeMap::new();
+        for (crate_id, category) in categories {
+            all_categories.insert(category.id, category.clone());
+            categories_by_crate
+                .entry(crate_id)
+                .or_default()
+                .push(category);
+        }
+
+        let mut top_versions = TopVersions::for_crates(conn, &crate_ids)?;
+        let default_versions = load_default_versions(&crate_ids, conn)?;
+
+        let encodable_crates = data
+            .iter()
+            .map(|(krate, downloads, recent_downloads)| {
+                let crate_versions = versions_by_crate.remove(&krate.id).unwrap_or_default();
+                let version_ids = crate_versions.iter().map(|v| v.id).collect();
+                let top_versions = top_versions.remove(&krate.id).unwrap_or_default();
+                let default_version = default_versions.get(&krate.id).map(String::as_str);
+                let keywords = keywords_by_crate.remove(&krate.id).unwrap_or_default();
+                let categories = categories_by_crate.remove(&krate.id).unwrap_or_default();
+
+                EncodableCrate::from(
+                    krate.clone(),
+                    Some(&top_versions),
+                    default_version,
+                    Some(version_ids),
+                    Some(keywords.as_slice()),
+                    Some(categories.as_slice()),
+                    Some(vec![]),
+                    false,
+                    *downloads,
+                    *recent_downloads,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let crate_names = data
+            .iter()
+            .map(|(krate, _, _)| (krate.id, krate.name.as_str()))
+            .collect::<HashMap<_, _>>();
+
+        let encodable_versions = versions_and_publishers
+            .into_iter()
+            .zip(actions)
+            .map(|((v, pb), aas)| {
+                let crate_name = crate_names[&v.crate_id];
+                EncodableVersion::from(v, crate_name, pb, aas, app.config.avatar_proxy)
+            })
+            .collect::<Vec<_>>();
+
+        let encodable_keywords = all_keywords
+            .into_values()
+            .map(Keyword::into)
+            .collect::<Vec<EncodableKeyword>>();
+
+        let encodable_categories = all_categories
+            .into_values()
+            .map(Category::into)
+            .collect::<Vec<EncodableCategory>>();
+
+        let body = json!({
+            "crates": encodable_crates,
+            "versions": encodable_versions,
+            "keywords": encodable_keywords,
+            "categories": encodable_categories,
+            "meta": {
+                "next_page": next_page,
+            },
+        });
+
+        let headers = [
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+        ];
+        Ok((headers, Json(body)).into_response())
+    })
+    .await
+}
+
+/// Derives a weak `ETag` from the selected crates, so that it can be
+/// computed before the remaining data of the response is loaded.
+///
+/// Changes to the versions of a crate also bump the `updated_at` timestamp of
+/// the crate via a database trigger. Changes to the publishers of versions,
+/// or to the crate counts of keywords and categories, are not reflected, which
+/// is why the `ETag` is weak.
+fn etag(data: &[(Crate, i64, Option<i64>)], next_page: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    for (krate, downloads, recent_downloads) in data {
+        let fingerprint = (krate.id, krate.updated_at, downloads, recent_downloads);
+        hasher.update(format!("{fingerprint:?}\n"));
+    }
+    hasher.update(next_page.unwrap_or_default());
+
+    format!("W/\"{}\"", hasher.finalize().encode_hex::<String>())
+}
+
+/// Returns whether the client already has an up-to-date copy of the response
+/// with the given `ETag`.
+fn is_fresh(req: &Parts, etag: &str) -> bool {
+    // `If-None-Match` uses the weak comparison, so the `W/` prefixes are
+    // ignored.
+    let opaque_tag = |value: &str| value.trim().trim_start_matches("W/").to_string();
+    let etag = opaque_tag(etag);
+
+    req.headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| opaque_tag(value) == etag || value.trim() == "*")
+}
+
+fn not_modified(etag: String) -> Response {
+    let headers = [
+        (header::ETAG, etag),
+        (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+    ];
+    (StatusCode::NOT_MODIFIED, headers).into_response()
+}
+
+fn canonicalize(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+#[derive(Debug)]
+enum BulkMode {
+    Names(Vec<String>),
+    UpdatedSince { updated_at: NaiveDateTime, id: i32 },
+}
+
+impl BulkMode {
+    fn from_request(req: &Parts) -> AppResult<Self> {
+        let params = req.query();
+        let ids = params.get("ids");
+        let updated_since = params.get("updated_since");
+        let seek = params.get("seek");
+
+        match (ids, updated_since, seek) {
+            (Some(ids), None, None) => {
+                let mut names = ids
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+
+                if names.is_empty() {
+                    return Err(bad_request("`ids` must contain at least one crate name"));
+                }
+                if names.len() > MAX_CRATES {
+                    let detail = format!("at most {MAX_CRATES} crates can be requested at once");
+                    return Err(bad_request(detail));
+                }
+
+                Ok(Self::Names(names))
+            }
+            (None, _, Some(seek)) => {
+                let (updated_at, id) =
+                    decode_seek(seek).map_err(|_| bad_request("invalid seek parameter"))?;
+                Ok(Self::UpdatedSince { updated_at, id })
+            }
+            (None, Some(updated_since), None) => {
+                let updated_at = DateTime::parse_from_rfc3339(updated_since)
+                    .map_err(|_| bad_request("`updated_since` must be an RFC 3339 timestamp"))?
+                    .naive_utc();
+                Ok(Self::UpdatedSince { updated_at, id: 0 })
+            }
+            (None, None, None) => Err(bad_request(
+                "either `ids` or `updated_since` must be provided",
+            )),
+            _ => Err(bad_request(
+                "`ids` can not be combined with `updated_since`",
+            )),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Endpoints for designating crates as critical for the ecosystem
+//!
+//! All owners of critical crates must have two-factor authentication enabled
+//! on their GitHub account, yanks of widely used versions have to be confirmed
+//! via email, and removals of owners are held back until two admins approved
+//! them or a waiting period is over.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, Crate, NewAdminAuditEntry, NewCriticalCrate, PendingOwnerRemoval, User,
+};
+use crate::schema::{crates, critical_crates};
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, forbidden, not_found};
+use crate::views::{EncodableCriticalCrate, EncodablePendingOwnerRemoval};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `PUT /api/private/crates/:crate_id/critical` route.
+///
+/// Designates the crate as critical. Designating a crate that already is
+/// critical returns the existing designation.
+pub async fn designate(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let krate = load_crate(&crate_name, conn)?;
+
+        warn!(
+            "Admin {} is designating {} as critical",
+            admin.gh_login, krate.name
+        );
+
+        let critical = NewCriticalCrate {
+            crate_id: krate.id,
+            designated_by: admin.id,
+        }
+        .upsert(conn)?;
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::DesignateCriticalCrate)
+            .krate(&krate.name)
+            .insert(conn)?;
+
+        let designated_by = User::find(conn, critical.designated_by)?;
+        let critical = EncodableCriticalCrate::from(
+            critical,
+            krate.name,
+            designated_by,
+            app.config.avatar_proxy,
+        );
+        Ok(Json(json!({ "critical": critical })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/crates/:crate_id/critical` route.
+///
+/// Pending removals of owners of the crate are applied by the next run of the
+/// `apply_pending_owner_removals` job, regardless of their waiting period.
+pub async fn revoke(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let krate = load_crate(&crate_name, conn)?;
+
+        let deleted = diesel::delete(critical_crates::table.find(krate.id)).execute(conn)?;
+        if deleted == 0 {
+            return Err(not_found());
+        }
+
+        warn!(
+            "Admin {} revoked the critical designation of {}",
+            admin.gh_login, krate.name
+        );
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::RevokeCriticalCrate)
+            .krate(&krate.name)
+            .insert(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `GET /api/private/owner_removals` route.
+///
+/// Lists the removals of owners of critical crates that have not been
+/// applied yet.
+pub async fn list_owner_removals(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let removals = PendingOwnerRemoval::all(conn)?
+            .into_iter()
+            .map(|removal| encode_owner_removal(&app, removal, conn))
+            .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(Json(json!({ "owner_removals": removals })))
+    })
+    .await
+}
+
+/// Handles the `PUT /api/private/owner_removals/:id/approve` route.
+///
+/// The first approval is only recorded, and the removal is applied once a
+/// second admin approves it too.
+pub async fn approve_owner_removal(
+    app: AppState,
+    Path(id): Path<i32>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let removal = PendingOwnerRemoval::find(conn, id)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        if admin.id == removal.requested_by {
+            return Err(forbidden(
+                "admins can not approve the removals that they requested",
+            ));
+        }
+
+        let crate_name: String = crates::table
+            .find(removal.crate_id)
+            .select(crates::name)
+            .first(conn)?;
+        let owner_id = removal.owner_id;
+
+        let applied = removal.approve(conn, &admin)?;
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::ApproveOwnerRemoval)
+            .krate(&crate_name)
+            .details(json!({ "removal_id": id, "owner_id": owner_id, "applied": applied }))
+            .insert(conn)?;
+        if applied {
+            warn!(
+                "Admin {} approved and applied the owner removal {id}",
+                admin.gh_login
+            );
+        } else {
+            warn!("Admin {} approved the owner removal {id}", admin.gh_login);
+        }
+
+        Ok(Json(json!({ "ok": true, "applied": applied })))
+    })
+    .await
+}
+
+fn encode_owner_removal(
+    app: &AppState,
+    removal: PendingOwnerRemoval,
+    conn: &mut impl Conn,
+) -> AppResult<EncodablePendingOwnerRemoval> {
+    let krate: String = crates::table
+        .find(removal.crate_id)
+        .select(crates::name)
+        .first(conn)?;
+    let owner = removal.owner(conn)?;
+    let requested_by = User::find(conn, removal.requested_by)?;
+    let approved_by = removal
+        .approved_by
+        .map(|id| User::find(conn, id))
+        .transpose()?;
+
+    Ok(EncodablePendingOwnerRemoval::from(
+        removal,
+        krate,
+        owner,
+        requested_by,
+        approved_by,
+        app.config.avatar_proxy,
+    ))
+}
+
+fn load_crate(crate_name: &str, conn: &mut impl Conn) -> AppResult<Crate> {
+    Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden("must be an admin to manage critical crates"));
+    }
+
+    Ok(user.clone())
+}
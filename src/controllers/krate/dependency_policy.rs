@@ -0,0 +1,170 @@
+//! Endpoints for managing the exceptions of the registry dependency policy
+//!
+//! New versions must not depend on quarantined versions of other crates.
+//! Admins can exempt a dependency from this policy, e.g. if only an old
+//! version of a widely used crate was quarantined after a compromise.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, Crate, DependencyPolicyException, NewAdminAuditEntry,
+    NewDependencyPolicyException, User,
+};
+use crate::schema::{crates, dependency_policy_exceptions, users};
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, forbidden, not_found};
+use crate::views::EncodableDependencyPolicyException;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum length of the reason of an exception.
+const MAX_REASON_LENGTH: usize = 1000;
+
+/// Handles the `GET /api/private/dependency_policy_exceptions` route.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let exceptions: Vec<(DependencyPolicyException, String, User)> =
+            dependency_policy_exceptions::table
+                .inner_join(crates::table)
+                .inner_join(users::table)
+                .order(crates::name.asc())
+                .select((
+                    DependencyPolicyException::as_select(),
+                    crates::name,
+                    users::all_columns,
+                ))
+                .load(conn)?;
+
+        let exceptions = exceptions
+            .into_iter()
+            .map(|(exception, krate, created_by)| {
+                EncodableDependencyPolicyException::from(
+                    exception,
+                    krate,
+                    created_by,
+                    app.config.avatar_proxy,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "exceptions": exceptions })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct ExceptionRequest {
+    reason: String,
+}
+
+/// Handles the `PUT /api/private/crates/:crate_id/dependency_policy_exception` route.
+///
+/// Allows new versions of other crates to depend on the crate, even if some
+/// of its versions are quarantined. Replaces the reason if the crate is
+/// already exempted.
+pub async fn add_exception(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<ExceptionRequest>,
+) -> AppResult<Json<Value>> {
+    let reason = body.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(bad_request("a reason is required to add an exception"));
+    }
+    if reason.len() > MAX_REASON_LENGTH {
+        return Err(bad_request(format!(
+            "the reason must not be longer than {MAX_REASON_LENGTH} characters"
+        )));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let krate = load_crate(&crate_name, conn)?;
+
+        warn!(
+            "Admin {} is exempting {} from the dependency policy: {reason}",
+            admin.gh_login, krate.name
+        );
+
+        let exception = NewDependencyPolicyException {
+            crate_id: krate.id,
+            reason: &reason,
+            created_by: admin.id,
+        }
+        .upsert(conn)?;
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::AddDependencyPolicyException)
+            .krate(&krate.name)
+            .details(json!({ "reason": reason }))
+            .insert(conn)?;
+
+        let exception = EncodableDependencyPolicyException::from(
+            exception,
+            krate.name,
+            admin,
+            app.config.avatar_proxy,
+        );
+        Ok(Json(json!({ "exception": exception })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/crates/:crate_id/dependency_policy_exception` route.
+pub async fn remove_exception(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let krate = load_crate(&crate_name, conn)?;
+
+        let deleted =
+            diesel::delete(dependency_policy_exceptions::table.find(krate.id)).execute(conn)?;
+        if deleted == 0 {
+            return Err(not_found());
+        }
+
+        warn!(
+            "Admin {} removed the dependency policy exception of {}",
+            admin.gh_login, krate.name
+        );
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::RemoveDependencyPolicyException)
+            .krate(&krate.name)
+            .insert(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+fn load_crate(crate_name: &str, conn: &mut impl Conn) -> AppResult<Crate> {
+    Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden(
+            "must be an admin to manage dependency policy exceptions",
+        ));
+    }
+
+    Ok(user.clone())
+}
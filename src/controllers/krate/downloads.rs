@@ -2,74 +2,268 @@
 //!
 //! The endpoint for downloading a crate and exposing version specific
 //! download counts are located in `version::downloads`.
+//!
+//! The download counts per country are only exposed in aggregated form.
+//! Countries with only a few downloads are not listed individually, since
+//! their counts could reveal the activity of individual users.
 
 use std::cmp;
+use std::collections::HashMap;
 
+use super::ensure_crate_visible;
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, Version, VersionDownload};
-use crate::schema::{crates, version_downloads, versions};
+use crate::models::{Crate, CrateDownloadSummary, Version, VersionDownload};
+use crate::schema::{
+    crate_download_summaries, crate_region_downloads, version_downloads, versions,
+};
 use crate::sql::to_char;
 use crate::util::errors::crate_not_found;
+use crate::util::rfc3339;
 use crate::views::EncodableVersionDownload;
-use diesel_async::RunQueryDsl;
+use chrono::NaiveDateTime;
+use crates_io_cdn_logs::regions::continent;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The minimum number of downloads within the reporting period that a
+/// country needs to be listed individually.
+const MIN_COUNTRY_DOWNLOADS: i64 = 100;
 
 /// Handles the `GET /crates/:crate_id/downloads` route.
-pub async fn downloads(state: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
-    let mut conn = state.db_read().await?;
-
-    use diesel::dsl::*;
-    use diesel::sql_types::BigInt;
-
-    let crate_id: i32 = Crate::by_name(&crate_name)
-        .select(crates::id)
-        .first(&mut conn)
-        .await
-        .optional()?
-        .ok_or_else(|| crate_not_found(&crate_name))?;
-
-    let mut versions: Vec<Version> = versions::table
-        .filter(versions::crate_id.eq(crate_id))
-        .load(&mut conn)
-        .await?;
-
-    versions.sort_by_cached_key(|version| cmp::Reverse(semver::Version::parse(&version.num).ok()));
-    let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
-
-    let downloads = VersionDownload::belonging_to(latest_five)
-        .filter(version_downloads::date.gt(date(now - 90.days())))
-        .order((
-            version_downloads::date.asc(),
-            version_downloads::version_id.desc(),
-        ))
-        .load(&mut conn)
-        .await?
-        .into_iter()
-        .map(VersionDownload::into)
-        .collect::<Vec<EncodableVersionDownload>>();
-
-    let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
-    let extra: Vec<ExtraDownload> = VersionDownload::belonging_to(rest)
-        .select((
-            to_char(version_downloads::date, "YYYY-MM-DD"),
-            sum_downloads,
-        ))
-        .filter(version_downloads::date.gt(date(now - 90.days())))
-        .group_by(version_downloads::date)
-        .order(version_downloads::date.asc())
-        .load(&mut conn)
-        .await?;
-
-    #[derive(Serialize, Queryable)]
-    struct ExtraDownload {
-        date: String,
-        downloads: i64,
-    }
+pub async fn downloads(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        use diesel::dsl::*;
+        use diesel::sql_types::BigInt;
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let mut versions: Vec<Version> = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .load(conn)?;
+
+        versions
+            .sort_by_cached_key(|version| cmp::Reverse(semver::Version::parse(&version.num).ok()));
+        let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
+
+        let downloads = VersionDownload::belonging_to(latest_five)
+            .filter(version_downloads::date.gt(date(now - 90.days())))
+            .order((
+                version_downloads::date.asc(),
+                version_downloads::version_id.desc(),
+            ))
+            .load(conn)?
+            .into_iter()
+            .map(VersionDownload::into)
+            .collect::<Vec<EncodableVersionDownload>>();
+
+        let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
+        let extra: Vec<ExtraDownload> = VersionDownload::belonging_to(rest)
+            .select((
+                to_char(version_downloads::date, "YYYY-MM-DD"),
+                sum_downloads,
+            ))
+            .filter(version_downloads::date.gt(date(now - 90.days())))
+            .group_by(version_downloads::date)
+            .order(version_downloads::date.asc())
+            .load(conn)?;
+
+        Ok(Json(json!({
+            "version_downloads": downloads,
+            "meta": {
+                "extra_downloads": extra,
+            },
+        })))
+    })
+    .await
+}
+
+#[derive(Serialize, Queryable)]
+struct ExtraDownload {
+    date: String,
+    downloads: i64,
+}
+
+/// Handles the `GET /crates/:crate_id/downloads/regions` route.
+///
+/// Returns the downloads of the last 90 days per continent and country.
+/// Countries with less than [`MIN_COUNTRY_DOWNLOADS`] downloads are only
+/// included in `meta.other_downloads`, and not in the continent totals, so
+/// that their counts can't be derived from the response.
+pub async fn region_downloads(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        use diesel::dsl::*;
+        use diesel::sql_types::BigInt;
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let sum_downloads = sql::<BigInt>("SUM(crate_region_downloads.downloads)");
+        let rows: Vec<(String, i64)> = crate_region_downloads::table
+            .filter(crate_region_downloads::crate_id.eq(krate.id))
+            .filter(crate_region_downloads::date.gt(date(now - 90.days())))
+            .group_by(crate_region_downloads::country)
+            .select((crate_region_downloads::country, sum_downloads))
+            .load(conn)?;
+
+        let mut countries = Vec::new();
+        let mut continents: HashMap<&str, i64> = HashMap::new();
+        let mut other_downloads = 0;
+        for (country, downloads) in rows {
+            match continent(&country) {
+                Some(continent) if downloads >= MIN_COUNTRY_DOWNLOADS => {
+                    *continents.entry(continent).or_default() += downloads;
+                    countries.push(CountryDownloads {
+                        country,
+                        continent,
+                        downloads,
+                    });
+                }
+                _ => other_downloads += downloads,
+            }
+        }
+
+        countries.sort_by(|a, b| {
+            b.downloads
+                .cmp(&a.downloads)
+                .then(a.country.cmp(&b.country))
+        });
 
-    Ok(Json(json!({
-        "version_downloads": downloads,
-        "meta": {
-            "extra_downloads": extra,
-        },
-    })))
+        let mut continents = continents
+            .into_iter()
+            .map(|(continent, downloads)| ContinentDownloads {
+                continent,
+                downloads,
+            })
+            .collect::<Vec<_>>();
+
+        continents.sort_by(|a, b| {
+            b.downloads
+                .cmp(&a.downloads)
+                .then(a.continent.cmp(b.continent))
+        });
+
+        Ok(Json(json!({
+            "continents": continents,
+            "countries": countries,
+            "meta": {
+                "other_downloads": other_downloads,
+                "min_country_downloads": MIN_COUNTRY_DOWNLOADS,
+            },
+        })))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+struct ContinentDownloads {
+    continent: &'static str,
+    downloads: i64,
+}
+
+#[derive(Serialize)]
+struct CountryDownloads {
+    country: String,
+    continent: &'static str,
+    downloads: i64,
+}
+
+/// Handles the `GET /crates/:crate_id/downloads/summary` route.
+///
+/// Returns the download totals of the last day, 7, 30 and 90 days, and the
+/// change compared to the week before. The totals are precomputed by the
+/// `update_downloads` background job, so that charts and badges don't have
+/// to aggregate the daily download counts on every request.
+pub async fn summary(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        // Crates without any downloads in the last 90 days have no summary.
+        let summary: Option<CrateDownloadSummary> = crate_download_summaries::table
+            .find(krate.id)
+            .select(CrateDownloadSummary::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(Json(json!({
+            "summary": DownloadSummary::from(summary),
+        })))
+    })
+    .await
+}
+
+#[derive(Serialize, Default)]
+struct DownloadSummary {
+    last_day: i64,
+    last_week: i64,
+    last_month: i64,
+    last_90_days: i64,
+    previous_week: i64,
+    week_over_week: WeekOverWeek,
+    #[serde(with = "rfc3339::option")]
+    computed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Default)]
+struct WeekOverWeek {
+    delta: i64,
+    /// The relative change in percent, or `None` if there were no downloads
+    /// in the previous week.
+    percent: Option<f64>,
+}
+
+impl From<Option<CrateDownloadSummary>> for DownloadSummary {
+    fn from(summary: Option<CrateDownloadSummary>) -> Self {
+        let Some(summary) = summary else {
+            return Self::default();
+        };
+
+        let delta = summary.last_week - summary.previous_week;
+        let percent = (summary.previous_week != 0)
+            .then(|| (delta as f64 / summary.previous_week as f64 * 1000.).round() / 10.);
+
+        Self {
+            last_day: summary.last_day,
+            last_week: summary.last_week,
+            last_month: summary.last_month,
+            last_90_days: summary.last_90_days,
+            previous_week: summary.previous_week,
+            week_over_week: WeekOverWeek { delta, percent },
+            computed_at: Some(summary.computed_at),
+        }
+    }
 }
@@ -0,0 +1,142 @@
+//! Endpoint for exporting the full version history of a crate
+//!
+//! This endpoint is meant for research and vendoring tools, which would
+//! otherwise have to page through the versions endpoint and fetch the
+//! dependencies of every version one request at a time.
+
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::ensure_crate_visible;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, Dependency, User, Version, VersionOwnerAction};
+use crate::schema::{crates, dependencies, users, versions};
+use crate::util::diesel::Conn;
+use crate::util::errors::crate_not_found;
+use crate::views::{EncodableDependency, EncodableVersion};
+
+/// The number of versions that are loaded from the database at once.
+const BATCH_SIZE: i64 = 500;
+
+/// A line of the export, which contains a version and its dependencies.
+#[derive(Serialize)]
+struct ExportedVersion {
+    #[serde(flatten)]
+    version: EncodableVersion,
+    dependencies: Vec<EncodableDependency>,
+}
+
+/// Handles the `GET /crates/:crate_id/versions/export` route.
+///
+/// Returns all versions of the crate in the order in which they were
+/// published, as gzip-compressed JSON lines. Every line contains the same
+/// fields as the versions endpoint, plus the dependencies of the version.
+///
+/// The versions are loaded in batches and compressed while they are being
+/// serialized, so that only the compressed export is held in memory.
+pub async fn export(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let mut last_id = 0;
+        loop {
+            let batch: Vec<(Version, Option<User>)> = versions::table
+                .left_outer_join(users::table)
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(versions::id.gt(last_id))
+                .select((versions::all_columns, users::all_columns.nullable()))
+                .order(versions::id.asc())
+                .limit(BATCH_SIZE)
+                .load(conn)?;
+
+            let Some((last, _)) = batch.last() else {
+                break;
+            };
+            last_id = last.id;
+
+            let is_last_batch = (batch.len() as i64) < BATCH_SIZE;
+            write_batch(
+                &mut encoder,
+                &krate.name,
+                batch,
+                state.config.avatar_proxy,
+                conn,
+            )?;
+            if is_last_batch {
+                break;
+            }
+        }
+
+        let body = encoder.finish()?;
+        let filename = format!("attachment; filename=\"{}-versions.jsonl.gz\"", krate.name);
+        let headers = [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ];
+
+        Ok((headers, body).into_response())
+    })
+    .await
+}
+
+/// Serializes a batch of versions, together with their audit actions and
+/// dependencies, as JSON lines.
+fn write_batch(
+    writer: &mut impl Write,
+    crate_name: &str,
+    batch: Vec<(Version, Option<User>)>,
+    avatar_proxy: bool,
+    conn: &mut impl Conn,
+) -> AppResult<()> {
+    let versions = batch.iter().map(|(v, _)| v).cloned().collect::<Vec<_>>();
+    let actions = VersionOwnerAction::for_versions(conn, &versions)?;
+
+    let version_ids = versions.iter().map(|v| v.id).collect::<Vec<_>>();
+    let deps: Vec<(Dependency, String)> = dependencies::table
+        .inner_join(crates::table)
+        .filter(dependencies::version_id.eq_any(&version_ids))
+        .select((dependencies::all_columns, crates::name))
+        .order((dependencies::optional, crates::name))
+        .load(conn)?;
+
+    let mut deps_by_version: HashMap<i32, Vec<EncodableDependency>> = HashMap::new();
+    for (dep, name) in deps {
+        deps_by_version
+            .entry(dep.version_id)
+            .or_default()
+            .push(EncodableDependency::from_dep(dep, &name));
+    }
+
+    for ((version, published_by), actions) in batch.into_iter().zip(actions) {
+        let dependencies = deps_by_version.remove(&version.id).unwrap_or_default();
+        let version =
+            EncodableVersion::from(version, crate_name, published_by, actions, avatar_proxy);
+
+        serde_json::to_writer(
+            &mut *writer,
+            &ExportedVersion {
+                version,
+                dependencies,
+            },
+        )?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Endpoints for freezing crates pending an investigation
+//!
+//! Frozen crates can still be downloaded, but no new versions can be
+//! published and their owners can not be changed until an admin lifts the
+//! freeze again. Use quarantines instead if the existing versions of the
+//! crate must not be downloaded anymore.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{AdminAction, Crate, NewAdminAuditEntry, NewCrateFreeze, User};
+use crate::schema::crate_freezes;
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, forbidden, not_found};
+use crate::views::EncodableCrateFreeze;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum length of the reason of a freeze.
+const MAX_REASON_LENGTH: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct FreezeRequest {
+    reason: String,
+}
+
+/// Handles the `PUT /api/private/crates/:crate_id/freeze` route.
+///
+/// Freezes the crate, or replaces the reason if the crate is already frozen.
+/// The reason is shown to the owners of the crate when they try to publish a
+/// new version or to change the owners of the crate.
+pub async fn freeze(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<FreezeRequest>,
+) -> AppResult<Json<Value>> {
+    let reason = body.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(bad_request("a reason is required to freeze a crate"));
+    }
+    if reason.len() > MAX_REASON_LENGTH {
+        return Err(bad_request(format!(
+            "the reason must not be longer than {MAX_REASON_LENGTH} characters"
+        )));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let krate = load_crate(&crate_name, conn)?;
+
+        warn!(
+            "Admin {} is freezing {}: {reason}",
+            admin.gh_login, krate.name
+        );
+
+        let freeze = NewCrateFreeze {
+            crate_id: krate.id,
+            reason: &reason,
+            frozen_by: admin.id,
+        }
+        .upsert(conn)?;
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::FreezeCrate)
+            .krate(&krate.name)
+            .details(json!({ "reason": reason }))
+            .insert(conn)?;
+
+        let freeze = EncodableCrateFreeze::from(freeze, krate.name, admin, app.config.avatar_proxy);
+        Ok(Json(json!({ "freeze": freeze })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/crates/:crate_id/freeze` route.
+pub async fn unfreeze(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin = authenticate_admin(&req, conn)?;
+        let krate = load_crate(&crate_name, conn)?;
+
+        let deleted = diesel::delete(crate_freezes::table.find(krate.id)).execute(conn)?;
+        if deleted == 0 {
+            return Err(not_found());
+        }
+
+        warn!(
+            "Admin {} lifted the freeze of {}",
+            admin.gh_login, krate.name
+        );
+
+        NewAdminAuditEntry::by_admin(&admin, AdminAction::UnfreezeCrate)
+            .krate(&krate.name)
+            .insert(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+fn load_crate(crate_name: &str, conn: &mut impl Conn) -> AppResult<Crate> {
+    Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden("must be an admin to freeze crates"));
+    }
+
+    Ok(user.clone())
+}
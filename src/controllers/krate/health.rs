@@ -0,0 +1,43 @@
+//! Endpoint for the health score of a crate
+//!
+//! The health scores are computed periodically by the `UpdateCrateHealth`
+//! background job, so that this endpoint does not have to aggregate the
+//! metrics of the crate on every request.
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{Crate, CrateHealth};
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::EncodableCrateHealth;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `GET /crates/:crate_id/health` route.
+pub async fn show(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
+        let health = CrateHealth::for_crate(conn, krate.id)?.ok_or_else(|| {
+            custom(
+                StatusCode::NOT_FOUND,
+                "the health score of this crate has not been computed yet",
+            )
+        })?;
+
+        let health = EncodableCrateHealth::from(health, krate.name);
+
+        Ok(Json(json!({ "health": health })))
+    })
+    .await
+}
@@ -8,12 +8,14 @@ use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use std::cmp::Reverse;
 use std::str::FromStr;
 
+use super::ensure_crate_visible;
+use crate::api_quota::check_api_quota;
 use crate::controllers::frontend_prelude::*;
 use crate::controllers::helpers::pagination::PaginationOptions;
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    User, Version, VersionOwnerAction,
+    load_default_versions, Category, Crate, CrateCategory, CrateKeyword, CrateSuccession,
+    CrateVersions, Keyword, RecentCrateDownloads, User, Version, VersionOwnerAction,
 };
 use crate::schema::*;
 use crate::util::errors::crate_not_found;
@@ -46,6 +48,8 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
             .optional()?
             .ok_or_else(|| crate_not_found(&name))?;
 
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
         let versions_publishers_and_audit_actions = if include.versions {
             let mut versions_and_publishers: Vec<(Version, Option<User>)> = krate
                 .all_versions()
@@ -112,9 +116,12 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
             None
         };
 
-        let encodable_crate = EncodableCrate::from(
+        let default_version = load_default_versions(&[krate.id], conn)?.remove(&krate.id);
+
+        let mut encodable_crate = EncodableCrate::from(
             krate.clone(),
             top_versions.as_ref(),
+            default_version.as_deref(),
             ids,
             kws.as_deref(),
             cats.as_deref(),
@@ -123,9 +130,16 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
             downloads,
             recent_downloads,
         );
+
+        encodable_crate.superseded_by = CrateSuccession::successor_name(conn, krate.id)?;
+        let predecessors = CrateSuccession::predecessor_names(conn, krate.id)?;
+        encodable_crate.supersedes = (!predecessors.is_empty()).then_some(predecessors);
+
         let encodable_versions = versions_publishers_and_audit_actions.map(|vpa| {
             vpa.into_iter()
-                .map(|(v, pb, aas)| EncodableVersion::from(v, &krate.name, pb, aas))
+                .map(|(v, pb, aas)| {
+                    EncodableVersion::from(v, &krate.name, pb, aas, app.config.avatar_proxy)
+                })
                 .collect::<Vec<_>>()
         });
         let encodable_keywords = kws.map(|kws| {
@@ -211,17 +225,49 @@ impl FromStr for ShowIncludeMode {
 }
 
 /// Handles the `GET /crates/:crate_id/:version/readme` route.
+///
+/// JSON requests receive the URL of the rendered README and its table of
+/// contents, if one was extracted when the README was rendered.
 pub async fn readme(
     app: AppState,
     Path((crate_name, version)): Path<(String, String)>,
     req: Parts,
-) -> Response {
-    let redirect_url = app.storage.readme_location(&crate_name, &version);
-    if req.wants_json() {
-        Json(json!({ "url": redirect_url })).into_response()
-    } else {
-        redirect(redirect_url)
-    }
+) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        // Redirects to missing crates are performed unconditionally, but
+        // private crates are only available to their owners.
+        let krate: Option<Crate> = Crate::by_name(&crate_name).first(conn).optional()?;
+        if let Some(krate) = &krate {
+            ensure_crate_visible(&app, &req, krate, conn)?;
+        }
+
+        let redirect_url = app.storage.readme_location(&crate_name, &version);
+        if req.wants_json() {
+            let table_of_contents: Option<Value> = match krate {
+                Some(krate) => readme_renderings::table
+                    .inner_join(versions::table)
+                    .filter(versions::crate_id.eq(krate.id))
+                    .filter(versions::num.eq(&version))
+                    .select(readme_renderings::table_of_contents)
+                    .first::<Option<Value>>(conn)
+                    .optional()?
+                    .flatten(),
+                None => None,
+            };
+
+            Ok(Json(json!({
+                "url": redirect_url,
+                "table_of_contents": table_of_contents,
+            }))
+            .into_response())
+        } else {
+            Ok(redirect(redirect_url))
+        }
+    })
+    .await
 }
 
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
@@ -230,6 +276,8 @@ pub async fn reverse_dependencies(
     Path(name): Path<String>,
     req: Parts,
 ) -> AppResult<Json<Value>> {
+    check_api_quota(&app, &req).await?;
+
     let conn = app.db_read().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
@@ -241,6 +289,8 @@ pub async fn reverse_dependencies(
             .optional()?
             .ok_or_else(|| crate_not_found(&name))?;
 
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
         let (rev_deps, total) = krate.reverse_dependencies(conn, pagination_options)?;
         let rev_deps: Vec<_> = rev_deps
             .into_iter()
@@ -268,7 +318,13 @@ pub async fn reverse_dependencies(
             .into_iter()
             .zip(VersionOwnerAction::for_versions(conn, &versions)?)
             .map(|((version, krate_name, published_by), actions)| {
-                EncodableVersion::from(version, &krate_name, published_by, actions)
+                EncodableVersion::from(
+                    version,
+                    &krate_name,
+                    published_by,
+                    actions,
+                    app.config.avatar_proxy,
+                )
             })
             .collect::<Vec<_>>();
 
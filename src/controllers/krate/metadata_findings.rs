@@ -0,0 +1,66 @@
+//! Endpoint for the metadata findings of a crate
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, CrateVisibility, MetadataFinding, Rights};
+use crate::schema::{metadata_findings, versions};
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::EncodableMetadataFinding;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use tokio::runtime::Handle;
+
+/// Handles the `GET /crates/:crate_id/metadata_findings` route.
+///
+/// Returns the violations of the current publish validation rules, that
+/// were found in the metadata of the already published versions of the
+/// crate by the `ValidateVersionMetadata` background job. Only the owners
+/// of the crate can see these findings.
+pub async fn list(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        let rights = Handle::current().block_on(user.rights(&app, &owners))?;
+        if rights == Rights::None && !user.is_admin {
+            if krate.visibility == CrateVisibility::Private {
+                return Err(crate_not_found(&crate_name));
+            }
+
+            return Err(custom(
+                StatusCode::FORBIDDEN,
+                "only owners have permission to see the metadata findings of a crate",
+            ));
+        }
+
+        let findings: Vec<(MetadataFinding, String)> = metadata_findings::table
+            .inner_join(versions::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .order((versions::id.desc(), metadata_findings::id.asc()))
+            .select((MetadataFinding::as_select(), versions::num))
+            .load(conn)?;
+
+        let findings = findings
+            .into_iter()
+            .map(|(finding, num)| EncodableMetadataFinding::from(finding, num))
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "findings": findings })))
+    })
+    .await
+}
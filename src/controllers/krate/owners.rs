@@ -1,16 +1,29 @@
 //! All routes related to managing owners of a crate
 
-use crate::auth::AuthCheck;
+use super::ensure_crate_visible;
+use crate::app::App;
+use crate::auth::{ensure_not_locked, AuthCheck};
 use crate::controllers::prelude::*;
 use crate::models::token::EndpointScope;
-use crate::models::{Crate, Owner, Rights, Team, User};
+use crate::models::{
+    Crate, CrateOwnerAction, CrateVisibility, CriticalCrate, DormantOwnerSuggestion,
+    NewPendingOwnerRemoval, Owner, OwnerKind, Rights, Team, User,
+};
+use crate::permissions::{Capability, Permissions};
+use crate::schema::{crates, teams, users};
+use crate::util::diesel::Conn;
 use crate::util::errors::{bad_request, crate_not_found, custom};
-use crate::views::EncodableOwner;
+use crate::views::{EncodableCrateOwnerAction, EncodableOwner};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::HashMap;
 use tokio::runtime::Handle;
 
 /// Handles the `GET /crates/:crate_id/owners` route.
-pub async fn owners(state: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
+pub async fn owners(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
     let conn = state.db_read().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
@@ -20,10 +33,12 @@ pub async fn owners(state: AppState, Path(crate_name): Path<String>) -> AppResul
             .optional()?
             .ok_or_else(|| crate_not_found(&crate_name))?;
 
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
         let owners = krate
             .owners(conn)?
             .into_iter()
-            .map(Owner::into)
+            .map(|owner| EncodableOwner::from(owner, state.config.avatar_proxy))
             .collect::<Vec<EncodableOwner>>();
 
         Ok(Json(json!({ "users": owners })))
@@ -32,7 +47,11 @@ pub async fn owners(state: AppState, Path(crate_name): Path<String>) -> AppResul
 }
 
 /// Handles the `GET /crates/:crate_id/owner_team` route.
-pub async fn owner_team(state: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
+pub async fn owner_team(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
     let conn = state.db_read().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
@@ -42,9 +61,11 @@ pub async fn owner_team(state: AppState, Path(crate_name): Path<String>) -> AppR
             .optional()?
             .ok_or_else(|| crate_not_found(&crate_name))?;
 
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
         let owners = Team::owning(&krate, conn)?
             .into_iter()
-            .map(Owner::into)
+            .map(|owner| EncodableOwner::from(owner, state.config.avatar_proxy))
             .collect::<Vec<EncodableOwner>>();
 
         Ok(Json(json!({ "teams": owners })))
@@ -53,7 +74,11 @@ pub async fn owner_team(state: AppState, Path(crate_name): Path<String>) -> AppR
 }
 
 /// Handles the `GET /crates/:crate_id/owner_user` route.
-pub async fn owner_user(state: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
+pub async fn owner_user(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
     let conn = state.db_read().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
@@ -63,9 +88,11 @@ pub async fn owner_user(state: AppState, Path(crate_name): Path<String>) -> AppR
             .optional()?
             .ok_or_else(|| crate_not_found(&crate_name))?;
 
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
         let owners = User::owning(&krate, conn)?
             .into_iter()
-            .map(Owner::into)
+            .map(|owner| EncodableOwner::from(owner, state.config.avatar_proxy))
             .collect::<Vec<EncodableOwner>>();
 
         Ok(Json(json!({ "users": owners })))
@@ -73,6 +100,95 @@ pub async fn owner_user(state: AppState, Path(crate_name): Path<String>) -> AppR
     .await
 }
 
+/// Handles the `GET /crates/:crate_id/owners/history` route.
+///
+/// Returns every addition and removal of an owner of the crate, including
+/// the owners that were removed since, and the users that performed them.
+/// Only the owners of the crate and admins can see the history.
+pub async fn history(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        let rights = Handle::current().block_on(user.rights(&state, &owners))?;
+        if rights == Rights::None && !user.is_admin {
+            if krate.visibility == CrateVisibility::Private {
+                return Err(crate_not_found(&crate_name));
+            }
+
+            return Err(custom(
+                StatusCode::FORBIDDEN,
+                "only owners have permission to see the ownership history of a crate",
+            ));
+        }
+
+        let actions = CrateOwnerAction::by_crate(conn, krate.id)?;
+
+        let user_ids = actions
+            .iter()
+            .filter(|action| action.owner_kind == OwnerKind::User)
+            .map(|action| action.owner_id)
+            .chain(actions.iter().filter_map(|action| action.performed_by))
+            .collect::<Vec<_>>();
+
+        let team_ids = actions
+            .iter()
+            .filter(|action| action.owner_kind == OwnerKind::Team)
+            .map(|action| action.owner_id)
+            .collect::<Vec<_>>();
+
+        let users: HashMap<i32, User> = users::table
+            .filter(users::id.eq_any(user_ids))
+            .load::<User>(conn)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let teams: HashMap<i32, Team> = teams::table
+            .filter(teams::id.eq_any(team_ids))
+            .load::<Team>(conn)?
+            .into_iter()
+            .map(|team| (team.id, team))
+            .collect();
+
+        let history = actions
+            .into_iter()
+            .filter_map(|action| {
+                let owner = match action.owner_kind {
+                    OwnerKind::User => Owner::User(users.get(&action.owner_id)?.clone()),
+                    OwnerKind::Team => Owner::Team(teams.get(&action.owner_id)?.clone()),
+                };
+                let performed_by = action.performed_by.and_then(|id| users.get(&id).cloned());
+                Some(EncodableCrateOwnerAction::from(
+                    action,
+                    owner,
+                    performed_by,
+                    state.config.avatar_proxy,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "history": history })))
+    })
+    .await
+}
+
 /// Handles the `PUT /crates/:crate_id/owners` route.
 pub async fn add_owners(
     app: AppState,
@@ -127,22 +243,8 @@ async fn modify_owners(
 
             let owners = krate.owners(conn)?;
 
-            match Handle::current().block_on(user.rights(&app, &owners))? {
-                Rights::Full => {}
-                // Yes!
-                Rights::Publish => {
-                    return Err(custom(
-                        StatusCode::FORBIDDEN,
-                        "team members don't have permission to modify owners",
-                    ));
-                }
-                Rights::None => {
-                    return Err(custom(
-                        StatusCode::FORBIDDEN,
-                        "only owners have permission to modify owners",
-                    ));
-                }
-            }
+            Permissions::load(&app, user, &krate, &owners, conn)?
+                .ensure(Capability::ManageOwners)?;
 
             let comma_sep_msg = if add {
                 let mut msgs = Vec::with_capacity(logins.len());
@@ -152,22 +254,12 @@ async fn modify_owners(
                     if owners.iter().any(login_test) {
                         return Err(bad_request(format_args!("`{login}` is already an owner")));
                     }
-                    let msg = krate.owner_add(&app, conn, user, login)?;
+                    let msg = krate.owner_add(&app, conn, user, login, &owners)?;
                     msgs.push(msg);
                 }
                 msgs.join(",")
             } else {
-                for login in &logins {
-                    krate.owner_remove(conn, login)?;
-                }
-                if User::owning(&krate, conn)?.is_empty() {
-                    return Err(bad_request(
-                        "cannot remove all individual owners of a crate. \
-                     Team member don't have permission to modify owners, so \
-                     at least one individual owner is required.",
-                    ));
-                }
-                "owners successfully removed".to_owned()
+                remove_owners_of_crate(&app, conn, &krate, &owners, user, &logins)?
             };
 
             Ok(Json(json!({ "ok": true, "msg": comma_sep_msg })))
@@ -175,3 +267,136 @@ async fn modify_owners(
     })
     .await
 }
+
+/// Handles the `PUT /api/v1/dormant_owner_removals/:token` route.
+///
+/// Removes the dormant owner of the crate that the owner who received the
+/// suggestion email was asked about. The removal is subject to the same
+/// rules as a regular removal by that owner.
+pub async fn confirm_dormant_owner_removal(
+    app: AppState,
+    Path(token): Path<String>,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        conn.transaction(|conn| {
+            let suggestion = DormantOwnerSuggestion::take_by_token(conn, &token)?
+                .ok_or_else(|| bad_request("Dormant owner suggestion not found or expired."))?;
+
+            let user = User::find(conn, suggestion.recipient_id)?;
+            ensure_not_locked(&user)?;
+
+            let krate: Crate = crates::table
+                .find(suggestion.crate_id)
+                .select(Crate::as_select())
+                .first(conn)?;
+
+            let owners = krate.owners(conn)?;
+            Permissions::load(&app, &user, &krate, &owners, conn)?
+                .ensure(Capability::ManageOwners)?;
+
+            let dormant_owner = User::find(conn, suggestion.owner_id)?;
+            let is_owner = owners
+                .iter()
+                .any(|owner| matches!(owner, Owner::User(u) if u.id == dormant_owner.id));
+            if !is_owner {
+                return Err(bad_request(format!(
+                    "`{}` is not an owner of `{}` anymore",
+                    dormant_owner.gh_login, krate.name
+                )));
+            }
+
+            let logins = [dormant_owner.gh_login];
+            let msg = remove_owners_of_crate(&app, conn, &krate, &owners, &user, &logins)?;
+
+            Ok(Json(json!({ "ok": true, "msg": msg })))
+        })
+    })
+    .await
+}
+
+/// Removes the owners with the given logins on behalf of `user`, or holds
+/// back their removal if the crate is critical.
+fn remove_owners_of_crate(
+    app: &App,
+    conn: &mut impl Conn,
+    krate: &Crate,
+    owners: &[Owner],
+    user: &User,
+    logins: &[String],
+) -> AppResult<String> {
+    if CriticalCrate::is_critical(conn, krate.id)? {
+        return request_owner_removals(app, conn, krate, owners, user, logins);
+    }
+
+    for login in logins {
+        krate.owner_remove(conn, user, login)?;
+    }
+    if User::owning(krate, conn)?.is_empty() {
+        return Err(bad_request(
+            "cannot remove all individual owners of a crate. \
+             Team member don't have permission to modify owners, so \
+             at least one individual owner is required.",
+        ));
+    }
+
+    Ok("owners successfully removed".to_owned())
+}
+
+/// Holds back the removal of the owners of a critical crate until two admins
+/// approved them, or until the waiting period is over.
+///
+/// See the `apply_pending_owner_removals` background job for the latter.
+fn request_owner_removals(
+    app: &App,
+    conn: &mut impl Conn,
+    krate: &Crate,
+    owners: &[Owner],
+    user: &User,
+    logins: &[String],
+) -> AppResult<String> {
+    let is_removed = |owner: &Owner| {
+        logins
+            .iter()
+            .any(|login| login.eq_ignore_ascii_case(owner.login()))
+    };
+    let remaining_users = owners
+        .iter()
+        .filter(|owner| matches!(owner, Owner::User(_)) && !is_removed(owner));
+    if remaining_users.count() == 0 {
+        return Err(bad_request(
+            "cannot remove all individual owners of a crate. \
+             Team member don't have permission to modify owners, so \
+             at least one individual owner is required.",
+        ));
+    }
+
+    for login in logins {
+        let owner = owners
+            .iter()
+            .find(|owner| owner.login().eq_ignore_ascii_case(login))
+            .ok_or_else(|| bad_request(format_args!("`{login}` is not an owner")))?;
+
+        let owner_kind = match owner {
+            Owner::User(_) => OwnerKind::User,
+            Owner::Team(_) => OwnerKind::Team,
+        };
+
+        NewPendingOwnerRemoval {
+            crate_id: krate.id,
+            owner_id: owner.id(),
+            owner_kind,
+            requested_by: user.id,
+        }
+        .insert(conn)?;
+    }
+
+    let days = app.config.critical_owner_removal_waiting_period.as_secs() / (24 * 60 * 60);
+    Ok(format!(
+        "`{}` is a critical crate, so the removal of owners takes effect in {days} days, \
+        unless two crates.io admins approve it earlier",
+        krate.name
+    ))
+}
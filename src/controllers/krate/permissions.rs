@@ -0,0 +1,136 @@
+//! Endpoint for the effective permissions of the current credential on a
+//! crate
+//!
+//! The permissions are evaluated with the same [`Permissions`] as the
+//! endpoints that perform the actions, so that clients can render the right
+//! UI and CI pipelines can fail fast with a clear reason before e.g.
+//! publishing.
+
+use crate::auth::{AuthCheck, Authentication};
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, CrateVisibility, Rights};
+use crate::permissions::{Capability, Permissions};
+use crate::util::diesel::Conn;
+use crate::util::errors::crate_not_found;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+#[derive(Debug, Serialize)]
+struct Permission {
+    allowed: bool,
+    /// Explanation of why the action is not allowed.
+    reason: Option<String>,
+}
+
+impl Permission {
+    /// Allows the action, unless one of the denial reasons is set, in which
+    /// case the first one is reported.
+    fn unless(denials: impl IntoIterator<Item = Option<String>>) -> Self {
+        let reason = denials.into_iter().flatten().next();
+        Self {
+            allowed: reason.is_none(),
+            reason,
+        }
+    }
+}
+
+/// Handles the `GET /crates/:crate_id/permissions` route.
+///
+/// Returns whether the session or API token of the request may publish new
+/// versions of the crate, yank versions, manage the owners and change the
+/// settings of the crate, after evaluating the ownership (including team
+/// membership), the scopes of the token, freezes and the two-factor
+/// requirement of critical crates.
+pub async fn show(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .allow_service_token()
+            .skip_scope_checks()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        let permissions = Permissions::load(&app, user, &krate, &owners, conn)?;
+
+        let is_private = krate.visibility == CrateVisibility::Private;
+        if is_private && permissions.rights() == Rights::None && !user.is_admin {
+            return Err(crate_not_found(&krate.name));
+        }
+
+        let denial = |capability| permissions.ensure(capability).err().map(|e| e.to_string());
+
+        let missing_email = user
+            .verified_email(conn)?
+            .is_none()
+            .then(|| "a verified email address is required to publish crates".to_string());
+
+        let publish = Permission::unless([
+            token_denial(&auth, Some(EndpointScope::PublishUpdate), &krate, conn)?,
+            denial(Capability::Publish),
+            missing_email,
+        ]);
+
+        let yank = Permission::unless([
+            token_denial(&auth, Some(EndpointScope::Yank), &krate, conn)?,
+            denial(Capability::Yank),
+        ]);
+
+        let manage_owners = Permission::unless([
+            token_denial(&auth, Some(EndpointScope::ChangeOwners), &krate, conn)?,
+            denial(Capability::ManageOwners),
+        ]);
+
+        let change_settings = Permission::unless([
+            token_denial(&auth, None, &krate, conn)?,
+            denial(Capability::ChangeSettings),
+        ]);
+
+        Ok(Json(json!({
+            "permissions": {
+                "publish": publish,
+                "yank": yank,
+                "manage_owners": manage_owners,
+                "change_settings": change_settings,
+            },
+        })))
+    })
+    .await
+}
+
+/// Returns the reason why the API token of the request can not be used for
+/// the endpoint with the given scope, if it can't.
+///
+/// Sessions are not restricted by scopes, so they are never denied.
+fn token_denial(
+    auth: &Authentication,
+    endpoint_scope: Option<EndpointScope>,
+    krate: &Crate,
+    conn: &mut impl Conn,
+) -> QueryResult<Option<String>> {
+    let Some(token) = auth.api_token() else {
+        return Ok(None);
+    };
+
+    let check = match endpoint_scope {
+        Some(endpoint_scope) => AuthCheck::default().with_endpoint_scope(endpoint_scope),
+        None => AuthCheck::default(),
+    };
+
+    let permitted = check.for_crate(&krate.name).permits(token, conn)?;
+    Ok((!permitted)
+        .then(|| "this token does not have the required permissions to perform this action".into()))
+}
@@ -0,0 +1,36 @@
+//! Endpoint for the policy documents of a crate
+//!
+//! The policy documents are changed by the owners of a crate via the
+//! `PATCH /crates/:crate_id/settings` endpoint, but can be read by anyone.
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{Crate, CrateSettings};
+use crate::util::errors::crate_not_found;
+use crate::views::EncodableCratePolicy;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `GET /crates/:crate_id/policy` route.
+pub async fn show(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
+        let settings = CrateSettings::for_crate(conn, krate.id)?;
+        let policy = EncodableCratePolicy::from(settings);
+
+        Ok(Json(json!({ "policy": policy })))
+    })
+    .await
+}
@@ -1,33 +1,52 @@
 //! Functionality related to publishing a new crate or version of a crate.
 
-use crate::auth::AuthCheck;
+mod batch;
+mod ci;
+mod idempotency;
+mod warnings;
+
+pub use self::batch::publish_batch;
+use self::ci::CiAnnotation;
+use self::idempotency::IdempotencyKey;
+use self::warnings::PublishedMetadata;
+use crate::auth::{ensure_not_locked, AuthCheck};
+use crate::email::Email;
 use crate::worker::jobs::{self, CheckTyposquat, UpdateDefaultVersion};
 use axum::body::Bytes;
 use cargo_manifest::{Dependency, DepsSet, TargetDepsSet};
-use crates_io_tarball::{process_tarball, TarballError};
+use crates_io_tarball::{process_tarball, StringOrBool, TarballError, TarballInfo, TarballLimits};
 use crates_io_worker::BackgroundJob;
 use diesel::connection::DefaultLoadingMode;
 use diesel::dsl::{exists, select};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use hex::ToHex;
 use hyper::body::Buf;
+use secrecy::{ExposeSecret, SecretString};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tokio::runtime::Handle;
 use url::Url;
 
 use crate::controllers::cargo_prelude::*;
 use crate::models::{
-    insert_version_owner_action, Category, Crate, DependencyKind, Keyword, NewCrate, NewVersion,
-    Rights, VersionAction,
+    self, insert_version_owner_action, Category, Crate, CrateSettings, CrateVisibility,
+    DependencyDenyList, DependencyKind, Keyword, NewCrate, NewPendingPublish, NewRegistryEvent,
+    NewSpamFlag, NewVersion, NewVersionCiAnnotation, NotificationClass, PendingPublish,
+    RegistryEventKind, ReproducibilityReport, ScanVerdict, StagedUpload, User, VersionAction,
+    VersionContentManifest,
 };
 
 use crate::licenses::parse_license_expr;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::registry::RequestRegistry;
 use crate::models::token::EndpointScope;
+use crate::permissions::{Capability, Permissions};
+use crate::publish_queue::PublishPermit;
 use crate::rate_limiter::LimitedAction;
+use crate::registries::{crate_registry, DEFAULT_REGISTRY};
 use crate::schema::*;
-use crate::sql::canon_crate_name;
+use crate::sql::{canon_crate_name, crate_name_skeleton};
+use crate::upstream;
 use crate::util::diesel::Conn;
 use crate::util::errors::{bad_request, custom, internal, AppResult};
 use crate::util::Maximums;
@@ -35,13 +54,14 @@ use crate::views::{
     EncodableCrate, EncodableCrateDependency, GoodCrate, PublishMetadata, PublishWarnings,
 };
 
-const MISSING_RIGHTS_ERROR_MESSAGE: &str = "this crate exists but you don't seem to be an owner. \
-     If you believe this is a mistake, perhaps you need \
-     to accept an invitation to be an owner before \
-     publishing.";
-
 const MAX_DESCRIPTION_LENGTH: usize = 1000;
 
+const MAX_CHANNEL_LENGTH: usize = 32;
+
+/// The maximum number of conflicting crates that are listed in the publish
+/// warning about a reused `links` key.
+const MAX_LINKS_CONFLICTS: i64 = 5;
+
 /// Handles the `PUT /crates/new` route.
 /// Used by `cargo publish` to publish a new crate or to publish a new version of an
 /// existing crate.
@@ -49,192 +69,407 @@ const MAX_DESCRIPTION_LENGTH: usize = 1000;
 /// Currently blocks the HTTP thread, perhaps some function calls can spawn new
 /// threads and return completion or error through other methods  a `cargo publish
 /// --status` command, via crates.io's front end, or email.
-pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCrate>> {
+pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Response> {
     let (req, bytes) = req.0.into_parts();
-    let (json_bytes, tarball_bytes) = split_body(bytes)?;
+    let request = PublishRequest::parse(bytes.clone())?;
+    let ci = CiAnnotation::from_headers(&req.headers)?;
+    let idempotency_key = IdempotencyKey::from_headers(&req.headers)?;
+    let PublishRequest {
+        metadata,
+        version_string,
+        ..
+    } = &request;
+
+    let request_log = req.request_log();
+    request_log.add("crate_name", &*metadata.name);
+    request_log.add("crate_version", version_string);
 
-    let metadata: PublishMetadata = serde_json::from_slice(&json_bytes)
-        .map_err(|e| bad_request(format_args!("invalid upload request: {e}")))?;
+    let crate_name = request.crate_name().to_string();
+    let version_string = request.version_string.clone();
 
-    Crate::validate_crate_name("crate", &metadata.name).map_err(bad_request)?;
+    let conn = app.db_write().await?;
+    let (auth, existing_crate, replay) = spawn_blocking({
+        let crate_name = crate_name.clone();
+        let version_string = version_string.clone();
+        let idempotency_key = idempotency_key.clone();
+        move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            // this query should only be used for the endpoint scope calculation
+            // since a race condition there would only cause `publish-new` instead of
+            // `publish-update` to be used.
+            let existing_crate: Option<Crate> = Crate::by_name(&crate_name)
+                .first::<Crate>(conn)
+                .optional()?;
+
+            let endpoint_scope = match existing_crate {
+                Some(_) => EndpointScope::PublishUpdate,
+                None => EndpointScope::PublishNew,
+            };
 
-    let version = match semver::Version::parse(&metadata.vers) {
-        Ok(parsed) => parsed,
-        Err(_) => {
-            return Err(bad_request(format_args!(
-                "\"{}\" is an invalid semver version",
-                metadata.vers
-            )))
+            let auth = AuthCheck::default()
+                .with_endpoint_scope(endpoint_scope)
+                .for_crate(&crate_name)
+                .check(&req, conn)?;
+
+            // Retries of a successful publish with the same idempotency key get
+            // the original response instead of a "version already exists" error.
+            let replay = match &idempotency_key {
+                Some(key) => key.replay(conn, auth.user_id(), &crate_name, &version_string)?,
+                None => None,
+            };
+
+            Ok::<_, BoxedAppError>((auth, existing_crate, replay))
         }
-    };
+    })
+    .await?;
 
-    // Convert the version back to a string to deal with any inconsistencies
-    let version_string = version.to_string();
+    if let Some(response) = replay {
+        return Ok(Json(response).into_response());
+    }
 
-    let request_log = req.request_log();
-    request_log.add("crate_name", &*metadata.name);
-    request_log.add("crate_version", &version_string);
+    let api_token_id = auth.api_token_id();
+
+    // Publishes using an API token are held back until the user confirms
+    // them via email, if the user has opted into this. Only valid
+    // publishes are held back, and they count towards the rate limit.
+    if api_token_id.is_some() && auth.user().publish_confirmation_required {
+        let conn = app.db_write().await?;
+        return spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            let user = auth.user();
+
+            check_publish_rate_limit(&app, conn, user, existing_crate.as_ref())?;
+            let validated = validate_publish(&app, conn, user, existing_crate.as_ref(), request)?;
+            let response = hold_publish(&app, conn, user, api_token_id, &validated, ci, bytes)?;
+            if let Some(key) = &idempotency_key {
+                key.store(conn, user.id, &crate_name, &version_string, &response);
+            }
+
+            Ok(Json(response).into_response())
+        })
+        .await;
+    }
+
+    // Queued publishes wait for their turn without holding a database
+    // connection or a blocking thread.
+    let permit = acquire_publish_slot(&app, auth.user_id()).await?;
 
     let conn = app.db_write().await?;
     spawn_blocking(move || {
+        let _permit = permit;
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+        let user = auth.user();
 
-        // this query should only be used for the endpoint scope calculation
-        // since a race condition there would only cause `publish-new` instead of
-        // `publish-update` to be used.
-        let existing_crate: Option<Crate> = Crate::by_name(&metadata.name)
-            .first::<Crate>(conn)
-            .optional()?;
+        let registry = req.registry();
+        let response = publish_version(
+            &app,
+            conn,
+            user,
+            api_token_id,
+            registry,
+            existing_crate,
+            request,
+            ci,
+            IndexSync::Enqueue,
+        )?;
+        if let Some(key) = &idempotency_key {
+            key.store(conn, user.id, &crate_name, &version_string, &response.0);
+        }
 
-        let endpoint_scope = match existing_crate {
-            Some(_) => EndpointScope::PublishUpdate,
-            None => EndpointScope::PublishNew,
-        };
+        Ok(response.into_response())
+    })
+    .await
+}
 
-        let auth = AuthCheck::default()
-            .with_endpoint_scope(endpoint_scope)
-            .for_crate(&metadata.name)
-            .check(&req, conn)?;
+/// Waits until the publish of the user may be processed.
+///
+/// Concurrent publishes are processed round-robin across users, so that a
+/// single user publishing a whole workspace can't starve the publishes of
+/// other users.
+async fn acquire_publish_slot(app: &AppState, user_id: i32) -> AppResult<PublishPermit> {
+    app.publish_queue.acquire(user_id).await.map_err(|_| {
+        app.instance_metrics.publish_queue_rejections_total.inc();
+        custom(
+            StatusCode::TOO_MANY_REQUESTS,
+            "You have too many publishes in progress. \
+             Please wait for them to finish and try again.",
+        )
+    })
+}
 
-        let api_token_id = auth.api_token_id();
-        let user = auth.user();
+/// Publishes a new crate or a new version of an existing crate on behalf of
+/// `user`, after the request has been authenticated.
+///
+/// This is used by the regular `PUT /crates/new` route, by the
+/// `PUT /crates/batch` route, and when a pending publish gets confirmed via
+/// `PUT /confirm_publish/:token`.
+#[allow(clippy::too_many_arguments)]
+fn publish_version(
+    app: &AppState,
+    conn: &mut impl Conn,
+    user: &User,
+    api_token_id: Option<i32>,
+    registry: &str,
+    existing_crate: Option<Crate>,
+    request: PublishRequest,
+    ci: Option<CiAnnotation>,
+    index_sync: IndexSync,
+) -> AppResult<Json<GoodCrate>> {
+    check_publish_rate_limit(app, conn, user, existing_crate.as_ref())?;
+
+    let validated = validate_publish(app, conn, user, existing_crate.as_ref(), request)?;
+    persist_version(
+        app,
+        conn,
+        user,
+        api_token_id,
+        existing_crate,
+        validated,
+        ci,
+        index_sync,
+    )
+}
 
-        let verified_email_address = user.verified_email(conn)?;
-        let verified_email_address = verified_email_address.ok_or_else(|| {
-            bad_request(format!(
-                "A verified email address is required to publish crates to crates.io. \
-             Visit https://{}/settings/profile to set and verify your email address.",
-                app.config.domain_name,
-            ))
-        })?;
+/// Checks the publish rate limit of `user`, which is different for new and
+/// for existing crates.
+fn check_publish_rate_limit(
+    app: &AppState,
+    conn: &mut impl Conn,
+    user: &User,
+    existing_crate: Option<&Crate>,
+) -> AppResult<()> {
+    let rate_limit_action = match existing_crate {
+        Some(_) => LimitedAction::PublishUpdate,
+        None => LimitedAction::PublishNew,
+    };
+    app.rate_limiter
+        .check_rate_limit(user.id, rate_limit_action, conn)
+}
 
-        // Use a different rate limit whether this is a new or an existing crate.
-        let rate_limit_action = match existing_crate {
-            Some(_) => LimitedAction::PublishUpdate,
-            None => LimitedAction::PublishNew,
-        };
-        app.rate_limiter
-            .check_rate_limit(user.id, rate_limit_action, conn)?;
+/// A publish request that passed all checks which don't require the crate
+/// to be locked, see [`validate_publish()`].
+struct ValidatedPublish {
+    metadata: PublishMetadata,
+    version_string: String,
+    tarball_bytes: Bytes,
+    tarball_info: TarballInfo,
+    verified_email_address: String,
+    description: Option<String>,
+    license: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    repository: Option<String>,
+    rust_version: Option<String>,
+    links: Option<String>,
+    build: Option<StringOrBool>,
+    keywords: Vec<String>,
+    categories: Vec<String>,
+    features: BTreeMap<String, Vec<String>>,
+    deps: Vec<EncodableCrateDependency>,
+    warnings: PublishWarnings,
+}
 
-        let content_length = tarball_bytes.len() as u64;
+/// Processes the tarball of the publish request and validates its metadata,
+/// without persisting anything.
+///
+/// This is used both before a publish is persisted and before it is held
+/// back for confirmation, so that only valid publishes are stored in the
+/// `pending_publishes` table.
+fn validate_publish(
+    app: &AppState,
+    conn: &mut impl Conn,
+    user: &User,
+    existing_crate: Option<&Crate>,
+    request: PublishRequest,
+) -> AppResult<ValidatedPublish> {
+    let mut request = request;
+    request.resolve_staged_upload(app, conn, user.id)?;
+
+    let PublishRequest {
+        metadata,
+        version_string,
+        tarball_bytes,
+    } = request;
+
+    let verified_email_address = user.verified_email(conn)?;
+    let verified_email_address = verified_email_address.ok_or_else(|| {
+        bad_request(format!(
+            "A verified email address is required to publish crates to crates.io. \
+         Visit https://{}/settings/profile to set and verify your email address.",
+            app.config.domain_name,
+        ))
+    })?;
+
+    let content_length = tarball_bytes.len() as u64;
+
+    let maximums = Maximums::new(
+        existing_crate.and_then(|c| c.max_upload_size),
+        app.config.max_upload_size,
+        app.config.max_unpack_size,
+    );
+
+    if content_length > maximums.max_upload_size {
+        return Err(custom(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("max upload size is: {}", maximums.max_upload_size),
+        ));
+    }
 
-        let maximums = Maximums::new(
-            existing_crate.as_ref().and_then(|c| c.max_upload_size),
-            app.config.max_upload_size,
-            app.config.max_unpack_size,
-        );
+    let pkg_name = format!("{}-{}", &*metadata.name, &version_string);
+    let limits = TarballLimits {
+        max_unpack_size: maximums.max_unpack_size,
+        max_files: app.config.max_tarball_files,
+        max_path_depth: app.config.max_tarball_path_depth,
+    };
+    let mut tarball_info = process_tarball(&pkg_name, &*tarball_bytes, &limits)?;
+
+    // `unwrap()` is safe here since `process_tarball()` validates that
+    // we only accept manifests with a `package` section and without
+    // inheritance.
+    let package = tarball_info.manifest.package.take().unwrap();
+
+    let description = package.description.map(|it| it.as_local().unwrap());
+    let mut license = package.license.map(|it| it.as_local().unwrap());
+    let license_file = package.license_file.map(|it| it.as_local().unwrap());
+    let homepage = package.homepage.map(|it| it.as_local().unwrap());
+    let documentation = package.documentation.map(|it| it.as_local().unwrap());
+    let repository = package.repository.map(|it| it.as_local().unwrap());
+    let rust_version = package.rust_version.map(|rv| rv.as_local().unwrap());
+
+    // Make sure required fields are provided
+    fn empty(s: Option<&String>) -> bool {
+        s.map_or(true, String::is_empty)
+    }
 
-        if content_length > maximums.max_upload_size {
-            return Err(custom(StatusCode::PAYLOAD_TOO_LARGE, format!(
-                "max upload size is: {}",
-                maximums.max_upload_size
-            )));
-        }
+    // It can have up to three elements per below conditions.
+    let mut missing = Vec::with_capacity(3);
+    if empty(description.as_ref()) {
+        missing.push("description");
+    }
+    if empty(license.as_ref()) && empty(license_file.as_ref()) {
+        missing.push("license");
+    }
+    if !missing.is_empty() {
+        let message = missing_metadata_error_message(&missing);
+        return Err(bad_request(&message));
+    }
 
-        let pkg_name = format!("{}-{}", &*metadata.name, &version_string);
-        let tarball_info = process_tarball(&pkg_name, &*tarball_bytes, maximums.max_unpack_size)?;
+    if let Some(description) = &description {
+        if description.len() > MAX_DESCRIPTION_LENGTH {
+            return Err(bad_request(format!("The `description` is too long. A maximum of {MAX_DESCRIPTION_LENGTH} characters are currently allowed.")));
+        }
+    }
 
-        // `unwrap()` is safe here since `process_tarball()` validates that
-        // we only accept manifests with a `package` section and without
-        // inheritance.
-        let package = tarball_info.manifest.package.unwrap();
+    if let Some(ref license) = license {
+        parse_license_expr(license).map_err(|e| bad_request(format_args!(
+            "unknown or invalid license expression; \
+            see http://opensource.org/licenses for options, \
+            and http://spdx.org/licenses/ for their identifiers\n\
+            Note: If you have a non-standard license that is not listed by SPDX, \
+            use the license-file field to specify the path to a file containing \
+            the text of the license.\n\
+            See https://doc.rust-lang.org/cargo/reference/manifest.html#the-license-and-license-file-fields \
+            for more information.\n\
+            {e}"
+        )))?;
+    } else if license_file.is_some() {
+        // If no license is given, but a license file is given, flag this
+        // crate as having a nonstandard license. Note that we don't
+        // actually do anything else with license_file currently.
+        license = Some(String::from("non-standard"));
+    }
 
-        let description = package.description.map(|it| it.as_local().unwrap());
-        let mut license = package.license.map(|it| it.as_local().unwrap());
-        let license_file = package.license_file.map(|it| it.as_local().unwrap());
-        let homepage = package.homepage.map(|it| it.as_local().unwrap());
-        let documentation = package.documentation.map(|it| it.as_local().unwrap());
-        let repository = package.repository.map(|it| it.as_local().unwrap());
-        let rust_version = package.rust_version.map(|rv| rv.as_local().unwrap());
+    validate_url(homepage.as_deref(), "homepage")?;
+    validate_url(documentation.as_deref(), "documentation")?;
+    validate_url(repository.as_deref(), "repository")?;
+    if let Some(ref rust_version) = rust_version {
+        validate_rust_version(rust_version)?;
+    }
 
-        // Make sure required fields are provided
-        fn empty(s: Option<&String>) -> bool {
-            s.map_or(true, String::is_empty)
-        }
+    if let Some(channel) = &metadata.channel {
+        validate_channel(channel)?;
+    }
 
-        // It can have up to three elements per below conditions.
-        let mut missing = Vec::with_capacity(3);
-        if empty(description.as_ref()) {
-            missing.push("description");
-        }
-        if empty(license.as_ref()) && empty(license_file.as_ref()) {
-            missing.push("license");
-        }
-        if !missing.is_empty() {
-            let message = missing_metadata_error_message(&missing);
-            return Err(bad_request(&message));
-        }
+    if let Some(normalized_cksum) = &metadata.normalized_cksum {
+        validate_normalized_cksum(normalized_cksum)?;
+    }
 
-        if let Some(description) = &description {
-            if description.len() > MAX_DESCRIPTION_LENGTH {
-                return Err(bad_request(format!("The `description` is too long. A maximum of {MAX_DESCRIPTION_LENGTH} characters are currently allowed.")));
-            }
-        }
+    let keywords = package
+        .keywords
+        .map(|it| it.as_local().unwrap())
+        .unwrap_or_default();
 
-        if let Some(ref license) = license {
-            parse_license_expr(license).map_err(|e| bad_request(format_args!(
-                "unknown or invalid license expression; \
-                see http://opensource.org/licenses for options, \
-                and http://spdx.org/licenses/ for their identifiers\n\
-                Note: If you have a non-standard license that is not listed by SPDX, \
-                use the license-file field to specify the path to a file containing \
-                the text of the license.\n\
-                See https://doc.rust-lang.org/cargo/reference/manifest.html#the-license-and-license-file-fields \
-                for more information.\n\
-                {e}"
-            )))?;
-        } else if license_file.is_some() {
-            // If no license is given, but a license file is given, flag this
-            // crate as having a nonstandard license. Note that we don't
-            // actually do anything else with license_file currently.
-            license = Some(String::from("non-standard"));
-        }
+    if keywords.len() > 5 {
+        return Err(bad_request("expected at most 5 keywords per crate"));
+    }
 
-        validate_url(homepage.as_deref(), "homepage")?;
-        validate_url(documentation.as_deref(), "documentation")?;
-        validate_url(repository.as_deref(), "repository")?;
-        if let Some(ref rust_version) =  rust_version {
-            validate_rust_version(rust_version)?;
+    for keyword in keywords.iter() {
+        if keyword.len() > 20 {
+            return Err(bad_request(format!(
+                "\"{keyword}\" is an invalid keyword (keywords must have less than 20 characters)"
+            )));
+        } else if !Keyword::valid_name(keyword) {
+            return Err(bad_request(format!("\"{keyword}\" is an invalid keyword")));
         }
+    }
 
-        let keywords = package
-            .keywords
-            .map(|it| it.as_local().unwrap())
-            .unwrap_or_default();
-
-        if keywords.len() > 5 {
-            return Err(bad_request("expected at most 5 keywords per crate"));
-        }
+    let categories = package
+        .categories
+        .map(|it| it.as_local().unwrap())
+        .unwrap_or_default();
 
-        for keyword in keywords.iter() {
-            if keyword.len() > 20 {
-                return Err(bad_request(format!(
-                    "\"{keyword}\" is an invalid keyword (keywords must have less than 20 characters)"
-                )));
-            } else if !Keyword::valid_name(keyword) {
-                return Err(bad_request(format!("\"{keyword}\" is an invalid keyword")));
-            }
-        }
+    if categories.len() > 5 {
+        return Err(bad_request("expected at most 5 categories per crate"));
+    }
 
-        let categories = package
-            .categories
-            .map(|it| it.as_local().unwrap())
-            .unwrap_or_default();
+    let links_conflicts = match &package.links {
+        Some(links) => find_links_conflicts(links, &metadata.name, conn)?,
+        None => vec![],
+    };
 
-        if categories.len() > 5 {
-            return Err(bad_request("expected at most 5 categories per crate"));
-        }
+    let warnings = warnings::validate(&PublishedMetadata {
+        description: description.as_deref(),
+        license_file: license_file.as_deref(),
+        categories: &categories,
+        badges: &metadata.badges,
+        paths: &tarball_info.paths,
+        links: package.links.as_deref(),
+        links_conflicts: &links_conflicts,
+    });
+
+    let max_features = existing_crate
+        .and_then(|c| c.max_features.map(|mf| mf as usize))
+        .unwrap_or(app.config.max_features);
+    let max_feature_values = existing_crate
+        .and_then(|c| c.max_feature_values.map(|mfv| mfv as usize))
+        .unwrap_or(app.config.max_feature_values);
+
+    let features = tarball_info.manifest.features.take().unwrap_or_default();
+    let num_features = features.len();
+    if num_features > max_features {
+        return Err(bad_request(format!(
+            "crates.io only allows a maximum number of {max_features} \
+            features, but your crate is declaring {num_features} features.\n\
+            \n\
+            Take a look at https://blog.rust-lang.org/2023/10/26/broken-badges-and-23k-keywords.html \
+            to understand why this restriction was introduced.\n\
+            \n\
+            If you have a use case that requires an increase of this limit, \
+            please send us an email to help@crates.io to discuss the details."
+        )));
+    }
 
-        let max_features = existing_crate.as_ref()
-            .and_then(|c| c.max_features.map(|mf| mf as usize))
-            .unwrap_or(app.config.max_features);
+    for (key, values) in features.iter() {
+        Crate::validate_feature_name(key).map_err(bad_request)?;
 
-        let features = tarball_info.manifest.features.unwrap_or_default();
-        let num_features = features.len();
-        if num_features > max_features {
+        let num_features = values.len();
+        if num_features > max_feature_values {
             return Err(bad_request(format!(
-                "crates.io only allows a maximum number of {max_features} \
-                features, but your crate is declaring {num_features} features.\n\
+                "crates.io only allows a maximum number of {max_feature_values} \
+                features or dependencies that another feature can enable, \
+                but the \"{key}\" feature of your crate is enabling \
+                {num_features} features or dependencies.\n\
                 \n\
                 Take a look at https://blog.rust-lang.org/2023/10/26/broken-badges-and-23k-keywords.html \
                 to understand why this restriction was introduced.\n\
@@ -244,244 +479,661 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             )));
         }
 
-        for (key, values) in features.iter() {
-            Crate::validate_feature_name(key).map_err(bad_request)?;
+        for value in values.iter() {
+            Crate::validate_feature(value).map_err(bad_request)?;
+        }
+    }
+
+    let deps = convert_dependencies(
+        tarball_info.manifest.dependencies.as_ref(),
+        tarball_info.manifest.dev_dependencies.as_ref(),
+        tarball_info.manifest.build_dependencies.as_ref(),
+        tarball_info.manifest.target.as_ref(),
+    );
+
+    // The override of the crate takes precedence over the override of the
+    // publishing user, e.g. for crates with a large number of platform
+    // specific dependencies.
+    let max_dependencies = existing_crate
+        .and_then(|c| c.max_dependencies)
+        .or(user.max_dependencies)
+        .map(|md| md as usize)
+        .unwrap_or(app.config.max_dependencies);
+    if deps.len() > max_dependencies {
+        return Err(bad_request(format!(
+            "crates.io only allows a maximum number of {max_dependencies} dependencies.\n\
+            \n\
+            If you have a use case that requires an increase of this limit, \
+            please send us an email to help@crates.io to discuss the details."
+        )));
+    }
+
+    for dep in &deps {
+        validate_dependency(dep)?;
+    }
+
+    if let Some(krate) = existing_crate {
+        let settings = CrateSettings::for_crate(conn, krate.id)?;
+        check_denied_dependencies(&settings.denied_dependencies, &deps)?;
+    }
+
+    check_quarantined_dependencies(conn, &deps)?;
+
+    Ok(ValidatedPublish {
+        metadata,
+        version_string,
+        tarball_bytes,
+        tarball_info,
+        verified_email_address,
+        description,
+        license,
+        homepage,
+        documentation,
+        repository,
+        rust_version,
+        links: package.links,
+        build: package.build,
+        keywords,
+        categories,
+        features,
+        deps,
+        warnings,
+    })
+}
+
+/// Persists a validated publish, and uploads the crate file.
+#[allow(clippy::too_many_arguments)]
+fn persist_version(
+    app: &AppState,
+    conn: &mut impl Conn,
+    user: &User,
+    api_token_id: Option<i32>,
+    existing_crate: Option<Crate>,
+    validated: ValidatedPublish,
+    ci: Option<CiAnnotation>,
+    index_sync: IndexSync,
+) -> AppResult<Json<GoodCrate>> {
+    let ValidatedPublish {
+        metadata,
+        version_string,
+        tarball_bytes,
+        tarball_info,
+        verified_email_address,
+        description,
+        license,
+        homepage,
+        documentation,
+        repository,
+        rust_version,
+        links,
+        build,
+        keywords,
+        categories,
+        features,
+        deps,
+        warnings,
+    } = validated;
+
+    let content_length = tarball_bytes.len() as u64;
+    let channel = metadata.channel;
+    let normalized_cksum = metadata.normalized_cksum;
+
+    // Create a transaction on the database, if there are no errors,
+    // commit the transactions to record a new or updated crate.
+    conn.transaction(|conn| {
+        let name = metadata.name;
+        let keywords = keywords.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        let categories = categories.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+        // Persist the new crate, if it doesn't already exist
+        let persist = NewCrate {
+            name: &name,
+            description: description.as_deref(),
+            homepage: homepage.as_deref(),
+            documentation: documentation.as_deref(),
+            readme: metadata.readme.as_deref(),
+            repository: repository.as_deref(),
+            max_upload_size: None,
+            max_features: None,
+            max_dependencies: None,
+            max_feature_values: None,
+        };
 
-            let num_features = values.len();
-            if num_features > max_features {
+        if is_reserved_name(persist.name, conn)? {
+            return Err(bad_request("cannot upload a crate with a reserved name"));
+        }
+
+        if existing_crate.is_none() && app.config.upstream.is_some() {
+            if let Some(upstream_name) = upstream::find_cached_crate(conn, persist.name)? {
                 return Err(bad_request(format!(
-                    "crates.io only allows a maximum number of {max_features} \
-                    features or dependencies that another feature can enable, \
-                    but the \"{key}\" feature of your crate is enabling \
-                    {num_features} features or dependencies.\n\
-                    \n\
-                    Take a look at https://blog.rust-lang.org/2023/10/26/broken-badges-and-23k-keywords.html \
-                    to understand why this restriction was introduced.\n\
-                    \n\
-                    If you have a use case that requires an increase of this limit, \
-                    please send us an email to help@crates.io to discuss the details."
+                    "the crate `{upstream_name}` is provided by the upstream registry, \
+                     so a crate with this name can not be published"
                 )));
             }
+        }
+
+        if existing_crate.is_none() {
+            if let Some((similar, visibility)) = find_confusable_crate(persist.name, conn)? {
+                // Don't leak the names of private crates to other users.
+                let similar = match visibility {
+                    CrateVisibility::Public => format!("the existing crate `{similar}`"),
+                    CrateVisibility::Private => "an existing crate".to_string(),
+                };
 
-            for value in values.iter() {
-                Crate::validate_feature(value).map_err(bad_request)?;
+                return Err(bad_request(format!(
+                    "the crate name `{name}` can be confused with {similar}.\n\
+                    \n\
+                    If this is a legitimate use case, \
+                    please send us an email to help@crates.io to discuss the details."
+                )));
             }
         }
 
-        let deps = convert_dependencies(
-            tarball_info.manifest.dependencies.as_ref(),
-            tarball_info.manifest.dev_dependencies.as_ref(),
-            tarball_info.manifest.build_dependencies.as_ref(),
-            tarball_info.manifest.target.as_ref()
-        );
+        // To avoid race conditions, we try to insert
+        // first so we know whether to add an owner
+        let krate = match persist.create(conn, user.id).optional()? {
+            Some(krate) => krate,
+            None => persist.update(conn)?,
+        };
 
-        let max_dependencies = app.config.max_dependencies;
-        if deps.len() > max_dependencies {
-            return Err(bad_request(format!(
-                "crates.io only allows a maximum number of {max_dependencies} dependencies.\n\
-                \n\
-                If you have a use case that requires an increase of this limit, \
-                please send us an email to help@crates.io to discuss the details."
+        let owners = krate.owners(conn)?;
+        Permissions::load(app, user, &krate, &owners, conn)?.ensure(Capability::Publish)?;
+
+        // New crates belong to the registry that they were published to,
+        // and new versions can only be published to that registry.
+        if existing_crate.is_none() {
+            diesel::update(&krate)
+                .set(crates::registry.eq(registry))
+                .execute(conn)?;
+        } else if crate_registry(conn, &krate.name)?.as_deref() != Some(registry) {
+            return Err(bad_request(format_args!(
+                "crate `{}` belongs to a different registry",
+                krate.name
             )));
         }
 
-        for dep in &deps {
-            validate_dependency(dep)?;
+        if krate.name != *name {
+            return Err(bad_request(format_args!(
+                "crate was previously named `{}`",
+                krate.name
+            )));
         }
 
-        // Create a transaction on the database, if there are no errors,
-        // commit the transactions to record a new or updated crate.
-        conn.transaction(|conn| {
-            let name = metadata.name;
-            let keywords = keywords.iter().map(|s| s.as_str()).collect::<Vec<_>>();
-            let categories = categories.iter().map(|s| s.as_str()).collect::<Vec<_>>();
-
-            // Persist the new crate, if it doesn't already exist
-            let persist = NewCrate {
-                name: &name,
-                description: description.as_deref(),
-                homepage: homepage.as_deref(),
-                documentation: documentation.as_deref(),
-                readme: metadata.readme.as_deref(),
-                repository: repository.as_deref(),
-                max_upload_size: None,
-                max_features: None,
-            };
-
-            if is_reserved_name(persist.name, conn)? {
-                return Err(bad_request("cannot upload a crate with a reserved name"));
+        if let Some(daily_version_limit) = app.config.new_version_rate_limit {
+            let published_today = count_versions_published_today(krate.id, conn)?;
+            if published_today >= daily_version_limit as i64 {
+                return Err(custom(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "You have published too many versions of this crate in the last 24 hours",
+                ));
             }
+        }
 
-            // To avoid race conditions, we try to insert
-            // first so we know whether to add an owner
-            let krate = match persist.create(conn, user.id).optional()? {
-                Some(krate) => krate,
-                None => persist.update(conn)?,
-            };
+        // https://doc.rust-lang.org/cargo/reference/cargo-targets.html#the-name-field says that
+        // the `name` field is required for `bin` targets, so we can ignore `None` values via
+        // `filter_map()` here.
+        let bin_names = tarball_info.manifest.bin
+            .into_iter()
+            .filter_map(|bin| bin.name.clone())
+            .collect();
+
+        // `process_tarball()` sets `package.build` if the package contains a
+        // `build.rs` file, and `build = false` disables the build script.
+        let has_build_script = match build {
+            Some(StringOrBool::Bool(build)) => build,
+            Some(StringOrBool::String(_)) => true,
+            None => false,
+        };
 
-            let owners = krate.owners(conn)?;
-            if Handle::current().block_on(user.rights(&app, &owners))? < Rights::Publish {
-                return Err(custom(StatusCode::FORBIDDEN, MISSING_RIGHTS_ERROR_MESSAGE));
+        let is_proc_macro = tarball_info.manifest.lib.as_ref().is_some_and(|lib| lib.proc_macro);
+
+        // Read tarball from request
+        let hex_cksum: String = Sha256::digest(&tarball_bytes).encode_hex();
+
+        // Persist the new version of this crate
+        let version = NewVersion::builder(krate.id, &version_string)
+            .features(&features)?
+            .license(license)
+            // Downcast is okay because the file length must be less than the max upload size
+            // to get here, and max upload sizes are way less than i32 max
+            .size(content_length as i32)
+            .uncompressed_size(tarball_info.uncompressed_size as i64)
+            .published_by(user.id)
+            .checksum(hex_cksum)
+            .links(links)
+            .rust_version(rust_version)
+            .channel(channel)
+            .normalized_checksum(normalized_cksum)
+            .has_lib(tarball_info.manifest.lib.is_some())
+            .bin_names(bin_names)
+            .has_build_script(has_build_script)
+            .is_proc_macro(is_proc_macro)
+            .build()
+            .map_err(|error| internal(error.to_string()))?
+            .save(conn, &verified_email_address)?;
+
+        insert_version_owner_action(
+            conn,
+            version.id,
+            user.id,
+            api_token_id,
+            VersionAction::Publish,
+            None,
+        )?;
+
+        if let Some(ci) = &ci {
+            NewVersionCiAnnotation {
+                version_id: version.id,
+                ci_system: &ci.system,
+                run_url: ci.run_url.as_deref(),
             }
+            .insert(conn)?;
+        }
 
-            if krate.name != *name {
-                return Err(bad_request(format_args!(
-                    "crate was previously named `{}`",
-                    krate.name
-                )));
-            }
+        // The manifest is used to verify replacement tarballs, in case the
+        // stored tarball has to be repaired later on.
+        VersionContentManifest::new(version.id, &tarball_info.file_checksums).insert(conn)?;
 
-            if let Some(daily_version_limit) = app.config.new_version_rate_limit {
-                let published_today = count_versions_published_today(krate.id, conn)?;
-                if published_today >= daily_version_limit as i64 {
-                    return Err(custom(
-                        StatusCode::TOO_MANY_REQUESTS,
-                        "You have published too many versions of this crate in the last 24 hours",
-                    ));
-                }
-            }
+        NewRegistryEvent::version(RegistryEventKind::Publish, &krate.name, &version.num)
+            .insert(conn)?;
 
-            // https://doc.rust-lang.org/cargo/reference/cargo-targets.html#the-name-field says that
-            // the `name` field is required for `bin` targets, so we can ignore `None` values via
-            // `filter_map()` here.
-            let bin_names = tarball_info.manifest.bin
-                .into_iter()
-                .filter_map(|bin| bin.name.clone())
-                .collect();
-
-            // Read tarball from request
-            let hex_cksum: String = Sha256::digest(&tarball_bytes).encode_hex();
-
-            // Persist the new version of this crate
-            let version = NewVersion::builder(krate.id, &version_string)
-                .features(&features)?
-                .license(license)
-                // Downcast is okay because the file length must be less than the max upload size
-                // to get here, and max upload sizes are way less than i32 max
-                .size(content_length as i32)
-                .published_by(user.id)
-                .checksum(hex_cksum)
-                .links(package.links)
-                .rust_version(rust_version)
-                .has_lib(tarball_info.manifest.lib.is_some())
-                .bin_names(bin_names)
-                .build()
-                .map_err(|error| internal(error.to_string()))?
-                .save(conn, &verified_email_address)?;
-
-            insert_version_owner_action(
-                conn,
-                version.id,
-                user.id,
-                api_token_id,
-                VersionAction::Publish,
-            )?;
-
-            // Link this new version to all dependencies
-            add_dependencies(conn, &deps, version.id)?;
-
-            // Insert the default version if it doesn't already exist. Compared
-            // to only using a background job, this prevents us from getting
-            // into a situation where a crate exists in the `crates` table but
-            // doesn't have a default version in the `default_versions` table.
-            let inserted_default_versions = diesel::insert_into(default_versions::table)
-                .values((
-                    default_versions::crate_id.eq(krate.id),
-                    default_versions::version_id.eq(version.id),
-                ))
-                .on_conflict_do_nothing()
-                .execute(conn)?;
+        // Link this new version to all dependencies
+        add_dependencies(conn, &deps, version.id)?;
 
-            // Update all keywords for this crate
-            Keyword::update_crate(conn, &krate, &keywords)?;
+        // Insert the default version if it doesn't already exist. Compared
+        // to only using a background job, this prevents us from getting
+        // into a situation where a crate exists in the `crates` table but
+        // doesn't have a default version in the `default_versions` table.
+        let inserted_default_versions = diesel::insert_into(default_versions::table)
+            .values((
+                default_versions::crate_id.eq(krate.id),
+                default_versions::version_id.eq(version.id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        // Update all keywords for this crate
+        Keyword::update_crate(conn, &krate, &keywords)?;
+
+        // Update all categories for this crate, collecting any invalid categories
+        // in order to be able to return an error to the user.
+        let unknown_categories = Category::update_crate(conn, &krate, &categories)?;
+        if !unknown_categories.is_empty() {
+            let unknown_categories = unknown_categories.join(", ");
+            let domain = &app.config.domain_name;
+            return Err(bad_request(format!("The following category slugs are not currently supported on crates.io: {}\n\nSee https://{}/category_slugs for a list of supported slugs.", unknown_categories, domain)));
+        }
 
-            // Update all categories for this crate, collecting any invalid categories
-            // in order to be able to return an error to the user.
-            let unknown_categories = Category::update_crate(conn, &krate, &categories)?;
-            if !unknown_categories.is_empty() {
-                let unknown_categories = unknown_categories.join(", ");
-                let domain = &app.config.domain_name;
-                return Err(bad_request(format!("The following category slugs are not currently supported on crates.io: {}\n\nSee https://{}/category_slugs for a list of supported slugs.", unknown_categories, domain)));
+        let top_versions = krate.top_versions(conn)?;
+
+        let downloads: i64 = crate_downloads::table.select(crate_downloads::downloads)
+            .filter(crate_downloads::crate_id.eq(krate.id))
+            .first(conn)?;
+
+        let pkg_path_in_vcs = tarball_info.vcs_info.map(|info| info.path_in_vcs);
+
+        if let Some(readme) = metadata.readme {
+            if !readme.is_empty() {
+                jobs::RenderAndUploadReadme::new(
+                    version.id,
+                    readme,
+                    metadata
+                        .readme_file
+                        .unwrap_or_else(|| String::from("README.md")),
+                    repository,
+                    pkg_path_in_vcs,
+                )
+                .enqueue(conn)?;
             }
+        }
 
-            let top_versions = krate.top_versions(conn)?;
-
-            let downloads: i64 = crate_downloads::table.select(crate_downloads::downloads)
-                .filter(crate_downloads::crate_id.eq(krate.id))
-                .first(conn)?;
-
-            let pkg_path_in_vcs = tarball_info.vcs_info.map(|info| info.path_in_vcs);
-
-            if let Some(readme) = metadata.readme {
-                if !readme.is_empty() {
-                    jobs::RenderAndUploadReadme::new(
-                        version.id,
-                        readme,
-                        metadata
-                            .readme_file
-                            .unwrap_or_else(|| String::from("README.md")),
-                        repository,
-                        pkg_path_in_vcs,
-                    )
-                    .enqueue(conn)?;
-                }
-            }
+        // Upload crate tarball
+        Handle::current()
+            .block_on(app.storage.upload_crate_file(
+                &krate.name,
+                &version_string,
+                krate.visibility,
+                tarball_bytes,
+            ))
+            .map_err(|e| internal(format!("failed to upload crate: {e}")))?;
 
-            // Upload crate tarball
-            Handle::current()
-                .block_on(app.storage.upload_crate_file(
-                    &krate.name,
-                    &version_string,
-                    tarball_bytes,
-                ))
-                .map_err(|e| internal(format!("failed to upload crate: {e}")))?;
+        if app.config.clamd_address.is_some() {
+            jobs::ScanTarball::new(version.id).enqueue(conn)?;
+        }
+
+        // Potential spam is published anyway, but has to be reviewed by the
+        // crates.io team, since the classification is only a heuristic.
+        let spam_score = app.spam_classifier.classify(description.as_deref(), &keywords);
+        if spam_score.score >= app.config.spam.threshold {
+            warn!(
+                spam.score = spam_score.score,
+                "Adding {}@{} to the spam moderation queue", krate.name, version.num
+            );
+            NewSpamFlag::new(version.id, &spam_score).insert(conn)?;
+        }
 
+        if index_sync == IndexSync::Enqueue {
             jobs::enqueue_sync_to_index(&krate.name, conn)?;
+        }
 
-            // If this is a new version for an existing crate it is sufficient
-            // to update the default version asynchronously in a background job.
-            if inserted_default_versions == 0 {
-                UpdateDefaultVersion::new(krate.id).enqueue(conn)?;
-            }
+        // Nobody can be subscribed to new versions of a crate that did not
+        // exist before.
+        if existing_crate.is_some() {
+            jobs::SendSubscriptionNotifications::new(version.id).enqueue(conn)?;
+        }
 
-            // Experiment: check new crates for potential typosquatting.
-            if existing_crate.is_none() {
-                CheckTyposquat::new(&krate.name).enqueue(conn)?;
-            }
+        // If this is a new version for an existing crate it is sufficient
+        // to update the default version asynchronously in a background job.
+        if inserted_default_versions == 0 {
+            UpdateDefaultVersion::new(krate.id).enqueue(conn)?;
+        }
+
+        // Experiment: check new crates for potential typosquatting.
+        if existing_crate.is_none() {
+            CheckTyposquat::new(&krate.name).enqueue(conn)?;
+        }
+
+        let job = jobs::rss::SyncCrateFeed::new(krate.name.clone());
+        if let Err(error) = job.enqueue(conn) {
+            error!("Failed to enqueue `rss::SyncCrateFeed` job: {error}");
+        }
+
+        if let Err(error) = jobs::rss::SyncUpdatesFeed.enqueue(conn) {
+            error!("Failed to enqueue `rss::SyncUpdatesFeed` job: {error}");
+        }
 
-            let job = jobs::rss::SyncCrateFeed::new(krate.name.clone());
-            if let Err(error) = job.enqueue(conn) {
-                error!("Failed to enqueue `rss::SyncCrateFeed` job: {error}");
+        if existing_crate.is_none() {
+            if let Err(error) = jobs::rss::SyncCratesFeed.enqueue(conn) {
+                error!("Failed to enqueue `rss::SyncCratesFeed` job: {error}");
             }
+        }
+
+        // The default version of existing crates is updated asynchronously
+        // in a background job, so it is not known yet at this point.
+        Ok(Json(GoodCrate {
+            krate: EncodableCrate::from_minimal(krate, Some(&top_versions), None, None, false, downloads, None),
+            warnings,
+        }))
+    })
+}
 
-            if let Err(error) = jobs::rss::SyncUpdatesFeed.enqueue(conn) {
-                error!("Failed to enqueue `rss::SyncUpdatesFeed` job: {error}");
+/// Whether [`publish_version()`] enqueues the index sync jobs for the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexSync {
+    Enqueue,
+    /// The caller enqueues the index sync jobs in the same transaction, e.g.
+    /// for all crates of a batch publish at once.
+    Deferred,
+}
+
+/// The parsed body of a `PUT /crates/new` request.
+struct PublishRequest {
+    metadata: PublishMetadata,
+    version_string: String,
+    tarball_bytes: Bytes,
+}
+
+impl PublishRequest {
+    fn parse(mut bytes: Bytes) -> AppResult<Self> {
+        let (json_bytes, tarball_bytes) = split_body(&mut bytes)?;
+        Self::from_parts(&json_bytes, tarball_bytes)
+    }
+
+    /// Parses the body of a `PUT /crates/batch` request, which consists of
+    /// the bodies of multiple `PUT /crates/new` requests, one after another.
+    fn parse_batch(mut bytes: Bytes) -> AppResult<Vec<Self>> {
+        let mut requests = Vec::new();
+        while bytes.has_remaining() {
+            let (json_bytes, tarball_bytes) = split_body(&mut bytes)?;
+            requests.push(Self::from_parts(&json_bytes, tarball_bytes)?);
+        }
+
+        Ok(requests)
+    }
+
+    fn from_parts(json_bytes: &[u8], tarball_bytes: Bytes) -> AppResult<Self> {
+        let metadata: PublishMetadata = serde_json::from_slice(json_bytes)
+            .map_err(|e| bad_request(format_args!("invalid upload request: {e}")))?;
+
+        Crate::validate_crate_name("crate", &metadata.name).map_err(bad_request)?;
+
+        let version = match semver::Version::parse(&metadata.vers) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                return Err(bad_request(format_args!(
+                    "\"{}\" is an invalid semver version",
+                    metadata.vers
+                )))
             }
+        };
+
+        // Convert the version back to a string to deal with any inconsistencies
+        let version_string = version.to_string();
+
+        Ok(Self {
+            metadata,
+            version_string,
+            tarball_bytes,
+        })
+    }
+
+    fn crate_name(&self) -> &str {
+        &self.metadata.name
+    }
+
+    /// Replaces the empty tarball of the request with the staged upload that
+    /// is referenced by its `staging_id`, if any.
+    fn resolve_staged_upload(
+        &mut self,
+        app: &AppState,
+        conn: &mut impl Conn,
+        user_id: i32,
+    ) -> AppResult<()> {
+        let Some(staging_id) = self.metadata.staging_id.take() else {
+            return Ok(());
+        };
+
+        if !self.tarball_bytes.is_empty() {
+            return Err(bad_request(
+                "a publish request can not contain a tarball and a `staging_id` at the same time",
+            ));
+        }
 
-            if existing_crate.is_none() {
-                if let Err(error) = jobs::rss::SyncCratesFeed.enqueue(conn) {
-                    error!("Failed to enqueue `rss::SyncCratesFeed` job: {error}");
+        let upload = StagedUpload::find(conn, user_id, &staging_id)?.ok_or_else(|| {
+            bad_request(format!(
+                "the staged upload `{staging_id}` does not exist or has expired"
+            ))
+        })?;
+
+        if upload.scan_verdict == Some(ScanVerdict::Infected) {
+            return Err(bad_request(format!(
+                "the staged upload `{staging_id}` was flagged by the virus scanner"
+            )));
+        }
+
+        let tarball_bytes = Handle::current()
+            .block_on(app.storage.download_staged_file(&staging_id))
+            .map_err(|e| internal(format!("failed to download staged upload: {e}")))?;
+
+        let checksum: String = Sha256::digest(&tarball_bytes).encode_hex();
+        if checksum != upload.checksum {
+            return Err(internal(format!(
+                "checksum mismatch of staged upload `{staging_id}`"
+            )));
+        }
+
+        self.tarball_bytes = tarball_bytes;
+        Ok(())
+    }
+}
+
+/// Stores the raw publish request in the `pending_publishes` table, after it
+/// has been validated, and sends an email to the user with a link to confirm
+/// the publish.
+///
+/// Cargo treats any non-200 response as a failure, so the pending state is
+/// reported to the user as a publish warning instead.
+fn hold_publish(
+    app: &AppState,
+    conn: &mut impl Conn,
+    user: &User,
+    api_token_id: Option<i32>,
+    validated: &ValidatedPublish,
+    ci: Option<CiAnnotation>,
+    body: Bytes,
+) -> AppResult<Value> {
+    let recipient = models::Email::find_recipient(conn, user.id, NotificationClass::Publishing)?;
+    let recipient = recipient.ok_or_else(|| {
+        bad_request(format!(
+            "A verified email address is required to publish crates to crates.io. \
+             Visit https://{}/settings/profile to set and verify your email address.",
+            app.config.domain_name,
+        ))
+    })?;
+
+    let pending = NewPendingPublish {
+        user_id: user.id,
+        api_token_id,
+        crate_name: &validated.metadata.name,
+        version: &validated.version_string,
+        body: &body,
+        ci_system: ci.as_ref().map(|ci| ci.system.as_str()),
+        ci_run_url: ci.as_ref().and_then(|ci| ci.run_url.as_deref()),
+    };
+
+    conn.transaction(|conn| {
+        let token = pending.insert(conn)?;
+
+        let email = PublishConfirmationEmail {
+            user_name: &user.gh_login,
+            domain: &app.emails.domain,
+            crate_name: pending.crate_name,
+            version: pending.version,
+            token,
+        };
+
+        app.emails
+            .send(conn, &recipient, email)
+            .map_err(BoxedAppError::from)
+    })?;
+
+    let message = format!(
+        "Publishing {} v{} requires confirmation. Please click the link in \
+         the email that was sent to {recipient} to complete the publish. \
+         The link expires in 24 hours.",
+        validated.metadata.name, validated.version_string,
+    );
+
+    let warnings = PublishWarnings {
+        other: vec![message],
+        ..Default::default()
+    };
+
+    Ok(json!({ "warnings": warnings }))
+}
+
+/// Handles the `PUT /confirm_publish/:token` route.
+///
+/// Completes a publish that was held back by [`hold_publish()`], using the
+/// stored body of the original request.
+pub async fn confirm_publish(
+    app: AppState,
+    Path(token): Path<String>,
+) -> AppResult<Json<GoodCrate>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        // If the publish fails, the transaction is rolled back, which keeps
+        // the pending publish around until it expires.
+        conn.transaction(|conn| {
+            let pending = PendingPublish::take_by_token(conn, &token)?
+                .ok_or_else(|| bad_request("Pending publish belonging to token not found."))?;
+
+            let mut registry = DEFAULT_REGISTRY.to_string();
+            if let Some(api_token_id) = pending.api_token_id {
+                let (revoked, token_registry): (bool, String) = api_tokens::table
+                    .find(api_token_id)
+                    .select((api_tokens::revoked, api_tokens::registry))
+                    .get_result(conn)?;
+
+                if revoked {
+                    return Err(bad_request(
+                        "The API token used for this publish has been revoked.",
+                    ));
                 }
+
+                // The publish belongs to the registry that the token was
+                // created for.
+                registry = token_registry;
             }
 
-            // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
-            // that is no longer needed. As such, crates.io currently does not return any `other`
-            // warnings at this time, but if we need to, the field is available.
-            let warnings = PublishWarnings {
-                invalid_categories: vec![],
-                invalid_badges: vec![],
-                other: vec![],
-            };
+            let user = User::find(conn, pending.user_id)?;
+            ensure_not_locked(&user)?;
+
+            let request = PublishRequest::parse(Bytes::from(pending.body))?;
+
+            let ci = pending
+                .ci_system
+                .map(|system| CiAnnotation::new(system, pending.ci_run_url))
+                .transpose()?;
 
-            Ok(Json(GoodCrate {
-                krate: EncodableCrate::from_minimal(krate, Some(&top_versions), None, false, downloads, None),
-                warnings,
-            }))
+            let existing_crate: Option<Crate> = Crate::by_name(request.crate_name())
+                .first(conn)
+                .optional()?;
+
+            // The rate limit was already checked when the publish was held
+            // back, but the crate might have changed since then.
+            let validated = validate_publish(&app, conn, &user, existing_crate.as_ref(), request)?;
+            persist_version(
+                &app,
+                conn,
+                &user,
+                pending.api_token_id,
+                &registry,
+                existing_crate,
+                validated,
+                ci,
+                IndexSync::Enqueue,
+            )
         })
     })
     .await
 }
 
+struct PublishConfirmationEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+    crate_name: &'a str,
+    version: &'a str,
+    token: SecretString,
+}
+
+impl Email for PublishConfirmationEmail<'_> {
+    const SUBJECT: &'static str = "Please confirm your crate publish";
+
+    fn body(&self) -> String {
+        format!(
+            "Hello {user_name}!
+
+Version {version} of the crate {crate_name} was published using one of your \
+API tokens. Since you have enabled publish confirmations, the publish will \
+only be completed after you click the link below:
+
+https://{domain}/confirm-publish/{token}
+
+The link expires in 24 hours. If you did not publish this version, please \
+do not click the link, and revoke your API tokens at \
+https://{domain}/settings/tokens instead.",
+            user_name = self.user_name,
+            domain = self.domain,
+            crate_name = self.crate_name,
+            version = self.version,
+            token = self.token.expose_secret(),
+        )
+    }
+}
+
 /// Counts the number of versions for `crate_id` that were published within
 /// the last 24 hours.
 fn count_versions_published_today(crate_id: i32, conn: &mut impl Conn) -> QueryResult<i64> {
@@ -495,7 +1147,7 @@ fn count_versions_published_today(crate_id: i32, conn: &mut impl Conn) -> QueryR
 }
 
 #[instrument(skip_all)]
-fn split_body(mut bytes: Bytes) -> AppResult<(Bytes, Bytes)> {
+fn split_body(bytes: &mut Bytes) -> AppResult<(Bytes, Bytes)> {
     // The format of the req.body() of a publish request is as follows:
     //
     // metadata length
@@ -541,6 +1193,50 @@ fn is_reserved_name(name: &str, conn: &mut impl Conn) -> QueryResult<bool> {
     .get_result(conn)
 }
 
+/// Returns the name and visibility of an existing crate, whose name only
+/// differs from the given name by characters that look alike, unless an
+/// override has been added for the given name.
+fn find_confusable_crate(
+    name: &str,
+    conn: &mut impl Conn,
+) -> QueryResult<Option<(String, CrateVisibility)>> {
+    let is_overridden = select(exists(crate_name_skeleton_overrides::table.filter(
+        canon_crate_name(crate_name_skeleton_overrides::name).eq(canon_crate_name(name)),
+    )))
+    .get_result(conn)?;
+
+    if is_overridden {
+        return Ok(None);
+    }
+
+    crates::table
+        .filter(crate_name_skeleton(crates::name).eq(crate_name_skeleton(name)))
+        .filter(canon_crate_name(crates::name).ne(canon_crate_name(name)))
+        .select((crates::name, crates::visibility))
+        .first(conn)
+        .optional()
+}
+
+/// Returns the names of the other public crates with non-yanked versions
+/// that declare the `links` key.
+///
+/// Cargo rejects dependency graphs with multiple packages that declare the
+/// same `links` key, but forks of `-sys` crates legitimately reuse the key of
+/// the original crate, so conflicts are reported as a publish warning.
+fn find_links_conflicts(links: &str, name: &str, conn: &mut impl Conn) -> QueryResult<Vec<String>> {
+    crates::table
+        .inner_join(versions::table)
+        .filter(versions::links.eq(links))
+        .filter(versions::yanked.eq(false))
+        .filter(canon_crate_name(crates::name).ne(canon_crate_name(name)))
+        .filter(crates::visibility.eq(CrateVisibility::Public))
+        .select(crates::name)
+        .distinct()
+        .order(crates::name)
+        .limit(MAX_LINKS_CONFLICTS)
+        .load(conn)
+}
+
 fn validate_url(url: Option<&str>, field: &str) -> AppResult<()> {
     let Some(url) = url else {
         return Ok(());
@@ -579,6 +1275,38 @@ fn validate_rust_version(value: &str) -> AppResult<()> {
     }
 }
 
+/// Release channel names consist of lowercase ASCII letters, digits and `-`,
+/// and start with a letter, so that they can be used in URLs as they are.
+fn validate_channel(value: &str) -> AppResult<()> {
+    if value.len() > MAX_CHANNEL_LENGTH {
+        return Err(bad_request(format!(
+            "The `channel` is too long. A maximum of {MAX_CHANNEL_LENGTH} characters are currently allowed."
+        )));
+    }
+
+    let mut chars = value.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if !valid {
+        return Err(bad_request(format!(
+            "invalid `channel` value `{value}`: channel names must start with a lowercase letter and only contain lowercase letters, digits and `-`"
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_normalized_cksum(value: &str) -> AppResult<()> {
+    if !ReproducibilityReport::valid_checksum(value) {
+        return Err(bad_request(
+            "invalid `normalized_cksum` value: expected a hex-encoded SHA256 checksum",
+        ));
+    }
+
+    Ok(())
+}
+
 fn convert_dependencies(
     normal_deps: Option<&DepsSet>,
     dev_deps: Option<&DepsSet>,
@@ -693,6 +1421,79 @@ pub fn validate_dependency(dep: &EncodableCrateDependency) -> AppResult<()> {
     Ok(())
 }
 
+/// Rejects dependencies that the owners of the crate put on its deny-list
+/// via the crate settings.
+fn check_denied_dependencies(
+    deny_list: &DependencyDenyList,
+    deps: &[EncodableCrateDependency],
+) -> AppResult<()> {
+    for dep in deps {
+        // The requirements were already validated by `validate_dependency()`
+        let Ok(version_req) = semver::VersionReq::parse(&dep.version_req) else {
+            continue;
+        };
+
+        if let Some(denied) = deny_list.find_violation(&dep.name, &version_req) {
+            let denied = match &denied.version_req {
+                Some(denied_req) => format!("`{} {denied_req}`", denied.name),
+                None => format!("`{}`", denied.name),
+            };
+
+            return Err(bad_request(format!(
+                "the dependency `{} {}` is not allowed, since the owners of this crate \
+                denied the use of {denied} in the crate settings",
+                dep.name, dep.version_req
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects dependencies that allow quarantined versions of other crates,
+/// unless an admin exempted the dependency from this policy.
+fn check_quarantined_dependencies(
+    conn: &mut impl Conn,
+    deps: &[EncodableCrateDependency],
+) -> AppResult<()> {
+    if deps.is_empty() {
+        return Ok(());
+    }
+
+    let exempted =
+        dependency_policy_exceptions::table.select(dependency_policy_exceptions::crate_id);
+
+    let quarantined: Vec<(String, String)> = version_quarantines::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(crates::name.eq_any(deps.iter().map(|dep| &dep.name)))
+        .filter(crates::id.ne_all(exempted))
+        .select((crates::name, versions::num))
+        .load(conn)?;
+
+    for dep in deps {
+        // The requirements were already validated by `validate_dependency()`
+        let Ok(version_req) = semver::VersionReq::parse(&dep.version_req) else {
+            continue;
+        };
+
+        let violation = quarantined.iter().find(|(name, num)| {
+            *name == dep.name
+                && semver::Version::parse(num).is_ok_and(|version| version_req.matches(&version))
+        });
+
+        if let Some((name, num)) = violation {
+            return Err(bad_request(format!(
+                "the dependency `{} {}` is not allowed, since `{name}@{num}` has been \
+                quarantined by the crates.io team because it is suspected to be malicious. \
+                Please contact help@crates.io if you think that this is a mistake.",
+                dep.name, dep.version_req
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 #[instrument(skip_all)]
 pub fn add_dependencies(
     conn: &mut impl Conn,
@@ -746,6 +1547,18 @@ impl From<TarballError> for BoxedAppError {
                 "uploaded tarball is malformed or too large when decompressed",
             ),
             TarballError::InvalidPath(path) => bad_request(format!("invalid path found: {path}")),
+            TarballError::PathTraversal(path) => {
+                bad_request(format!("invalid path found: {path}; `..` is not allowed in paths"))
+            }
+            TarballError::PathTooDeep { path, max } => bad_request(format!(
+                "uploaded tarball contains a path that is nested more than {max} levels deep: {path}"
+            )),
+            TarballError::TooManyFiles { max } => bad_request(format!(
+                "uploaded tarball contains more than {max} files"
+            )),
+            TarballError::TooLarge { max } => bad_request(format!(
+                "uploaded tarball is larger than {max} bytes when decompressed"
+            )),
             TarballError::UnexpectedSymlink(path) => {
                 bad_request(format!("unexpected symlink or hard link found: {path}"))
             }
@@ -0,0 +1,294 @@
+//! Publishing the crates of a workspace in a single request.
+//!
+//! The body of a `PUT /crates/batch` request consists of the bodies of
+//! multiple `PUT /crates/new` requests, one after another. The dependencies
+//! between the crates of the batch are validated up front, and the crates are
+//! then published in dependency order within a single database transaction,
+//! so that either all or none of them are published. The index files of all
+//! crates are updated in a single git commit.
+//!
+//! Each crate is subject to the same checks as a regular publish, including
+//! the scopes of the API token and the rate limits. Like for a regular
+//! publish, the tarball of a crate can be a staged upload (see
+//! [`crate::controllers::staging`]).
+
+use super::ci::CiAnnotation;
+use super::{
+    acquire_publish_slot, convert_dependencies, publish_version, IndexSync, PublishRequest,
+};
+use crate::auth::AuthCheck;
+use crate::controllers::cargo_prelude::*;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::registry::RequestRegistry;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, DependencyKind};
+use crate::util::errors::bad_request;
+use crate::util::Maximums;
+use crate::views::EncodableCrateDependency;
+use crate::worker::jobs;
+use crates_io_tarball::{process_tarball, TarballLimits};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::{HashMap, HashSet};
+use tokio::runtime::Handle;
+
+/// The maximum number of crates that can be published in a single batch.
+const MAX_BATCH_SIZE: usize = 50;
+
+/// A crate of a batch publish.
+struct BatchEntry {
+    request: PublishRequest,
+    existing_crate: Option<Crate>,
+    /// The dependencies declared in the manifest of the tarball.
+    deps: Vec<EncodableCrateDependency>,
+}
+
+/// Handles the `PUT /crates/batch` route.
+///
+/// Publishes all crates of the request body atomically, and responds with
+/// the published crates in the order in which they were published.
+pub async fn publish_batch(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    let (req, bytes) = req.0.into_parts();
+    let requests = PublishRequest::parse_batch(bytes)?;
+    let ci = CiAnnotation::from_headers(&req.headers)?;
+
+    if requests.len() > MAX_BATCH_SIZE {
+        return Err(bad_request(format!(
+            "a batch can contain at most {MAX_BATCH_SIZE} crates"
+        )));
+    }
+
+    let mut names = HashSet::new();
+    for request in &requests {
+        if !names.insert(request.crate_name()) {
+            return Err(bad_request(format!(
+                "the batch contains the crate `{}` more than once",
+                request.crate_name()
+            )));
+        }
+    }
+
+    req.request_log().add("batch_size", requests.len());
+
+    let conn = app.db_write().await?;
+    let (auth, batch) = spawn_blocking({
+        let app = app.clone();
+        move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let mut batch = Vec::with_capacity(requests.len());
+            let mut auth = None;
+            for mut request in requests {
+                // See `publish()` for why this query is only used for the
+                // endpoint scope calculation.
+                let existing_crate: Option<Crate> = Crate::by_name(request.crate_name())
+                    .first(conn)
+                    .optional()?;
+
+                let endpoint_scope = match existing_crate {
+                    Some(_) => EndpointScope::PublishUpdate,
+                    None => EndpointScope::PublishNew,
+                };
+
+                // The API token has to be allowed to publish every crate of
+                // the batch.
+                let crate_auth = AuthCheck::default()
+                    .with_endpoint_scope(endpoint_scope)
+                    .for_crate(request.crate_name())
+                    .check(&req, conn)?;
+
+                request.resolve_staged_upload(&app, conn, crate_auth.user_id())?;
+                auth = Some(crate_auth);
+
+                let deps = tarball_dependencies(&app, &request, existing_crate.as_ref())?;
+                batch.push(BatchEntry {
+                    request,
+                    existing_crate,
+                    deps,
+                });
+            }
+
+            let auth = auth.ok_or_else(|| bad_request("the batch does not contain any crates"))?;
+
+            if auth.api_token_id().is_some() && auth.user().publish_confirmation_required {
+                return Err(bad_request(
+                    "Publishes that have to be confirmed via email can not be batched. \
+                     Please publish the crates individually.",
+                ));
+            }
+
+            let order = publish_order(&batch)?;
+            let mut batch = batch.into_iter().enumerate().collect::<Vec<_>>();
+            batch.sort_by_key(|(index, _)| order.iter().position(|i| i == index));
+
+            Ok::<_, BoxedAppError>((auth, batch))
+        }
+    })
+    .await?;
+
+    // See `publish()` for why the slot is acquired without holding a
+    // database connection.
+    let permit = acquire_publish_slot(&app, auth.user_id()).await?;
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let _permit = permit;
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+        let api_token_id = auth.api_token_id();
+        let user = auth.user();
+
+        let mut crates = Vec::with_capacity(batch.len());
+        let mut uploaded = Vec::with_capacity(batch.len());
+        let result = conn.transaction(|conn| {
+            for (_, entry) in batch {
+                let version = entry.request.version_string.clone();
+                let Json(krate) = publish_version(
+                    &app,
+                    conn,
+                    user,
+                    api_token_id,
+                    req.registry(),
+                    entry.existing_crate,
+                    entry.request,
+                    ci.clone(),
+                    IndexSync::Deferred,
+                )?;
+                uploaded.push((krate.krate.name.clone(), version));
+                crates.push(krate);
+            }
+
+            let names = crates
+                .iter()
+                .map(|c| c.krate.name.clone())
+                .collect::<Vec<_>>();
+            jobs::enqueue_batch_sync_to_index(&names, conn)?;
+
+            Ok::<_, BoxedAppError>(())
+        });
+
+        if let Err(error) = result {
+            // The tarballs of the crates that were published before the
+            // failure have already been uploaded, but their versions were
+            // rolled back.
+            for (name, version) in &uploaded {
+                let future = app.storage.delete_crate_file(name, version);
+                if let Err(error) = Handle::current().block_on(future) {
+                    warn!("Failed to delete the tarball of {name}@{version}: {error}");
+                }
+            }
+
+            return Err(error);
+        }
+
+        Ok(Json(json!({ "crates": crates })))
+    })
+    .await
+}
+
+/// Reads the dependencies from the manifest in the tarball of the request.
+///
+/// The tarball is processed again by [`publish_version()`], which performs
+/// the full validation.
+fn tarball_dependencies(
+    app: &AppState,
+    request: &PublishRequest,
+    existing_crate: Option<&Crate>,
+) -> AppResult<Vec<EncodableCrateDependency>> {
+    let maximums = Maximums::new(
+        existing_crate.and_then(|c| c.max_upload_size),
+        app.config.max_upload_size,
+        app.config.max_unpack_size,
+    );
+
+    let pkg_name = format!("{}-{}", request.crate_name(), request.version_string);
+    let limits = TarballLimits {
+        max_unpack_size: maximums.max_unpack_size,
+        max_files: app.config.max_tarball_files,
+        max_path_depth: app.config.max_tarball_path_depth,
+    };
+    let manifest = process_tarball(&pkg_name, &*request.tarball_bytes, &limits)?.manifest;
+
+    Ok(convert_dependencies(
+        manifest.dependencies.as_ref(),
+        manifest.dev_dependencies.as_ref(),
+        manifest.build_dependencies.as_ref(),
+        manifest.target.as_ref(),
+    ))
+}
+
+/// Returns the indices of the crates of the batch in the order in which they
+/// have to be published, so that every crate is published after the crates
+/// of the batch that it depends on.
+///
+/// Dependencies on crates of the batch whose version requirement does not
+/// match the version in the batch refer to previously published versions,
+/// which requires the crate to exist already. Dev-dependencies on existing
+/// crates don't affect the order, since they may form cycles.
+fn publish_order(batch: &[BatchEntry]) -> AppResult<Vec<usize>> {
+    let versions = batch
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let version = semver::Version::parse(&entry.request.version_string).ok()?;
+            Some((entry.request.crate_name(), (index, version)))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut dependencies = vec![HashSet::new(); batch.len()];
+    for (index, entry) in batch.iter().enumerate() {
+        for dep in &entry.deps {
+            let Some((dep_index, version)) = versions.get(dep.name.as_str()) else {
+                continue;
+            };
+
+            // Invalid requirements are reported by `publish_version()`.
+            let Ok(req) = semver::VersionReq::parse(&dep.version_req) else {
+                continue;
+            };
+
+            let dep_entry = &batch[*dep_index];
+            if !req.matches(version) {
+                if dep_entry.existing_crate.is_none() {
+                    return Err(bad_request(format!(
+                        "`{}` depends on `{}` {}, which does not match version {} of the batch",
+                        entry.request.crate_name(),
+                        dep.name,
+                        dep.version_req,
+                        version
+                    )));
+                }
+                continue;
+            }
+
+            let is_dev = dep.kind == Some(DependencyKind::Dev);
+            if *dep_index != index && !(is_dev && dep_entry.existing_crate.is_some()) {
+                dependencies[index].insert(*dep_index);
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(batch.len());
+    let mut published = vec![false; batch.len()];
+    while order.len() < batch.len() {
+        let next = (0..batch.len()).find(|&index| {
+            !published[index] && dependencies[index].iter().all(|&dep| published[dep])
+        });
+
+        let Some(next) = next else {
+            let names = (0..batch.len())
+                .filter(|&index| !published[index])
+                .map(|index| format!("`{}`", batch[index].request.crate_name()))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            return Err(bad_request(format!(
+                "the crates {names} of the batch depend on each other, \
+                 so they can not be published in any order"
+            )));
+        };
+
+        published[next] = true;
+        order.push(next);
+    }
+
+    Ok(order)
+}
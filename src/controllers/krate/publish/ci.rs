@@ -0,0 +1,141 @@
+//! Annotations of publishes from CI systems.
+//!
+//! Publish requests can optionally identify the CI system and the CI run
+//! that performed the publish via the `X-Cargo-CI-System` and
+//! `X-Cargo-CI-Run-URL` headers. The annotation is stored on the version and
+//! shown in the version detail API.
+//!
+//! The headers are self-reported by the publisher, so the annotation is not
+//! verified in any way.
+
+use crate::util::errors::{bad_request, AppResult};
+use http::HeaderMap;
+use url::Url;
+
+const CI_SYSTEM_HEADER: &str = "x-cargo-ci-system";
+const CI_RUN_URL_HEADER: &str = "x-cargo-ci-run-url";
+
+const MAX_CI_SYSTEM_LENGTH: usize = 64;
+const MAX_RUN_URL_LENGTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiAnnotation {
+    /// Identifier of the CI system, e.g. `github-actions`.
+    pub system: String,
+    pub run_url: Option<String>,
+}
+
+impl CiAnnotation {
+    /// Parses and validates the CI annotation of a publish request.
+    ///
+    /// Returns `None` if the request does not contain any CI headers.
+    pub fn from_headers(headers: &HeaderMap) -> AppResult<Option<Self>> {
+        let system = header_value(headers, CI_SYSTEM_HEADER)?;
+        let run_url = header_value(headers, CI_RUN_URL_HEADER)?;
+
+        let Some(system) = system else {
+            if run_url.is_some() {
+                return Err(bad_request(
+                    "the `X-Cargo-CI-Run-URL` header requires the `X-Cargo-CI-System` header",
+                ));
+            }
+
+            return Ok(None);
+        };
+
+        Self::new(system, run_url).map(Some)
+    }
+
+    pub fn new(system: String, run_url: Option<String>) -> AppResult<Self> {
+        let system = system.to_lowercase();
+        let is_valid_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+        if system.len() > MAX_CI_SYSTEM_LENGTH || !system.chars().all(is_valid_char) {
+            return Err(bad_request(format!(
+                "invalid CI system `{system}`. The `X-Cargo-CI-System` header must only \
+                 contain alphanumeric characters, `-`, `_` and `.`, and must not be longer \
+                 than {MAX_CI_SYSTEM_LENGTH} characters."
+            )));
+        }
+
+        if let Some(run_url) = &run_url {
+            let is_valid_url = run_url.len() <= MAX_RUN_URL_LENGTH
+                && run_url.starts_with("https://")
+                && Url::parse(run_url).is_ok();
+
+            if !is_valid_url {
+                return Err(bad_request(format!(
+                    "invalid CI run URL `{run_url}`. The `X-Cargo-CI-Run-URL` header must \
+                     contain an https:// URL that is not longer than {MAX_RUN_URL_LENGTH} \
+                     characters."
+                )));
+            }
+        }
+
+        Ok(Self { system, run_url })
+    }
+}
+
+/// Returns the trimmed value of the header, or `None` if the header is
+/// missing or empty.
+fn header_value(headers: &HeaderMap, name: &str) -> AppResult<Option<String>> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+
+    let value = value
+        .to_str()
+        .map_err(|_| bad_request(format!("the `{name}` header must be valid UTF-8")))?
+        .trim();
+
+    Ok(Some(value.to_string()).filter(|value| !value.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn headers(system: Option<&'static str>, run_url: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(system) = system {
+            headers.insert(CI_SYSTEM_HEADER, HeaderValue::from_static(system));
+        }
+        if let Some(run_url) = run_url {
+            headers.insert(CI_RUN_URL_HEADER, HeaderValue::from_static(run_url));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_from_headers() {
+        assert_eq!(
+            CiAnnotation::from_headers(&headers(None, None)).unwrap(),
+            None
+        );
+        assert_eq!(
+            CiAnnotation::from_headers(&headers(Some(" "), None)).unwrap(),
+            None
+        );
+
+        let run_url = "https://github.com/rust-lang/foo/actions/runs/123";
+        let annotation =
+            CiAnnotation::from_headers(&headers(Some("GitHub-Actions"), Some(run_url)));
+        assert_eq!(
+            annotation.unwrap(),
+            Some(CiAnnotation {
+                system: "github-actions".into(),
+                run_url: Some(run_url.into()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        let run_url = "https://github.com/rust-lang/foo/actions/runs/123";
+        assert!(CiAnnotation::from_headers(&headers(None, Some(run_url))).is_err());
+        assert!(CiAnnotation::from_headers(&headers(Some("git hub"), None)).is_err());
+
+        let run_url = "http://ci.example.com/runs/123";
+        assert!(CiAnnotation::from_headers(&headers(Some("jenkins"), Some(run_url))).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Idempotency keys of publish requests.
+//!
+//! `cargo` retries publishes after network failures, which used to result in
+//! confusing "version already exists" errors if the original request had
+//! already succeeded. Publish requests can therefore include an
+//! `Idempotency-Key` header. The response of a successful publish is stored
+//! for a day, keyed by the user and the key, and is replayed for retries
+//! with the same key.
+//!
+//! Failed publishes are not stored, so that retries after a temporary
+//! failure are processed normally.
+
+use crate::models::{NewPublishIdempotencyKey, PublishIdempotencyKey};
+use crate::util::diesel::Conn;
+use crate::util::errors::{bad_request, custom, AppResult};
+use http::{HeaderMap, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    /// Parses and validates the idempotency key of a publish request.
+    ///
+    /// Returns `None` if the request does not contain the header.
+    pub fn from_headers(headers: &HeaderMap) -> AppResult<Option<Self>> {
+        let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+            return Ok(None);
+        };
+
+        let key = value.to_str().unwrap_or_default().trim();
+        let is_valid_char = |c: char| c.is_ascii_graphic();
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH || !key.chars().all(is_valid_char) {
+            return Err(bad_request(format!(
+                "invalid idempotency key. The `Idempotency-Key` header must only contain \
+                 printable ASCII characters, and must be between 1 and {MAX_KEY_LENGTH} \
+                 characters long."
+            )));
+        }
+
+        Ok(Some(Self(key.to_string())))
+    }
+
+    /// Returns the stored response of a previous publish of the user with
+    /// the same key, if there is one.
+    ///
+    /// Keys can only be reused for retries of the same publish, so an error
+    /// is returned if the key was used to publish a different version.
+    pub fn replay(
+        &self,
+        conn: &mut impl Conn,
+        user_id: i32,
+        crate_name: &str,
+        version: &str,
+    ) -> AppResult<Option<Value>> {
+        let Some(stored) = PublishIdempotencyKey::find(conn, user_id, &self.0)? else {
+            return Ok(None);
+        };
+
+        if stored.crate_name != crate_name || stored.version != version {
+            return Err(custom(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!(
+                    "the idempotency key was already used to publish {} v{}",
+                    stored.crate_name, stored.version
+                ),
+            ));
+        }
+
+        Ok(Some(stored.response))
+    }
+
+    /// Stores the response of a successful publish, so that it can be
+    /// replayed for retries with the same key.
+    ///
+    /// The publish has already been completed at this point, so failures are
+    /// only logged instead of being returned to the user.
+    pub fn store(
+        &self,
+        conn: &mut impl Conn,
+        user_id: i32,
+        crate_name: &str,
+        version: &str,
+        response: &impl Serialize,
+    ) {
+        let result = serde_json::to_value(response)
+            .map_err(anyhow::Error::from)
+            .and_then(|response| {
+                let new_key = NewPublishIdempotencyKey {
+                    user_id,
+                    key: &self.0,
+                    crate_name,
+                    version,
+                    response: &response,
+                };
+
+                Ok(new_key.insert(conn)?)
+            });
+
+        if let Err(error) = result {
+            warn!(
+                ?error,
+                "Failed to store the idempotency key of {crate_name} v{version}"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn headers(key: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static(key));
+        headers
+    }
+
+    #[test]
+    fn test_from_headers() {
+        assert_eq!(
+            IdempotencyKey::from_headers(&HeaderMap::new()).unwrap(),
+            None
+        );
+
+        let key = IdempotencyKey::from_headers(&headers(" 3f2a-b9c1 ")).unwrap();
+        assert_eq!(key, Some(IdempotencyKey("3f2a-b9c1".to_string())));
+
+        assert!(IdempotencyKey::from_headers(&headers("")).is_err());
+        assert!(IdempotencyKey::from_headers(&headers("foo bar")).is_err());
+
+        let too_long = "a".repeat(MAX_KEY_LENGTH + 1);
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, too_long.parse().unwrap());
+        assert!(IdempotencyKey::from_headers(&headers).is_err());
+    }
+}
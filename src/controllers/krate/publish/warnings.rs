@@ -0,0 +1,263 @@
+//! Non-fatal problems with the metadata of a published crate.
+//!
+//! In contrast to the validations in the publish handler, these problems do
+//! not reject the publish. They are returned in the `warnings` field of the
+//! publish response, both as plain strings in the lists that cargo displays,
+//! and as typed [`PublishWarning`] entries with a machine-readable code and a
+//! hint on how to resolve the problem.
+
+use crate::views::{PublishWarning, PublishWarningCode, PublishWarnings};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// The metadata of a published crate that is checked for warnings.
+pub struct PublishedMetadata<'a> {
+    pub description: Option<&'a str>,
+    pub license_file: Option<&'a str>,
+    pub categories: &'a [String],
+    pub badges: &'a BTreeMap<String, BTreeMap<String, String>>,
+    /// The paths of all files in the tarball, relative to the package root.
+    pub paths: &'a [PathBuf],
+    pub links: Option<&'a str>,
+    /// The other crates whose versions declare the same `links` key.
+    pub links_conflicts: &'a [String],
+}
+
+pub fn validate(metadata: &PublishedMetadata<'_>) -> PublishWarnings {
+    let mut warnings = PublishWarnings::default();
+
+    let description = metadata.description.unwrap_or_default();
+    if description.trim().is_empty() {
+        warnings.other.push(add(
+            &mut warnings.details,
+            PublishWarningCode::MissingDescription,
+            "the `description` field is empty".to_string(),
+            "Add a short summary of what the crate does to the `description` field in `Cargo.toml`.",
+        ));
+    }
+
+    if let Some(license_file) = metadata.license_file {
+        if !contains_path(metadata.paths, license_file) {
+            warnings.other.push(add(
+                &mut warnings.details,
+                PublishWarningCode::MissingLicenseFile,
+                format!("the license file `{license_file}` is not included in the package"),
+                "Make sure that the file exists and is not excluded by the `include` or \
+                `exclude` fields in `Cargo.toml`.",
+            ));
+        }
+    }
+
+    if let Some(links) = metadata.links {
+        if !metadata.links_conflicts.is_empty() {
+            let crates = metadata
+                .links_conflicts
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            warnings.other.push(add(
+                &mut warnings.details,
+                PublishWarningCode::LinksConflict,
+                format!("the `links` key `{links}` is already used by {crates}"),
+                "Cargo only allows a single package with a given `links` key in a dependency \
+                graph, so this crate can not be used together with these crates. Use a \
+                different `links` key unless the crate is meant to replace them.",
+            ));
+        }
+    }
+
+    let mut seen_categories = HashSet::new();
+    for category in metadata.categories {
+        if !seen_categories.insert(category) {
+            warnings.other.push(add(
+                &mut warnings.details,
+                PublishWarningCode::InvalidCategory,
+                format!("the category `{category}` is listed more than once"),
+                "Remove the duplicate entry from the `categories` field in `Cargo.toml`.",
+            ));
+        }
+    }
+
+    for badge in metadata.badges.keys() {
+        add(
+            &mut warnings.details,
+            PublishWarningCode::InvalidBadge,
+            format!("the `{badge}` badge is ignored"),
+            "crates.io does not display badges anymore. Remove the `[badges]` section \
+            from `Cargo.toml` and show the badge in the README instead.",
+        );
+        warnings.invalid_badges.push(badge.clone());
+    }
+
+    warnings
+}
+
+/// Adds a typed warning to `details` and returns its message, so that it can
+/// also be added to one of the plain lists.
+fn add(
+    details: &mut Vec<PublishWarning>,
+    code: PublishWarningCode,
+    message: String,
+    hint: &str,
+) -> String {
+    details.push(PublishWarning {
+        code,
+        message: message.clone(),
+        hint: hint.to_string(),
+    });
+    message
+}
+
+/// Checks if `path` is one of the `paths` in the package, ignoring leading
+/// `./` components.
+fn contains_path(paths: &[PathBuf], path: &str) -> bool {
+    let normalize = |path: &Path| -> PathBuf {
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    };
+
+    let path = normalize(Path::new(path));
+    paths.iter().any(|p| normalize(p) == path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> PublishedMetadata<'static> {
+        static BADGES: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+
+        PublishedMetadata {
+            description: Some("description"),
+            license_file: None,
+            categories: &[],
+            badges: &BADGES,
+            paths: &[],
+            links: None,
+            links_conflicts: &[],
+        }
+    }
+
+    fn codes(warnings: &PublishWarnings) -> Vec<PublishWarningCode> {
+        warnings
+            .details
+            .iter()
+            .map(|warning| warning.code)
+            .collect()
+    }
+
+    #[test]
+    fn no_warnings() {
+        let warnings = validate(&metadata());
+        assert!(warnings.details.is_empty());
+        assert!(warnings.other.is_empty());
+        assert!(warnings.invalid_badges.is_empty());
+        assert!(warnings.invalid_categories.is_empty());
+    }
+
+    #[test]
+    fn missing_description() {
+        for description in [None, Some(""), Some("  \n")] {
+            let metadata = PublishedMetadata {
+                description,
+                ..metadata()
+            };
+
+            let warnings = validate(&metadata);
+            assert_eq!(codes(&warnings), [PublishWarningCode::MissingDescription]);
+            assert_eq!(warnings.other, ["the `description` field is empty"]);
+        }
+    }
+
+    #[test]
+    fn missing_license_file() {
+        let paths = [PathBuf::from("Cargo.toml"), PathBuf::from("LICENSE")];
+
+        for license_file in ["LICENSE", "./LICENSE"] {
+            let metadata = PublishedMetadata {
+                license_file: Some(license_file),
+                paths: &paths,
+                ..metadata()
+            };
+            assert!(validate(&metadata).details.is_empty());
+        }
+
+        let metadata = PublishedMetadata {
+            license_file: Some("LICENSE-MIT"),
+            paths: &paths,
+            ..metadata()
+        };
+
+        let warnings = validate(&metadata);
+        assert_eq!(codes(&warnings), [PublishWarningCode::MissingLicenseFile]);
+        assert_eq!(
+            warnings.other,
+            ["the license file `LICENSE-MIT` is not included in the package"]
+        );
+    }
+
+    #[test]
+    fn duplicate_categories() {
+        let categories = ["cat1".to_string(), "cat2".to_string(), "cat1".to_string()];
+        let metadata = PublishedMetadata {
+            categories: &categories,
+            ..metadata()
+        };
+
+        let warnings = validate(&metadata);
+        assert_eq!(codes(&warnings), [PublishWarningCode::InvalidCategory]);
+        assert_eq!(
+            warnings.other,
+            ["the category `cat1` is listed more than once"]
+        );
+    }
+
+    #[test]
+    fn badges() {
+        let badges = BTreeMap::from([
+            ("maintenance".to_string(), BTreeMap::new()),
+            ("travis-ci".to_string(), BTreeMap::new()),
+        ]);
+        let metadata = PublishedMetadata {
+            badges: &badges,
+            ..metadata()
+        };
+
+        let warnings = validate(&metadata);
+        assert_eq!(
+            codes(&warnings),
+            [
+                PublishWarningCode::InvalidBadge,
+                PublishWarningCode::InvalidBadge
+            ]
+        );
+        assert_eq!(warnings.invalid_badges, ["maintenance", "travis-ci"]);
+        assert!(warnings.other.is_empty());
+    }
+
+    #[test]
+    fn links_conflicts() {
+        let metadata = PublishedMetadata {
+            links: Some("git2"),
+            ..metadata()
+        };
+        assert!(validate(&metadata).details.is_empty());
+
+        let conflicts = ["git2-sys".to_string(), "libgit2-sys".to_string()];
+        let metadata = PublishedMetadata {
+            links: Some("git2"),
+            links_conflicts: &conflicts,
+            ..metadata()
+        };
+
+        let warnings = validate(&metadata);
+        assert_eq!(codes(&warnings), [PublishWarningCode::LinksConflict]);
+        assert_eq!(
+            warnings.other,
+            ["the `links` key `git2` is already used by `git2-sys`, `libgit2-sys`"]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Endpoint for the crates that are related to a crate
+//!
+//! The related crates are computed periodically by the
+//! `UpdateCrateRecommendations` background job, based on the dependencies of
+//! the default versions and the recent downloads of all crates.
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{Crate, CrateVisibility, RecommendationKind};
+use crate::schema::{crate_recommendations, crates};
+use crate::util::errors::crate_not_found;
+use crate::util::rfc3339;
+use crate::views::EncodableRelatedCrate;
+use chrono::NaiveDateTime;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `GET /crates/:crate_id/related` route.
+pub async fn related(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
+        // Crates that are not public are never recommended, even if the
+        // recommendations were computed before they were hidden.
+        let rows: Vec<(
+            RecommendationKind,
+            String,
+            Option<String>,
+            i64,
+            NaiveDateTime,
+        )> = crate_recommendations::table
+            .inner_join(crates::table.on(crates::id.eq(crate_recommendations::related_crate_id)))
+            .filter(crate_recommendations::crate_id.eq(krate.id))
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .select((
+                crate_recommendations::kind,
+                crates::name,
+                crates::description,
+                crate_recommendations::score,
+                crate_recommendations::computed_at,
+            ))
+            .order((crate_recommendations::score.desc(), crates::name))
+            .load(conn)?;
+
+        let mut response = RelatedCrates::default();
+        for (kind, name, description, score, computed_at) in rows {
+            let list = match kind {
+                RecommendationKind::DependedOnTogether => &mut response.depended_on_together,
+                RecommendationKind::UsedTogether => &mut response.used_together,
+            };
+
+            list.push(EncodableRelatedCrate {
+                name,
+                description,
+                score,
+            });
+
+            response.meta.computed_at = response.meta.computed_at.max(Some(computed_at));
+        }
+
+        Ok(Json(serde_json::to_value(response)?))
+    })
+    .await
+}
+
+#[derive(Serialize, Default)]
+struct RelatedCrates {
+    depended_on_together: Vec<EncodableRelatedCrate>,
+    used_together: Vec<EncodableRelatedCrate>,
+    meta: Meta,
+}
+
+#[derive(Serialize, Default)]
+struct Meta {
+    /// When the related crates were computed, or `None` if no related
+    /// crates were found.
+    #[serde(with = "rfc3339::option")]
+    computed_at: Option<NaiveDateTime>,
+}
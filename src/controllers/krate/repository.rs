@@ -0,0 +1,183 @@
+//! Endpoint for verifying the source repository of a crate
+//!
+//! Anyone can point the `repository` field of their crate at a reputable
+//! repository. To let users tell these crates apart from the real ones,
+//! owners can prove that they control the declared repository by committing
+//! a [`VERIFICATION_FILE_PATH`] file to its default branch, which lists the
+//! names of the crates that are published from the repository, one per line.
+//!
+//! Only GitHub repositories are supported for now.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, CrateVisibility, Rights};
+use crate::schema::crates;
+use crate::util::errors::{crate_not_found, custom};
+use chrono::NaiveDateTime;
+use crates_io_github::GitHubError;
+use diesel::dsl::now;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use oauth2::AccessToken;
+use tokio::runtime::Handle;
+use url::Url;
+
+/// The path of the file that lists the crates published from a repository.
+pub const VERIFICATION_FILE_PATH: &str = ".well-known/crates-io";
+
+/// Handles the `PUT /crates/:crate_id/repository/verify` route.
+///
+/// The verification is reset whenever a new version changes the
+/// `repository` field of the crate.
+pub async fn verify_repository(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        let rights = Handle::current().block_on(user.rights(&app, &owners))?;
+        if rights < Rights::Full {
+            if rights == Rights::None && krate.visibility == CrateVisibility::Private {
+                return Err(crate_not_found(&crate_name));
+            }
+
+            return Err(custom(
+                StatusCode::FORBIDDEN,
+                "only owners have permission to verify the repository of a crate",
+            ));
+        }
+
+        let Some(repository) = krate.repository.as_deref() else {
+            return Err(bad_request("this crate does not declare a repository"));
+        };
+
+        let Some((owner, repo)) = parse_github_repository(repository) else {
+            return Err(bad_request("only GitHub repositories can be verified"));
+        };
+
+        let token = AccessToken::new(user.gh_access_token.clone());
+        let future = app
+            .github
+            .repository_file(&owner, &repo, VERIFICATION_FILE_PATH, &token);
+
+        let contents = match Handle::current().block_on(future) {
+            Ok(contents) => contents,
+            Err(GitHubError::NotFound(_)) => {
+                return Err(bad_request(format!(
+                    "could not find a `{VERIFICATION_FILE_PATH}` file in the default branch of {repository}"
+                )));
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if !lists_crate(&contents, &krate.name) {
+            return Err(bad_request(format!(
+                "the `{VERIFICATION_FILE_PATH}` file of {repository} does not list the `{}` crate",
+                krate.name
+            )));
+        }
+
+        // Only mark the repository as verified if it was not changed by a
+        // concurrent publish in the meantime.
+        let verified_at = diesel::update(&krate)
+            .filter(crates::repository.eq(repository))
+            .set(crates::repository_verified_at.eq(now.nullable()))
+            .returning(crates::repository_verified_at)
+            .get_result::<Option<NaiveDateTime>>(conn)
+            .optional()?
+            .flatten()
+            .ok_or_else(|| bad_request("the repository of this crate has changed"))?;
+
+        Ok(Json(json!({
+            "repository": repository,
+            "verified_at": verified_at.and_utc(),
+        })))
+    })
+    .await
+}
+
+/// Extracts the owner and name of a GitHub repository from its URL, e.g.
+/// `https://github.com/rust-lang/crates.io.git`.
+fn parse_github_repository(repository: &str) -> Option<(String, String)> {
+    let url = Url::parse(repository).ok()?;
+    if url.scheme() != "https" || url.host_str() != Some("github.com") {
+        return None;
+    }
+
+    let mut segments = url.path_segments()?;
+    let owner = segments.next().filter(|owner| !owner.is_empty())?;
+    let repo = segments.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    if repo.is_empty() {
+        return None;
+    }
+
+    Some((owner.to_string(), repo.to_string()))
+}
+
+/// Checks if the contents of a verification file list the given crate.
+///
+/// Empty lines and lines starting with `#` are ignored. Crate names are
+/// compared the same way as on publish, so `-` and `_` are interchangeable.
+fn lists_crate(contents: &str, crate_name: &str) -> bool {
+    let canonicalize = |name: &str| name.to_lowercase().replace('-', "_");
+    let crate_name = canonicalize(crate_name);
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .any(|line| canonicalize(line) == crate_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_repository() {
+        let parse = parse_github_repository;
+        let expected = Some(("rust-lang".to_string(), "crates.io".to_string()));
+
+        assert_eq!(parse("https://github.com/rust-lang/crates.io"), expected);
+        assert_eq!(parse("https://github.com/rust-lang/crates.io/"), expected);
+        assert_eq!(
+            parse("https://github.com/rust-lang/crates.io.git"),
+            expected
+        );
+        assert_eq!(
+            parse("https://github.com/rust-lang/crates.io/tree/main"),
+            expected
+        );
+        assert_eq!(parse("http://github.com/rust-lang/crates.io"), None);
+        assert_eq!(parse("https://gitlab.com/rust-lang/crates.io"), None);
+        assert_eq!(parse("https://github.com/rust-lang"), None);
+        assert_eq!(parse("https://github.com/"), None);
+        assert_eq!(parse("not a url"), None);
+    }
+
+    #[test]
+    fn test_lists_crate() {
+        let contents = "# crates in this repository\n\nfoo\n  bar-baz  \n# qux\n";
+        assert!(lists_crate(contents, "foo"));
+        assert!(lists_crate(contents, "bar_baz"));
+        assert!(lists_crate(contents, "Bar-Baz"));
+        assert!(!lists_crate(contents, "qux"));
+        assert!(!lists_crate(contents, "fo"));
+        assert!(!lists_crate("", "foo"));
+    }
+}
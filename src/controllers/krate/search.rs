@@ -1,15 +1,19 @@
 //! Endpoint for searching and discovery functionality
 
+use crate::api_quota::check_api_quota;
 use crate::auth::AuthCheck;
 use diesel::dsl::*;
-use diesel::sql_types::{Array, Bool, Text};
+use diesel::expression::SqlLiteral;
+use diesel::sql_types::{Array, Bool, Float, Text};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use diesel_full_text_search::*;
 use std::cell::OnceCell;
 
 use crate::controllers::cargo_prelude::*;
 use crate::controllers::helpers::Paginate;
-use crate::models::{Crate, CrateOwner, CrateVersions, OwnerKind, TopVersions, Version};
+use crate::models::{
+    load_default_versions, Crate, CrateOwner, CrateVisibility, OwnerKind, TopVersions,
+};
 use crate::schema::*;
 use crate::util::errors::bad_request;
 use crate::views::EncodableCrate;
@@ -41,11 +45,12 @@ use crate::util::diesel::Conn;
 /// function out to cover the different use cases, and create unit tests
 /// for them.
 pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    check_api_quota(&app, &req).await?;
+
     let conn = app.db_read().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        use diesel::sql_types::Float;
         use seek::*;
 
         let params = req.query();
@@ -71,9 +76,21 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             team_id: option_param("team_id").and_then(|s| s.parse::<i32>().ok()),
             following: option_param("following").is_some(),
             has_ids: option_param("ids[]").is_some(),
+            no_build_script: option_param("no_build_script") == Some("true"),
+            no_proc_macro: option_param("no_proc_macro") == Some("true"),
+            maintenance_wanted: option_param("maintenance_wanted") == Some("true"),
+            include_owners: option_param("include_owners") == Some("true"),
             ..Default::default()
         };
 
+        // Crates looking for new maintainers are listed with the most
+        // recently flagged ones first, unless a search query or another sort
+        // order is given.
+        let sort = sort.or_else(|| {
+            let has_query = q_string.as_deref().is_some_and(|q| !q.is_empty());
+            (filter_params.maintenance_wanted && !has_query).then_some("maintenance-wanted")
+        });
+
         let selection = (
             ALL_COLUMNS,
             false.into_sql::<Bool>(),
@@ -96,19 +113,15 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
                 query = query.order(Crate::with_name(q_string).desc());
 
                 if sort == "relevance" {
-                    let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                        .bind::<Text, _>(q_string)
-                        .sql(")");
-                    let rank = ts_rank_cd(crates::textsearchable_index_col, q);
                     query = query.select((
                         ALL_COLUMNS,
                         Crate::with_name(q_string),
                         crate_downloads::downloads,
                         recent_crate_downloads::downloads.nullable(),
-                        rank.clone(),
+                        filter_params.relevance_rank(q_string),
                     ));
                     seek = Some(Seek::Relevance);
-                    query = query.then_order_by(rank.desc())
+                    query = query.then_order_by(filter_params.relevance_rank(q_string).desc())
                 } else {
                     query = query.select((
                         ALL_COLUMNS,
@@ -142,6 +155,12 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
         } else if sort == Some("new") {
             seek = Some(Seek::New);
             query = query.order((crates::created_at.desc(), crates::id.desc()));
+        } else if sort == Some("maintenance-wanted") {
+            seek = Some(Seek::MaintenanceWanted);
+            query = query.order((
+                crates::maintenance_wanted_at.desc().nulls_last(),
+                crates::id.desc(),
+            ));
         } else {
             seek = seek.or(Some(Seek::Name));
             // Since the name is unique value, the inherent ordering becomes naturally unique.
@@ -217,21 +236,23 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             .map(|(c, _, _, _, _)| c)
             .collect::<Vec<_>>();
 
-        let versions: Vec<Version> = info_span!("db.query", message = "SELECT ... FROM versions")
-            .in_scope(|| crates.versions().load(conn))?;
-        let versions = versions
-            .grouped_by(&crates)
-            .into_iter()
-            .map(TopVersions::from_versions);
+        let crate_ids = crates.iter().map(|krate| krate.id).collect::<Vec<_>>();
+        let mut top_versions = info_span!("db.query", message = "SELECT ... FROM versions")
+            .in_scope(|| TopVersions::for_crates(conn, &crate_ids))?;
+        let default_versions = info_span!("db.query", message = "SELECT ... FROM default_versions")
+            .in_scope(|| load_default_versions(&crate_ids, conn))?;
 
-        let crates = versions
-            .zip(crates)
+        let crates = crates
+            .into_iter()
             .zip(perfect_matches)
             .zip(downloads)
-            .map(|(((max_version, krate), perfect_match), (total, recent))| {
+            .map(|((krate, perfect_match), (total, recent))| {
+                let max_version = top_versions.remove(&krate.id).unwrap_or_default();
+                let default_version = default_versions.get(&krate.id).map(String::as_str);
                 EncodableCrate::from_minimal(
                     krate,
                     Some(&max_version),
+                    default_version,
                     Some(vec![]),
                     perfect_match,
                     total,
@@ -264,6 +285,10 @@ struct FilterParams<'a> {
     team_id: Option<i32>,
     following: bool,
     has_ids: bool,
+    no_build_script: bool,
+    no_proc_macro: bool,
+    maintenance_wanted: bool,
+    include_owners: bool,
     _auth_user_id: OnceCell<i32>,
     _ids: OnceCell<Option<Vec<String>>>,
 }
@@ -304,17 +329,25 @@ impl<'a> FilterParams<'a> {
         req: &Parts,
         conn: &mut impl Conn,
     ) -> AppResult<crates::BoxedQuery<'a, diesel::pg::Pg>> {
-        let mut query = crates::table.into_boxed();
+        // Private crates are never included in search results
+        let mut query = crates::table
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .into_boxed();
 
         if let Some(q_string) = self.q_string {
             if !q_string.is_empty() {
                 let q = sql::<TsQuery>("plainto_tsquery('english', ")
                     .bind::<Text, _>(q_string)
                     .sql(")");
-                query = query.filter(
-                    q.matches(crates::textsearchable_index_col)
-                        .or(Crate::loosly_matches_name(q_string)),
-                );
+                let text_matches = q
+                    .matches(crates::textsearchable_index_col)
+                    .or(Crate::loosly_matches_name(q_string));
+
+                if self.include_owners {
+                    query = query.filter(text_matches.or(owner_matches(q_string)));
+                } else {
+                    query = query.filter(text_matches);
+                }
             }
         }
 
@@ -408,9 +441,62 @@ impl<'a> FilterParams<'a> {
             ));
         }
 
+        // These filters only look at the default version of each crate, and
+        // exclude versions that have not been analyzed yet.
+        if self.no_build_script {
+            query = query.filter(exists(
+                default_versions::table
+                    .inner_join(versions::table)
+                    .filter(default_versions::crate_id.eq(crates::id))
+                    .filter(versions::has_build_script.eq(false)),
+            ));
+        }
+
+        if self.no_proc_macro {
+            query = query.filter(exists(
+                default_versions::table
+                    .inner_join(versions::table)
+                    .filter(default_versions::crate_id.eq(crates::id))
+                    .filter(versions::is_proc_macro.eq(false)),
+            ));
+        }
+
+        if self.maintenance_wanted {
+            query = query.filter(crates::maintenance_wanted_at.is_not_null());
+        }
+
         Ok(query)
     }
 
+    /// The relevance rank of a crate for the search query.
+    ///
+    /// If owners are included in the search, crates that only match because
+    /// of their owners are ranked below all crates whose name, description
+    /// or keywords match the query.
+    fn relevance_rank(&self, q_string: &'a str) -> BoxedRank<'a> {
+        let q = || {
+            sql::<TsQuery>("plainto_tsquery('english', ")
+                .bind::<Text, _>(q_string)
+                .sql(")")
+        };
+        let rank = Times::new(
+            ts_rank_cd(crates::textsearchable_index_col, q()),
+            superseded_penalty(),
+        );
+
+        if !self.include_owners {
+            return Box::new(rank);
+        }
+
+        let text_matches = q()
+            .matches(crates::textsearchable_index_col)
+            .or(Crate::loosly_matches_name::<SearchQuerySource>(q_string));
+        let owner_only_penalty = case_when(text_matches, 0_f32.into_sql::<Float>())
+            .otherwise((-1_f32).into_sql::<Float>());
+
+        Box::new(Plus::new(rank, owner_only_penalty))
+    }
+
     fn seek_after(&self, seek_payload: &seek::SeekPayload) -> BoxedCondition<'a> {
         use seek::*;
 
@@ -453,6 +539,41 @@ impl<'a> FilterParams<'a> {
                     Box::new(crates::updated_at.lt(updated_at).nullable()),
                 ]
             }
+            SeekPayload::MaintenanceWanted(MaintenanceWanted {
+                maintenance_wanted_at,
+                id,
+            }) => {
+                // Equivalent of:
+                // for maintenance_wanted_at is not None:
+                // `WHERE (maintenance_wanted_at = maintenance_wanted_at' AND id < id')
+                //      OR (maintenance_wanted_at < maintenance_wanted_at' OR maintenance_wanted_at IS NULL)`
+                // for maintenance_wanted_at is None:
+                // `WHERE (maintenance_wanted_at IS NULL AND id < id')`
+                match maintenance_wanted_at {
+                    Some(wanted_at) => {
+                        vec![
+                            Box::new(
+                                crates::maintenance_wanted_at
+                                    .eq(wanted_at)
+                                    .and(crates::id.lt(id)),
+                            ),
+                            Box::new(
+                                crates::maintenance_wanted_at
+                                    .lt(wanted_at)
+                                    .or(crates::maintenance_wanted_at.is_null()),
+                            ),
+                        ]
+                    }
+                    None => {
+                        vec![Box::new(
+                            crates::maintenance_wanted_at
+                                .is_null()
+                                .and(crates::id.lt(id))
+                                .nullable(),
+                        )]
+                    }
+                }
+            }
             SeekPayload::RecentDownloads(RecentDownloads {
                 recent_downloads,
                 id,
@@ -529,20 +650,22 @@ impl<'a> FilterParams<'a> {
                 //      OR (exact_match = exact_match' AND rank < rank')
                 //      OR exact_match < exact_match'`
                 let q_string = self.q_string.expect("q_string should not be None");
-                let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                    .bind::<Text, _>(q_string)
-                    .sql(")");
-                let rank = ts_rank_cd(crates::textsearchable_index_col, q);
+                let rank = || self.relevance_rank(q_string);
                 let name_exact_match = Crate::with_name(q_string);
                 vec![
                     Box::new(
                         name_exact_match
                             .eq(exact)
-                            .and(rank.clone().eq(rank_in))
+                            .and(rank().eq(rank_in))
                             .and(crates::name.nullable().gt(crate_name_by_id(id)))
                             .nullable(),
                     ),
-                    Box::new(name_exact_match.eq(exact).and(rank.lt(rank_in)).nullable()),
+                    Box::new(
+                        name_exact_match
+                            .eq(exact)
+                            .and(rank().lt(rank_in))
+                            .nullable(),
+                    ),
                     Box::new(name_exact_match.lt(exact).nullable()),
                 ]
             }
@@ -566,7 +689,7 @@ impl<'a> FilterParams<'a> {
 mod seek {
     use crate::controllers::helpers::pagination::seek;
     use crate::models::Crate;
-    use chrono::naive::serde::ts_microseconds;
+    use chrono::naive::serde::{ts_microseconds, ts_microseconds_option};
 
     seek!(
         pub enum Seek {
@@ -587,6 +710,11 @@ mod seek {
                 recent_downloads: Option<i64>,
                 id: i32,
             },
+            MaintenanceWanted {
+                #[serde(with = "ts_microseconds_option")]
+                maintenance_wanted_at: Option<chrono::NaiveDateTime>,
+                id: i32,
+            },
             Downloads {
                 downloads: i64,
                 id: i32,
@@ -613,6 +741,7 @@ mod seek {
                     id,
                     updated_at,
                     created_at,
+                    maintenance_wanted_at,
                     ..
                 },
                 exact_match,
@@ -625,6 +754,10 @@ mod seek {
                 Seek::Name => SeekPayload::Name(Name { id }),
                 Seek::New => SeekPayload::New(New { created_at, id }),
                 Seek::RecentUpdates => SeekPayload::RecentUpdates(RecentUpdates { updated_at, id }),
+                Seek::MaintenanceWanted => SeekPayload::MaintenanceWanted(MaintenanceWanted {
+                    maintenance_wanted_at,
+                    id,
+                }),
                 Seek::RecentDownloads => SeekPayload::RecentDownloads(RecentDownloads {
                     recent_downloads,
                     id,
@@ -641,15 +774,67 @@ mod seek {
     }
 }
 
+type SearchQuerySource = LeftJoinQuerySource<
+    InnerJoinQuerySource<crates::table, crate_downloads::table>,
+    recent_crate_downloads::table,
+>;
+
 type BoxedCondition<'a> = Box<
     dyn BoxableExpression<
-            LeftJoinQuerySource<
-                InnerJoinQuerySource<crates::table, crate_downloads::table>,
-                recent_crate_downloads::table,
-            >,
+            SearchQuerySource,
             diesel::pg::Pg,
             SqlType = diesel::sql_types::Nullable<Bool>,
         > + 'a,
 >;
 
+type BoxedRank<'a> =
+    Box<dyn BoxableExpression<SearchQuerySource, diesel::pg::Pg, SqlType = Float> + 'a>;
+
 diesel::infix_operator!(Contains, "@>");
+diesel::infix_operator!(Times, " * ", Float);
+diesel::infix_operator!(Plus, " + ", Float);
+
+/// Halves the search rank of crates that have been superseded by another
+/// crate, so that their successors are usually listed first.
+fn superseded_penalty() -> SqlLiteral<Float> {
+    sql(r#"(CASE WHEN EXISTS (
+        SELECT 1 FROM crate_successions
+        WHERE crate_successions.predecessor_id = crates.id
+          AND crate_successions.predecessor_confirmed_at IS NOT NULL
+          AND crate_successions.successor_confirmed_at IS NOT NULL
+    ) THEN real '0.5' ELSE real '1' END)"#)
+}
+
+/// Matches crates that are owned by a user or team whose login or display
+/// name equals the search query, ignoring case.
+fn owner_matches(
+    q_string: &str,
+) -> Box<dyn BoxableExpression<crates::table, diesel::pg::Pg, SqlType = Bool>> {
+    let q_lower = q_string.to_lowercase();
+
+    let users = users::table.select(users::id).filter(
+        lower(users::gh_login)
+            .eq(q_lower.clone())
+            .or(lower(users::name.assume_not_null()).eq(q_lower.clone())),
+    );
+
+    let teams = teams::table.select(teams::id).filter(
+        lower(teams::login)
+            .eq(q_lower.clone())
+            .or(lower(teams::name.assume_not_null()).eq(q_lower)),
+    );
+
+    Box::new(
+        crates::id
+            .eq_any(
+                CrateOwner::by_owner_kind(OwnerKind::User)
+                    .select(crate_owners::crate_id)
+                    .filter(crate_owners::owner_id.eq_any(users)),
+            )
+            .or(crates::id.eq_any(
+                CrateOwner::by_owner_kind(OwnerKind::Team)
+                    .select(crate_owners::crate_id)
+                    .filter(crate_owners::owner_id.eq_any(teams)),
+            )),
+    )
+}
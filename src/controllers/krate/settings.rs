@@ -0,0 +1,361 @@
+//! Endpoints for reading and changing the settings of a crate
+//!
+//! Most settings can be changed by the owners of a crate, but some of them
+//! (e.g. the overrides of the publish limits) can only be changed by admins.
+//! The permissions are checked per field, so that a request that only
+//! changes owner-controlled settings never requires admin rights.
+//!
+//! The policy documents of a crate (security contact, disclosure policy and
+//! MSRV policy) are changed via these endpoints too, but are publicly
+//! readable via the `GET /crates/:crate_id/policy` endpoint.
+//!
+//! Owners can also flag their crate as looking for new maintainers, which
+//! makes it show up in the `GET /crates?maintenance_wanted=true` list.
+//!
+//! The dependency deny-list of a crate is enforced when new versions of the
+//! crate are published, see [`DependencyDenyList`].
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, Crate, CrateSettings, CrateSettingsUpdate, DeniedDependency, DependencyDenyList,
+    NewAdminAuditEntry, Rights, User,
+};
+use crate::permissions::{Capability, Permissions};
+use crate::schema::crates;
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::EncodableCrateSettings;
+use chrono::Utc;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use lettre::Address;
+use url::Url;
+
+/// The maximum number of days that can be configured for the retention of
+/// pre-release versions.
+const MAX_PRERELEASE_RETENTION_DAYS: i32 = 3650;
+
+/// The maximum length of the security contact of a crate.
+const MAX_SECURITY_CONTACT_LENGTH: usize = 256;
+
+/// The maximum length of the disclosure and MSRV policies of a crate.
+const MAX_POLICY_LENGTH: usize = 10_000;
+
+/// The maximum number of entries of the dependency deny-list of a crate.
+const MAX_DENIED_DEPENDENCIES: usize = 100;
+
+#[derive(Deserialize)]
+pub struct UpdateSettingsRequest {
+    /// `None` if the setting should not be changed, `Some(None)` if the
+    /// retention policy should be disabled.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    prerelease_retention_days: Option<Option<i32>>,
+    /// Flags the crate as looking for new maintainers, which lists it in
+    /// `GET /crates?maintenance_wanted=true`.
+    maintenance_wanted: Option<bool>,
+    /// Can only be changed by admins.
+    limits: Option<UpdateLimitsRequest>,
+    policy: Option<UpdatePolicyRequest>,
+    /// Replaces the dependency deny-list, an empty list removes all entries.
+    denied_dependencies: Option<Vec<DeniedDependency>>,
+}
+
+#[derive(Deserialize, AsChangeset)]
+#[diesel(table_name = crates, check_for_backend(diesel::pg::Pg))]
+pub struct UpdateLimitsRequest {
+    #[serde(default, deserialize_with = "deserialize_some")]
+    max_upload_size: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    max_features: Option<Option<i16>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    max_dependencies: Option<Option<i16>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    max_feature_values: Option<Option<i16>>,
+}
+
+impl UpdateLimitsRequest {
+    fn is_empty(&self) -> bool {
+        self.max_upload_size.is_none()
+            && self.max_features.is_none()
+            && self.max_dependencies.is_none()
+            && self.max_feature_values.is_none()
+    }
+
+    fn validate(&self) -> AppResult<()> {
+        let values = [
+            self.max_upload_size.flatten(),
+            self.max_features.flatten().map(i32::from),
+            self.max_dependencies.flatten().map(i32::from),
+            self.max_feature_values.flatten().map(i32::from),
+        ];
+
+        if values.into_iter().flatten().any(|value| value <= 0) {
+            return Err(bad_request("limits must be positive numbers"));
+        }
+
+        Ok(())
+    }
+}
+
+/// `None` fields are not changed, while `Some(None)` and empty strings
+/// remove the corresponding policy document.
+#[derive(Default, Deserialize)]
+pub struct UpdatePolicyRequest {
+    #[serde(default, deserialize_with = "deserialize_some")]
+    security_contact: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    disclosure_policy: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    msrv_policy: Option<Option<String>>,
+}
+
+impl UpdatePolicyRequest {
+    /// Validates the policy documents and normalizes empty strings to `None`.
+    fn validate(self) -> AppResult<Self> {
+        let security_contact = normalize(self.security_contact);
+        if let Some(Some(contact)) = &security_contact {
+            validate_security_contact(contact)?;
+        }
+
+        let disclosure_policy = normalize(self.disclosure_policy);
+        validate_policy_length(&disclosure_policy, "disclosure_policy")?;
+
+        let msrv_policy = normalize(self.msrv_policy);
+        validate_policy_length(&msrv_policy, "msrv_policy")?;
+
+        Ok(Self {
+            security_contact,
+            disclosure_policy,
+            msrv_policy,
+        })
+    }
+}
+
+fn validate_denied_dependencies(entries: &[DeniedDependency]) -> AppResult<()> {
+    if entries.len() > MAX_DENIED_DEPENDENCIES {
+        return Err(bad_request(format!(
+            "`denied_dependencies` must not have more than {MAX_DENIED_DEPENDENCIES} entries"
+        )));
+    }
+
+    for entry in entries {
+        Crate::validate_crate_name("dependency", &entry.name).map_err(bad_request)?;
+
+        if let Some(version_req) = &entry.version_req {
+            if semver::VersionReq::parse(version_req).is_err() {
+                return Err(bad_request(format!(
+                    "\"{version_req}\" is an invalid version requirement"
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn normalize(value: Option<Option<String>>) -> Option<Option<String>> {
+    value.map(|value| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+/// The security contact must either be an email address or an `https` URL.
+fn validate_security_contact(contact: &str) -> AppResult<()> {
+    if contact.len() > MAX_SECURITY_CONTACT_LENGTH {
+        return Err(bad_request(format!(
+            "`security_contact` must not be longer than {MAX_SECURITY_CONTACT_LENGTH} characters"
+        )));
+    }
+
+    let is_url = contact.starts_with("https://") && Url::parse(contact).is_ok();
+    if !is_url && contact.parse::<Address>().is_err() {
+        return Err(bad_request(format!(
+            "`security_contact` must be an email address or an https:// URL (contact: {contact})"
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_policy_length(policy: &Option<Option<String>>, field: &str) -> AppResult<()> {
+    if let Some(Some(policy)) = policy {
+        if policy.len() > MAX_POLICY_LENGTH {
+            return Err(bad_request(format!(
+                "`{field}` must not be longer than {MAX_POLICY_LENGTH} characters"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Distinguishes between a missing field and an explicit `null` value.
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Handles the `GET /crates/:crate_id/settings` route.
+pub async fn get_settings(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let krate = load_crate(&crate_name, conn)?;
+        ensure_owner_or_admin(&app, auth.user(), &krate, Capability::ViewSettings, conn)?;
+
+        let settings = CrateSettings::for_crate(conn, krate.id)?;
+        let settings = EncodableCrateSettings::from(settings, &krate);
+
+        Ok(Json(json!({ "settings": settings })))
+    })
+    .await
+}
+
+/// Handles the `PATCH /crates/:crate_id/settings` route.
+///
+/// If `prerelease_retention_days` is set, pre-release versions of the crate
+/// that are older than the configured number of days are yanked by the
+/// `ApplyPrereleaseRetention` background job, once a newer stable version
+/// has been published.
+pub async fn update_settings(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(mut body): Json<UpdateSettingsRequest>,
+) -> AppResult<Json<Value>> {
+    if let Some(Some(days)) = body.prerelease_retention_days {
+        if !(1..=MAX_PRERELEASE_RETENTION_DAYS).contains(&days) {
+            return Err(bad_request(format!(
+                "`prerelease_retention_days` must be between 1 and {MAX_PRERELEASE_RETENTION_DAYS}"
+            )));
+        }
+    }
+
+    if let Some(limits) = &body.limits {
+        limits.validate()?;
+    }
+
+    body.policy = body.policy.map(UpdatePolicyRequest::validate).transpose()?;
+
+    if let Some(entries) = &body.denied_dependencies {
+        validate_denied_dependencies(entries)?;
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let mut krate = load_crate(&crate_name, conn)?;
+        ensure_owner_or_admin(&app, user, &krate, Capability::ChangeSettings, conn)?;
+
+        let limits = body.limits.filter(|limits| !limits.is_empty());
+        if limits.is_some() && !user.is_admin {
+            return Err(custom(
+                StatusCode::FORBIDDEN,
+                "only crates.io admins have permission to change the limits of a crate",
+            ));
+        }
+
+        let policy = body.policy.unwrap_or_default();
+
+        let update = CrateSettingsUpdate {
+            prerelease_retention_days: body.prerelease_retention_days,
+            security_contact: policy.security_contact,
+            disclosure_policy: policy.disclosure_policy,
+            msrv_policy: policy.msrv_policy,
+            denied_dependencies: body.denied_dependencies.map(DependencyDenyList),
+        };
+
+        let settings = conn.transaction(|conn| {
+            if let Some(limits) = limits {
+                warn!(
+                    "Admin {} is changing the limits of {}",
+                    user.gh_login, krate.name
+                );
+
+                krate = diesel::update(&krate)
+                    .set(&limits)
+                    .returning(Crate::as_returning())
+                    .get_result(conn)?;
+
+                NewAdminAuditEntry::by_admin(user, AdminAction::ChangeCrateLimits)
+                    .krate(&krate.name)
+                    .details(json!({
+                        "max_upload_size": krate.max_upload_size,
+                        "max_features": krate.max_features,
+                        "max_dependencies": krate.max_dependencies,
+                        "max_feature_values": krate.max_feature_values,
+                    }))
+                    .insert(conn)?;
+            }
+
+            // Keep the original date if the crate is already flagged, so
+            // that the list of crates looking for maintainers stays stable.
+            if let Some(wanted) = body.maintenance_wanted {
+                if wanted != krate.maintenance_wanted_at.is_some() {
+                    let maintenance_wanted_at = wanted.then(|| Utc::now().naive_utc());
+                    krate = diesel::update(&krate)
+                        .set(crates::maintenance_wanted_at.eq(maintenance_wanted_at))
+                        .returning(Crate::as_returning())
+                        .get_result(conn)?;
+                }
+            }
+
+            update.apply(conn, krate.id)
+        })?;
+
+        let settings = EncodableCrateSettings::from(settings, &krate);
+
+        Ok(Json(json!({ "settings": settings })))
+    })
+    .await
+}
+
+fn load_crate(crate_name: &str, conn: &mut impl Conn) -> AppResult<Crate> {
+    Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))
+}
+
+/// Checks that the user is either an owner of the crate or an admin.
+fn ensure_owner_or_admin(
+    app: &AppState,
+    user: &User,
+    krate: &Crate,
+    capability: Capability,
+    conn: &mut impl Conn,
+) -> AppResult<()> {
+    let owners = krate.owners(conn)?;
+    let permissions = Permissions::load(app, user, krate, &owners, conn)?;
+    permissions.ensure(capability)?;
+
+    if permissions.rights() < Rights::Full {
+        warn!(
+            "Admin {} is accessing the settings of {}",
+            user.gh_login, krate.name
+        );
+    }
+
+    Ok(())
+}
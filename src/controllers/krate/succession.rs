@@ -0,0 +1,394 @@
+//! Endpoints for declaring that a crate has been superseded by another crate
+//!
+//! The owners of either crate can declare the relation, but it only takes
+//! effect once the owners of the other crate confirmed it by declaring the
+//! same relation from their side. Users that own both crates can declare the
+//! relation in a single step.
+//!
+//! Confirmed relations are included in the API responses of both crates,
+//! and superseded crates are ranked lower in search results.
+
+use super::ensure_crate_visible;
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::email::{Email, Notification};
+use crate::models::{
+    Crate, CrateSuccession, CrateVisibility, NewCrateSuccession, NotificationClass, OwnerKind,
+    Rights, User,
+};
+use crate::schema::{crate_owners, crate_successions, crates, users};
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::EncodableCrateSuccession;
+use chrono::Utc;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::HashSet;
+use tokio::runtime::Handle;
+
+#[derive(Deserialize)]
+pub struct SetSuccessorRequest {
+    successor: String,
+}
+
+/// Handles the `PUT /crates/:crate_id/successor` route.
+///
+/// Declares that the crate is superseded by another crate, replacing any
+/// previously declared successor.
+pub async fn set_successor(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<SetSuccessorRequest>,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let predecessor = load_crate(&crate_name, conn)?;
+        ensure_owner(&app, user, &predecessor, "successor", conn)?;
+
+        let successor = load_crate(&body.successor, conn)?;
+        ensure_crate_visible(&app, &req, &successor, conn)?;
+
+        let owns_successor = is_owner(&app, user, &successor, conn)?;
+        let succession = declare(
+            &app,
+            user,
+            &predecessor,
+            &successor,
+            true,
+            owns_successor,
+            conn,
+        )?;
+        let succession =
+            EncodableCrateSuccession::from(succession, predecessor.name, successor.name);
+
+        Ok(Json(json!({ "succession": succession })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /crates/:crate_id/successor` route.
+pub async fn remove_successor(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let krate = load_crate(&crate_name, conn)?;
+        ensure_owner(&app, auth.user(), &krate, "successor", conn)?;
+
+        diesel::delete(crate_successions::table.find(krate.id)).execute(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `PUT /crates/:crate_id/predecessors/:predecessor_id` route.
+///
+/// Declares that the crate supersedes another crate. If the owners of the
+/// other crate already declared a different successor for it, the relation
+/// can not be declared from this side.
+pub async fn add_predecessor(
+    app: AppState,
+    Path((crate_name, predecessor_name)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let successor = load_crate(&crate_name, conn)?;
+        ensure_owner(&app, user, &successor, "predecessors", conn)?;
+
+        let predecessor = load_crate(&predecessor_name, conn)?;
+        ensure_crate_visible(&app, &req, &predecessor, conn)?;
+
+        let owns_predecessor = is_owner(&app, user, &predecessor, conn)?;
+        let succession = declare(
+            &app,
+            user,
+            &predecessor,
+            &successor,
+            owns_predecessor,
+            true,
+            conn,
+        )?;
+        let succession =
+            EncodableCrateSuccession::from(succession, predecessor.name, successor.name);
+
+        Ok(Json(json!({ "succession": succession })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /crates/:crate_id/predecessors/:predecessor_id` route.
+pub async fn remove_predecessor(
+    app: AppState,
+    Path((crate_name, predecessor_name)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let krate = load_crate(&crate_name, conn)?;
+        ensure_owner(&app, auth.user(), &krate, "predecessors", conn)?;
+
+        let predecessor_ids = Crate::by_name(&predecessor_name).select(crates::id);
+        diesel::delete(crate_successions::table)
+            .filter(crate_successions::predecessor_id.eq_any(predecessor_ids))
+            .filter(crate_successions::successor_id.eq(krate.id))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Stores the relation between the two crates, confirming it on behalf of
+/// the owners of the crates that the user owns.
+///
+/// If the relation still needs to be confirmed by the owners of the other
+/// crate, they are notified via email.
+fn declare(
+    app: &AppState,
+    user: &User,
+    predecessor: &Crate,
+    successor: &Crate,
+    owns_predecessor: bool,
+    owns_successor: bool,
+    conn: &mut impl Conn,
+) -> AppResult<CrateSuccession> {
+    if predecessor.id == successor.id {
+        return Err(bad_request("a crate cannot supersede itself"));
+    }
+
+    ensure_no_cycle(predecessor, successor, conn)?;
+
+    let now = Utc::now().naive_utc();
+    let existing = CrateSuccession::for_predecessor(conn, predecessor.id)?;
+    let succession = match existing {
+        Some(existing) if existing.successor_id == successor.id => {
+            use crate::schema::crate_successions::columns::*;
+
+            let predecessor_confirmed = existing
+                .predecessor_confirmed_at
+                .or(owns_predecessor.then_some(now));
+            let successor_confirmed = existing
+                .successor_confirmed_at
+                .or(owns_successor.then_some(now));
+
+            diesel::update(&existing)
+                .set((
+                    predecessor_confirmed_at.eq(predecessor_confirmed),
+                    successor_confirmed_at.eq(successor_confirmed),
+                ))
+                .returning(CrateSuccession::as_returning())
+                .get_result(conn)?
+        }
+        // The successor that was chosen by the owners of the predecessor
+        // can only be replaced by them.
+        Some(existing) if !owns_predecessor && existing.predecessor_confirmed_at.is_some() => {
+            return Err(bad_request(format!(
+                "the owners of `{}` have already declared a different successor for it",
+                predecessor.name
+            )));
+        }
+        _ => NewCrateSuccession {
+            predecessor_id: predecessor.id,
+            successor_id: successor.id,
+            predecessor_confirmed_at: owns_predecessor.then_some(now),
+            successor_confirmed_at: owns_successor.then_some(now),
+            created_by: user.id,
+        }
+        .upsert(conn)?,
+    };
+
+    if !succession.is_confirmed() {
+        let pending = if owns_predecessor {
+            successor
+        } else {
+            predecessor
+        };
+
+        notify_owners(app, user, predecessor, successor, pending, conn)?;
+    }
+
+    Ok(succession)
+}
+
+/// Ensures that the successor is not (transitively) superseded by the
+/// predecessor, including relations that have not been confirmed yet.
+fn ensure_no_cycle(predecessor: &Crate, successor: &Crate, conn: &mut impl Conn) -> AppResult<()> {
+    let mut visited = HashSet::new();
+    let mut current = successor.id;
+    while visited.insert(current) {
+        let next: Option<i32> = crate_successions::table
+            .find(current)
+            .select(crate_successions::successor_id)
+            .first(conn)
+            .optional()?;
+
+        match next {
+            Some(next) if next == predecessor.id => {
+                return Err(bad_request(format!(
+                    "`{}` cannot supersede `{}`, because it is already superseded by it",
+                    successor.name, predecessor.name
+                )));
+            }
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks the owners of the `pending` crate to confirm the relation.
+fn notify_owners(
+    app: &AppState,
+    user: &User,
+    predecessor: &Crate,
+    successor: &Crate,
+    pending: &Crate,
+    conn: &mut impl Conn,
+) -> QueryResult<()> {
+    let owners: Vec<(i32, String)> = crate_owners::table
+        .inner_join(users::table)
+        .filter(crate_owners::crate_id.eq(pending.id))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .select((users::id, users::gh_login))
+        .load(conn)?;
+
+    let confirmation = if pending.id == successor.id {
+        format!(
+            "PUT /api/v1/crates/{}/predecessors/{}",
+            successor.name, predecessor.name
+        )
+    } else {
+        format!(
+            "PUT /api/v1/crates/{}/successor {{\"successor\": \"{}\"}}",
+            predecessor.name, successor.name
+        )
+    };
+
+    for (owner_id, owner_name) in &owners {
+        let email = SuccessionRequestEmail {
+            user_name: owner_name,
+            requested_by: &user.gh_login,
+            domain: &app.emails.domain,
+            predecessor: &predecessor.name,
+            successor: &successor.name,
+            pending: &pending.name,
+            confirmation: &confirmation,
+        };
+
+        if let Err(error) = app.emails.send_notification(*owner_id, email, conn) {
+            warn!(?error, "Failed to send succession request to {owner_name}");
+        }
+    }
+
+    Ok(())
+}
+
+fn load_crate(crate_name: &str, conn: &mut impl Conn) -> AppResult<Crate> {
+    Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))
+}
+
+fn is_owner(app: &AppState, user: &User, krate: &Crate, conn: &mut impl Conn) -> AppResult<bool> {
+    let owners = krate.owners(conn)?;
+    let rights = Handle::current().block_on(user.rights(app, &owners))?;
+    Ok(rights >= Rights::Full)
+}
+
+/// Checks that the user is an owner of the crate.
+fn ensure_owner(
+    app: &AppState,
+    user: &User,
+    krate: &Crate,
+    relation: &str,
+    conn: &mut impl Conn,
+) -> AppResult<()> {
+    let owners = krate.owners(conn)?;
+    let rights = Handle::current().block_on(user.rights(app, &owners))?;
+    if rights >= Rights::Full {
+        return Ok(());
+    }
+
+    if rights == Rights::None && krate.visibility == CrateVisibility::Private {
+        Err(crate_not_found(&krate.name))
+    } else {
+        let detail = format!("only owners have permission to change the {relation} of a crate");
+        Err(custom(StatusCode::FORBIDDEN, detail))
+    }
+}
+
+struct SuccessionRequestEmail<'a> {
+    user_name: &'a str,
+    requested_by: &'a str,
+    domain: &'a str,
+    predecessor: &'a str,
+    successor: &'a str,
+    pending: &'a str,
+    confirmation: &'a str,
+}
+
+impl Email for SuccessionRequestEmail<'_> {
+    const SUBJECT: &'static str = "Please confirm the successor of a crate";
+
+    fn body(&self) -> String {
+        format!(
+            "Hello {user_name}!
+
+{requested_by} (https://{domain}/users/{requested_by}) declared that the crate \
+{predecessor} is superseded by the crate {successor}. Since you are an owner of \
+{pending}, the relation only takes effect once you confirm it.
+
+If you agree, you can confirm the relation with the following API request:
+
+  {confirmation}
+
+If you don't agree, no action is required.",
+            user_name = self.user_name,
+            requested_by = self.requested_by,
+            domain = self.domain,
+            predecessor = self.predecessor,
+            successor = self.successor,
+            pending = self.pending,
+            confirmation = self.confirmation,
+        )
+    }
+}
+
+impl Notification for SuccessionRequestEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Ownership;
+}
@@ -6,13 +6,15 @@ use diesel::connection::DefaultLoadingMode;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use indexmap::IndexMap;
 
+use super::ensure_crate_visible;
 use crate::controllers::frontend_prelude::*;
 use crate::controllers::helpers::pagination::{encode_seek, Page, PaginationOptions};
 
 use crate::models::{Crate, User, Version, VersionOwnerAction};
-use crate::schema::{crates, users, versions};
+use crate::schema::{default_versions, users, versions};
+use crate::sql::coalesce;
 use crate::util::diesel::Conn;
-use crate::util::errors::crate_not_found;
+use crate::util::errors::{channel_not_found, crate_not_found, not_found};
 use crate::views::EncodableVersion;
 
 /// Handles the `GET /crates/:crate_id/versions` route.
@@ -25,12 +27,14 @@ pub async fn versions(
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        let crate_id: i32 = Crate::by_name(&crate_name)
-            .select(crates::id)
+        let krate: Crate = Crate::by_name(&crate_name)
             .first(conn)
             .optional()?
             .ok_or_else(|| crate_not_found(&crate_name))?;
 
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+        let crate_id = krate.id;
+
         let mut pagination = None;
         let params = req.query();
         // To keep backward compatibility, we paginate only if per_page is provided
@@ -47,6 +51,7 @@ pub async fn versions(
         let versions_and_publishers = match params.get("sort").map(|s| s.to_lowercase()).as_deref()
         {
             Some("date") => list_by_date(crate_id, pagination.as_ref(), &req, conn)?,
+            Some("size") => list_by_size(crate_id, pagination.as_ref(), &req, conn)?,
             _ => list_by_semver(crate_id, pagination.as_ref(), &req, conn)?,
         };
 
@@ -60,7 +65,9 @@ pub async fn versions(
             .data
             .into_iter()
             .zip(VersionOwnerAction::for_versions(conn, &versions)?)
-            .map(|((v, pb), aas)| EncodableVersion::from(v, &crate_name, pb, aas))
+            .map(|((v, pb), aas)| {
+                EncodableVersion::from(v, &crate_name, pb, aas, state.config.avatar_proxy)
+            })
             .collect::<Vec<_>>();
 
         Ok(Json(match pagination {
@@ -71,6 +78,95 @@ pub async fn versions(
     .await
 }
 
+/// Handles the `GET /crates/:crate_id/default_version` route.
+///
+/// Returns the version that is shown and used by default for the crate,
+/// which is the highest stable version that is not yanked, falling back to
+/// the highest pre-release version.
+pub async fn default_version(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let version: Version = default_versions::table
+            .inner_join(versions::table)
+            .filter(default_versions::crate_id.eq(krate.id))
+            .select(versions::all_columns)
+            .first(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        let published_by = version.published_by(conn);
+        let actions = VersionOwnerAction::by_version(conn, &version)?;
+
+        let version = EncodableVersion::from(
+            version,
+            &krate.name,
+            published_by,
+            actions,
+            state.config.avatar_proxy,
+        );
+        Ok(Json(json!({ "version": version })))
+    })
+    .await
+}
+
+/// Handles the `GET /crates/:crate_id/channels/:channel/latest` route.
+///
+/// Returns the highest version (in semver order) that was published to the
+/// given release channel and is not yanked.
+pub async fn latest_in_channel(
+    state: AppState,
+    Path((crate_name, channel)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let version: Version = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::channel.eq(&channel))
+            .filter(versions::yanked.eq(false))
+            .filter(versions::semver_ord.is_not_null())
+            .order((versions::semver_ord.desc(), versions::id.desc()))
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| channel_not_found(&crate_name, &channel))?;
+
+        let published_by = version.published_by(conn);
+        let actions = VersionOwnerAction::by_version(conn, &version)?;
+
+        let version = EncodableVersion::from(
+            version,
+            &krate.name,
+            published_by,
+            actions,
+            state.config.avatar_proxy,
+        );
+        Ok(Json(json!({ "version": version })))
+    })
+    .await
+}
+
 /// Seek-based pagination of versions by date
 ///
 /// # Panics
@@ -132,6 +228,69 @@ fn list_by_date(
     })
 }
 
+/// Seek-based pagination of versions by the size of their crate file,
+/// largest first
+///
+/// Versions without a recorded size are listed last.
+///
+/// # Panics
+///
+/// This function will panic if `option` is built with `enable_pages` set to true.
+fn list_by_size(
+    crate_id: i32,
+    options: Option<&PaginationOptions>,
+    req: &Parts,
+    conn: &mut impl Conn,
+) -> AppResult<PaginatedVersionsAndPublishers> {
+    use seek::*;
+
+    let size = coalesce(versions::crate_size, 0);
+
+    let mut query = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .left_outer_join(users::table)
+        .select((versions::all_columns, users::all_columns.nullable()))
+        .into_boxed();
+
+    if let Some(options) = options {
+        assert!(
+            !matches!(&options.page, Page::Numeric(_)),
+            "?page= is not supported"
+        );
+        if let Some(SeekPayload::Size(Size { crate_size, id })) = Seek::Size.after(&options.page)? {
+            query = query.filter(
+                size.eq(crate_size)
+                    .and(versions::id.lt(id))
+                    .or(size.lt(crate_size)),
+            )
+        }
+        query = query.limit(options.per_page);
+    }
+
+    query = query.order((size.desc(), versions::id.desc()));
+
+    let data: Vec<(Version, Option<User>)> = query.load(conn)?;
+    let mut next_page = None;
+    if let Some(options) = options {
+        next_page = next_seek_params(&data, options, |last| Seek::Size.to_payload(last))?
+            .map(|p| req.query_with_params(p));
+    };
+
+    let total = if !data.is_empty() {
+        versions::table
+            .filter(versions::crate_id.eq(crate_id))
+            .count()
+            .get_result(conn)?
+    } else {
+        0
+    };
+
+    Ok(PaginatedVersionsAndPublishers {
+        data,
+        meta: ResponseMeta { total, next_page },
+    })
+}
+
 /// Seek-based pagination of versions by semver
 ///
 /// # Panics
@@ -250,15 +409,31 @@ mod seek {
                 created_at: chrono::NaiveDateTime,
                 id: i32,
             },
+            Size {
+                crate_size: i32,
+                id: i32,
+            },
         }
     );
 
     impl Seek {
         pub(crate) fn to_payload(&self, record: &(Version, Option<User>)) -> SeekPayload {
-            let (Version { id, created_at, .. }, _) = *record;
+            let (
+                Version {
+                    id,
+                    created_at,
+                    crate_size,
+                    ..
+                },
+                _,
+            ) = *record;
             match *self {
                 Seek::Semver => SeekPayload::Semver(Semver { id }),
                 Seek::Date => SeekPayload::Date(Date { created_at, id }),
+                Seek::Size => SeekPayload::Size(Size {
+                    crate_size: crate_size.unwrap_or(0),
+                    id,
+                }),
             }
         }
     }
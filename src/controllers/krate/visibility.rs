@@ -0,0 +1,83 @@
+//! Endpoint for changing the visibility of a crate
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{AdminAction, Crate, CrateVisibility, NewAdminAuditEntry, Rights};
+use crate::schema::crates;
+use crate::util::errors::{crate_not_found, custom};
+use crate::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use tokio::runtime::Handle;
+
+#[derive(Deserialize)]
+pub struct UpdateVisibilityRequest {
+    visibility: CrateVisibility,
+}
+
+/// Handles the `PUT /crates/:crate_id/visibility` route.
+///
+/// Private crates are hidden from search results and the index, and their
+/// metadata and downloads are only available to their owners. The crate
+/// files are moved between the public and private storage areas by a
+/// background job.
+pub async fn update_visibility(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<UpdateVisibilityRequest>,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        let rights = Handle::current().block_on(user.rights(&app, &owners))?;
+        if rights < Rights::Full {
+            if user.is_admin {
+                warn!(
+                    "Admin {} is changing the visibility of {}",
+                    user.gh_login, krate.name
+                );
+            } else if rights == Rights::None && krate.visibility == CrateVisibility::Private {
+                return Err(crate_not_found(&crate_name));
+            } else {
+                return Err(custom(
+                    StatusCode::FORBIDDEN,
+                    "only owners have permission to change the visibility of a crate",
+                ));
+            }
+        }
+
+        if body.visibility != krate.visibility {
+            diesel::update(&krate)
+                .set(crates::visibility.eq(body.visibility))
+                .execute(conn)?;
+
+            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+            jobs::SyncCrateFileVisibility::new(krate.id).enqueue(conn)?;
+            app.private_crates.refresh(conn)?;
+        }
+
+        if rights < Rights::Full {
+            NewAdminAuditEntry::by_admin(user, AdminAction::ChangeVisibility)
+                .krate(&krate.name)
+                .details(json!({ "visibility": body.visibility }))
+                .insert(conn)?;
+        }
+
+        ok_true()
+    })
+    .await
+}
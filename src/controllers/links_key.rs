@@ -0,0 +1,46 @@
+//! Endpoint for the crates that declare a `links` key
+//!
+//! Cargo only allows a single package with a given `links` key in a
+//! dependency graph, so crates that declare the same key can not be used
+//! together. This endpoint allows users to find out which crates already use
+//! a key, before they run into confusing build failures.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::CrateVisibility;
+use crate::schema::{crates, versions};
+use crate::views::EncodableLinksKeyCrate;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `GET /links_keys/:key` route.
+///
+/// Returns the public crates with non-yanked versions that declare the
+/// `links` key, together with these versions, newest first.
+pub async fn show(app: AppState, Path(key): Path<String>) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let rows: Vec<(String, String)> = versions::table
+            .inner_join(crates::table)
+            .filter(versions::links.eq(&key))
+            .filter(versions::yanked.eq(false))
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .select((crates::name, versions::num))
+            .order((crates::name, versions::id.desc()))
+            .load(conn)?;
+
+        let mut crates: Vec<EncodableLinksKeyCrate> = Vec::new();
+        for (name, num) in rows {
+            match crates.last_mut() {
+                Some(krate) if krate.name == name => krate.versions.push(num),
+                _ => crates.push(EncodableLinksKeyCrate {
+                    name,
+                    versions: vec![num],
+                }),
+            }
+        }
+
+        Ok(Json(json!({ "key": key, "crates": crates })))
+    })
+    .await
+}
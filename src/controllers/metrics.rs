@@ -1,5 +1,6 @@
 use crate::controllers::frontend_prelude::*;
 use crate::util::errors::{custom, forbidden, not_found};
+use crate::util::token::secure_compare;
 use prometheus::TextEncoder;
 
 /// Handles the `GET /api/private/metrics/:kind` endpoint.
@@ -11,7 +12,7 @@ pub async fn prometheus(app: AppState, Path(kind): Path<String>, req: Parts) ->
             .and_then(|value| value.to_str().ok())
             .and_then(|value| value.strip_prefix("Bearer "));
 
-        if provided_token != Some(expected_token.as_str()) {
+        if !provided_token.is_some_and(|token| secure_compare(token, expected_token)) {
             return Err(forbidden("invalid or missing authorization token"));
         }
     } else {
@@ -0,0 +1,401 @@
+//! Endpoints for reviewing the requests that were rejected by the rate limiter
+//!
+//! The report helps admins to tell abusive users apart from legitimate users
+//! that regularly run into the limits, and might need an override in the
+//! `publish_rate_overrides` table.
+//!
+//! Users can request such an override themselves (see
+//! [`crate::controllers::user::rate_limits`]). Approving a request creates
+//! the override, and the user is notified about the decision via email.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::email::{Email, Notification};
+use crate::models::{
+    AdminAction, NewAdminAuditEntry, NotificationClass, RateLimitOverrideRequest, User,
+};
+use crate::rate_limiter::{LimitedAction, REJECTIONS_RETENTION_DAYS};
+use crate::schema::{
+    publish_rate_overrides, rate_limit_override_requests, rate_limit_rejections, users,
+};
+use crate::util::diesel::Conn;
+use crate::util::errors::{forbidden, not_found};
+use crate::views::{
+    EncodablePublicUser, EncodableRateLimitOverrideRequest, EncodableThrottledAction,
+    EncodableThrottledUser,
+};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use diesel::dsl::{count_star, max, now};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// The default number of days that the report covers.
+const DEFAULT_DAYS: i64 = 7;
+
+/// The default and maximum number of users that are included in the report.
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+/// The default and maximum number of days after which the override of an
+/// approved request expires.
+const DEFAULT_OVERRIDE_DAYS: i64 = 90;
+const MAX_OVERRIDE_DAYS: i64 = 365;
+
+/// Handles the `GET /api/private/rate_limits/report` route.
+///
+/// Returns the users with the most rejected requests within the last `days`
+/// days, most throttled first, broken down by the rate limited action.
+pub async fn report(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let days = parse_param(&req, "days", DEFAULT_DAYS)?;
+    if !(1..=REJECTIONS_RETENTION_DAYS).contains(&days) {
+        return Err(bad_request(format!(
+            "`days` must be between 1 and {REJECTIONS_RETENTION_DAYS}"
+        )));
+    }
+
+    let limit = parse_param(&req, "limit", DEFAULT_LIMIT)?;
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(bad_request(format!(
+            "`limit` must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn, "must be an admin to view the rate limit report")?;
+
+        let now = Utc::now().naive_utc();
+        let since = now - TimeDelta::days(days);
+
+        let rows: Vec<(i32, LimitedAction, i64, NaiveDateTime)> = rate_limit_rejections::table
+            .filter(rate_limit_rejections::created_at.gt(since))
+            .group_by((
+                rate_limit_rejections::user_id,
+                rate_limit_rejections::action,
+            ))
+            .select((
+                rate_limit_rejections::user_id,
+                rate_limit_rejections::action,
+                count_star(),
+                max(rate_limit_rejections::created_at).assume_not_null(),
+            ))
+            .load(conn)?;
+
+        let mut actions_by_user: HashMap<i32, Vec<(LimitedAction, i64, NaiveDateTime)>> =
+            HashMap::new();
+        for (user_id, action, rejections, last_rejected_at) in rows {
+            let actions = actions_by_user.entry(user_id).or_default();
+            actions.push((action, rejections, last_rejected_at));
+        }
+
+        let mut totals = actions_by_user
+            .iter()
+            .map(|(&user_id, actions)| {
+                let rejections: i64 = actions.iter().map(|(_, count, _)| count).sum();
+                (user_id, rejections)
+            })
+            .collect::<Vec<_>>();
+
+        totals.sort_by_key(|(user_id, rejections)| (Reverse(*rejections), *user_id));
+        totals.truncate(limit);
+
+        let user_ids = totals.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+
+        let mut users: HashMap<i32, User> = users::table
+            .filter(users::id.eq_any(&user_ids))
+            .load::<User>(conn)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let overrides: HashMap<(i32, LimitedAction), i32> = publish_rate_overrides::table
+            .filter(publish_rate_overrides::user_id.eq_any(&user_ids))
+            .filter(
+                publish_rate_overrides::expires_at
+                    .is_null()
+                    .or(publish_rate_overrides::expires_at.gt(now)),
+            )
+            .select((
+                publish_rate_overrides::user_id,
+                publish_rate_overrides::action,
+                publish_rate_overrides::burst,
+            ))
+            .load::<(i32, LimitedAction, i32)>(conn)?
+            .into_iter()
+            .map(|(user_id, action, burst)| ((user_id, action), burst))
+            .collect();
+
+        let report = totals
+            .into_iter()
+            .filter_map(|(user_id, rejections)| {
+                let user = users.remove(&user_id)?;
+
+                let mut actions = actions_by_user.remove(&user_id).unwrap_or_default();
+                actions.sort_by_key(|(action, count, _)| (Reverse(*count), *action as i32));
+
+                let actions = actions
+                    .into_iter()
+                    .map(
+                        |(action, rejections, last_rejected_at)| EncodableThrottledAction {
+                            action,
+                            rejections,
+                            last_rejected_at,
+                            burst_override: overrides.get(&(user_id, action)).copied(),
+                        },
+                    )
+                    .collect::<Vec<_>>();
+
+                let last_rejected_at = actions.iter().map(|a| a.last_rejected_at).max()?;
+
+                Some(EncodableThrottledUser {
+                    user: EncodablePublicUser::from(user, state.config.avatar_proxy),
+                    rejections,
+                    last_rejected_at,
+                    actions,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "days": days, "users": report })))
+    })
+    .await
+}
+
+/// Handles the `GET /api/private/rate_limits/requests` route.
+///
+/// Returns the override requests that were not reviewed yet, oldest first.
+pub async fn list_override_requests(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn, "must be an admin to review rate limit requests")?;
+
+        let requests = RateLimitOverrideRequest::pending(conn)?;
+
+        let user_ids = requests.iter().map(|r| r.user_id).collect::<Vec<_>>();
+        let users: HashMap<i32, User> = users::table
+            .filter(users::id.eq_any(&user_ids))
+            .load::<User>(conn)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let requests = requests
+            .into_iter()
+            .filter_map(|request| {
+                let user = users.get(&request.user_id)?.clone();
+                Some(EncodableRateLimitOverrideRequest::from(
+                    request,
+                    user,
+                    state.config.avatar_proxy,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "requests": requests })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    Approve,
+    Reject,
+}
+
+#[derive(Deserialize)]
+pub struct Review {
+    decision: ReviewDecision,
+    /// The burst of the override, if it differs from the requested one.
+    burst: Option<i32>,
+    /// The number of days after which the override expires.
+    expires_in_days: Option<i64>,
+}
+
+/// Handles the `PUT /api/private/rate_limits/requests/:id/review` route.
+///
+/// Approving a request creates the override, replacing any previous
+/// override of the same user and action.
+pub async fn review_override_request(
+    state: AppState,
+    Path(id): Path<i32>,
+    req: Parts,
+    Json(review): Json<Review>,
+) -> AppResult<Json<Value>> {
+    let days = review.expires_in_days.unwrap_or(DEFAULT_OVERRIDE_DAYS);
+    if !(1..=MAX_OVERRIDE_DAYS).contains(&days) {
+        return Err(bad_request(format!(
+            "`expires_in_days` must be between 1 and {MAX_OVERRIDE_DAYS}"
+        )));
+    }
+
+    if review.burst.is_some_and(|burst| burst < 1) {
+        return Err(bad_request("`burst` must be positive"));
+    }
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let admin =
+            authenticate_admin(&req, conn, "must be an admin to review rate limit requests")?;
+
+        let request = conn.transaction(|conn| {
+            let request = RateLimitOverrideRequest::find(conn, id)
+                .optional()?
+                .ok_or_else(not_found)?;
+
+            if !request.is_pending() {
+                return Err(bad_request("the request was already reviewed"));
+            }
+
+            let user = User::find(conn, request.user_id)?;
+            let target = rate_limit_override_requests::table.find(id);
+
+            let request = match review.decision {
+                ReviewDecision::Approve => {
+                    let burst = review.burst.unwrap_or(request.burst);
+                    let expires_at = Utc::now().naive_utc() + TimeDelta::days(days);
+
+                    diesel::insert_into(publish_rate_overrides::table)
+                        .values((
+                            publish_rate_overrides::user_id.eq(request.user_id),
+                            publish_rate_overrides::action.eq(request.action),
+                            publish_rate_overrides::burst.eq(burst),
+                            publish_rate_overrides::expires_at.eq(expires_at),
+                        ))
+                        .on_conflict((
+                            publish_rate_overrides::user_id,
+                            publish_rate_overrides::action,
+                        ))
+                        .do_update()
+                        .set((
+                            publish_rate_overrides::burst.eq(burst),
+                            publish_rate_overrides::expires_at.eq(expires_at),
+                        ))
+                        .execute(conn)?;
+
+                    diesel::update(target)
+                        .set((
+                            rate_limit_override_requests::burst.eq(burst),
+                            rate_limit_override_requests::reviewed_by.eq(admin.id),
+                            rate_limit_override_requests::reviewed_at.eq(now.nullable()),
+                            rate_limit_override_requests::approved.eq(true),
+                            rate_limit_override_requests::expires_at.eq(expires_at),
+                        ))
+                        .returning(RateLimitOverrideRequest::as_returning())
+                        .get_result(conn)?
+                }
+                ReviewDecision::Reject => diesel::update(target)
+                    .set((
+                        rate_limit_override_requests::reviewed_by.eq(admin.id),
+                        rate_limit_override_requests::reviewed_at.eq(now.nullable()),
+                        rate_limit_override_requests::approved.eq(false),
+                    ))
+                    .returning(RateLimitOverrideRequest::as_returning())
+                    .get_result(conn)?,
+            };
+
+            let action = match review.decision {
+                ReviewDecision::Approve => AdminAction::ApproveRateLimitOverride,
+                ReviewDecision::Reject => AdminAction::RejectRateLimitOverride,
+            };
+            NewAdminAuditEntry::by_admin(&admin, action)
+                .details(json!({
+                    "user": user.gh_login,
+                    "action": request.action,
+                    "burst": request.burst,
+                    "expires_at": request.expires_at,
+                }))
+                .insert(conn)?;
+
+            Ok::<_, BoxedAppError>((request, user))
+        });
+        let (request, user) = request?;
+
+        let email = OverrideReviewEmail {
+            user_name: &user.gh_login,
+            action: request.action,
+            burst: request.burst,
+            expires_at: request.expires_at,
+        };
+        if let Err(error) = state.emails.send_notification(user.id, email, conn) {
+            warn!(
+                ?error,
+                "Failed to notify user {} about the review of their rate limit request", user.id
+            );
+        }
+
+        let request =
+            EncodableRateLimitOverrideRequest::from(request, user, state.config.avatar_proxy);
+        Ok(Json(json!({ "request": request })))
+    })
+    .await
+}
+
+fn parse_param<T: std::str::FromStr>(req: &Parts, name: &str, default: T) -> AppResult<T> {
+    match req.query().get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| bad_request(format!("invalid `{name}` parameter"))),
+        None => Ok(default),
+    }
+}
+
+fn authenticate_admin(
+    req: &Parts,
+    conn: &mut impl Conn,
+    error_message: &'static str,
+) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden(error_message));
+    }
+
+    Ok(user.clone())
+}
+
+struct OverrideReviewEmail<'a> {
+    user_name: &'a str,
+    action: LimitedAction,
+    burst: i32,
+    /// The expiry of the override, or `None` if the request was rejected.
+    expires_at: Option<NaiveDateTime>,
+}
+
+impl Email for OverrideReviewEmail<'_> {
+    const SUBJECT: &'static str = "Your request for a higher rate limit was reviewed";
+
+    fn body(&self) -> String {
+        let description = self.action.description();
+        match self.expires_at {
+            Some(expires_at) => format!(
+                "Hello {user_name}!
+
+Your request for a higher rate limit for {description} was approved. You can \
+now perform up to {burst} of these actions in a short period of time. The \
+higher limit expires on {expires_at} UTC.",
+                user_name = self.user_name,
+                burst = self.burst,
+                expires_at = expires_at.format("%Y-%m-%d"),
+            ),
+            None => format!(
+                "Hello {user_name}!
+
+Your request for a higher rate limit for {description} was rejected by the \
+crates.io team. If you have any questions, please email help@crates.io.",
+                user_name = self.user_name,
+            ),
+        }
+    }
+}
+
+impl Notification for OverrideReviewEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Publishing;
+}
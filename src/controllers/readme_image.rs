@@ -0,0 +1,29 @@
+//! Endpoint for images in READMEs that are served via the image proxy
+//!
+//! See the `readme_images` module for more details.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::ReadmeImage;
+use crate::util::errors::not_found;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `GET /api/v1/readme_images/:digest` route.
+///
+/// Redirects to the cached copy of the image in the storage bucket. Images
+/// that have not been fetched yet, or that failed to be fetched, are not
+/// found.
+pub async fn show(app: AppState, Path(digest): Path<String>) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let image = ReadmeImage::find(conn, &digest)?;
+        if !image.is_some_and(|image| image.is_cached()) {
+            return Err(not_found());
+        }
+
+        let redirect_url = app.storage.readme_image_location(&digest);
+        Ok(redirect(redirect_url))
+    })
+    .await
+}
@@ -0,0 +1,271 @@
+//! Endpoints with aggregate statistics of the registry for research
+//!
+//! Research groups used to scrape the whole API to build dependency graphs
+//! and download statistics. These endpoints provide the same data in bulk,
+//! and require a service token (see [`crate::controllers::service_token`]),
+//! so that the operators of crates.io know who to contact.
+//!
+//! The distributions can optionally be protected with differential privacy
+//! by passing the `epsilon` query parameter. Laplace noise with a scale of
+//! `1 / epsilon` is then added to every count, so that the presence of a
+//! single crate or user can not be derived from the response.
+
+use crate::api_quota::check_api_quota;
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{ApiToken, CrateVisibility, DependencyKind};
+use crate::schema::{crates, default_versions, dependencies, versions};
+use crate::util::diesel::Conn;
+use crate::util::errors::forbidden;
+use diesel::sql_types::{BigInt, Integer, Text};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// The maximum number of crates that are returned by a single request to
+/// the dependency graph endpoint.
+const MAX_CRATES: i64 = 1000;
+
+/// The number of buckets of the distributions. The last bucket contains all
+/// values of at least `10^(NUM_BUCKETS - 2)`.
+const NUM_BUCKETS: i32 = 12;
+
+/// The range of accepted values of the `epsilon` parameter. Smaller values
+/// add more noise.
+const MIN_EPSILON: f64 = 0.01;
+const MAX_EPSILON: f64 = 10.0;
+
+/// Handles the `GET /research/dependency_graph` route.
+///
+/// Returns the normal, build and dev dependencies of the default versions of
+/// the public crates, ordered by the id of the crates. The `meta.next_cursor`
+/// field of the response can be used as the `since` parameter of the next
+/// request.
+pub async fn dependency_graph(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let since: i32 = match req.query().get("since") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| bad_request("invalid `since` parameter"))?,
+        None => 0,
+    };
+
+    check_api_quota(&app, &req).await?;
+
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_service_token(&req, conn)?;
+
+        let crates: Vec<(i32, String, i32, String)> = default_versions::table
+            .inner_join(versions::table)
+            .inner_join(crates::table)
+            .filter(crates::id.gt(since))
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .select((crates::id, crates::name, versions::id, versions::num))
+            .order(crates::id)
+            .limit(MAX_CRATES)
+            .load(conn)?;
+
+        let version_ids = crates.iter().map(|(_, _, id, _)| *id).collect::<Vec<_>>();
+        let deps: Vec<(i32, String, String, DependencyKind, bool)> = dependencies::table
+            .inner_join(crates::table)
+            .filter(dependencies::version_id.eq_any(&version_ids))
+            .select((
+                dependencies::version_id,
+                crates::name,
+                dependencies::req,
+                dependencies::kind,
+                dependencies::optional,
+            ))
+            .order((dependencies::version_id, crates::name))
+            .load(conn)?;
+
+        let mut deps_by_version: HashMap<i32, Vec<Value>> = HashMap::new();
+        for (version_id, name, req, kind, optional) in deps {
+            deps_by_version.entry(version_id).or_default().push(json!({
+                "name": name,
+                "req": req,
+                "kind": kind,
+                "optional": optional,
+            }));
+        }
+
+        let next_cursor = crates.last().map(|(id, ..)| *id).unwrap_or(since);
+        let more = crates.len() as i64 == MAX_CRATES;
+
+        let crates = crates
+            .into_iter()
+            .map(|(_, name, version_id, num)| {
+                let dependencies = deps_by_version.remove(&version_id).unwrap_or_default();
+                json!({ "name": name, "version": num, "dependencies": dependencies })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "crates": crates,
+            "meta": {
+                "next_cursor": next_cursor.to_string(),
+                "more": more,
+            },
+        })))
+    })
+    .await
+}
+
+/// Handles the `GET /research/download_distribution` route.
+///
+/// Returns the number of public crates per bucket of all-time and recent
+/// downloads.
+pub async fn download_distribution(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    distribution(app, req, include_str!("research_downloads.sql")).await
+}
+
+/// Handles the `GET /research/owner_distribution` route.
+///
+/// Returns the number of users per bucket of the number of public crates
+/// that they own.
+pub async fn owner_distribution(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    distribution(app, req, include_str!("research_owners.sql")).await
+}
+
+async fn distribution(app: AppState, req: Parts, query: &'static str) -> AppResult<Json<Value>> {
+    let epsilon = parse_epsilon(&req)?;
+
+    check_api_quota(&app, &req).await?;
+
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_service_token(&req, conn)?;
+
+        let rows: Vec<BucketCount> = diesel::sql_query(query)
+            .bind::<Integer, _>(NUM_BUCKETS)
+            .load(conn)?;
+
+        let mut counts: HashMap<String, Vec<i64>> = HashMap::new();
+        for row in rows {
+            let buckets = counts
+                .entry(row.kind)
+                .or_insert_with(|| vec![0; NUM_BUCKETS as usize]);
+            buckets[row.bucket as usize] += row.count;
+        }
+
+        // Empty buckets have to be noised as well, since their absence would
+        // reveal information.
+        let mut rng = rand::thread_rng();
+        let distributions = counts
+            .into_iter()
+            .map(|(kind, counts)| {
+                let buckets = counts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(bucket, count)| {
+                        let count = match epsilon {
+                            Some(epsilon) => add_laplace_noise(count, epsilon, &mut rng),
+                            None => count,
+                        };
+                        let (min, max) = bucket_range(bucket as u32);
+                        json!({ "min": min, "max": max, "count": count })
+                    })
+                    .collect::<Vec<_>>();
+
+                (kind, Value::Array(buckets))
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        Ok(Json(json!({
+            "distributions": distributions,
+            "meta": { "epsilon": epsilon },
+        })))
+    })
+    .await
+}
+
+/// A helper struct for the result of the distribution queries.
+#[derive(QueryableByName)]
+struct BucketCount {
+    #[diesel(sql_type = Text)]
+    kind: String,
+    #[diesel(sql_type = Integer)]
+    bucket: i32,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Rejects all requests that were not authenticated with a service token.
+fn authenticate_service_token(req: &Parts, conn: &mut impl Conn) -> AppResult<()> {
+    let auth = AuthCheck::default()
+        .allow_service_token()
+        .check(req, conn)?;
+    if !auth.api_token().is_some_and(ApiToken::is_service_token) {
+        return Err(forbidden(
+            "the research API can only be used with service tokens",
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_epsilon(req: &Parts) -> AppResult<Option<f64>> {
+    let Some(value) = req.query().get("epsilon") else {
+        return Ok(None);
+    };
+
+    let epsilon: f64 = value
+        .parse()
+        .map_err(|_| bad_request("invalid `epsilon` parameter"))?;
+    if !(MIN_EPSILON..=MAX_EPSILON).contains(&epsilon) {
+        return Err(bad_request(format!(
+            "`epsilon` must be between {MIN_EPSILON} and {MAX_EPSILON}"
+        )));
+    }
+
+    Ok(Some(epsilon))
+}
+
+/// Returns the smallest and largest value of the bucket. The last bucket has
+/// no upper bound.
+fn bucket_range(bucket: u32) -> (u64, Option<u64>) {
+    if bucket == 0 {
+        return (0, Some(0));
+    }
+
+    let min = 10u64.pow(bucket - 1);
+    let max = (bucket < NUM_BUCKETS as u32 - 1).then(|| 10u64.pow(bucket) - 1);
+    (min, max)
+}
+
+/// Adds noise from a Laplace distribution with a scale of `1 / epsilon` to
+/// the count. The result is rounded and never negative.
+fn add_laplace_noise(count: i64, epsilon: f64, rng: &mut impl Rng) -> i64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    let noise = -u.signum() * (1.0 - 2.0 * u.abs()).ln() / epsilon;
+    (count as f64 + noise).round().max(0.0) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_ranges() {
+        assert_eq!(bucket_range(0), (0, Some(0)));
+        assert_eq!(bucket_range(1), (1, Some(9)));
+        assert_eq!(bucket_range(3), (100, Some(999)));
+        assert_eq!(bucket_range(NUM_BUCKETS as u32 - 1), (10_000_000_000, None));
+    }
+
+    #[test]
+    fn laplace_noise_is_never_negative() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            assert!(add_laplace_noise(0, MIN_EPSILON, &mut rng) >= 0);
+        }
+
+        // With a large epsilon, the noise is small
+        let noisy = add_laplace_noise(1000, MAX_EPSILON, &mut rng);
+        assert!((990..=1010).contains(&noisy));
+    }
+}
@@ -0,0 +1,18 @@
+-- The number of public crates per download bucket. Bucket 0 contains the
+-- crates without downloads, and bucket `n` the crates with at least
+-- `10^(n-1)` downloads. The last bucket (`$1 - 1`) is open-ended.
+SELECT 'downloads' AS kind,
+       LEAST(CASE WHEN downloads < 1 THEN 0 ELSE FLOOR(LOG(downloads))::int + 1 END, $1 - 1) AS bucket,
+       COUNT(*) AS count
+FROM crate_downloads
+INNER JOIN crates ON crates.id = crate_downloads.crate_id
+WHERE crates.visibility = 0
+GROUP BY 1, 2
+UNION ALL
+SELECT 'recent_downloads' AS kind,
+       LEAST(CASE WHEN COALESCE(downloads, 0) < 1 THEN 0 ELSE FLOOR(LOG(downloads))::int + 1 END, $1 - 1) AS bucket,
+       COUNT(*) AS count
+FROM crates
+LEFT JOIN recent_crate_downloads ON recent_crate_downloads.crate_id = crates.id
+WHERE crates.visibility = 0
+GROUP BY 1, 2
@@ -0,0 +1,15 @@
+-- The number of users per bucket of the number of public crates that they
+-- own, using the same buckets as `research_downloads.sql`.
+SELECT 'crates' AS kind,
+       LEAST(FLOOR(LOG(crates))::int + 1, $1 - 1) AS bucket,
+       COUNT(*) AS count
+FROM (
+    SELECT crate_owners.owner_id, COUNT(*) AS crates
+    FROM crate_owners
+    INNER JOIN crates ON crates.id = crate_owners.crate_id
+    WHERE crate_owners.owner_kind = 0
+      AND NOT crate_owners.deleted
+      AND crates.visibility = 0
+    GROUP BY crate_owners.owner_id
+) owners
+GROUP BY 1, 2
@@ -0,0 +1,124 @@
+//! Service tokens for high-volume consumers of the read-only API.
+//!
+//! Service tokens identify legitimate high-volume consumers (e.g.
+//! documentation sites or package indexes), so that they can be rate-limited
+//! separately from anonymous scrapers. They require contact information of
+//! the operator, can not be used to perform any actions, and have elevated
+//! API quotas (see [`crate::api_quota`]).
+
+use super::frontend_prelude::*;
+
+use crate::auth::AuthCheck;
+use crate::models::ApiToken;
+use crate::schema::api_tokens;
+use crate::views::EncodableApiTokenWithToken;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use serde_json as json;
+
+/// The maximum number of active service tokens per user.
+const MAX_SERVICE_TOKENS_PER_USER: i64 = 10;
+
+/// The maximum length of the contact information of a service token.
+const MAX_CONTACT_LENGTH: usize = 256;
+
+/// Handles the `GET /me/service_tokens` route.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let tokens: Vec<ApiToken> = ApiToken::belonging_to(user)
+            .select(ApiToken::as_select())
+            .filter(api_tokens::revoked.eq(false))
+            .filter(api_tokens::service_contact.is_not_null())
+            .order(api_tokens::id.desc())
+            .load(conn)?;
+
+        Ok(Json(json!({ "service_tokens": tokens })))
+    })
+    .await
+}
+
+/// Handles the `PUT /me/service_tokens` route.
+pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        #[derive(Deserialize)]
+        struct NewServiceToken {
+            name: String,
+            contact: String,
+        }
+
+        #[derive(Deserialize)]
+        struct NewServiceTokenRequest {
+            service_token: NewServiceToken,
+        }
+
+        let new: NewServiceTokenRequest = json::from_slice(req.body())
+            .map_err(|e| bad_request(format!("invalid new service token request: {e:?}")))?;
+
+        let name = new.service_token.name.trim();
+        if name.is_empty() {
+            return Err(bad_request("name must have a value"));
+        }
+
+        let contact = new.service_token.contact.trim();
+        if contact.is_empty() {
+            return Err(bad_request(
+                "contact must have a value, so that the operators of crates.io can reach you",
+            ));
+        }
+        if contact.len() > MAX_CONTACT_LENGTH {
+            return Err(bad_request(format!(
+                "contact must not be longer than {MAX_CONTACT_LENGTH} characters"
+            )));
+        }
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let count: i64 = ApiToken::belonging_to(user)
+            .filter(api_tokens::revoked.eq(false))
+            .filter(api_tokens::service_contact.is_not_null())
+            .count()
+            .get_result(conn)?;
+        if count >= MAX_SERVICE_TOKENS_PER_USER {
+            return Err(bad_request(format!(
+                "maximum service tokens per user is: {MAX_SERVICE_TOKENS_PER_USER}"
+            )));
+        }
+
+        let service_token = ApiToken::insert_service_token(conn, user.id, name, contact)?;
+        let service_token = EncodableApiTokenWithToken::from(service_token);
+
+        Ok(Json(json!({ "service_token": service_token })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /me/service_tokens/:id` route.
+pub async fn revoke(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let token = ApiToken::belonging_to(user)
+            .filter(api_tokens::id.eq(id))
+            .filter(api_tokens::service_contact.is_not_null());
+
+        diesel::update(token)
+            .set(api_tokens::revoked.eq(true))
+            .execute(conn)?;
+
+        Ok(Json(json!({})))
+    })
+    .await
+}
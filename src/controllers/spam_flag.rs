@@ -0,0 +1,151 @@
+//! Endpoints for the admin moderation queue of potential spam
+//!
+//! Publishes whose descriptions or keywords are classified as potential spam
+//! are not rejected, but flagged for a review by an admin. Admins can either
+//! confirm the classification, which quarantines the version, or dismiss it.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, CrateVisibility, NewAdminAuditEntry, SpamFlag, User, VersionQuarantine,
+};
+use crate::schema::{crates, spam_flags, version_quarantines, versions};
+use crate::util::diesel::Conn;
+use crate::util::errors::{forbidden, not_found};
+use crate::views::EncodableSpamFlag;
+use diesel::dsl::now;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use tokio::runtime::Handle;
+
+/// The reason of the quarantines of versions that were confirmed as spam.
+const QUARANTINE_REASON: &str = "The metadata of the version was confirmed as spam";
+
+/// Handles the `GET /api/private/spam_flags` route.
+///
+/// Returns the flagged versions that were not reviewed by an admin yet,
+/// oldest first.
+pub async fn list(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let quarantines = version_quarantines::table
+            .on(version_quarantines::version_id.eq(spam_flags::version_id));
+
+        let flags: Vec<(SpamFlag, String, String, Option<String>, Option<i32>)> = spam_flags::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .left_join(quarantines)
+            .filter(spam_flags::reviewed_at.is_null())
+            .order(spam_flags::created_at.asc())
+            .select((
+                SpamFlag::as_select(),
+                crates::name,
+                versions::num,
+                crates::description,
+                version_quarantines::version_id.nullable(),
+            ))
+            .load(conn)?;
+
+        let flags = flags
+            .into_iter()
+            .map(|(flag, krate, num, description, quarantine)| {
+                EncodableSpamFlag::from(flag, krate, num, description, quarantine.is_some())
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "spam_flags": flags })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// The metadata is spam and the version is quarantined.
+    Confirm,
+    /// The classification was a false positive.
+    Dismiss,
+}
+
+#[derive(Deserialize)]
+pub struct Review {
+    decision: ReviewDecision,
+}
+
+/// Handles the `PUT /api/private/spam_flags/:version_id/review` route.
+pub async fn review(
+    state: AppState,
+    Path(version_id): Path<i32>,
+    req: Parts,
+    Json(review): Json<Review>,
+) -> AppResult<Response> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user = authenticate_admin(&req, conn)?;
+
+        let (crate_name, num, visibility): (String, String, CrateVisibility) = spam_flags::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .filter(spam_flags::version_id.eq(version_id))
+            .select((crates::name, versions::num, crates::visibility))
+            .first(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        conn.transaction(|conn| {
+            diesel::update(spam_flags::table.find(version_id))
+                .set((
+                    spam_flags::reviewed_by.eq(user.id),
+                    spam_flags::reviewed_at.eq(now.nullable()),
+                ))
+                .execute(conn)?;
+
+            if let ReviewDecision::Confirm = review.decision {
+                if VersionQuarantine::create(conn, version_id, QUARANTINE_REASON)? {
+                    let future = state
+                        .storage
+                        .quarantine_crate_file(&crate_name, &num, visibility);
+                    Handle::current()
+                        .block_on(future)
+                        .map_err(|e| server_error(format!("failed to quarantine tarball: {e}")))?;
+                }
+            }
+
+            let action = match review.decision {
+                ReviewDecision::Confirm => AdminAction::ConfirmSpamFlag,
+                ReviewDecision::Dismiss => AdminAction::DismissSpamFlag,
+            };
+            NewAdminAuditEntry::by_admin(&user, action)
+                .krate(&crate_name)
+                .details(json!({ "version": num }))
+                .insert(conn)?;
+
+            Ok::<_, BoxedAppError>(())
+        })?;
+
+        let decision = match review.decision {
+            ReviewDecision::Confirm => "confirmed",
+            ReviewDecision::Dismiss => "dismissed",
+        };
+        warn!(
+            "Admin {} {decision} the spam flag of {crate_name}@{num}",
+            user.gh_login
+        );
+
+        ok_true()
+    })
+    .await
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden("must be an admin to review spam flags"));
+    }
+
+    Ok(user.clone())
+}
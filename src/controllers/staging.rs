@@ -0,0 +1,106 @@
+//! Endpoint for uploading crate tarballs ahead of their publish requests.
+//!
+//! A tarball that was uploaded via `POST /api/v1/staging` can be published by
+//! passing the returned id as the `staging_id` field of the metadata of a
+//! regular `PUT /api/v1/crates/new` request, or of a crate of a
+//! `PUT /api/v1/crates/batch` request, with an empty tarball. This allows
+//! clients to retry a failed upload without reuploading the metadata, and
+//! large tarballs to be scanned for viruses before they are published.
+//!
+//! Uploading the same tarball again returns the existing staged upload,
+//! which makes uploads resumable. Staged uploads expire after a day.
+
+use crate::auth::AuthCheck;
+use crate::controllers::cargo_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{NewStagedUpload, StagedUpload};
+use crate::util::errors::{bad_request, custom, forbidden, internal};
+use crate::worker::jobs;
+use chrono::Duration;
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use hex::ToHex;
+use sha2::{Digest, Sha256};
+use tokio::runtime::Handle;
+
+/// Handles the `POST /api/v1/staging` route.
+///
+/// The request body is the `.crate` tarball. The crate scopes of the API
+/// token are only checked once the tarball is published.
+pub async fn upload(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    let (req, bytes) = req.0.into_parts();
+
+    if bytes.is_empty() {
+        return Err(bad_request("the request body must contain the tarball"));
+    }
+
+    let max_upload_size = app.config.max_upload_size;
+    if bytes.len() as u64 > max_upload_size {
+        return Err(custom(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("max upload size is: {max_upload_size}"),
+        ));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default().skip_scope_checks().check(&req, conn)?;
+        if let Some(scopes) = auth.api_token().and_then(|t| t.endpoint_scopes.as_ref()) {
+            let can_publish = scopes
+                .iter()
+                .any(|s| matches!(s, EndpointScope::PublishNew | EndpointScope::PublishUpdate));
+
+            if !can_publish {
+                return Err(forbidden(
+                    "this token does not have the required permissions to perform this action",
+                ));
+            }
+        }
+
+        let user_id = auth.user_id();
+        let checksum: String = Sha256::digest(&bytes).encode_hex();
+
+        let upload = match StagedUpload::find_by_checksum(conn, user_id, &checksum)? {
+            Some(upload) => upload,
+            None => {
+                for id in StagedUpload::delete_expired(conn, user_id)? {
+                    let future = app.storage.delete_staged_file(&id);
+                    if let Err(error) = Handle::current().block_on(future) {
+                        warn!("Failed to delete the staged upload {id}: {error}");
+                    }
+                }
+
+                conn.transaction(|conn| {
+                    let new_upload = NewStagedUpload {
+                        user_id,
+                        checksum: &checksum,
+                        size: bytes.len() as i32,
+                    };
+                    let upload = new_upload.insert(conn)?;
+
+                    Handle::current()
+                        .block_on(app.storage.upload_staged_file(&upload.id, bytes))
+                        .map_err(|e| internal(format!("failed to upload tarball: {e}")))?;
+
+                    if app.config.clamd_address.is_some() {
+                        jobs::ScanStagedUpload::new(&upload.id).enqueue(conn)?;
+                    }
+
+                    Ok::<_, BoxedAppError>(upload)
+                })?
+            }
+        };
+
+        let expires_at = upload.created_at + Duration::days(1);
+
+        Ok(Json(json!({
+            "id": upload.id,
+            "checksum": upload.checksum,
+            "size": upload.size,
+            "expires_at": expires_at,
+        })))
+    })
+    .await
+}
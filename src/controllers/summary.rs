@@ -1,6 +1,8 @@
 use crate::app::AppState;
 use crate::controllers::cargo_prelude::AppResult;
-use crate::models::{Category, Crate, CrateVersions, Keyword, TopVersions, Version};
+use crate::models::{
+    load_default_versions, Category, Crate, CrateVisibility, Keyword, TopVersions,
+};
 use crate::schema::{crate_downloads, crates, keywords, metadata, recent_crate_downloads};
 use crate::tasks::spawn_blocking;
 use crate::util::diesel::Conn;
@@ -18,7 +20,10 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
 
         let config = &state.config;
 
-        let num_crates: i64 = crates::table.count().get_result(conn)?;
+        // Private crates are not included in any of the summary lists
+        let is_public = crates::visibility.eq(CrateVisibility::Public);
+
+        let num_crates: i64 = crates::table.filter(is_public).count().get_result(conn)?;
         let num_downloads: i64 = metadata::table
             .select(metadata::total_downloads)
             .get_result(conn)?;
@@ -27,24 +32,21 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
             conn: &mut impl Conn,
             data: Vec<(Crate, i64, Option<i64>)>,
         ) -> AppResult<Vec<EncodableCrate>> {
-            let downloads = data
+            let crate_ids = data
                 .iter()
-                .map(|&(_, total, recent)| (total, recent))
+                .map(|(krate, _, _)| krate.id)
                 .collect::<Vec<_>>();
+            let mut top_versions = TopVersions::for_crates(conn, &crate_ids)?;
+            let default_versions = load_default_versions(&crate_ids, conn)?;
 
-            let krates = data.into_iter().map(|(c, _, _)| c).collect::<Vec<_>>();
-
-            let versions: Vec<Version> = krates.versions().load(conn)?;
-            versions
-                .grouped_by(&krates)
-                .into_iter()
-                .map(TopVersions::from_versions)
-                .zip(krates)
-                .zip(downloads)
-                .map(|((top_versions, krate), (total, recent))| {
+            data.into_iter()
+                .map(|(krate, total, recent)| {
+                    let top_versions = top_versions.remove(&krate.id).unwrap_or_default();
+                    let default_version = default_versions.get(&krate.id).map(String::as_str);
                     Ok(EncodableCrate::from_minimal(
                         krate,
                         Some(&top_versions),
+                        default_version,
                         None,
                         false,
                         total,
@@ -63,6 +65,7 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
         let new_crates = crates::table
             .inner_join(crate_downloads::table)
             .left_join(recent_crate_downloads::table)
+            .filter(is_public)
             .order(crates::created_at.desc())
             .select(selection)
             .limit(10)
@@ -70,6 +73,7 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
         let just_updated = crates::table
             .inner_join(crate_downloads::table)
             .left_join(recent_crate_downloads::table)
+            .filter(is_public)
             .filter(crates::updated_at.ne(crates::created_at))
             .order(crates::updated_at.desc())
             .select(selection)
@@ -79,6 +83,7 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
         let mut most_downloaded_query = crates::table
             .inner_join(crate_downloads::table)
             .left_join(recent_crate_downloads::table)
+            .filter(is_public)
             .into_boxed();
         if !config.excluded_crate_names.is_empty() {
             most_downloaded_query =
@@ -93,6 +98,7 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
         let mut most_recently_downloaded_query = crates::table
             .inner_join(crate_downloads::table)
             .inner_join(recent_crate_downloads::table)
+            .filter(is_public)
             .into_boxed();
         if !config.excluded_crate_names.is_empty() {
             most_recently_downloaded_query = most_recently_downloaded_query
@@ -0,0 +1,150 @@
+//! Endpoints for the admin review queue of infected crate tarballs
+//!
+//! Versions whose tarballs were flagged by the virus scanner are quarantined
+//! automatically. Admins can either confirm the verdict, which keeps the
+//! version quarantined, or overrule it, which lifts the quarantine again.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, CrateVisibility, NewAdminAuditEntry, ScanVerdict, TarballScan, User,
+    VersionQuarantine,
+};
+use crate::schema::{crates, tarball_scans, version_quarantines, versions};
+use crate::util::diesel::Conn;
+use crate::util::errors::{forbidden, not_found};
+use crate::views::EncodableTarballScan;
+use diesel::dsl::now;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use tokio::runtime::Handle;
+
+/// Handles the `GET /api/private/tarball_scans` route.
+///
+/// Returns the infected tarballs that were not reviewed by an admin yet,
+/// oldest first.
+pub async fn list(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let quarantines = version_quarantines::table
+            .on(version_quarantines::version_id.eq(tarball_scans::version_id));
+
+        let scans: Vec<(TarballScan, String, String, Option<i32>)> = tarball_scans::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .left_join(quarantines)
+            .filter(tarball_scans::verdict.eq(ScanVerdict::Infected))
+            .filter(tarball_scans::reviewed_at.is_null())
+            .order(tarball_scans::scanned_at.asc())
+            .select((
+                TarballScan::as_select(),
+                crates::name,
+                versions::num,
+                version_quarantines::version_id.nullable(),
+            ))
+            .load(conn)?;
+
+        let scans = scans
+            .into_iter()
+            .map(|(scan, krate, num, quarantine)| {
+                EncodableTarballScan::from(scan, krate, num, quarantine.is_some())
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "tarball_scans": scans })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// The tarball is malicious and the version stays quarantined.
+    Confirm,
+    /// The verdict was a false positive and the quarantine is lifted.
+    Release,
+}
+
+#[derive(Deserialize)]
+pub struct Review {
+    decision: ReviewDecision,
+}
+
+/// Handles the `PUT /api/private/tarball_scans/:version_id/review` route.
+pub async fn review(
+    state: AppState,
+    Path(version_id): Path<i32>,
+    req: Parts,
+    Json(review): Json<Review>,
+) -> AppResult<Response> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user = authenticate_admin(&req, conn)?;
+
+        let (crate_name, num, visibility): (String, String, CrateVisibility) = tarball_scans::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .filter(tarball_scans::version_id.eq(version_id))
+            .filter(tarball_scans::verdict.eq(ScanVerdict::Infected))
+            .select((crates::name, versions::num, crates::visibility))
+            .first(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        conn.transaction(|conn| {
+            diesel::update(tarball_scans::table.find(version_id))
+                .set((
+                    tarball_scans::reviewed_by.eq(user.id),
+                    tarball_scans::reviewed_at.eq(now.nullable()),
+                ))
+                .execute(conn)?;
+
+            if let ReviewDecision::Release = review.decision {
+                if VersionQuarantine::release(conn, version_id)? {
+                    let future = state
+                        .storage
+                        .release_crate_file(&crate_name, &num, visibility);
+                    Handle::current()
+                        .block_on(future)
+                        .map_err(|e| server_error(format!("failed to release tarball: {e}")))?;
+                }
+            }
+
+            let action = match review.decision {
+                ReviewDecision::Confirm => AdminAction::ConfirmTarballScan,
+                ReviewDecision::Release => AdminAction::ReleaseTarballScan,
+            };
+            NewAdminAuditEntry::by_admin(&user, action)
+                .krate(&crate_name)
+                .details(json!({ "version": num }))
+                .insert(conn)?;
+
+            Ok::<_, BoxedAppError>(())
+        })?;
+
+        let decision = match review.decision {
+            ReviewDecision::Confirm => "confirmed",
+            ReviewDecision::Release => "overruled",
+        };
+        warn!(
+            "Admin {} {decision} the antivirus verdict for {crate_name}@{num}",
+            user.gh_login
+        );
+
+        ok_true()
+    })
+    .await
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden("must be an admin to review tarball scans"));
+    }
+
+    Ok(user.clone())
+}
@@ -1,3 +1,6 @@
+pub mod crates;
+pub mod tokens;
+
 use crate::controllers::frontend_prelude::*;
 
 use crate::models::Team;
@@ -0,0 +1,65 @@
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use crate::models::{CrateVisibility, OwnerKind, Team, User};
+use crate::schema::{crate_owners, crates, teams, users};
+use crate::views::EncodableTeamCrate;
+use chrono::NaiveDateTime;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Handles the `GET /teams/:team_id/crates` route.
+///
+/// Lists the public crates that are owned by the team, together with the
+/// date the team was added as an owner and the user that added it.
+pub async fn list(
+    app: AppState,
+    Path(team_login): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let team: Team = teams::table
+            .filter(teams::login.eq(&team_login))
+            .first(conn)?;
+
+        let query = crate_owners::table
+            .inner_join(crates::table)
+            .left_join(users::table.on(crate_owners::created_by.eq(users::id.nullable())))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::Team))
+            .filter(crate_owners::owner_id.eq(team.id))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .order(crates::name.asc())
+            .select((
+                crates::id,
+                crates::name,
+                crate_owners::created_at,
+                users::all_columns.nullable(),
+            ))
+            .pages_pagination(PaginationOptions::builder().gather(&req)?);
+
+        let data: Paginated<(i32, String, NaiveDateTime, Option<User>)> = query.load(conn)?;
+        let total = data.total();
+        let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+        let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+        let crates = data
+            .into_iter()
+            .map(|(id, name, added_at, added_by)| {
+                EncodableTeamCrate::from(id, name, added_at, added_by, app.config.avatar_proxy)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "crates": crates,
+            "meta": {
+                "total": total,
+                "next_page": next_page,
+                "prev_page": prev_page,
+            },
+        })))
+    })
+    .await
+}
@@ -0,0 +1,219 @@
+//! Endpoints for managing the shared API tokens of a team
+//!
+//! Team tokens are meant to be used in CI workflows of organizations, so that
+//! nobody has to put a personal token into the CI configuration. They can
+//! only be used to publish updates and to yank versions of the crates that
+//! are owned by the team.
+//!
+//! The tokens can only be managed by the maintainers of the team on GitHub,
+//! or by the owners of the GitHub organization. The maintainer that created
+//! a token is recorded as the acting user of all actions performed with it.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::middleware::real_ip::RealIp;
+use crate::models::token::{CrateScope, EndpointScope};
+use crate::models::{ApiToken, NewSecurityEvent, SecurityEventKind, Team, User};
+use crate::schema::{api_tokens, users};
+use crate::util::diesel::Conn;
+use crate::util::errors::{custom, not_found};
+use crate::util::rfc3339;
+use crate::views::{EncodableApiTokenWithToken, EncodableTeamApiToken};
+use chrono::NaiveDateTime;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use tokio::runtime::Handle;
+
+/// The maximum number of active tokens that a team can have.
+const MAX_TOKENS_PER_TEAM: i64 = 100;
+
+/// Handles the `GET /teams/:team_id/tokens` route.
+pub async fn list(
+    app: AppState,
+    Path(team_login): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let team = load_team_as_maintainer(&app, &team_login, auth.user(), conn)?;
+
+        let tokens = api_tokens::table
+            .inner_join(users::table)
+            .filter(api_tokens::team_id.eq(team.id))
+            .filter(api_tokens::revoked.eq(false))
+            .order(api_tokens::id.desc())
+            .select((ApiToken::as_select(), users::all_columns))
+            .load::<(ApiToken, User)>(conn)?
+            .into_iter()
+            .map(|(token, user)| EncodableTeamApiToken::from(token, user, app.config.avatar_proxy))
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "api_tokens": tokens })))
+    })
+    .await
+}
+
+/// Handles the `PUT /teams/:team_id/tokens` route.
+pub async fn create(
+    app: AppState,
+    Path(team_login): Path<String>,
+    req: BytesRequest,
+) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewTeamToken {
+        name: String,
+        crate_scopes: Option<Vec<String>>,
+        endpoint_scopes: Option<Vec<String>>,
+        #[serde(default, with = "rfc3339::option")]
+        expired_at: Option<NaiveDateTime>,
+    }
+
+    #[derive(Deserialize)]
+    struct NewTeamTokenRequest {
+        api_token: NewTeamToken,
+    }
+
+    let new: NewTeamTokenRequest = serde_json::from_slice(req.body())
+        .map_err(|e| bad_request(format!("invalid new token request: {e:?}")))?;
+    let new = new.api_token;
+
+    if new.name.is_empty() {
+        return Err(bad_request("name must have a value"));
+    }
+
+    let crate_scopes = new
+        .crate_scopes
+        .map(|scopes| {
+            scopes
+                .into_iter()
+                .map(CrateScope::try_from)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+        .map_err(|_err| bad_request("invalid crate scope"))?;
+
+    let endpoint_scopes = new
+        .endpoint_scopes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|scope| EndpointScope::try_from(scope.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_err| bad_request("invalid endpoint scope"))?;
+
+    // Teams can not publish new crates or change owners, so only these
+    // scopes are useful for team tokens.
+    let is_allowed =
+        |scope: &EndpointScope| matches!(scope, EndpointScope::PublishUpdate | EndpointScope::Yank);
+    if endpoint_scopes.is_empty() || !endpoint_scopes.iter().all(is_allowed) {
+        return Err(bad_request(
+            "team tokens must have the `publish-update` and/or `yank` endpoint scopes",
+        ));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+        let team = load_team_as_maintainer(&app, &team_login, user, conn)?;
+
+        let count: i64 = api_tokens::table
+            .filter(api_tokens::team_id.eq(team.id))
+            .filter(api_tokens::revoked.eq(false))
+            .count()
+            .get_result(conn)?;
+        if count >= MAX_TOKENS_PER_TEAM {
+            return Err(bad_request(format!(
+                "maximum tokens per team is: {MAX_TOKENS_PER_TEAM}"
+            )));
+        }
+
+        let api_token = conn.transaction(|conn| {
+            let api_token = ApiToken::insert_team_token(
+                conn,
+                user.id,
+                team.id,
+                &new.name,
+                crate_scopes,
+                endpoint_scopes,
+                new.expired_at,
+            )?;
+
+            let user_agent = req.headers().get(header::USER_AGENT);
+            let event = NewSecurityEvent {
+                user_id: user.id,
+                kind: SecurityEventKind::ApiTokenCreated,
+                ip_address: req.extensions().get::<RealIp>().map(|ip| ip.to_string()),
+                user_agent: user_agent.and_then(|h| h.to_str().ok()),
+                api_token_id: Some(api_token.model.id),
+            };
+            event.insert_and_notify(user, Some(&new.name), &app.emails, conn)?;
+
+            QueryResult::Ok(api_token)
+        })?;
+
+        info!(
+            "User {} created the token {} for team {}",
+            user.gh_login, api_token.model.id, team.login
+        );
+
+        let api_token = EncodableApiTokenWithToken::from(api_token);
+
+        Ok(Json(json!({ "api_token": api_token })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /teams/:team_id/tokens/:id` route.
+///
+/// Maintainers can revoke all tokens of the team, not only the ones that
+/// they created themselves.
+pub async fn revoke(
+    app: AppState,
+    Path((team_login, id)): Path<(String, i32)>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let team = load_team_as_maintainer(&app, &team_login, auth.user(), conn)?;
+
+        let updated = diesel::update(api_tokens::table.find(id))
+            .filter(api_tokens::team_id.eq(team.id))
+            .set(api_tokens::revoked.eq(true))
+            .execute(conn)?;
+
+        if updated == 0 {
+            return Err(not_found());
+        }
+
+        ok_true()
+    })
+    .await
+}
+
+/// Loads the team and checks that the user is allowed to manage its tokens.
+fn load_team_as_maintainer(
+    app: &AppState,
+    team_login: &str,
+    user: &User,
+    conn: &mut impl Conn,
+) -> AppResult<Team> {
+    let team = Team::find_by_login(conn, team_login)
+        .optional()?
+        .ok_or_else(not_found)?;
+
+    if !Handle::current().block_on(team.is_maintainer(app, user))? {
+        return Err(custom(
+            StatusCode::FORBIDDEN,
+            "only maintainers of the team can manage its tokens",
+        ));
+    }
+
+    Ok(team)
+}
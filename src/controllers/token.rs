@@ -1,12 +1,15 @@
 use super::frontend_prelude::*;
 
-use crate::models::ApiToken;
+use crate::middleware::real_ip::RealIp;
+use crate::models::{ApiToken, NewSecurityEvent, SecurityEventKind};
 use crate::schema::api_tokens;
 use crate::util::rfc3339;
 use crate::views::EncodableApiTokenWithToken;
 
 use crate::auth::AuthCheck;
+use crate::middleware::registry::RequestRegistry;
 use crate::models::token::{CrateScope, EndpointScope};
+use crate::registries::DEFAULT_REGISTRY;
 use axum::extract::Query;
 use axum::response::IntoResponse;
 use chrono::NaiveDateTime;
@@ -46,6 +49,10 @@ pub async fn list(
         let tokens: Vec<ApiToken> = ApiToken::belonging_to(user)
             .select(ApiToken::as_select())
             .filter(api_tokens::revoked.eq(false))
+            // Service tokens are listed via `GET /me/service_tokens`
+            .filter(api_tokens::service_contact.is_null())
+            // Team tokens are listed via `GET /teams/:team_id/tokens`
+            .filter(api_tokens::team_id.is_null())
             .filter(
                 api_tokens::expired_at.is_null().or(api_tokens::expired_at
                     .assume_not_null()
@@ -130,14 +137,49 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
             .transpose()
             .map_err(|_err| bad_request("invalid endpoint scope"))?;
 
-        let api_token = ApiToken::insert_with_scopes(
-            conn,
-            user.id,
-            name,
-            crate_scopes,
-            endpoint_scopes,
-            new.api_token.expired_at,
-        )?;
+        // Tokens without endpoint scopes can be used for all endpoints
+        let can_publish = endpoint_scopes.as_ref().map_or(true, |scopes| {
+            scopes.iter().any(|scope| {
+                matches!(
+                    scope,
+                    EndpointScope::PublishNew | EndpointScope::PublishUpdate
+                )
+            })
+        });
+
+        let api_token = conn.transaction(|conn| {
+            let api_token = ApiToken::insert_with_scopes(
+                conn,
+                user.id,
+                name,
+                crate_scopes,
+                endpoint_scopes,
+                new.api_token.expired_at,
+            )?;
+
+            // Tokens can only be used with the registry they were created for
+            let registry = req.registry();
+            if registry != DEFAULT_REGISTRY {
+                diesel::update(api_tokens::table.find(api_token.model.id))
+                    .set(api_tokens::registry.eq(registry))
+                    .execute(conn)?;
+            }
+
+            if can_publish {
+                let user_agent = req.headers().get(header::USER_AGENT);
+                let event = NewSecurityEvent {
+                    user_id: user.id,
+                    kind: SecurityEventKind::ApiTokenCreated,
+                    ip_address: req.extensions().get::<RealIp>().map(|ip| ip.to_string()),
+                    user_agent: user_agent.and_then(|h| h.to_str().ok()),
+                    api_token_id: Some(api_token.model.id),
+                };
+                event.insert_and_notify(user, Some(name), &app.emails, conn)?;
+            }
+
+            QueryResult::Ok(api_token)
+        })?;
+
         let api_token = EncodableApiTokenWithToken::from(api_token);
 
         Ok(Json(json!({ "api_token": api_token })))
@@ -1,3 +1,7 @@
+pub mod avatar;
+pub mod emails;
 pub mod me;
 pub mod other;
+pub mod rate_limits;
 pub mod session;
+pub mod subscriptions;
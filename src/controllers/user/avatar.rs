@@ -0,0 +1,85 @@
+//! Endpoint for the avatars of users that are served via the avatar proxy
+//!
+//! See the `avatars` module for more details.
+
+use crate::avatars::{self, REFRESH_INTERVAL};
+use crate::controllers::frontend_prelude::*;
+use crate::models::{User, UserAvatar};
+use crate::worker::jobs;
+use chrono::Utc;
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// Identicons are only cached briefly, since they are replaced by the real
+/// avatar once it has been fetched.
+const IDENTICON_CACHE_CONTROL: &str = "public,max-age=300";
+
+/// Handles the `GET /api/v1/users/:user_id/avatar` route.
+///
+/// Redirects to the avatar of the user on GitHub, or to the cached copy in
+/// the storage bucket if the avatar proxy is enabled. Avatars that are stale
+/// are fetched again in the background. If no avatar is available, an
+/// identicon is served instead.
+pub async fn show(app: AppState, Path(user_id): Path<i32>) -> AppResult<Response> {
+    let conn = app.db_read().await?;
+    let (response, is_stale) = spawn_blocking({
+        let app = app.clone();
+        move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let user = User::find(conn, user_id)?;
+
+            if !app.config.avatar_proxy {
+                let response = match user.gh_avatar {
+                    Some(url) => redirect(url),
+                    None => identicon(user.id),
+                };
+                return Ok::<_, BoxedAppError>((response, false));
+            }
+
+            let avatar = UserAvatar::find(conn, user.id)?;
+
+            let is_stale = user.gh_avatar.as_ref().is_some_and(|url| {
+                let cutoff = (Utc::now() - REFRESH_INTERVAL).naive_utc();
+                avatar
+                    .as_ref()
+                    .map_or(true, |avatar| avatar.is_stale(url, cutoff))
+            });
+
+            // Previously cached avatars are served until the refresh succeeds.
+            let response = match avatar.and_then(|avatar| avatar.digest) {
+                Some(digest) => redirect(app.storage.avatar_location(&digest)),
+                None => identicon(user.id),
+            };
+
+            Ok((response, is_stale))
+        }
+    })
+    .await?;
+
+    if is_stale && app.avatar_refreshes.request(user_id) {
+        if let Err(error) = enqueue_refresh(&app, user_id).await {
+            warn!("Failed to enqueue the refresh of the avatar of user {user_id}: {error}");
+        }
+    }
+
+    Ok(response)
+}
+
+async fn enqueue_refresh(app: &AppState, user_id: i32) -> AppResult<()> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+        jobs::FetchAvatar::new(user_id).enqueue(conn)?;
+        Ok(())
+    })
+    .await
+}
+
+fn identicon(user_id: i32) -> Response {
+    let headers = [
+        (header::CONTENT_TYPE, "image/svg+xml"),
+        (header::CACHE_CONTROL, IDENTICON_CACHE_CONTROL),
+    ];
+    (headers, avatars::identicon(&user_id.to_string())).into_response()
+}
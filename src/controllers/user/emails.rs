@@ -0,0 +1,284 @@
+//! Endpoints for managing the email addresses of the current user.
+//!
+//! Users can add multiple addresses, each of which has to be verified
+//! separately. One of them is the primary address, which receives all
+//! notifications unless the user selected another verified address for a
+//! specific class of notifications.
+//!
+//! API tokens are not allowed here, since a stolen token could otherwise be
+//! used to redirect the notifications of a user.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::user::me::UserConfirmEmail;
+use crate::models::{Email, NewEmail, NotificationClass, MAX_EMAILS_PER_USER};
+use crate::schema::{email_notification_preferences, emails};
+use crate::sql::lower;
+use crate::util::diesel::Conn;
+use crate::util::errors::not_found;
+use crate::views::{EncodableEmail, EncodableEmailPreference};
+use diesel::dsl::{count_star, exists, select, sql};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use lettre::Address;
+use secrecy::{ExposeSecret, SecretString};
+
+/// Handles the `GET /me/emails` route.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let emails = emails::table
+            .filter(emails::user_id.eq(user_id))
+            .order(emails::id)
+            .load::<Email>(conn)?
+            .into_iter()
+            .map(EncodableEmail::from)
+            .collect::<Vec<_>>();
+
+        let preferences = email_notification_preferences::table
+            .filter(email_notification_preferences::user_id.eq(user_id))
+            .select((
+                email_notification_preferences::notification_class,
+                email_notification_preferences::email_id,
+            ))
+            .order(email_notification_preferences::notification_class)
+            .load::<(NotificationClass, i32)>(conn)?
+            .into_iter()
+            .map(|(notification_class, email_id)| EncodableEmailPreference {
+                notification_class,
+                email_id: Some(email_id),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "emails": emails,
+            "email_preferences": preferences,
+        })))
+    })
+    .await
+}
+
+/// Handles the `PUT /me/emails` route.
+pub async fn add(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewEmailRequest {
+        email: String,
+    }
+
+    let request: NewEmailRequest =
+        serde_json::from_slice(req.body()).map_err(|_| bad_request("invalid json request"))?;
+
+    let address = request.email.trim().to_string();
+    if address.parse::<Address>().is_err() {
+        return Err(bad_request("invalid email address"));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let email = conn.transaction::<_, BoxedAppError, _>(|conn| {
+            let user_emails = emails::table.filter(emails::user_id.eq(user.id));
+
+            let num_emails: i64 = user_emails.select(count_star()).get_result(conn)?;
+            if num_emails >= MAX_EMAILS_PER_USER {
+                return Err(bad_request(format!(
+                    "a user can have at most {MAX_EMAILS_PER_USER} email addresses"
+                )));
+            }
+
+            let is_duplicate = select(exists(
+                user_emails.filter(lower(emails::email).eq(address.to_lowercase())),
+            ))
+            .get_result(conn)?;
+
+            if is_duplicate {
+                return Err(bad_request("this email address has already been added"));
+            }
+
+            // The first address of a user automatically becomes their primary one
+            let new_email = NewEmail {
+                user_id: user.id,
+                email: &address,
+                is_primary: num_emails == 0,
+            };
+
+            Ok(diesel::insert_into(emails::table)
+                .values(&new_email)
+                .get_result::<Email>(conn)?)
+        })?;
+
+        // Swallows any errors, since the user can request another confirmation email.
+        let confirm_email = UserConfirmEmail {
+            user_name: &user.gh_login,
+            domain: &app.emails.domain,
+            token: SecretString::new(email.token.expose_secret().to_string()),
+        };
+        let _ = app.emails.send_confirmation(&email.email, confirm_email);
+
+        Ok(Json(json!({ "email": EncodableEmail::from(email) })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /me/emails/:email_id` route.
+pub async fn delete(app: AppState, Path(email_id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+        let email = find_email(conn, user_id, email_id)?;
+
+        if email.is_primary {
+            return Err(bad_request("the primary email address cannot be removed"));
+        }
+
+        diesel::delete(&email).execute(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `PUT /me/emails/:email_id/primary` route.
+pub async fn set_primary(
+    app: AppState,
+    Path(email_id): Path<i32>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+        let email = find_email(conn, user_id, email_id)?;
+
+        if !email.verified {
+            return Err(bad_request(
+                "only verified email addresses can become the primary address",
+            ));
+        }
+
+        conn.transaction(|conn| {
+            diesel::update(emails::table)
+                .filter(emails::user_id.eq(user_id))
+                .filter(emails::is_primary.eq(true))
+                .set(emails::is_primary.eq(false))
+                .execute(conn)?;
+
+            diesel::update(&email)
+                .set(emails::is_primary.eq(true))
+                .execute(conn)
+        })?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `PUT /me/emails/:email_id/resend` route.
+pub async fn resend(app: AppState, Path(email_id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+        let email = find_email(conn, user.id, email_id)?;
+
+        // Undeliverable addresses have to be verified again.
+        if email.verified && email.undeliverable_at.is_none() {
+            return Err(bad_request("this email address is already verified"));
+        }
+
+        let email: Email = diesel::update(&email)
+            .set(emails::token.eq(sql("DEFAULT")))
+            .get_result(conn)?;
+
+        let confirm_email = UserConfirmEmail {
+            user_name: &user.gh_login,
+            domain: &app.emails.domain,
+            token: email.token,
+        };
+
+        app.emails.send_confirmation(&email.email, confirm_email)?;
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `PUT /me/email_preferences` route.
+///
+/// Only the notification classes that are included in the request are
+/// changed. An `email_id` of `null` resets the class to the primary address.
+pub async fn update_preferences(app: AppState, req: BytesRequest) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct PreferencesRequest {
+        email_preferences: Vec<EncodableEmailPreference>,
+    }
+
+    let request: PreferencesRequest =
+        serde_json::from_slice(req.body()).map_err(|_| bad_request("invalid json request"))?;
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        conn.transaction(|conn| {
+            for preference in &request.email_preferences {
+                let class = preference.notification_class;
+
+                let Some(email_id) = preference.email_id else {
+                    diesel::delete(email_notification_preferences::table.find((user_id, class)))
+                        .execute(conn)?;
+                    continue;
+                };
+
+                let email = find_email(conn, user_id, email_id)?;
+                if !email.verified {
+                    return Err(bad_request(
+                        "only verified email addresses can receive notifications",
+                    ));
+                }
+
+                diesel::insert_into(email_notification_preferences::table)
+                    .values((
+                        email_notification_preferences::user_id.eq(user_id),
+                        email_notification_preferences::notification_class.eq(class),
+                        email_notification_preferences::email_id.eq(email.id),
+                    ))
+                    .on_conflict((
+                        email_notification_preferences::user_id,
+                        email_notification_preferences::notification_class,
+                    ))
+                    .do_update()
+                    .set(email_notification_preferences::email_id.eq(email.id))
+                    .execute(conn)?;
+            }
+
+            Ok::<_, BoxedAppError>(())
+        })?;
+
+        ok_true()
+    })
+    .await
+}
+
+fn find_email(conn: &mut impl Conn, user_id: i32, email_id: i32) -> AppResult<Email> {
+    emails::table
+        .find(email_id)
+        .filter(emails::user_id.eq(user_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(not_found)
+}
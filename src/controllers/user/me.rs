@@ -1,4 +1,5 @@
 use crate::auth::AuthCheck;
+use chrono::{NaiveDateTime, Utc};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use secrecy::{ExposeSecret, SecretString};
 use std::collections::HashMap;
@@ -8,11 +9,18 @@ use crate::controllers::frontend_prelude::*;
 use crate::controllers::helpers::*;
 
 use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::middleware::real_ip::RealIp;
 use crate::models::{
-    CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    ApiToken, CrateOwner, CrateOwnerEmailInvitation, Email, Follow, NewEmail, OwnerKind,
+    SecurityEvent, User, Version, VersionOwnerAction,
+};
+use crate::schema::{
+    api_tokens, crate_owners, crates, digest_subscriptions, emails, follows, users, versions,
+};
+use crate::views::{
+    EncodableApiTokenUsage, EncodableApiUsage, EncodableMe, EncodablePrivateUser,
+    EncodableQuotaUsage, EncodableVersion, OwnedCrate,
 };
-use crate::schema::{crate_owners, crates, emails, follows, users, versions};
-use crate::views::{EncodableMe, EncodablePrivateUser, EncodableVersion, OwnedCrate};
 
 /// Handles the `GET /me` route.
 pub async fn me(app: AppState, req: Parts) -> AppResult<Json<EncodableMe>> {
@@ -22,17 +30,23 @@ pub async fn me(app: AppState, req: Parts) -> AppResult<Json<EncodableMe>> {
 
         let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
 
-        let (user, verified, email, verification_sent): (User, Option<bool>, Option<String>, bool) =
-            users::table
-                .find(user_id)
-                .left_join(emails::table)
-                .select((
-                    users::all_columns,
-                    emails::verified.nullable(),
-                    emails::email.nullable(),
-                    emails::token_generated_at.nullable().is_not_null(),
-                ))
-                .first(conn)?;
+        let (user, verified, email, verification_sent, undeliverable): (
+            User,
+            Option<bool>,
+            Option<String>,
+            bool,
+            bool,
+        ) = users::table
+            .find(user_id)
+            .left_join(emails::table.on(emails::user_id.eq(users::id).and(emails::is_primary)))
+            .select((
+                users::all_columns,
+                emails::verified.nullable(),
+                emails::email.nullable(),
+                emails::token_generated_at.nullable().is_not_null(),
+                emails::undeliverable_at.nullable().is_not_null(),
+            ))
+            .first(conn)?;
 
         let owned_crates = CrateOwner::by_owner_kind(OwnerKind::User)
             .inner_join(crates::table)
@@ -51,7 +65,14 @@ pub async fn me(app: AppState, req: Parts) -> AppResult<Json<EncodableMe>> {
         let verified = verified.unwrap_or(false);
         let verification_sent = verified || verification_sent;
         Ok(Json(EncodableMe {
-            user: EncodablePrivateUser::from(user, email, verified, verification_sent),
+            user: EncodablePrivateUser::from(
+                user,
+                email,
+                verified,
+                verification_sent,
+                undeliverable,
+                app.config.avatar_proxy,
+            ),
             owned_crates,
         }))
     })
@@ -90,7 +111,13 @@ pub async fn updates(app: AppState, req: Parts) -> AppResult<Json<Value>> {
         let versions = data
             .into_iter()
             .map(|(version, crate_name, published_by, actions)| {
-                EncodableVersion::from(version, &crate_name, published_by, actions)
+                EncodableVersion::from(
+                    version,
+                    &crate_name,
+                    published_by,
+                    actions,
+                    app.config.avatar_proxy,
+                )
             })
             .collect::<Vec<_>>();
 
@@ -102,6 +129,65 @@ pub async fn updates(app: AppState, req: Parts) -> AppResult<Json<Value>> {
     .await
 }
 
+/// Handles the `GET /me/usage` route.
+///
+/// Returns the API request quota consumption of the authenticated user within
+/// the current quota window. If the request is authenticated via API token,
+/// only the consumption of that token is included; otherwise all active tokens
+/// of the user are listed.
+pub async fn usage(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .allow_service_token()
+            .check(&req, conn)?;
+        let user_id = auth.user_id();
+
+        let quota = &app.api_quota;
+        let config = quota.config();
+        let now = Utc::now().naive_utc();
+        let window_start = quota.window_start(now);
+
+        let tokens: Vec<ApiToken> = match auth.api_token() {
+            Some(token) => vec![token.clone()],
+            None => api_tokens::table
+                .filter(api_tokens::user_id.eq(user_id))
+                .filter(api_tokens::revoked.eq(false))
+                .order(api_tokens::id.asc())
+                .select(ApiToken::as_select())
+                .load(conn)?,
+        };
+
+        let token_ids = tokens.iter().map(|token| token.id).collect::<Vec<_>>();
+        let token_usage = quota.token_usage(&token_ids, now, conn)?;
+
+        let tokens = tokens
+            .into_iter()
+            .map(|token| EncodableApiTokenUsage {
+                id: token.id,
+                requests: token_usage.get(&token.id).copied().unwrap_or(0),
+                limit: quota.token_limit(&token),
+                name: token.name,
+            })
+            .collect();
+
+        let usage = EncodableApiUsage {
+            window_start,
+            window_end: quota.window_end(window_start),
+            user: EncodableQuotaUsage {
+                requests: quota.user_usage(user_id, now, conn)?,
+                limit: config.user_requests,
+            },
+            tokens,
+        };
+
+        Ok(Json(json!({ "usage": usage })))
+    })
+    .await
+}
+
 /// Handles the `PUT /users/:user_id` route.
 pub async fn update_user(
     state: AppState,
@@ -112,8 +198,7 @@ pub async fn update_user(
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        use self::emails::user_id;
-        use diesel::insert_into;
+        use diesel::{insert_into, update};
 
         let auth = AuthCheck::default().check(&req, conn)?;
         let user = auth.user();
@@ -146,21 +231,33 @@ pub async fn update_user(
         }
 
         conn.transaction::<_, BoxedAppError, _>(|conn| {
-            let new_email = NewEmail {
-                user_id: user.id,
-                email: user_email,
-            };
-
-            let token = insert_into(emails::table)
-                .values(&new_email)
-                .on_conflict(user_id)
-                .do_update()
-                .set(&new_email)
+            // Changing the address resets the verification of the primary address
+            let token = update(Email::belonging_to(user).filter(emails::is_primary.eq(true)))
+                .set(emails::email.eq(user_email))
                 .returning(emails::token)
                 .get_result(conn)
-                .map(SecretString::new)
+                .optional()
                 .map_err(|_| server_error("Error in creating token"))?;
 
+            let token = match token {
+                Some(token) => token,
+                None => {
+                    let new_email = NewEmail {
+                        user_id: user.id,
+                        email: user_email,
+                        is_primary: true,
+                    };
+
+                    insert_into(emails::table)
+                        .values(&new_email)
+                        .returning(emails::token)
+                        .get_result(conn)
+                        .map_err(|_| server_error("Error in creating token"))?
+                }
+            };
+
+            let token = SecretString::new(token);
+
             // This swallows any errors that occur while attempting to send the email. Some users have
             // an invalid email set in their GitHub profile, and we should let them sign in even though
             // we're trying to silently use their invalid address during signup and can't send them an
@@ -171,7 +268,7 @@ pub async fn update_user(
                 token,
             };
 
-            let _ = state.emails.send(user_email, email);
+            let _ = state.emails.send_confirmation(user_email, email);
 
             Ok(())
         })?;
@@ -189,12 +286,53 @@ pub async fn confirm_user_email(state: AppState, Path(token): Path<String>) -> A
 
         use diesel::update;
 
-        let updated_rows = update(emails::table.filter(emails::token.eq(&token)))
-            .set(emails::verified.eq(true))
-            .execute(conn)?;
+        // Verifying an undeliverable address again shows that it receives
+        // emails, so the notifications are resumed.
+        let user_id: i32 = update(emails::table.filter(emails::token.eq(&token)))
+            .set((
+                emails::verified.eq(true),
+                emails::undeliverable_at.eq(None::<NaiveDateTime>),
+                emails::undeliverable_reason.eq(None::<String>),
+            ))
+            .returning(emails::user_id)
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(|| bad_request("Email belonging to token not found."))?;
+
+        if let Err(error) = CrateOwnerEmailInvitation::claim(conn, user_id, &state.config) {
+            warn!(
+                ?error,
+                "Failed to claim ownership invitations of user {user_id}"
+            );
+        }
+
+        ok_true()
+    })
+    .await
+}
+
+/// Handles the `PUT /lock_account/:token` route.
+///
+/// This is the "this wasn't me" link of security alert emails. It locks the
+/// account indefinitely until the crates.io team has verified the identity
+/// of the user.
+pub async fn lock_account(
+    state: AppState,
+    Path(token): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let ip_address = req.extensions.get::<RealIp>().map(|ip| ip.to_string());
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        if updated_rows == 0 {
-            return Err(bad_request("Email belonging to token not found."));
+        let user_agent = req.headers.get(header::USER_AGENT);
+        let user_agent = user_agent.and_then(|h| h.to_str().ok());
+
+        let user_id = SecurityEvent::lock_account(conn, &token, ip_address, user_agent)?;
+        if user_id.is_none() {
+            return Err(bad_request("Invalid or expired lock token."));
         }
 
         ok_true()
@@ -224,11 +362,12 @@ pub async fn regenerate_token_and_send(
         }
 
         conn.transaction(|conn| -> AppResult<_> {
-            let email: Email = update(Email::belonging_to(user))
-                .set(emails::token.eq(sql("DEFAULT")))
-                .get_result(conn)
-                .optional()?
-                .ok_or_else(|| bad_request("Email could not be found"))?;
+            let email: Email =
+                update(Email::belonging_to(user).filter(emails::is_primary.eq(true)))
+                    .set(emails::token.eq(sql("DEFAULT")))
+                    .get_result(conn)
+                    .optional()?
+                    .ok_or_else(|| bad_request("Email could not be found"))?;
 
             let email1 = UserConfirmEmail {
                 user_name: &user.gh_login,
@@ -236,7 +375,10 @@ pub async fn regenerate_token_and_send(
                 token: email.token,
             };
 
-            state.emails.send(&email.email, email1).map_err(Into::into)
+            state
+                .emails
+                .send_confirmation(&email.email, email1)
+                .map_err(Into::into)
         })?;
 
         ok_true()
@@ -309,6 +451,125 @@ pub async fn update_email_notifications(app: AppState, req: BytesRequest) -> App
     .await
 }
 
+/// Handles `PUT /me/publish_settings` route
+pub async fn update_publish_settings(app: AppState, req: BytesRequest) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct PublishSettings {
+        publish_confirmation_required: bool,
+    }
+
+    let settings: PublishSettings =
+        serde_json::from_slice(req.body()).map_err(|_| bad_request("invalid json request"))?;
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        // API tokens are not allowed here, since a stolen token could
+        // otherwise be used to turn off the publish confirmations.
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        if settings.publish_confirmation_required && user.verified_email(conn)?.is_none() {
+            return Err(bad_request(
+                "a verified email address is required to enable publish confirmations",
+            ));
+        }
+
+        diesel::update(user)
+            .set(users::publish_confirmation_required.eq(settings.publish_confirmation_required))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+#[derive(Deserialize, Serialize)]
+struct DigestSettings {
+    enabled: bool,
+    timezone: String,
+}
+
+/// Handles the `GET /me/digest_settings` route.
+pub async fn digest_settings(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let timezone: Option<String> = digest_subscriptions::table
+            .find(user_id)
+            .select(digest_subscriptions::timezone)
+            .first(conn)
+            .optional()?;
+
+        let digest_settings = DigestSettings {
+            enabled: timezone.is_some(),
+            timezone: timezone.unwrap_or_else(|| "UTC".to_string()),
+        };
+
+        Ok(Json(json!({ "digest_settings": digest_settings })))
+    })
+    .await
+}
+
+/// Handles the `PUT /me/digest_settings` route.
+pub async fn update_digest_settings(app: AppState, req: BytesRequest) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct Request {
+        digest_settings: DigestSettings,
+    }
+
+    let settings = serde_json::from_slice::<Request>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?
+        .digest_settings;
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        if !settings.enabled {
+            diesel::delete(digest_subscriptions::table.find(user.id)).execute(conn)?;
+            return ok_true();
+        }
+
+        if user.verified_email(conn)?.is_none() {
+            return Err(bad_request(
+                "a verified email address is required to subscribe to the weekly digest",
+            ));
+        }
+
+        let is_known_timezone =
+            diesel::sql_query("SELECT 1 FROM pg_timezone_names WHERE name = $1")
+                .bind::<diesel::sql_types::Text, _>(&settings.timezone)
+                .execute(conn)?
+                > 0;
+
+        if !is_known_timezone {
+            let timezone = &settings.timezone;
+            return Err(bad_request(format!("unknown timezone `{timezone}`")));
+        }
+
+        diesel::insert_into(digest_subscriptions::table)
+            .values((
+                digest_subscriptions::user_id.eq(user.id),
+                digest_subscriptions::timezone.eq(&settings.timezone),
+            ))
+            .on_conflict(digest_subscriptions::user_id)
+            .do_update()
+            .set(digest_subscriptions::timezone.eq(&settings.timezone))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
 pub struct UserConfirmEmail<'a> {
     pub user_name: &'a str,
     pub domain: &'a str,
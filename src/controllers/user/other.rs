@@ -20,7 +20,9 @@ pub async fn show(state: AppState, Path(user_name): Path<String>) -> AppResult<J
         .first(&mut conn)
         .await?;
 
-    Ok(Json(json!({ "user": EncodablePublicUser::from(user) })))
+    Ok(Json(
+        json!({ "user": EncodablePublicUser::from(user, state.config.avatar_proxy) }),
+    ))
 }
 
 /// Handles the `GET /users/:user_id/stats` route.
@@ -0,0 +1,108 @@
+//! Endpoints for requesting higher rate limits
+//!
+//! Users that regularly run into a rate limit can request a higher burst for
+//! the rate limited action. The requests are reviewed by admins (see
+//! [`crate::controllers::rate_limit`]), and approving a request creates an
+//! override that expires after a while.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{NewRateLimitOverrideRequest, RateLimitOverrideRequest};
+use crate::rate_limiter::LimitedAction;
+use crate::views::EncodableRateLimitOverrideRequest;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum burst that can be requested.
+const MAX_BURST: i32 = 1000;
+
+/// The maximum length of the justification of a request.
+const MAX_JUSTIFICATION_LENGTH: usize = 2000;
+
+#[derive(Deserialize)]
+pub struct NewOverrideRequest {
+    action: LimitedAction,
+    burst: i32,
+    justification: String,
+}
+
+/// Handles the `GET /me/rate_limits/requests` route.
+pub async fn list_requests(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let requests = RateLimitOverrideRequest::for_user(conn, user)?
+            .into_iter()
+            .map(|request| {
+                EncodableRateLimitOverrideRequest::from(
+                    request,
+                    user.clone(),
+                    app.config.avatar_proxy,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "requests": requests })))
+    })
+    .await
+}
+
+/// Handles the `POST /me/rate_limits/requests` route.
+///
+/// Users can only have a single pending request per rate limited action.
+pub async fn create_request(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<NewOverrideRequest>,
+) -> AppResult<Json<Value>> {
+    let justification = body.justification.trim().to_string();
+    if justification.is_empty() {
+        return Err(bad_request(
+            "the justification must explain why you need a higher limit",
+        ));
+    }
+    if justification.chars().count() > MAX_JUSTIFICATION_LENGTH {
+        return Err(bad_request(format!(
+            "the justification must not be longer than {MAX_JUSTIFICATION_LENGTH} characters"
+        )));
+    }
+
+    let current_burst = app
+        .config
+        .rate_limiter
+        .get(&body.action)
+        .map(|config| config.burst)
+        .unwrap_or_else(|| body.action.default_burst());
+    if body.burst <= current_burst || body.burst > MAX_BURST {
+        return Err(bad_request(format!(
+            "the burst must be greater than {current_burst} and at most {MAX_BURST}"
+        )));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let request = NewRateLimitOverrideRequest {
+            user_id: user.id,
+            action: body.action,
+            burst: body.burst,
+            justification: &justification,
+        }
+        .insert(conn)?
+        .ok_or_else(|| {
+            bad_request("you already have a pending request for a higher limit of this action")
+        })?;
+
+        let request =
+            EncodableRateLimitOverrideRequest::from(request, user.clone(), app.config.avatar_proxy);
+        Ok(Json(json!({ "request": request })))
+    })
+    .await
+}
@@ -7,9 +7,14 @@ use oauth2::{AuthorizationCode, CsrfToken, Scope, TokenResponse};
 use tokio::runtime::Handle;
 
 use crate::email::Emails;
+use crate::middleware::impersonation::Impersonation;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
 use crate::middleware::session::SessionExtension;
-use crate::models::{NewUser, User};
+use crate::models::{
+    CrateOwnerEmailInvitation, NewSecurityEvent, NewUser, PersistentSession, SecurityEventKind,
+    User,
+};
 use crate::schema::users;
 use crate::util::diesel::Conn;
 use crate::util::errors::ReadOnlyMode;
@@ -87,6 +92,12 @@ pub async fn authorize(
 ) -> AppResult<Json<EncodableMe>> {
     let app_clone = app.clone();
     let request_log = req.request_log().clone();
+    let ip_address = req.extensions.get::<RealIp>().map(|ip| ip.to_string());
+    let user_agent = req
+        .headers
+        .get(header::USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .map(ToString::to_string);
 
     let conn = app.db_write().await?;
     spawn_blocking(move || {
@@ -115,8 +126,27 @@ pub async fn authorize(
         let ghuser = Handle::current().block_on(app.github.current_user(token))?;
         let user = save_user_to_database(&ghuser, token.secret(), &app.emails, conn)?;
 
-        // Log in by setting a cookie and the middleware authentication
+        // Log in by setting a cookie and the middleware authentication. The
+        // session identifier is rotated to prevent session fixation.
+        PersistentSession::rotate(&session, conn, user.id)?;
         session.insert("user_id".to_string(), user.id.to_string());
+        Impersonation::clear(&session);
+
+        // Failing to record the sign-in (e.g. in read-only mode) should not
+        // prevent the user from signing in
+        let user_agent = user_agent.as_deref();
+        if let Err(error) = record_login(&user, ip_address, user_agent, &app.emails, conn) {
+            warn!(?error, "Failed to record sign-in of user {}", user.id);
+        }
+
+        // Pending ownership invitations for the verified email addresses of
+        // the user are turned into regular invitations
+        if let Err(error) = CrateOwnerEmailInvitation::claim(conn, user.id, &app.config) {
+            warn!(
+                ?error,
+                "Failed to claim ownership invitations of user {}", user.id
+            );
+        }
 
         Ok(())
     })
@@ -131,13 +161,16 @@ fn save_user_to_database(
     emails: &Emails,
     conn: &mut impl Conn,
 ) -> AppResult<User> {
-    NewUser::new(
-        user.id,
-        &user.login,
-        user.name.as_deref(),
-        user.avatar_url.as_deref(),
-        access_token,
-    )
+    NewUser {
+        gh_two_factor: user.two_factor_authentication,
+        ..NewUser::new(
+            user.id,
+            &user.login,
+            user.name.as_deref(),
+            user.avatar_url.as_deref(),
+            access_token,
+        )
+    }
     .create_or_update(user.email.as_deref(), emails, conn)
     .map_err(Into::into)
     .or_else(|e: BoxedAppError| {
@@ -155,10 +188,48 @@ fn save_user_to_database(
     })
 }
 
+/// Records the sign-in as a security event and sends a security alert to the
+/// user if it happened from an unrecognized IP address or user agent.
+fn record_login(
+    user: &User,
+    ip_address: Option<String>,
+    user_agent: Option<&str>,
+    emails: &Emails,
+    conn: &mut impl Conn,
+) -> QueryResult<()> {
+    let event = NewSecurityEvent {
+        user_id: user.id,
+        kind: SecurityEventKind::Login,
+        ip_address,
+        user_agent,
+        api_token_id: None,
+    };
+
+    if event.is_unrecognized_login(conn)? {
+        event.insert_and_notify(user, None, emails, conn)
+    } else {
+        event.insert(conn).map(|_| ())
+    }
+}
+
 /// Handles the `DELETE /api/private/session` route.
-pub async fn logout(session: SessionExtension) -> Json<bool> {
+pub async fn logout(app: AppState, session: SessionExtension) -> AppResult<Json<bool>> {
     session.remove("user_id");
-    Json(true)
+    Impersonation::clear(&session);
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        // Failing to revoke the session (e.g. in read-only mode) should not
+        // prevent the user from signing out
+        if let Err(error) = PersistentSession::revoke(&session, conn) {
+            warn!(?error, "Failed to revoke session");
+        }
+
+        Ok(Json(true))
+    })
+    .await
 }
 
 #[cfg(test)]
@@ -176,6 +247,7 @@ mod tests {
             login: "github_user".into(),
             id: -1,
             avatar_url: None,
+            two_factor_authentication: false,
         };
         let result = save_user_to_database(&gh_user, "arbitrary_token", &emails, conn);
 
@@ -0,0 +1,134 @@
+//! Endpoints for managing the dependency subscriptions of the current user
+//!
+//! A subscription notifies the user via email whenever the crate publishes a
+//! version that matches the version requirement of the subscription (see the
+//! `SendSubscriptionNotifications` background job).
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{
+    Crate, DependencySubscription, NewDependencySubscription, MAX_SUBSCRIPTIONS_PER_USER,
+};
+use crate::schema::dependency_subscriptions;
+use crate::util::errors::crate_not_found;
+use crate::views::EncodableDependencySubscription;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum length of the version requirement of a subscription.
+const MAX_VERSION_REQ_LENGTH: usize = 100;
+
+#[derive(Deserialize)]
+pub struct NewSubscriptionRequest {
+    subscription: NewSubscription,
+}
+
+#[derive(Deserialize)]
+struct NewSubscription {
+    #[serde(rename = "crate")]
+    krate: String,
+    version_req: String,
+}
+
+/// Handles the `GET /me/subscriptions` route.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let subscriptions = DependencySubscription::for_user(conn, user_id)?
+            .into_iter()
+            .map(|(subscription, crate_name)| {
+                EncodableDependencySubscription::from(subscription, crate_name)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "subscriptions": subscriptions })))
+    })
+    .await
+}
+
+/// Handles the `PUT /me/subscriptions` route.
+pub async fn create(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<NewSubscriptionRequest>,
+) -> AppResult<Json<Value>> {
+    let new = body.subscription;
+
+    let version_req = new.version_req.trim();
+    if version_req.len() > MAX_VERSION_REQ_LENGTH {
+        return Err(bad_request(format!(
+            "version_req must not be longer than {MAX_VERSION_REQ_LENGTH} characters"
+        )));
+    }
+    if let Err(error) = semver::VersionReq::parse(version_req) {
+        return Err(bad_request(format!(
+            "invalid version requirement `{version_req}`: {error}"
+        )));
+    }
+
+    let version_req = version_req.to_string();
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        if user.verified_email(conn)?.is_none() {
+            return Err(bad_request(
+                "a verified email address is required to subscribe to new versions",
+            ));
+        }
+
+        let krate: Crate = Crate::by_name(&new.krate)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&new.krate))?;
+
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
+        let count: i64 = DependencySubscription::belonging_to(user)
+            .count()
+            .get_result(conn)?;
+        if count >= MAX_SUBSCRIPTIONS_PER_USER {
+            return Err(bad_request(format!(
+                "maximum subscriptions per user is: {MAX_SUBSCRIPTIONS_PER_USER}"
+            )));
+        }
+
+        let subscription = NewDependencySubscription {
+            user_id: user.id,
+            crate_id: krate.id,
+            version_req: &version_req,
+        }
+        .insert(conn)?;
+
+        let subscription = EncodableDependencySubscription::from(subscription, krate.name);
+
+        Ok(Json(json!({ "subscription": subscription })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /me/subscriptions/:id` route.
+pub async fn delete(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        diesel::delete(dependency_subscriptions::table)
+            .filter(dependency_subscriptions::id.eq(id))
+            .filter(dependency_subscriptions::user_id.eq(user_id))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await
+}
@@ -0,0 +1,121 @@
+//! Endpoints for managing the `User-Agent` throttling policies at runtime
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{
+    AdminAction, NewAdminAuditEntry, NewUserAgentPolicy, ThrottleClass, User, UserAgentPolicy,
+};
+use crate::schema::user_agent_policies;
+use crate::util::diesel::Conn;
+use crate::util::errors::{forbidden, not_found};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum length of a `User-Agent` pattern.
+const MAX_PATTERN_LENGTH: usize = 200;
+
+/// Handles the `GET /api/private/user_agent_policies` route.
+pub async fn list(state: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        authenticate_admin(&req, conn)?;
+
+        let policies = UserAgentPolicy::all(conn)?;
+        Ok(Json(json!({ "policies": policies })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct PolicyUpdate {
+    pattern: String,
+    throttle_class: ThrottleClass,
+}
+
+/// Handles the `PUT /api/private/user_agent_policies` route.
+///
+/// Creates a new policy, or changes the class of the existing policy with
+/// the same pattern. The change is applied to the current instance right
+/// away, and to all other instances on their next refresh.
+pub async fn update(
+    state: AppState,
+    req: Parts,
+    Json(update): Json<PolicyUpdate>,
+) -> AppResult<Json<Value>> {
+    let pattern = update.pattern.trim().to_lowercase();
+    if pattern.is_empty() || pattern.len() > MAX_PATTERN_LENGTH {
+        let detail = format!("`pattern` must be between 1 and {MAX_PATTERN_LENGTH} characters");
+        return Err(bad_request(detail));
+    }
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user = authenticate_admin(&req, conn)?;
+
+        let new_policy = NewUserAgentPolicy {
+            pattern: &pattern,
+            throttle_class: update.throttle_class,
+        };
+        let policy = new_policy.upsert(conn)?;
+
+        warn!(
+            "Admin {} set the throttle class of user agents matching {:?} to {:?}",
+            user.gh_login, policy.pattern, policy.throttle_class
+        );
+
+        NewAdminAuditEntry::by_admin(&user, AdminAction::SetUserAgentPolicy)
+            .details(json!({
+                "pattern": policy.pattern,
+                "throttle_class": policy.throttle_class,
+            }))
+            .insert(conn)?;
+
+        state.user_agent_throttle.refresh(conn)?;
+
+        Ok(Json(json!({ "policy": policy })))
+    })
+    .await
+}
+
+/// Handles the `DELETE /api/private/user_agent_policies/:id` route.
+pub async fn delete(state: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let user = authenticate_admin(&req, conn)?;
+
+        let pattern: String = diesel::delete(user_agent_policies::table.find(id))
+            .returning(user_agent_policies::pattern)
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(not_found)?;
+
+        warn!(
+            "Admin {} removed the policy for user agents matching {pattern:?}",
+            user.gh_login
+        );
+
+        NewAdminAuditEntry::by_admin(&user, AdminAction::DeleteUserAgentPolicy)
+            .details(json!({ "pattern": pattern }))
+            .insert(conn)?;
+
+        state.user_agent_throttle.refresh(conn)?;
+
+        ok_true()
+    })
+    .await
+}
+
+fn authenticate_admin(req: &Parts, conn: &mut impl Conn) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        return Err(forbidden("must be an admin to manage user agent policies"));
+    }
+
+    Ok(user.clone())
+}
@@ -1,5 +1,9 @@
+pub mod attestations;
 pub mod downloads;
+pub mod flag;
 pub mod metadata;
+pub mod repair;
+pub mod reproducibility;
 pub mod yank;
 
 use super::prelude::*;
@@ -0,0 +1,146 @@
+//! Endpoints for the third-party attestations of crate versions
+//!
+//! Approved attestation providers can attach signed statements to versions,
+//! e.g. about a reproducible rebuild, a `cargo vet` audit or the coverage of
+//! a fuzzing campaign. crates.io does not verify the signatures, it only
+//! provides a central place to discover the attestations.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{
+    AttestationKind, AttestationProvider, NewVersionAttestation, VersionAttestation,
+};
+use crate::schema::{attestation_providers, users, version_attestations};
+use crate::util::errors::{forbidden, version_not_found};
+use crate::views::EncodableVersionAttestation;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use url::Url;
+
+use super::version_and_crate;
+
+/// The maximum size of the statement of an attestation, in bytes.
+const MAX_STATEMENT_SIZE: usize = 64 * 1024;
+
+/// The maximum size of the signature of an attestation, in bytes.
+const MAX_SIGNATURE_SIZE: usize = 8 * 1024;
+
+/// Handles the `GET /crates/:crate_id/:version/attestations` route.
+///
+/// Returns the attestations of the version, most recent first.
+pub async fn list(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let attestations: Vec<(VersionAttestation, AttestationProvider, String)> =
+            version_attestations::table
+                .inner_join(attestation_providers::table.inner_join(users::table))
+                .filter(version_attestations::version_id.eq(version.id))
+                .order(version_attestations::id.desc())
+                .select((
+                    VersionAttestation::as_select(),
+                    AttestationProvider::as_select(),
+                    users::gh_login,
+                ))
+                .load(conn)?;
+
+        let attestations = attestations
+            .into_iter()
+            .map(|(attestation, provider, login)| {
+                EncodableVersionAttestation::from(attestation, provider, login)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "attestations": attestations })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct NewAttestation {
+    kind: AttestationKind,
+    statement: String,
+    signature: String,
+    url: Option<String>,
+}
+
+/// Handles the `POST /crates/:crate_id/:version/attestations` route.
+///
+/// Only accounts that were approved as attestation providers by an admin
+/// can submit attestations.
+pub async fn create(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+    Json(attestation): Json<NewAttestation>,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    validate(&attestation)?;
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default().check(&req, conn)?;
+        let user = auth.user();
+
+        let provider = AttestationProvider::find(conn, user.id)?.ok_or_else(|| {
+            forbidden("only approved attestation providers can submit attestations")
+        })?;
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let created = NewVersionAttestation {
+            version_id: version.id,
+            provider_id: provider.user_id,
+            kind: attestation.kind,
+            statement: &attestation.statement,
+            signature: &attestation.signature,
+            url: attestation.url.as_deref(),
+        }
+        .insert(conn)?;
+
+        let created = EncodableVersionAttestation::from(created, provider, user.gh_login.clone());
+        Ok(Json(json!({ "attestation": created })))
+    })
+    .await
+}
+
+fn validate(attestation: &NewAttestation) -> AppResult<()> {
+    if attestation.statement.is_empty() || attestation.statement.len() > MAX_STATEMENT_SIZE {
+        let detail = format!("`statement` must be between 1 and {MAX_STATEMENT_SIZE} bytes");
+        return Err(bad_request(detail));
+    }
+
+    if attestation.signature.is_empty() || attestation.signature.len() > MAX_SIGNATURE_SIZE {
+        let detail = format!("`signature` must be between 1 and {MAX_SIGNATURE_SIZE} bytes");
+        return Err(bad_request(detail));
+    }
+
+    if let Some(url) = &attestation.url {
+        let is_valid = Url::parse(url).is_ok_and(|url| url.scheme() == "https");
+        if !is_valid {
+            return Err(bad_request(format!(
+                "`url` is not a valid https URL: `{url}`"
+            )));
+        }
+    }
+
+    Ok(())
+}
@@ -3,28 +3,329 @@
 //! Crate level functionality is located in `krate::downloads`.
 
 use super::version_and_crate;
+use crate::auth::AuthCheck;
+use crate::controllers::krate::ensure_crate_visible;
 use crate::controllers::prelude::*;
-use crate::models::VersionDownload;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::registry::RequestRegistry;
+use crate::mirrors;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, CrateVisibility, Mirror, Version, VersionDownload};
+use crate::registries::crate_registry;
 use crate::schema::*;
-use crate::util::errors::version_not_found;
+use crate::storage::{crate_file_path, Storage};
+use crate::upstream;
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, custom, internal, version_not_found};
 use crate::views::EncodableVersionDownload;
-use chrono::{Duration, NaiveDate, Utc};
+use axum::body::Body;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use hex::ToHex;
+use sha2::{Digest, Sha256};
 
 /// Handles the `GET /crates/:crate_id/:version/download` route.
 /// This returns a URL to the location where the crate is stored.
+///
+/// If downloads require authentication, the request has to be authenticated
+/// via a session cookie or an API token with the `download` scope, which is
+/// what cargo sends for registries that advertise `auth-required`.
+///
+/// The crate files of private crates are not available via the CDN, so they
+/// are served by this endpoint after the ownership check. The same applies to
+/// all crate files if downloads require authentication, since the CDN can't
+/// check the token.
 pub async fn download(
     app: AppState,
     Path((crate_name, version)): Path<(String, String)>,
     req: Parts,
 ) -> AppResult<Response> {
     let wants_json = req.wants_json();
-    let redirect_url = app.storage.crate_location(&crate_name, &version);
+
+    // Downloads of public crates are redirected without looking up the
+    // crate, unless one of the optional features needs it.
+    let is_private = app.private_crates.is_private(&crate_name);
+    let needs_crate = is_private != Some(false)
+        || app.config.download_auth_required
+        || app.config.mirror_redirects
+        || app.upstream.is_some()
+        || !app.config.registries.is_empty();
+
+    if !needs_crate {
+        let redirect_url = app.storage.crate_location(&crate_name, &version);
+        return Ok(download_response(wants_json, redirect_url));
+    }
+
+    let conn = app.db_read().await?;
+    let target = spawn_blocking({
+        let app = app.clone();
+        move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            download_target(&app, &req, crate_name, &version, conn).map(|target| (target, version))
+        }
+    })
+    .await?;
+
+    // The crate files of pull-through caches are fetched from the upstream
+    // registry on their first download, without holding the connection.
+    let target = match target {
+        (DownloadTarget::Upstream(name), version) => {
+            cache_upstream_crate_file(&app, &name, &version).await?;
+            (public_target(&app, name, &version), version)
+        }
+        target => target,
+    };
+
+    match target {
+        (DownloadTarget::Redirect(redirect_url), _) => {
+            Ok(download_response(wants_json, redirect_url))
+        }
+        (DownloadTarget::Stream { name, .. }, version) if wants_json => {
+            let domain_name = &app.config.domain_name;
+            let url = format!("https://{domain_name}/api/v1/crates/{name}/{version}/download");
+            Ok(download_response(wants_json, url))
+        }
+        (DownloadTarget::Stream { name, visibility }, version) => {
+            stream_crate_file(&app, &name, &version, visibility).await
+        }
+        (DownloadTarget::Upstream(_), _) => unreachable!("upstream crates were cached above"),
+    }
+}
+
+enum DownloadTarget {
+    /// The crate file is redirected to the CDN or a mirror.
+    Redirect(String),
+    /// The crate file of the crate with this name is served by the API from
+    /// the storage area of the given visibility.
+    Stream {
+        name: String,
+        visibility: CrateVisibility,
+    },
+    /// The crate with this name was cached from the upstream registry, and
+    /// its crate file might have to be fetched first.
+    Upstream(String),
+}
+
+fn download_response(wants_json: bool, redirect_url: String) -> Response {
     if wants_json {
-        Ok(Json(json!({ "url": redirect_url })).into_response())
+        Json(json!({ "url": redirect_url })).into_response()
     } else {
-        Ok(redirect(redirect_url))
+        redirect(redirect_url)
+    }
+}
+
+fn download_target(
+    app: &AppState,
+    req: &Parts,
+    crate_name: String,
+    version: &str,
+    conn: &mut impl Conn,
+) -> AppResult<DownloadTarget> {
+    if app.config.download_auth_required {
+        // `AuthCheck` already adds the user and token to the request log,
+        // which attributes the download to them.
+        let auth = AuthCheck::default()
+            .allow_service_token()
+            .with_endpoint_scope(EndpointScope::Download)
+            .for_crate(&crate_name)
+            .check(req, conn)?;
+
+        let method = if auth.api_token().is_some() {
+            "token"
+        } else {
+            "cookie"
+        };
+        req.request_log().add("download_auth", method);
+    }
+
+    // Crate files can only be downloaded via the registry of the crate.
+    // This is skipped for deployments that only serve the default
+    // registry.
+    if !app.config.registries.is_empty() {
+        let registry = crate_registry(conn, &crate_name)?;
+        if registry.is_some_and(|registry| registry != req.registry()) {
+            return Err(crate_not_found(&crate_name));
+        }
+    }
+
+    // Redirects to missing crates are performed unconditionally, but
+    // private crates are only available to their owners.
+    let krate: Option<Crate> = Crate::by_name(&crate_name).first(conn).optional()?;
+    if let Some(krate) = &krate {
+        ensure_crate_visible(app, req, krate, conn)?;
+
+        if krate.visibility == CrateVisibility::Private {
+            return Ok(DownloadTarget::Stream {
+                name: krate.name.clone(),
+                visibility: CrateVisibility::Private,
+            });
+        }
+    }
+
+    let mirror_url = match &krate {
+        // Mirrors serve their files to anyone, so authenticated
+        // downloads are never redirected to them.
+        Some(krate) if app.config.mirror_redirects && !app.config.download_auth_required => {
+            mirror_location(app, req, krate, version, conn)?
+        }
+        _ => None,
+    };
+
+    if krate.is_none() && app.upstream.is_some() {
+        if let Some(name) = upstream::find_cached_crate(conn, &crate_name)? {
+            return Ok(DownloadTarget::Upstream(name));
+        }
+    }
+
+    Ok(match mirror_url {
+        Some(mirror_url) => DownloadTarget::Redirect(mirror_url),
+        None => public_target(app, crate_name, version),
+    })
+}
+
+/// Returns where the crate file of a public crate is downloaded from.
+fn public_target(app: &AppState, crate_name: String, version: &str) -> DownloadTarget {
+    // Redirecting to the CDN would make the crate files available to anyone
+    // who knows or guesses the URL, so they are served by the API instead.
+    if app.config.download_auth_required {
+        return DownloadTarget::Stream {
+            name: crate_name,
+            visibility: CrateVisibility::Public,
+        };
     }
+
+    DownloadTarget::Redirect(app.storage.crate_location(&crate_name, version))
+}
+
+/// Serves a crate file from the storage bucket through the API, for crate
+/// files that must not be available via the CDN.
+async fn stream_crate_file(
+    app: &AppState,
+    name: &str,
+    version: &str,
+    visibility: CrateVisibility,
+) -> AppResult<Response> {
+    let stream = app
+        .storage
+        .stream_crate_file(name, version, visibility)
+        .await
+        .map_err(|e| internal(format!("failed to read crate file: {e}")))?
+        .ok_or_else(|| version_not_found(name, version))?;
+
+    let headers = [
+        (header::CONTENT_TYPE, "application/gzip"),
+        (header::CACHE_CONTROL, "private,no-store"),
+    ];
+    Ok((headers, Body::from_stream(stream)).into_response())
+}
+
+/// Fetches the crate file of a version of a cached upstream crate into the
+/// storage bucket, unless it was cached already.
+async fn cache_upstream_crate_file(app: &AppState, name: &str, version: &str) -> AppResult<()> {
+    let Some(upstream) = app.upstream.as_deref() else {
+        return Ok(());
+    };
+
+    let storage = &app.storage;
+    if crate_file_exists(storage, name, version).await? {
+        return Ok(());
+    }
+
+    // Concurrent downloads of the same crate file wait for the first one to
+    // fetch it, and then find it in the storage bucket.
+    let path = crate_file_path(name, version).to_string();
+    let _guard = app.upstream_fetches.lock(path).await;
+    if crate_file_exists(storage, name, version).await? {
+        return Ok(());
+    }
+
+    // The crate file is verified against the checksum in the cached index
+    // file, which is the checksum that cargo verifies the download with.
+    let index_file = storage
+        .read_index_file(name)
+        .await
+        .map_err(|e| internal(format!("failed to read index file: {e}")))?
+        .unwrap_or_default();
+    let entries = upstream::parse_index_file(&index_file)
+        .map_err(|e| internal(format!("invalid cached index file of `{name}`: {e}")))?;
+    let Some(entry) = entries.into_iter().find(|entry| entry.vers == version) else {
+        return Err(version_not_found(name, version));
+    };
+
+    let max_size = app.config.max_upload_size;
+    let bytes = upstream
+        .crate_file(name, version, max_size)
+        .await
+        .map_err(|error| {
+            warn!("Failed to fetch the crate file of `{name}@{version}` from upstream: {error:#}");
+            custom(
+                StatusCode::BAD_GATEWAY,
+                "the crate file could not be fetched from the upstream registry",
+            )
+        })?;
+
+    let checksum: String = Sha256::digest(&bytes).encode_hex();
+    if checksum != entry.cksum {
+        return Err(custom(
+            StatusCode::BAD_GATEWAY,
+            format!("checksum mismatch of the upstream crate file of `{name}@{version}`"),
+        ));
+    }
+
+    storage
+        .upload_crate_file(name, version, CrateVisibility::Public, bytes)
+        .await
+        .map_err(|e| internal(format!("failed to cache crate file: {e}")))?;
+
+    info!("Cached the crate file of `{name}@{version}` from the upstream registry");
+
+    Ok(())
+}
+
+async fn crate_file_exists(storage: &Storage, name: &str, version: &str) -> AppResult<bool> {
+    let exists = storage.crate_file_exists(name, version).await;
+    exists.map_err(|e| internal(format!("failed to look up crate file: {e}")))
+}
+
+/// Returns the URL of the crate file on a healthy mirror in the region of
+/// the client, if there is one.
+///
+/// Private crates are never redirected to mirrors, and neither are versions
+/// that the mirrors might not have synchronized yet.
+fn mirror_location(
+    app: &AppState,
+    req: &Parts,
+    krate: &Crate,
+    version: &str,
+    conn: &mut impl Conn,
+) -> QueryResult<Option<String>> {
+    if krate.visibility != CrateVisibility::Public {
+        return Ok(None);
+    }
+
+    let Some(region) = mirrors::client_region(&req.headers, &app.config.mirror_country_header)
+    else {
+        return Ok(None);
+    };
+
+    let candidates = Mirror::enabled_in_region(conn, region)?;
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+
+    let published_at: Option<NaiveDateTime> = Version::belonging_to(krate)
+        .filter(versions::num.eq(version))
+        .select(versions::created_at)
+        .first(conn)
+        .optional()?;
+
+    let Some(published_at) = published_at else {
+        return Ok(None);
+    };
+
+    let now = Utc::now().naive_utc();
+    let mirror = mirrors::select(&candidates, &krate.name, published_at, now);
+    Ok(mirror.map(|mirror| mirror.crate_location(&krate.name, version)))
 }
 
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
@@ -41,7 +342,8 @@ pub async fn downloads(
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        let (version, _) = version_and_crate(conn, &crate_name, &version)?;
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&app, &req, &krate, conn)?;
 
         let cutoff_end_date = req
             .query()
@@ -0,0 +1,143 @@
+//! Endpoints for flagging and unflagging specific versions of crates
+//!
+//! Flagging a version attaches a warning for its consumers, e.g. because of
+//! a known bug, without yanking it. In contrast to yanked versions, flagged
+//! versions are still used by dependency resolution, so the flag is not
+//! synced to the index.
+
+use super::version_and_crate;
+use crate::auth::AuthCheck;
+use crate::controllers::cargo_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{
+    insert_version_owner_action, Crate, NewRegistryEvent, RegistryEventKind, Version, VersionAction,
+};
+use crate::permissions::{Capability, Permissions};
+use crate::rate_limiter::LimitedAction;
+use crate::schema::versions;
+use crate::util::diesel::Conn;
+use crate::util::errors::{bad_request, version_not_found};
+use diesel::dsl::now;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+/// The maximum length of the message of a flagged version.
+const MAX_MESSAGE_LENGTH: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct FlagRequest {
+    message: String,
+}
+
+/// Handles the `PUT /crates/:crate_id/:version/flag` route.
+///
+/// Flags the version with the message of the request body, or replaces the
+/// message of an already flagged version.
+pub async fn flag(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+    Json(request): Json<FlagRequest>,
+) -> AppResult<Response> {
+    let message = request.message.trim().to_string();
+    if message.is_empty() {
+        return Err(bad_request("the message must not be empty"));
+    }
+
+    if message.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(bad_request(format!(
+            "the message must not be longer than {MAX_MESSAGE_LENGTH} characters"
+        )));
+    }
+
+    modify_flag(app, crate_name, version, req, Some(message)).await
+}
+
+/// Handles the `DELETE /crates/:crate_id/:version/flag` route.
+pub async fn unflag(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Response> {
+    modify_flag(app, crate_name, version, req, None).await
+}
+
+async fn modify_flag(
+    app: AppState,
+    crate_name: String,
+    version: String,
+    req: Parts,
+    message: Option<String>,
+) -> AppResult<Response> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default()
+            .with_endpoint_scope(EndpointScope::Yank)
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        app.rate_limiter
+            .check_rate_limit(auth.user_id(), LimitedAction::YankUnyank, conn)?;
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        let user = auth.user();
+        let owners = krate.owners(conn)?;
+
+        Permissions::load(&app, user, &krate, &owners, conn)?.ensure(Capability::Yank)?;
+
+        if version.flag_message != message {
+            let reason = message.as_deref();
+            conn.transaction(|conn| {
+                perform_flag(conn, &krate, &version, user.id, auth.api_token_id(), reason)
+            })?;
+        }
+
+        ok_true()
+    })
+    .await
+}
+
+/// Changes the flag of the version, and records the change in the audit log
+/// and registry events.
+fn perform_flag(
+    conn: &mut impl Conn,
+    krate: &Crate,
+    version: &Version,
+    user_id: i32,
+    api_token_id: Option<i32>,
+    message: Option<&str>,
+) -> QueryResult<()> {
+    let (action, event_kind) = match message {
+        Some(message) => {
+            diesel::update(version)
+                .set((
+                    versions::flag_message.eq(message),
+                    versions::flagged_at.eq(now.nullable()),
+                ))
+                .execute(conn)?;
+
+            (VersionAction::Flag, RegistryEventKind::Flag)
+        }
+        None => {
+            diesel::update(version)
+                .set((
+                    versions::flag_message.eq(None::<String>),
+                    versions::flagged_at.eq(None::<chrono::NaiveDateTime>),
+                ))
+                .execute(conn)?;
+
+            (VersionAction::Unflag, RegistryEventKind::Unflag)
+        }
+    };
+
+    insert_version_owner_action(conn, version.id, user_id, api_token_id, action, message)?;
+
+    NewRegistryEvent::version(event_kind, &krate.name, &version.num).insert(conn)?;
+
+    Ok(())
+}
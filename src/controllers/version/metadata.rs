@@ -5,11 +5,14 @@
 //! `Cargo.toml` file.
 
 use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 
-use crate::models::VersionOwnerAction;
+use crate::models::{VersionCiAnnotation, VersionOwnerAction};
+use crate::schema::versions;
 use crate::util::errors::version_not_found;
-use crate::views::{EncodableDependency, EncodableVersion};
+use crate::views::{EncodableDependency, EncodableOutdatedDependency, EncodableVersion};
+use std::collections::HashMap;
 
 use super::version_and_crate;
 
@@ -23,6 +26,7 @@ use super::version_and_crate;
 pub async fn dependencies(
     state: AppState,
     Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
 ) -> AppResult<Json<Value>> {
     if semver::Version::parse(&version).is_err() {
         return Err(version_not_found(&crate_name, &version));
@@ -32,7 +36,9 @@ pub async fn dependencies(
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-        let (version, _) = version_and_crate(conn, &crate_name, &version)?;
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
         let deps = version.dependencies(conn)?;
         let deps = deps
             .into_iter()
@@ -44,6 +50,86 @@ pub async fn dependencies(
     .await
 }
 
+/// Handles the `GET /crates/:crate_id/:version/outdated_deps` route.
+///
+/// Compares the requirement of each dependency with the newest version of
+/// the dependency that matches the requirement, and with the newest version
+/// of the dependency overall. Yanked versions are ignored.
+pub async fn outdated_dependencies(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let deps = version.dependencies(conn)?;
+
+        let crate_ids = deps.iter().map(|(dep, _)| dep.crate_id).collect::<Vec<_>>();
+        let rows: Vec<(i32, String)> = versions::table
+            .filter(versions::crate_id.eq_any(&crate_ids))
+            .filter(versions::yanked.eq(false))
+            .select((versions::crate_id, versions::num))
+            .load(conn)?;
+
+        let mut available: HashMap<i32, Vec<semver::Version>> = HashMap::new();
+        for (crate_id, num) in rows {
+            if let Ok(num) = semver::Version::parse(&num) {
+                available.entry(crate_id).or_default().push(num);
+            }
+        }
+
+        let deps = deps
+            .into_iter()
+            .map(|(dep, name)| {
+                let versions = available.get(&dep.crate_id).map(Vec::as_slice);
+                let versions = versions.unwrap_or_default();
+
+                // Requirements are validated on publish, but older versions
+                // may contain requirements that don't parse anymore.
+                let latest_compatible = semver::VersionReq::parse(&dep.req)
+                    .ok()
+                    .and_then(|req| versions.iter().filter(|v| req.matches(v)).max());
+
+                let latest = versions
+                    .iter()
+                    .filter(|v| v.pre.is_empty())
+                    .max()
+                    .or_else(|| versions.iter().max());
+
+                let outdated = latest.is_some() && latest != latest_compatible;
+
+                EncodableOutdatedDependency {
+                    crate_id: name,
+                    req: dep.req,
+                    kind: dep.kind,
+                    optional: dep.optional,
+                    target: dep.target,
+                    latest_compatible: latest_compatible.map(ToString::to_string),
+                    latest: latest.map(ToString::to_string),
+                    outdated,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let num_outdated = deps.iter().filter(|dep| dep.outdated).count();
+
+        Ok(Json(json!({
+            "dependencies": deps,
+            "meta": { "outdated": num_outdated },
+        })))
+    })
+    .await
+}
+
 /// Handles the `GET /crates/:crate_id/:version/authors` route.
 pub async fn authors() -> Json<Value> {
     // Currently we return the empty list.
@@ -62,6 +148,7 @@ pub async fn authors() -> Json<Value> {
 pub async fn show(
     state: AppState,
     Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
 ) -> AppResult<Json<Value>> {
     if semver::Version::parse(&version).is_err() {
         return Err(version_not_found(&crate_name, &version));
@@ -72,10 +159,22 @@ pub async fn show(
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
         let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
         let published_by = version.published_by(conn);
         let actions = VersionOwnerAction::by_version(conn, &version)?;
 
-        let version = EncodableVersion::from(version, &krate.name, published_by, actions);
+        let ci = VersionCiAnnotation::for_version(conn, version.id)?;
+
+        let mut version = EncodableVersion::from(
+            version,
+            &krate.name,
+            published_by,
+            actions,
+            state.config.avatar_proxy,
+        );
+        version.ci = ci.map(Into::into);
+
         Ok(Json(json!({ "version": version })))
     })
     .await
@@ -0,0 +1,286 @@
+//! Endpoints for repairing corrupted crate tarballs
+//!
+//! If the stored tarball of a version is corrupted, admins can upload a
+//! replacement tarball. The replacement has to contain exactly the same files
+//! as the original tarball, which is verified against the content manifest
+//! that was recorded at publish time. Since the replacement is usually
+//! compressed differently, its checksum changes, and both checksums are
+//! recorded so that users can verify the rotation via a signed advisory.
+
+use super::version_and_crate;
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{
+    AdminAction, ChecksumRotation, NewAdminAuditEntry, NewChecksumRotation, VersionContentManifest,
+    VersionQuarantine,
+};
+use crate::schema::versions;
+use crate::util::errors::{custom, forbidden, not_found, version_not_found};
+use crate::util::Maximums;
+use crate::worker::jobs::{self, InvalidateCrateFile, ScanTarball};
+use axum::extract::Query;
+use base64::{engine::general_purpose, Engine};
+use chrono::NaiveDateTime;
+use crates_io_tarball::{process_tarball, TarballLimits};
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use hex::ToHex;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::Signature;
+use p256::PublicKey;
+use sha2::{Digest, Sha256};
+use tokio::runtime::Handle;
+
+/// The maximum length of the reason for a repair.
+const MAX_REASON_LENGTH: usize = 1000;
+
+/// The maximum number of mismatched paths that are listed in the error
+/// message if a replacement tarball does not match the content manifest.
+const MAX_LISTED_PATHS: usize = 10;
+
+#[derive(Deserialize)]
+pub struct RepairParams {
+    /// Explanation of why the tarball had to be repaired, which is shown in
+    /// the advisory about the checksum rotation.
+    reason: String,
+}
+
+/// Handles the `PUT /api/private/crates/:crate_id/:version/tarball` route.
+///
+/// Replaces the stored tarball of the version with the tarball in the
+/// request body, if it contains the same files as the original tarball. If
+/// the checksum of the replacement differs from the checksum of the version,
+/// the checksum is rotated and the index is updated.
+pub async fn repair(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    Query(params): Query<RepairParams>,
+    req: BytesRequest,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let reason = params.reason.trim().to_string();
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LENGTH {
+        let detail = format!("`reason` must be between 1 and {MAX_REASON_LENGTH} characters");
+        return Err(bad_request(detail));
+    }
+
+    let (req, tarball_bytes) = req.0.into_parts();
+
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+        if !user.is_admin {
+            return Err(forbidden("must be an admin to repair tarballs"));
+        }
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+
+        let maximums = Maximums::new(
+            krate.max_upload_size,
+            app.config.max_upload_size,
+            app.config.max_unpack_size,
+        );
+        if tarball_bytes.len() as u64 > maximums.max_upload_size {
+            return Err(custom(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("max upload size is: {}", maximums.max_upload_size),
+            ));
+        }
+
+        // Uploading the tarball would implicitly lift the quarantine, which
+        // has to go through the review of the tarball scan instead.
+        if VersionQuarantine::is_quarantined(conn, version.id)? {
+            return Err(bad_request("quarantined versions can not be repaired"));
+        }
+
+        let Some(manifest) = VersionContentManifest::for_version(conn, version.id)? else {
+            let detail = "no content manifest was recorded for this version, \
+                so the replacement tarball can not be verified";
+            return Err(bad_request(detail));
+        };
+
+        let pkg_name = format!("{}-{}", krate.name, version.num);
+        let limits = TarballLimits {
+            max_unpack_size: maximums.max_unpack_size,
+            max_files: app.config.max_tarball_files,
+            max_path_depth: app.config.max_tarball_path_depth,
+        };
+        let tarball_info = process_tarball(&pkg_name, &*tarball_bytes, &limits)?;
+
+        let replacement = VersionContentManifest::new(version.id, &tarball_info.file_checksums);
+        let mismatched_paths = manifest.mismatched_paths(&replacement);
+        if !mismatched_paths.is_empty() {
+            let mut paths = mismatched_paths
+                .iter()
+                .take(MAX_LISTED_PATHS)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            if mismatched_paths.len() > MAX_LISTED_PATHS {
+                paths.push_str(", ...");
+            }
+            let detail = format!(
+                "the replacement tarball does not match the content manifest of the version: {paths}"
+            );
+            return Err(bad_request(detail));
+        }
+
+        let old_checksum = version.checksum.clone();
+        let new_checksum: String = Sha256::digest(&tarball_bytes).encode_hex();
+        let rotated = new_checksum != old_checksum;
+
+        conn.transaction(|conn| {
+            if rotated {
+                NewChecksumRotation {
+                    version_id: version.id,
+                    old_checksum: &old_checksum,
+                    new_checksum: &new_checksum,
+                    reason: &reason,
+                }
+                .insert(conn)?;
+
+                diesel::update(versions::table.find(version.id))
+                    .set(versions::checksum.eq(&new_checksum))
+                    .execute(conn)?;
+
+                jobs::enqueue_sync_to_index(&krate.name, conn)?;
+            }
+
+            NewAdminAuditEntry::by_admin(user, AdminAction::RepairTarball)
+                .krate(&krate.name)
+                .details(json!({
+                    "version": version.num,
+                    "old_checksum": old_checksum,
+                    "new_checksum": new_checksum,
+                    "reason": reason,
+                }))
+                .insert(conn)?;
+
+            InvalidateCrateFile::new(krate.name.clone(), version.num.clone()).enqueue(conn)?;
+            if app.config.clamd_address.is_some() {
+                ScanTarball::new(version.id).enqueue(conn)?;
+            }
+
+            let future = app
+                .storage
+                .upload_crate_file(&krate.name, &version.num, krate.visibility, tarball_bytes);
+            Handle::current()
+                .block_on(future)
+                .map_err(|e| server_error(format!("failed to upload tarball: {e}")))?;
+
+            Ok::<_, BoxedAppError>(())
+        })?;
+
+        warn!(
+            "Admin {} repaired the tarball of {}@{} ({old_checksum} -> {new_checksum})",
+            user.gh_login, krate.name, version.num
+        );
+
+        Ok(Json(json!({
+            "old_checksum": old_checksum,
+            "new_checksum": new_checksum,
+            "rotated": rotated,
+        })))
+    })
+    .await
+}
+
+/// The contents of an advisory about the checksum rotations of a version.
+///
+/// The fields are serialized in the order of their declaration, which is
+/// the payload that the signature is computed over.
+#[derive(Serialize)]
+struct ChecksumAdvisory<'a> {
+    #[serde(rename = "crate")]
+    krate: &'a str,
+    version: &'a str,
+    /// The current checksum of the version, as listed in the index.
+    checksum: &'a str,
+    rotations: Vec<AdvisoryRotation>,
+}
+
+#[derive(Serialize)]
+struct AdvisoryRotation {
+    old_checksum: String,
+    new_checksum: String,
+    reason: String,
+    rotated_at: NaiveDateTime,
+}
+
+/// Handles the `GET /api/v1/crates/:crate_id/:version/checksum_advisory`
+/// route.
+///
+/// Returns the checksum rotations of the version, oldest first. The
+/// `advisory` field contains the rotations as a JSON string, and the
+/// `signature` field contains the base64-encoded, DER-encoded ECDSA P-256
+/// signature of that string, which can be verified with the key returned by
+/// [`advisory_key`]. The signature is `null` if no signing key is configured.
+pub async fn advisory(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
+        let rotations = ChecksumRotation::for_version(conn, version.id)?
+            .into_iter()
+            .map(|rotation| AdvisoryRotation {
+                old_checksum: rotation.old_checksum,
+                new_checksum: rotation.new_checksum,
+                reason: rotation.reason,
+                rotated_at: rotation.created_at,
+            })
+            .collect();
+
+        let advisory = ChecksumAdvisory {
+            krate: &krate.name,
+            version: &version.num,
+            checksum: &version.checksum,
+            rotations,
+        };
+        let advisory = serde_json::to_string(&advisory)
+            .map_err(|e| server_error(format!("failed to serialize advisory: {e}")))?;
+
+        let signature = app.config.checksum_advisory_key.as_ref().map(|key| {
+            let signature: Signature = key.sign(advisory.as_bytes());
+            general_purpose::STANDARD.encode(signature.to_der())
+        });
+
+        Ok(Json(json!({
+            "advisory": advisory,
+            "signature": signature,
+        })))
+    })
+    .await
+}
+
+/// Handles the `GET /api/v1/checksum_advisory_key` route.
+///
+/// Returns the PEM-encoded public key that the checksum advisories are signed
+/// with.
+pub async fn advisory_key(app: AppState) -> AppResult<Json<Value>> {
+    let key = app
+        .config
+        .checksum_advisory_key
+        .as_ref()
+        .ok_or_else(not_found)?;
+    let public_key = PublicKey::from(key.verifying_key());
+
+    Ok(Json(json!({ "public_key": public_key.to_string() })))
+}
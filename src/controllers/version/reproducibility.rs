@@ -0,0 +1,139 @@
+//! Endpoints for the reproducibility attestations of crate versions
+//!
+//! Publishers can report the checksum of a normalized tarball (fixed mtimes,
+//! sorted entries) at publish time. Independent rebuilders can then build
+//! the version from source, normalize the resulting tarball the same way,
+//! and report whether their checksum matches.
+
+use crate::auth::{ensure_not_locked, AuthCheck};
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::models::{NewReproducibilityReport, ReproducibilityReport, User};
+use crate::schema::{reproducibility_reports, users};
+use crate::util::errors::version_not_found;
+use crate::views::EncodableReproducibilityReport;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+
+use super::version_and_crate;
+
+/// The maximum length of the free-form `details` of a report.
+const MAX_DETAILS_LENGTH: usize = 1000;
+
+/// Handles the `GET /crates/:crate_id/:version/reproducibility` route.
+///
+/// Returns the normalized checksum that was reported by the publisher, and
+/// the reports of all rebuilders, most recent first.
+pub async fn list(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let reports: Vec<(ReproducibilityReport, User)> = reproducibility_reports::table
+            .inner_join(users::table)
+            .filter(reproducibility_reports::version_id.eq(version.id))
+            .order(reproducibility_reports::created_at.desc())
+            .select((ReproducibilityReport::as_select(), users::all_columns))
+            .load(conn)?;
+
+        let matching = reports.iter().filter(|(report, _)| report.matches).count();
+        let mismatching = reports.len() - matching;
+
+        let reports = reports
+            .into_iter()
+            .map(|(report, reporter)| {
+                EncodableReproducibilityReport::from(report, reporter, state.config.avatar_proxy)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "normalized_checksum": version.normalized_checksum,
+            "reports": reports,
+            "meta": { "matching": matching, "mismatching": mismatching },
+        })))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct NewReport {
+    /// The checksum of the normalized tarball that the rebuilder produced.
+    checksum: String,
+    details: Option<String>,
+}
+
+/// Handles the `PUT /crates/:crate_id/:version/reproducibility` route.
+///
+/// Records whether the checksum that the authenticated rebuilder produced
+/// matches the normalized checksum of the version. Every user can submit one
+/// report per version, and submitting another one replaces it.
+pub async fn report(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+    Json(new_report): Json<NewReport>,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let NewReport { checksum, details } = new_report;
+    if !ReproducibilityReport::valid_checksum(&checksum) {
+        return Err(bad_request(
+            "invalid `checksum` value: expected a hex-encoded SHA256 checksum",
+        ));
+    }
+
+    let details = details.filter(|details| !details.is_empty());
+    if details
+        .as_ref()
+        .is_some_and(|details| details.len() > MAX_DETAILS_LENGTH)
+    {
+        return Err(bad_request(format!(
+            "The `details` are too long. A maximum of {MAX_DETAILS_LENGTH} characters are currently allowed."
+        )));
+    }
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let auth = AuthCheck::default().check(&req, conn)?;
+        let user = auth.user();
+        ensure_not_locked(user)?;
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&state, &req, &krate, conn)?;
+
+        let Some(normalized_checksum) = &version.normalized_checksum else {
+            return Err(bad_request(format!(
+                "crate `{crate_name}` version `{}` does not have a normalized checksum",
+                version.num
+            )));
+        };
+
+        let report = NewReproducibilityReport {
+            version_id: version.id,
+            reporter_id: user.id,
+            checksum: &checksum,
+            matches: checksum == *normalized_checksum,
+            details: details.as_deref(),
+        }
+        .upsert(conn)?;
+
+        let report =
+            EncodableReproducibilityReport::from(report, user.clone(), state.config.avatar_proxy);
+        Ok(Json(json!({ "report": report })))
+    })
+    .await
+}
@@ -1,20 +1,32 @@
 //! Endpoints for yanking and unyanking specific versions of crates
 
 use super::version_and_crate;
-use crate::auth::AuthCheck;
+use crate::auth::{ensure_not_locked, AuthCheck};
 use crate::controllers::cargo_prelude::*;
+use crate::controllers::krate::ensure_crate_visible;
+use crate::email::{Email, Notification};
 use crate::models::token::EndpointScope;
-use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::models::{
+    set_yanked, AdminAction, Crate, CriticalCrate, NewAdminAuditEntry, NewPendingYank,
+    NotificationClass, OwnerKind, PendingYank, Rights, User, Version, VersionOwnerAction,
+    VersionQuarantine, YankActor,
+};
+use crate::permissions::{Capability, Permissions};
 use crate::rate_limiter::LimitedAction;
-use crate::schema::versions;
-use crate::util::errors::{custom, version_not_found};
-use crate::worker::jobs;
-use crate::worker::jobs::UpdateDefaultVersion;
-use crates_io_worker::BackgroundJob;
+use crate::schema::{api_tokens, crate_owners, crates, users, version_downloads, versions};
+use crate::util::diesel::Conn;
+use crate::util::errors::{bad_request, custom, version_not_found};
+use crate::views::EncodableYankHistoryEntry;
+use chrono::Utc;
+use diesel::dsl::{date, now, sum, IntervalDsl};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use secrecy::ExposeSecret;
 use tokio::runtime::Handle;
 
+/// The maximum length of the reason that can be provided when yanking or
+/// unyanking a version.
+const MAX_REASON_LENGTH: usize = 256;
+
 /// Handles the `DELETE /crates/:crate_id/:version/yank` route.
 /// This does not delete a crate version, it makes the crate
 /// version accessible only to crates that already have a
@@ -24,6 +36,9 @@ use tokio::runtime::Handle;
 /// Crate deletion is not implemented to avoid breaking builds,
 /// and the goal of yanking a crate is to prevent crates
 /// beginning to depend on the yanked crate version.
+///
+/// An optional `reason` query parameter is recorded in the yank history of
+/// the version.
 pub async fn yank(
     app: AppState,
     Path((crate_name, version)): Path<(String, String)>,
@@ -56,6 +71,21 @@ async fn modify_yank(
         return Err(version_not_found(&crate_name, &version));
     }
 
+    let reason = req
+        .query()
+        .swap_remove("reason")
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+
+    if reason
+        .as_ref()
+        .is_some_and(|r| r.chars().count() > MAX_REASON_LENGTH)
+    {
+        return Err(bad_request(format!(
+            "the reason must not be longer than {MAX_REASON_LENGTH} characters"
+        )));
+    }
+
     let conn = state.db_write().await?;
     spawn_blocking(move || {
         let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
@@ -74,19 +104,21 @@ async fn modify_yank(
         let user = auth.user();
         let owners = krate.owners(conn)?;
 
-        if Handle::current().block_on(user.rights(&state, &owners))? < Rights::Publish {
-            if user.is_admin {
-                let action = if yanked { "yanking" } else { "unyanking" };
-                warn!(
-                    "Admin {} is {action} {}@{}",
-                    user.gh_login, krate.name, version.num
-                );
-            } else {
-                return Err(custom(
-                    StatusCode::FORBIDDEN,
-                    "must already be an owner to yank or unyank",
-                ));
-            }
+        let permissions = Permissions::load(&state, user, &krate, &owners, conn)?;
+        permissions.ensure(Capability::Yank)?;
+        if !permissions.is_owner() {
+            let action = if yanked { "yanking" } else { "unyanking" };
+            warn!(
+                "Admin {} is {action} {}@{}",
+                user.gh_login, krate.name, version.num
+            );
+        }
+
+        if !yanked && VersionQuarantine::is_quarantined(conn, version.id)? {
+            return Err(custom(
+                StatusCode::FORBIDDEN,
+                "this version is quarantined and can not be unyanked",
+            ));
         }
 
         if version.yanked == yanked {
@@ -94,23 +126,316 @@ async fn modify_yank(
             return ok_true();
         }
 
-        diesel::update(&version)
-            .set(versions::yanked.eq(yanked))
-            .execute(conn)?;
+        if !user.is_admin {
+            if let Some(cooldown) = state.config.yank_cooldown {
+                ensure_cooldown_expired(conn, &krate, &version, cooldown)?;
+            }
+        }
+
+        // Yanking heavily downloaded versions has to be confirmed by one of
+        // the owners, unless an admin is yanking it (e.g. because of malware).
+        // Critical crates use a lower threshold.
+        if yanked && !user.is_admin {
+            let threshold = match CriticalCrate::is_critical(conn, krate.id)? {
+                true => Some(state.config.critical_yank_confirmation_downloads),
+                false => state.config.yank_confirmation_downloads,
+            };
+            if let Some(threshold) = threshold {
+                let downloads = recent_downloads(conn, version.id)?;
+                if downloads > threshold {
+                    return hold_yank(
+                        &state,
+                        conn,
+                        &krate,
+                        &version,
+                        user,
+                        api_token_id,
+                        reason.as_deref(),
+                        downloads,
+                    );
+                }
+            }
+        }
 
-        let action = if yanked {
-            VersionAction::Yank
-        } else {
-            VersionAction::Unyank
+        let reason = reason.as_deref();
+        let actor = YankActor::User {
+            user_id: user.id,
+            api_token_id,
         };
+        set_yanked(
+            conn,
+            krate.id,
+            &krate.name,
+            &[version.id],
+            yanked,
+            actor,
+            reason,
+        )?;
 
-        insert_version_owner_action(conn, version.id, user.id, api_token_id, action)?;
+        if !permissions.is_owner() {
+            let action = match yanked {
+                true => AdminAction::YankVersion,
+                false => AdminAction::UnyankVersion,
+            };
+            NewAdminAuditEntry::by_admin(user, action)
+                .krate(&krate.name)
+                .details(json!({ "version": version.num, "reason": reason }))
+                .insert(conn)?;
+        }
 
-        jobs::enqueue_sync_to_index(&krate.name, conn)?;
+        ok_true()
+    })
+    .await
+}
 
-        UpdateDefaultVersion::new(krate.id).enqueue(conn)?;
+/// Checks that the yanked state of the version has not been changed within
+/// the configured cooldown period, since frequent changes would poison the
+/// caches of dependency resolvers.
+fn ensure_cooldown_expired(
+    conn: &mut impl Conn,
+    krate: &Crate,
+    version: &Version,
+    cooldown: std::time::Duration,
+) -> AppResult<()> {
+    let Some(last_change) = VersionOwnerAction::last_yank_time(conn, version.id)? else {
+        return Ok(());
+    };
 
-        ok_true()
+    let cooldown = chrono::Duration::from_std(cooldown).unwrap_or(chrono::Duration::MAX);
+    let Some(retry_after) = last_change.checked_add_signed(cooldown) else {
+        return Ok(());
+    };
+
+    if retry_after <= Utc::now().naive_utc() {
+        return Ok(());
+    }
+
+    let detail = format!(
+        "{} v{} was yanked or unyanked recently. To avoid confusing the caches \
+         of dependency resolvers, please try again after {} UTC.",
+        krate.name,
+        version.num,
+        retry_after.format("%Y-%m-%d %H:%M:%S"),
+    );
+
+    Err(custom(StatusCode::TOO_MANY_REQUESTS, detail))
+}
+
+/// Returns the number of downloads of the version within the last 90 days.
+fn recent_downloads(conn: &mut impl Conn, version_id: i32) -> QueryResult<i64> {
+    version_downloads::table
+        .filter(version_downloads::version_id.eq(version_id))
+        .filter(version_downloads::date.gt(date(now - 90.days())))
+        .select(sum(version_downloads::downloads))
+        .get_result::<Option<i64>>(conn)
+        .map(Option::unwrap_or_default)
+}
+
+/// Stores the yank in the `pending_yanks` table and sends an email with a
+/// confirmation link to all owners of the crate.
+///
+/// The yank is reported as an error, so that cargo shows the message to the
+/// user instead of claiming that the version was yanked.
+fn hold_yank(
+    app: &AppState,
+    conn: &mut impl Conn,
+    krate: &Crate,
+    version: &Version,
+    user: &User,
+    api_token_id: Option<i32>,
+    reason: Option<&str>,
+    downloads: i64,
+) -> AppResult<Response> {
+    let owners: Vec<(i32, String)> = crate_owners::table
+        .inner_join(users::table)
+        .filter(crate_owners::crate_id.eq(krate.id))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .select((users::id, users::gh_login))
+        .load(conn)?;
+
+    let pending = NewPendingYank {
+        version_id: version.id,
+        user_id: user.id,
+        api_token_id,
+        reason,
+    };
+
+    conn.transaction(|conn| {
+        let token = pending.insert(conn)?;
+
+        let mut num_recipients = 0;
+        for (owner_id, owner_name) in &owners {
+            let email = YankConfirmationEmail {
+                user_name: owner_name,
+                requested_by: &user.gh_login,
+                domain: &app.emails.domain,
+                crate_name: &krate.name,
+                version: &version.num,
+                downloads,
+                token: token.expose_secret(),
+            };
+
+            match app.emails.send_notification(*owner_id, email, conn) {
+                Ok(Some(_)) => num_recipients += 1,
+                Ok(None) => {}
+                Err(error) => warn!(?error, "Failed to send yank confirmation to {owner_name}"),
+            }
+        }
+
+        if num_recipients == 0 {
+            return Err(bad_request(
+                "None of the owners of this crate has a verified email address \
+                 that the yank confirmation could be sent to.",
+            ));
+        }
+
+        Ok(())
+    })?;
+
+    let message = format!(
+        "Yanking {} v{} requires confirmation, since it was downloaded {downloads} \
+         times within the last 90 days. A confirmation link was sent to the owners \
+         of the crate. The link expires in 24 hours.",
+        krate.name, version.num,
+    );
+
+    Err(custom(StatusCode::FORBIDDEN, message))
+}
+
+/// Handles the `PUT /confirm_yank/:token` route.
+///
+/// Completes a yank that was held back by [`hold_yank()`] on behalf of the
+/// user that originally requested it.
+pub async fn confirm_yank(app: AppState, Path(token): Path<String>) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        conn.transaction(|conn| {
+            let pending = PendingYank::take_by_token(conn, &token)?
+                .ok_or_else(|| bad_request("Pending yank belonging to token not found."))?;
+
+            if let Some(api_token_id) = pending.api_token_id {
+                let revoked: bool = api_tokens::table
+                    .find(api_token_id)
+                    .select(api_tokens::revoked)
+                    .get_result(conn)?;
+
+                if revoked {
+                    return Err(bad_request(
+                        "The API token used for this yank has been revoked.",
+                    ));
+                }
+            }
+
+            let user = User::find(conn, pending.user_id)?;
+            ensure_not_locked(&user)?;
+
+            let version: Version = versions::table.find(pending.version_id).first(conn)?;
+            let krate: Crate = crates::table
+                .find(version.crate_id)
+                .select(Crate::as_select())
+                .first(conn)?;
+
+            let owners = krate.owners(conn)?;
+            if Handle::current().block_on(user.rights(&app, &owners))? < Rights::Publish {
+                return Err(custom(
+                    StatusCode::FORBIDDEN,
+                    "the user that requested the yank is no longer an owner of the crate",
+                ));
+            }
+
+            if !version.yanked {
+                let actor = YankActor::User {
+                    user_id: user.id,
+                    api_token_id: pending.api_token_id,
+                };
+                let reason = pending.reason.as_deref();
+                set_yanked(
+                    conn,
+                    krate.id,
+                    &krate.name,
+                    &[version.id],
+                    true,
+                    actor,
+                    reason,
+                )?;
+            }
+
+            ok_true()
+        })
     })
     .await
 }
+
+/// Handles the `GET /crates/:crate_id/:version/yank_history` route.
+pub async fn yank_history(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = app.db_read().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        ensure_crate_visible(&app, &req, &krate, conn)?;
+
+        let history = VersionOwnerAction::yank_history(conn, version.id)?
+            .into_iter()
+            .map(|(action, user, team)| {
+                EncodableYankHistoryEntry::from(action, user, team, app.config.avatar_proxy)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "yank_history": history })))
+    })
+    .await
+}
+
+struct YankConfirmationEmail<'a> {
+    user_name: &'a str,
+    requested_by: &'a str,
+    domain: &'a str,
+    crate_name: &'a str,
+    version: &'a str,
+    downloads: i64,
+    token: &'a str,
+}
+
+impl Email for YankConfirmationEmail<'_> {
+    const SUBJECT: &'static str = "Please confirm the yank of your crate";
+
+    fn body(&self) -> String {
+        format!(
+            "Hello {user_name}!
+
+{requested_by} requested to yank version {version} of the crate {crate_name}. \
+Since this version was downloaded {downloads} times within the last 90 days, \
+the yank will only be completed after one of the owners of the crate clicks \
+the link below:
+
+https://{domain}/confirm-yank/{token}
+
+The link expires in 24 hours. If you don't want this version to be yanked, \
+please do not click the link, and check the API tokens of the owners of the \
+crate instead.",
+            user_name = self.user_name,
+            requested_by = self.requested_by,
+            domain = self.domain,
+            crate_name = self.crate_name,
+            version = self.version,
+            downloads = self.downloads,
+            token = self.token,
+        )
+    }
+}
+
+impl Notification for YankConfirmationEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Publishing;
+}
@@ -0,0 +1,143 @@
+//! Deprecation of API endpoints and query parameters.
+//!
+//! Responses of deprecated surfaces contain the following headers, so that
+//! API clients can detect the deprecation automatically:
+//!
+//! - `Deprecation` (RFC 9745) with the time when the surface was deprecated.
+//! - `Sunset` (RFC 8594) with the time when the surface will be removed, if
+//!   that has been decided already.
+//! - `Link` with the announcement of the deprecation.
+//!
+//! The requests to deprecated surfaces are counted in the
+//! `deprecated_requests_total` metric. Requests that are authenticated with
+//! an API token are additionally recorded in the `deprecated_api_usage`
+//! table, so that admins can contact the owners of the tokens before a
+//! surface is removed.
+//!
+//! See [`crate::middleware`] for where this is enforced.
+
+use chrono::{DateTime, TimeZone, Utc};
+use http::header::{HeaderName, HeaderValue, LINK};
+use http::HeaderMap;
+
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// A deprecated route, or a deprecated query parameter of a route.
+#[derive(Debug, Clone)]
+pub struct Deprecation {
+    /// The route pattern, e.g. `/api/v1/crates/:crate_id/:version/authors`.
+    pub route: String,
+    /// The deprecated query parameter, or `None` if the whole route is
+    /// deprecated.
+    pub param: Option<String>,
+    pub deprecated_at: DateTime<Utc>,
+    /// When the surface will be removed, if that has been decided already.
+    pub sunset_at: Option<DateTime<Utc>>,
+    /// The announcement of the deprecation, including the alternatives.
+    pub link: String,
+}
+
+impl Deprecation {
+    /// Identifies the surface in metrics and usage reports, e.g.
+    /// `/api/v1/crates?letter` for the `letter` parameter of the
+    /// `/api/v1/crates` route.
+    pub fn surface(&self) -> String {
+        match &self.param {
+            Some(param) => format!("{}?{param}", self.route),
+            None => self.route.clone(),
+        }
+    }
+
+    /// Returns whether a request for the given route pattern and query
+    /// string uses the deprecated surface.
+    pub fn matches(&self, route: &str, query: Option<&str>) -> bool {
+        if self.route != route {
+            return false;
+        }
+
+        let Some(param) = &self.param else {
+            return true;
+        };
+
+        let query = query.unwrap_or_default().as_bytes();
+        url::form_urlencoded::parse(query).any(|(key, _)| key == param.as_str())
+    }
+
+    pub fn add_headers(&self, headers: &mut HeaderMap) {
+        let deprecated_at = format!("@{}", self.deprecated_at.timestamp());
+        if let Ok(value) = HeaderValue::try_from(deprecated_at) {
+            headers.insert(DEPRECATION.clone(), value);
+        }
+
+        if let Some(sunset_at) = self.sunset_at {
+            let sunset_at = sunset_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            if let Ok(value) = HeaderValue::try_from(sunset_at) {
+                headers.insert(SUNSET.clone(), value);
+            }
+        }
+
+        let link = format!(r#"<{}>; rel="deprecation"; type="text/html""#, self.link);
+        if let Ok(value) = HeaderValue::try_from(link) {
+            headers.append(LINK, value);
+        }
+    }
+}
+
+/// The deprecated surfaces of the API.
+///
+/// Deprecations are documented here rather than in the configuration, so
+/// that the list is public and reviewed like any other API change.
+pub fn default_deprecations() -> Vec<Deprecation> {
+    vec![Deprecation {
+        route: "/api/v1/crates/:crate_id/:version/authors".into(),
+        param: None,
+        // The endpoint only returns empty lists since RFC 3052 was
+        // implemented.
+        deprecated_at: Utc.with_ymd_and_hms(2021, 3, 22, 0, 0, 0).unwrap(),
+        sunset_at: None,
+        link: "https://github.com/rust-lang/rfcs/pull/3052".into(),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deprecation(param: Option<&str>) -> Deprecation {
+        Deprecation {
+            route: "/api/v1/crates".into(),
+            param: param.map(Into::into),
+            deprecated_at: Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap(),
+            sunset_at: Some(Utc.with_ymd_and_hms(2025, 12, 1, 12, 30, 0).unwrap()),
+            link: "https://example.com/deprecation".into(),
+        }
+    }
+
+    #[test]
+    fn matches_routes_and_params() {
+        let route = deprecation(None);
+        assert!(route.matches("/api/v1/crates", None));
+        assert!(route.matches("/api/v1/crates", Some("q=foo")));
+        assert!(!route.matches("/api/v1/crates/:crate_id", None));
+
+        let param = deprecation(Some("letter"));
+        assert!(param.matches("/api/v1/crates", Some("q=foo&letter=a")));
+        assert!(!param.matches("/api/v1/crates", Some("q=letter")));
+        assert!(!param.matches("/api/v1/crates", None));
+        assert_eq!(param.surface(), "/api/v1/crates?letter");
+    }
+
+    #[test]
+    fn headers() {
+        let mut headers = HeaderMap::new();
+        deprecation(None).add_headers(&mut headers);
+
+        assert_eq!(headers["deprecation"], "@1748736000");
+        assert_eq!(headers["sunset"], "Mon, 01 Dec 2025 12:30:00 GMT");
+        assert_eq!(
+            headers["link"],
+            r#"<https://example.com/deprecation>; rel="deprecation"; type="text/html""#
+        );
+    }
+}
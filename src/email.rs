@@ -1,4 +1,6 @@
 use crate::config;
+use crate::models::{self, NotificationClass};
+use crate::util::diesel::Conn;
 use crate::Env;
 use lettre::address::Envelope;
 use lettre::message::header::ContentType;
@@ -15,6 +17,13 @@ pub trait Email {
     fn body(&self) -> String;
 }
 
+/// An email that notifies a user about activity related to their account or
+/// crates. Users can choose which of their addresses receives each class of
+/// notifications.
+pub trait Notification: Email {
+    const CLASS: NotificationClass;
+}
+
 #[derive(Debug, Clone)]
 pub struct Emails {
     backend: EmailBackend,
@@ -83,7 +92,31 @@ impl Emails {
         }
     }
 
-    pub fn send<E: Email>(&self, recipient: &str, email: E) -> Result<(), EmailError> {
+    /// Sends the email, unless the email provider reported the recipient as
+    /// undeliverable (see `src/controllers/email_events.rs`), in which case
+    /// the email is skipped.
+    pub fn send<E: Email>(
+        &self,
+        conn: &mut impl Conn,
+        recipient: &str,
+        email: E,
+    ) -> Result<(), EmailError> {
+        if models::Email::is_undeliverable(conn, recipient)? {
+            info!(
+                "Skipping email \"{}\" to an undeliverable address",
+                E::SUBJECT
+            );
+            return Ok(());
+        }
+
+        self.send_confirmation(recipient, email)
+    }
+
+    /// Sends the email, even if the recipient was reported as undeliverable.
+    ///
+    /// This is only meant for the emails that confirm an address, since
+    /// confirming an address clears its undeliverable state.
+    pub fn send_confirmation<E: Email>(&self, recipient: &str, email: E) -> Result<(), EmailError> {
         // The message ID is normally generated by the SMTP server, but if we let it generate the
         // ID there will be no way for the crates.io application to know the ID of the message it
         // just sent, as it's not included in the SMTP response.
@@ -110,6 +143,25 @@ impl Emails {
 
         self.backend.send(email).map_err(EmailError::TransportError)
     }
+
+    /// Sends the notification to the address that the user selected for its
+    /// class, or to their primary address if they did not select one.
+    ///
+    /// Only verified addresses are considered. Returns the address that the
+    /// notification was sent to, or `None` if the user has no suitable address.
+    pub fn send_notification<N: Notification>(
+        &self,
+        user_id: i32,
+        notification: N,
+        conn: &mut impl Conn,
+    ) -> Result<Option<String>, EmailError> {
+        let Some(recipient) = models::Email::find_recipient(conn, user_id, N::CLASS)? else {
+            return Ok(None);
+        };
+
+        self.send(conn, &recipient, notification)?;
+        Ok(Some(recipient))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -120,6 +172,8 @@ pub enum EmailError {
     MessageBuilderError(#[from] lettre::error::Error),
     #[error(transparent)]
     TransportError(anyhow::Error),
+    #[error(transparent)]
+    DatabaseError(#[from] diesel::result::Error),
 }
 
 #[derive(Debug, Clone)]
@@ -171,7 +225,7 @@ mod tests {
     fn sending_to_invalid_email_fails() {
         let emails = Emails::new_in_memory();
 
-        assert_err!(emails.send(
+        assert_err!(emails.send_confirmation(
             "String.Format(\"{0}.{1}@live.com\", FirstName, LastName)",
             TestEmail
         ));
@@ -181,6 +235,6 @@ mod tests {
     fn sending_to_valid_email_succeeds() {
         let emails = Emails::new_in_memory();
 
-        assert_ok!(emails.send("someone@example.com", TestEmail));
+        assert_ok!(emails.send_confirmation("someone@example.com", TestEmail));
     }
 }
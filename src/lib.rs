@@ -23,34 +23,53 @@ use std::sync::Arc;
 
 use crate::app::AppState;
 use crate::router::build_axum_router;
+use axum::middleware::from_fn_with_state;
 use tikv_jemallocator::Jemalloc;
 
 #[global_allocator]
 static ALLOC: Jemalloc = Jemalloc;
 
 pub mod admin;
+pub mod advisories;
+pub mod antivirus;
+pub mod api_quota;
 mod app;
 pub mod auth;
+pub mod avatars;
 pub mod boot;
 pub mod certs;
+pub mod challenge;
+pub mod changes_feed;
 pub mod ci;
 pub mod cloudfront;
 pub mod config;
 pub mod controllers;
 pub mod db;
+pub mod deprecation;
 pub mod email;
 pub mod external_urls;
 pub mod fastly;
 pub mod headers;
 mod licenses;
+pub mod load_shedding;
 pub mod metrics;
 pub mod middleware;
+pub mod mirrors;
 pub mod models;
+pub mod permissions;
+pub mod private_crates;
+pub mod publish_queue;
 pub mod rate_limiter;
+pub mod readme_images;
 mod real_ip;
+pub mod registries;
+pub mod request_limits;
+pub mod retention;
 mod router;
 pub mod schema;
 pub mod sentry;
+pub mod shutdown;
+pub mod spam;
 pub mod sql;
 pub mod sqs;
 pub mod ssh;
@@ -59,6 +78,8 @@ pub mod tasks;
 pub mod team_repo;
 mod test_util;
 pub mod typosquat;
+pub mod upstream;
+pub mod user_agent_throttle;
 pub mod util;
 pub mod views;
 pub mod worker;
@@ -83,5 +104,16 @@ pub fn build_handler(app: Arc<App>) -> axum::Router {
     let state = AppState(app);
 
     let axum_router = build_axum_router(state.clone());
-    middleware::apply_axum_middleware(state, axum_router)
+    let axum_router = middleware::apply_axum_middleware(state.clone(), axum_router);
+
+    if state.config.registries.is_empty() {
+        return axum_router;
+    }
+
+    // The registry has to be selected before the request is routed, since
+    // the path prefix of the registry is removed from the request path.
+    let select_registry = from_fn_with_state(state, middleware::registry::select_registry);
+    axum::Router::new()
+        .fallback_service(axum_router)
+        .layer(select_registry)
 }
@@ -0,0 +1,267 @@
+//! Shedding of low priority traffic while the instance is overloaded.
+//!
+//! Every route has a [`Priority`]. While the number of in-flight requests or
+//! the lag of the tokio event loop exceed the configured limits, requests are
+//! rejected with a `503 Service Unavailable` response, starting with the
+//! lowest priority:
+//!
+//! - [`Priority::Low`] requests (e.g. search and download statistics, and all
+//!   requests of anonymous crawlers) are shed once half of the in-flight limit
+//!   is reached, or once the lag exceeds its limit.
+//! - [`Priority::Normal`] requests are shed once the in-flight limit is
+//!   reached, or once the lag exceeds twice its limit.
+//! - [`Priority::Critical`] requests (downloads and publishes) are never shed.
+//!
+//! See [`crate::middleware`] for where this is enforced.
+
+use crate::App;
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_RETRY_AFTER_SECONDS: u64 = 30;
+
+/// How often the lag of the event loop is measured.
+const LAG_PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::Critical => "critical",
+        }
+    }
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Priority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "critical" => Ok(Priority::Critical),
+            _ => Err(anyhow!("unknown priority `{s}`")),
+        }
+    }
+}
+
+/// Parses a `<route>=<priority>` pair of the `LOAD_SHEDDING_ROUTE_PRIORITIES`
+/// environment variable.
+pub fn parse_route_priority(s: &str) -> anyhow::Result<(String, Priority)> {
+    let (route, priority) = s
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("expected `<route>=<priority>`"))?;
+
+    Ok((route.to_string(), priority.parse()?))
+}
+
+/// The priorities of the routes that are not [`Priority::Normal`], unless
+/// they are overridden by the configuration.
+pub fn default_route_priorities() -> HashMap<String, Priority> {
+    [
+        ("/api/v1/crates/new", Priority::Critical),
+        ("/api/v1/crates/batch", Priority::Critical),
+        (
+            "/api/v1/crates/:crate_id/:version/download",
+            Priority::Critical,
+        ),
+        ("/api/v1/crates", Priority::Low),
+        ("/api/v1/summary", Priority::Low),
+        ("/api/v1/crates/:crate_id/downloads", Priority::Low),
+        ("/api/v1/crates/:crate_id/downloads/regions", Priority::Low),
+        ("/api/v1/crates/:crate_id/downloads/summary", Priority::Low),
+        ("/api/v1/crates/:crate_id/versions/export", Priority::Low),
+        ("/api/v1/crates/:crate_id/:version/downloads", Priority::Low),
+    ]
+    .into_iter()
+    .map(|(route, priority)| (route.to_string(), priority))
+    .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadSheddingConfig {
+    /// The number of in-flight requests at which normal priority requests
+    /// are shed. Low priority requests are shed at half of this number.
+    pub max_in_flight: Option<usize>,
+    /// The event loop lag at which low priority requests are shed. Normal
+    /// priority requests are shed at twice this lag.
+    pub max_lag: Option<Duration>,
+    /// The priorities of the route patterns (e.g. `/api/v1/summary`). Routes
+    /// that are not listed have [`Priority::Normal`].
+    pub route_priorities: HashMap<String, Priority>,
+    /// The value of the `Retry-After` header of shed requests.
+    pub retry_after: Duration,
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: None,
+            max_lag: None,
+            route_priorities: default_route_priorities(),
+            retry_after: Duration::from_secs(DEFAULT_RETRY_AFTER_SECONDS),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadShedder {
+    config: LoadSheddingConfig,
+    /// The most recently measured event loop lag, in microseconds.
+    lag_micros: AtomicU64,
+}
+
+impl LoadShedder {
+    pub fn new(config: LoadSheddingConfig) -> Self {
+        Self {
+            config,
+            lag_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the priority of a request for the given route pattern.
+    ///
+    /// Requests of crawlers are downgraded to [`Priority::Low`], unless the
+    /// route is critical.
+    pub fn priority(&self, route: Option<&str>, is_crawler: bool) -> Priority {
+        let priority = route
+            .and_then(|route| self.config.route_priorities.get(route))
+            .copied()
+            .unwrap_or(Priority::Normal);
+
+        match priority {
+            Priority::Normal if is_crawler => Priority::Low,
+            priority => priority,
+        }
+    }
+
+    /// Returns whether a request of the given priority has to be rejected,
+    /// given the number of in-flight requests including this one.
+    pub fn should_shed(&self, priority: Priority, in_flight: usize) -> bool {
+        let (max_in_flight, max_lag) = match priority {
+            Priority::Critical => return false,
+            Priority::Normal => (
+                self.config.max_in_flight,
+                self.config.max_lag.map(|l| l * 2),
+            ),
+            Priority::Low => (
+                self.config.max_in_flight.map(|max| max / 2),
+                self.config.max_lag,
+            ),
+        };
+
+        let lag = self.lag();
+        max_in_flight.is_some_and(|max| in_flight > max) || max_lag.is_some_and(|max| lag > max)
+    }
+
+    pub fn retry_after(&self) -> Duration {
+        self.config.retry_after
+    }
+
+    pub fn record_lag(&self, lag: Duration) {
+        let micros = u64::try_from(lag.as_micros()).unwrap_or(u64::MAX);
+        self.lag_micros.store(micros, Ordering::Relaxed);
+    }
+
+    /// Returns the most recently measured event loop lag.
+    pub fn lag(&self) -> Duration {
+        Duration::from_micros(self.lag_micros.load(Ordering::Relaxed))
+    }
+}
+
+/// Measures how much later than requested a sleeping task is woken up, which
+/// indicates how busy the event loop is.
+///
+/// This function never returns and is meant to be spawned as a task.
+pub async fn monitor_event_loop_lag(app: Arc<App>) {
+    loop {
+        let start = Instant::now();
+        tokio::time::sleep(LAG_PROBE_INTERVAL).await;
+
+        let lag = start.elapsed().saturating_sub(LAG_PROBE_INTERVAL);
+        app.load_shedder.record_lag(lag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok_eq};
+
+    fn shedder(max_in_flight: Option<usize>, max_lag: Option<Duration>) -> LoadShedder {
+        LoadShedder::new(LoadSheddingConfig {
+            max_in_flight,
+            max_lag,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn parse_route_priorities() {
+        assert_ok_eq!(
+            parse_route_priority("/api/v1/summary=critical"),
+            ("/api/v1/summary".to_string(), Priority::Critical)
+        );
+        assert_err!(parse_route_priority("/api/v1/summary"));
+        assert_err!(parse_route_priority("/api/v1/summary=urgent"));
+    }
+
+    #[test]
+    fn crawlers_are_downgraded_unless_critical() {
+        let shedder = shedder(None, None);
+
+        let search = Some("/api/v1/crates");
+        let detail = Some("/api/v1/crates/:crate_id");
+        let download = Some("/api/v1/crates/:crate_id/:version/download");
+
+        assert_eq!(shedder.priority(search, false), Priority::Low);
+        assert_eq!(shedder.priority(detail, false), Priority::Normal);
+        assert_eq!(shedder.priority(detail, true), Priority::Low);
+        assert_eq!(shedder.priority(download, true), Priority::Critical);
+        assert_eq!(shedder.priority(None, false), Priority::Normal);
+    }
+
+    #[test]
+    fn sheds_by_in_flight_requests() {
+        let shedder = shedder(Some(100), None);
+
+        assert!(!shedder.should_shed(Priority::Low, 50));
+        assert!(shedder.should_shed(Priority::Low, 51));
+        assert!(!shedder.should_shed(Priority::Normal, 100));
+        assert!(shedder.should_shed(Priority::Normal, 101));
+        assert!(!shedder.should_shed(Priority::Critical, 1000));
+    }
+
+    #[test]
+    fn sheds_by_event_loop_lag() {
+        let shedder = shedder(None, Some(Duration::from_millis(100)));
+        assert!(!shedder.should_shed(Priority::Low, 1000));
+
+        shedder.record_lag(Duration::from_millis(150));
+        assert!(shedder.should_shed(Priority::Low, 1));
+        assert!(!shedder.should_shed(Priority::Normal, 1));
+
+        shedder.record_lag(Duration::from_millis(250));
+        assert!(shedder.should_shed(Priority::Normal, 1));
+        assert!(!shedder.should_shed(Priority::Critical, 1));
+    }
+}
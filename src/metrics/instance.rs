@@ -22,7 +22,7 @@ use crate::metrics::macros::metrics;
 use diesel_async::pooled_connection::deadpool::Pool;
 use diesel_async::AsyncPgConnection;
 use prometheus::{
-    proto::MetricFamily, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    proto::MetricFamily, Gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
 };
 
 metrics! {
@@ -45,6 +45,22 @@ metrics! {
         pub response_times: HistogramVec["endpoint"],
         /// Nmber of responses per status code
         pub responses_by_status_code_total: IntCounterVec["status"],
+
+        /// Number of requests rejected by the load shedder, by priority
+        pub requests_shed_total: IntCounterVec["priority"],
+        /// Number of requests to deprecated API surfaces, by surface
+        pub deprecated_requests_total: IntCounterVec["surface"],
+        /// Most recently measured lag of the event loop, in seconds
+        event_loop_lag_seconds: Gauge,
+
+        /// Number of publishes currently being processed
+        publishes_in_progress: IntGauge,
+        /// Number of publishes waiting in the publish queue
+        publish_queue_depth: IntGauge,
+        /// Number of users with publishes waiting in the publish queue
+        publish_queue_users: IntGauge,
+        /// Number of publishes rejected because the queue of the user was full
+        pub publish_queue_rejections_total: IntCounter,
     }
 
     // All instance metrics will be prefixed with this namespace.
@@ -59,6 +75,15 @@ impl InstanceMetrics {
             self.refresh_pool_stats("async_follower", follower)?;
         }
 
+        self.event_loop_lag_seconds
+            .set(app.load_shedder.lag().as_secs_f64());
+
+        let publish_queue = app.publish_queue.stats();
+        self.publishes_in_progress.set(publish_queue.running as i64);
+        self.publish_queue_depth.set(publish_queue.waiting as i64);
+        self.publish_queue_users
+            .set(publish_queue.waiting_users as i64);
+
         Ok(self.registry.gather())
     }
 
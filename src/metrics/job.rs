@@ -0,0 +1,31 @@
+//! This module defines the metrics of the background jobs of crates.io.
+//!
+//! Job metrics are collected separately for each background worker process, and are updated by
+//! the jobs while they run, accessing the metrics through `env.metrics.$metric_name`. Since the
+//! background worker does not serve any HTTP requests, the metrics are logged periodically
+//! together with the metrics of the job runner itself.
+
+use crate::metrics::macros::metrics;
+use prometheus::{proto::MetricFamily, IntCounterVec};
+
+metrics! {
+    pub struct JobMetrics {
+        /// Number of expired rows that were deleted or invalidated, by table
+        pub purged_rows_total: IntCounterVec["table"],
+    }
+
+    // All job metrics will be prefixed with this namespace.
+    namespace: "cratesio_jobs",
+}
+
+impl JobMetrics {
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+}
+
+impl Default for JobMetrics {
+    fn default() -> Self {
+        Self::new().expect("could not initialize job metrics")
+    }
+}
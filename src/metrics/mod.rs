@@ -1,8 +1,10 @@
 pub use self::instance::InstanceMetrics;
+pub use self::job::JobMetrics;
 pub use self::log_encoder::LogEncoder;
 pub use self::service::ServiceMetrics;
 
 mod instance;
+mod job;
 mod log_encoder;
 mod macros;
 mod service;
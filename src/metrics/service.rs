@@ -11,7 +11,8 @@
 //! instance-level metric, and you should add it to `src/metrics/instance.rs`.
 
 use crate::metrics::macros::metrics;
-use crate::schema::{background_jobs, crates, versions};
+use crate::models::ScanVerdict;
+use crate::schema::{background_jobs, crates, tarball_scans, versions};
 use crate::util::errors::AppResult;
 use diesel::{dsl::count_star, prelude::*};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
@@ -25,6 +26,10 @@ metrics! {
         versions_total: IntGauge,
         /// Number of queued up background jobs
         background_jobs: IntGaugeVec["priority", "job"],
+        /// Number of scanned crate tarballs, by antivirus verdict
+        tarball_scans: IntGaugeVec["verdict"],
+        /// Number of infected crate tarballs that were not reviewed by an admin yet
+        tarball_scans_pending_review: IntGauge,
     }
 
     // All service metrics will be prefixed with this namespace.
@@ -59,6 +64,32 @@ impl ServiceMetrics {
                 .set(count);
         }
 
+        let tarball_scans = tarball_scans::table
+            .group_by(tarball_scans::verdict)
+            .select((tarball_scans::verdict, count_star()))
+            .load::<(ScanVerdict, i64)>(conn)
+            .await?;
+
+        self.tarball_scans.reset();
+        for (verdict, count) in tarball_scans {
+            let verdict = match verdict {
+                ScanVerdict::Clean => "clean",
+                ScanVerdict::Infected => "infected",
+            };
+            self.tarball_scans
+                .get_metric_with_label_values(&[verdict])?
+                .set(count);
+        }
+
+        self.tarball_scans_pending_review.set(
+            tarball_scans::table
+                .filter(tarball_scans::verdict.eq(ScanVerdict::Infected))
+                .filter(tarball_scans::reviewed_at.is_null())
+                .select(count_star())
+                .first(conn)
+                .await?,
+        );
+
         Ok(self.registry.gather())
     }
 }
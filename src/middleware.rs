@@ -1,28 +1,34 @@
 pub mod app;
 mod block_traffic;
 pub mod cargo_compat;
+mod challenge;
 mod common_headers;
 mod debug;
+mod deprecation;
 mod ember_html;
+pub mod impersonation;
+mod load_shedding;
 pub mod log_request;
 pub mod normalize_path;
 pub mod real_ip;
+pub mod registry;
+mod request_limits;
 mod require_user_agent;
 pub mod session;
+mod shutdown;
 mod static_or_continue;
 mod update_metrics;
 
 use ::sentry::integrations::tower as sentry_tower;
+use axum::extract::DefaultBodyLimit;
 use axum::middleware::{from_fn, from_fn_with_state};
 use axum::Router;
 use axum_extra::either::Either;
 use axum_extra::middleware::option_layer;
-use std::time::Duration;
 use tower::layer::util::Identity;
 use tower_http::add_extension::AddExtensionLayer;
 use tower_http::catch_panic::CatchPanicLayer;
 use tower_http::compression::{CompressionLayer, CompressionLevel};
-use tower_http::timeout::{RequestBodyTimeoutLayer, TimeoutLayer};
 
 use crate::app::AppState;
 use crate::Env;
@@ -59,12 +65,24 @@ pub fn apply_axum_middleware(state: AppState, router: Router<()>) -> Router {
             state.config.cargo_compat_status_code_config,
             cargo_compat::middleware,
         ))
+        // The body size limits are enforced per route class by the
+        // `request_limits` middleware instead.
+        .layer(DefaultBodyLimit::disable())
+        .layer(from_fn_with_state(
+            state.clone(),
+            request_limits::middleware,
+        ))
+        .layer(from_fn_with_state(state.clone(), shutdown::middleware))
         .layer(from_fn_with_state(state.clone(), session::attach_session))
+        .layer(from_fn_with_state(state.clone(), impersonation::middleware))
         .layer(from_fn_with_state(
             state.clone(),
             require_user_agent::require_user_agent,
         ))
+        .layer(from_fn_with_state(state.clone(), load_shedding::middleware))
+        .layer(from_fn_with_state(state.clone(), deprecation::middleware))
         .layer(from_fn_with_state(state.clone(), block_traffic::middleware))
+        .layer(from_fn_with_state(state.clone(), challenge::middleware))
         .layer(from_fn_with_state(
             state.clone(),
             common_headers::add_common_headers,
@@ -83,8 +101,6 @@ pub fn apply_axum_middleware(state: AppState, router: Router<()>) -> Router {
     router
         .layer(middlewares_2)
         .layer(middlewares_1)
-        .layer(TimeoutLayer::new(Duration::from_secs(30)))
-        .layer(RequestBodyTimeoutLayer::new(Duration::from_secs(30)))
         .layer(CompressionLayer::new().quality(CompressionLevel::Fastest))
 }
 
@@ -1,11 +1,14 @@
 use crate::app::AppState;
 use crate::middleware::log_request::RequestLogExt;
 use crate::middleware::real_ip::RealIp;
+use crate::middleware::session::RequestSession;
+use crate::models::ThrottleClass;
 use crate::util::errors::custom;
 use axum::extract::{Extension, MatchedPath, Request};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
-use http::{HeaderMap, StatusCode};
+use http::{header, HeaderMap, StatusCode};
+use std::time::Instant;
 
 pub async fn middleware(
     Extension(real_ip): Extension<RealIp>,
@@ -16,6 +19,7 @@ pub async fn middleware(
 ) -> Result<impl IntoResponse, Response> {
     block_by_ip(&real_ip, &state, req.headers())?;
     block_by_header(&state, &req)?;
+    throttle_by_user_agent(&state, &req)?;
     block_routes(matched_path.as_ref(), &state)?;
 
     Ok(next.run(req).await)
@@ -49,6 +53,54 @@ pub fn block_by_header(state: &AppState, req: &Request) -> Result<(), Response>
     Ok(())
 }
 
+/// Middleware that throttles or blocks anonymous requests based on the
+/// policies in the `user_agent_policies` table.
+///
+/// The policies can be managed at runtime via the
+/// `/api/private/user_agent_policies` endpoints, which is useful for reacting
+/// to crawler incidents without a redeploy. Authenticated requests are not
+/// affected, since they are subject to the API quotas instead.
+pub fn throttle_by_user_agent(state: &AppState, req: &Request) -> Result<(), Response> {
+    let is_authenticated =
+        req.headers().contains_key(header::AUTHORIZATION) || req.session().get("user_id").is_some();
+    if is_authenticated {
+        return Ok(());
+    }
+
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let throttle = &state.user_agent_throttle;
+    let class = throttle.classify(user_agent);
+    let Err(retry_after) = throttle.take_token(class, Instant::now()) else {
+        return Ok(());
+    };
+
+    if class == ThrottleClass::Blocked {
+        req.request_log()
+            .add("cause", "blocked due to user-agent policy");
+        return Err(rejection_response_from(state, req.headers()));
+    }
+
+    req.request_log()
+        .add("cause", "throttled due to user-agent policy");
+
+    // Round up, so that clients don't retry before a token is available
+    let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    let body = "Too many requests from your client. Please slow down, \
+                or email help@crates.io to discuss your use case.";
+    let mut response = custom(StatusCode::TOO_MANY_REQUESTS, body).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, retry_after.into());
+
+    Err(response)
+}
+
 pub fn block_by_ip(
     real_ip: &RealIp,
     state: &AppState,
@@ -0,0 +1,102 @@
+//! Middleware that requires anonymous and new users to solve a challenge
+//! before they can use the routes in the `CHALLENGED_ROUTES` list.
+//!
+//! See [`crate::challenge`] for the available challenge providers.
+
+use crate::app::AppState;
+use crate::auth::authenticate_optional;
+use crate::challenge::{CHALLENGE_HEADER, CHALLENGE_RESPONSE_HEADER};
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::session::RequestSession;
+use crate::schema::versions;
+use crate::tasks::spawn_blocking;
+use crate::util::errors::{custom, AppResult};
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use http::request::Parts;
+use http::{header, HeaderValue, StatusCode};
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, Response> {
+    let is_challenged = matched_path
+        .as_ref()
+        .is_some_and(|path| state.challenge.is_challenged(path.as_str()));
+
+    if !is_challenged {
+        return Ok(next.run(req).await);
+    }
+
+    let (parts, body) = req.into_parts();
+    check_challenge(&state, &parts).await?;
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+async fn check_challenge(state: &AppState, req: &Parts) -> Result<(), Response> {
+    match is_established_user(state, req).await {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(error) => warn!("Failed to check if the user is established: {error}"),
+    }
+
+    let solution = req
+        .headers
+        .get(CHALLENGE_RESPONSE_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    if let Some(solution) = solution {
+        match state.challenge.verify(req.uri.path(), solution).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => req.request_log().add("cause", "invalid challenge solution"),
+            Err(error) => warn!("Failed to verify challenge solution: {error}"),
+        }
+    }
+
+    let detail = format!(
+        "This route requires solving a challenge. Please send the solution in the \
+        `{CHALLENGE_RESPONSE_HEADER}` header, or log in with an account that has \
+        published a crate."
+    );
+
+    let mut response = custom(StatusCode::FORBIDDEN, detail).into_response();
+    if let Ok(value) = HeaderValue::from_str(&state.challenge.describe()) {
+        response.headers_mut().insert(CHALLENGE_HEADER, value);
+    }
+
+    Err(response)
+}
+
+/// Authenticated users that have already published a version are never
+/// challenged.
+async fn is_established_user(state: &AppState, req: &Parts) -> AppResult<bool> {
+    let has_credentials =
+        req.headers.contains_key(header::AUTHORIZATION) || req.session().get("user_id").is_some();
+
+    if !has_credentials || state.config.db.are_all_read_only() {
+        return Ok(false);
+    }
+
+    let req = req.clone();
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let Some(auth) = authenticate_optional(&req, conn) else {
+            return Ok(false);
+        };
+
+        let published_versions = versions::table.filter(versions::published_by.eq(auth.user().id));
+        let is_established = diesel::select(exists(published_versions)).get_result(conn)?;
+
+        Ok(is_established)
+    })
+    .await
+}
@@ -0,0 +1,85 @@
+//! Middleware that adds the deprecation headers to the responses of
+//! deprecated API surfaces, and records who still uses them.
+//!
+//! See [`crate::deprecation`] for how deprecations are declared.
+
+use crate::app::AppState;
+use crate::models::NewDeprecatedApiUsage;
+use crate::schema::api_tokens;
+use crate::tasks::spawn_blocking;
+use crate::util::token::HashedToken;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use http::header;
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = matched_path.as_ref().map(MatchedPath::as_str);
+    let query = req.uri().query();
+    let deprecation = route.and_then(|route| {
+        let deprecations = &state.config.deprecations;
+        deprecations.iter().find(|d| d.matches(route, query))
+    });
+
+    let Some(deprecation) = deprecation else {
+        return next.run(req).await;
+    };
+
+    let surface = deprecation.surface();
+    state
+        .instance_metrics
+        .deprecated_requests_total
+        .with_label_values(&[&surface])
+        .inc();
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| HashedToken::parse(value).ok());
+
+    if let Some(token) = token {
+        if let Err(error) = record_usage(&state, surface, token).await {
+            warn!("Failed to record the usage of a deprecated API surface: {error}");
+        }
+    }
+
+    let mut response = next.run(req).await;
+    deprecation.add_headers(response.headers_mut());
+    response
+}
+
+/// Records the request in the `deprecated_api_usage` table, unless the API
+/// token is unknown or revoked, in which case the request fails later on
+/// anyway.
+async fn record_usage(state: &AppState, surface: String, token: HashedToken) -> anyhow::Result<()> {
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let api_token_id: Option<i32> = api_tokens::table
+            .filter(api_tokens::token.eq(&token))
+            .filter(api_tokens::revoked.eq(false))
+            .select(api_tokens::id)
+            .first(conn)
+            .optional()?;
+
+        if let Some(api_token_id) = api_token_id {
+            let usage = NewDeprecatedApiUsage {
+                surface: &surface,
+                api_token_id,
+            };
+            usage.record(conn)?;
+        }
+
+        Ok(())
+    })
+    .await
+}
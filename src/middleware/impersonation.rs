@@ -0,0 +1,150 @@
+//! Middleware for sessions in which an admin impersonates another user.
+//!
+//! Impersonation sessions are started via
+//! `POST /api/private/impersonate/:user_id` and replace the `user_id` of the
+//! admin's session with the one of the impersonated user for a limited time.
+//! Every request of such a session is written to the
+//! `impersonation_audit_log` table before it is processed, and only read-only
+//! requests are allowed, so that support staff can not accidentally publish
+//! crates or create API tokens on behalf of the user.
+
+use crate::app::AppState;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::session::{RequestSession, SessionExtension};
+use crate::models::NewImpersonationAuditLogEntry;
+use crate::tasks::spawn_blocking;
+use crate::util::errors::{custom, forbidden, AppResult};
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use http::{Method, StatusCode};
+
+/// The session key of the ID of the admin that impersonates the user.
+const IMPERSONATOR_ID_KEY: &str = "impersonator_id";
+
+/// The session key of the Unix timestamp at which the impersonation expires.
+const EXPIRES_AT_KEY: &str = "impersonation_expires_at";
+
+/// The path of the route that ends the impersonation, which is the only
+/// route that accepts non-read-only requests while impersonating.
+const END_PATH: &str = "/api/private/impersonate";
+
+/// How long an impersonation session lasts before the admin has to start a
+/// new one.
+pub const IMPERSONATION_DURATION: chrono::Duration = chrono::Duration::minutes(30);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Impersonation {
+    pub impersonator_id: i32,
+    pub user_id: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Impersonation {
+    /// Returns the impersonation of the session, if there is one.
+    pub fn from_session(session: &SessionExtension) -> Option<Self> {
+        let impersonator_id = session.get(IMPERSONATOR_ID_KEY)?.parse().ok()?;
+        let user_id = session.get("user_id")?.parse().ok()?;
+        let expires_at = session.get(EXPIRES_AT_KEY)?.parse().ok()?;
+        let expires_at = DateTime::from_timestamp(expires_at, 0)?;
+
+        Some(Self {
+            impersonator_id,
+            user_id,
+            expires_at,
+        })
+    }
+
+    /// Marks the session as impersonated and signs it in as the given user.
+    pub fn start(&self, session: &SessionExtension) {
+        let expires_at = self.expires_at.timestamp().to_string();
+        session.insert(
+            IMPERSONATOR_ID_KEY.to_string(),
+            self.impersonator_id.to_string(),
+        );
+        session.insert(EXPIRES_AT_KEY.to_string(), expires_at);
+        session.insert("user_id".to_string(), self.user_id.to_string());
+    }
+
+    /// Ends the impersonation and signs the session back in as the admin.
+    pub fn end(&self, session: &SessionExtension) {
+        Self::clear(session);
+        session.insert("user_id".to_string(), self.impersonator_id.to_string());
+    }
+
+    /// Removes the impersonation markers from the session, without changing
+    /// the signed in user.
+    pub fn clear(session: &SessionExtension) {
+        session.remove(IMPERSONATOR_ID_KEY);
+        session.remove(EXPIRES_AT_KEY);
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+pub async fn middleware(
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, Response> {
+    let Some(impersonation) = Impersonation::from_session(req.session()) else {
+        return Ok(next.run(req).await);
+    };
+
+    let is_end_request = req.method() == Method::DELETE && req.uri().path() == END_PATH;
+    if impersonation.is_expired(Utc::now()) && !is_end_request {
+        impersonation.end(req.session());
+
+        req.request_log().add("cause", "impersonation expired");
+        let detail = "the impersonation session has expired";
+        return Err(custom(StatusCode::FORBIDDEN, detail).into_response());
+    }
+
+    req.request_log()
+        .add("impersonator", impersonation.impersonator_id);
+
+    // Requests are only processed after they have been logged successfully
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    record_request(&state, &impersonation, method, path)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    if !req.method().is_safe() && !is_end_request {
+        req.request_log()
+            .add("cause", "blocked while impersonating");
+        let detail = "this action can not be performed while impersonating another user";
+        return Err(forbidden(detail).into_response());
+    }
+
+    Ok(next.run(req).await)
+}
+
+async fn record_request(
+    state: &AppState,
+    impersonation: &Impersonation,
+    method: String,
+    path: String,
+) -> AppResult<()> {
+    let impersonator_id = impersonation.impersonator_id;
+    let user_id = impersonation.user_id;
+
+    let conn = state.db_write().await?;
+    spawn_blocking(move || {
+        let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+        let entry = NewImpersonationAuditLogEntry {
+            impersonator_id,
+            user_id,
+            method: &method,
+            path: &path,
+        };
+
+        Ok(entry.insert(conn)?)
+    })
+    .await
+}
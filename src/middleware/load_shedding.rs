@@ -0,0 +1,70 @@
+//! Middleware that rejects low priority requests while the instance is
+//! overloaded.
+//!
+//! See [`crate::load_shedding`] for how the priorities and limits work.
+
+use crate::app::AppState;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::session::RequestSession;
+use crate::models::ThrottleClass;
+use crate::util::errors::custom;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::{header, StatusCode};
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, Response> {
+    let shedder = &state.load_shedder;
+
+    let route = matched_path.as_ref().map(MatchedPath::as_str);
+    let priority = shedder.priority(route, is_crawler(&state, &req));
+
+    // The counter already includes this request, since it is incremented by
+    // the `update_metrics` middleware further up the stack.
+    let in_flight = state.instance_metrics.requests_in_flight.get();
+    let in_flight = usize::try_from(in_flight).unwrap_or_default();
+
+    if !shedder.should_shed(priority, in_flight) {
+        return Ok(next.run(req).await);
+    }
+
+    req.request_log()
+        .add("cause", format!("shed {priority} priority request"));
+
+    state
+        .instance_metrics
+        .requests_shed_total
+        .with_label_values(&[priority.as_str()])
+        .inc();
+
+    let body = "The service is currently overloaded. Please try again later.";
+    let mut response = custom(StatusCode::SERVICE_UNAVAILABLE, body).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, shedder.retry_after().as_secs().into());
+
+    Err(response)
+}
+
+/// Anonymous requests whose `User-Agent` header matches a throttling policy
+/// are considered to be crawlers.
+fn is_crawler(state: &AppState, req: &Request) -> bool {
+    let is_authenticated =
+        req.headers().contains_key(header::AUTHORIZATION) || req.session().get("user_id").is_some();
+    if is_authenticated {
+        return false;
+    }
+
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    state.user_agent_throttle.classify(user_agent) != ThrottleClass::Normal
+}
@@ -0,0 +1,72 @@
+//! Select the registry of a request, see [`crate::registries`].
+
+use crate::app::AppState;
+use crate::controllers::util::RequestPartsExt;
+use crate::middleware::normalize_path::OriginalPath;
+use crate::registries::DEFAULT_REGISTRY;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use http::{header, Uri};
+
+/// The name of the registry that a request was sent to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegistryName(pub String);
+
+/// Selects the registry by the hostname or path prefix of the request, and
+/// removes the path prefix, so that all registries share the same routes.
+///
+/// This has to run before the request is routed, which is why it is applied
+/// around the whole router in [`crate::build_handler()`].
+pub async fn select_registry(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let host = req
+        .uri()
+        .host()
+        .or_else(|| req.headers().get(header::HOST)?.to_str().ok())
+        .map(|host| host.split(':').next().unwrap_or(host));
+
+    let path = req.uri().path();
+    let (registry, new_path) = state.config.registries.select(host, path);
+    let registry = RegistryName(registry.to_string());
+
+    if new_path != path {
+        let original_path = OriginalPath(path.to_string());
+
+        let new_path_and_query = match req.uri().query() {
+            Some(query) => format!("{new_path}?{query}"),
+            None => new_path.to_string(),
+        };
+
+        let mut parts = req.uri().clone().into_parts();
+        parts.path_and_query = new_path_and_query.parse().ok();
+
+        if let Ok(new_uri) = Uri::from_parts(parts) {
+            *req.uri_mut() = new_uri;
+            if req.extensions().get::<OriginalPath>().is_none() {
+                req.extensions_mut().insert(original_path);
+            }
+        }
+    }
+
+    req.extensions_mut().insert(registry);
+    next.run(req).await
+}
+
+/// Adds a `registry()` method to the request types, returning the name of the
+/// registry that the request was sent to.
+pub trait RequestRegistry {
+    fn registry(&self) -> &str;
+}
+
+impl<T: RequestPartsExt> RequestRegistry for T {
+    fn registry(&self) -> &str {
+        self.extensions()
+            .get::<RegistryName>()
+            .map(|registry| registry.0.as_str())
+            .unwrap_or(DEFAULT_REGISTRY)
+    }
+}
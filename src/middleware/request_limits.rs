@@ -0,0 +1,70 @@
+//! Middleware that enforces the timeout and body size limit of the route
+//! class of a request.
+//!
+//! See [`crate::request_limits`] for how the route classes are configured.
+
+use crate::app::AppState;
+use crate::middleware::log_request::RequestLogExt;
+use crate::request_limits::RouteClass;
+use crate::util::errors::custom;
+use axum::body::Body;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::{header, StatusCode};
+use http_body_util::Limited;
+use tower_http::timeout::TimeoutBody;
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let route = matched_path.as_ref().map(MatchedPath::as_str);
+    let class = state.config.request_limits.class(route);
+    let limits = state.config.request_limits.limits(route);
+
+    let content_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    if content_length.is_some_and(|length| length > limits.max_body_size) {
+        return Err(payload_too_large(limits.max_body_size));
+    }
+
+    // Requests without a `Content-Length` header are limited while their body
+    // is read, which the body extractors turn into a `413` response.
+    let (parts, body) = req.into_parts();
+    let body = Limited::new(body, limits.max_body_size);
+
+    // Publish requests run their database transactions and storage uploads
+    // in blocking tasks, which are not cancelled when the response future is
+    // dropped. Aborting them would only hide the outcome of the publish from
+    // the client, so their timeout only applies to receiving the body.
+    if class == RouteClass::Publish {
+        let body = Body::new(TimeoutBody::new(limits.timeout, body));
+        let req = Request::from_parts(parts, body);
+        return Ok(next.run(req).await);
+    }
+
+    let req = Request::from_parts(parts, Body::new(body));
+
+    let request_log = req.request_log().clone();
+    match tokio::time::timeout(limits.timeout, next.run(req)).await {
+        Ok(response) => Ok(response),
+        Err(_) => {
+            request_log.add("cause", "request timeout");
+
+            let body = "The request took too long to process.";
+            Err(custom(StatusCode::REQUEST_TIMEOUT, body).into_response())
+        }
+    }
+}
+
+fn payload_too_large(max_body_size: usize) -> Response {
+    let detail = format!("max request body size is: {max_body_size}");
+    custom(StatusCode::PAYLOAD_TOO_LARGE, detail).into_response()
+}
@@ -0,0 +1,41 @@
+//! Middleware that rejects new mutating requests while the server is shutting
+//! down, and tracks the in-flight ones so that the shutdown can wait for them.
+//!
+//! See [`crate::shutdown`] for how the shutdown is coordinated.
+
+use crate::app::AppState;
+use crate::util::errors::custom;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::{header, Method, StatusCode};
+
+/// The `Retry-After` value of rejected requests, which is roughly the time it
+/// takes for a replacement process to start.
+const RETRY_AFTER_SECONDS: u64 = 10;
+
+pub async fn middleware(state: AppState, req: Request, next: Next) -> Result<Response, Response> {
+    if is_read_only(req.method()) {
+        return Ok(next.run(req).await);
+    }
+
+    // Track the request before checking whether the server is draining, so
+    // that a shutdown that starts in between waits for this request.
+    let _guard = state.shutdown.track_mutation();
+
+    if state.shutdown.is_draining() {
+        let body = "The server is restarting. Please try again shortly.";
+        let mut response = custom(StatusCode::SERVICE_UNAVAILABLE, body).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, RETRY_AFTER_SECONDS.into());
+
+        return Err(response);
+    }
+
+    Ok(next.run(req).await)
+}
+
+fn is_read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
@@ -0,0 +1,183 @@
+//! Redirection of crate downloads to mirrors close to the client.
+//!
+//! Mirrors are registered with the `crates-admin mirrors` command and serve
+//! the crate files of one continent. If mirror redirects are enabled, the
+//! download endpoint determines the continent of the client from the
+//! country header that the CDN adds to the requests, and redirects to a
+//! healthy mirror of that continent. All other downloads are redirected to
+//! the primary storage as before.
+//!
+//! A mirror is healthy if it is enabled and if the `check_mirrors` background
+//! job recently found a crate file with the expected checksum on it. Only
+//! versions that were published a while before that check are redirected to
+//! the mirror, to give it time to synchronize new versions. Mirrors that
+//! could not be verified for [`DISABLE_AFTER_HOURS`] are disabled and have
+//! to be enabled again manually.
+//!
+//! The [MirrorProbe] trait is used to abstract away the HTTP client for
+//! testing purposes.
+
+use crate::models::Mirror;
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime};
+use crates_io_cdn_logs::regions::{continent, country_code};
+use http::HeaderMap;
+use hyper::body::Bytes;
+use mockall::automock;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The codes of the continents that mirrors can serve.
+pub const REGIONS: &[&str] = &["AF", "AN", "AS", "EU", "NA", "OC", "SA"];
+
+/// The default header that the CDN adds with the country code of the client.
+pub const DEFAULT_COUNTRY_HEADER: &str = "CloudFront-Viewer-Country";
+
+/// Mirrors are only redirected to if they were verified within this amount
+/// of time.
+pub const HEALTHY_WITHIN_MINUTES: i64 = 30;
+
+/// Versions are only redirected to a mirror if they were published this
+/// amount of time before the mirror was verified.
+pub const SYNC_GRACE_PERIOD_MINUTES: i64 = 60;
+
+/// Mirrors that could not be verified for this amount of time are disabled.
+pub const DISABLE_AFTER_HOURS: i64 = 24;
+
+/// The `User-Agent` header that is sent to the mirrors.
+const USER_AGENT: &str = "crates.io mirror health check (https://crates.io)";
+
+/// The maximum duration of fetching a crate file from a mirror.
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Returns the continent code of the client, based on the country header
+/// that the CDN added to the request.
+pub fn client_region(headers: &HeaderMap, country_header: &str) -> Option<&'static str> {
+    let country = headers.get(country_header)?.to_str().ok()?;
+    continent(country_code(country)?)
+}
+
+/// Returns the mirror that a download of the given version is redirected to,
+/// if any of the mirrors is healthy and synchronized the version already.
+///
+/// Downloads are spread across multiple eligible mirrors by the crate name,
+/// so that every mirror only has to cache a part of the crates.
+pub fn select<'a>(
+    mirrors: &'a [Mirror],
+    crate_name: &str,
+    published_at: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Option<&'a Mirror> {
+    let healthy_since = now - Duration::minutes(HEALTHY_WITHIN_MINUTES);
+    let synced_before = published_at + Duration::minutes(SYNC_GRACE_PERIOD_MINUTES);
+
+    let eligible = mirrors
+        .iter()
+        .filter(|mirror| mirror.enabled)
+        .filter(|mirror| {
+            mirror.verified_at.is_some_and(|verified_at| {
+                verified_at >= healthy_since && verified_at >= synced_before
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if eligible.is_empty() {
+        return None;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    crate_name.hash(&mut hasher);
+    let index = hasher.finish() % eligible.len() as u64;
+
+    eligible.get(index as usize).copied()
+}
+
+#[automock]
+#[async_trait]
+pub trait MirrorProbe {
+    /// Fetches the file at the given URL from a mirror.
+    async fn fetch(&self, url: &str) -> anyhow::Result<Bytes>;
+}
+
+/// Fetches files from the mirrors via HTTP.
+pub struct HttpMirrorProbe {
+    client: reqwest::Client,
+}
+
+impl HttpMirrorProbe {
+    pub fn new() -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(FETCH_TIMEOUT)
+            .build()?;
+
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl MirrorProbe for HttpMirrorProbe {
+    #[instrument(skip(self))]
+    async fn fetch(&self, url: &str) -> anyhow::Result<Bytes> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        Ok(response.bytes().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use http::HeaderValue;
+
+    fn mirror(id: i32, enabled: bool, verified_at: Option<NaiveDateTime>) -> Mirror {
+        Mirror {
+            id,
+            name: format!("mirror-{id}"),
+            base_url: format!("https://mirror-{id}.example.com"),
+            region: "EU".into(),
+            enabled,
+            created_at: NaiveDateTime::default(),
+            checked_at: verified_at,
+            verified_at,
+        }
+    }
+
+    #[test]
+    fn regions() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_region(&headers, DEFAULT_COUNTRY_HEADER), None);
+
+        let name = "cloudfront-viewer-country";
+        headers.insert(name, HeaderValue::from_static("de"));
+        assert_eq!(client_region(&headers, DEFAULT_COUNTRY_HEADER), Some("EU"));
+        assert_eq!(client_region(&headers, "x-country"), None);
+
+        headers.insert(name, HeaderValue::from_static("XX"));
+        assert_eq!(client_region(&headers, DEFAULT_COUNTRY_HEADER), None);
+    }
+
+    #[test]
+    fn selects_healthy_and_synchronized_mirrors() {
+        let now = Utc::now().naive_utc();
+        let published_at = now - Duration::hours(2);
+
+        let recently = Some(now - Duration::minutes(5));
+        let stale = Some(now - Duration::hours(1));
+        let mirrors = [
+            mirror(1, false, recently),
+            mirror(2, true, None),
+            mirror(3, true, stale),
+            mirror(4, true, recently),
+        ];
+
+        let selected = select(&mirrors, "foo", published_at, now).unwrap();
+        assert_eq!(selected.id, 4);
+
+        // The mirror might not have synchronized new versions yet
+        let published_at = now - Duration::minutes(30);
+        assert!(select(&mirrors, "foo", published_at, now).is_none());
+
+        assert!(select(&[], "foo", published_at, now).is_none());
+    }
+}
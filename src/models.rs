@@ -1,35 +1,136 @@
+pub use self::account_recovery::{
+    AccountRecovery, NewAccountRecovery, ACCOUNT_RECOVERY_LOCK_REASON,
+};
 pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
+pub use self::admin_audit::{AdminAction, AdminAuditEntry, NewAdminAuditEntry};
+pub use self::advisory::Advisory;
+pub use self::attestation::{
+    AttestationKind, AttestationProvider, NewAttestationProvider, NewVersionAttestation,
+    VersionAttestation,
+};
 pub use self::category::{Category, CrateCategory, NewCategory};
+pub use self::ci_annotation::{NewVersionCiAnnotation, VersionCiAnnotation};
+pub use self::crate_freeze::{CrateFreeze, NewCrateFreeze};
+pub use self::crate_health::{CrateHealth, CrateHealthMetrics, HealthComponent, HealthComponents};
+pub use self::crate_owner_action::{CrateOwnerAction, NewCrateOwnerAction, OwnerAction};
+pub use self::crate_owner_email_invitation::CrateOwnerEmailInvitation;
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitationOutcome};
-pub use self::default_versions::{update_default_version, verify_default_version};
+pub use self::crate_recommendation::{CrateRecommendation, RecommendationKind};
+pub use self::crate_settings::{
+    CrateSettings, CrateSettingsUpdate, DeniedDependency, DependencyDenyList,
+};
+pub use self::crate_succession::{CrateSuccession, NewCrateSuccession};
+pub use self::critical_crate::{CriticalCrate, NewCriticalCrate};
+pub use self::database_dump::{
+    DatabaseDump, DatabaseDumpDelta, NewDatabaseDump, NewDatabaseDumpDelta,
+};
+pub use self::default_versions::{
+    load_default_versions, update_default_version, verify_default_version,
+};
 pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
+pub use self::dependency_policy_exception::{
+    DependencyPolicyException, NewDependencyPolicyException,
+};
+pub use self::deprecated_api_usage::NewDeprecatedApiUsage;
+pub use self::dormant_owner_suggestion::{DormantOwnerSuggestion, NewDormantOwnerSuggestion};
 pub use self::download::VersionDownload;
-pub use self::email::{Email, NewEmail};
+pub use self::email::{
+    Email, NewEmail, NotificationClass, UndeliverableReason, MAX_EMAILS_PER_USER,
+};
 pub use self::follow::Follow;
+pub use self::impersonation::{ImpersonationAuditLogEntry, NewImpersonationAuditLogEntry};
 pub use self::keyword::{CrateKeyword, Keyword};
-pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
+pub use self::krate::{
+    Crate, CrateDownloadSummary, CrateVersions, CrateVisibility, NewCrate, RecentCrateDownloads,
+};
+pub use self::metadata_finding::{MetadataFinding, MetadataRule, NewMetadataFinding};
+pub use self::mirror::{Mirror, NewMirror};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::pending_owner_removal::{NewPendingOwnerRemoval, PendingOwnerRemoval};
+pub use self::pending_publish::{NewPendingPublish, PendingPublish};
+pub use self::pending_yank::{NewPendingYank, PendingYank};
+pub use self::persistent_session::{PersistentSession, SESSION_TOKEN_KEY};
+pub use self::publish_idempotency_key::{NewPublishIdempotencyKey, PublishIdempotencyKey};
+pub use self::quarantine::VersionQuarantine;
+pub use self::rate_limit_override_request::{
+    NewRateLimitOverrideRequest, RateLimitOverrideRequest,
+};
+pub use self::readme_image::{NewReadmeImage, ReadmeImage};
+pub use self::registry_event::{NewRegistryEvent, RegistryEvent, RegistryEventKind};
+pub use self::reproducibility_report::{NewReproducibilityReport, ReproducibilityReport};
 pub use self::rights::Rights;
+pub use self::security_event::{
+    NewSecurityEvent, SecurityEvent, SecurityEventKind, SECURITY_ALERT_LOCK_REASON,
+};
+pub use self::spam_flag::{NewSpamFlag, SpamFlag};
+pub use self::staged_upload::{NewStagedUpload, StagedUpload};
+pub use self::subscription::{
+    DependencySubscription, NewDependencySubscription, MAX_SUBSCRIPTIONS_PER_USER,
+};
+pub use self::tarball_repair::{ChecksumRotation, NewChecksumRotation, VersionContentManifest};
+pub use self::tarball_scan::{NewTarballScan, ScanVerdict, TarballScan};
 pub use self::team::{NewTeam, Team};
 pub use self::token::{ApiToken, CreatedApiToken};
 pub use self::user::{NewUser, User};
-pub use self::version::{NewVersion, TopVersions, Version};
+pub use self::user_agent_policy::{NewUserAgentPolicy, ThrottleClass, UserAgentPolicy};
+pub use self::user_avatar::UserAvatar;
+pub use self::version::{DocsBuildStatus, NewVersion, TopVersions, Version};
+pub use self::yank::{set_yanked, YankActor};
 
 pub mod helpers;
 
+mod account_recovery;
 mod action;
+mod admin_audit;
+mod advisory;
+mod attestation;
 pub mod category;
+mod ci_annotation;
+mod crate_freeze;
+mod crate_health;
+mod crate_owner_action;
+mod crate_owner_email_invitation;
 mod crate_owner_invitation;
+mod crate_recommendation;
+mod crate_settings;
+mod crate_succession;
+mod critical_crate;
+mod database_dump;
 mod default_versions;
 pub mod dependency;
+mod dependency_policy_exception;
+mod deprecated_api_usage;
+mod dormant_owner_suggestion;
 mod download;
 mod email;
 mod follow;
+mod impersonation;
 mod keyword;
 pub mod krate;
+mod metadata_finding;
+mod mirror;
 mod owner;
+mod pending_owner_removal;
+mod pending_publish;
+mod pending_yank;
+mod persistent_session;
+mod publish_idempotency_key;
+mod quarantine;
+mod rate_limit_override_request;
+mod readme_image;
+mod registry_event;
+mod reproducibility_report;
 mod rights;
+mod security_event;
+mod spam_flag;
+mod staged_upload;
+mod subscription;
+mod tarball_repair;
+mod tarball_scan;
 mod team;
 pub mod token;
 pub mod user;
+mod user_agent_policy;
+mod user_avatar;
 pub mod version;
+mod yank;
@@ -0,0 +1,173 @@
+use crate::models::User;
+use crate::schema::{account_recoveries, api_tokens, users};
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use secrecy::SecretString;
+use std::time::Duration;
+
+/// The lock reason of accounts that were merged into a recovered account.
+pub const ACCOUNT_RECOVERY_LOCK_REASON: &str = "The GitHub identity of this account was \
+    linked to another account during an account recovery. Please sign in again to use the \
+    recovered account.";
+
+/// A request to re-link an account to a new GitHub identity.
+///
+/// Recoveries are requested by the account of the new GitHub identity, and
+/// have to be confirmed via the verified email address of the recovered
+/// account and approved by an admin. They can only be completed once the
+/// waiting period after the approval has passed, and can be cancelled at
+/// any time before that.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = account_recoveries, check_for_backend(diesel::pg::Pg))]
+pub struct AccountRecovery {
+    pub id: i32,
+    pub user_id: i32,
+    pub requested_by: i32,
+    pub email: String,
+    pub email_confirmed_at: Option<NaiveDateTime>,
+    pub approved_by: Option<i32>,
+    pub approved_at: Option<NaiveDateTime>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub cancelled_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl AccountRecovery {
+    pub fn find(conn: &mut impl Conn, id: i32) -> QueryResult<Self> {
+        account_recoveries::table
+            .find(id)
+            .select(Self::as_select())
+            .first(conn)
+    }
+
+    /// Returns all recoveries that have been neither completed nor
+    /// cancelled yet, with the oldest requests first.
+    pub fn open(conn: &mut impl Conn) -> QueryResult<Vec<Self>> {
+        account_recoveries::table
+            .filter(account_recoveries::completed_at.is_null())
+            .filter(account_recoveries::cancelled_at.is_null())
+            .select(Self::as_select())
+            .order(account_recoveries::id)
+            .load(conn)
+    }
+
+    /// Confirms the open recovery with the given email token.
+    ///
+    /// Returns `None` if no matching recovery was found, or if it has
+    /// already been confirmed.
+    pub fn confirm_email(conn: &mut impl Conn, email_token: &str) -> QueryResult<Option<Self>> {
+        diesel::update(account_recoveries::table)
+            .filter(account_recoveries::email_token.eq(email_token))
+            .filter(account_recoveries::email_confirmed_at.is_null())
+            .filter(account_recoveries::completed_at.is_null())
+            .filter(account_recoveries::cancelled_at.is_null())
+            .set(account_recoveries::email_confirmed_at.eq(now))
+            .returning(Self::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.completed_at.is_none() && self.cancelled_at.is_none()
+    }
+
+    /// Returns the date and time from which on the recovery can be
+    /// completed, or `None` if it has not been approved yet.
+    pub fn completable_at(&self, waiting_period: Duration) -> Option<NaiveDateTime> {
+        let waiting_period = chrono::Duration::from_std(waiting_period).ok()?;
+        self.approved_at
+            .map(|approved_at| approved_at + waiting_period)
+    }
+
+    pub fn approve(&self, conn: &mut impl Conn, admin_id: i32) -> QueryResult<Self> {
+        diesel::update(self)
+            .set((
+                account_recoveries::approved_by.eq(admin_id),
+                account_recoveries::approved_at.eq(now),
+            ))
+            .returning(Self::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn cancel(&self, conn: &mut impl Conn) -> QueryResult<Self> {
+        diesel::update(self)
+            .set(account_recoveries::cancelled_at.eq(now))
+            .returning(Self::as_returning())
+            .get_result(conn)
+    }
+
+    /// Re-links the recovered account to the GitHub identity of the account
+    /// that requested the recovery.
+    ///
+    /// The requesting account gives up its GitHub identity, so that signing
+    /// in with it leads to the recovered account from now on. Its API tokens
+    /// are revoked and the account is locked.
+    pub fn complete(&self, conn: &mut impl Conn) -> QueryResult<Self> {
+        conn.transaction(|conn| {
+            let requester = User::find(conn, self.requested_by)?;
+
+            // GitHub IDs have to be unique, so the requesting account has to
+            // release its GitHub ID before it can be assigned to the
+            // recovered account.
+            diesel::update(users::table.find(requester.id))
+                .set((
+                    users::gh_id.eq(-1),
+                    users::gh_access_token.eq(""),
+                    users::account_lock_reason.eq(ACCOUNT_RECOVERY_LOCK_REASON),
+                    users::account_lock_until.eq(None::<NaiveDateTime>),
+                ))
+                .execute(conn)?;
+
+            diesel::update(api_tokens::table)
+                .filter(api_tokens::user_id.eq(requester.id))
+                .set(api_tokens::revoked.eq(true))
+                .execute(conn)?;
+
+            diesel::update(users::table.find(self.user_id))
+                .set((
+                    users::gh_id.eq(requester.gh_id),
+                    users::gh_login.eq(&requester.gh_login),
+                    users::gh_avatar.eq(&requester.gh_avatar),
+                    users::gh_access_token.eq(&requester.gh_access_token),
+                ))
+                .execute(conn)?;
+
+            diesel::update(self)
+                .set(account_recoveries::completed_at.eq(now))
+                .returning(Self::as_returning())
+                .get_result(conn)
+        })
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = account_recoveries, check_for_backend(diesel::pg::Pg))]
+pub struct NewAccountRecovery<'a> {
+    pub user_id: i32,
+    pub requested_by: i32,
+    pub email: &'a str,
+}
+
+impl NewAccountRecovery<'_> {
+    /// Inserts the recovery and returns it together with the token that
+    /// confirms it via email.
+    ///
+    /// Returns `None` if the account already has an open recovery.
+    pub fn insert(
+        &self,
+        conn: &mut impl Conn,
+    ) -> QueryResult<Option<(AccountRecovery, SecretString)>> {
+        diesel::insert_into(account_recoveries::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .returning((
+                AccountRecovery::as_returning(),
+                account_recoveries::email_token,
+            ))
+            .get_result::<(AccountRecovery, String)>(conn)
+            .optional()
+            .map(|result| result.map(|(recovery, token)| (recovery, SecretString::new(token))))
+    }
+}
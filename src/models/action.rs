@@ -3,6 +3,7 @@ use crate::schema::*;
 use crate::sql::pg_enum;
 use crate::util::diesel::Conn;
 use chrono::NaiveDateTime;
+use diesel::dsl::max;
 use diesel::prelude::*;
 
 pg_enum! {
@@ -10,6 +11,8 @@ pg_enum! {
         Publish = 0,
         Yank = 1,
         Unyank = 2,
+        Flag = 3,
+        Unflag = 4,
     }
 }
 
@@ -19,6 +22,8 @@ impl From<VersionAction> for &'static str {
             VersionAction::Publish => "publish",
             VersionAction::Yank => "yank",
             VersionAction::Unyank => "unyank",
+            VersionAction::Flag => "flag",
+            VersionAction::Unflag => "unflag",
         }
     }
 }
@@ -31,7 +36,7 @@ impl From<VersionAction> for String {
     }
 }
 
-#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
 #[diesel(
     table_name = version_owner_actions,
     check_for_backend(diesel::pg::Pg),
@@ -42,10 +47,13 @@ impl From<VersionAction> for String {
 pub struct VersionOwnerAction {
     pub id: i32,
     pub version_id: i32,
-    pub user_id: i32,
+    /// The user that performed the action, or `None` if the action was
+    /// performed by crates.io itself, see [`crate::models::YankActor`].
+    pub user_id: Option<i32>,
     pub api_token_id: Option<i32>,
     pub action: VersionAction,
     pub time: NaiveDateTime,
+    pub reason: Option<String>,
 }
 
 impl VersionOwnerAction {
@@ -73,6 +81,47 @@ impl VersionOwnerAction {
             .load(conn)?
             .grouped_by(versions))
     }
+
+    /// Returns all yank and unyank actions of the version, in the order in
+    /// which they happened.
+    ///
+    /// If an action was performed with a team token, the login of the team
+    /// is returned too. Actions that were performed by crates.io itself have
+    /// no user.
+    pub fn yank_history(
+        conn: &mut impl Conn,
+        version_id: i32,
+    ) -> QueryResult<Vec<(Self, Option<User>, Option<String>)>> {
+        version_owner_actions::table
+            .filter(version_owner_actions::version_id.eq(version_id))
+            .filter(
+                version_owner_actions::action.eq_any([VersionAction::Yank, VersionAction::Unyank]),
+            )
+            .left_join(users::table)
+            .left_join(api_tokens::table.left_join(teams::table))
+            .select((
+                version_owner_actions::all_columns,
+                users::all_columns.nullable(),
+                teams::login.nullable(),
+            ))
+            .order(version_owner_actions::id)
+            .load(conn)
+    }
+
+    /// Returns the time of the most recent yank or unyank action of the
+    /// version, if there is any.
+    pub fn last_yank_time(
+        conn: &mut impl Conn,
+        version_id: i32,
+    ) -> QueryResult<Option<NaiveDateTime>> {
+        version_owner_actions::table
+            .filter(version_owner_actions::version_id.eq(version_id))
+            .filter(
+                version_owner_actions::action.eq_any([VersionAction::Yank, VersionAction::Unyank]),
+            )
+            .select(max(version_owner_actions::time))
+            .get_result(conn)
+    }
 }
 
 pub fn insert_version_owner_action(
@@ -81,8 +130,9 @@ pub fn insert_version_owner_action(
     user_id_: i32,
     api_token_id_: Option<i32>,
     action_: VersionAction,
+    reason_: Option<&str>,
 ) -> QueryResult<VersionOwnerAction> {
-    use version_owner_actions::dsl::{action, api_token_id, user_id, version_id};
+    use version_owner_actions::dsl::{action, api_token_id, reason, user_id, version_id};
 
     diesel::insert_into(version_owner_actions::table)
         .values((
@@ -90,6 +140,7 @@ pub fn insert_version_owner_action(
             user_id.eq(user_id_),
             api_token_id.eq(api_token_id_),
             action.eq(action_),
+            reason.eq(reason_),
         ))
         .get_result(conn)
 }
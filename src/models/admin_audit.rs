@@ -0,0 +1,111 @@
+use crate::models::User;
+use crate::schema::admin_audit_log;
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::Value;
+
+pg_enum! {
+    pub enum AdminAction {
+        FreezeCrate = 0,
+        UnfreezeCrate = 1,
+        DesignateCriticalCrate = 2,
+        RevokeCriticalCrate = 3,
+        ApproveOwnerRemoval = 4,
+        ConfirmSpamFlag = 5,
+        DismissSpamFlag = 6,
+        ConfirmTarballScan = 7,
+        ReleaseTarballScan = 8,
+        BulkYank = 9,
+        YankVersion = 10,
+        UnyankVersion = 11,
+        DeleteCrate = 12,
+        DeleteVersion = 13,
+        RebuildIndex = 14,
+        Impersonate = 15,
+        ChangeCrateLimits = 16,
+        ChangeVisibility = 17,
+        AddDependencyPolicyException = 18,
+        RemoveDependencyPolicyException = 19,
+        SetUserAgentPolicy = 20,
+        DeleteUserAgentPolicy = 21,
+        ApproveAttestationProvider = 22,
+        RevokeAttestationProvider = 23,
+        ApproveAccountRecovery = 24,
+        RepairTarball = 25,
+        ApproveRateLimitOverride = 26,
+        RejectRateLimitOverride = 27,
+        AllowCrateName = 28,
+        AddMirror = 29,
+        EnableMirror = 30,
+        DisableMirror = 31,
+        TransferCrates = 32,
+        ChangeUserLimits = 33,
+    }
+}
+
+/// An entry of the append-only audit log of admin actions.
+///
+/// The table rejects updates and deletions, so entries can only be added
+/// via [`NewAdminAuditEntry`].
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = admin_audit_log, check_for_backend(diesel::pg::Pg))]
+pub struct AdminAuditEntry {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub action: AdminAction,
+    pub crate_name: Option<String>,
+    pub details: Value,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = admin_audit_log, check_for_backend(diesel::pg::Pg))]
+pub struct NewAdminAuditEntry<'a> {
+    actor_id: Option<i32>,
+    action: AdminAction,
+    crate_name: Option<&'a str>,
+    details: Value,
+}
+
+impl<'a> NewAdminAuditEntry<'a> {
+    /// Creates an entry for an action that an admin performed via the API.
+    pub fn by_admin(admin: &User, action: AdminAction) -> Self {
+        Self {
+            actor_id: Some(admin.id),
+            action,
+            crate_name: None,
+            details: Value::Object(Default::default()),
+        }
+    }
+
+    /// Creates an entry for an action that was performed via the admin CLI.
+    pub fn by_cli(action: AdminAction) -> Self {
+        Self {
+            actor_id: None,
+            action,
+            crate_name: None,
+            details: Value::Object(Default::default()),
+        }
+    }
+
+    /// Sets the crate that the action affected.
+    pub fn krate(mut self, crate_name: &'a str) -> Self {
+        self.crate_name = Some(crate_name);
+        self
+    }
+
+    /// Sets additional details of the action, like the reason of a freeze.
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(admin_audit_log::table)
+            .values(self)
+            .execute(conn)
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,88 @@
+use crate::advisories::ParsedAdvisory;
+use crate::schema::advisories;
+use crate::util::diesel::Conn;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::dsl::now;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+
+/// A security advisory that was imported from the advisory database.
+///
+/// See [`crate::advisories`] for how advisories are imported.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = advisories, check_for_backend(diesel::pg::Pg))]
+pub struct Advisory {
+    pub id: String,
+    pub crate_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub withdrawn: Option<NaiveDate>,
+    pub aliases: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    pub synced_at: NaiveDateTime,
+}
+
+impl Advisory {
+    pub fn find(conn: &mut impl Conn, id: &str) -> QueryResult<Option<Self>> {
+        advisories::table
+            .find(id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Inserts the given advisories, or updates them if they were imported
+    /// before.
+    pub fn upsert_all(conn: &mut impl Conn, parsed: &[ParsedAdvisory]) -> QueryResult<usize> {
+        let values = parsed
+            .iter()
+            .map(|advisory| {
+                (
+                    advisories::id.eq(&advisory.id),
+                    advisories::crate_name.eq(&advisory.crate_name),
+                    advisories::title.eq(&advisory.title),
+                    advisories::url.eq(&advisory.url),
+                    advisories::date.eq(advisory.date),
+                    advisories::withdrawn.eq(advisory.withdrawn),
+                    advisories::aliases.eq(&advisory.aliases),
+                    advisories::patched.eq(&advisory.patched),
+                    advisories::unaffected.eq(&advisory.unaffected),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut count = 0;
+        // Postgres limits the number of bind parameters of a single query
+        for chunk in values.chunks(1000) {
+            count += diesel::insert_into(advisories::table)
+                .values(chunk)
+                .on_conflict(advisories::id)
+                .do_update()
+                .set((
+                    advisories::crate_name.eq(excluded(advisories::crate_name)),
+                    advisories::title.eq(excluded(advisories::title)),
+                    advisories::url.eq(excluded(advisories::url)),
+                    advisories::date.eq(excluded(advisories::date)),
+                    advisories::withdrawn.eq(excluded(advisories::withdrawn)),
+                    advisories::aliases.eq(excluded(advisories::aliases)),
+                    advisories::patched.eq(excluded(advisories::patched)),
+                    advisories::unaffected.eq(excluded(advisories::unaffected)),
+                    advisories::synced_at.eq(now),
+                ))
+                .execute(conn)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Deletes the advisories that are not in the given list of IDs anymore,
+    /// and returns their IDs.
+    pub fn delete_all_except(conn: &mut impl Conn, ids: &[&str]) -> QueryResult<Vec<String>> {
+        diesel::delete(advisories::table)
+            .filter(advisories::id.ne_all(ids))
+            .returning(advisories::id)
+            .get_results(conn)
+    }
+}
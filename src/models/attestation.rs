@@ -0,0 +1,93 @@
+use crate::schema::{attestation_providers, version_attestations};
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+pg_enum! {
+    pub enum AttestationKind {
+        ReproducibleBuild = 0,
+        Audit = 1,
+        Fuzzing = 2,
+    }
+}
+
+/// An account that was approved by an admin to attach attestations to
+/// versions, e.g. an independent rebuilder or an audit organization.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = attestation_providers, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(user_id))]
+pub struct AttestationProvider {
+    pub user_id: i32,
+    pub name: String,
+    pub approved_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl AttestationProvider {
+    pub fn find(conn: &mut impl Conn, user_id: i32) -> QueryResult<Option<Self>> {
+        attestation_providers::table
+            .find(user_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = attestation_providers, check_for_backend(diesel::pg::Pg))]
+pub struct NewAttestationProvider<'a> {
+    pub user_id: i32,
+    pub name: &'a str,
+    pub approved_by: i32,
+}
+
+impl NewAttestationProvider<'_> {
+    /// Approves the provider, or renames it if it was approved already.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<AttestationProvider> {
+        diesel::insert_into(attestation_providers::table)
+            .values(self)
+            .on_conflict(attestation_providers::user_id)
+            .do_update()
+            .set(attestation_providers::name.eq(self.name))
+            .returning(AttestationProvider::as_returning())
+            .get_result(conn)
+    }
+}
+
+/// A signed statement of an [`AttestationProvider`] about a version.
+///
+/// crates.io only stores the statements. Consumers are expected to verify
+/// the signatures with the keys that the providers publish themselves.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = version_attestations, check_for_backend(diesel::pg::Pg))]
+pub struct VersionAttestation {
+    pub id: i32,
+    pub version_id: i32,
+    pub provider_id: i32,
+    pub kind: AttestationKind,
+    pub statement: String,
+    pub signature: String,
+    pub url: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_attestations, check_for_backend(diesel::pg::Pg))]
+pub struct NewVersionAttestation<'a> {
+    pub version_id: i32,
+    pub provider_id: i32,
+    pub kind: AttestationKind,
+    pub statement: &'a str,
+    pub signature: &'a str,
+    pub url: Option<&'a str>,
+}
+
+impl NewVersionAttestation<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<VersionAttestation> {
+        diesel::insert_into(version_attestations::table)
+            .values(self)
+            .returning(VersionAttestation::as_returning())
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::schema::version_ci_annotations;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// Information about the CI run that published a version.
+///
+/// The information is self-reported by the publisher via the
+/// `X-Cargo-CI-System` and `X-Cargo-CI-Run-URL` headers of the publish
+/// request, so it is not verified in any way.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = version_ci_annotations, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(version_id))]
+pub struct VersionCiAnnotation {
+    pub version_id: i32,
+    pub ci_system: String,
+    pub run_url: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionCiAnnotation {
+    pub fn for_version(conn: &mut impl Conn, version_id: i32) -> QueryResult<Option<Self>> {
+        version_ci_annotations::table
+            .find(version_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_ci_annotations, check_for_backend(diesel::pg::Pg))]
+pub struct NewVersionCiAnnotation<'a> {
+    pub version_id: i32,
+    pub ci_system: &'a str,
+    pub run_url: Option<&'a str>,
+}
+
+impl NewVersionCiAnnotation<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(version_ci_annotations::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::schema::crate_freezes;
+use crate::util::diesel::Conn;
+use crate::util::errors::{custom, AppResult, BoxedAppError};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use http::StatusCode;
+
+/// The address that the owners of a frozen crate can contact for details.
+const FREEZE_CONTACT_ADDRESS: &str = "help@crates.io";
+
+/// A crate that has been frozen by an admin pending an investigation.
+///
+/// Unlike quarantined versions, the existing versions of a frozen crate can
+/// still be downloaded, but no new versions can be published and the owners
+/// of the crate can not be changed until the freeze is lifted.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate_freezes, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(crate_id))]
+pub struct CrateFreeze {
+    pub crate_id: i32,
+    pub reason: String,
+    pub frozen_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateFreeze {
+    pub fn for_crate(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Option<Self>> {
+        crate_freezes::table
+            .find(crate_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns an error naming the reason of the freeze if the crate is
+    /// currently frozen.
+    pub fn ensure_not_frozen(
+        conn: &mut impl Conn,
+        crate_id: i32,
+        crate_name: &str,
+    ) -> AppResult<()> {
+        match Self::for_crate(conn, crate_id)? {
+            Some(freeze) => Err(Self::error(crate_name, &freeze.reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// The error for changes to a frozen crate, naming the reason of the
+    /// freeze.
+    pub fn error(crate_name: &str, reason: &str) -> BoxedAppError {
+        let detail = format!(
+            "The crate `{crate_name}` has been frozen by the crates.io team pending an \
+            investigation, so it can not be changed at the moment (reason: {reason}). \
+            Please contact {FREEZE_CONTACT_ADDRESS} for more information."
+        );
+
+        custom(StatusCode::FORBIDDEN, detail)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate_freezes, check_for_backend(diesel::pg::Pg))]
+pub struct NewCrateFreeze<'a> {
+    pub crate_id: i32,
+    pub reason: &'a str,
+    pub frozen_by: i32,
+}
+
+impl NewCrateFreeze<'_> {
+    /// Freezes the crate, replacing the reason of an existing freeze.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<CrateFreeze> {
+        use crate::schema::crate_freezes::columns::*;
+
+        diesel::insert_into(crate_freezes::table)
+            .values(self)
+            .on_conflict(crate_id)
+            .do_update()
+            .set((
+                reason.eq(excluded(reason)),
+                frozen_by.eq(excluded(frozen_by)),
+            ))
+            .returning(CrateFreeze::as_returning())
+            .get_result(conn)
+    }
+}
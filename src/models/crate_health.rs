@@ -0,0 +1,239 @@
+use crate::models::DocsBuildStatus;
+use crate::schema::crate_health;
+use crate::util::diesel::Conn;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+/// The metrics of a crate that its health score is computed from.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate_health, check_for_backend(diesel::pg::Pg))]
+pub struct CrateHealthMetrics {
+    pub crate_id: i32,
+    pub last_release_at: NaiveDateTime,
+    pub maintainers: i32,
+    pub active_maintainers: i32,
+    pub quarantined_versions: i32,
+    pub docs_build_status: Option<DocsBuildStatus>,
+    pub versions: i32,
+    pub yanked_versions: i32,
+}
+
+/// The stored health score of a crate, as computed by the
+/// `UpdateCrateHealth` background job.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate_health, check_for_backend(diesel::pg::Pg))]
+pub struct CrateHealth {
+    #[diesel(embed)]
+    pub metrics: CrateHealthMetrics,
+    pub score: i16,
+    pub computed_at: NaiveDateTime,
+}
+
+impl CrateHealth {
+    pub fn for_crate(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Option<Self>> {
+        crate_health::table
+            .find(crate_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the breakdown of the score into its components, relative to
+    /// the time when the score was computed.
+    pub fn components(&self) -> HealthComponents {
+        self.metrics.components(self.computed_at)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate_health, check_for_backend(diesel::pg::Pg))]
+struct NewCrateHealth<'a> {
+    #[diesel(embed)]
+    metrics: &'a CrateHealthMetrics,
+    score: i16,
+}
+
+impl CrateHealthMetrics {
+    /// Computes the health score components of the crate.
+    ///
+    /// The recency of the last release is evaluated relative to `now`.
+    pub fn components(&self, now: NaiveDateTime) -> HealthComponents {
+        let age = now - self.last_release_at;
+        let releases = match age {
+            age if age <= TimeDelta::days(180) => 25,
+            age if age <= TimeDelta::days(365) => 15,
+            age if age <= TimeDelta::days(730) => 5,
+            _ => 0,
+        };
+
+        let maintainers = match self.maintainers {
+            0 => 0,
+            1 => 10,
+            _ => 15,
+        } + if self.active_maintainers > 0 { 10 } else { 0 };
+
+        let advisories = if self.quarantined_versions == 0 {
+            20
+        } else {
+            0
+        };
+
+        let docs = match self.docs_build_status {
+            Some(DocsBuildStatus::Success) => 15,
+            Some(DocsBuildStatus::InProgress) => 8,
+            Some(DocsBuildStatus::Failure) | None => 0,
+        };
+
+        let yanked_versions = if self.versions > 0 {
+            let ratio = self.yanked_versions as f64 / self.versions as f64;
+            (15. * (1. - ratio)).round() as i16
+        } else {
+            0
+        };
+
+        HealthComponents {
+            releases: HealthComponent::new(releases, 25),
+            maintainers: HealthComponent::new(maintainers, 25),
+            advisories: HealthComponent::new(advisories, 20),
+            docs: HealthComponent::new(docs, 15),
+            yanked_versions: HealthComponent::new(yanked_versions, 15),
+        }
+    }
+
+    /// Stores the metrics and the resulting health scores of multiple
+    /// crates at once, replacing their previous values.
+    pub fn upsert_all(conn: &mut impl Conn, metrics: &[Self]) -> QueryResult<usize> {
+        use crate::schema::crate_health::columns::*;
+
+        let current_time = Utc::now().naive_utc();
+        let rows = metrics
+            .iter()
+            .map(|metrics| NewCrateHealth {
+                metrics,
+                score: metrics.components(current_time).total(),
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(crate_health::table)
+            .values(&rows)
+            .on_conflict(crate_id)
+            .do_update()
+            .set((
+                score.eq(excluded(score)),
+                last_release_at.eq(excluded(last_release_at)),
+                maintainers.eq(excluded(maintainers)),
+                active_maintainers.eq(excluded(active_maintainers)),
+                quarantined_versions.eq(excluded(quarantined_versions)),
+                docs_build_status.eq(excluded(docs_build_status)),
+                versions.eq(excluded(versions)),
+                yanked_versions.eq(excluded(yanked_versions)),
+                computed_at.eq(now),
+            ))
+            .execute(conn)
+    }
+}
+
+/// A single component of the health score of a crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthComponent {
+    pub score: i16,
+    pub max: i16,
+}
+
+impl HealthComponent {
+    fn new(score: i16, max: i16) -> Self {
+        Self { score, max }
+    }
+}
+
+/// The breakdown of the health score of a crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthComponents {
+    /// Based on how recently the last version was published.
+    pub releases: HealthComponent,
+    /// Based on the number of owners, and whether any of them published a
+    /// version within the last year.
+    pub maintainers: HealthComponent,
+    /// Full score if none of the versions are quarantined.
+    pub advisories: HealthComponent,
+    /// Based on the docs.rs build status of the default version.
+    pub docs: HealthComponent,
+    /// Based on the ratio of yanked versions.
+    pub yanked_versions: HealthComponent,
+}
+
+impl HealthComponents {
+    /// The overall health score, between 0 and 100.
+    pub fn total(&self) -> i16 {
+        self.releases.score
+            + self.maintainers.score
+            + self.advisories.score
+            + self.docs.score
+            + self.yanked_versions.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn metrics(last_release_at: NaiveDateTime) -> CrateHealthMetrics {
+        CrateHealthMetrics {
+            crate_id: 1,
+            last_release_at,
+            maintainers: 2,
+            active_maintainers: 1,
+            quarantined_versions: 0,
+            docs_build_status: Some(DocsBuildStatus::Success),
+            versions: 4,
+            yanked_versions: 0,
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_healthy_crate() {
+        let now = date(2024, 12, 1);
+        let components = metrics(date(2024, 11, 1)).components(now);
+        assert_eq!(components.total(), 100);
+    }
+
+    #[test]
+    fn test_release_recency() {
+        let now = date(2024, 12, 1);
+        let score = |last_release_at| metrics(last_release_at).components(now).releases.score;
+        assert_eq!(score(date(2024, 6, 5)), 25);
+        assert_eq!(score(date(2024, 1, 1)), 15);
+        assert_eq!(score(date(2023, 1, 1)), 5);
+        assert_eq!(score(date(2020, 1, 1)), 0);
+    }
+
+    #[test]
+    fn test_unhealthy_crate() {
+        let now = date(2024, 12, 1);
+        let metrics = CrateHealthMetrics {
+            maintainers: 1,
+            active_maintainers: 0,
+            quarantined_versions: 1,
+            docs_build_status: Some(DocsBuildStatus::Failure),
+            yanked_versions: 3,
+            ..metrics(date(2020, 1, 1))
+        };
+
+        let components = metrics.components(now);
+        assert_eq!(components.maintainers, HealthComponent::new(10, 25));
+        assert_eq!(components.advisories, HealthComponent::new(0, 20));
+        assert_eq!(components.docs, HealthComponent::new(0, 15));
+        assert_eq!(components.yanked_versions, HealthComponent::new(4, 15));
+        assert_eq!(components.total(), 14);
+    }
+}
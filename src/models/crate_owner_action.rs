@@ -0,0 +1,84 @@
+use crate::models::OwnerKind;
+use crate::schema::crate_owner_actions;
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+pg_enum! {
+    pub enum OwnerAction {
+        Add = 0,
+        Remove = 1,
+    }
+}
+
+/// An entry of the ownership history of a crate.
+///
+/// Unlike the `crate_owners` table, which only contains the current state of
+/// each owner, this records every time an owner was added or removed, and
+/// by whom.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate_owner_actions, check_for_backend(diesel::pg::Pg))]
+pub struct CrateOwnerAction {
+    pub id: i32,
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub owner_kind: OwnerKind,
+    pub action: OwnerAction,
+    pub performed_by: Option<i32>,
+    pub time: NaiveDateTime,
+}
+
+impl CrateOwnerAction {
+    /// Returns the ownership history of the crate, in the order in which the
+    /// actions happened.
+    pub fn by_crate(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Vec<Self>> {
+        crate_owner_actions::table
+            .filter(crate_owner_actions::crate_id.eq(crate_id))
+            .select(Self::as_select())
+            .order(crate_owner_actions::id)
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate_owner_actions, check_for_backend(diesel::pg::Pg))]
+pub struct NewCrateOwnerAction {
+    crate_id: i32,
+    owner_id: i32,
+    owner_kind: OwnerKind,
+    action: OwnerAction,
+    performed_by: Option<i32>,
+}
+
+impl NewCrateOwnerAction {
+    /// Creates an action that added an owner to a crate.
+    pub fn add(crate_id: i32, owner_id: i32, owner_kind: OwnerKind, by: Option<i32>) -> Self {
+        Self {
+            crate_id,
+            owner_id,
+            owner_kind,
+            action: OwnerAction::Add,
+            performed_by: by,
+        }
+    }
+
+    /// Creates an action that removed an owner from a crate.
+    pub fn remove(crate_id: i32, owner_id: i32, owner_kind: OwnerKind, by: Option<i32>) -> Self {
+        Self {
+            crate_id,
+            owner_id,
+            owner_kind,
+            action: OwnerAction::Remove,
+            performed_by: by,
+        }
+    }
+
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(crate_owner_actions::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::config;
+use crate::models::{CrateOwnerInvitation, NewCrateOwnerInvitationOutcome, OwnerKind};
+use crate::schema::{crate_owner_email_invitations, crate_owners, emails};
+use crate::sql::lower;
+use crate::util::diesel::Conn;
+
+/// The model representing a row in the `crate_owner_email_invitations` database table.
+///
+/// These invitations are created when a crate owner invites an email address
+/// that does not belong to a crates.io account yet. They are turned into
+/// regular [`CrateOwnerInvitation`]s by [`CrateOwnerEmailInvitation::claim`]
+/// once a user with a matching verified email address logs in.
+#[derive(Clone, Debug, Identifiable, Queryable, Selectable)]
+#[diesel(
+    table_name = crate_owner_email_invitations,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(crate_id, email)
+)]
+pub struct CrateOwnerEmailInvitation {
+    pub crate_id: i32,
+    pub email: String,
+    pub invited_by_user_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateOwnerEmailInvitation {
+    /// Stores a pending invitation for the given email address.
+    ///
+    /// Returns `false` if an unexpired invitation for the email address
+    /// already exists.
+    pub fn create(
+        email: &str,
+        invited_by_user_id: i32,
+        crate_id: i32,
+        conn: &mut impl Conn,
+        config: &config::Server,
+    ) -> QueryResult<bool> {
+        #[derive(Insertable, Debug)]
+        #[diesel(table_name = crate_owner_email_invitations, check_for_backend(diesel::pg::Pg))]
+        struct NewRecord<'a> {
+            crate_id: i32,
+            email: &'a str,
+            invited_by_user_id: i32,
+        }
+
+        let email = email.to_lowercase();
+
+        // Same as for regular invitations, expired invitations are deleted
+        // first to allow inviting the email address again.
+        conn.transaction(|conn| -> QueryResult<()> {
+            let existing: Option<CrateOwnerEmailInvitation> = crate_owner_email_invitations::table
+                .find((crate_id, &email))
+                .for_update()
+                .first(conn)
+                .optional()?;
+
+            if let Some(existing) = existing {
+                if existing.is_expired(config) {
+                    diesel::delete(&existing).execute(conn)?;
+                }
+            }
+            Ok(())
+        })?;
+
+        let inserted = diesel::insert_into(crate_owner_email_invitations::table)
+            .values(&NewRecord {
+                crate_id,
+                email: &email,
+                invited_by_user_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(inserted > 0)
+    }
+
+    /// Turns the pending invitations for any of the verified email addresses
+    /// of the given user into regular invitations.
+    ///
+    /// Expired invitations and invitations for crates that the user already
+    /// owns are discarded. Returns the number of created invitations.
+    pub fn claim(
+        conn: &mut impl Conn,
+        user_id: i32,
+        config: &config::Server,
+    ) -> QueryResult<usize> {
+        let verified_emails = emails::table
+            .filter(emails::user_id.eq(user_id))
+            .filter(emails::verified.eq(true))
+            .select(lower(emails::email));
+
+        let owned_crates = crate_owners::table
+            .filter(crate_owners::owner_id.eq(user_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::deleted.eq(false))
+            .select(crate_owners::crate_id);
+
+        conn.transaction(|conn| {
+            let invitations: Vec<CrateOwnerEmailInvitation> = crate_owner_email_invitations::table
+                .filter(crate_owner_email_invitations::email.eq_any(verified_emails))
+                .select(CrateOwnerEmailInvitation::as_select())
+                .for_update()
+                .load(conn)?;
+
+            let owned_crates: Vec<i32> = owned_crates.load(conn)?;
+
+            let mut num_created = 0;
+            for invitation in invitations {
+                diesel::delete(&invitation).execute(conn)?;
+
+                if invitation.is_expired(config) || owned_crates.contains(&invitation.crate_id) {
+                    continue;
+                }
+
+                let outcome = CrateOwnerInvitation::create(
+                    user_id,
+                    invitation.invited_by_user_id,
+                    invitation.crate_id,
+                    conn,
+                    config,
+                )?;
+
+                if let NewCrateOwnerInvitationOutcome::InviteCreated { .. } = outcome {
+                    num_created += 1;
+                }
+            }
+
+            Ok(num_created)
+        })
+    }
+
+    pub fn is_expired(&self, config: &config::Server) -> bool {
+        let days = chrono::Duration::days(config.ownership_invitations_expiration_days as i64);
+        self.created_at + days <= Utc::now().naive_utc()
+    }
+}
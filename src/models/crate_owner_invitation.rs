@@ -4,7 +4,10 @@ use http::StatusCode;
 use secrecy::SecretString;
 
 use crate::config;
-use crate::models::{CrateOwner, OwnerKind};
+use crate::models::{
+    CrateFreeze, CrateOwner, CriticalCrate, NewCrateOwnerAction, NewRegistryEvent, OwnerKind,
+    RegistryEventKind, User,
+};
 use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::util::diesel::Conn;
 use crate::util::errors::{custom, AppResult};
@@ -112,6 +115,16 @@ impl CrateOwnerInvitation {
             return Err(custom(StatusCode::GONE, detail));
         }
 
+        let crate_name: String = crates::table
+            .find(self.crate_id)
+            .select(crates::name)
+            .first(conn)?;
+
+        CrateFreeze::ensure_not_frozen(conn, self.crate_id, &crate_name)?;
+
+        let invited_user = User::find(conn, self.invited_user_id)?;
+        CriticalCrate::ensure_two_factor(conn, self.crate_id, &crate_name, &invited_user)?;
+
         conn.transaction(|conn| {
             diesel::insert_into(crate_owners::table)
                 .values(&CrateOwner {
@@ -128,6 +141,13 @@ impl CrateOwnerInvitation {
 
             diesel::delete(&self).execute(conn)?;
 
+            // The owner is recorded as added by the user that invited them.
+            let by = Some(self.invited_by_user_id);
+            NewCrateOwnerAction::add(self.crate_id, self.invited_user_id, OwnerKind::User, by)
+                .insert(conn)?;
+
+            NewRegistryEvent::krate(RegistryEventKind::OwnerChange, &crate_name).insert(conn)?;
+
             Ok(())
         })
     }
@@ -0,0 +1,26 @@
+use crate::schema::crate_recommendations;
+use crate::sql::pg_enum;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+// `DependedOnTogether` ranks the related crates by the number of crates that
+// depend on both crates, `UsedTogether` by the recent downloads of these
+// crates.
+pg_enum! {
+    pub enum RecommendationKind {
+        DependedOnTogether = 0,
+        UsedTogether = 1,
+    }
+}
+
+/// A crate that is recommended on the page of another crate, as computed by
+/// the `UpdateCrateRecommendations` background job.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate_recommendations, check_for_backend(diesel::pg::Pg))]
+pub struct CrateRecommendation {
+    pub crate_id: i32,
+    pub kind: RecommendationKind,
+    pub related_crate_id: i32,
+    pub score: i64,
+    pub computed_at: NaiveDateTime,
+}
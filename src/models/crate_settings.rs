@@ -0,0 +1,260 @@
+use crate::schema::crate_settings;
+use crate::util::diesel::Conn;
+use diesel::deserialize::{self, FromSql};
+use diesel::dsl::now;
+use diesel::pg::{Pg, PgValue};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Jsonb;
+use diesel::upsert::excluded;
+use semver::{Comparator, Op, Version, VersionReq};
+use std::io::Write;
+
+/// The settings of a crate that can be changed by its owners.
+///
+/// New settings should be added here instead of to the `crates` table, so
+/// that they can be managed consistently via the
+/// `/api/v1/crates/:crate_id/settings` endpoints.
+#[derive(Debug, Clone, Default, Queryable, Identifiable, Selectable)]
+#[diesel(
+    table_name = crate_settings,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(crate_id),
+)]
+pub struct CrateSettings {
+    pub crate_id: i32,
+    pub prerelease_retention_days: Option<i32>,
+    /// Email address or `https` URL that security issues should be reported to.
+    pub security_contact: Option<String>,
+    pub disclosure_policy: Option<String>,
+    pub msrv_policy: Option<String>,
+    pub denied_dependencies: DependencyDenyList,
+}
+
+impl CrateSettings {
+    /// Returns the settings of the given crate, or the default settings if
+    /// they were never changed.
+    pub fn for_crate(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Self> {
+        let settings = crate_settings::table
+            .find(crate_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(settings.unwrap_or_else(|| Self {
+            crate_id,
+            ..Default::default()
+        }))
+    }
+}
+
+/// A change of the settings of a crate.
+///
+/// `None` fields are not changed, while `Some(None)` resets the setting to
+/// its default value.
+#[derive(Debug, Default)]
+pub struct CrateSettingsUpdate {
+    pub prerelease_retention_days: Option<Option<i32>>,
+    pub security_contact: Option<Option<String>>,
+    pub disclosure_policy: Option<Option<String>>,
+    pub msrv_policy: Option<Option<String>>,
+    pub denied_dependencies: Option<DependencyDenyList>,
+}
+
+impl CrateSettingsUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.prerelease_retention_days.is_none()
+            && self.security_contact.is_none()
+            && self.disclosure_policy.is_none()
+            && self.msrv_policy.is_none()
+            && self.denied_dependencies.is_none()
+    }
+
+    /// Applies the change to the settings of the given crate, and returns the
+    /// resulting settings.
+    pub fn apply(&self, conn: &mut impl Conn, crate_id: i32) -> QueryResult<CrateSettings> {
+        if self.is_empty() {
+            return CrateSettings::for_crate(conn, crate_id);
+        }
+
+        use crate::schema::crate_settings::columns::*;
+
+        conn.transaction(|conn| {
+            let current = CrateSettings::for_crate(conn, crate_id)?;
+
+            let new_prerelease_retention_days = self
+                .prerelease_retention_days
+                .unwrap_or(current.prerelease_retention_days);
+            let new_security_contact = self
+                .security_contact
+                .clone()
+                .unwrap_or(current.security_contact);
+            let new_disclosure_policy = self
+                .disclosure_policy
+                .clone()
+                .unwrap_or(current.disclosure_policy);
+            let new_msrv_policy = self.msrv_policy.clone().unwrap_or(current.msrv_policy);
+            let new_denied_dependencies = self
+                .denied_dependencies
+                .clone()
+                .unwrap_or(current.denied_dependencies);
+
+            diesel::insert_into(crate_settings::table)
+                .values((
+                    crate_settings::crate_id.eq(crate_id),
+                    prerelease_retention_days.eq(new_prerelease_retention_days),
+                    security_contact.eq(new_security_contact),
+                    disclosure_policy.eq(new_disclosure_policy),
+                    msrv_policy.eq(new_msrv_policy),
+                    denied_dependencies.eq(new_denied_dependencies),
+                ))
+                .on_conflict(crate_settings::crate_id)
+                .do_update()
+                .set((
+                    prerelease_retention_days.eq(excluded(prerelease_retention_days)),
+                    security_contact.eq(excluded(security_contact)),
+                    disclosure_policy.eq(excluded(disclosure_policy)),
+                    msrv_policy.eq(excluded(msrv_policy)),
+                    denied_dependencies.eq(excluded(denied_dependencies)),
+                    updated_at.eq(now),
+                ))
+                .returning(CrateSettings::as_returning())
+                .get_result(conn)
+        })
+    }
+}
+
+/// Dependencies that the owners of a crate don't want new versions of their
+/// crate to use, e.g. to prevent regressions to vulnerable versions when
+/// re-publishing a workspace.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = Jsonb)]
+#[serde(transparent)]
+pub struct DependencyDenyList(pub Vec<DeniedDependency>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeniedDependency {
+    /// Name of the denied crate.
+    pub name: String,
+    /// If set, only versions of the crate matching this requirement are
+    /// denied, e.g. `<1`.
+    #[serde(default)]
+    pub version_req: Option<String>,
+}
+
+impl DependencyDenyList {
+    /// Returns the entry of the deny-list that a dependency on `name` with
+    /// the given version requirement violates, if any.
+    ///
+    /// A dependency violates a version-restricted entry if the lowest version
+    /// that its requirement allows matches the denied requirement. For
+    /// example, `^0.10` violates `<1`, while `^1.0` does not.
+    pub fn find_violation(
+        &self,
+        name: &str,
+        version_req: &VersionReq,
+    ) -> Option<&DeniedDependency> {
+        self.0.iter().find(|denied| {
+            if !denied.name.eq_ignore_ascii_case(name) {
+                return false;
+            }
+
+            let Some(denied_req) = &denied.version_req else {
+                return true;
+            };
+
+            // Entries are validated when they are saved, so this only fails
+            // for entries that were written by an older version of the
+            // validation, which are ignored.
+            let Ok(denied_req) = VersionReq::parse(denied_req) else {
+                return false;
+            };
+
+            denied_req.matches(&lowest_version(version_req))
+        })
+    }
+}
+
+/// Returns the lowest version that a requirement allows.
+///
+/// Comma-separated comparators must all match, so the lowest version is the
+/// highest of their lower bounds.
+fn lowest_version(req: &VersionReq) -> Version {
+    req.comparators
+        .iter()
+        .filter_map(lower_bound)
+        .max()
+        .unwrap_or(Version::new(0, 0, 0))
+}
+
+fn lower_bound(comparator: &Comparator) -> Option<Version> {
+    match comparator.op {
+        Op::Exact | Op::Greater | Op::GreaterEq | Op::Tilde | Op::Caret | Op::Wildcard => {
+            let mut version = Version::new(
+                comparator.major,
+                comparator.minor.unwrap_or(0),
+                comparator.patch.unwrap_or(0),
+            );
+            version.pre = comparator.pre.clone();
+            Some(version)
+        }
+        _ => None,
+    }
+}
+
+impl FromSql<Jsonb, Pg> for DependencyDenyList {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl ToSql<Jsonb, Pg> for DependencyDenyList {
+    fn to_sql(&self, out: &mut Output<'_, '_, Pg>) -> serialize::Result {
+        // The binary representation of `jsonb` values is prefixed with its
+        // format version.
+        out.write_all(&[1])?;
+        serde_json::to_writer(out, self)?;
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deny_list(entries: &[(&str, Option<&str>)]) -> DependencyDenyList {
+        let entries = entries
+            .iter()
+            .map(|(name, version_req)| DeniedDependency {
+                name: name.to_string(),
+                version_req: version_req.map(ToString::to_string),
+            })
+            .collect();
+
+        DependencyDenyList(entries)
+    }
+
+    fn violates(deny_list: &DependencyDenyList, name: &str, version_req: &str) -> bool {
+        let version_req = VersionReq::parse(version_req).unwrap();
+        deny_list.find_violation(name, &version_req).is_some()
+    }
+
+    #[test]
+    fn test_find_violation() {
+        let deny_list = deny_list(&[("openssl", Some("<1")), ("left-pad", None)]);
+
+        assert!(violates(&deny_list, "openssl", "^0.10"));
+        assert!(violates(&deny_list, "openssl", "=0.9.8"));
+        assert!(violates(&deny_list, "openssl", "<2"));
+        assert!(violates(&deny_list, "OpenSSL", "0.10"));
+        assert!(!violates(&deny_list, "openssl", "^1.0"));
+        assert!(!violates(&deny_list, "openssl", ">=0.10, >=1.1"));
+        assert!(!violates(&deny_list, "openssl-sys", "^0.9"));
+
+        assert!(violates(&deny_list, "left-pad", "^1.0"));
+        assert!(violates(&deny_list, "left-pad", "~0.1"));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::models::CrateVisibility;
+use crate::schema::{crate_successions, crates};
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+/// A relation between a crate that has been superseded and the crate that
+/// supersedes it.
+///
+/// The relation can be declared by the owners of either crate, but it is
+/// only in effect once the owners of both crates confirmed it.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = crate_successions, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(predecessor_id))]
+pub struct CrateSuccession {
+    pub predecessor_id: i32,
+    pub successor_id: i32,
+    pub predecessor_confirmed_at: Option<NaiveDateTime>,
+    pub successor_confirmed_at: Option<NaiveDateTime>,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateSuccession {
+    pub fn is_confirmed(&self) -> bool {
+        self.predecessor_confirmed_at.is_some() && self.successor_confirmed_at.is_some()
+    }
+
+    /// Returns the relation of the given crate to the crate that supersedes
+    /// it, whether it has been confirmed or not.
+    pub fn for_predecessor(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Option<Self>> {
+        crate_successions::table
+            .find(crate_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the name of the public crate that supersedes the given crate,
+    /// if the relation has been confirmed.
+    pub fn successor_name(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Option<String>> {
+        crate_successions::table
+            .inner_join(crates::table.on(crates::id.eq(crate_successions::successor_id)))
+            .filter(crate_successions::predecessor_id.eq(crate_id))
+            .filter(crate_successions::predecessor_confirmed_at.is_not_null())
+            .filter(crate_successions::successor_confirmed_at.is_not_null())
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .select(crates::name)
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the names of the public crates that are superseded by the
+    /// given crate, if the relations have been confirmed.
+    pub fn predecessor_names(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Vec<String>> {
+        crate_successions::table
+            .inner_join(crates::table.on(crates::id.eq(crate_successions::predecessor_id)))
+            .filter(crate_successions::successor_id.eq(crate_id))
+            .filter(crate_successions::predecessor_confirmed_at.is_not_null())
+            .filter(crate_successions::successor_confirmed_at.is_not_null())
+            .filter(crates::visibility.eq(CrateVisibility::Public))
+            .select(crates::name)
+            .order(crates::name)
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate_successions, check_for_backend(diesel::pg::Pg))]
+pub struct NewCrateSuccession {
+    pub predecessor_id: i32,
+    pub successor_id: i32,
+    pub predecessor_confirmed_at: Option<NaiveDateTime>,
+    pub successor_confirmed_at: Option<NaiveDateTime>,
+    pub created_by: i32,
+}
+
+impl NewCrateSuccession {
+    /// Inserts the relation, replacing any previous relation of the
+    /// predecessor to another crate.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<CrateSuccession> {
+        use crate::schema::crate_successions::columns::*;
+
+        diesel::insert_into(crate_successions::table)
+            .values(self)
+            .on_conflict(predecessor_id)
+            .do_update()
+            .set((
+                successor_id.eq(excluded(successor_id)),
+                predecessor_confirmed_at.eq(excluded(predecessor_confirmed_at)),
+                successor_confirmed_at.eq(excluded(successor_confirmed_at)),
+                created_by.eq(excluded(created_by)),
+                created_at.eq(now),
+            ))
+            .returning(CrateSuccession::as_returning())
+            .get_result(conn)
+    }
+}
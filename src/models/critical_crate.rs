@@ -0,0 +1,92 @@
+use crate::models::User;
+use crate::schema::critical_crates;
+use crate::util::diesel::Conn;
+use crate::util::errors::{custom, AppResult, BoxedAppError};
+use chrono::NaiveDateTime;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use http::StatusCode;
+
+/// A crate that has been designated as critical for the ecosystem by an
+/// admin.
+///
+/// All owners of a critical crate must have two-factor authentication enabled
+/// on their GitHub account to publish, yank or change the owners of the crate.
+/// Yanking widely used versions has to be confirmed via email at a lower
+/// download threshold, and removals of owners only take effect after two
+/// admins approved them or a waiting period is over.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = critical_crates, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(crate_id))]
+pub struct CriticalCrate {
+    pub crate_id: i32,
+    pub designated_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl CriticalCrate {
+    pub fn for_crate(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Option<Self>> {
+        critical_crates::table
+            .find(crate_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    pub fn is_critical(conn: &mut impl Conn, crate_id: i32) -> QueryResult<bool> {
+        diesel::select(exists(critical_crates::table.find(crate_id))).get_result(conn)
+    }
+
+    /// Returns an error if the crate is critical and the user does not have
+    /// two-factor authentication enabled on their GitHub account.
+    ///
+    /// The two-factor status is only refreshed when the user signs in, so the
+    /// error message asks the user to sign in again after enabling it.
+    pub fn ensure_two_factor(
+        conn: &mut impl Conn,
+        crate_id: i32,
+        crate_name: &str,
+        user: &User,
+    ) -> AppResult<()> {
+        if user.gh_two_factor || !Self::is_critical(conn, crate_id)? {
+            return Ok(());
+        }
+
+        Err(Self::two_factor_error(crate_name))
+    }
+
+    /// The error for owners of a critical crate without two-factor
+    /// authentication.
+    pub fn two_factor_error(crate_name: &str) -> BoxedAppError {
+        let detail = format!(
+            "The crate `{crate_name}` is a critical crate, so all of its owners must have \
+            two-factor authentication enabled on their GitHub account. Please enable it at \
+            https://github.com/settings/security and sign in to crates.io again."
+        );
+
+        custom(StatusCode::FORBIDDEN, detail)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = critical_crates, check_for_backend(diesel::pg::Pg))]
+pub struct NewCriticalCrate {
+    pub crate_id: i32,
+    pub designated_by: i32,
+}
+
+impl NewCriticalCrate {
+    /// Designates the crate as critical, or returns the existing designation
+    /// if the crate already is critical.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<CriticalCrate> {
+        diesel::insert_into(critical_crates::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        critical_crates::table
+            .find(self.crate_id)
+            .select(CriticalCrate::as_select())
+            .first(conn)
+    }
+}
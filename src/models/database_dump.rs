@@ -0,0 +1,100 @@
+use crate::schema::{database_dump_deltas, database_dumps};
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A public database dump that was uploaded to the storage bucket by the
+/// `dump_db` background job.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = database_dumps, check_for_backend(diesel::pg::Pg))]
+pub struct DatabaseDump {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub directory: String,
+    pub tar_size: i64,
+    pub tar_sha256: String,
+    pub zip_size: i64,
+    pub zip_sha256: String,
+    pub last_event_id: i64,
+}
+
+impl DatabaseDump {
+    /// Loads the most recent database dumps, newest first.
+    pub fn recent(conn: &mut impl Conn, limit: i64) -> QueryResult<Vec<Self>> {
+        database_dumps::table
+            .select(Self::as_select())
+            .order((database_dumps::created_at.desc(), database_dumps::id.desc()))
+            .limit(limit)
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = database_dumps, check_for_backend(diesel::pg::Pg))]
+pub struct NewDatabaseDump<'a> {
+    pub created_at: NaiveDateTime,
+    pub directory: &'a str,
+    pub tar_size: i64,
+    pub tar_sha256: &'a str,
+    pub zip_size: i64,
+    pub zip_sha256: &'a str,
+    pub last_event_id: i64,
+}
+
+impl NewDatabaseDump<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<DatabaseDump> {
+        diesel::insert_into(database_dumps::table)
+            .values(self)
+            .returning(DatabaseDump::as_returning())
+            .get_result(conn)
+    }
+}
+
+/// The changes since a [`DatabaseDump`], derived from the registry events
+/// with IDs in the range `from_event_id` (exclusive) to `to_event_id`
+/// (inclusive).
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(belongs_to(DatabaseDump, foreign_key = dump_id))]
+#[diesel(table_name = database_dump_deltas, check_for_backend(diesel::pg::Pg))]
+pub struct DatabaseDumpDelta {
+    pub id: i32,
+    pub dump_id: i32,
+    pub created_at: NaiveDateTime,
+    pub from_event_id: i64,
+    pub to_event_id: i64,
+    pub path: String,
+    pub size: i64,
+    pub sha256: String,
+}
+
+impl DatabaseDumpDelta {
+    /// Loads all deltas of the given dump, in the order in which they have to
+    /// be applied.
+    pub fn for_dump(conn: &mut impl Conn, dump_id: i32) -> QueryResult<Vec<Self>> {
+        database_dump_deltas::table
+            .filter(database_dump_deltas::dump_id.eq(dump_id))
+            .select(Self::as_select())
+            .order(database_dump_deltas::to_event_id)
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = database_dump_deltas, check_for_backend(diesel::pg::Pg))]
+pub struct NewDatabaseDumpDelta<'a> {
+    pub dump_id: i32,
+    pub from_event_id: i64,
+    pub to_event_id: i64,
+    pub path: &'a str,
+    pub size: i64,
+    pub sha256: &'a str,
+}
+
+impl NewDatabaseDumpDelta<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<DatabaseDumpDelta> {
+        diesel::insert_into(database_dump_deltas::table)
+            .values(self)
+            .returning(DatabaseDumpDelta::as_returning())
+            .get_result(conn)
+    }
+}
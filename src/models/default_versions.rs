@@ -2,6 +2,7 @@ use crate::schema::{default_versions, versions};
 use crate::sql::SemverVersion;
 use crate::util::diesel::Conn;
 use diesel::prelude::*;
+use std::collections::HashMap;
 
 /// A subset of the columns of the `versions` table.
 ///
@@ -87,6 +88,24 @@ pub fn verify_default_version(crate_id: i32, conn: &mut PgConnection) -> QueryRe
     Ok(())
 }
 
+/// Loads the version numbers of the default versions of the specified crates,
+/// keyed by crate ID.
+///
+/// Crates without an entry in the `default_versions` table are not included
+/// in the returned map.
+pub fn load_default_versions(
+    crate_ids: &[i32],
+    conn: &mut impl Conn,
+) -> QueryResult<HashMap<i32, String>> {
+    let default_versions: Vec<(i32, String)> = default_versions::table
+        .inner_join(versions::table)
+        .filter(default_versions::crate_id.eq_any(crate_ids))
+        .select((default_versions::crate_id, versions::num))
+        .load(conn)?;
+
+    Ok(default_versions.into_iter().collect())
+}
+
 fn calculate_default_version(crate_id: i32, conn: &mut impl Conn) -> QueryResult<Version> {
     debug!("Loading all versions for the crate…");
     let versions = versions::table
@@ -0,0 +1,47 @@
+use crate::schema::dependency_policy_exceptions;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+/// A crate that other crates may depend on, even though some of its versions
+/// are quarantined.
+///
+/// New versions that depend on quarantined versions of a crate are rejected
+/// by the publish endpoint, unless an admin added an exception for the
+/// dependency, e.g. because only an old, unused version was quarantined.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = dependency_policy_exceptions, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(crate_id))]
+pub struct DependencyPolicyException {
+    pub crate_id: i32,
+    pub reason: String,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = dependency_policy_exceptions, check_for_backend(diesel::pg::Pg))]
+pub struct NewDependencyPolicyException<'a> {
+    pub crate_id: i32,
+    pub reason: &'a str,
+    pub created_by: i32,
+}
+
+impl NewDependencyPolicyException<'_> {
+    /// Adds the exception, replacing the reason of an existing exception.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<DependencyPolicyException> {
+        use crate::schema::dependency_policy_exceptions::columns::*;
+
+        diesel::insert_into(dependency_policy_exceptions::table)
+            .values(self)
+            .on_conflict(crate_id)
+            .do_update()
+            .set((
+                reason.eq(excluded(reason)),
+                created_by.eq(excluded(created_by)),
+            ))
+            .returning(DependencyPolicyException::as_returning())
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,30 @@
+use crate::schema::deprecated_api_usage;
+use crate::util::diesel::Conn;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+/// A request to a deprecated API surface that was authenticated with an API
+/// token, see `src/deprecation.rs`.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = deprecated_api_usage, check_for_backend(diesel::pg::Pg))]
+pub struct NewDeprecatedApiUsage<'a> {
+    pub surface: &'a str,
+    pub api_token_id: i32,
+}
+
+impl NewDeprecatedApiUsage<'_> {
+    /// Records the request, or increments the number of requests if the
+    /// token was used for the surface before.
+    pub fn record(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        use crate::schema::deprecated_api_usage::columns::*;
+
+        diesel::insert_into(deprecated_api_usage::table)
+            .values(self)
+            .on_conflict((surface, api_token_id))
+            .do_update()
+            .set((requests.eq(requests + 1), last_used_at.eq(now)))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+use secrecy::SecretString;
+
+use crate::models::Crate;
+use crate::schema::dormant_owner_suggestions;
+use crate::util::diesel::Conn;
+
+/// A suggestion to remove an owner of a crate that has not been active for a
+/// while, which was sent to one of the active owners of the crate.
+///
+/// The removal only happens if the active owner confirms it via the link in
+/// the suggestion email.
+#[derive(Debug, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(Crate))]
+pub struct DormantOwnerSuggestion {
+    pub id: i32,
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub recipient_id: i32,
+    #[diesel(deserialize_as = String)]
+    pub token: SecretString,
+    pub created_at: NaiveDateTime,
+}
+
+impl DormantOwnerSuggestion {
+    /// Removes the suggestion belonging to `token` from the database and
+    /// returns it, unless it has already expired.
+    pub fn take_by_token(conn: &mut impl Conn, token: &str) -> QueryResult<Option<Self>> {
+        diesel::delete(dormant_owner_suggestions::table)
+            .filter(dormant_owner_suggestions::token.eq(token))
+            .filter(dormant_owner_suggestions::created_at.gt(now - 30.days()))
+            .get_result(conn)
+            .optional()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = dormant_owner_suggestions, check_for_backend(diesel::pg::Pg))]
+pub struct NewDormantOwnerSuggestion {
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub recipient_id: i32,
+}
+
+impl NewDormantOwnerSuggestion {
+    /// Inserts the suggestion and returns the token that is required to
+    /// confirm the removal.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<SecretString> {
+        diesel::insert_into(dormant_owner_suggestions::table)
+            .values(self)
+            .returning(dormant_owner_suggestions::token)
+            .get_result(conn)
+            .map(SecretString::new)
+    }
+}
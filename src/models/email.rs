@@ -1,8 +1,15 @@
 use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
 use secrecy::SecretString;
 
 use crate::models::User;
-use crate::schema::emails;
+use crate::schema::{email_notification_preferences, emails};
+use crate::sql::{lower, pg_enum};
+use crate::util::diesel::Conn;
+
+/// The maximum number of email addresses per user.
+pub const MAX_EMAILS_PER_USER: i64 = 10;
 
 #[derive(Debug, Queryable, Identifiable, Associations)]
 #[diesel(belongs_to(User))]
@@ -14,11 +21,108 @@ pub struct Email {
     #[diesel(deserialize_as = String, serialize_as = String)]
     pub token: SecretString,
     pub token_generated_at: Option<NaiveDateTime>,
+    pub is_primary: bool,
+    pub undeliverable_at: Option<NaiveDateTime>,
+    pub undeliverable_reason: Option<String>,
+}
+
+impl Email {
+    /// Returns the verified address that the user selected for the given
+    /// class of notifications, falling back to their verified primary address.
+    pub fn find_recipient(
+        conn: &mut impl Conn,
+        user_id: i32,
+        class: NotificationClass,
+    ) -> QueryResult<Option<String>> {
+        let selected = email_notification_preferences::table
+            .inner_join(emails::table)
+            .filter(email_notification_preferences::user_id.eq(user_id))
+            .filter(email_notification_preferences::notification_class.eq(class))
+            .filter(emails::verified.eq(true))
+            .filter(emails::undeliverable_at.is_null())
+            .select(emails::email)
+            .first(conn)
+            .optional()?;
+
+        if selected.is_some() {
+            return Ok(selected);
+        }
+
+        emails::table
+            .filter(emails::user_id.eq(user_id))
+            .filter(emails::is_primary.eq(true))
+            .filter(emails::verified.eq(true))
+            .filter(emails::undeliverable_at.is_null())
+            .select(emails::email)
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns whether the email provider reported the address as
+    /// undeliverable for any of the users that added it.
+    pub fn is_undeliverable(conn: &mut impl Conn, address: &str) -> QueryResult<bool> {
+        diesel::select(diesel::dsl::exists(
+            emails::table
+                .filter(lower(emails::email).eq(address.to_lowercase()))
+                .filter(emails::undeliverable_at.is_not_null()),
+        ))
+        .get_result(conn)
+    }
+
+    /// Marks all rows with the given address as undeliverable, so that no
+    /// further notifications are sent to it until it is verified again.
+    ///
+    /// Returns the number of affected rows. Addresses that are already
+    /// undeliverable keep their original reason.
+    pub fn mark_undeliverable(
+        conn: &mut impl Conn,
+        address: &str,
+        reason: UndeliverableReason,
+    ) -> QueryResult<usize> {
+        diesel::update(emails::table)
+            .filter(lower(emails::email).eq(address.to_lowercase()))
+            .filter(emails::undeliverable_at.is_null())
+            .set((
+                emails::undeliverable_at.eq(now),
+                emails::undeliverable_reason.eq(reason.as_str()),
+            ))
+            .execute(conn)
+    }
 }
 
-#[derive(Debug, Insertable, AsChangeset)]
+/// The reason why the email provider reported an address as undeliverable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndeliverableReason {
+    /// Emails to the address bounced permanently.
+    Bounce,
+    /// The recipient marked an email as spam.
+    Complaint,
+}
+
+impl UndeliverableReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bounce => "bounce",
+            Self::Complaint => "complaint",
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
 #[diesel(table_name = emails, check_for_backend(diesel::pg::Pg))]
 pub struct NewEmail<'a> {
     pub user_id: i32,
     pub email: &'a str,
+    pub is_primary: bool,
+}
+
+// The classes of notifications for which users can choose a different
+// address than their primary one.
+pg_enum! {
+    pub enum NotificationClass {
+        Publishing = 0,
+        Ownership = 1,
+        Digest = 2,
+        Subscriptions = 3,
+    }
 }
@@ -0,0 +1,46 @@
+use crate::schema::impersonation_audit_log;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A request that was made by an admin while impersonating another user.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = impersonation_audit_log, check_for_backend(diesel::pg::Pg))]
+pub struct ImpersonationAuditLogEntry {
+    pub id: i32,
+    pub impersonator_id: i32,
+    pub user_id: i32,
+    pub method: String,
+    pub path: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl ImpersonationAuditLogEntry {
+    /// Returns all log entries of the given impersonated user, with the most
+    /// recent entries first.
+    pub fn for_user(conn: &mut impl Conn, user_id: i32) -> QueryResult<Vec<Self>> {
+        impersonation_audit_log::table
+            .filter(impersonation_audit_log::user_id.eq(user_id))
+            .select(Self::as_select())
+            .order(impersonation_audit_log::id.desc())
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = impersonation_audit_log, check_for_backend(diesel::pg::Pg))]
+pub struct NewImpersonationAuditLogEntry<'a> {
+    pub impersonator_id: i32,
+    pub user_id: i32,
+    pub method: &'a str,
+    pub path: &'a str,
+}
+
+impl NewImpersonationAuditLogEntry<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(impersonation_audit_log::table)
+            .values(self)
+            .execute(conn)
+            .map(|_| ())
+    }
+}
@@ -6,21 +6,23 @@ use diesel::dsl;
 use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::sql_types::{Bool, Text};
+use lettre::Address;
 use secrecy::{ExposeSecret, SecretString};
 
 use crate::app::App;
 use crate::controllers::helpers::pagination::*;
-use crate::email::Email;
+use crate::email::{Email, Notification};
 use crate::models::version::TopVersions;
 use crate::models::{
-    CrateOwner, CrateOwnerInvitation, Dependency, NewCrateOwnerInvitationOutcome, Owner, OwnerKind,
-    ReverseDependency, User, Version,
+    CrateOwner, CrateOwnerEmailInvitation, CrateOwnerInvitation, Dependency, NewCrateOwnerAction,
+    NewCrateOwnerInvitationOutcome, NewRegistryEvent, NotificationClass, Owner, OwnerKind,
+    RegistryEventKind, ReverseDependency, User, Version,
 };
-use crate::util::errors::{version_not_found, AppResult};
+use crate::util::errors::{bad_request, version_not_found, AppResult};
 
 use crate::models::helpers::with_count::*;
 use crate::schema::*;
-use crate::sql::canon_crate_name;
+use crate::sql::{canon_crate_name, pg_enum};
 use crate::util::diesel::Conn;
 
 #[derive(Debug, Queryable, Identifiable, Associations, Clone, Copy)]
@@ -35,6 +37,24 @@ pub struct RecentCrateDownloads {
     pub downloads: i32,
 }
 
+/// Precomputed download totals of a crate, which are refreshed by the
+/// `update_downloads` background job.
+#[derive(Debug, Queryable, Identifiable, Selectable, Clone, Copy)]
+#[diesel(
+    table_name = crate_download_summaries,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(crate_id),
+)]
+pub struct CrateDownloadSummary {
+    pub crate_id: i32,
+    pub last_day: i64,
+    pub last_week: i64,
+    pub last_month: i64,
+    pub last_90_days: i64,
+    pub previous_week: i64,
+    pub computed_at: NaiveDateTime,
+}
+
 #[derive(Debug, Clone, Queryable, Identifiable, AsChangeset, QueryableByName, Selectable)]
 #[diesel(table_name = crates, check_for_backend(diesel::pg::Pg))]
 pub struct Crate {
@@ -48,6 +68,18 @@ pub struct Crate {
     pub repository: Option<String>,
     pub max_upload_size: Option<i32>,
     pub max_features: Option<i16>,
+    pub visibility: CrateVisibility,
+    pub max_dependencies: Option<i16>,
+    pub repository_verified_at: Option<NaiveDateTime>,
+    pub maintenance_wanted_at: Option<NaiveDateTime>,
+    pub max_feature_values: Option<i16>,
+}
+
+pg_enum! {
+    pub enum CrateVisibility {
+        Public = 0,
+        Private = 1,
+    }
 }
 
 /// We literally never want to select `textsearchable_index_col`
@@ -63,6 +95,11 @@ type AllColumns = (
     crates::repository,
     crates::max_upload_size,
     crates::max_features,
+    crates::visibility,
+    crates::max_dependencies,
+    crates::repository_verified_at,
+    crates::maintenance_wanted_at,
+    crates::max_feature_values,
 );
 
 pub const ALL_COLUMNS: AllColumns = (
@@ -76,6 +113,11 @@ pub const ALL_COLUMNS: AllColumns = (
     crates::repository,
     crates::max_upload_size,
     crates::max_features,
+    crates::visibility,
+    crates::max_dependencies,
+    crates::repository_verified_at,
+    crates::maintenance_wanted_at,
+    crates::max_feature_values,
 );
 
 pub const MAX_NAME_LENGTH: usize = 64;
@@ -98,23 +140,34 @@ pub struct NewCrate<'a> {
     pub repository: Option<&'a str>,
     pub max_upload_size: Option<i32>,
     pub max_features: Option<i16>,
+    pub max_dependencies: Option<i16>,
+    pub max_feature_values: Option<i16>,
 }
 
 impl<'a> NewCrate<'a> {
     pub fn update(&self, conn: &mut impl Conn) -> QueryResult<Crate> {
         use diesel::update;
 
-        update(crates::table)
-            .filter(canon_crate_name(crates::name).eq(canon_crate_name(self.name)))
-            .set((
-                crates::description.eq(self.description),
-                crates::homepage.eq(self.homepage),
-                crates::documentation.eq(self.documentation),
-                crates::readme.eq(self.readme),
-                crates::repository.eq(self.repository),
-            ))
-            .returning(Crate::as_returning())
-            .get_result(conn)
+        conn.transaction(|conn| {
+            // Changing the repository invalidates its verification
+            update(crates::table)
+                .filter(canon_crate_name(crates::name).eq(canon_crate_name(self.name)))
+                .filter(crates::repository.is_distinct_from(self.repository))
+                .set(crates::repository_verified_at.eq(None::<NaiveDateTime>))
+                .execute(conn)?;
+
+            update(crates::table)
+                .filter(canon_crate_name(crates::name).eq(canon_crate_name(self.name)))
+                .set((
+                    crates::description.eq(self.description),
+                    crates::homepage.eq(self.homepage),
+                    crates::documentation.eq(self.documentation),
+                    crates::readme.eq(self.readme),
+                    crates::repository.eq(self.repository),
+                ))
+                .returning(Crate::as_returning())
+                .get_result(conn)
+        })
     }
 
     pub fn create(&self, conn: &mut impl Conn, user_id: i32) -> QueryResult<Crate> {
@@ -137,6 +190,9 @@ impl<'a> NewCrate<'a> {
                 .values(&owner)
                 .execute(conn)?;
 
+            NewCrateOwnerAction::add(krate.id, user_id, OwnerKind::User, Some(user_id))
+                .insert(conn)?;
+
             Ok(krate)
         })
     }
@@ -328,11 +384,8 @@ impl Crate {
     /// Return both the newest (most recently updated) and
     /// highest version (in semver order) for the current crate.
     pub fn top_versions(&self, conn: &mut impl Conn) -> QueryResult<TopVersions> {
-        Ok(TopVersions::from_date_version_pairs(
-            self.versions()
-                .select((versions::created_at, versions::num))
-                .load(conn)?,
-        ))
+        let mut top_versions = TopVersions::for_crates(conn, &[self.id])?;
+        Ok(top_versions.remove(&self.id).unwrap_or_default())
     }
 
     pub fn owners(&self, conn: &mut impl Conn) -> QueryResult<Vec<Owner>> {
@@ -354,47 +407,40 @@ impl Crate {
         Ok(users.chain(teams).collect())
     }
 
+    /// Invites a user, or adds a team, as an owner of the crate.
+    ///
+    /// `current_owners` is used to give invited users some context about the
+    /// crate in the invitation email.
     pub fn owner_add(
         &self,
         app: &App,
         conn: &mut impl Conn,
         req_user: &User,
         login: &str,
+        current_owners: &[Owner],
     ) -> AppResult<String> {
         use diesel::insert_into;
 
+        if login.contains('@') {
+            return self.owner_add_by_email(app, conn, req_user, login, current_owners);
+        }
+
         let owner = Owner::find_or_create_by_login(app, conn, req_user, login)?;
 
         match owner {
             // Users are invited and must accept before being added
             Owner::User(user) => {
-                let config = &app.config;
-                match CrateOwnerInvitation::create(user.id, req_user.id, self.id, conn, config)? {
-                    NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
-                        if let Ok(Some(recipient)) = user.verified_email(conn) {
-                            // Swallow any error. Whether or not the email is sent, the invitation
-                            // entry will be created in the database and the user will see the
-                            // invitation when they visit https://crates.io/me/pending-invites/.
-                            let email = OwnerInviteEmail {
-                                user_name: &req_user.gh_login,
-                                domain: &app.emails.domain,
-                                crate_name: &self.name,
-                                token: plaintext_token,
-                            };
-
-                            let _ = app.emails.send(&recipient, email);
-                        }
-
-                        Ok(format!(
-                            "user {} has been invited to be an owner of crate {}",
-                            user.gh_login, self.name
-                        ))
-                    }
-                    NewCrateOwnerInvitationOutcome::AlreadyExists => Ok(format!(
+                let invited = self.invite_user(app, conn, req_user, &user, current_owners)?;
+                Ok(match invited {
+                    true => format!(
+                        "user {} has been invited to be an owner of crate {}",
+                        user.gh_login, self.name
+                    ),
+                    false => format!(
                         "user {} already has a pending invitation to be an owner of crate {}",
                         user.gh_login, self.name
-                    )),
-                }
+                    ),
+                })
             }
             // Teams are added as owners immediately
             owner @ Owner::Team(_) => {
@@ -411,6 +457,10 @@ impl Crate {
                     .set(crate_owners::deleted.eq(false))
                     .execute(conn)?;
 
+                let by = Some(req_user.id);
+                NewCrateOwnerAction::add(self.id, owner.id(), OwnerKind::Team, by).insert(conn)?;
+                NewRegistryEvent::krate(RegistryEventKind::OwnerChange, &self.name).insert(conn)?;
+
                 Ok(format!(
                     "team {} has been added as an owner of crate {}",
                     owner.login(),
@@ -420,13 +470,135 @@ impl Crate {
         }
     }
 
-    pub fn owner_remove(&self, conn: &mut impl Conn, login: &str) -> AppResult<()> {
+    /// Invites the owner of the given email address.
+    ///
+    /// If a user verified the email address, they are invited like any other
+    /// user. Otherwise a pending invitation is stored, which is turned into a
+    /// regular invitation once a user with that verified email address logs
+    /// in. The response message is the same in both cases to avoid revealing
+    /// which account the email address belongs to.
+    fn owner_add_by_email(
+        &self,
+        app: &App,
+        conn: &mut impl Conn,
+        req_user: &User,
+        email: &str,
+        current_owners: &[Owner],
+    ) -> AppResult<String> {
+        let email = email.trim();
+        if email.parse::<Address>().is_err() {
+            return Err(bad_request(format_args!(
+                "`{email}` is not a valid email address"
+            )));
+        }
+
+        let invited = match User::find_by_verified_email(conn, email).optional()? {
+            Some(user) => {
+                let is_owner = current_owners
+                    .iter()
+                    .any(|owner| matches!(owner, Owner::User(owner) if owner.id == user.id));
+
+                // Existing owners are not invited again, but the response
+                // does not reveal that either.
+                is_owner || self.invite_user(app, conn, req_user, &user, current_owners)?
+            }
+            None => {
+                let config = &app.config;
+                let created =
+                    CrateOwnerEmailInvitation::create(email, req_user.id, self.id, conn, config)?;
+
+                if created {
+                    let email_body = OwnerEmailInviteEmail {
+                        user_name: &req_user.gh_login,
+                        domain: &app.emails.domain,
+                        crate_name: &self.name,
+                        crate_description: self.description.as_deref(),
+                    };
+
+                    // Swallow any error, the invitation is claimed on login anyway.
+                    let _ = app.emails.send(conn, email, email_body);
+                }
+
+                created
+            }
+        };
+
+        Ok(match invited {
+            true => format!(
+                "{email} has been invited to be an owner of crate {}",
+                self.name
+            ),
+            false => format!(
+                "{email} already has a pending invitation to be an owner of crate {}",
+                self.name
+            ),
+        })
+    }
+
+    /// Creates an ownership invitation for the given user and notifies them
+    /// via email.
+    ///
+    /// Returns `false` if the user already has a pending invitation.
+    fn invite_user(
+        &self,
+        app: &App,
+        conn: &mut impl Conn,
+        req_user: &User,
+        user: &User,
+        current_owners: &[Owner],
+    ) -> QueryResult<bool> {
+        let config = &app.config;
+        match CrateOwnerInvitation::create(user.id, req_user.id, self.id, conn, config)? {
+            NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
+                // Swallow any error. Whether or not the email is sent, the invitation
+                // entry will be created in the database and the user will see the
+                // invitation when they visit https://crates.io/me/pending-invites/.
+                let email = OwnerInviteEmail {
+                    user_name: &req_user.gh_login,
+                    domain: &app.emails.domain,
+                    crate_name: &self.name,
+                    crate_description: self.description.as_deref(),
+                    current_owners: current_owners.iter().map(Owner::login).collect(),
+                    invitee: &user.gh_login,
+                    token: plaintext_token,
+                };
+
+                let _ = app.emails.send_notification(user.id, email, conn);
+
+                Ok(true)
+            }
+            NewCrateOwnerInvitationOutcome::AlreadyExists => Ok(false),
+        }
+    }
+
+    /// Removes a user or team from the owners of the crate, and records
+    /// that `req_user` removed them in the ownership history.
+    pub fn owner_remove(
+        &self,
+        conn: &mut impl Conn,
+        req_user: &User,
+        login: &str,
+    ) -> AppResult<()> {
         let owner = Owner::find_by_login(conn, login)?;
 
-        let target = crate_owners::table.find((self.id(), owner.id(), owner.kind()));
-        diesel::update(target)
+        let target = crate_owners::table
+            .find((self.id(), owner.id(), owner.kind()))
+            .filter(crate_owners::deleted.eq(false));
+        let removed = diesel::update(target)
             .set(crate_owners::deleted.eq(true))
             .execute(conn)?;
+
+        if removed > 0 {
+            let kind = match owner {
+                Owner::User(_) => OwnerKind::User,
+                Owner::Team(_) => OwnerKind::Team,
+            };
+            let by = Some(req_user.id);
+            NewCrateOwnerAction::remove(self.id, owner.id(), kind, by).insert(conn)?;
+        }
+
+        NewRegistryEvent::krate(RegistryEventKind::OwnerChange, &self.name).insert(conn)?;
+
         Ok(())
     }
 
@@ -452,6 +624,10 @@ impl Crate {
     }
 
     /// Gather all the necessary data to write an index metadata file
+    ///
+    /// All features are returned in the `features` field, and have to be
+    /// encoded with [`crates_io_index::Crate::encode_features()`] before the
+    /// entries are written to the index.
     pub fn index_metadata(&self, conn: &mut impl Conn) -> QueryResult<Vec<crates_io_index::Crate>> {
         let mut versions: Vec<Version> = self.all_versions().load(conn)?;
 
@@ -501,18 +677,6 @@ impl Crate {
 
                 let features: BTreeMap<String, Vec<String>> =
                     serde_json::from_value(version.features).unwrap_or_default();
-                let (features, features2): (BTreeMap<_, _>, BTreeMap<_, _>) =
-                    features.into_iter().partition(|(_k, vals)| {
-                        !vals
-                            .iter()
-                            .any(|v| v.starts_with("dep:") || v.contains("?/"))
-                    });
-
-                let (features2, v) = if features2.is_empty() {
-                    (None, None)
-                } else {
-                    (Some(features2), Some(2))
-                };
 
                 let krate = crates_io_index::Crate {
                     name: self.name.clone(),
@@ -523,8 +687,9 @@ impl Crate {
                     features,
                     links: version.links,
                     rust_version: version.rust_version,
-                    features2,
-                    v,
+                    channel: version.channel,
+                    features2: None,
+                    v: None,
                 };
 
                 Ok(krate)
@@ -537,6 +702,9 @@ struct OwnerInviteEmail<'a> {
     user_name: &'a str,
     domain: &'a str,
     crate_name: &'a str,
+    crate_description: Option<&'a str>,
+    current_owners: Vec<&'a str>,
+    invitee: &'a str,
     token: SecretString,
 }
 
@@ -544,15 +712,76 @@ impl Email for OwnerInviteEmail<'_> {
     const SUBJECT: &'static str = "Crate ownership invitation";
 
     fn body(&self) -> String {
-        format!(
-            "{user_name} has invited you to become an owner of the crate {crate_name}!\n
-Visit https://{domain}/accept-invite/{token} to accept this invitation,
-or go to https://{domain}/me/pending-invites to manage all of your crate ownership invitations.",
+        let domain = self.domain;
+        let crate_name = self.crate_name;
+
+        let mut body = format!(
+            "{user_name} (https://{domain}/users/{user_name}) has invited you to become an owner \
+             of the crate {crate_name} (https://{domain}/crates/{crate_name})!\n",
             user_name = self.user_name,
-            domain = self.domain,
-            crate_name = self.crate_name,
+        );
+
+        if let Some(description) = self.crate_description.map(str::trim) {
+            if !description.is_empty() {
+                body.push_str(&format!("\n{description}\n"));
+            }
+        }
+
+        body.push_str("\nOwners of the crate after accepting this invitation:\n\n");
+        for owner in &self.current_owners {
+            body.push_str(&format!("  {owner}\n"));
+        }
+        body.push_str(&format!("+ {} (you)\n", self.invitee));
+
+        body.push_str(&format!(
+            "\nVisit https://{domain}/accept-invite/{token} to accept this invitation,
+or go to https://{domain}/me/pending-invites to manage all of your crate ownership invitations.",
             token = self.token.expose_secret(),
-        )
+        ));
+
+        body
+    }
+}
+
+impl Notification for OwnerInviteEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Ownership;
+}
+
+/// Invitation for an email address that does not belong to a crates.io
+/// account yet.
+struct OwnerEmailInviteEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+    crate_name: &'a str,
+    crate_description: Option<&'a str>,
+}
+
+impl Email for OwnerEmailInviteEmail<'_> {
+    const SUBJECT: &'static str = "Crate ownership invitation";
+
+    fn body(&self) -> String {
+        let domain = self.domain;
+        let crate_name = self.crate_name;
+
+        let mut body = format!(
+            "{user_name} (https://{domain}/users/{user_name}) has invited you to become an owner \
+             of the crate {crate_name} (https://{domain}/crates/{crate_name})!\n",
+            user_name = self.user_name,
+        );
+
+        if let Some(description) = self.crate_description.map(str::trim) {
+            if !description.is_empty() {
+                body.push_str(&format!("\n{description}\n"));
+            }
+        }
+
+        body.push_str(&format!(
+            "\nTo accept this invitation, log in to https://{domain} with your GitHub account \
+and verify this email address in your account settings at https://{domain}/settings/profile.
+The invitation will then be listed at https://{domain}/me/pending-invites.",
+        ));
+
+        body
     }
 }
 
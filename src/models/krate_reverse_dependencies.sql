@@ -12,6 +12,11 @@ WITH filtered_default_versions as (
         SELECT 1
         FROM versions
         WHERE id = version_id and yanked
+    ) AND NOT EXISTS (
+        -- Filter out private crates
+        SELECT 1
+        FROM crates
+        WHERE crates.id = default_versions.crate_id and visibility <> 0
     )
 )
 SELECT
@@ -0,0 +1,46 @@
+use crate::schema::metadata_findings;
+use crate::sql::pg_enum;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+pg_enum! {
+    pub enum MetadataRule {
+        TooManyFeatures = 0,
+        TooManyEnabledFeatures = 1,
+        InvalidFeatureName = 2,
+        InvalidFeature = 3,
+        TooManyDependencies = 4,
+        InvalidLicense = 5,
+        InvalidRustVersion = 6,
+    }
+}
+
+/// A violation of the current publish validation rules, that was found in
+/// the metadata of an already published version.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = metadata_findings, check_for_backend(diesel::pg::Pg))]
+pub struct MetadataFinding {
+    pub id: i32,
+    pub version_id: i32,
+    pub rule: MetadataRule,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Insertable)]
+#[diesel(table_name = metadata_findings, check_for_backend(diesel::pg::Pg))]
+pub struct NewMetadataFinding {
+    pub version_id: i32,
+    pub rule: MetadataRule,
+    pub message: String,
+}
+
+impl NewMetadataFinding {
+    pub fn new(version_id: i32, rule: MetadataRule, message: impl Into<String>) -> Self {
+        Self {
+            version_id,
+            rule,
+            message: message.into(),
+        }
+    }
+}
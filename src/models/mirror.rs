@@ -0,0 +1,110 @@
+use crate::schema::mirrors;
+use crate::sql::coalesce;
+use crate::storage::crate_file_path;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+/// A mirror of the crate files that downloads can be redirected to.
+///
+/// See [`crate::mirrors`] for how downloads are redirected.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = mirrors, check_for_backend(diesel::pg::Pg))]
+pub struct Mirror {
+    pub id: i32,
+    pub name: String,
+    pub base_url: String,
+    pub region: String,
+    pub enabled: bool,
+    pub created_at: NaiveDateTime,
+    pub checked_at: Option<NaiveDateTime>,
+    pub verified_at: Option<NaiveDateTime>,
+}
+
+impl Mirror {
+    pub fn all(conn: &mut impl Conn) -> QueryResult<Vec<Self>> {
+        mirrors::table
+            .select(Self::as_select())
+            .order(mirrors::name)
+            .load(conn)
+    }
+
+    pub fn find_by_name(conn: &mut impl Conn, name: &str) -> QueryResult<Option<Self>> {
+        mirrors::table
+            .filter(mirrors::name.eq(name))
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the enabled mirrors that serve the given continent.
+    pub fn enabled_in_region(conn: &mut impl Conn, region: &str) -> QueryResult<Vec<Self>> {
+        mirrors::table
+            .filter(mirrors::enabled)
+            .filter(mirrors::region.eq(region))
+            .select(Self::as_select())
+            .order(mirrors::id)
+            .load(conn)
+    }
+
+    /// Returns the URL of a crate file on the mirror.
+    pub fn crate_location(&self, name: &str, version: &str) -> String {
+        let base_url = self.base_url.trim_end_matches('/');
+        let path = crate_file_path(name, version);
+        format!("{base_url}/{path}").replace('+', "%2B")
+    }
+
+    /// Records a health check of the mirror, and whether it served the
+    /// expected crate file.
+    pub fn record_check(&self, conn: &mut impl Conn, verified: bool) -> QueryResult<usize> {
+        if verified {
+            diesel::update(self)
+                .set((mirrors::checked_at.eq(now), mirrors::verified_at.eq(now)))
+                .execute(conn)
+        } else {
+            diesel::update(self)
+                .set(mirrors::checked_at.eq(now))
+                .execute(conn)
+        }
+    }
+
+    pub fn set_enabled(&self, conn: &mut impl Conn, enabled: bool) -> QueryResult<usize> {
+        diesel::update(self)
+            .set(mirrors::enabled.eq(enabled))
+            .execute(conn)
+    }
+
+    /// Disables the enabled mirrors that have not been verified since
+    /// `cutoff`, and returns their names.
+    ///
+    /// Mirrors that have never been verified are disabled once they were
+    /// registered before `cutoff`.
+    pub fn disable_stale(conn: &mut impl Conn, cutoff: NaiveDateTime) -> QueryResult<Vec<String>> {
+        let last_verified_at = coalesce(mirrors::verified_at, mirrors::created_at);
+
+        diesel::update(mirrors::table)
+            .filter(mirrors::enabled)
+            .filter(last_verified_at.lt(cutoff))
+            .set(mirrors::enabled.eq(false))
+            .returning(mirrors::name)
+            .get_results(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = mirrors, check_for_backend(diesel::pg::Pg))]
+pub struct NewMirror<'a> {
+    pub name: &'a str,
+    pub base_url: &'a str,
+    pub region: &'a str,
+}
+
+impl NewMirror<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<Mirror> {
+        diesel::insert_into(mirrors::table)
+            .values(self)
+            .returning(Mirror::as_returning())
+            .get_result(conn)
+    }
+}
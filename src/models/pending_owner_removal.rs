@@ -0,0 +1,138 @@
+use crate::models::{Crate, Owner, OwnerKind, User};
+use crate::schema::{crates, critical_crates, pending_owner_removals, teams};
+use crate::util::diesel::Conn;
+use crate::util::errors::{bad_request, AppResult};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A removal of an owner of a critical crate that is held back until two
+/// admins approved it, or until the waiting period is over.
+///
+/// The waiting period gives the other owners and the crates.io team a chance
+/// to notice and object to a removal that was requested by a compromised
+/// account.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = pending_owner_removals, check_for_backend(diesel::pg::Pg))]
+pub struct PendingOwnerRemoval {
+    pub id: i32,
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub owner_kind: OwnerKind,
+    pub requested_by: i32,
+    pub approved_by: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl PendingOwnerRemoval {
+    pub fn find(conn: &mut impl Conn, id: i32) -> QueryResult<Self> {
+        pending_owner_removals::table
+            .find(id)
+            .select(Self::as_select())
+            .first(conn)
+    }
+
+    /// Returns all pending removals, oldest first.
+    pub fn all(conn: &mut impl Conn) -> QueryResult<Vec<Self>> {
+        pending_owner_removals::table
+            .select(Self::as_select())
+            .order(pending_owner_removals::id)
+            .load(conn)
+    }
+
+    /// Returns the pending removals that were requested before `cutoff`, and
+    /// the pending removals of crates that are not critical anymore.
+    pub fn due(conn: &mut impl Conn, cutoff: NaiveDateTime) -> QueryResult<Vec<Self>> {
+        let critical_crate_ids = critical_crates::table.select(critical_crates::crate_id);
+
+        pending_owner_removals::table
+            .filter(
+                pending_owner_removals::created_at
+                    .lt(cutoff)
+                    .or(pending_owner_removals::crate_id.ne_all(critical_crate_ids)),
+            )
+            .select(Self::as_select())
+            .order(pending_owner_removals::id)
+            .load(conn)
+    }
+
+    /// Loads the user or team that is being removed.
+    pub fn owner(&self, conn: &mut impl Conn) -> QueryResult<Owner> {
+        Ok(match self.owner_kind {
+            OwnerKind::User => Owner::User(User::find(conn, self.owner_id)?),
+            OwnerKind::Team => Owner::Team(teams::table.find(self.owner_id).first(conn)?),
+        })
+    }
+
+    /// Records the approval of `admin`, and applies the removal if it was
+    /// already approved by another admin before.
+    ///
+    /// Returns `true` if the removal was applied.
+    pub fn approve(&self, conn: &mut impl Conn, admin: &User) -> AppResult<bool> {
+        match self.approved_by {
+            None => {
+                diesel::update(self)
+                    .set(pending_owner_removals::approved_by.eq(admin.id))
+                    .execute(conn)?;
+
+                Ok(false)
+            }
+            Some(approved_by) if approved_by == admin.id => Err(bad_request(
+                "the removal has to be approved by a second admin",
+            )),
+            Some(_) => {
+                self.apply(conn)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Removes the owner from the crate and deletes the pending removal.
+    ///
+    /// The removal is recorded in the ownership history as performed by the
+    /// user that requested it. It fails without changing anything if the
+    /// crate would be left without individual owners.
+    pub fn apply(&self, conn: &mut impl Conn) -> AppResult<()> {
+        conn.transaction(|conn| {
+            let krate: Crate = crates::table
+                .find(self.crate_id)
+                .select(Crate::as_select())
+                .first(conn)?;
+
+            let owner = self.owner(conn)?;
+            let requested_by = User::find(conn, self.requested_by)?;
+            krate.owner_remove(conn, &requested_by, owner.login())?;
+
+            if User::owning(&krate, conn)?.is_empty() {
+                return Err(bad_request(
+                    "cannot remove the last individual owner of a crate",
+                ));
+            }
+
+            diesel::delete(self).execute(conn)?;
+
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = pending_owner_removals, check_for_backend(diesel::pg::Pg))]
+pub struct NewPendingOwnerRemoval {
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub owner_kind: OwnerKind,
+    pub requested_by: i32,
+}
+
+impl NewPendingOwnerRemoval {
+    /// Inserts the pending removal, unless the removal of the same owner was
+    /// already requested before.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(pending_owner_removals::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
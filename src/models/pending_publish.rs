@@ -0,0 +1,70 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+use secrecy::SecretString;
+
+use crate::models::User;
+use crate::schema::pending_publishes;
+use crate::util::diesel::Conn;
+
+/// A publish that is held back until the user confirms it via the link in
+/// the confirmation email.
+#[derive(Debug, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(User))]
+pub struct PendingPublish {
+    pub id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub crate_name: String,
+    pub version: String,
+    pub body: Vec<u8>,
+    #[diesel(deserialize_as = String)]
+    pub token: SecretString,
+    pub created_at: NaiveDateTime,
+    pub ci_system: Option<String>,
+    pub ci_run_url: Option<String>,
+}
+
+impl PendingPublish {
+    /// Removes the pending publish belonging to `token` from the database
+    /// and returns it, unless it has already expired.
+    pub fn take_by_token(conn: &mut impl Conn, token: &str) -> QueryResult<Option<Self>> {
+        diesel::delete(pending_publishes::table)
+            .filter(pending_publishes::token.eq(token))
+            .filter(pending_publishes::created_at.gt(now - 1.day()))
+            .get_result(conn)
+            .optional()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = pending_publishes, check_for_backend(diesel::pg::Pg))]
+pub struct NewPendingPublish<'a> {
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub crate_name: &'a str,
+    pub version: &'a str,
+    pub body: &'a [u8],
+    pub ci_system: Option<&'a str>,
+    pub ci_run_url: Option<&'a str>,
+}
+
+impl NewPendingPublish<'_> {
+    /// Inserts the pending publish and returns the token that is required to
+    /// confirm it.
+    ///
+    /// Expired pending publishes of the same user are removed at the same
+    /// time, since they can't be confirmed anymore.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<SecretString> {
+        diesel::delete(pending_publishes::table)
+            .filter(pending_publishes::user_id.eq(self.user_id))
+            .filter(pending_publishes::created_at.le(now - 1.day()))
+            .execute(conn)?;
+
+        diesel::insert_into(pending_publishes::table)
+            .values(self)
+            .returning(pending_publishes::token)
+            .get_result(conn)
+            .map(SecretString::new)
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+use secrecy::SecretString;
+
+use crate::models::User;
+use crate::schema::pending_yanks;
+use crate::util::diesel::Conn;
+
+/// A yank of a heavily downloaded version that is held back until one of the
+/// owners of the crate confirms it via the link in the confirmation email.
+#[derive(Debug, Queryable, Identifiable, Associations)]
+#[diesel(belongs_to(User))]
+pub struct PendingYank {
+    pub id: i32,
+    pub version_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    #[diesel(deserialize_as = String)]
+    pub token: SecretString,
+    pub created_at: NaiveDateTime,
+    pub reason: Option<String>,
+}
+
+impl PendingYank {
+    /// Removes the pending yank belonging to `token` from the database and
+    /// returns it, unless it has already expired.
+    pub fn take_by_token(conn: &mut impl Conn, token: &str) -> QueryResult<Option<Self>> {
+        diesel::delete(pending_yanks::table)
+            .filter(pending_yanks::token.eq(token))
+            .filter(pending_yanks::created_at.gt(now - 1.day()))
+            .get_result(conn)
+            .optional()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = pending_yanks, check_for_backend(diesel::pg::Pg))]
+pub struct NewPendingYank<'a> {
+    pub version_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub reason: Option<&'a str>,
+}
+
+impl NewPendingYank<'_> {
+    /// Inserts the pending yank and returns the token that is required to
+    /// confirm it.
+    ///
+    /// Expired pending yanks of the same version are removed at the same
+    /// time, since they can't be confirmed anymore.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<SecretString> {
+        diesel::delete(pending_yanks::table)
+            .filter(pending_yanks::version_id.eq(self.version_id))
+            .filter(pending_yanks::created_at.le(now - 1.day()))
+            .execute(conn)?;
+
+        diesel::insert_into(pending_yanks::table)
+            .values(self)
+            .returning(pending_yanks::token)
+            .get_result(conn)
+            .map(SecretString::new)
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use secrecy::ExposeSecret;
+
+use crate::config;
+use crate::middleware::session::SessionExtension;
+use crate::schema::persistent_sessions;
+use crate::util::diesel::Conn;
+use crate::util::token::{HashedToken, PlainToken};
+
+/// The session key of the token that identifies the persistent session.
+pub const SESSION_TOKEN_KEY: &str = "session_token";
+
+/// How often the `last_used_at` column of a session is updated. The idle
+/// timeout is measured in days, so there is no need to write to the database
+/// on every request.
+const LAST_USED_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// The model representing a row in the `persistent_sessions` database table.
+///
+/// The signed session cookie only contains a random token, whose hash is
+/// stored in this table. This allows the server to expire sessions after
+/// [`config::Server::session_lifetime`] or after being idle for
+/// [`config::Server::session_idle_timeout`], and to revoke sessions when
+/// users sign out.
+#[derive(Clone, Debug, Identifiable, Queryable, Selectable)]
+#[diesel(table_name = persistent_sessions, check_for_backend(diesel::pg::Pg))]
+pub struct PersistentSession {
+    pub id: i64,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub revoked: bool,
+}
+
+impl PersistentSession {
+    /// Creates a new session for the given user and returns it together with
+    /// the plaintext token that has to be stored in the session cookie.
+    pub fn create(conn: &mut impl Conn, user_id: i32) -> QueryResult<(Self, PlainToken)> {
+        let token = PlainToken::generate();
+
+        let session = diesel::insert_into(persistent_sessions::table)
+            .values((
+                persistent_sessions::user_id.eq(user_id),
+                persistent_sessions::hashed_token.eq(token.hashed()),
+            ))
+            .returning(PersistentSession::as_returning())
+            .get_result(conn)?;
+
+        Ok((session, token))
+    }
+
+    /// Looks up the unrevoked and unexpired session for the given token and
+    /// marks it as used, unless it was already marked as used within the
+    /// last hour.
+    pub fn find_by_token(
+        conn: &mut impl Conn,
+        token: &HashedToken,
+        config: &config::Server,
+    ) -> QueryResult<Self> {
+        use diesel::{dsl::now, update};
+
+        let created_after = cutoff(config.session_lifetime);
+        let used_after = cutoff(config.session_idle_timeout);
+
+        let session: Self = persistent_sessions::table
+            .filter(persistent_sessions::revoked.eq(false))
+            .filter(persistent_sessions::created_at.gt(created_after))
+            .filter(persistent_sessions::last_used_at.gt(used_after))
+            .filter(persistent_sessions::hashed_token.eq(token))
+            .select(PersistentSession::as_select())
+            .first(conn)?;
+
+        if session.last_used_at < cutoff(LAST_USED_UPDATE_INTERVAL) {
+            // If the database is in read only mode, we can't update
+            // last_used_at, which is not a reason to reject the session.
+            let result = conn.transaction(|conn| {
+                update(persistent_sessions::table.find(session.id))
+                    .set(persistent_sessions::last_used_at.eq(now))
+                    .execute(conn)
+            });
+
+            if let Err(error) = result {
+                warn!(
+                    "Failed to update the last use of session {}: {error}",
+                    session.id
+                );
+            }
+        }
+
+        Ok(session)
+    }
+
+    /// Looks up the session whose token is stored in the session cookie.
+    ///
+    /// Returns `None` if the cookie does not contain a token, or if the
+    /// session was revoked or has expired.
+    pub fn from_session(
+        session: &SessionExtension,
+        conn: &mut impl Conn,
+        config: &config::Server,
+    ) -> QueryResult<Option<Self>> {
+        let Some(token) = session.get(SESSION_TOKEN_KEY) else {
+            return Ok(None);
+        };
+
+        let Ok(token) = HashedToken::parse(&token) else {
+            return Ok(None);
+        };
+
+        Self::find_by_token(conn, &token, config).optional()
+    }
+
+    /// Creates a persistent session for a cookie that was issued before
+    /// sessions were stored server-side, and that therefore only contains
+    /// the ID of the user.
+    ///
+    /// The new session starts its lifetime now, so that users are not
+    /// signed out when the persistent sessions are rolled out.
+    pub fn upgrade_legacy(
+        session: &SessionExtension,
+        conn: &mut impl Conn,
+        user_id: i32,
+    ) -> QueryResult<Self> {
+        let (persistent_session, token) = Self::create(conn, user_id)?;
+        session.insert(SESSION_TOKEN_KEY.to_string(), token.expose_secret().clone());
+
+        Ok(persistent_session)
+    }
+
+    /// Replaces the session identifier of the cookie with the one of a new
+    /// persistent session for the given user, and revokes the previous one.
+    ///
+    /// This must happen whenever the privileges of the session change (e.g.
+    /// when signing in), so that a session identifier that was planted or
+    /// stolen before can not be used with the new privileges.
+    pub fn rotate(
+        session: &SessionExtension,
+        conn: &mut impl Conn,
+        user_id: i32,
+    ) -> QueryResult<()> {
+        Self::revoke(session, conn)?;
+
+        let (_, token) = Self::create(conn, user_id)?;
+        session.insert(SESSION_TOKEN_KEY.to_string(), token.expose_secret().clone());
+
+        Ok(())
+    }
+
+    /// Removes the session identifier from the cookie and revokes the
+    /// corresponding persistent session.
+    pub fn revoke(session: &SessionExtension, conn: &mut impl Conn) -> QueryResult<()> {
+        let Some(token) = session.remove(SESSION_TOKEN_KEY) else {
+            return Ok(());
+        };
+
+        let Ok(token) = HashedToken::parse(&token) else {
+            return Ok(());
+        };
+
+        diesel::update(persistent_sessions::table)
+            .filter(persistent_sessions::hashed_token.eq(token))
+            .set(persistent_sessions::revoked.eq(true))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the point in time that lies the given duration in the past.
+fn cutoff(duration: std::time::Duration) -> NaiveDateTime {
+    chrono::Duration::from_std(duration)
+        .ok()
+        .and_then(|duration| Utc::now().naive_utc().checked_sub_signed(duration))
+        .unwrap_or(NaiveDateTime::MIN)
+}
@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde_json::Value;
+
+use crate::schema::publish_idempotency_keys;
+use crate::util::diesel::Conn;
+
+/// The stored outcome of a successful publish that was sent with an
+/// `Idempotency-Key` header.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = publish_idempotency_keys, check_for_backend(diesel::pg::Pg))]
+pub struct PublishIdempotencyKey {
+    pub user_id: i32,
+    pub key: String,
+    pub crate_name: String,
+    pub version: String,
+    pub response: Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl PublishIdempotencyKey {
+    /// Returns the stored outcome of the publish of `user_id` with the given
+    /// key, unless it has already expired.
+    pub fn find(conn: &mut impl Conn, user_id: i32, key: &str) -> QueryResult<Option<Self>> {
+        publish_idempotency_keys::table
+            .find((user_id, key))
+            .filter(publish_idempotency_keys::created_at.gt(now - 1.day()))
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = publish_idempotency_keys, check_for_backend(diesel::pg::Pg))]
+pub struct NewPublishIdempotencyKey<'a> {
+    pub user_id: i32,
+    pub key: &'a str,
+    pub crate_name: &'a str,
+    pub version: &'a str,
+    pub response: &'a Value,
+}
+
+impl NewPublishIdempotencyKey<'_> {
+    /// Stores the outcome of the publish, replacing an expired entry with
+    /// the same key.
+    ///
+    /// Other expired keys of the same user are removed at the same time,
+    /// since they can't be replayed anymore.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<usize> {
+        use crate::schema::publish_idempotency_keys::columns::*;
+
+        diesel::delete(publish_idempotency_keys::table)
+            .filter(user_id.eq(self.user_id))
+            .filter(created_at.le(now - 1.day()))
+            .execute(conn)?;
+
+        diesel::insert_into(publish_idempotency_keys::table)
+            .values(self)
+            .on_conflict((user_id, key))
+            .do_update()
+            .set((
+                crate_name.eq(excluded(crate_name)),
+                version.eq(excluded(version)),
+                response.eq(excluded(response)),
+                created_at.eq(now),
+            ))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::models::{set_yanked, YankActor};
+use crate::schema::{crates, version_quarantines, versions};
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use crates_io_worker::EnqueueError;
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
+
+/// The reason that is recorded in the public yank history of quarantined
+/// versions. The actual reason of the quarantine is only visible to admins.
+const QUARANTINE_YANK_REASON: &str = "Quarantined by the crates.io team";
+
+/// The reason that is recorded in the yank history when a quarantine is
+/// lifted.
+const RELEASE_UNYANK_REASON: &str = "Quarantine lifted by the crates.io team";
+
+/// A version that is suspected to be malicious.
+///
+/// Quarantined versions are yanked and can not be unyanked by their owners,
+/// until the quarantine is lifted by an admin. Moving the tarball out of the
+/// public storage area is the responsibility of the caller, since it involves
+/// async calls to the storage backend.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = version_quarantines, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(version_id))]
+pub struct VersionQuarantine {
+    pub version_id: i32,
+    pub reason: String,
+    pub was_yanked: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionQuarantine {
+    /// Returns `true` if the version is currently quarantined.
+    pub fn is_quarantined(conn: &mut impl Conn, version_id: i32) -> QueryResult<bool> {
+        select(exists(version_quarantines::table.find(version_id))).get_result(conn)
+    }
+
+    /// Quarantines the version by yanking it and enqueueing the corresponding
+    /// index updates.
+    ///
+    /// Returns `false` if the version was already quarantined.
+    pub fn create(
+        conn: &mut impl Conn,
+        version_id: i32,
+        reason: &str,
+    ) -> Result<bool, EnqueueError> {
+        conn.transaction(|conn| {
+            let (crate_id, crate_name, was_yanked) = versions::table
+                .inner_join(crates::table)
+                .filter(versions::id.eq(version_id))
+                .select((crates::id, crates::name, versions::yanked))
+                .first::<(i32, String, bool)>(conn)?;
+
+            let inserted = diesel::insert_into(version_quarantines::table)
+                .values((
+                    version_quarantines::version_id.eq(version_id),
+                    version_quarantines::reason.eq(reason),
+                    version_quarantines::was_yanked.eq(was_yanked),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            if inserted == 0 {
+                return Ok(false);
+            }
+
+            if !was_yanked {
+                set_yanked(
+                    conn,
+                    crate_id,
+                    &crate_name,
+                    &[version_id],
+                    true,
+                    YankActor::System,
+                    Some(QUARANTINE_YANK_REASON),
+                )?;
+            }
+
+            Ok(true)
+        })
+    }
+
+    /// Lifts the quarantine of the version, and unyanks it again unless it
+    /// was already yanked before it was quarantined.
+    ///
+    /// Returns `false` if the version was not quarantined.
+    pub fn release(conn: &mut impl Conn, version_id: i32) -> Result<bool, EnqueueError> {
+        conn.transaction(|conn| {
+            let was_yanked = diesel::delete(version_quarantines::table.find(version_id))
+                .returning(version_quarantines::was_yanked)
+                .get_result::<bool>(conn)
+                .optional()?;
+
+            let Some(was_yanked) = was_yanked else {
+                return Ok(false);
+            };
+
+            if !was_yanked {
+                let (crate_id, crate_name) = versions::table
+                    .inner_join(crates::table)
+                    .filter(versions::id.eq(version_id))
+                    .select((crates::id, crates::name))
+                    .first::<(i32, String)>(conn)?;
+
+                set_yanked(
+                    conn,
+                    crate_id,
+                    &crate_name,
+                    &[version_id],
+                    false,
+                    YankActor::System,
+                    Some(RELEASE_UNYANK_REASON),
+                )?;
+            }
+
+            Ok(true)
+        })
+    }
+}
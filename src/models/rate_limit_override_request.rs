@@ -0,0 +1,80 @@
+use crate::models::User;
+use crate::rate_limiter::LimitedAction;
+use crate::schema::rate_limit_override_requests;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A request of a user for a higher burst of a rate limited action.
+///
+/// Requests are reviewed by admins, and approving a request creates or
+/// replaces the corresponding row of the `publish_rate_overrides` table.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable, Associations)]
+#[diesel(table_name = rate_limit_override_requests, check_for_backend(diesel::pg::Pg))]
+#[diesel(belongs_to(User))]
+pub struct RateLimitOverrideRequest {
+    pub id: i32,
+    pub user_id: i32,
+    pub action: LimitedAction,
+    pub burst: i32,
+    pub justification: String,
+    pub created_at: NaiveDateTime,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<NaiveDateTime>,
+    pub approved: Option<bool>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl RateLimitOverrideRequest {
+    pub fn find(conn: &mut impl Conn, id: i32) -> QueryResult<Self> {
+        rate_limit_override_requests::table
+            .find(id)
+            .select(Self::as_select())
+            .first(conn)
+    }
+
+    /// Returns the requests that were not reviewed yet, oldest first.
+    pub fn pending(conn: &mut impl Conn) -> QueryResult<Vec<Self>> {
+        rate_limit_override_requests::table
+            .filter(rate_limit_override_requests::reviewed_at.is_null())
+            .select(Self::as_select())
+            .order(rate_limit_override_requests::id)
+            .load(conn)
+    }
+
+    /// Returns all requests of the user, newest first.
+    pub fn for_user(conn: &mut impl Conn, user: &User) -> QueryResult<Vec<Self>> {
+        Self::belonging_to(user)
+            .select(Self::as_select())
+            .order(rate_limit_override_requests::id.desc())
+            .load(conn)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.reviewed_at.is_none()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = rate_limit_override_requests, check_for_backend(diesel::pg::Pg))]
+pub struct NewRateLimitOverrideRequest<'a> {
+    pub user_id: i32,
+    pub action: LimitedAction,
+    pub burst: i32,
+    pub justification: &'a str,
+}
+
+impl NewRateLimitOverrideRequest<'_> {
+    /// Inserts the request, unless the user already has a pending request
+    /// for the same action.
+    ///
+    /// Returns `None` if there is a pending request already.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<Option<RateLimitOverrideRequest>> {
+        diesel::insert_into(rate_limit_override_requests::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .returning(RateLimitOverrideRequest::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+}
@@ -0,0 +1,100 @@
+use crate::schema::readme_images;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+/// An image that is referenced by a rendered README and served via the
+/// image proxy.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = readme_images, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(digest))]
+pub struct ReadmeImage {
+    pub digest: String,
+    pub url: String,
+    pub content_type: Option<String>,
+    pub size: Option<i32>,
+    pub fetched_at: Option<NaiveDateTime>,
+    pub failed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ReadmeImage {
+    pub fn find(conn: &mut impl Conn, digest: &str) -> QueryResult<Option<Self>> {
+        readme_images::table
+            .find(digest)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the images with the given digests that have neither been
+    /// fetched nor failed to be fetched since `cutoff`.
+    pub fn stale(
+        conn: &mut impl Conn,
+        digests: &[String],
+        cutoff: NaiveDateTime,
+    ) -> QueryResult<Vec<Self>> {
+        readme_images::table
+            .filter(readme_images::digest.eq_any(digests))
+            .filter(
+                readme_images::fetched_at
+                    .is_null()
+                    .or(readme_images::fetched_at.lt(cutoff)),
+            )
+            .filter(
+                readme_images::failed_at
+                    .is_null()
+                    .or(readme_images::failed_at.lt(cutoff)),
+            )
+            .select(Self::as_select())
+            .load(conn)
+    }
+
+    /// Returns `true` if a copy of the image is available in the storage
+    /// bucket.
+    pub fn is_cached(&self) -> bool {
+        self.fetched_at.is_some()
+    }
+
+    pub fn record_fetched(
+        &self,
+        conn: &mut impl Conn,
+        content_type: &str,
+        size: i32,
+    ) -> QueryResult<usize> {
+        diesel::update(self)
+            .set((
+                readme_images::content_type.eq(content_type),
+                readme_images::size.eq(size),
+                readme_images::fetched_at.eq(now),
+                readme_images::failed_at.eq(None::<NaiveDateTime>),
+            ))
+            .execute(conn)
+    }
+
+    /// Records a failed attempt to fetch the image. A previously cached copy
+    /// of the image is still served.
+    pub fn record_failure(&self, conn: &mut impl Conn) -> QueryResult<usize> {
+        diesel::update(self)
+            .set(readme_images::failed_at.eq(now))
+            .execute(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = readme_images, check_for_backend(diesel::pg::Pg))]
+pub struct NewReadmeImage<'a> {
+    pub digest: &'a str,
+    pub url: &'a str,
+}
+
+impl NewReadmeImage<'_> {
+    /// Inserts the images, skipping those that are already known.
+    pub fn insert_all(conn: &mut impl Conn, images: &[Self]) -> QueryResult<usize> {
+        diesel::insert_into(readme_images::table)
+            .values(images)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+}
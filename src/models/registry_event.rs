@@ -0,0 +1,92 @@
+use crate::schema::registry_events;
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+pg_enum! {
+    pub enum RegistryEventKind {
+        Publish = 0,
+        Yank = 1,
+        Unyank = 2,
+        OwnerChange = 3,
+        Delete = 4,
+        Flag = 5,
+        Unflag = 6,
+    }
+}
+
+impl From<RegistryEventKind> for &'static str {
+    fn from(kind: RegistryEventKind) -> Self {
+        match kind {
+            RegistryEventKind::Publish => "publish",
+            RegistryEventKind::Yank => "yank",
+            RegistryEventKind::Unyank => "unyank",
+            RegistryEventKind::OwnerChange => "owner_change",
+            RegistryEventKind::Delete => "delete",
+            RegistryEventKind::Flag => "flag",
+            RegistryEventKind::Unflag => "unflag",
+        }
+    }
+}
+
+/// An entry of the append-only changes feed of the registry.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = registry_events, check_for_backend(diesel::pg::Pg))]
+pub struct RegistryEvent {
+    pub id: i64,
+    pub kind: RegistryEventKind,
+    pub crate_name: String,
+    pub version: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl RegistryEvent {
+    /// Returns the ID of the most recent event, or `0` if there are no events
+    /// yet.
+    pub fn last_id(conn: &mut impl Conn) -> QueryResult<i64> {
+        use diesel::dsl::max;
+
+        let last_id: Option<i64> = registry_events::table
+            .select(max(registry_events::id))
+            .get_result(conn)?;
+
+        Ok(last_id.unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = registry_events, check_for_backend(diesel::pg::Pg))]
+pub struct NewRegistryEvent<'a> {
+    kind: RegistryEventKind,
+    crate_name: &'a str,
+    version: Option<&'a str>,
+}
+
+impl<'a> NewRegistryEvent<'a> {
+    /// Creates an event that applies to a single version of a crate.
+    pub fn version(kind: RegistryEventKind, crate_name: &'a str, version: &'a str) -> Self {
+        Self {
+            kind,
+            crate_name,
+            version: Some(version),
+        }
+    }
+
+    /// Creates an event that applies to the crate as a whole.
+    pub fn krate(kind: RegistryEventKind, crate_name: &'a str) -> Self {
+        Self {
+            kind,
+            crate_name,
+            version: None,
+        }
+    }
+
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(registry_events::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
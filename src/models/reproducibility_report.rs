@@ -0,0 +1,54 @@
+use crate::schema::reproducibility_reports;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+/// The attestation of an independent rebuilder whether the normalized
+/// tarball of a version could be reproduced from source.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = reproducibility_reports, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(version_id, reporter_id))]
+pub struct ReproducibilityReport {
+    pub version_id: i32,
+    pub reporter_id: i32,
+    pub checksum: String,
+    pub matches: bool,
+    pub details: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ReproducibilityReport {
+    /// Returns whether the value is a lowercase hex-encoded SHA256 checksum,
+    /// like the checksums in the index.
+    pub fn valid_checksum(value: &str) -> bool {
+        value.len() == 64 && value.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+    }
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = reproducibility_reports, check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct NewReproducibilityReport<'a> {
+    pub version_id: i32,
+    pub reporter_id: i32,
+    pub checksum: &'a str,
+    pub matches: bool,
+    pub details: Option<&'a str>,
+}
+
+impl NewReproducibilityReport<'_> {
+    /// Records the report, replacing any previous report of the same
+    /// reporter for the same version.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<ReproducibilityReport> {
+        diesel::insert_into(reproducibility_reports::table)
+            .values(self)
+            .on_conflict((
+                reproducibility_reports::version_id,
+                reproducibility_reports::reporter_id,
+            ))
+            .do_update()
+            .set((self, reproducibility_reports::created_at.eq(now)))
+            .get_result(conn)
+    }
+}
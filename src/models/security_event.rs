@@ -0,0 +1,214 @@
+use crate::email::Email;
+use crate::models::User;
+use crate::schema::{security_events, users};
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use crate::Emails;
+use chrono::NaiveDateTime;
+use diesel::dsl::{exists, now, select, IntervalDsl};
+use diesel::prelude::*;
+use secrecy::{ExposeSecret, SecretString};
+
+/// The number of days for which the link in a security alert can be used to
+/// lock the account.
+const LOCK_TOKEN_VALIDITY_DAYS: i32 = 30;
+
+/// The lock reason of accounts that were locked via a security alert.
+pub const SECURITY_ALERT_LOCK_REASON: &str = "The account was locked by its owner because of \
+    an unrecognized security event. Please contact help@crates.io to verify your identity and \
+    unlock the account.";
+
+pg_enum! {
+    pub enum SecurityEventKind {
+        Login = 0,
+        ApiTokenCreated = 1,
+        AccountLocked = 2,
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = security_events, check_for_backend(diesel::pg::Pg))]
+pub struct SecurityEvent {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: SecurityEventKind,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub api_token_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl SecurityEvent {
+    /// Locks the account of the user that the event with the given lock
+    /// token belongs to, unless the token has expired.
+    ///
+    /// Returns `None` if no matching event was found.
+    pub fn lock_account(
+        conn: &mut impl Conn,
+        lock_token: &str,
+        ip_address: Option<String>,
+        user_agent: Option<&str>,
+    ) -> QueryResult<Option<i32>> {
+        conn.transaction(|conn| {
+            let user_id: Option<i32> = security_events::table
+                .filter(security_events::lock_token.eq(lock_token))
+                .filter(security_events::kind.ne(SecurityEventKind::AccountLocked))
+                .filter(security_events::created_at.gt(now - LOCK_TOKEN_VALIDITY_DAYS.days()))
+                .select(security_events::user_id)
+                .first(conn)
+                .optional()?;
+
+            let Some(user_id) = user_id else {
+                return Ok(None);
+            };
+
+            diesel::update(users::table.find(user_id))
+                .set((
+                    users::account_lock_reason.eq(SECURITY_ALERT_LOCK_REASON),
+                    users::account_lock_until.eq(None::<NaiveDateTime>),
+                ))
+                .execute(conn)?;
+
+            let event = NewSecurityEvent {
+                user_id,
+                kind: SecurityEventKind::AccountLocked,
+                ip_address,
+                user_agent,
+                api_token_id: None,
+            };
+            event.insert(conn)?;
+
+            Ok(Some(user_id))
+        })
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = security_events, check_for_backend(diesel::pg::Pg))]
+pub struct NewSecurityEvent<'a> {
+    pub user_id: i32,
+    pub kind: SecurityEventKind,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<&'a str>,
+    pub api_token_id: Option<i32>,
+}
+
+impl NewSecurityEvent<'_> {
+    /// Inserts the event and returns the token that can be used to lock the
+    /// account if the event was not caused by the user.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<SecretString> {
+        diesel::insert_into(security_events::table)
+            .values(self)
+            .returning(security_events::lock_token)
+            .get_result(conn)
+            .map(SecretString::new)
+    }
+
+    /// Returns `true` if the user has signed in before, but never with the
+    /// same IP address and user agent as this event.
+    ///
+    /// The very first sign-in of a user is not considered unrecognized, since
+    /// there is nothing to compare it to.
+    pub fn is_unrecognized_login(&self, conn: &mut impl Conn) -> QueryResult<bool> {
+        let previous_logins = security_events::table
+            .filter(security_events::user_id.eq(self.user_id))
+            .filter(security_events::kind.eq(SecurityEventKind::Login));
+
+        let has_previous_logins =
+            select(exists(previous_logins.clone())).get_result::<bool>(conn)?;
+        if !has_previous_logins {
+            return Ok(false);
+        }
+
+        let is_known = select(exists(
+            previous_logins
+                .filter(security_events::ip_address.is_not_distinct_from(&self.ip_address))
+                .filter(security_events::user_agent.is_not_distinct_from(self.user_agent)),
+        ))
+        .get_result::<bool>(conn)?;
+
+        Ok(!is_known)
+    }
+
+    /// Inserts the event and sends a security alert with a link to lock the
+    /// account to the primary email address of the user.
+    ///
+    /// `api_token_name` has to be set for events about created API tokens.
+    ///
+    /// Errors while sending the email are logged, but otherwise ignored.
+    pub fn insert_and_notify(
+        &self,
+        user: &User,
+        api_token_name: Option<&str>,
+        emails: &Emails,
+        conn: &mut impl Conn,
+    ) -> QueryResult<()> {
+        let lock_token = self.insert(conn)?;
+
+        let Some(recipient) = user.email(conn)? else {
+            return Ok(());
+        };
+
+        let email = SecurityAlertEmail {
+            user_name: &user.gh_login,
+            domain: &emails.domain,
+            ip_address: self.ip_address.as_deref(),
+            user_agent: self.user_agent,
+            api_token_name,
+            lock_token,
+        };
+
+        if let Err(error) = emails.send(conn, &recipient, email) {
+            warn!(?error, "Failed to send security alert to {recipient}");
+        }
+
+        Ok(())
+    }
+}
+
+struct SecurityAlertEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+    ip_address: Option<&'a str>,
+    user_agent: Option<&'a str>,
+    api_token_name: Option<&'a str>,
+    lock_token: SecretString,
+}
+
+impl Email for SecurityAlertEmail<'_> {
+    const SUBJECT: &'static str = "Security alert for your crates.io account";
+
+    fn body(&self) -> String {
+        let description = match self.api_token_name {
+            Some(name) => format!(
+                "A new API token named \"{name}\" with permission to publish crates was \
+                 created for your account."
+            ),
+            None => "Someone signed in to your account from an unrecognized device or \
+                location."
+                .to_string(),
+        };
+
+        format!(
+            "Hello {user_name}!
+
+{description}
+
+IP address: {ip_address}
+User agent: {user_agent}
+
+If this was you, you can ignore this email. If this wasn't you, please lock \
+your account immediately by visiting the following link:
+
+https://{domain}/lock-account/{lock_token}
+
+Your account will stay locked until your identity has been verified by the \
+crates.io team.",
+            user_name = self.user_name,
+            ip_address = self.ip_address.unwrap_or("unknown"),
+            user_agent = self.user_agent.unwrap_or("unknown"),
+            domain = self.domain,
+            lock_token = self.lock_token.expose_secret(),
+        )
+    }
+}
@@ -0,0 +1,43 @@
+use crate::schema::spam_flags;
+use crate::spam::SpamScore;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A version whose metadata was classified as potential spam, and that is
+/// waiting in the moderation queue for a review by an admin.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = spam_flags, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(version_id))]
+pub struct SpamFlag {
+    pub version_id: i32,
+    pub score: i32,
+    pub reasons: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = spam_flags, check_for_backend(diesel::pg::Pg))]
+pub struct NewSpamFlag<'a> {
+    pub version_id: i32,
+    pub score: i32,
+    pub reasons: &'a [String],
+}
+
+impl<'a> NewSpamFlag<'a> {
+    pub fn new(version_id: i32, score: &'a SpamScore) -> Self {
+        Self {
+            version_id,
+            score: score.score.try_into().unwrap_or(i32::MAX),
+            reasons: &score.reasons,
+        }
+    }
+
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<SpamFlag> {
+        diesel::insert_into(spam_flags::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,91 @@
+use crate::models::{ScanVerdict, User};
+use crate::schema::staged_uploads;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+
+/// A crate tarball that was uploaded ahead of the publish request that
+/// references it.
+///
+/// Staged uploads expire after a day.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(belongs_to(User))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct StagedUpload {
+    pub id: String,
+    pub user_id: i32,
+    pub checksum: String,
+    pub size: i32,
+    pub scan_verdict: Option<ScanVerdict>,
+    pub created_at: NaiveDateTime,
+}
+
+impl StagedUpload {
+    /// Returns the staged upload of the user with the given id, unless it
+    /// has expired.
+    pub fn find(conn: &mut impl Conn, user_id: i32, id: &str) -> QueryResult<Option<Self>> {
+        staged_uploads::table
+            .filter(staged_uploads::id.eq(id))
+            .filter(staged_uploads::user_id.eq(user_id))
+            .filter(staged_uploads::created_at.gt(now - 1.day()))
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the most recent staged upload of the user with the given
+    /// checksum, unless it has expired.
+    pub fn find_by_checksum(
+        conn: &mut impl Conn,
+        user_id: i32,
+        checksum: &str,
+    ) -> QueryResult<Option<Self>> {
+        staged_uploads::table
+            .filter(staged_uploads::user_id.eq(user_id))
+            .filter(staged_uploads::checksum.eq(checksum))
+            .filter(staged_uploads::created_at.gt(now - 1.day()))
+            .order(staged_uploads::created_at.desc())
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Removes the expired staged uploads of the user from the database, and
+    /// returns their ids, so that the tarballs can be deleted as well.
+    pub fn delete_expired(conn: &mut impl Conn, user_id: i32) -> QueryResult<Vec<String>> {
+        diesel::delete(staged_uploads::table)
+            .filter(staged_uploads::user_id.eq(user_id))
+            .filter(staged_uploads::created_at.le(now - 1.day()))
+            .returning(staged_uploads::id)
+            .get_results(conn)
+    }
+
+    /// Records the verdict of the antivirus scan of the tarball.
+    pub fn set_scan_verdict(
+        conn: &mut impl Conn,
+        id: &str,
+        verdict: ScanVerdict,
+    ) -> QueryResult<usize> {
+        diesel::update(staged_uploads::table.find(id))
+            .set(staged_uploads::scan_verdict.eq(verdict))
+            .execute(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = staged_uploads, check_for_backend(diesel::pg::Pg))]
+pub struct NewStagedUpload<'a> {
+    pub user_id: i32,
+    pub checksum: &'a str,
+    pub size: i32,
+}
+
+impl NewStagedUpload<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<StagedUpload> {
+        diesel::insert_into(staged_uploads::table)
+            .values(self)
+            .returning(StagedUpload::as_returning())
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{Crate, User};
+use crate::schema::dependency_subscriptions;
+use crate::util::diesel::Conn;
+
+/// The maximum number of dependency subscriptions per user.
+pub const MAX_SUBSCRIPTIONS_PER_USER: i64 = 100;
+
+/// A subscription of a user that wants to be notified when a crate publishes
+/// a version matching the version requirement.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(
+    table_name = dependency_subscriptions,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(User),
+    belongs_to(Crate),
+)]
+pub struct DependencySubscription {
+    pub id: i32,
+    pub user_id: i32,
+    pub crate_id: i32,
+    pub version_req: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl DependencySubscription {
+    /// Returns the subscriptions of the user, together with the names of the
+    /// crates that they belong to.
+    pub fn for_user(conn: &mut impl Conn, user_id: i32) -> QueryResult<Vec<(Self, String)>> {
+        use crate::schema::crates;
+
+        dependency_subscriptions::table
+            .inner_join(crates::table)
+            .filter(dependency_subscriptions::user_id.eq(user_id))
+            .select((Self::as_select(), crates::name))
+            .order(dependency_subscriptions::id)
+            .load(conn)
+    }
+
+    /// Returns all subscriptions to the crate.
+    pub fn for_crate(conn: &mut impl Conn, crate_id: i32) -> QueryResult<Vec<Self>> {
+        dependency_subscriptions::table
+            .filter(dependency_subscriptions::crate_id.eq(crate_id))
+            .select(Self::as_select())
+            .order(dependency_subscriptions::id)
+            .load(conn)
+    }
+
+    /// Returns `true` if `version` matches the version requirement of the
+    /// subscription.
+    ///
+    /// Invalid requirements never match, although they are rejected when
+    /// the subscription is created.
+    pub fn matches(&self, version: &semver::Version) -> bool {
+        semver::VersionReq::parse(&self.version_req).is_ok_and(|req| req.matches(version))
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = dependency_subscriptions, check_for_backend(diesel::pg::Pg))]
+pub struct NewDependencySubscription<'a> {
+    pub user_id: i32,
+    pub crate_id: i32,
+    pub version_req: &'a str,
+}
+
+impl NewDependencySubscription<'_> {
+    /// Inserts the subscription, unless the user is already subscribed to the
+    /// crate with the same version requirement, in which case the existing
+    /// subscription is returned.
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<DependencySubscription> {
+        let inserted = diesel::insert_into(dependency_subscriptions::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .returning(DependencySubscription::as_returning())
+            .get_result(conn)
+            .optional()?;
+
+        match inserted {
+            Some(subscription) => Ok(subscription),
+            None => dependency_subscriptions::table
+                .filter(dependency_subscriptions::user_id.eq(self.user_id))
+                .filter(dependency_subscriptions::crate_id.eq(self.crate_id))
+                .filter(dependency_subscriptions::version_req.eq(self.version_req))
+                .select(DependencySubscription::as_select())
+                .first(conn),
+        }
+    }
+}
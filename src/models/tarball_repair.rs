@@ -0,0 +1,117 @@
+use crate::schema::{version_checksum_rotations, version_content_manifests};
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use hex::ToHex;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// The files in the tarball of a version, as recorded at publish time.
+///
+/// The manifest only depends on the paths and contents of the files, so a
+/// tarball that was compressed differently still matches the manifest of the
+/// original tarball.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable, Insertable)]
+#[diesel(table_name = version_content_manifests, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(version_id))]
+pub struct VersionContentManifest {
+    pub version_id: i32,
+    /// JSON object with the paths of the files as keys and the hex-encoded
+    /// SHA256 checksums of their contents as values.
+    pub files: Value,
+}
+
+impl VersionContentManifest {
+    pub fn new(version_id: i32, file_checksums: &BTreeMap<PathBuf, [u8; 32]>) -> Self {
+        let files = file_checksums
+            .iter()
+            .map(|(path, checksum)| {
+                let path = path.to_string_lossy().into_owned();
+                (path, Value::String(checksum.encode_hex()))
+            })
+            .collect();
+
+        Self {
+            version_id,
+            files: Value::Object(files),
+        }
+    }
+
+    pub fn for_version(conn: &mut impl Conn, version_id: i32) -> QueryResult<Option<Self>> {
+        version_content_manifests::table
+            .find(version_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(version_content_manifests::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Returns the paths of the files that are missing in one of the
+    /// manifests, or whose contents differ between them.
+    pub fn mismatched_paths(&self, other: &Self) -> Vec<String> {
+        let (Some(files), Some(other_files)) = (self.files.as_object(), other.files.as_object())
+        else {
+            return vec![];
+        };
+
+        files
+            .keys()
+            .chain(other_files.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|path| files.get(*path) != other_files.get(*path))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A change of the checksum of a version, after an admin replaced its
+/// corrupted tarball with a tarball that has the same contents.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = version_checksum_rotations, check_for_backend(diesel::pg::Pg))]
+pub struct ChecksumRotation {
+    pub id: i32,
+    pub version_id: i32,
+    pub old_checksum: String,
+    pub new_checksum: String,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl ChecksumRotation {
+    /// Returns the rotations of the given version, oldest first.
+    pub fn for_version(conn: &mut impl Conn, version_id: i32) -> QueryResult<Vec<Self>> {
+        version_checksum_rotations::table
+            .filter(version_checksum_rotations::version_id.eq(version_id))
+            .order(version_checksum_rotations::id.asc())
+            .select(Self::as_select())
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_checksum_rotations, check_for_backend(diesel::pg::Pg))]
+pub struct NewChecksumRotation<'a> {
+    pub version_id: i32,
+    pub old_checksum: &'a str,
+    pub new_checksum: &'a str,
+    pub reason: &'a str,
+}
+
+impl NewChecksumRotation<'_> {
+    pub fn insert(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        diesel::insert_into(version_checksum_rotations::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use crate::schema::tarball_scans;
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+pg_enum! {
+    pub enum ScanVerdict {
+        Clean = 0,
+        Infected = 1,
+    }
+}
+
+/// The result of the antivirus scan of the tarball of a version.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = tarball_scans, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(version_id))]
+pub struct TarballScan {
+    pub version_id: i32,
+    pub verdict: ScanVerdict,
+    pub signature: Option<String>,
+    pub scanned_at: NaiveDateTime,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = tarball_scans, check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct NewTarballScan<'a> {
+    pub version_id: i32,
+    pub verdict: ScanVerdict,
+    pub signature: Option<&'a str>,
+}
+
+impl NewTarballScan<'_> {
+    /// Records the result of the scan, replacing the result and the review
+    /// of any previous scan of the same tarball.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<TarballScan> {
+        diesel::insert_into(tarball_scans::table)
+            .values(self)
+            .on_conflict(tarball_scans::version_id)
+            .do_update()
+            .set((
+                self,
+                tarball_scans::scanned_at.eq(now),
+                tarball_scans::reviewed_by.eq(None::<i32>),
+                tarball_scans::reviewed_at.eq(None::<NaiveDateTime>),
+            ))
+            .get_result(conn)
+    }
+}
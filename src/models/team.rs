@@ -4,7 +4,7 @@ use http::StatusCode;
 use crate::app::App;
 use crate::util::errors::{bad_request, custom, AppResult};
 
-use crates_io_github::GitHubError;
+use crates_io_github::{GitHubError, GitHubTeamMembership};
 use oauth2::AccessToken;
 use tokio::runtime::Handle;
 
@@ -15,7 +15,7 @@ use crate::util::diesel::Conn;
 
 /// For now, just a Github Team. Can be upgraded to other teams
 /// later if desirable.
-#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
+#[derive(Clone, Queryable, Identifiable, Serialize, Deserialize, Debug)]
 pub struct Team {
     /// Unique table id
     pub id: i32,
@@ -196,6 +196,22 @@ impl Team {
         }
     }
 
+    /// Phones home to Github to ask if this User is a maintainer of the team,
+    /// or an owner of the organization that the team belongs to. The same
+    /// caveats as for [`Team::contains_user`] apply.
+    pub async fn is_maintainer(&self, app: &App, user: &User) -> AppResult<bool> {
+        let Some(org_id) = self.org_id else {
+            return Ok(false);
+        };
+
+        let membership = team_membership(app, org_id, self.github_id, user).await?;
+        if membership.is_some_and(|m| m.state == "active" && m.role == "maintainer") {
+            return Ok(true);
+        }
+
+        is_gh_org_owner(app, org_id, user).await
+    }
+
     pub fn owning(krate: &Crate, conn: &mut impl Conn) -> QueryResult<Vec<Owner>> {
         let base_query = CrateOwner::belonging_to(krate).filter(crate_owners::deleted.eq(false));
         let teams = base_query
@@ -236,21 +252,33 @@ async fn team_with_gh_id_contains_user(
     github_team_id: i32,
     user: &User,
 ) -> AppResult<bool> {
-    // GET /organizations/:org_id/team/:team_id/memberships/:username
     // check that "state": "active"
+    let Some(membership) = team_membership(app, github_org_id, github_team_id, user).await? else {
+        return Ok(false);
+    };
+
+    // There is also `state: pending` for which we could possibly give
+    // some feedback, but it's not obvious how that should work.
+    Ok(membership.state == "active")
+}
+
+async fn team_membership(
+    app: &App,
+    github_org_id: i32,
+    github_team_id: i32,
+    user: &User,
+) -> AppResult<Option<GitHubTeamMembership>> {
+    // GET /organizations/:org_id/team/:team_id/memberships/:username
 
     let token = AccessToken::new(user.gh_access_token.clone());
-    let membership = match app
+    match app
         .github
         .team_membership(github_org_id, github_team_id, &user.gh_login, &token)
         .await
     {
+        Ok(membership) => Ok(Some(membership)),
         // Officially how `false` is returned
-        Err(GitHubError::NotFound(_)) => return Ok(false),
-        x => x?,
-    };
-
-    // There is also `state: pending` for which we could possibly give
-    // some feedback, but it's not obvious how that should work.
-    Ok(membership.state == "active")
+        Err(GitHubError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
 }
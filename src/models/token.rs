@@ -11,7 +11,7 @@ use crate::util::rfc3339;
 use crate::util::token::{HashedToken, PlainToken};
 
 /// The model representing a row in the `api_tokens` database table.
-#[derive(Debug, Identifiable, Queryable, Selectable, Associations, Serialize)]
+#[derive(Debug, Clone, Identifiable, Queryable, Selectable, Associations, Serialize)]
 #[diesel(belongs_to(User))]
 pub struct ApiToken {
     pub id: i32,
@@ -30,6 +30,21 @@ pub struct ApiToken {
     pub endpoint_scopes: Option<Vec<EndpointScope>>,
     #[serde(with = "rfc3339::option")]
     pub expired_at: Option<NaiveDateTime>,
+    /// Contact information of the operator, if this is a service token.
+    ///
+    /// Service tokens are meant for high-volume consumers of the read-only
+    /// API (e.g. documentation sites or package indexes). They can not be
+    /// used to perform any actions, but have elevated API quotas.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_contact: Option<String>,
+    /// The team that owns this token, if this is a team token.
+    ///
+    /// Team tokens can only be used for crates owned by the team. The
+    /// `user_id` of a team token refers to the team maintainer that created
+    /// it, who is recorded as the acting user of all actions performed with
+    /// the token.
+    #[serde(skip)]
+    pub team_id: Option<i32>,
 }
 
 impl ApiToken {
@@ -66,6 +81,71 @@ impl ApiToken {
         })
     }
 
+    /// Generates a new named service token for a user.
+    ///
+    /// Service tokens have an empty list of endpoint scopes, so that they can
+    /// not be used for any endpoint that requires a scope.
+    pub fn insert_service_token(
+        conn: &mut impl Conn,
+        user_id: i32,
+        name: &str,
+        contact: &str,
+    ) -> QueryResult<CreatedApiToken> {
+        let token = PlainToken::generate();
+
+        let model: ApiToken = diesel::insert_into(api_tokens::table)
+            .values((
+                api_tokens::user_id.eq(user_id),
+                api_tokens::name.eq(name),
+                api_tokens::token.eq(token.hashed()),
+                api_tokens::endpoint_scopes.eq(Some(Vec::<EndpointScope>::new())),
+                api_tokens::service_contact.eq(contact),
+            ))
+            .returning(ApiToken::as_returning())
+            .get_result(conn)?;
+
+        Ok(CreatedApiToken {
+            plaintext: token,
+            model,
+        })
+    }
+
+    /// Generates a new named team token, created by one of the maintainers
+    /// of the team.
+    pub fn insert_team_token(
+        conn: &mut impl Conn,
+        user_id: i32,
+        team_id: i32,
+        name: &str,
+        crate_scopes: Option<Vec<CrateScope>>,
+        endpoint_scopes: Vec<EndpointScope>,
+        expired_at: Option<NaiveDateTime>,
+    ) -> QueryResult<CreatedApiToken> {
+        let token = PlainToken::generate();
+
+        let model: ApiToken = diesel::insert_into(api_tokens::table)
+            .values((
+                api_tokens::user_id.eq(user_id),
+                api_tokens::team_id.eq(team_id),
+                api_tokens::name.eq(name),
+                api_tokens::token.eq(token.hashed()),
+                api_tokens::crate_scopes.eq(crate_scopes),
+                api_tokens::endpoint_scopes.eq(Some(endpoint_scopes)),
+                api_tokens::expired_at.eq(expired_at),
+            ))
+            .returning(ApiToken::as_returning())
+            .get_result(conn)?;
+
+        Ok(CreatedApiToken {
+            plaintext: token,
+            model,
+        })
+    }
+
+    pub fn is_service_token(&self) -> bool {
+        self.service_contact.is_some()
+    }
+
     pub fn find_by_api_token(conn: &mut impl Conn, token: &HashedToken) -> QueryResult<ApiToken> {
         use diesel::{dsl::now, update};
 
@@ -119,6 +199,8 @@ mod tests {
             crate_scopes: None,
             endpoint_scopes: None,
             expired_at: None,
+            service_contact: None,
+            team_id: None,
         };
         let json = serde_json::to_string(&tok).unwrap();
         assert_some!(json
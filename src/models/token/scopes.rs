@@ -13,6 +13,7 @@ pub enum EndpointScope {
     PublishUpdate,
     Yank,
     ChangeOwners,
+    Download,
 }
 
 impl From<&EndpointScope> for &[u8] {
@@ -22,6 +23,7 @@ impl From<&EndpointScope> for &[u8] {
             EndpointScope::PublishUpdate => b"publish-update",
             EndpointScope::Yank => b"yank",
             EndpointScope::ChangeOwners => b"change-owners",
+            EndpointScope::Download => b"download",
         }
     }
 }
@@ -42,6 +44,7 @@ impl TryFrom<&[u8]> for EndpointScope {
             b"publish-update" => Ok(EndpointScope::PublishUpdate),
             b"yank" => Ok(EndpointScope::Yank),
             b"change-owners" => Ok(EndpointScope::ChangeOwners),
+            b"download" => Ok(EndpointScope::Download),
             _ => Err("Unrecognized enum variant".to_string()),
         }
     }
@@ -135,6 +138,7 @@ mod tests {
         }
 
         assert(EndpointScope::ChangeOwners, "\"change-owners\"");
+        assert(EndpointScope::Download, "\"download\"");
         assert(EndpointScope::PublishNew, "\"publish-new\"");
         assert(EndpointScope::PublishUpdate, "\"publish-update\"");
         assert(EndpointScope::Yank, "\"yank\"");
@@ -24,6 +24,11 @@ pub struct User {
     pub account_lock_reason: Option<String>,
     pub account_lock_until: Option<NaiveDateTime>,
     pub is_admin: bool,
+    pub publish_confirmation_required: bool,
+    pub gh_two_factor: bool,
+    /// Overrides the maximum number of dependencies of the versions that
+    /// this user publishes, unless the crate has its own override.
+    pub max_dependencies: Option<i16>,
 }
 
 /// Represents a new user record insertable to the `users` table
@@ -35,6 +40,7 @@ pub struct NewUser<'a> {
     pub name: Option<&'a str>,
     pub gh_avatar: Option<&'a str>,
     pub gh_access_token: &'a str,
+    pub gh_two_factor: bool,
 }
 
 impl<'a> NewUser<'a> {
@@ -51,6 +57,7 @@ impl<'a> NewUser<'a> {
             name,
             gh_avatar,
             gh_access_token,
+            gh_two_factor: false,
         }
     }
 
@@ -84,6 +91,7 @@ impl<'a> NewUser<'a> {
                     users::name.eq(excluded(users::name)),
                     users::gh_avatar.eq(excluded(users::gh_avatar)),
                     users::gh_access_token.eq(excluded(users::gh_access_token)),
+                    users::gh_two_factor.eq(excluded(users::gh_two_factor)),
                 ))
                 .get_result(conn)?;
 
@@ -92,8 +100,10 @@ impl<'a> NewUser<'a> {
                 let new_email = NewEmail {
                     user_id: user.id,
                     email: user_email,
+                    is_primary: true,
                 };
 
+                // Nothing is inserted if the user already has a primary address
                 let token = insert_into(emails::table)
                     .values(&new_email)
                     .on_conflict_do_nothing()
@@ -109,7 +119,7 @@ impl<'a> NewUser<'a> {
                         domain: &emails.domain,
                         token,
                     };
-                    let _ = emails.send(user_email, email);
+                    let _ = emails.send_confirmation(user_email, email);
                 }
             }
 
@@ -131,6 +141,17 @@ impl User {
             .first(conn)
     }
 
+    /// Queries the database for the user that verified the given email address.
+    pub fn find_by_verified_email(conn: &mut impl Conn, email: &str) -> QueryResult<User> {
+        users::table
+            .inner_join(emails::table)
+            .filter(lower(emails::email).eq(email.to_lowercase()))
+            .filter(emails::verified.eq(true))
+            .filter(users::gh_id.ne(-1))
+            .select(users::all_columns)
+            .first(conn)
+    }
+
     pub fn owning(krate: &Crate, conn: &mut impl Conn) -> QueryResult<Vec<Owner>> {
         let users = CrateOwner::by_owner_kind(OwnerKind::User)
             .inner_join(users::table)
@@ -170,20 +191,26 @@ impl User {
         Ok(best)
     }
 
-    /// Queries the database for the verified emails
-    /// belonging to a given user
+    /// Queries the database for the verified primary email
+    /// belonging to a given user, unless the email provider reported it as
+    /// undeliverable
     pub fn verified_email(&self, conn: &mut impl Conn) -> QueryResult<Option<String>> {
         Email::belonging_to(self)
             .select(emails::email)
+            .filter(emails::is_primary.eq(true))
             .filter(emails::verified.eq(true))
+            .filter(emails::undeliverable_at.is_null())
             .first(conn)
             .optional()
     }
 
-    /// Queries for the email belonging to a particular user
+    /// Queries for the primary email belonging to a particular user, unless
+    /// the email provider reported it as undeliverable
     pub fn email(&self, conn: &mut impl Conn) -> QueryResult<Option<String>> {
         Email::belonging_to(self)
             .select(emails::email)
+            .filter(emails::is_primary.eq(true))
+            .filter(emails::undeliverable_at.is_null())
             .first(conn)
             .optional()
     }
@@ -0,0 +1,86 @@
+use crate::schema::user_agent_policies;
+use crate::sql::pg_enum;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+pg_enum! {
+    pub enum ThrottleClass {
+        Normal = 0,
+        Slow = 1,
+        Blocked = 2,
+    }
+}
+
+impl ThrottleClass {
+    /// Returns how restrictive the class is, which is used to decide between
+    /// multiple matching policies.
+    fn severity(&self) -> u8 {
+        match self {
+            ThrottleClass::Normal => 0,
+            ThrottleClass::Slow => 1,
+            ThrottleClass::Blocked => 2,
+        }
+    }
+
+    /// Returns the more restrictive of the two classes.
+    pub fn max(self, other: Self) -> Self {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// A policy that applies a [`ThrottleClass`] to all anonymous requests whose
+/// `User-Agent` header contains the `pattern` (case-insensitive).
+#[derive(Debug, Clone, Serialize, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = user_agent_policies, check_for_backend(diesel::pg::Pg))]
+pub struct UserAgentPolicy {
+    pub id: i32,
+    pub pattern: String,
+    pub throttle_class: ThrottleClass,
+    #[serde(with = "crate::util::rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl UserAgentPolicy {
+    pub fn all(conn: &mut impl Conn) -> QueryResult<Vec<Self>> {
+        user_agent_policies::table
+            .select(Self::as_select())
+            .order(user_agent_policies::id.asc())
+            .load(conn)
+    }
+
+    /// Returns whether the policy applies to the given `User-Agent` header.
+    ///
+    /// The `user_agent` is expected to be lowercased already.
+    pub fn matches(&self, user_agent: &str) -> bool {
+        user_agent.contains(&self.pattern.to_lowercase())
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_agent_policies, check_for_backend(diesel::pg::Pg))]
+pub struct NewUserAgentPolicy<'a> {
+    pub pattern: &'a str,
+    pub throttle_class: ThrottleClass,
+}
+
+impl NewUserAgentPolicy<'_> {
+    /// Inserts the policy, or updates the class of an existing policy with
+    /// the same pattern.
+    pub fn upsert(&self, conn: &mut impl Conn) -> QueryResult<UserAgentPolicy> {
+        diesel::insert_into(user_agent_policies::table)
+            .values(self)
+            .on_conflict(user_agent_policies::pattern)
+            .do_update()
+            .set((
+                user_agent_policies::throttle_class.eq(self.throttle_class),
+                user_agent_policies::created_at.eq(diesel::dsl::now),
+            ))
+            .returning(UserAgentPolicy::as_returning())
+            .get_result(conn)
+    }
+}
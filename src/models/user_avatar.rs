@@ -0,0 +1,73 @@
+use crate::schema::user_avatars;
+use crate::util::diesel::Conn;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+/// The cached copy of the avatar of a user, which is served via the avatar
+/// proxy. See the `avatars` module for more details.
+#[derive(Debug, Clone, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = user_avatars, check_for_backend(diesel::pg::Pg))]
+#[diesel(primary_key(user_id))]
+pub struct UserAvatar {
+    pub user_id: i32,
+    pub url: String,
+    pub digest: Option<String>,
+    pub content_type: Option<String>,
+    pub fetched_at: Option<NaiveDateTime>,
+    pub failed_at: Option<NaiveDateTime>,
+}
+
+impl UserAvatar {
+    pub fn find(conn: &mut impl Conn, user_id: i32) -> QueryResult<Option<Self>> {
+        user_avatars::table
+            .find(user_id)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns `true` if the avatar has to be fetched again, because it was
+    /// fetched from another URL, or because it has neither been fetched nor
+    /// failed to be fetched since `cutoff`.
+    pub fn is_stale(&self, url: &str, cutoff: NaiveDateTime) -> bool {
+        let last_attempt = self.fetched_at.max(self.failed_at);
+        self.url != url || last_attempt.map_or(true, |at| at < cutoff)
+    }
+
+    pub fn record_fetched(
+        conn: &mut impl Conn,
+        user_id: i32,
+        url: &str,
+        digest: &str,
+        content_type: &str,
+    ) -> QueryResult<usize> {
+        let values = (
+            user_avatars::url.eq(url),
+            user_avatars::digest.eq(digest),
+            user_avatars::content_type.eq(content_type),
+            user_avatars::fetched_at.eq(now),
+            user_avatars::failed_at.eq(None::<NaiveDateTime>),
+        );
+
+        diesel::insert_into(user_avatars::table)
+            .values((user_avatars::user_id.eq(user_id), values))
+            .on_conflict(user_avatars::user_id)
+            .do_update()
+            .set(values)
+            .execute(conn)
+    }
+
+    /// Records a failed attempt to fetch the avatar. A previously cached copy
+    /// of the avatar is still served.
+    pub fn record_failure(conn: &mut impl Conn, user_id: i32, url: &str) -> QueryResult<usize> {
+        let values = (user_avatars::url.eq(url), user_avatars::failed_at.eq(now));
+
+        diesel::insert_into(user_avatars::table)
+            .values((user_avatars::user_id.eq(user_id), values))
+            .on_conflict(user_avatars::user_id)
+            .do_update()
+            .set(values)
+            .execute(conn)
+    }
+}
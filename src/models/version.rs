@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::NaiveDateTime;
 use derive_builder::Builder;
@@ -8,7 +8,7 @@ use crate::util::errors::{bad_request, AppResult};
 
 use crate::models::{Crate, Dependency, User};
 use crate::schema::*;
-use crate::sql::split_part;
+use crate::sql::{pg_enum, split_part};
 use crate::util::diesel::Conn;
 
 // Queryable has a custom implementation below
@@ -31,6 +31,24 @@ pub struct Version {
     pub rust_version: Option<String>,
     pub has_lib: Option<bool>,
     pub bin_names: Option<Vec<Option<String>>>,
+    pub semver_ord: Option<serde_json::Value>,
+    pub has_build_script: Option<bool>,
+    pub is_proc_macro: Option<bool>,
+    pub docs_build_status: Option<DocsBuildStatus>,
+    pub uncompressed_size: Option<i64>,
+    pub channel: Option<String>,
+    pub normalized_checksum: Option<String>,
+    pub flag_message: Option<String>,
+    pub flagged_at: Option<NaiveDateTime>,
+}
+
+// Status of the documentation build of a version on docs.rs
+pg_enum! {
+    pub enum DocsBuildStatus {
+        InProgress = 0,
+        Success = 1,
+        Failure = 2,
+    }
 }
 
 impl Version {
@@ -43,14 +61,38 @@ impl Version {
             .load(conn)
     }
 
-    pub fn record_readme_rendering(version_id: i32, conn: &mut impl Conn) -> QueryResult<usize> {
+    /// Records that the README of the version was rendered with the given
+    /// version of the sanitizer policy.
+    pub fn record_readme_rendering(
+        version_id: i32,
+        policy_version: i32,
+        conn: &mut impl Conn,
+    ) -> QueryResult<usize> {
         use diesel::dsl::now;
 
         diesel::insert_into(readme_renderings::table)
-            .values(readme_renderings::version_id.eq(version_id))
+            .values((
+                readme_renderings::version_id.eq(version_id),
+                readme_renderings::policy_version.eq(policy_version),
+            ))
             .on_conflict(readme_renderings::version_id)
             .do_update()
-            .set(readme_renderings::rendered_at.eq(now))
+            .set((
+                readme_renderings::rendered_at.eq(now),
+                readme_renderings::policy_version.eq(policy_version),
+            ))
+            .execute(conn)
+    }
+
+    /// Records the table of contents of the rendered README of the version,
+    /// after the rendering itself was recorded.
+    pub fn record_readme_table_of_contents(
+        version_id: i32,
+        table_of_contents: Option<&serde_json::Value>,
+        conn: &mut impl Conn,
+    ) -> QueryResult<usize> {
+        diesel::update(readme_renderings::table.find(version_id))
+            .set(readme_renderings::table_of_contents.eq(table_of_contents))
             .execute(conn)
     }
 
@@ -89,6 +131,16 @@ pub struct NewVersion {
     pub has_lib: Option<bool>,
     #[builder(default, setter(strip_option))]
     pub bin_names: Option<Vec<String>>,
+    #[builder(default, setter(strip_option))]
+    pub has_build_script: Option<bool>,
+    #[builder(default, setter(strip_option))]
+    pub is_proc_macro: Option<bool>,
+    #[builder(default, setter(strip_option))]
+    pub uncompressed_size: Option<i64>,
+    #[builder(default)]
+    channel: Option<String>,
+    #[builder(default)]
+    normalized_checksum: Option<String>,
 }
 
 impl NewVersionBuilder {
@@ -157,7 +209,7 @@ fn strip_build_metadata(version: &str) -> &str {
 
 /// The highest version (semver order) and the most recently updated version.
 /// Typically used for a single crate.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct TopVersions {
     /// The "highest" version in terms of semver
     pub highest: Option<semver::Version>,
@@ -168,6 +220,75 @@ pub struct TopVersions {
 }
 
 impl TopVersions {
+    /// Load the top versions of the given crates from the database.
+    ///
+    /// The semver order is determined by the `semver_ord` column. Yanked
+    /// versions and versions that can't be parsed are ignored. Crates without
+    /// any remaining versions are not included in the returned map.
+    pub fn for_crates(conn: &mut impl Conn, crate_ids: &[i32]) -> QueryResult<HashMap<i32, Self>> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::Bool;
+
+        // Stable versions use an object instead of an array for the
+        // prerelease part of their `semver_ord` value
+        let is_stable = sql::<Bool>("jsonb_typeof(versions.semver_ord -> 3) = 'object'");
+
+        let highest: Vec<(i32, String)> = versions::table
+            .filter(versions::crate_id.eq_any(crate_ids))
+            .filter(versions::yanked.eq(false))
+            .filter(versions::semver_ord.is_not_null())
+            .select((versions::crate_id, versions::num))
+            .distinct_on(versions::crate_id)
+            .order((
+                versions::crate_id,
+                versions::semver_ord.desc(),
+                versions::id.desc(),
+            ))
+            .load(conn)?;
+
+        let highest_stable: Vec<(i32, String)> = versions::table
+            .filter(versions::crate_id.eq_any(crate_ids))
+            .filter(versions::yanked.eq(false))
+            .filter(versions::semver_ord.is_not_null())
+            .filter(is_stable)
+            .select((versions::crate_id, versions::num))
+            .distinct_on(versions::crate_id)
+            .order((
+                versions::crate_id,
+                versions::semver_ord.desc(),
+                versions::id.desc(),
+            ))
+            .load(conn)?;
+
+        let newest: Vec<(i32, String)> = versions::table
+            .filter(versions::crate_id.eq_any(crate_ids))
+            .filter(versions::yanked.eq(false))
+            .filter(versions::semver_ord.is_not_null())
+            .select((versions::crate_id, versions::num))
+            .distinct_on(versions::crate_id)
+            .order((
+                versions::crate_id,
+                versions::created_at.desc(),
+                versions::id.desc(),
+            ))
+            .load(conn)?;
+
+        let parse = |num: String| semver::Version::parse(&num).ok();
+
+        let mut top_versions: HashMap<i32, Self> = HashMap::new();
+        for (crate_id, num) in highest {
+            top_versions.entry(crate_id).or_default().highest = parse(num);
+        }
+        for (crate_id, num) in highest_stable {
+            top_versions.entry(crate_id).or_default().highest_stable = parse(num);
+        }
+        for (crate_id, num) in newest {
+            top_versions.entry(crate_id).or_default().newest = parse(num);
+        }
+
+        Ok(top_versions)
+    }
+
     /// Return both the newest (most recently updated) and the
     /// highest version (in semver order) for a list of `Version` instances.
     pub fn from_versions(versions: Vec<Version>) -> Self {
@@ -0,0 +1,90 @@
+use crate::models::{NewRegistryEvent, RegistryEventKind, VersionAction};
+use crate::schema::{version_owner_actions, versions};
+use crate::util::diesel::Conn;
+use crate::worker::jobs::{self, UpdateDefaultVersion};
+use crates_io_worker::{BackgroundJob, EnqueueError};
+use diesel::prelude::*;
+
+/// The user or process that yanks or unyanks versions.
+#[derive(Debug, Clone, Copy)]
+pub enum YankActor {
+    /// A user, optionally authenticated via one of their API tokens.
+    User {
+        user_id: i32,
+        api_token_id: Option<i32>,
+    },
+    /// crates.io itself, e.g. a background job or an admin command. These
+    /// changes are recorded without a user.
+    System,
+}
+
+/// Yanks or unyanks the given versions of a crate.
+///
+/// The change is recorded in the version owner actions and the registry
+/// events, and the index update and the default version update are
+/// enqueued. Versions that are already in the requested state are skipped.
+///
+/// Returns the numbers of the versions that were changed.
+pub fn set_yanked(
+    conn: &mut impl Conn,
+    crate_id: i32,
+    crate_name: &str,
+    version_ids: &[i32],
+    yanked: bool,
+    actor: YankActor,
+    reason: Option<&str>,
+) -> Result<Vec<String>, EnqueueError> {
+    let (action, event_kind) = if yanked {
+        (VersionAction::Yank, RegistryEventKind::Yank)
+    } else {
+        (VersionAction::Unyank, RegistryEventKind::Unyank)
+    };
+
+    let (user_id, api_token_id) = match actor {
+        YankActor::User {
+            user_id,
+            api_token_id,
+        } => (Some(user_id), api_token_id),
+        YankActor::System => (None, None),
+    };
+
+    conn.transaction(|conn| {
+        let changed: Vec<(i32, String)> = diesel::update(versions::table)
+            .filter(versions::crate_id.eq(crate_id))
+            .filter(versions::id.eq_any(version_ids))
+            .filter(versions::yanked.ne(yanked))
+            .set(versions::yanked.eq(yanked))
+            .returning((versions::id, versions::num))
+            .get_results(conn)?;
+
+        if changed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let actions = changed
+            .iter()
+            .map(|(version_id, _)| {
+                (
+                    version_owner_actions::version_id.eq(*version_id),
+                    version_owner_actions::user_id.eq(user_id),
+                    version_owner_actions::api_token_id.eq(api_token_id),
+                    version_owner_actions::action.eq(action),
+                    version_owner_actions::reason.eq(reason),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(version_owner_actions::table)
+            .values(&actions)
+            .execute(conn)?;
+
+        for (_, num) in &changed {
+            NewRegistryEvent::version(event_kind, crate_name, num).insert(conn)?;
+        }
+
+        jobs::enqueue_sync_to_index(crate_name, conn)?;
+        UpdateDefaultVersion::new(crate_id).enqueue(conn)?;
+
+        Ok(changed.into_iter().map(|(_, num)| num).collect())
+    })
+}
@@ -0,0 +1,427 @@
+//! Decisions about what users may do with a crate
+//!
+//! The endpoints that change a crate load the [`Permissions`] of the user on
+//! the crate and check the [`Capability`] that the endpoint requires. The
+//! policy itself is implemented by the pure [`Permissions::decide`] function,
+//! so that the whole matrix of ownership, admin status, freezes and the
+//! two-factor requirement of critical crates can be tested without a
+//! database.
+//!
+//! Token scopes are checked separately by [`AuthCheck`](crate::auth::AuthCheck),
+//! before the permissions are loaded.
+
+use crate::app::App;
+use crate::models::{Crate, CrateFreeze, CrateVisibility, CriticalCrate, Owner, Rights, User};
+use crate::util::diesel::Conn;
+use crate::util::errors::{crate_not_found, custom, AppResult, BoxedAppError};
+use http::StatusCode;
+use tokio::runtime::Handle;
+
+const MISSING_RIGHTS_ERROR_MESSAGE: &str = "this crate exists but you don't seem to be an owner. \
+     If you believe this is a mistake, perhaps you need \
+     to accept an invitation to be an owner before \
+     publishing.";
+
+/// An action on an existing crate that requires permissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Publishing new versions of the crate.
+    Publish,
+    /// Yanking, unyanking and flagging versions of the crate.
+    Yank,
+    /// Inviting and removing owners of the crate.
+    ManageOwners,
+    /// Viewing the settings of the crate.
+    ViewSettings,
+    /// Changing the settings of the crate.
+    ChangeSettings,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::Publish,
+        Capability::Yank,
+        Capability::ManageOwners,
+        Capability::ViewSettings,
+        Capability::ChangeSettings,
+    ];
+}
+
+/// The reason why a [`Capability`] is denied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denial {
+    /// The user is neither an owner nor a member of an owning team.
+    NotOwner,
+    /// The user is a member of an owning team, but the capability is
+    /// reserved for the owners themselves.
+    TeamMember,
+    /// The crate is private and the user can not see it at all.
+    NotVisible,
+    /// The crate has been frozen by an admin.
+    Frozen { reason: String },
+    /// The crate is critical, but the user has not enabled two-factor
+    /// authentication on their GitHub account.
+    MissingTwoFactor,
+}
+
+/// Everything about a user and a crate that the permission decisions are
+/// based on.
+#[derive(Debug, Clone)]
+pub struct Permissions {
+    crate_name: String,
+    rights: Rights,
+    is_admin: bool,
+    has_two_factor: bool,
+    is_private: bool,
+    is_critical: bool,
+    freeze_reason: Option<String>,
+}
+
+impl Permissions {
+    /// Loads the permissions of the user on the crate with the given owners.
+    ///
+    /// Note that this function blocks on the GitHub API for team owners, so
+    /// it must be called from a `spawn_blocking()` context.
+    pub fn load(
+        app: &App,
+        user: &User,
+        krate: &Crate,
+        owners: &[Owner],
+        conn: &mut impl Conn,
+    ) -> AppResult<Self> {
+        let rights = Handle::current().block_on(user.rights(app, owners))?;
+        let freeze = CrateFreeze::for_crate(conn, krate.id)?;
+
+        Ok(Self {
+            crate_name: krate.name.clone(),
+            rights,
+            is_admin: user.is_admin,
+            has_two_factor: user.gh_two_factor,
+            is_private: krate.visibility == CrateVisibility::Private,
+            is_critical: CriticalCrate::is_critical(conn, krate.id)?,
+            freeze_reason: freeze.map(|freeze| freeze.reason),
+        })
+    }
+
+    pub fn rights(&self) -> Rights {
+        self.rights
+    }
+
+    /// Whether the user is an owner of the crate or a member of an owning
+    /// team.
+    pub fn is_owner(&self) -> bool {
+        self.rights >= Rights::Publish
+    }
+
+    /// Decides whether the user has the capability.
+    ///
+    /// Admins may yank versions of all crates (e.g. because of malware) and
+    /// manage the settings of all crates, but they can not publish or change
+    /// the owners of crates that they don't own.
+    ///
+    /// Private crates don't exist for users that can't see them, so all
+    /// capabilities are denied without revealing the crate.
+    pub fn decide(&self, capability: Capability) -> Result<(), Denial> {
+        if self.is_private && !self.is_admin && self.rights == Rights::None {
+            return Err(Denial::NotVisible);
+        }
+
+        match capability {
+            Capability::Publish => {
+                self.ensure_rights(Rights::Publish)?;
+                self.ensure_not_frozen()?;
+                self.ensure_two_factor()
+            }
+            Capability::Yank if self.is_admin => Ok(()),
+            Capability::Yank => {
+                self.ensure_rights(Rights::Publish)?;
+                self.ensure_two_factor()
+            }
+            Capability::ManageOwners => {
+                self.ensure_rights(Rights::Full)?;
+                self.ensure_not_frozen()?;
+                self.ensure_two_factor()
+            }
+            Capability::ViewSettings | Capability::ChangeSettings if self.is_admin => Ok(()),
+            Capability::ViewSettings | Capability::ChangeSettings => {
+                self.ensure_rights(Rights::Full)
+            }
+        }
+    }
+
+    /// Returns the error for the endpoints of the capability if the user
+    /// doesn't have it.
+    pub fn ensure(&self, capability: Capability) -> AppResult<()> {
+        self.decide(capability)
+            .map_err(|denial| self.error(capability, denial))
+    }
+
+    fn ensure_rights(&self, required: Rights) -> Result<(), Denial> {
+        match self.rights {
+            rights if rights >= required => Ok(()),
+            Rights::None => Err(Denial::NotOwner),
+            _ => Err(Denial::TeamMember),
+        }
+    }
+
+    fn ensure_not_frozen(&self) -> Result<(), Denial> {
+        match &self.freeze_reason {
+            Some(reason) => Err(Denial::Frozen {
+                reason: reason.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn ensure_two_factor(&self) -> Result<(), Denial> {
+        if self.is_critical && !self.has_two_factor {
+            return Err(Denial::MissingTwoFactor);
+        }
+        Ok(())
+    }
+
+    fn error(&self, capability: Capability, denial: Denial) -> BoxedAppError {
+        let crate_name = &self.crate_name;
+        let detail = match denial {
+            Denial::NotVisible => return crate_not_found(crate_name),
+            Denial::Frozen { reason } => return CrateFreeze::error(crate_name, &reason),
+            Denial::MissingTwoFactor => return CriticalCrate::two_factor_error(crate_name),
+            Denial::TeamMember if capability == Capability::ManageOwners => {
+                "team members don't have permission to modify owners"
+            }
+            Denial::NotOwner | Denial::TeamMember => match capability {
+                Capability::Publish => MISSING_RIGHTS_ERROR_MESSAGE,
+                Capability::Yank => "must already be an owner to yank or unyank",
+                Capability::ManageOwners => "only owners have permission to modify owners",
+                Capability::ViewSettings => {
+                    "only owners have permission to view the settings of a crate"
+                }
+                Capability::ChangeSettings => {
+                    "only owners have permission to change the settings of a crate"
+                }
+            },
+        };
+
+        custom(StatusCode::FORBIDDEN, detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(rights: Rights) -> Permissions {
+        Permissions {
+            crate_name: "foo".into(),
+            rights,
+            is_admin: false,
+            has_two_factor: false,
+            is_private: false,
+            is_critical: false,
+            freeze_reason: None,
+        }
+    }
+
+    fn admin() -> Permissions {
+        Permissions {
+            is_admin: true,
+            ..permissions(Rights::None)
+        }
+    }
+
+    fn frozen(rights: Rights) -> Permissions {
+        Permissions {
+            freeze_reason: Some("malware".into()),
+            ..permissions(rights)
+        }
+    }
+
+    fn critical(rights: Rights, has_two_factor: bool) -> Permissions {
+        Permissions {
+            is_critical: true,
+            has_two_factor,
+            ..permissions(rights)
+        }
+    }
+
+    fn private(rights: Rights) -> Permissions {
+        Permissions {
+            is_private: true,
+            ..permissions(rights)
+        }
+    }
+
+    fn decisions(permissions: &Permissions) -> Vec<Result<(), Denial>> {
+        Capability::ALL
+            .iter()
+            .map(|capability| permissions.decide(*capability))
+            .collect()
+    }
+
+    fn frozen_denial() -> Denial {
+        Denial::Frozen {
+            reason: "malware".into(),
+        }
+    }
+
+    #[test]
+    fn owners() {
+        let expected = vec![Ok(()), Ok(()), Ok(()), Ok(()), Ok(())];
+        assert_eq!(decisions(&permissions(Rights::Full)), expected);
+        assert_eq!(decisions(&private(Rights::Full)), expected);
+        assert_eq!(decisions(&critical(Rights::Full, true)), expected);
+    }
+
+    #[test]
+    fn team_members() {
+        let expected = vec![
+            Ok(()),
+            Ok(()),
+            Err(Denial::TeamMember),
+            Err(Denial::TeamMember),
+            Err(Denial::TeamMember),
+        ];
+        assert_eq!(decisions(&permissions(Rights::Publish)), expected);
+        assert_eq!(decisions(&private(Rights::Publish)), expected);
+    }
+
+    #[test]
+    fn other_users() {
+        let expected = vec![
+            Err(Denial::NotOwner),
+            Err(Denial::NotOwner),
+            Err(Denial::NotOwner),
+            Err(Denial::NotOwner),
+            Err(Denial::NotOwner),
+        ];
+        assert_eq!(decisions(&permissions(Rights::None)), expected);
+        assert_eq!(decisions(&frozen(Rights::None)), expected);
+        assert_eq!(decisions(&critical(Rights::None, false)), expected);
+
+        let expected = vec![Err(Denial::NotVisible); 5];
+        assert_eq!(decisions(&private(Rights::None)), expected);
+
+        let private_frozen = Permissions {
+            freeze_reason: Some("malware".into()),
+            ..private(Rights::None)
+        };
+        assert_eq!(decisions(&private_frozen), expected);
+    }
+
+    #[test]
+    fn admins() {
+        let expected = vec![
+            Err(Denial::NotOwner),
+            Ok(()),
+            Err(Denial::NotOwner),
+            Ok(()),
+            Ok(()),
+        ];
+        assert_eq!(decisions(&admin()), expected);
+
+        let private_admin = Permissions {
+            is_private: true,
+            ..admin()
+        };
+        assert_eq!(decisions(&private_admin), expected);
+
+        // Admins may yank versions of critical crates without two-factor
+        // authentication, e.g. because of malware
+        let critical_admin = Permissions {
+            is_critical: true,
+            ..admin()
+        };
+        assert_eq!(decisions(&critical_admin), expected);
+    }
+
+    #[test]
+    fn frozen_crates() {
+        let expected = vec![
+            Err(frozen_denial()),
+            Ok(()),
+            Err(frozen_denial()),
+            Ok(()),
+            Ok(()),
+        ];
+        assert_eq!(decisions(&frozen(Rights::Full)), expected);
+
+        let expected = vec![
+            Err(frozen_denial()),
+            Ok(()),
+            Err(Denial::TeamMember),
+            Err(Denial::TeamMember),
+            Err(Denial::TeamMember),
+        ];
+        assert_eq!(decisions(&frozen(Rights::Publish)), expected);
+    }
+
+    #[test]
+    fn critical_crates() {
+        let expected = vec![
+            Err(Denial::MissingTwoFactor),
+            Err(Denial::MissingTwoFactor),
+            Err(Denial::MissingTwoFactor),
+            Ok(()),
+            Ok(()),
+        ];
+        assert_eq!(decisions(&critical(Rights::Full, false)), expected);
+
+        let expected = vec![
+            Ok(()),
+            Ok(()),
+            Err(Denial::TeamMember),
+            Err(Denial::TeamMember),
+            Err(Denial::TeamMember),
+        ];
+        assert_eq!(decisions(&critical(Rights::Publish, true)), expected);
+    }
+
+    #[test]
+    fn frozen_critical_crates_report_the_freeze_first() {
+        let permissions = Permissions {
+            freeze_reason: Some("malware".into()),
+            ..critical(Rights::Full, false)
+        };
+        assert_eq!(
+            permissions.decide(Capability::Publish),
+            Err(frozen_denial())
+        );
+        assert_eq!(
+            permissions.decide(Capability::Yank),
+            Err(Denial::MissingTwoFactor)
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        let message = |permissions: &Permissions, capability| {
+            permissions.ensure(capability).unwrap_err().to_string()
+        };
+
+        let team_member = permissions(Rights::Publish);
+        assert_eq!(
+            message(&team_member, Capability::ManageOwners),
+            "team members don't have permission to modify owners"
+        );
+        assert_eq!(
+            message(&team_member, Capability::ChangeSettings),
+            "only owners have permission to change the settings of a crate"
+        );
+
+        let other = permissions(Rights::None);
+        assert_eq!(
+            message(&other, Capability::Publish),
+            MISSING_RIGHTS_ERROR_MESSAGE
+        );
+        assert_eq!(
+            message(&other, Capability::Yank),
+            "must already be an owner to yank or unyank"
+        );
+        for capability in Capability::ALL {
+            assert_eq!(
+                message(&private(Rights::None), capability),
+                "crate `foo` does not exist"
+            );
+        }
+    }
+}
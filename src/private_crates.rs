@@ -0,0 +1,81 @@
+//! In-memory cache of the names of private crates.
+//!
+//! Downloads of public crates are redirected to the CDN without a database
+//! query. Only the downloads of private crates, which require an ownership
+//! check, look up the crate. The names are reloaded from the database
+//! periodically, and whenever the visibility of a crate is changed via this
+//! instance.
+//!
+//! A stale cache never exposes a private crate, since the crate files of
+//! private crates are moved out of the public storage area (see
+//! [`crate::worker::jobs::SyncCrateFileVisibility`]).
+
+use crate::models::CrateVisibility;
+use crate::schema::crates;
+use crate::sql::canon_crate_name;
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::App;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use parking_lot::RwLock;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the names of private crates are reloaded from the database.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+pub struct PrivateCrates {
+    /// The canonical names of all private crates, or `None` until they have
+    /// been loaded for the first time.
+    names: RwLock<Option<HashSet<String>>>,
+}
+
+impl PrivateCrates {
+    /// Replaces the cached names with the private crates in the database.
+    pub fn refresh(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        let names: Vec<String> = crates::table
+            .filter(crates::visibility.eq(CrateVisibility::Private))
+            .select(canon_crate_name(crates::name))
+            .load(conn)?;
+
+        *self.names.write() = Some(names.into_iter().collect());
+        Ok(())
+    }
+
+    /// Returns whether the crate with the given name is private, or `None`
+    /// if the names have not been loaded yet, in which case the caller has
+    /// to look up the crate in the database.
+    pub fn is_private(&self, name: &str) -> Option<bool> {
+        let canonical_name = name.replace('-', "_").to_lowercase();
+        let names = self.names.read();
+        Some(names.as_ref()?.contains(&canonical_name))
+    }
+}
+
+/// Reloads the names of private crates in a fixed interval, so that changes
+/// made via other instances are picked up.
+///
+/// This function never returns and is meant to be spawned as a task.
+pub async fn refresh_periodically(app: Arc<App>) {
+    let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let app = app.clone();
+        let result = async {
+            let conn = app.db_read().await?;
+            spawn_blocking(move || {
+                let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+                Ok::<_, anyhow::Error>(app.private_crates.refresh(conn)?)
+            })
+            .await
+        };
+
+        if let Err(error) = result.await {
+            warn!("Failed to refresh the names of private crates: {error}");
+        }
+    }
+}
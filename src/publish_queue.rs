@@ -0,0 +1,324 @@
+//! Fair scheduling of concurrent publishes across users.
+//!
+//! Publishing is one of the most expensive operations of the application. A
+//! single user publishing all crates of a large workspace at once could
+//! otherwise occupy most of the capacity of an instance and starve the
+//! publishes of other users, even while staying within their rate limits.
+//!
+//! If a maximum number of concurrent publishes is configured, publishes
+//! beyond that limit wait in a queue per user. Whenever a publish finishes,
+//! its slot is handed to the next waiting publish in round-robin order across
+//! users, so that every user with waiting publishes gets a turn before any
+//! user gets a second one.
+//!
+//! Once a user has the configured maximum number of waiting publishes,
+//! further publishes of that user are rejected with a
+//! `429 Too Many Requests` response, which acts as back-pressure for the
+//! client without affecting other users.
+
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+pub const DEFAULT_MAX_QUEUED_PER_USER: usize = 10;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PublishQueueConfig {
+    /// The maximum number of publishes that are processed concurrently by
+    /// this instance. Publishes are not queued if this is not set.
+    pub max_concurrent: Option<usize>,
+    /// The maximum number of waiting publishes of a single user, above which
+    /// further publishes of the user are rejected.
+    pub max_queued_per_user: usize,
+}
+
+impl Default for PublishQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            max_queued_per_user: DEFAULT_MAX_QUEUED_PER_USER,
+        }
+    }
+}
+
+/// The publish could not be queued, because the user already has the
+/// maximum number of waiting publishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull;
+
+/// A snapshot of the state of the queue, for the instance metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PublishQueueStats {
+    /// The number of publishes that are currently being processed.
+    pub running: usize,
+    /// The number of publishes that are waiting for a slot.
+    pub waiting: usize,
+    /// The number of users with waiting publishes.
+    pub waiting_users: usize,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    running: usize,
+    /// The users with waiting publishes, in the order of their next turn.
+    turns: VecDeque<i32>,
+    /// The waiting publishes of each user in `turns`, oldest first.
+    waiting: HashMap<i32, VecDeque<oneshot::Sender<PublishPermit>>>,
+}
+
+#[derive(Debug)]
+pub struct PublishQueue {
+    config: PublishQueueConfig,
+    state: Arc<Mutex<State>>,
+}
+
+impl PublishQueue {
+    pub fn new(config: PublishQueueConfig) -> Self {
+        Self {
+            config,
+            state: Default::default(),
+        }
+    }
+
+    /// Waits until a publish of the given user may be processed.
+    ///
+    /// The publish may be processed as long as the returned permit is held.
+    pub async fn acquire(&self, user_id: i32) -> Result<PublishPermit, QueueFull> {
+        let max_concurrent = self.config.max_concurrent.unwrap_or(usize::MAX);
+
+        let receiver = {
+            let mut guard = self.state.lock();
+            let state = &mut *guard;
+            if state.running < max_concurrent && state.turns.is_empty() {
+                state.running += 1;
+                return Ok(self.permit());
+            }
+
+            let max_queued = self.config.max_queued_per_user;
+            let queue = state.waiting.entry(user_id).or_default();
+
+            // Publishes whose requests were cancelled don't count towards
+            // the limit anymore.
+            queue.retain(|sender| !sender.is_closed());
+            if queue.len() >= max_queued {
+                if queue.is_empty() {
+                    state.waiting.remove(&user_id);
+                }
+                return Err(QueueFull);
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            queue.push_back(sender);
+            if queue.len() == 1 && !state.turns.contains(&user_id) {
+                state.turns.push_back(user_id);
+            }
+
+            receiver
+        };
+
+        // The sender is only dropped after a permit was sent through it.
+        Ok(receiver
+            .await
+            .expect("publish queue dropped a waiting publish"))
+    }
+
+    /// Returns a snapshot of the state of the queue.
+    pub fn stats(&self) -> PublishQueueStats {
+        let state = self.state.lock();
+        PublishQueueStats {
+            running: state.running,
+            waiting: state.waiting.values().map(VecDeque::len).sum(),
+            waiting_users: state.turns.len(),
+        }
+    }
+
+    fn permit(&self) -> PublishPermit {
+        PublishPermit {
+            state: Some(self.state.clone()),
+        }
+    }
+}
+
+/// A slot for processing a publish, which is handed to the next waiting
+/// publish once it's dropped.
+#[derive(Debug)]
+pub struct PublishPermit {
+    state: Option<Arc<Mutex<State>>>,
+}
+
+impl Drop for PublishPermit {
+    fn drop(&mut self) {
+        let Some(shared) = self.state.take() else {
+            return;
+        };
+
+        let mut guard = shared.lock();
+        let state = &mut *guard;
+        while let Some(user_id) = state.turns.pop_front() {
+            let Some(queue) = state.waiting.get_mut(&user_id) else {
+                continue;
+            };
+
+            let sender = queue.pop_front();
+            if queue.is_empty() {
+                state.waiting.remove(&user_id);
+            } else {
+                state.turns.push_back(user_id);
+            }
+
+            let Some(sender) = sender else {
+                continue;
+            };
+
+            let permit = PublishPermit {
+                state: Some(shared.clone()),
+            };
+
+            // If the waiting publish was cancelled in the meantime, the
+            // permit is returned and the slot goes to the next one instead.
+            // Should the waiting publish be cancelled after receiving the
+            // permit, dropping it hands the slot on as well.
+            match sender.send(permit) {
+                Ok(()) => return,
+                Err(mut permit) => permit.state = None,
+            }
+        }
+
+        state.running -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio::task::JoinHandle;
+
+    type Started = mpsc::UnboundedSender<(i32, oneshot::Sender<()>)>;
+
+    fn queue(max_concurrent: usize, max_queued_per_user: usize) -> Arc<PublishQueue> {
+        Arc::new(PublishQueue::new(PublishQueueConfig {
+            max_concurrent: Some(max_concurrent),
+            max_queued_per_user,
+        }))
+    }
+
+    /// Queues a publish of the user, which reports its user id once it's
+    /// processed and then holds its permit until the reported sender is
+    /// dropped.
+    fn spawn_publish(
+        queue: &Arc<PublishQueue>,
+        user_id: i32,
+        started: &Started,
+    ) -> JoinHandle<Result<(), QueueFull>> {
+        let queue = queue.clone();
+        let started = started.clone();
+        tokio::spawn(async move {
+            let _permit = queue.acquire(user_id).await?;
+            let (release, released) = oneshot::channel();
+            started.send((user_id, release)).unwrap();
+            let _ = released.await;
+            Ok(())
+        })
+    }
+
+    async fn wait_for_waiting(queue: &PublishQueue, waiting: usize) {
+        while queue.stats().waiting != waiting {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_are_not_queued_by_default() {
+        let queue = PublishQueue::new(PublishQueueConfig::default());
+
+        let mut permits = vec![];
+        for _ in 0..100 {
+            permits.push(queue.acquire(1).await.unwrap());
+        }
+        assert_eq!(queue.stats().running, 100);
+
+        drop(permits);
+        assert_eq!(queue.stats(), PublishQueueStats::default());
+    }
+
+    #[tokio::test]
+    async fn slots_are_handed_out_round_robin() {
+        let queue = queue(1, 10);
+        let (started, mut started_rx) = mpsc::unbounded_channel();
+
+        spawn_publish(&queue, 1, &started);
+        let (_, mut release) = started_rx.recv().await.unwrap();
+
+        // User 1 queues a whole workspace before users 2 and 3 queue theirs.
+        for (waiting, user_id) in [1, 1, 1, 2, 3, 2].into_iter().enumerate() {
+            spawn_publish(&queue, user_id, &started);
+            wait_for_waiting(&queue, waiting + 1).await;
+        }
+
+        assert_eq!(
+            queue.stats(),
+            PublishQueueStats {
+                running: 1,
+                waiting: 6,
+                waiting_users: 3,
+            }
+        );
+
+        let mut order = vec![];
+        for _ in 0..6 {
+            drop(release);
+            let (user_id, next) = started_rx.recv().await.unwrap();
+            order.push(user_id);
+            release = next;
+        }
+
+        assert_eq!(order, [1, 2, 3, 1, 2, 1]);
+        assert_eq!(queue.stats().waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn users_with_full_queues_are_rejected() {
+        let queue = queue(1, 2);
+        let (started, mut started_rx) = mpsc::unbounded_channel();
+
+        spawn_publish(&queue, 1, &started);
+        let _running = started_rx.recv().await.unwrap();
+
+        spawn_publish(&queue, 1, &started);
+        spawn_publish(&queue, 1, &started);
+        wait_for_waiting(&queue, 2).await;
+
+        let rejected = spawn_publish(&queue, 1, &started);
+        assert_eq!(rejected.await.unwrap(), Err(QueueFull));
+
+        // Other users can still queue their publishes
+        spawn_publish(&queue, 2, &started);
+        wait_for_waiting(&queue, 3).await;
+    }
+
+    #[tokio::test]
+    async fn cancelled_publishes_pass_on_their_slot() {
+        let queue = queue(1, 10);
+        let (started, mut started_rx) = mpsc::unbounded_channel();
+
+        spawn_publish(&queue, 1, &started);
+        let (_, release) = started_rx.recv().await.unwrap();
+
+        let cancelled = spawn_publish(&queue, 2, &started);
+        wait_for_waiting(&queue, 1).await;
+        spawn_publish(&queue, 3, &started);
+        wait_for_waiting(&queue, 2).await;
+
+        cancelled.abort();
+        let _ = cancelled.await;
+        drop(release);
+
+        let (user_id, _release) = started_rx.recv().await.unwrap();
+        assert_eq!(user_id, 3);
+        assert_eq!(queue.stats().running, 1);
+        assert_eq!(queue.stats().waiting, 0);
+    }
+}
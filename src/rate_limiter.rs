@@ -1,5 +1,5 @@
-use crate::schema::{publish_limit_buckets, publish_rate_overrides};
-use crate::sql::{date_part, floor, greatest, interval_part, least, pg_enum};
+use crate::schema::{publish_limit_buckets, publish_rate_overrides, rate_limit_rejections};
+use crate::sql::{date_part, floor, greatest, least, pg_enum, round};
 use crate::util::diesel::Conn;
 use crate::util::errors::{AppResult, TooManyRequests};
 use chrono::{NaiveDateTime, Utc};
@@ -10,11 +10,16 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::time::Duration;
 
+/// The number of days that rejected requests are kept in the
+/// `rate_limit_rejections` table.
+pub const REJECTIONS_RETENTION_DAYS: i64 = 90;
+
 pg_enum! {
     pub enum LimitedAction {
         PublishNew = 0,
         PublishUpdate = 1,
         YankUnyank = 2,
+        BulkMetadata = 3,
     }
 }
 
@@ -24,6 +29,7 @@ impl LimitedAction {
             LimitedAction::PublishNew => 10 * 60, // 10 minutes
             LimitedAction::PublishUpdate => 60,   // 1 minute
             LimitedAction::YankUnyank => 60,      // 1 minute
+            LimitedAction::BulkMetadata => 1,     // 1 second
         }
     }
 
@@ -32,6 +38,7 @@ impl LimitedAction {
             LimitedAction::PublishNew => 5,
             LimitedAction::PublishUpdate => 30,
             LimitedAction::YankUnyank => 100,
+            LimitedAction::BulkMetadata => 60,
         }
     }
 
@@ -40,6 +47,18 @@ impl LimitedAction {
             LimitedAction::PublishNew => "PUBLISH_NEW",
             LimitedAction::PublishUpdate => "PUBLISH_UPDATE",
             LimitedAction::YankUnyank => "YANK_UNYANK",
+            LimitedAction::BulkMetadata => "BULK_METADATA",
+        }
+    }
+
+    /// A short description of the action for emails, e.g. "publishing new
+    /// crates".
+    pub fn description(&self) -> &'static str {
+        match self {
+            LimitedAction::PublishNew => "publishing new crates",
+            LimitedAction::PublishUpdate => "publishing updates to existing crates",
+            LimitedAction::YankUnyank => "yanking and unyanking versions",
+            LimitedAction::BulkMetadata => "requesting the metadata of crates in bulk",
         }
     }
 
@@ -54,6 +73,46 @@ impl LimitedAction {
             LimitedAction::YankUnyank => {
                 "You have yanked or unyanked too many versions in a short period of time"
             }
+            LimitedAction::BulkMetadata => {
+                "You have requested the metadata of too many crates in a short period of time"
+            }
+        }
+    }
+}
+
+/// A rate limited action, optionally narrowed down to a scope.
+///
+/// Actions without a scope share a single bucket per user, while scoped
+/// actions have a separate bucket per user and scope, e.g. per crate. The
+/// rate, burst and overrides are configured per [`LimitedAction`] and apply
+/// to all scopes of the action.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionDescriptor {
+    pub action: LimitedAction,
+    pub scope: Option<String>,
+}
+
+impl ActionDescriptor {
+    pub fn scoped(action: LimitedAction, scope: impl Into<String>) -> Self {
+        Self {
+            action,
+            scope: Some(scope.into()),
+        }
+    }
+
+    /// Returns the value of the `scope` column of the bucket, which is an
+    /// empty string for actions without a scope, since the column is part of
+    /// the primary key and can't be `NULL`.
+    fn bucket_scope(&self) -> &str {
+        self.scope.as_deref().unwrap_or_default()
+    }
+}
+
+impl From<LimitedAction> for ActionDescriptor {
+    fn from(action: LimitedAction) -> Self {
+        Self {
+            action,
+            scope: None,
         }
     }
 }
@@ -77,18 +136,21 @@ impl RateLimiter {
     pub fn check_rate_limit(
         &self,
         uploader: i32,
-        performed_action: LimitedAction,
+        performed_action: impl Into<ActionDescriptor>,
         conn: &mut impl Conn,
     ) -> AppResult<()> {
-        let bucket = self.take_token(uploader, performed_action, Utc::now().naive_utc(), conn)?;
+        let performed_action = performed_action.into();
+        let bucket = self.take_token(uploader, &performed_action, Utc::now().naive_utc(), conn)?;
         if bucket.tokens >= 1 {
             Ok(())
         } else {
+            record_rejection(uploader, &performed_action, conn)?;
+
+            let action = performed_action.action;
             Err(Box::new(TooManyRequests {
-                action: performed_action,
+                action,
                 retry_after: bucket.last_refill
-                    + chrono::Duration::from_std(self.config_for_action(performed_action).rate)
-                        .unwrap(),
+                    + chrono::Duration::from_std(self.config_for_action(action).rate).unwrap(),
             }))
         }
     }
@@ -104,15 +166,17 @@ impl RateLimiter {
     fn take_token(
         &self,
         uploader: i32,
-        performed_action: LimitedAction,
+        performed_action: &ActionDescriptor,
         now: NaiveDateTime,
         conn: &mut impl Conn,
     ) -> QueryResult<Bucket> {
-        let config = self.config_for_action(performed_action);
-        let refill_rate = (config.rate.as_millis() as i64).milliseconds();
+        let action = performed_action.action;
+        let config = self.config_for_action(action);
+        let refill_rate_micros = (config.rate.as_micros() as i64).max(1);
+        let refill_rate = refill_rate_micros.microseconds();
 
         let burst: i32 = publish_rate_overrides::table
-            .find((uploader, performed_action))
+            .find((uploader, action))
             .filter(
                 publish_rate_overrides::expires_at
                     .is_null()
@@ -125,22 +189,31 @@ impl RateLimiter {
 
         // Interval division is poorly defined in general (what is 1 month / 30 days?)
         // However, for the intervals we're dealing with, it is always well
-        // defined, so we convert to an f64 of seconds to represent this.
-        let tokens_to_add = floor(
+        // defined. Dividing the elapsed seconds by the rate as floating point
+        // numbers is prone to rounding errors though (e.g. 0.3 / 0.1 is
+        // slightly less than 3), so the elapsed time is rounded to whole
+        // microseconds first, which both timestamps are exact multiples of.
+        //
+        // If the clock went backwards, no tokens are added, instead of taking
+        // tokens away.
+        let elapsed_micros = round(
             (date_part("epoch", now) - date_part("epoch", publish_limit_buckets::last_refill))
-                / interval_part("epoch", refill_rate),
+                * 1_000_000.0,
         );
+        let tokens_to_add = greatest(0, floor(elapsed_micros / refill_rate_micros as f64));
 
         diesel::insert_into(publish_limit_buckets::table)
             .values((
                 publish_limit_buckets::user_id.eq(uploader),
-                publish_limit_buckets::action.eq(performed_action),
+                publish_limit_buckets::action.eq(action),
+                publish_limit_buckets::scope.eq(performed_action.bucket_scope()),
                 publish_limit_buckets::tokens.eq(burst),
                 publish_limit_buckets::last_refill.eq(now),
             ))
             .on_conflict((
                 publish_limit_buckets::user_id,
                 publish_limit_buckets::action,
+                publish_limit_buckets::scope,
             ))
             .do_update()
             .set((
@@ -166,7 +239,23 @@ impl RateLimiter {
     }
 }
 
-#[derive(Queryable, Insertable, Debug, PartialEq, Clone, Copy)]
+/// Records a rejected request in the `rate_limit_rejections` table, so that
+/// admins can see which users are throttled the most.
+fn record_rejection(
+    user_id: i32,
+    action: &ActionDescriptor,
+    conn: &mut impl Conn,
+) -> QueryResult<usize> {
+    diesel::insert_into(rate_limit_rejections::table)
+        .values((
+            rate_limit_rejections::user_id.eq(user_id),
+            rate_limit_rejections::action.eq(action.action),
+            rate_limit_rejections::scope.eq(action.scope.as_deref()),
+        ))
+        .execute(conn)
+}
+
+#[derive(Queryable, Insertable, Debug, PartialEq, Clone)]
 #[diesel(table_name = publish_limit_buckets, check_for_backend(diesel::pg::Pg))]
 #[allow(dead_code)] // Most fields only read in tests
 struct Bucket {
@@ -174,6 +263,7 @@ struct Bucket {
     tokens: i32,
     last_refill: NaiveDateTime,
     action: LimitedAction,
+    scope: String,
 }
 
 #[cfg(test)]
@@ -210,7 +300,7 @@ mod tests {
         let mut expected_last_refill_times = vec![];
         for publish_num in 1..=10 {
             let publish_time = now + chrono::Duration::minutes(10 * publish_num);
-            let bucket = rate.take_token(user_id, action, publish_time, conn)?;
+            let bucket = rate.take_token(user_id, &action.into(), publish_time, conn)?;
 
             last_refill_times.push(bucket.last_refill);
             expected_last_refill_times.push(publish_time);
@@ -225,7 +315,7 @@ mod tests {
         let mut expected_last_refill_times = vec![];
         for publish_num in 1..=35 {
             let publish_time = now + chrono::Duration::minutes(publish_num);
-            let bucket = rate.take_token(user_id, action, publish_time, conn)?;
+            let bucket = rate.take_token(user_id, &action.into(), publish_time, conn)?;
 
             last_refill_times.push(bucket.last_refill);
             expected_last_refill_times.push(publish_time);
@@ -240,7 +330,7 @@ mod tests {
         let mut expected_last_refill_times = vec![];
         for publish_num in 1..=110 {
             let publish_time = now + chrono::Duration::minutes(publish_num);
-            let bucket = rate.take_token(user_id, action, publish_time, conn)?;
+            let bucket = rate.take_token(user_id, &action.into(), publish_time, conn)?;
 
             last_refill_times.push(bucket.last_refill);
             expected_last_refill_times.push(publish_time);
@@ -263,7 +353,7 @@ mod tests {
         .create();
         let bucket = rate.take_token(
             new_user(conn, "user1")?,
-            LimitedAction::PublishNew,
+            &LimitedAction::PublishNew.into(),
             now,
             conn,
         )?;
@@ -272,6 +362,7 @@ mod tests {
             tokens: 10,
             last_refill: now,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
 
@@ -283,7 +374,7 @@ mod tests {
         .create();
         let bucket = rate.take_token(
             new_user(conn, "user2")?,
-            LimitedAction::PublishNew,
+            &LimitedAction::PublishNew.into(),
             now,
             conn,
         )?;
@@ -292,6 +383,7 @@ mod tests {
             tokens: 20,
             last_refill: now,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -309,12 +401,13 @@ mod tests {
         }
         .create();
         let user_id = new_user_bucket(conn, 5, now)?.user_id;
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?;
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?;
         let expected = Bucket {
             user_id,
             tokens: 4,
             last_refill: now,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -333,12 +426,18 @@ mod tests {
         .create();
         let user_id = new_user_bucket(conn, 5, now)?.user_id;
         let refill_time = now + chrono::Duration::seconds(2);
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, refill_time, conn)?;
+        let bucket = rate.take_token(
+            user_id,
+            &LimitedAction::PublishNew.into(),
+            refill_time,
+            conn,
+        )?;
         let expected = Bucket {
             user_id,
             tokens: 6,
             last_refill: refill_time,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -361,17 +460,182 @@ mod tests {
         .create();
         let user_id = new_user_bucket(conn, 5, now)?.user_id;
         let refill_time = now + chrono::Duration::milliseconds(300);
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, refill_time, conn)?;
+        let bucket = rate.take_token(
+            user_id,
+            &LimitedAction::PublishNew.into(),
+            refill_time,
+            conn,
+        )?;
         let expected = Bucket {
             user_id,
             tokens: 7,
             last_refill: refill_time,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
         Ok(())
     }
 
+    #[test]
+    fn clock_going_backwards_does_not_take_tokens() -> QueryResult<()> {
+        let (_test_db, conn) = &mut test_db_connection();
+        let now = now();
+
+        let rate = SampleRateLimiter {
+            rate: Duration::from_secs(1),
+            burst: 10,
+            action: LimitedAction::PublishNew,
+        }
+        .create();
+        let user_id = new_user_bucket(conn, 5, now)?.user_id;
+        let earlier = now - chrono::Duration::milliseconds(1500);
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), earlier, conn)?;
+        let expected = Bucket {
+            user_id,
+            tokens: 4,
+            last_refill: now,
+            action: LimitedAction::PublishNew,
+            scope: String::new(),
+        };
+        assert_eq!(expected, bucket);
+        Ok(())
+    }
+
+    /// Compares `take_token` against the [`ReferenceBucket`] model for
+    /// randomized rates, bursts, timestamps and time deltas.
+    ///
+    /// Failing inputs are shrunk and persisted by `proptest`, so that
+    /// failures can be reproduced.
+    #[test]
+    fn take_token_matches_reference_model() -> QueryResult<()> {
+        use proptest::prelude::*;
+
+        let (_test_db, conn) = &mut test_db_connection();
+        let user_id = new_user(conn, "user")?;
+        let action = LimitedAction::PublishNew;
+
+        // The test cases are run in an `Fn` closure, which can't borrow the
+        // connection mutably
+        let conn = std::cell::RefCell::new(conn);
+
+        let epoch =
+            NaiveDateTime::parse_from_str("2019-03-19T00:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        // Rates range from whole minutes to odd sub-millisecond values
+        let rate = prop_oneof![
+            (1..=600u64).prop_map(Duration::from_secs),
+            (1..=10_000u64).prop_map(Duration::from_millis),
+            (1..=1_000_000u64).prop_map(Duration::from_micros),
+        ];
+
+        let scenario = (rate, 1..=100i32).prop_flat_map(|(rate, burst)| {
+            // Mostly multiples and fractions of the rate, but sometimes also
+            // arbitrary deltas, including negative ones
+            let rate_micros = rate.as_micros() as i64;
+            let delta = prop_oneof![
+                (0..=3i64).prop_map(move |n| rate_micros * n),
+                0..=rate_micros,
+                -rate_micros..=rate_micros * 5,
+                0..=rate_micros * burst as i64 * 2,
+            ];
+
+            (
+                Just(rate),
+                Just(burst),
+                0..1i64 << 45,
+                prop::option::of(0..=burst),
+                prop::collection::vec(delta, 1..=20),
+            )
+        });
+
+        proptest!(ProptestConfig::with_cases(100), |(scenario in scenario)| {
+            let (rate, burst, start_micros, initial_tokens, deltas) = scenario;
+            let mut conn = conn.borrow_mut();
+            let conn = &mut **conn;
+
+            let rate_limiter = SampleRateLimiter {
+                rate,
+                burst,
+                action,
+            }
+            .create();
+
+            diesel::delete(publish_limit_buckets::table).execute(conn)?;
+
+            let mut now = epoch + chrono::Duration::microseconds(start_micros);
+            let mut model = None;
+            if let Some(tokens) = initial_tokens {
+                diesel::insert_into(publish_limit_buckets::table)
+                    .values(Bucket {
+                        user_id,
+                        tokens,
+                        last_refill: now,
+                        action,
+                        scope: String::new(),
+                    })
+                    .execute(conn)?;
+                model = Some(ReferenceBucket {
+                    tokens,
+                    last_refill: now,
+                });
+            }
+
+            for delta_micros in deltas {
+                now += chrono::Duration::microseconds(delta_micros);
+
+                let expected = ReferenceBucket::take_token(model, now, rate, burst);
+                let bucket = rate_limiter.take_token(user_id, &action.into(), now, conn)?;
+
+                prop_assert_eq!(
+                    (bucket.tokens, bucket.last_refill),
+                    (expected.tokens, expected.last_refill),
+                    "now = {}, previous bucket = {:?}",
+                    now,
+                    model
+                );
+                model = Some(expected);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// A pure-Rust reference implementation of the token bucket that
+    /// `take_token` implements in SQL.
+    #[derive(Debug, Clone, Copy)]
+    struct ReferenceBucket {
+        tokens: i32,
+        last_refill: NaiveDateTime,
+    }
+
+    impl ReferenceBucket {
+        fn take_token(
+            bucket: Option<Self>,
+            now: NaiveDateTime,
+            rate: Duration,
+            burst: i32,
+        ) -> Self {
+            let Some(bucket) = bucket else {
+                return Self {
+                    tokens: burst,
+                    last_refill: now,
+                };
+            };
+
+            let rate_micros = rate.as_micros() as i64;
+            let elapsed_micros = (now - bucket.last_refill).num_microseconds().unwrap();
+            let tokens_to_add = elapsed_micros.div_euclid(rate_micros).max(0);
+
+            let tokens = (bucket.tokens - 1).max(0) as i64 + tokens_to_add;
+            Self {
+                tokens: tokens.min(burst as i64) as i32,
+                last_refill: bucket.last_refill
+                    + chrono::Duration::microseconds(rate_micros * tokens_to_add),
+            }
+        }
+    }
+
     #[test]
     fn last_refill_always_advanced_by_multiple_of_rate() -> QueryResult<()> {
         let (_test_db, conn) = &mut test_db_connection();
@@ -386,7 +650,7 @@ mod tests {
         let user_id = new_user_bucket(conn, 5, now)?.user_id;
         let bucket = rate.take_token(
             user_id,
-            LimitedAction::PublishNew,
+            &LimitedAction::PublishNew.into(),
             now + chrono::Duration::milliseconds(250),
             conn,
         )?;
@@ -396,6 +660,7 @@ mod tests {
             tokens: 6,
             last_refill: expected_refill_time,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
         Ok(())
@@ -413,16 +678,17 @@ mod tests {
         }
         .create();
         let user_id = new_user_bucket(conn, 1, now)?.user_id;
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?;
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?;
         let expected = Bucket {
             user_id,
             tokens: 0,
             last_refill: now,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
 
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?;
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?;
         assert_eq!(expected, bucket);
         Ok(())
     }
@@ -440,12 +706,18 @@ mod tests {
         .create();
         let user_id = new_user_bucket(conn, 0, now)?.user_id;
         let refill_time = now + chrono::Duration::seconds(1);
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, refill_time, conn)?;
+        let bucket = rate.take_token(
+            user_id,
+            &LimitedAction::PublishNew.into(),
+            refill_time,
+            conn,
+        )?;
         let expected = Bucket {
             user_id,
             tokens: 1,
             last_refill: refill_time,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
 
@@ -465,12 +737,18 @@ mod tests {
         .create();
         let user_id = new_user_bucket(conn, 8, now)?.user_id;
         let refill_time = now + chrono::Duration::seconds(4);
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, refill_time, conn)?;
+        let bucket = rate.take_token(
+            user_id,
+            &LimitedAction::PublishNew.into(),
+            refill_time,
+            conn,
+        )?;
         let expected = Bucket {
             user_id,
             tokens: 10,
             last_refill: refill_time,
             action: LimitedAction::PublishNew,
+            scope: String::new(),
         };
         assert_eq!(expected, bucket);
 
@@ -503,17 +781,17 @@ mod tests {
 
         assert_eq!(
             10,
-            rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?
+            rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?
                 .tokens
         );
         assert_eq!(
             9,
-            rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?
+            rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?
                 .tokens
         );
         assert_eq!(
             20,
-            rate.take_token(user_id, LimitedAction::YankUnyank, now, conn)?
+            rate.take_token(user_id, &LimitedAction::YankUnyank.into(), now, conn)?
                 .tokens
         );
 
@@ -542,8 +820,9 @@ mod tests {
             ))
             .execute(conn)?;
 
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?;
-        let other_bucket = rate.take_token(other_user_id, LimitedAction::PublishNew, now, conn)?;
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?;
+        let other_bucket =
+            rate.take_token(other_user_id, &LimitedAction::PublishNew.into(), now, conn)?;
 
         assert_eq!(bucket.tokens, 20);
         assert_eq!(other_bucket.tokens, 10);
@@ -573,8 +852,9 @@ mod tests {
             ))
             .execute(conn)?;
 
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?;
-        let other_bucket = rate.take_token(other_user_id, LimitedAction::PublishNew, now, conn)?;
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?;
+        let other_bucket =
+            rate.take_token(other_user_id, &LimitedAction::PublishNew.into(), now, conn)?;
 
         assert_eq!(bucket.tokens, 20);
         assert_eq!(other_bucket.tokens, 10);
@@ -585,8 +865,9 @@ mod tests {
             .filter(publish_rate_overrides::user_id.eq(user_id))
             .execute(conn)?;
 
-        let bucket = rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?;
-        let other_bucket = rate.take_token(other_user_id, LimitedAction::PublishNew, now, conn)?;
+        let bucket = rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?;
+        let other_bucket =
+            rate.take_token(other_user_id, &LimitedAction::PublishNew.into(), now, conn)?;
 
         // The number of tokens of user_id is 10 and not 9 because when the new burst limit is
         // lower than the amount of available tokens, the number of available tokens is reset to
@@ -625,18 +906,77 @@ mod tests {
 
         assert_eq!(
             20,
-            rate.take_token(user_id, LimitedAction::PublishNew, now, conn)?
+            rate.take_token(user_id, &LimitedAction::PublishNew.into(), now, conn)?
                 .tokens,
         );
         assert_eq!(
             10,
-            rate.take_token(user_id, LimitedAction::YankUnyank, now, conn)?
+            rate.take_token(user_id, &LimitedAction::YankUnyank.into(), now, conn)?
                 .tokens,
         );
 
         Ok(())
     }
 
+    #[test]
+    fn scopes_dont_interfere_with_each_other() -> QueryResult<()> {
+        let (_test_db, conn) = &mut test_db_connection();
+        let now = now();
+
+        let rate = SampleRateLimiter {
+            rate: Duration::from_secs(1),
+            burst: 10,
+            action: LimitedAction::PublishUpdate,
+        }
+        .create();
+        let user_id = new_user(conn, "user")?;
+
+        let foo = ActionDescriptor::scoped(LimitedAction::PublishUpdate, "foo");
+        let bar = ActionDescriptor::scoped(LimitedAction::PublishUpdate, "bar");
+        let global = ActionDescriptor::from(LimitedAction::PublishUpdate);
+
+        assert_eq!(10, rate.take_token(user_id, &foo, now, conn)?.tokens);
+        assert_eq!(9, rate.take_token(user_id, &foo, now, conn)?.tokens);
+        assert_eq!(10, rate.take_token(user_id, &bar, now, conn)?.tokens);
+        assert_eq!(10, rate.take_token(user_id, &global, now, conn)?.tokens);
+
+        let bucket = rate.take_token(user_id, &foo, now, conn)?;
+        assert_eq!(bucket.scope, "foo");
+        assert_eq!(bucket.tokens, 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejections_are_recorded_with_their_scope() -> QueryResult<()> {
+        let (_test_db, conn) = &mut test_db_connection();
+
+        let rate = SampleRateLimiter {
+            rate: Duration::from_secs(60),
+            burst: 1,
+            action: LimitedAction::PublishUpdate,
+        }
+        .create();
+        let user_id = new_user(conn, "user")?;
+
+        let foo = ActionDescriptor::scoped(LimitedAction::PublishUpdate, "foo");
+        assert!(rate.check_rate_limit(user_id, foo.clone(), conn).is_ok());
+        assert!(rate.check_rate_limit(user_id, foo, conn).is_err());
+        assert!(rate
+            .check_rate_limit(user_id, LimitedAction::PublishUpdate, conn)
+            .is_ok());
+
+        let rejections: Vec<(LimitedAction, Option<String>)> = rate_limit_rejections::table
+            .select((rate_limit_rejections::action, rate_limit_rejections::scope))
+            .load(conn)?;
+        assert_eq!(
+            rejections,
+            vec![(LimitedAction::PublishUpdate, Some("foo".to_string()))]
+        );
+
+        Ok(())
+    }
+
     fn new_user(conn: &mut impl Conn, gh_login: &str) -> QueryResult<i32> {
         use crate::models::NewUser;
 
@@ -659,6 +999,7 @@ mod tests {
                 tokens,
                 last_refill: now,
                 action: LimitedAction::PublishNew,
+                scope: String::new(),
             })
             .get_result(conn)
     }
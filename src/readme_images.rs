@@ -0,0 +1,415 @@
+//! Proxying and caching of images that are referenced by READMEs.
+//!
+//! Loading README images directly from third-party hosts leaks the IP
+//! addresses of crates.io visitors to these hosts, and breaks the READMEs
+//! whenever the images are moved or deleted. If the proxy is enabled, the
+//! image URLs are rewritten during rendering to the
+//! `/api/v1/readme_images/:digest` endpoint, and the background worker
+//! fetches the images and uploads them to the storage bucket.
+//!
+//! The [ImageFetcher] trait is used to abstract away the HTTP client for
+//! testing purposes.
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use crates_io_markdown::ImageProxy;
+use hyper::body::Bytes;
+use mockall::automock;
+use parking_lot::Mutex;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+/// The content types of images that are served by the proxy. Everything
+/// else is rejected, so that the proxy can not be abused to serve arbitrary
+/// files from the crates.io domain.
+pub const ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "image/avif",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/svg+xml",
+    "image/webp",
+];
+
+/// The `User-Agent` header that is sent to the hosts of the images.
+const USER_AGENT: &str = "crates.io README image proxy (https://crates.io)";
+
+/// The maximum duration of fetching a single image.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum number of redirects that are followed when fetching an image.
+const MAX_REDIRECTS: usize = 5;
+
+/// Returns the digest that identifies the image with the given URL in the
+/// database, the storage bucket and the URLs of the proxy endpoint.
+pub fn digest(url: &str) -> String {
+    hex::encode(Sha256::digest(url.as_bytes()))
+}
+
+/// Returns `true` if the URL points to a host that may be fetched by the
+/// proxy, i.e. if it is neither a local host name nor an IP address of a
+/// private network.
+///
+/// Host names are not resolved here. The [HttpImageFetcher] resolves them
+/// via the [PublicResolver], which only returns public IP addresses.
+pub fn is_public_url(url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// Returns `true` if the IP address is globally reachable, i.e. if it is not
+/// part of a private, shared, reserved or otherwise special-purpose network.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+
+            // "This network" (0.0.0.0/8), shared address space for carrier
+            // grade NAT (100.64.0.0/10), IETF protocol assignments
+            // (192.0.0.0/24), benchmarking (198.18.0.0/15) and reserved
+            // addresses (240.0.0.0/4)
+            let is_this_network = a == 0;
+            let is_shared = a == 100 && b & 0xc0 == 64;
+            let is_protocol_assignment = a == 192 && b == 0 && ip.octets()[2] == 0;
+            let is_benchmarking = a == 198 && b & 0xfe == 18;
+            let is_reserved = a >= 240;
+
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || is_this_network
+                || is_shared
+                || is_protocol_assignment
+                || is_benchmarking
+                || is_reserved)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            let is_unique_local = segments[0] & 0xfe00 == 0xfc00;
+            let is_unicast_link_local = segments[0] & 0xffc0 == 0xfe80;
+            let is_documentation = segments[0] == 0x2001 && segments[1] == 0xdb8;
+
+            // Addresses that embed an IPv4 address are only public if the
+            // embedded address is public: IPv4-mapped (::ffff:0:0/96),
+            // IPv4-compatible (::/96), NAT64 (64:ff9b::/96) and 6to4
+            // (2002::/16) addresses.
+            let octets = ip.octets();
+            let embedded = if let Some(ip) = ip.to_ipv4_mapped() {
+                Some(ip)
+            } else if segments[..6] == [0; 6] || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                Some([octets[12], octets[13], octets[14], octets[15]].into())
+            } else if segments[0] == 0x2002 {
+                Some([octets[2], octets[3], octets[4], octets[5]].into())
+            } else {
+                None
+            };
+
+            match embedded {
+                Some(ip) => is_public_ip(IpAddr::V4(ip)),
+                None => {
+                    !(ip.is_loopback()
+                        || ip.is_unspecified()
+                        || ip.is_multicast()
+                        || is_unique_local
+                        || is_unicast_link_local
+                        || is_documentation)
+                }
+            }
+        }
+    }
+}
+
+/// A DNS resolver that only returns the public IP addresses of a host name,
+/// see [is_public_ip()].
+///
+/// The resolver is used for the initial request and for all redirects, so
+/// host names that point to internal services can not be used to reach
+/// them, even if the DNS records change after [is_public_url()] was checked.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addrs = tokio::net::lookup_host((host, 0)).await?;
+            let addrs = addrs
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect::<Vec<_>>();
+
+            if addrs.is_empty() {
+                let message = format!("`{host}` does not resolve to a public IP address");
+                return Err(message.into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Rewrites the image URLs of a README to the proxy endpoint, and collects
+/// the original URLs by their digest, so that the images can be fetched
+/// afterwards.
+pub struct ReadmeImageProxy {
+    base_url: String,
+    images: Mutex<BTreeMap<String, String>>,
+}
+
+impl ReadmeImageProxy {
+    /// Creates a new proxy for the given domain name, e.g. `crates.io`.
+    pub fn new(domain_name: &str) -> Self {
+        Self {
+            base_url: format!("https://{domain_name}/api/v1/readme_images"),
+            images: Default::default(),
+        }
+    }
+
+    /// Returns the original URLs of all images that were proxied so far by
+    /// their digest, and resets the list.
+    pub fn take_images(&self) -> BTreeMap<String, String> {
+        std::mem::take(&mut self.images.lock())
+    }
+}
+
+impl ImageProxy for ReadmeImageProxy {
+    fn proxy_url(&self, url: &str) -> Option<String> {
+        let parsed = Url::parse(url).ok()?;
+        if !is_public_url(&parsed) {
+            return None;
+        }
+
+        let digest = digest(url);
+        let proxy_url = format!("{}/{digest}", self.base_url);
+        self.images.lock().insert(digest, url.to_string());
+        Some(proxy_url)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchedImage {
+    pub content_type: String,
+    pub bytes: Bytes,
+}
+
+impl FetchedImage {
+    /// Checks that the fetched file is an image with one of the
+    /// [ALLOWED_CONTENT_TYPES] and not larger than `max_size` bytes.
+    pub fn validate(&self, max_size: u64) -> anyhow::Result<()> {
+        if !ALLOWED_CONTENT_TYPES.contains(&self.content_type.as_str()) {
+            bail!("unsupported content type `{}`", self.content_type);
+        }
+        if self.bytes.len() as u64 > max_size {
+            bail!("the image is larger than {max_size} bytes");
+        }
+        Ok(())
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait ImageFetcher {
+    /// Fetches the image at the given URL. Fails if the response is larger
+    /// than `max_size` bytes.
+    async fn fetch(&self, url: &str, max_size: u64) -> anyhow::Result<FetchedImage>;
+}
+
+/// Fetches images via HTTP, without connecting to private hosts, neither
+/// directly nor via redirects.
+pub struct HttpImageFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpImageFetcher {
+    pub fn new() -> anyhow::Result<Self> {
+        let redirect_policy = Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if !is_public_url(attempt.url()) {
+                attempt.error("redirect to a private host")
+            } else {
+                attempt.follow()
+            }
+        });
+
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(FETCH_TIMEOUT)
+            .redirect(redirect_policy)
+            .dns_resolver(Arc::new(PublicResolver))
+            // A proxy would resolve the host names itself
+            .no_proxy()
+            .build()?;
+
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl ImageFetcher for HttpImageFetcher {
+    #[instrument(skip(self))]
+    async fn fetch(&self, url: &str, max_size: u64) -> anyhow::Result<FetchedImage> {
+        let parsed = Url::parse(url)?;
+        if !is_public_url(&parsed) {
+            bail!("images from private hosts are not proxied");
+        }
+
+        let mut response = self.client.get(parsed).send().await?.error_for_status()?;
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > max_size)
+        {
+            bail!("the image is larger than {max_size} bytes");
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .ok_or_else(|| anyhow!("the response has no content type"))?
+            .to_str()
+            .context("invalid content type")?;
+
+        // Strip parameters like `; charset=utf-8`
+        let content_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        // The `Content-Length` header is optional, so the size has to be
+        // checked while the body is downloaded too.
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > max_size {
+                bail!("the image is larger than {max_size} bytes");
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        let bytes = bytes.into();
+        Ok(FetchedImage {
+            content_type,
+            bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_urls() {
+        let is_public = |url: &str| is_public_url(&Url::parse(url).unwrap());
+
+        assert!(is_public("https://img.shields.io/crates/v/clap.svg"));
+        assert!(is_public("http://93.184.216.34/logo.png"));
+        assert!(is_public("https://[2606:2800:220:1::]/logo.png"));
+
+        assert!(!is_public("ftp://example.com/logo.png"));
+        assert!(!is_public("http://localhost/logo.png"));
+        assert!(!is_public("http://foo.localhost./logo.png"));
+        assert!(!is_public("http://127.0.0.1:8888/logo.png"));
+        assert!(!is_public("http://10.0.0.1/logo.png"));
+        assert!(!is_public("http://192.168.1.1/logo.png"));
+        assert!(!is_public("http://169.254.169.254/latest/meta-data/"));
+        assert!(!is_public("http://[::1]/logo.png"));
+        assert!(!is_public("http://[fd00::1]/logo.png"));
+        assert!(!is_public("http://[::ffff:127.0.0.1]/logo.png"));
+    }
+
+    #[test]
+    fn public_ips() {
+        let is_public = |ip: &str| is_public_ip(ip.parse().unwrap());
+
+        assert!(is_public("93.184.216.34"));
+        assert!(is_public("100.128.0.1"));
+        assert!(is_public("198.20.0.1"));
+        assert!(is_public("2606:2800:220:1::"));
+        assert!(is_public("2002:5db8:d822::1"));
+
+        assert!(!is_public("0.1.2.3"));
+        assert!(!is_public("100.64.0.1"));
+        assert!(!is_public("100.127.255.254"));
+        assert!(!is_public("169.254.169.254"));
+        assert!(!is_public("192.0.0.170"));
+        assert!(!is_public("198.18.0.1"));
+        assert!(!is_public("198.19.255.254"));
+        assert!(!is_public("224.0.0.1"));
+        assert!(!is_public("240.0.0.1"));
+        assert!(!is_public("255.255.255.255"));
+        assert!(!is_public("::127.0.0.1"));
+        assert!(!is_public("64:ff9b::a00:1"));
+        assert!(!is_public("2002:a00:1::1"));
+        assert!(!is_public("2001:db8::1"));
+        assert!(!is_public("ff02::1"));
+    }
+
+    #[tokio::test]
+    async fn resolver_rejects_private_hosts() {
+        let name = "localhost".parse().unwrap();
+        let error = PublicResolver.resolve(name).await.err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "`localhost` does not resolve to a public IP address"
+        );
+    }
+
+    #[test]
+    fn proxy_urls() {
+        let proxy = ReadmeImageProxy::new("crates.io");
+
+        let url = "https://img.shields.io/crates/v/clap.svg";
+        let proxy_url = proxy.proxy_url(url).unwrap();
+        assert_eq!(
+            proxy_url,
+            format!("https://crates.io/api/v1/readme_images/{}", digest(url))
+        );
+
+        assert_eq!(proxy.proxy_url("http://localhost/logo.png"), None);
+        assert_eq!(proxy.proxy_url("not a url"), None);
+
+        let images = proxy.take_images();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images.get(&digest(url)).unwrap(), url);
+    }
+
+    #[test]
+    fn validate_images() {
+        let image = |content_type: &str, size: usize| FetchedImage {
+            content_type: content_type.into(),
+            bytes: vec![0; size].into(),
+        };
+
+        assert!(image("image/png", 100).validate(100).is_ok());
+        assert!(image("image/svg+xml", 100).validate(100).is_ok());
+
+        let error = image("image/png", 101).validate(100).unwrap_err();
+        assert_eq!(error.to_string(), "the image is larger than 100 bytes");
+
+        let error = image("text/html", 100).validate(100).unwrap_err();
+        assert_eq!(error.to_string(), "unsupported content type `text/html`");
+    }
+}
@@ -0,0 +1,291 @@
+//! Support for serving multiple logical registries from one deployment, e.g.
+//! a public registry and one for crates that are only used internally.
+//!
+//! Every deployment serves the default registry. Additional registries are
+//! configured with the `REGISTRIES` environment variable, and each of them is
+//! selected by the hostname or by a path prefix of the request:
+//!
+//! ```text
+//! REGISTRIES=internal
+//! REGISTRY_INTERNAL_HOSTNAME=crates.internal.example.com
+//! REGISTRY_INTERNAL_PATH_PREFIX=/internal
+//! ```
+//!
+//! The path prefix is removed from the request path before routing, so all
+//! registries have the same routes.
+//!
+//! Crate names are unique across all registries of a deployment, but a crate
+//! only belongs to the registry that it was first published to. New versions
+//! can only be published, and crate files only be downloaded, via that
+//! registry. API tokens can only be used with the registry they were created
+//! for.
+//!
+//! The sparse index of an additional registry is stored in a separate
+//! namespace of the index storage (`registries/<name>/`), together with its
+//! own `config.json` file (see the `sync_registry_configs` job). The git
+//! index only contains the crates of the default registry.
+
+use crate::schema::crates;
+use crate::sql::canon_crate_name;
+use crate::util::diesel::Conn;
+use anyhow::bail;
+use crates_io_env_vars::{list, var};
+use diesel::prelude::*;
+use std::collections::HashSet;
+
+/// The name of the registry that is served by every deployment.
+pub const DEFAULT_REGISTRY: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryConfig {
+    /// The name of the registry, which is stored in the database for the
+    /// crates and API tokens of the registry.
+    pub name: String,
+    /// The hostname that selects the registry, e.g. `crates.example.com`.
+    pub hostname: Option<String>,
+    /// The path prefix that selects the registry, e.g. `/internal`.
+    pub path_prefix: Option<String>,
+}
+
+impl RegistryConfig {
+    /// Returns the base URL of the web API of the registry.
+    pub fn api_url(&self, domain_name: &str) -> String {
+        match (&self.hostname, &self.path_prefix) {
+            (Some(hostname), _) => format!("https://{hostname}"),
+            (None, Some(path_prefix)) => format!("https://{domain_name}{path_prefix}"),
+            (None, None) => format!("https://{domain_name}"),
+        }
+    }
+}
+
+/// The additional registries of a deployment.
+#[derive(Debug, Clone, Default)]
+pub struct Registries {
+    registries: Vec<RegistryConfig>,
+}
+
+impl Registries {
+    /// Reads the additional registries from the `REGISTRIES` environment
+    /// variable, and their hostnames and path prefixes from the
+    /// `REGISTRY_<NAME>_HOSTNAME` and `REGISTRY_<NAME>_PATH_PREFIX`
+    /// environment variables.
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let registries = list("REGISTRIES")?
+            .into_iter()
+            .map(|name| {
+                let key = name.to_uppercase().replace('-', "_");
+                let hostname = var(&format!("REGISTRY_{key}_HOSTNAME"))?;
+                let path_prefix = var(&format!("REGISTRY_{key}_PATH_PREFIX"))?;
+                Ok(RegistryConfig {
+                    name,
+                    hostname,
+                    path_prefix,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Self::new(registries)
+    }
+
+    pub fn new(registries: Vec<RegistryConfig>) -> anyhow::Result<Self> {
+        let mut names = HashSet::new();
+        for registry in &registries {
+            let name = &registry.name;
+
+            let valid_name = name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if name.is_empty() || !valid_name {
+                bail!("Invalid registry name `{name}`, only lowercase letters, digits and `-` are allowed");
+            }
+            if name == DEFAULT_REGISTRY || !names.insert(name) {
+                bail!("The registry name `{name}` is used more than once");
+            }
+
+            if registry.hostname.is_none() && registry.path_prefix.is_none() {
+                bail!("The registry `{name}` needs a hostname or a path prefix");
+            }
+
+            if let Some(path_prefix) = &registry.path_prefix {
+                if !path_prefix.starts_with('/') || path_prefix.ends_with('/') {
+                    bail!("The path prefix of the registry `{name}` has to start with `/`, and must not end with `/`");
+                }
+            }
+        }
+
+        Ok(Self { registries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RegistryConfig> {
+        self.registries.iter()
+    }
+
+    /// Returns the names of all registries, including the default registry.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        let names = self
+            .registries
+            .iter()
+            .map(|registry| registry.name.as_str());
+        std::iter::once(DEFAULT_REGISTRY).chain(names)
+    }
+
+    /// Selects the registry of a request by its hostname and path.
+    ///
+    /// Returns the name of the registry, and the path without the prefix of
+    /// the registry. Requests that don't match any additional registry
+    /// belong to the default registry.
+    pub fn select<'a>(&self, host: Option<&str>, path: &'a str) -> (&str, &'a str) {
+        for registry in &self.registries {
+            if let (Some(hostname), Some(host)) = (&registry.hostname, host) {
+                if hostname.eq_ignore_ascii_case(host) {
+                    return (&registry.name, path);
+                }
+            }
+
+            if let Some(rest) = registry
+                .path_prefix
+                .as_deref()
+                .and_then(|path_prefix| path.strip_prefix(path_prefix))
+            {
+                if rest.is_empty() {
+                    return (&registry.name, "/");
+                }
+                if rest.starts_with('/') {
+                    return (&registry.name, rest);
+                }
+            }
+        }
+
+        (DEFAULT_REGISTRY, path)
+    }
+}
+
+/// Returns the path of a file of the sparse index of a registry, relative to
+/// the root of the index storage.
+pub fn index_path(registry: &str, path: &str) -> String {
+    match registry {
+        DEFAULT_REGISTRY => path.to_string(),
+        registry => format!("registries/{registry}/{path}"),
+    }
+}
+
+/// Returns the name of the registry that a crate belongs to, or `None` if the
+/// crate does not exist.
+pub fn crate_registry(conn: &mut impl Conn, crate_name: &str) -> QueryResult<Option<String>> {
+    crates::table
+        .filter(canon_crate_name(crates::name).eq(canon_crate_name(crate_name)))
+        .select(crates::registry)
+        .first(conn)
+        .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registries() -> Registries {
+        Registries::new(vec![
+            RegistryConfig {
+                name: "internal".into(),
+                hostname: Some("crates.internal.example.com".into()),
+                path_prefix: Some("/internal".into()),
+            },
+            RegistryConfig {
+                name: "staging".into(),
+                hostname: None,
+                path_prefix: Some("/staging".into()),
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn select() {
+        let registries = registries();
+
+        assert_eq!(
+            registries.select(None, "/api/v1/crates"),
+            ("default", "/api/v1/crates")
+        );
+        assert_eq!(
+            registries.select(Some("crates.io"), "/api/v1/crates"),
+            ("default", "/api/v1/crates")
+        );
+        assert_eq!(
+            registries.select(Some("Crates.Internal.example.com"), "/api/v1/crates"),
+            ("internal", "/api/v1/crates")
+        );
+        assert_eq!(
+            registries.select(None, "/internal/api/v1/crates"),
+            ("internal", "/api/v1/crates")
+        );
+        assert_eq!(registries.select(None, "/internal"), ("internal", "/"));
+        assert_eq!(
+            registries.select(None, "/staging/api/v1/crates"),
+            ("staging", "/api/v1/crates")
+        );
+        assert_eq!(
+            registries.select(None, "/internals"),
+            ("default", "/internals")
+        );
+    }
+
+    #[test]
+    fn names() {
+        let registries = registries();
+        let names = registries.names().collect::<Vec<_>>();
+        assert_eq!(names, ["default", "internal", "staging"]);
+    }
+
+    #[test]
+    fn api_url() {
+        let registries = registries();
+        let urls = registries
+            .iter()
+            .map(|registry| registry.api_url("crates.io"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://crates.internal.example.com",
+                "https://crates.io/staging"
+            ]
+        );
+    }
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("default", "3/f/foo"), "3/f/foo");
+        assert_eq!(
+            index_path("internal", "3/f/foo"),
+            "registries/internal/3/f/foo"
+        );
+    }
+
+    #[test]
+    fn invalid_registries() {
+        let registry = |name: &str, path_prefix: &str| RegistryConfig {
+            name: name.into(),
+            hostname: None,
+            path_prefix: Some(path_prefix.into()),
+        };
+
+        assert_err!(Registries::new(vec![registry("Internal", "/internal")]));
+        assert_err!(Registries::new(vec![registry("default", "/default")]));
+        assert_err!(Registries::new(vec![registry("internal", "internal")]));
+        assert_err!(Registries::new(vec![registry("internal", "/internal/")]));
+        assert_err!(Registries::new(vec![
+            registry("internal", "/a"),
+            registry("internal", "/b"),
+        ]));
+        assert_err!(Registries::new(vec![RegistryConfig {
+            name: "internal".into(),
+            hostname: None,
+            path_prefix: None,
+        }]));
+    }
+}
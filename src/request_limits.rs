@@ -0,0 +1,206 @@
+//! Per-route limits of the request processing time and body size.
+//!
+//! Every route belongs to a [`RouteClass`], which determines how long a
+//! request may take, including the time it takes to receive the request
+//! body, and how large the request body may be:
+//!
+//! - [`RouteClass::Publish`] requests upload whole crate files and may take
+//!   a long time and have large bodies. Their timeout only limits how long
+//!   the request body may stall, since the processing of a publish can't be
+//!   cancelled once it has started.
+//! - [`RouteClass::Search`] requests are expected to be answered quickly and
+//!   don't have a body.
+//! - [`RouteClass::Default`] applies to all other routes.
+//!
+//! Requests that exceed the timeout are answered with a `408 Request Timeout`
+//! response, and requests whose body exceeds the size limit with a
+//! `413 Payload Too Large` response.
+//!
+//! See [`crate::middleware`] for where this is enforced.
+
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Publish,
+    Search,
+    Default,
+}
+
+impl RouteClass {
+    pub const ALL: [RouteClass; 3] = [RouteClass::Publish, RouteClass::Search, RouteClass::Default];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteClass::Publish => "publish",
+            RouteClass::Search => "search",
+            RouteClass::Default => "default",
+        }
+    }
+
+    /// The limits of the class, unless they are overridden by the
+    /// configuration.
+    pub fn default_limits(&self) -> RequestLimits {
+        match self {
+            RouteClass::Publish => RequestLimits {
+                timeout: Duration::from_secs(120),
+                max_body_size: 128 * 1024 * 1024,
+            },
+            RouteClass::Search => RequestLimits {
+                timeout: Duration::from_secs(10),
+                max_body_size: 1024,
+            },
+            RouteClass::Default => RequestLimits {
+                timeout: Duration::from_secs(30),
+                max_body_size: 2 * 1024 * 1024,
+            },
+        }
+    }
+}
+
+impl Display for RouteClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RouteClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "publish" => Ok(RouteClass::Publish),
+            "search" => Ok(RouteClass::Search),
+            "default" => Ok(RouteClass::Default),
+            _ => Err(anyhow!("unknown route class `{s}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// The maximum time between receiving the request and sending the
+    /// response, or for [`RouteClass::Publish`] the maximum time between two
+    /// chunks of the request body.
+    pub timeout: Duration,
+    /// The maximum size of the request body, in bytes.
+    pub max_body_size: usize,
+}
+
+/// Parses a `<route>=<class>` pair of the `REQUEST_LIMITS_ROUTE_CLASSES`
+/// environment variable.
+pub fn parse_route_class(s: &str) -> anyhow::Result<(String, RouteClass)> {
+    let (route, class) = s
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("expected `<route>=<class>`"))?;
+
+    Ok((route.to_string(), class.parse()?))
+}
+
+/// The classes of the routes that are not [`RouteClass::Default`], unless
+/// they are overridden by the configuration.
+pub fn default_route_classes() -> HashMap<String, RouteClass> {
+    [
+        ("/api/v1/crates/new", RouteClass::Publish),
+        ("/api/v1/crates/batch", RouteClass::Publish),
+        ("/api/v1/staging", RouteClass::Publish),
+        (
+            "/api/private/crates/:crate_id/:version/tarball",
+            RouteClass::Publish,
+        ),
+        ("/api/v1/crates", RouteClass::Search),
+    ]
+    .into_iter()
+    .map(|(route, class)| (route.to_string(), class))
+    .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestLimitsConfig {
+    /// The limits of the route classes. Classes that are not listed use
+    /// [`RouteClass::default_limits`].
+    pub classes: HashMap<RouteClass, RequestLimits>,
+    /// The classes of the route patterns (e.g. `/api/v1/crates/new`). Routes
+    /// that are not listed have [`RouteClass::Default`].
+    pub route_classes: HashMap<String, RouteClass>,
+}
+
+impl Default for RequestLimitsConfig {
+    fn default() -> Self {
+        Self {
+            classes: RouteClass::ALL
+                .into_iter()
+                .map(|class| (class, class.default_limits()))
+                .collect(),
+            route_classes: default_route_classes(),
+        }
+    }
+}
+
+impl RequestLimitsConfig {
+    /// Returns the class of a request for the given route pattern.
+    pub fn class(&self, route: Option<&str>) -> RouteClass {
+        route
+            .and_then(|route| self.route_classes.get(route))
+            .copied()
+            .unwrap_or(RouteClass::Default)
+    }
+
+    /// Returns the limits of a request for the given route pattern.
+    pub fn limits(&self, route: Option<&str>) -> RequestLimits {
+        let class = self.class(route);
+        self.classes
+            .get(&class)
+            .copied()
+            .unwrap_or_else(|| class.default_limits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn parse_route_classes() {
+        assert_ok_eq!(
+            parse_route_class("/api/v1/summary=search"),
+            ("/api/v1/summary".to_string(), RouteClass::Search)
+        );
+        assert_err!(parse_route_class("/api/v1/summary"));
+        assert_err!(parse_route_class("/api/v1/summary=upload"));
+    }
+
+    #[test]
+    fn limits_by_route() {
+        let mut config = RequestLimitsConfig::default();
+
+        let publish = Some("/api/v1/crates/new");
+        let search = Some("/api/v1/crates");
+        let detail = Some("/api/v1/crates/:crate_id");
+
+        assert_eq!(config.class(publish), RouteClass::Publish);
+        assert_eq!(config.class(search), RouteClass::Search);
+        assert_eq!(config.class(detail), RouteClass::Default);
+        assert_eq!(config.class(None), RouteClass::Default);
+
+        let limits = config.limits(publish);
+        assert_eq!(limits, RouteClass::Publish.default_limits());
+
+        let custom = RequestLimits {
+            timeout: Duration::from_secs(1),
+            max_body_size: 1,
+        };
+        config.classes.insert(RouteClass::Default, custom);
+        assert_eq!(config.limits(detail), custom);
+        assert_eq!(config.limits(None), custom);
+
+        config.classes.remove(&RouteClass::Search);
+        let limits = config.limits(search);
+        assert_eq!(limits, RouteClass::Search.default_limits());
+    }
+}
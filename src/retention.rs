@@ -0,0 +1,57 @@
+//! Retention of expired invitations and tokens.
+//!
+//! Expired ownership invitations, expired API tokens and unused email
+//! verification tokens are kept for a while after they expired, so that
+//! users still get a meaningful error message when they use them, and are
+//! then purged by the `purge_expired_records` background job.
+//!
+//! The rows are purged in batches of [`RetentionConfig::batch_size`] rows,
+//! each in its own short statement, so that purging a large backlog of rows
+//! does not hold locks on the tables for a long time.
+
+use std::time::Duration;
+
+const DAY: u64 = 24 * 60 * 60;
+
+pub const DEFAULT_INVITATIONS_DAYS: u64 = 30;
+pub const DEFAULT_API_TOKENS_DAYS: u64 = 90;
+pub const DEFAULT_EMAIL_TOKENS_DAYS: u64 = 30;
+pub const DEFAULT_BATCH_SIZE: i64 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    /// Amount of time that ownership invitations are kept after they
+    /// expired.
+    pub invitations: Duration,
+    /// Amount of time that API tokens are kept after they expired. Tokens
+    /// that were used to publish or yank a version are kept indefinitely,
+    /// since they are referenced by the audit log.
+    pub api_tokens: Duration,
+    /// Amount of time after which the verification tokens of unverified
+    /// email addresses are invalidated. The users can request a new
+    /// verification email afterwards.
+    pub email_tokens: Duration,
+    /// Maximum number of rows that are purged by a single statement.
+    pub batch_size: i64,
+}
+
+impl RetentionConfig {
+    pub fn from_days(invitations: u64, api_tokens: u64, email_tokens: u64) -> Self {
+        Self {
+            invitations: Duration::from_secs(invitations * DAY),
+            api_tokens: Duration::from_secs(api_tokens * DAY),
+            email_tokens: Duration::from_secs(email_tokens * DAY),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self::from_days(
+            DEFAULT_INVITATIONS_DAYS,
+            DEFAULT_API_TOKENS_DAYS,
+            DEFAULT_EMAIL_TOKENS_DAYS,
+        )
+    }
+}
@@ -1,6 +1,5 @@
-use axum::extract::DefaultBodyLimit;
 use axum::response::IntoResponse;
-use axum::routing::{delete, get, post, put};
+use axum::routing::{delete, get, patch, post, put};
 use axum::Router;
 use http::{Method, StatusCode};
 
@@ -9,19 +8,18 @@ use crate::controllers::*;
 use crate::util::errors::not_found;
 use crate::Env;
 
-const MAX_PUBLISH_CONTENT_LENGTH: usize = 128 * 1024 * 1024; // 128 MB
-
 pub fn build_axum_router(state: AppState) -> Router<()> {
     let mut router = Router::new()
         // Route used by both `cargo search` and the frontend
         .route("/api/v1/crates", get(krate::search::search))
+        .route("/api/v1/bulk/crates", get(krate::bulk::list))
         // Routes used by `cargo`
         .route(
             "/api/v1/crates/new",
-            put(krate::publish::publish)
-                .layer(DefaultBodyLimit::max(MAX_PUBLISH_CONTENT_LENGTH))
-                .get(krate::metadata::show_new),
+            put(krate::publish::publish).get(krate::metadata::show_new),
         )
+        .route("/api/v1/crates/batch", put(krate::publish::publish_batch))
+        .route("/api/v1/staging", post(staging::upload))
         .route(
             "/api/v1/crates/:crate_id/owners",
             get(krate::owners::owners)
@@ -36,10 +34,35 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/:version/unyank",
             put(version::yank::unyank),
         )
+        .route(
+            "/api/v1/crates/:crate_id/:version/flag",
+            put(version::flag::flag).delete(version::flag::unflag),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/yank_history",
+            get(version::yank::yank_history),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/reproducibility",
+            get(version::reproducibility::list).put(version::reproducibility::report),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/attestations",
+            get(version::attestations::list).post(version::attestations::create),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/checksum_advisory",
+            get(version::repair::advisory),
+        )
+        .route(
+            "/api/v1/checksum_advisory_key",
+            get(version::repair::advisory_key),
+        )
         .route(
             "/api/v1/crates/:crate_id/:version/download",
             get(version::downloads::download),
         )
+        .route("/config.json", get(index::config))
         // Routes used by the frontend
         .route("/api/v1/crates/:crate_id", get(krate::metadata::show))
         .route(
@@ -50,10 +73,15 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/:version/readme",
             get(krate::metadata::readme),
         )
+        .route("/api/v1/readme_images/:digest", get(readme_image::show))
         .route(
             "/api/v1/crates/:crate_id/:version/dependencies",
             get(version::metadata::dependencies),
         )
+        .route(
+            "/api/v1/crates/:crate_id/:version/outdated_deps",
+            get(version::metadata::outdated_dependencies),
+        )
         .route(
             "/api/v1/crates/:crate_id/:version/downloads",
             get(version::downloads::downloads),
@@ -66,10 +94,30 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/downloads",
             get(krate::downloads::downloads),
         )
+        .route(
+            "/api/v1/crates/:crate_id/downloads/regions",
+            get(krate::downloads::region_downloads),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/downloads/summary",
+            get(krate::downloads::summary),
+        )
         .route(
             "/api/v1/crates/:crate_id/versions",
             get(krate::versions::versions),
         )
+        .route(
+            "/api/v1/crates/:crate_id/versions/export",
+            get(krate::export::export),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/default_version",
+            get(krate::versions::default_version),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/channels/:channel/latest",
+            get(krate::versions::latest_in_channel),
+        )
         .route(
             "/api/v1/crates/:crate_id/follow",
             put(krate::follow::follow).delete(krate::follow::unfollow),
@@ -78,6 +126,10 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/following",
             get(krate::follow::following),
         )
+        .route(
+            "/api/v1/crates/:crate_id/owners/history",
+            get(krate::owners::history),
+        )
         .route(
             "/api/v1/crates/:crate_id/owner_team",
             get(krate::owners::owner_team),
@@ -86,12 +138,48 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/owner_user",
             get(krate::owners::owner_user),
         )
+        .route(
+            "/api/v1/crates/:crate_id/visibility",
+            put(krate::visibility::update_visibility),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/metadata_findings",
+            get(krate::metadata_findings::list),
+        )
+        .route("/api/v1/crates/:crate_id/health", get(krate::health::show))
+        .route(
+            "/api/v1/crates/:crate_id/related",
+            get(krate::related::related),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/permissions",
+            get(krate::permissions::show),
+        )
+        .route("/api/v1/crates/:crate_id/policy", get(krate::policy::show))
+        .route(
+            "/api/v1/crates/:crate_id/settings",
+            get(krate::settings::get_settings).patch(krate::settings::update_settings),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/successor",
+            put(krate::succession::set_successor).delete(krate::succession::remove_successor),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/predecessors/:predecessor_id",
+            put(krate::succession::add_predecessor).delete(krate::succession::remove_predecessor),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/repository/verify",
+            put(krate::repository::verify_repository),
+        )
         .route(
             "/api/v1/crates/:crate_id/reverse_dependencies",
             get(krate::metadata::reverse_dependencies),
         )
+        .route("/api/v1/advisories/:advisory_id", get(advisory::show))
         .route("/api/v1/keywords", get(keyword::index))
         .route("/api/v1/keywords/:keyword_id", get(keyword::show))
+        .route("/api/v1/links_keys/:key", get(links_key::show))
         .route("/api/v1/categories", get(category::index))
         .route("/api/v1/categories/:category_id", get(category::show))
         .route("/api/v1/category_slugs", get(category::slugs))
@@ -100,15 +188,38 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             get(user::other::show).put(user::me::update_user),
         )
         .route("/api/v1/users/:user_id/stats", get(user::other::stats))
+        .route("/api/v1/users/:user_id/avatar", get(user::avatar::show))
         .route("/api/v1/teams/:team_id", get(team::show_team))
+        .route("/api/v1/teams/:team_id/crates", get(team::crates::list))
+        .route(
+            "/api/v1/teams/:team_id/tokens",
+            get(team::tokens::list).put(team::tokens::create),
+        )
+        .route(
+            "/api/v1/teams/:team_id/tokens/:id",
+            delete(team::tokens::revoke),
+        )
         .route("/api/v1/me", get(user::me::me))
         .route("/api/v1/me/updates", get(user::me::updates))
+        .route("/api/v1/me/usage", get(user::me::usage))
         .route("/api/v1/me/tokens", get(token::list).put(token::new))
         .route(
             "/api/v1/me/tokens/:id",
             get(token::show).delete(token::revoke),
         )
         .route("/api/v1/tokens/current", delete(token::revoke_current))
+        .route(
+            "/api/v1/me/service_tokens",
+            get(service_token::list).put(service_token::new),
+        )
+        .route(
+            "/api/v1/me/service_tokens/:id",
+            delete(service_token::revoke),
+        )
+        .route(
+            "/api/v1/me/rate_limits/requests",
+            get(user::rate_limits::list_requests).post(user::rate_limits::create_request),
+        )
         .route(
             "/api/v1/me/crate_owner_invitations",
             get(crate_owner_invitation::list),
@@ -125,11 +236,76 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/me/email_notifications",
             put(user::me::update_email_notifications),
         )
+        .route(
+            "/api/v1/me/publish_settings",
+            put(user::me::update_publish_settings),
+        )
+        .route(
+            "/api/v1/me/emails",
+            get(user::emails::list).put(user::emails::add),
+        )
+        .route("/api/v1/me/emails/:email_id", delete(user::emails::delete))
+        .route(
+            "/api/v1/me/emails/:email_id/primary",
+            put(user::emails::set_primary),
+        )
+        .route(
+            "/api/v1/me/emails/:email_id/resend",
+            put(user::emails::resend),
+        )
+        .route(
+            "/api/v1/me/email_preferences",
+            put(user::emails::update_preferences),
+        )
+        .route(
+            "/api/v1/me/digest_settings",
+            get(user::me::digest_settings).put(user::me::update_digest_settings),
+        )
+        .route(
+            "/api/v1/me/subscriptions",
+            get(user::subscriptions::list).put(user::subscriptions::create),
+        )
+        .route(
+            "/api/v1/me/subscriptions/:id",
+            delete(user::subscriptions::delete),
+        )
         .route("/api/v1/summary", get(summary::summary))
+        .route("/api/v1/db_dumps", get(db_dump::list))
+        .route("/api/v1/changes", get(changes::list))
+        .route("/api/v1/changes/stream", get(changes::stream))
+        .route(
+            "/api/v1/research/dependency_graph",
+            get(research::dependency_graph),
+        )
+        .route(
+            "/api/v1/research/download_distribution",
+            get(research::download_distribution),
+        )
+        .route(
+            "/api/v1/research/owner_distribution",
+            get(research::owner_distribution),
+        )
         .route(
             "/api/v1/confirm/:email_token",
             put(user::me::confirm_user_email),
         )
+        .route("/api/v1/lock_account/:token", put(user::me::lock_account))
+        .route(
+            "/api/v1/confirm_account_recovery/:token",
+            put(account_recovery::confirm),
+        )
+        .route(
+            "/api/v1/confirm_publish/:token",
+            put(krate::publish::confirm_publish),
+        )
+        .route(
+            "/api/v1/confirm_yank/:token",
+            put(version::yank::confirm_yank),
+        )
+        .route(
+            "/api/v1/dormant_owner_removals/:token",
+            put(krate::owners::confirm_dormant_owner_removal),
+        )
         .route(
             "/api/v1/users/:user_id/resend",
             put(user::me::regenerate_token_and_send),
@@ -147,6 +323,121 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         .route("/api/private/session", delete(user::session::logout))
         // Metrics
         .route("/api/private/metrics/:kind", get(metrics::prometheus))
+        .route(
+            "/api/private/docs_rs/build_status",
+            put(docs_rs::update_build_status),
+        )
+        .route("/api/private/email_events/ses", post(email_events::ses))
+        .route(
+            "/api/private/email_events/mailgun",
+            post(email_events::mailgun),
+        )
+        // Index maintenance
+        .route("/api/private/index/:crate_id/rebuild", post(index::rebuild))
+        // Audit log of admin actions
+        .route("/api/private/audit", get(admin_audit::list))
+        .route("/api/private/impersonate", delete(impersonation::end))
+        .route(
+            "/api/private/impersonate/:user_id",
+            post(impersonation::start),
+        )
+        // Crawler throttling policies
+        .route(
+            "/api/private/user_agent_policies",
+            get(user_agent_policy::list).put(user_agent_policy::update),
+        )
+        .route(
+            "/api/private/user_agent_policies/:id",
+            delete(user_agent_policy::delete),
+        )
+        // Accounts that can attach attestations to versions
+        .route(
+            "/api/private/attestation_providers",
+            get(attestation_provider::list),
+        )
+        .route(
+            "/api/private/attestation_providers/:user_id",
+            put(attestation_provider::approve).delete(attestation_provider::revoke),
+        )
+        // Antivirus review queue
+        .route("/api/private/tarball_scans", get(tarball_scan::list))
+        .route(
+            "/api/private/tarball_scans/:version_id/review",
+            put(tarball_scan::review),
+        )
+        // Yanking the versions published by compromised accounts or tokens
+        .route("/api/private/bulk_yanks", put(bulk_yank::bulk_yank))
+        // Spam moderation queue
+        .route("/api/private/spam_flags", get(spam_flag::list))
+        .route(
+            "/api/private/spam_flags/:version_id/review",
+            put(spam_flag::review),
+        )
+        // Report of the users that are throttled the most
+        .route("/api/private/rate_limits/report", get(rate_limit::report))
+        // Requests of users for higher rate limits
+        .route(
+            "/api/private/rate_limits/requests",
+            get(rate_limit::list_override_requests),
+        )
+        .route(
+            "/api/private/rate_limits/requests/:id/review",
+            put(rate_limit::review_override_request),
+        )
+        // Report of the deprecated API surfaces that are still in use
+        .route("/api/private/deprecations", get(deprecation::report))
+        // Report of the largest crates in the registry
+        .route("/api/private/crates/largest", get(crate_size::largest))
+        // Recovering accounts after their owners lost access to GitHub
+        .route(
+            "/api/private/account_recoveries",
+            get(account_recovery::list).put(account_recovery::request),
+        )
+        .route(
+            "/api/private/account_recoveries/:id",
+            delete(account_recovery::cancel),
+        )
+        .route(
+            "/api/private/account_recoveries/:id/approve",
+            put(account_recovery::approve),
+        )
+        .route(
+            "/api/private/account_recoveries/:id/complete",
+            put(account_recovery::complete),
+        )
+        // Freezing crates pending an investigation
+        .route(
+            "/api/private/crates/:crate_id/freeze",
+            put(krate::freeze::freeze).delete(krate::freeze::unfreeze),
+        )
+        // Repairing corrupted crate tarballs
+        .route(
+            "/api/private/crates/:crate_id/:version/tarball",
+            put(version::repair::repair),
+        )
+        // Critical crates and the approval of their owner removals
+        .route(
+            "/api/private/crates/:crate_id/critical",
+            put(krate::critical::designate).delete(krate::critical::revoke),
+        )
+        .route(
+            "/api/private/owner_removals",
+            get(krate::critical::list_owner_removals),
+        )
+        .route(
+            "/api/private/owner_removals/:id/approve",
+            put(krate::critical::approve_owner_removal),
+        )
+        // Exceptions of the registry dependency policy
+        .route(
+            "/api/private/dependency_policy_exceptions",
+            get(krate::dependency_policy::list),
+        )
+        .route(
+            "/api/private/crates/:crate_id/dependency_policy_exception",
+            put(krate::dependency_policy::add_exception)
+                .delete(krate::dependency_policy::remove_exception),
+        )
         // Crate ownership invitations management in the frontend
         .route(
             "/api/private/crate_owner_invitations",
@@ -158,6 +449,14 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             post(github::secret_scanning::verify),
         );
 
+    // Pull-through caches serve the sparse index themselves, so that the
+    // index files of unknown crates can be fetched from upstream on demand.
+    // Deployments that require authentication for downloads serve it
+    // themselves as well, since the CDN can't check the token.
+    if state.config.upstream.is_some() || state.config.download_auth_required {
+        router = router.route("/index/*path", get(index::sparse_index_file));
+    }
+
     // Only serve the local checkout of the git index in development mode.
     // In production, for crates.io, cargo gets the index from
     // https://github.com/rust-lang/crates.io-index directly
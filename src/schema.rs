@@ -17,6 +17,78 @@ pub mod sql_types {
     pub use diesel_full_text_search::Tsvector;
 }
 
+diesel::table! {
+    /// Requests to re-link accounts to a new GitHub identity after their owners lost access to the previous one. Recoveries have to be confirmed via the verified email address of the account and approved by an admin, and can only be completed after a waiting period.
+    account_recoveries (id) {
+        /// Unique identifier of the account recovery.
+        id -> Int4,
+        /// Reference to the account that is recovered in the `users` table.
+        user_id -> Int4,
+        /// Reference to the account of the new GitHub identity that requested the recovery, which is merged into the recovered account on completion.
+        requested_by -> Int4,
+        /// The verified email address of the recovered account that the confirmation link was sent to.
+        email -> Varchar,
+        /// Secret token that is sent to the verified email address of the recovered account to confirm the recovery.
+        email_token -> Text,
+        /// Date and time when the recovery was confirmed via the email address of the recovered account.
+        email_confirmed_at -> Nullable<Timestamp>,
+        /// Reference to the admin that approved the recovery.
+        approved_by -> Nullable<Int4>,
+        /// Date and time when the recovery was approved by an admin.
+        approved_at -> Nullable<Timestamp>,
+        /// Date and time when the account was re-linked to the new GitHub identity.
+        completed_at -> Nullable<Timestamp>,
+        /// Date and time when the recovery was cancelled.
+        cancelled_at -> Nullable<Timestamp>,
+        /// Date and time when the recovery was requested.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Actions that were performed by crates.io admins, either via the API or via the admin CLI. Entries can not be changed or deleted.
+    admin_audit_log (id) {
+        /// Unique identifier of the log entry.
+        id -> Int4,
+        /// Reference to the admin in the `users` table that performed the action, or NULL if the action was performed via the admin CLI.
+        actor_id -> Nullable<Int4>,
+        /// The kind of action that was performed (see `AdminAction` in the source code).
+        action -> Int4,
+        /// Name of the crate that the action affected, if any. This is not a reference to the `crates` table, so that the entries of deleted crates are kept.
+        crate_name -> Nullable<Varchar>,
+        /// Additional details of the action, e.g. the reason of a freeze or the affected versions.
+        details -> Jsonb,
+        /// Date and time when the action was performed.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Security advisories of the RustSec advisory database, which are imported by the `sync_advisories` background job.
+    advisories (id) {
+        /// The ID of the advisory in the RustSec advisory database, e.g. `RUSTSEC-2024-0001`.
+        id -> Varchar,
+        /// The name of the affected crate. The crate does not necessarily exist on this registry.
+        crate_name -> Varchar,
+        /// The title of the advisory.
+        title -> Text,
+        /// The URL of a web page with more details about the advisory, if there is one.
+        url -> Nullable<Text>,
+        /// The date when the advisory was published.
+        date -> Date,
+        /// The date when the advisory was withdrawn, e.g. because it was published in error, or NULL if it is still in effect.
+        withdrawn -> Nullable<Date>,
+        /// Other IDs of the same vulnerability, e.g. CVE or GHSA IDs.
+        aliases -> Array<Text>,
+        /// Version requirements that match the versions in which the vulnerability is fixed.
+        patched -> Array<Text>,
+        /// Version requirements that match the versions that were never affected by the vulnerability.
+        unaffected -> Array<Text>,
+        /// Date and time when the advisory was last imported from the advisory database.
+        synced_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `api_tokens` table.
     ///
@@ -76,6 +148,38 @@ diesel::table! {
         expired_at -> Nullable<Timestamp>,
         /// timestamp of when the user was informed about their token's impending expiration
         expiry_notification_at -> Nullable<Timestamp>,
+        /// Name of the registry that the token can be used with.
+        registry -> Varchar,
+        /// Contact information of the operator of a service token (e.g. an email address or a URL). Service tokens can not be used to perform any actions, but have elevated API quotas for read requests. NULL for regular API tokens.
+        service_contact -> Nullable<Varchar>,
+        /// Reference to the team in the `teams` table, if this is a team token. Team tokens can only be used for crates owned by the team, and `user_id` refers to the team maintainer that created the token, who is recorded as the acting user of all actions performed with it. NULL for regular API tokens.
+        team_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    /// Number of quota-limited API requests that have been made with an API token in the current quota window.
+    api_token_usage (api_token_id) {
+        /// Reference to the API token in the `api_tokens` table.
+        api_token_id -> Int4,
+        /// Start of the quota window that the `requests` counter applies to.
+        window_start -> Timestamp,
+        /// Number of requests within the quota window.
+        requests -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Accounts that were approved by an admin to attach attestations (e.g. rebuilds or audits) to versions.
+    attestation_providers (user_id) {
+        /// Reference to the account of the provider in the `users` table.
+        user_id -> Int4,
+        /// Human-readable name of the provider, e.g. the name of the rebuilder or audit organization.
+        name -> Varchar,
+        /// Reference to the admin in the `users` table that approved the provider.
+        approved_by -> Nullable<Int4>,
+        /// Date and time when the provider was approved.
+        created_at -> Timestamp,
     }
 }
 
@@ -176,6 +280,26 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Precomputed download totals of the crates for the windows that are shown in charts and badges. Refreshed by the `update_downloads` background job.
+    crate_download_summaries (crate_id) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// Number of downloads of the current day.
+        last_day -> Int8,
+        /// Number of downloads of the last 7 days, including the current day.
+        last_week -> Int8,
+        /// Number of downloads of the last 30 days, including the current day.
+        last_month -> Int8,
+        /// Number of downloads of the last 90 days, including the current day.
+        last_90_days -> Int8,
+        /// Number of downloads of the 7 days before the last 7 days.
+        previous_week -> Int8,
+        /// Date and time when the view was last refreshed.
+        computed_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Number of downloads per crate. This was extracted from the `crates` table for performance reasons.
     crate_downloads (crate_id) {
@@ -186,6 +310,90 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Crates that have been frozen by an admin pending an investigation. No new versions can be published for frozen crates and their owners can not be changed, but their existing versions can still be downloaded.
+    crate_freezes (crate_id) {
+        /// Reference to the frozen crate in the `crates` table.
+        crate_id -> Int4,
+        /// The reason why the crate was frozen, which is shown to the owners of the crate when they try to change it.
+        reason -> Text,
+        /// Reference to the admin that froze the crate.
+        frozen_by -> Int4,
+        /// Date and time when the crate was frozen.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Health scores of crates and the metrics that they are computed from. Updated periodically by the `update_crate_health` background job.
+    crate_health (crate_id) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// Overall health score of the crate, between 0 and 100.
+        score -> Int2,
+        /// Date and time when the most recent version of the crate was published.
+        last_release_at -> Timestamp,
+        /// Number of users that own the crate.
+        maintainers -> Int4,
+        /// Number of owners that published a version of the crate within the last year.
+        active_maintainers -> Int4,
+        /// Number of versions of the crate that are quarantined because they are suspected to be malicious.
+        quarantined_versions -> Int4,
+        /// Status of the docs.rs build of the default version: 0=in progress, 1=success, 2=failure, NULL=unknown.
+        docs_build_status -> Nullable<Int4>,
+        /// Total number of versions of the crate.
+        versions -> Int4,
+        /// Number of yanked versions of the crate.
+        yanked_versions -> Int4,
+        /// Date and time when the health score was computed.
+        computed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Crate names that may be published even though they can be confused with the name of an existing crate.
+    crate_name_skeleton_overrides (name) {
+        /// Name of the crate that is allowed to be published.
+        name -> Varchar,
+        /// Date and time when the override was added.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Append-only log of the users and teams that were added to or removed from the owners of a crate.
+    crate_owner_actions (id) {
+        /// Unique identifier of the action.
+        id -> Int4,
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// This refers either to the `users.id` or `teams.id` column, depending on the value of the `owner_kind` column.
+        owner_id -> Int4,
+        /// `owner_kind = 0` refers to `users`, `owner_kind = 1` refers to `teams`.
+        owner_kind -> Int4,
+        /// 0=add, 1=remove
+        action -> Int4,
+        /// Reference to the user in the `users` table that performed the action, or NULL if it was performed by the crates.io team or is unknown.
+        performed_by -> Nullable<Int4>,
+        /// Date and time when the action was performed.
+        time -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Pending crate ownership invitations for email addresses that do not belong to a crates.io account yet. They are turned into regular invitations once a user with a matching verified email address logs in.
+    crate_owner_email_invitations (crate_id, email) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// Lowercased email address of the invitee.
+        email -> Varchar,
+        /// Reference to the owner in the `users` table that sent the invitation.
+        invited_by_user_id -> Int4,
+        /// Date and time when the invitation was created.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `crate_owner_invitations` table.
     ///
@@ -278,6 +486,74 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Crates that are related to other crates, as computed by the `update_crate_recommendations` background job.
+    crate_recommendations (crate_id, kind, related_crate_id) {
+        /// Reference to the crate in the `crates` table that the recommendation is shown for.
+        crate_id -> Int4,
+        /// 0=depended_on_together, 1=used_together
+        kind -> Int4,
+        /// Reference to the recommended crate in the `crates` table.
+        related_crate_id -> Int4,
+        /// Number of crates that depend on both crates for `depended_on_together`, or the recent downloads of these crates for `used_together`.
+        score -> Int8,
+        /// Date and time when the recommendation was computed.
+        computed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Daily download counts of crates per country, as determined by the CDN. Only the last 90 days are kept, and the counts are only exposed in aggregated form.
+    crate_region_downloads (crate_id, date, country) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// Date of the downloads.
+        date -> Date,
+        /// ISO 3166-1 alpha-2 code of the country that the downloads originated from.
+        country -> Varchar,
+        /// Number of downloads of the crate from the country on the date.
+        downloads -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Settings of a crate that can be changed by its owners. Crates without a row use the default settings.
+    crate_settings (crate_id) {
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// If set, pre-release versions older than this number of days are automatically yanked once a newer stable version has been published.
+        prerelease_retention_days -> Nullable<Int4>,
+        /// Date and time when the settings were last changed.
+        updated_at -> Timestamp,
+        /// Email address or URL that security issues of the crate should be reported to.
+        security_contact -> Nullable<Varchar>,
+        /// Description of how security issues of the crate are handled and disclosed.
+        disclosure_policy -> Nullable<Text>,
+        /// Description of the minimum supported Rust version policy of the crate.
+        msrv_policy -> Nullable<Text>,
+        /// Dependencies that new versions of the crate must not use, as a JSON array of objects with a `name` and an optional `version_req`.
+        denied_dependencies -> Jsonb,
+    }
+}
+
+diesel::table! {
+    /// Relations between crates that have been superseded and the crates that supersede them. A relation is only in effect once the owners of both crates confirmed it.
+    crate_successions (predecessor_id) {
+        /// Reference to the superseded crate in the `crates` table. Every crate can only be superseded by one other crate.
+        predecessor_id -> Int4,
+        /// Reference to the superseding crate in the `crates` table.
+        successor_id -> Int4,
+        /// Date and time when an owner of the superseded crate confirmed the relation, or NULL if it is still awaiting their confirmation.
+        predecessor_confirmed_at -> Nullable<Timestamp>,
+        /// Date and time when an owner of the superseding crate confirmed the relation, or NULL if it is still awaiting their confirmation.
+        successor_confirmed_at -> Nullable<Timestamp>,
+        /// Reference to the user that declared the relation.
+        created_by -> Int4,
+        /// Date and time when the relation was declared.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::Tsvector;
@@ -358,6 +634,18 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         max_features -> Nullable<Int2>,
+        /// Name of the registry that the crate belongs to, see `src/registries.rs`.
+        registry -> Varchar,
+        /// Visibility of the crate. 0 = public, 1 = private. Private crates are hidden from search, and their metadata and downloads are only available to their owners.
+        visibility -> Int4,
+        /// If set, overrides the maximum number of dependencies that a version of this crate can declare.
+        max_dependencies -> Nullable<Int2>,
+        /// Time at which an owner proved control of the `repository` of the crate, or NULL if the repository has not been verified. Reset whenever the repository changes.
+        repository_verified_at -> Nullable<Timestamp>,
+        /// Time at which the owners of the crate flagged it as looking for new maintainers, or NULL if the crate is not looking for maintainers.
+        maintenance_wanted_at -> Nullable<Timestamp>,
+        /// If set, overrides the maximum number of features or dependencies that a single feature of this crate can enable.
+        max_feature_values -> Nullable<Int2>,
     }
 }
 
@@ -401,6 +689,62 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Crates that have been designated as critical for the ecosystem by an admin. All owners of critical crates must use two-factor authentication, and yanks and owner removals are subject to stricter rules.
+    critical_crates (crate_id) {
+        /// Reference to the critical crate in the `crates` table.
+        crate_id -> Int4,
+        /// Reference to the admin that designated the crate as critical.
+        designated_by -> Int4,
+        /// Date and time when the crate was designated as critical.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Daily changes since a public database dump, derived from the registry events.
+    database_dump_deltas (id) {
+        /// Unique identifier of the delta.
+        id -> Int4,
+        /// Reference to the full database dump in the `database_dumps` table that the delta is based on.
+        dump_id -> Int4,
+        /// Date and time when the delta was created.
+        created_at -> Timestamp,
+        /// ID of the registry event after which the delta starts (exclusive).
+        from_event_id -> Int8,
+        /// ID of the last registry event included in the delta (inclusive).
+        to_event_id -> Int8,
+        /// Path of the delta file in the storage bucket.
+        path -> Varchar,
+        /// Size of the delta file in bytes.
+        size -> Int8,
+        /// Hex-encoded SHA-256 checksum of the delta file.
+        sha256 -> Varchar,
+    }
+}
+
+diesel::table! {
+    /// Public database dumps that were uploaded to the storage bucket.
+    database_dumps (id) {
+        /// Unique identifier of the database dump.
+        id -> Int4,
+        /// Date and time when the database dump was started.
+        created_at -> Timestamp,
+        /// Path of the directory in the storage bucket that contains the archives and the manifest of the dump.
+        directory -> Varchar,
+        /// Size of the `db-dump.tar.gz` archive in bytes.
+        tar_size -> Int8,
+        /// Hex-encoded SHA-256 checksum of the `db-dump.tar.gz` archive.
+        tar_sha256 -> Varchar,
+        /// Size of the `db-dump.zip` archive in bytes.
+        zip_size -> Int8,
+        /// Hex-encoded SHA-256 checksum of the `db-dump.zip` archive.
+        zip_sha256 -> Varchar,
+        /// ID of the last registry event at the time the dump was started. The deltas of this dump start after this event.
+        last_event_id -> Int8,
+    }
+}
+
 diesel::table! {
     /// A mapping from crates to the versions that the frontend will display by default.
     default_versions (crate_id) {
@@ -479,6 +823,96 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Crates that new versions of other crates may depend on, even though some of their versions are quarantined. Managed by the admins.
+    dependency_policy_exceptions (crate_id) {
+        /// Reference to the exempted dependency in the `crates` table.
+        crate_id -> Int4,
+        /// The reason why dependencies on the crate are allowed.
+        reason -> Text,
+        /// Reference to the admin that added the exception.
+        created_by -> Int4,
+        /// Date and time when the exception was added.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Subscriptions of users that want to be notified when a crate publishes a version matching a version requirement.
+    dependency_subscriptions (id) {
+        /// Unique identifier of the subscription.
+        id -> Int4,
+        /// The user that is notified about new versions.
+        user_id -> Int4,
+        /// The crate that the user subscribed to.
+        crate_id -> Int4,
+        /// Semver version requirement that new versions have to match, e.g. `^2`.
+        version_req -> Varchar,
+        /// Date and time when the subscription was created.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Usage of deprecated API endpoints and query parameters per API token, so that the owners of the tokens can be contacted before the surfaces are removed.
+    deprecated_api_usage (surface, api_token_id) {
+        /// The deprecated route pattern, followed by `?<param>` if only a query parameter of the route is deprecated.
+        surface -> Varchar,
+        /// Reference to the API token in the `api_tokens` table that the requests were authenticated with.
+        api_token_id -> Int4,
+        /// Number of requests to the deprecated surface.
+        requests -> Int8,
+        /// Date and time of the first request to the deprecated surface.
+        first_used_at -> Timestamp,
+        /// Date and time of the most recent request to the deprecated surface.
+        last_used_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Users that opted in to the weekly maintainer digest email.
+    digest_subscriptions (user_id) {
+        /// Reference to the user in the `users` table.
+        user_id -> Int4,
+        /// IANA timezone name of the user, used to send the digest on Monday morning local time.
+        timezone -> Varchar,
+        /// Date and time when the last digest was sent to the user.
+        last_sent_at -> Nullable<Timestamp>,
+        /// Date and time when the user opted in to the digest.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Suggestions to remove dormant owners of a crate that were sent to the active owners of the crate via email.
+    dormant_owner_suggestions (id) {
+        /// Unique identifier of the suggestion.
+        id -> Int4,
+        /// The crate that the dormant owner owns.
+        crate_id -> Int4,
+        /// The owner that has not been active for a while.
+        owner_id -> Int4,
+        /// The active owner that the suggestion was sent to.
+        recipient_id -> Int4,
+        /// Secret token that is sent to the active owner to confirm the removal.
+        token -> Text,
+        /// Date and time when the suggestion was sent. Suggestions expire after 30 days.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Selects which of the email addresses of a user receives which class of notifications.
+    email_notification_preferences (user_id, notification_class) {
+        /// Reference to the user in the `users` table.
+        user_id -> Int4,
+        /// Class of notifications: 0=publishing, 1=ownership, 2=digest, 3=subscriptions.
+        notification_class -> Int4,
+        /// Reference to the address in the `emails` table that receives the notifications.
+        email_id -> Int4,
+    }
+}
+
 diesel::table! {
     /// Representation of the `emails` table.
     ///
@@ -520,6 +954,12 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         token_generated_at -> Nullable<Timestamp>,
+        /// Whether this is the primary address of the user. Every user has at most one primary address.
+        is_primary -> Bool,
+        /// Date and time when the email provider reported that emails to this address bounced permanently or were marked as spam. No notifications are sent to undeliverable addresses until they are verified again.
+        undeliverable_at -> Nullable<Timestamp>,
+        /// The reason why the address is undeliverable, either `bounce` or `complaint`.
+        undeliverable_reason -> Nullable<Varchar>,
     }
 }
 
@@ -543,6 +983,24 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Requests that were made by admins while impersonating another user.
+    impersonation_audit_log (id) {
+        /// Unique identifier of the log entry.
+        id -> Int4,
+        /// Reference to the admin in the `users` table that impersonated the user.
+        impersonator_id -> Int4,
+        /// Reference to the impersonated user in the `users` table.
+        user_id -> Int4,
+        /// HTTP method of the request.
+        method -> Varchar,
+        /// Path of the request, without the query string.
+        path -> Varchar,
+        /// Date and time when the request was made.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `keywords` table.
     ///
@@ -575,6 +1033,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Violations of the current publish validation rules that were found in the metadata of already published versions.
+    metadata_findings (id) {
+        /// Unique identifier of the finding.
+        id -> Int4,
+        /// The version whose metadata violates the rule.
+        version_id -> Int4,
+        /// The validation rule that is violated. See the `MetadataRule` enum for the possible values.
+        rule -> Int4,
+        /// Human-readable description of the violation.
+        message -> Text,
+        /// Date and time when the finding was recorded.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `metadata` table.
     ///
@@ -589,6 +1063,112 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Registered mirrors of the crate files, which downloads are redirected to based on the region of the client.
+    mirrors (id) {
+        /// Unique identifier of the mirror.
+        id -> Int4,
+        /// Unique name of the mirror, used to manage it with the `crates-admin mirrors` command.
+        name -> Varchar,
+        /// URL that the paths of the crate files are appended to, e.g. `https://mirror.example.com` for `https://mirror.example.com/crates/foo/foo-1.0.0.crate`.
+        base_url -> Varchar,
+        /// Code of the continent that the mirror serves, e.g. `EU`.
+        region -> Varchar,
+        /// Whether downloads are redirected to the mirror. Mirrors are disabled automatically if they could not be verified for a while.
+        enabled -> Bool,
+        /// Date and time when the mirror was registered.
+        created_at -> Timestamp,
+        /// Date and time of the most recent health check of the mirror.
+        checked_at -> Nullable<Timestamp>,
+        /// Date and time of the most recent health check that found a crate file with the expected checksum on the mirror.
+        verified_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// Server-side state of the cookie sessions of the users, which allows enforcing session lifetimes and revoking sessions.
+    persistent_sessions (id) {
+        /// Unique identifier of the session.
+        id -> Int8,
+        /// Reference to the user in the `users` table that signed in. For impersonation sessions this is the admin.
+        user_id -> Int4,
+        /// SHA256 hash of the token that is stored in the session cookie.
+        hashed_token -> Bytea,
+        /// Date and time when the user signed in.
+        created_at -> Timestamp,
+        /// Date and time when the session was last used to authenticate a request.
+        last_used_at -> Timestamp,
+        /// Whether the session was revoked, e.g. because the user signed out or the session identifier was rotated.
+        revoked -> Bool,
+    }
+}
+
+diesel::table! {
+    /// Removals of owners of critical crates that are held back until two admins approved them, or until the waiting period is over.
+    pending_owner_removals (id) {
+        /// Unique identifier of the pending removal.
+        id -> Int4,
+        /// Reference to the crate in the `crates` table.
+        crate_id -> Int4,
+        /// Reference to the owner that is being removed, either in the `users` or the `teams` table.
+        owner_id -> Int4,
+        /// 0 = user, 1 = team
+        owner_kind -> Int4,
+        /// Reference to the user that requested the removal.
+        requested_by -> Int4,
+        /// Reference to the first admin that approved the removal, or NULL if no admin approved it yet.
+        approved_by -> Nullable<Int4>,
+        /// Date and time when the removal was requested.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Publishes that are held back until the user confirms them via email.
+    pending_publishes (id) {
+        /// Unique identifier of the pending publish.
+        id -> Int4,
+        /// The user that published the crate, and that has to confirm the publish.
+        user_id -> Int4,
+        /// The API token that was used to publish the crate.
+        api_token_id -> Nullable<Int4>,
+        /// Name of the crate that is being published.
+        crate_name -> Varchar,
+        /// Version of the crate that is being published.
+        version -> Varchar,
+        /// Raw body of the original publish request, including the metadata and the tarball.
+        body -> Bytea,
+        /// Secret token that is sent to the user to confirm the publish.
+        token -> Text,
+        /// Date and time when the publish was requested. Pending publishes expire after a day.
+        created_at -> Timestamp,
+        /// Identifier of the CI system that requested the publish, if it was reported via the `X-Cargo-CI-System` header.
+        ci_system -> Nullable<Varchar>,
+        /// URL of the CI run that requested the publish, if it was reported via the `X-Cargo-CI-Run-URL` header.
+        ci_run_url -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    /// Yanks of heavily downloaded versions that are held back until one of the crate owners confirms them via email.
+    pending_yanks (id) {
+        /// Unique identifier of the pending yank.
+        id -> Int4,
+        /// The version that is being yanked.
+        version_id -> Int4,
+        /// The user that requested the yank.
+        user_id -> Int4,
+        /// The API token that was used to request the yank.
+        api_token_id -> Nullable<Int4>,
+        /// Secret token that is sent to the owners of the crate to confirm the yank.
+        token -> Text,
+        /// Date and time when the yank was requested. Pending yanks expire after a day.
+        created_at -> Timestamp,
+        /// Optional reason that the user provided for the yank.
+        reason -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     /// List of all processed CDN log files, used to avoid processing the same file multiple times.
     processed_log_files (path) {
@@ -599,11 +1179,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Outcomes of successful publishes that were sent with an `Idempotency-Key` header. Retries of a publish with the same key replay the stored response instead of failing.
+    publish_idempotency_keys (user_id, key) {
+        /// Reference to the user in the `users` table that sent the publish.
+        user_id -> Int4,
+        /// Value of the `Idempotency-Key` header, unique per user.
+        key -> Varchar,
+        /// Name of the crate that was published with the key.
+        crate_name -> Varchar,
+        /// Version of the crate that was published with the key.
+        version -> Varchar,
+        /// JSON body of the original response, which is replayed for retries.
+        response -> Jsonb,
+        /// Date and time when the publish was completed. Keys expire after a day.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `publish_limit_buckets` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_limit_buckets (user_id, action) {
+    publish_limit_buckets (user_id, action, scope) {
         /// The `user_id` column of the `publish_limit_buckets` table.
         ///
         /// Its SQL type is `Int4`.
@@ -628,6 +1226,8 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         action -> Int4,
+        /// Scope of the rate limited action, e.g. the name of a crate, or an empty string if the bucket applies to the action as a whole.
+        scope -> Varchar,
     }
 }
 
@@ -663,6 +1263,68 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Requests of users for a higher burst of a rate limited action, which are reviewed by admins.
+    rate_limit_override_requests (id) {
+        /// Unique identifier of the request.
+        id -> Int4,
+        /// Reference to the user in the `users` table that filed the request.
+        user_id -> Int4,
+        /// The rate limited action: 0=publish_new, 1=publish_update, 2=yank_unyank, 3=bulk_metadata.
+        action -> Int4,
+        /// The requested burst, which the admin may change when approving the request.
+        burst -> Int4,
+        /// Explanation of the user why they need a higher limit.
+        justification -> Varchar,
+        /// Date and time when the request was filed.
+        created_at -> Timestamp,
+        /// Reference to the admin in the `users` table that reviewed the request.
+        reviewed_by -> Nullable<Int4>,
+        /// Date and time when the request was reviewed, or NULL if it is still pending.
+        reviewed_at -> Nullable<Timestamp>,
+        /// Whether the request was approved, or NULL if it is still pending.
+        approved -> Nullable<Bool>,
+        /// Date and time when the granted override expires, if the request was approved.
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// Requests that were rejected by the rate limiter. Used by the admin report of the most throttled users. Old rows are removed by the daily database maintenance job.
+    rate_limit_rejections (id) {
+        /// Unique identifier of the rejection.
+        id -> Int8,
+        /// Reference to the user in the `users` table whose request was rejected.
+        user_id -> Int4,
+        /// The rate limited action that was rejected: 0=publish_new, 1=publish_update, 2=yank_unyank, 3=bulk_metadata.
+        action -> Int4,
+        /// Date and time when the request was rejected.
+        created_at -> Timestamp,
+        /// Scope of the rejected action, e.g. the name of a crate, or NULL if the action is not scoped.
+        scope -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    /// Images that are referenced by rendered READMEs and served from the storage bucket of crates.io instead of being loaded from third-party hosts.
+    readme_images (digest) {
+        /// SHA256 digest of the original URL of the image, which is used in the URLs of the proxy endpoint and the storage bucket.
+        digest -> Text,
+        /// The original URL of the image.
+        url -> Text,
+        /// The content type of the cached image, or NULL if it has not been fetched successfully yet.
+        content_type -> Nullable<Varchar>,
+        /// The size of the cached image in bytes, or NULL if it has not been fetched successfully yet.
+        size -> Nullable<Int4>,
+        /// Date and time when the image was last fetched and uploaded to the storage bucket successfully.
+        fetched_at -> Nullable<Timestamp>,
+        /// Date and time when fetching the image last failed, e.g. because it was too large or not an image.
+        failed_at -> Nullable<Timestamp>,
+        /// Date and time when the image was first referenced by a README.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `readme_renderings` table.
     ///
@@ -680,6 +1342,10 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         rendered_at -> Timestamp,
+        /// Version of the HTML sanitizer policy that the README was rendered with. READMEs that were rendered with an older policy are rendered again by the `rerender_readmes` background job.
+        policy_version -> Int4,
+        /// The headings of the rendered README as a JSON array of objects with `level`, `text` and `anchor` fields, or NULL if the README is not a Markdown file or was rendered before tables of contents were extracted.
+        table_of_contents -> Nullable<Jsonb>,
     }
 }
 
@@ -701,6 +1367,41 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Append-only log of changes to the registry, which is exposed via the changes feed.
+    registry_events (id) {
+        /// Monotonically increasing identifier of the event, which is used as the cursor of the changes feed.
+        id -> Int8,
+        /// The kind of change: 0=publish, 1=yank, 2=unyank, 3=owner change, 4=delete, 5=flag, 6=unflag.
+        kind -> Int4,
+        /// Name of the crate that was changed. Deliberately not a foreign key, since events outlive deleted crates.
+        crate_name -> Varchar,
+        /// Version of the crate that was changed, or NULL if the event applies to the whole crate.
+        version -> Nullable<Varchar>,
+        /// Date and time when the change happened.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Reports of independent rebuilders that attest whether a version could be reproduced from source.
+    reproducibility_reports (version_id, reporter_id) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Reference to the user in the `users` table that submitted the report.
+        reporter_id -> Int4,
+        /// SHA256 checksum of the normalized tarball that the rebuilder produced.
+        #[max_length = 64]
+        checksum -> Bpchar,
+        /// TRUE if the checksum matches the `normalized_checksum` of the version at the time of the report.
+        matches -> Bool,
+        /// Optional free-form details about the rebuild, e.g. a link to the build log.
+        details -> Nullable<Varchar>,
+        /// Date and time when the report was last submitted.
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `reserved_crate_names` table.
     ///
@@ -715,6 +1416,83 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Security relevant events of user accounts, like sign-ins and the creation of API tokens.
+    security_events (id) {
+        /// Unique identifier of the security event.
+        id -> Int4,
+        /// Reference to the user in the `users` table.
+        user_id -> Int4,
+        /// Kind of the event: 0=login, 1=api_token_created, 2=account_locked.
+        kind -> Int4,
+        /// IP address of the client that caused the event.
+        ip_address -> Nullable<Varchar>,
+        /// User agent of the client that caused the event.
+        user_agent -> Nullable<Varchar>,
+        /// Reference to the API token in the `api_tokens` table, if the event is related to an API token.
+        api_token_id -> Nullable<Int4>,
+        /// Secret token that is sent to the user in security alerts, and that can be used to lock the account if the event was not caused by the user.
+        lock_token -> Text,
+        /// Date and time when the event happened.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Moderation queue of published versions whose descriptions or keywords were classified as potential spam.
+    spam_flags (version_id) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Spam score of the metadata of the version at the time of the publish.
+        score -> Int4,
+        /// Human-readable explanations of the spam score.
+        reasons -> Array<Text>,
+        /// Date and time when the version was flagged.
+        created_at -> Timestamp,
+        /// Reference to the admin in the `users` table that reviewed the flag.
+        reviewed_by -> Nullable<Int4>,
+        /// Date and time when the flag was reviewed by an admin.
+        reviewed_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// Crate tarballs that were uploaded ahead of the publish request that references them.
+    staged_uploads (id) {
+        /// Unique identifier of the staged upload, which is referenced by the publish request.
+        id -> Text,
+        /// The user that uploaded the tarball, and that is the only one who can publish it.
+        user_id -> Int4,
+        /// SHA256 checksum of the tarball.
+        #[max_length = 64]
+        checksum -> Bpchar,
+        /// Size of the tarball in bytes.
+        size -> Int4,
+        /// Verdict of the antivirus scan of the tarball: 0=clean, 1=infected, or NULL if it has not been scanned yet.
+        scan_verdict -> Nullable<Int4>,
+        /// Date and time when the tarball was uploaded. Staged uploads expire after a day.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Results of the antivirus scans of uploaded crate tarballs.
+    tarball_scans (version_id) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Verdict of the scan: 0=clean, 1=infected.
+        verdict -> Int4,
+        /// Name of the signature that matched, if the tarball is infected.
+        signature -> Nullable<Varchar>,
+        /// Date and time when the tarball was scanned.
+        scanned_at -> Timestamp,
+        /// Reference to the admin in the `users` table that reviewed the verdict.
+        reviewed_by -> Nullable<Int4>,
+        /// Date and time when the verdict was reviewed by an admin.
+        reviewed_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     /// Representation of the `teams` table.
     ///
@@ -747,6 +1525,62 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Crates that were fetched from the upstream registry of a pull-through cache. Their index files and crate files are cached in the storage bucket, but they are not added to the other tables.
+    upstream_crates (name) {
+        /// The name of the crate in the upstream registry.
+        name -> Varchar,
+        /// Date and time when the crate was fetched from the upstream registry for the first time.
+        created_at -> Timestamp,
+        /// Date and time when the cached index file was last revalidated against the upstream registry.
+        revalidated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Number of quota-limited API requests that a user has made in the current quota window.
+    user_api_usage (user_id) {
+        /// Reference to the user in the `users` table.
+        user_id -> Int4,
+        /// Start of the quota window that the `requests` counter applies to.
+        window_start -> Timestamp,
+        /// Number of requests within the quota window.
+        requests -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Throttling policies for anonymous requests, based on their `User-Agent` header.
+    user_agent_policies (id) {
+        /// Unique identifier of the policy.
+        id -> Int4,
+        /// Case-insensitive substring that is matched against the `User-Agent` header.
+        pattern -> Varchar,
+        /// How matching requests are treated. See the `ThrottleClass` enum for the possible values.
+        throttle_class -> Int4,
+        /// Date and time when the policy was created or last changed.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Avatars of users that are served from the storage bucket of crates.io instead of being loaded from GitHub.
+    user_avatars (user_id) {
+        /// Reference to the user in the `users` table.
+        user_id -> Int4,
+        /// The original URL of the avatar that was last fetched, or that failed to be fetched.
+        url -> Text,
+        /// SHA256 digest of the cached avatar, which is used in its path in the storage bucket, or NULL if it has not been fetched successfully yet.
+        digest -> Nullable<Text>,
+        /// The content type of the cached avatar, or NULL if it has not been fetched successfully yet.
+        content_type -> Nullable<Varchar>,
+        /// Date and time when the avatar was last fetched and uploaded to the storage bucket successfully.
+        fetched_at -> Nullable<Timestamp>,
+        /// Date and time when fetching the avatar last failed, e.g. because it was too large or not an image.
+        failed_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     /// Representation of the `users` table.
     ///
@@ -806,6 +1640,78 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         is_admin -> Bool,
+        /// If true, publishes using an API token are held pending until the user confirms them via the link in the confirmation email.
+        publish_confirmation_required -> Bool,
+        /// Whether the GitHub account of the user had two-factor authentication enabled when the user last signed in.
+        gh_two_factor -> Bool,
+        /// If set, overrides the maximum number of dependencies that a version published by this user can declare, unless the crate has its own override.
+        max_dependencies -> Nullable<Int2>,
+    }
+}
+
+diesel::table! {
+    /// Signed statements of attestation providers about versions, e.g. reproducible rebuilds, audits or fuzzing coverage.
+    version_attestations (id) {
+        /// Unique identifier of the attestation.
+        id -> Int4,
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Reference to the provider in the `attestation_providers` table.
+        provider_id -> Int4,
+        /// Kind of the attestation: 0=reproducible build, 1=audit, 2=fuzzing.
+        kind -> Int4,
+        /// The statement of the provider, in a format that is defined by the provider.
+        statement -> Text,
+        /// Signature of the statement, which can be verified with the published key of the provider.
+        signature -> Text,
+        /// Optional link to more details, e.g. a build log or an audit report.
+        url -> Nullable<Varchar>,
+        /// Date and time when the attestation was submitted.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Changes of the checksum of a version, after its stored tarball was replaced by an admin with a repaired tarball with the same contents.
+    version_checksum_rotations (id) {
+        /// Unique identifier of the rotation.
+        id -> Int4,
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// SHA256 checksum of the tarball before the repair.
+        #[max_length = 64]
+        old_checksum -> Bpchar,
+        /// SHA256 checksum of the replacement tarball.
+        #[max_length = 64]
+        new_checksum -> Bpchar,
+        /// Explanation of why the tarball had to be repaired, as given by the admin.
+        reason -> Varchar,
+        /// Date and time when the tarball was replaced.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Information about the CI run that published a version, as reported by the publisher via the `X-Cargo-CI-System` and `X-Cargo-CI-Run-URL` headers.
+    version_ci_annotations (version_id) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Identifier of the CI system that published the version (e.g. `github-actions`).
+        ci_system -> Varchar,
+        /// URL of the CI run that published the version.
+        run_url -> Nullable<Varchar>,
+        /// Date and time when the version was published.
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// The files in the crate tarball of a version, recorded at publish time. This is used to verify replacement tarballs when a stored tarball has to be repaired.
+    version_content_manifests (version_id) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// JSON object with the paths of all files in the tarball, relative to the package root, as keys and the hex-encoded SHA256 checksums of their contents as values.
+        files -> Jsonb,
     }
 }
 
@@ -866,10 +1772,10 @@ diesel::table! {
         version_id -> Int4,
         /// The `user_id` column of the `version_owner_actions` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
+        user_id -> Nullable<Int4>,
         /// The `api_token_id` column of the `version_owner_actions` table.
         ///
         /// Its SQL type is `Nullable<Int4>`.
@@ -888,6 +1794,22 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         time -> Timestamp,
+        /// Optional reason that the user provided for the action, e.g. why a version was yanked.
+        reason -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    /// Versions that are quarantined because they are suspected to be malicious. Quarantined versions are yanked, their tarballs are moved out of the public storage area, and they can not be unyanked by their owners.
+    version_quarantines (version_id) {
+        /// Reference to the version in the `versions` table.
+        version_id -> Int4,
+        /// Reason why the version was quarantined.
+        reason -> Varchar,
+        /// Whether the version was already yanked before it was quarantined. Used to restore the previous state if the quarantine is lifted.
+        was_yanked -> Bool,
+        /// Date and time when the version was quarantined.
+        created_at -> Timestamp,
     }
 }
 
@@ -985,6 +1907,25 @@ diesel::table! {
         has_lib -> Nullable<Bool>,
         /// list of the names of all detected binaries in the version. the list may be empty which indicates that no binaries were detected in the version. the column may be NULL is the version has not been analyzed yet.
         bin_names -> Nullable<Array<Nullable<Text>>>,
+        /// JSONB representation of the version number for sorting purposes.
+        semver_ord -> Nullable<Jsonb>,
+        /// TRUE if the version has a build script (e.g. `build.rs`), FALSE if no build script was detected, or NULL if the version has not been analyzed yet.
+        has_build_script -> Nullable<Bool>,
+        /// TRUE if the library of the version is a procedural macro (`proc-macro = true`), FALSE if it is not, or NULL if the version has not been analyzed yet.
+        is_proc_macro -> Nullable<Bool>,
+        /// Status of the documentation build on docs.rs, as reported by its webhook. 0 = in progress, 1 = success, 2 = failure, NULL = unknown.
+        docs_build_status -> Nullable<Int4>,
+        /// Total size of all files in the crate file after decompression, in bytes. NULL if the version was published before the size was recorded.
+        uncompressed_size -> Nullable<Int8>,
+        /// Name of the release channel (e.g. `beta` or `nightly`) that the version was published to, or NULL if it was not published to a channel.
+        channel -> Nullable<Varchar>,
+        /// SHA256 checksum of the normalized crate tarball (fixed mtimes, sorted entries), as reported by the publisher, or NULL if it was not reported.
+        #[max_length = 64]
+        normalized_checksum -> Nullable<Bpchar>,
+        /// Warning that the owners attached to the version, e.g. because of a known bug, without yanking it, or NULL if the version is not flagged.
+        flag_message -> Nullable<Varchar>,
+        /// Date and time when the version was last flagged, or NULL if the version is not flagged.
+        flagged_at -> Nullable<Timestamp>,
     }
 }
 
@@ -1008,61 +1949,165 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(account_recoveries -> users (user_id));
+diesel::joinable!(api_token_usage -> api_tokens (api_token_id));
+diesel::joinable!(api_tokens -> teams (team_id));
 diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(attestation_providers -> users (user_id));
+diesel::joinable!(crate_download_summaries -> crates (crate_id));
 diesel::joinable!(crate_downloads -> crates (crate_id));
+diesel::joinable!(crate_freezes -> crates (crate_id));
+diesel::joinable!(crate_freezes -> users (frozen_by));
+diesel::joinable!(crate_health -> crates (crate_id));
+diesel::joinable!(crate_owner_actions -> crates (crate_id));
+diesel::joinable!(crate_owner_actions -> users (performed_by));
+diesel::joinable!(crate_owner_email_invitations -> crates (crate_id));
+diesel::joinable!(crate_owner_email_invitations -> users (invited_by_user_id));
 diesel::joinable!(crate_owner_invitations -> crates (crate_id));
 diesel::joinable!(crate_owners -> crates (crate_id));
 diesel::joinable!(crate_owners -> teams (owner_id));
 diesel::joinable!(crate_owners -> users (owner_id));
+diesel::joinable!(crate_region_downloads -> crates (crate_id));
+diesel::joinable!(crate_settings -> crates (crate_id));
+diesel::joinable!(crate_successions -> users (created_by));
 diesel::joinable!(crates_categories -> categories (category_id));
 diesel::joinable!(crates_categories -> crates (crate_id));
 diesel::joinable!(crates_keywords -> crates (crate_id));
 diesel::joinable!(crates_keywords -> keywords (keyword_id));
+diesel::joinable!(critical_crates -> crates (crate_id));
+diesel::joinable!(critical_crates -> users (designated_by));
+diesel::joinable!(database_dump_deltas -> database_dumps (dump_id));
 diesel::joinable!(default_versions -> crates (crate_id));
 diesel::joinable!(default_versions -> versions (version_id));
 diesel::joinable!(dependencies -> crates (crate_id));
 diesel::joinable!(dependencies -> versions (version_id));
+diesel::joinable!(dependency_policy_exceptions -> crates (crate_id));
+diesel::joinable!(dependency_policy_exceptions -> users (created_by));
+diesel::joinable!(dependency_subscriptions -> crates (crate_id));
+diesel::joinable!(dependency_subscriptions -> users (user_id));
+diesel::joinable!(deprecated_api_usage -> api_tokens (api_token_id));
+diesel::joinable!(digest_subscriptions -> users (user_id));
+diesel::joinable!(dormant_owner_suggestions -> crates (crate_id));
+diesel::joinable!(email_notification_preferences -> emails (email_id));
+diesel::joinable!(email_notification_preferences -> users (user_id));
 diesel::joinable!(emails -> users (user_id));
 diesel::joinable!(follows -> crates (crate_id));
 diesel::joinable!(follows -> users (user_id));
+diesel::joinable!(metadata_findings -> versions (version_id));
+diesel::joinable!(pending_owner_removals -> crates (crate_id));
+diesel::joinable!(pending_publishes -> api_tokens (api_token_id));
+diesel::joinable!(pending_publishes -> users (user_id));
+diesel::joinable!(pending_yanks -> api_tokens (api_token_id));
+diesel::joinable!(pending_yanks -> users (user_id));
+diesel::joinable!(pending_yanks -> versions (version_id));
+diesel::joinable!(persistent_sessions -> users (user_id));
+diesel::joinable!(publish_idempotency_keys -> users (user_id));
 diesel::joinable!(publish_limit_buckets -> users (user_id));
 diesel::joinable!(publish_rate_overrides -> users (user_id));
+diesel::joinable!(rate_limit_rejections -> users (user_id));
 diesel::joinable!(readme_renderings -> versions (version_id));
 diesel::joinable!(recent_crate_downloads -> crates (crate_id));
+diesel::joinable!(reproducibility_reports -> users (reporter_id));
+diesel::joinable!(reproducibility_reports -> versions (version_id));
+diesel::joinable!(security_events -> api_tokens (api_token_id));
+diesel::joinable!(security_events -> users (user_id));
+diesel::joinable!(spam_flags -> users (reviewed_by));
+diesel::joinable!(spam_flags -> versions (version_id));
+diesel::joinable!(staged_uploads -> users (user_id));
+diesel::joinable!(tarball_scans -> users (reviewed_by));
+diesel::joinable!(tarball_scans -> versions (version_id));
+diesel::joinable!(user_api_usage -> users (user_id));
+diesel::joinable!(user_avatars -> users (user_id));
+diesel::joinable!(version_attestations -> attestation_providers (provider_id));
+diesel::joinable!(version_attestations -> versions (version_id));
+diesel::joinable!(version_checksum_rotations -> versions (version_id));
+diesel::joinable!(version_ci_annotations -> versions (version_id));
+diesel::joinable!(version_content_manifests -> versions (version_id));
 diesel::joinable!(version_downloads -> versions (version_id));
 diesel::joinable!(version_owner_actions -> api_tokens (api_token_id));
 diesel::joinable!(version_owner_actions -> users (user_id));
 diesel::joinable!(version_owner_actions -> versions (version_id));
+diesel::joinable!(version_quarantines -> versions (version_id));
 diesel::joinable!(versions -> crates (crate_id));
 diesel::joinable!(versions -> users (published_by));
 diesel::joinable!(versions_published_by -> versions (version_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    account_recoveries,
+    admin_audit_log,
+    advisories,
+    api_token_usage,
     api_tokens,
+    attestation_providers,
     background_jobs,
     categories,
+    crate_download_summaries,
     crate_downloads,
+    crate_freezes,
+    crate_health,
+    crate_name_skeleton_overrides,
+    crate_owner_actions,
+    crate_owner_email_invitations,
     crate_owner_invitations,
     crate_owners,
+    crate_recommendations,
+    crate_region_downloads,
+    crate_settings,
+    crate_successions,
     crates,
     crates_categories,
     crates_keywords,
+    critical_crates,
+    database_dump_deltas,
+    database_dumps,
     default_versions,
     dependencies,
+    dependency_policy_exceptions,
+    dependency_subscriptions,
+    deprecated_api_usage,
+    digest_subscriptions,
+    dormant_owner_suggestions,
+    email_notification_preferences,
     emails,
     follows,
+    impersonation_audit_log,
     keywords,
     metadata,
+    metadata_findings,
+    mirrors,
+    pending_owner_removals,
+    pending_publishes,
+    persistent_sessions,
+    pending_yanks,
     processed_log_files,
+    publish_idempotency_keys,
     publish_limit_buckets,
     publish_rate_overrides,
+    rate_limit_override_requests,
+    rate_limit_rejections,
+    readme_images,
     readme_renderings,
     recent_crate_downloads,
+    registry_events,
+    reproducibility_reports,
     reserved_crate_names,
+    security_events,
+    spam_flags,
+    staged_uploads,
+    tarball_scans,
     teams,
+    upstream_crates,
+    user_agent_policies,
+    user_api_usage,
+    user_avatars,
     users,
+    version_attestations,
+    version_checksum_rotations,
+    version_ci_annotations,
+    version_content_manifests,
     version_downloads,
     version_owner_actions,
+    version_quarantines,
     versions,
     versions_published_by,
 );
@@ -0,0 +1,133 @@
+//! Coordinated shutdown of the server and background worker processes.
+//!
+//! Once a process receives a shutdown signal, it stops accepting new work and
+//! waits a bounded amount of time for the work in progress to finish:
+//!
+//! - The server rejects new mutating requests (e.g. publishes) with a
+//!   `503 Service Unavailable` response, and waits for the in-flight mutating
+//!   requests to finish before it stops accepting connections. Read-only
+//!   requests are still served in the meantime.
+//! - The background worker finishes the jobs that it is currently running,
+//!   but does not start new ones. Jobs that don't finish in time are unlocked
+//!   when the process exits and are retried by another worker.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    draining: AtomicBool,
+    in_flight_mutations: AtomicUsize,
+    /// Notified whenever the last in-flight mutating request finishes.
+    idle: Notify,
+}
+
+impl Shutdown {
+    /// Starts rejecting new mutating requests.
+    pub fn begin_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Tracks a mutating request as in-flight until the returned guard is
+    /// dropped.
+    pub fn track_mutation(&self) -> MutationGuard<'_> {
+        self.in_flight_mutations.fetch_add(1, Ordering::SeqCst);
+        MutationGuard { shutdown: self }
+    }
+
+    pub fn in_flight_mutations(&self) -> usize {
+        self.in_flight_mutations.load(Ordering::SeqCst)
+    }
+
+    /// Waits until there are no in-flight mutating requests anymore.
+    ///
+    /// Returns `false` if there are still in-flight mutating requests after
+    /// the timeout has elapsed.
+    pub async fn wait_for_mutations(&self, timeout: Duration) -> bool {
+        let wait = async {
+            loop {
+                let idle = self.idle.notified();
+                tokio::pin!(idle);
+
+                // Register for the notification before checking the counter,
+                // so that a request finishing in between is not missed.
+                idle.as_mut().enable();
+                if self.in_flight_mutations() == 0 {
+                    return;
+                }
+
+                idle.await;
+            }
+        };
+
+        tokio::time::timeout(timeout, wait).await.is_ok()
+    }
+}
+
+pub struct MutationGuard<'a> {
+    shutdown: &'a Shutdown,
+}
+
+impl Drop for MutationGuard<'_> {
+    fn drop(&mut self) {
+        let shutdown = self.shutdown;
+        if shutdown.in_flight_mutations.fetch_sub(1, Ordering::SeqCst) == 1 {
+            shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+/// Waits until the process receives a `SIGINT` or `SIGTERM` signal.
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        signal(SignalKind::interrupt())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    let terminate = async {
+        signal(SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_in_flight_mutations() {
+        let shutdown = Shutdown::default();
+        assert!(shutdown.wait_for_mutations(Duration::ZERO).await);
+
+        let first = shutdown.track_mutation();
+        let second = shutdown.track_mutation();
+        assert!(!shutdown.wait_for_mutations(Duration::from_millis(10)).await);
+
+        drop(first);
+        assert!(!shutdown.wait_for_mutations(Duration::from_millis(10)).await);
+
+        let finish = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(second);
+        };
+
+        let (finished, _) =
+            tokio::join!(shutdown.wait_for_mutations(Duration::from_secs(10)), finish);
+        assert!(finished);
+    }
+}
@@ -0,0 +1,221 @@
+//! Classification of spam in the metadata of published crates.
+//!
+//! Every publish is scored by the [SpamClassifier] of the app. Publishes
+//! whose score reaches the configured threshold are not rejected, since the
+//! classification is only a heuristic, but are added to the moderation queue
+//! that is reviewed by the crates.io team (see the `spam_flags` table).
+//!
+//! The [Heuristics] classifier is the default implementation, which looks for
+//! URL-heavy descriptions, long runs of repeated characters and known spam
+//! phrases.
+
+pub const DEFAULT_THRESHOLD: u32 = 100;
+
+/// The score of URL-heavy descriptions.
+const URL_DENSITY_SCORE: u32 = 60;
+/// The minimum share of words in the description that are URLs, in percent.
+const MIN_URL_DENSITY_PERCENT: usize = 20;
+
+/// The score of descriptions and keywords with long runs of a single
+/// character, e.g. emojis.
+const REPEATED_CHARS_SCORE: u32 = 40;
+/// The minimum length of a run of a single non-alphanumeric character.
+const MIN_REPEATED_CHARS: usize = 5;
+
+/// The score of every known spam phrase.
+const SPAM_PHRASE_SCORE: u32 = 100;
+
+/// Phrases that are used by the [Heuristics] classifier in addition to the
+/// configured ones.
+const DEFAULT_SPAM_PHRASES: &[&str] = &[
+    "buy followers",
+    "casino bonus",
+    "crypto giveaway",
+    "free robux",
+    "free v-bucks",
+    "online pharmacy",
+    "viagra",
+];
+
+#[derive(Debug, Clone)]
+pub struct SpamConfig {
+    /// The minimum score of publishes that are added to the moderation
+    /// queue.
+    pub threshold: u32,
+    /// Additional phrases that are considered spam, in lower case.
+    pub phrases: Vec<String>,
+}
+
+impl Default for SpamConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            phrases: Vec::new(),
+        }
+    }
+}
+
+/// The result of a classification. A higher score means that the metadata is
+/// more likely to be spam.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpamScore {
+    pub score: u32,
+    /// Human-readable explanations of the score, for the moderators.
+    pub reasons: Vec<String>,
+}
+
+impl SpamScore {
+    pub fn add(&mut self, score: u32, reason: impl Into<String>) {
+        self.score += score;
+        self.reasons.push(reason.into());
+    }
+}
+
+pub trait SpamClassifier: Send + Sync {
+    fn classify(&self, description: Option<&str>, keywords: &[&str]) -> SpamScore;
+}
+
+/// Classifies spam with a couple of simple heuristics.
+pub struct Heuristics {
+    phrases: Vec<String>,
+}
+
+impl Heuristics {
+    pub fn new(config: &SpamConfig) -> Self {
+        let default_phrases = DEFAULT_SPAM_PHRASES.iter().map(|phrase| phrase.to_string());
+        let configured_phrases = config.phrases.iter().map(|phrase| phrase.to_lowercase());
+
+        Self {
+            phrases: default_phrases.chain(configured_phrases).collect(),
+        }
+    }
+}
+
+impl SpamClassifier for Heuristics {
+    fn classify(&self, description: Option<&str>, keywords: &[&str]) -> SpamScore {
+        let mut score = SpamScore::default();
+        let description = description.unwrap_or_default();
+
+        let words = description.split_whitespace().count();
+        let urls = description.split_whitespace().filter(|w| is_url(w)).count();
+        if urls > 0 && urls * 100 >= words * MIN_URL_DENSITY_PERCENT {
+            let reason = format!("{urls} of {words} words in the description are URLs");
+            score.add(URL_DENSITY_SCORE, reason);
+        }
+
+        let texts = std::iter::once(description).chain(keywords.iter().copied());
+        if let Some(run) = texts.filter_map(longest_repeated_run).max() {
+            if run >= MIN_REPEATED_CHARS {
+                let reason = format!("contains a character that is repeated {run} times");
+                score.add(REPEATED_CHARS_SCORE, reason);
+            }
+        }
+
+        let text = std::iter::once(description)
+            .chain(keywords.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+
+        for phrase in &self.phrases {
+            if text.contains(phrase.as_str()) {
+                let reason = format!("contains the known spam phrase `{phrase}`");
+                score.add(SPAM_PHRASE_SCORE, reason);
+            }
+        }
+
+        score
+    }
+}
+
+fn is_url(word: &str) -> bool {
+    let word = word.to_lowercase();
+    word.starts_with("http://") || word.starts_with("https://") || word.starts_with("www.")
+}
+
+/// Returns the length of the longest run of a single character that is
+/// neither alphanumeric nor whitespace, e.g. `!!!!` or `🔥🔥🔥`.
+fn longest_repeated_run(text: &str) -> Option<usize> {
+    let mut longest = None;
+    let mut current: Option<(char, usize)> = None;
+
+    for c in text.chars() {
+        current = match current {
+            Some((previous, count)) if previous == c => Some((c, count + 1)),
+            _ if c.is_alphanumeric() || c.is_whitespace() => None,
+            _ => Some((c, 1)),
+        };
+
+        if let Some((_, count)) = current {
+            longest = longest.max(Some(count));
+        }
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(description: &str, keywords: &[&str]) -> SpamScore {
+        Heuristics::new(&SpamConfig::default()).classify(Some(description), keywords)
+    }
+
+    #[test]
+    fn legitimate_metadata() {
+        let description = "A fast and safe HTTP client, see https://example.com for details";
+        let score = classify(description, &["http", "client"]);
+        assert_eq!(score, SpamScore::default());
+
+        let score = classify("Formats numbers like 1,000,000!", &["fmt"]);
+        assert_eq!(score, SpamScore::default());
+
+        let score = Heuristics::new(&SpamConfig::default()).classify(None, &[]);
+        assert_eq!(score, SpamScore::default());
+    }
+
+    #[test]
+    fn url_density() {
+        let score = classify("Best deals https://a.example https://b.example", &[]);
+        assert_eq!(score.score, URL_DENSITY_SCORE);
+        assert_eq!(score.reasons, ["2 of 4 words in the description are URLs"]);
+    }
+
+    #[test]
+    fn repeated_characters() {
+        let score = classify("Amazing 🔥🔥🔥🔥🔥🔥", &[]);
+        assert_eq!(score.score, REPEATED_CHARS_SCORE);
+        assert_eq!(
+            score.reasons,
+            ["contains a character that is repeated 6 times"]
+        );
+
+        let score = classify("Amazing", &["!!!!!"]);
+        assert_eq!(score.score, REPEATED_CHARS_SCORE);
+
+        let score = classify("Amazing 🔥🔥🔥🔥", &[]);
+        assert_eq!(score, SpamScore::default());
+    }
+
+    #[test]
+    fn spam_phrases() {
+        let score = classify("Get FREE Robux now", &["viagra"]);
+        assert_eq!(score.score, 2 * SPAM_PHRASE_SCORE);
+        assert_eq!(
+            score.reasons,
+            [
+                "contains the known spam phrase `free robux`",
+                "contains the known spam phrase `viagra`",
+            ]
+        );
+
+        let config = SpamConfig {
+            phrases: vec!["Cheap Watches".into()],
+            ..Default::default()
+        };
+        let heuristics = Heuristics::new(&config);
+        let score = heuristics.classify(Some("cheap watches for sale"), &[]);
+        assert_eq!(score.score, SPAM_PHRASE_SCORE);
+    }
+}
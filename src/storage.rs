@@ -1,5 +1,8 @@
+use crate::models::CrateVisibility;
+use crate::registries::{self, DEFAULT_REGISTRY};
 use anyhow::Context;
 use crates_io_env_vars::required_var;
+use futures_util::stream::BoxStream;
 use futures_util::{StreamExt, TryStreamExt};
 use hyper::body::Bytes;
 use object_store::aws::{AmazonS3, AmazonS3Builder};
@@ -16,17 +19,26 @@ use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 
+const PREFIX_AVATARS: &str = "avatars";
 const PREFIX_CRATES: &str = "crates";
+const PREFIX_PRIVATE: &str = "private";
+const PREFIX_QUARANTINE: &str = "quarantine";
 const PREFIX_READMES: &str = "readmes";
+const PREFIX_README_IMAGES: &str = "readme-images";
+const PREFIX_STAGING: &str = "staging";
+const INDEX_CONFIG_PATH: &str = "config.json";
 const DEFAULT_REGION: &str = "us-west-1";
 const CONTENT_TYPE_CRATE: &str = "application/gzip";
 const CONTENT_TYPE_GZIP: &str = "application/gzip";
 const CONTENT_TYPE_ZIP: &str = "application/zip";
 const CONTENT_TYPE_INDEX: &str = "text/plain";
+const CONTENT_TYPE_INDEX_CONFIG: &str = "application/json";
 const CONTENT_TYPE_README: &str = "text/html";
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
+const CACHE_CONTROL_PRIVATE: &str = "private,no-store";
 const CACHE_CONTROL_INDEX: &str = "public,max-age=600";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
+const CACHE_CONTROL_README_IMAGE: &str = "public,max-age=86400";
 
 type StdPath = std::path::Path;
 
@@ -179,17 +191,25 @@ impl Storage {
             StorageBackend::InMemory => {
                 warn!("Using in-memory file storage");
                 let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
-
-                Self {
-                    cdn_prefix,
-                    store: store.clone(),
-                    index_store: Arc::new(PrefixStore::new(store, "index")),
-                    supports_attributes: true,
-                }
+                Self::from_store(store, cdn_prefix)
             }
         }
     }
 
+    /// Creates a storage on top of an arbitrary object store, with the index
+    /// files stored below the `index` prefix.
+    ///
+    /// This is mostly useful for tests, which can wrap an in-memory store to
+    /// simulate failures. The store has to support object attributes.
+    pub fn from_store(store: Arc<dyn ObjectStore>, cdn_prefix: Option<String>) -> Self {
+        Self {
+            cdn_prefix,
+            store: store.clone(),
+            index_store: Arc::new(PrefixStore::new(store, "index")),
+            supports_attributes: true,
+        }
+    }
+
     /// Returns the URL of an uploaded crate's version archive.
     ///
     /// The function doesn't check for the existence of the file.
@@ -197,6 +217,13 @@ impl Storage {
         apply_cdn_prefix(&self.cdn_prefix, &crate_file_path(name, version)).replace('+', "%2B")
     }
 
+    /// Returns the URL below which the crate files are served by the CDN, or
+    /// `None` if no CDN is configured.
+    pub fn crate_files_location(&self) -> Option<String> {
+        self.cdn_prefix.as_ref()?;
+        Some(apply_cdn_prefix(&self.cdn_prefix, &PREFIX_CRATES.into()))
+    }
+
     /// Returns the URL of an uploaded crate's version readme.
     ///
     /// The function doesn't check for the existence of the file.
@@ -204,14 +231,38 @@ impl Storage {
         apply_cdn_prefix(&self.cdn_prefix, &readme_path(name, version)).replace('+', "%2B")
     }
 
+    /// Returns the URL of a cached README image.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn readme_image_location(&self, digest: &str) -> String {
+        apply_cdn_prefix(&self.cdn_prefix, &readme_image_path(digest))
+    }
+
+    /// Returns the URL of a cached user avatar.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn avatar_location(&self, digest: &str) -> String {
+        apply_cdn_prefix(&self.cdn_prefix, &avatar_path(digest))
+    }
+
     /// Returns the URL of an uploaded RSS feed.
     pub fn feed_url(&self, feed_id: &FeedId) -> String {
         apply_cdn_prefix(&self.cdn_prefix, &feed_id.into()).replace('+', "%2B")
     }
 
+    /// Returns the URL of a file of an uploaded database dump.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn db_dump_location(&self, target: &str) -> String {
+        apply_cdn_prefix(&self.cdn_prefix, &target.into())
+    }
+
     #[instrument(skip(self))]
     pub async fn delete_all_crate_files(&self, name: &str) -> Result<()> {
         let prefix = format!("{PREFIX_CRATES}/{name}").into();
+        self.delete_all_with_prefix(&prefix).await?;
+
+        let prefix = format!("{PREFIX_PRIVATE}/{PREFIX_CRATES}/{name}").into();
         self.delete_all_with_prefix(&prefix).await
     }
 
@@ -224,6 +275,19 @@ impl Storage {
     #[instrument(skip(self))]
     pub async fn delete_crate_file(&self, name: &str, version: &str) -> Result<()> {
         let path = crate_file_path(name, version);
+        self.store.delete(&path).await?;
+
+        // The crate might have been private when the file was uploaded.
+        let path = private_crate_file_path(name, version);
+        match self.store.delete(&path).await {
+            Err(object_store::Error::NotFound { .. }) => Ok(()),
+            result => result,
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete_staged_file(&self, id: &str) -> Result<()> {
+        let path = staged_file_path(id);
         self.store.delete(&path).await
     }
 
@@ -239,18 +303,145 @@ impl Storage {
         self.store.delete(&path).await
     }
 
-    #[instrument(skip(self, bytes))]
-    pub async fn upload_crate_file(&self, name: &str, version: &str, bytes: Bytes) -> Result<()> {
+    #[instrument(skip(self))]
+    pub async fn download_crate_file(
+        &self,
+        name: &str,
+        version: &str,
+        visibility: CrateVisibility,
+    ) -> Result<Bytes> {
+        let path = crate_file_path_for(name, version, visibility);
+        self.store.get(&path).await?.bytes().await
+    }
+
+    /// Returns the content of the crate file of a version as a stream, so
+    /// that it can be served through the API instead of the CDN, or `None`
+    /// if the file does not exist.
+    #[instrument(skip(self))]
+    pub async fn stream_crate_file(
+        &self,
+        name: &str,
+        version: &str,
+        visibility: CrateVisibility,
+    ) -> Result<Option<BoxStream<'static, Result<Bytes>>>> {
+        let path = crate_file_path_for(name, version, visibility);
+        match self.store.get(&path).await {
+            Ok(result) => Ok(Some(result.into_stream())),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Moves the crate file of a version to the storage area of the given
+    /// visibility. The files of private crates are stored outside of the
+    /// public `crates/` prefix, so that they can not be downloaded from the
+    /// CDN.
+    ///
+    /// Files that are not found in the other storage area, e.g. because the
+    /// version is quarantined or was moved already, are skipped.
+    #[instrument(skip(self))]
+    pub async fn move_crate_file(
+        &self,
+        name: &str,
+        version: &str,
+        visibility: CrateVisibility,
+    ) -> Result<()> {
+        let (from, to) = match visibility {
+            CrateVisibility::Public => (
+                private_crate_file_path(name, version),
+                crate_file_path(name, version),
+            ),
+            CrateVisibility::Private => (
+                crate_file_path(name, version),
+                private_crate_file_path(name, version),
+            ),
+        };
+
+        match self.store.rename(&from, &to).await {
+            Err(object_store::Error::NotFound { .. }) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Returns whether the crate file of the version exists in the bucket.
+    #[instrument(skip(self))]
+    pub async fn crate_file_exists(&self, name: &str, version: &str) -> Result<bool> {
         let path = crate_file_path(name, version);
+        match self.store.head(&path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn download_staged_file(&self, id: &str) -> Result<Bytes> {
+        let path = staged_file_path(id);
+        self.store.get(&path).await?.bytes().await
+    }
+
+    /// Moves the tarball of a quarantined version out of the public storage
+    /// area, so that it can not be downloaded anymore.
+    #[instrument(skip(self))]
+    pub async fn quarantine_crate_file(
+        &self,
+        name: &str,
+        version: &str,
+        visibility: CrateVisibility,
+    ) -> Result<()> {
+        let path = crate_file_path_for(name, version, visibility);
+        let quarantine_path = quarantine_path(&path);
+        self.store.rename(&path, &quarantine_path).await
+    }
+
+    /// Moves the tarball of a formerly quarantined version back into the
+    /// storage area of its crate.
+    #[instrument(skip(self))]
+    pub async fn release_crate_file(
+        &self,
+        name: &str,
+        version: &str,
+        visibility: CrateVisibility,
+    ) -> Result<()> {
+        let path = crate_file_path_for(name, version, visibility);
+        let quarantine_path = quarantine_path(&path);
+        self.store.rename(&quarantine_path, &path).await
+    }
+
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_crate_file(
+        &self,
+        name: &str,
+        version: &str,
+        visibility: CrateVisibility,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let path = crate_file_path_for(name, version, visibility);
+        let cache_control = match visibility {
+            CrateVisibility::Public => CACHE_CONTROL_IMMUTABLE,
+            CrateVisibility::Private => CACHE_CONTROL_PRIVATE,
+        };
         let attributes = self.attrs([
             (Attribute::ContentType, CONTENT_TYPE_CRATE),
-            (Attribute::CacheControl, CACHE_CONTROL_IMMUTABLE),
+            (Attribute::CacheControl, cache_control),
         ]);
         let opts = attributes.into();
         self.store.put_opts(&path, bytes.into(), opts).await?;
         Ok(())
     }
 
+    /// Stores a tarball that was uploaded ahead of its publish request.
+    ///
+    /// Staged tarballs are not public, and don't need any caching headers.
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_staged_file(&self, id: &str, bytes: Bytes) -> Result<()> {
+        let path = staged_file_path(id);
+        let attributes = self.attrs([(Attribute::ContentType, CONTENT_TYPE_CRATE)]);
+        let opts = attributes.into();
+        self.store.put_opts(&path, bytes.into(), opts).await?;
+        Ok(())
+    }
+
     #[instrument(skip(self, bytes))]
     pub async fn upload_readme(&self, name: &str, version: &str, bytes: Bytes) -> Result<()> {
         let path = readme_path(name, version);
@@ -263,6 +454,41 @@ impl Storage {
         Ok(())
     }
 
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_readme_image(
+        &self,
+        digest: &str,
+        content_type: &str,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let path = readme_image_path(digest);
+        let mut attributes = self.attrs([(Attribute::CacheControl, CACHE_CONTROL_README_IMAGE)]);
+        if self.supports_attributes {
+            attributes.insert(Attribute::ContentType, content_type.to_string().into());
+        }
+        let opts = attributes.into();
+        self.store.put_opts(&path, bytes.into(), opts).await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_avatar(
+        &self,
+        digest: &str,
+        content_type: &str,
+        bytes: Bytes,
+    ) -> Result<()> {
+        // The path contains the digest of the content, so it never changes.
+        let path = avatar_path(digest);
+        let mut attributes = self.attrs([(Attribute::CacheControl, CACHE_CONTROL_IMMUTABLE)]);
+        if self.supports_attributes {
+            attributes.insert(Attribute::ContentType, content_type.to_string().into());
+        }
+        let opts = attributes.into();
+        self.store.put_opts(&path, bytes.into(), opts).await?;
+        Ok(())
+    }
+
     #[instrument(skip(self, channel))]
     pub async fn upload_feed(
         &self,
@@ -282,9 +508,37 @@ impl Storage {
         Ok(())
     }
 
+    /// Returns the contents of the sparse index file of a crate, or `None` if
+    /// the crate has no index file.
+    #[instrument(skip(self))]
+    pub async fn read_index_file(&self, name: &str) -> Result<Option<String>> {
+        let path = crates_io_index::Repository::relative_index_file_for_url(name).into();
+        let bytes = match self.index_store.get(&path).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
     #[instrument(skip(self, content))]
     pub async fn sync_index(&self, name: &str, content: Option<String>) -> Result<()> {
-        let path = crates_io_index::Repository::relative_index_file_for_url(name).into();
+        self.sync_registry_index(DEFAULT_REGISTRY, name, content)
+            .await
+    }
+
+    /// Uploads or deletes the index file of a crate in the index namespace
+    /// of the given registry.
+    #[instrument(skip(self, content))]
+    pub async fn sync_registry_index(
+        &self,
+        registry: &str,
+        name: &str,
+        content: Option<String>,
+    ) -> Result<()> {
+        let path = crates_io_index::Repository::relative_index_file_for_url(name);
+        let path = registries::index_path(registry, &path).into();
         if let Some(content) = content {
             let attributes = self.attrs([
                 (Attribute::ContentType, CONTENT_TYPE_INDEX),
@@ -300,6 +554,35 @@ impl Storage {
         Ok(())
     }
 
+    #[instrument(skip(self, content))]
+    pub async fn sync_index_config(&self, content: String) -> Result<()> {
+        let path = INDEX_CONFIG_PATH.into();
+        let attributes = self.attrs([
+            (Attribute::ContentType, CONTENT_TYPE_INDEX_CONFIG),
+            (Attribute::CacheControl, CACHE_CONTROL_INDEX),
+        ]);
+        let payload = content.into();
+        let opts = attributes.into();
+        self.index_store.put_opts(&path, payload, opts).await?;
+
+        Ok(())
+    }
+
+    /// Uploads the `config.json` file of the sparse index of an additional
+    /// registry.
+    #[instrument(skip(self, content))]
+    pub async fn upload_registry_config(&self, registry: &str, content: String) -> Result<()> {
+        let path = registries::index_path(registry, INDEX_CONFIG_PATH).into();
+        let attributes = self.attrs([
+            (Attribute::ContentType, CONTENT_TYPE_INDEX_CONFIG),
+            (Attribute::CacheControl, CACHE_CONTROL_INDEX),
+        ]);
+        let payload = content.into();
+        let opts = attributes.into();
+        self.index_store.put_opts(&path, payload, opts).await?;
+        Ok(())
+    }
+
     #[instrument(skip(self))]
     pub async fn upload_db_dump(&self, target: &str, local_path: &StdPath) -> anyhow::Result<()> {
         let store = self.store.clone();
@@ -324,6 +607,13 @@ impl Storage {
         Ok(())
     }
 
+    /// Copies an already uploaded database dump file within the storage
+    /// bucket, without downloading and uploading it again.
+    #[instrument(skip(self))]
+    pub async fn copy_db_dump(&self, source: &str, target: &str) -> Result<()> {
+        self.store.copy(&source.into(), &target.into()).await
+    }
+
     /// This should only be used for assertions in the test suite!
     pub fn as_inner(&self) -> Arc<dyn ObjectStore> {
         self.store.clone()
@@ -362,14 +652,41 @@ fn build_s3(config: &S3Config, client_options: ClientOptions) -> AmazonS3 {
         .unwrap()
 }
 
-fn crate_file_path(name: &str, version: &str) -> Path {
+pub(crate) fn crate_file_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_CRATES}/{name}/{name}-{version}.crate").into()
 }
 
+fn private_crate_file_path(name: &str, version: &str) -> Path {
+    format!("{PREFIX_PRIVATE}/{}", crate_file_path(name, version)).into()
+}
+
+fn crate_file_path_for(name: &str, version: &str, visibility: CrateVisibility) -> Path {
+    match visibility {
+        CrateVisibility::Public => crate_file_path(name, version),
+        CrateVisibility::Private => private_crate_file_path(name, version),
+    }
+}
+
+fn staged_file_path(id: &str) -> Path {
+    format!("{PREFIX_STAGING}/{id}.crate").into()
+}
+
+fn quarantine_path(path: &Path) -> Path {
+    format!("{PREFIX_QUARANTINE}/{path}").into()
+}
+
 fn readme_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_READMES}/{name}/{name}-{version}.html").into()
 }
 
+fn readme_image_path(digest: &str) -> Path {
+    format!("{PREFIX_README_IMAGES}/{digest}").into()
+}
+
+fn avatar_path(digest: &str) -> Path {
+    format!("{PREFIX_AVATARS}/{digest}").into()
+}
+
 fn apply_cdn_prefix(cdn_prefix: &Option<String>, path: &Path) -> String {
     match cdn_prefix {
         Some(cdn_prefix) if !cdn_prefix.starts_with("https://") => {
@@ -461,6 +778,16 @@ mod tests {
         for (name, version, expected) in readme_tests {
             assert_eq!(storage.readme_location(name, version), expected);
         }
+
+        assert_eq!(
+            storage.readme_image_location("0123abcd"),
+            "https://static.crates.io/readme-images/0123abcd"
+        );
+
+        assert_eq!(
+            storage.avatar_location("0123abcd"),
+            "https://static.crates.io/avatars/0123abcd"
+        );
     }
 
     #[test]
@@ -535,6 +862,82 @@ mod tests {
         assert_eq!(stored_files(&storage.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn quarantine_and_release_crate_file() {
+        let storage = prepare().await;
+
+        let public = CrateVisibility::Public;
+        storage
+            .quarantine_crate_file("foo", "1.2.3", public)
+            .await
+            .unwrap();
+
+        let expected_files = vec![
+            "crates/bar/bar-2.0.0.crate",
+            "crates/foo/foo-1.0.0.crate",
+            "quarantine/crates/foo/foo-1.2.3.crate",
+            "readmes/bar/bar-2.0.0.html",
+            "readmes/foo/foo-1.0.0.html",
+            "readmes/foo/foo-1.2.3.html",
+        ];
+        assert_eq!(stored_files(&storage.store).await, expected_files);
+        assert_err!(storage.download_crate_file("foo", "1.2.3", public).await);
+
+        storage
+            .release_crate_file("foo", "1.2.3", public)
+            .await
+            .unwrap();
+
+        let expected_files = vec![
+            "crates/bar/bar-2.0.0.crate",
+            "crates/foo/foo-1.0.0.crate",
+            "crates/foo/foo-1.2.3.crate",
+            "readmes/bar/bar-2.0.0.html",
+            "readmes/foo/foo-1.0.0.html",
+            "readmes/foo/foo-1.2.3.html",
+        ];
+        assert_eq!(stored_files(&storage.store).await, expected_files);
+        assert_ok!(storage.download_crate_file("foo", "1.2.3", public).await);
+    }
+
+    #[tokio::test]
+    async fn move_crate_file() {
+        let storage = prepare().await;
+        let private = CrateVisibility::Private;
+
+        storage
+            .move_crate_file("foo", "1.2.3", private)
+            .await
+            .unwrap();
+
+        let expected_files = vec![
+            "crates/bar/bar-2.0.0.crate",
+            "crates/foo/foo-1.0.0.crate",
+            "private/crates/foo/foo-1.2.3.crate",
+            "readmes/bar/bar-2.0.0.html",
+            "readmes/foo/foo-1.0.0.html",
+            "readmes/foo/foo-1.2.3.html",
+        ];
+        assert_eq!(stored_files(&storage.store).await, expected_files);
+        assert_ok!(storage.download_crate_file("foo", "1.2.3", private).await);
+
+        // Files that were moved already are skipped
+        storage
+            .move_crate_file("foo", "1.2.3", private)
+            .await
+            .unwrap();
+
+        storage.delete_all_crate_files("foo").await.unwrap();
+
+        let expected_files = vec![
+            "crates/bar/bar-2.0.0.crate",
+            "readmes/bar/bar-2.0.0.html",
+            "readmes/foo/foo-1.0.0.html",
+            "readmes/foo/foo-1.2.3.html",
+        ];
+        assert_eq!(stored_files(&storage.store).await, expected_files);
+    }
+
     #[tokio::test]
     async fn delete_readme() {
         let storage = prepare().await;
@@ -611,6 +1014,46 @@ mod tests {
         assert!(stored_files(&s.store).await.is_empty());
     }
 
+    #[tokio::test]
+    async fn sync_registry_index() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let content = "foo".to_string();
+        s.sync_registry_index("internal", "foo", Some(content))
+            .await
+            .unwrap();
+
+        let expected_files = vec!["index/registries/internal/3/f/foo"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+
+        s.sync_registry_index("internal", "foo", None)
+            .await
+            .unwrap();
+
+        assert!(stored_files(&s.store).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn upload_registry_config() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let content = r#"{"dl":"https://crates.io/internal/api/v1/crates"}"#.to_string();
+        s.upload_registry_config("internal", content).await.unwrap();
+
+        let expected_files = vec!["index/registries/internal/config.json"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+    }
+
+    #[tokio::test]
+    async fn sync_index_config() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        s.sync_index_config("{}".to_string()).await.unwrap();
+
+        let expected_files = vec!["index/config.json"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+    }
+
     #[tokio::test]
     async fn upload_db_dump() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -624,4 +1067,19 @@ mod tests {
         let expected_files = vec![target];
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
+
+    #[tokio::test]
+    async fn copy_db_dump() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let source = "db-dumps/2024-10-28-101530/db-dump.tar.gz";
+        let file = NamedTempFile::new().unwrap();
+        s.upload_db_dump(source, file.path()).await.unwrap();
+
+        let target = "db-dump.tar.gz";
+        s.copy_db_dump(source, target).await.unwrap();
+
+        let expected_files = vec![target, source];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+    }
 }
@@ -0,0 +1,217 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::ACCOUNT_RECOVERY_LOCK_REASON;
+use crates_io::schema::{account_recoveries, crate_owners, users};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/account_recoveries";
+const CONFIRM_SUBJECT: &str = "Subject: Please confirm the recovery of your crates.io account";
+const NOTIFICATION_SUBJECT: &str = "Subject: Recovery of your crates.io account";
+
+fn num_mails(app: &TestApp, subject: &str) -> usize {
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    mails.iter().filter(|(_, m)| m.contains(subject)).count()
+}
+
+fn email_token(app: &TestApp, id: i32) -> String {
+    app.db(|conn| {
+        account_recoveries::table
+            .find(id)
+            .select(account_recoveries::email_token)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+async fn request_recovery(requester: &MockCookieUser, login: &str) -> i32 {
+    let body = json!({ "login": login }).to_string();
+    let json = requester.put::<Value>(URL, body).await.good();
+    json["account_recovery"]["id"].as_i64().unwrap() as i32
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recover_account() {
+    let (app, anon, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let previous = app.db_new_user("previous");
+    let requester = app.db_new_user("requester");
+    let requester_gh_id = requester.as_model().gh_id;
+    app.db(|conn| {
+        CrateBuilder::new("foo_recovered", previous.as_model().id).expect_build(conn);
+    });
+
+    let id = request_recovery(&requester, "previous").await;
+    assert_eq!(num_mails(&app, CONFIRM_SUBJECT), 1);
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    let (_, mail) = mails
+        .iter()
+        .find(|(_, m)| m.contains(CONFIRM_SUBJECT))
+        .unwrap();
+    let token = email_token(&app, id);
+    assert!(mail.contains(&format!("/confirm-account-recovery/{token}")));
+
+    // Recoveries can only be approved after they were confirmed via email
+    let approve_url = format!("{URL}/{id}/approve");
+    let response = admin.put::<()>(&approve_url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the recovery has not been confirmed via email yet"}]}"###);
+
+    let response = anon
+        .put::<()>("/api/v1/confirm_account_recovery/invalid", "")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let confirm_url = format!("/api/v1/confirm_account_recovery/{token}");
+    anon.put::<Value>(&confirm_url, "").await.good();
+
+    let complete_url = format!("{URL}/{id}/complete");
+    let response = requester.put::<()>(&complete_url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the recovery has not been approved by the crates.io team yet"}]}"###);
+
+    let json = admin.put::<Value>(&approve_url, "").await.good();
+    assert!(json["account_recovery"]["completable_at"].is_string());
+    assert_eq!(num_mails(&app, NOTIFICATION_SUBJECT), 1);
+
+    // The waiting period has not passed yet
+    let response = requester.put::<()>(&complete_url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    app.db(|conn| {
+        let approved_at = Utc::now().naive_utc() - Duration::days(8);
+        diesel::update(account_recoveries::table.find(id))
+            .set(account_recoveries::approved_at.eq(approved_at))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json = requester.put::<Value>(&complete_url, "").await.good();
+    assert!(json["account_recovery"]["completed_at"].is_string());
+    assert_eq!(num_mails(&app, NOTIFICATION_SUBJECT), 2);
+
+    // The previous account is now linked to the new GitHub identity
+    let json = previous.get::<Value>("/api/v1/me").await.good();
+    assert_eq!(json["user"]["login"], "requester");
+
+    let (gh_id, owned_crates): (i32, i64) = app.db(|conn| {
+        let gh_id = users::table
+            .find(previous.as_model().id)
+            .select(users::gh_id)
+            .get_result(conn)
+            .unwrap();
+        let owned_crates = crate_owners::table
+            .filter(crate_owners::owner_id.eq(previous.as_model().id))
+            .count()
+            .get_result(conn)
+            .unwrap();
+        (gh_id, owned_crates)
+    });
+    assert_eq!(gh_id, requester_gh_id);
+    assert_eq!(owned_crates, 1);
+
+    // The account that requested the recovery is locked
+    let response = requester.get::<()>("/api/v1/me").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let error_message =
+        format!("This account is indefinitely locked. Reason: {ACCOUNT_RECOVERY_LOCK_REASON}");
+    assert_eq!(response.json()["errors"][0]["detail"], error_message);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests() {
+    let (app, _, requester) = TestApp::init().with_user();
+    app.db_new_user("previous");
+
+    let body = json!({ "login": "unknown" }).to_string();
+    let response = requester.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = json!({ "login": "foo" }).to_string();
+    let response = requester.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"you can not recover your own account"}]}"###);
+
+    request_recovery(&requester, "previous").await;
+
+    let body = json!({ "login": "previous" }).to_string();
+    let response = requester.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"there already is an open recovery for the account `previous`"}]}"###);
+
+    // Accounts that own crates can not be used to recover other accounts
+    let owner = app.db_new_user("owner");
+    app.db(|conn| {
+        CrateBuilder::new("foo_owned", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "login": "previous" }).to_string();
+    let response = owner.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"accounts that own crates can not be used to recover another account"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_approve_recoveries() {
+    let (app, anon, requester) = TestApp::init().with_user();
+    app.db_new_user("previous");
+
+    let id = request_recovery(&requester, "previous").await;
+    let token = email_token(&app, id);
+    let confirm_url = format!("/api/v1/confirm_account_recovery/{token}");
+    anon.put::<Value>(&confirm_url, "").await.good();
+
+    let response = requester.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to manage account recoveries"}]}"###);
+
+    let response = requester
+        .put::<()>(&format!("{URL}/{id}/approve"), "")
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let admin = app.db_new_user("admin");
+    admin.make_admin();
+
+    let json = admin.get::<Value>(URL).await.good();
+    let recoveries = json["account_recoveries"].as_array().unwrap();
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(recoveries[0]["user"]["login"], "previous");
+    assert_eq!(recoveries[0]["requested_by"]["login"], "foo");
+    assert!(recoveries[0]["email_confirmed_at"].is_string());
+    assert!(recoveries[0]["approved_at"].is_null());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cancel_recovery() {
+    let (app, _, requester) = TestApp::init().with_user();
+    let previous = app.db_new_user("previous");
+    let other = app.db_new_user("other");
+
+    let id = request_recovery(&requester, "previous").await;
+
+    let cancel_url = format!("{URL}/{id}");
+    let response = other.delete::<()>(&cancel_url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The previous owner of the account can cancel the recovery
+    previous.delete::<Value>(&cancel_url).await.good();
+
+    let response = requester
+        .put::<()>(&format!("{cancel_url}/complete"), "")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let token = email_token(&app, id);
+    let confirm_url = format!("/api/v1/confirm_account_recovery/{token}");
+    let response = requester.put::<()>(&confirm_url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // A new recovery can be requested once the previous one was cancelled
+    request_recovery(&requester, "previous").await;
+}
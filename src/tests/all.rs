@@ -21,26 +21,35 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use diesel::prelude::*;
 
 mod account_lock;
+mod account_recovery;
 mod authentication;
 mod blocked_routes;
 mod builders;
 mod categories;
+mod challenge;
+mod deprecation;
 mod dump_db;
 mod github_secret_scanning;
 mod krate;
+mod load_shedding;
 mod middleware;
 mod models;
 mod not_found_error;
 mod owners;
 mod pagination;
 mod read_only_mode;
+mod registries;
+mod request_limits;
 mod routes;
 mod schema_details;
+mod security_events;
 mod server;
 mod server_binary;
+mod shutdown;
 mod team;
 mod token;
 mod unhealthy_database;
+mod upstream;
 mod user;
 mod util;
 mod version;
@@ -105,6 +114,7 @@ fn new_user(login: &str) -> NewUser<'_> {
         name: None,
         gh_avatar: None,
         gh_access_token: "some random token",
+        gh_two_factor: false,
     }
 }
 
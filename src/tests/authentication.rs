@@ -1,7 +1,10 @@
-use crate::util::{MockRequestExt, RequestHelper, Response};
+use crate::util::{MockCookieUser, MockRequestExt, RequestHelper, Response};
 use crate::TestApp;
 
 use crate::util::encode_session_header;
+use chrono::{Duration, Utc};
+use crates_io::schema::persistent_sessions;
+use diesel::prelude::*;
 use http::{header, Method, StatusCode};
 use insta::assert_snapshot;
 
@@ -43,3 +46,128 @@ async fn cookie_auth_cannot_find_user() {
     let error = anon.run::<()>(request).await;
     assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
 }
+
+fn age_session(app: &TestApp, user: &MockCookieUser, created_days: i64, used_days: i64) {
+    let now = Utc::now().naive_utc();
+    app.db(|conn| {
+        diesel::update(persistent_sessions::table)
+            .filter(persistent_sessions::user_id.eq(user.as_model().id))
+            .set((
+                persistent_sessions::created_at.eq(now - Duration::days(created_days)),
+                persistent_sessions::last_used_at.eq(now - Duration::days(used_days)),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_auth_with_expired_session() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    age_session(&app, &user, 89, 1);
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    age_session(&app, &user, 91, 1);
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_auth_with_idle_session() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    age_session(&app, &user, 31, 31);
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_auth_marks_session_as_used() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    age_session(&app, &user, 29, 29);
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let last_used_at = app.db(|conn| {
+        persistent_sessions::table
+            .filter(persistent_sessions::user_id.eq(user.as_model().id))
+            .select(persistent_sessions::last_used_at)
+            .get_result::<chrono::NaiveDateTime>(conn)
+            .unwrap()
+    });
+    assert!(last_used_at > Utc::now().naive_utc() - Duration::days(1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_auth_throttles_session_usage_updates() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let recently = Utc::now().naive_utc() - Duration::minutes(10);
+    app.db(|conn| {
+        diesel::update(persistent_sessions::table)
+            .filter(persistent_sessions::user_id.eq(user.as_model().id))
+            .set(persistent_sessions::last_used_at.eq(recently))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let last_used_at = app.db(|conn| {
+        persistent_sessions::table
+            .filter(persistent_sessions::user_id.eq(user.as_model().id))
+            .select(persistent_sessions::last_used_at)
+            .get_result::<chrono::NaiveDateTime>(conn)
+            .unwrap()
+    });
+    assert!(last_used_at < Utc::now().naive_utc() - Duration::minutes(5));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_auth_without_session_token() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    // Cookies that were issued before sessions were stored server-side only
+    // contain the user ID
+    let session_key = app.as_inner().session_key();
+    let cookie = encode_session_header(session_key, user.as_model().id);
+
+    let mut request = anon.request_builder(Method::GET, URL);
+    request.header(header::COOKIE, &cookie);
+
+    let response: Response<()> = anon.run(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A persistent session is created on first use, and its token is added
+    // to the cookie
+    assert!(response.headers().contains_key(header::SET_COOKIE));
+
+    let sessions: i64 = app.db(|conn| {
+        persistent_sessions::table
+            .filter(persistent_sessions::user_id.eq(user.as_model().id))
+            .count()
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_eq!(sessions, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn logout_revokes_session() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response: Response<()> = user.delete("/api/private/session").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Replaying the cookie from before signing out is rejected
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
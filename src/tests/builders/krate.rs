@@ -56,6 +56,12 @@ impl<'a> CrateBuilder<'a> {
         self
     }
 
+    /// Sets the crate's `repository` URL.
+    pub fn repository(mut self, repository: &'a str) -> Self {
+        self.krate.repository = Some(repository);
+        self
+    }
+
     /// Sets the crate's `homepage` URL.
     pub fn homepage(mut self, homepage: &'a str) -> Self {
         self.krate.homepage = Some(homepage);
@@ -118,6 +124,16 @@ impl<'a> CrateBuilder<'a> {
         self
     }
 
+    pub fn max_dependencies(mut self, max_dependencies: i16) -> Self {
+        self.krate.max_dependencies = Some(max_dependencies);
+        self
+    }
+
+    pub fn max_feature_values(mut self, max_feature_values: i16) -> Self {
+        self.krate.max_feature_values = Some(max_feature_values);
+        self
+    }
+
     pub fn build(mut self, connection: &mut PgConnection) -> AppResult<Crate> {
         use diesel::{insert_into, select, update};
 
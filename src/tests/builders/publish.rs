@@ -12,7 +12,9 @@ use super::DependencyBuilder;
 /// a crate to exist and don't need to test behavior caused by the publish request, inserting
 /// a crate into the database directly by using CrateBuilder will be faster.
 pub struct PublishBuilder {
+    badges: BTreeMap<String, BTreeMap<String, String>>,
     categories: Vec<String>,
+    channel: Option<String>,
     deps: Vec<u::EncodableCrateDependency>,
     desc: Option<String>,
     doc_url: Option<String>,
@@ -22,7 +24,9 @@ pub struct PublishBuilder {
     license: Option<String>,
     license_file: Option<String>,
     manifest: Manifest,
+    normalized_cksum: Option<String>,
     readme: Option<String>,
+    staging_id: Option<String>,
     version: semver::Version,
     features: BTreeMap<String, Vec<String>>,
 }
@@ -38,7 +42,9 @@ impl PublishBuilder {
     /// in its tarball.
     pub fn new(krate_name: &str, version: &str) -> Self {
         PublishBuilder {
+            badges: BTreeMap::new(),
             categories: vec![],
+            channel: None,
             deps: vec![],
             desc: Some("description".to_string()),
             doc_url: None,
@@ -48,7 +54,9 @@ impl PublishBuilder {
             license: Some("MIT".to_string()),
             license_file: None,
             manifest: Manifest::Generated,
+            normalized_cksum: None,
             readme: None,
+            staging_id: None,
             version: semver::Version::parse(version).unwrap(),
             features: BTreeMap::new(),
         }
@@ -91,6 +99,31 @@ impl PublishBuilder {
         self
     }
 
+    /// Add a badge to the publish metadata of this crate.
+    pub fn badge(mut self, name: &str) -> Self {
+        self.badges.insert(name.into(), BTreeMap::new());
+        self
+    }
+
+    /// Set the release channel of this version in the publish metadata.
+    pub fn channel(mut self, channel: &str) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    /// Set the checksum of the normalized tarball in the publish metadata.
+    pub fn normalized_cksum(mut self, normalized_cksum: &str) -> Self {
+        self.normalized_cksum = Some(normalized_cksum.into());
+        self
+    }
+
+    /// Reference a tarball that was uploaded via `POST /api/v1/staging` in the
+    /// publish metadata.
+    pub fn staging_id(mut self, staging_id: &str) -> Self {
+        self.staging_id = Some(staging_id.into());
+        self
+    }
+
     /// Add a category to this crate. Make sure the category already exists in the
     /// database or it will be ignored.
     pub fn category(mut self, slug: &str) -> Self {
@@ -144,6 +177,10 @@ impl PublishBuilder {
             vers: self.version.to_string(),
             readme: self.readme,
             readme_file: None,
+            badges: self.badges,
+            channel: self.channel,
+            normalized_cksum: self.normalized_cksum,
+            staging_id: self.staging_id,
         };
 
         let mut tarball_builder = TarballBuilder::new();
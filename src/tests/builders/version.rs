@@ -17,10 +17,13 @@ pub struct VersionBuilder {
     license: Option<String>,
     num: semver::Version,
     size: i32,
+    uncompressed_size: Option<i64>,
     yanked: bool,
     checksum: String,
     links: Option<String>,
     rust_version: Option<String>,
+    channel: Option<String>,
+    normalized_checksum: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -43,10 +46,13 @@ impl VersionBuilder {
             license: None,
             num,
             size: 0,
+            uncompressed_size: None,
             yanked: false,
             checksum: String::new(),
             links: None,
             rust_version: None,
+            channel: None,
+            normalized_checksum: None,
         }
     }
 
@@ -85,12 +91,30 @@ impl VersionBuilder {
         self
     }
 
+    /// Sets the version's `uncompressed_size` value.
+    pub fn uncompressed_size(mut self, uncompressed_size: i64) -> Self {
+        self.uncompressed_size = Some(uncompressed_size);
+        self
+    }
+
     /// Sets the version's `rust_version` value.
     pub fn rust_version(mut self, rust_version: &str) -> Self {
         self.rust_version = Some(rust_version.to_owned());
         self
     }
 
+    /// Sets the version's `channel` value.
+    pub fn channel(mut self, channel: &str) -> Self {
+        self.channel = Some(channel.to_owned());
+        self
+    }
+
+    /// Sets the version's `normalized_checksum` value.
+    pub fn normalized_checksum(mut self, normalized_checksum: &str) -> Self {
+        self.normalized_checksum = Some(normalized_checksum.to_owned());
+        self
+    }
+
     pub fn build(
         self,
         crate_id: i32,
@@ -107,6 +131,8 @@ impl VersionBuilder {
             .checksum(self.checksum)
             .links(self.links)
             .rust_version(self.rust_version)
+            .channel(self.channel)
+            .normalized_checksum(self.normalized_checksum)
             .build()
             .map_err(|error| internal(error.to_string()))?;
 
@@ -118,6 +144,12 @@ impl VersionBuilder {
                 .get_result(connection)?;
         }
 
+        if let Some(uncompressed_size) = self.uncompressed_size {
+            vers = update(&vers)
+                .set(versions::uncompressed_size.eq(uncompressed_size))
+                .get_result(connection)?;
+        }
+
         if let Some(created_at) = self.created_at {
             vers = update(&vers)
                 .set(versions::created_at.eq(created_at))
@@ -0,0 +1,103 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockAnonymousUser, MockRequestExt, RequestHelper, TestApp};
+use crates_io::challenge::ChallengeProvider;
+use http::{Method, StatusCode};
+use insta::assert_snapshot;
+use sha2::{Digest, Sha256};
+
+const ROUTE: &str = "/api/v1/crates/:crate_id";
+const PATH: &str = "/api/v1/crates/foo";
+const DIFFICULTY: u32 = 4;
+
+fn challenged_app() -> (TestApp, MockAnonymousUser) {
+    TestApp::init()
+        .with_config(|config| {
+            config.challenge.routes.insert(ROUTE.into());
+            config.challenge.provider = ChallengeProvider::ProofOfWork {
+                difficulty: DIFFICULTY,
+            };
+        })
+        .empty()
+}
+
+fn solve(path: &str) -> String {
+    let timestamp = chrono::Utc::now().timestamp();
+    (0..)
+        .map(|nonce| format!("{timestamp}:{nonce}"))
+        .find(|solution| {
+            let hash = Sha256::digest(format!("{solution}:{path}"));
+            hash[0] >> (8 - DIFFICULTY) == 0
+        })
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unchallenged_routes_do_not_require_a_solution() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let response = anon.get::<()>(PATH).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn anonymous_requests_require_a_solution() {
+    let (app, anon) = challenged_app();
+    let user = app.db_new_user("foo");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let response = anon.get::<()>(PATH).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.headers()["x-challenge"],
+        "proof-of-work; difficulty=4"
+    );
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"This route requires solving a challenge. Please send the solution in the `x-challenge-response` header, or log in with an account that has published a crate."}]}"###);
+
+    let mut request = anon.request_builder(Method::GET, PATH);
+    request.header("x-challenge-response", "1:2");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let solution = solve(PATH);
+
+    let mut request = anon.request_builder(Method::GET, PATH);
+    request.header("x-challenge-response", &solution);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Solutions can only be used once
+    let mut request = anon.request_builder(Method::GET, PATH);
+    request.header("x-challenge-response", &solution);
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn established_users_bypass_the_challenge() {
+    let (app, _) = challenged_app();
+    let user = app.db_new_user("foo");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    // Users without any published versions are challenged
+    let response = user.get::<()>(PATH).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    app.db(|conn| {
+        CrateBuilder::new("bar", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let response = user.get::<()>(PATH).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
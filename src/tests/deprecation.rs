@@ -0,0 +1,137 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockCookieUser, MockTokenUser, RequestHelper, TestApp};
+use chrono::{TimeZone, Utc};
+use crates_io::deprecation::{default_deprecations, Deprecation};
+use crates_io::schema::users;
+use diesel::prelude::*;
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const AUTHORS_URL: &str = "/api/v1/crates/foo/1.0.0/authors";
+
+fn deprecated_letter_param() -> Deprecation {
+    Deprecation {
+        route: "/api/v1/crates".into(),
+        param: Some("letter".into()),
+        deprecated_at: Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap(),
+        sunset_at: Some(Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap()),
+        link: "https://example.com/letter".into(),
+    }
+}
+
+fn app() -> (TestApp, MockCookieUser, MockTokenUser) {
+    let (app, _, user, token) = TestApp::init()
+        .with_config(|config| {
+            config.deprecations = default_deprecations();
+            config.deprecations.push(deprecated_letter_param());
+        })
+        .with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    (app, user, token)
+}
+
+fn num_deprecated_requests(app: &TestApp, surface: &str) -> u64 {
+    let metrics = &app.as_inner().instance_metrics;
+    let counter = &metrics.deprecated_requests_total;
+    counter.with_label_values(&[surface]).get()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deprecated_routes_have_deprecation_headers() {
+    let (app, _, token) = app();
+
+    let response = token.get::<()>(AUTHORS_URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "@1616371200");
+    assert!(!response.headers().contains_key("sunset"));
+    assert_eq!(
+        response.headers()[header::LINK],
+        r#"<https://github.com/rust-lang/rfcs/pull/3052>; rel="deprecation"; type="text/html""#
+    );
+
+    let response = token.get::<()>("/api/v1/crates/foo/1.0.0").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("deprecation"));
+
+    let surface = "/api/v1/crates/:crate_id/:version/authors";
+    assert_eq!(num_deprecated_requests(&app, surface), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deprecated_params_have_deprecation_headers() {
+    let (app, _, token) = app();
+
+    let response = token
+        .get_with_query::<()>("/api/v1/crates", "letter=f")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "@1748736000");
+    assert_eq!(
+        response.headers()["sunset"],
+        "Mon, 01 Dec 2025 00:00:00 GMT"
+    );
+
+    let response = token.get_with_query::<()>("/api/v1/crates", "q=f").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("deprecation"));
+
+    assert_eq!(num_deprecated_requests(&app, "/api/v1/crates?letter"), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_view_report() {
+    let (_, user, _) = app();
+
+    let response = user.get::<()>("/api/private/deprecations").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to view the deprecation report"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn report_lists_token_usage() {
+    let (app, user, token) = app();
+
+    // Requests that are not authenticated with a token are only counted in
+    // the metrics
+    user.get::<()>(AUTHORS_URL).await;
+    token.get::<()>(AUTHORS_URL).await;
+    token.get::<()>(AUTHORS_URL).await;
+
+    app.db(|conn| {
+        diesel::update(user.as_model())
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = user.get::<Value>("/api/private/deprecations").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = response.json();
+    let deprecations = json["deprecations"].as_array().unwrap();
+    assert_eq!(deprecations.len(), 2);
+
+    let authors = &deprecations[0];
+    assert_eq!(
+        authors["surface"],
+        "/api/v1/crates/:crate_id/:version/authors"
+    );
+    assert_eq!(authors["requests"], 2);
+    assert_eq!(authors["usage"].as_array().unwrap().len(), 1);
+    assert_eq!(authors["usage"][0]["user"]["login"], "foo");
+    assert_eq!(authors["usage"][0]["token_name"], "bar");
+    assert_eq!(authors["usage"][0]["requests"], 2);
+
+    let letter = &deprecations[1];
+    assert_eq!(letter["surface"], "/api/v1/crates?letter");
+    assert_eq!(letter["sunset_at"], "2025-12-01T00:00:00+00:00");
+    assert_eq!(letter["requests"], 0);
+    assert_eq!(letter["usage"].as_array().unwrap().len(), 0);
+}
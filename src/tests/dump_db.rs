@@ -1,12 +1,17 @@
-use crate::builders::CrateBuilder;
-use crate::util::TestApp;
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
 use bytes::Buf;
-use crates_io::worker::jobs::{dump_db, DumpDb};
+use crates_io::models::{DatabaseDump, DatabaseDumpDelta};
+use crates_io::worker::jobs::dump_db::{DeltaChain, DeltaRecord};
+use crates_io::worker::jobs::{dump_db, DumpDb, DumpDbDelta};
 use crates_io_test_db::TestDatabase;
 use crates_io_worker::BackgroundJob;
 use flate2::read::GzDecoder;
+use hex::ToHex;
 use insta::{assert_debug_snapshot, assert_snapshot};
 use regex::Regex;
+use sha2::{Digest, Sha256};
 use std::io::{Cursor, Read};
 use std::sync::LazyLock;
 use tar::Archive;
@@ -14,6 +19,9 @@ use tar::Archive;
 static PATH_DATE_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}-\d{6}").unwrap());
 
+static DIR_DATE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"/\d{4}-\d{2}-\d{2}-\d{6}/").unwrap());
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_dump_db_job() {
     let (app, _, _, token) = TestApp::full().with_token();
@@ -26,15 +34,51 @@ async fn test_dump_db_job() {
 
     app.run_pending_background_jobs().await;
 
-    assert_snapshot!(app.stored_files().await.join("\n"), @r###"
+    let stored_files = app
+        .stored_files()
+        .await
+        .into_iter()
+        .map(|path| {
+            DIR_DATE_RE
+                .replace(&path, "/YYYY-MM-DD-HHMMSS/")
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_snapshot!(stored_files.join("\n"), @r###"
     db-dump.tar.gz
     db-dump.zip
+    db-dumps/YYYY-MM-DD-HHMMSS/db-dump.tar.gz
+    db-dumps/YYYY-MM-DD-HHMMSS/db-dump.zip
+    db-dumps/YYYY-MM-DD-HHMMSS/manifest.json
     "###);
 
+    let dumps = app.db(|conn| DatabaseDump::recent(conn, 10).unwrap());
+    assert_eq!(dumps.len(), 1);
+    let dump = &dumps[0];
+    assert!(dump.directory.starts_with("db-dumps/"));
+
+    let path = format!("{}/manifest.json", dump.directory);
+    let path = object_store::path::Path::parse(path).unwrap();
+    let result = app.as_inner().storage.as_inner().get(&path).await.unwrap();
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&result.bytes().await.unwrap()).unwrap();
+    assert_eq!(manifest["files"][0]["path"], "db-dump.tar.gz");
+    assert_eq!(manifest["files"][0]["size"], dump.tar_size);
+    assert_eq!(manifest["files"][0]["sha256"], dump.tar_sha256);
+    assert_eq!(manifest["files"][1]["path"], "db-dump.zip");
+    assert_eq!(manifest["files"][1]["size"], dump.zip_size);
+    assert_eq!(manifest["files"][1]["sha256"], dump.zip_sha256);
+
     let path = object_store::path::Path::parse("db-dump.tar.gz").unwrap();
     let result = app.as_inner().storage.as_inner().get(&path).await.unwrap();
     let bytes = result.bytes().await.unwrap();
 
+    assert_eq!(bytes.len() as i64, dump.tar_size);
+    assert_eq!(
+        Sha256::digest(&bytes).encode_hex::<String>(),
+        dump.tar_sha256
+    );
+
     let gz = GzDecoder::new(bytes.reader());
     let mut tar = Archive::new(gz);
 
@@ -43,6 +87,7 @@ async fn test_dump_db_job() {
     [
         "YYYY-MM-DD-HHMMSS",
         "YYYY-MM-DD-HHMMSS/README.md",
+        "YYYY-MM-DD-HHMMSS/checksums.sha256",
         "YYYY-MM-DD-HHMMSS/export.sql",
         "YYYY-MM-DD-HHMMSS/import.sql",
         "YYYY-MM-DD-HHMMSS/metadata.json",
@@ -75,6 +120,7 @@ async fn test_dump_db_job() {
     assert_debug_snapshot!(zip_paths, @r###"
     [
         "README.md",
+        "checksums.sha256",
         "export.sql",
         "import.sql",
         "metadata.json",
@@ -99,6 +145,87 @@ async fn test_dump_db_job() {
     "###);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dump_db_delta_job() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    // Without a full dump, no delta is created
+    app.db(|conn| DumpDbDelta.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+    assert!(app.stored_files().await.is_empty());
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    app.db(|conn| DumpDb.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let dump = app.db(|conn| DatabaseDump::recent(conn, 1).unwrap().remove(0));
+    assert!(dump.last_event_id > 0);
+
+    // Without any changes since the dump, no delta is created
+    app.db(|conn| DumpDbDelta.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+    let deltas = app.db(|conn| DatabaseDumpDelta::for_dump(conn, dump.id).unwrap());
+    assert!(deltas.is_empty());
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0");
+    token.publish_crate(crate_to_publish).await.good();
+    token.yank("foo", "1.0.0").await.good();
+
+    app.db(|conn| DumpDbDelta.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let deltas = app.db(|conn| DatabaseDumpDelta::for_dump(conn, dump.id).unwrap());
+    assert_eq!(deltas.len(), 1);
+    let delta = &deltas[0];
+    assert_eq!(delta.from_event_id, dump.last_event_id);
+    let prefix = format!("{}/deltas/", dump.directory);
+    assert!(delta.path.starts_with(&prefix));
+
+    let manifest = get_file(&app, &format!("{}/deltas.json", dump.directory)).await;
+    let chain: DeltaChain = serde_json::from_slice(&manifest).unwrap();
+    assert_eq!(chain.last_event_id, dump.last_event_id);
+    assert_eq!(chain.deltas.len(), 1);
+    assert_eq!(chain.deltas[0].to_event_id, delta.to_event_id);
+    assert_eq!(chain.deltas[0].sha256, delta.sha256);
+
+    let bytes = get_file(&app, &delta.path).await;
+    assert_eq!(bytes.len() as i64, delta.size);
+
+    let mut content = String::new();
+    GzDecoder::new(bytes.reader())
+        .read_to_string(&mut content)
+        .unwrap();
+    let records = content
+        .lines()
+        .map(|line| serde_json::from_str::<DeltaRecord>(line).unwrap())
+        .collect::<Vec<_>>();
+
+    let summary = records
+        .iter()
+        .map(|record| {
+            let version = record.version.as_deref().unwrap_or_default();
+            format!("{} {}@{version}", record.kind, record.krate)
+        })
+        .collect::<Vec<_>>();
+    assert_debug_snapshot!(summary, @r###"
+    [
+        "publish foo@1.1.0",
+        "yank foo@1.0.0",
+    ]
+    "###);
+
+    let published = records[0].published.as_ref().unwrap();
+    assert_eq!(published.checksum.len(), 64);
+}
+
+async fn get_file(app: &TestApp, path: &str) -> bytes::Bytes {
+    let path = object_store::path::Path::parse(path).unwrap();
+    let result = app.as_inner().storage.as_inner().get(&path).await.unwrap();
+    result.bytes().await.unwrap()
+}
+
 fn tar_paths<R: Read>(archive: &mut Archive<R>) -> Vec<String> {
     archive
         .entries()
@@ -0,0 +1,113 @@
+use crate::builders::{DependencyBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use bytes::{Bytes, BytesMut};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/crates/batch";
+
+fn batch_body(crates: impl IntoIterator<Item = PublishBuilder>) -> Bytes {
+    let mut body = BytesMut::new();
+    for krate in crates {
+        body.extend_from_slice(&krate.body());
+    }
+    body.freeze()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crates_are_published_in_dependency_order() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    let body = batch_body([
+        PublishBuilder::new("foo_app", "1.0.0")
+            .dependency(DependencyBuilder::new("foo_lib").version_req("^1.0.0")),
+        PublishBuilder::new("foo_lib", "1.0.0"),
+    ]);
+
+    let json = token.put::<Value>(URL, body).await.good();
+    let names = json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["crate"]["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["foo_lib", "foo_app"]);
+
+    app.run_pending_background_jobs().await;
+
+    let index = app.crates_from_index_head("foo_app");
+    assert_eq!(index[0].deps[0].name, "foo_lib");
+    assert_eq!(app.crates_from_index_head("foo_lib").len(), 1);
+
+    // Both crates were added to the index in a single commit
+    let commits = app.upstream_index().list_commits().unwrap();
+    assert_snapshot!(commits.last().unwrap(), @r###"
+    Update 2 crates
+
+    - Create crate `foo_lib`
+    - Create crate `foo_app`
+    "###);
+
+    anon.get::<Value>("/api/v1/crates/foo_app").await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batches_are_published_atomically() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    let body = batch_body([
+        PublishBuilder::new("foo_lib", "1.0.0"),
+        PublishBuilder::new("foo_app", "1.0.0")
+            .dependency(DependencyBuilder::new("foo_lib"))
+            .unset_description(),
+    ]);
+
+    let response = token.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/crates/foo_lib").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cross_dependencies_are_validated() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let body = batch_body([
+        PublishBuilder::new("foo_app", "1.0.0")
+            .dependency(DependencyBuilder::new("foo_lib").version_req("^2.0.0")),
+        PublishBuilder::new("foo_lib", "1.0.0"),
+    ]);
+
+    let response = token.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`foo_app` depends on `foo_lib` ^2.0.0, which does not match version 1.0.0 of the batch"}]}"###);
+
+    let body = batch_body([
+        PublishBuilder::new("foo_a", "1.0.0").dependency(DependencyBuilder::new("foo_b")),
+        PublishBuilder::new("foo_b", "1.0.0").dependency(DependencyBuilder::new("foo_a")),
+    ]);
+
+    let response = token.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the crates `foo_a`, `foo_b` of the batch depend on each other, so they can not be published in any order"}]}"###);
+
+    let body = batch_body([
+        PublishBuilder::new("foo_lib", "1.0.0"),
+        PublishBuilder::new("foo_lib", "1.0.1"),
+    ]);
+
+    let response = token.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the batch contains the crate `foo_lib` more than once"}]}"###);
+
+    let response = token.put::<()>(URL, Bytes::new()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the batch does not contain any crates"}]}"###);
+
+    assert!(app.stored_files().await.is_empty());
+}
@@ -0,0 +1,54 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use googletest::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_to_channel() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0-beta.1").channel("beta");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let json: Value = anon.get("/api/v1/crates/foo/1.0.0-beta.1").await.good();
+    assert_eq!(json["version"]["channel"], "beta");
+
+    let crates = app.crates_from_index_head("foo");
+    assert_eq!(crates[0].channel.as_deref(), Some("beta"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_without_channel() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let json: Value = anon.get("/api/v1/crates/foo/1.0.0").await.good();
+    assert!(json["version"].get("channel").is_none());
+
+    let crates = app.crates_from_index_head("foo");
+    assert_eq!(crates[0].channel, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_channel() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").channel("Beta Train");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `channel` value `Beta Train`: channel names must start with a lowercase letter and only contain lowercase letters, digits and `-`"}]}"###);
+
+    let channel = "a".repeat(33);
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").channel(&channel);
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The `channel` is too long. A maximum of 32 characters are currently allowed."}]}"###);
+
+    assert_that!(app.stored_files().await, empty());
+}
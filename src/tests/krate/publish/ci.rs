@@ -0,0 +1,91 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, MockTokenUser, RequestHelper, Response, TestApp};
+use crates_io::schema::pending_publishes;
+use diesel::{QueryDsl, RunQueryDsl};
+use http::{Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::json;
+
+const RUN_URL: &str = "https://github.com/rust-lang/foo/actions/runs/123";
+
+async fn publish_from_ci(
+    token: &MockTokenUser,
+    crate_to_publish: PublishBuilder,
+    system: &str,
+    run_url: &str,
+) -> Response<()> {
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = crate_to_publish.body();
+    request.header("x-cargo-ci-system", system);
+    request.header("x-cargo-ci-run-url", run_url);
+
+    let response = token.run(request).await;
+    token.app().run_pending_background_jobs().await;
+    response
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_ci_annotation() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_from_ci(&token, crate_to_publish, "GitHub-Actions", RUN_URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = anon.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    assert_eq!(
+        json["version"]["ci"],
+        json!({ "system": "github-actions", "run_url": RUN_URL })
+    );
+
+    // Versions that were not published from CI don't have an annotation
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let json = anon.get::<()>("/api/v1/crates/foo/1.1.0").await.json();
+    assert!(json["version"].get("ci").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_invalid_ci_annotation() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let run_url = "http://ci.example.com/runs/123";
+    let response = publish_from_ci(&token, crate_to_publish, "jenkins", run_url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid CI run URL `http://ci.example.com/runs/123`. The `X-Cargo-CI-Run-URL` header must contain an https:// URL that is not longer than 512 characters."}]}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn held_publish_keeps_ci_annotation() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+
+    let body = json!({ "publish_confirmation_required": true }).to_string();
+    let response = user.put::<()>("/api/v1/me/publish_settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_from_ci(&token, crate_to_publish, "github-actions", RUN_URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let pending_token: String = app.db(|conn| {
+        pending_publishes::table
+            .select(pending_publishes::token)
+            .get_result(conn)
+            .unwrap()
+    });
+
+    let url = format!("/api/v1/confirm_publish/{pending_token}");
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = anon.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    assert_eq!(
+        json["version"]["ci"],
+        json!({ "system": "github-actions", "run_url": RUN_URL })
+    );
+}
@@ -0,0 +1,119 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crates_io::schema::{api_tokens, pending_publishes};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use googletest::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+async fn require_confirmation(user: &MockCookieUser) {
+    let body = json!({ "publish_confirmation_required": true }).to_string();
+    let response = user.put::<()>("/api/v1/me/publish_settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+fn pending_token(app: &TestApp) -> String {
+    app.db(|conn| {
+        pending_publishes::table
+            .select(pending_publishes::token)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_publish_is_held_until_confirmed() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    require_confirmation(&user).await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.json()["warnings"]["other"][0], @r###""Publishing foo v1.0.0 requires confirmation. Please click the link in the email that was sent to something@example.com to complete the publish. The link expires in 24 hours.""###);
+
+    assert_that!(app.stored_files().await, empty());
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let url = format!("/api/v1/confirm_publish/{}", pending_token(&app));
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["crate"]["name"], "foo");
+    app.run_pending_background_jobs().await;
+
+    assert_that!(app.stored_files().await, not(empty()));
+    assert_eq!(app.crates_from_index_head("foo").len(), 1);
+
+    // The confirmation token can only be used once
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Pending publish belonging to token not found."}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_publish_is_not_held() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    require_confirmation(&user).await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").unset_description();
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0").no_manifest())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let pending: i64 = app.db(|conn| pending_publishes::table.count().get_result(conn).unwrap());
+    assert_eq!(pending, 0);
+    assert_that!(app.as_inner().emails.mails_in_memory().unwrap(), empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cookie_publish_is_not_held() {
+    let (app, _, user) = TestApp::full().with_user();
+    require_confirmation(&user).await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["crate"]["name"], "foo");
+
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn confirmation_fails_for_revoked_token() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    require_confirmation(&user).await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.db(|conn| {
+        diesel::update(api_tokens::table)
+            .set(api_tokens::revoked.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let url = format!("/api/v1/confirm_publish/{}", pending_token(&app));
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The API token used for this publish has been revoked."}]}"###);
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn settings_can_not_be_changed_with_token() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    let body = json!({ "publish_confirmation_required": false }).to_string();
+    let response = token.put::<()>("/api/v1/me/publish_settings", body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action can only be performed on the crates.io website"}]}"###);
+}
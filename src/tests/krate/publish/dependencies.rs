@@ -1,5 +1,7 @@
 use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
 use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::users;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
 use googletest::prelude::*;
 use http::StatusCode;
 use insta::{assert_json_snapshot, assert_snapshot};
@@ -354,3 +356,107 @@ async fn test_dep_limit() {
         ".crate.updated_at" => "[datetime]",
     });
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dep_limit_with_custom_limit() {
+    let (app, _, user, token) = TestApp::full()
+        .with_config(|config| config.max_dependencies = 1)
+        .with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("dep-a", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("dep-b", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("dep-c", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo", user.as_model().id)
+            .max_dependencies(2)
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .dependency(DependencyBuilder::new("dep-a"))
+        .dependency(DependencyBuilder::new("dep-b"))
+        .dependency(DependencyBuilder::new("dep-c"));
+
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crates.io only allows a maximum number of 2 dependencies.\n\nIf you have a use case that requires an increase of this limit, please send us an email to help@crates.io to discuss the details."}]}"###);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .dependency(DependencyBuilder::new("dep-a"))
+        .dependency(DependencyBuilder::new("dep-b"));
+
+    token.publish_crate(crate_to_publish).await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dep_limit_with_user_limit() {
+    let (app, _, user, token) = TestApp::full()
+        .with_config(|config| config.max_dependencies = 1)
+        .with_token();
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::max_dependencies.eq(2))
+            .execute(conn)
+            .unwrap();
+
+        CrateBuilder::new("dep-a", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("dep-b", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("dep-c", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar", user.as_model().id)
+            .max_dependencies(3)
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .dependency(DependencyBuilder::new("dep-a"))
+        .dependency(DependencyBuilder::new("dep-b"))
+        .dependency(DependencyBuilder::new("dep-c"));
+
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crates.io only allows a maximum number of 2 dependencies.\n\nIf you have a use case that requires an increase of this limit, please send us an email to help@crates.io to discuss the details."}]}"###);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .dependency(DependencyBuilder::new("dep-a"))
+        .dependency(DependencyBuilder::new("dep-b"));
+
+    token.publish_crate(crate_to_publish).await.good();
+
+    // The override of the crate takes precedence over the one of the user
+    let crate_to_publish = PublishBuilder::new("bar", "1.0.0")
+        .dependency(DependencyBuilder::new("dep-a"))
+        .dependency(DependencyBuilder::new("dep-b"))
+        .dependency(DependencyBuilder::new("dep-c"));
+
+    token.publish_crate(crate_to_publish).await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn denied_dependency() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("openssl", user.as_model().id).expect_build(conn);
+    });
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let body = json!({ "denied_dependencies": [{ "name": "openssl", "version_req": "<1" }] });
+    let url = "/api/v1/crates/foo/settings";
+    let response = user.patch::<()>(url, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0")
+        .dependency(DependencyBuilder::new("openssl").version_req("^0.10"));
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the dependency `openssl ^0.10` is not allowed, since the owners of this crate denied the use of `openssl <1` in the crate settings"}]}"###);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0")
+        .dependency(DependencyBuilder::new("openssl").version_req("^1.0"));
+    token.publish_crate(crate_to_publish).await.good();
+}
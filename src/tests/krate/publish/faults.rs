@@ -0,0 +1,78 @@
+use crate::builders::PublishBuilder;
+use crate::util::faults::Fault;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::crates;
+use diesel::{QueryDsl, RunQueryDsl};
+use http::StatusCode;
+use insta::assert_snapshot;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn storage_failure_rolls_back_publish() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    app.storage_faults().inject(Fault::ServerError);
+
+    let crate_to_publish = PublishBuilder::new("foo_faulty", "1.0.0").readme("hello world");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_snapshot!(response.text(), @"Internal Server Error");
+
+    app.storage_faults().clear();
+
+    // Neither the crate nor any of the background jobs were persisted
+    let num_crates: i64 = app.db(|conn| crates::table.count().get_result(conn).unwrap());
+    assert_eq!(num_crates, 0);
+    assert_eq!(app.stored_files().await, Vec::<String>::new());
+
+    // Publishing again after the storage recovered works
+    let crate_to_publish = PublishBuilder::new("foo_faulty", "1.0.0").readme("hello world");
+    token.publish_crate(crate_to_publish).await.good();
+
+    assert_snapshot!(app.stored_files().await.join("\n"), @r###"
+    crates/foo_faulty/foo_faulty-1.0.0.crate
+    index/fo/o_/foo_faulty
+    readmes/foo_faulty/foo_faulty-1.0.0.html
+    rss/crates.xml
+    rss/crates/foo_faulty.xml
+    rss/updates.xml
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn storage_timeout() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    app.storage_faults().inject_times(Fault::Timeout, 1);
+
+    let crate_to_publish = PublishBuilder::new("foo_timeout", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(app.storage_faults().num_injected(), 1);
+
+    // The fault was only injected once, so retrying the publish works
+    let crate_to_publish = PublishBuilder::new("foo_timeout", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    assert_eq!(app.storage_faults().num_injected(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_storage() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    app.storage_faults()
+        .inject(Fault::Slow(Duration::from_millis(100)));
+
+    let crate_to_publish = PublishBuilder::new("foo_slow", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    assert!(app.storage_faults().num_injected() > 0);
+
+    app.storage_faults().clear();
+    assert_snapshot!(app.stored_files().await.join("\n"), @r###"
+    crates/foo_slow/foo_slow-1.0.0.crate
+    index/fo/o_/foo_slow
+    rss/crates.xml
+    rss/crates/foo_slow.xml
+    rss/updates.xml
+    "###);
+}
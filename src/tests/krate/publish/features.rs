@@ -161,7 +161,7 @@ async fn too_many_features_with_custom_limit() {
 async fn too_many_enabled_features() {
     let (app, _, _, token) = TestApp::full()
         .with_config(|config| {
-            config.max_features = 3;
+            config.max_feature_values = 3;
         })
         .with_token();
 
@@ -177,13 +177,13 @@ async fn too_many_enabled_features() {
 async fn too_many_enabled_features_with_custom_limit() {
     let (app, _, user, token) = TestApp::full()
         .with_config(|config| {
-            config.max_features = 3;
+            config.max_feature_values = 3;
         })
         .with_token();
 
     app.db(|conn| {
         CrateBuilder::new("foo", user.as_model().id)
-            .max_features(4)
+            .max_feature_values(4)
             .expect_build(conn)
     });
 
@@ -0,0 +1,139 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, MockTokenUser, RequestHelper, Response, TestApp};
+use crates_io::schema::{pending_publishes, publish_idempotency_keys};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use http::{Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::json;
+
+async fn publish_with_key(
+    token: &MockTokenUser,
+    crate_to_publish: PublishBuilder,
+    key: &str,
+) -> Response<()> {
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = crate_to_publish.body();
+    request.header("idempotency-key", key);
+
+    let response = token.run(request).await;
+    token.app().run_pending_background_jobs().await;
+    response
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retry_replays_original_response() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let original = response.json();
+    assert_eq!(original["crate"]["name"], "foo");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), original);
+
+    let json = anon.get::<()>("/api/v1/crates/foo/versions").await.json();
+    assert_eq!(json["versions"].as_array().unwrap().len(), 1);
+
+    // Without the key, the retry fails as usual
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let count: i64 = app.db(|conn| {
+        publish_idempotency_keys::table
+            .count()
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_eq!(count, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn key_cannot_be_reused_for_other_versions() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the idempotency key was already used to publish foo v1.0.0"}]}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.1.0").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn expired_keys_are_not_replayed() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.db(|conn| {
+        let two_days_ago = chrono::Utc::now().naive_utc() - chrono::TimeDelta::days(2);
+        diesel::update(publish_idempotency_keys::table)
+            .set(publish_idempotency_keys::created_at.eq(two_days_ago))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["crate"]["max_version"], "1.1.0");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_publishes_are_not_stored() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").unset_description();
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["crate"]["name"], "foo");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn held_publishes_are_not_duplicated() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    let body = json!({ "publish_confirmation_required": true }).to_string();
+    let response = user.put::<()>("/api/v1/me/publish_settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for _ in 0..2 {
+        let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+        let response = publish_with_key(&token, crate_to_publish, "key-1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.json()["warnings"]["other"][0]
+            .as_str()
+            .unwrap()
+            .contains("requires confirmation"));
+    }
+
+    let count: i64 = app.db(|conn| pending_publishes::table.count().get_result(conn).unwrap());
+    assert_eq!(count, 1);
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_key() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = publish_with_key(&token, crate_to_publish, "foo bar").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid idempotency key. The `Idempotency-Key` header must only contain printable ASCII characters, and must be between 1 and 255 characters long."}]}"###);
+}
@@ -172,3 +172,42 @@ async fn test_lib_and_bin_crate() {
         ".version.audit_actions[].user.id" => id_redaction(token.as_model().user_id),
     });
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn build_script_and_proc_macro() {
+    let (_app, _anon, _cookie, token) = TestApp::full().with_token();
+
+    let publish_builder = PublishBuilder::new("foo", "1.0.0")
+        .add_file("foo-1.0.0/src/lib.rs", "pub fn foo() {}")
+        .add_file("foo-1.0.0/build.rs", "fn main() {}");
+
+    token.publish_crate(publish_builder).await.good();
+
+    let json = token.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    assert_eq!(json["version"]["has_build_script"], true);
+    assert_eq!(json["version"]["is_proc_macro"], false);
+
+    let publish_builder = PublishBuilder::new("foo_derive", "1.0.0")
+        .custom_manifest(
+            r#"[package]
+            name = "foo_derive"
+            version = "1.0.0"
+            description = "description"
+            license = "MIT"
+            build = false
+
+            [lib]
+            proc-macro = true"#,
+        )
+        .add_file("foo_derive-1.0.0/src/lib.rs", "")
+        .add_file("foo_derive-1.0.0/build.rs", "fn main() {}");
+
+    token.publish_crate(publish_builder).await.good();
+
+    let json = token
+        .get::<()>("/api/v1/crates/foo_derive/1.0.0")
+        .await
+        .json();
+    assert_eq!(json["version"]["has_build_script"], false);
+    assert_eq!(json["version"]["is_proc_macro"], true);
+}
@@ -1,20 +1,28 @@
 mod audit_action;
 mod auth;
 mod basics;
+mod batch;
 mod build_metadata;
 mod categories;
+mod channel;
+mod ci;
+mod confirmation;
 mod dependencies;
 mod emails;
+mod faults;
 mod features;
 mod git;
+mod idempotency;
 mod inheritance;
 mod keywords;
 mod links;
 mod manifest;
 mod max_size;
+mod queue;
 mod rate_limit;
 mod readme;
 mod similar_names;
+mod staging;
 mod tarball;
 mod timestamps;
 mod validation;
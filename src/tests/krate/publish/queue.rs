@@ -0,0 +1,36 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn queued_publishes_do_not_hold_database_connections() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.publish_queue.max_concurrent = Some(1))
+        .with_token();
+
+    // Another publish occupies the only slot
+    let permit = app.as_inner().publish_queue.acquire(0).await.unwrap();
+
+    let publish = token.publish_crate(PublishBuilder::new("foo", "1.0.0"));
+    tokio::pin!(publish);
+
+    let queued = async {
+        while app.as_inner().publish_queue.stats().waiting == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+
+    tokio::select! {
+        _ = &mut publish => panic!("the publish was not queued"),
+        _ = queued => {}
+    }
+
+    let status = app.as_inner().primary_database.status();
+    assert_eq!(status.size, status.available);
+
+    drop(permit);
+    let response = publish.await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(app.as_inner().publish_queue.stats().running, 0);
+}
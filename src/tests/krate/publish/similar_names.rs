@@ -57,3 +57,71 @@ async fn new_crate_similar_name_underscore() {
 
     assert_that!(app.stored_files().await, empty());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_crate_confusable_name() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo-confusable", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("f00-confusab1e", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
+
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_crate_confusable_name_of_private_crate() {
+    use crates_io::models::CrateVisibility;
+    use crates_io::schema::crates;
+    use diesel::prelude::*;
+
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo-confusable", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        diesel::update(&krate)
+            .set(crates::visibility.eq(CrateVisibility::Private))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo-confusab1e", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
+
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_crate_confusable_name_with_override() {
+    use crates_io::schema::crate_name_skeleton_overrides;
+    use diesel::prelude::*;
+
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo-confusable", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        diesel::insert_into(crate_name_skeleton_overrides::table)
+            .values(crate_name_skeleton_overrides::name.eq("f00-confusab1e"))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("f00-confusab1e", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,92 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use bytes::Bytes;
+use crates_io::models::token::EndpointScope;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/staging";
+
+/// Returns the publish request body for a crate whose tarball was staged
+/// with the given id.
+fn staged_body(builder: PublishBuilder, staging_id: &str) -> Bytes {
+    let (json, _) = builder.staging_id(staging_id).build();
+    PublishBuilder::create_publish_body(&json, &[])
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn staged_tarballs_can_be_published() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let (_, tarball) = PublishBuilder::new("foo_staged", "1.0.0").build();
+
+    let json = token.post::<Value>(URL, tarball.clone()).await.good();
+    let id = json["id"].as_str().unwrap();
+    assert_eq!(json["size"], tarball.len());
+    assert_eq!(json["checksum"].as_str().unwrap().len(), 64);
+
+    // Uploading the same tarball again returns the existing staged upload
+    let retried = token.post::<Value>(URL, tarball).await.good();
+    assert_eq!(retried["id"], id);
+
+    let body = staged_body(PublishBuilder::new("foo_staged", "1.0.0"), id);
+    let crate_json = token.publish_crate(body).await.good();
+    assert_eq!(crate_json.krate.name, "foo_staged");
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"crates/foo_staged/foo_staged-1.0.0.crate".to_string()));
+    assert!(stored_files.contains(&format!("staging/{id}.crate")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_staging_ids_are_rejected() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let body = staged_body(PublishBuilder::new("foo_staged", "1.0.0"), "unknown");
+    let response = token.publish_crate(body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the staged upload `unknown` does not exist or has expired"}]}"###);
+
+    let (_, tarball) = PublishBuilder::new("foo_staged", "1.0.0").build();
+    let json = token.post::<Value>(URL, tarball).await.good();
+    let id = json["id"].as_str().unwrap();
+
+    // Staged uploads can only be published by the user that uploaded them
+    let other = app.db_new_user("other").db_new_token("other");
+    let body = staged_body(PublishBuilder::new("foo_staged", "1.0.0"), id);
+    let response = other.publish_crate(body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // The tarball can't be sent along with a staging id
+    let body = PublishBuilder::new("foo_staged", "1.0.0")
+        .staging_id(id)
+        .body();
+    let response = token.publish_crate(body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a publish request can not contain a tarball and a `staging_id` at the same time"}]}"###);
+
+    let response = token.post::<()>(URL, Bytes::new()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the request body must contain the tarball"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn uploads_require_a_publish_scope() {
+    let (_, anon, user) = TestApp::full().with_user();
+
+    let (_, tarball) = PublishBuilder::new("foo_staged", "1.0.0").build();
+
+    let response = anon.post::<()>(URL, tarball.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let endpoint_scopes = Some(vec![EndpointScope::Yank]);
+    let token = user.db_new_scoped_token("yank", None, endpoint_scopes, None);
+    let response = token.post::<()>(URL, tarball.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this token does not have the required permissions to perform this action"}]}"###);
+
+    let endpoint_scopes = Some(vec![EndpointScope::PublishNew]);
+    let token = user.db_new_scoped_token("publish", None, endpoint_scopes, None);
+    token.post::<Value>(URL, tarball).await.good();
+}
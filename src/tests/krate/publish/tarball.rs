@@ -103,3 +103,72 @@ async fn tarball_bytes_truncated() {
     assert_json_snapshot!(response.json());
     assert_that!(app.stored_files().await, empty());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_with_path_traversal() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let tarball = {
+        let mut builder = TarballBuilder::new();
+
+        // `tar::Header::set_path()` refuses `..` components, so the name has
+        // to be written into the header directly.
+        let mut header = tar::Header::new_gnu();
+        let name = b"foo-1.1.0/../bar-1.1.0/src/lib.rs";
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.set_size(0);
+        header.set_cksum();
+        assert_ok!(builder.as_mut().append(&header, &[][..]));
+
+        builder.build()
+    };
+
+    let (json, _tarball) = PublishBuilder::new("foo", "1.1.0").build();
+    let body = PublishBuilder::create_publish_body(&json, &tarball);
+
+    let response = token.publish_crate(body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid path found: foo-1.1.0/../bar-1.1.0/src/lib.rs; `..` is not allowed in paths" }] })
+    );
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_with_too_many_files() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.max_tarball_files = 3)
+        .with_token();
+
+    let builder = (0..3).fold(PublishBuilder::new("foo", "1.0.0"), |builder, i| {
+        builder.add_file(format!("foo-1.0.0/src/{i}.rs"), "")
+    });
+
+    let response = token.publish_crate(builder).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "uploaded tarball contains more than 3 files" }] })
+    );
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_with_deeply_nested_path() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.max_tarball_path_depth = 3)
+        .with_token();
+
+    let builder = PublishBuilder::new("foo", "1.0.0")
+        .add_file("foo-1.0.0/src/a/lib.rs", "")
+        .add_file("foo-1.0.0/src/a/b/c.rs", "");
+
+    let response = token.publish_crate(builder).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "uploaded tarball contains a path that is nested more than 3 levels deep: foo-1.0.0/src/a/b/c.rs" }] })
+    );
+    assert_that!(app.stored_files().await, empty());
+}
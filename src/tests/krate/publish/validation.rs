@@ -4,6 +4,7 @@ use crates_io::models::krate::MAX_NAME_LENGTH;
 use googletest::prelude::*;
 use http::StatusCode;
 use insta::{assert_json_snapshot, assert_snapshot};
+use serde_json::json;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn empty_json() {
@@ -129,3 +130,30 @@ async fn invalid_urls() {
 
     assert_that!(app.stored_files().await, empty());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn warnings() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .license_file("LICENSE")
+        .badge("travis-ci");
+
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = response.json();
+    let warnings = &json["warnings"];
+    assert_eq!(warnings["invalid_badges"], json!(["travis-ci"]));
+    assert_eq!(
+        warnings["other"],
+        json!(["the license file `LICENSE` is not included in the package"])
+    );
+
+    let details = warnings["details"].as_array().unwrap();
+    assert_eq!(details.len(), 2);
+    assert_eq!(details[0]["code"], "missing_license_file");
+    assert_eq!(details[1]["code"], "invalid_badge");
+    assert_eq!(details[1]["message"], "the `travis-ci` badge is ignored");
+    assert!(details[1]["hint"].as_str().unwrap().contains("[badges]"));
+}
@@ -0,0 +1,126 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockAnonymousUser, MockRequestExt, RequestHelper, TestApp};
+use crates_io::load_shedding::Priority;
+use crates_io::models::{NewUserAgentPolicy, ThrottleClass};
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+
+/// Creates an app that sheds low priority requests as soon as a single
+/// request is in flight, but still serves normal priority requests.
+fn overloaded_app() -> (TestApp, MockAnonymousUser) {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.load_shedding.max_in_flight = Some(1);
+        })
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    (app, anon)
+}
+
+fn num_shed(app: &TestApp, priority: Priority) -> u64 {
+    let metrics = &app.as_inner().instance_metrics;
+    let counter = &metrics.requests_shed_total;
+    counter.with_label_values(&[priority.as_str()]).get()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn low_priority_requests_are_shed() {
+    let (app, anon) = overloaded_app();
+
+    let response = anon.get::<()>("/api/v1/crates?q=foo").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The service is currently overloaded. Please try again later."}]}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/downloads").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    assert_eq!(num_shed(&app, Priority::Low), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn normal_and_critical_requests_are_served() {
+    let (app, anon) = overloaded_app();
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+
+    assert_eq!(num_shed(&app, Priority::Low), 0);
+    assert_eq!(num_shed(&app, Priority::Normal), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn critical_requests_are_never_shed() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.load_shedding.max_in_flight = Some(0);
+        })
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(num_shed(&app, Priority::Normal), 1);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn route_priorities_are_configurable() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.load_shedding.max_in_flight = Some(0);
+            config
+                .load_shedding
+                .route_priorities
+                .insert("/api/v1/summary".into(), Priority::Critical);
+        })
+        .empty();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crawler_requests_are_low_priority() {
+    let (app, anon) = overloaded_app();
+
+    app.db(|conn| {
+        NewUserAgentPolicy {
+            pattern: "crawler",
+            throttle_class: ThrottleClass::Slow,
+        }
+        .upsert(conn)
+        .unwrap();
+
+        app.as_inner().user_agent_throttle.refresh(conn).unwrap();
+    });
+
+    let mut request = anon.get_request("/api/v1/crates/foo");
+    request.header(header::USER_AGENT, "crawler/1.0");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Downloads of crawlers are still served
+    let mut request = anon.get_request("/api/v1/crates/foo/1.0.0/download");
+    request.header(header::USER_AGENT, "crawler/1.0");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+
+    assert_eq!(num_shed(&app, Priority::Low), 1);
+}
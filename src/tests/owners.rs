@@ -6,9 +6,11 @@ use crate::{
     TestApp,
 };
 use crates_io::{
-    models::Crate,
+    models::{Crate, OwnerAction},
+    schema::users,
     views::{
-        EncodableCrateOwnerInvitationV1, EncodableOwner, EncodablePublicUser, InvitationResponse,
+        EncodableCrateOwnerAction, EncodableCrateOwnerInvitationV1, EncodableOwner,
+        EncodablePublicUser, InvitationResponse,
     },
     Emails,
 };
@@ -357,7 +359,7 @@ async fn deleted_ownership_isnt_in_owner_user() {
 
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user.id).expect_build(conn);
-        krate.owner_remove(conn, &user.gh_login).unwrap();
+        krate.owner_remove(conn, user, &user.gh_login).unwrap();
     });
 
     let json: UserResponse = anon
@@ -572,6 +574,34 @@ async fn test_accept_invitation_by_mail() {
     assert_eq!(json.users.len(), 2);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn invitation_email_includes_crate_context() {
+    let (app, _, owner, owner_token) = TestApp::init().with_token();
+    let owner = owner.as_model();
+    app.db_new_user("user_bar");
+    app.db(|conn| {
+        CrateBuilder::new("context_crate", owner.id)
+            .description("A crate with some context")
+            .expect_build(conn)
+    });
+
+    owner_token
+        .add_named_owner("context_crate", "user_bar")
+        .await
+        .good();
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    let (_, message) = emails
+        .into_iter()
+        .find(|(_, m)| m.contains("Subject: Crate ownership invitation"))
+        .expect("missing email");
+
+    assert!(message.contains("foo (https://crates.io/users/foo) has invited you"));
+    assert!(message.contains("(https://crates.io/crates/context_crate)"));
+    assert!(message.contains("\r\nA crate with some context\r\n"));
+    assert!(message.contains("\r\n  foo\r\n+ user_bar (you)\r\n"));
+}
+
 /// Hacky way to simulate the expiration of an ownership invitation. Instead of letting a month
 /// pass, the creation date of the invite is moved back a month.
 pub fn expire_invitation(app: &TestApp, crate_id: i32) {
@@ -712,6 +742,7 @@ async fn inactive_users_dont_get_invitations() {
             name: None,
             gh_avatar: None,
             gh_access_token: "some random token",
+            gh_two_factor: false,
         }
         .create_or_update(None, &app.as_inner().emails, conn)
         .unwrap();
@@ -756,6 +787,115 @@ async fn highest_gh_id_is_most_recent_account_we_know_of() {
     assert_eq!(json.crate_owner_invitations.len(), 1);
 }
 
+/// Replaces the email address of the user, returning its verification token.
+fn set_email(app: &TestApp, user: &MockCookieUser, email: &str, verified: bool) -> String {
+    use crates_io::schema::emails;
+
+    app.db(|conn| {
+        diesel::update(emails::table.filter(emails::user_id.eq(user.as_model().id)))
+            .set((emails::email.eq(email), emails::verified.eq(verified)))
+            .returning(emails::token)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invite_existing_user_by_email() {
+    let (app, _, _, owner) = TestApp::init().with_token();
+    let invited_user = app.db_new_user("invited_user");
+    set_email(&app, &invited_user, "invited@example.com", true);
+    app.db(|conn| CrateBuilder::new("crate_name", owner.as_model().user_id).expect_build(conn));
+
+    let response = owner
+        .add_named_owner("crate_name", "Invited@example.com")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({
+            "msg": "Invited@example.com has been invited to be an owner of crate crate_name",
+            "ok": true,
+        })
+    );
+
+    let json = invited_user.list_invitations().await;
+    assert_eq!(json.crate_owner_invitations.len(), 1);
+    assert_eq!(json.crate_owner_invitations[0].crate_name, "crate_name");
+
+    // The regular invitation email is sent to the user
+    let token = extract_token_from_invite_email(&app.as_inner().emails);
+    assert!(!token.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invite_unknown_email() {
+    let (app, anon, _, owner) = TestApp::init().with_token();
+    let krate =
+        app.db(|conn| CrateBuilder::new("crate_name", owner.as_model().user_id).expect_build(conn));
+
+    let response = owner.add_named_owner("crate_name", "not an email@").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`not an email@` is not a valid email address"}]}"###);
+
+    let response = owner
+        .add_named_owner("crate_name", "newcomer@example.com")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({
+            "msg": "newcomer@example.com has been invited to be an owner of crate crate_name",
+            "ok": true,
+        })
+    );
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(mails.len(), 1);
+    let (envelope, message) = &mails[0];
+    assert_eq!(envelope.to()[0].to_string(), "newcomer@example.com");
+    assert!(message.contains("Subject: Crate ownership invitation"));
+    assert!(message.contains("verify this email address"));
+
+    let response = owner
+        .add_named_owner("crate_name", "newcomer@example.com")
+        .await;
+    assert_eq!(
+        response.json(),
+        json!({
+            "msg": "newcomer@example.com already has a pending invitation to be an owner of crate crate_name",
+            "ok": true,
+        })
+    );
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+
+    // The invitation is claimed once the new user verifies the email address
+    let newcomer = app.db_new_user("newcomer");
+    let email_token = set_email(&app, &newcomer, "Newcomer@example.com", false);
+    assert_eq!(
+        newcomer
+            .list_invitations()
+            .await
+            .crate_owner_invitations
+            .len(),
+        0
+    );
+
+    let url = format!("/api/v1/confirm/{email_token}");
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = newcomer.list_invitations().await;
+    assert_eq!(json.crate_owner_invitations.len(), 1);
+    assert_eq!(json.crate_owner_invitations[0].crate_id, krate.id);
+
+    newcomer
+        .accept_ownership_invitation("crate_name", krate.id)
+        .await;
+    let json = anon.show_crate_owners("crate_name").await;
+    assert_eq!(json.users.len(), 2);
+}
+
 fn extract_token_from_invite_email(emails: &Emails) -> String {
     let emails = emails.mails_in_memory().unwrap();
 
@@ -773,3 +913,69 @@ fn extract_token_from_invite_email(emails: &Emails) -> String {
     let after_pos = before_pos + body[before_pos..].find(after_token).unwrap();
     body[before_pos..after_pos].to_string()
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ownership_history_includes_removed_owners() {
+    #[derive(Deserialize)]
+    struct HistoryResponse {
+        history: Vec<EncodableCrateOwnerAction>,
+    }
+
+    let (app, anon, owner, owner_token) = TestApp::init().with_token();
+    let owner_model = owner.as_model();
+    let invited_user = app.db_new_user("user_bar");
+    let other_user = app.db_new_user("user_baz");
+    let krate =
+        app.db(|conn| CrateBuilder::new("owner_history", owner_model.id).expect_build(conn));
+
+    owner_token
+        .add_named_owner("owner_history", "user_bar")
+        .await
+        .good();
+    invited_user
+        .accept_ownership_invitation(&krate.name, krate.id)
+        .await;
+    owner_token
+        .remove_named_owner("owner_history", "user_bar")
+        .await
+        .good();
+
+    let url = "/api/v1/crates/owner_history/owners/history";
+    let json: HistoryResponse = owner.get(url).await.good();
+    let history = json
+        .history
+        .iter()
+        .map(|action| {
+            let performed_by = action.performed_by.as_ref().map(|user| user.login.as_str());
+            (action.action, action.owner.login.as_str(), performed_by)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        history,
+        vec![
+            (OwnerAction::Add, "foo", Some("foo")),
+            (OwnerAction::Add, "user_bar", Some("foo")),
+            (OwnerAction::Remove, "user_bar", Some("foo")),
+        ]
+    );
+
+    // The removed owner, other users and anonymous users can't see the history
+    let response = invited_user.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = other_user.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Admins can see the history of all crates
+    app.db(|conn| {
+        diesel::update(other_user.as_model())
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json: HistoryResponse = other_user.get(url).await.good();
+    assert_eq!(json.history.len(), 3);
+}
@@ -0,0 +1,145 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, MockTokenUser, RequestHelper, TestApp};
+use crates_io::registries::{Registries, RegistryConfig};
+use crates_io::schema::{api_tokens, crates};
+use crates_io::views::GoodCrate;
+use diesel::prelude::*;
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+
+fn registries() -> Registries {
+    Registries::new(vec![RegistryConfig {
+        name: "internal".into(),
+        hostname: Some("crates.internal.example.com".into()),
+        path_prefix: Some("/internal".into()),
+    }])
+    .unwrap()
+}
+
+fn set_token_registry(app: &TestApp, token: &MockTokenUser, registry: &str) {
+    app.db(|conn| {
+        diesel::update(api_tokens::table.find(token.as_model().id))
+            .set(api_tokens::registry.eq(registry))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn crate_registry(app: &TestApp, name: &str) -> String {
+    app.db(|conn| {
+        crates::table
+            .filter(crates::name.eq(name))
+            .select(crates::registry)
+            .first(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_to_additional_registry() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.registries = registries())
+        .with_token();
+
+    set_token_registry(&app, &token, "internal");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token
+        .put::<GoodCrate>("/internal/api/v1/crates/new", crate_to_publish)
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(crate_registry(&app, "foo"), "internal");
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"index/registries/internal/3/f/foo".to_string()));
+    assert!(!stored_files.contains(&"index/3/f/foo".to_string()));
+
+    // The git index only contains the crates of the default registry
+    assert!(!app.upstream_index().crate_exists("foo").unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_existing_crate_to_other_registry() {
+    let (app, _, user, token) = TestApp::full()
+        .with_config(|config| config.registries = registries())
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    assert_eq!(crate_registry(&app, "foo"), "default");
+
+    let internal_token = user.db_new_token("internal");
+    set_token_registry(&app, &internal_token, "internal");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0");
+    let response = internal_token
+        .put::<()>("/internal/api/v1/crates/new", crate_to_publish)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` belongs to a different registry"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_of_other_registry() {
+    let (_, _, _, token) = TestApp::full()
+        .with_config(|config| config.registries = registries())
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token
+        .put::<()>("/internal/api/v1/crates/new", crate_to_publish)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this token can not be used with this registry"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_token_belongs_to_registry() {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.registries = registries())
+        .with_user();
+
+    let body = json!({ "api_token": { "name": "bar" } }).to_string();
+    let json: serde_json::Value = user.put("/internal/api/v1/me/tokens", body).await.good();
+
+    let id = json["api_token"]["id"].as_i64().unwrap() as i32;
+    let registry: String = app.db(|conn| {
+        api_tokens::table
+            .find(id)
+            .select(api_tokens::registry)
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(registry, "internal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_from_additional_registry() {
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.registries = registries())
+        .with_token();
+
+    set_token_registry(&app, &token, "internal");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token
+        .put::<GoodCrate>("/internal/api/v1/crates/new", crate_to_publish)
+        .await
+        .good();
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = anon
+        .get::<()>("/internal/api/v1/crates/foo/1.0.0/download")
+        .await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+
+    // The registry can also be selected by the hostname of the request
+    let mut request = anon.request_builder(Method::GET, "/api/v1/crates/foo/1.0.0/download");
+    request.header(header::HOST, "crates.internal.example.com");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+}
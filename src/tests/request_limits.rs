@@ -0,0 +1,98 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::request_limits::{RequestLimits, RouteClass};
+use http::StatusCode;
+use insta::assert_snapshot;
+use std::time::Duration;
+
+fn limits(timeout: Duration, max_body_size: usize) -> RequestLimits {
+    RequestLimits {
+        timeout,
+        max_body_size,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_body_size_is_limited() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            let publish = limits(Duration::from_secs(30), 100);
+            let classes = &mut config.request_limits.classes;
+            classes.insert(RouteClass::Publish, publish);
+        })
+        .with_token();
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_is_not_limited_by_other_classes() {
+    let (_, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            let tiny = limits(Duration::from_secs(30), 100);
+            let classes = &mut config.request_limits.classes;
+            classes.insert(RouteClass::Default, tiny);
+            classes.insert(RouteClass::Search, tiny);
+        })
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn search_body_size_is_limited() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/crates").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut request = anon.get_request("/api/v1/crates");
+    *request.body_mut() = vec![b'a'; 2048].into();
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_requests_time_out() {
+    let (_, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            let default = limits(Duration::ZERO, 1024 * 1024);
+            let classes = &mut config.request_limits.classes;
+            classes.insert(RouteClass::Default, default);
+        })
+        .with_token();
+
+    let response = token.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The request took too long to process."}]}"###);
+
+    // Other route classes are not affected by the default timeout
+    let response = token.get::<()>("/api/v1/crates").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_is_not_aborted_after_receiving_the_body() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            let publish = limits(Duration::from_millis(1), 1024 * 1024);
+            let classes = &mut config.request_limits.classes;
+            classes.insert(RouteClass::Publish, publish);
+        })
+        .with_token();
+
+    // The body is received at once, so the publish is processed even though
+    // it takes longer than the timeout
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    assert!(!app.stored_files().await.is_empty());
+}
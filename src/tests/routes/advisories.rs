@@ -0,0 +1,130 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use chrono::NaiveDate;
+use crates_io::models::Crate;
+use crates_io::schema::{advisories, dependencies, users, versions};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+fn insert_advisory(conn: &mut PgConnection, withdrawn: Option<NaiveDate>) {
+    diesel::insert_into(advisories::table)
+        .values((
+            advisories::id.eq("RUSTSEC-2024-0001"),
+            advisories::crate_name.eq("foo"),
+            advisories::title.eq("Memory corruption in `Foo::bar`"),
+            advisories::date.eq(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()),
+            advisories::withdrawn.eq(withdrawn),
+            advisories::aliases.eq(vec!["CVE-2024-1234"]),
+            advisories::patched.eq(vec![">= 1.2.3", "^1.1.5"]),
+            advisories::unaffected.eq(vec!["< 1.0.0"]),
+        ))
+        .execute(conn)
+        .unwrap();
+}
+
+/// Creates a crate that depends on `foo` with the given requirement.
+fn dependent(conn: &mut PgConnection, foo: &Crate, name: &str, req: &str, downloads: i32) {
+    let user_id = users::table.select(users::id).first(conn).unwrap();
+    let version = VersionBuilder::new("1.0.0").dependency(foo, None);
+    let krate = CrateBuilder::new(name, user_id)
+        .downloads(downloads)
+        .version(version)
+        .expect_build(conn);
+
+    let version_ids = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .select(versions::id);
+
+    diesel::update(dependencies::table)
+        .filter(dependencies::version_id.eq_any(version_ids))
+        .set(dependencies::req.eq(req))
+        .execute(conn)
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn show() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        let foo = CrateBuilder::new("foo", user_id)
+            .version("0.9.0")
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("1.1.5")
+            .version("1.2.0")
+            .version(VersionBuilder::new("1.2.3").yanked(true))
+            .version("1.2.4")
+            .expect_build(conn);
+
+        insert_advisory(conn, None);
+
+        dependent(conn, &foo, "pinned", "=1.1.0", 100);
+        dependent(conn, &foo, "pinned_to_yanked_fix", "~1.2.0, <1.2.4", 50);
+        // Crates that can update to a patched version are not listed
+        dependent(conn, &foo, "compatible", "^1.1.0", 1000);
+        dependent(conn, &foo, "unaffected", "^0.9", 500);
+    });
+
+    let json: Value = anon
+        .get("/api/v1/advisories/RUSTSEC-2024-0001")
+        .await
+        .good();
+    let advisory = &json["advisory"];
+    assert_eq!(advisory["id"], "RUSTSEC-2024-0001");
+    assert_eq!(advisory["crate"], "foo");
+    assert_eq!(advisory["date"], "2024-01-15");
+    assert_eq!(advisory["aliases"], json!(["CVE-2024-1234"]));
+    assert_eq!(
+        advisory["affected_ranges"],
+        json!([
+            { "first": "1.0.0", "last": "1.1.0", "first_patched": "1.1.5" },
+            { "first": "1.2.0", "last": "1.2.0", "first_patched": "1.2.4" },
+        ])
+    );
+    assert_eq!(
+        advisory["pinned_dependents"],
+        json!([
+            { "crate": "pinned", "version": "1.0.0", "req": "=1.1.0", "downloads": 100 },
+            {
+                "crate": "pinned_to_yanked_fix",
+                "version": "1.0.0",
+                "req": "~1.2.0, <1.2.4",
+                "downloads": 50,
+            },
+        ])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn withdrawn_advisories_have_no_affected_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        insert_advisory(conn, NaiveDate::from_ymd_opt(2024, 2, 1));
+    });
+
+    let json: Value = anon
+        .get("/api/v1/advisories/RUSTSEC-2024-0001")
+        .await
+        .good();
+    assert_eq!(json["advisory"]["withdrawn"], "2024-02-01");
+    assert_eq!(json["advisory"]["affected_ranges"], json!([]));
+    assert_eq!(json["advisory"]["pinned_dependents"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_advisory() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/advisories/RUSTSEC-2024-9999").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"advisory `RUSTSEC-2024-9999` does not exist"}]}"###);
+}
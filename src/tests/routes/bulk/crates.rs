@@ -0,0 +1,214 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockRequestExt, RequestHelper, Response, TestApp};
+use chrono::NaiveDate;
+use crates_io::models::CrateVisibility;
+use crates_io::rate_limiter::LimitedAction;
+use crates_io::schema::{crates, publish_limit_buckets};
+use diesel::prelude::*;
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::Value;
+use std::time::Duration;
+
+const URL: &str = "/api/v1/bulk/crates";
+
+fn crate_names(json: &Value) -> Vec<&str> {
+    json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| krate["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn anonymous_user_unauthorized() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response: Response<()> = anon.get_with_query(URL, "ids=foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn by_name() {
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id)
+            .keyword("cli")
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+
+        CrateBuilder::new("bar_baz", user_id)
+            .version("0.1.0")
+            .expect_build(conn);
+
+        let secret = CrateBuilder::new("secret", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        diesel::update(&secret)
+            .set(crates::visibility.eq(CrateVisibility::Private))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response: Response<()> = user
+        .get_with_query(URL, "ids=foo,bar-baz,secret,missing,foo")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = response.json();
+    assert_eq!(crate_names(&json), vec!["bar_baz", "foo"]);
+    assert_eq!(json["crates"][1]["max_version"], "1.1.0");
+    assert_eq!(json["crates"][1]["keywords"], serde_json::json!(["cli"]));
+    assert_eq!(json["versions"].as_array().unwrap().len(), 3);
+    assert_eq!(json["keywords"].as_array().unwrap().len(), 1);
+    assert_eq!(json["meta"]["next_page"], Value::Null);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn updated_since() {
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    let date = |day| {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    };
+
+    app.db(|conn| {
+        CrateBuilder::new("old", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .updated_at(date(1))
+            .expect_build(conn);
+
+        CrateBuilder::new("newer", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .updated_at(date(3))
+            .expect_build(conn);
+
+        CrateBuilder::new("new", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .updated_at(date(2))
+            .expect_build(conn);
+    });
+
+    let response: Response<()> = user
+        .get_with_query(URL, "updated_since=2024-01-02T00:00:00Z")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(crate_names(&response.json()), vec!["new", "newer"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_parameters() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response: Response<()> = user.get(URL).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"either `ids` or `updated_since` must be provided"}]}"###);
+
+    let query = "ids=foo&updated_since=2024-01-01T00:00:00Z";
+    let response: Response<()> = user.get_with_query(URL, query).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`ids` can not be combined with `updated_since`"}]}"###);
+
+    let response: Response<()> = user.get_with_query(URL, "updated_since=yesterday").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`updated_since` must be an RFC 3339 timestamp"}]}"###);
+
+    let ids = (0..101).map(|i| format!("crate{i}")).collect::<Vec<_>>();
+    let query = format!("ids={}", ids.join(","));
+    let response: Response<()> = user.get_with_query(URL, &query).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"at most 100 crates can be requested at once"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn conditional_requests() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let response: Response<()> = user.get_with_query(URL, "ids=foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "private,max-age=300"
+    );
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let mut request = user.request_builder(Method::GET, &format!("{URL}?ids=foo"));
+    request.header(header::IF_NONE_MATCH, &etag);
+    let response: Response<()> = user.run(request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+    assert!(response.text().is_empty());
+
+    let mut request = user.request_builder(Method::GET, &format!("{URL}?ids=foo"));
+    request.header(header::IF_NONE_MATCH, "\"outdated\"");
+    let response: Response<()> = user.run(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn not_modified_responses_are_not_rate_limited() {
+    let (app, _, user) = TestApp::init()
+        .with_rate_limit(LimitedAction::BulkMetadata, Duration::from_secs(3600), 1)
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let response: Response<()> = user.get_with_query(URL, "ids=foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()[header::ETAG].clone();
+
+    for _ in 0..3 {
+        let mut request = user.request_builder(Method::GET, &format!("{URL}?ids=foo"));
+        request.header(header::IF_NONE_MATCH, etag.to_str().unwrap());
+        let response: Response<()> = user.run(request).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    user.get::<()>(&format!("{URL}?ids=foo"))
+        .await
+        .assert_rate_limited(LimitedAction::BulkMetadata);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rate_limited() {
+    let (app, _, user) = TestApp::init()
+        .with_rate_limit(LimitedAction::BulkMetadata, Duration::from_millis(500), 1)
+        .with_user();
+
+    app.db(|conn| {
+        // Ratelimit bucket should next refill in about a year
+        let far_future = chrono::Utc::now().naive_utc() + Duration::from_secs(60 * 60 * 24 * 365);
+        diesel::insert_into(publish_limit_buckets::table)
+            .values((
+                publish_limit_buckets::user_id.eq(user.as_model().id),
+                publish_limit_buckets::action.eq(LimitedAction::BulkMetadata),
+                publish_limit_buckets::tokens.eq(0),
+                publish_limit_buckets::last_refill.eq(far_future),
+            ))
+            .execute(conn)
+            .expect("Failed to set fake ratelimit")
+    });
+
+    user.get::<()>(&format!("{URL}?ids=foo"))
+        .await
+        .assert_rate_limited(LimitedAction::BulkMetadata);
+}
@@ -0,0 +1,228 @@
+use crate::builders::PublishBuilder;
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use crate::{add_team_to_crate, new_team};
+use axum::body::{Body, Bytes};
+use axum::extract::connect_info::MockConnectInfo;
+use crates_io::models::{Crate, CrateVisibility};
+use crates_io::schema::{crates, registry_events};
+use diesel::prelude::*;
+use futures_util::StreamExt;
+use http::{header, Request, StatusCode};
+use insta::assert_snapshot;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tower::ServiceExt;
+
+fn summarize(json: &Value) -> Vec<String> {
+    json["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| {
+            let kind = event["kind"].as_str().unwrap();
+            let krate = event["crate"].as_str().unwrap();
+            match event["version"].as_str() {
+                Some(version) => format!("{kind} {krate}@{version}"),
+                None => format!("{kind} {krate}"),
+            }
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_feed() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token.yank("foo", "1.0.0").await.good();
+    token.unyank("foo", "1.0.0").await.good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+
+    app.db(|conn| {
+        let team = new_team("github:test-org:core")
+            .create_or_update(conn)
+            .unwrap();
+        let krate: Crate = Crate::by_name("foo").first(conn).unwrap();
+        add_team_to_crate(&team, &krate, user.as_model(), conn).unwrap();
+    });
+
+    token
+        .remove_named_owner("foo", "github:test-org:core")
+        .await
+        .good();
+
+    let json = anon.get::<()>("/api/v1/changes").await.json();
+    assert_eq!(
+        summarize(&json),
+        vec![
+            "publish foo@1.0.0",
+            "yank foo@1.0.0",
+            "unyank foo@1.0.0",
+            "publish foo@1.1.0",
+            "owner_change foo",
+        ]
+    );
+    assert_eq!(json["meta"]["more"], false);
+
+    // Only events after the cursor are returned
+    let cursor = json["events"][2]["id"].as_str().unwrap();
+    let json = anon
+        .get_with_query::<()>("/api/v1/changes", &format!("since={cursor}"))
+        .await
+        .json();
+    assert_eq!(
+        summarize(&json),
+        vec!["publish foo@1.1.0", "owner_change foo"]
+    );
+
+    // The cursor does not move if there are no new events
+    let cursor = json["meta"]["next_cursor"].as_str().unwrap().to_string();
+    let json = anon
+        .get_with_query::<()>("/api/v1/changes", &format!("since={cursor}"))
+        .await
+        .json();
+    assert!(summarize(&json).is_empty());
+    assert_eq!(json["meta"]["next_cursor"], cursor.as_str());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crates_are_hidden() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("secret", "1.0.0"))
+        .await
+        .good();
+
+    app.db(|conn| {
+        diesel::update(crates::table.filter(crates::name.eq("secret")))
+            .set(crates::visibility.eq(CrateVisibility::Private))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json = anon.get::<()>("/api/v1/changes").await.json();
+    assert_eq!(summarize(&json), vec!["publish foo@1.0.0"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recent_events_are_delayed() {
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.changes_feed_delay = Duration::from_secs(60))
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let json = anon.get::<()>("/api/v1/changes").await.json();
+    assert_eq!(summarize(&json), Vec::<String>::new());
+    assert_eq!(json["meta"]["next_cursor"], "0");
+
+    app.db(|conn| {
+        let created_at = (chrono::Utc::now() - chrono::Duration::minutes(2)).naive_utc();
+        diesel::update(registry_events::table)
+            .set(registry_events::created_at.eq(created_at))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json = anon.get::<()>("/api/v1/changes").await.json();
+    assert_eq!(summarize(&json), vec!["publish foo@1.0.0"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_cursor() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/changes", "since=foo")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `since` cursor"}]}"###);
+}
+
+/// Reads the body of a Server-Sent Events response until `count` events
+/// have been received.
+async fn read_events(app: &TestApp, request: Request<Bytes>, count: usize) -> Vec<String> {
+    let mocket_addr = SocketAddr::from(([127, 0, 0, 1], 52381));
+    let router = app.router().clone().layer(MockConnectInfo(mocket_addr));
+
+    let response = router.oneshot(request.map(Body::from)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+
+    let mut stream = response.into_body().into_data_stream();
+    let mut text = String::new();
+    while text.matches("\n\n").count() < count {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), stream.next())
+            .await
+            .expect("timed out waiting for events")
+            .unwrap()
+            .unwrap();
+
+        text.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+
+    text.split_terminator("\n\n").map(String::from).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stream_events() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token.yank("foo", "1.0.0").await.good();
+
+    let request = anon.get_request("/api/v1/changes/stream?since=0");
+    let events = read_events(&app, request, 2).await;
+    assert_eq!(events.len(), 2);
+    assert!(events[0].contains("event: publish\n"));
+    assert!(events[0].contains(r#""crate":"foo","version":"1.0.0""#));
+    assert!(events[1].contains("event: yank\n"));
+
+    // Streams can be resumed with the `Last-Event-ID` header
+    let last_event_id = events[0]
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap();
+
+    let mut request = anon.get_request("/api/v1/changes/stream");
+    request.header("last-event-id", last_event_id);
+    let events = read_events(&app, request, 1).await;
+    assert!(events[0].contains("event: yank\n"));
+
+    // Events that happen while the stream is open are delivered too
+    let request = anon.get_request("/api/v1/changes/stream");
+    let reader = read_events(&app, request, 1);
+    let publisher = async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        token
+            .publish_crate(PublishBuilder::new("bar", "1.0.0"))
+            .await
+            .good();
+    };
+
+    let (events, _) = tokio::join!(reader, publisher);
+    assert!(events[0].contains("event: publish\n"));
+    assert!(events[0].contains(r#""crate":"bar""#));
+}
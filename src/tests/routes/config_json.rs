@@ -0,0 +1,37 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::upstream::MockUpstreamRegistry;
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn advertises_the_features_of_the_deployment() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/config.json").await;
+    assert_snapshot!(response.text(), @r###"{"dl":"https://static.crates.io/crates","api":"https://crates.io","features":["attestations","batch-publish","staged-uploads"]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocked_features_are_not_advertised() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.sparse_index_url = Some("https://index.crates.io/".into());
+            config.blocked_routes.insert("/api/v1/crates/batch".into());
+            config.blocked_routes.insert("/api/v1/staging".into());
+        })
+        .empty();
+
+    let response = anon.get::<()>("/config.json").await;
+    assert_snapshot!(response.text(), @r###"{"dl":"https://static.crates.io/crates","api":"https://crates.io","sparse-index":"sparse+https://index.crates.io/","features":["attestations"]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pull_through_caches_advertise_their_sparse_index() {
+    let upstream = MockUpstreamRegistry::new();
+    let (_, anon) = TestApp::init().with_upstream(upstream).empty();
+
+    // The config is also served at the root of the sparse index
+    for path in ["/config.json", "/index/config.json"] {
+        let response = anon.get::<()>(path).await;
+        assert_snapshot!(response.text(), @r###"{"dl":"https://crates.io/api/v1/crates","api":"https://crates.io","sparse-index":"sparse+https://crates.io/index/","features":["attestations","batch-publish","pull-through-cache","staged-uploads"]}"###);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{MockAnonymousUser, RequestHelper, TestApp};
 use chrono::{Duration, Utc};
-use crates_io::schema::{crates, version_downloads, versions};
+use crates_io::schema::{crate_region_downloads, crates, version_downloads, versions};
 use crates_io::views::EncodableVersionDownload;
 use diesel::prelude::*;
 use http::StatusCode;
@@ -216,3 +216,117 @@ async fn test_version_downloads() {
         @r###"{"errors":[{"detail":"crate `foo` does not have a version `invalid-version`"}]}"###
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_region_downloads() {
+    let (app, anon, cookie) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo", cookie.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        let today = Utc::now().date_naive();
+        let rows = [
+            (today, "DE", 150),
+            (today - Duration::days(1), "DE", 50),
+            (today, "FR", 120),
+            (today, "US", 400),
+            // Countries with only a few downloads are not listed
+            (today, "NZ", 5),
+            (today, "JP", 40),
+            // Downloads older than 90 days are ignored
+            (today - Duration::days(120), "NZ", 500),
+        ]
+        .map(|(date, country, downloads)| {
+            (
+                crate_region_downloads::crate_id.eq(krate.id),
+                crate_region_downloads::date.eq(date),
+                crate_region_downloads::country.eq(country),
+                crate_region_downloads::downloads.eq(downloads),
+            )
+        });
+
+        diesel::insert_into(crate_region_downloads::table)
+            .values(&rows[..])
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/foo/downloads/regions").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"continents":[{"continent":"NA","downloads":400},{"continent":"EU","downloads":320}],"countries":[{"continent":"NA","country":"US","downloads":400},{"continent":"EU","country":"DE","downloads":200},{"continent":"EU","country":"FR","downloads":120}],"meta":{"min_country_downloads":100,"other_downloads":45}}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/bar/downloads/regions").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_summary() {
+    let (app, anon, cookie) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        let user_id = cookie.as_model().id;
+        let krate = CrateBuilder::new("foo", user_id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        let version_ids: Vec<i32> = versions::table
+            .select(versions::id)
+            .filter(versions::crate_id.eq(krate.id))
+            .order(versions::id)
+            .load(conn)
+            .unwrap();
+
+        let today = Utc::now().date_naive();
+        let rows = [
+            (version_ids[0], today, 4),
+            (version_ids[1], today, 6),
+            (version_ids[1], today - Duration::days(3), 20),
+            // The week before the last week
+            (version_ids[0], today - Duration::days(10), 15),
+            (version_ids[0], today - Duration::days(40), 5),
+            // Downloads older than 90 days are ignored
+            (version_ids[0], today - Duration::days(120), 500),
+        ]
+        .map(|(version_id, date, downloads)| {
+            (
+                version_downloads::version_id.eq(version_id),
+                version_downloads::date.eq(date),
+                version_downloads::downloads.eq(downloads),
+            )
+        });
+
+        diesel::insert_into(version_downloads::table)
+            .values(&rows[..])
+            .execute(conn)
+            .unwrap();
+
+        diesel::sql_query("SELECT refresh_crate_download_summaries()")
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/foo/downloads/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".summary.computed_at" => "[datetime]",
+    });
+
+    // crates without recent downloads have an empty summary
+    let response = anon.get::<()>("/api/v1/crates/bar/downloads/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"summary":{"computed_at":null,"last_90_days":0,"last_day":0,"last_month":0,"last_week":0,"previous_week":0,"week_over_week":{"delta":0,"percent":null}}}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/baz/downloads/summary").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(
+        response.text(),
+        @r###"{"errors":[{"detail":"crate `baz` does not exist"}]}"###
+    );
+}
@@ -1,8 +1,9 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
-use crate::{new_category, new_user};
-use crates_io::models::Category;
-use crates_io::schema::crates;
+use crate::{add_team_to_crate, new_category, new_team, new_user};
+use chrono::NaiveDateTime;
+use crates_io::models::{Category, NewTeam};
+use crates_io::schema::{crates, versions};
 use diesel::{dsl::*, prelude::*, update};
 use googletest::prelude::*;
 use http::StatusCode;
@@ -729,6 +730,152 @@ async fn index_include_yanked() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn index_no_build_script_and_no_proc_macro() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let flags = [
+            ("not_analyzed", None, None),
+            ("plain", Some(false), Some(false)),
+            ("with_build_script", Some(true), Some(false)),
+            ("proc_macro", Some(false), Some(true)),
+        ];
+
+        for (name, has_build_script, is_proc_macro) in flags {
+            let krate = CrateBuilder::new(name, user.id)
+                .version("1.0.0")
+                .expect_build(conn);
+
+            update(versions::table.filter(versions::crate_id.eq(krate.id)))
+                .set((
+                    versions::has_build_script.eq(has_build_script),
+                    versions::is_proc_macro.eq(is_proc_macro),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    for json in search_both(&anon, "no_build_script=true&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 2);
+        assert_eq!(json.crates[0].name, "plain");
+        assert_eq!(json.crates[1].name, "proc_macro");
+    }
+
+    for json in search_both(&anon, "no_proc_macro=true&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 2);
+        assert_eq!(json.crates[0].name, "plain");
+        assert_eq!(json.crates[1].name, "with_build_script");
+    }
+
+    let query = "no_build_script=true&no_proc_macro=true&sort=alphabetical";
+    for json in search_both(&anon, query).await {
+        assert_eq!(json.meta.total, 1);
+        assert_eq!(json.crates[0].name, "plain");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_maintenance_wanted() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let flags = [
+            ("old_flag", Some("2024-01-01T00:00:00")),
+            ("not_flagged", None),
+            ("new_flag", Some("2024-06-01T00:00:00")),
+        ];
+
+        for (name, maintenance_wanted_at) in flags {
+            let krate = CrateBuilder::new(name, user.id)
+                .version("1.0.0")
+                .expect_build(conn);
+
+            let maintenance_wanted_at: Option<NaiveDateTime> =
+                maintenance_wanted_at.map(|date| date.parse().unwrap());
+            update(&krate)
+                .set(crates::maintenance_wanted_at.eq(maintenance_wanted_at))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    // The most recently flagged crates are listed first by default
+    for json in search_both(&anon, "maintenance_wanted=true").await {
+        assert_eq!(json.meta.total, 2);
+        assert_eq!(json.crates[0].name, "new_flag");
+        assert_eq!(json.crates[1].name, "old_flag");
+        assert_some!(json.crates[0].maintenance_wanted_at);
+    }
+
+    for json in search_both(&anon, "maintenance_wanted=true&sort=alphabetical").await {
+        assert_eq!(json.crates[0].name, "new_flag");
+        assert_eq!(json.crates[1].name, "old_flag");
+    }
+
+    let (resp, calls) = page_with_seek(&anon, "sort=maintenance-wanted").await;
+    assert_eq!(calls, 4);
+    assert_eq!(resp[0].crates[0].name, "new_flag");
+    assert_eq!(resp[1].crates[0].name, "old_flag");
+    assert_eq!(resp[2].crates[0].name, "not_flagged");
+    assert_none!(resp[2].crates[0].maintenance_wanted_at);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_include_owners() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let dtolnay = app.db_new_user("dtolnay");
+    let dtolnay = dtolnay.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("syn", dtolnay.id).expect_build(conn);
+        CrateBuilder::new("quote", dtolnay.id).expect_build(conn);
+        CrateBuilder::new("dtolnay-utils", user.id).expect_build(conn);
+
+        let team = NewTeam {
+            name: Some("Serde Publishers".into()),
+            ..new_team("github:serde-rs:publish")
+        }
+        .create_or_update(conn)
+        .unwrap();
+
+        let krate = CrateBuilder::new("serde_json", user.id).expect_build(conn);
+        add_team_to_crate(&team, &krate, user, conn).unwrap();
+    });
+
+    // Owners are only searched if explicitly requested
+    for json in search_both(&anon, "q=dtolnay").await {
+        assert_eq!(json.meta.total, 1);
+        assert_eq!(json.crates[0].name, "dtolnay-utils");
+    }
+
+    // Crates that only match because of their owners are listed last
+    for json in search_both(&anon, "q=DTolnay&include_owners=true").await {
+        assert_eq!(json.meta.total, 3);
+        assert_eq!(json.crates[0].name, "dtolnay-utils");
+        assert_eq!(json.crates[1].name, "quote");
+        assert_eq!(json.crates[2].name, "syn");
+    }
+
+    let (resp, calls) = page_with_seek(&anon, "q=dtolnay&include_owners=true").await;
+    assert_eq!(calls, 4);
+    assert_eq!(resp[0].crates[0].name, "dtolnay-utils");
+    assert_eq!(resp[1].crates[0].name, "quote");
+    assert_eq!(resp[2].crates[0].name, "syn");
+
+    for query in ["github:serde-rs:publish", "serde%20publishers"] {
+        let query = format!("q={query}&include_owners=true");
+        for json in search_both(&anon, &query).await {
+            assert_eq!(json.meta.total, 1);
+            assert_eq!(json.crates[0].name, "serde_json");
+        }
+    }
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn yanked_versions_are_not_considered_for_max_version() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -767,6 +914,62 @@ async fn max_stable_version() {
     for json in search_both(&anon, "q=foo").await {
         assert_eq!(json.meta.total, 1);
         assert_eq!(json.crates[0].max_stable_version, Some("1.0.0".to_string()));
+        assert_eq!(json.crates[0].highest_stable, Some("1.0.0".to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn max_version_uses_semver_precedence() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .description("foo")
+            .version("1.0.0-alpha.2")
+            .version("1.0.0-alpha.10")
+            .version("1.0.0-alpha.beta")
+            .version("0.9.0+build.5")
+            .expect_build(conn);
+    });
+
+    for json in search_both(&anon, "q=foo").await {
+        assert_eq!(json.meta.total, 1);
+        assert_eq!(json.crates[0].max_version, "1.0.0-alpha.beta");
+        assert_eq!(
+            json.crates[0].max_stable_version,
+            Some("0.9.0+build.5".to_string())
+        );
+    }
+
+    app.db(|conn| {
+        CrateBuilder::new("bar", user.id)
+            .description("bar")
+            .version("1.0.0-alpha.2")
+            .version("1.0.0-alpha.10")
+            .expect_build(conn);
+    });
+
+    for json in search_both(&anon, "q=bar").await {
+        assert_eq!(json.meta.total, 1);
+        assert_eq!(json.crates[0].max_version, "1.0.0-alpha.10");
+        assert_eq!(json.crates[0].max_stable_version, None);
+        assert_eq!(json.crates[0].highest_stable, None);
+    }
+
+    // Prereleases with more than 10 identifiers are still compared element
+    // by element
+    app.db(|conn| {
+        CrateBuilder::new("baz", user.id)
+            .description("baz")
+            .version("1.0.0-a.1.2.3.4.5.6.7.8.9.10.11")
+            .version("1.0.0-b")
+            .expect_build(conn);
+    });
+
+    for json in search_both(&anon, "q=baz").await {
+        assert_eq!(json.meta.total, 1);
+        assert_eq!(json.crates[0].max_version, "1.0.0-b");
     }
 }
 
@@ -1075,7 +1278,7 @@ async fn crates_by_user_id_not_including_deleted_owners() {
 
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user.id).expect_build(conn);
-        krate.owner_remove(conn, "foo").unwrap();
+        krate.owner_remove(conn, user, "foo").unwrap();
     });
 
     for response in search_both_by_user_id(&anon, user.id).await {
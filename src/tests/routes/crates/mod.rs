@@ -3,6 +3,12 @@ mod following;
 mod list;
 mod new;
 pub mod owners;
+mod permissions;
+mod policy;
 mod read;
+mod repository;
 mod reverse_dependencies;
+mod settings;
+mod succession;
 pub mod versions;
+mod visibility;
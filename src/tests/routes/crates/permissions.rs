@@ -0,0 +1,149 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::models::{NewCrateFreeze, NewCriticalCrate};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/crates/foo/permissions";
+
+fn allowed(json: &Value) -> Vec<(&str, bool)> {
+    let permissions = json["permissions"].as_object().unwrap();
+    permissions
+        .iter()
+        .map(|(action, permission)| (action.as_str(), permission["allowed"].as_bool().unwrap()))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owners_have_all_permissions() {
+    let (app, anon, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = owner.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"permissions":{"change_settings":{"allowed":true,"reason":null},"manage_owners":{"allowed":true,"reason":null},"publish":{"allowed":true,"reason":null},"yank":{"allowed":true,"reason":null}}}"###);
+
+    let response = owner.get::<()>("/api/v1/crates/unknown/permissions").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn other_users_have_no_permissions() {
+    let (app, _, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let json = other.get::<Value>(URL).await.good();
+    assert_eq!(
+        allowed(&json),
+        [
+            ("change_settings", false),
+            ("manage_owners", false),
+            ("publish", false),
+            ("yank", false),
+        ]
+    );
+    assert_snapshot!(json["permissions"]["publish"]["reason"], @r###""this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing.""###);
+    assert_eq!(
+        json["permissions"]["manage_owners"]["reason"],
+        "only owners have permission to modify owners"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_scopes_are_respected() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        let user_id = owner.as_model().id;
+        CrateBuilder::new("foo", user_id).expect_build(conn);
+        CrateBuilder::new("bar", user_id).expect_build(conn);
+    });
+
+    let endpoint_scopes = Some(vec![EndpointScope::PublishUpdate, EndpointScope::Yank]);
+    let token = owner.db_new_scoped_token("ci", None, endpoint_scopes, None);
+
+    let json = token.get::<Value>(URL).await.good();
+    assert_eq!(
+        allowed(&json),
+        [
+            ("change_settings", false),
+            ("manage_owners", false),
+            ("publish", true),
+            ("yank", true),
+        ]
+    );
+    assert_eq!(
+        json["permissions"]["manage_owners"]["reason"],
+        "this token does not have the required permissions to perform this action"
+    );
+
+    let crate_scopes = Some(vec![CrateScope::try_from("bar").unwrap()]);
+    let endpoint_scopes = Some(vec![EndpointScope::PublishUpdate]);
+    let token = owner.db_new_scoped_token("bar", crate_scopes, endpoint_scopes, None);
+
+    let json = token.get::<Value>(URL).await.good();
+    assert_eq!(json["permissions"]["publish"]["allowed"], false);
+
+    let json = token
+        .get::<Value>("/api/v1/crates/bar/permissions")
+        .await
+        .good();
+    assert_eq!(json["permissions"]["publish"]["allowed"], true);
+
+    // Legacy tokens can be used for all endpoints
+    let token = owner.db_new_token("legacy");
+    let json = token.get::<Value>(URL).await.good();
+    assert!(allowed(&json).iter().all(|(_, allowed)| *allowed));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn freezes_and_critical_crates_are_respected() {
+    let (app, _, owner) = TestApp::init().with_user();
+    let user_id = owner.as_model().id;
+
+    let krate = app.db(|conn| CrateBuilder::new("foo", user_id).expect_build(conn));
+
+    app.db(|conn| {
+        let critical = NewCriticalCrate {
+            crate_id: krate.id,
+            designated_by: user_id,
+        };
+        critical.upsert(conn).unwrap();
+    });
+
+    let json = owner.get::<Value>(URL).await.good();
+    assert_eq!(
+        allowed(&json),
+        [
+            ("change_settings", true),
+            ("manage_owners", false),
+            ("publish", false),
+            ("yank", false),
+        ]
+    );
+    assert_snapshot!(json["permissions"]["publish"]["reason"], @r###""The crate `foo` is a critical crate, so all of its owners must have two-factor authentication enabled on their GitHub account. Please enable it at https://github.com/settings/security and sign in to crates.io again.""###);
+
+    app.db(|conn| {
+        let freeze = NewCrateFreeze {
+            crate_id: krate.id,
+            reason: "malware",
+            frozen_by: user_id,
+        };
+        freeze.upsert(conn).unwrap();
+    });
+
+    let json = owner.get::<Value>(URL).await.good();
+    assert_snapshot!(json["permissions"]["publish"]["reason"], @r###""The crate `foo` has been frozen by the crates.io team pending an investigation, so it can not be changed at the moment (reason: malware). Please contact help@crates.io for more information.""###);
+}
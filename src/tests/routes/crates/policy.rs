@@ -0,0 +1,76 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_and_show_policy() {
+    let (app, anon, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/foo/policy").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null}}"###);
+
+    let body = json!({
+        "policy": {
+            "security_contact": "security@example.com",
+            "msrv_policy": "The last three stable Rust versions are supported.",
+        }
+    });
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":null},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":"The last three stable Rust versions are supported.","security_contact":"security@example.com"},"prerelease_retention_days":null}}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/policy").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"policy":{"disclosure_policy":null,"msrv_policy":"The last three stable Rust versions are supported.","security_contact":"security@example.com"}}"###);
+
+    // Missing fields are not changed, while empty strings remove the document
+    let body = json!({
+        "policy": {
+            "security_contact": "https://example.com/security",
+            "msrv_policy": "",
+        }
+    });
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/policy").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":"https://example.com/security"}}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/bar/policy").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_policy() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "policy": { "security_contact": "http://example.com/security" } });
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`security_contact` must be an email address or an https:// URL (contact: http://example.com/security)"}]}"###);
+
+    let body = json!({ "policy": { "disclosure_policy": "a".repeat(10_001) } });
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`disclosure_policy` must not be longer than 10000 characters"}]}"###);
+}
@@ -0,0 +1,114 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+const VERIFY_URL: &str = "/api/v1/crates/foo/repository/verify";
+
+fn manifest(version: &str, repository: &str) -> String {
+    format!(
+        r#"[package]
+        name = "foo"
+        version = "{version}"
+        description = "description"
+        license = "MIT"
+        repository = "{repository}""#
+    )
+}
+
+async fn verified_repository(user: &impl RequestHelper) -> serde_json::Value {
+    let json = user.get::<()>("/api/v1/crates/foo").await.json();
+    json["crate"]["verified_repository"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_repository() {
+    let (_app, anon, owner, token) = TestApp::full().with_token();
+
+    let repository = "https://github.com/rust-lang/foo";
+    let pb = PublishBuilder::new("foo", "1.0.0").custom_manifest(manifest("1.0.0", repository));
+    token.publish_crate(pb).await.good();
+    assert_eq!(verified_repository(&anon).await, false);
+
+    let response = owner.put::<()>(VERIFY_URL, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["repository"], repository);
+    assert_eq!(verified_repository(&anon).await, true);
+
+    // Publishing with the same repository keeps the verification
+    let pb = PublishBuilder::new("foo", "1.1.0").custom_manifest(manifest("1.1.0", repository));
+    token.publish_crate(pb).await.good();
+    assert_eq!(verified_repository(&anon).await, true);
+
+    // Changing the repository resets the verification
+    let repository = "https://github.com/rust-lang/bar";
+    let pb = PublishBuilder::new("foo", "1.2.0").custom_manifest(manifest("1.2.0", repository));
+    token.publish_crate(pb).await.good();
+    assert_eq!(verified_repository(&anon).await, false);
+
+    let response = owner.put::<()>(VERIFY_URL, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"could not find a `.well-known/crates-io` file in the default branch of https://github.com/rust-lang/bar"}]}"###);
+    assert_eq!(verified_repository(&anon).await, false);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_repository_requires_listed_crate() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("bar", owner.as_model().id)
+            .repository("https://github.com/rust-lang/foo")
+            .expect_build(conn);
+    });
+
+    let response = owner
+        .put::<()>("/api/v1/crates/bar/repository/verify", "")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the `.well-known/crates-io` file of https://github.com/rust-lang/foo does not list the `bar` crate"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_repository_requires_github_repository() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo-derive", owner.as_model().id)
+            .repository("https://gitlab.com/rust-lang/foo")
+            .expect_build(conn);
+    });
+
+    let response = owner.put::<()>(VERIFY_URL, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this crate does not declare a repository"}]}"###);
+
+    let response = owner
+        .put::<()>("/api/v1/crates/foo-derive/repository/verify", "")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only GitHub repositories can be verified"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_verify_repository() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id)
+            .repository("https://github.com/rust-lang/foo")
+            .expect_build(conn);
+    });
+
+    let response = anon.put::<()>(VERIFY_URL, "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = other.put::<()>(VERIFY_URL, "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to verify the repository of a crate"}]}"###);
+
+    let response = owner.put::<()>(VERIFY_URL, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
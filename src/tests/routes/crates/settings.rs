@@ -0,0 +1,206 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::Crate;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_settings() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let response = owner.get::<()>("/api/v1/crates/foo/settings").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":null},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":null}}"###);
+
+    let response = other.get::<()>("/api/v1/crates/foo/settings").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to view the settings of a crate"}]}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/settings").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_prerelease_retention() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "prerelease_retention_days": 30 }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":null},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":30}}"###);
+
+    // Missing fields are not changed
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", "{}").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":null},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":30}}"###);
+
+    let body = json!({ "prerelease_retention_days": null }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":null},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":null}}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_maintenance_wanted() {
+    let (app, anon, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "maintenance_wanted": true }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["settings"]["maintenance_wanted"], true);
+
+    let json = anon.search("maintenance_wanted=true").await;
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "foo");
+    let flagged_at = json.crates[0].maintenance_wanted_at;
+    assert_some!(flagged_at);
+
+    // Flagging the crate again keeps the original date
+    let body = json!({ "maintenance_wanted": true }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = anon.search("maintenance_wanted=true").await;
+    assert_eq!(json.crates[0].maintenance_wanted_at, flagged_at);
+
+    let body = json!({ "maintenance_wanted": false }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["settings"]["maintenance_wanted"], false);
+
+    let json = anon.search("maintenance_wanted=true").await;
+    assert_eq!(json.meta.total, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_prerelease_retention() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "prerelease_retention_days": 0 }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`prerelease_retention_days` must be between 1 and 3650"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_denied_dependencies() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({
+        "denied_dependencies": [
+            { "name": "openssl", "version_req": "<1" },
+            { "name": "left-pad" },
+        ]
+    });
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[{"name":"openssl","version_req":"<1"},{"name":"left-pad","version_req":null}],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":null},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":null}}"###);
+
+    let body = json!({ "denied_dependencies": [] }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json()["settings"]["denied_dependencies"],
+        json!([])
+    );
+
+    let body = json!({ "denied_dependencies": [{ "name": "openssl", "version_req": "<<1" }] });
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.to_string())
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"\"<<1\" is an invalid version requirement"}]}"###);
+
+    let body = json!({ "denied_dependencies": [{ "name": "" }] }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_change_settings() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "prerelease_retention_days": 30 }).to_string();
+
+    let response = anon
+        .patch::<()>("/api/v1/crates/foo/settings", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = other
+        .patch::<()>("/api/v1/crates/foo/settings", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to change the settings of a crate"}]}"###);
+
+    let response = anon.patch::<()>("/api/v1/crates/bar/settings", body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_change_limits() {
+    let (app, _, owner) = TestApp::init().with_user();
+    let admin = app.db_new_user("admin");
+    admin.make_admin();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "limits": { "max_upload_size": 20_000_000 } }).to_string();
+    let response = owner
+        .patch::<()>("/api/v1/crates/foo/settings", body.clone())
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only crates.io admins have permission to change the limits of a crate"}]}"###);
+
+    let response = admin.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":20000000},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":null}}"###);
+
+    let max_upload_size = app.db(|conn| {
+        let krate: Crate = Crate::by_name("foo").first(conn).unwrap();
+        krate.max_upload_size
+    });
+    assert_eq!(max_upload_size, Some(20_000_000));
+
+    // Owners can still change the other settings, and see the limits
+    let body = json!({ "prerelease_retention_days": 30 }).to_string();
+    let response = owner.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"settings":{"denied_dependencies":[],"limits":{"max_dependencies":null,"max_feature_values":null,"max_features":null,"max_upload_size":20000000},"maintenance_wanted":false,"policy":{"disclosure_policy":null,"msrv_policy":null,"security_contact":null},"prerelease_retention_days":30}}"###);
+
+    let body = json!({ "limits": { "max_upload_size": null, "max_features": 0 } }).to_string();
+    let response = admin.patch::<()>("/api/v1/crates/foo/settings", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"limits must be positive numbers"}]}"###);
+}
@@ -0,0 +1,162 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+async fn crate_json(user: &impl RequestHelper, name: &str) -> Value {
+    let url = format!("/api/v1/crates/{name}");
+    user.get::<()>(&url).await.json()["crate"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owner_of_both_crates() {
+    let (app, anon, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("legacy", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("modern", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "successor": "modern" }).to_string();
+    let response = owner
+        .put::<()>("/api/v1/crates/legacy/successor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["succession"]["predecessor"], "legacy");
+    assert_eq!(json["succession"]["successor"], "modern");
+    assert_eq!(json["succession"]["confirmed"], true);
+
+    let json = crate_json(&anon, "legacy").await;
+    assert_eq!(json["superseded_by"], "modern");
+    assert!(json.get("supersedes").is_none());
+
+    let json = crate_json(&anon, "modern").await;
+    assert!(json.get("superseded_by").is_none());
+    assert_eq!(json["supersedes"], json!(["legacy"]));
+
+    // Relations cannot form a cycle
+    let body = json!({ "successor": "legacy" }).to_string();
+    let response = owner
+        .put::<()>("/api/v1/crates/modern/successor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`legacy` cannot supersede `modern`, because it is already superseded by it"}]}"###);
+
+    let response = owner.delete::<()>("/api/v1/crates/legacy/successor").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = crate_json(&anon, "legacy").await;
+    assert!(json.get("superseded_by").is_none());
+
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requires_confirmation_of_other_owners() {
+    let (app, anon, legacy_owner) = TestApp::init().with_user();
+    let modern_owner = app.db_new_user("modern-owner");
+
+    app.db(|conn| {
+        CrateBuilder::new("legacy", legacy_owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("modern", modern_owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "successor": "modern" }).to_string();
+    let response = legacy_owner
+        .put::<()>("/api/v1/crates/legacy/successor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["succession"]["confirmed"], false);
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+
+    // Unconfirmed relations are not public
+    let json = crate_json(&anon, "legacy").await;
+    assert!(json.get("superseded_by").is_none());
+
+    let url = "/api/v1/crates/modern/predecessors/legacy";
+    let response = modern_owner.put::<()>(url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["succession"]["confirmed"], true);
+
+    let json = crate_json(&anon, "legacy").await;
+    assert_eq!(json["superseded_by"], "modern");
+
+    let response = modern_owner.delete::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = crate_json(&anon, "modern").await;
+    assert!(json.get("supersedes").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn successor_chosen_by_predecessor_owners() {
+    let (app, _, legacy_owner) = TestApp::init().with_user();
+    let other_owner = app.db_new_user("other-owner");
+
+    app.db(|conn| {
+        CrateBuilder::new("legacy", legacy_owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("modern", legacy_owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("other", other_owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "successor": "modern" }).to_string();
+    let response = legacy_owner
+        .put::<()>("/api/v1/crates/legacy/successor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = "/api/v1/crates/other/predecessors/legacy";
+    let response = other_owner.put::<()>(url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the owners of `legacy` have already declared a different successor for it"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_relations() {
+    let (app, _, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "successor": "foo" }).to_string();
+    let response = owner.put::<()>("/api/v1/crates/foo/successor", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a crate cannot supersede itself"}]}"###);
+
+    let body = json!({ "successor": "missing" }).to_string();
+    let response = owner.put::<()>("/api/v1/crates/foo/successor", body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = json!({ "successor": "foo" }).to_string();
+    let response = other.put::<()>("/api/v1/crates/foo/successor", body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to change the successor of a crate"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn superseded_crates_are_ranked_lower() {
+    let (app, anon, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        for name in ["parser-old", "parser-new"] {
+            CrateBuilder::new(name, owner.as_model().id)
+                .description("A fast parser")
+                .expect_build(conn);
+        }
+    });
+
+    let body = json!({ "successor": "parser-new" }).to_string();
+    let response = owner
+        .put::<()>("/api/v1/crates/parser-old/successor", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = anon.search("q=fast%20parser").await;
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "parser-new");
+    assert_eq!(json.crates[1].name, "parser-old");
+}
@@ -0,0 +1,111 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crates_io::models::NewAttestationProvider;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/v1/crates/foo/1.0.0/attestations";
+
+fn attestation(kind: &str) -> String {
+    json!({
+        "kind": kind,
+        "statement": "{\"result\":\"reproducible\"}",
+        "signature": "c2lnbmF0dXJl",
+        "url": "https://rebuilders.example.com/foo/1.0.0",
+    })
+    .to_string()
+}
+
+/// Creates the `foo` crate, and an approved attestation provider.
+fn setup() -> (TestApp, MockCookieUser, MockCookieUser) {
+    let (app, _, user) = TestApp::init().with_user();
+    let provider = app.db_new_user("rebuilder");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        NewAttestationProvider {
+            user_id: provider.as_model().id,
+            name: "Rebuilders Inc.",
+            approved_by: user.as_model().id,
+        }
+        .upsert(conn)
+        .unwrap();
+    });
+
+    (app, user, provider)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn providers_can_submit_attestations() {
+    let (app, _, provider) = setup();
+
+    let json: Value = provider
+        .post(URL, attestation("reproducible_build"))
+        .await
+        .good();
+    assert_eq!(json["attestation"]["kind"], "reproducible_build");
+    assert_eq!(json["attestation"]["provider"], "Rebuilders Inc.");
+    assert_eq!(json["attestation"]["provider_login"], "rebuilder");
+
+    provider
+        .post::<Value>(URL, attestation("audit"))
+        .await
+        .good();
+
+    let other = app.db_new_user("other");
+    let json: Value = other.get(URL).await.good();
+    let attestations = json["attestations"].as_array().unwrap();
+    assert_eq!(attestations.len(), 2);
+    assert_eq!(attestations[0]["kind"], "audit");
+    assert_eq!(attestations[1]["kind"], "reproducible_build");
+    assert_eq!(attestations[1]["signature"], "c2lnbmF0dXJl");
+    assert_eq!(
+        attestations[1]["url"],
+        "https://rebuilders.example.com/foo/1.0.0"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_providers_can_submit_attestations() {
+    let (_, user, _) = setup();
+
+    let response = user.post::<()>(URL, attestation("audit")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only approved attestation providers can submit attestations"}]}"###);
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(json["attestations"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_attestations() {
+    let (_, _, provider) = setup();
+
+    let body = json!({ "kind": "audit", "statement": "", "signature": "abc" }).to_string();
+    let response = provider.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`statement` must be between 1 and 65536 bytes"}]}"###);
+
+    let body = json!({
+        "kind": "audit",
+        "statement": "ok",
+        "signature": "abc",
+        "url": "javascript:alert(1)",
+    })
+    .to_string();
+    let response = provider.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`url` is not a valid https URL: `javascript:alert(1)`"}]}"###);
+
+    let response = provider
+        .post::<()>(
+            "/api/v1/crates/foo/2.0.0/attestations",
+            attestation("audit"),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,76 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+async fn latest_in_channel(anon: &impl RequestHelper, crate_name: &str, channel: &str) -> Value {
+    let url = format!("/api/v1/crates/{crate_name}/channels/{channel}/latest");
+    let json: Value = anon.get(&url).await.good();
+    assert_eq!(json["version"]["channel"], channel);
+    json["version"]["num"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn returns_highest_version_in_channel() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("1.1.0-beta.1").channel("beta"))
+            .version(VersionBuilder::new("1.1.0-beta.2").channel("beta"))
+            .version(
+                VersionBuilder::new("1.1.0-beta.3")
+                    .channel("beta")
+                    .yanked(true),
+            )
+            .version(VersionBuilder::new("1.2.0-nightly.1").channel("nightly"))
+            .version("1.1.0")
+            .expect_build(conn);
+    });
+
+    assert_eq!(
+        latest_in_channel(&anon, "foo", "beta").await,
+        "1.1.0-beta.2"
+    );
+    assert_eq!(
+        latest_in_channel(&anon, "foo", "nightly").await,
+        "1.2.0-nightly.1"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_channel() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version("1.0.0")
+            .version(
+                VersionBuilder::new("1.1.0-beta.1")
+                    .channel("beta")
+                    .yanked(true),
+            )
+            .expect_build(conn);
+    });
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/channels/beta/latest")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not have any versions in the `beta` channel"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/channels/beta/latest")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
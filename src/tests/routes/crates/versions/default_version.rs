@@ -0,0 +1,56 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+async fn default_version(anon: &impl RequestHelper, crate_name: &str) -> Value {
+    let url = format!("/api/v1/crates/{crate_name}/default_version");
+    let json: Value = anon.get(&url).await.good();
+    json["version"]["num"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prefers_highest_stable_version() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .version("2.0.0-beta.1")
+            .version(VersionBuilder::new("1.2.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    assert_eq!(default_version(&anon, "foo").await, "1.1.0");
+
+    let json: Value = anon.get("/api/v1/crates/foo").await.good();
+    assert_eq!(json["crate"]["default_version"], "1.1.0");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn falls_back_to_prerelease() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version("1.0.0-alpha.1")
+            .version("1.0.0-beta.1")
+            .version(VersionBuilder::new("0.1.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    assert_eq!(default_version(&anon, "foo").await, "1.0.0-beta.1");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/crates/foo/default_version").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
@@ -1,5 +1,13 @@
-use crate::builders::{CrateBuilder, VersionBuilder};
-use crate::util::{RequestHelper, TestApp};
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{MockRequestExt, RequestHelper, Response, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::models::{CrateVisibility, NewMirror};
+use crates_io::schema::{crates, mirrors};
+use diesel::prelude::*;
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+use serde_json::json;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_redirects() {
@@ -51,3 +59,177 @@ async fn download_with_build_metadata() {
         .await
         .assert_redirect_ends_with("/readmes/foo/foo-1.0.0%2Bbar.html");
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn redirects_to_mirrors_in_the_client_region() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.mirror_redirects = true)
+        .with_user();
+    let user_id = user.as_model().id;
+
+    let two_hours_ago = (Utc::now() - Duration::hours(2)).naive_utc();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id)
+            .version(VersionBuilder::new("1.0.0").created_at(two_hours_ago))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+
+        let secret = CrateBuilder::new("secret", user_id)
+            .version(VersionBuilder::new("1.0.0").created_at(two_hours_ago))
+            .expect_build(conn);
+
+        diesel::update(crates::table.find(secret.id))
+            .set(crates::visibility.eq(CrateVisibility::Private))
+            .execute(conn)
+            .unwrap();
+
+        let new_mirror = NewMirror {
+            name: "eu",
+            base_url: "https://eu.mirror.example.com/",
+            region: "EU",
+        };
+        let mirror = new_mirror.insert(conn).unwrap();
+
+        diesel::update(&mirror)
+            .set(mirrors::verified_at.eq(Utc::now().naive_utc()))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let download = |path: &str, country: Option<&str>| {
+        let mut request = anon.get_request(path);
+        if let Some(country) = country {
+            request.header("cloudfront-viewer-country", country);
+        }
+        anon.run::<()>(request)
+    };
+
+    download("/api/v1/crates/foo/1.0.0/download", Some("DE"))
+        .await
+        .assert_redirect_ends_with("https://eu.mirror.example.com/crates/foo/foo-1.0.0.crate");
+
+    // Clients in other regions, or in unknown regions, are redirected to the
+    // primary storage
+    download("/api/v1/crates/foo/1.0.0/download", Some("US"))
+        .await
+        .assert_redirect_ends_with("https://static.crates.io/crates/foo/foo-1.0.0.crate");
+
+    download("/api/v1/crates/foo/1.0.0/download", None)
+        .await
+        .assert_redirect_ends_with("https://static.crates.io/crates/foo/foo-1.0.0.crate");
+
+    // The mirror might not have synchronized new versions yet
+    download("/api/v1/crates/foo/1.1.0/download", Some("DE"))
+        .await
+        .assert_redirect_ends_with("https://static.crates.io/crates/foo/foo-1.1.0.crate");
+
+    // Private crates are not available on mirrors
+    let mut request = user.get_request("/api/v1/crates/secret/1.0.0/download");
+    request.header("cloudfront-viewer-country", "DE");
+    user.run::<()>(request)
+        .await
+        .assert_redirect_ends_with("https://static.crates.io/crates/secret/secret-1.0.0.crate");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn downloads_require_authentication_if_configured() {
+    let (app, anon, user, token) = TestApp::full()
+        .with_config(|config| config.download_auth_required = true)
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("secret", "1.0.0"))
+        .await
+        .good();
+
+    let body = json!({ "visibility": "private" }).to_string();
+    let response = user
+        .put::<()>("/api/v1/crates/secret/visibility", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    // The crate files are served by the API instead of being redirected to
+    // the CDN, which can't check the authentication
+    let response = user.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_crate_file(&response);
+
+    // Legacy tokens and tokens with the `download` scope can download all
+    // crates that are visible to their owner
+    let response = token.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_crate_file(&response);
+
+    let endpoint_scopes = Some(vec![EndpointScope::Download]);
+    let token = user.db_new_scoped_token("download", None, endpoint_scopes, None);
+    let response = token.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_crate_file(&response);
+    let response = token
+        .get::<()>("/api/v1/crates/secret/1.0.0/download")
+        .await;
+    assert_crate_file(&response);
+
+    // Tokens of other users can not download private crates
+    let other = app.db_new_user("other").db_new_token("other");
+    let response = other
+        .get::<()>("/api/v1/crates/secret/1.0.0/download")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Tokens without the `download` scope are rejected
+    let endpoint_scopes = Some(vec![EndpointScope::PublishUpdate]);
+    let token = user.db_new_scoped_token("publish", None, endpoint_scopes, None);
+    let response = token.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Crate scopes are respected
+    let crate_scopes = Some(vec![CrateScope::try_from("bar").unwrap()]);
+    let endpoint_scopes = Some(vec![EndpointScope::Download]);
+    let token = user.db_new_scoped_token("bar", crate_scopes, endpoint_scopes, None);
+    let response = token.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sparse_index_requires_authentication_if_configured() {
+    let (_, anon, user, token) = TestApp::full()
+        .with_config(|config| config.download_auth_required = true)
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    // The config is public, so that cargo can discover that it has to send
+    // its token
+    let json: serde_json::Value = anon.get("/index/config.json").await.good();
+    assert_eq!(json["auth-required"], true);
+
+    let response = anon.get::<()>("/index/3/f/foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let endpoint_scopes = Some(vec![EndpointScope::Download]);
+    let token = user.db_new_scoped_token("download", None, endpoint_scopes, None);
+    let response = token.get::<()>("/index/3/f/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().contains(r#""name":"foo""#));
+
+    let response = token.get::<()>("/index/3/b/bar").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[track_caller]
+fn assert_crate_file(response: &Response<()>) {
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+    assert!(!response.bytes().is_empty());
+}
@@ -0,0 +1,66 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use flate2::read::GzDecoder;
+use http::{header, StatusCode};
+use serde_json::Value;
+use std::io::Read;
+
+const URL: &str = "/api/v1/crates/foo_export/versions/export";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_contains_all_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let dep = CrateBuilder::new("bar_export", user.id).expect_build(conn);
+        CrateBuilder::new("foo_export", user.id)
+            .version(VersionBuilder::new("1.0.0").checksum("abc"))
+            .version(VersionBuilder::new("1.1.0").yanked(true))
+            .version(VersionBuilder::new("2.0.0").dependency(&dep, None))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"foo_export-versions.jsonl.gz\""
+    );
+
+    let mut jsonl = String::new();
+    GzDecoder::new(response.bytes().as_ref())
+        .read_to_string(&mut jsonl)
+        .unwrap();
+
+    let lines = jsonl
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+
+    assert_eq!(lines[0]["num"], "1.0.0");
+    assert_eq!(lines[0]["crate"], "foo_export");
+    assert_eq!(lines[0]["checksum"], "abc");
+    assert_eq!(lines[0]["yanked"], false);
+    assert_eq!(lines[0]["dependencies"], Value::Array(vec![]));
+
+    assert_eq!(lines[1]["num"], "1.1.0");
+    assert_eq!(lines[1]["yanked"], true);
+
+    assert_eq!(lines[2]["num"], "2.0.0");
+    let dependencies = lines[2]["dependencies"].as_array().unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(dependencies[0]["crate_id"], "bar_export");
+    assert!(lines[2]["created_at"].is_string());
+    assert!(lines[2]["features"].is_object());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn export_of_unknown_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,81 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/v1/crates/foo/1.0.0/flag";
+
+fn flag_body(message: &str) -> Vec<u8> {
+    serde_json::to_vec(&json!({ "message": message })).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owners_can_flag_versions() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    token
+        .put::<OkBool>(URL, flag_body("Breaks on 32-bit targets, see #123"))
+        .await
+        .good();
+
+    let json = anon.get::<Value>("/api/v1/crates/foo/1.0.0").await.good();
+    assert_eq!(json["version"]["yanked"], false);
+    assert_eq!(
+        json["version"]["flag"]["message"],
+        "Breaks on 32-bit targets, see #123"
+    );
+    assert!(json["version"]["flag"]["flagged_at"].is_string());
+
+    // Flags don't affect dependency resolution
+    app.run_pending_background_jobs().await;
+    let crates = app.crates_from_index_head("foo");
+    assert_eq!(crates[0].yanked, Some(false));
+
+    token.delete::<OkBool>(URL).await.good();
+
+    let json = anon.get::<Value>("/api/v1/crates/foo/1.0.0").await.good();
+    assert!(json["version"].get("flag").is_none());
+
+    let json = anon.get::<Value>("/api/v1/changes").await.good();
+    let kinds = json["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["kind"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, ["publish", "flag", "unflag"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn flags_are_validated() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let response = token.put::<()>(URL, flag_body("  ")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the message must not be empty"}]}"###);
+
+    let response = token.put::<()>(URL, flag_body(&"x".repeat(1001))).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the message must not be longer than 1000 characters"}]}"###);
+
+    let other = app.db_new_user("other");
+    let response = other.put::<()>(URL, flag_body("not mine")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user
+        .put::<()>("/api/v1/crates/foo/2.0.0/flag", flag_body("missing"))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
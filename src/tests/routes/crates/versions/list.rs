@@ -198,6 +198,41 @@ async fn test_seek_based_pagination_semver_sorting() {
     assert_eq!(json.meta.total, 0);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_size_sorting() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_versions", user.id)
+            .version(VersionBuilder::new("0.1.0").size(100))
+            .version(VersionBuilder::new("0.2.0").size(300))
+            .version(VersionBuilder::new("0.3.0").size(200))
+            .version(VersionBuilder::new("0.4.0").size(300))
+            .expect_build(conn);
+
+        // Mimic a version published before we started recording sizes
+        let none: Option<i32> = None;
+        update(versions::table)
+            .filter(versions::num.eq("0.1.0"))
+            .set(versions::crate_size.eq(none))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let url = "/api/v1/crates/foo_versions/versions?sort=size";
+    let expects = ["0.4.0", "0.2.0", "0.3.0", "0.1.0"];
+
+    let json: AllVersions = anon.get(url).await.good();
+    assert_eq!(nums(&json.versions), expects);
+
+    let (resp, calls) = page_with_seek(&anon, url).await;
+    for (json, expect) in resp.iter().zip(expects) {
+        assert_eq!(json.versions[0].num, expect);
+        assert_eq!(json.meta.total as usize, expects.len());
+    }
+    assert_eq!(calls as usize, expects.len() + 1);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn invalid_seek_parameter() {
     let (app, anon, user) = TestApp::init().with_user();
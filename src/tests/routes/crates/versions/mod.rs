@@ -1,6 +1,16 @@
+mod attestations;
 mod authors;
+mod channels;
+mod default_version;
 pub mod dependencies;
 pub mod download;
+mod export;
+mod flag;
 mod list;
+mod outdated_deps;
 mod read;
+mod readme;
+mod reproducibility;
+mod yank_confirmation;
+mod yank_history;
 pub mod yank_unyank;
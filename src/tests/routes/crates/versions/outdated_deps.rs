@@ -0,0 +1,58 @@
+use crate::builders::{DependencyBuilder, PublishBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::views::EncodableOutdatedDependency;
+use http::StatusCode;
+
+#[derive(Deserialize)]
+struct OutdatedDeps {
+    dependencies: Vec<EncodableOutdatedDependency>,
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn outdated_dependencies() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    for (name, num) in [
+        ("foo_lib", "1.0.0"),
+        ("foo_lib", "1.2.0"),
+        ("foo_lib", "2.0.0"),
+        ("foo_lib", "2.1.0"),
+        ("foo_lib", "3.0.0-beta.1"),
+        ("foo_util", "0.1.0"),
+    ] {
+        token
+            .publish_crate(PublishBuilder::new(name, num))
+            .await
+            .good();
+    }
+    token.yank("foo_lib", "2.1.0").await.good();
+
+    let app = PublishBuilder::new("foo_app", "1.0.0")
+        .dependency(DependencyBuilder::new("foo_lib").version_req("^1.0.0"))
+        .dependency(DependencyBuilder::new("foo_util").version_req("^0.1.0"));
+    token.publish_crate(app).await.good();
+
+    let json: OutdatedDeps = anon
+        .get("/api/v1/crates/foo_app/1.0.0/outdated_deps")
+        .await
+        .good();
+
+    let lib = &json.dependencies[0];
+    assert_eq!(lib.crate_id, "foo_lib");
+    assert_eq!(lib.req, "^1.0.0");
+    assert_eq!(lib.latest_compatible.as_deref(), Some("1.2.0"));
+    assert_eq!(lib.latest.as_deref(), Some("2.0.0"));
+    assert!(lib.outdated);
+
+    let util = &json.dependencies[1];
+    assert_eq!(util.crate_id, "foo_util");
+    assert_eq!(util.latest_compatible.as_deref(), Some("0.1.0"));
+    assert_eq!(util.latest.as_deref(), Some("0.1.0"));
+    assert!(!util.outdated);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo_app/1.0.1/outdated_deps")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,39 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use http::header;
+use serde_json::{json, Value};
+
+const README: &str = "# foo\n\n## Usage\n\nRun `foo`.\n\n## Usage\n";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readme_includes_table_of_contents() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .readme(README)
+        .add_file("foo-1.0.0/README.md", README);
+    user.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    let mut request = anon.get_request("/api/v1/crates/foo/1.0.0/readme");
+    request.header(header::ACCEPT, "application/json");
+    let json: Value = anon.run(request).await.good();
+    assert!(json["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("/readmes/foo/foo-1.0.0.html"));
+    assert_eq!(
+        json["table_of_contents"],
+        json!([
+            { "level": 1, "text": "foo", "anchor": "#foo" },
+            { "level": 2, "text": "Usage", "anchor": "#usage" },
+            { "level": 2, "text": "Usage", "anchor": "#usage-1" },
+        ])
+    );
+
+    // Unknown versions have no table of contents
+    let mut request = anon.get_request("/api/v1/crates/foo/2.0.0/readme");
+    request.header(header::ACCEPT, "application/json");
+    let json: Value = anon.run(request).await.good();
+    assert_eq!(json["table_of_contents"], Value::Null);
+}
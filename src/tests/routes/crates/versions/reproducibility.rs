@@ -0,0 +1,111 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const CHECKSUM: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+const OTHER_CHECKSUM: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+const URL: &str = "/api/v1/crates/foo/1.0.0/reproducibility";
+
+fn report(checksum: &str) -> String {
+    json!({ "checksum": checksum, "details": "https://example.com/build/1" }).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_normalized_checksum() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").normalized_cksum(CHECKSUM);
+    token.publish_crate(crate_to_publish).await.good();
+
+    let json: Value = anon.get("/api/v1/crates/foo/1.0.0").await.good();
+    assert_eq!(json["version"]["normalized_checksum"], CHECKSUM);
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json["normalized_checksum"], CHECKSUM);
+    assert_eq!(json["reports"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_invalid_normalized_checksum() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").normalized_cksum("abc");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `normalized_cksum` value: expected a hex-encoded SHA256 checksum"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn matching_and_mismatching_reports() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").normalized_checksum(CHECKSUM))
+            .expect_build(conn);
+    });
+
+    let json: Value = user.put(URL, report(CHECKSUM)).await.good();
+    assert_eq!(json["report"]["matches"], true);
+    assert_eq!(json["report"]["reporter"]["login"], "foo");
+
+    let other = app.db_new_user("bar");
+    let json: Value = other.put(URL, report(OTHER_CHECKSUM)).await.good();
+    assert_eq!(json["report"]["matches"], false);
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json["reports"].as_array().unwrap().len(), 2);
+    assert_eq!(json["meta"], json!({ "matching": 1, "mismatching": 1 }));
+
+    // Submitting another report replaces the previous one of the same user
+    let json: Value = other.put(URL, report(CHECKSUM)).await.good();
+    assert_eq!(json["report"]["matches"], true);
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json["reports"].as_array().unwrap().len(), 2);
+    assert_eq!(json["meta"], json!({ "matching": 2, "mismatching": 0 }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn version_without_normalized_checksum() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let response = user.put::<()>(URL, report(CHECKSUM)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` version `1.0.0` does not have a normalized checksum"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_reports() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").normalized_checksum(CHECKSUM))
+            .expect_build(conn);
+    });
+
+    let response = anon.put::<()>(URL, report(CHECKSUM)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.put::<()>(URL, report("not a checksum")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `checksum` value: expected a hex-encoded SHA256 checksum"}]}"###);
+
+    let response = user
+        .put::<()>("/api/v1/crates/foo/2.0.0/reproducibility", report(CHECKSUM))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json["reports"], json!([]));
+}
@@ -0,0 +1,101 @@
+use crate::builders::PublishBuilder;
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::{pending_yanks, version_downloads, versions};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+
+fn add_downloads(app: &TestApp, num: &str, downloads: i32) {
+    app.db(|conn| {
+        let version_id: i32 = versions::table
+            .filter(versions::num.eq(num))
+            .select(versions::id)
+            .get_result(conn)
+            .unwrap();
+
+        diesel::insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version_id),
+                version_downloads::downloads.eq(downloads),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn is_yanked(app: &TestApp, num: &str) -> bool {
+    app.db(|conn| {
+        versions::table
+            .filter(versions::num.eq(num))
+            .select(versions::yanked)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+fn pending_token(app: &TestApp) -> String {
+    app.db(|conn| {
+        pending_yanks::table
+            .select(pending_yanks::token)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn popular_version_yank_is_held_until_confirmed() {
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.yank_confirmation_downloads = Some(100))
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    add_downloads(&app, "1.0.0", 500);
+
+    let response = token.yank("foo", "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Yanking foo v1.0.0 requires confirmation, since it was downloaded 500 times within the last 90 days. A confirmation link was sent to the owners of the crate. The link expires in 24 hours."}]}"###);
+
+    assert!(!is_yanked(&app, "1.0.0"));
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    let email = emails.last().unwrap();
+    assert!(email.1.contains("Please confirm the yank of your crate"));
+
+    let url = format!("/api/v1/confirm_yank/{}", pending_token(&app));
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    assert!(is_yanked(&app, "1.0.0"));
+    assert!(app.crates_from_index_head("foo")[0].yanked.unwrap());
+
+    // The confirmation token can only be used once
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Pending yank belonging to token not found."}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_below_threshold_is_not_held() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.yank_confirmation_downloads = Some(100))
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    add_downloads(&app, "1.0.0", 50);
+
+    token.yank("foo", "1.0.0").await.good();
+    assert!(is_yanked(&app, "1.0.0"));
+
+    // Unyanking never requires a confirmation
+    add_downloads(&app, "1.0.0", 500);
+    token.unyank("foo", "1.0.0").await.good();
+    assert!(!is_yanked(&app, "1.0.0"));
+}
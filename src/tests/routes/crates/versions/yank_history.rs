@@ -0,0 +1,113 @@
+use crate::builders::PublishBuilder;
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::insta::id_redaction;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_history() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let url = "/api/v1/crates/foo/1.0.0/yank?reason=Contains%20a%20soundness%20bug";
+    token.delete::<OkBool>(url).await.good();
+    token.unyank("foo", "1.0.0").await.good();
+    app.run_pending_background_jobs().await;
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/yank_history")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".yank_history[].time" => "[datetime]",
+        ".yank_history[].user.id" => id_redaction(token.as_model().user_id),
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_history_of_unknown_version() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/2.0.0/yank_history")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not have a version `2.0.0`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_reason_too_long() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let url = format!("/api/v1/crates/foo/1.0.0/yank?reason={}", "a".repeat(257));
+    let response = token.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the reason must not be longer than 256 characters"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_cooldown() {
+    let (_, _, _, token) = TestApp::full()
+        .with_config(|config| config.yank_cooldown = Some(Duration::from_secs(600)))
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    token.yank("foo", "1.0.0").await.good();
+
+    let response = token.unyank("foo", "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response
+        .text()
+        .starts_with(r#"{"errors":[{"detail":"foo v1.0.0 was yanked or unyanked recently."#));
+
+    // Repeating the current state is not affected by the cooldown
+    token.yank("foo", "1.0.0").await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_cooldown_does_not_apply_to_admins() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.yank_cooldown = Some(Duration::from_secs(600)))
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    token.yank("foo", "1.0.0").await.good();
+
+    let admin = app.db_new_user("admin");
+    app.db(|conn| {
+        use crates_io::schema::users;
+        use diesel::prelude::*;
+
+        diesel::update(users::table.find(admin.as_model().id))
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+
+    admin.unyank("foo", "1.0.0").await.good();
+}
@@ -0,0 +1,262 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{MockCookieUser, RequestHelper, Response, TestApp};
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+use serde_json::json;
+
+async fn set_visibility(
+    user: &impl RequestHelper,
+    crate_name: &str,
+    visibility: &str,
+) -> Response<()> {
+    let url = format!("/api/v1/crates/{crate_name}/visibility");
+    let body = json!({ "visibility": visibility }).to_string();
+    user.put(&url, body).await
+}
+
+/// Creates a `foo` crate owned by `user`, and makes it private.
+async fn private_crate(app: &TestApp, user: &MockCookieUser) {
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let response = set_visibility(user, "foo", "private").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"ok":true}"###);
+}
+
+const METADATA_URLS: &[&str] = &[
+    "/api/v1/crates/foo",
+    "/api/v1/crates/foo/versions",
+    "/api/v1/crates/foo/downloads",
+    "/api/v1/crates/foo/owners",
+    "/api/v1/crates/foo/owner_team",
+    "/api/v1/crates/foo/owner_user",
+    "/api/v1/crates/foo/reverse_dependencies",
+    "/api/v1/crates/foo/1.0.0",
+    "/api/v1/crates/foo/1.0.0/dependencies",
+    "/api/v1/crates/foo/1.0.0/downloads",
+];
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crate_metadata_is_only_visible_to_owners() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    private_crate(&app, &owner).await;
+
+    for url in METADATA_URLS {
+        let response = anon.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{url}");
+
+        let response = other.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{url}");
+
+        let response = owner.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::OK, "{url}");
+    }
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crate_is_visible_to_admins() {
+    let (app, _, owner) = TestApp::init().with_user();
+    let admin = app.db_new_user("admin");
+    admin.make_admin();
+    private_crate(&app, &owner).await;
+
+    for url in METADATA_URLS {
+        let response = admin.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::OK, "{url}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crate_changes_by_other_users_are_not_found() {
+    let (app, _, owner) = TestApp::full().with_user();
+    let other = app.db_new_user("other");
+    private_crate(&app, &owner).await;
+
+    let expected = r#"{"errors":[{"detail":"crate `foo` does not exist"}]}"#;
+
+    let response = other.yank("foo", "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.text(), expected);
+
+    let response = other.add_named_owner("foo", "other").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.text(), expected);
+
+    let token = other.db_new_token("publish");
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "2.0.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.text(), expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crate_download_requires_authentication() {
+    let (app, anon, owner, token) = TestApp::init().with_token();
+    private_crate(&app, &owner).await;
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/readme").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Private crate files are not redirected to, so a missing file is a 404
+    let response = token.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    owner
+        .get::<()>("/api/v1/crates/foo/1.0.0/readme")
+        .await
+        .assert_redirect_ends_with("/readmes/foo/foo-1.0.0.html");
+
+    // Redirects to missing crates are still performed unconditionally
+    anon.get::<()>("/api/v1/crates/bar/1.0.0/download")
+        .await
+        .assert_redirect_ends_with("/crates/bar/bar-1.0.0.crate");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crate_files_are_only_served_via_the_api() {
+    let (app, anon, owner, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    let response = set_visibility(&owner, "foo", "private").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    // The crate file is moved out of the public storage area, and the crate
+    // is removed from the index
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"private/crates/foo/foo-1.0.0.crate".to_string()));
+    assert!(!stored_files.contains(&"crates/foo/foo-1.0.0.crate".to_string()));
+    assert!(!stored_files.contains(&"index/3/f/foo".to_string()));
+    assert!(app.upstream_index().crates_from_index_head("foo").is_err());
+
+    let url = "/api/v1/crates/foo/1.0.0/download";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = token.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "private,no-store"
+    );
+    assert!(!response.bytes().is_empty());
+
+    // Making the crate public again restores the public crate file
+    let response = set_visibility(&owner, "foo", "public").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"crates/foo/foo-1.0.0.crate".to_string()));
+    assert!(stored_files.contains(&"index/3/f/foo".to_string()));
+
+    anon.get::<()>(url)
+        .await
+        .assert_redirect_ends_with("/crates/foo/foo-1.0.0.crate");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn private_crates_are_hidden_from_listings() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let user_id = owner.as_model().id;
+
+    app.db(|conn| {
+        CrateBuilder::new("bar", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+
+        let foo = CrateBuilder::new("foo_dep", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+
+        CrateBuilder::new("baz", user_id)
+            .version(VersionBuilder::new("1.0.0").dependency(&foo, None))
+            .expect_build(conn);
+    });
+
+    assert_eq!(anon.search("").await.meta.total, 3);
+
+    let response = set_visibility(&owner, "baz", "private").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = anon.search("").await;
+    assert_eq!(json.meta.total, 2);
+    assert!(json.crates.iter().all(|krate| krate.name != "baz"));
+
+    let json = anon.search(&format!("user_id={user_id}")).await;
+    assert_eq!(json.meta.total, 2);
+
+    let json = anon.search("q=baz").await;
+    assert_eq!(json.meta.total, 0);
+
+    let json: serde_json::Value = anon.get("/api/v1/summary").await.good();
+    assert_eq!(json["num_crates"], 2);
+    let new_crates = json["new_crates"].as_array().unwrap();
+    assert!(new_crates.iter().all(|krate| krate["name"] != "baz"));
+
+    let json: serde_json::Value = anon
+        .get("/api/v1/crates/foo_dep/reverse_dependencies")
+        .await
+        .good();
+    assert_eq!(json["meta"]["total"], 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_change_visibility() {
+    let (app, anon, owner) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let response = set_visibility(&anon, "foo", "private").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = set_visibility(&other, "foo", "private").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners have permission to change the visibility of a crate"}]}"###);
+
+    let response = set_visibility(&owner, "foo", "private").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Once the crate is private, other users can't see it anymore
+    let response = set_visibility(&other, "foo", "public").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = set_visibility(&owner, "foo", "public").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_visibility() {
+    let (app, _, owner) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id).expect_build(conn);
+    });
+
+    let response = set_visibility(&owner, "foo", "secret").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
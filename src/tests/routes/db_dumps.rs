@@ -0,0 +1,59 @@
+use crate::util::{RequestHelper, TestApp};
+use chrono::NaiveDateTime;
+use crates_io::models::{NewDatabaseDump, NewDatabaseDumpDelta};
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+fn new_dump<'a>(created_at: &str, directory: &'a str) -> NewDatabaseDump<'a> {
+    let created_at = NaiveDateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    NewDatabaseDump {
+        created_at,
+        directory,
+        tar_size: 1024,
+        tar_sha256: "a1b2c3",
+        zip_size: 2048,
+        zip_sha256: "d4e5f6",
+        last_event_id: 42,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_empty() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/db_dumps").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"db_dumps":[]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list() {
+    let (app, anon) = TestApp::init().empty();
+
+    app.db(|conn| {
+        new_dump("2024-10-27 02:00:00", "db-dumps/2024-10-27-020000")
+            .insert(conn)
+            .unwrap();
+        let dump = new_dump("2024-10-28 02:00:00", "db-dumps/2024-10-28-020000")
+            .insert(conn)
+            .unwrap();
+
+        NewDatabaseDumpDelta {
+            dump_id: dump.id,
+            from_event_id: 42,
+            to_event_id: 50,
+            path: "db-dumps/2024-10-28-020000/deltas/2024-10-29-020000.jsonl.gz",
+            size: 128,
+            sha256: "a7b8c9",
+        }
+        .insert(conn)
+        .unwrap();
+    });
+
+    let response = anon.get::<()>("/api/v1/db_dumps").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".db_dumps[].id" => "[id]",
+    });
+}
@@ -0,0 +1,58 @@
+use crate::builders::PublishBuilder;
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, TestApp};
+use insta::assert_snapshot;
+use serde_json::Value;
+
+fn publish_with_links(name: &str, version: &str, links: &str) -> PublishBuilder {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "{name}"
+        version = "{version}"
+        description = "description"
+        license = "MIT"
+        links = "{links}"
+        "#
+    );
+
+    PublishBuilder::new(name, version).custom_manifest(manifest)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reused_links_keys_are_reported() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_json = token
+        .publish_crate(publish_with_links("foo-sys", "1.0.0", "foo"))
+        .await
+        .good();
+    assert!(crate_json.warnings.other.is_empty());
+
+    // New versions of the same crate don't conflict with previous versions
+    let crate_json = token
+        .publish_crate(publish_with_links("foo-sys", "1.1.0", "foo"))
+        .await
+        .good();
+    assert!(crate_json.warnings.other.is_empty());
+
+    let crate_json = token
+        .publish_crate(publish_with_links("foo-sys2", "1.0.0", "foo"))
+        .await
+        .good();
+    assert_eq!(
+        crate_json.warnings.other,
+        ["the `links` key `foo` is already used by `foo-sys`"]
+    );
+
+    let json = anon.get::<Value>("/api/v1/links_keys/foo").await.good();
+    assert_snapshot!(json, @r###"{"crates":[{"name":"foo-sys","versions":["1.1.0","1.0.0"]},{"name":"foo-sys2","versions":["1.0.0"]}],"key":"foo"}"###);
+
+    // Yanked versions don't declare the key anymore
+    token.yank("foo-sys2", "1.0.0").await.good();
+    let json = anon.get::<Value>("/api/v1/links_keys/foo").await.good();
+    assert_eq!(json["crates"].as_array().unwrap().len(), 1);
+
+    let json = anon.get::<Value>("/api/v1/links_keys/unknown").await.good();
+    assert_snapshot!(json, @r###"{"crates":[],"key":"unknown"}"###);
+}
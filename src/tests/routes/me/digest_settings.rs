@@ -0,0 +1,69 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::emails;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+const URL: &str = "/api/v1/me/digest_settings";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribe_and_unsubscribe() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"digest_settings":{"enabled":false,"timezone":"UTC"}}"###);
+
+    let body = json!({ "digest_settings": { "enabled": true, "timezone": "Europe/Berlin" } });
+    let response = user.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.get::<()>(URL).await;
+    assert_snapshot!(response.text(), @r###"{"digest_settings":{"enabled":true,"timezone":"Europe/Berlin"}}"###);
+
+    let body = json!({ "digest_settings": { "enabled": false, "timezone": "Europe/Berlin" } });
+    let response = user.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.get::<()>(URL).await;
+    assert_snapshot!(response.text(), @r###"{"digest_settings":{"enabled":false,"timezone":"UTC"}}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_timezones_are_rejected() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = json!({ "digest_settings": { "enabled": true, "timezone": "Mars/Olympus_Mons" } });
+    let response = user.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"unknown timezone `Mars/Olympus_Mons`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verified_email_is_required() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        diesel::update(emails::table)
+            .set(emails::verified.eq(false))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let body = json!({ "digest_settings": { "enabled": true, "timezone": "UTC" } });
+    let response = user.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a verified email address is required to subscribe to the weekly digest"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_tokens_are_rejected() {
+    let (_, anon, _, token) = TestApp::init().with_token();
+
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
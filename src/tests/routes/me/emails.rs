@@ -0,0 +1,171 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crates_io::schema::emails;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+impl MockCookieUser {
+    async fn add_email(&self, email: &str) -> Value {
+        let body = json!({ "email": email }).to_string();
+        let response = self.put::<()>("/api/v1/me/emails", body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        response.json()
+    }
+
+    async fn list_emails(&self) -> Value {
+        let response = self.get::<()>("/api/v1/me/emails").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        response.json()
+    }
+}
+
+fn email_token(app: &TestApp, email_id: i32) -> String {
+    app.db(|conn| {
+        emails::table
+            .find(email_id)
+            .select(emails::token)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_verify_and_remove_emails() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    let json = user.list_emails().await;
+    assert_snapshot!(json["emails"][0]["email"], @r###""something@example.com""###);
+    assert_eq!(json["emails"][0]["primary"], true);
+    let primary_id = json["emails"][0]["id"].as_i64().unwrap();
+
+    let json = user.add_email(" other@example.com ").await;
+    assert_snapshot!(json["email"]["email"], @r###""other@example.com""###);
+    assert_eq!(json["email"]["verified"], false);
+    assert_eq!(json["email"]["verification_sent"], true);
+    assert_eq!(json["email"]["primary"], false);
+    let email_id = json["email"]["id"].as_i64().unwrap() as i32;
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(mails.len(), 1);
+    assert!(mails[0].1.contains("Please confirm your email address"));
+
+    // Unverified addresses can not become the primary address
+    let url = format!("/api/v1/me/emails/{email_id}/primary");
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let url = format!("/api/v1/confirm/{}", email_token(&app, email_id));
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = format!("/api/v1/me/emails/{email_id}/primary");
+    let response = user.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = user.get::<()>("/api/v1/me").await.json();
+    assert_snapshot!(json["user"]["email"], @r###""other@example.com""###);
+
+    // The primary address can not be removed
+    let url = format!("/api/v1/me/emails/{email_id}");
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the primary email address cannot be removed"}]}"###);
+
+    let url = format!("/api/v1/me/emails/{primary_id}");
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = user.list_emails().await;
+    assert_eq!(json["emails"].as_array().unwrap().len(), 1);
+    assert_eq!(json["emails"][0]["id"], email_id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_and_duplicate_emails_are_rejected() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = json!({ "email": "not an email" }).to_string();
+    let response = user.put::<()>("/api/v1/me/emails", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid email address"}]}"###);
+
+    let body = json!({ "email": "Something@Example.com" }).to_string();
+    let response = user.put::<()>("/api/v1/me/emails", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this email address has already been added"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn emails_of_other_users_are_not_found() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    let other_email_id = other.list_emails().await["emails"][0]["id"].clone();
+
+    let url = format!("/api/v1/me/emails/{other_email_id}");
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_tokens_are_rejected() {
+    let (_, _, _, token) = TestApp::init().with_token();
+
+    let response = token.get::<()>("/api/v1/me/emails").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body = json!({ "email": "other@example.com" }).to_string();
+    let response = token.put::<()>("/api/v1/me/emails", body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn notifications_are_sent_to_the_selected_address() {
+    let (app, anon, owner, owner_token) = TestApp::init().with_token();
+    let invitee = app.db_new_user("invitee");
+
+    app.db(|conn| CrateBuilder::new("foo", owner.as_model().id).expect_build(conn));
+
+    let json = invitee.add_email("invitations@example.com").await;
+    let email_id = json["email"]["id"].as_i64().unwrap() as i32;
+
+    let body = json!({
+        "email_preferences": [{ "notification_class": "ownership", "email_id": email_id }]
+    });
+
+    // Unverified addresses can not be selected
+    let url = "/api/v1/me/email_preferences";
+    let response = invitee.put::<()>(url, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let url = format!("/api/v1/confirm/{}", email_token(&app, email_id));
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = "/api/v1/me/email_preferences";
+    let response = invitee.put::<()>(url, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = invitee.list_emails().await;
+    assert_eq!(
+        json["email_preferences"][0]["notification_class"],
+        "ownership"
+    );
+    assert_eq!(json["email_preferences"][0]["email_id"], email_id);
+
+    owner_token.add_named_owner("foo", "invitee").await.good();
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    let (envelope, _) = mails
+        .iter()
+        .find(|(_, m)| m.contains("Subject: Crate ownership invitation"))
+        .expect("missing email");
+
+    let recipients = envelope
+        .to()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(recipients, vec!["invitations@example.com"]);
+}
@@ -1,4 +1,10 @@
+mod digest_settings;
 mod email_notifications;
+mod emails;
 pub mod get;
+mod rate_limit_requests;
+mod service_tokens;
+mod subscriptions;
 pub mod tokens;
 mod updates;
+mod usage;
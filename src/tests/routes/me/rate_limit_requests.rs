@@ -0,0 +1,67 @@
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/me/rate_limits/requests";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_logged_out() {
+    let (_, anon) = TestApp::init().empty();
+
+    let body = r#"{ "action": "publish_new", "burst": 20, "justification": "workspace" }"#;
+    anon.post::<()>(URL, body).await.assert_forbidden();
+    anon.get::<()>(URL).await.assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_and_list_requests() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = r#"{ "action": "publish_new", "burst": 20, "justification": "We are publishing a workspace with 15 crates." }"#;
+    let json = user.post::<Value>(URL, body).await.good();
+    assert_eq!(json["request"]["action"], "publish_new");
+    assert_eq!(json["request"]["burst"], 20);
+    assert_eq!(json["request"]["status"], "pending");
+    assert_eq!(json["request"]["user"]["login"], "foo");
+
+    // Only a single request per action can be pending at a time
+    let response = user.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"you already have a pending request for a higher limit of this action"}]}"###);
+
+    let body = r#"{ "action": "yank_unyank", "burst": 200, "justification": "Yanking a broken release train." }"#;
+    user.post::<Value>(URL, body).await.good();
+
+    let json = user.get::<Value>(URL).await.good();
+    let actions = json["requests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["action"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(actions, ["yank_unyank", "publish_new"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = r#"{ "action": "publish_new", "burst": 20, "justification": "  " }"#;
+    let response = user.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the justification must explain why you need a higher limit"}]}"###);
+
+    let body = r#"{ "action": "publish_new", "burst": 1, "justification": "workspace" }"#;
+    let response = user.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the burst must be greater than 5 and at most 1000"}]}"###);
+
+    let body = r#"{ "action": "publish_new", "burst": 5000, "justification": "workspace" }"#;
+    let response = user.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = r#"{ "action": "unknown", "burst": 20, "justification": "workspace" }"#;
+    let response = user.post::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
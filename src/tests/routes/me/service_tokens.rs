@@ -0,0 +1,136 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::ApiToken;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+
+static NEW_SERVICE_TOKEN: &[u8] =
+    br#"{ "service_token": { "name": "docs", "contact": "ops@example.com" } }"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_logged_out() {
+    let (_, anon) = TestApp::init().empty();
+    anon.put("/api/v1/me/service_tokens", NEW_SERVICE_TOKEN)
+        .await
+        .assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_requires_contact() {
+    let (_, _, user) = TestApp::init().with_user();
+    let body: &[u8] = br#"{ "service_token": { "name": "docs", "contact": " " } }"#;
+    let response = user.put::<()>("/api/v1/me/service_tokens", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"contact must have a value, so that the operators of crates.io can reach you"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_exceeded_tokens_per_user() {
+    let (app, _, user) = TestApp::init().with_user();
+    let id = user.as_model().id;
+    app.db(|conn| {
+        for i in 0..10 {
+            let name = format!("token {i}");
+            ApiToken::insert_service_token(conn, id, &name, "ops@example.com").unwrap();
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/service_tokens", NEW_SERVICE_TOKEN)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"maximum service tokens per user is: 10"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_list_and_revoke() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let response = user
+        .put::<()>("/api/v1/me/service_tokens", NEW_SERVICE_TOKEN)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["service_token"]["name"], "docs");
+    assert_eq!(json["service_token"]["service_contact"], "ops@example.com");
+    assert!(json["service_token"]["token"].is_string());
+    let id = json["service_token"]["id"].as_i64().unwrap();
+
+    let json = user.get::<()>("/api/v1/me/service_tokens").await.json();
+    let tokens = json["service_tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["id"], id);
+    assert!(tokens[0].get("token").is_none());
+
+    // Service tokens are not listed as regular API tokens
+    let json = user.get::<()>("/api/v1/me/tokens").await.json();
+    assert_eq!(json["api_tokens"].as_array().unwrap().len(), 0);
+
+    let url = format!("/api/v1/me/service_tokens/{id}");
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = user.get::<()>("/api/v1/me/service_tokens").await.json();
+    assert_eq!(json["service_tokens"].as_array().unwrap().len(), 0);
+
+    let revoked: bool = app.db(|conn| {
+        use crates_io::schema::api_tokens;
+        api_tokens::table
+            .find(id as i32)
+            .select(api_tokens::revoked)
+            .get_result(conn)
+            .unwrap()
+    });
+    assert!(revoked);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn service_tokens_can_not_perform_actions() {
+    let (_, _, user) = TestApp::full().with_user();
+    let token = user.db_new_service_token("docs", "ops@example.com");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"service tokens can only be used for read-only requests"}]}"###);
+
+    let response = token.get::<()>("/api/v1/me").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = token
+        .put::<()>(
+            "/api/v1/me/tokens",
+            br#"{ "api_token": { "name": "bar" } }"# as &[u8],
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn service_tokens_have_separate_quotas() {
+    let (_, _, user) = TestApp::init()
+        .with_config(|config| {
+            config.api_quota.user_requests = 1;
+            config.api_quota.token_requests = 1;
+            config.api_quota.service_token_requests = 3;
+        })
+        .with_user();
+    let token = user.db_new_service_token("docs", "ops@example.com");
+
+    for _ in 0..3 {
+        let response = token.get::<()>("/api/v1/crates").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = token.get::<()>("/api/v1/crates").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Service token requests do not count against the quota of the user
+    let response = user.get::<()>("/api/v1/crates").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = token.get::<()>("/api/v1/me/usage").await.json();
+    assert_snapshot!(json["usage"]["tokens"][0]["limit"], @"3");
+    assert_snapshot!(json["usage"]["tokens"][0]["requests"], @"4");
+}
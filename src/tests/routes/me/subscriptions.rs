@@ -0,0 +1,142 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+const SUBJECT: &str = "A crate you subscribed to published a new version";
+
+fn subscription_request(krate: &str, version_req: &str) -> Vec<u8> {
+    let body = json!({ "subscription": { "crate": krate, "version_req": version_req } });
+    serde_json::to_vec(&body).unwrap()
+}
+
+fn subscription_emails(app: &TestApp) -> Vec<String> {
+    app.as_inner()
+        .emails
+        .mails_in_memory()
+        .unwrap()
+        .into_iter()
+        .map(|(_, body)| body)
+        .filter(|body| body.contains(SUBJECT))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_logged_out() {
+    let (_, anon) = TestApp::init().empty();
+    anon.put(
+        "/api/v1/me/subscriptions",
+        subscription_request("foo", "^2"),
+    )
+    .await
+    .assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_list_and_delete() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let body = subscription_request("foo", "^2");
+    let response = user.put::<()>("/api/v1/me/subscriptions", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["subscription"]["crate"], "foo");
+    assert_eq!(json["subscription"]["version_req"], "^2");
+    let id = json["subscription"]["id"].as_i64().unwrap();
+
+    // Subscribing twice with the same requirement returns the existing subscription
+    let body = subscription_request("foo", "^2");
+    let response = user.put::<()>("/api/v1/me/subscriptions", body).await;
+    assert_eq!(response.json()["subscription"]["id"].as_i64(), Some(id));
+
+    let json = user.get::<()>("/api/v1/me/subscriptions").await.json();
+    let subscriptions = json["subscriptions"].as_array().unwrap();
+    assert_eq!(subscriptions.len(), 1);
+    assert_eq!(subscriptions[0]["id"].as_i64(), Some(id));
+
+    let url = format!("/api/v1/me/subscriptions/{id}");
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = user.get::<()>("/api/v1/me/subscriptions").await.json();
+    assert_eq!(json["subscriptions"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_with_invalid_version_req() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let body = subscription_request("foo", "not a requirement");
+    let response = user.put::<()>("/api/v1/me/subscriptions", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let expected = r#"{"errors":[{"detail":"invalid version requirement `not a requirement`: "#;
+    assert!(response.text().starts_with(expected));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_for_unknown_crate() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = subscription_request("foo", "^2");
+    let response = user.put::<()>("/api/v1/me/subscriptions", body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn matching_versions_are_notified() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let subscriber = app.db_new_user("bar");
+    let body = subscription_request("foo", "^2");
+    let response = subscriber.put::<()>("/api/v1/me/subscriptions", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "2.0.0"))
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    let emails = subscription_emails(&app);
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].contains("Hello bar!"));
+    assert!(emails[0].contains("Version 2.0.0 of the crate foo"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publisher_is_not_notified() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let body = subscription_request("foo", "*");
+    let response = user.put::<()>("/api/v1/me/subscriptions", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    assert!(subscription_emails(&app).is_empty());
+}
@@ -0,0 +1,51 @@
+use crate::util::{RequestHelper, TestApp};
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quotas_are_enforced_and_reported() {
+    let (_, anon, user, token) = TestApp::init()
+        .with_config(|config| {
+            config.api_quota.user_requests = 3;
+            config.api_quota.token_requests = 2;
+        })
+        .with_token();
+
+    // Anonymous requests are not counted
+    for _ in 0..5 {
+        let response = anon.get::<()>("/api/v1/crates").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    for _ in 0..2 {
+        let response = token.get::<()>("/api/v1/crates").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = token.get::<()>("/api/v1/crates").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+    // The requests of all tokens count against the quota of the user
+    let response = user.get::<()>("/api/v1/crates").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let response = user.get::<()>("/api/v1/me/usage").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_snapshot!(json["usage"]["user"], @r###"{"limit":3,"requests":4}"###);
+    assert_eq!(json["usage"]["tokens"][0]["id"], token.as_model().id);
+    assert_eq!(json["usage"]["tokens"][0]["name"], "bar");
+    assert_snapshot!(json["usage"]["tokens"][0]["requests"], @"3");
+
+    // The usage endpoint itself does not count against the quota
+    let json = token.get::<()>("/api/v1/me/usage").await.json();
+    assert_snapshot!(json["usage"]["user"], @r###"{"limit":3,"requests":4}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn anonymous_users_have_no_usage() {
+    let (_, anon) = TestApp::init().empty();
+    let response = anon.get::<()>("/api/v1/me/usage").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -11,13 +11,21 @@
 //! - testing output serialization of a route
 //! - testing query parameter combinations of a route
 
+mod advisories;
+pub mod bulk;
 pub mod categories;
 pub mod category_slugs;
+mod changes;
+mod config_json;
 pub mod crates;
+mod db_dumps;
 pub mod keywords;
+mod links_keys;
 pub mod me;
 pub mod metrics;
 mod private;
+mod research;
 pub mod session;
 pub mod summary;
+mod teams;
 pub mod users;
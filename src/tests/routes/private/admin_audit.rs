@@ -0,0 +1,154 @@
+//! Tests for the `/api/private/audit` endpoint
+
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::admin_audit_log;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/audit";
+
+fn freeze(name: &str) -> String {
+    format!("/api/private/crates/{name}/freeze")
+}
+
+fn freeze_body(reason: &str) -> String {
+    json!({ "reason": reason }).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_query_the_audit_log() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to query the audit log"}]}"###);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admin_actions_are_recorded() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+    let second_admin = app.db_new_user("second_admin");
+    second_admin.make_admin();
+
+    app.db(|conn| {
+        let user_id = admin.as_model().id;
+        CrateBuilder::new("foo_audit", user_id).expect_build(conn);
+        CrateBuilder::new("bar_audit", user_id).expect_build(conn);
+    });
+
+    admin
+        .put::<Value>(&freeze("foo_audit"), freeze_body("malware"))
+        .await
+        .good();
+    admin.delete::<Value>(&freeze("foo_audit")).await.good();
+    second_admin
+        .put::<Value>(&freeze("bar_audit"), freeze_body("spam"))
+        .await
+        .good();
+
+    let json = admin.get::<Value>(URL).await.good();
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0]["action"], "freeze_crate");
+    assert_eq!(entries[0]["crate"], "bar_audit");
+    assert_eq!(entries[0]["actor"]["login"], "second_admin");
+    assert_eq!(entries[0]["details"], json!({ "reason": "spam" }));
+    assert_eq!(entries[1]["action"], "unfreeze_crate");
+    assert_eq!(entries[2]["action"], "freeze_crate");
+    assert_eq!(entries[2]["crate"], "foo_audit");
+    assert_eq!(entries[2]["actor"]["login"], "foo");
+
+    let json = admin
+        .get::<Value>(&format!("{URL}?action=freeze_crate&crate=foo_audit"))
+        .await
+        .good();
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["details"], json!({ "reason": "malware" }));
+
+    let json = admin
+        .get::<Value>(&format!("{URL}?actor=second_admin"))
+        .await
+        .good();
+    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
+
+    let json = admin
+        .get::<Value>(&format!("{URL}?actor=unknown"))
+        .await
+        .good();
+    assert_eq!(json["entries"].as_array().unwrap().len(), 0);
+
+    let response = admin.get::<()>(&format!("{URL}?action=unknown")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audit_log_is_paginated() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_audit", admin.as_model().id).expect_build(conn);
+    });
+
+    for reason in ["first", "second", "third"] {
+        admin
+            .put::<Value>(&freeze("foo_audit"), freeze_body(reason))
+            .await
+            .good();
+    }
+
+    let json = admin
+        .get::<Value>(&format!("{URL}?per_page=2"))
+        .await
+        .good();
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["details"]["reason"], "third");
+    assert_eq!(entries[1]["details"]["reason"], "second");
+
+    let next_page = json["meta"]["next_page"].as_str().unwrap();
+    let json = admin
+        .get::<Value>(&format!("{URL}{next_page}"))
+        .await
+        .good();
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["details"]["reason"], "first");
+    assert_eq!(json["meta"]["next_page"], Value::Null);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audit_log_is_append_only() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_audit", admin.as_model().id).expect_build(conn);
+    });
+
+    admin
+        .put::<Value>(&freeze("foo_audit"), freeze_body("malware"))
+        .await
+        .good();
+
+    app.db(|conn| {
+        let result = diesel::update(admin_audit_log::table)
+            .set(admin_audit_log::crate_name.eq("bar_audit"))
+            .execute(conn);
+        assert_err!(result);
+
+        let result = diesel::delete(admin_audit_log::table).execute(conn);
+        assert_err!(result);
+    });
+
+    let json = admin.get::<Value>(URL).await.good();
+    assert_eq!(json["entries"][0]["crate"], "foo_audit");
+}
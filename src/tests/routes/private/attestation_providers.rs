@@ -0,0 +1,79 @@
+//! Tests for the `/api/private/attestation_providers` endpoints
+
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/attestation_providers";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_manage_providers() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let rebuilder = app.db_new_user("rebuilder");
+    let url = format!("{URL}/{}", rebuilder.as_model().id);
+
+    let body = json!({ "name": "Rebuilders Inc." }).to_string();
+
+    let response = user.put::<()>(&url, body.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to manage attestation providers"}]}"###);
+
+    let response = anon.put::<()>(&url, body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn approve_and_revoke_provider() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let rebuilder = app.db_new_user("rebuilder");
+    let url = format!("{URL}/{}", rebuilder.as_model().id);
+
+    let body = json!({ "name": "Rebuilders Inc." }).to_string();
+    let response = admin.put::<()>(&url, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["provider"]["name"], "Rebuilders Inc.");
+    assert_eq!(json["provider"]["user"]["login"], "rebuilder");
+
+    // Approving the account again renames the provider
+    let body = json!({ "name": "Rebuilders Ltd." }).to_string();
+    admin.put::<Value>(&url, body).await.good();
+
+    let json: Value = admin.get(URL).await.good();
+    assert_eq!(json["providers"].as_array().unwrap().len(), 1);
+    assert_eq!(json["providers"][0]["name"], "Rebuilders Ltd.");
+
+    admin.delete::<Value>(&url).await.good();
+
+    let json: Value = admin.get(URL).await.good();
+    assert_eq!(json["providers"], json!([]));
+
+    let response = admin.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_approvals() {
+    let (_, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let body = json!({ "name": "  " }).to_string();
+    let response = admin
+        .put::<()>(&format!("{URL}/{}", admin.as_model().id), body)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`name` must be between 1 and 100 characters"}]}"###);
+
+    let body = json!({ "name": "Rebuilders Inc." }).to_string();
+    let response = admin.put::<()>(&format!("{URL}/0"), body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,182 @@
+//! Tests for the `/api/private/bulk_yanks` endpoint
+
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, SecondsFormat, Utc};
+use crates_io::models::{CrateOwner, OwnerKind};
+use crates_io::schema::{crate_owners, crates, versions};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/bulk_yanks";
+const SUBJECT: &str = "Subject: Versions of your crate were yanked by the crates.io team";
+
+fn time_window() -> (String, String) {
+    let now = Utc::now();
+    let from = now - Duration::hours(1);
+    let until = now + Duration::hours(1);
+    (
+        from.to_rfc3339_opts(SecondsFormat::Secs, true),
+        until.to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+}
+
+fn yanked_versions(app: &TestApp) -> Vec<(String, String)> {
+    app.db(|conn| {
+        versions::table
+            .inner_join(crates::table)
+            .filter(versions::yanked.eq(true))
+            .select((crates::name, versions::num))
+            .order((crates::name, versions::num))
+            .load(conn)
+            .unwrap()
+    })
+}
+
+fn num_mails(app: &TestApp) -> usize {
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    mails.iter().filter(|(_, m)| m.contains(SUBJECT)).count()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_bulk_yank() {
+    let (_, anon, user) = TestApp::init().with_user();
+    let (from, until) = time_window();
+    let body = json!({ "crates": ["foo"], "user": "foo", "from": from, "until": until });
+
+    let response = user.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to bulk yank versions"}]}"###);
+
+    let response = anon.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests() {
+    let (_, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+    let (from, until) = time_window();
+
+    let body = json!({ "crates": [], "user": "foo", "from": from, "until": until });
+    let response = admin.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"at least one crate is required"}]}"###);
+
+    let body = json!({ "crates": ["foo"], "from": from, "until": until });
+    let response = admin.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"either a user or an API token is required"}]}"###);
+
+    let body = json!({ "crates": ["foo"], "user": "foo", "from": until, "until": from });
+    let response = admin.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the time window must not be empty"}]}"###);
+
+    let body = json!({ "crates": ["foo"], "user": "unknown", "from": from, "until": until });
+    let response = admin.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = json!({ "crates": ["foo"], "api_token_id": 42, "from": from, "until": until });
+    let response = admin.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yank_versions_of_compromised_account() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let owner = app.db_new_user("owner");
+    let mallory = app.db_new_user("mallory");
+    let token = mallory.db_new_token("stolen");
+
+    owner
+        .publish_crate(PublishBuilder::new("foo_a", "1.0.0"))
+        .await
+        .good();
+
+    app.db(|conn| {
+        let crate_id: i32 = crates::table
+            .filter(crates::name.eq("foo_a"))
+            .select(crates::id)
+            .get_result(conn)
+            .unwrap();
+
+        let crate_owner = CrateOwner {
+            crate_id,
+            owner_id: mallory.as_model().id,
+            created_by: owner.as_model().id,
+            owner_kind: OwnerKind::User,
+            email_notifications: true,
+        };
+
+        diesel::insert_into(crate_owners::table)
+            .values(&crate_owner)
+            .execute(conn)
+            .unwrap();
+    });
+
+    token
+        .publish_crate(PublishBuilder::new("foo_a", "1.0.1"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo_b", "1.0.0"))
+        .await
+        .good();
+
+    let (from, until) = time_window();
+    let body = json!({
+        "crates": ["foo_a", "foo_b"],
+        "user": "mallory",
+        "from": from,
+        "until": until,
+        "dry_run": true,
+    });
+    let json = admin.put::<Value>(URL, body.to_string()).await.good();
+    assert_eq!(json["dry_run"], true);
+
+    let versions = json["versions"].as_array().unwrap();
+    let versions = versions
+        .iter()
+        .map(|v| {
+            format!(
+                "{}@{}",
+                v["crate"].as_str().unwrap(),
+                v["num"].as_str().unwrap()
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(versions, ["foo_a@1.0.1", "foo_b@1.0.0"]);
+
+    app.run_pending_background_jobs().await;
+    assert!(yanked_versions(&app).is_empty());
+
+    let body = json!({
+        "crates": ["foo_a", "foo_b"],
+        "api_token_id": token.as_model().id,
+        "from": from,
+        "until": until,
+    });
+    let json = admin.put::<Value>(URL, body.to_string()).await.good();
+    assert_eq!(json["dry_run"], false);
+    assert_eq!(json["versions"].as_array().unwrap().len(), 2);
+
+    app.run_pending_background_jobs().await;
+
+    let yanked = yanked_versions(&app);
+    assert_eq!(
+        yanked,
+        [
+            ("foo_a".to_string(), "1.0.1".to_string()),
+            ("foo_b".to_string(), "1.0.0".to_string()),
+        ]
+    );
+
+    // Only the remaining owner of `foo_a` is notified, but not the owner of
+    // the compromised account.
+    assert_eq!(num_mails(&app), 1);
+}
@@ -0,0 +1,98 @@
+//! Tests for the `/api/private/crates/:crate_id/freeze` endpoints
+
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/crates/foo_frozen/freeze";
+
+fn freeze_body(reason: &str) -> String {
+    json!({ "reason": reason }).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_freeze_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_frozen", user.as_model().id).expect_build(conn);
+    });
+
+    let response = user.put::<()>(URL, freeze_body("malware")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to freeze crates"}]}"###);
+
+    let response = user.delete::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.put::<()>(URL, freeze_body("malware")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests() {
+    let (_, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let response = admin.put::<()>(URL, freeze_body("  ")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a reason is required to freeze a crate"}]}"###);
+
+    let response = admin.put::<()>(URL, freeze_body(&"a".repeat(1001))).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the reason must not be longer than 1000 characters"}]}"###);
+
+    let response = admin.put::<()>(URL, freeze_body("malware")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo_frozen` does not exist"}]}"###);
+
+    let response = admin.delete::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn frozen_crates_can_not_be_changed() {
+    let (app, anon, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let owner = app.db_new_user("owner");
+    app.db_new_user("new_owner");
+    app.db(|conn| {
+        CrateBuilder::new("foo_frozen", owner.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json = admin
+        .put::<Value>(URL, freeze_body(" suspected account takeover "))
+        .await
+        .good();
+    assert_eq!(json["freeze"]["crate"], "foo_frozen");
+    assert_eq!(json["freeze"]["reason"], "suspected account takeover");
+    assert_eq!(json["freeze"]["frozen_by"]["login"], "foo");
+
+    // New versions can not be published
+    let crate_to_publish = PublishBuilder::new("foo_frozen", "1.1.0");
+    let response = owner.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The crate `foo_frozen` has been frozen by the crates.io team pending an investigation, so it can not be changed at the moment (reason: suspected account takeover). Please contact help@crates.io for more information."}]}"###);
+
+    // Owners can not be changed
+    let response = owner.add_named_owner("foo_frozen", "new_owner").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Existing versions can still be downloaded
+    anon.get::<()>("/api/v1/crates/foo_frozen/1.0.0/download")
+        .await
+        .assert_redirect_ends_with("/crates/foo_frozen/foo_frozen-1.0.0.crate");
+
+    admin.delete::<Value>(URL).await.good();
+
+    let crate_to_publish = PublishBuilder::new("foo_frozen", "1.1.0");
+    let response = owner.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = owner.add_named_owner("foo_frozen", "new_owner").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -349,3 +349,82 @@ async fn invitation_list_other_crates() {
         .await;
     assert_eq!(resp.status(), StatusCode::FORBIDDEN);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invitation_list_for_invitee_and_crate() {
+    let (app, _, owner, token) = TestApp::init().with_token();
+    let user1 = app.db_new_user("user_1");
+    let user2 = app.db_new_user("user_2");
+    app.db(|conn| {
+        CrateBuilder::new("crate_1", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("crate_2", owner.as_model().id).expect_build(conn);
+    });
+    token.add_named_owner("crate_1", "user_1").await.good();
+    token.add_named_owner("crate_1", "user_2").await.good();
+    token.add_named_owner("crate_2", "user_1").await.good();
+
+    // Invitees can query their own invitations for a crate that they don't
+    // own yet
+    let query = format!("crate_name=crate_1&invitee_id={}", user1.as_model().id);
+    let invitations = get_invitations(&user1, &query).await;
+    assert_eq!(invitations.invitations.len(), 1);
+    assert_eq!(invitations.invitations[0].crate_name, "crate_1");
+    assert_eq!(invitations.invitations[0].invitee_id, user1.as_model().id);
+
+    // ... but not the invitations of other users
+    let resp = user2
+        .get_with_query::<()>("/api/private/crate_owner_invitations", &query)
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = user1
+        .get_with_query::<()>("/api/private/crate_owner_invitations", "crate_name=crate_1")
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invitation_list_with_invalid_invitee_id() {
+    let (app, _, owner, _) = TestApp::init().with_token();
+    app.db(|conn| {
+        CrateBuilder::new("crate_1", owner.as_model().id).expect_build(conn);
+    });
+
+    let resp = owner
+        .get_with_query::<()>(
+            "/api/private/crate_owner_invitations",
+            "crate_name=crate_1&invitee_id=foo",
+        )
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json(),
+        json!({ "errors": [{ "detail": "missing or invalid filter" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invitation_list_with_token_and_meta() {
+    let (app, _, owner, token) = TestApp::init().with_token();
+    app.db(|conn| {
+        CrateBuilder::new("crate_1", owner.as_model().id).expect_build(conn);
+    });
+    for name in ["user_1", "user_2", "user_3"] {
+        app.db_new_user(name);
+        token.add_named_owner("crate_1", name).await.good();
+    }
+
+    let resp = token
+        .get_with_query::<()>(
+            "/api/private/crate_owner_invitations",
+            "crate_name=crate_1&per_page=2",
+        )
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let json = resp.json();
+    assert_eq!(json["invitations"].as_array().unwrap().len(), 2);
+    assert_eq!(json["meta"]["total"], 3);
+    assert_eq!(json["meta"]["expiration_days"], 30);
+    assert!(json["meta"]["next_page"].is_string());
+}
@@ -0,0 +1,229 @@
+//! Tests for the `/api/private/crates/:crate_id/critical` and
+//! `/api/private/owner_removals` endpoints
+
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::{CrateOwner, OwnerKind};
+use crates_io::schema::{
+    crate_owners, crates, pending_owner_removals, users, version_downloads, versions,
+};
+use crates_io::worker::jobs::ApplyPendingOwnerRemovals;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/private/crates/foo_critical/critical";
+
+fn enable_two_factor(app: &TestApp, user: &MockCookieUser) {
+    app.db(|conn| {
+        diesel::update(user.as_model())
+            .set(users::gh_two_factor.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+/// Creates the `foo_critical` crate owned by `owner` and `other`.
+fn create_crate(app: &TestApp, owner: &MockCookieUser, other: &MockCookieUser) {
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_critical", owner.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: other.as_model().id,
+                created_by: owner.as_model().id,
+                owner_kind: OwnerKind::User,
+                email_notifications: true,
+            })
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn owner_logins(app: &TestApp) -> Vec<String> {
+    app.db(|conn| {
+        crate_owners::table
+            .inner_join(crates::table)
+            .inner_join(users::table.on(users::id.eq(crate_owners::owner_id)))
+            .filter(crates::name.eq("foo_critical"))
+            .filter(crate_owners::deleted.eq(false))
+            .select(users::gh_login)
+            .order(users::gh_login)
+            .load(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_designate_critical_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_critical", user.as_model().id).expect_build(conn);
+    });
+
+    let response = user.put::<()>(URL, "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to manage critical crates"}]}"###);
+
+    let response = user.delete::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.get::<()>("/api/private/owner_removals").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.put::<()>(URL, "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn critical_crates_require_two_factor_authentication() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let owner = app.db_new_user("owner");
+    let other = app.db_new_user("other");
+    app.db_new_user("new_owner");
+    create_crate(&app, &owner, &other);
+
+    let json = admin.put::<Value>(URL, "").await.good();
+    assert_eq!(json["critical"]["crate"], "foo_critical");
+    assert_eq!(json["critical"]["designated_by"]["login"], "foo");
+
+    let crate_to_publish = PublishBuilder::new("foo_critical", "1.1.0");
+    let response = owner.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The crate `foo_critical` is a critical crate, so all of its owners must have two-factor authentication enabled on their GitHub account. Please enable it at https://github.com/settings/security and sign in to crates.io again."}]}"###);
+
+    let response = owner.yank("foo_critical", "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = owner.add_named_owner("foo_critical", "new_owner").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    enable_two_factor(&app, &owner);
+
+    let crate_to_publish = PublishBuilder::new("foo_critical", "1.1.0");
+    let response = owner.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Widely used versions of critical crates can only be yanked with a
+    // confirmation
+    app.db(|conn| {
+        let version_id: i32 = versions::table
+            .filter(versions::num.eq("1.0.0"))
+            .select(versions::id)
+            .get_result(conn)
+            .unwrap();
+
+        diesel::insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version_id),
+                version_downloads::downloads.eq(20_000),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = owner.yank("foo_critical", "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Yanking foo_critical v1.0.0 requires confirmation, since it was downloaded 20000 times within the last 90 days. A confirmation link was sent to the owners of the crate. The link expires in 24 hours."}]}"###);
+
+    admin.delete::<Value>(URL).await.good();
+
+    let response = other.add_named_owner("foo_critical", "new_owner").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owner_removals_require_two_admins() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+    let second_admin = app.db_new_user("second_admin");
+    second_admin.make_admin();
+
+    let owner = app.db_new_user("owner");
+    let other = app.db_new_user("other");
+    create_crate(&app, &owner, &other);
+    enable_two_factor(&app, &owner);
+
+    admin.put::<Value>(URL, "").await.good();
+
+    let response = owner
+        .remove_named_owners("foo_critical", &["other", "owner"])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = owner.remove_named_owner("foo_critical", "other").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"msg":"`foo_critical` is a critical crate, so the removal of owners takes effect in 7 days, unless two crates.io admins approve it earlier","ok":true}"###);
+    assert_eq!(owner_logins(&app), vec!["other", "owner"]);
+
+    let json = admin
+        .get::<Value>("/api/private/owner_removals")
+        .await
+        .good();
+    let removals = json["owner_removals"].as_array().unwrap();
+    assert_eq!(removals.len(), 1);
+    assert_eq!(removals[0]["crate"], "foo_critical");
+    assert_eq!(removals[0]["owner"]["login"], "other");
+    assert_eq!(removals[0]["requested_by"]["login"], "owner");
+    assert_eq!(removals[0]["approved_by"], Value::Null);
+
+    let url = format!("/api/private/owner_removals/{}/approve", removals[0]["id"]);
+    let json = admin.put::<Value>(&url, "").await.good();
+    assert_eq!(json["applied"], false);
+    assert_eq!(owner_logins(&app), vec!["other", "owner"]);
+
+    let response = admin.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the removal has to be approved by a second admin"}]}"###);
+
+    let json = second_admin.put::<Value>(&url, "").await.good();
+    assert_eq!(json["applied"], true);
+    assert_eq!(owner_logins(&app), vec!["owner"]);
+
+    let json = admin
+        .get::<Value>("/api/private/owner_removals")
+        .await
+        .good();
+    assert_eq!(json["owner_removals"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owner_removals_take_effect_after_waiting_period() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let owner = app.db_new_user("owner");
+    let other = app.db_new_user("other");
+    create_crate(&app, &owner, &other);
+    enable_two_factor(&app, &owner);
+
+    admin.put::<Value>(URL, "").await.good();
+
+    let response = owner.remove_named_owner("foo_critical", "other").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.db(|conn| ApplyPendingOwnerRemovals.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+    assert_eq!(owner_logins(&app), vec!["other", "owner"]);
+
+    app.db(|conn| {
+        let requested_at = (Utc::now() - Duration::days(8)).naive_utc();
+        diesel::update(pending_owner_removals::table)
+            .set(pending_owner_removals::created_at.eq(requested_at))
+            .execute(conn)
+            .unwrap();
+
+        ApplyPendingOwnerRemovals.enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs().await;
+    assert_eq!(owner_logins(&app), vec!["owner"]);
+}
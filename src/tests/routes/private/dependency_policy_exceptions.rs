@@ -0,0 +1,124 @@
+//! Tests for the dependency policy exception endpoints
+
+use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::VersionQuarantine;
+use crates_io::schema::{crates, versions};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/crates/malicious/dependency_policy_exception";
+const LIST_URL: &str = "/api/private/dependency_policy_exceptions";
+
+fn quarantine(app: &TestApp, crate_name: &str, num: &str) {
+    app.db(|conn| {
+        let version_id: i32 = versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq(crate_name))
+            .filter(versions::num.eq(num))
+            .select(versions::id)
+            .get_result(conn)
+            .unwrap();
+
+        VersionQuarantine::create(conn, version_id, "malware").unwrap();
+    });
+}
+
+fn exception_body(reason: &str) -> String {
+    json!({ "reason": reason }).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_manage_exceptions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("malicious", user.as_model().id).expect_build(conn);
+    });
+
+    let response = user.put::<()>(URL, exception_body("false positive")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to manage dependency policy exceptions"}]}"###);
+
+    let response = user.delete::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.get::<()>(LIST_URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.put::<()>(URL, exception_body("false positive")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requests() {
+    let (_, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let response = admin.put::<()>(URL, exception_body("  ")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a reason is required to add an exception"}]}"###);
+
+    let response = admin.put::<()>(URL, exception_body("false positive")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `malicious` does not exist"}]}"###);
+
+    let response = admin.delete::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependencies_on_quarantined_versions_are_rejected() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let owner = app.db_new_user("owner");
+    app.db(|conn| {
+        CrateBuilder::new("malicious", owner.as_model().id)
+            .version("1.0.0")
+            .version("2.0.0")
+            .expect_build(conn);
+    });
+
+    quarantine(&app, "malicious", "1.0.0");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .dependency(DependencyBuilder::new("malicious").version_req("^1.0"));
+    let response = owner.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the dependency `malicious ^1.0` is not allowed, since `malicious@1.0.0` has been quarantined by the crates.io team because it is suspected to be malicious. Please contact help@crates.io if you think that this is a mistake."}]}"###);
+
+    // Requirements that don't match the quarantined version are allowed
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .dependency(DependencyBuilder::new("malicious").version_req("^2.0"));
+    owner.publish_crate(crate_to_publish).await.good();
+
+    let json = admin
+        .put::<Value>(URL, exception_body(" only an old version was affected "))
+        .await
+        .good();
+    assert_eq!(json["exception"]["crate"], "malicious");
+    assert_eq!(
+        json["exception"]["reason"],
+        "only an old version was affected"
+    );
+    assert_eq!(json["exception"]["created_by"]["login"], "foo");
+
+    let json = admin.get::<Value>(LIST_URL).await.good();
+    assert_eq!(json["exceptions"].as_array().unwrap().len(), 1);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0")
+        .dependency(DependencyBuilder::new("malicious").version_req("^1.0"));
+    owner.publish_crate(crate_to_publish).await.good();
+
+    admin.delete::<Value>(URL).await.good();
+
+    let json = admin.get::<Value>(LIST_URL).await.good();
+    assert_eq!(json["exceptions"].as_array().unwrap().len(), 0);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.2.0")
+        .dependency(DependencyBuilder::new("malicious").version_req("^1.0"));
+    let response = owner.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,93 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockAnonymousUser, MockRequestExt, RequestHelper, Response, TestApp};
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::json;
+
+const URL: &str = "/api/private/docs_rs/build_status";
+
+async fn report(
+    anon: &MockAnonymousUser,
+    token: Option<&str>,
+    body: serde_json::Value,
+) -> Response<()> {
+    let mut request = anon.request_builder(Method::PUT, URL);
+    *request.body_mut() = body.to_string().into();
+    request.header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = token {
+        request.header(header::AUTHORIZATION, &format!("Bearer {token}"));
+    }
+    anon.run(request).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn build_status_is_exposed_in_version_api() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.docs_rs_webhook_token = Some("secret".into()))
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+    });
+
+    let json = anon.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    assert_eq!(
+        json["version"]["docs_build_status"],
+        serde_json::Value::Null
+    );
+
+    let body = json!({ "crate": "foo", "version": "1.0.0", "status": "in_progress" });
+    let response = report(&anon, Some("secret"), body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = anon.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    assert_eq!(json["version"]["docs_build_status"], "in_progress");
+
+    let body = json!({ "crate": "foo", "version": "1.0.0", "status": "failure" });
+    let response = report(&anon, Some("secret"), body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = anon.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    assert_eq!(json["version"]["docs_build_status"], "failure");
+
+    // Other versions are not affected
+    let json = anon.get::<()>("/api/v1/crates/foo/1.1.0").await.json();
+    assert_eq!(
+        json["version"]["docs_build_status"],
+        serde_json::Value::Null
+    );
+
+    let body = json!({ "crate": "foo", "version": "2.0.0", "status": "success" });
+    let response = report(&anon, Some("secret"), body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not have a version `2.0.0`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn build_status_requires_token() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.docs_rs_webhook_token = Some("secret".into()))
+        .empty();
+
+    let body = json!({ "crate": "foo", "version": "1.0.0", "status": "success" });
+
+    let response = report(&anon, None, body.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid or missing authorization token"}]}"###);
+
+    let response = report(&anon, Some("foobar"), body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn build_status_webhook_disabled() {
+    let (_, anon) = TestApp::init().empty();
+
+    let body = json!({ "crate": "foo", "version": "1.0.0", "status": "success" });
+    let response = report(&anon, Some("secret"), body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The docs.rs webhook is disabled on this crates.io instance"}]}"###);
+}
@@ -0,0 +1,217 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockAnonymousUser, MockCookieUser, RequestHelper, Response, TestApp};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crates_io::models::{Email, NotificationClass};
+use crates_io::schema::emails;
+use diesel::prelude::*;
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const SES_URL: &str = "/api/private/email_events/ses";
+const MAILGUN_URL: &str = "/api/private/email_events/mailgun";
+
+async fn send(
+    anon: &MockAnonymousUser,
+    url: &str,
+    token: Option<&str>,
+    body: Value,
+) -> Response<Value> {
+    let mut request = anon.request_builder(Method::POST, url);
+    *request.body_mut() = body.to_string().into();
+    request.header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = token {
+        let credentials = STANDARD.encode(format!("crates-io:{token}"));
+        request.header(header::AUTHORIZATION, &format!("Basic {credentials}"));
+    }
+    anon.run(request).await
+}
+
+/// Wraps the SES notification in an SNS message.
+fn sns_notification(notification: Value) -> Value {
+    json!({
+        "Type": "Notification",
+        "MessageId": "22b80b92-fdea-4c2c-8f9d-bdfb0c7bf324",
+        "TopicArn": "arn:aws:sns:us-west-2:123456789012:ses-notifications",
+        "Message": notification.to_string(),
+    })
+}
+
+fn ses_bounce(bounce_type: &str, address: &str) -> Value {
+    sns_notification(json!({
+        "notificationType": "Bounce",
+        "bounce": {
+            "bounceType": bounce_type,
+            "bouncedRecipients": [{ "emailAddress": address }],
+        },
+    }))
+}
+
+async fn email_undeliverable(user: &MockCookieUser) -> Value {
+    let json = user.get::<()>("/api/v1/me").await.json();
+    json["user"]["email_undeliverable"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn webhooks_require_the_token() {
+    let (_, anon) = TestApp::init().empty();
+
+    let body = ses_bounce("Permanent", "something@example.com");
+    let response = send(&anon, SES_URL, Some("secret"), body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The email webhooks are disabled on this crates.io instance"}]}"###);
+
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.email_webhook_token = Some("secret".into()))
+        .empty();
+
+    let body = ses_bounce("Permanent", "something@example.com");
+    let response = send(&anon, SES_URL, None, body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body = ses_bounce("Permanent", "something@example.com");
+    let response = send(&anon, SES_URL, Some("wrong"), body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid or missing authorization token"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ses_bounces_suppress_notifications_until_verified_again() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.email_webhook_token = Some("secret".into()))
+        .with_user();
+    let user_id = user.as_model().id;
+
+    let find_recipient = |app: &TestApp| {
+        app.db(|conn| Email::find_recipient(conn, user_id, NotificationClass::Publishing).unwrap())
+    };
+
+    // Transient bounces are ignored
+    let body = ses_bounce("Transient", "something@example.com");
+    send(&anon, SES_URL, Some("secret"), body).await.good();
+    assert_eq!(email_undeliverable(&user).await, false);
+
+    let body = ses_bounce("Permanent", "Something@Example.com");
+    send(&anon, SES_URL, Some("secret"), body).await.good();
+    assert_eq!(email_undeliverable(&user).await, true);
+    assert_eq!(find_recipient(&app), None);
+
+    let json = user.get::<()>("/api/v1/me/emails").await.json();
+    assert_eq!(json["emails"][0]["undeliverable"], true);
+    let email_id = json["emails"][0]["id"].as_i64().unwrap() as i32;
+
+    // Undeliverable addresses can be verified again
+    let url = format!("/api/v1/me/emails/{email_id}/resend");
+    user.put::<Value>(&url, "").await.good();
+
+    let token: String = app.db(|conn| {
+        emails::table
+            .find(email_id)
+            .select(emails::token)
+            .get_result(conn)
+            .unwrap()
+    });
+    let url = format!("/api/v1/confirm/{token}");
+    anon.put::<Value>(&url, "").await.good();
+
+    assert_eq!(email_undeliverable(&user).await, false);
+    assert_eq!(
+        find_recipient(&app).as_deref(),
+        Some("something@example.com")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ses_complaints_and_subscription_confirmations() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.email_webhook_token = Some("secret".into()))
+        .with_user();
+
+    // Subscriptions have to be confirmed manually via the logged URL
+    let body = json!({
+        "Type": "SubscriptionConfirmation",
+        "TopicArn": "arn:aws:sns:us-west-2:123456789012:ses-notifications",
+        "SubscribeURL": "https://sns.us-west-2.amazonaws.com/?Action=ConfirmSubscription",
+    });
+    send(&anon, SES_URL, Some("secret"), body).await.good();
+
+    // Notifications of SES event publishing use `eventType`
+    let body = sns_notification(json!({
+        "eventType": "Complaint",
+        "complaint": {
+            "complainedRecipients": [{ "emailAddress": "something@example.com" }],
+        },
+    }));
+    send(&anon, SES_URL, Some("secret"), body).await.good();
+    assert_eq!(email_undeliverable(&user).await, true);
+
+    let reason: Option<String> = app.db(|conn| {
+        emails::table
+            .select(emails::undeliverable_reason)
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(reason.as_deref(), Some("complaint"));
+
+    let response = send(&anon, SES_URL, Some("secret"), json!({ "Type": "Unknown" })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid SNS message"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mailgun_permanent_failures() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.email_webhook_token = Some("secret".into()))
+        .with_user();
+
+    let event = |severity: &str| {
+        json!({
+            "signature": { "timestamp": "1529006854", "token": "a8ce0edb", "signature": "d2271d12" },
+            "event-data": {
+                "event": "failed",
+                "severity": severity,
+                "recipient": "something@example.com",
+            },
+        })
+    };
+
+    // Temporary failures are retried by Mailgun
+    send(&anon, MAILGUN_URL, Some("secret"), event("temporary"))
+        .await
+        .good();
+    assert_eq!(email_undeliverable(&user).await, false);
+
+    send(&anon, MAILGUN_URL, Some("secret"), event("permanent"))
+        .await
+        .good();
+    assert_eq!(email_undeliverable(&user).await, true);
+
+    let reason: Option<String> = app.db(|conn| {
+        emails::table
+            .select(emails::undeliverable_reason)
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(reason.as_deref(), Some("bounce"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn no_emails_are_sent_to_undeliverable_addresses() {
+    let (app, anon, user, token) = TestApp::init()
+        .with_config(|config| config.email_webhook_token = Some("secret".into()))
+        .with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+    app.db_new_user("bar");
+
+    let body = ses_bounce("Permanent", "something@example.com");
+    send(&anon, SES_URL, Some("secret"), body).await.good();
+
+    // The invitation is created, but the email to the invited user is skipped
+    token.add_named_owner("foo", "bar").await.good();
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(mails.len(), 0);
+}
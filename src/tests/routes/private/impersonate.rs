@@ -0,0 +1,154 @@
+//! Tests for the `/api/private/impersonate` endpoints
+
+use crate::util::{
+    encode_session_data, MockAnonymousUser, MockRequestExt, RequestHelper, Response, TestApp,
+};
+use crates_io::models::{ImpersonationAuditLogEntry, SESSION_TOKEN_KEY};
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+use std::collections::HashMap;
+
+/// Returns the session cookie that was set by the response.
+fn session_cookie<T>(response: &Response<T>) -> String {
+    let set_cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+    set_cookie.split(';').next().unwrap().to_string()
+}
+
+async fn request_with_cookie(
+    anon: &MockAnonymousUser,
+    method: Method,
+    path: &str,
+    cookie: &str,
+) -> Response<()> {
+    let mut request = anon.request_builder(method, path);
+    request.header(header::COOKIE, cookie);
+    anon.run(request).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_impersonate() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    let url = format!("/api/private/impersonate/{}", other.as_model().id);
+
+    let response = user.run::<()>(user.post_request(&url)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to impersonate users"}]}"###);
+
+    let response = anon.run::<()>(anon.post_request(&url)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn admins_can_not_be_impersonated() {
+    let (app, _, user) = TestApp::init().with_user();
+    user.make_admin();
+    let other = app.db_new_user("other");
+    other.make_admin();
+
+    let url = format!("/api/private/impersonate/{}", other.as_model().id);
+    let response = user.run::<()>(user.post_request(&url)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"admins can not be impersonated"}]}"###);
+
+    let response = user
+        .run::<()>(user.post_request("/api/private/impersonate/404"))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn impersonation_session() {
+    let (app, anon, admin) = TestApp::init().with_user();
+    admin.make_admin();
+    let user = app.db_new_user("other");
+    let user_id = user.as_model().id;
+
+    let url = format!("/api/private/impersonate/{user_id}");
+    let response = admin.run::<()>(admin.post_request(&url)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["impersonation"]["user"]["login"], "other");
+    assert!(json["impersonation"]["expires_at"].is_string());
+    let cookie = session_cookie(&response);
+
+    // The session identifier of the admin was rotated
+    let response = admin.get::<()>("/api/v1/me").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Read-only requests are performed as the impersonated user
+    let response = request_with_cookie(&anon, Method::GET, "/api/v1/me", &cookie).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["user"]["login"], "other");
+
+    // Destructive actions are blocked
+    let response = request_with_cookie(&anon, Method::PUT, "/api/v1/me/tokens", &cookie).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action can not be performed while impersonating another user"}]}"###);
+
+    let response = request_with_cookie(&anon, Method::PUT, "/api/v1/crates/new", &cookie).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Ending the impersonation signs the session back in as the admin
+    let path = "/api/private/impersonate";
+    let response = request_with_cookie(&anon, Method::DELETE, path, &cookie).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response);
+
+    let response = request_with_cookie(&anon, Method::GET, "/api/v1/me", &cookie).await;
+    assert_eq!(response.json()["user"]["login"], "foo");
+
+    let response = request_with_cookie(&anon, Method::DELETE, path, &cookie).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Every request of the impersonation session was logged
+    let entries = app.db(|conn| ImpersonationAuditLogEntry::for_user(conn, user_id).unwrap());
+    let entries = entries
+        .iter()
+        .rev()
+        .map(|entry| {
+            assert_eq!(entry.impersonator_id, admin.as_model().id);
+            format!("{} {}", entry.method, entry.path)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![
+            format!("POST /api/private/impersonate/{user_id}"),
+            "GET /api/v1/me".to_string(),
+            "PUT /api/v1/me/tokens".to_string(),
+            "PUT /api/v1/crates/new".to_string(),
+            "DELETE /api/private/impersonate".to_string(),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn expired_impersonation_session() {
+    let (app, anon, admin) = TestApp::init().with_user();
+    admin.make_admin();
+    let user = app.db_new_user("other");
+
+    let data = HashMap::from([
+        ("user_id".to_string(), user.as_model().id.to_string()),
+        (
+            "impersonator_id".to_string(),
+            admin.as_model().id.to_string(),
+        ),
+        ("impersonation_expires_at".to_string(), "1000".to_string()),
+        (
+            SESSION_TOKEN_KEY.to_string(),
+            admin.session_token().to_string(),
+        ),
+    ]);
+    let cookie = encode_session_data(app.as_inner().session_key(), &data);
+
+    let response = request_with_cookie(&anon, Method::GET, "/api/v1/me", &cookie).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the impersonation session has expired"}]}"###);
+
+    // The session is signed back in as the admin
+    let cookie = session_cookie(&response);
+    let response = request_with_cookie(&anon, Method::GET, "/api/v1/me", &cookie).await;
+    assert_eq!(response.json()["user"]["login"], "foo");
+}
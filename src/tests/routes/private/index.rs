@@ -0,0 +1,76 @@
+//! Tests for the `POST /api/private/index/:crate_id/rebuild` endpoint
+
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, Response, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+async fn rebuild(user: &impl RequestHelper, crate_name: &str) -> Response<()> {
+    let url = format!("/api/private/index/{crate_name}/rebuild");
+    user.run(user.post_request(&url)).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rebuild_regenerates_index_file() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    let upstream = app.upstream_index();
+
+    let body = PublishBuilder::new("foo", "1.0.0").body();
+    token.publish_crate(body).await.good();
+
+    let original = upstream.read_file("3/f/foo").unwrap();
+    upstream.write_file("3/f/foo", "corrupted\n").unwrap();
+
+    user.make_admin();
+
+    let response = rebuild(&user, "foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"ok":true}"###);
+
+    app.run_pending_background_jobs().await;
+    assert_ok_eq!(upstream.read_file("3/f/foo"), original);
+    assert_ok_eq!(
+        upstream.list_commits(),
+        vec![
+            "Initial Commit",
+            "Create crate `foo`",
+            "Write `3/f/foo`",
+            "Update crate `foo`",
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rebuild_requires_admin() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+
+    let body = PublishBuilder::new("foo", "1.0.0").body();
+    token.publish_crate(body).await.good();
+
+    let response = rebuild(&anon, "foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = rebuild(&token, "foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = rebuild(&user, "foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to rebuild the index"}]}"###);
+
+    app.run_pending_background_jobs().await;
+    assert_ok_eq!(
+        app.upstream_index().list_commits(),
+        vec!["Initial Commit", "Create crate `foo`"]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rebuild_unknown_crate() {
+    let (_, _, user) = TestApp::full().with_user();
+    user.make_admin();
+
+    let response = rebuild(&user, "unknown").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `unknown` does not exist"}]}"###);
+}
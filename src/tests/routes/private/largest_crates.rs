@@ -0,0 +1,98 @@
+//! Tests for the `/api/private/crates/largest` endpoint
+
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/private/crates/largest";
+
+fn names(json: &Value) -> Vec<&str> {
+    json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| krate["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_view_report() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to view the largest crates"}]}"###);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_parameters() {
+    let (_, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let response = admin.get_with_query::<()>(URL, "limit=0").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`limit` must be between 1 and 100"}]}"###);
+
+    let response = admin.get_with_query::<()>(URL, "sort=foo").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`sort` must be either `compressed` or `uncompressed`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn largest_crates() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+    let user_id = admin.as_model().id;
+
+    app.db(|conn| {
+        CrateBuilder::new("small", user_id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .size(100)
+                    .uncompressed_size(900),
+            )
+            .expect_build(conn);
+
+        // Only the default version of a crate is taken into account
+        CrateBuilder::new("regressed", user_id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .size(5000)
+                    .uncompressed_size(9000),
+            )
+            .version(
+                VersionBuilder::new("1.1.0")
+                    .size(300)
+                    .uncompressed_size(600),
+            )
+            .expect_build(conn);
+
+        CrateBuilder::new("dense", user_id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .size(200)
+                    .uncompressed_size(400),
+            )
+            .expect_build(conn);
+    });
+
+    let json = admin.get::<Value>(URL).await.good();
+    assert_eq!(names(&json), ["regressed", "dense", "small"]);
+    assert_eq!(json["crates"][0]["version"], "1.1.0");
+    assert_eq!(json["crates"][0]["crate_size"], 300);
+    assert_eq!(json["crates"][0]["uncompressed_size"], 600);
+
+    let json = admin
+        .get_with_query::<Value>(URL, "sort=uncompressed")
+        .await
+        .good();
+    assert_eq!(names(&json), ["small", "regressed", "dense"]);
+
+    let json = admin.get_with_query::<Value>(URL, "limit=1").await.good();
+    assert_eq!(names(&json), ["regressed"]);
+}
@@ -1 +1,16 @@
+mod admin_audit;
+mod attestation_providers;
+mod bulk_yanks;
+mod crate_freezes;
 mod crate_owner_invitations;
+mod critical_crates;
+mod dependency_policy_exceptions;
+mod docs_rs;
+mod email_events;
+mod impersonate;
+mod index;
+mod largest_crates;
+mod rate_limits;
+mod spam_flags;
+mod tarball_repairs;
+mod user_agent_policies;
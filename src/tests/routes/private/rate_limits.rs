@@ -0,0 +1,272 @@
+//! Tests for the `/api/private/rate_limits/*` endpoints
+
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use chrono::{TimeDelta, Utc};
+use crates_io::rate_limiter::LimitedAction;
+use crates_io::schema::{
+    publish_limit_buckets, publish_rate_overrides, rate_limit_rejections, users,
+};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+use std::time::Duration;
+
+const URL: &str = "/api/private/rate_limits/report";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_view_report() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to view the rate limit report"}]}"###);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_parameters() {
+    let (_, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let response = admin.get_with_query::<()>(URL, "days=0").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`days` must be between 1 and 90"}]}"###);
+
+    let response = admin.get_with_query::<()>(URL, "limit=foo").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `limit` parameter"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn most_throttled_users() {
+    let (app, _, admin) = TestApp::init()
+        .with_rate_limit(LimitedAction::BulkMetadata, Duration::from_millis(500), 1)
+        .with_user();
+    admin.make_admin();
+
+    let throttled = app.db_new_user("throttled");
+    let occasional = app.db_new_user("occasional");
+    let throttled_id = throttled.as_model().id;
+    let occasional_id = occasional.as_model().id;
+
+    app.db(|conn| {
+        // Ratelimit bucket should next refill in about a year
+        let far_future = Utc::now().naive_utc() + Duration::from_secs(60 * 60 * 24 * 365);
+        diesel::insert_into(publish_limit_buckets::table)
+            .values((
+                publish_limit_buckets::user_id.eq(throttled_id),
+                publish_limit_buckets::action.eq(LimitedAction::BulkMetadata),
+                publish_limit_buckets::tokens.eq(0),
+                publish_limit_buckets::last_refill.eq(far_future),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        diesel::insert_into(publish_rate_overrides::table)
+            .values((
+                publish_rate_overrides::user_id.eq(throttled_id),
+                publish_rate_overrides::action.eq(LimitedAction::PublishNew),
+                publish_rate_overrides::burst.eq(10),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        let rejections = vec![
+            (throttled_id, LimitedAction::PublishNew, TimeDelta::hours(1)),
+            (
+                occasional_id,
+                LimitedAction::YankUnyank,
+                TimeDelta::hours(2),
+            ),
+            // Rejections outside of the report window are ignored
+            (
+                occasional_id,
+                LimitedAction::YankUnyank,
+                TimeDelta::days(10),
+            ),
+            (
+                occasional_id,
+                LimitedAction::YankUnyank,
+                TimeDelta::days(20),
+            ),
+        ]
+        .into_iter()
+        .map(|(user_id, action, age)| {
+            (
+                rate_limit_rejections::user_id.eq(user_id),
+                rate_limit_rejections::action.eq(action),
+                rate_limit_rejections::created_at.eq(Utc::now().naive_utc() - age),
+            )
+        })
+        .collect::<Vec<_>>();
+
+        diesel::insert_into(rate_limit_rejections::table)
+            .values(&rejections)
+            .execute(conn)
+            .unwrap();
+    });
+
+    for _ in 0..2 {
+        let response = throttled
+            .get_with_query::<()>("/api/v1/bulk/crates", "ids=foo")
+            .await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let response = admin.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["days"], 7);
+
+    let users = json["users"].as_array().unwrap();
+    assert_eq!(users.len(), 2);
+
+    assert_eq!(users[0]["user"]["login"], "throttled");
+    assert_eq!(users[0]["rejections"], 3);
+    let actions = users[0]["actions"].as_array().unwrap();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[0]["action"], "bulk_metadata");
+    assert_eq!(actions[0]["rejections"], 2);
+    assert_eq!(actions[0]["burst_override"], serde_json::Value::Null);
+    assert_eq!(actions[1]["action"], "publish_new");
+    assert_eq!(actions[1]["rejections"], 1);
+    assert_eq!(actions[1]["burst_override"], 10);
+
+    assert_eq!(users[1]["user"]["login"], "occasional");
+    assert_eq!(users[1]["rejections"], 1);
+
+    let response = admin.get_with_query::<()>(URL, "days=30").await;
+    let json = response.json();
+    assert_eq!(json["users"][0]["user"]["login"], "occasional");
+    assert_eq!(json["users"][0]["rejections"], 4);
+
+    let response = admin.get_with_query::<()>(URL, "limit=1").await;
+    let json = response.json();
+    assert_eq!(json["users"].as_array().unwrap().len(), 1);
+    assert_eq!(json["users"][0]["user"]["login"], "throttled");
+}
+
+const REQUESTS_URL: &str = "/api/private/rate_limits/requests";
+
+async fn create_override_request(user: &MockCookieUser) -> i64 {
+    let body = r#"{ "action": "publish_new", "burst": 20, "justification": "workspace" }"#;
+    let json = user
+        .post::<Value>("/api/v1/me/rate_limits/requests", body)
+        .await
+        .good();
+    json["request"]["id"].as_i64().unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_review_requests() {
+    let (_, _, user) = TestApp::init().with_user();
+    let id = create_override_request(&user).await;
+
+    let response = user.get::<()>(REQUESTS_URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to review rate limit requests"}]}"###);
+
+    let url = format!("{REQUESTS_URL}/{id}/review");
+    let response = user.put::<()>(&url, r#"{ "decision": "approve" }"#).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn approving_a_request_creates_an_override() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let user = app.db_new_user("requester");
+    let user_id = user.as_model().id;
+    let id = create_override_request(&user).await;
+
+    let json = admin.get::<Value>(REQUESTS_URL).await.good();
+    assert_eq!(json["requests"].as_array().unwrap().len(), 1);
+    assert_eq!(json["requests"][0]["user"]["login"], "requester");
+    assert_eq!(json["requests"][0]["justification"], "workspace");
+
+    let url = format!("{REQUESTS_URL}/{id}/review");
+    let body = r#"{ "decision": "approve", "burst": 15, "expires_in_days": 30 }"#;
+    let json = admin.put::<Value>(&url, body).await.good();
+    assert_eq!(json["request"]["status"], "approved");
+    assert_eq!(json["request"]["burst"], 15);
+
+    let (burst, expires_at) = app.db(|conn| {
+        publish_rate_overrides::table
+            .filter(publish_rate_overrides::user_id.eq(user_id))
+            .filter(publish_rate_overrides::action.eq(LimitedAction::PublishNew))
+            .select((
+                publish_rate_overrides::burst,
+                publish_rate_overrides::expires_at,
+            ))
+            .first::<(i32, Option<chrono::NaiveDateTime>)>(conn)
+            .unwrap()
+    });
+    assert_eq!(burst, 15);
+    let expires_at = expires_at.unwrap();
+    assert!(expires_at > Utc::now().naive_utc() + TimeDelta::days(29));
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].1.contains("was approved"));
+
+    // Requests can only be reviewed once
+    let response = admin.put::<()>(&url, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the request was already reviewed"}]}"###);
+
+    let json = admin.get::<Value>(REQUESTS_URL).await.good();
+    assert!(json["requests"].as_array().unwrap().is_empty());
+
+    // The user can file a new request once the previous one was reviewed
+    create_override_request(&user).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejecting_a_request() {
+    let (app, _, admin) = TestApp::init().with_user();
+    admin.make_admin();
+
+    let user = app.db_new_user("requester");
+    let id = create_override_request(&user).await;
+
+    let url = format!("{REQUESTS_URL}/{id}/review");
+    let response = admin
+        .put::<()>(
+            &url,
+            r#"{ "decision": "approve", "expires_in_days": 1000 }"#,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`expires_in_days` must be between 1 and 365"}]}"###);
+
+    let json = admin
+        .put::<Value>(&url, r#"{ "decision": "reject" }"#)
+        .await
+        .good();
+    assert_eq!(json["request"]["status"], "rejected");
+    assert_eq!(json["request"]["expires_at"], Value::Null);
+
+    let overrides = app.db(|conn| {
+        publish_rate_overrides::table
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap()
+    });
+    assert_eq!(overrides, 0);
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].1.contains("was rejected"));
+
+    let response = admin
+        .put::<()>(
+            &format!("{REQUESTS_URL}/0/review"),
+            r#"{ "decision": "reject" }"#,
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
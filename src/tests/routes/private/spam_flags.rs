@@ -0,0 +1,120 @@
+//! Tests for the `/api/private/spam_flags` endpoints
+
+use crate::builders::PublishBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crates_io::models::VersionQuarantine;
+use crates_io::schema::versions;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/private/spam_flags";
+const SPAM_DESCRIPTION: &str = "Get free robux at https://robux.example";
+
+fn version(app: &TestApp, num: &str) -> (i32, bool) {
+    app.db(|conn| {
+        versions::table
+            .filter(versions::num.eq(num))
+            .select((versions::id, versions::yanked))
+            .first(conn)
+            .unwrap()
+    })
+}
+
+async fn review(admin: &MockCookieUser, version_id: i32, decision: &str) {
+    let url = format!("{URL}/{version_id}/review");
+    let body = json!({ "decision": decision }).to_string();
+    admin.put::<Value>(&url, body).await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_review_spam_flags() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to review spam flags"}]}"###);
+
+    let body = json!({ "decision": "confirm" }).to_string();
+    let response = user.put::<()>(&format!("{URL}/1/review"), body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spam_is_published_and_flagged() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").description("A regular crate");
+    admin.publish_crate(crate_to_publish).await.good();
+
+    let json = admin.get::<Value>(URL).await.good();
+    assert_eq!(json["spam_flags"].as_array().unwrap().len(), 0);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0")
+        .description(SPAM_DESCRIPTION)
+        .keyword("robux");
+    admin.publish_crate(crate_to_publish).await.good();
+
+    let (version_id, yanked) = version(&app, "1.1.0");
+    assert!(!yanked);
+
+    let json = admin.get::<Value>(URL).await.good();
+    let flags = json["spam_flags"].as_array().unwrap();
+    assert_eq!(flags.len(), 1);
+    assert_eq!(flags[0]["version_id"], version_id);
+    assert_eq!(flags[0]["crate"], "foo");
+    assert_eq!(flags[0]["version"], "1.1.0");
+    assert_eq!(flags[0]["description"], SPAM_DESCRIPTION);
+    assert_eq!(flags[0]["score"], 160);
+    assert_eq!(flags[0]["quarantined"], false);
+    assert_eq!(
+        flags[0]["reasons"],
+        json!([
+            "1 of 5 words in the description are URLs",
+            "contains the known spam phrase `free robux`",
+        ])
+    );
+
+    review(&admin, version_id, "confirm").await;
+    app.run_pending_background_jobs().await;
+
+    let (_, yanked) = version(&app, "1.1.0");
+    assert!(yanked);
+    assert!(app.db(|conn| VersionQuarantine::is_quarantined(conn, version_id).unwrap()));
+
+    let stored_files = app.stored_files().await;
+    assert!(!stored_files.contains(&"crates/foo/foo-1.1.0.crate".to_string()));
+    assert!(stored_files.contains(&"quarantine/crates/foo/foo-1.1.0.crate".to_string()));
+
+    let json = admin.get::<Value>(URL).await.good();
+    assert_eq!(json["spam_flags"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dismissed_flags_keep_the_version() {
+    let (app, _, admin) = TestApp::full().with_user();
+    admin.make_admin();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").description(SPAM_DESCRIPTION);
+    admin.publish_crate(crate_to_publish).await.good();
+
+    let (version_id, _) = version(&app, "1.0.0");
+    review(&admin, version_id, "dismiss").await;
+
+    let (_, yanked) = version(&app, "1.0.0");
+    assert!(!yanked);
+    assert!(!app.db(|conn| VersionQuarantine::is_quarantined(conn, version_id).unwrap()));
+
+    let json = admin.get::<Value>(URL).await.good();
+    assert_eq!(json["spam_flags"].as_array().unwrap().len(), 0);
+
+    let url = format!("{URL}/{}/review", version_id + 1);
+    let body = json!({ "decision": "dismiss" }).to_string();
+    let response = admin.put::<()>(&url, body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,161 @@
+//! Tests for the `/api/private/crates/:crate_id/:version/tarball` endpoint
+//! and the advisories about the resulting checksum rotations
+
+use crate::builders::PublishBuilder;
+use crate::util::{MockTokenUser, RequestHelper, TestApp};
+use base64::{engine::general_purpose, Engine};
+use crates_io::models::CrateVisibility;
+use crates_io_tarball::TarballBuilder;
+use flate2::Compression;
+use hex::ToHex;
+use http::StatusCode;
+use insta::assert_snapshot;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const URL: &str = "/api/private/crates/foo_repair/1.0.0/tarball?reason=corrupted%20in%20storage";
+const ADVISORY_URL: &str = "/api/v1/crates/foo_repair/1.0.0/checksum_advisory";
+
+const MANIFEST: &[u8] = br#"[package]
+name = "foo_repair"
+version = "1.0.0"
+description = "description"
+license = "MIT"
+"#;
+
+const LIB: &[u8] = b"pub fn foo() {}";
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_slice(&[7; 32]).unwrap()
+}
+
+/// Builds a tarball with the same files as the published tarball, but
+/// without compression, so that its checksum differs.
+fn replacement(lib: &[u8]) -> Vec<u8> {
+    TarballBuilder::new()
+        .add_file("foo_repair-1.0.0/Cargo.toml", MANIFEST)
+        .add_file("foo_repair-1.0.0/src/lib.rs", lib)
+        .build_with_compression(Compression::none())
+}
+
+async fn publish(token: &MockTokenUser) {
+    let crate_to_publish = PublishBuilder::new("foo_repair", "1.0.0")
+        .custom_manifest(MANIFEST)
+        .add_file("foo_repair-1.0.0/src/lib.rs", LIB);
+    token.publish_crate(crate_to_publish).await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_repair_tarballs() {
+    let (_, _, user, token) = TestApp::full().with_token();
+    publish(&token).await;
+
+    let response = user.put::<()>(URL, replacement(LIB)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to repair tarballs"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn repair_rotates_the_checksum() {
+    let (app, anon, user, token) = TestApp::full()
+        .with_config(|config| config.checksum_advisory_key = Some(signing_key()))
+        .with_token();
+    user.make_admin();
+    publish(&token).await;
+
+    let json = anon
+        .get::<Value>("/api/v1/crates/foo_repair/1.0.0")
+        .await
+        .good();
+    let old_checksum = json["version"]["checksum"].as_str().unwrap().to_string();
+
+    let tarball = replacement(LIB);
+    let new_checksum: String = Sha256::digest(&tarball).encode_hex();
+    assert_ne!(old_checksum, new_checksum);
+
+    let json = user.put::<Value>(URL, tarball.clone()).await.good();
+    assert_eq!(json["old_checksum"], old_checksum);
+    assert_eq!(json["new_checksum"], new_checksum);
+    assert_eq!(json["rotated"], true);
+    app.run_pending_background_jobs().await;
+
+    let json = anon
+        .get::<Value>("/api/v1/crates/foo_repair/1.0.0")
+        .await
+        .good();
+    assert_eq!(json["version"]["checksum"], new_checksum);
+
+    let index = app.crates_from_index_head("foo_repair");
+    assert_eq!(index[0].cksum, new_checksum);
+
+    let stored = app
+        .as_inner()
+        .storage
+        .download_crate_file("foo_repair", "1.0.0", CrateVisibility::Public)
+        .await
+        .unwrap();
+    assert_eq!(stored.as_ref(), tarball.as_slice());
+
+    let json = anon.get::<Value>(ADVISORY_URL).await.good();
+    let advisory = json["advisory"].as_str().unwrap();
+    let signature = json["signature"].as_str().unwrap();
+
+    let signature = general_purpose::STANDARD.decode(signature).unwrap();
+    let signature = Signature::from_der(&signature).unwrap();
+    let verifying_key = VerifyingKey::from(&signing_key());
+    assert_ok!(verifying_key.verify(advisory.as_bytes(), &signature));
+
+    let advisory: Value = serde_json::from_str(advisory).unwrap();
+    assert_eq!(advisory["crate"], "foo_repair");
+    assert_eq!(advisory["version"], "1.0.0");
+    assert_eq!(advisory["checksum"], new_checksum);
+    let rotations = advisory["rotations"].as_array().unwrap();
+    assert_eq!(rotations.len(), 1);
+    assert_eq!(rotations[0]["old_checksum"], old_checksum);
+    assert_eq!(rotations[0]["new_checksum"], new_checksum);
+    assert_eq!(rotations[0]["reason"], "corrupted in storage");
+
+    let json = anon
+        .get::<Value>("/api/v1/checksum_advisory_key")
+        .await
+        .good();
+    let public_key = json["public_key"].as_str().unwrap();
+    assert!(public_key.starts_with("-----BEGIN PUBLIC KEY-----"));
+
+    // Uploading the same tarball again restores it without another rotation
+    let json = user.put::<Value>(URL, tarball).await.good();
+    assert_eq!(json["rotated"], false);
+
+    let json = anon.get::<Value>(ADVISORY_URL).await.good();
+    let advisory: Value = serde_json::from_str(json["advisory"].as_str().unwrap()).unwrap();
+    assert_eq!(advisory["rotations"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn repair_requires_matching_contents() {
+    let (_, anon, user, token) = TestApp::full().with_token();
+    user.make_admin();
+    publish(&token).await;
+
+    let response = user.put::<()>(URL, replacement(b"pub fn bar() {}")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the replacement tarball does not match the content manifest of the version: src/lib.rs"}]}"###);
+
+    let response = user
+        .put::<()>(
+            "/api/private/crates/foo_repair/1.0.0/tarball?reason=",
+            replacement(LIB),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json = anon.get::<Value>(ADVISORY_URL).await.good();
+    assert_eq!(json["signature"], Value::Null);
+    let advisory: Value = serde_json::from_str(json["advisory"].as_str().unwrap()).unwrap();
+    assert_eq!(advisory["rotations"], Value::Array(vec![]));
+
+    let response = anon.get::<()>("/api/v1/checksum_advisory_key").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,101 @@
+//! Tests for the `/api/private/user_agent_policies` endpoints
+
+use crate::util::{MockAnonymousUser, MockRequestExt, RequestHelper, TestApp};
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+use serde_json::json;
+use std::time::Duration;
+
+const URL: &str = "/api/private/user_agent_policies";
+
+async fn request_as(anon: &MockAnonymousUser, user_agent: &str) -> StatusCode {
+    let mut request = anon.get_request("/api/v1/summary");
+    request.header(header::USER_AGENT, user_agent);
+    anon.run::<()>(request).await.status()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_admins_can_manage_policies() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    let body = json!({ "pattern": "BadBot", "throttle_class": "blocked" }).to_string();
+
+    let response = user.put::<()>(URL, body.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must be an admin to manage user agent policies"}]}"###);
+
+    let response = anon.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocked_user_agents() {
+    let (_, anon, user) = TestApp::init().with_user();
+    user.make_admin();
+
+    let body = json!({ "pattern": "BadBot", "throttle_class": "blocked" }).to_string();
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["policy"]["pattern"], "badbot");
+    assert_eq!(json["policy"]["throttle_class"], "blocked");
+
+    assert_eq!(request_as(&anon, "Mozilla/5.0").await, StatusCode::OK);
+    assert_eq!(request_as(&anon, "badbot/1.0").await, StatusCode::FORBIDDEN);
+
+    // Authenticated requests are not affected
+    let mut request = user.get_request("/api/v1/summary");
+    request.header(header::USER_AGENT, "badbot/1.0");
+    assert_eq!(user.run::<()>(request).await.status(), StatusCode::OK);
+
+    let json = user.get::<()>(URL).await.json();
+    let id = json["policies"][0]["id"].as_i64().unwrap();
+
+    let response = user.delete::<()>(&format!("{URL}/{id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(request_as(&anon, "badbot/1.0").await, StatusCode::OK);
+
+    let response = user.delete::<()>(&format!("{URL}/{id}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_user_agents() {
+    let (_, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.user_agent_throttle.slow_rate = Duration::from_secs(60);
+            config.user_agent_throttle.slow_burst = 2;
+        })
+        .with_user();
+    user.make_admin();
+
+    let body = json!({ "pattern": "crawler", "throttle_class": "slow" }).to_string();
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(request_as(&anon, "crawler/1.0").await, StatusCode::OK);
+    assert_eq!(request_as(&anon, "crawler/2.0").await, StatusCode::OK);
+
+    let mut request = anon.get_request("/api/v1/summary");
+    request.header(header::USER_AGENT, "crawler/1.0");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "60");
+
+    // Other user agents are not affected
+    assert_eq!(request_as(&anon, "Mozilla/5.0").await, StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_pattern() {
+    let (_, _, user) = TestApp::init().with_user();
+    user.make_admin();
+
+    let body = json!({ "pattern": " ", "throttle_class": "slow" }).to_string();
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`pattern` must be between 1 and 200 characters"}]}"###);
+}
@@ -0,0 +1,134 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn research_endpoints_require_a_service_token() {
+    let (_, anon, user, token) = TestApp::init().with_token();
+
+    for url in [
+        "/api/v1/research/dependency_graph",
+        "/api/v1/research/download_distribution",
+        "/api/v1/research/owner_distribution",
+    ] {
+        let response = anon.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = user.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = token.get::<()>(url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the research API can only be used with service tokens"}]}"###);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependency_graph() {
+    let (app, _, user) = TestApp::init().with_user();
+    let service_token = user.db_new_service_token("research", "research@example.com");
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        let lib = CrateBuilder::new("foo_lib", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        CrateBuilder::new("foo_app", user_id)
+            .version(VersionBuilder::new("1.0.0").dependency(&lib, None))
+            .expect_build(conn);
+    });
+
+    let json = service_token
+        .get::<Value>("/api/v1/research/dependency_graph")
+        .await
+        .good();
+
+    let crates = json["crates"].as_array().unwrap();
+    assert_eq!(crates.len(), 2);
+    assert_eq!(crates[0]["name"], "foo_lib");
+    assert_eq!(crates[0]["dependencies"], Value::Array(vec![]));
+    assert_eq!(crates[1]["name"], "foo_app");
+    assert_eq!(crates[1]["version"], "1.0.0");
+    assert_eq!(crates[1]["dependencies"][0]["name"], "foo_lib");
+    assert_eq!(crates[1]["dependencies"][0]["kind"], "normal");
+    assert_eq!(json["meta"]["more"], false);
+
+    let url = format!(
+        "/api/v1/research/dependency_graph?since={}",
+        json["meta"]["next_cursor"].as_str().unwrap()
+    );
+    let json = service_token.get::<Value>(&url).await.good();
+    assert_eq!(json["crates"], Value::Array(vec![]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_distribution() {
+    let (app, _, user) = TestApp::init().with_user();
+    let service_token = user.db_new_service_token("research", "research@example.com");
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id)
+            .version("1.0.0")
+            .downloads(150)
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/research/download_distribution";
+    let json = service_token.get::<Value>(url).await.good();
+    let buckets = json["distributions"]["downloads"].as_array().unwrap();
+    assert_eq!(buckets.len(), 12);
+    assert_eq!(
+        buckets[0],
+        serde_json::json!({ "min": 0, "max": 0, "count": 1 })
+    );
+    assert_eq!(
+        buckets[3],
+        serde_json::json!({ "min": 100, "max": 999, "count": 1 })
+    );
+    assert_eq!(buckets[11]["max"], Value::Null);
+    assert_eq!(json["meta"]["epsilon"], Value::Null);
+
+    let json = service_token
+        .get::<Value>(&format!("{url}?epsilon=0.5"))
+        .await
+        .good();
+    assert_eq!(json["meta"]["epsilon"], 0.5);
+    let buckets = json["distributions"]["downloads"].as_array().unwrap();
+    assert!(buckets.iter().all(|b| b["count"].as_i64().unwrap() >= 0));
+
+    let response = service_token.get::<()>(&format!("{url}?epsilon=0")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`epsilon` must be between 0.01 and 10"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owner_distribution() {
+    let (app, _, user) = TestApp::init().with_user();
+    let service_token = user.db_new_service_token("research", "research@example.com");
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        for name in ["foo", "bar", "baz"] {
+            CrateBuilder::new(name, user_id).expect_build(conn);
+        }
+    });
+
+    let json = service_token
+        .get::<Value>("/api/v1/research/owner_distribution")
+        .await
+        .good();
+    let buckets = json["distributions"]["crates"].as_array().unwrap();
+    assert_eq!(buckets[0]["count"], 0);
+    assert_eq!(
+        buckets[1],
+        serde_json::json!({ "min": 1, "max": 9, "count": 1 })
+    );
+}
@@ -0,0 +1,86 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::CrateVisibility;
+use crates_io::schema::crates;
+use diesel::prelude::*;
+use http::StatusCode;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/teams/github:test-org:all/crates";
+
+fn crate_names(json: &Value) -> Vec<&str> {
+    json["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| krate["name"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_team_crates() {
+    let (app, anon) = TestApp::init().empty();
+    let user = app.db_new_user("user-all-teams");
+    let user_id = user.as_model().id;
+    let token = user.db_new_token("bar");
+
+    app.db(|conn| {
+        for name in ["foo", "bar", "baz", "secret", "not-owned"] {
+            CrateBuilder::new(name, user_id).expect_build(conn);
+        }
+    });
+
+    for name in ["foo", "bar", "baz", "secret"] {
+        token
+            .add_named_owner(name, "github:test-org:all")
+            .await
+            .good();
+    }
+
+    app.db(|conn| {
+        diesel::update(crates::table.filter(crates::name.eq("secret")))
+            .set(crates::visibility.eq(CrateVisibility::Private))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(crate_names(&json), vec!["bar", "baz", "foo"]);
+    assert_eq!(json["crates"][0]["added_by"]["login"], "user-all-teams");
+    assert!(json["crates"][0]["added_at"].is_string());
+    assert_eq!(json["meta"]["total"], 3);
+    assert_eq!(json["meta"]["next_page"], Value::Null);
+
+    let json = anon.get_with_query::<()>(URL, "per_page=2").await.json();
+    assert_eq!(crate_names(&json), vec!["bar", "baz"]);
+    assert_eq!(json["meta"]["total"], 3);
+    assert_eq!(json["meta"]["next_page"], "?per_page=2&page=2");
+
+    let json = anon
+        .get_with_query::<()>(URL, "per_page=2&page=2")
+        .await
+        .json();
+    assert_eq!(crate_names(&json), vec!["foo"]);
+    assert_eq!(json["meta"]["prev_page"], "?per_page=2&page=1");
+
+    // Removed owners are not listed
+    token
+        .remove_named_owner("foo", "github:test-org:all")
+        .await
+        .good();
+
+    let json = anon.get::<()>(URL).await.json();
+    assert_eq!(crate_names(&json), vec!["bar", "baz"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_team() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon
+        .get::<()>("/api/v1/teams/github:test-org:missing/crates")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
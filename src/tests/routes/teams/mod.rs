@@ -0,0 +1,2 @@
+mod crates;
+mod tokens;
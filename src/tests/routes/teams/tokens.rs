@@ -0,0 +1,146 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crates_io::models::token::EndpointScope;
+use crates_io::models::Team;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+const URL: &str = "/api/v1/teams/github:test-org:all/tokens";
+
+/// Creates the `foo` crate, which is owned by the `github:test-org:all` team,
+/// and returns the id of the team.
+async fn crate_owned_by_team(app: &TestApp, maintainer: &MockCookieUser) -> i32 {
+    app.db(|conn| {
+        CrateBuilder::new("foo", maintainer.as_model().id).expect_build(conn);
+    });
+
+    maintainer
+        .db_new_token("personal")
+        .add_named_owner("foo", "github:test-org:all")
+        .await
+        .good();
+
+    app.db(|conn| Team::find_by_login(conn, "github:test-org:all").unwrap().id)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_list_and_revoke() {
+    let (app, _) = TestApp::init().empty();
+    let maintainer = app.db_new_user("user-all-teams");
+    crate_owned_by_team(&app, &maintainer).await;
+
+    let body =
+        json!({ "api_token": { "name": "ci", "endpoint_scopes": ["publish-update", "yank"] } });
+    let response = maintainer.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["api_token"]["name"], "ci");
+    assert!(json["api_token"]["token"].is_string());
+    let id = json["api_token"]["id"].as_i64().unwrap();
+
+    let json = maintainer.get::<()>(URL).await.json();
+    let tokens = json["api_tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["id"], id);
+    assert_eq!(tokens[0]["created_by"]["login"], "user-all-teams");
+    assert!(tokens[0].get("token").is_none());
+
+    // Team tokens are not listed as personal API tokens
+    let json = maintainer.get::<()>("/api/v1/me/tokens").await.json();
+    let tokens = json["api_tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["name"], "personal");
+
+    let response = maintainer.delete::<()>(&format!("{URL}/{id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = maintainer.get::<()>(URL).await.json();
+    assert_eq!(json["api_tokens"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_maintainers_can_manage_tokens() {
+    let (app, anon) = TestApp::init().empty();
+    let maintainer = app.db_new_user("user-all-teams");
+    crate_owned_by_team(&app, &maintainer).await;
+
+    let member = app.db_new_user("user-one-team");
+    let response = member.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only maintainers of the team can manage its tokens"}]}"###);
+
+    let body = json!({ "api_token": { "name": "ci", "endpoint_scopes": ["yank"] } });
+    let response = member.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Tokens can not be used to manage team tokens
+    let token = maintainer.db_new_token("bar");
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = maintainer
+        .get::<()>("/api/v1/teams/github:test-org:unknown/tokens")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_with_invalid_scopes() {
+    let (app, _) = TestApp::init().empty();
+    let maintainer = app.db_new_user("user-all-teams");
+    crate_owned_by_team(&app, &maintainer).await;
+
+    let body = json!({ "api_token": { "name": "ci" } });
+    let response = maintainer.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"team tokens must have the `publish-update` and/or `yank` endpoint scopes"}]}"###);
+
+    let body =
+        json!({ "api_token": { "name": "ci", "endpoint_scopes": ["yank", "change-owners"] } });
+    let response = maintainer.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"team tokens must have the `publish-update` and/or `yank` endpoint scopes"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn team_tokens_can_only_be_used_for_team_crates() {
+    let (app, _) = TestApp::full().empty();
+    let maintainer = app.db_new_user("user-all-teams");
+    let team_id = crate_owned_by_team(&app, &maintainer).await;
+
+    app.db(|conn| {
+        CrateBuilder::new("bar", maintainer.as_model().id).expect_build(conn);
+    });
+
+    let scopes = vec![EndpointScope::PublishUpdate, EndpointScope::Yank];
+    let token = maintainer.db_new_team_token("ci", team_id, scopes);
+
+    let crate_to_publish = PublishBuilder::new("foo", "2.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let crate_to_publish = PublishBuilder::new("bar", "2.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"team tokens can only be used for crates owned by the team"}]}"###);
+
+    let response = token.get::<()>("/api/v1/me").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Actions are attributed to the team, and the maintainer that created
+    // the token is recorded as the acting user
+    let response = token.delete::<()>("/api/v1/crates/foo/2.0.0/yank").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = token
+        .get::<()>("/api/v1/crates/foo/2.0.0/yank_history")
+        .await
+        .json();
+    let history = json["yank_history"].as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["team"], "github:test-org:all");
+    assert_eq!(history[0]["user"]["login"], "user-all-teams");
+}
@@ -0,0 +1,139 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::readme_images::{FetchedImage, MockImageFetcher};
+use crates_io::schema::{background_jobs, user_avatars, users};
+use diesel::prelude::*;
+use http::{header, StatusCode};
+use sha2::{Digest, Sha256};
+
+const AVATAR_URL: &str = "https://avatars.githubusercontent.com/u/1234";
+
+fn fetcher(content_type: &'static str) -> MockImageFetcher {
+    let mut fetcher = MockImageFetcher::new();
+    fetcher.expect_fetch().returning(move |_, _| {
+        Ok(FetchedImage {
+            content_type: content_type.into(),
+            bytes: b"content"[..].into(),
+        })
+    });
+    fetcher
+}
+
+fn set_avatar(app: &TestApp, user_id: i32) {
+    app.db(|conn| {
+        diesel::update(users::table.find(user_id))
+            .set(users::gh_avatar.eq(AVATAR_URL))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn avatars_are_proxied() {
+    let (app, anon, user) = TestApp::full()
+        .with_image_fetcher(fetcher("image/png"))
+        .with_config(|config| config.avatar_proxy = true)
+        .with_user();
+    let user_id = user.as_model().id;
+    set_avatar(&app, user_id);
+
+    let url = format!("/api/v1/users/{user_id}/avatar");
+
+    // An identicon is served until the avatar has been fetched
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+    assert!(response.text().starts_with("<svg "));
+
+    app.run_pending_background_jobs().await;
+
+    let digest = hex::encode(Sha256::digest(b"content"));
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&format!("avatars/{digest}")));
+
+    anon.get::<()>(&url)
+        .await
+        .assert_redirect_ends_with(&format!("/avatars/{digest}"));
+
+    let response = anon.get::<()>("/api/v1/users/0/avatar").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The API points at the endpoint instead of GitHub
+    let login = &user.as_model().gh_login;
+    let json = anon
+        .get::<()>(&format!("/api/v1/users/{login}"))
+        .await
+        .json();
+    assert_eq!(json["user"]["avatar"], url);
+    let json = user.get::<()>("/api/v1/me").await.json();
+    assert_eq!(json["user"]["avatar"], url);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn avatar_refreshes_are_rate_limited() {
+    let (app, anon, user) = TestApp::full()
+        .with_image_fetcher(fetcher("image/png"))
+        .with_config(|config| config.avatar_proxy = true)
+        .with_user();
+    let user_id = user.as_model().id;
+    set_avatar(&app, user_id);
+
+    let url = format!("/api/v1/users/{user_id}/avatar");
+    for _ in 0..3 {
+        let response = anon.get::<()>(&url).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // Only the background job writes to the database
+    let jobs: i64 = app.db(|conn| background_jobs::table.count().get_result(conn).unwrap());
+    assert_eq!(jobs, 1);
+    let avatars: i64 = app.db(|conn| user_avatars::table.count().get_result(conn).unwrap());
+    assert_eq!(avatars, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_avatars_are_replaced_by_identicons() {
+    let (app, anon, user) = TestApp::full()
+        .with_image_fetcher(fetcher("text/html"))
+        .with_config(|config| config.avatar_proxy = true)
+        .with_user();
+    let user_id = user.as_model().id;
+    set_avatar(&app, user_id);
+
+    let url = format!("/api/v1/users/{user_id}/avatar");
+    anon.get::<()>(&url).await;
+    app.run_pending_background_jobs().await;
+
+    let failed: bool = app.db(|conn| {
+        user_avatars::table
+            .find(user_id)
+            .select(user_avatars::failed_at.is_not_null())
+            .get_result(conn)
+            .unwrap()
+    });
+    assert!(failed);
+
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn avatars_are_not_proxied_by_default() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    // Users without an avatar get an identicon
+    let url = format!("/api/v1/users/{user_id}/avatar");
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+
+    set_avatar(&app, user_id);
+    anon.get::<()>(&url)
+        .await
+        .assert_redirect_ends_with(AVATAR_URL);
+
+    let count: i64 = app.db(|conn| user_avatars::table.count().get_result(conn).unwrap());
+    assert_eq!(count, 0);
+}
@@ -1,3 +1,4 @@
+mod avatar;
 mod read;
 mod stats;
 pub mod update;
@@ -0,0 +1,115 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::{NewSecurityEvent, SecurityEventKind, SECURITY_ALERT_LOCK_REASON};
+use crates_io::schema::security_events;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+
+const ALERT_SUBJECT: &str = "Subject: Security alert for your crates.io account";
+
+fn lock_token(app: &TestApp, user_id: i32) -> String {
+    app.db(|conn| {
+        security_events::table
+            .filter(security_events::user_id.eq(user_id))
+            .filter(security_events::kind.eq(SecurityEventKind::ApiTokenCreated))
+            .select(security_events::lock_token)
+            .get_result(conn)
+            .unwrap()
+    })
+}
+
+fn num_alerts(app: &TestApp) -> usize {
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    mails
+        .iter()
+        .filter(|(_, m)| m.contains(ALERT_SUBJECT))
+        .count()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_tokens_send_security_alerts() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let body: &[u8] = br#"{ "api_token": { "name": "yank-only", "endpoint_scopes": ["yank"] } }"#;
+    let response = user.put::<()>("/api/v1/me/tokens", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(num_alerts(&app), 0);
+
+    let body: &[u8] =
+        br#"{ "api_token": { "name": "publisher", "endpoint_scopes": ["publish-new"] } }"#;
+    let response = user.put::<()>("/api/v1/me/tokens", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(num_alerts(&app), 1);
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    let (_, mail) = mails
+        .iter()
+        .find(|(_, m)| m.contains(ALERT_SUBJECT))
+        .unwrap();
+    assert!(mail.contains(r#"A new API token named "publisher""#));
+
+    let token = lock_token(&app, user.as_model().id);
+    assert!(mail.contains(&format!("/lock-account/{token}")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lock_link_locks_the_account() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    let body: &[u8] = br#"{ "api_token": { "name": "bar" } }"#;
+    let response = user.put::<()>("/api/v1/me/tokens", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = "/api/v1/lock_account/invalid";
+    let response = anon.put::<()>(url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Invalid or expired lock token."}]}"###);
+
+    let url = format!(
+        "/api/v1/lock_account/{}",
+        lock_token(&app, user.as_model().id)
+    );
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.get::<()>("/api/v1/me").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let error_message =
+        format!("This account is indefinitely locked. Reason: {SECURITY_ALERT_LOCK_REASON}");
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": error_message }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn logins_from_new_devices_are_unrecognized() {
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    let login = |ip_address: &str, user_agent| NewSecurityEvent {
+        user_id,
+        kind: SecurityEventKind::Login,
+        ip_address: Some(ip_address.to_string()),
+        user_agent: Some(user_agent),
+        api_token_id: None,
+    };
+
+    app.db(|conn| {
+        // The very first login is not considered unrecognized
+        let first = login("127.0.0.1", "firefox");
+        assert!(!first.is_unrecognized_login(conn).unwrap());
+        first.insert(conn).unwrap();
+
+        assert!(!login("127.0.0.1", "firefox")
+            .is_unrecognized_login(conn)
+            .unwrap());
+        assert!(login("127.0.0.1", "chrome")
+            .is_unrecognized_login(conn)
+            .unwrap());
+        assert!(login("10.0.0.1", "firefox")
+            .is_unrecognized_login(conn)
+            .unwrap());
+    });
+}
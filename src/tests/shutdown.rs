@@ -0,0 +1,50 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publishes_are_rejected_while_draining() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    app.as_inner().shutdown.begin_draining();
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "10");
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"The server is restarting. Please try again shortly."}]}"###);
+
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_only_requests_are_served_while_draining() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    app.as_inner().shutdown.begin_draining();
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn completed_requests_are_not_in_flight() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let shutdown = &app.as_inner().shutdown;
+    assert_eq!(shutdown.in_flight_mutations(), 0);
+    assert!(shutdown.wait_for_mutations(Duration::ZERO).await);
+}
@@ -1,3 +1,4 @@
+use crate::util::faults::Fault;
 use crate::{
     add_team_to_crate,
     builders::{CrateBuilder, PublishBuilder},
@@ -217,6 +218,46 @@ async fn add_team_as_non_member() {
     );
 }
 
+/// Test adding a team as owner while the GitHub API is unavailable
+#[tokio::test(flavor = "multi_thread")]
+async fn add_team_with_github_unavailable() {
+    let (app, _) = TestApp::init().empty();
+    let user = app.db_new_user("user-org-owner");
+    let token = user.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_github_unavailable", user.as_model().id).expect_build(conn);
+    });
+
+    app.github_faults().inject(Fault::ServerError);
+
+    let response = token
+        .add_named_owner("foo_github_unavailable", "github:test-org:core")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({
+            "errors": [{
+                "detail":
+                "could not find the github team test-org/core. \
+                Make sure that you have the right permissions in GitHub. \
+                See https://doc.rust-lang.org/cargo/reference/publishing.html#github-permissions"
+            }]
+        })
+    );
+
+    let num_teams: i64 = app.db(|conn| teams::table.count().get_result(conn).unwrap());
+    assert_eq!(num_teams, 0);
+
+    app.github_faults().clear();
+
+    token
+        .add_named_owner("foo_github_unavailable", "github:test-org:core")
+        .await
+        .good();
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn remove_team_as_named_owner() {
     let (app, _) = TestApp::full().empty();
@@ -401,6 +442,41 @@ async fn publish_owned() {
         .good();
 }
 
+/// Test trying to publish a krate owned by a team while the GitHub API does
+/// not respond
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_owned_with_github_timeout() {
+    let (app, _) = TestApp::full().empty();
+    let user_on_both_teams = app.db_new_user("user-all-teams");
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_team_timeout", user_on_both_teams.as_model().id).expect_build(conn);
+    });
+
+    token_on_both_teams
+        .add_named_owner("foo_team_timeout", "github:test-org:all")
+        .await
+        .good();
+
+    let user_on_one_team = app.db_new_user("user-one-team");
+
+    app.github_faults().inject(Fault::Timeout);
+
+    let crate_to_publish = PublishBuilder::new("foo_team_timeout", "2.0.0");
+    let response = user_on_one_team.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(app.github_faults().num_injected(), 1);
+
+    app.github_faults().clear();
+
+    let crate_to_publish = PublishBuilder::new("foo_team_timeout", "2.0.0");
+    user_on_one_team
+        .publish_crate(crate_to_publish)
+        .await
+        .good();
+}
+
 /// Test trying to change owners (when only on an owning team)
 #[tokio::test(flavor = "multi_thread")]
 async fn add_owners_as_org_owner() {
@@ -489,7 +565,7 @@ async fn crates_by_team_id_not_including_deleted_owners() {
 
         let krate = CrateBuilder::new("foo", user.id).expect_build(conn);
         add_team_to_crate(&t, &krate, user, conn).unwrap();
-        krate.owner_remove(conn, &t.login).unwrap();
+        krate.owner_remove(conn, user, &t.login).unwrap();
         t
     });
 
@@ -0,0 +1,139 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use bytes::Bytes;
+use crates_io::schema::upstream_crates;
+use crates_io::upstream::MockUpstreamRegistry;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+const CONTENT: &[u8] = b"crate file";
+
+fn index_file() -> String {
+    let checksum = hex::encode(Sha256::digest(CONTENT));
+    format!(
+        "{{\"name\":\"Foo\",\"vers\":\"1.0.0\",\"deps\":[],\"cksum\":\"{checksum}\",\"features\":{{}},\"yanked\":false}}\n\
+         {{\"name\":\"Foo\",\"vers\":\"1.1.0\",\"deps\":[],\"cksum\":\"0000\",\"features\":{{}},\"yanked\":false}}\n"
+    )
+}
+
+fn upstream() -> MockUpstreamRegistry {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_index_file().returning(|name| match name {
+        "foo" => Ok(Some(index_file())),
+        "broken" => Err(anyhow::anyhow!("connection refused")),
+        _ => Ok(None),
+    });
+    upstream
+        .expect_crate_file()
+        .returning(|_, _, _| Ok(Bytes::from_static(CONTENT)));
+    upstream
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_files_are_cached() {
+    let (app, anon) = TestApp::init().with_upstream(upstream()).empty();
+
+    let response = anon.get::<()>("/index/3/f/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text(), index_file());
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"index/3/f/foo".to_string()));
+
+    // The crate is recorded with the name of the upstream registry
+    let names: Vec<String> = app.db(|conn| {
+        upstream_crates::table
+            .select(upstream_crates::name)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(names, ["Foo"]);
+
+    // Cached index files are served from the storage bucket
+    let response = anon.get::<()>("/index/3/f/foo").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text(), index_file());
+
+    let response = anon.get::<()>("/index/3/b/bar").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Paths that don't match the crate name are rejected
+    let response = anon.get::<()>("/index/3/x/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = anon.get::<()>("/index/br/ok/broken").await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the crate could not be fetched from the upstream registry"}]}"###);
+
+    // Downloads of cached crates have to go through the API
+    let config = anon.get::<Value>("/index/config.json").await.good();
+    assert_eq!(config["dl"], "https://crates.io/api/v1/crates");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_files_are_only_served_by_pull_through_caches() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/index/3/f/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_files_are_cached() {
+    let (app, anon) = TestApp::init().with_upstream(upstream()).empty();
+
+    // Crates that were not cached yet are redirected unconditionally
+    anon.get::<()>("/api/v1/crates/foo/1.0.0/download")
+        .await
+        .assert_redirect_ends_with("/crates/foo/foo-1.0.0.crate");
+    assert!(app.stored_files().await.is_empty());
+
+    anon.get::<()>("/index/3/f/foo").await;
+
+    anon.get::<()>("/api/v1/crates/foo/1.0.0/download")
+        .await
+        .assert_redirect_ends_with("/crates/Foo/Foo-1.0.0.crate");
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"crates/Foo/Foo-1.0.0.crate".to_string()));
+
+    // The crate file has to match the checksum of the index file
+    let response = anon.get::<()>("/api/v1/crates/foo/1.1.0/download").await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"checksum mismatch of the upstream crate file of `Foo@1.1.0`"}]}"###);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/2.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `Foo` does not have a version `2.0.0`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cached_crates_can_not_be_published() {
+    let (app, anon, _, token) = TestApp::full().with_upstream(upstream()).with_token();
+
+    anon.get::<()>("/index/3/f/foo").await;
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the crate `Foo` is provided by the upstream registry, so a crate with this name can not be published"}]}"###);
+
+    let crate_to_publish = PublishBuilder::new("bar", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    // The index files of local crates are never fetched from upstream
+    let response = anon.get::<()>("/index/3/b/bar").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().contains("\"name\":\"bar\""));
+
+    let names: Vec<String> = app.db(|conn| {
+        upstream_crates::table
+            .select(upstream_crates::name)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(names, ["Foo"]);
+}
@@ -24,7 +24,7 @@ use crate::{
     OwnersResponse, VersionResponse,
 };
 use crates_io::middleware::session;
-use crates_io::models::{ApiToken, CreatedApiToken, User};
+use crates_io::models::{ApiToken, CreatedApiToken, PersistentSession, User, SESSION_TOKEN_KEY};
 
 use http::{Method, Request};
 
@@ -33,7 +33,9 @@ use axum::extract::connect_info::MockConnectInfo;
 use chrono::NaiveDateTime;
 use cookie::Cookie;
 use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::schema::users;
 use crates_io::util::token::PlainToken;
+use diesel::{ExpressionMethods, RunQueryDsl};
 use http::header;
 use secrecy::ExposeSecret;
 use std::collections::HashMap;
@@ -41,6 +43,7 @@ use std::net::SocketAddr;
 use tower::ServiceExt;
 
 mod chaosproxy;
+pub mod faults;
 mod github;
 pub mod insta;
 pub mod matchers;
@@ -65,14 +68,19 @@ pub use test_app::TestApp;
 /// The implementation matches roughly what is happening inside of our
 /// session middleware.
 pub fn encode_session_header(session_key: &cookie::Key, user_id: i32) -> String {
-    let cookie_name = "cargo_session";
-
     // build session data map
     let mut map = HashMap::new();
     map.insert("user_id".into(), user_id.to_string());
 
+    encode_session_data(session_key, &map)
+}
+
+/// Like [`encode_session_header`], but with arbitrary session data.
+pub fn encode_session_data(session_key: &cookie::Key, data: &HashMap<String, String>) -> String {
+    let cookie_name = "cargo_session";
+
     // encode the map into a cookie value string
-    let encoded = session::encode(&map);
+    let encoded = session::encode(data);
 
     // put the cookie into a signed cookie jar
     let cookie = Cookie::build((cookie_name, encoded));
@@ -146,6 +154,34 @@ pub trait RequestHelper {
         self.run(request).await
     }
 
+    /// Issue a POST request
+    async fn post<T>(&self, path: &str, body: impl Into<Bytes>) -> Response<T> {
+        let body = body.into();
+        let is_json = body.starts_with(b"{") && body.ends_with(b"}");
+
+        let mut request = self.post_request(path);
+        *request.body_mut() = body;
+        if is_json {
+            request.header(header::CONTENT_TYPE, "application/json");
+        }
+
+        self.run(request).await
+    }
+
+    /// Issue a PATCH request
+    async fn patch<T>(&self, path: &str, body: impl Into<Bytes>) -> Response<T> {
+        let body = body.into();
+        let is_json = body.starts_with(b"{") && body.ends_with(b"}");
+
+        let mut request = self.request_builder(Method::PATCH, path);
+        *request.body_mut() = body;
+        if is_json {
+            request.header(header::CONTENT_TYPE, "application/json");
+        }
+
+        self.run(request).await
+    }
+
     /// Issue a DELETE request
     async fn delete<T>(&self, path: &str) -> Response<T> {
         let request = self.request_builder(Method::DELETE, path);
@@ -237,12 +273,17 @@ impl RequestHelper for MockAnonymousUser {
 pub struct MockCookieUser {
     app: TestApp,
     user: User,
+    session_token: String,
 }
 
 impl RequestHelper for MockCookieUser {
     fn request_builder(&self, method: Method, path: &str) -> MockRequest {
         let session_key = &self.app.as_inner().session_key();
-        let cookie = encode_session_header(session_key, self.user.id);
+        let data = HashMap::from([
+            ("user_id".to_string(), self.user.id.to_string()),
+            (SESSION_TOKEN_KEY.to_string(), self.session_token.clone()),
+        ]);
+        let cookie = encode_session_data(session_key, &data);
 
         let mut request = req(method, path);
         request.header(header::COOKIE, &cookie);
@@ -257,9 +298,12 @@ impl RequestHelper for MockCookieUser {
 impl MockCookieUser {
     /// Creates an instance from a database `User` instance
     pub fn new(app: &TestApp, user: User) -> Self {
+        let (_, token) = app.db(|conn| PersistentSession::create(conn, user.id).unwrap());
+
         Self {
             app: app.clone(),
             user,
+            session_token: token.expose_secret().clone(),
         }
     }
 
@@ -268,6 +312,24 @@ impl MockCookieUser {
         &self.user
     }
 
+    /// Returns the token of the persistent session that is used for the
+    /// cookie of the requests
+    pub fn session_token(&self) -> &str {
+        &self.session_token
+    }
+
+    /// Grants the user admin permissions
+    ///
+    /// This method updates the database directly
+    pub fn make_admin(&self) {
+        self.app.db(|conn| {
+            diesel::update(&self.user)
+                .set(users::is_admin.eq(true))
+                .execute(conn)
+                .unwrap();
+        });
+    }
+
     /// Creates a token and wraps it in a helper struct
     ///
     /// This method updates the database directly
@@ -301,6 +363,46 @@ impl MockCookieUser {
             token,
         }
     }
+
+    /// Creates a team token and wraps it in a helper struct
+    ///
+    /// This method updates the database directly
+    pub fn db_new_team_token(
+        &self,
+        name: &str,
+        team_id: i32,
+        endpoint_scopes: Vec<EndpointScope>,
+    ) -> MockTokenUser {
+        let token = self.app.db(|conn| {
+            ApiToken::insert_team_token(
+                conn,
+                self.user.id,
+                team_id,
+                name,
+                None,
+                endpoint_scopes,
+                None,
+            )
+            .unwrap()
+        });
+        MockTokenUser {
+            app: self.app.clone(),
+            token,
+        }
+    }
+
+    /// Creates a service token and wraps it in a helper struct
+    ///
+    /// This method updates the database directly
+    pub fn db_new_service_token(&self, name: &str, contact: &str) -> MockTokenUser {
+        let token = self
+            .app
+            .db(|conn| ApiToken::insert_service_token(conn, self.user.id, name, contact).unwrap());
+        MockTokenUser {
+            app: self.app.clone(),
+            token,
+        }
+    }
 }
 
 /// A type that can generate token authenticated requests
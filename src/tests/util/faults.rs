@@ -0,0 +1,208 @@
+//! Fault injection for the mocked external services of the test app.
+//!
+//! Every [`TestApp`](super::TestApp) wraps its in-memory storage backend and
+//! its GitHub API client mock with a [`FaultInjector`]. Tests can use
+//! [`TestApp::storage_faults()`](super::TestApp::storage_faults) and
+//! [`TestApp::github_faults()`](super::TestApp::github_faults) to make the
+//! calls to these services fail or respond slowly, e.g. to verify that a
+//! failed upload does not leave a half-published crate behind.
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use crates_io_github::GitHubError;
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta, ObjectStore, PutMultipartOpts,
+    PutOptions, PutPayload, PutResult,
+};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A fault that is injected into the calls to a mocked external service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum Fault {
+    /// The call fails as if the service did not respond in time. The call
+    /// fails immediately, so that tests don't have to wait for the timeout.
+    #[error("operation timed out")]
+    Timeout,
+    /// The call fails as if the service responded with a
+    /// `500 Internal Server Error`.
+    #[error("500 Internal Server Error")]
+    ServerError,
+    /// The call succeeds, but only after the given delay.
+    #[error("slow response")]
+    Slow(Duration),
+}
+
+#[derive(Debug, Default)]
+struct FaultState {
+    fault: Option<Fault>,
+    /// Number of calls that the fault is still injected into, or `None` if
+    /// it is injected until it is cleared.
+    remaining: Option<usize>,
+    /// Number of calls that the faults were injected into so far.
+    injected: usize,
+}
+
+/// A shared handle to the faults of a mocked external service.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector(Arc<Mutex<FaultState>>);
+
+impl FaultInjector {
+    /// Injects the fault into all calls until [`FaultInjector::clear()`] is
+    /// called.
+    pub fn inject(&self, fault: Fault) {
+        let mut state = self.0.lock().unwrap();
+        state.fault = Some(fault);
+        state.remaining = None;
+    }
+
+    /// Injects the fault into the next `times` calls only, e.g. to simulate
+    /// an intermittent failure that a retry recovers from.
+    pub fn inject_times(&self, fault: Fault, times: usize) {
+        let mut state = self.0.lock().unwrap();
+        state.fault = Some(fault).filter(|_| times > 0);
+        state.remaining = Some(times);
+    }
+
+    /// Stops injecting faults.
+    pub fn clear(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.fault = None;
+        state.remaining = None;
+    }
+
+    /// Returns the number of calls that faults were injected into so far.
+    pub fn num_injected(&self) -> usize {
+        self.0.lock().unwrap().injected
+    }
+
+    /// Returns the fault for the current call, if there is one.
+    fn take(&self) -> Option<Fault> {
+        let mut state = self.0.lock().unwrap();
+        let fault = state.fault?;
+
+        state.injected += 1;
+        if let Some(remaining) = state.remaining.as_mut() {
+            *remaining -= 1;
+            if *remaining == 0 {
+                state.fault = None;
+            }
+        }
+
+        Some(fault)
+    }
+
+    /// Applies the fault for the current call, if there is one, by either
+    /// delaying the call or returning the error that it fails with.
+    pub(crate) async fn apply(&self) -> Result<(), Fault> {
+        match self.take() {
+            None => Ok(()),
+            Some(Fault::Slow(delay)) => {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Some(fault) => Err(fault),
+        }
+    }
+}
+
+impl From<Fault> for object_store::Error {
+    fn from(fault: Fault) -> Self {
+        object_store::Error::Generic {
+            store: "FaultyStore",
+            source: Box::new(fault),
+        }
+    }
+}
+
+impl From<Fault> for GitHubError {
+    fn from(fault: Fault) -> Self {
+        GitHubError::Other(anyhow!(fault))
+    }
+}
+
+/// An [`ObjectStore`] that injects the faults of a [`FaultInjector`] into
+/// all calls to the wrapped store.
+#[derive(Debug)]
+pub(crate) struct FaultyStore {
+    inner: Arc<dyn ObjectStore>,
+    faults: FaultInjector,
+}
+
+impl FaultyStore {
+    pub(crate) fn new(inner: Arc<dyn ObjectStore>, faults: FaultInjector) -> Self {
+        Self { inner, faults }
+    }
+}
+
+impl Display for FaultyStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FaultyStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FaultyStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        self.faults.apply().await?;
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        opts: PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn MultipartUpload>> {
+        self.faults.apply().await?;
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.faults.apply().await?;
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.faults.apply().await?;
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        match self.faults.take() {
+            None => self.inner.list(prefix),
+            Some(Fault::Slow(delay)) => stream::once(tokio::time::sleep(delay))
+                .filter_map(|_| async { None })
+                .chain(self.inner.list(prefix))
+                .boxed(),
+            Some(fault) => stream::once(async move { Err(fault.into()) }).boxed(),
+        }
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.faults.apply().await?;
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.faults.apply().await?;
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.faults.apply().await?;
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
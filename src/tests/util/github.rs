@@ -6,6 +6,8 @@ use crates_io_github::{
 };
 use oauth2::AccessToken;
 
+use super::faults::FaultInjector;
+
 pub(crate) const MOCK_GITHUB_DATA: MockData = MockData {
     orgs: &[MockOrg {
         id: 1000,
@@ -16,11 +18,13 @@ pub(crate) const MOCK_GITHUB_DATA: MockData = MockData {
                 id: 2000,
                 name: "all",
                 members: &["user-all-teams", "user-one-team"],
+                maintainers: &["user-all-teams"],
             },
             MockTeam {
                 id: 2001,
                 name: "core",
                 members: &["user-all-teams"],
+                maintainers: &[],
             },
         ],
     }],
@@ -52,21 +56,29 @@ pub(crate) const MOCK_GITHUB_DATA: MockData = MockData {
             is_current: true,
         },
     ],
+    repository_files: &[MockRepositoryFile {
+        owner: "rust-lang",
+        repo: "foo",
+        path: ".well-known/crates-io",
+        content: "# crates published from this repository\nfoo\nfoo-derive\n",
+    }],
 };
 
 pub(crate) struct MockGitHubClient {
     data: &'static MockData,
+    faults: FaultInjector,
 }
 
 impl MockGitHubClient {
-    pub(crate) fn new(data: &'static MockData) -> Self {
-        Self { data }
+    pub(crate) fn new(data: &'static MockData, faults: FaultInjector) -> Self {
+        Self { data, faults }
     }
 }
 
 #[async_trait]
 impl GitHubClient for MockGitHubClient {
     async fn current_user(&self, _auth: &AccessToken) -> Result<GithubUser, GitHubError> {
+        self.faults.apply().await?;
         let user = &self.data.users[0];
         Ok(GithubUser {
             id: user.id,
@@ -74,6 +86,7 @@ impl GitHubClient for MockGitHubClient {
             name: Some(user.name.into()),
             email: Some(user.email.into()),
             avatar_url: Some(format!("https://avatars.example.com/{}", user.id)),
+            two_factor_authentication: false,
         })
     }
 
@@ -82,6 +95,7 @@ impl GitHubClient for MockGitHubClient {
         org_name: &str,
         _auth: &AccessToken,
     ) -> Result<GitHubOrganization, GitHubError> {
+        self.faults.apply().await?;
         let org = self
             .data
             .orgs
@@ -100,6 +114,7 @@ impl GitHubClient for MockGitHubClient {
         team_name: &str,
         auth: &AccessToken,
     ) -> Result<GitHubTeam, GitHubError> {
+        self.faults.apply().await?;
         let team = self
             .data
             .orgs
@@ -124,6 +139,7 @@ impl GitHubClient for MockGitHubClient {
         username: &str,
         _auth: &AccessToken,
     ) -> Result<GitHubTeamMembership, GitHubError> {
+        self.faults.apply().await?;
         let team = self
             .data
             .orgs
@@ -135,8 +151,14 @@ impl GitHubClient for MockGitHubClient {
             .find(|team| team.id == team_id)
             .ok_or_else(not_found)?;
         if team.members.contains(&username) {
+            let role = if team.maintainers.contains(&username) {
+                "maintainer"
+            } else {
+                "member"
+            };
             Ok(GitHubTeamMembership {
                 state: "active".into(),
+                role: role.into(),
             })
         } else {
             Err(not_found())
@@ -149,6 +171,7 @@ impl GitHubClient for MockGitHubClient {
         username: &str,
         _auth: &AccessToken,
     ) -> Result<GitHubOrgMembership, GitHubError> {
+        self.faults.apply().await?;
         let org = self
             .data
             .orgs
@@ -179,8 +202,25 @@ impl GitHubClient for MockGitHubClient {
         _username: &str,
         _password: &str,
     ) -> Result<Vec<GitHubPublicKey>, GitHubError> {
+        self.faults.apply().await?;
         Ok(self.data.public_keys.iter().map(Into::into).collect())
     }
+
+    async fn repository_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        _auth: &AccessToken,
+    ) -> Result<String, GitHubError> {
+        self.faults.apply().await?;
+        self.data
+            .repository_files
+            .iter()
+            .find(|file| file.owner == owner && file.repo == repo && file.path == path)
+            .map(|file| file.content.to_string())
+            .ok_or_else(not_found)
+    }
 }
 
 fn not_found() -> GitHubError {
@@ -191,6 +231,7 @@ pub(crate) struct MockData {
     orgs: &'static [MockOrg],
     users: &'static [MockUser],
     public_keys: &'static [MockPublicKey],
+    repository_files: &'static [MockRepositoryFile],
 }
 
 struct MockUser {
@@ -211,6 +252,7 @@ struct MockTeam {
     id: i32,
     name: &'static str,
     members: &'static [&'static str],
+    maintainers: &'static [&'static str],
 }
 
 struct MockPublicKey {
@@ -219,6 +261,13 @@ struct MockPublicKey {
     is_current: bool,
 }
 
+struct MockRepositoryFile {
+    owner: &'static str,
+    repo: &'static str,
+    path: &'static str,
+    content: &'static str,
+}
+
 impl From<&'static MockPublicKey> for GitHubPublicKey {
     fn from(k: &'static MockPublicKey) -> Self {
         Self {
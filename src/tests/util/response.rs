@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 use std::str::from_utf8;
 
 use crates_io::rate_limiter::LimitedAction;
-use http::{header, StatusCode};
+use http::{header, HeaderMap, StatusCode};
 
 /// A type providing helper methods for working with responses
 #[must_use]
@@ -48,10 +48,18 @@ impl<T> Response<T> {
         assert_ok!(from_utf8(bytes)).to_string()
     }
 
+    pub fn bytes(&self) -> &Bytes {
+        self.response.body()
+    }
+
     pub fn status(&self) -> StatusCode {
         self.response.status()
     }
 
+    pub fn headers(&self) -> &HeaderMap {
+        self.response.headers()
+    }
+
     #[track_caller]
     pub fn assert_redirect_ends_with(&self, target: &str) -> &Self {
         let headers = self.response.headers();
@@ -1,14 +1,20 @@
 use super::{MockAnonymousUser, MockCookieUser, MockTokenUser};
 use crate::util::chaosproxy::ChaosProxy;
+use crate::util::faults::{FaultInjector, FaultyStore};
 use crate::util::github::{MockGitHubClient, MOCK_GITHUB_DATA};
+use crates_io::advisories::{AdvisorySource, MockAdvisorySource};
+use crates_io::antivirus::{MockVirusScanner, VirusScanner};
 use crates_io::config::{
     self, Base, CdnLogQueueConfig, CdnLogStorageConfig, DatabasePools, DbPoolConfig,
 };
 use crates_io::middleware::cargo_compat::StatusCodeConfig;
+use crates_io::mirrors::{self, MirrorProbe, MockMirrorProbe};
 use crates_io::models::token::{CrateScope, EndpointScope};
 use crates_io::rate_limiter::{LimitedAction, RateLimiterConfig};
-use crates_io::storage::StorageConfig;
+use crates_io::readme_images::{ImageFetcher, MockImageFetcher};
+use crates_io::storage::{Storage, StorageConfig};
 use crates_io::team_repo::MockTeamRepo;
+use crates_io::upstream::{MockUpstreamRegistry, UpstreamConfig, UpstreamRegistry};
 use crates_io::worker::{Environment, RunnerExt};
 use crates_io::{App, Emails, Env};
 use crates_io_index::testing::UpstreamIndex;
@@ -18,6 +24,7 @@ use crates_io_worker::Runner;
 use diesel::PgConnection;
 use futures_util::TryStreamExt;
 use oauth2::{ClientId, ClientSecret};
+use object_store::memory::InMemory;
 use std::collections::HashSet;
 use std::{rc::Rc, sync::Arc, time::Duration};
 use tokio::runtime::Handle;
@@ -32,6 +39,9 @@ struct TestAppInner {
     primary_db_chaosproxy: Option<Arc<ChaosProxy>>,
     replica_db_chaosproxy: Option<Arc<ChaosProxy>>,
 
+    storage_faults: FaultInjector,
+    github_faults: FaultInjector,
+
     // Must be the last field of the struct!
     test_database: TestDatabase,
 }
@@ -96,6 +106,12 @@ impl TestApp {
             build_job_runner: false,
             use_chaos_proxy: false,
             team_repo: MockTeamRepo::new(),
+            virus_scanner: None,
+            image_fetcher: None,
+            mirror_probe: None,
+            advisory_source: None,
+            upstream: None,
+            sparse_index_url: None,
         }
     }
 
@@ -132,15 +148,13 @@ impl TestApp {
                     emails::user_id.eq(user.id),
                     emails::email.eq(email),
                     emails::verified.eq(true),
+                    emails::is_primary.eq(true),
                 ))
                 .execute(conn)
                 .unwrap();
             user
         });
-        MockCookieUser {
-            app: self.clone(),
-            user,
-        }
+        MockCookieUser::new(self, user)
     }
 
     /// Obtain a reference to the upstream repository ("the index")
@@ -194,6 +208,18 @@ impl TestApp {
             .expect("ChaosProxy is not enabled on this test, call with_database during app init")
     }
 
+    /// Returns the handle to the faults that are injected into the calls to
+    /// the storage backend.
+    pub fn storage_faults(&self) -> &FaultInjector {
+        &self.0.storage_faults
+    }
+
+    /// Returns the handle to the faults that are injected into the calls to
+    /// the GitHub API client mock.
+    pub fn github_faults(&self) -> &FaultInjector {
+        &self.0.github_faults
+    }
+
     pub(crate) fn replica_db_chaosproxy(&self) -> Arc<ChaosProxy> {
         self.0
             .replica_db_chaosproxy
@@ -208,6 +234,11 @@ pub struct TestAppBuilder {
     build_job_runner: bool,
     use_chaos_proxy: bool,
     team_repo: MockTeamRepo,
+    virus_scanner: Option<MockVirusScanner>,
+    image_fetcher: Option<MockImageFetcher>,
+    mirror_probe: Option<MockMirrorProbe>,
+    advisory_source: Option<MockAdvisorySource>,
+    upstream: Option<MockUpstreamRegistry>,
 }
 
 impl TestAppBuilder {
@@ -252,7 +283,13 @@ impl TestAppBuilder {
             (primary_proxy, replica_proxy)
         };
 
-        let (app, router) = build_app(self.config);
+        let storage_faults = FaultInjector::default();
+        let github_faults = FaultInjector::default();
+        let upstream = self.upstream.map(|upstream| {
+            let upstream: Arc<dyn UpstreamRegistry + Send + Sync> = Arc::new(upstream);
+            upstream
+        });
+        let (app, router) = build_app(self.config, upstream, &storage_faults, &github_faults);
 
         let runner = if self.build_job_runner {
             let index = self
@@ -272,6 +309,23 @@ impl TestAppBuilder {
                 .deadpool(app.primary_database.clone())
                 .emails(app.emails.clone())
                 .team_repo(Box::new(self.team_repo))
+                .virus_scanner(self.virus_scanner.map(|scanner| {
+                    let scanner: Box<dyn VirusScanner + Send + Sync> = Box::new(scanner);
+                    scanner
+                }))
+                .image_fetcher(self.image_fetcher.map(|fetcher| {
+                    let fetcher: Box<dyn ImageFetcher + Send + Sync> = Box::new(fetcher);
+                    fetcher
+                }))
+                .mirror_probe(self.mirror_probe.map(|probe| {
+                    let probe: Box<dyn MirrorProbe + Send + Sync> = Box::new(probe);
+                    probe
+                }))
+                .advisory_source(self.advisory_source.map(|source| {
+                    let source: Box<dyn AdvisorySource + Send + Sync> = Box::new(source);
+                    source
+                }))
+                .upstream(app.upstream.clone())
                 .build()
                 .unwrap();
 
@@ -292,6 +346,8 @@ impl TestAppBuilder {
             runner,
             primary_db_chaosproxy,
             replica_db_chaosproxy,
+            storage_faults,
+            github_faults,
         };
         let test_app = TestApp(Rc::new(test_app_inner));
         let anon = MockAnonymousUser {
@@ -359,6 +415,51 @@ impl TestAppBuilder {
         self
     }
 
+    /// Enables the scanning of published tarballs with the given scanner.
+    pub fn with_virus_scanner(mut self, virus_scanner: MockVirusScanner) -> Self {
+        // The address is never used, since the mock replaces the clamd client
+        self.config.clamd_address = Some("localhost:3310".into());
+        self.virus_scanner = Some(virus_scanner);
+        self
+    }
+
+    /// Enables the README image proxy, fetching the images with the given
+    /// fetcher.
+    pub fn with_image_fetcher(mut self, image_fetcher: MockImageFetcher) -> Self {
+        self.config.readme_image_proxy = true;
+        self.image_fetcher = Some(image_fetcher);
+        self
+    }
+
+    /// Enables the redirects of downloads to mirrors, checking the health of
+    /// the mirrors with the given probe.
+    pub fn with_mirror_probe(mut self, mirror_probe: MockMirrorProbe) -> Self {
+        self.config.mirror_redirects = true;
+        self.mirror_probe = Some(mirror_probe);
+        self
+    }
+
+    /// Enables the import of security advisories, fetching them from the
+    /// given source.
+    pub fn with_advisory_source(mut self, advisory_source: MockAdvisorySource) -> Self {
+        // The URL is never used, since the mock replaces the HTTP client
+        self.config.advisory_db_url = Some("https://advisories.example.com/db.tar.gz".into());
+        self.advisory_source = Some(advisory_source);
+        self
+    }
+
+    /// Enables the pull-through cache mode, fetching the crates of the
+    /// upstream registry with the given mock.
+    pub fn with_upstream(mut self, upstream: MockUpstreamRegistry) -> Self {
+        // The URLs are never used, since the mock replaces the HTTP client
+        self.config.upstream = Some(UpstreamConfig {
+            index_url: "https://index.example.com".into(),
+            download_url: "https://static.example.com/crates".into(),
+        });
+        self.upstream = Some(upstream);
+        self
+    }
+
     pub fn with_replica(mut self) -> Self {
         let primary = &self.config.db.primary;
 
@@ -411,9 +512,20 @@ fn simple_config() -> config::Server {
         gh_client_secret: ClientSecret::new(dotenvy::var("GH_CLIENT_SECRET").unwrap_or_default()),
         max_upload_size: 128 * 1024, // 128 kB should be enough for most testing purposes
         max_unpack_size: 128 * 1024, // 128 kB should be enough for most testing purposes
+        max_tarball_files: 1000,
+        max_tarball_path_depth: 16,
         max_features: 10,
+        max_feature_values: 10,
         max_dependencies: 10,
         rate_limiter: Default::default(),
+        api_quota: Default::default(),
+        user_agent_throttle: Default::default(),
+        challenge: Default::default(),
+        load_shedding: Default::default(),
+        publish_queue: Default::default(),
+        request_limits: Default::default(),
+        deprecations: Default::default(),
+        spam: Default::default(),
         new_version_rate_limit: Some(10),
         blocked_traffic: Default::default(),
         blocked_ips: Default::default(),
@@ -425,12 +537,39 @@ fn simple_config() -> config::Server {
         allowed_origins: Default::default(),
         downloads_persist_interval: Duration::from_secs(1),
         ownership_invitations_expiration_days: 30,
+        retention: Default::default(),
         metrics_authorization_token: None,
         instance_metrics_log_every_seconds: None,
         blocked_routes: HashSet::new(),
         version_id_cache_size: 10000,
         version_id_cache_ttl: Duration::from_secs(5 * 60),
         cdn_user_agent: "Amazon CloudFront".to_string(),
+        clamd_address: None,
+        docs_rs_webhook_token: None,
+        email_webhook_token: None,
+        checksum_advisory_key: None,
+        git_index_sync_batch_size: 20,
+        registries: Default::default(),
+        yank_confirmation_downloads: None,
+        yank_cooldown: None,
+        account_recovery_waiting_period: Duration::from_secs(7 * 24 * 60 * 60),
+        critical_yank_confirmation_downloads: 10_000,
+        critical_owner_removal_waiting_period: Duration::from_secs(7 * 24 * 60 * 60),
+        dormant_owner_period: Some(Duration::from_secs(365 * 24 * 60 * 60)),
+        session_lifetime: Duration::from_secs(90 * 24 * 60 * 60),
+        session_idle_timeout: Duration::from_secs(30 * 24 * 60 * 60),
+        shutdown_timeout: Duration::from_secs(25),
+        changes_feed_delay: Duration::ZERO,
+        readme_image_proxy: false,
+        avatar_proxy: false,
+        mirror_redirects: false,
+        mirror_country_header: mirrors::DEFAULT_COUNTRY_HEADER.into(),
+        advisory_db_url: None,
+        download_auth_required: false,
+        index_feature_encoding: Default::default(),
+        upstream: None,
+        max_readme_image_size: 5 * 1024 * 1024,
+        readme_sanitizer_policy: Default::default(),
 
         // The middleware has its own unit tests to verify its functionality.
         // Here, we can test what would happen if we toggled the status code
@@ -444,16 +583,35 @@ fn simple_config() -> config::Server {
     }
 }
 
-fn build_app(config: config::Server) -> (Arc<App>, axum::Router) {
+fn build_app(
+    config: config::Server,
+    upstream: Option<Arc<dyn UpstreamRegistry + Send + Sync>>,
+    storage_faults: &FaultInjector,
+    github_faults: &FaultInjector,
+) -> (Arc<App>, axum::Router) {
     // Use the in-memory email backend for all tests, allowing tests to analyze the emails sent by
     // the application. This will also prevent cluttering the filesystem.
     let emails = Emails::new_in_memory();
 
     // Use a custom mock for the GitHub client, allowing to define the GitHub users and
     // organizations without actually having to create GitHub accounts.
-    let github = Box::new(MockGitHubClient::new(&MOCK_GITHUB_DATA));
-
-    let app = App::new(config, emails, github);
+    let github = Box::new(MockGitHubClient::new(
+        &MOCK_GITHUB_DATA,
+        github_faults.clone(),
+    ));
+
+    let cdn_prefix = config.storage.cdn_prefix.clone();
+    let mut app = App::new(config, emails, github);
+
+    // Wrap the in-memory storage, allowing tests to simulate failures of the
+    // storage backend.
+    let store = FaultyStore::new(Arc::new(InMemory::new()), storage_faults.clone());
+    app.storage = Arc::new(Storage::from_store(Arc::new(store), cdn_prefix));
+
+    // Replace the HTTP client of the pull-through cache with the mock.
+    if upstream.is_some() {
+        app.upstream = upstream;
+    }
 
     let app = Arc::new(app);
     let router = crates_io::build_handler(Arc::clone(&app));
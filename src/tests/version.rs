@@ -11,7 +11,7 @@ async fn record_rerendered_readme_time() {
         let c = CrateBuilder::new("foo_authors", user.id).expect_build(conn);
         let version = VersionBuilder::new("1.0.0").expect_build(c.id, user.id, conn);
 
-        Version::record_readme_rendering(version.id, conn).unwrap();
-        Version::record_readme_rendering(version.id, conn).unwrap();
+        Version::record_readme_rendering(version.id, 1, conn).unwrap();
+        Version::record_readme_rendering(version.id, 2, conn).unwrap();
     });
 }
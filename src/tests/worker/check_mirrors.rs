@@ -0,0 +1,97 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::TestApp;
+use bytes::Bytes;
+use chrono::{Duration, Utc};
+use crates_io::mirrors::MockMirrorProbe;
+use crates_io::models::{Mirror, NewMirror};
+use crates_io::schema::mirrors;
+use crates_io::worker::jobs::CheckMirrors;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+
+const CONTENT: &[u8] = b"crate file";
+
+fn probe() -> MockMirrorProbe {
+    let mut probe = MockMirrorProbe::new();
+    probe.expect_fetch().returning(|url| match url {
+        "https://good.example.com/crates/foo/foo-1.0.0.crate" => Ok(Bytes::from_static(CONTENT)),
+        "https://corrupt.example.com/crates/foo/foo-1.0.0.crate" => {
+            Ok(Bytes::from_static(b"something else"))
+        }
+        _ => Err(anyhow::anyhow!("connection refused")),
+    });
+    probe
+}
+
+fn register(conn: &mut PgConnection, name: &str) -> Mirror {
+    let base_url = format!("https://{name}.example.com");
+    let new_mirror = NewMirror {
+        name,
+        base_url: &base_url,
+        region: "EU",
+    };
+    new_mirror.insert(conn).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verifies_mirrors_and_disables_stale_ones() {
+    let (app, _, user) = TestApp::full().with_mirror_probe(probe()).with_user();
+    let user_id = user.as_model().id;
+
+    let checksum = hex::encode(Sha256::digest(CONTENT));
+    let two_hours_ago = (Utc::now() - Duration::hours(2)).naive_utc();
+    let two_days_ago = (Utc::now() - Duration::days(2)).naive_utc();
+
+    app.db(|conn| {
+        let version = VersionBuilder::new("1.0.0")
+            .checksum(&checksum)
+            .created_at(two_hours_ago);
+        CrateBuilder::new("foo", user_id)
+            .version(version)
+            .expect_build(conn);
+
+        // The most recent version might not be synchronized yet, so it is
+        // not used for the health checks
+        CrateBuilder::new("bar", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+
+        register(conn, "good");
+        register(conn, "corrupt");
+        register(conn, "down");
+        let old = register(conn, "old");
+
+        diesel::update(&old)
+            .set((
+                mirrors::created_at.eq(two_days_ago),
+                mirrors::verified_at.eq(two_days_ago),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        CheckMirrors.enqueue(conn).unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let mirrors = app.db(|conn| Mirror::all(conn).unwrap());
+    let states = mirrors
+        .iter()
+        .map(|mirror| {
+            let verified = mirror.verified_at.is_some_and(|at| at > two_hours_ago);
+            (mirror.name.as_str(), mirror.enabled, verified)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        states,
+        [
+            ("corrupt", true, false),
+            ("down", true, false),
+            ("good", true, true),
+            ("old", false, false),
+        ]
+    );
+    assert!(mirrors.iter().all(|mirror| mirror.checked_at.is_some()));
+}
@@ -0,0 +1,45 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::worker::jobs::UpdateCrateHealth;
+use crates_io_worker::BackgroundJob;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn computes_health_scores() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user_id = user.as_model().id;
+
+    let recent = Utc::now().naive_utc() - Duration::days(5);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id)
+            .version(VersionBuilder::new("1.0.0").created_at(recent))
+            .version(VersionBuilder::new("1.1.0").created_at(recent).yanked(true))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/foo/health").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the health score of this crate has not been computed yet"}]}"###);
+
+    app.db(|conn| UpdateCrateHealth.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/crates/foo/health").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".health.computed_at" => "[datetime]",
+        ".health.components.releases.last_release_at" => "[datetime]",
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/crates/foo/health").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
@@ -0,0 +1,80 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::CrateVisibility;
+use crates_io::schema::crates;
+use crates_io::worker::jobs::UpdateCrateRecommendations;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn computes_related_crates() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        let serde = CrateBuilder::new("serde", user_id).expect_build(conn);
+        let tokio = CrateBuilder::new("tokio", user_id)
+            .description("An event-driven runtime")
+            .expect_build(conn);
+        let rand = CrateBuilder::new("rand", user_id).expect_build(conn);
+        let secret = CrateBuilder::new("secret", user_id).expect_build(conn);
+
+        diesel::update(crates::table.find(secret.id))
+            .set(crates::visibility.eq(CrateVisibility::Private))
+            .execute(conn)
+            .unwrap();
+
+        let dependents = [
+            ("app1", 100, vec![&serde, &tokio, &secret]),
+            ("app2", 10, vec![&serde, &tokio, &rand]),
+            ("app3", 1000, vec![&serde, &rand, &secret]),
+            ("app4", 0, vec![&tokio]),
+        ];
+
+        for (name, recent_downloads, dependencies) in dependents {
+            let version = dependencies
+                .into_iter()
+                .fold(VersionBuilder::new("1.0.0"), |version, dependency| {
+                    version.dependency(dependency, None)
+                });
+
+            let mut builder = CrateBuilder::new(name, user_id).version(version);
+            if recent_downloads > 0 {
+                builder = builder.recent_downloads(recent_downloads);
+            }
+            builder.expect_build(conn);
+        }
+    });
+
+    let response = anon.get::<()>("/api/v1/crates/serde/related").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"depended_on_together":[],"meta":{"computed_at":null},"used_together":[]}"###);
+
+    app.db(|conn| UpdateCrateRecommendations.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/crates/serde/related").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".meta.computed_at" => "[datetime]",
+    });
+
+    // `rand` and `tokio` only have a single dependent crate in common
+    let response = anon.get::<()>("/api/v1/crates/rand/related").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    let related = json["depended_on_together"].as_array().unwrap();
+    let names = related.iter().map(|c| &c["name"]).collect::<Vec<_>>();
+    assert_eq!(names, ["serde"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unknown_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/crates/foo/related").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
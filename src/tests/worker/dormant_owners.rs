@@ -0,0 +1,111 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::{CrateOwner, OwnerKind};
+use crates_io::schema::{crate_owners, dormant_owner_suggestions};
+use crates_io::worker::jobs::SuggestDormantOwnerRemovals;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+/// Creates the `foo` crate, published by `owner`, with `dormant` as an
+/// additional owner that was added three years ago.
+fn create_crate(app: &TestApp, owner: &MockCookieUser, dormant: &MockCookieUser) {
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo", owner.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: dormant.as_model().id,
+                created_by: owner.as_model().id,
+                owner_kind: OwnerKind::User,
+                email_notifications: true,
+            })
+            .execute(conn)
+            .unwrap();
+
+        let created_at = Utc::now().naive_utc() - Duration::days(3 * 365);
+        diesel::update(crate_owners::table)
+            .filter(crate_owners::owner_id.eq(dormant.as_model().id))
+            .set(crate_owners::created_at.eq(created_at))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn suggestion_tokens(app: &TestApp) -> Vec<String> {
+    app.db(|conn| {
+        dormant_owner_suggestions::table
+            .select(dormant_owner_suggestions::token)
+            .load(conn)
+            .unwrap()
+    })
+}
+
+async fn owner_logins(anon: &impl RequestHelper) -> Vec<String> {
+    let json = anon.get::<Value>("/api/v1/crates/foo/owners").await.good();
+    let mut logins = json["users"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|user| user["login"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    logins.sort();
+    logins
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dormant_owners_are_suggested_for_removal() {
+    let (app, anon, owner) = TestApp::full().with_user();
+    let dormant = app.db_new_user("dormant");
+    create_crate(&app, &owner, &dormant);
+
+    app.db(|conn| SuggestDormantOwnerRemovals.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0]
+        .1
+        .contains("An owner of your crate seems to be inactive"));
+    assert!(emails[0].1.contains("/remove-dormant-owner/"));
+
+    // The suggestion is only sent once
+    app.db(|conn| SuggestDormantOwnerRemovals.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+
+    let tokens = suggestion_tokens(&app);
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(owner_logins(&anon).await, ["dormant", "foo"]);
+
+    let url = format!("/api/v1/dormant_owner_removals/{}", tokens[0]);
+    let json = anon.put::<Value>(&url, "").await.good();
+    assert_eq!(json["msg"], "owners successfully removed");
+    assert_eq!(owner_logins(&anon).await, ["foo"]);
+
+    // The token can only be used once
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Dormant owner suggestion not found or expired."}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn suggestions_can_be_disabled() {
+    let (app, _, owner) = TestApp::full()
+        .with_config(|config| config.dormant_owner_period = None)
+        .with_user();
+    let dormant = app.db_new_user("dormant");
+    create_crate(&app, &owner, &dormant);
+
+    app.db(|conn| SuggestDormantOwnerRemovals.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert!(app.as_inner().emails.mails_in_memory().unwrap().is_empty());
+    assert!(suggestion_tokens(&app).is_empty());
+}
@@ -1,9 +1,15 @@
-use crate::builders::PublishBuilder;
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
 use crates_io::models::Crate;
+use crates_io::schema::versions;
 use crates_io::worker::jobs;
+use crates_io_index::Repository;
+use crates_io_worker::BackgroundJob;
 use diesel::prelude::*;
 use http::StatusCode;
+use insta::assert_snapshot;
+use object_store::ObjectStore;
+use serde_json::json;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn index_smoke_test() {
@@ -67,6 +73,53 @@ async fn index_smoke_test() {
     assert_ok_eq!(upstream.crate_exists("serde"), false);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn index_updates_are_batched() {
+    let (app, _, _, token) = TestApp::full().with_token();
+    let upstream = app.upstream_index();
+
+    for name in ["foo", "bar", "baz"] {
+        let body = PublishBuilder::new(name, "1.0.0").body();
+        let response = token.put::<()>("/api/v1/crates/new", body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    app.run_pending_background_jobs().await;
+    assert_ok_eq!(
+        upstream.list_commits(),
+        vec![
+            "Initial Commit",
+            "Update 3 crates\n\n\
+            - Create crate `foo`\n\
+            - Create crate `bar`\n\
+            - Create crate `baz`\n",
+        ]
+    );
+    assert_ok_eq!(upstream.crate_exists("foo"), true);
+    assert_ok_eq!(upstream.crate_exists("bar"), true);
+    assert_ok_eq!(upstream.crate_exists("baz"), true);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_updates_are_not_batched_with_batch_size_one() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.git_index_sync_batch_size = 1)
+        .with_token();
+    let upstream = app.upstream_index();
+
+    for name in ["foo", "bar"] {
+        let body = PublishBuilder::new(name, "1.0.0").body();
+        let response = token.put::<()>("/api/v1/crates/new", body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    app.run_pending_background_jobs().await;
+    assert_ok_eq!(
+        upstream.list_commits(),
+        vec!["Initial Commit", "Create crate `foo`", "Create crate `bar`"]
+    );
+}
+
 /// This test checks that changes to the `config.json` file on the git index
 /// are preserved when the background worker updates the index.
 #[tokio::test(flavor = "multi_thread")]
@@ -105,3 +158,100 @@ async fn test_config_changes() {
     // Check that the `config.json` changes on the upstream index are preserved
     assert_ok_eq!(upstream.read_file("config.json"), UPDATED_CONFIG);
 }
+
+async fn read_sparse_index_config(app: &TestApp) -> String {
+    let store = app.as_inner().storage.as_inner();
+    let result = store.get(&"index/config.json".into()).await.unwrap();
+    String::from_utf8(result.bytes().await.unwrap().to_vec()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_index_config() {
+    let (app, _) = TestApp::full().empty();
+
+    app.db(|conn| jobs::SyncIndexConfig.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let config = read_sparse_index_config(&app).await;
+    assert_snapshot!(config, @r###"{"dl":"https://static.crates.io/crates","api":"https://crates.io","features":["attestations","batch-publish","staged-uploads"]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_index_config_with_download_auth() {
+    let (app, _) = TestApp::full()
+        .with_config(|config| config.download_auth_required = true)
+        .empty();
+
+    app.db(|conn| jobs::SyncIndexConfig.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let config = read_sparse_index_config(&app).await;
+    assert_snapshot!(config, @r###"{"dl":"https://crates.io/api/v1/crates","api":"https://crates.io","auth-required":true,"features":["attestations","batch-publish","staged-uploads"]}"###);
+}
+
+async fn read_sparse_index_file(app: &TestApp, crate_name: &str) -> String {
+    let path = Repository::relative_index_file_for_url(crate_name);
+    let store = app.as_inner().storage.as_inner();
+    let result = store.get(&format!("index/{path}").into()).await.unwrap();
+    String::from_utf8(result.bytes().await.unwrap().to_vec()).unwrap()
+}
+
+async fn regenerate_sparse_index(app: &TestApp, user_id: i32) -> String {
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id)
+            .version(VersionBuilder::new("1.0.0").checksum("0123456789abcdef"))
+            .expect_build(conn);
+
+        let features = json!({
+            "default": ["std", "std"],
+            "serde": ["dep:serde", "dep:serde"],
+            "std": [],
+        });
+        diesel::update(versions::table)
+            .set(versions::features.eq(features))
+            .execute(conn)
+            .unwrap();
+
+        jobs::RegenerateSparseIndex::default()
+            .enqueue(conn)
+            .unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    read_sparse_index_file(app, "foo").await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn regenerate_sparse_index_with_default_encoding() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    let index_file = regenerate_sparse_index(&app, user.as_model().id).await;
+    assert_snapshot!(index_file, @r###"
+    {"name":"foo","vers":"1.0.0","deps":[],"cksum":"0123456789abcdef","features":{"default":["std","std"],"std":[]},"features2":{"serde":["dep:serde","dep:serde"]},"yanked":false,"v":2}
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn regenerate_sparse_index_with_deduplicated_features() {
+    let (app, _, user) = TestApp::full()
+        .with_config(|config| config.index_feature_encoding.deduplicate = true)
+        .with_user();
+
+    let index_file = regenerate_sparse_index(&app, user.as_model().id).await;
+    assert_snapshot!(index_file, @r###"
+    {"name":"foo","vers":"1.0.0","deps":[],"cksum":"0123456789abcdef","features":{"default":["std"],"std":[]},"features2":{"serde":["dep:serde"]},"yanked":false,"v":2}
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn regenerate_sparse_index_without_legacy_features() {
+    let (app, _, user) = TestApp::full()
+        .with_config(|config| config.index_feature_encoding.legacy_features = false)
+        .with_user();
+
+    let index_file = regenerate_sparse_index(&app, user.as_model().id).await;
+    assert_snapshot!(index_file, @r###"
+    {"name":"foo","vers":"1.0.0","deps":[],"cksum":"0123456789abcdef","features":{},"features2":{"default":["std","std"],"serde":["dep:serde","dep:serde"],"std":[]},"yanked":false,"v":2}
+    "###);
+}
@@ -0,0 +1,124 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::worker::jobs::{ImportCrate, ImportedOwner, ImportedVersion};
+use crates_io_worker::BackgroundJob;
+
+fn index_entry(
+    name: &str,
+    version: &str,
+    deps: Vec<crates_io_index::Dependency>,
+) -> crates_io_index::Crate {
+    crates_io_index::Crate {
+        name: name.to_string(),
+        vers: version.to_string(),
+        deps,
+        cksum: "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
+        features: Default::default(),
+        features2: None,
+        yanked: None,
+        links: None,
+        rust_version: None,
+        channel: None,
+        v: None,
+    }
+}
+
+fn job(
+    name: &str,
+    owners: Vec<ImportedOwner>,
+    versions: Vec<crates_io_index::Crate>,
+) -> ImportCrate {
+    ImportCrate {
+        name: name.to_string(),
+        description: Some(format!("the {name} crate")),
+        homepage: None,
+        documentation: None,
+        repository: None,
+        keywords: vec!["imported".to_string()],
+        owners,
+        versions: versions
+            .into_iter()
+            .map(|index| ImportedVersion {
+                index,
+                license: Some("MIT".to_string()),
+                crate_size: 42,
+            })
+            .collect(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn imports_crates_with_versions_and_owners() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let other = app.db_new_user("other");
+
+    let owners = vec![
+        ImportedOwner {
+            user_id: user.as_model().id,
+            email: "something@example.com".to_string(),
+        },
+        ImportedOwner {
+            user_id: other.as_model().id,
+            email: "something@example.com".to_string(),
+        },
+    ];
+
+    let dependency = crates_io_index::Dependency {
+        name: "renamed".to_string(),
+        req: "^1.0".to_string(),
+        features: vec![],
+        optional: false,
+        default_features: true,
+        target: None,
+        kind: None,
+        package: Some("bar".to_string()),
+    };
+
+    let mut yanked = index_entry("foo", "1.1.0", vec![dependency]);
+    yanked.yanked = Some(true);
+
+    let foo = job(
+        "foo",
+        owners.clone(),
+        vec![index_entry("foo", "1.0.0", vec![]), yanked],
+    );
+    let bar = job("bar", owners, vec![index_entry("bar", "1.0.0", vec![])]);
+
+    app.db(|conn| bar.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    app.db(|conn| foo.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let json = anon.get::<()>("/api/v1/crates/foo").await.json();
+    assert_eq!(json["crate"]["description"], "the foo crate");
+    assert_eq!(json["crate"]["default_version"], "1.0.0");
+    assert_eq!(json["crate"]["keywords"], serde_json::json!(["imported"]));
+
+    let versions = json["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["num"], "1.1.0");
+    assert_eq!(versions[0]["yanked"], true);
+    assert_eq!(versions[0]["published_by"]["login"], "foo");
+    assert_eq!(versions[1]["num"], "1.0.0");
+    assert_eq!(versions[1]["yanked"], false);
+
+    let json = anon
+        .get::<()>("/api/v1/crates/foo/1.1.0/dependencies")
+        .await
+        .json();
+    let dependencies = json["dependencies"].as_array().unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(dependencies[0]["crate_id"], "bar");
+
+    let json = anon.get::<()>("/api/v1/crates/foo/owner_user").await.json();
+    let mut logins = json["users"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|user| user["login"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    logins.sort();
+    assert_eq!(logins, ["foo", "other"]);
+
+    assert_eq!(app.crates_from_index_head("foo").len(), 2);
+}
@@ -1,3 +1,17 @@
+mod check_mirrors;
+mod crate_health;
+mod crate_recommendations;
+mod dormant_owners;
 mod git;
+mod import_crate;
+mod prerelease_retention;
+mod purge_expired_records;
+mod readme_images;
+mod rerender_readmes;
+mod revalidate_upstream_crates;
 mod rss;
+mod scan_tarball;
 mod sync_admins;
+mod sync_advisories;
+mod validate_version_metadata;
+mod weekly_digest;
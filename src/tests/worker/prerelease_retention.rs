@@ -0,0 +1,82 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::models::{Crate, CrateSettingsUpdate};
+use crates_io::schema::versions;
+use crates_io::worker::jobs::ApplyPrereleaseRetention;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yanks_superseded_prereleases() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user_id = user.as_model().id;
+
+    let old = Utc::now().naive_utc() - Duration::days(60);
+    let recent = Utc::now().naive_utc() - Duration::days(5);
+
+    app.db(|conn| {
+        let foo = CrateBuilder::new("foo", user_id)
+            .version(VersionBuilder::new("1.0.0-beta.1").created_at(old))
+            .version(VersionBuilder::new("1.0.0-rc.1").created_at(recent))
+            .version(VersionBuilder::new("1.0.0").created_at(recent))
+            .version(VersionBuilder::new("2.0.0-alpha.1").created_at(old))
+            .expect_build(conn);
+
+        let update = CrateSettingsUpdate {
+            prerelease_retention_days: Some(Some(30)),
+            ..Default::default()
+        };
+        update.apply(conn, foo.id).unwrap();
+
+        // Crates without a retention policy are not affected
+        CrateBuilder::new("bar", user_id)
+            .version(VersionBuilder::new("1.0.0-beta.1").created_at(old))
+            .version(VersionBuilder::new("1.0.0").created_at(recent))
+            .expect_build(conn);
+    });
+
+    app.db(|conn| ApplyPrereleaseRetention.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let yanked = |name: &str| -> Vec<String> {
+        app.db(|conn| {
+            let krate: Crate = Crate::by_name(name).first(conn).unwrap();
+            versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(versions::yanked.eq(true))
+                .select(versions::num)
+                .order(versions::num)
+                .load(conn)
+                .unwrap()
+        })
+    };
+
+    assert_eq!(yanked("foo"), vec!["1.0.0-beta.1"]);
+    assert!(yanked("bar").is_empty());
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].1.contains("- 1.0.0-beta.1"));
+
+    // The yank is recorded in the yank history without a user
+    let json: serde_json::Value = anon
+        .get("/api/v1/crates/foo/1.0.0-beta.1/yank_history")
+        .await
+        .good();
+    let history = json["yank_history"].as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["action"], "yank");
+    assert_eq!(history[0]["user"], serde_json::Value::Null);
+    assert_eq!(
+        history[0]["reason"],
+        "Pre-release is older than the retention period of 30 days"
+    );
+
+    // Running the job again does not yank anything else
+    app.db(|conn| ApplyPrereleaseRetention.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(yanked("foo"), vec!["1.0.0-beta.1"]);
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+}
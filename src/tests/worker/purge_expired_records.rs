@@ -0,0 +1,162 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::TestApp;
+use chrono::{Duration, NaiveDateTime, Utc};
+use crates_io::models::{
+    insert_version_owner_action, ApiToken, CrateOwnerInvitation, NewPendingPublish, VersionAction,
+};
+use crates_io::schema::{api_tokens, crate_owner_invitations, emails, pending_publishes, versions};
+use crates_io::worker::jobs::PurgeExpiredRecords;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+fn days_ago(days: i64) -> NaiveDateTime {
+    (Utc::now() - Duration::days(days)).naive_utc()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn purges_expired_records_in_batches() {
+    let (app, _, user) = TestApp::full()
+        .with_config(|config| config.retention.batch_size = 1)
+        .with_user();
+    let user_id = user.as_model().id;
+
+    let config = app.as_inner().config.clone();
+    let expiration = config.ownership_invitations_expiration_days as i64;
+
+    let stale_user = app.db_new_user("stale");
+    let stale_user_id = stale_user.as_model().id;
+    let recent_user = app.db_new_user("recent");
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo", user_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+
+        let invitees = [
+            (stale_user_id, days_ago(expiration + 31)),
+            (recent_user.as_model().id, days_ago(expiration + 1)),
+        ];
+        for (invitee_id, created_at) in invitees {
+            CrateOwnerInvitation::create(invitee_id, user_id, krate.id, conn, &config).unwrap();
+            diesel::update(crate_owner_invitations::table)
+                .filter(crate_owner_invitations::invited_user_id.eq(invitee_id))
+                .set(crate_owner_invitations::created_at.eq(created_at))
+                .execute(conn)
+                .unwrap();
+        }
+
+        let tokens = [
+            ("stale-1", Some(days_ago(91))),
+            ("stale-2", Some(days_ago(120))),
+            ("audited", Some(days_ago(91))),
+            ("recent", Some(days_ago(1))),
+            ("unlimited", None),
+        ];
+        for (name, expired_at) in tokens {
+            ApiToken::insert_with_scopes(conn, user_id, name, None, None, expired_at).unwrap();
+        }
+
+        // Tokens that are referenced by the audit log are kept
+        let audited_id = api_tokens::table
+            .filter(api_tokens::name.eq("audited"))
+            .select(api_tokens::id)
+            .get_result(conn)
+            .unwrap();
+        let version_id = versions::table
+            .select(versions::id)
+            .get_result(conn)
+            .unwrap();
+        let action = VersionAction::Publish;
+        insert_version_owner_action(conn, version_id, user_id, Some(audited_id), action, None)
+            .unwrap();
+
+        diesel::update(emails::table)
+            .filter(emails::user_id.eq(stale_user_id))
+            .set((
+                emails::verified.eq(false),
+                emails::token_generated_at.eq(days_ago(31)),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        for (user_id, created_at) in [
+            (stale_user_id, days_ago(2)),
+            (recent_user.as_model().id, Utc::now().naive_utc()),
+        ] {
+            let pending = NewPendingPublish {
+                user_id,
+                api_token_id: None,
+                crate_name: "foo",
+                version: "2.0.0",
+                body: b"",
+                ci_system: None,
+                ci_run_url: None,
+            };
+            pending.insert(conn).unwrap();
+
+            diesel::update(pending_publishes::table)
+                .filter(pending_publishes::user_id.eq(user_id))
+                .set(pending_publishes::created_at.eq(created_at))
+                .execute(conn)
+                .unwrap();
+        }
+
+        PurgeExpiredRecords.enqueue(conn).unwrap();
+    });
+
+    let old_token: String = app.db(|conn| {
+        emails::table
+            .filter(emails::user_id.eq(stale_user_id))
+            .select(emails::token)
+            .get_result(conn)
+            .unwrap()
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let invitees: Vec<i32> = app.db(|conn| {
+        crate_owner_invitations::table
+            .select(crate_owner_invitations::invited_user_id)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(invitees, vec![recent_user.as_model().id]);
+
+    let tokens: Vec<String> = app.db(|conn| {
+        api_tokens::table
+            .select(api_tokens::name)
+            .order(api_tokens::name)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(tokens, vec!["audited", "recent", "unlimited"]);
+
+    let pending_publishes: Vec<i32> = app.db(|conn| {
+        pending_publishes::table
+            .select(pending_publishes::user_id)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(pending_publishes, vec![recent_user.as_model().id]);
+
+    let (token, token_generated_at): (String, Option<NaiveDateTime>) = app.db(|conn| {
+        emails::table
+            .filter(emails::user_id.eq(stale_user_id))
+            .select((emails::token, emails::token_generated_at))
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_ne!(token, old_token);
+    assert_eq!(token_generated_at, None);
+
+    // The verified email addresses are not touched
+    let verified: i64 = app.db(|conn| {
+        emails::table
+            .filter(emails::verified)
+            .filter(emails::token_generated_at.is_not_null())
+            .count()
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_eq!(verified, 2);
+}
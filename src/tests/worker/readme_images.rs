@@ -0,0 +1,92 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::readme_images::{digest, FetchedImage, MockImageFetcher};
+use crates_io::schema::readme_images;
+use diesel::prelude::*;
+use http::StatusCode;
+
+const README: &str = "![logo](https://example.com/logo.png) ![page](https://example.com/page.html) ![local](http://localhost/logo.png)";
+const LOGO_URL: &str = "https://example.com/logo.png";
+const PAGE_URL: &str = "https://example.com/page.html";
+
+fn fetcher() -> MockImageFetcher {
+    let mut fetcher = MockImageFetcher::new();
+    fetcher.expect_fetch().returning(|url, _| {
+        let content_type = match url {
+            LOGO_URL => "image/png",
+            _ => "text/html",
+        };
+        Ok(FetchedImage {
+            content_type: content_type.into(),
+            bytes: b"content"[..].into(),
+        })
+    });
+    fetcher
+}
+
+async fn stored_readme(app: &TestApp) -> String {
+    let store = app.as_inner().storage.as_inner();
+    let path = "readmes/foo/foo-1.0.0.html".into();
+    let bytes = store.get(&path).await.unwrap().bytes().await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readme_images_are_proxied() {
+    let (app, anon, user) = TestApp::full().with_image_fetcher(fetcher()).with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").readme(README);
+    user.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    let logo_digest = digest(LOGO_URL);
+    let page_digest = digest(PAGE_URL);
+
+    let readme = stored_readme(&app).await;
+    let proxy_url = format!("https://crates.io/api/v1/readme_images/{logo_digest}");
+    assert!(readme.contains(&proxy_url));
+    assert!(readme.contains(&page_digest));
+    assert!(readme.contains("http://localhost/logo.png"));
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&format!("readme-images/{logo_digest}")));
+    assert!(!stored_files.contains(&format!("readme-images/{page_digest}")));
+
+    let failed: Vec<String> = app.db(|conn| {
+        readme_images::table
+            .filter(readme_images::failed_at.is_not_null())
+            .select(readme_images::url)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(failed, vec![PAGE_URL]);
+
+    anon.get::<()>(&format!("/api/v1/readme_images/{logo_digest}"))
+        .await
+        .assert_redirect_ends_with(&format!("/readme-images/{logo_digest}"));
+
+    // Files that are not images are not served
+    let response = anon
+        .get::<()>(&format!("/api/v1/readme_images/{page_digest}"))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = anon.get::<()>("/api/v1/readme_images/unknown").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readme_images_are_not_proxied_by_default() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").readme(README);
+    user.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    let readme = stored_readme(&app).await;
+    assert!(readme.contains(LOGO_URL));
+    assert!(!readme.contains("/api/v1/readme_images/"));
+
+    let count: i64 = app.db(|conn| readme_images::table.count().get_result(conn).unwrap());
+    assert_eq!(count, 0);
+}
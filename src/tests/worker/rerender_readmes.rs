@@ -0,0 +1,84 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::schema::readme_renderings;
+use crates_io::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+const README: &str =
+    "<details open><summary>Usage</summary>\n\n`foo`\n\n```rust\nfn main() {}\n```\n\n</details>\n";
+
+async fn stored_readme(app: &TestApp) -> String {
+    let store = app.as_inner().storage.as_inner();
+    let path = "readmes/foo/foo-1.0.0.html".into();
+    let bytes = store.get(&path).await.unwrap().bytes().await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+fn policy_versions(app: &TestApp) -> Vec<i32> {
+    app.db(|conn| {
+        readme_renderings::table
+            .select(readme_renderings::policy_version)
+            .load(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn outdated_readmes_are_rendered_again() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .readme(README)
+        .add_file("foo-1.0.0/README.md", README);
+    user.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(policy_versions(&app), vec![3]);
+
+    // Simulate a README that was rendered before syntax highlighting
+    app.db(|conn| {
+        diesel::update(readme_renderings::table)
+            .set(readme_renderings::policy_version.eq(2))
+            .execute(conn)
+            .unwrap();
+    });
+
+    app.as_inner()
+        .storage
+        .upload_readme("foo", "1.0.0", "outdated".into())
+        .await
+        .unwrap();
+
+    app.db(|conn| jobs::RerenderReadmes::default().enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(policy_versions(&app), vec![3]);
+
+    let readme = stored_readme(&app).await;
+    assert!(readme.contains("<details open=\"\">"));
+    assert!(readme.contains("<code>foo</code>"));
+    assert!(readme.contains("<span class=\"hl-keyword\">fn</span>"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn up_to_date_readmes_are_not_rendered_again() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .readme(README)
+        .add_file("foo-1.0.0/README.md", README);
+    user.publish_crate(crate_to_publish).await.good();
+    app.run_pending_background_jobs().await;
+
+    app.as_inner()
+        .storage
+        .upload_readme("foo", "1.0.0", "unchanged".into())
+        .await
+        .unwrap();
+
+    app.db(|conn| jobs::RerenderReadmes::default().enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(stored_readme(&app).await, "unchanged");
+}
@@ -0,0 +1,88 @@
+use crate::util::TestApp;
+use chrono::{Duration, Utc};
+use crates_io::schema::upstream_crates;
+use crates_io::upstream::MockUpstreamRegistry;
+use crates_io::worker::jobs::RevalidateUpstreamCrates;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+const OLD_CONTENT: &str = r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"abc","features":{},"yanked":false}
+"#;
+
+const NEW_CONTENT: &str = r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"abc","features":{},"yanked":true}
+"#;
+
+fn upstream() -> MockUpstreamRegistry {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_index_file().returning(|name| match name {
+        "foo" | "fresh" => Ok(Some(NEW_CONTENT.to_string())),
+        "broken" => Err(anyhow::anyhow!("connection refused")),
+        _ => Ok(None),
+    });
+    upstream
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn revalidates_outdated_index_files() {
+    let (app, _) = TestApp::full().with_upstream(upstream()).empty();
+    let storage = &app.as_inner().storage;
+
+    let two_hours_ago = (Utc::now() - Duration::hours(2)).naive_utc();
+    for name in ["foo", "gone", "broken", "fresh"] {
+        storage
+            .sync_index(name, Some(OLD_CONTENT.to_string()))
+            .await
+            .unwrap();
+    }
+
+    app.db(|conn| {
+        for name in ["foo", "gone", "broken", "fresh"] {
+            diesel::insert_into(upstream_crates::table)
+                .values(upstream_crates::name.eq(name))
+                .execute(conn)
+                .unwrap();
+        }
+
+        // Recently revalidated crates are skipped
+        diesel::update(upstream_crates::table.filter(upstream_crates::name.ne("fresh")))
+            .set(upstream_crates::revalidated_at.eq(two_hours_ago))
+            .execute(conn)
+            .unwrap();
+
+        RevalidateUpstreamCrates.enqueue(conn).unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let index_file = storage.read_index_file("foo").await.unwrap();
+    assert_eq!(index_file.as_deref(), Some(NEW_CONTENT));
+
+    // Crates that were deleted upstream are removed from the cache
+    let index_file = storage.read_index_file("gone").await.unwrap();
+    assert_eq!(index_file, None);
+
+    for name in ["broken", "fresh"] {
+        let index_file = storage.read_index_file(name).await.unwrap();
+        assert_eq!(index_file.as_deref(), Some(OLD_CONTENT));
+    }
+
+    let crates: Vec<(String, bool)> = app.db(|conn| {
+        upstream_crates::table
+            .select((
+                upstream_crates::name,
+                upstream_crates::revalidated_at.gt(two_hours_ago),
+            ))
+            .order(upstream_crates::name)
+            .load(conn)
+            .unwrap()
+    });
+
+    assert_eq!(
+        crates,
+        [
+            ("broken".to_string(), false),
+            ("foo".to_string(), true),
+            ("fresh".to_string(), true),
+        ]
+    );
+}
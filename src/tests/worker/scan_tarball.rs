@@ -0,0 +1,108 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::antivirus::{MockVirusScanner, ScanResult};
+use crates_io::models::{ScanVerdict, VersionQuarantine};
+use crates_io::schema::{tarball_scans, versions};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::json;
+
+const TARBALL_PATH: &str = "crates/foo/foo-1.0.0.crate";
+const QUARANTINE_PATH: &str = "quarantine/crates/foo/foo-1.0.0.crate";
+
+fn scanner(result: ScanResult) -> MockVirusScanner {
+    let mut scanner = MockVirusScanner::new();
+    scanner.expect_scan().returning(move |_| Ok(result.clone()));
+    scanner
+}
+
+fn version(app: &TestApp) -> (i32, bool) {
+    app.db(|conn| {
+        versions::table
+            .select((versions::id, versions::yanked))
+            .first(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clean_tarballs_are_not_quarantined() {
+    let (app, _, user) = TestApp::full()
+        .with_virus_scanner(scanner(ScanResult::Clean))
+        .with_user();
+
+    user.publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    let (version_id, yanked) = version(&app);
+    assert!(!yanked);
+
+    let verdict: ScanVerdict = app.db(|conn| {
+        tarball_scans::table
+            .find(version_id)
+            .select(tarball_scans::verdict)
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_eq!(verdict, ScanVerdict::Clean);
+
+    assert!(app.stored_files().await.contains(&TARBALL_PATH.to_string()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn infected_tarballs_are_quarantined_until_reviewed() {
+    let infected = ScanResult::Infected("Eicar-Signature".into());
+    let (app, _, user) = TestApp::full()
+        .with_virus_scanner(scanner(infected))
+        .with_user();
+
+    user.publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    let (version_id, yanked) = version(&app);
+    assert!(yanked);
+    assert!(app.db(|conn| VersionQuarantine::is_quarantined(conn, version_id).unwrap()));
+
+    let stored_files = app.stored_files().await;
+    assert!(!stored_files.contains(&TARBALL_PATH.to_string()));
+    assert!(stored_files.contains(&QUARANTINE_PATH.to_string()));
+
+    // Owners can not unyank quarantined versions
+    let response = user.put::<()>("/api/v1/crates/foo/1.0.0/unyank", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this version is quarantined and can not be unyanked"}]}"###);
+
+    // Only admins can access the review queue
+    let response = user.get::<()>("/api/private/tarball_scans").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    user.make_admin();
+
+    let json = user.get::<()>("/api/private/tarball_scans").await.json();
+    let scans = json["tarball_scans"].as_array().unwrap();
+    assert_eq!(scans.len(), 1);
+    assert_eq!(scans[0]["crate"], "foo");
+    assert_eq!(scans[0]["version"], "1.0.0");
+    assert_eq!(scans[0]["verdict"], "infected");
+    assert_eq!(scans[0]["signature"], "Eicar-Signature");
+    assert_eq!(scans[0]["quarantined"], true);
+
+    let url = format!("/api/private/tarball_scans/{version_id}/review");
+    let body = json!({ "decision": "release" }).to_string();
+    let response = user.put::<()>(&url, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    let (_, yanked) = version(&app);
+    assert!(!yanked);
+    assert!(!app.db(|conn| VersionQuarantine::is_quarantined(conn, version_id).unwrap()));
+    assert!(app.stored_files().await.contains(&TARBALL_PATH.to_string()));
+
+    let json = user.get::<()>("/api/private/tarball_scans").await.json();
+    assert_eq!(json["tarball_scans"].as_array().unwrap().len(), 0);
+}
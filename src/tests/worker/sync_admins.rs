@@ -89,6 +89,7 @@ fn create_user(name: &str, gh_id: i32, is_admin: bool, conn: &mut PgConnection)
             emails::user_id.eq(user_id),
             emails::email.eq(format!("{}@crates.io", name)),
             emails::verified.eq(true),
+            emails::is_primary.eq(true),
         ))
         .execute(conn)?;
 
@@ -0,0 +1,78 @@
+use crate::util::TestApp;
+use chrono::NaiveDate;
+use crates_io::advisories::{MockAdvisorySource, ParsedAdvisory};
+use crates_io::models::Advisory;
+use crates_io::schema::advisories;
+use crates_io::worker::jobs::SyncAdvisories;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn advisory(id: &str, title: &str) -> ParsedAdvisory {
+    ParsedAdvisory {
+        id: id.into(),
+        crate_name: "foo".into(),
+        title: title.into(),
+        url: None,
+        date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+        withdrawn: None,
+        aliases: vec![],
+        patched: vec![">= 1.2.3".into()],
+        unaffected: vec![],
+    }
+}
+
+fn titles(conn: &mut PgConnection) -> Vec<(String, String)> {
+    advisories::table
+        .select((advisories::id, advisories::title))
+        .order(advisories::id)
+        .load(conn)
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn syncs_advisories() {
+    // Every run of the job returns the next version of the advisory database
+    let databases = [
+        vec![
+            advisory("RUSTSEC-2024-0001", "a"),
+            advisory("RUSTSEC-2024-0002", "b"),
+        ],
+        vec![advisory("RUSTSEC-2024-0002", "b (updated)")],
+        vec![],
+    ];
+    let runs = AtomicUsize::new(0);
+
+    let mut source = MockAdvisorySource::new();
+    source.expect_fetch().returning(move || {
+        let run = runs.fetch_add(1, Ordering::SeqCst);
+        Ok(databases[run].clone())
+    });
+
+    let (app, _) = TestApp::full().with_advisory_source(source).empty();
+
+    app.db(|conn| SyncAdvisories.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let expected = vec![
+        ("RUSTSEC-2024-0001".into(), "a".into()),
+        ("RUSTSEC-2024-0002".into(), "b".into()),
+    ];
+    assert_eq!(app.db(titles), expected);
+
+    let advisory = app.db(|conn| Advisory::find(conn, "RUSTSEC-2024-0001").unwrap());
+    assert_eq!(advisory.unwrap().patched, vec![">= 1.2.3"]);
+
+    // Updated advisories are updated, and removed ones are deleted
+    app.db(|conn| SyncAdvisories.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let expected = vec![("RUSTSEC-2024-0002".into(), "b (updated)".into())];
+    assert_eq!(app.db(titles), expected);
+
+    // An empty advisory database does not delete the existing advisories
+    app.db(|conn| SyncAdvisories.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(app.db(titles), expected);
+}
@@ -0,0 +1,49 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crates_io::worker::jobs::ValidateVersionMetadata;
+use crates_io_worker::BackgroundJob;
+use http::StatusCode;
+use serde_json::Value;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn records_findings_for_owners() {
+    let (app, anon, owner) = TestApp::full().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", owner.as_model().id)
+            .version(VersionBuilder::new("1.0.0").license("apache 2.0"))
+            .version(VersionBuilder::new("1.1.0").license("MIT"))
+            .expect_build(conn);
+    });
+
+    app.db(|conn| ValidateVersionMetadata::default().enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let url = "/api/v1/crates/foo/metadata_findings";
+
+    let response = owner.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    let findings = json["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0]["version"], "1.0.0");
+    assert_eq!(findings[0]["rule"], "invalid_license");
+
+    let response = other.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Running the job again replaces the existing findings
+    app.db(|conn| ValidateVersionMetadata::default().enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let json = owner.get::<()>(url).await.json();
+    assert_eq!(json["findings"].as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        json["findings"][0]["message"],
+        Value::from("unknown or invalid license expression `apache 2.0`")
+    );
+}
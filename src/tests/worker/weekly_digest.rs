@@ -0,0 +1,41 @@
+use crate::builders::CrateBuilder;
+use crate::util::TestApp;
+use chrono::{Duration, NaiveDateTime, Utc};
+use crates_io::schema::digest_subscriptions;
+use crates_io::worker::jobs::SendWeeklyDigests;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recently_sent_digests_are_not_sent_again() {
+    let (app, _, user) = TestApp::full().with_user();
+    let user_id = user.as_model().id;
+
+    let last_sent_at = Utc::now().naive_utc() - Duration::days(2);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id).expect_build(conn);
+
+        diesel::insert_into(digest_subscriptions::table)
+            .values((
+                digest_subscriptions::user_id.eq(user_id),
+                digest_subscriptions::last_sent_at.eq(last_sent_at),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    app.db(|conn| SendWeeklyDigests.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    assert!(app.as_inner().emails.mails_in_memory().unwrap().is_empty());
+
+    let last_sent_at: Option<NaiveDateTime> = app.db(|conn| {
+        digest_subscriptions::table
+            .find(user_id)
+            .select(digest_subscriptions::last_sent_at)
+            .first(conn)
+            .unwrap()
+    });
+    assert!(last_sent_at.unwrap() < Utc::now().naive_utc() - Duration::days(1));
+}
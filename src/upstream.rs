@@ -0,0 +1,261 @@
+//! Pull-through caching of crates from an upstream registry.
+//!
+//! Private deployments can be configured with the `UPSTREAM_INDEX_URL`
+//! environment variable to act as a cache for another registry, usually
+//! crates.io. Crates that don't exist locally are then fetched on demand:
+//!
+//! - If cargo requests the index file of an unknown crate from the
+//!   `/index/*path` endpoint, the index file is fetched from the sparse index
+//!   of the upstream registry, cached in the local sparse index, and recorded
+//!   in the `upstream_crates` table.
+//! - If cargo downloads a version of a cached crate, the crate file is
+//!   fetched from the upstream registry, verified against the checksum in the
+//!   cached index file, and cached in the storage bucket.
+//!
+//! Cached crates are not added to the other tables, so they don't show up in
+//! the web interface and the API, and local crates with the same name can't
+//! be published. Since the dependencies of local crates reference crates in
+//! the database, local crates can't depend on cached crates either. The
+//! `revalidate_upstream_crates` job regularly refreshes the cached index
+//! files, which picks up new versions and changes of the yank status.
+//!
+//! Concurrent requests for the same uncached file are deduplicated via
+//! [FetchLocks], and the files are fetched without holding a database
+//! connection.
+//!
+//! The [UpstreamRegistry] trait is used to abstract away the HTTP client for
+//! testing purposes.
+
+use crate::schema::upstream_crates;
+use crate::sql::canon_crate_name;
+use crate::util::diesel::Conn;
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use crates_io_index::Repository;
+use diesel::prelude::*;
+use hyper::body::Bytes;
+use mockall::automock;
+use parking_lot::Mutex;
+use reqwest::{Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+
+/// The default URL below which the crate files of the upstream registry are
+/// served.
+pub const DEFAULT_DOWNLOAD_URL: &str = "https://static.crates.io/crates";
+
+/// The `User-Agent` header that is sent to the upstream registry.
+const USER_AGENT: &str = "crates.io pull-through cache (https://github.com/rust-lang/crates.io)";
+
+/// The maximum duration of fetching a single file from the upstream registry.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The maximum size of an index file that is fetched from the upstream
+/// registry, in bytes.
+const MAX_INDEX_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct UpstreamConfig {
+    /// The URL of the sparse index of the upstream registry, without the
+    /// `sparse+` prefix (e.g. `https://index.crates.io`).
+    pub index_url: String,
+    /// The URL below which the crate files of the upstream registry are
+    /// served (e.g. `https://static.crates.io/crates`).
+    pub download_url: String,
+}
+
+#[automock]
+#[async_trait]
+pub trait UpstreamRegistry {
+    /// Fetches the index file of a crate, or returns `None` if the crate does
+    /// not exist in the upstream registry.
+    async fn index_file(&self, name: &str) -> anyhow::Result<Option<String>>;
+
+    /// Fetches the crate file of a version, unless it is larger than
+    /// `max_size` bytes.
+    async fn crate_file(&self, name: &str, version: &str, max_size: u64) -> anyhow::Result<Bytes>;
+}
+
+/// Fetches the files from an upstream registry via HTTP.
+pub struct HttpUpstreamRegistry {
+    client: reqwest::Client,
+    config: UpstreamConfig,
+}
+
+impl HttpUpstreamRegistry {
+    pub fn new(config: UpstreamConfig) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(FETCH_TIMEOUT)
+            .build()?;
+
+        Ok(Self { client, config })
+    }
+}
+
+#[async_trait]
+impl UpstreamRegistry for HttpUpstreamRegistry {
+    #[instrument(skip(self))]
+    async fn index_file(&self, name: &str) -> anyhow::Result<Option<String>> {
+        let index_url = self.config.index_url.trim_end_matches('/');
+        let path = Repository::relative_index_file_for_url(name);
+        let response = self
+            .client
+            .get(format!("{index_url}/{path}"))
+            .send()
+            .await?;
+
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+            return Ok(None);
+        }
+
+        let response = response.error_for_status()?;
+        let bytes = read_limited(response, MAX_INDEX_FILE_SIZE).await?;
+        Ok(Some(String::from_utf8(bytes.into())?))
+    }
+
+    #[instrument(skip(self))]
+    async fn crate_file(&self, name: &str, version: &str, max_size: u64) -> anyhow::Result<Bytes> {
+        let download_url = self.config.download_url.trim_end_matches('/');
+        let version = version.replace('+', "%2B");
+        let url = format!("{download_url}/{name}/{name}-{version}.crate");
+
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        read_limited(response, max_size).await
+    }
+}
+
+/// Reads the body of the response, unless it is larger than `max_size`
+/// bytes.
+async fn read_limited(mut response: Response, max_size: u64) -> anyhow::Result<Bytes> {
+    // The `Content-Length` header is optional, so the size has to be checked
+    // while the body is downloaded too.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > max_size {
+            bail!("the file is larger than {max_size} bytes");
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(bytes.into())
+}
+
+/// Deduplicates concurrent fetches of the same file from the upstream
+/// registry.
+///
+/// Requests that miss the cache lock the path of the file before fetching
+/// it. Concurrent requests for the same file wait for the lock, and then
+/// find the file in the cache.
+#[derive(Debug, Default)]
+pub struct FetchLocks {
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl FetchLocks {
+    /// Waits until no other request holds the lock of the `path`, and
+    /// returns a guard that holds the lock until it is dropped.
+    pub async fn lock(&self, path: String) -> FetchGuard<'_> {
+        let lock = self.locks.lock().entry(path.clone()).or_default().clone();
+        let guard = lock.lock_owned().await;
+
+        FetchGuard {
+            locks: self,
+            path,
+            guard,
+        }
+    }
+}
+
+pub struct FetchGuard<'a> {
+    locks: &'a FetchLocks,
+    path: String,
+    guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for FetchGuard<'_> {
+    fn drop(&mut self) {
+        // Unless other requests are waiting for the lock, only the map and
+        // this guard reference it, and it can be removed from the map.
+        let mut locks = self.locks.locks.lock();
+        if Arc::strong_count(OwnedMutexGuard::mutex(&self.guard)) <= 2 {
+            locks.remove(&self.path);
+        }
+    }
+}
+
+/// Parses the entries of an index file.
+pub fn parse_index_file(content: &str) -> anyhow::Result<Vec<crates_io_index::Crate>> {
+    content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_str(line).context("Failed to parse index entry"))
+        .collect()
+}
+
+/// Returns the name of the cached upstream crate with the given name, if
+/// there is one. The lookup ignores the differences between `-` and `_` and
+/// the case of the name, like the lookup of local crates.
+pub fn find_cached_crate(conn: &mut impl Conn, name: &str) -> QueryResult<Option<String>> {
+    upstream_crates::table
+        .filter(canon_crate_name(upstream_crates::name).eq(canon_crate_name(name)))
+        .select(upstream_crates::name)
+        .first(conn)
+        .optional()
+}
+
+/// Records that the index file of the crate was cached from the upstream
+/// registry.
+pub fn record_cached_crate(conn: &mut impl Conn, name: &str) -> QueryResult<()> {
+    diesel::insert_into(upstream_crates::table)
+        .values(upstream_crates::name.eq(name))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_index_file() {
+        let content = "\
+            {\"name\":\"foo\",\"vers\":\"1.0.0\",\"deps\":[],\"cksum\":\"abc\",\"features\":{},\"yanked\":false}\n\
+            {\"name\":\"foo\",\"vers\":\"1.1.0\",\"deps\":[],\"cksum\":\"def\",\"features\":{},\"yanked\":true}\n\
+        ";
+
+        let entries = parse_index_file(content).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].vers, "1.1.0");
+        assert_eq!(entries[1].yanked, Some(true));
+
+        assert!(parse_index_file("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_locks() {
+        let locks = FetchLocks::default();
+
+        let guard = locks.lock("3/f/foo".into()).await;
+        let other = locks.lock("3/b/bar".into()).await;
+        assert_eq!(locks.locks.lock().len(), 2);
+
+        // Concurrent fetches of the same path wait for the first one
+        let waiting = locks.lock("3/f/foo".into());
+        tokio::pin!(waiting);
+        assert!(futures_util::poll!(waiting.as_mut()).is_pending());
+
+        drop(guard);
+        let guard = waiting.await;
+        assert_eq!(locks.locks.lock().len(), 2);
+
+        // The locks are removed once they are not used anymore
+        drop(guard);
+        drop(other);
+        assert!(locks.locks.lock().is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+//! Throttling of anonymous crawlers based on their `User-Agent` header.
+//!
+//! The policies are stored in the `user_agent_policies` table and cached in
+//! memory, so that they can be changed at runtime without a redeploy. See
+//! [`crate::middleware`] for where they are enforced.
+
+use crate::models::{ThrottleClass, UserAgentPolicy};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::App;
+use diesel::QueryResult;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SLOW_RATE_MILLIS: u64 = 1000; // 1 second
+pub const DEFAULT_SLOW_BURST: u32 = 30;
+pub const DEFAULT_REFRESH_INTERVAL_SECONDS: u64 = 60; // 1 minute
+
+#[derive(Debug, Clone, Copy)]
+pub struct UserAgentThrottleConfig {
+    /// The interval in which a token is added to the bucket of the `slow`
+    /// class.
+    pub slow_rate: Duration,
+    /// The maximum number of tokens in the bucket of the `slow` class.
+    pub slow_burst: u32,
+    /// How often the policies are reloaded from the database.
+    pub refresh_interval: Duration,
+}
+
+impl Default for UserAgentThrottleConfig {
+    fn default() -> Self {
+        Self {
+            slow_rate: Duration::from_millis(DEFAULT_SLOW_RATE_MILLIS),
+            slow_burst: DEFAULT_SLOW_BURST,
+            refresh_interval: Duration::from_secs(DEFAULT_REFRESH_INTERVAL_SECONDS),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UserAgentThrottle {
+    config: UserAgentThrottleConfig,
+    policies: RwLock<Vec<UserAgentPolicy>>,
+    buckets: Mutex<HashMap<ThrottleClass, Bucket>>,
+}
+
+impl UserAgentThrottle {
+    pub fn new(config: UserAgentThrottleConfig) -> Self {
+        Self {
+            config,
+            policies: Default::default(),
+            buckets: Default::default(),
+        }
+    }
+
+    /// Replaces the cached policies with the current content of the
+    /// `user_agent_policies` table.
+    pub fn refresh(&self, conn: &mut impl Conn) -> QueryResult<()> {
+        let policies = UserAgentPolicy::all(conn)?;
+        *self.policies.write() = policies;
+        Ok(())
+    }
+
+    /// Returns the most restrictive class of all policies that match the
+    /// `User-Agent` header, or [`ThrottleClass::Normal`] if none match.
+    pub fn classify(&self, user_agent: &str) -> ThrottleClass {
+        let user_agent = user_agent.to_lowercase();
+
+        self.policies
+            .read()
+            .iter()
+            .filter(|policy| policy.matches(&user_agent))
+            .fold(ThrottleClass::Normal, |class, policy| {
+                class.max(policy.throttle_class)
+            })
+    }
+
+    /// Takes a token from the bucket of the given class.
+    ///
+    /// Returns the time after which the next token will be available, if the
+    /// bucket is currently empty.
+    pub fn take_token(&self, class: ThrottleClass, now: Instant) -> Result<(), Duration> {
+        let (rate, burst) = match class {
+            ThrottleClass::Normal => return Ok(()),
+            ThrottleClass::Slow => (self.config.slow_rate, self.config.slow_burst),
+            ThrottleClass::Blocked => return Err(Duration::MAX),
+        };
+
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets.entry(class).or_insert(Bucket {
+            tokens: burst,
+            last_refill: now,
+        });
+
+        bucket.take(rate, burst, now)
+    }
+}
+
+/// Reloads the policies of the app from the database in the configured
+/// interval, so that changes made via other instances are picked up.
+///
+/// This function never returns and is meant to be spawned as a task.
+pub async fn refresh_periodically(app: Arc<App>) {
+    let mut interval = tokio::time::interval(app.config.user_agent_throttle.refresh_interval);
+    loop {
+        interval.tick().await;
+
+        let app = app.clone();
+        let result = async {
+            let conn = app.db_read().await?;
+            spawn_blocking(move || {
+                let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+                Ok::<_, anyhow::Error>(app.user_agent_throttle.refresh(conn)?)
+            })
+            .await
+        };
+
+        if let Err(error) = result.await {
+            warn!("Failed to refresh the user agent policies: {error}");
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn take(&mut self, rate: Duration, burst: u32, now: Instant) -> Result<(), Duration> {
+        let rate_nanos = rate.as_nanos().max(1);
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let tokens_to_add = elapsed.as_nanos() / rate_nanos;
+        if tokens_to_add > 0 {
+            let refilled = self.tokens as u128 + tokens_to_add;
+            self.tokens = refilled.min(burst as u128) as u32;
+            self.last_refill += rate * tokens_to_add.min(u32::MAX as u128) as u32;
+        }
+
+        if self.tokens == 0 {
+            let elapsed = now.saturating_duration_since(self.last_refill);
+            return Err(rate.saturating_sub(elapsed));
+        }
+
+        self.tokens -= 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn policy(pattern: &str, throttle_class: ThrottleClass) -> UserAgentPolicy {
+        UserAgentPolicy {
+            id: 0,
+            pattern: pattern.to_string(),
+            throttle_class,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn classify_uses_most_restrictive_policy() {
+        let throttle = UserAgentThrottle::new(Default::default());
+        *throttle.policies.write() = vec![
+            policy("crawler", ThrottleClass::Slow),
+            policy("BadBot", ThrottleClass::Blocked),
+            policy("friendly", ThrottleClass::Normal),
+        ];
+
+        assert_eq!(throttle.classify("Mozilla/5.0"), ThrottleClass::Normal);
+        assert_eq!(throttle.classify("SomeCrawler/1.0"), ThrottleClass::Slow);
+        assert_eq!(throttle.classify("badbot crawler"), ThrottleClass::Blocked);
+        assert_eq!(throttle.classify("friendly-crawler"), ThrottleClass::Slow);
+    }
+
+    #[test]
+    fn slow_bucket_refills() {
+        let throttle = UserAgentThrottle::new(UserAgentThrottleConfig {
+            slow_rate: Duration::from_secs(1),
+            slow_burst: 2,
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+        assert_eq!(throttle.take_token(ThrottleClass::Slow, now), Ok(()));
+        assert_eq!(throttle.take_token(ThrottleClass::Slow, now), Ok(()));
+
+        let later = now + Duration::from_millis(400);
+        let retry_after = Duration::from_millis(600);
+        assert_eq!(
+            throttle.take_token(ThrottleClass::Slow, later),
+            Err(retry_after)
+        );
+
+        let later = now + Duration::from_millis(1500);
+        assert_eq!(throttle.take_token(ThrottleClass::Slow, later), Ok(()));
+        assert!(throttle.take_token(ThrottleClass::Slow, later).is_err());
+
+        // Other classes are not affected
+        assert_eq!(throttle.take_token(ThrottleClass::Normal, later), Ok(()));
+        assert!(throttle.take_token(ThrottleClass::Blocked, later).is_err());
+    }
+}
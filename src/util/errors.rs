@@ -32,7 +32,9 @@ mod json;
 use crate::email::EmailError;
 use crates_io_github::GitHubError;
 pub use json::TOKEN_FORMAT_ERROR;
-pub(crate) use json::{custom, InsecurelyGeneratedTokenRevoked, ReadOnlyMode, TooManyRequests};
+pub(crate) use json::{
+    custom, ApiQuotaExceeded, InsecurelyGeneratedTokenRevoked, ReadOnlyMode, TooManyRequests,
+};
 
 pub type BoxedAppError = Box<dyn AppError>;
 
@@ -83,6 +85,11 @@ pub fn version_not_found(krate: &str, version: &str) -> BoxedAppError {
     custom(StatusCode::NOT_FOUND, detail)
 }
 
+pub fn channel_not_found(krate: &str, channel: &str) -> BoxedAppError {
+    let detail = format!("crate `{krate}` does not have any versions in the `{channel}` channel");
+    custom(StatusCode::NOT_FOUND, detail)
+}
+
 // =============================================================================
 // AppError trait
 
@@ -167,6 +174,7 @@ impl From<EmailError> for BoxedAppError {
                 error!(?error, "Failed to send email");
                 server_error("Failed to send the email")
             }
+            EmailError::DatabaseError(error) => error.into(),
         }
     }
 }
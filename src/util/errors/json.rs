@@ -74,8 +74,8 @@ impl AppError for TooManyRequests {
         let retry_after = self.retry_after.format(HTTP_DATE_FORMAT);
 
         let detail = format!(
-            "{}. Please try again after {retry_after} or email \
-             help@crates.io to have your limit increased.",
+            "{}. Please try again after {retry_after} or request a higher \
+             limit via your account settings on crates.io.",
             self.action.error_message()
         );
         let mut response = json_error(&detail, StatusCode::TOO_MANY_REQUESTS);
@@ -96,6 +96,38 @@ impl fmt::Display for TooManyRequests {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct ApiQuotaExceeded {
+    pub retry_after: NaiveDateTime,
+}
+
+impl AppError for ApiQuotaExceeded {
+    fn response(&self) -> Response {
+        const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+        let retry_after = self.retry_after.format(HTTP_DATE_FORMAT);
+
+        let detail = format!(
+            "You have exceeded your API request quota. Please try again after \
+             {retry_after} or email help@crates.io to have your quota increased."
+        );
+        let mut response = json_error(&detail, StatusCode::TOO_MANY_REQUESTS);
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            retry_after
+                .to_string()
+                .try_into()
+                .expect("HTTP_DATE_FORMAT contains invalid char"),
+        );
+        response
+    }
+}
+
+impl fmt::Display for ApiQuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "API request quota exceeded".fmt(f)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct InsecurelyGeneratedTokenRevoked;
 
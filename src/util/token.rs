@@ -83,6 +83,24 @@ impl ExposeSecret<String> for PlainToken {
     }
 }
 
+/// Compares a secret provided by a client, e.g. the token of a webhook
+/// request, with the expected secret in constant time.
+///
+/// Both secrets are hashed first, so that neither the position of the first
+/// mismatch nor the length of the expected secret can be inferred from the
+/// time the comparison takes.
+pub fn secure_compare(provided: &str, expected: &str) -> bool {
+    let provided = Sha256::digest(provided.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+
+    let difference = provided
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+
+    std::hint::black_box(difference) == 0
+}
+
 fn generate_secure_alphanumeric_string(len: usize) -> String {
     const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
 
@@ -116,4 +134,12 @@ mod tests {
     fn test_parse_no_kind() {
         assert_err!(HashedToken::parse("nokind"));
     }
+
+    #[test]
+    fn test_secure_compare() {
+        assert!(secure_compare("secret", "secret"));
+        assert!(!secure_compare("secret", "secreT"));
+        assert!(!secure_compare("secret", "secret2"));
+        assert!(!secure_compare("", "secret"));
+    }
 }
@@ -1,14 +1,28 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use secrecy::ExposeSecret;
+use std::time::Duration;
 
+use crate::advisories::AffectedRange;
 use crate::external_urls::remove_blocked_urls;
 use crate::models::{
-    ApiToken, Category, Crate, CrateOwnerInvitation, CreatedApiToken, Dependency, DependencyKind,
-    Keyword, Owner, ReverseDependency, Team, TopVersions, User, Version, VersionDownload,
-    VersionOwnerAction,
+    AccountRecovery, AdminAction, AdminAuditEntry, Advisory, ApiToken, AttestationKind,
+    AttestationProvider, Category, Crate, CrateFreeze, CrateHealth, CrateOwnerAction,
+    CrateOwnerInvitation, CrateSettings, CrateSuccession, CreatedApiToken, CriticalCrate,
+    DatabaseDump, DeniedDependency, Dependency, DependencyKind, DependencyPolicyException,
+    DependencySubscription, DocsBuildStatus, Email, HealthComponent, Keyword, MetadataFinding,
+    MetadataRule, NotificationClass, Owner, OwnerAction, PendingOwnerRemoval,
+    RateLimitOverrideRequest, RegistryEvent, RegistryEventKind, ReproducibilityReport,
+    ReverseDependency, ScanVerdict, SpamFlag, TarballScan, Team, TopVersions, User, Version,
+    VersionAttestation, VersionCiAnnotation, VersionDownload, VersionOwnerAction,
 };
+use crate::rate_limiter::LimitedAction;
+use crate::storage::Storage;
 use crate::util::rfc3339;
+use crate::worker::jobs::dump_db::{
+    DELTAS_MANIFEST_FILE_NAME, MANIFEST_FILE_NAME, TAR_FILE_NAME, ZIP_FILE_NAME,
+};
 use crates_io_github as github;
+use crates_io_markdown::Heading;
 
 pub mod krate_publish;
 pub use self::krate_publish::{EncodableCrateDependency, PublishMetadata};
@@ -167,6 +181,430 @@ impl From<VersionDownload> for EncodableVersionDownload {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableRegistryEvent {
+    /// The cursor of the event, which can be used as the `since` parameter
+    /// of the changes feed.
+    pub id: String,
+    pub kind: RegistryEventKind,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl From<RegistryEvent> for EncodableRegistryEvent {
+    fn from(event: RegistryEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            kind: event.kind,
+            krate: event.crate_name,
+            version: event.version,
+            created_at: event.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAdvisory {
+    pub id: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub withdrawn: Option<NaiveDate>,
+    pub aliases: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    /// The ranges of published versions that are affected by the advisory.
+    pub affected_ranges: Vec<EncodableAffectedRange>,
+    /// The most downloaded crates whose default version depends on an
+    /// affected version, and can not use a patched version.
+    pub pinned_dependents: Vec<EncodablePinnedDependent>,
+    #[serde(with = "rfc3339")]
+    pub synced_at: NaiveDateTime,
+}
+
+impl EncodableAdvisory {
+    pub fn from(
+        advisory: Advisory,
+        affected_ranges: Vec<AffectedRange>,
+        pinned_dependents: Vec<EncodablePinnedDependent>,
+    ) -> Self {
+        Self {
+            id: advisory.id,
+            krate: advisory.crate_name,
+            title: advisory.title,
+            url: advisory.url,
+            date: advisory.date,
+            withdrawn: advisory.withdrawn,
+            aliases: advisory.aliases,
+            patched: advisory.patched,
+            unaffected: advisory.unaffected,
+            affected_ranges: affected_ranges.into_iter().map(Into::into).collect(),
+            pinned_dependents,
+            synced_at: advisory.synced_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAffectedRange {
+    pub first: String,
+    pub last: String,
+    /// The first version after the range that is neither affected nor
+    /// yanked.
+    pub first_patched: Option<String>,
+}
+
+impl From<AffectedRange> for EncodableAffectedRange {
+    fn from(range: AffectedRange) -> Self {
+        Self {
+            first: range.first.to_string(),
+            last: range.last.to_string(),
+            first_patched: range.first_patched.map(|version| version.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodablePinnedDependent {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// The default version of the dependent crate.
+    pub version: String,
+    /// The version requirement of the dependency on the affected crate.
+    pub req: String,
+    pub downloads: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableMetadataFinding {
+    pub id: i32,
+    pub version: String,
+    pub rule: MetadataRule,
+    pub message: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableMetadataFinding {
+    pub fn from(finding: MetadataFinding, version: String) -> Self {
+        Self {
+            id: finding.id,
+            version,
+            rule: finding.rule,
+            message: finding.message,
+            created_at: finding.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTarballScan {
+    pub version_id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: String,
+    pub verdict: ScanVerdict,
+    pub signature: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub scanned_at: NaiveDateTime,
+    pub quarantined: bool,
+}
+
+impl EncodableTarballScan {
+    pub fn from(scan: TarballScan, krate: String, version: String, quarantined: bool) -> Self {
+        Self {
+            version_id: scan.version_id,
+            krate,
+            version,
+            verdict: scan.verdict,
+            signature: scan.signature,
+            scanned_at: scan.scanned_at,
+            quarantined,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAttestationProvider {
+    pub user: EncodablePublicUser,
+    pub name: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableAttestationProvider {
+    pub fn from(provider: AttestationProvider, user: User, avatar_proxy: bool) -> Self {
+        Self {
+            user: EncodablePublicUser::from(user, avatar_proxy),
+            name: provider.name,
+            created_at: provider.created_at,
+        }
+    }
+}
+
+/// A signed statement of an attestation provider about a version.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionAttestation {
+    pub id: i32,
+    pub kind: AttestationKind,
+    /// The name of the attestation provider.
+    pub provider: String,
+    /// The login of the account of the attestation provider.
+    pub provider_login: String,
+    pub statement: String,
+    pub signature: String,
+    pub url: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableVersionAttestation {
+    pub fn from(
+        attestation: VersionAttestation,
+        provider: AttestationProvider,
+        provider_login: String,
+    ) -> Self {
+        Self {
+            id: attestation.id,
+            kind: attestation.kind,
+            provider: provider.name,
+            provider_login,
+            statement: attestation.statement,
+            signature: attestation.signature,
+            url: attestation.url,
+            created_at: attestation.created_at,
+        }
+    }
+}
+
+/// The attestation of an independent rebuilder whether a version could be
+/// reproduced from source.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReproducibilityReport {
+    pub reporter: EncodablePublicUser,
+    pub checksum: String,
+    pub matches: bool,
+    pub details: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableReproducibilityReport {
+    pub fn from(report: ReproducibilityReport, reporter: User, avatar_proxy: bool) -> Self {
+        Self {
+            reporter: EncodablePublicUser::from(reporter, avatar_proxy),
+            checksum: report.checksum,
+            matches: report.matches,
+            details: report.details,
+            created_at: report.created_at,
+        }
+    }
+}
+
+/// A version that matches a bulk yank request of an admin.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableBulkYankVersion {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableSpamFlag {
+    pub version_id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub score: i32,
+    pub reasons: Vec<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub quarantined: bool,
+}
+
+impl EncodableSpamFlag {
+    pub fn from(
+        flag: SpamFlag,
+        krate: String,
+        version: String,
+        description: Option<String>,
+        quarantined: bool,
+    ) -> Self {
+        Self {
+            version_id: flag.version_id,
+            krate,
+            version,
+            description,
+            score: flag.score,
+            reasons: flag.reasons,
+            created_at: flag.created_at,
+            quarantined,
+        }
+    }
+}
+
+/// A user that was throttled by the rate limiter, as listed in the admin
+/// report of the most throttled users.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableThrottledUser {
+    pub user: EncodablePublicUser,
+    /// The total number of rejected requests within the report window.
+    pub rejections: i64,
+    #[serde(with = "rfc3339")]
+    pub last_rejected_at: NaiveDateTime,
+    pub actions: Vec<EncodableThrottledAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableThrottledAction {
+    pub action: LimitedAction,
+    pub rejections: i64,
+    #[serde(with = "rfc3339")]
+    pub last_rejected_at: NaiveDateTime,
+    /// The burst of the active override for the user, if there is one.
+    pub burst_override: Option<i32>,
+}
+
+/// A request of a user for a higher burst of a rate limited action.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableRateLimitOverrideRequest {
+    pub id: i32,
+    pub user: EncodablePublicUser,
+    pub action: LimitedAction,
+    pub burst: i32,
+    pub justification: String,
+    /// Either `pending`, `approved` or `rejected`.
+    pub status: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub reviewed_at: Option<NaiveDateTime>,
+    /// The expiry of the granted override, if the request was approved.
+    #[serde(with = "rfc3339::option")]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl EncodableRateLimitOverrideRequest {
+    pub fn from(request: RateLimitOverrideRequest, user: User, avatar_proxy: bool) -> Self {
+        let status = match request.approved {
+            None => "pending",
+            Some(true) => "approved",
+            Some(false) => "rejected",
+        };
+
+        Self {
+            id: request.id,
+            user: EncodablePublicUser::from(user, avatar_proxy),
+            action: request.action,
+            burst: request.burst,
+            justification: request.justification,
+            status: status.to_string(),
+            created_at: request.created_at,
+            reviewed_at: request.reviewed_at,
+            expires_at: request.expires_at,
+        }
+    }
+}
+
+/// A deprecated API surface, as listed in the admin report of the
+/// deprecated surfaces that are still in use.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDeprecation {
+    pub surface: String,
+    #[serde(with = "rfc3339")]
+    pub deprecated_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub sunset_at: Option<NaiveDateTime>,
+    pub link: String,
+    /// The total number of recorded requests to the surface.
+    pub requests: i64,
+    pub usage: Vec<EncodableDeprecatedApiUsage>,
+}
+
+/// The requests to a deprecated API surface that were authenticated with a
+/// specific API token.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDeprecatedApiUsage {
+    pub user: EncodablePublicUser,
+    pub token_id: i32,
+    pub token_name: String,
+    pub requests: i64,
+    #[serde(with = "rfc3339")]
+    pub first_used_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub last_used_at: NaiveDateTime,
+}
+
+/// The size of the default version of a crate, as listed in the admin report
+/// of the largest crates.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateSize {
+    pub name: String,
+    pub version: String,
+    pub crate_size: Option<i32>,
+    pub uncompressed_size: Option<i64>,
+    #[serde(with = "rfc3339")]
+    pub published_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDatabaseDump {
+    pub id: i32,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub tar: EncodableDatabaseDumpFile,
+    pub zip: EncodableDatabaseDumpFile,
+    /// The URL of the `manifest.json` file, which contains the sizes and the
+    /// checksums of the archives.
+    pub manifest_url: String,
+    /// The ID of the last registry event at the time the dump was started.
+    pub last_event_id: i64,
+    /// The URL of the `deltas.json` file, which lists the daily deltas that
+    /// can be applied to the dump, or `None` if there are no deltas yet.
+    pub deltas_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDatabaseDumpFile {
+    pub url: String,
+    pub size: i64,
+    pub sha256: String,
+}
+
+impl EncodableDatabaseDump {
+    pub fn from(dump: DatabaseDump, has_deltas: bool, storage: &Storage) -> Self {
+        let location =
+            |file_name: &str| storage.db_dump_location(&format!("{}/{file_name}", dump.directory));
+
+        Self {
+            id: dump.id,
+            created_at: dump.created_at,
+            tar: EncodableDatabaseDumpFile {
+                url: location(TAR_FILE_NAME),
+                size: dump.tar_size,
+                sha256: dump.tar_sha256,
+            },
+            zip: EncodableDatabaseDumpFile {
+                url: location(ZIP_FILE_NAME),
+                size: dump.zip_size,
+                sha256: dump.zip_sha256,
+            },
+            manifest_url: location(MANIFEST_FILE_NAME),
+            last_event_id: dump.last_event_id,
+            deltas_url: has_deltas.then(|| location(DELTAS_MANIFEST_FILE_NAME)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableKeyword {
     pub id: String,
@@ -211,11 +649,33 @@ pub struct EncodableCrate {
     // NOTE: Used by shields.io, altering `max_version` requires a PR with shields.io
     pub max_version: String,
     pub newest_version: String, // Most recently updated version, which may not be max
-    pub max_stable_version: Option<String>,
+    pub max_stable_version: Option<String>, // Highest version without pre-release identifiers
+    /// The highest stable version of the crate in semver order, or `None` if
+    /// the crate has no stable versions. Unlike `default_version`, this never
+    /// falls back to a pre-release.
+    pub highest_stable: Option<String>,
+    pub default_version: Option<String>, // Highest stable version, falling back to pre-releases
     pub description: Option<String>,
     pub homepage: Option<String>,
     pub documentation: Option<String>,
     pub repository: Option<String>,
+    /// Whether an owner proved control of the `repository` of the crate.
+    pub verified_repository: bool,
+    /// The date the owners flagged the crate as looking for new maintainers.
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub maintenance_wanted_at: Option<NaiveDateTime>,
+    /// The name of the crate that supersedes this crate. Only set by the
+    /// `GET /crates/:crate_id` endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+    /// The names of the crates that are superseded by this crate. Only set
+    /// by the `GET /crates/:crate_id` endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supersedes: Option<Vec<String>>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
 }
@@ -225,6 +685,7 @@ impl EncodableCrate {
     pub fn from(
         krate: Crate,
         top_versions: Option<&TopVersions>,
+        default_version: Option<&str>,
         versions: Option<Vec<i32>>,
         keywords: Option<&[Keyword]>,
         categories: Option<&[Category]>,
@@ -241,6 +702,8 @@ impl EncodableCrate {
             homepage,
             documentation,
             repository,
+            repository_verified_at,
+            maintenance_wanted_at,
             ..
         } = krate;
         let versions_link = match versions {
@@ -253,6 +716,7 @@ impl EncodableCrate {
         let homepage = remove_blocked_urls(homepage);
         let documentation = remove_blocked_urls(documentation);
         let repository = remove_blocked_urls(repository);
+        let verified_repository = repository.is_some() && repository_verified_at.is_some();
 
         let max_version = top_versions
             .and_then(|v| v.highest.as_ref())
@@ -268,6 +732,8 @@ impl EncodableCrate {
             .and_then(|v| v.highest_stable.as_ref())
             .map(|v| v.to_string());
 
+        let default_version = default_version.map(ToString::to_string);
+
         // the total number of downloads is eventually consistent, but can lag
         // behind the number of "recent downloads". to hide this inconsistency
         // we will use the "recent downloads" as "total downloads" in case it is
@@ -291,12 +757,18 @@ impl EncodableCrate {
             badges,
             max_version,
             newest_version,
+            highest_stable: max_stable_version.clone(),
             max_stable_version,
+            default_version,
             documentation,
             homepage,
             exact_match,
             description,
             repository,
+            verified_repository,
+            maintenance_wanted_at,
+            superseded_by: None,
+            supersedes: None,
             links: EncodableCrateLinks {
                 version_downloads: format!("/api/v1/crates/{name}/downloads"),
                 versions: versions_link,
@@ -311,6 +783,7 @@ impl EncodableCrate {
     pub fn from_minimal(
         krate: Crate,
         top_versions: Option<&TopVersions>,
+        default_version: Option<&str>,
         badges: Option<Vec<()>>,
         exact_match: bool,
         downloads: i64,
@@ -319,6 +792,7 @@ impl EncodableCrate {
         Self::from(
             krate,
             top_versions,
+            default_version,
             None,
             None,
             None,
@@ -330,6 +804,173 @@ impl EncodableCrate {
     }
 }
 
+/// A relation between a superseded crate and the crate that supersedes it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateSuccession {
+    pub predecessor: String,
+    pub successor: String,
+    /// Whether the owners of both crates confirmed the relation.
+    pub confirmed: bool,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableCrateSuccession {
+    pub fn from(succession: CrateSuccession, predecessor: String, successor: String) -> Self {
+        Self {
+            predecessor,
+            successor,
+            confirmed: succession.is_confirmed(),
+            created_at: succession.created_at,
+        }
+    }
+}
+
+/// A crate that has been frozen by an admin pending an investigation.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateFreeze {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub reason: String,
+    pub frozen_by: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableCrateFreeze {
+    pub fn from(freeze: CrateFreeze, krate: String, frozen_by: User, avatar_proxy: bool) -> Self {
+        Self {
+            krate,
+            reason: freeze.reason,
+            frozen_by: EncodablePublicUser::from(frozen_by, avatar_proxy),
+            created_at: freeze.created_at,
+        }
+    }
+}
+
+/// An entry of the audit log of admin actions.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAdminAuditEntry {
+    pub id: i32,
+    /// The admin that performed the action, or `None` if the action was
+    /// performed via the admin CLI.
+    pub actor: Option<EncodablePublicUser>,
+    pub action: AdminAction,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub details: serde_json::Value,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableAdminAuditEntry {
+    pub fn from(entry: AdminAuditEntry, actor: Option<User>, avatar_proxy: bool) -> Self {
+        Self {
+            id: entry.id,
+            actor: actor.map(|actor| EncodablePublicUser::from(actor, avatar_proxy)),
+            action: entry.action,
+            krate: entry.crate_name,
+            details: entry.details,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+/// A crate that has been designated as critical by an admin.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCriticalCrate {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub designated_by: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableCriticalCrate {
+    pub fn from(
+        critical: CriticalCrate,
+        krate: String,
+        designated_by: User,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            krate,
+            designated_by: EncodablePublicUser::from(designated_by, avatar_proxy),
+            created_at: critical.created_at,
+        }
+    }
+}
+
+/// A dependency that is exempted from the registry dependency policy.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyPolicyException {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub reason: String,
+    pub created_by: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableDependencyPolicyException {
+    pub fn from(
+        exception: DependencyPolicyException,
+        krate: String,
+        created_by: User,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            krate,
+            reason: exception.reason,
+            created_by: EncodablePublicUser::from(created_by, avatar_proxy),
+            created_at: exception.created_at,
+        }
+    }
+}
+
+/// A request to re-link an account to a new GitHub identity.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAccountRecovery {
+    pub id: i32,
+    /// The account that is recovered.
+    pub user: EncodablePublicUser,
+    /// The account of the new GitHub identity that requested the recovery.
+    pub requested_by: EncodablePublicUser,
+    #[serde(with = "rfc3339::option")]
+    pub email_confirmed_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub approved_at: Option<NaiveDateTime>,
+    /// The date and time from which on the recovery can be completed, once
+    /// it has been approved.
+    #[serde(with = "rfc3339::option")]
+    pub completable_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub completed_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableAccountRecovery {
+    pub fn from(
+        recovery: AccountRecovery,
+        user: User,
+        requested_by: User,
+        waiting_period: Duration,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            id: recovery.id,
+            user: EncodablePublicUser::from(user, avatar_proxy),
+            requested_by: EncodablePublicUser::from(requested_by, avatar_proxy),
+            email_confirmed_at: recovery.email_confirmed_at,
+            approved_at: recovery.approved_at,
+            completable_at: recovery.completable_at(waiting_period),
+            completed_at: recovery.completed_at,
+            created_at: recovery.created_at,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableCrateLinks {
     pub version_downloads: String,
@@ -340,6 +981,199 @@ pub struct EncodableCrateLinks {
     pub reverse_dependencies: String,
 }
 
+/// The settings of a crate, as returned by the
+/// `/api/v1/crates/:crate_id/settings` endpoints.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateSettings {
+    pub prerelease_retention_days: Option<i32>,
+    /// Whether the crate is looking for new maintainers.
+    pub maintenance_wanted: bool,
+    /// Overrides of the publish limits, which can only be changed by admins.
+    pub limits: EncodableCrateLimits,
+    pub policy: EncodableCratePolicy,
+    /// Dependencies that new versions of the crate must not use.
+    pub denied_dependencies: Vec<DeniedDependency>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateLimits {
+    pub max_upload_size: Option<i32>,
+    pub max_features: Option<i16>,
+    pub max_dependencies: Option<i16>,
+    pub max_feature_values: Option<i16>,
+}
+
+/// The policy documents of a crate, as returned by the
+/// `GET /api/v1/crates/:crate_id/policy` endpoint.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCratePolicy {
+    pub security_contact: Option<String>,
+    pub disclosure_policy: Option<String>,
+    pub msrv_policy: Option<String>,
+}
+
+impl From<CrateSettings> for EncodableCratePolicy {
+    fn from(settings: CrateSettings) -> Self {
+        Self {
+            security_contact: settings.security_contact,
+            disclosure_policy: settings.disclosure_policy,
+            msrv_policy: settings.msrv_policy,
+        }
+    }
+}
+
+impl EncodableCrateSettings {
+    pub fn from(settings: CrateSettings, krate: &Crate) -> Self {
+        Self {
+            prerelease_retention_days: settings.prerelease_retention_days,
+            maintenance_wanted: krate.maintenance_wanted_at.is_some(),
+            limits: EncodableCrateLimits {
+                max_upload_size: krate.max_upload_size,
+                max_features: krate.max_features,
+                max_dependencies: krate.max_dependencies,
+                max_feature_values: krate.max_feature_values,
+            },
+            denied_dependencies: settings.denied_dependencies.0.clone(),
+            policy: settings.into(),
+        }
+    }
+}
+
+/// The health score of a crate, as returned by the
+/// `GET /api/v1/crates/:crate_id/health` endpoint.
+///
+/// Every component contains the metrics that its score is based on, so that
+/// consumers can see how the overall score was computed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateHealth {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub score: i16,
+    #[serde(with = "rfc3339")]
+    pub computed_at: NaiveDateTime,
+    pub components: EncodableHealthComponents,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableHealthComponents {
+    pub releases: EncodableReleasesHealth,
+    pub maintainers: EncodableMaintainersHealth,
+    pub advisories: EncodableAdvisoriesHealth,
+    pub docs: EncodableDocsHealth,
+    pub yanked_versions: EncodableYankedVersionsHealth,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReleasesHealth {
+    #[serde(flatten)]
+    pub component: HealthComponent,
+    #[serde(with = "rfc3339")]
+    pub last_release_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableMaintainersHealth {
+    #[serde(flatten)]
+    pub component: HealthComponent,
+    pub maintainers: i32,
+    pub active_maintainers: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAdvisoriesHealth {
+    #[serde(flatten)]
+    pub component: HealthComponent,
+    pub quarantined_versions: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDocsHealth {
+    #[serde(flatten)]
+    pub component: HealthComponent,
+    pub docs_build_status: Option<DocsBuildStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableYankedVersionsHealth {
+    #[serde(flatten)]
+    pub component: HealthComponent,
+    pub versions: i32,
+    pub yanked_versions: i32,
+}
+
+impl EncodableCrateHealth {
+    pub fn from(health: CrateHealth, crate_name: String) -> Self {
+        let components = health.components();
+        let metrics = health.metrics;
+
+        Self {
+            krate: crate_name,
+            score: health.score,
+            computed_at: health.computed_at,
+            components: EncodableHealthComponents {
+                releases: EncodableReleasesHealth {
+                    component: components.releases,
+                    last_release_at: metrics.last_release_at,
+                },
+                maintainers: EncodableMaintainersHealth {
+                    component: components.maintainers,
+                    maintainers: metrics.maintainers,
+                    active_maintainers: metrics.active_maintainers,
+                },
+                advisories: EncodableAdvisoriesHealth {
+                    component: components.advisories,
+                    quarantined_versions: metrics.quarantined_versions,
+                },
+                docs: EncodableDocsHealth {
+                    component: components.docs,
+                    docs_build_status: metrics.docs_build_status,
+                },
+                yanked_versions: EncodableYankedVersionsHealth {
+                    component: components.yanked_versions,
+                    versions: metrics.versions,
+                    yanked_versions: metrics.yanked_versions,
+                },
+            },
+        }
+    }
+}
+
+/// A crate that is related to another crate, as returned by the
+/// `GET /api/v1/crates/:crate_id/related` endpoint.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableRelatedCrate {
+    pub name: String,
+    pub description: Option<String>,
+    pub score: i64,
+}
+
+/// A crate that declares a `links` key, as returned by the
+/// `GET /api/v1/links_keys/:key` endpoint.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLinksKeyCrate {
+    pub name: String,
+    /// The non-yanked versions of the crate that declare the key.
+    pub versions: Vec<String>,
+}
+
+/// A dependency of a version, as returned by the
+/// `GET /api/v1/crates/:crate_id/:version/outdated_deps` endpoint.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableOutdatedDependency {
+    pub crate_id: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub optional: bool,
+    pub target: Option<String>,
+    /// The newest non-yanked version that matches `req`.
+    pub latest_compatible: Option<String>,
+    /// The newest non-yanked version, ignoring pre-releases unless the
+    /// crate has no other versions.
+    pub latest: Option<String>,
+    /// Whether `req` does not match `latest`.
+    pub outdated: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableOwner {
     pub id: i32,
@@ -350,8 +1184,8 @@ pub struct EncodableOwner {
     pub avatar: Option<String>,
 }
 
-impl From<Owner> for EncodableOwner {
-    fn from(owner: Owner) -> Self {
+impl EncodableOwner {
+    pub fn from(owner: Owner, avatar_proxy: bool) -> Self {
         match owner {
             Owner::User(User {
                 id,
@@ -364,7 +1198,7 @@ impl From<Owner> for EncodableOwner {
                 Self {
                     id,
                     login: gh_login,
-                    avatar: gh_avatar,
+                    avatar: user_avatar(id, gh_avatar, avatar_proxy),
                     url: Some(url),
                     name,
                     kind: String::from("user"),
@@ -391,6 +1225,90 @@ impl From<Owner> for EncodableOwner {
     }
 }
 
+/// An entry of the ownership history of a crate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateOwnerAction {
+    pub id: i32,
+    pub action: OwnerAction,
+    pub owner: EncodableOwner,
+    /// The user that added or removed the owner, or `None` if it was the
+    /// crates.io team or is unknown.
+    pub performed_by: Option<EncodablePublicUser>,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+impl EncodableCrateOwnerAction {
+    pub fn from(
+        action: CrateOwnerAction,
+        owner: Owner,
+        performed_by: Option<User>,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            id: action.id,
+            action: action.action,
+            owner: EncodableOwner::from(owner, avatar_proxy),
+            performed_by: performed_by.map(|user| EncodablePublicUser::from(user, avatar_proxy)),
+            time: action.time,
+        }
+    }
+}
+
+/// A heading of a rendered README, which is used by the frontend to render
+/// the table of contents of the README.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReadmeHeading {
+    pub level: u8,
+    pub text: String,
+    /// The fragment that links to the heading, e.g. `#usage`.
+    pub anchor: String,
+}
+
+impl From<Heading> for EncodableReadmeHeading {
+    fn from(heading: Heading) -> Self {
+        Self {
+            level: heading.level,
+            text: heading.text,
+            anchor: heading.anchor,
+        }
+    }
+}
+
+/// A removal of an owner of a critical crate that is waiting for the
+/// approval of two admins or the end of the waiting period.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodablePendingOwnerRemoval {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub owner: EncodableOwner,
+    pub requested_by: EncodablePublicUser,
+    pub approved_by: Option<EncodablePublicUser>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodablePendingOwnerRemoval {
+    pub fn from(
+        removal: PendingOwnerRemoval,
+        krate: String,
+        owner: Owner,
+        requested_by: User,
+        approved_by: Option<User>,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            id: removal.id,
+            krate,
+            owner: EncodableOwner::from(owner, avatar_proxy),
+            requested_by: EncodablePublicUser::from(requested_by, avatar_proxy),
+            approved_by: approved_by.map(|user| EncodablePublicUser::from(user, avatar_proxy)),
+            created_at: removal.created_at,
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 pub struct EncodableTeam {
     pub id: i32,
@@ -421,6 +1339,36 @@ impl From<Team> for EncodableTeam {
     }
 }
 
+/// A crate owned by a team, as returned by the `GET /teams/:team_id/crates`
+/// endpoint.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTeamCrate {
+    pub id: i32,
+    pub name: String,
+    /// The date the team was added as an owner of the crate.
+    #[serde(with = "rfc3339")]
+    pub added_at: NaiveDateTime,
+    /// The user that added the team as an owner of the crate.
+    pub added_by: Option<EncodablePublicUser>,
+}
+
+impl EncodableTeamCrate {
+    pub fn from(
+        id: i32,
+        name: String,
+        added_at: NaiveDateTime,
+        added_by: Option<User>,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            added_at,
+            added_by: added_by.map(|user| EncodablePublicUser::from(user, avatar_proxy)),
+        }
+    }
+}
+
 /// The serialization format for the `ApiToken` model with its token value.
 /// This should only be used when initially creating a new token to minimize
 /// the chance of token leaks.
@@ -441,6 +1389,24 @@ impl From<CreatedApiToken> for EncodableApiTokenWithToken {
     }
 }
 
+/// A token of a team, as returned by the `GET /teams/:team_id/tokens`
+/// endpoint, together with the maintainer that created it.
+#[derive(Serialize, Debug)]
+pub struct EncodableTeamApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub created_by: EncodablePublicUser,
+}
+
+impl EncodableTeamApiToken {
+    pub fn from(token: ApiToken, created_by: User, avatar_proxy: bool) -> Self {
+        Self {
+            token,
+            created_by: EncodablePublicUser::from(created_by, avatar_proxy),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct OwnedCrate {
     pub id: i32,
@@ -454,6 +1420,88 @@ pub struct EncodableMe {
     pub owned_crates: Vec<OwnedCrate>,
 }
 
+/// The API request quota consumption of a user within the current quota
+/// window.
+#[derive(Serialize, Debug)]
+pub struct EncodableApiUsage {
+    #[serde(with = "rfc3339")]
+    pub window_start: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub window_end: NaiveDateTime,
+    pub user: EncodableQuotaUsage,
+    pub tokens: Vec<EncodableApiTokenUsage>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EncodableQuotaUsage {
+    pub requests: i32,
+    pub limit: i32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EncodableApiTokenUsage {
+    pub id: i32,
+    pub name: String,
+    pub requests: i32,
+    pub limit: i32,
+}
+
+/// One of the email addresses of the current user.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableEmail {
+    pub id: i32,
+    pub email: String,
+    pub verified: bool,
+    pub verification_sent: bool,
+    pub primary: bool,
+    /// Whether the email provider reported the address as undeliverable. It
+    /// has to be verified again before it receives notifications.
+    pub undeliverable: bool,
+}
+
+impl From<Email> for EncodableEmail {
+    fn from(email: Email) -> Self {
+        Self {
+            id: email.id,
+            email: email.email,
+            verified: email.verified,
+            verification_sent: email.verified || email.token_generated_at.is_some(),
+            primary: email.is_primary,
+            undeliverable: email.undeliverable_at.is_some(),
+        }
+    }
+}
+
+/// Selects the address that receives a class of notifications.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableEmailPreference {
+    pub notification_class: NotificationClass,
+    /// `None` means that the primary address receives the notifications.
+    pub email_id: Option<i32>,
+}
+
+/// A subscription of the user to new versions of a crate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencySubscription {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version_req: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableDependencySubscription {
+    pub fn from(subscription: DependencySubscription, crate_name: String) -> Self {
+        Self {
+            id: subscription.id,
+            krate: crate_name,
+            version_req: subscription.version_req,
+            created_at: subscription.created_at,
+        }
+    }
+}
+
 /// The serialization format for the `User` model.
 /// Same as public user, except for addition of
 /// email field
@@ -463,11 +1511,15 @@ pub struct EncodablePrivateUser {
     pub login: String,
     pub email_verified: bool,
     pub email_verification_sent: bool,
+    /// Whether the email provider reported the primary address as
+    /// undeliverable, in which case the user is asked to verify a new one.
+    pub email_undeliverable: bool,
     pub name: Option<String>,
     pub email: Option<String>,
     pub avatar: Option<String>,
     pub url: Option<String>,
     pub is_admin: bool,
+    pub publish_confirmation_required: bool,
 }
 
 impl EncodablePrivateUser {
@@ -477,6 +1529,8 @@ impl EncodablePrivateUser {
         email: Option<String>,
         email_verified: bool,
         email_verification_sent: bool,
+        email_undeliverable: bool,
+        avatar_proxy: bool,
     ) -> Self {
         let User {
             id,
@@ -484,6 +1538,7 @@ impl EncodablePrivateUser {
             gh_login,
             gh_avatar,
             is_admin,
+            publish_confirmation_required,
             ..
         } = user;
         let url = format!("https://github.com/{gh_login}");
@@ -493,15 +1548,28 @@ impl EncodablePrivateUser {
             email,
             email_verified,
             email_verification_sent,
-            avatar: gh_avatar,
+            email_undeliverable,
+            avatar: user_avatar(id, gh_avatar, avatar_proxy),
             login: gh_login,
             name,
             url: Some(url),
             is_admin,
+            publish_confirmation_required,
         }
     }
 }
 
+/// Returns the URL of the avatar of a user, which points at the
+/// `/api/v1/users/:user_id/avatar` endpoint if the avatar proxy is enabled,
+/// so that the avatar is not loaded from GitHub directly.
+fn user_avatar(user_id: i32, gh_avatar: Option<String>, avatar_proxy: bool) -> Option<String> {
+    if avatar_proxy {
+        Some(format!("/api/v1/users/{user_id}/avatar"))
+    } else {
+        gh_avatar
+    }
+}
+
 /// The serialization format for the `User` model.
 /// Same as private user, except no email field
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
@@ -513,9 +1581,9 @@ pub struct EncodablePublicUser {
     pub url: String,
 }
 
-/// Converts a `User` model into an `EncodablePublicUser` for JSON serialization.
-impl From<User> for EncodablePublicUser {
-    fn from(user: User) -> Self {
+impl EncodablePublicUser {
+    /// Converts a `User` model into an `EncodablePublicUser` for JSON serialization.
+    pub fn from(user: User, avatar_proxy: bool) -> Self {
         let User {
             id,
             name,
@@ -526,7 +1594,7 @@ impl From<User> for EncodablePublicUser {
         let url = format!("https://github.com/{gh_login}");
         EncodablePublicUser {
             id,
-            avatar: gh_avatar,
+            avatar: user_avatar(id, gh_avatar, avatar_proxy),
             login: gh_login,
             name,
             url,
@@ -542,6 +1610,40 @@ pub struct EncodableAuditAction {
     pub time: NaiveDateTime,
 }
 
+/// An entry of the yank history of a version, as returned by the
+/// `GET /crates/:crate_id/:version/yank_history` endpoint.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableYankHistoryEntry {
+    pub action: String,
+    pub reason: Option<String>,
+    /// The user that performed the action, or `None` if it was performed by
+    /// crates.io itself, e.g. by the pre-release retention policy.
+    pub user: Option<EncodablePublicUser>,
+    /// The login of the team, if the action was performed with a team token.
+    /// `user` is the team maintainer that created the token in that case.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+impl EncodableYankHistoryEntry {
+    pub fn from(
+        action: VersionOwnerAction,
+        user: Option<User>,
+        team: Option<String>,
+        avatar_proxy: bool,
+    ) -> Self {
+        Self {
+            action: action.action.into(),
+            reason: action.reason,
+            user: user.map(|user| EncodablePublicUser::from(user, avatar_proxy)),
+            team,
+            time: action.time,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersion {
     pub id: i32,
@@ -563,12 +1665,32 @@ pub struct EncodableVersion {
     pub license: Option<String>,
     pub links: EncodableVersionLinks,
     pub crate_size: Option<i32>,
+    /// The total size of all files in the crate file after decompression.
+    pub uncompressed_size: Option<i64>,
     pub published_by: Option<EncodablePublicUser>,
     pub audit_actions: Vec<EncodableAuditAction>,
     pub checksum: String,
     pub rust_version: Option<String>,
     pub has_lib: Option<bool>,
     pub bin_names: Option<Vec<Option<String>>>,
+    pub has_build_script: Option<bool>,
+    pub is_proc_macro: Option<bool>,
+    pub docs_build_status: Option<DocsBuildStatus>,
+    /// The release channel (e.g. `beta`) that the version was published to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// The SHA256 checksum of the normalized tarball, if the publisher
+    /// reported it. See the `/reproducibility` endpoint for attestations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalized_checksum: Option<String>,
+    /// The CI run that published the version, if the publisher reported it.
+    /// Only included in the `GET /crates/:crate_id/:version` response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ci: Option<EncodableCiAnnotation>,
+    /// The warning that the owners attached to the version, if it is flagged.
+    /// Flagged versions are still used by dependency resolution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag: Option<EncodableVersionFlag>,
 }
 
 impl EncodableVersion {
@@ -577,6 +1699,7 @@ impl EncodableVersion {
         crate_name: &str,
         published_by: Option<User>,
         audit_actions: Vec<(VersionOwnerAction, User)>,
+        avatar_proxy: bool,
     ) -> Self {
         let Version {
             id,
@@ -593,9 +1716,24 @@ impl EncodableVersion {
             rust_version,
             has_lib,
             bin_names,
+            has_build_script,
+            is_proc_macro,
+            docs_build_status,
+            uncompressed_size,
+            channel,
+            normalized_checksum,
+            flag_message,
+            flagged_at,
             ..
         } = version;
 
+        let flag = flag_message
+            .zip(flagged_at)
+            .map(|(message, flagged_at)| EncodableVersionFlag {
+                message,
+                flagged_at,
+            });
+
         let links = EncodableVersionLinks {
             dependencies: format!("/api/v1/crates/{crate_name}/{num}/dependencies"),
             version_downloads: format!("/api/v1/crates/{crate_name}/{num}/downloads"),
@@ -617,19 +1755,50 @@ impl EncodableVersion {
             license,
             links,
             crate_size,
+            uncompressed_size,
             checksum,
             rust_version,
             has_lib,
             bin_names,
-            published_by: published_by.map(User::into),
+            has_build_script,
+            is_proc_macro,
+            docs_build_status,
+            channel,
+            normalized_checksum,
+            published_by: published_by.map(|user| EncodablePublicUser::from(user, avatar_proxy)),
             audit_actions: audit_actions
                 .into_iter()
                 .map(|(audit_action, user)| EncodableAuditAction {
                     action: audit_action.action.into(),
-                    user: user.into(),
+                    user: EncodablePublicUser::from(user, avatar_proxy),
                     time: audit_action.time,
                 })
                 .collect(),
+            ci: None,
+            flag,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionFlag {
+    pub message: String,
+    #[serde(with = "rfc3339")]
+    pub flagged_at: NaiveDateTime,
+}
+
+/// The self-reported information about the CI run that published a version.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCiAnnotation {
+    pub system: String,
+    pub run_url: Option<String>,
+}
+
+impl From<VersionCiAnnotation> for EncodableCiAnnotation {
+    fn from(annotation: VersionCiAnnotation) -> Self {
+        Self {
+            system: annotation.ci_system,
+            run_url: annotation.run_url,
         }
     }
 }
@@ -648,11 +1817,32 @@ pub struct GoodCrate {
     pub warnings: PublishWarnings,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct PublishWarnings {
     pub invalid_categories: Vec<String>,
     pub invalid_badges: Vec<String>,
     pub other: Vec<String>,
+    /// Machine-readable versions of the warnings above, including hints on
+    /// how to resolve them. cargo only reads the plain lists above.
+    #[serde(default)]
+    pub details: Vec<PublishWarning>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PublishWarning {
+    pub code: PublishWarningCode,
+    pub message: String,
+    pub hint: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishWarningCode {
+    InvalidCategory,
+    InvalidBadge,
+    MissingDescription,
+    MissingLicenseFile,
+    LinksConflict,
 }
 
 #[cfg(test)]
@@ -744,10 +1934,16 @@ mod tests {
                 authors: "".to_string(),
             },
             crate_size: Some(1234),
+            uncompressed_size: Some(5678),
             checksum: String::new(),
             rust_version: None,
             has_lib: None,
             bin_names: None,
+            has_build_script: None,
+            is_proc_macro: None,
+            docs_build_status: None,
+            channel: None,
+            normalized_checksum: None,
             published_by: None,
             audit_actions: vec![EncodableAuditAction {
                 action: "publish".to_string(),
@@ -763,6 +1959,8 @@ mod tests {
                     .and_hms_opt(14, 23, 12)
                     .unwrap(),
             }],
+            ci: None,
+            flag: None,
         };
         let json = serde_json::to_string(&ver).unwrap();
         assert_some!(json
@@ -796,10 +1994,16 @@ mod tests {
             max_version: "".to_string(),
             newest_version: "".to_string(),
             max_stable_version: None,
+            highest_stable: None,
+            default_version: None,
             description: None,
             homepage: None,
             documentation: None,
             repository: None,
+            verified_repository: false,
+            maintenance_wanted_at: None,
+            superseded_by: None,
+            supersedes: None,
             links: EncodableCrateLinks {
                 version_downloads: "".to_string(),
                 versions: None,
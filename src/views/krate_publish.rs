@@ -4,6 +4,7 @@
 //! integration tests.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use crate::models::DependencyKind;
 
@@ -13,6 +14,23 @@ pub struct PublishMetadata {
     pub vers: String,
     pub readme: Option<String>,
     pub readme_file: Option<String>,
+    /// The `[badges]` section of the manifest, as sent by cargo. Badges are
+    /// not displayed on crates.io anymore.
+    #[serde(default)]
+    pub badges: BTreeMap<String, BTreeMap<String, String>>,
+    /// The release channel (e.g. `beta`) to publish the version to. This is
+    /// not sent by cargo itself, but by tooling that publishes release trains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// The SHA256 checksum of the normalized tarball (fixed mtimes, sorted
+    /// entries), which allows independent rebuilders to attest that the
+    /// version is reproducible. This is not sent by cargo itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalized_cksum: Option<String>,
+    /// The id of a tarball that was uploaded via `POST /api/v1/staging`, in
+    /// which case the publish request itself does not contain a tarball.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staging_id: Option<String>,
 }
 
 #[derive(Debug)]
@@ -1,8 +1,14 @@
+use crate::advisories::AdvisorySource;
+use crate::antivirus::VirusScanner;
 use crate::cloudfront::CloudFront;
 use crate::fastly::Fastly;
+use crate::metrics::JobMetrics;
+use crate::mirrors::MirrorProbe;
+use crate::readme_images::ImageFetcher;
 use crate::storage::Storage;
 use crate::team_repo::TeamRepo;
 use crate::typosquat;
+use crate::upstream::UpstreamRegistry;
 use crate::util::diesel::Conn;
 use crate::Emails;
 use anyhow::Context;
@@ -34,6 +40,18 @@ pub struct Environment {
     pub deadpool: Pool<AsyncPgConnection>,
     pub emails: Emails,
     pub team_repo: Box<dyn TeamRepo + Send + Sync>,
+    #[builder(default)]
+    pub virus_scanner: Option<Box<dyn VirusScanner + Send + Sync>>,
+    #[builder(default)]
+    pub image_fetcher: Option<Box<dyn ImageFetcher + Send + Sync>>,
+    #[builder(default)]
+    pub mirror_probe: Option<Box<dyn MirrorProbe + Send + Sync>>,
+    #[builder(default)]
+    pub advisory_source: Option<Box<dyn AdvisorySource + Send + Sync>>,
+    #[builder(default)]
+    pub upstream: Option<Arc<dyn UpstreamRegistry + Send + Sync>>,
+    #[builder(default)]
+    pub metrics: JobMetrics,
 
     /// A lazily initialised cache of the most popular crates ready to use in typosquatting checks.
     #[builder(default, setter(skip))]
@@ -0,0 +1,96 @@
+use crate::avatars::{MAX_AVATAR_SIZE, REFRESH_INTERVAL};
+use crate::models::{User, UserAvatar};
+use crate::readme_images::{is_public_url, FetchedImage};
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::Utc;
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use url::Url;
+
+/// Fetches the current avatar of a user, and uploads it to the storage
+/// bucket, so that it can be served by the avatar proxy.
+///
+/// Avatars are stored by the digest of their content, so that browsers and
+/// the CDN can cache them indefinitely. Avatars that can not be fetched, or
+/// that are not valid images, are recorded as failures, and the previously
+/// cached copy is served until the next refresh.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FetchAvatar {
+    user_id: i32,
+}
+
+impl FetchAvatar {
+    pub fn new(user_id: i32) -> Self {
+        Self { user_id }
+    }
+}
+
+impl BackgroundJob for FetchAvatar {
+    const JOB_NAME: &'static str = "fetch_avatar";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(user_id = self.user_id), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let user_id = self.user_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let Some(fetcher) = env.image_fetcher.as_deref() else {
+                warn!("Skipping avatar, since no image fetcher is configured");
+                return Ok(());
+            };
+
+            let user = User::find(conn, user_id)?;
+            let Some(url) = user.gh_avatar else {
+                info!("Skipping avatar, since the user has no avatar");
+                return Ok(());
+            };
+
+            // The refresh may have been enqueued by several instances.
+            let cutoff = (Utc::now() - REFRESH_INTERVAL).naive_utc();
+            let avatar = UserAvatar::find(conn, user_id)?;
+            if avatar.is_some_and(|avatar| !avatar.is_stale(&url, cutoff)) {
+                info!("Skipping avatar, since it was refreshed recently");
+                return Ok(());
+            }
+
+            let fetched = if Url::parse(&url).is_ok_and(|url| is_public_url(&url)) {
+                let future = fetcher.fetch(&url, MAX_AVATAR_SIZE);
+                Handle::current()
+                    .block_on(future)
+                    .and_then(|fetched| fetched.validate(MAX_AVATAR_SIZE).map(|_| fetched))
+            } else {
+                Err(anyhow!("the avatar URL is not public"))
+            };
+
+            let FetchedImage {
+                content_type,
+                bytes,
+            } = match fetched {
+                Ok(fetched) => fetched,
+                Err(error) => {
+                    info!(%url, "Failed to fetch avatar: {error}");
+                    UserAvatar::record_failure(conn, user_id, &url)?;
+                    return Ok(());
+                }
+            };
+
+            let digest = hex::encode(Sha256::digest(&bytes));
+            let future = env.storage.upload_avatar(&digest, &content_type, bytes);
+            Handle::current().block_on(future)?;
+
+            UserAvatar::record_fetched(conn, user_id, &url, &digest, &content_type)?;
+
+            Ok(())
+        })
+        .await
+    }
+}
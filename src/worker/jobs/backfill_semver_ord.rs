@@ -0,0 +1,74 @@
+use crate::schema::versions;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Jsonb, Nullable};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// The number of versions that are updated by a single job.
+const BATCH_SIZE: i64 = 1000;
+
+/// Calculates the `semver_ord` column of versions that were published before
+/// the column was added.
+///
+/// Each job handles a single batch of versions, ordered by ID, and then
+/// enqueues another job for the next batch, so that the rows are only locked
+/// briefly. Versions that can't be parsed keep a `NULL` value, which is why
+/// the batches are selected by ID instead of by the `NULL` values.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct BackfillSemverOrd {
+    after_version_id: i32,
+}
+
+impl BackfillSemverOrd {
+    /// Backfills the versions with an ID larger than `after_version_id`.
+    pub fn after(after_version_id: i32) -> Self {
+        Self { after_version_id }
+    }
+}
+
+impl BackgroundJob for BackfillSemverOrd {
+    const JOB_NAME: &'static str = "backfill_semver_ord";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let after_version_id = self.after_version_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let ids: Vec<i32> = versions::table
+                .filter(versions::id.gt(after_version_id))
+                .filter(versions::semver_ord.is_null())
+                .order(versions::id.asc())
+                .limit(BATCH_SIZE)
+                .select(versions::id)
+                .load(conn)?;
+
+            let Some(&last_version_id) = ids.last() else {
+                info!("Finished backfilling the `semver_ord` column");
+                return Ok(());
+            };
+
+            let updated = diesel::update(versions::table)
+                .filter(versions::id.eq_any(&ids))
+                .set(versions::semver_ord.eq(sql::<Nullable<Jsonb>>("semver_ord(versions.num)")))
+                .execute(conn)?;
+
+            info!("Backfilled the `semver_ord` column of {updated} versions (up to version ID {last_version_id})");
+
+            if ids.len() as i64 == BATCH_SIZE {
+                BackfillSemverOrd::after(last_version_id).enqueue(conn)?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
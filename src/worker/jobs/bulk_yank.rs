@@ -0,0 +1,179 @@
+use crate::email::{Email, Notification};
+use crate::models::{set_yanked, NotificationClass, OwnerKind, YankActor};
+use crate::schema::{crate_owners, crates, users, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use crate::Emails;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The reason that is recorded in the yank history of the versions.
+const BULK_YANK_REASON: &str = "Published by a compromised account or API token";
+
+/// Yanks a set of versions during the incident response to a compromised
+/// account or API token, see the `PUT /api/private/bulk_yanks` endpoint.
+///
+/// The remaining owners of the affected crates, i.e. everyone except the
+/// compromised user, are notified via email about the yanked versions.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BulkYankVersions {
+    version_ids: Vec<i32>,
+    compromised_user_id: Option<i32>,
+    /// The admin that requested the bulk yank, who is recorded as the user
+    /// that yanked the versions.
+    #[serde(default)]
+    admin_id: Option<i32>,
+}
+
+impl BulkYankVersions {
+    pub fn new(version_ids: Vec<i32>, compromised_user_id: Option<i32>, admin_id: i32) -> Self {
+        Self {
+            version_ids,
+            compromised_user_id,
+            admin_id: Some(admin_id),
+        }
+    }
+}
+
+impl BackgroundJob for BulkYankVersions {
+    const JOB_NAME: &'static str = "bulk_yank_versions";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let version_ids = self.version_ids.clone();
+        let compromised_user_id = self.compromised_user_id;
+        let admin_id = self.admin_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            yank_versions(
+                &env.emails,
+                conn,
+                &version_ids,
+                compromised_user_id,
+                admin_id,
+            )
+        })
+        .await
+    }
+}
+
+fn yank_versions(
+    emails: &Emails,
+    conn: &mut impl Conn,
+    version_ids: &[i32],
+    compromised_user_id: Option<i32>,
+    admin_id: Option<i32>,
+) -> anyhow::Result<()> {
+    let versions: Vec<(i32, String, i32)> = versions::table
+        .inner_join(crates::table)
+        .filter(versions::id.eq_any(version_ids))
+        .filter(versions::yanked.eq(false))
+        .select((crates::id, crates::name, versions::id))
+        .load(conn)?;
+
+    let mut versions_by_crate: BTreeMap<(i32, String), Vec<i32>> = BTreeMap::new();
+    for (crate_id, crate_name, version_id) in versions {
+        versions_by_crate
+            .entry((crate_id, crate_name))
+            .or_default()
+            .push(version_id);
+    }
+
+    let actor = match admin_id {
+        Some(user_id) => YankActor::User {
+            user_id,
+            api_token_id: None,
+        },
+        None => YankActor::System,
+    };
+
+    for ((crate_id, crate_name), version_ids) in versions_by_crate {
+        let reason = Some(BULK_YANK_REASON);
+        let nums = set_yanked(
+            conn,
+            crate_id,
+            &crate_name,
+            &version_ids,
+            true,
+            actor,
+            reason,
+        )?;
+        if nums.is_empty() {
+            continue;
+        }
+
+        warn!("Yanked {} versions of {crate_name}: {nums:?}", nums.len());
+
+        let owners: Vec<(i32, String)> = crate_owners::table
+            .inner_join(users::table)
+            .filter(crate_owners::crate_id.eq(crate_id))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .select((users::id, users::gh_login))
+            .load(conn)?;
+
+        let remaining_owners = owners
+            .into_iter()
+            .filter(|(user_id, _)| Some(*user_id) != compromised_user_id);
+
+        for (user_id, user_name) in remaining_owners {
+            let email = BulkYankEmail {
+                user_name: &user_name,
+                crate_name: &crate_name,
+                versions: &nums,
+            };
+
+            if let Err(error) = emails.send_notification(user_id, email, conn) {
+                warn!(?error, "Failed to send bulk yank email to {user_name}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct BulkYankEmail<'a> {
+    user_name: &'a str,
+    crate_name: &'a str,
+    versions: &'a [String],
+}
+
+impl Email for BulkYankEmail<'_> {
+    const SUBJECT: &'static str = "Versions of your crate were yanked by the crates.io team";
+
+    fn body(&self) -> String {
+        let versions = self
+            .versions
+            .iter()
+            .map(|num| format!("- {num}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!(
+            "Hello {user_name}!
+
+The crates.io team yanked the following versions of your crate {crate_name}, \
+since they were published by an account or API token that is suspected to be \
+compromised:
+
+{versions}
+
+Please review the changes in these versions before unyanking any of them. If \
+you have any questions, please contact help@crates.io.",
+            user_name = self.user_name,
+            crate_name = self.crate_name,
+        )
+    }
+}
+
+impl Notification for BulkYankEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Ownership;
+}
@@ -0,0 +1,90 @@
+use crate::mirrors::{DISABLE_AFTER_HOURS, SYNC_GRACE_PERIOD_MINUTES};
+use crate::models::{CrateVisibility, Mirror};
+use crate::schema::{crates, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use chrono::{Duration, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// Checks the health of the enabled mirrors by fetching a recently published
+/// crate file from each of them and comparing its checksum, and disables the
+/// mirrors that could not be verified for a while.
+///
+/// See [`crate::mirrors`] for how the results are used. This job is meant to
+/// run every few minutes.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct CheckMirrors;
+
+impl BackgroundJob for CheckMirrors {
+    const JOB_NAME: &'static str = "check_mirrors";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let Some(probe) = env.mirror_probe.as_deref() else {
+                warn!("Skipping mirror health checks, since no mirror probe is configured");
+                return Ok(());
+            };
+
+            let Some((name, num, checksum)) = sample_version(conn)? else {
+                info!("Skipping mirror health checks, since no version can be sampled yet");
+                return Ok(());
+            };
+
+            let mirrors = Mirror::all(conn)?;
+            for mirror in mirrors.iter().filter(|mirror| mirror.enabled) {
+                let url = mirror.crate_location(&name, &num);
+                let verified = match Handle::current().block_on(probe.fetch(&url)) {
+                    Ok(bytes) if hex::encode(Sha256::digest(&bytes)) == checksum => true,
+                    Ok(_) => {
+                        warn!(mirror = %mirror.name, %url, "Mirror served a crate file with an unexpected checksum");
+                        false
+                    }
+                    Err(error) => {
+                        warn!(mirror = %mirror.name, %url, "Failed to fetch crate file from mirror: {error}");
+                        false
+                    }
+                };
+
+                mirror.record_check(conn, verified)?;
+            }
+
+            let cutoff = Utc::now().naive_utc() - Duration::hours(DISABLE_AFTER_HOURS);
+            for name in Mirror::disable_stale(conn, cutoff)? {
+                warn!(mirror = %name, "Disabled mirror that could not be verified since {cutoff}");
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Returns the name, version number and checksum of the most recently
+/// published public version that the mirrors are expected to have
+/// synchronized already.
+fn sample_version(conn: &mut impl Conn) -> QueryResult<Option<(String, String, String)>> {
+    let cutoff = Utc::now().naive_utc() - Duration::minutes(SYNC_GRACE_PERIOD_MINUTES);
+
+    versions::table
+        .inner_join(crates::table)
+        .filter(crates::visibility.eq(CrateVisibility::Public))
+        .filter(versions::created_at.lt(cutoff))
+        .filter(versions::yanked.eq(false))
+        .select((crates::name, versions::num, versions::checksum))
+        .order(versions::created_at.desc())
+        .first(conn)
+        .optional()
+}
@@ -0,0 +1,162 @@
+use crate::models::{CrateHealthMetrics, DocsBuildStatus, OwnerKind};
+use crate::schema::{crate_owners, crates, default_versions, version_quarantines, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The number of crates whose health score is computed at once.
+const BATCH_SIZE: i64 = 1000;
+
+/// Owners that published a version within this period are considered active.
+const ACTIVE_MAINTAINER_PERIOD: TimeDelta = TimeDelta::days(365);
+
+/// Computes the health scores of all crates and stores them in the
+/// `crate_health` table, from where they are served by the
+/// `GET /api/v1/crates/:crate_id/health` endpoint.
+///
+/// This job is meant to run once a day.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct UpdateCrateHealth;
+
+impl BackgroundJob for UpdateCrateHealth {
+    const JOB_NAME: &'static str = "update_crate_health";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            update_crate_health(conn)
+        })
+        .await
+    }
+}
+
+fn update_crate_health(conn: &mut impl Conn) -> anyhow::Result<()> {
+    let mut last_id = 0;
+    let mut num_updated = 0;
+
+    loop {
+        let crate_ids: Vec<i32> = crates::table
+            .filter(crates::id.gt(last_id))
+            .select(crates::id)
+            .order(crates::id)
+            .limit(BATCH_SIZE)
+            .load(conn)?;
+
+        let Some(&max_id) = crate_ids.last() else {
+            break;
+        };
+
+        let metrics = load_metrics(&crate_ids, conn)?;
+        num_updated += CrateHealthMetrics::upsert_all(conn, &metrics)?;
+
+        last_id = max_id;
+    }
+
+    info!("Updated the health scores of {num_updated} crates");
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct VersionStats {
+    last_release_at: Option<NaiveDateTime>,
+    versions: i32,
+    yanked_versions: i32,
+    recent_publishers: HashSet<i32>,
+}
+
+/// Loads the health metrics of a batch of crates at once, to avoid running
+/// separate queries for every crate.
+///
+/// Crates without any versions are skipped.
+fn load_metrics(crate_ids: &[i32], conn: &mut impl Conn) -> QueryResult<Vec<CrateHealthMetrics>> {
+    let active_since = Utc::now().naive_utc() - ACTIVE_MAINTAINER_PERIOD;
+
+    let all_versions: Vec<(i32, NaiveDateTime, bool, Option<i32>)> = versions::table
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .select((
+            versions::crate_id,
+            versions::created_at,
+            versions::yanked,
+            versions::published_by,
+        ))
+        .load(conn)?;
+
+    let mut stats: HashMap<i32, VersionStats> = HashMap::new();
+    for (crate_id, created_at, yanked, published_by) in all_versions {
+        let entry = stats.entry(crate_id).or_default();
+        entry.last_release_at = entry.last_release_at.max(Some(created_at));
+        entry.versions += 1;
+        if yanked {
+            entry.yanked_versions += 1;
+        }
+        if let Some(published_by) = published_by.filter(|_| created_at > active_since) {
+            entry.recent_publishers.insert(published_by);
+        }
+    }
+
+    let owners: Vec<(i32, i32)> = crate_owners::table
+        .filter(crate_owners::crate_id.eq_any(crate_ids))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .filter(crate_owners::deleted.eq(false))
+        .select((crate_owners::crate_id, crate_owners::owner_id))
+        .load(conn)?;
+
+    let mut owners_by_crate: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (crate_id, owner_id) in owners {
+        owners_by_crate.entry(crate_id).or_default().push(owner_id);
+    }
+
+    let quarantined: Vec<i32> = version_quarantines::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .select(versions::crate_id)
+        .load(conn)?;
+
+    let mut quarantined_by_crate: HashMap<i32, i32> = HashMap::new();
+    for crate_id in quarantined {
+        *quarantined_by_crate.entry(crate_id).or_default() += 1;
+    }
+
+    let docs_build_status: HashMap<i32, Option<DocsBuildStatus>> = default_versions::table
+        .inner_join(versions::table)
+        .filter(default_versions::crate_id.eq_any(crate_ids))
+        .select((default_versions::crate_id, versions::docs_build_status))
+        .load::<(i32, Option<DocsBuildStatus>)>(conn)?
+        .into_iter()
+        .collect();
+
+    let metrics = stats
+        .into_iter()
+        .filter_map(|(crate_id, stats)| {
+            let owners = owners_by_crate.remove(&crate_id).unwrap_or_default();
+            let active_maintainers = owners
+                .iter()
+                .filter(|&owner_id| stats.recent_publishers.contains(owner_id))
+                .count();
+
+            Some(CrateHealthMetrics {
+                crate_id,
+                last_release_at: stats.last_release_at?,
+                maintainers: owners.len() as i32,
+                active_maintainers: active_maintainers as i32,
+                quarantined_versions: quarantined_by_crate.get(&crate_id).copied().unwrap_or(0),
+                docs_build_status: docs_build_status.get(&crate_id).copied().flatten(),
+                versions: stats.versions,
+                yanked_versions: stats.yanked_versions,
+            })
+        })
+        .collect();
+
+    Ok(metrics)
+}
@@ -0,0 +1,60 @@
+use crate::schema::crate_recommendations;
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// The minimum number of crates that have to depend on two crates for them
+/// to be considered related.
+const MIN_DEPENDENTS: i64 = 2;
+
+/// The maximum number of related crates that are stored per crate and kind.
+const MAX_RECOMMENDATIONS: i64 = 10;
+
+/// Computes which crates are related to each other, based on how often they
+/// are dependencies of the same crates and how often these crates are
+/// downloaded, and stores them in the `crate_recommendations` table, from
+/// where they are served by the `GET /api/v1/crates/:crate_id/related`
+/// endpoint.
+///
+/// This job is meant to run once a day.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct UpdateCrateRecommendations;
+
+impl BackgroundJob for UpdateCrateRecommendations {
+    const JOB_NAME: &'static str = "update_crate_recommendations";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            Ok(update_recommendations(conn)?)
+        })
+        .await
+    }
+}
+
+fn update_recommendations(conn: &mut impl Conn) -> QueryResult<()> {
+    // Replace the previous recommendations in a single transaction, so that
+    // the endpoint never serves a partial set.
+    let num_inserted = conn.transaction(|conn| {
+        diesel::delete(crate_recommendations::table).execute(conn)?;
+
+        diesel::sql_query(include_str!("crate_recommendations.sql"))
+            .bind::<BigInt, _>(MIN_DEPENDENTS)
+            .bind::<BigInt, _>(MAX_RECOMMENDATIONS)
+            .execute(conn)
+    })?;
+
+    info!("Stored {num_inserted} crate recommendations");
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+WITH crate_dependencies AS (
+    -- The normal dependencies of the default version of every crate.
+    SELECT DISTINCT default_versions.crate_id AS dependent_id, dependencies.crate_id
+    FROM default_versions
+    INNER JOIN dependencies ON dependencies.version_id = default_versions.version_id
+    WHERE dependencies.kind = 0
+), pairs AS (
+    -- Count the crates that depend on both crates of a pair, and sum up
+    -- their recent downloads.
+    SELECT a.crate_id,
+           b.crate_id AS related_crate_id,
+           COUNT(*) AS dependents,
+           COALESCE(SUM(recent_crate_downloads.downloads), 0) AS downloads
+    FROM crate_dependencies a
+    INNER JOIN crate_dependencies b
+        ON b.dependent_id = a.dependent_id AND b.crate_id != a.crate_id
+    LEFT JOIN recent_crate_downloads ON recent_crate_downloads.crate_id = a.dependent_id
+    GROUP BY a.crate_id, b.crate_id
+    HAVING COUNT(*) >= $1
+), ranked AS (
+    SELECT crate_id, 0 AS kind, related_crate_id, dependents AS score,
+           ROW_NUMBER() OVER (
+               PARTITION BY crate_id
+               ORDER BY dependents DESC, downloads DESC, related_crate_id
+           ) AS rank
+    FROM pairs
+    UNION ALL
+    SELECT crate_id, 1 AS kind, related_crate_id, downloads AS score,
+           ROW_NUMBER() OVER (
+               PARTITION BY crate_id
+               ORDER BY downloads DESC, dependents DESC, related_crate_id
+           ) AS rank
+    FROM pairs
+    WHERE downloads > 0
+)
+-- Keep only the best ranked crates of every kind.
+INSERT INTO crate_recommendations (crate_id, kind, related_crate_id, score)
+SELECT crate_id, kind, related_crate_id, score
+FROM ranked
+WHERE rank <= $2
@@ -0,0 +1,84 @@
+use crate::models::{Crate, CrateVisibility};
+use crate::schema::{crates, versions};
+use crate::storage::crate_file_path;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// Moves the crate files of all versions of a crate to the storage area of
+/// its current visibility, after the visibility was changed.
+///
+/// The crate files of private crates are stored outside of the public
+/// storage area, so that they can only be downloaded via the API. The CDN
+/// caches of the public crate files are invalidated when a crate becomes
+/// private.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncCrateFileVisibility {
+    crate_id: i32,
+}
+
+impl SyncCrateFileVisibility {
+    pub fn new(crate_id: i32) -> Self {
+        Self { crate_id }
+    }
+}
+
+impl BackgroundJob for SyncCrateFileVisibility {
+    const JOB_NAME: &'static str = "sync_crate_file_visibility";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let crate_id = self.crate_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let krate: Option<Crate> = Crate::all()
+                .filter(crates::id.eq(crate_id))
+                .first(conn)
+                .optional()?;
+
+            let Some(krate) = krate else {
+                info!("Skipping crate files, since the crate does not exist anymore");
+                return Ok(());
+            };
+
+            let nums: Vec<String> = versions::table
+                .filter(versions::crate_id.eq(crate_id))
+                .select(versions::num)
+                .load(conn)?;
+
+            for num in &nums {
+                let future = env
+                    .storage
+                    .move_crate_file(&krate.name, num, krate.visibility);
+                Handle::current().block_on(future)?;
+
+                if krate.visibility == CrateVisibility::Private {
+                    let path = crate_file_path(&krate.name, num);
+                    let future = env.invalidate_cdns(path.as_ref());
+                    if let Err(error) = Handle::current().block_on(future) {
+                        warn!("Failed to invalidate CDN caches for {path}: {error}");
+                    }
+                }
+            }
+
+            info!(
+                "Moved the crate files of {} versions of {} to the {:?} storage area",
+                nums.len(),
+                krate.name,
+                krate.visibility
+            );
+
+            Ok(())
+        })
+        .await
+    }
+}
@@ -1,7 +1,11 @@
+use crate::rate_limiter::REJECTIONS_RETENTION_DAYS;
+use crate::schema::{crate_region_downloads, rate_limit_rejections};
 use crate::tasks::spawn_blocking;
 use crate::worker::Environment;
 use crates_io_worker::BackgroundJob;
-use diesel::{sql_query, RunQueryDsl};
+use diesel::dsl::{date, now, IntervalDsl};
+use diesel::prelude::*;
+use diesel::sql_query;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use std::sync::Arc;
 
@@ -10,6 +14,7 @@ pub struct DailyDbMaintenance;
 
 impl BackgroundJob for DailyDbMaintenance {
     const JOB_NAME: &'static str = "daily_db_maintenance";
+    const EXCLUSIVE: bool = true;
 
     type Context = Arc<Environment>;
 
@@ -22,6 +27,10 @@ impl BackgroundJob for DailyDbMaintenance {
     /// We only need to keep 90 days of entries in `version_downloads`. Once we have a mechanism to
     /// archive daily download counts and drop historical data, we can drop this task and rely on
     /// auto-vacuum again.
+    ///
+    /// Rejected requests in the `rate_limit_rejections` table are only kept for
+    /// `REJECTIONS_RETENTION_DAYS` days, and the download counts per country in the
+    /// `crate_region_downloads` table for 90 days, so older rows are deleted here too.
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
         let conn = env.deadpool.get().await?;
         spawn_blocking(move || {
@@ -30,6 +39,17 @@ impl BackgroundJob for DailyDbMaintenance {
             info!("Running VACUUM on version_downloads table");
             sql_query("VACUUM version_downloads;").execute(conn)?;
             info!("Finished running VACUUM on version_downloads table");
+
+            let cutoff = now - REJECTIONS_RETENTION_DAYS.days();
+            let deleted = diesel::delete(rate_limit_rejections::table)
+                .filter(rate_limit_rejections::created_at.lt(cutoff))
+                .execute(conn)?;
+            info!("Deleted {deleted} expired rate limit rejections");
+
+            let deleted = diesel::delete(crate_region_downloads::table)
+                .filter(crate_region_downloads::date.lt(date(now - 90.days())))
+                .execute(conn)?;
+            info!("Deleted {deleted} expired download counts per country");
             Ok(())
         })
         .await
@@ -0,0 +1,171 @@
+use crate::email::{Email, Notification};
+use crate::models::{Crate, NewDormantOwnerSuggestion, NotificationClass, User};
+use crate::schema::crates;
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use chrono::{Duration, NaiveDateTime, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Integer, Timestamp};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use secrecy::ExposeSecret;
+use std::sync::Arc;
+
+/// The maximum number of dormant owners that are handled per run.
+const MAX_ROWS: i64 = 1000;
+
+/// The minimum amount of time between two suggestions to remove the same
+/// owner of a crate.
+const SUGGESTION_INTERVAL: chrono::TimeDelta = chrono::TimeDelta::days(180);
+
+/// Suggests the removal of owners that have not been active for the
+/// configured period to the active owners of the same crates.
+///
+/// An owner is considered active if they used a session or an API token,
+/// published a version or yanked a version within the period. The owners
+/// are only removed if one of the active owners confirms the removal via the
+/// link in the email. This job is meant to run daily.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct SuggestDormantOwnerRemovals;
+
+impl BackgroundJob for SuggestDormantOwnerRemovals {
+    const JOB_NAME: &'static str = "suggest_dormant_owner_removals";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let Some(period) = env.config.dormant_owner_period else {
+            info!("Dormant owner suggestions are disabled");
+            return Ok(());
+        };
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let now = Utc::now().naive_utc();
+            let cutoff = now - Duration::from_std(period)?;
+            let suggested_since = now - SUGGESTION_INTERVAL;
+
+            let dormant_owners: Vec<DormantOwner> =
+                diesel::sql_query(include_str!("dormant_owners.sql"))
+                    .bind::<Timestamp, _>(cutoff)
+                    .bind::<Timestamp, _>(suggested_since)
+                    .bind::<BigInt, _>(MAX_ROWS)
+                    .load(conn)?;
+
+            info!("Found {} dormant crate owners", dormant_owners.len());
+
+            for dormant_owner in &dormant_owners {
+                if let Err(error) = suggest_removal(&env, conn, dormant_owner, cutoff) {
+                    warn!(
+                        "Failed to suggest the removal of owner {} of crate {}: {error}",
+                        dormant_owner.owner_id, dormant_owner.crate_id
+                    );
+                }
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// A helper struct for the result of the `dormant_owners.sql` query.
+#[derive(Debug, QueryableByName)]
+struct DormantOwner {
+    #[diesel(sql_type = Integer)]
+    crate_id: i32,
+    #[diesel(sql_type = Integer)]
+    owner_id: i32,
+    #[diesel(sql_type = Array<Integer>)]
+    active_owner_ids: Vec<i32>,
+}
+
+/// Sends an email with a removal link to each of the active owners of the
+/// crate.
+fn suggest_removal(
+    env: &Environment,
+    conn: &mut impl Conn,
+    dormant_owner: &DormantOwner,
+    cutoff: NaiveDateTime,
+) -> anyhow::Result<()> {
+    let krate: Crate = crates::table
+        .find(dormant_owner.crate_id)
+        .select(Crate::as_select())
+        .first(conn)?;
+
+    let owner = User::find(conn, dormant_owner.owner_id)?;
+
+    for &recipient_id in &dormant_owner.active_owner_ids {
+        let recipient = User::find(conn, recipient_id)?;
+
+        conn.transaction(|conn| {
+            let token = NewDormantOwnerSuggestion {
+                crate_id: krate.id,
+                owner_id: owner.id,
+                recipient_id,
+            }
+            .insert(conn)?;
+
+            let email = DormantOwnerEmail {
+                user_name: &recipient.gh_login,
+                owner_name: &owner.gh_login,
+                crate_name: &krate.name,
+                inactive_since: cutoff,
+                domain: &env.emails.domain,
+                token: token.expose_secret(),
+            };
+
+            env.emails.send_notification(recipient_id, email, conn)?;
+
+            Ok::<_, anyhow::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct DormantOwnerEmail<'a> {
+    user_name: &'a str,
+    owner_name: &'a str,
+    crate_name: &'a str,
+    inactive_since: NaiveDateTime,
+    domain: &'a str,
+    token: &'a str,
+}
+
+impl Email for DormantOwnerEmail<'_> {
+    const SUBJECT: &'static str = "An owner of your crate seems to be inactive";
+
+    fn body(&self) -> String {
+        format!(
+            "Hello {user_name}!
+
+{owner_name} is an owner of the crate {crate_name}, but has not been active \
+on crates.io since at least {inactive_since}. To keep the list of owners of \
+the crate current, you might want to remove them as an owner. If so, please \
+click the link below:
+
+https://{domain}/remove-dormant-owner/{token}
+
+The link expires in 30 days. The removal is subject to the same rules as a \
+removal via the owners page of the crate. If {owner_name} should stay an \
+owner of the crate, you can ignore this email.",
+            user_name = self.user_name,
+            owner_name = self.owner_name,
+            crate_name = self.crate_name,
+            inactive_since = self.inactive_since.date(),
+            domain = self.domain,
+            token = self.token,
+        )
+    }
+}
+
+impl Notification for DormantOwnerEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Ownership;
+}
@@ -0,0 +1,39 @@
+WITH user_owners AS (
+    SELECT crate_id, owner_id, created_at
+    FROM crate_owners
+    WHERE owner_kind = 0 AND NOT deleted
+), activity AS (
+    -- The most recent activity of every user that owns a crate. `GREATEST`
+    -- ignores `NULL` values.
+    SELECT owners.owner_id AS user_id,
+           GREATEST(
+               (SELECT MAX(last_used_at) FROM persistent_sessions WHERE user_id = owners.owner_id),
+               (SELECT MAX(last_used_at) FROM api_tokens WHERE user_id = owners.owner_id),
+               (SELECT MAX(created_at) FROM versions WHERE published_by = owners.owner_id),
+               (SELECT MAX(time) FROM version_owner_actions WHERE user_id = owners.owner_id)
+           ) AS last_active_at
+    FROM (SELECT DISTINCT owner_id FROM user_owners) owners
+), owners AS (
+    SELECT user_owners.*, activity.last_active_at
+    FROM user_owners
+    INNER JOIN activity ON activity.user_id = user_owners.owner_id
+)
+SELECT dormant.crate_id,
+       dormant.owner_id,
+       ARRAY_AGG(active.owner_id ORDER BY active.owner_id) AS active_owner_ids
+FROM owners dormant
+INNER JOIN owners active
+    ON active.crate_id = dormant.crate_id AND active.last_active_at >= $1
+-- Owners that were added recently had no chance to be active yet.
+WHERE dormant.created_at < $1
+  AND (dormant.last_active_at IS NULL OR dormant.last_active_at < $1)
+  AND NOT EXISTS (
+      SELECT 1
+      FROM dormant_owner_suggestions
+      WHERE dormant_owner_suggestions.crate_id = dormant.crate_id
+        AND dormant_owner_suggestions.owner_id = dormant.owner_id
+        AND dormant_owner_suggestions.created_at > $2
+  )
+GROUP BY dormant.crate_id, dormant.owner_id
+ORDER BY dormant.crate_id, dormant.owner_id
+LIMIT $3
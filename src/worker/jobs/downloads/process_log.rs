@@ -1,4 +1,5 @@
 use crate::config::CdnLogStorageConfig;
+use crate::schema::{crate_region_downloads, crates};
 use crate::tasks::spawn_blocking;
 use crate::util::diesel::Conn;
 use crate::worker::Environment;
@@ -8,6 +9,7 @@ use crates_io_cdn_logs::{count_downloads, Decompressor, DownloadsMap};
 use crates_io_worker::BackgroundJob;
 use diesel::dsl::exists;
 use diesel::prelude::*;
+use diesel::upsert::excluded;
 use diesel::{select, QueryResult};
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use diesel_async::pooled_connection::deadpool::Pool;
@@ -18,6 +20,7 @@ use object_store::memory::InMemory;
 use object_store::path::Path;
 use object_store::ObjectStore;
 use semver::Version;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 use tokio::io::BufReader;
@@ -219,6 +222,10 @@ impl From<(String, Version, NaiveDate, u64)> for NewDownload {
 /// connection pool is used, the temporary table will not be dropped when
 /// the connection is returned to the pool.
 pub fn save_downloads(downloads: DownloadsMap, conn: &mut impl Conn) -> anyhow::Result<()> {
+    debug!("Saving counted downloads per country to crate_region_downloads table");
+    save_to_crate_region_downloads(&downloads, conn)
+        .context("Failed to save downloads to crate_region_downloads table")?;
+
     debug!("Creating temp_downloads table");
     create_temp_downloads_table(conn).context("Failed to create temp_downloads table")?;
 
@@ -238,6 +245,75 @@ pub fn save_downloads(downloads: DownloadsMap, conn: &mut impl Conn) -> anyhow::
     Ok(())
 }
 
+/// Saves the downloads per country from the given [`DownloadsMap`] to the
+/// `crate_region_downloads` table.
+///
+/// Downloads of crates that don't exist in the database are skipped, just
+/// like in [`save_to_version_downloads()`].
+#[instrument(
+    "db.query",
+    skip_all,
+    fields(message = "INSERT INTO crate_region_downloads ...")
+)]
+fn save_to_crate_region_downloads(
+    downloads: &DownloadsMap,
+    conn: &mut impl Conn,
+) -> QueryResult<()> {
+    const MAX_BATCH_SIZE: usize = 5_000;
+
+    let downloads = downloads.country_vec();
+    if downloads.is_empty() {
+        return Ok(());
+    }
+
+    let mut names = downloads
+        .iter()
+        .map(|(name, _, _, _)| *name)
+        .collect::<Vec<_>>();
+
+    names.sort_unstable();
+    names.dedup();
+
+    let crate_ids: HashMap<String, i32> = crates::table
+        .filter(crates::name.eq_any(names))
+        .select((crates::name, crates::id))
+        .load(conn)?
+        .into_iter()
+        .collect();
+
+    let rows = downloads
+        .into_iter()
+        .filter_map(|(name, date, country, downloads)| {
+            let crate_id = *crate_ids.get(name)?;
+            Some((
+                crate_region_downloads::crate_id.eq(crate_id),
+                crate_region_downloads::date.eq(date),
+                crate_region_downloads::country.eq(country),
+                crate_region_downloads::downloads.eq(downloads as i32),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    for chunk in rows.chunks(MAX_BATCH_SIZE) {
+        diesel::insert_into(crate_region_downloads::table)
+            .values(chunk)
+            .on_conflict((
+                crate_region_downloads::crate_id,
+                crate_region_downloads::date,
+                crate_region_downloads::country,
+            ))
+            .do_update()
+            .set(
+                crate_region_downloads::downloads
+                    .eq(crate_region_downloads::downloads
+                        + excluded(crate_region_downloads::downloads)),
+            )
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
 /// Creates the temporary `temp_downloads` table that is used to store the
 /// counted downloads before they are inserted into the `version_downloads`
 /// table.
@@ -395,7 +471,7 @@ fn save_as_processed(path: impl Into<String>, conn: &mut impl Conn) -> QueryResu
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::{crates, version_downloads, versions};
+    use crate::schema::{version_downloads, versions};
     use crate::util::diesel::Conn;
     use crates_io_test_db::TestDatabase;
     use diesel_async::pooled_connection::AsyncDieselConnectionManager;
@@ -14,6 +14,7 @@ pub struct UpdateDownloads;
 
 impl BackgroundJob for UpdateDownloads {
     const JOB_NAME: &'static str = "update_downloads";
+    const EXCLUSIVE: bool = true;
 
     type Context = Arc<Environment>;
 
@@ -73,6 +74,10 @@ fn update(conn: &mut impl Conn) -> QueryResult<()> {
     select(refresh_recent_crate_downloads()).execute(conn)?;
     info!("Finished running refresh_recent_crate_downloads");
 
+    define_sql_function!(fn refresh_crate_download_summaries());
+    select(refresh_crate_download_summaries()).execute(conn)?;
+    info!("Finished running refresh_crate_download_summaries");
+
     Ok(())
 }
 
@@ -1,33 +1,63 @@
 use self::configuration::VisibilityConfig;
+use crate::models::{NewDatabaseDump, RegistryEvent};
 use crate::tasks::spawn_blocking;
 use crate::worker::Environment;
 use anyhow::{anyhow, Context};
 use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use hex::ToHex;
 use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use zip::write::SimpleFileOptions;
 
+/// The directory in the storage bucket that contains the archived database
+/// dumps, in subdirectories named after the time the dump was started.
+const DUMPS_PREFIX: &str = "db-dumps";
+
+/// The format of the names of the dump directories in the storage bucket and
+/// the archives.
+const DUMP_NAME_FORMAT: &str = "%Y-%m-%d-%H%M%S";
+
+pub const TAR_FILE_NAME: &str = "db-dump.tar.gz";
+pub const ZIP_FILE_NAME: &str = "db-dump.zip";
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+const CHECKSUMS_FILE_NAME: &str = "checksums.sha256";
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DumpDb;
 
 impl BackgroundJob for DumpDb {
     const JOB_NAME: &'static str = "dump_db";
+    const EXCLUSIVE: bool = true;
 
     type Context = Arc<Environment>;
 
     /// Create CSV dumps of the public information in the database, wrap them in a
     /// tarball and upload to S3.
+    ///
+    /// Each dump is uploaded into its own `db-dumps/<timestamp>/` directory
+    /// together with a `manifest.json` file, and is recorded in the
+    /// `database_dumps` table. The most recent dump is additionally available
+    /// at the well-known `db-dump.tar.gz` and `db-dump.zip` paths.
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
-        const TAR_PATH: &str = "db-dump.tar.gz";
-        const ZIP_PATH: &str = "db-dump.zip";
+        // Events that happen during the export may or may not be included in
+        // the dump, so the deltas of this dump start with the first event after
+        // the export was started.
+        let conn = env.deadpool.get().await?;
+        let last_event_id = spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            RegistryEvent::last_id(conn)
+        })
+        .await?;
 
         let db_config = &env.config.db;
         let db_pool_config = db_config.replica.as_ref().unwrap_or(&db_config.primary);
         let database_url = db_pool_config.url.clone();
 
-        let archives = spawn_blocking(move || {
+        let (timestamp, dump_name, archives) = spawn_blocking(move || {
             let directory = DumpDirectory::create()?;
 
             info!("Exporting database…");
@@ -35,38 +65,112 @@ impl BackgroundJob for DumpDb {
 
             let export_dir = directory.path();
             info!(path = ?export_dir, "Creating tarball…");
-            let tarball_prefix =
-                PathBuf::from(directory.timestamp.format("%Y-%m-%d-%H%M%S").to_string());
-            create_archives(export_dir, &tarball_prefix)
+            let dump_name = directory.name();
+            let archives = create_archives(export_dir, Path::new(&dump_name))?;
+
+            Ok::<_, anyhow::Error>((directory.timestamp, dump_name, archives))
         })
         .await?;
 
+        let dump_dir = format!("{DUMPS_PREFIX}/{dump_name}");
+        let tar_path = format!("{dump_dir}/{TAR_FILE_NAME}");
+        let zip_path = format!("{dump_dir}/{ZIP_FILE_NAME}");
+
         info!("Uploading tarball…");
         env.storage
-            .upload_db_dump(TAR_PATH, archives.tar.path())
+            .upload_db_dump(&tar_path, archives.tar.path())
             .await?;
         info!("Database dump tarball uploaded");
 
-        info!("Invalidating CDN caches…");
-        if let Err(error) = env.invalidate_cdns(TAR_PATH).await {
-            warn!("Failed to invalidate CDN caches: {error}");
-        }
-
         info!("Uploading zip file…");
         env.storage
-            .upload_db_dump(ZIP_PATH, archives.zip.path())
+            .upload_db_dump(&zip_path, archives.zip.path())
             .await?;
         info!("Database dump zip file uploaded");
 
-        info!("Invalidating CDN caches…");
-        if let Err(error) = env.invalidate_cdns(ZIP_PATH).await {
-            warn!("Failed to invalidate CDN caches: {error}");
+        info!("Uploading manifest…");
+        let manifest = Manifest {
+            timestamp,
+            crates_io_commit: crates_io_commit(),
+            last_event_id,
+            files: vec![
+                ManifestFile::new(TAR_FILE_NAME, &archives.tar_checksum),
+                ManifestFile::new(ZIP_FILE_NAME, &archives.zip_checksum),
+            ],
+        };
+        let manifest_file = tempfile::NamedTempFile::new()?;
+        serde_json::to_writer_pretty(manifest_file.as_file(), &manifest)?;
+        let manifest_path = format!("{dump_dir}/{MANIFEST_FILE_NAME}");
+        env.storage
+            .upload_db_dump(&manifest_path, manifest_file.path())
+            .await?;
+
+        for (source, target) in [(&tar_path, TAR_FILE_NAME), (&zip_path, ZIP_FILE_NAME)] {
+            info!("Updating `{target}`…");
+            env.storage.copy_db_dump(source, target).await?;
+
+            info!("Invalidating CDN caches…");
+            if let Err(error) = env.invalidate_cdns(target).await {
+                warn!("Failed to invalidate CDN caches: {error}");
+            }
         }
 
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            NewDatabaseDump {
+                created_at: timestamp.naive_utc(),
+                directory: &dump_dir,
+                tar_size: archives.tar_checksum.size as i64,
+                tar_sha256: &archives.tar_checksum.sha256,
+                zip_size: archives.zip_checksum.size as i64,
+                zip_sha256: &archives.zip_checksum.sha256,
+                last_event_id,
+            }
+            .insert(conn)?;
+
+            Ok::<_, anyhow::Error>(())
+        })
+        .await?;
+
+        info!("Database dump `{dump_name}` recorded");
+
         Ok(())
     }
 }
 
+/// The `manifest.json` file that is uploaded next to the archives of a dump.
+#[derive(Serialize)]
+struct Manifest {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    crates_io_commit: String,
+    /// The ID of the last registry event at the time the dump was started.
+    last_event_id: i64,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize)]
+struct ManifestFile {
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+impl ManifestFile {
+    fn new(path: &str, checksum: &FileChecksum) -> Self {
+        Self {
+            path: path.to_string(),
+            size: checksum.size,
+            sha256: checksum.sha256.clone(),
+        }
+    }
+}
+
+fn crates_io_commit() -> String {
+    dotenvy::var("HEROKU_SLUG_COMMIT").unwrap_or_else(|_| "unknown".to_owned())
+}
+
 /// Manage the export directory.
 ///
 /// Create the directory, populate it with the psql scripts and CSV dumps, and
@@ -91,6 +195,12 @@ impl DumpDirectory {
         self.tempdir.path()
     }
 
+    /// The name of the dump, which is used as the prefix of the paths in the
+    /// archives and as the name of the directory in the storage bucket.
+    pub fn name(&self) -> String {
+        self.timestamp.format(DUMP_NAME_FORMAT).to_string()
+    }
+
     pub fn populate(&self, database_url: &str) -> anyhow::Result<()> {
         self.add_readme()
             .context("Failed to write README.md file")?;
@@ -102,7 +212,10 @@ impl DumpDirectory {
             .context("Failed to generate schema.sql file")?;
 
         self.dump_db(database_url)
-            .context("Failed to create database dump")
+            .context("Failed to create database dump")?;
+
+        self.add_checksums()
+            .context("Failed to write checksums.sha256 file")
     }
 
     fn add_readme(&self) -> anyhow::Result<()> {
@@ -123,8 +236,7 @@ impl DumpDirectory {
         }
         let metadata = Metadata {
             timestamp: &self.timestamp,
-            crates_io_commit: dotenvy::var("HEROKU_SLUG_COMMIT")
-                .unwrap_or_else(|_| "unknown".to_owned()),
+            crates_io_commit: crates_io_commit(),
         };
         let path = self.path().join("metadata.json");
         debug!(?path, "Writing metadata.json file…");
@@ -133,6 +245,35 @@ impl DumpDirectory {
         Ok(())
     }
 
+    /// Writes the SHA-256 checksums of all other files in the export
+    /// directory in the format of `sha256sum`, so that they can be verified
+    /// with `sha256sum --check checksums.sha256`.
+    fn add_checksums(&self) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let mut paths = Vec::new();
+        for dir in [self.path().to_path_buf(), self.path().join("data")] {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    paths.push(entry.path());
+                }
+            }
+        }
+        paths.sort();
+
+        let path = self.path().join(CHECKSUMS_FILE_NAME);
+        debug!(?path, "Writing checksums.sha256 file…");
+        let mut file = File::create(path)?;
+        for path in paths {
+            let checksum = FileChecksum::calculate(&path)?;
+            let relative_path = path.strip_prefix(self.path())?;
+            writeln!(file, "{}  {}", checksum.sha256, relative_path.display())?;
+        }
+
+        Ok(())
+    }
+
     pub fn dump_schema(&self, database_url: &str) -> anyhow::Result<()> {
         let path = self.path().join("schema.sql");
         debug!(?path, "Writing schema.sql file…");
@@ -202,9 +343,40 @@ pub fn run_psql(script: &Path, database_url: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The size and the hex-encoded SHA-256 checksum of a file.
+#[derive(Debug)]
+struct FileChecksum {
+    size: u64,
+    sha256: String,
+}
+
+impl FileChecksum {
+    fn calculate(path: &Path) -> anyhow::Result<Self> {
+        use std::io::Read;
+
+        let mut file = File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        let mut size = 0;
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
+
+        let sha256 = hasher.finalize().encode_hex();
+        Ok(Self { size, sha256 })
+    }
+}
+
 struct Archives {
     tar: tempfile::NamedTempFile,
+    tar_checksum: FileChecksum,
     zip: tempfile::NamedTempFile,
+    zip_checksum: FileChecksum,
 }
 
 fn create_archives(export_dir: &Path, tarball_prefix: &Path) -> anyhow::Result<Archives> {
@@ -273,18 +445,30 @@ fn create_archives(export_dir: &Path, tarball_prefix: &Path) -> anyhow::Result<A
         }
     }
 
-    drop(tar);
+    tar.into_inner()?.finish()?;
     zip.finish()?;
 
+    debug!("Calculating archive checksums…");
+    let tar_checksum = FileChecksum::calculate(tar_tempfile.path())?;
+    let zip_checksum = FileChecksum::calculate(zip_tempfile.path())?;
+
     Ok(Archives {
         tar: tar_tempfile,
+        tar_checksum,
         zip: zip_tempfile,
+        zip_checksum,
     })
 }
 
 mod configuration;
+mod delta;
 mod gen_scripts;
 
+pub use self::delta::{
+    read_delta, DeltaChain, DeltaChainEntry, DeltaRecord, DeltaVersion, DumpDbDelta, RegistryState,
+    DELTAS_MANIFEST_FILE_NAME,
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,552 @@
+//! Daily deltas of the public database dumps.
+//!
+//! Full database dumps are large, so consumers that only need to keep track
+//! of changes can instead apply the deltas of a dump. Each delta is a gzipped
+//! file with one JSON-encoded [`DeltaRecord`] per line, which is derived from
+//! the `registry_events` table.
+//!
+//! The deltas of a dump are listed in the `deltas.json` file in the directory
+//! of the dump (see [`DeltaChain`]). The first delta starts after the last
+//! registry event at the time the dump was started, and every following delta
+//! starts where the previous one ended. Since events that happened during the
+//! export may already be included in the dump, applying a delta has to be
+//! idempotent.
+
+use super::{FileChecksum, DUMP_NAME_FORMAT};
+use crate::models::{
+    Crate, CrateVisibility, DatabaseDump, DatabaseDumpDelta, NewDatabaseDumpDelta, RegistryEvent,
+    RegistryEventKind,
+};
+use crate::schema::{crates, registry_events, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::util::rfc3339;
+use crate::worker::Environment;
+use anyhow::{anyhow, bail, Context};
+use chrono::NaiveDateTime;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+pub const DELTAS_MANIFEST_FILE_NAME: &str = "deltas.json";
+
+/// Creates a delta with the changes since the previous delta (or the full
+/// dump, if there is no previous delta) of the most recent database dump.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DumpDbDelta;
+
+impl BackgroundJob for DumpDbDelta {
+    const JOB_NAME: &'static str = "dump_db_delta";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        let delta = spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            PreparedDelta::create(conn)
+        })
+        .await?;
+
+        let Some(delta) = delta else {
+            return Ok(());
+        };
+
+        info!(path = %delta.path, "Uploading database dump delta…");
+        env.storage
+            .upload_db_dump(&delta.path, delta.file.path())
+            .await?;
+
+        let conn = env.deadpool.get().await?;
+        let (dump, chain) = spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            NewDatabaseDumpDelta {
+                dump_id: delta.dump.id,
+                from_event_id: delta.from_event_id,
+                to_event_id: delta.to_event_id,
+                path: &delta.path,
+                size: delta.checksum.size as i64,
+                sha256: &delta.checksum.sha256,
+            }
+            .insert(conn)?;
+
+            let deltas = DatabaseDumpDelta::for_dump(conn, delta.dump.id)?;
+            let chain = DeltaChain::new(&delta.dump, deltas);
+
+            Ok::<_, anyhow::Error>((delta.dump, chain))
+        })
+        .await?;
+
+        info!("Uploading {DELTAS_MANIFEST_FILE_NAME}…");
+        let manifest_file = tempfile::NamedTempFile::new()?;
+        serde_json::to_writer_pretty(manifest_file.as_file(), &chain)?;
+        let manifest_path = format!("{}/{DELTAS_MANIFEST_FILE_NAME}", dump.directory);
+        env.storage
+            .upload_db_dump(&manifest_path, manifest_file.path())
+            .await?;
+
+        info!("Invalidating CDN caches…");
+        if let Err(error) = env.invalidate_cdns(&manifest_path).await {
+            warn!("Failed to invalidate CDN caches: {error}");
+        }
+
+        Ok(())
+    }
+}
+
+/// A delta file that was written to disk, but not uploaded yet.
+struct PreparedDelta {
+    dump: DatabaseDump,
+    from_event_id: i64,
+    to_event_id: i64,
+    path: String,
+    file: tempfile::NamedTempFile,
+    checksum: FileChecksum,
+}
+
+impl PreparedDelta {
+    /// Writes the changes since the previous delta of the most recent dump to
+    /// a temporary file.
+    ///
+    /// Returns `None` if there is no dump yet, or if nothing changed since
+    /// the previous delta.
+    fn create(conn: &mut impl Conn) -> anyhow::Result<Option<Self>> {
+        let Some(dump) = DatabaseDump::recent(conn, 1)?.pop() else {
+            info!("Skipping database dump delta, since there is no database dump yet");
+            return Ok(None);
+        };
+
+        let from_event_id = DatabaseDumpDelta::for_dump(conn, dump.id)?
+            .last()
+            .map(|delta| delta.to_event_id)
+            .unwrap_or(dump.last_event_id);
+
+        let to_event_id = RegistryEvent::last_id(conn)?;
+        if to_event_id <= from_event_id {
+            info!("Skipping database dump delta, since there are no new registry events");
+            return Ok(None);
+        }
+
+        let events = load_events(conn, from_event_id, to_event_id)?;
+        info!(
+            "Creating database dump delta with {} events ({from_event_id}..={to_event_id})",
+            events.len()
+        );
+
+        let file = tempfile::NamedTempFile::new()?;
+        let encoder = flate2::write::GzEncoder::new(file.as_file(), flate2::Compression::default());
+        let mut writer = std::io::BufWriter::new(encoder);
+        for event in events {
+            let record = DeltaRecord::from_event(conn, event)?;
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer
+            .into_inner()
+            .map_err(|error| error.into_error())?
+            .finish()?;
+
+        let checksum = FileChecksum::calculate(file.path())?;
+
+        let name = chrono::Utc::now().format(DUMP_NAME_FORMAT);
+        let path = format!("{}/deltas/{name}.jsonl.gz", dump.directory);
+
+        Ok(Some(Self {
+            dump,
+            from_event_id,
+            to_event_id,
+            path,
+            file,
+            checksum,
+        }))
+    }
+}
+
+/// Loads the events in the range `from` (exclusive) to `to` (inclusive).
+///
+/// Events of private crates are not included, since private crates are not
+/// included in the dumps either.
+fn load_events(conn: &mut impl Conn, from: i64, to: i64) -> QueryResult<Vec<RegistryEvent>> {
+    let private_crates = crates::table
+        .filter(crates::visibility.ne(CrateVisibility::Public))
+        .select(crates::name);
+
+    registry_events::table
+        .filter(registry_events::id.gt(from))
+        .filter(registry_events::id.le(to))
+        .filter(registry_events::crate_name.ne_all(private_crates))
+        .order(registry_events::id.asc())
+        .select(RegistryEvent::as_select())
+        .load(conn)
+}
+
+/// A single change of the registry, as it appears in a delta file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeltaRecord {
+    pub event_id: i64,
+    /// One of `publish`, `yank`, `unyank`, `owner_change` or `delete`.
+    pub kind: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// The affected version, or `None` if the change applies to the whole
+    /// crate.
+    pub version: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    /// The published version, for `publish` events. `None` if the version
+    /// does not exist anymore.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<DeltaVersion>,
+    /// The logins of the owners of the crate at the time the delta was
+    /// created, for `owner_change` events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owners: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Queryable)]
+pub struct DeltaVersion {
+    pub checksum: String,
+    pub license: Option<String>,
+    pub crate_size: Option<i32>,
+    pub rust_version: Option<String>,
+    pub links: Option<String>,
+}
+
+impl DeltaRecord {
+    fn from_event(conn: &mut impl Conn, event: RegistryEvent) -> QueryResult<Self> {
+        let mut record = Self {
+            event_id: event.id,
+            kind: <&str>::from(event.kind).to_string(),
+            krate: event.crate_name,
+            version: event.version,
+            created_at: event.created_at,
+            published: None,
+            owners: None,
+        };
+
+        match (event.kind, &record.version) {
+            (RegistryEventKind::Publish, Some(num)) => {
+                record.published = versions::table
+                    .inner_join(crates::table)
+                    .filter(crates::name.eq(&record.krate))
+                    .filter(versions::num.eq(num))
+                    .select((
+                        versions::checksum,
+                        versions::license,
+                        versions::crate_size,
+                        versions::rust_version,
+                        versions::links,
+                    ))
+                    .first(conn)
+                    .optional()?;
+            }
+            (RegistryEventKind::OwnerChange, _) => {
+                let krate: Option<Crate> = Crate::by_name(&record.krate).first(conn).optional()?;
+                if let Some(krate) = krate {
+                    let owners = krate.owners(conn)?;
+                    let logins = owners.iter().map(|owner| owner.login().to_string());
+                    record.owners = Some(logins.collect());
+                }
+            }
+            _ => {}
+        }
+
+        Ok(record)
+    }
+}
+
+/// The `deltas.json` file in the directory of a database dump, which lists
+/// the deltas that have to be applied to the dump, in order.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeltaChain {
+    /// The ID of the last registry event that is included in the dump.
+    pub last_event_id: i64,
+    pub deltas: Vec<DeltaChainEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeltaChainEntry {
+    /// The path of the delta file, relative to the directory of the dump.
+    pub path: String,
+    pub from_event_id: i64,
+    pub to_event_id: i64,
+    pub size: u64,
+    pub sha256: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl DeltaChain {
+    fn new(dump: &DatabaseDump, deltas: Vec<DatabaseDumpDelta>) -> Self {
+        let prefix = format!("{}/", dump.directory);
+        let deltas = deltas
+            .into_iter()
+            .map(|delta| DeltaChainEntry {
+                path: delta
+                    .path
+                    .strip_prefix(&prefix)
+                    .unwrap_or(&delta.path)
+                    .to_string(),
+                from_event_id: delta.from_event_id,
+                to_event_id: delta.to_event_id,
+                size: delta.size as u64,
+                sha256: delta.sha256,
+                created_at: delta.created_at,
+            })
+            .collect();
+
+        Self {
+            last_event_id: dump.last_event_id,
+            deltas,
+        }
+    }
+
+    /// Reads the `deltas.json` file from the given directory.
+    pub fn read(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(DELTAS_MANIFEST_FILE_NAME);
+        let file = File::open(&path).with_context(|| format!("Failed to open {path:?}"))?;
+        serde_json::from_reader(file).with_context(|| format!("Failed to parse {path:?}"))
+    }
+
+    /// Checks that the deltas form an unbroken chain starting at the dump,
+    /// and that the delta files in `dir` match their checksums.
+    pub fn verify(&self, dir: &Path) -> anyhow::Result<()> {
+        let mut last_event_id = self.last_event_id;
+        for entry in &self.deltas {
+            if entry.from_event_id != last_event_id {
+                bail!(
+                    "Delta `{}` starts after event {}, but the previous delta ended with event {last_event_id}",
+                    entry.path,
+                    entry.from_event_id,
+                );
+            }
+            if entry.to_event_id <= entry.from_event_id {
+                bail!("Delta `{}` has an empty event range", entry.path);
+            }
+
+            let checksum = FileChecksum::calculate(&dir.join(&entry.path))
+                .with_context(|| format!("Failed to read delta `{}`", entry.path))?;
+            if checksum.size != entry.size || checksum.sha256 != entry.sha256 {
+                bail!("Delta `{}` does not match its checksum", entry.path);
+            }
+
+            last_event_id = entry.to_event_id;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the records of a delta file, and checks that their event IDs are
+/// increasing and within the range of the delta.
+pub fn read_delta(path: &Path, entry: &DeltaChainEntry) -> anyhow::Result<Vec<DeltaRecord>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+    let reader = BufReader::new(flate2::read::GzDecoder::new(file));
+
+    let mut records = Vec::new();
+    let mut last_event_id = entry.from_event_id;
+    for line in reader.lines() {
+        let record: DeltaRecord = serde_json::from_str(&line?)?;
+        if record.event_id <= last_event_id || record.event_id > entry.to_event_id {
+            bail!(
+                "Event {} in delta `{}` is out of order or out of range",
+                record.event_id,
+                entry.path
+            );
+        }
+
+        last_event_id = record.event_id;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// The crates and versions of the registry, with the yanked state of each
+/// version, which is used to replay deltas onto a database dump.
+#[derive(Debug, Default, PartialEq)]
+pub struct RegistryState {
+    pub crates: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+impl RegistryState {
+    /// Reads the state from the `data/crates.csv` and `data/versions.csv`
+    /// files of an extracted database dump.
+    pub fn from_dump(dir: &Path) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct CrateRow {
+            id: i32,
+            name: String,
+        }
+
+        #[derive(Deserialize)]
+        struct VersionRow {
+            crate_id: i32,
+            num: String,
+            yanked: String,
+        }
+
+        let data = dir.join("data");
+
+        let mut names = BTreeMap::new();
+        let mut state = Self::default();
+        for row in csv::Reader::from_path(data.join("crates.csv"))?.deserialize() {
+            let row: CrateRow = row?;
+            state.crates.insert(row.name.clone(), BTreeMap::new());
+            names.insert(row.id, row.name);
+        }
+
+        for row in csv::Reader::from_path(data.join("versions.csv"))?.deserialize() {
+            let row: VersionRow = row?;
+            let name = names
+                .get(&row.crate_id)
+                .ok_or_else(|| anyhow!("Unknown crate ID {} in versions.csv", row.crate_id))?;
+
+            // PostgreSQL exports booleans as `t` and `f`
+            let yanked = row.yanked == "t";
+            state
+                .crates
+                .entry(name.clone())
+                .or_default()
+                .insert(row.num, yanked);
+        }
+
+        Ok(state)
+    }
+
+    /// Applies a single change to the state.
+    ///
+    /// Changes of crates and versions that are unknown are ignored, since the
+    /// dump may already contain changes that happened during the export.
+    pub fn apply(&mut self, record: &DeltaRecord) -> anyhow::Result<()> {
+        let version = record.version.as_deref();
+        match (record.kind.as_str(), version) {
+            ("publish", Some(num)) => {
+                let versions = self.crates.entry(record.krate.clone()).or_default();
+                versions.entry(num.to_string()).or_insert(false);
+            }
+            ("yank" | "unyank", Some(num)) => {
+                let yanked = record.kind == "yank";
+                let versions = self.crates.get_mut(&record.krate);
+                if let Some(state) = versions.and_then(|versions| versions.get_mut(num)) {
+                    *state = yanked;
+                }
+            }
+            ("delete", Some(num)) => {
+                if let Some(versions) = self.crates.get_mut(&record.krate) {
+                    versions.remove(num);
+                }
+            }
+            ("delete", None) => {
+                self.crates.remove(&record.krate);
+            }
+            ("owner_change", _) => {}
+            (kind, _) => bail!("Unexpected `{kind}` change in event {}", record.event_id),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(event_id: i64, kind: &str, krate: &str, version: Option<&str>) -> DeltaRecord {
+        DeltaRecord {
+            event_id,
+            kind: kind.to_string(),
+            krate: krate.to_string(),
+            version: version.map(ToString::to_string),
+            created_at: NaiveDateTime::default(),
+            published: None,
+            owners: None,
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut state = RegistryState::default();
+
+        let records = [
+            record(1, "publish", "foo", Some("1.0.0")),
+            record(2, "publish", "foo", Some("1.1.0")),
+            record(3, "yank", "foo", Some("1.0.0")),
+            record(4, "publish", "bar", Some("0.1.0")),
+            record(5, "delete", "foo", Some("1.1.0")),
+            record(6, "owner_change", "bar", None),
+            record(7, "publish", "baz", Some("0.1.0")),
+            record(8, "delete", "baz", None),
+            // Changes of unknown versions are ignored
+            record(9, "unyank", "qux", Some("1.0.0")),
+        ];
+        for record in &records {
+            state.apply(record).unwrap();
+        }
+
+        let expected = BTreeMap::from([
+            (
+                "bar".to_string(),
+                BTreeMap::from([("0.1.0".to_string(), false)]),
+            ),
+            (
+                "foo".to_string(),
+                BTreeMap::from([("1.0.0".to_string(), true)]),
+            ),
+        ]);
+        assert_eq!(state.crates, expected);
+
+        // Replaying the same changes again does not change the state
+        for record in &records {
+            state.apply(record).unwrap();
+        }
+        assert_eq!(state.crates, expected);
+
+        assert!(state.apply(&record(10, "unknown", "foo", None)).is_err());
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = tempdir.path();
+
+        std::fs::create_dir(dir.join("deltas")).unwrap();
+        std::fs::write(dir.join("deltas/1.jsonl.gz"), "foo").unwrap();
+        std::fs::write(dir.join("deltas/2.jsonl.gz"), "bar").unwrap();
+
+        let entry = |path: &str, from_event_id, to_event_id| {
+            let checksum = FileChecksum::calculate(&dir.join(path)).unwrap();
+            DeltaChainEntry {
+                path: path.to_string(),
+                from_event_id,
+                to_event_id,
+                size: checksum.size,
+                sha256: checksum.sha256,
+                created_at: NaiveDateTime::default(),
+            }
+        };
+
+        let mut chain = DeltaChain {
+            last_event_id: 10,
+            deltas: vec![
+                entry("deltas/1.jsonl.gz", 10, 20),
+                entry("deltas/2.jsonl.gz", 20, 25),
+            ],
+        };
+        chain.verify(dir).unwrap();
+
+        chain.deltas[1].from_event_id = 21;
+        assert!(chain.verify(dir).is_err());
+
+        chain.deltas[1].from_event_id = 20;
+        chain.deltas[1].sha256 = "0000".to_string();
+        assert!(chain.verify(dir).is_err());
+    }
+}
@@ -89,7 +89,7 @@ fn handle_expiring_token(
             token_name: &token.name,
             expiry_date: token.expired_at.unwrap().and_utc(),
         };
-        emails.send(&recipient, email)?;
+        emails.send(conn, &recipient, email)?;
     } else {
         info!(
             "User {} has no email address set. Skipping expiry notification.",
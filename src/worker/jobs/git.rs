@@ -1,17 +1,22 @@
 use crate::models;
+use crate::registries::{self, DEFAULT_REGISTRY};
+use crate::schema::crates;
 use crate::tasks::spawn_blocking;
 use crate::util::diesel::Conn;
 use crate::worker::Environment;
 use anyhow::Context;
 use chrono::Utc;
 use crates_io_env_vars::var_parsed;
-use crates_io_index::{Crate, Repository};
+use crates_io_index::{Crate, FeatureEncoding, IndexConfig, Repository};
+use crates_io_worker::schema::background_jobs;
 use crates_io_worker::BackgroundJob;
 use diesel::prelude::*;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use sentry::Level;
+use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Arc;
 use url::Url;
@@ -19,12 +24,27 @@ use url::Url;
 #[derive(Serialize, Deserialize)]
 pub struct SyncToGitIndex {
     krate: String,
+    /// Other crates whose index files are updated in the same commit, e.g.
+    /// the other crates of a batch publish.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    batch: Vec<String>,
 }
 
 impl SyncToGitIndex {
     pub fn new(krate: impl Into<String>) -> Self {
         let krate = krate.into();
-        Self { krate }
+        let batch = Vec::new();
+        Self { krate, batch }
+    }
+
+    /// Updates the index files of the given other crates in the same commit.
+    pub fn with_batch(mut self, batch: Vec<String>) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    fn crates(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.krate).chain(&self.batch)
     }
 }
 
@@ -35,53 +55,198 @@ impl BackgroundJob for SyncToGitIndex {
 
     type Context = Arc<Environment>;
 
-    /// Regenerates or removes an index file for a single crate
+    /// Regenerates or removes the index files for this crate, the other
+    /// crates of its batch and, if configured, for other crates with pending
+    /// `sync_to_git_index` jobs, coalescing all changes into a single commit.
     #[instrument(skip_all, fields(krate.name = ? self.krate))]
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
         info!("Syncing to git index");
 
-        let crate_name = self.krate.clone();
+        let crate_names = self.crates().cloned().collect::<Vec<_>>();
+        let batch_size = env.config.git_index_sync_batch_size;
+        let encoding = env.config.index_feature_encoding;
         let conn = env.deadpool.get().await?;
         spawn_blocking(move || {
             let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
-            let new = get_index_data(&crate_name, conn).context("Failed to get index data")?;
+            conn.transaction(|conn| {
+                // The job that is currently running is locked by the worker,
+                // so it will be skipped by this query.
+                let pending_jobs = claim_pending_jobs(batch_size.saturating_sub(1), conn)?;
+                if !pending_jobs.is_empty() {
+                    info!(num_jobs = pending_jobs.len(), "Batching pending index updates");
+                }
 
-            let repo = env.lock_index()?;
-            let dst = repo.index_file(&crate_name);
+                let repo = env.lock_index()?;
 
-            // Read the previous crate contents
-            let old = match fs::read_to_string(&dst) {
-                Ok(content) => Some(content),
-                Err(error) if error.kind() == ErrorKind::NotFound => None,
-                Err(error) => return Err(error.into()),
-            };
+                let mut changes = Vec::new();
+                let mut seen = HashSet::new();
 
-            match (old, new) {
-                (None, Some(new)) => {
-                    fs::create_dir_all(dst.parent().unwrap())?;
-                    let mut file = File::create(&dst)?;
-                    file.write_all(new.as_bytes())?;
-                    repo.commit_and_push(&format!("Create crate `{}`", &crate_name), &dst)?;
+                // Apply the changes for the current job first, so that a
+                // failure is reported back to the worker as a job failure.
+                for crate_name in crate_names {
+                    if seen.insert(crate_name.clone()) {
+                        changes.extend(apply_index_change(&crate_name, encoding, &repo, conn)?);
+                    }
                 }
-                (Some(old), Some(new)) if old != new => {
-                    let mut file = File::create(&dst)?;
-                    file.write_all(new.as_bytes())?;
-                    repo.commit_and_push(&format!("Update crate `{}`", &crate_name), &dst)?;
+
+                let mut completed_jobs = Vec::new();
+                let mut failed_jobs = Vec::new();
+                for (job_id, job) in pending_jobs {
+                    let mut failed = false;
+                    for crate_name in job.crates() {
+                        // Index files are always regenerated from the database,
+                        // so a crate only needs to be written once per batch.
+                        if !seen.insert(crate_name.clone()) {
+                            continue;
+                        }
+
+                        match apply_index_change(crate_name, encoding, &repo, conn) {
+                            Ok(change) => changes.extend(change),
+                            Err(error) => {
+                                warn!(krate.name = %crate_name, "Failed to update index file: {error:#}");
+                                failed = true;
+                            }
+                        }
+                    }
+
+                    if failed {
+                        failed_jobs.push(job_id);
+                    } else {
+                        completed_jobs.push(job_id);
+                    }
                 }
-                (Some(_old), None) => {
-                    fs::remove_file(&dst)?;
-                    repo.commit_and_push(&format!("Delete crate `{}`", &crate_name), &dst)?;
+
+                if changes.is_empty() {
+                    debug!("Skipping sync because index is up-to-date");
+                } else {
+                    let message = batch_commit_message(&changes);
+                    let paths = changes.iter().map(|c| c.path.as_path()).collect::<Vec<_>>();
+                    repo.commit_and_push_files(&message, &paths)?;
                 }
-                _ => debug!("Skipping sync because index is up-to-date"),
-            }
 
-            Ok(())
+                // Only remove the batched jobs once the commit has been pushed
+                // successfully. Failed jobs are left in the queue to be retried
+                // individually.
+                diesel::delete(background_jobs::table)
+                    .filter(background_jobs::id.eq_any(&completed_jobs))
+                    .execute(conn)?;
+
+                diesel::update(background_jobs::table)
+                    .filter(background_jobs::id.eq_any(&failed_jobs))
+                    .set((
+                        background_jobs::retries.eq(background_jobs::retries + 1),
+                        background_jobs::last_retry.eq(diesel::dsl::now),
+                    ))
+                    .execute(conn)?;
+
+                Ok(())
+            })
         })
         .await
     }
 }
 
+/// Locks and returns up to `limit` other pending `sync_to_git_index` jobs,
+/// ordered by their creation, so that they can be processed as a batch.
+///
+/// Jobs that have previously failed are not included, so that they can be
+/// retried individually with the regular backoff.
+fn claim_pending_jobs(
+    limit: usize,
+    conn: &mut impl Conn,
+) -> anyhow::Result<Vec<(i64, SyncToGitIndex)>> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let rows: Vec<(i64, serde_json::Value)> = background_jobs::table
+        .select((background_jobs::id, background_jobs::data))
+        .filter(background_jobs::job_type.eq(SyncToGitIndex::JOB_NAME))
+        .filter(background_jobs::retries.eq(0))
+        .order(background_jobs::id)
+        .limit(limit as i64)
+        .for_update()
+        .skip_locked()
+        .load(conn)?;
+
+    rows.into_iter()
+        .map(|(id, data)| Ok((id, serde_json::from_value(data)?)))
+        .collect()
+}
+
+/// A single modification of an index file in the local checkout.
+struct IndexChange {
+    path: PathBuf,
+    message: String,
+}
+
+/// Regenerates or removes the index file for a single crate in the local
+/// checkout, without committing it.
+///
+/// Returns `None` if the index file is already up-to-date.
+fn apply_index_change(
+    crate_name: &str,
+    encoding: FeatureEncoding,
+    repo: &Repository,
+    conn: &mut impl Conn,
+) -> anyhow::Result<Option<IndexChange>> {
+    let new = get_index_data(crate_name, encoding, conn).context("Failed to get index data")?;
+
+    // The git index only contains the crates of the default registry
+    let registry = registries::crate_registry(conn, crate_name)?;
+    let new = new.filter(|_| registry.as_deref() == Some(DEFAULT_REGISTRY));
+
+    let dst = repo.index_file(crate_name);
+
+    // Read the previous crate contents
+    let old = match fs::read_to_string(&dst) {
+        Ok(content) => Some(content),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => return Err(error.into()),
+    };
+
+    let message = match (old, new) {
+        (None, Some(new)) => {
+            fs::create_dir_all(dst.parent().unwrap())?;
+            let mut file = File::create(&dst)?;
+            file.write_all(new.as_bytes())?;
+            format!("Create crate `{crate_name}`")
+        }
+        (Some(old), Some(new)) if old != new => {
+            let mut file = File::create(&dst)?;
+            file.write_all(new.as_bytes())?;
+            format!("Update crate `{crate_name}`")
+        }
+        (Some(_old), None) => {
+            fs::remove_file(&dst)?;
+            format!("Delete crate `{crate_name}`")
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(IndexChange { path: dst, message }))
+}
+
+/// Builds the commit message for a batch of index changes.
+///
+/// A single change keeps the message format of unbatched updates, while
+/// multiple changes are summarized and listed in the commit body.
+fn batch_commit_message(changes: &[IndexChange]) -> String {
+    match changes {
+        [change] => change.message.clone(),
+        changes => {
+            let mut message = format!("Update {} crates\n\n", changes.len());
+            for change in changes {
+                message.push_str("- ");
+                message.push_str(&change.message);
+                message.push('\n');
+            }
+            message
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SyncToSparseIndex {
     krate: String,
@@ -106,30 +271,225 @@ impl BackgroundJob for SyncToSparseIndex {
         info!("Syncing to sparse index");
 
         let crate_name = self.krate.clone();
+        let encoding = env.config.index_feature_encoding;
         let conn = env.deadpool.get().await?;
-        let content = spawn_blocking(move || {
+        let (registry, content) = spawn_blocking(move || {
             let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
-            get_index_data(&crate_name, conn)
+            let registry = registries::crate_registry(conn, &crate_name)?;
+            let content = get_index_data(&crate_name, encoding, conn)?;
+            Ok::<_, anyhow::Error>((registry, content))
         })
         .await
         .context("Failed to get index data")?;
 
-        let future = env.storage.sync_index(&self.krate, content);
-        future.await.context("Failed to sync index data")?;
+        // Deleted crates don't have a registry anymore, so their index file
+        // is removed from the index namespaces of all registries.
+        let target_registries = match registry {
+            Some(registry) => vec![registry],
+            None => env.config.registries.names().map(String::from).collect(),
+        };
+
+        for registry in target_registries {
+            let future = env
+                .storage
+                .sync_registry_index(&registry, &self.krate, content.clone());
+            future.await.context("Failed to sync index data")?;
+
+            if let Some(cloudfront) = env.cloudfront() {
+                let path = Repository::relative_index_file_for_url(&self.krate);
+                let path = registries::index_path(&registry, &path);
+
+                info!(%path, "Invalidating index file on CloudFront");
+                let future = cloudfront.invalidate(&path);
+                future.await.context("Failed to invalidate CloudFront")?;
+            }
+        }
 
-        if let Some(cloudfront) = env.cloudfront() {
-            let path = Repository::relative_index_file_for_url(&self.krate);
+        Ok(())
+    }
+}
+
+/// The number of crates whose sparse index files are regenerated by a single
+/// [`RegenerateSparseIndex`] job.
+const REGENERATE_BATCH_SIZE: i64 = 100;
+
+/// Regenerates the sparse index files of all crates, e.g. after the feature
+/// encoding of the index entries was changed in the server config.
+///
+/// Each job enqueues a [`SyncToSparseIndex`] job for a batch of crates, which
+/// rewrites all entries of the index file with the current encoding, and then
+/// enqueues itself for the next batch.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RegenerateSparseIndex {
+    after_crate_id: i32,
+}
+
+impl BackgroundJob for RegenerateSparseIndex {
+    const JOB_NAME: &'static str = "regenerate_sparse_index";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(after_crate_id = self.after_crate_id), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let after_crate_id = self.after_crate_id;
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let crates: Vec<(i32, String)> = crates::table
+                .filter(crates::id.gt(after_crate_id))
+                .order(crates::id.asc())
+                .limit(REGENERATE_BATCH_SIZE)
+                .select((crates::id, crates::name))
+                .load(conn)?;
+
+            let Some(&(last_crate_id, _)) = crates.last() else {
+                info!("Finished regenerating the sparse index");
+                return Ok(());
+            };
+
+            conn.transaction(|conn| {
+                for (_, name) in &crates {
+                    SyncToSparseIndex::new(name).enqueue(conn)?;
+                }
 
-            info!(%path, "Invalidating index file on CloudFront");
-            let future = cloudfront.invalidate(&path);
+                if crates.len() as i64 == REGENERATE_BATCH_SIZE {
+                    let next = RegenerateSparseIndex {
+                        after_crate_id: last_crate_id,
+                    };
+                    next.enqueue(conn)?;
+                }
+
+                Ok::<_, anyhow::Error>(())
+            })?;
+
+            info!(
+                "Enqueued the sparse index files of {} crates for regeneration (up to crate ID {last_crate_id})",
+                crates.len()
+            );
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Regenerates the `config.json` file of the sparse index from the server
+/// config.
+#[derive(Serialize, Deserialize)]
+pub struct SyncIndexConfig;
+
+impl BackgroundJob for SyncIndexConfig {
+    const JOB_NAME: &'static str = "sync_index_config";
+    const PRIORITY: i16 = 100;
+
+    type Context = Arc<Environment>;
+
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        info!("Syncing sparse index config");
+
+        let config = index_config(&env.config, env.storage.crate_files_location());
+        let content = serde_json::to_string(&config)?;
+
+        let future = env.storage.sync_index_config(content);
+        future.await.context("Failed to sync index config")?;
+
+        if let Some(cloudfront) = env.cloudfront() {
+            info!("Invalidating index config on CloudFront");
+            let future = cloudfront.invalidate("config.json");
             future.await.context("Failed to invalidate CloudFront")?;
         }
+
         Ok(())
     }
 }
 
+/// The optional features that are advertised in the index config, with the
+/// routes that they depend on. Features whose route is blocked via the
+/// `BLOCKED_ROUTES` environment variable are not advertised.
+const ROUTE_FEATURES: &[(&str, &str)] = &[
+    (
+        "attestations",
+        "/api/v1/crates/:crate_id/:version/attestations",
+    ),
+    ("batch-publish", "/api/v1/crates/batch"),
+    (
+        "checksum-advisories",
+        "/api/v1/crates/:crate_id/:version/checksum_advisory",
+    ),
+    ("staged-uploads", "/api/v1/staging"),
+];
+
+/// Builds the index config that advertises the download and API URLs, and
+/// the optional features of this deployment, to cargo and other clients.
+///
+/// Crate files are downloaded from the CDN if there is one, except when
+/// downloads require authentication, since only the API can check the
+/// token that cargo sends along, or when crate files are fetched from an
+/// upstream registry on demand.
+pub(crate) fn index_config(
+    config: &crate::config::Server,
+    crate_files_location: Option<String>,
+) -> IndexConfig {
+    let api = format!("https://{}", config.domain_name);
+
+    let via_api = config.download_auth_required || config.upstream.is_some();
+    let dl = match crate_files_location {
+        Some(location) if !via_api => location,
+        _ => format!("{api}/api/v1/crates"),
+    };
+
+    // Pull-through caches serve their sparse index from the API.
+    let sparse_index = match &config.sparse_index_url {
+        Some(url) => Some(url.clone()),
+        None if config.upstream.is_some() => Some(format!("{api}/index/")),
+        None => None,
+    };
+
+    IndexConfig {
+        dl,
+        api: Some(api),
+        auth_required: config.download_auth_required,
+        sparse_index: sparse_index.map(|url| format!("sparse+{url}")),
+        features: registry_features(config),
+    }
+}
+
+/// Returns the optional features that are enabled in the server config, in
+/// alphabetical order.
+fn registry_features(config: &crate::config::Server) -> Vec<String> {
+    let mut features = ROUTE_FEATURES
+        .iter()
+        .filter(|(_, route)| !config.blocked_routes.contains(*route))
+        .filter(|(feature, _)| {
+            *feature != "checksum-advisories" || config.checksum_advisory_key.is_some()
+        })
+        .map(|(feature, _)| feature.to_string())
+        .collect::<Vec<_>>();
+
+    if config.upstream.is_some() {
+        features.push("pull-through-cache".to_string());
+    }
+
+    features.sort();
+    features
+}
+
+/// Generates the contents of the index file of a crate, or returns `None` if
+/// the crate does not exist (anymore).
+///
+/// Private crates are left out of the index, so that their names and
+/// metadata are not published via the public git and sparse indexes. Their
+/// index files are deleted when a crate becomes private.
+///
+/// The features of the entries are encoded with the given `encoding` (see
+/// [`Crate::encode_features()`]).
 #[instrument(skip_all, fields(krate.name = ?name))]
-pub fn get_index_data(name: &str, conn: &mut impl Conn) -> anyhow::Result<Option<String>> {
+pub fn get_index_data(
+    name: &str,
+    encoding: FeatureEncoding,
+    conn: &mut impl Conn,
+) -> anyhow::Result<Option<String>> {
     debug!("Looking up crate by name");
     let Some(krate): Option<models::Crate> =
         models::Crate::by_exact_name(name).first(conn).optional()?
@@ -137,11 +497,20 @@ pub fn get_index_data(name: &str, conn: &mut impl Conn) -> anyhow::Result<Option
         return Ok(None);
     };
 
+    if krate.visibility == models::CrateVisibility::Private {
+        debug!("Skipping private crate");
+        return Ok(None);
+    }
+
     debug!("Gathering remaining index data");
-    let crates = krate
+    let mut crates = krate
         .index_metadata(conn)
         .context("Failed to gather index metadata")?;
 
+    for krate in &mut crates {
+        krate.encode_features(encoding);
+    }
+
     // This can sometimes happen when we delete versions upon owner request
     // but don't realize that the crate is now left with no versions at all.
     //
@@ -164,12 +533,67 @@ pub fn get_index_data(name: &str, conn: &mut impl Conn) -> anyhow::Result<Option
     Ok(Some(str))
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct SyncRegistryConfigs;
+
+impl BackgroundJob for SyncRegistryConfigs {
+    const JOB_NAME: &'static str = "sync_registry_configs";
+
+    type Context = Arc<Environment>;
+
+    /// Uploads the `config.json` files of the sparse indexes of all
+    /// additional registries.
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let domain_name = &env.config.domain_name;
+
+        for registry in env.config.registries.iter() {
+            info!(registry = %registry.name, "Uploading registry config");
+
+            // Crate files of additional registries are always downloaded via
+            // the API, which checks that they belong to the registry.
+            let api = registry.api_url(domain_name);
+
+            // The sparse index of the registry is stored in its namespace
+            // of the index storage.
+            let sparse_index = env.config.sparse_index_url.as_ref().map(|url| {
+                let url = url.trim_end_matches('/');
+                let path = registries::index_path(&registry.name, "");
+                format!("sparse+{url}/{path}")
+            });
+
+            let config = IndexConfig {
+                dl: format!("{api}/api/v1/crates"),
+                api: Some(api),
+                auth_required: env.config.download_auth_required,
+                sparse_index,
+                features: registry_features(&env.config),
+            };
+            let content = serde_json::to_string(&config)?;
+
+            let future = env.storage.upload_registry_config(&registry.name, content);
+            future.await.context("Failed to upload registry config")?;
+
+            if let Some(cloudfront) = env.cloudfront() {
+                let path = registries::index_path(&registry.name, "config.json");
+
+                info!(%path, "Invalidating registry config on CloudFront");
+                let future = cloudfront.invalidate(&path);
+                future.await.context("Failed to invalidate CloudFront")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SquashIndex;
 
 impl BackgroundJob for SquashIndex {
     const JOB_NAME: &'static str = "squash_index";
     const QUEUE: &'static str = "repository";
+    const EXCLUSIVE: bool = true;
 
     type Context = Arc<Environment>;
 
@@ -236,6 +660,7 @@ impl NormalizeIndex {
 impl BackgroundJob for NormalizeIndex {
     const JOB_NAME: &'static str = "normalize_index";
     const QUEUE: &'static str = "repository";
+    const EXCLUSIVE: bool = true;
 
     type Context = Arc<Environment>;
 
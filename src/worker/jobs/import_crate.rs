@@ -0,0 +1,216 @@
+use crate::controllers::krate::publish::add_dependencies;
+use crate::models::{
+    set_yanked, update_default_version, CrateOwner, DependencyKind, Keyword, NewCrate,
+    NewCrateOwnerAction, NewVersion, OwnerKind, YankActor,
+};
+use crate::schema::{crate_owners, crates};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::views::EncodableCrateDependency;
+use crate::worker::jobs;
+use crate::worker::Environment;
+use anyhow::{anyhow, bail};
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// The reason that is recorded in the yank history of versions that were
+/// already yanked in the other registry.
+const IMPORTED_YANK_REASON: &str = "Yanked in the registry that the crate was imported from";
+
+/// Imports a crate with all of its versions from another registry (e.g.
+/// Alexandrie or Kellnr) into the database.
+///
+/// These jobs are enqueued by the `crates-admin import-registry` command,
+/// which reads the git index and the crate files of the other registry,
+/// uploads the crate files to our storage, and maps the owners to existing
+/// users via their email addresses.
+///
+/// If a dependency of the crate has not been imported yet, the job fails and
+/// is retried later, so that crates can be imported in any order.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportCrate {
+    pub name: String,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub documentation: Option<String>,
+    pub repository: Option<String>,
+    pub keywords: Vec<String>,
+    /// The owners of the crate. The first owner is recorded as the publisher
+    /// of all imported versions.
+    pub owners: Vec<ImportedOwner>,
+    pub versions: Vec<ImportedVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportedOwner {
+    pub user_id: i32,
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportedVersion {
+    /// The index entry of the version in the other registry.
+    pub index: crates_io_index::Crate,
+    pub license: Option<String>,
+    pub crate_size: i32,
+}
+
+impl BackgroundJob for ImportCrate {
+    const JOB_NAME: &'static str = "import_crate";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(krate.name = %self.name), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let job = self.clone();
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            conn.transaction(|conn| job.import(conn))
+        })
+        .await
+    }
+}
+
+impl ImportCrate {
+    fn import(&self, conn: &mut impl Conn) -> anyhow::Result<()> {
+        let Some(publisher) = self.owners.first() else {
+            bail!("Crate `{}` has no owners", self.name);
+        };
+
+        let already_exists = crates::table.filter(crates::name.eq(&self.name));
+        if diesel::select(exists(already_exists)).get_result(conn)? {
+            bail!("Crate `{}` already exists", self.name);
+        }
+
+        let krate = NewCrate {
+            name: &self.name,
+            description: self.description.as_deref(),
+            homepage: self.homepage.as_deref(),
+            documentation: self.documentation.as_deref(),
+            readme: None,
+            repository: self.repository.as_deref(),
+            max_upload_size: None,
+            max_features: None,
+            max_dependencies: None,
+            max_feature_values: None,
+        }
+        .create(conn, publisher.user_id)?;
+
+        let additional_owners = self
+            .owners
+            .iter()
+            .skip(1)
+            .map(|owner| CrateOwner {
+                crate_id: krate.id,
+                owner_id: owner.user_id,
+                created_by: publisher.user_id,
+                owner_kind: OwnerKind::User,
+                email_notifications: true,
+            })
+            .collect::<Vec<_>>();
+
+        if !additional_owners.is_empty() {
+            diesel::insert_into(crate_owners::table)
+                .values(&additional_owners)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            for owner in &additional_owners {
+                let by = Some(owner.created_by);
+                NewCrateOwnerAction::add(krate.id, owner.owner_id, OwnerKind::User, by)
+                    .insert(conn)?;
+            }
+        }
+
+        let mut yanked_version_ids = Vec::new();
+        for imported in &self.versions {
+            let index = &imported.index;
+
+            let mut features = index.features.clone();
+            features.extend(index.features2.clone().unwrap_or_default());
+
+            let version = NewVersion::builder(krate.id, &index.vers)
+                .features(&features)?
+                .license(imported.license.clone())
+                .size(imported.crate_size)
+                .published_by(publisher.user_id)
+                .checksum(&index.cksum)
+                .links(index.links.clone())
+                .rust_version(index.rust_version.clone())
+                .channel(index.channel.clone())
+                .build()?
+                .save(conn, &publisher.email)
+                .map_err(|error| anyhow!("Failed to save version {}: {error}", index.vers))?;
+
+            if index.yanked == Some(true) {
+                yanked_version_ids.push(version.id);
+            }
+
+            let deps = index
+                .deps
+                .iter()
+                .map(convert_dependency)
+                .collect::<Vec<_>>();
+            add_dependencies(conn, &deps, version.id)
+                .map_err(|error| anyhow!("Failed to import version {}: {error}", index.vers))?;
+        }
+
+        let actor = YankActor::User {
+            user_id: publisher.user_id,
+            api_token_id: None,
+        };
+        let reason = Some(IMPORTED_YANK_REASON);
+        set_yanked(
+            conn,
+            krate.id,
+            &krate.name,
+            &yanked_version_ids,
+            true,
+            actor,
+            reason,
+        )?;
+
+        let keywords = self.keywords.iter().map(String::as_str).collect::<Vec<_>>();
+        Keyword::update_crate(conn, &krate, &keywords)?;
+
+        update_default_version(krate.id, conn)?;
+        jobs::enqueue_sync_to_index(&krate.name, conn)?;
+
+        info!(
+            "Imported crate `{}` with {} versions",
+            krate.name,
+            self.versions.len()
+        );
+
+        Ok(())
+    }
+}
+
+/// Converts a dependency from an index entry into the format that is used
+/// when publishing crates.
+///
+/// In the index, `name` is the name of the dependency in the manifest, and
+/// `package` is the name of the actual crate, if the dependency was renamed.
+fn convert_dependency(dep: &crates_io_index::Dependency) -> EncodableCrateDependency {
+    let (name, explicit_name_in_toml) = match &dep.package {
+        Some(package) => (package.clone(), Some(dep.name.clone())),
+        None => (dep.name.clone(), None),
+    };
+
+    EncodableCrateDependency {
+        optional: dep.optional,
+        default_features: dep.default_features,
+        name,
+        features: dep.features.clone(),
+        version_req: dep.req.clone(),
+        target: dep.target.clone(),
+        kind: dep.kind.map(DependencyKind::from),
+        explicit_name_in_toml,
+        registry: None,
+    }
+}
@@ -0,0 +1,33 @@
+use crate::storage::crate_file_path;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use std::sync::Arc;
+
+/// Invalidates the CDN caches of the tarball of a version, after the
+/// tarball was replaced in the storage bucket.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InvalidateCrateFile {
+    crate_name: String,
+    version: String,
+}
+
+impl InvalidateCrateFile {
+    pub fn new(crate_name: String, version: String) -> Self {
+        Self {
+            crate_name,
+            version,
+        }
+    }
+}
+
+impl BackgroundJob for InvalidateCrateFile {
+    const JOB_NAME: &'static str = "invalidate_crate_file";
+
+    type Context = Arc<Environment>;
+
+    async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+        let path = crate_file_path(&self.crate_name, &self.version);
+        info!("Invalidating CDN caches for {path}");
+        ctx.invalidate_cdns(path.as_ref()).await
+    }
+}
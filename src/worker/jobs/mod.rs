@@ -7,29 +7,73 @@ use diesel::sql_types::{Int2, Jsonb, Text};
 use std::fmt::Display;
 
 mod archive_version_downloads;
+mod avatars;
+mod backfill_semver_ord;
+mod bulk_yank;
+mod check_mirrors;
+mod crate_health;
+mod crate_recommendations;
+mod crate_visibility;
 mod daily_db_maintenance;
+mod dormant_owners;
 mod downloads;
 pub mod dump_db;
 mod expiry_notification;
 mod git;
+mod import_crate;
+mod invalidate_crate_file;
+mod pending_owner_removals;
+mod prerelease_retention;
+mod purge_expired_records;
+mod readme_images;
 mod readmes;
 pub mod rss;
+mod scan_tarball;
+mod subscription_notifications;
 mod sync_admins;
+mod sync_advisories;
 mod typosquat;
 mod update_default_version;
+mod upstream;
+mod validate_version_metadata;
+mod weekly_digest;
 
 pub use self::archive_version_downloads::ArchiveVersionDownloads;
+pub use self::avatars::FetchAvatar;
+pub use self::backfill_semver_ord::BackfillSemverOrd;
+pub use self::bulk_yank::BulkYankVersions;
+pub use self::check_mirrors::CheckMirrors;
+pub use self::crate_health::UpdateCrateHealth;
+pub use self::crate_recommendations::UpdateCrateRecommendations;
+pub use self::crate_visibility::SyncCrateFileVisibility;
 pub use self::daily_db_maintenance::DailyDbMaintenance;
+pub use self::dormant_owners::SuggestDormantOwnerRemovals;
 pub use self::downloads::{
     CleanProcessedLogFiles, ProcessCdnLog, ProcessCdnLogQueue, UpdateDownloads,
 };
-pub use self::dump_db::DumpDb;
+pub use self::dump_db::{DumpDb, DumpDbDelta};
 pub use self::expiry_notification::SendTokenExpiryNotifications;
-pub use self::git::{NormalizeIndex, SquashIndex, SyncToGitIndex, SyncToSparseIndex};
-pub use self::readmes::RenderAndUploadReadme;
+pub(crate) use self::git::index_config;
+pub use self::git::{
+    NormalizeIndex, RegenerateSparseIndex, SquashIndex, SyncIndexConfig, SyncRegistryConfigs,
+    SyncToGitIndex, SyncToSparseIndex,
+};
+pub use self::import_crate::{ImportCrate, ImportedOwner, ImportedVersion};
+pub use self::invalidate_crate_file::InvalidateCrateFile;
+pub use self::pending_owner_removals::ApplyPendingOwnerRemovals;
+pub use self::prerelease_retention::ApplyPrereleaseRetention;
+pub use self::purge_expired_records::PurgeExpiredRecords;
+pub use self::readme_images::FetchReadmeImages;
+pub use self::readmes::{RenderAndUploadReadme, RerenderReadmes};
+pub use self::scan_tarball::{ScanStagedUpload, ScanTarball};
+pub use self::subscription_notifications::SendSubscriptionNotifications;
 pub use self::sync_admins::SyncAdmins;
+pub use self::sync_advisories::SyncAdvisories;
 pub use self::typosquat::CheckTyposquat;
 pub use self::update_default_version::UpdateDefaultVersion;
+pub use self::upstream::RevalidateUpstreamCrates;
+pub use self::validate_version_metadata::ValidateVersionMetadata;
+pub use self::weekly_digest::SendWeeklyDigests;
 
 /// Enqueue both index sync jobs (git and sparse) for a crate, unless they
 /// already exist in the background job queue.
@@ -101,3 +145,28 @@ pub fn enqueue_sync_to_index<T: Display>(
 
     Ok(())
 }
+
+/// Enqueues the index sync jobs for multiple crates that were changed
+/// together, e.g. by a batch publish.
+///
+/// In contrast to [`enqueue_sync_to_index()`], a single `sync_to_git_index`
+/// job is enqueued for all crates, so that the git index is updated in a
+/// single commit.
+pub fn enqueue_batch_sync_to_index(
+    crates: &[String],
+    conn: &mut impl Conn,
+) -> Result<(), EnqueueError> {
+    let Some((first, others)) = crates.split_first() else {
+        return Ok(());
+    };
+
+    SyncToGitIndex::new(first)
+        .with_batch(others.to_vec())
+        .enqueue(conn)?;
+
+    for krate in crates {
+        SyncToSparseIndex::new(krate).enqueue(conn)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::models::PendingOwnerRemoval;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use chrono::{Duration, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// Applies the removals of owners of critical crates whose waiting period is
+/// over, and the pending removals of crates that are not critical anymore.
+///
+/// Removals that would leave a crate without individual owners are dropped.
+/// This job is meant to run hourly.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct ApplyPendingOwnerRemovals;
+
+impl BackgroundJob for ApplyPendingOwnerRemovals {
+    const JOB_NAME: &'static str = "apply_pending_owner_removals";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let waiting_period = env.config.critical_owner_removal_waiting_period;
+            let cutoff = Utc::now().naive_utc() - Duration::from_std(waiting_period)?;
+
+            for removal in PendingOwnerRemoval::due(conn, cutoff)? {
+                if let Err(error) = removal.apply(conn) {
+                    warn!(
+                        "Dropping the removal of owner {} ({:?}) of crate {}: {error}",
+                        removal.owner_id, removal.owner_kind, removal.crate_id
+                    );
+                    diesel::delete(&removal).execute(conn)?;
+                    continue;
+                }
+
+                info!(
+                    "Removed owner {} ({:?}) of crate {}",
+                    removal.owner_id, removal.owner_kind, removal.crate_id
+                );
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,257 @@
+use crate::email::{Email, Notification};
+use crate::models::{set_yanked, NotificationClass, OwnerKind, YankActor};
+use crate::schema::{crate_owners, crate_settings, crates, users, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use crate::Emails;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// Yanks the pre-release versions of all crates with a pre-release retention
+/// policy, once they are older than the configured number of days and a
+/// newer stable version of the crate has been published.
+///
+/// The owners of the affected crates are notified via email about the
+/// yanked versions.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct ApplyPrereleaseRetention;
+
+impl BackgroundJob for ApplyPrereleaseRetention {
+    const JOB_NAME: &'static str = "apply_prerelease_retention";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            apply_retention_policies(&env.emails, conn)
+        })
+        .await
+    }
+}
+
+fn apply_retention_policies(emails: &Emails, conn: &mut impl Conn) -> anyhow::Result<()> {
+    let crates: Vec<(i32, String, i32)> = crates::table
+        .inner_join(crate_settings::table)
+        .filter(crate_settings::prerelease_retention_days.is_not_null())
+        .select((
+            crates::id,
+            crates::name,
+            crate_settings::prerelease_retention_days.assume_not_null(),
+        ))
+        .load(conn)?;
+
+    info!(
+        "Applying pre-release retention policies of {} crates…",
+        crates.len()
+    );
+
+    for (crate_id, crate_name, days) in crates {
+        if let Err(error) = apply_retention_policy(emails, conn, crate_id, &crate_name, days) {
+            error!(
+                ?error,
+                "Failed to apply pre-release retention policy of {crate_name}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = versions, check_for_backend(diesel::pg::Pg))]
+struct VersionInfo {
+    id: i32,
+    num: String,
+    created_at: NaiveDateTime,
+}
+
+fn apply_retention_policy(
+    emails: &Emails,
+    conn: &mut impl Conn,
+    crate_id: i32,
+    crate_name: &str,
+    days: i32,
+) -> anyhow::Result<()> {
+    let versions: Vec<VersionInfo> = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .filter(versions::yanked.eq(false))
+        .select(VersionInfo::as_select())
+        .load(conn)?;
+
+    let threshold = Utc::now().naive_utc() - TimeDelta::days(days.into());
+    let expired = find_expired_prereleases(&versions, threshold);
+    if expired.is_empty() {
+        return Ok(());
+    }
+
+    let version_ids = expired.iter().map(|v| v.id).collect::<Vec<_>>();
+    let reason = format!("Pre-release is older than the retention period of {days} days");
+    let actor = YankActor::System;
+    let version_nums = set_yanked(
+        conn,
+        crate_id,
+        crate_name,
+        &version_ids,
+        true,
+        actor,
+        Some(&reason),
+    )?;
+    if version_nums.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        "Yanked {} expired pre-release versions of {crate_name}: {version_nums:?}",
+        version_nums.len()
+    );
+
+    let owners: Vec<(i32, String)> = crate_owners::table
+        .inner_join(users::table)
+        .filter(crate_owners::crate_id.eq(crate_id))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .select((users::id, users::gh_login))
+        .load(conn)?;
+
+    for (user_id, user_name) in owners {
+        let email = PrereleaseRetentionEmail {
+            user_name: &user_name,
+            crate_name,
+            days,
+            versions: &version_nums,
+        };
+
+        if let Err(error) = emails.send_notification(user_id, email, conn) {
+            warn!(
+                ?error,
+                "Failed to send pre-release retention email to {user_name}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the pre-release versions that were published before `threshold`
+/// and that are superseded by a stable version.
+fn find_expired_prereleases(
+    versions: &[VersionInfo],
+    threshold: NaiveDateTime,
+) -> Vec<&VersionInfo> {
+    let parsed = versions
+        .iter()
+        .filter_map(|v| semver::Version::parse(&v.num).ok().map(|num| (v, num)))
+        .collect::<Vec<_>>();
+
+    let highest_stable = parsed
+        .iter()
+        .map(|(_, num)| num)
+        .filter(|num| num.pre.is_empty())
+        .max();
+
+    let Some(highest_stable) = highest_stable else {
+        return vec![];
+    };
+
+    parsed
+        .iter()
+        .filter(|(_, num)| !num.pre.is_empty() && num < highest_stable)
+        .filter(|(v, _)| v.created_at < threshold)
+        .map(|(v, _)| *v)
+        .collect()
+}
+
+struct PrereleaseRetentionEmail<'a> {
+    user_name: &'a str,
+    crate_name: &'a str,
+    days: i32,
+    versions: &'a [String],
+}
+
+impl Email for PrereleaseRetentionEmail<'_> {
+    const SUBJECT: &'static str = "Pre-release versions of your crate were yanked";
+
+    fn body(&self) -> String {
+        let versions = self
+            .versions
+            .iter()
+            .map(|num| format!("- {num}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!(
+            "Hello {user_name}!
+
+The following pre-release versions of your crate {crate_name} were yanked, \
+since they are older than {days} days and a newer stable version has been \
+published:
+
+{versions}
+
+This is done automatically because of the pre-release retention policy that \
+is configured for this crate. If this was a mistake, the versions can be \
+unyanked using `cargo yank --undo`.",
+            user_name = self.user_name,
+            crate_name = self.crate_name,
+            days = self.days,
+        )
+    }
+}
+
+impl Notification for PrereleaseRetentionEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Publishing;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(num: &str, age_in_days: i64) -> VersionInfo {
+        VersionInfo {
+            id: 0,
+            num: num.to_string(),
+            created_at: Utc::now().naive_utc() - TimeDelta::days(age_in_days),
+        }
+    }
+
+    fn expired_nums(versions: &[VersionInfo], days: i64) -> Vec<&str> {
+        let threshold = Utc::now().naive_utc() - TimeDelta::days(days);
+        find_expired_prereleases(versions, threshold)
+            .into_iter()
+            .map(|v| v.num.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_expired_prereleases() {
+        let versions = vec![
+            version("1.0.0-alpha.1", 60),
+            version("1.0.0-beta.1", 40),
+            version("1.0.0-rc.1", 10),
+            version("1.0.0", 5),
+            version("1.1.0-beta.1", 60),
+        ];
+
+        assert_eq!(
+            expired_nums(&versions, 30),
+            vec!["1.0.0-alpha.1", "1.0.0-beta.1"]
+        );
+        assert_eq!(
+            expired_nums(&versions, 1),
+            vec!["1.0.0-alpha.1", "1.0.0-beta.1", "1.0.0-rc.1"]
+        );
+    }
+
+    #[test]
+    fn test_find_expired_prereleases_without_stable_version() {
+        let versions = vec![version("1.0.0-alpha.1", 60), version("1.0.0-beta.1", 40)];
+        assert!(expired_nums(&versions, 30).is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::schema::emails;
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use chrono::{Duration, NaiveDateTime, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamp};
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// Purges expired ownership invitations, API tokens and pending publishes,
+/// and invalidates the verification tokens of unverified email addresses
+/// that were not used for a while.
+///
+/// See [`crate::retention`] for how long the rows are kept. This job is meant
+/// to run daily.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct PurgeExpiredRecords;
+
+impl BackgroundJob for PurgeExpiredRecords {
+    const JOB_NAME: &'static str = "purge_expired_records";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let retention = &env.config.retention;
+            let batch_size = retention.batch_size;
+            let now = Utc::now().naive_utc();
+
+            let expiration = env.config.ownership_invitations_expiration_days as i64;
+            let cutoff =
+                now - Duration::days(expiration) - Duration::from_std(retention.invitations)?;
+            for table in ["crate_owner_invitations", "crate_owner_email_invitations"] {
+                let query = format!(
+                    "DELETE FROM {table} WHERE ctid = ANY(ARRAY(\
+                        SELECT ctid FROM {table} WHERE created_at < $1 LIMIT $2\
+                    ))"
+                );
+                let purged = delete_in_batches(conn, &query, cutoff, batch_size)?;
+                record_purged(&env, table, purged);
+            }
+
+            // Tokens that are referenced by the audit log of a version are
+            // kept, since `version_owner_actions` does not cascade deletions.
+            let cutoff = now - Duration::from_std(retention.api_tokens)?;
+            let query = "DELETE FROM api_tokens WHERE id IN (\
+                SELECT id FROM api_tokens WHERE expired_at < $1 AND NOT EXISTS (\
+                    SELECT 1 FROM version_owner_actions \
+                    WHERE version_owner_actions.api_token_id = api_tokens.id\
+                ) LIMIT $2\
+            )";
+            let purged = delete_in_batches(conn, query, cutoff, batch_size)?;
+            record_purged(&env, "api_tokens", purged);
+
+            // Pending publishes contain the whole tarball and can't be
+            // confirmed anymore after a day, so they are not retained.
+            let cutoff = now - Duration::days(1);
+            let query = "DELETE FROM pending_publishes WHERE id IN (\
+                SELECT id FROM pending_publishes WHERE created_at < $1 LIMIT $2\
+            )";
+            let purged = delete_in_batches(conn, query, cutoff, batch_size)?;
+            record_purged(&env, "pending_publishes", purged);
+
+            let cutoff = now - Duration::from_std(retention.email_tokens)?;
+            let purged = invalidate_email_tokens(conn, cutoff, batch_size)?;
+            record_purged(&env, "emails", purged);
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Runs the `DELETE` query with the `cutoff` and `batch_size` parameters
+/// until it deletes less than `batch_size` rows, and returns the total
+/// number of deleted rows.
+///
+/// Every batch is deleted by its own statement, so that the locks on the
+/// deleted rows are only held briefly.
+fn delete_in_batches(
+    conn: &mut impl Conn,
+    query: &str,
+    cutoff: NaiveDateTime,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    let mut total = 0;
+    loop {
+        let deleted = diesel::sql_query(query)
+            .bind::<Timestamp, _>(cutoff)
+            .bind::<BigInt, _>(batch_size)
+            .execute(conn)?;
+
+        total += deleted;
+        if (deleted as i64) < batch_size {
+            return Ok(total);
+        }
+    }
+}
+
+/// Replaces the verification tokens of unverified email addresses that
+/// were generated before `cutoff`, so that old verification links stop
+/// working, and returns the number of invalidated tokens.
+///
+/// The `token_generated_at` column is reset afterwards, since the new
+/// tokens were never sent to the users.
+fn invalidate_email_tokens(
+    conn: &mut impl Conn,
+    cutoff: NaiveDateTime,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    let mut total = 0;
+    loop {
+        let invalidated = conn.transaction(|conn| {
+            let ids: Vec<i32> = emails::table
+                .filter(emails::verified.eq(false))
+                .filter(emails::token_generated_at.lt(cutoff))
+                .select(emails::id)
+                .limit(batch_size)
+                .for_update()
+                .skip_locked()
+                .load(conn)?;
+
+            // The `token_generated_at` column is set by a trigger whenever
+            // the token changes, so it has to be reset in a second statement.
+            diesel::update(emails::table.filter(emails::id.eq_any(&ids)))
+                .set(emails::token.eq(sql("DEFAULT")))
+                .execute(conn)?;
+            diesel::update(emails::table.filter(emails::id.eq_any(&ids)))
+                .set(emails::token_generated_at.eq(None::<NaiveDateTime>))
+                .execute(conn)?;
+
+            QueryResult::Ok(ids.len())
+        })?;
+
+        total += invalidated;
+        if (invalidated as i64) < batch_size {
+            return Ok(total);
+        }
+    }
+}
+
+fn record_purged(env: &Environment, table: &str, purged: usize) {
+    info!("Purged {purged} expired rows from the {table} table");
+    env.metrics
+        .purged_rows_total
+        .with_label_values(&[table])
+        .inc_by(purged as u64);
+}
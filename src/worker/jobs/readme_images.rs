@@ -0,0 +1,89 @@
+use crate::models::ReadmeImage;
+use crate::readme_images::FetchedImage;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use chrono::{Duration, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// Cached images are fetched again after this amount of time, so that
+/// updated images show up in the READMEs eventually. The same applies to
+/// images that failed to be fetched.
+const REFRESH_INTERVAL_DAYS: i64 = 1;
+
+/// Fetches the images that are referenced by a rendered README, and uploads
+/// them to the storage bucket, so that they can be served by the README
+/// image proxy.
+///
+/// Images that have been fetched recently are skipped. Images that are too
+/// large, or that are not of one of the allowed content types, are not
+/// uploaded and can not be loaded via the proxy.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FetchReadmeImages {
+    digests: Vec<String>,
+}
+
+impl FetchReadmeImages {
+    pub fn new(digests: Vec<String>) -> Self {
+        Self { digests }
+    }
+}
+
+impl BackgroundJob for FetchReadmeImages {
+    const JOB_NAME: &'static str = "fetch_readme_images";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(num_images = self.digests.len()), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let digests = self.digests.clone();
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let Some(fetcher) = env.image_fetcher.as_deref() else {
+                warn!("Skipping README images, since no image fetcher is configured");
+                return Ok(());
+            };
+
+            let cutoff = (Utc::now() - Duration::days(REFRESH_INTERVAL_DAYS)).naive_utc();
+            let images = ReadmeImage::stale(conn, &digests, cutoff)?;
+            let max_size = env.config.max_readme_image_size;
+
+            for image in images {
+                let future = fetcher.fetch(&image.url, max_size);
+                let fetched = Handle::current()
+                    .block_on(future)
+                    .and_then(|fetched| fetched.validate(max_size).map(|_| fetched));
+
+                let fetched = match fetched {
+                    Ok(fetched) => fetched,
+                    Err(error) => {
+                        info!(url = %image.url, "Failed to fetch README image: {error}");
+                        image.record_failure(conn)?;
+                        continue;
+                    }
+                };
+
+                let FetchedImage {
+                    content_type,
+                    bytes,
+                } = fetched;
+
+                let size = bytes.len() as i32;
+                let future = env
+                    .storage
+                    .upload_readme_image(&image.digest, &content_type, bytes);
+                Handle::current().block_on(future)?;
+
+                image.record_fetched(conn, &content_type, size)?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
@@ -1,14 +1,32 @@
 //! Render README files to HTML.
 
-use crate::models::Version;
+use crate::models::{CrateVisibility, NewReadmeImage, Version};
+use crate::readme_images::ReadmeImageProxy;
+use crate::schema::{crates, readme_renderings, versions};
 use crate::tasks::spawn_blocking;
+use crate::views::EncodableReadmeHeading;
+use crate::worker::jobs::FetchReadmeImages;
 use crate::worker::Environment;
-use crates_io_markdown::text_to_html;
+use anyhow::Context;
+use crates_io_markdown::{
+    render_document, ImageProxy, RenderOptions, SyntaxHighlighter, SyntectHighlighter,
+};
+use crates_io_tarball::{CargoVcsInfo, Manifest, StringOrBool};
 use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
-use std::sync::Arc;
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+use tar::Archive;
 use tokio::runtime::Handle;
 
+/// The number of versions whose READMEs are rendered again by a single
+/// [`RerenderReadmes`] job.
+const RERENDER_BATCH_SIZE: i64 = 100;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct RenderAndUploadReadme {
     version_id: i32,
@@ -36,6 +54,11 @@ impl RenderAndUploadReadme {
     }
 }
 
+/// The syntax definitions are only loaded once, when the first README with
+/// syntax highlighting is rendered.
+static HIGHLIGHTER: LazyLock<Arc<SyntectHighlighter>> =
+    LazyLock::new(|| Arc::new(SyntectHighlighter::new()));
+
 impl BackgroundJob for RenderAndUploadReadme {
     const JOB_NAME: &'static str = "render_and_upload_readme";
     const PRIORITY: i16 = 50;
@@ -44,32 +67,72 @@ impl BackgroundJob for RenderAndUploadReadme {
 
     #[instrument(skip_all, fields(krate.name))]
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
-        use crate::schema::*;
-        use diesel::prelude::*;
-
         info!(version_id = ?self.version_id, "Rendering README");
 
         let job = self.clone();
-        let rendered = spawn_blocking(move || {
-            Ok::<_, anyhow::Error>(text_to_html(
+        let policy = env.config.readme_sanitizer_policy.clone();
+        let policy_version = policy.version;
+        let proxy_domain = env
+            .config
+            .readme_image_proxy
+            .then(|| env.config.domain_name.clone());
+
+        let (rendered, images) = spawn_blocking(move || {
+            let image_proxy = proxy_domain.map(|domain| Arc::new(ReadmeImageProxy::new(&domain)));
+            // The anchors of the table of contents only work if the `id`
+            // attributes of the headings are kept by the sanitizer
+            let table_of_contents = policy.anchor_ids;
+            let highlighter = policy
+                .syntax_highlighting
+                .then(|| HIGHLIGHTER.clone() as Arc<dyn SyntaxHighlighter>);
+            let options = RenderOptions {
+                policy,
+                image_proxy: image_proxy
+                    .clone()
+                    .map(|proxy| proxy as Arc<dyn ImageProxy>),
+                table_of_contents,
+                highlighter,
+            };
+
+            let rendered = render_document(
                 &job.text,
                 &job.readme_path,
                 job.base_url.as_deref(),
                 job.pkg_path_in_vcs.as_ref(),
-            ))
+                &options,
+            );
+
+            let images = image_proxy
+                .map(|proxy| proxy.take_images())
+                .unwrap_or_default();
+
+            Ok::<_, anyhow::Error>((rendered, images))
         })
         .await?;
 
-        if rendered.is_empty() {
+        if rendered.html.is_empty() {
             return Ok(());
         }
 
+        let table_of_contents = rendered
+            .table_of_contents
+            .map(|headings| {
+                let headings = headings.into_iter().map(EncodableReadmeHeading::from);
+                serde_json::to_value(headings.collect::<Vec<_>>())
+            })
+            .transpose()?;
+
         let conn = env.deadpool.get().await?;
         spawn_blocking(move || {
             let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
 
             conn.transaction(|conn| {
-                Version::record_readme_rendering(job.version_id, conn)?;
+                Version::record_readme_rendering(job.version_id, policy_version, conn)?;
+                Version::record_readme_table_of_contents(
+                    job.version_id,
+                    table_of_contents.as_ref(),
+                    conn,
+                )?;
                 let (crate_name, vers): (String, String) = versions::table
                     .find(job.version_id)
                     .inner_join(crates::table)
@@ -78,13 +141,239 @@ impl BackgroundJob for RenderAndUploadReadme {
 
                 tracing::Span::current().record("krate.name", tracing::field::display(&crate_name));
 
-                let bytes = rendered.into();
+                let bytes = rendered.html.into();
                 let future = env.storage.upload_readme(&crate_name, &vers, bytes);
                 Handle::current().block_on(future)?;
 
+                if !images.is_empty() {
+                    let new_images = images
+                        .iter()
+                        .map(|(digest, url)| NewReadmeImage { digest, url })
+                        .collect::<Vec<_>>();
+                    NewReadmeImage::insert_all(conn, &new_images)?;
+
+                    let digests = images.into_keys().collect();
+                    FetchReadmeImages::new(digests).enqueue(conn)?;
+                }
+
                 Ok(())
             })
         })
         .await
     }
 }
+
+/// Renders the READMEs again that were rendered with an older version of the
+/// configured sanitizer policy, so that existing crates benefit from policy
+/// changes too.
+///
+/// The READMEs are read from the crate files in the storage bucket, and are
+/// rendered by separate [`RenderAndUploadReadme`] jobs. Each job handles a
+/// single batch of versions, ordered by ID, and then enqueues another job
+/// for the next batch.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct RerenderReadmes {
+    after_version_id: i32,
+}
+
+impl RerenderReadmes {
+    /// Renders the READMEs of the versions with an ID larger than
+    /// `after_version_id` again.
+    pub fn after(after_version_id: i32) -> Self {
+        Self { after_version_id }
+    }
+}
+
+impl BackgroundJob for RerenderReadmes {
+    const JOB_NAME: &'static str = "rerender_readmes";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let after_version_id = self.after_version_id;
+        let policy_version = env.config.readme_sanitizer_policy.version;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let versions: Vec<(i32, String, String, CrateVisibility)> = versions::table
+                .inner_join(crates::table)
+                .inner_join(readme_renderings::table)
+                .filter(versions::id.gt(after_version_id))
+                .filter(readme_renderings::policy_version.lt(policy_version))
+                .order(versions::id.asc())
+                .limit(RERENDER_BATCH_SIZE)
+                .select((versions::id, crates::name, versions::num, crates::visibility))
+                .load(conn)?;
+
+            let Some(&(last_version_id, _, _, _)) = versions.last() else {
+                info!("Finished rendering the READMEs with an outdated sanitizer policy");
+                return Ok(());
+            };
+
+            for (version_id, crate_name, num, visibility) in &versions {
+                let future = env.storage.download_crate_file(crate_name, num, *visibility);
+                let job = Handle::current()
+                    .block_on(future)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|tarball| {
+                        let pkg_name = format!("{crate_name}-{num}");
+                        read_readme(*version_id, &tarball, &pkg_name)
+                    });
+
+                match job {
+                    Ok(Some(job)) => {
+                        job.enqueue(conn)?;
+                    }
+                    Ok(None) => {}
+                    Err(error) => {
+                        warn!("Failed to read the README of {crate_name}@{num}: {error}");
+                    }
+                }
+            }
+
+            info!("Enqueued the READMEs of {} versions for rendering (up to version ID {last_version_id})", versions.len());
+
+            if versions.len() as i64 == RERENDER_BATCH_SIZE {
+                RerenderReadmes::after(last_version_id).enqueue(conn)?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Reads the README of a version from its crate file, and returns the job
+/// that renders it.
+///
+/// Returns `None` if the manifest disables the README, or if the README file
+/// is missing or empty.
+fn read_readme(
+    version_id: i32,
+    tarball: &[u8],
+    pkg_name: &str,
+) -> anyhow::Result<Option<RenderAndUploadReadme>> {
+    let pkg_root = Path::new(pkg_name);
+
+    let manifest = read_tarball_file(tarball, &pkg_root.join("Cargo.toml"))?
+        .context("Failed to find Cargo.toml file")?;
+
+    // We don't call `validate_manifest()` here since the additional validation is not needed
+    // and it would prevent us from reading a couple of legacy crate files.
+    let manifest = Manifest::from_str(&manifest).context("Failed to parse manifest file")?;
+    let package = manifest.package.as_ref();
+
+    let readme = package
+        .and_then(|p| p.readme.as_ref())
+        .and_then(|r| r.as_ref().as_local());
+
+    let readme_path = match readme {
+        Some(StringOrBool::Bool(false)) => return Ok(None),
+        Some(StringOrBool::String(path)) => path.clone(),
+        _ => String::from("README.md"),
+    };
+
+    let text = read_tarball_file(tarball, &pkg_root.join(&readme_path))?;
+    let Some(text) = text.filter(|text| !text.is_empty()) else {
+        return Ok(None);
+    };
+
+    let repository = package
+        .and_then(|p| p.repository.as_ref())
+        .and_then(|r| r.as_ref().as_local())
+        .cloned();
+
+    let pkg_path_in_vcs = read_tarball_file(tarball, &pkg_root.join(".cargo_vcs_info.json"))?
+        .and_then(|contents| CargoVcsInfo::from_contents(&contents).ok())
+        .map(|vcs_info| vcs_info.path_in_vcs);
+
+    let job =
+        RenderAndUploadReadme::new(version_id, text, readme_path, repository, pkg_path_in_vcs);
+    Ok(Some(job))
+}
+
+/// Reads the file at the given path from a crate file, or returns `None` if
+/// the file does not exist.
+fn read_tarball_file(tarball: &[u8], path: &Path) -> anyhow::Result<Option<String>> {
+    let mut archive = Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()? == path {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io_tarball::TarballBuilder;
+
+    #[test]
+    fn test_read_readme() {
+        let tarball = TarballBuilder::new()
+            .add_file(
+                "foo-0.0.1/Cargo.toml",
+                br#"
+[package]
+name = "foo"
+version = "0.0.1"
+readme = "docs/README.md"
+repository = "https://github.com/foo/foo"
+"#,
+            )
+            .add_file(
+                "foo-0.0.1/.cargo_vcs_info.json",
+                br#"{"path_in_vcs": "foo"}"#,
+            )
+            .add_file("foo-0.0.1/docs/README.md", b"readme")
+            .build();
+
+        let job = read_readme(1, &tarball, "foo-0.0.1").unwrap().unwrap();
+        assert_eq!(job.text, "readme");
+        assert_eq!(job.readme_path, "docs/README.md");
+        assert_eq!(job.base_url.as_deref(), Some("https://github.com/foo/foo"));
+        assert_eq!(job.pkg_path_in_vcs.as_deref(), Some("foo"));
+    }
+
+    #[test]
+    fn test_read_disabled_readme() {
+        let tarball = TarballBuilder::new()
+            .add_file(
+                "foo-0.0.1/Cargo.toml",
+                br#"
+[package]
+name = "foo"
+version = "0.0.1"
+readme = false
+"#,
+            )
+            .add_file("foo-0.0.1/README.md", b"readme")
+            .build();
+
+        assert!(read_readme(1, &tarball, "foo-0.0.1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_missing_readme() {
+        let tarball = TarballBuilder::new()
+            .add_file(
+                "foo-0.0.1/Cargo.toml",
+                br#"
+[package]
+name = "foo"
+version = "0.0.1"
+"#,
+            )
+            .build();
+
+        assert!(read_readme(1, &tarball, "foo-0.0.1").unwrap().is_none());
+    }
+}
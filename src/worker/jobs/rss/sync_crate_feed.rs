@@ -1,3 +1,4 @@
+use crate::models::CrateVisibility;
 use crate::schema::{crates, versions};
 use crate::storage::FeedId;
 use crate::tasks::spawn_blocking;
@@ -107,6 +108,7 @@ fn load_version_updates(name: &str, conn: &mut impl Conn) -> QueryResult<Vec<Ver
     let updates = versions::table
         .inner_join(crates::table)
         .filter(crates::name.eq(name))
+        .filter(crates::visibility.eq(CrateVisibility::Public))
         .filter(versions::created_at.gt(threshold_dt))
         .order(versions::created_at.desc())
         .select(VersionUpdate::as_select())
@@ -120,6 +122,7 @@ fn load_version_updates(name: &str, conn: &mut impl Conn) -> QueryResult<Vec<Ver
     versions::table
         .inner_join(crates::table)
         .filter(crates::name.eq(name))
+        .filter(crates::visibility.eq(CrateVisibility::Public))
         .order(versions::created_at.desc())
         .select(VersionUpdate::as_select())
         .limit(NUM_ITEMS)
@@ -1,3 +1,4 @@
+use crate::models::CrateVisibility;
 use crate::schema::crates;
 use crate::storage::FeedId;
 use crate::tasks::spawn_blocking;
@@ -89,6 +90,7 @@ fn load_new_crates(conn: &mut impl Conn) -> QueryResult<Vec<NewCrate>> {
     let threshold_dt = chrono::Utc::now().naive_utc() - ALWAYS_INCLUDE_AGE;
 
     let new_crates = crates::table
+        .filter(crates::visibility.eq(CrateVisibility::Public))
         .filter(crates::created_at.gt(threshold_dt))
         .order(crates::created_at.desc())
         .select(NewCrate::as_select())
@@ -100,6 +102,7 @@ fn load_new_crates(conn: &mut impl Conn) -> QueryResult<Vec<NewCrate>> {
     }
 
     crates::table
+        .filter(crates::visibility.eq(CrateVisibility::Public))
         .order(crates::created_at.desc())
         .select(NewCrate::as_select())
         .limit(NUM_ITEMS)
@@ -1,3 +1,4 @@
+use crate::models::CrateVisibility;
 use crate::schema::{crates, versions};
 use crate::storage::FeedId;
 use crate::tasks::spawn_blocking;
@@ -90,6 +91,7 @@ fn load_version_updates(conn: &mut impl Conn) -> QueryResult<Vec<VersionUpdate>>
 
     let updates = versions::table
         .inner_join(crates::table)
+        .filter(crates::visibility.eq(CrateVisibility::Public))
         .filter(versions::created_at.gt(threshold_dt))
         .order(versions::created_at.desc())
         .select(VersionUpdate::as_select())
@@ -102,6 +104,7 @@ fn load_version_updates(conn: &mut impl Conn) -> QueryResult<Vec<VersionUpdate>>
 
     versions::table
         .inner_join(crates::table)
+        .filter(crates::visibility.eq(CrateVisibility::Public))
         .order(versions::created_at.desc())
         .select(VersionUpdate::as_select())
         .limit(NUM_ITEMS)
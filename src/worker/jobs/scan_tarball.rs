@@ -0,0 +1,160 @@
+use crate::antivirus::ScanResult;
+use crate::models::{
+    CrateVisibility, NewTarballScan, ScanVerdict, StagedUpload, VersionQuarantine,
+};
+use crate::schema::{crates, versions};
+use crate::storage::crate_file_path;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// Scans the tarball of a newly published version with the configured
+/// virus scanner, and records the verdict in the `tarball_scans` table.
+///
+/// Versions with infected tarballs are quarantined automatically, and show
+/// up in the admin review queue until an admin confirms or overrules the
+/// verdict.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScanTarball {
+    version_id: i32,
+}
+
+impl ScanTarball {
+    pub fn new(version_id: i32) -> Self {
+        Self { version_id }
+    }
+}
+
+impl BackgroundJob for ScanTarball {
+    const JOB_NAME: &'static str = "scan_tarball";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let version_id = self.version_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let Some(scanner) = env.virus_scanner.as_deref() else {
+                warn!("Skipping tarball scan, since no virus scanner is configured");
+                return Ok(());
+            };
+
+            let version: Option<(String, String, CrateVisibility)> = versions::table
+                .find(version_id)
+                .inner_join(crates::table)
+                .select((crates::name, versions::num, crates::visibility))
+                .first(conn)
+                .optional()?;
+
+            let Some((crate_name, num, visibility)) = version else {
+                info!("Skipping tarball scan, since the version does not exist anymore");
+                return Ok(());
+            };
+
+            let bytes = Handle::current().block_on(env.storage.download_crate_file(&crate_name, &num, visibility))?;
+            let result = Handle::current().block_on(scanner.scan(bytes))?;
+
+            let new_scan = match &result {
+                ScanResult::Clean => NewTarballScan {
+                    version_id,
+                    verdict: ScanVerdict::Clean,
+                    signature: None,
+                },
+                ScanResult::Infected(signature) => NewTarballScan {
+                    version_id,
+                    verdict: ScanVerdict::Infected,
+                    signature: Some(signature),
+                },
+            };
+            new_scan.upsert(conn)?;
+
+            let ScanResult::Infected(signature) = result else {
+                return Ok(());
+            };
+
+            warn!("Quarantining {crate_name}@{num}, since its tarball matched the antivirus signature {signature}");
+
+            let reason = format!("The tarball matched the antivirus signature {signature}");
+            if VersionQuarantine::create(conn, version_id, &reason)? {
+                let future = env.storage.quarantine_crate_file(&crate_name, &num, visibility);
+                Handle::current().block_on(future)?;
+
+                let path = crate_file_path(&crate_name, &num);
+                let future = env.invalidate_cdns(path.as_ref());
+                if let Err(error) = Handle::current().block_on(future) {
+                    warn!("Failed to invalidate CDN caches for {path}: {error}");
+                }
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Scans a staged tarball with the configured virus scanner, before it is
+/// published, and records the verdict on the staged upload.
+///
+/// Publishes of infected staged tarballs are rejected.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScanStagedUpload {
+    id: String,
+}
+
+impl ScanStagedUpload {
+    pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
+        Self { id }
+    }
+}
+
+impl BackgroundJob for ScanStagedUpload {
+    const JOB_NAME: &'static str = "scan_staged_upload";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let Some(scanner) = env.virus_scanner.as_deref() else {
+            warn!("Skipping staged upload scan, since no virus scanner is configured");
+            return Ok(());
+        };
+
+        let bytes = match env.storage.download_staged_file(&self.id).await {
+            Ok(bytes) => bytes,
+            Err(object_store::Error::NotFound { .. }) => {
+                info!("Skipping staged upload scan, since the tarball does not exist anymore");
+                return Ok(());
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let verdict = match scanner.scan(bytes).await? {
+            ScanResult::Clean => ScanVerdict::Clean,
+            ScanResult::Infected(signature) => {
+                warn!(
+                    "Staged upload {} matched the antivirus signature {signature}",
+                    self.id
+                );
+                ScanVerdict::Infected
+            }
+        };
+
+        let id = self.id.clone();
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            StagedUpload::set_scan_verdict(conn, &id, verdict)?;
+            Ok(())
+        })
+        .await
+    }
+}
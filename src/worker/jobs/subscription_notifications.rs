@@ -0,0 +1,189 @@
+use crate::email::{Email, Notification};
+use crate::models::{CrateVisibility, DependencySubscription, NotificationClass};
+use crate::schema::{crates, users, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use crate::Emails;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Notifies the users that subscribed to new versions of a crate about a
+/// newly published version, if it matches the version requirement of their
+/// subscription.
+///
+/// The user that published the version is never notified, and subscriptions
+/// to private crates are ignored.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SendSubscriptionNotifications {
+    version_id: i32,
+}
+
+impl SendSubscriptionNotifications {
+    pub fn new(version_id: i32) -> Self {
+        Self { version_id }
+    }
+}
+
+impl BackgroundJob for SendSubscriptionNotifications {
+    const JOB_NAME: &'static str = "send_subscription_notifications";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let version_id = self.version_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            send_notifications(&env.emails, version_id, conn)
+        })
+        .await
+    }
+}
+
+fn send_notifications(
+    emails: &Emails,
+    version_id: i32,
+    conn: &mut impl Conn,
+) -> anyhow::Result<()> {
+    let version: Option<(i32, String, CrateVisibility, String, Option<i32>)> = versions::table
+        .find(version_id)
+        .inner_join(crates::table)
+        .select((
+            crates::id,
+            crates::name,
+            crates::visibility,
+            versions::num,
+            versions::published_by,
+        ))
+        .first(conn)
+        .optional()?;
+
+    let Some((crate_id, crate_name, visibility, num, published_by)) = version else {
+        info!("Skipping subscription notifications, since the version does not exist anymore");
+        return Ok(());
+    };
+
+    if visibility != CrateVisibility::Public {
+        debug!("Skipping subscription notifications for private crate {crate_name}");
+        return Ok(());
+    }
+
+    let semver = semver::Version::parse(&num)?;
+
+    // A user might have multiple matching subscriptions to the same crate,
+    // but should only receive a single email.
+    let mut matching: BTreeMap<i32, String> = BTreeMap::new();
+    for subscription in DependencySubscription::for_crate(conn, crate_id)? {
+        if Some(subscription.user_id) != published_by && subscription.matches(&semver) {
+            matching
+                .entry(subscription.user_id)
+                .or_insert(subscription.version_req);
+        }
+    }
+
+    if matching.is_empty() {
+        return Ok(());
+    }
+
+    let user_ids = matching.keys().copied().collect::<Vec<_>>();
+    let recipients: Vec<(i32, String)> = users::table
+        .filter(users::id.eq_any(&user_ids))
+        .select((users::id, users::gh_login))
+        .load(conn)?;
+
+    info!(
+        "Notifying {} subscribers about {crate_name} v{num}…",
+        recipients.len()
+    );
+
+    for (user_id, user_name) in &recipients {
+        let email = NewVersionEmail {
+            user_name,
+            domain: &emails.domain,
+            crate_name: &crate_name,
+            version: &num,
+            version_req: &matching[user_id],
+        };
+
+        match emails.send_notification(*user_id, email, conn) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                debug!("Skipping subscription notification for {user_name} without verified email")
+            }
+            Err(error) => warn!(
+                ?error,
+                "Failed to send subscription notification to {user_name}"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+struct NewVersionEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+    crate_name: &'a str,
+    version: &'a str,
+    version_req: &'a str,
+}
+
+impl Email for NewVersionEmail<'_> {
+    const SUBJECT: &'static str = "A crate you subscribed to published a new version";
+
+    fn body(&self) -> String {
+        format!(
+            "Hello {user_name}!
+
+Version {version} of the crate {crate_name} was just published to crates.io:
+
+https://{domain}/crates/{crate_name}/{version}
+
+You are receiving this email because you subscribed to new versions of \
+{crate_name} matching `{version_req}`. You can unsubscribe in your account \
+settings at https://{domain}/settings/profile",
+            user_name = self.user_name,
+            domain = self.domain,
+            crate_name = self.crate_name,
+            version = self.version,
+            version_req = self.version_req,
+        )
+    }
+}
+
+impl Notification for NewVersionEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Subscriptions;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn test_email_body() {
+        let email = NewVersionEmail {
+            user_name: "ferris",
+            domain: "crates.io",
+            crate_name: "foo",
+            version: "2.1.0",
+            version_req: "^2",
+        };
+
+        assert_snapshot!(email.body(), @r###"
+        Hello ferris!
+
+        Version 2.1.0 of the crate foo was just published to crates.io:
+
+        https://crates.io/crates/foo/2.1.0
+
+        You are receiving this email because you subscribed to new versions of foo matching `^2`. You can unsubscribe in your account settings at https://crates.io/settings/profile
+        "###);
+    }
+}
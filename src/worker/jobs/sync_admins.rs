@@ -45,7 +45,7 @@ impl BackgroundJob for SyncAdmins {
             // Existing admins from the database.
 
             let database_admins = users::table
-                .left_join(emails::table)
+                .left_join(emails::table.on(emails::user_id.eq(users::id).and(emails::is_primary)))
                 .select((users::gh_id, users::gh_login, emails::email.nullable()))
                 .filter(users::is_admin.eq(true))
                 .get_results::<(i32, String, Option<String>)>(conn)?;
@@ -144,7 +144,7 @@ impl BackgroundJob for SyncAdmins {
             for database_admin in &database_admins {
                 let (_, _, email_address) = database_admin;
                 if let Some(email_address) = email_address {
-                    if let Err(error) = ctx.emails.send(email_address, email.clone()) {
+                    if let Err(error) = ctx.emails.send(conn, email_address, email.clone()) {
                         warn!(
                             "Failed to send email to admin {} ({}, github_id: {}): {}",
                             database_admin.1, email_address, database_admin.0, error
@@ -0,0 +1,58 @@
+use crate::models::Advisory;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use diesel::Connection;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+
+/// Imports the security advisories of the advisory database, and removes
+/// the advisories that were deleted from it.
+///
+/// See [`crate::advisories`] for how the advisories are used. This job is
+/// meant to run every few hours.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct SyncAdvisories;
+
+impl BackgroundJob for SyncAdvisories {
+    const JOB_NAME: &'static str = "sync_advisories";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let Some(source) = env.advisory_source.as_deref() else {
+            warn!("Skipping advisory sync, since no advisory database is configured");
+            return Ok(());
+        };
+
+        let advisories = source.fetch().await?;
+
+        // An empty database is more likely caused by a broken download than
+        // by all advisories being deleted, so the existing ones are kept.
+        if advisories.is_empty() {
+            warn!("Skipping advisory sync, since the advisory database is empty");
+            return Ok(());
+        }
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            conn.transaction(|conn| {
+                let synced = Advisory::upsert_all(conn, &advisories)?;
+
+                let ids = advisories
+                    .iter()
+                    .map(|advisory| advisory.id.as_str())
+                    .collect::<Vec<_>>();
+                let deleted = Advisory::delete_all_except(conn, &ids)?;
+
+                info!(synced, deleted = deleted.len(), "Synced advisories");
+                Ok(())
+            })
+        })
+        .await
+    }
+}
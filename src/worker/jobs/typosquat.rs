@@ -1,10 +1,13 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
 use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
 use typomania::Package;
 
 use crate::email::Email;
+use crate::schema::{crate_settings, crates};
 use crate::tasks::spawn_blocking;
 use crate::util::diesel::Conn;
 use crate::{
@@ -57,14 +60,18 @@ fn check(emails: &Emails, cache: &Cache, conn: &mut impl Conn, name: &str) -> an
             // hopefully care to check into things more closely.
             info!(?squats, "Found potential typosquatting");
 
+            let squatted_names = squats.iter().map(|squat| squat.package()).collect();
+            let security_contacts = load_security_contacts(conn, squatted_names)?;
+
             let email = PossibleTyposquatEmail {
                 domain: &emails.domain,
                 crate_name: name,
                 squats: &squats,
+                security_contacts: &security_contacts,
             };
 
             for recipient in cache.iter_emails() {
-                if let Err(error) = emails.send(recipient, email.clone()) {
+                if let Err(error) = emails.send(conn, recipient, email.clone()) {
                     error!(
                         ?error,
                         ?recipient,
@@ -78,11 +85,31 @@ fn check(emails: &Emails, cache: &Cache, conn: &mut impl Conn, name: &str) -> an
     Ok(())
 }
 
+/// Loads the security contacts that the owners of the given crates have
+/// configured in the policy settings of their crates.
+fn load_security_contacts(
+    conn: &mut impl Conn,
+    names: Vec<&str>,
+) -> QueryResult<HashMap<String, String>> {
+    crate_settings::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq_any(names))
+        .filter(crate_settings::security_contact.is_not_null())
+        .select((
+            crates::name,
+            crate_settings::security_contact.assume_not_null(),
+        ))
+        .load(conn)
+        .map(|contacts: Vec<(String, String)>| contacts.into_iter().collect())
+}
+
 #[derive(Debug, Clone)]
 struct PossibleTyposquatEmail<'a> {
     domain: &'a str,
     crate_name: &'a str,
     squats: &'a [typomania::checks::Squat],
+    /// Security contacts of the squatted crates, keyed by crate name.
+    security_contacts: &'a HashMap<String, String>,
 }
 
 impl Email for PossibleTyposquatEmail<'_> {
@@ -95,7 +122,12 @@ impl Email for PossibleTyposquatEmail<'_> {
             .map(|squat| {
                 let domain = self.domain;
                 let crate_name = squat.package();
-                format!("- {squat} (https://{domain}/crates/{crate_name})\n")
+                match self.security_contacts.get(crate_name) {
+                    Some(contact) => format!(
+                        "- {squat} (https://{domain}/crates/{crate_name}, security contact: {contact})\n"
+                    ),
+                    None => format!("- {squat} (https://{domain}/crates/{crate_name})\n"),
+                }
             })
             .collect::<Vec<_>>()
             .join("");
@@ -0,0 +1,110 @@
+use crate::schema::upstream_crates;
+use crate::tasks::spawn_blocking;
+use crate::worker::Environment;
+use anyhow::Context;
+use chrono::{TimeDelta, Utc};
+use crates_io_index::Repository;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// The maximum number of crates that are revalidated by a single job.
+const BATCH_SIZE: i64 = 100;
+
+/// The minimum age of a cached index file before it is revalidated.
+const REVALIDATE_AFTER: TimeDelta = TimeDelta::minutes(30);
+
+/// Revalidates the cached index files of the crates of the upstream registry
+/// of a pull-through cache, which picks up new versions and changes of the
+/// yank status. See `src/upstream.rs` for more details.
+///
+/// Each job revalidates the least recently revalidated crates, and enqueues
+/// another job if there might be more outdated crates.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct RevalidateUpstreamCrates;
+
+impl BackgroundJob for RevalidateUpstreamCrates {
+    const JOB_NAME: &'static str = "revalidate_upstream_crates";
+    const EXCLUSIVE: bool = true;
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let Some(upstream) = env.upstream.clone() else {
+            warn!("Skipping revalidation, since no upstream registry is configured");
+            return Ok(());
+        };
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+
+            let cutoff = Utc::now().naive_utc() - REVALIDATE_AFTER;
+            let names: Vec<String> = upstream_crates::table
+                .filter(upstream_crates::revalidated_at.lt(cutoff))
+                .order(upstream_crates::revalidated_at.asc())
+                .limit(BATCH_SIZE)
+                .select(upstream_crates::name)
+                .load(conn)?;
+
+            let mut num_updated = 0;
+            let mut num_failed = 0;
+            for name in &names {
+                let content = match Handle::current().block_on(upstream.index_file(name)) {
+                    Ok(content) => content,
+                    Err(error) => {
+                        warn!("Failed to revalidate the index file of `{name}`: {error:#}");
+                        num_failed += 1;
+                        continue;
+                    }
+                };
+
+                let cached = Handle::current().block_on(env.storage.read_index_file(name))?;
+                if cached != content {
+                    let future = env.storage.sync_index(name, content.clone());
+                    Handle::current()
+                        .block_on(future)
+                        .context("Failed to update the cached index file")?;
+
+                    if let Some(cloudfront) = env.cloudfront() {
+                        let path = Repository::relative_index_file_for_url(name);
+                        Handle::current()
+                            .block_on(cloudfront.invalidate(&path))
+                            .context("Failed to invalidate CloudFront")?;
+                    }
+
+                    num_updated += 1;
+                }
+
+                let target = upstream_crates::table.find(name);
+                if content.is_some() {
+                    diesel::update(target)
+                        .set(upstream_crates::revalidated_at.eq(diesel::dsl::now))
+                        .execute(conn)?;
+                } else {
+                    // The crate was deleted from the upstream registry.
+                    info!("Removed `{name}`, since it was deleted from the upstream registry");
+                    diesel::delete(target).execute(conn)?;
+                }
+            }
+
+            info!(
+                "Revalidated {} upstream crates, {num_updated} of them changed",
+                names.len()
+            );
+
+            // Crates that failed to revalidate are retried by the next
+            // scheduled job, instead of requeueing the same batch over and
+            // over while the upstream registry is unavailable.
+            if names.len() as i64 == BATCH_SIZE && num_failed == 0 {
+                RevalidateUpstreamCrates.enqueue(conn)?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config::Server;
+use crate::licenses::parse_license_expr;
+use crate::models::{Crate, MetadataRule, NewMetadataFinding};
+use crate::schema::{crates, dependencies, metadata_findings, versions};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// The number of versions that are validated by a single job.
+const BATCH_SIZE: i64 = 1000;
+
+/// Runs the current publish validation rules against the metadata of
+/// already published versions, and records the violations in the
+/// `metadata_findings` table.
+///
+/// The affected versions are not modified in any way. This is meant to
+/// measure the impact of stricter validation rules, before they are
+/// enforced for new publishes.
+///
+/// Each job validates a single batch of versions, ordered by ID, and then
+/// enqueues another job for the next batch.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct ValidateVersionMetadata {
+    after_version_id: i32,
+}
+
+impl ValidateVersionMetadata {
+    /// Validates the versions with an ID larger than `after_version_id`.
+    pub fn after(after_version_id: i32) -> Self {
+        Self { after_version_id }
+    }
+}
+
+impl BackgroundJob for ValidateVersionMetadata {
+    const JOB_NAME: &'static str = "validate_version_metadata";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let after_version_id = self.after_version_id;
+
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            validate_batch(&env.config, after_version_id, conn)
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = versions, check_for_backend(diesel::pg::Pg))]
+struct VersionMetadata {
+    id: i32,
+    features: serde_json::Value,
+    license: Option<String>,
+    rust_version: Option<String>,
+}
+
+/// The per-crate overrides of the publish limits.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = crates, check_for_backend(diesel::pg::Pg))]
+struct CrateLimits {
+    max_features: Option<i16>,
+    max_feature_values: Option<i16>,
+    max_dependencies: Option<i16>,
+}
+
+/// The limits that apply to a specific crate, taking the per-crate
+/// overrides into account.
+#[derive(Debug)]
+struct Limits {
+    max_features: usize,
+    max_feature_values: usize,
+    max_dependencies: usize,
+}
+
+fn validate_batch(
+    config: &Server,
+    after_version_id: i32,
+    conn: &mut impl Conn,
+) -> anyhow::Result<()> {
+    let versions: Vec<(VersionMetadata, CrateLimits)> = versions::table
+        .inner_join(crates::table)
+        .filter(versions::id.gt(after_version_id))
+        .order(versions::id.asc())
+        .limit(BATCH_SIZE)
+        .select((VersionMetadata::as_select(), CrateLimits::as_select()))
+        .load(conn)?;
+
+    let Some(last_version_id) = versions.last().map(|(version, _)| version.id) else {
+        info!("Finished validating the metadata of all versions");
+        return Ok(());
+    };
+
+    let version_ids = versions
+        .iter()
+        .map(|(version, _)| version.id)
+        .collect::<Vec<_>>();
+
+    let num_dependencies: HashMap<i32, i64> = dependencies::table
+        .filter(dependencies::version_id.eq_any(&version_ids))
+        .group_by(dependencies::version_id)
+        .select((dependencies::version_id, count_star()))
+        .load::<(i32, i64)>(conn)?
+        .into_iter()
+        .collect();
+
+    let findings = versions
+        .iter()
+        .flat_map(|(version, crate_limits)| {
+            let limits = Limits {
+                max_features: crate_limits
+                    .max_features
+                    .map(|mf| mf as usize)
+                    .unwrap_or(config.max_features),
+                max_feature_values: crate_limits
+                    .max_feature_values
+                    .map(|mfv| mfv as usize)
+                    .unwrap_or(config.max_feature_values),
+                max_dependencies: crate_limits
+                    .max_dependencies
+                    .map(|md| md as usize)
+                    .unwrap_or(config.max_dependencies),
+            };
+
+            let num_dependencies = num_dependencies.get(&version.id).copied().unwrap_or(0);
+            validate_version(version, num_dependencies as usize, &limits)
+        })
+        .collect::<Vec<_>>();
+
+    info!(
+        "Found {} metadata findings in {} versions (up to version ID {last_version_id})",
+        findings.len(),
+        version_ids.len(),
+    );
+
+    conn.transaction(|conn| {
+        diesel::delete(metadata_findings::table)
+            .filter(metadata_findings::version_id.eq_any(&version_ids))
+            .execute(conn)?;
+
+        if !findings.is_empty() {
+            diesel::insert_into(metadata_findings::table)
+                .values(&findings)
+                .execute(conn)?;
+        }
+
+        if versions.len() as i64 == BATCH_SIZE {
+            ValidateVersionMetadata::after(last_version_id).enqueue(conn)?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    })
+}
+
+fn validate_version(
+    version: &VersionMetadata,
+    num_dependencies: usize,
+    limits: &Limits,
+) -> Vec<NewMetadataFinding> {
+    let mut findings = Vec::new();
+    let mut add = |rule, message: String| {
+        findings.push(NewMetadataFinding::new(version.id, rule, message));
+    };
+
+    let features: BTreeMap<String, Vec<String>> =
+        serde_json::from_value(version.features.clone()).unwrap_or_default();
+
+    let max_features = limits.max_features;
+    let max_feature_values = limits.max_feature_values;
+    if features.len() > max_features {
+        let message = format!(
+            "declares {} features, but only {max_features} are allowed",
+            features.len()
+        );
+        add(MetadataRule::TooManyFeatures, message);
+    }
+
+    for (name, values) in &features {
+        if let Err(error) = Crate::validate_feature_name(name) {
+            add(MetadataRule::InvalidFeatureName, error.to_string());
+        }
+
+        if values.len() > max_feature_values {
+            let message = format!(
+                "the `{name}` feature enables {} features or dependencies, but only {max_feature_values} are allowed",
+                values.len()
+            );
+            add(MetadataRule::TooManyEnabledFeatures, message);
+        }
+
+        for value in values {
+            if let Err(error) = Crate::validate_feature(value) {
+                add(MetadataRule::InvalidFeature, error.to_string());
+            }
+        }
+    }
+
+    let max_dependencies = limits.max_dependencies;
+    if num_dependencies > max_dependencies {
+        let message = format!(
+            "declares {num_dependencies} dependencies, but only {max_dependencies} are allowed"
+        );
+        add(MetadataRule::TooManyDependencies, message);
+    }
+
+    if let Some(license) = &version.license {
+        if parse_license_expr(license).is_err() {
+            let message = format!("unknown or invalid license expression `{license}`");
+            add(MetadataRule::InvalidLicense, message);
+        }
+    }
+
+    if let Some(rust_version) = &version.rust_version {
+        if !is_valid_rust_version(rust_version) {
+            let message = format!("invalid `rust-version` value `{rust_version}`");
+            add(MetadataRule::InvalidRustVersion, message);
+        }
+    }
+
+    findings
+}
+
+/// Uses the same rules as the publish endpoint, which excludes semver
+/// operators like `^` and pre-release identifiers.
+fn is_valid_rust_version(value: &str) -> bool {
+    semver::VersionReq::parse(value).is_ok()
+        && value.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version(features: serde_json::Value) -> VersionMetadata {
+        VersionMetadata {
+            id: 1,
+            features,
+            license: Some("MIT OR Apache-2.0".to_string()),
+            rust_version: Some("1.70".to_string()),
+        }
+    }
+
+    fn rules(version: &VersionMetadata, num_dependencies: usize) -> Vec<MetadataRule> {
+        let limits = Limits {
+            max_features: 2,
+            max_feature_values: 2,
+            max_dependencies: 3,
+        };
+
+        validate_version(version, num_dependencies, &limits)
+            .into_iter()
+            .map(|finding| finding.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_version() {
+        let version = version(json!({ "default": ["std"], "std": [] }));
+        assert_eq!(rules(&version, 3), vec![]);
+    }
+
+    #[test]
+    fn test_features() {
+        let version = version(json!({ "a": [], "b": [], "c": ["x", "y", "z"] }));
+        assert_eq!(
+            rules(&version, 0),
+            vec![
+                MetadataRule::TooManyFeatures,
+                MetadataRule::TooManyEnabledFeatures
+            ]
+        );
+
+        let version = version(json!({ "🍺": ["dep:🍻"] }));
+        assert_eq!(
+            rules(&version, 0),
+            vec![
+                MetadataRule::InvalidFeatureName,
+                MetadataRule::InvalidFeature
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let version = version(json!({}));
+        assert_eq!(rules(&version, 4), vec![MetadataRule::TooManyDependencies]);
+    }
+
+    #[test]
+    fn test_license_and_rust_version() {
+        let mut version = version(json!({}));
+        version.license = Some("apache 2.0".to_string());
+        version.rust_version = Some("^1.70".to_string());
+        assert_eq!(
+            rules(&version, 0),
+            vec![
+                MetadataRule::InvalidLicense,
+                MetadataRule::InvalidRustVersion
+            ]
+        );
+    }
+}
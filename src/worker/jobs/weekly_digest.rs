@@ -0,0 +1,405 @@
+use crate::config::Server;
+use crate::email::{Email, Notification};
+use crate::models::{NotificationClass, OwnerKind};
+use crate::schema::{
+    crate_owner_invitations, crate_owners, crates, dependencies, digest_subscriptions, users,
+    version_downloads, versions,
+};
+use crate::sql::{date_part, timezone};
+use crate::tasks::spawn_blocking;
+use crate::util::diesel::Conn;
+use crate::worker::Environment;
+use crate::Emails;
+use chrono::{Days, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::{now, sum};
+use diesel::prelude::*;
+use diesel_async::async_connection_wrapper::AsyncConnectionWrapper;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// The number of digests that are rendered and sent by a single job.
+const BATCH_SIZE: i64 = 100;
+
+/// The local hour of the day on Monday after which the digest is sent.
+const SEND_HOUR: f64 = 9.;
+
+/// Sends the weekly maintainer digest to all users that opted in to it, and
+/// for whom it is currently Monday morning in their own timezone.
+///
+/// This job is meant to run every hour. If there are more digests due than
+/// fit into a single batch, another job is enqueued for the remaining users.
+#[derive(Default, Serialize, Deserialize, Debug)]
+pub struct SendWeeklyDigests;
+
+impl BackgroundJob for SendWeeklyDigests {
+    const JOB_NAME: &'static str = "send_weekly_digests";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        spawn_blocking(move || {
+            let conn: &mut AsyncConnectionWrapper<_> = &mut conn.into();
+            send_digests(&env.config, &env.emails, conn)
+        })
+        .await
+    }
+}
+
+fn send_digests(config: &Server, emails: &Emails, conn: &mut impl Conn) -> anyhow::Result<()> {
+    let local_now = timezone(digest_subscriptions::timezone, now);
+    let last_week = Utc::now().naive_utc() - TimeDelta::days(6);
+
+    let recipients: Vec<(i32, String)> = digest_subscriptions::table
+        .inner_join(users::table)
+        .filter(date_part("isodow", local_now).eq(1.))
+        .filter(date_part("hour", local_now).ge(SEND_HOUR))
+        .filter(
+            digest_subscriptions::last_sent_at
+                .is_null()
+                .or(digest_subscriptions::last_sent_at.lt(last_week)),
+        )
+        .select((users::id, users::gh_login))
+        .order(users::id)
+        .limit(BATCH_SIZE)
+        .load(conn)?;
+
+    if recipients.is_empty() {
+        info!("No weekly digests are due");
+        return Ok(());
+    }
+
+    let user_ids = recipients.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let today = Utc::now().date_naive();
+    let mut digests = load_digests(config, &user_ids, today, conn)?;
+
+    info!("Sending {} weekly digests…", recipients.len());
+
+    for (user_id, user_name) in &recipients {
+        let digest = digests.remove(user_id).unwrap_or_default();
+        if digest.is_empty() {
+            debug!("Skipping empty weekly digest for {user_name}");
+        } else {
+            let email = WeeklyDigestEmail {
+                user_name,
+                domain: &emails.domain,
+                digest: &digest,
+            };
+
+            match emails.send_notification(*user_id, email, conn) {
+                Ok(Some(_)) => {}
+                Ok(None) => debug!("Skipping weekly digest for {user_name} without verified email"),
+                Err(error) => warn!(?error, "Failed to send weekly digest to {user_name}"),
+            }
+        }
+
+        diesel::update(digest_subscriptions::table.find(user_id))
+            .set(digest_subscriptions::last_sent_at.eq(now.nullable()))
+            .execute(conn)?;
+    }
+
+    if recipients.len() as i64 == BATCH_SIZE {
+        SendWeeklyDigests.enqueue(conn)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Digest {
+    crates: BTreeMap<String, CrateDigest>,
+    pending_invitations: BTreeSet<String>,
+}
+
+impl Digest {
+    fn is_empty(&self) -> bool {
+        self.crates.is_empty() && self.pending_invitations.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+struct CrateDigest {
+    /// Downloads in the last seven full days.
+    downloads: i64,
+    /// Downloads in the seven days before that.
+    previous_downloads: i64,
+    /// Crates that started depending on this crate within the last week.
+    new_reverse_dependencies: BTreeSet<String>,
+}
+
+/// Loads the digest contents for a batch of users at once, to avoid running
+/// separate queries for every user and crate.
+fn load_digests(
+    config: &Server,
+    user_ids: &[i32],
+    today: NaiveDate,
+    conn: &mut impl Conn,
+) -> QueryResult<HashMap<i32, Digest>> {
+    let owned_crates: Vec<(i32, i32, String)> = crate_owners::table
+        .inner_join(crates::table)
+        .filter(crate_owners::owner_id.eq_any(user_ids))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .filter(crate_owners::deleted.eq(false))
+        .select((crate_owners::owner_id, crates::id, crates::name))
+        .load(conn)?;
+
+    let crate_ids = owned_crates
+        .iter()
+        .map(|(_, crate_id, _)| *crate_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let downloads = load_downloads(&crate_ids, today, conn)?;
+    let new_reverse_dependencies = load_new_reverse_dependencies(&crate_ids, conn)?;
+
+    let mut digests: HashMap<i32, Digest> = HashMap::new();
+    for (user_id, crate_id, crate_name) in owned_crates {
+        let (downloads, previous_downloads) = downloads.get(&crate_id).copied().unwrap_or_default();
+        let new_reverse_dependencies = new_reverse_dependencies
+            .get(&crate_id)
+            .cloned()
+            .unwrap_or_default();
+
+        let crate_digest = CrateDigest {
+            downloads,
+            previous_downloads,
+            new_reverse_dependencies,
+        };
+
+        let digest = digests.entry(user_id).or_default();
+        digest.crates.insert(crate_name, crate_digest);
+    }
+
+    let expiration = TimeDelta::days(config.ownership_invitations_expiration_days as i64);
+    let invitations: Vec<(i32, String)> = crate_owner_invitations::table
+        .inner_join(crates::table)
+        .filter(crate_owner_invitations::invited_user_id.eq_any(user_ids))
+        .filter(crate_owner_invitations::created_at.gt(Utc::now().naive_utc() - expiration))
+        .select((crate_owner_invitations::invited_user_id, crates::name))
+        .load(conn)?;
+
+    for (user_id, crate_name) in invitations {
+        let digest = digests.entry(user_id).or_default();
+        digest.pending_invitations.insert(crate_name);
+    }
+
+    Ok(digests)
+}
+
+/// Returns the downloads of the last seven full days, and of the seven days
+/// before that, for each of the crates.
+fn load_downloads(
+    crate_ids: &[i32],
+    today: NaiveDate,
+    conn: &mut impl Conn,
+) -> QueryResult<HashMap<i32, (i64, i64)>> {
+    let week_start = today - Days::new(7);
+    let previous_week_start = today - Days::new(14);
+
+    let daily_downloads: Vec<(i32, NaiveDate, Option<i64>)> = version_downloads::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .filter(version_downloads::date.ge(previous_week_start))
+        .filter(version_downloads::date.lt(today))
+        .group_by((versions::crate_id, version_downloads::date))
+        .select((
+            versions::crate_id,
+            version_downloads::date,
+            sum(version_downloads::downloads),
+        ))
+        .load(conn)?;
+
+    let mut downloads: HashMap<i32, (i64, i64)> = HashMap::new();
+    for (crate_id, date, count) in daily_downloads {
+        let entry = downloads.entry(crate_id).or_default();
+        if date >= week_start {
+            entry.0 += count.unwrap_or(0);
+        } else {
+            entry.1 += count.unwrap_or(0);
+        }
+    }
+
+    Ok(downloads)
+}
+
+/// Returns the names of the crates that published their first version
+/// depending on one of the given crates within the last week.
+fn load_new_reverse_dependencies(
+    crate_ids: &[i32],
+    conn: &mut impl Conn,
+) -> QueryResult<HashMap<i32, BTreeSet<String>>> {
+    let since: NaiveDateTime = Utc::now().naive_utc() - TimeDelta::days(7);
+
+    let recent: Vec<(i32, i32, String)> = dependencies::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(dependencies::crate_id.eq_any(crate_ids))
+        .filter(versions::created_at.gt(since))
+        .select((dependencies::crate_id, crates::id, crates::name))
+        .distinct()
+        .load(conn)?;
+
+    if recent.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let dependent_ids = recent.iter().map(|(_, id, _)| *id).collect::<Vec<_>>();
+
+    // Crates that already depended on the crate before are not new
+    let existing: HashSet<(i32, i32)> = dependencies::table
+        .inner_join(versions::table)
+        .filter(dependencies::crate_id.eq_any(crate_ids))
+        .filter(versions::crate_id.eq_any(&dependent_ids))
+        .filter(versions::created_at.le(since))
+        .select((dependencies::crate_id, versions::crate_id))
+        .distinct()
+        .load::<(i32, i32)>(conn)?
+        .into_iter()
+        .collect();
+
+    let mut new_reverse_dependencies: HashMap<i32, BTreeSet<String>> = HashMap::new();
+    for (crate_id, dependent_id, dependent_name) in recent {
+        if crate_id != dependent_id && !existing.contains(&(crate_id, dependent_id)) {
+            new_reverse_dependencies
+                .entry(crate_id)
+                .or_default()
+                .insert(dependent_name);
+        }
+    }
+
+    Ok(new_reverse_dependencies)
+}
+
+struct WeeklyDigestEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+    digest: &'a Digest,
+}
+
+impl Email for WeeklyDigestEmail<'_> {
+    const SUBJECT: &'static str = "Your weekly crates.io digest";
+
+    fn body(&self) -> String {
+        let domain = self.domain;
+        let mut body = format!(
+            "Hello {}!\n\nHere is what happened with your crates last week.\n",
+            self.user_name
+        );
+
+        if !self.digest.pending_invitations.is_empty() {
+            body.push_str("\nYou have pending invitations to become an owner of:\n\n");
+            for crate_name in &self.digest.pending_invitations {
+                let _ = writeln!(body, "- {crate_name}");
+            }
+            let _ = writeln!(
+                body,
+                "\nYou can accept or decline them at https://{domain}/me/pending-invites"
+            );
+        }
+
+        for (crate_name, crate_digest) in &self.digest.crates {
+            let _ = writeln!(
+                body,
+                "\n{crate_name} (https://{domain}/crates/{crate_name})\n"
+            );
+
+            let downloads = crate_digest.downloads;
+            let trend = format_trend(downloads, crate_digest.previous_downloads);
+            let _ = writeln!(body, "- Downloads: {downloads} ({trend})");
+
+            let dependents = &crate_digest.new_reverse_dependencies;
+            if !dependents.is_empty() {
+                let dependents = dependents.iter().map(String::as_str).collect::<Vec<_>>();
+                let _ = writeln!(body, "- New dependents: {}", dependents.join(", "));
+            }
+        }
+
+        let _ = write!(
+            body,
+            "\nYou are receiving this email because you subscribed to the weekly \
+             digest. You can unsubscribe in your account settings at \
+             https://{domain}/settings/profile"
+        );
+
+        body
+    }
+}
+
+impl Notification for WeeklyDigestEmail<'_> {
+    const CLASS: NotificationClass = NotificationClass::Digest;
+}
+
+fn format_trend(downloads: i64, previous_downloads: i64) -> String {
+    if previous_downloads == 0 {
+        return match downloads {
+            0 => "no change".to_string(),
+            _ => "new".to_string(),
+        };
+    }
+
+    let change = (downloads - previous_downloads) as f64 / previous_downloads as f64 * 100.;
+    format!("{change:+.0}% compared to the previous week")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn test_email_body() {
+        let mut digest = Digest::default();
+        digest.pending_invitations.insert("baz".to_string());
+
+        let foo = CrateDigest {
+            downloads: 150,
+            previous_downloads: 100,
+            new_reverse_dependencies: ["qux".to_string(), "quux".to_string()].into(),
+        };
+        digest.crates.insert("foo".to_string(), foo);
+        digest
+            .crates
+            .insert("bar".to_string(), CrateDigest::default());
+
+        let email = WeeklyDigestEmail {
+            user_name: "ferris",
+            domain: "crates.io",
+            digest: &digest,
+        };
+
+        assert_snapshot!(email.body(), @r###"
+        Hello ferris!
+
+        Here is what happened with your crates last week.
+
+        You have pending invitations to become an owner of:
+
+        - baz
+
+        You can accept or decline them at https://crates.io/me/pending-invites
+
+        bar (https://crates.io/crates/bar)
+
+        - Downloads: 0 (no change)
+
+        foo (https://crates.io/crates/foo)
+
+        - Downloads: 150 (+50% compared to the previous week)
+        - New dependents: quux, qux
+
+        You are receiving this email because you subscribed to the weekly digest. You can unsubscribe in your account settings at https://crates.io/settings/profile
+        "###);
+    }
+
+    #[test]
+    fn test_format_trend() {
+        assert_eq!(format_trend(0, 0), "no change");
+        assert_eq!(format_trend(10, 0), "new");
+        assert_eq!(format_trend(150, 100), "+50% compared to the previous week");
+        assert_eq!(format_trend(75, 100), "-25% compared to the previous week");
+        assert_eq!(format_trend(100, 100), "+0% compared to the previous week");
+    }
+}
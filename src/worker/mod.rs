@@ -19,22 +19,48 @@ pub trait RunnerExt {
 
 impl RunnerExt for Runner<Arc<Environment>> {
     fn register_crates_io_job_types(self) -> Self {
-        self.register_job_type::<jobs::ArchiveVersionDownloads>()
+        self.register_job_type::<jobs::ApplyPendingOwnerRemovals>()
+            .register_job_type::<jobs::ApplyPrereleaseRetention>()
+            .register_job_type::<jobs::ArchiveVersionDownloads>()
+            .register_job_type::<jobs::BackfillSemverOrd>()
+            .register_job_type::<jobs::BulkYankVersions>()
+            .register_job_type::<jobs::CheckMirrors>()
             .register_job_type::<jobs::CheckTyposquat>()
             .register_job_type::<jobs::CleanProcessedLogFiles>()
             .register_job_type::<jobs::DailyDbMaintenance>()
             .register_job_type::<jobs::DumpDb>()
+            .register_job_type::<jobs::DumpDbDelta>()
+            .register_job_type::<jobs::FetchAvatar>()
+            .register_job_type::<jobs::FetchReadmeImages>()
+            .register_job_type::<jobs::ImportCrate>()
+            .register_job_type::<jobs::InvalidateCrateFile>()
             .register_job_type::<jobs::NormalizeIndex>()
             .register_job_type::<jobs::ProcessCdnLog>()
             .register_job_type::<jobs::ProcessCdnLogQueue>()
+            .register_job_type::<jobs::PurgeExpiredRecords>()
+            .register_job_type::<jobs::RegenerateSparseIndex>()
             .register_job_type::<jobs::RenderAndUploadReadme>()
+            .register_job_type::<jobs::RerenderReadmes>()
+            .register_job_type::<jobs::RevalidateUpstreamCrates>()
+            .register_job_type::<jobs::ScanStagedUpload>()
+            .register_job_type::<jobs::ScanTarball>()
             .register_job_type::<jobs::SquashIndex>()
+            .register_job_type::<jobs::SuggestDormantOwnerRemovals>()
             .register_job_type::<jobs::SyncAdmins>()
+            .register_job_type::<jobs::SyncAdvisories>()
+            .register_job_type::<jobs::SyncRegistryConfigs>()
+            .register_job_type::<jobs::SyncCrateFileVisibility>()
             .register_job_type::<jobs::SyncToGitIndex>()
             .register_job_type::<jobs::SyncToSparseIndex>()
+            .register_job_type::<jobs::SyncIndexConfig>()
+            .register_job_type::<jobs::UpdateCrateHealth>()
+            .register_job_type::<jobs::UpdateCrateRecommendations>()
             .register_job_type::<jobs::UpdateDownloads>()
             .register_job_type::<jobs::UpdateDefaultVersion>()
+            .register_job_type::<jobs::ValidateVersionMetadata>()
             .register_job_type::<jobs::SendTokenExpiryNotifications>()
+            .register_job_type::<jobs::SendWeeklyDigests>()
+            .register_job_type::<jobs::SendSubscriptionNotifications>()
             .register_job_type::<jobs::rss::SyncCrateFeed>()
             .register_job_type::<jobs::rss::SyncCratesFeed>()
             .register_job_type::<jobs::rss::SyncUpdatesFeed>()
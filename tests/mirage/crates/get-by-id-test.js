@@ -42,6 +42,7 @@ module('Mirage | GET /api/v1/crates/:id', function (hooks) {
         },
         max_version: '1.0.0-beta.1',
         max_stable_version: null,
+        default_version: '1.0.0-beta.1',
         name: 'rand',
         newest_version: '1.0.0-beta.1',
         repository: null,
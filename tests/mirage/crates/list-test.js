@@ -58,6 +58,7 @@ module('Mirage | GET /api/v1/crates', function (hooks) {
           },
           max_version: '2.0.0-beta.1',
           max_stable_version: '1.0.0',
+          default_version: '1.0.0',
           name: 'rand',
           newest_version: '2.0.0-beta.1',
           repository: null,
@@ -56,6 +56,7 @@ module('Mirage | GET /api/v1/summary', function (hooks) {
       },
       max_version: '1.0.0',
       max_stable_version: '1.0.0',
+      default_version: '1.0.0',
       name: 'crate-0',
       newest_version: '1.0.0',
       repository: null,
@@ -83,6 +84,7 @@ module('Mirage | GET /api/v1/summary', function (hooks) {
       },
       max_version: '1.0.4',
       max_stable_version: '1.0.4',
+      default_version: '1.0.4',
       name: 'crate-4',
       newest_version: '1.0.4',
       repository: null,
@@ -110,6 +112,7 @@ module('Mirage | GET /api/v1/summary', function (hooks) {
       },
       max_version: '1.0.0',
       max_stable_version: '1.0.0',
+      default_version: '1.0.0',
       name: 'crate-0',
       newest_version: '1.0.0',
       repository: null,
@@ -137,6 +140,7 @@ module('Mirage | GET /api/v1/summary', function (hooks) {
       },
       max_version: '1.0.0',
       max_stable_version: '1.0.0',
+      default_version: '1.0.0',
       name: 'crate-0',
       newest_version: '1.0.0',
       repository: null,